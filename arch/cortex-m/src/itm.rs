@@ -0,0 +1,189 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! ARM Instrumentation Trace Macrocell (ITM), and Trace Port Interface Unit
+//! (TPIU) in its Serial Wire Output (SWO) mode.
+//!
+//! The ITM sends data written to its stimulus ports to the debug probe, over
+//! SWO, without using a UART. `ItmTrace` is a [`TraceSink`] writing kernel
+//! trace points to the stimulus ports, so that they can be recorded with
+//! little effect on timing.
+//!
+//! The TPIU is often configured by the debug probe. Otherwise, call
+//! `configure_swo` with the frequency of the trace clock, which is usually
+//! the core clock. The chip may also need its SWO pin to be enabled.
+//!
+//! <https://developer.arm.com/documentation/ddi0403/latest> (ARMv7-M
+//! Architecture Reference Manual, appendix C1)
+
+use kernel::trace::TraceSink;
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
+use kernel::utilities::registers::{
+    register_bitfields, register_structs, ReadOnly, ReadWrite, WriteOnly,
+};
+use kernel::utilities::StaticRef;
+
+register_structs! {
+    ItmRegisters {
+        /// Stimulus Port Registers
+        (0x000 => stim: [ReadWrite<u32, Stimulus::Register>; 32]),
+        (0x080 => _reserved0),
+        /// Trace Enable Register
+        (0xE00 => ter: ReadWrite<u32>),
+        (0xE04 => _reserved1),
+        /// Trace Privilege Register
+        (0xE40 => tpr: ReadWrite<u32>),
+        (0xE44 => _reserved2),
+        /// Trace Control Register
+        (0xE80 => tcr: ReadWrite<u32, TraceControl::Register>),
+        (0xE84 => _reserved3),
+        /// Lock Access Register
+        (0xFB0 => lar: WriteOnly<u32>),
+        /// Lock Status Register
+        (0xFB4 => lsr: ReadOnly<u32>),
+        (0xFB8 => @END),
+    },
+
+    TpiuRegisters {
+        /// Supported Parallel Port Size Register
+        (0x000 => sspsr: ReadOnly<u32>),
+        /// Current Parallel Port Size Register
+        (0x004 => cspsr: ReadWrite<u32>),
+        (0x008 => _reserved0),
+        /// Asynchronous Clock Prescaler Register
+        (0x010 => acpr: ReadWrite<u32>),
+        (0x014 => _reserved1),
+        /// Selected Pin Protocol Register
+        (0x0F0 => sppr: ReadWrite<u32, SelectedPinProtocol::Register>),
+        (0x0F4 => _reserved2),
+        /// Formatter and Flush Control Register
+        (0x304 => ffcr: ReadWrite<u32, FormatterControl::Register>),
+        (0x308 => @END),
+    }
+}
+
+register_bitfields![u32,
+    Stimulus [
+        /// Reads 1 when the port can accept data.
+        FIFOREADY OFFSET(0) NUMBITS(1) []
+    ],
+
+    TraceControl [
+        /// Indicates whether the ITM is currently processing events.
+        BUSY OFFSET(23) NUMBITS(1) [],
+        /// Identifier for multi-source trace stream formatting.
+        TRACE_BUS_ID OFFSET(16) NUMBITS(7) [],
+        /// Global timestamp frequency.
+        GTSFREQ OFFSET(10) NUMBITS(2) [],
+        /// Local timestamp prescaler.
+        TSPRESCALE OFFSET(8) NUMBITS(2) [],
+        /// Enables asynchronous clocking of the timestamp counter.
+        SWOENA OFFSET(4) NUMBITS(1) [],
+        /// Enables forwarding of hardware event packets from the DWT.
+        TXENA OFFSET(3) NUMBITS(1) [],
+        /// Enables synchronization packet transmission.
+        SYNCENA OFFSET(2) NUMBITS(1) [],
+        /// Enables local timestamp generation.
+        TSENA OFFSET(1) NUMBITS(1) [],
+        /// Enables the ITM.
+        ITMENA OFFSET(0) NUMBITS(1) []
+    ],
+
+    SelectedPinProtocol [
+        TXMODE OFFSET(0) NUMBITS(2) [
+            ParallelTracePort = 0,
+            SwoManchester = 1,
+            SwoNrz = 2
+        ]
+    ],
+
+    FormatterControl [
+        /// Continuous formatting, needed for the trace port but not for SWO.
+        ENFCONT OFFSET(1) NUMBITS(1) []
+    ],
+
+    DebugExceptionMonitorControl [
+        /// Global enable of the DWT, ITM, ETM and TPIU.
+        TRCENA OFFSET(24) NUMBITS(1) []
+    ]
+];
+
+const ITM: StaticRef<ItmRegisters> = unsafe { StaticRef::new(0xE0000000 as *const ItmRegisters) };
+
+const TPIU: StaticRef<TpiuRegisters> =
+    unsafe { StaticRef::new(0xE0040000 as *const TpiuRegisters) };
+
+const DEMCR: StaticRef<ReadWrite<u32, DebugExceptionMonitorControl::Register>> = unsafe {
+    StaticRef::new(0xE000EDFC as *const ReadWrite<u32, DebugExceptionMonitorControl::Register>)
+};
+
+/// Value of the Lock Access Register unlocking writes to the ITM.
+const UNLOCK: u32 = 0xC5ACCE55;
+
+/// Enable the ITM and the stimulus ports set in `ports`, which can then be
+/// written to by unprivileged code too.
+pub unsafe fn enable(ports: u32) {
+    DEMCR.modify(DebugExceptionMonitorControl::TRCENA::SET);
+    ITM.lar.set(UNLOCK);
+    ITM.tcr.write(
+        TraceControl::ITMENA::SET
+            + TraceControl::SYNCENA::SET
+            + TraceControl::SWOENA::SET
+            + TraceControl::TRACE_BUS_ID.val(1),
+    );
+    ITM.tpr.set(0);
+    ITM.ter.set(ports);
+}
+
+/// Configure the TPIU to send trace data over SWO, with NRZ (UART) encoding
+/// at `swo_baud_rate`, from a trace clock of `trace_clock_hz`.
+pub unsafe fn configure_swo(trace_clock_hz: u32, swo_baud_rate: u32) {
+    DEMCR.modify(DebugExceptionMonitorControl::TRCENA::SET);
+    TPIU.cspsr.set(1);
+    TPIU.sppr.write(SelectedPinProtocol::TXMODE::SwoNrz);
+    TPIU.acpr
+        .set((trace_clock_hz / swo_baud_rate).saturating_sub(1));
+    TPIU.ffcr.modify(FormatterControl::ENFCONT::CLEAR);
+}
+
+/// Write `value` to the stimulus `port`, unless the ITM is disabled or its
+/// FIFO is full. Returns whether the value was written.
+pub fn write_u32(port: usize, value: u32) -> bool {
+    let stim = &ITM.stim[port % 32];
+    // The port reads 0 if the ITM or the port are disabled, as well as when
+    // the FIFO is full. Waiting could block forever without a debug probe.
+    if stim.is_set(Stimulus::FIFOREADY) {
+        stim.set(value);
+        true
+    } else {
+        false
+    }
+}
+
+/// Trace sink writing events to the ITM stimulus ports.
+///
+/// The event `id` is written to port `1 + id % 31`, leaving port 0 for text
+/// output. Events are dropped if the ITM is not ready, instead of waiting for
+/// it.
+pub struct ItmTrace;
+
+impl ItmTrace {
+    /// Ports used for trace events.
+    pub const PORTS: u32 = 0xFFFF_FFFE;
+
+    pub const fn new() -> ItmTrace {
+        ItmTrace
+    }
+
+    /// Enable the ITM ports used for trace events.
+    pub unsafe fn enable(&self) {
+        enable(Self::PORTS);
+    }
+}
+
+impl TraceSink for ItmTrace {
+    fn event(&self, id: u8, value: u32) {
+        write_u32(1 + id as usize % 31, value);
+    }
+}
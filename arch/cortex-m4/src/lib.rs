@@ -15,6 +15,7 @@ pub mod mpu {
 }
 
 pub use cortexm::initialize_ram_jump_to_main;
+pub use cortexm::itm;
 pub use cortexm::nvic;
 pub use cortexm::scb;
 pub use cortexm::support;
@@ -140,6 +140,7 @@ impl KernelResources<nrf52832::chip::NRF52<'static, Nrf52832DefaultPeripherals<'
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm4::systick::SysTick;
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -163,6 +164,9 @@ impl KernelResources<nrf52832::chip::NRF52<'static, Nrf52832DefaultPeripherals<'
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -671,6 +675,7 @@ pub unsafe fn main() {
 
     kernel::process::load_processes(
         board_kernel,
+        &platform,
         chip,
         core::slice::from_raw_parts(
             &_sapps as *const u8,
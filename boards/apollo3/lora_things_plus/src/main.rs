@@ -138,6 +138,7 @@ struct LoRaThingsPlus {
     >,
     temperature: &'static capsules_extra::temperature::TemperatureSensor<'static>,
     humidity: &'static capsules_extra::humidity::HumiditySensor<'static>,
+    pressure: &'static capsules_extra::pressure::PressureSensor<'static>,
     air_quality: &'static capsules_extra::air_quality::AirQualitySensor<'static>,
     scheduler: &'static RoundRobinSched<'static>,
     systick: cortexm4::systick::SysTick,
@@ -161,6 +162,7 @@ impl SyscallDriverLookup for LoRaThingsPlus {
             capsules_extra::ble_advertising_driver::DRIVER_NUM => f(Some(self.ble_radio)),
             capsules_extra::temperature::DRIVER_NUM => f(Some(self.temperature)),
             capsules_extra::humidity::DRIVER_NUM => f(Some(self.humidity)),
+            capsules_extra::pressure::DRIVER_NUM => f(Some(self.pressure)),
             capsules_extra::air_quality::DRIVER_NUM => f(Some(self.air_quality)),
             _ => f(None),
         }
@@ -175,6 +177,7 @@ impl KernelResources<apollo3::chip::Apollo3<Apollo3DefaultPeripherals>> for LoRa
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm4::systick::SysTick;
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -198,6 +201,9 @@ impl KernelResources<apollo3::chip::Apollo3<Apollo3DefaultPeripherals>> for LoRa
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -353,6 +359,12 @@ unsafe fn setup() -> (
         bme280,
     )
     .finalize(components::humidity_component_static!());
+    let pressure = components::pressure::PressureComponent::new(
+        board_kernel,
+        capsules_extra::pressure::DRIVER_NUM,
+        bme280,
+    )
+    .finalize(components::pressure_component_static!());
     BME280 = Some(bme280);
 
     let ccs811 = Ccs811Component::new(mux_i2c, 0x5B).finalize(
@@ -469,6 +481,7 @@ unsafe fn setup() -> (
             ble_radio,
             temperature,
             humidity,
+            pressure,
             air_quality,
             scheduler,
             systick,
@@ -483,6 +496,7 @@ unsafe fn setup() -> (
 
     kernel::process::load_processes(
         board_kernel,
+        artemis_nano,
         chip,
         core::slice::from_raw_parts(
             &_sapps as *const u8,
@@ -13,14 +13,15 @@ use crate::{BME280, CCS811};
 use core::cell::Cell;
 use kernel::debug;
 use kernel::hil::sensors::{
-    AirQualityClient, AirQualityDriver, HumidityClient, HumidityDriver, TemperatureClient,
-    TemperatureDriver,
+    AirQualityClient, AirQualityDriver, HumidityClient, HumidityDriver, PressureClient,
+    PressureDriver, TemperatureClient, TemperatureDriver,
 };
 use kernel::ErrorCode;
 
 struct SensorTestCallback {
     temperature_done: Cell<bool>,
     humidity_done: Cell<bool>,
+    pressure_done: Cell<bool>,
     co2_done: Cell<bool>,
     tvoc_done: Cell<bool>,
     calibration_temp: Cell<Option<i32>>,
@@ -34,6 +35,7 @@ impl<'a> SensorTestCallback {
         SensorTestCallback {
             temperature_done: Cell::new(false),
             humidity_done: Cell::new(false),
+            pressure_done: Cell::new(false),
             co2_done: Cell::new(false),
             tvoc_done: Cell::new(false),
             calibration_temp: Cell::new(None),
@@ -44,6 +46,7 @@ impl<'a> SensorTestCallback {
     fn reset(&self) {
         self.temperature_done.set(false);
         self.humidity_done.set(false);
+        self.pressure_done.set(false);
         self.co2_done.set(false);
         self.tvoc_done.set(false);
     }
@@ -67,6 +70,14 @@ impl<'a> HumidityClient for SensorTestCallback {
     }
 }
 
+impl<'a> PressureClient for SensorTestCallback {
+    fn callback(&self, result: Result<u32, ErrorCode>) {
+        self.pressure_done.set(true);
+
+        debug!("Pressure: {} Pa", result.unwrap());
+    }
+}
+
 impl<'a> AirQualityClient for SensorTestCallback {
     fn environment_specified(&self, result: Result<(), ErrorCode>) {
         result.unwrap();
@@ -133,6 +144,29 @@ fn run_bme280_humidity() {
     run_kernel_op(100);
 }
 
+#[test_case]
+fn run_bme280_pressure() {
+    debug!("check run BME280 Pressure... ");
+    run_kernel_op(100);
+
+    let bme280 = unsafe { BME280.unwrap() };
+
+    // Make sure the device is ready for us.
+    // The setup can take a little bit of time
+    run_kernel_op(800000);
+
+    PressureDriver::set_client(bme280, &CALLBACK);
+    CALLBACK.reset();
+
+    bme280.read_pressure().unwrap();
+
+    run_kernel_op(50000);
+    assert_eq!(CALLBACK.pressure_done.get(), true);
+
+    debug!("    [ok]");
+    run_kernel_op(100);
+}
+
 #[test_case]
 fn run_ccs811_co2() {
     debug!("check run CCS811 CO2... ");
@@ -94,6 +94,7 @@ impl KernelResources<arty_e21_chip::chip::ArtyExx<'static, ArtyExxDefaultPeriphe
     type Scheduler = PrioritySched;
     type SchedulerTimer = ();
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -117,6 +118,9 @@ impl KernelResources<arty_e21_chip::chip::ArtyExx<'static, ArtyExxDefaultPeriphe
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -278,6 +282,7 @@ pub unsafe fn main() {
 
     kernel::process::load_processes(
         board_kernel,
+        &artye21,
         chip,
         core::slice::from_raw_parts(
             &_sapps as *const u8,
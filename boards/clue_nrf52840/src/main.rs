@@ -232,6 +232,7 @@ impl KernelResources<nrf52::chip::NRF52<'static, Nrf52840DefaultPeripherals<'sta
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm4::systick::SysTick;
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -255,6 +256,9 @@ impl KernelResources<nrf52::chip::NRF52<'static, Nrf52840DefaultPeripherals<'sta
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -813,6 +817,7 @@ pub unsafe fn main() {
 
     kernel::process::load_processes(
         board_kernel,
+        &platform,
         chip,
         core::slice::from_raw_parts(
             &_sapps as *const u8,
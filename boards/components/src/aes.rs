@@ -0,0 +1,69 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the AES-128 engine of a board.
+//!
+//! Uses the AES engine of the chip if the board passes one, and the software
+//! implementation of AES-128 otherwise.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! // A chip with an AES engine
+//! let aes = components::aes::AesFallbackComponent::new(Some(&peripherals.aes))
+//!     .finalize(components::aes_fallback_component_static!());
+//! // A chip without one
+//! let aes = components::aes::AesFallbackComponent::new(None)
+//!     .finalize(components::aes_fallback_component_static!());
+//! ```
+
+use capsules_extra::symmetric_encryption::aes_fallback::{Aes128Engine, Aes128Fallback};
+use capsules_extra::symmetric_encryption::aes_software::Aes128Software;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::deferred_call::DeferredCallClient;
+
+// Setup static space for the objects.
+#[macro_export]
+macro_rules! aes_fallback_component_static {
+    () => {{
+        let software = kernel::static_buf!(
+            capsules_extra::symmetric_encryption::aes_software::Aes128Software<'static>
+        );
+        let fallback = kernel::static_buf!(
+            capsules_extra::symmetric_encryption::aes_fallback::Aes128Fallback<'static>
+        );
+
+        (software, fallback)
+    };};
+}
+
+pub struct AesFallbackComponent {
+    hardware: Option<&'static dyn Aes128Engine<'static>>,
+}
+
+impl AesFallbackComponent {
+    pub fn new(hardware: Option<&'static dyn Aes128Engine<'static>>) -> AesFallbackComponent {
+        AesFallbackComponent { hardware }
+    }
+}
+
+impl Component for AesFallbackComponent {
+    type StaticInput = (
+        &'static mut MaybeUninit<Aes128Software<'static>>,
+        &'static mut MaybeUninit<Aes128Fallback<'static>>,
+    );
+    type Output = &'static Aes128Fallback<'static>;
+
+    fn finalize(self, s: Self::StaticInput) -> Self::Output {
+        match self.hardware {
+            Some(hardware) => s.1.write(Aes128Fallback::new(hardware, true)),
+            None => {
+                let software = s.0.write(Aes128Software::new());
+                software.register();
+                s.1.write(Aes128Fallback::new(software, false))
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for loading applications while the kernel is running.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let app_loader = components::app_loader::AppLoaderComponent::new(
+//!     &peripherals.flash,
+//!     0x08000000,
+//!     app_flash,
+//!     loader,
+//!     checker,
+//! )
+//! .finalize(components::app_loader_component_static!(
+//!     stm32f303xc::flash::Flash
+//! ));
+//! process_console.add_command(app_loader).unwrap();
+//! ```
+
+use capsules_extra::app_loader::AppLoader;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil;
+use kernel::process::ProcessLoader;
+use kernel::process_checker::AppCredentialsChecker;
+
+#[macro_export]
+macro_rules! app_loader_component_static {
+    ($F:ty $(,)?) => {{
+        let page_buffer = kernel::static_buf!(<$F as kernel::hil::flash::Flash>::Page);
+        let app_loader = kernel::static_buf!(capsules_extra::app_loader::AppLoader<'static, $F>);
+        (page_buffer, app_loader)
+    };};
+}
+
+pub struct AppLoaderComponent<
+    F: 'static + hil::flash::Flash + hil::flash::HasClient<'static, AppLoader<'static, F>>,
+> {
+    flash: &'static F,
+    flash_address: usize,
+    app_flash: &'static [u8],
+    loader: &'static dyn ProcessLoader,
+    checker: &'static dyn AppCredentialsChecker<'static>,
+}
+
+impl<F: 'static + hil::flash::Flash + hil::flash::HasClient<'static, AppLoader<'static, F>>>
+    AppLoaderComponent<F>
+{
+    /// `flash_address` is the address of page 0 of `flash`, and `app_flash`
+    /// the application region of the board. `checker` checks the credentials
+    /// of the images, and must not be the checker of the kernel.
+    pub fn new(
+        flash: &'static F,
+        flash_address: usize,
+        app_flash: &'static [u8],
+        loader: &'static dyn ProcessLoader,
+        checker: &'static dyn AppCredentialsChecker<'static>,
+    ) -> Self {
+        Self {
+            flash,
+            flash_address,
+            app_flash,
+            loader,
+            checker,
+        }
+    }
+}
+
+impl<F: 'static + hil::flash::Flash + hil::flash::HasClient<'static, AppLoader<'static, F>>>
+    Component for AppLoaderComponent<F>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<<F as hil::flash::Flash>::Page>,
+        &'static mut MaybeUninit<AppLoader<'static, F>>,
+    );
+    type Output = &'static AppLoader<'static, F>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let page_buffer = static_buffer
+            .0
+            .write(<F as hil::flash::Flash>::Page::default());
+
+        let app_loader = static_buffer.1.write(AppLoader::new(
+            self.flash,
+            self.flash_address,
+            self.app_flash,
+            self.loader,
+            self.checker,
+            page_buffer,
+        ));
+        self.flash.set_client(app_loader);
+        self.checker.set_client(app_loader);
+
+        app_loader
+    }
+}
@@ -0,0 +1,109 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for per-application software watchdogs.
+//!
+//! The returned `AppWatchdog` also implements `WatchDog` and should be used
+//! as the `KernelResources` watchdog of the board, so that it can stop
+//! tickling the hardware watchdog when the `ResetBoard` policy is selected.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let app_watchdog = components::app_watchdog::AppWatchdogComponent::new(
+//!     board_kernel,
+//!     capsules_extra::app_watchdog::DRIVER_NUM,
+//!     mux_alarm,
+//!     &peripherals.watchdog,
+//!     capsules_extra::app_watchdog::MissedDeadlinePolicy::RestartProcess,
+//! )
+//! .finalize(components::app_watchdog_component_static!(
+//!     stm32f303xc::tim2::Tim2,
+//!     stm32f303xc::wdt::WindoWdg<'static>,
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::app_watchdog::{AppWatchdog, MissedDeadlinePolicy};
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::time::Alarm;
+use kernel::platform::watchdog::WatchDog;
+
+#[macro_export]
+macro_rules! app_watchdog_component_static {
+    ($A:ty, $W:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let app_watchdog = kernel::static_buf!(
+            capsules_extra::app_watchdog::AppWatchdog<
+                'static,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+                $W,
+                components::app_watchdog::Capability,
+            >
+        );
+
+        (alarm, app_watchdog)
+    };};
+}
+
+pub struct Capability;
+unsafe impl capabilities::ProcessManagementCapability for Capability {}
+
+pub struct AppWatchdogComponent<A: 'static + Alarm<'static>, W: 'static + WatchDog> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    watchdog: &'static W,
+    policy: MissedDeadlinePolicy,
+}
+
+impl<A: 'static + Alarm<'static>, W: 'static + WatchDog> AppWatchdogComponent<A, W> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        watchdog: &'static W,
+        policy: MissedDeadlinePolicy,
+    ) -> Self {
+        AppWatchdogComponent {
+            board_kernel,
+            driver_num,
+            alarm_mux,
+            watchdog,
+            policy,
+        }
+    }
+}
+
+impl<A: 'static + Alarm<'static>, W: 'static + WatchDog> Component for AppWatchdogComponent<A, W> {
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<AppWatchdog<'static, VirtualMuxAlarm<'static, A>, W, Capability>>,
+    );
+    type Output = &'static AppWatchdog<'static, VirtualMuxAlarm<'static, A>, W, Capability>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let app_watchdog = static_buffer.1.write(AppWatchdog::new(
+            self.board_kernel,
+            alarm,
+            self.watchdog,
+            Capability,
+            self.policy,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+        alarm.set_alarm_client(app_watchdog);
+
+        app_watchdog
+    }
+}
@@ -0,0 +1,117 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for AT24C and M24C I2C EEPROMs.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let eeprom = components::at24c_eeprom::At24cComponent::new(
+//!     mux_i2c,
+//!     0x50,
+//!     mux_alarm,
+//!     8192,
+//!     32,
+//! )
+//! .finalize(components::at24c_component_static!(
+//!     nrf52840::rtc::Rtc<'static>,
+//!     nrf52840::i2c::TWI
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_core::virtualizers::virtual_i2c::{I2CDevice, MuxI2C};
+use capsules_extra::at24c_eeprom::AT24C;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::i2c;
+use kernel::hil::time::Alarm;
+
+// Setup static space for the objects.
+#[macro_export]
+macro_rules! at24c_component_static {
+    ($A:ty, $I:ty $(,)?) => {{
+        let buffer = kernel::static_buf!([u8; capsules_extra::at24c_eeprom::BUF_LEN]);
+        let i2c_device =
+            kernel::static_buf!(capsules_core::virtualizers::virtual_i2c::I2CDevice<'static, $I>);
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let at24c = kernel::static_buf!(
+            capsules_extra::at24c_eeprom::AT24C<
+                'static,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+                capsules_core::virtualizers::virtual_i2c::I2CDevice<'static, $I>,
+            >
+        );
+
+        (alarm, i2c_device, at24c, buffer)
+    };};
+}
+
+pub struct At24cComponent<A: 'static + Alarm<'static>, I: 'static + i2c::I2CMaster<'static>> {
+    i2c_mux: &'static MuxI2C<'static, I>,
+    i2c_address: u8,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    size: usize,
+    page_size: usize,
+}
+
+impl<A: 'static + Alarm<'static>, I: 'static + i2c::I2CMaster<'static>> At24cComponent<A, I> {
+    pub fn new(
+        i2c_mux: &'static MuxI2C<'static, I>,
+        i2c_address: u8,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        size: usize,
+        page_size: usize,
+    ) -> At24cComponent<A, I> {
+        At24cComponent {
+            i2c_mux,
+            i2c_address,
+            alarm_mux,
+            size,
+            page_size,
+        }
+    }
+}
+
+impl<A: 'static + Alarm<'static>, I: 'static + i2c::I2CMaster<'static>> Component
+    for At24cComponent<A, I>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<I2CDevice<'static, I>>,
+        &'static mut MaybeUninit<
+            AT24C<'static, VirtualMuxAlarm<'static, A>, I2CDevice<'static, I>>,
+        >,
+        &'static mut MaybeUninit<[u8; capsules_extra::at24c_eeprom::BUF_LEN]>,
+    );
+    type Output = &'static AT24C<'static, VirtualMuxAlarm<'static, A>, I2CDevice<'static, I>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let at24c_i2c = static_buffer
+            .1
+            .write(I2CDevice::new(self.i2c_mux, self.i2c_address));
+
+        let buffer = static_buffer
+            .3
+            .write([0; capsules_extra::at24c_eeprom::BUF_LEN]);
+
+        let at24c_alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        at24c_alarm.setup();
+
+        let at24c = static_buffer.2.write(AT24C::new(
+            at24c_i2c,
+            at24c_alarm,
+            buffer,
+            self.size,
+            self.page_size,
+        ));
+        at24c_i2c.set_client(at24c);
+        at24c_alarm.set_alarm_client(at24c);
+
+        at24c
+    }
+}
@@ -0,0 +1,101 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for an engine running AT commands over a UART.
+//!
+//! The UART is configured for `baud_rate` 8N1 and is used only by the
+//! engine. The capsule of the module sets itself as the client of the
+//! engine.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let at_engine =
+//!     components::at_engine::AtEngineComponent::new(&peripherals.uart1, mux_alarm, 115200)
+//!         .finalize(components::at_engine_component_static!(
+//!             rp2040::uart::Uart<'static>,
+//!             rp2040::timer::RPTimer<'static>,
+//!         ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::at_engine::{AtEngine, BUFFER_LEN, LINE_LEN};
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::time::Alarm;
+use kernel::hil::uart;
+
+#[macro_export]
+macro_rules! at_engine_component_static {
+    ($U:ty, $A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let engine = kernel::static_buf!(
+            capsules_extra::at_engine::AtEngine<
+                'static,
+                $U,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+        let rx_byte = kernel::static_buf!([u8; 1]);
+        let line = kernel::static_buf!([u8; capsules_extra::at_engine::LINE_LEN]);
+        let tx_buffer = kernel::static_buf!([u8; capsules_extra::at_engine::BUFFER_LEN]);
+
+        (alarm, engine, rx_byte, line, tx_buffer)
+    };};
+}
+
+pub struct AtEngineComponent<U: 'static + uart::Uart<'static>, A: 'static + Alarm<'static>> {
+    uart: &'static U,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    baud_rate: u32,
+}
+
+impl<U: 'static + uart::Uart<'static>, A: 'static + Alarm<'static>> AtEngineComponent<U, A> {
+    pub fn new(uart: &'static U, alarm_mux: &'static MuxAlarm<'static, A>, baud_rate: u32) -> Self {
+        AtEngineComponent {
+            uart,
+            alarm_mux,
+            baud_rate,
+        }
+    }
+}
+
+impl<U: 'static + uart::Uart<'static>, A: 'static + Alarm<'static>> Component
+    for AtEngineComponent<U, A>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<AtEngine<'static, U, VirtualMuxAlarm<'static, A>>>,
+        &'static mut MaybeUninit<[u8; 1]>,
+        &'static mut MaybeUninit<[u8; LINE_LEN]>,
+        &'static mut MaybeUninit<[u8; BUFFER_LEN]>,
+    );
+    type Output = &'static AtEngine<'static, U, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let rx_byte = static_buffer.2.write([0; 1]);
+        let line = static_buffer.3.write([0; LINE_LEN]);
+        let tx_buffer = static_buffer.4.write([0; BUFFER_LEN]);
+
+        let engine = static_buffer.1.write(AtEngine::new(
+            self.uart,
+            alarm,
+            self.baud_rate,
+            rx_byte,
+            line,
+            tx_buffer,
+        ));
+        alarm.set_alarm_client(engine);
+        uart::Transmit::set_transmit_client(self.uart, engine);
+        uart::Receive::set_receive_client(self.uart, engine);
+        let _ = engine.initialize();
+
+        engine
+    }
+}
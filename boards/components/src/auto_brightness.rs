@@ -0,0 +1,103 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for automatic screen brightness.
+//!
+//! The driver becomes the client of the ambient light sensor, so the sensor
+//! cannot also be used by the ambient light driver.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let auto_brightness = components::auto_brightness::AutoBrightnessComponent::new(
+//!     board_kernel,
+//!     capsules_extra::auto_brightness::DRIVER_NUM,
+//!     mux_alarm,
+//!     photoresistor,
+//!     backlight_pwm_pin,
+//! )
+//! .finalize(components::auto_brightness_component_static!(
+//!     imxrt1050::gpt::Gpt1<'static>
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::auto_brightness::AutoBrightness;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::pwm::PwmPin;
+use kernel::hil::sensors::AmbientLight;
+use kernel::hil::time::Alarm;
+
+#[macro_export]
+macro_rules! auto_brightness_component_static {
+    ($A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let auto_brightness = kernel::static_buf!(
+            capsules_extra::auto_brightness::AutoBrightness<
+                'static,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+
+        (alarm, auto_brightness)
+    };};
+}
+
+pub struct AutoBrightnessComponent<A: 'static + Alarm<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    sensor: &'static dyn AmbientLight<'static>,
+    backlight: &'static dyn PwmPin,
+}
+
+impl<A: 'static + Alarm<'static>> AutoBrightnessComponent<A> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        sensor: &'static dyn AmbientLight<'static>,
+        backlight: &'static dyn PwmPin,
+    ) -> Self {
+        AutoBrightnessComponent {
+            board_kernel,
+            driver_num,
+            alarm_mux,
+            sensor,
+            backlight,
+        }
+    }
+}
+
+impl<A: 'static + Alarm<'static>> Component for AutoBrightnessComponent<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<AutoBrightness<'static, VirtualMuxAlarm<'static, A>>>,
+    );
+    type Output = &'static AutoBrightness<'static, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let auto_brightness = static_buffer.1.write(AutoBrightness::new(
+            self.sensor,
+            self.backlight,
+            alarm,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+        alarm.set_alarm_client(auto_brightness);
+        self.sensor.set_client(auto_brightness);
+        auto_brightness.start();
+
+        auto_brightness
+    }
+}
@@ -0,0 +1,50 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the random delays of network protocols.
+//!
+//! The entropy source is used by the output only: boards which also expose
+//! it to applications should give it a dedicated source.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let backoff = components::backoff::RandomBackoffComponent::new(&peripherals.trng)
+//!     .finalize(components::random_backoff_component_static!());
+//! dhcp.set_jitter(backoff);
+//! ```
+
+use capsules_extra::net::backoff::RandomBackoff;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::entropy::Entropy32;
+
+#[macro_export]
+macro_rules! random_backoff_component_static {
+    () => {{
+        kernel::static_buf!(capsules_extra::net::backoff::RandomBackoff<'static>)
+    };};
+}
+
+pub struct RandomBackoffComponent {
+    entropy: &'static dyn Entropy32<'static>,
+}
+
+impl RandomBackoffComponent {
+    pub fn new(entropy: &'static dyn Entropy32<'static>) -> Self {
+        Self { entropy }
+    }
+}
+
+impl Component for RandomBackoffComponent {
+    type StaticInput = &'static mut MaybeUninit<RandomBackoff<'static>>;
+    type Output = &'static RandomBackoff<'static>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let backoff = static_buffer.write(RandomBackoff::new(self.entropy));
+        self.entropy.set_client(backoff);
+        backoff.start();
+        backoff
+    }
+}
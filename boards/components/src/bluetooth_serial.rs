@@ -0,0 +1,144 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for a UART Bluetooth module, such as the HC-05 or the HM-10.
+//!
+//! The UART is configured for `baud_rate` 8N1, the data rate of the module,
+//! and is used only by the capsule. The KEY pin is only needed by the
+//! HC-05, and the STATE pin is optional.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let bluetooth = components::bluetooth_serial::BluetoothSerialComponent::new(
+//!     board_kernel,
+//!     capsules_extra::bluetooth_serial::DRIVER_NUM,
+//!     &peripherals.uart1,
+//!     mux_alarm,
+//!     9600,
+//!     capsules_extra::bluetooth_serial::Module::Hc05,
+//!     Some(peripherals.pins.get_pin(RPGpio::GPIO6)),
+//!     Some(peripherals.pins.get_pin(RPGpio::GPIO7)),
+//! )
+//! .finalize(components::bluetooth_serial_component_static!(
+//!     rp2040::uart::Uart<'static>,
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::bluetooth_serial::{BluetoothSerial, Module, RESPONSE_LEN, RX_LEN, TX_LEN};
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::gpio;
+use kernel::hil::time::Alarm;
+use kernel::hil::uart;
+
+#[macro_export]
+macro_rules! bluetooth_serial_component_static {
+    ($U:ty, $A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let bluetooth = kernel::static_buf!(
+            capsules_extra::bluetooth_serial::BluetoothSerial<
+                'static,
+                $U,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+        let rx_byte = kernel::static_buf!([u8; 1]);
+        let rx_ring = kernel::static_buf!([u8; capsules_extra::bluetooth_serial::RX_LEN]);
+        let tx_buffer = kernel::static_buf!([u8; capsules_extra::bluetooth_serial::TX_LEN]);
+        let response = kernel::static_buf!([u8; capsules_extra::bluetooth_serial::RESPONSE_LEN]);
+
+        (alarm, bluetooth, rx_byte, rx_ring, tx_buffer, response)
+    };};
+}
+
+pub struct BluetoothSerialComponent<U: 'static + uart::Uart<'static>, A: 'static + Alarm<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    uart: &'static U,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    baud_rate: u32,
+    module: Module,
+    key_pin: Option<&'static dyn gpio::Pin>,
+    state_pin: Option<&'static dyn gpio::InterruptPin<'static>>,
+}
+
+impl<U: 'static + uart::Uart<'static>, A: 'static + Alarm<'static>> BluetoothSerialComponent<U, A> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        uart: &'static U,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        baud_rate: u32,
+        module: Module,
+        key_pin: Option<&'static dyn gpio::Pin>,
+        state_pin: Option<&'static dyn gpio::InterruptPin<'static>>,
+    ) -> Self {
+        BluetoothSerialComponent {
+            board_kernel,
+            driver_num,
+            uart,
+            alarm_mux,
+            baud_rate,
+            module,
+            key_pin,
+            state_pin,
+        }
+    }
+}
+
+impl<U: 'static + uart::Uart<'static>, A: 'static + Alarm<'static>> Component
+    for BluetoothSerialComponent<U, A>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<BluetoothSerial<'static, U, VirtualMuxAlarm<'static, A>>>,
+        &'static mut MaybeUninit<[u8; 1]>,
+        &'static mut MaybeUninit<[u8; RX_LEN]>,
+        &'static mut MaybeUninit<[u8; TX_LEN]>,
+        &'static mut MaybeUninit<[u8; RESPONSE_LEN]>,
+    );
+    type Output = &'static BluetoothSerial<'static, U, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let rx_byte = static_buffer.2.write([0; 1]);
+        let rx_ring = static_buffer.3.write([0; RX_LEN]);
+        let tx_buffer = static_buffer.4.write([0; TX_LEN]);
+        let response = static_buffer.5.write([0; RESPONSE_LEN]);
+
+        let bluetooth = static_buffer.1.write(BluetoothSerial::new(
+            self.uart,
+            alarm,
+            self.baud_rate,
+            self.module,
+            self.key_pin,
+            self.state_pin,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+            rx_byte,
+            rx_ring,
+            tx_buffer,
+            response,
+        ));
+        alarm.set_alarm_client(bluetooth);
+        if let Some(state_pin) = self.state_pin {
+            state_pin.set_client(bluetooth);
+        }
+        uart::Transmit::set_transmit_client(self.uart, bluetooth);
+        uart::Receive::set_receive_client(self.uart, bluetooth);
+        let _ = bluetooth.initialize();
+
+        bluetooth
+    }
+}
@@ -0,0 +1,64 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the boot and watchdog reset counter.
+//!
+//! The component records the current boot, so it should be finalized once,
+//! early during board initialization.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let boot_counter = components::boot_counter::BootCounterComponent::new(
+//!     &peripherals.watchdog,
+//!     0,
+//!     peripherals.watchdog.reset_reason(),
+//! )
+//! .finalize(components::boot_counter_component_static!(
+//!     rp2040::watchdog::Watchdog<'static>
+//! ));
+//! ```
+
+use capsules_extra::boot_counter::{BootCounter, NUM_REGISTERS};
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::backup_registers::BackupRegisters;
+use kernel::hil::reset_reason::ResetReason;
+
+#[macro_export]
+macro_rules! boot_counter_component_static {
+    ($B:ty $(,)?) => {{
+        kernel::static_buf!(capsules_extra::boot_counter::BootCounter<'static, $B>)
+    };};
+}
+
+pub struct BootCounterComponent<B: 'static + BackupRegisters> {
+    registers: &'static B,
+    first_register: usize,
+    reset_reason: ResetReason,
+}
+
+impl<B: 'static + BackupRegisters> BootCounterComponent<B> {
+    pub fn new(registers: &'static B, first_register: usize, reset_reason: ResetReason) -> Self {
+        Self {
+            registers,
+            first_register,
+            reset_reason,
+        }
+    }
+}
+
+impl<B: 'static + BackupRegisters> Component for BootCounterComponent<B> {
+    type StaticInput = &'static mut MaybeUninit<BootCounter<'static, B>>;
+    type Output = &'static BootCounter<'static, B>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        assert!(self.first_register + NUM_REGISTERS <= self.registers.num_registers());
+
+        let boot_counter =
+            static_buffer.write(BootCounter::new(self.registers, self.first_register));
+        let _ = boot_counter.record_boot(self.reset_reason);
+        boot_counter
+    }
+}
@@ -0,0 +1,160 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Components for the I2C and SPI bus analyzer.
+//!
+//! This provides three components.
+//!
+//! 1. `BusLogComponent` provides the log of the transactions, which is also
+//!    the `bus` command of the process console. It only reads the time, so
+//!    it takes the hardware timer directly.
+//!
+//! 2. `I2CMasterAnalyzerComponent` wraps an I2C controller. The `MuxI2C` is
+//!    then created on top of the analyzer.
+//!
+//! 3. `SpiDeviceAnalyzerComponent` wraps a virtual SPI device. The driver of
+//!    the device is then given the analyzer.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let bus_log = components::bus_analyzer::BusLogComponent::new(&base_peripherals.tim2)
+//!     .finalize(components::bus_log_component_static!(stm32f412g::tim2::Tim2, 32));
+//! let i2c_analyzer = components::bus_analyzer::I2CMasterAnalyzerComponent::new(
+//!     &base_peripherals.i2c1,
+//!     bus_log,
+//!     "i2c1",
+//! )
+//! .finalize(components::i2c_master_analyzer_component_static!(
+//!     stm32f412g::i2c::I2C<'static>,
+//!     stm32f412g::tim2::Tim2,
+//! ));
+//! let mux_i2c = components::i2c::I2CMuxComponent::new(i2c_analyzer, None).finalize(
+//!     components::i2c_mux_component_static!(
+//!         capsules_extra::bus_analyzer::I2CMasterAnalyzer<
+//!             'static,
+//!             stm32f412g::i2c::I2C<'static>,
+//!             stm32f412g::tim2::Tim2,
+//!         >
+//!     ),
+//! );
+//! process_console.add_command(bus_log).unwrap();
+//! ```
+
+use capsules_extra::bus_analyzer::{BusLog, I2CMasterAnalyzer, SpiDeviceAnalyzer, Transaction};
+use core::cell::Cell;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::i2c;
+use kernel::hil::spi;
+use kernel::hil::time;
+
+#[macro_export]
+macro_rules! bus_log_component_static {
+    ($T:ty, $N:expr $(,)?) => {{
+        let log = kernel::static_buf!(
+            [core::cell::Cell<Option<capsules_extra::bus_analyzer::Transaction>>; $N]
+        );
+        let bus_log = kernel::static_buf!(capsules_extra::bus_analyzer::BusLog<'static, $T>);
+
+        (log, bus_log)
+    };};
+}
+
+#[macro_export]
+macro_rules! i2c_master_analyzer_component_static {
+    ($I:ty, $T:ty $(,)?) => {{
+        kernel::static_buf!(capsules_extra::bus_analyzer::I2CMasterAnalyzer<'static, $I, $T>)
+    };};
+}
+
+#[macro_export]
+macro_rules! spi_device_analyzer_component_static {
+    ($S:ty, $T:ty $(,)?) => {{
+        kernel::static_buf!(capsules_extra::bus_analyzer::SpiDeviceAnalyzer<'static, $S, $T>)
+    };};
+}
+
+pub struct BusLogComponent<T: 'static + time::Time, const N: usize> {
+    time: &'static T,
+}
+
+impl<T: 'static + time::Time, const N: usize> BusLogComponent<T, N> {
+    pub fn new(time: &'static T) -> Self {
+        Self { time }
+    }
+}
+
+impl<T: 'static + time::Time, const N: usize> Component for BusLogComponent<T, N> {
+    type StaticInput = (
+        &'static mut MaybeUninit<[Cell<Option<Transaction>>; N]>,
+        &'static mut MaybeUninit<BusLog<'static, T>>,
+    );
+    type Output = &'static BusLog<'static, T>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let log = static_buffer
+            .0
+            .write(core::array::from_fn(|_| Cell::new(None)));
+        static_buffer.1.write(BusLog::new(self.time, log))
+    }
+}
+
+pub struct I2CMasterAnalyzerComponent<I: 'static + i2c::I2CMaster<'static>, T: 'static + time::Time>
+{
+    i2c: &'static I,
+    log: &'static BusLog<'static, T>,
+    label: &'static str,
+}
+
+impl<I: 'static + i2c::I2CMaster<'static>, T: 'static + time::Time>
+    I2CMasterAnalyzerComponent<I, T>
+{
+    pub fn new(i2c: &'static I, log: &'static BusLog<'static, T>, label: &'static str) -> Self {
+        Self { i2c, log, label }
+    }
+}
+
+impl<I: 'static + i2c::I2CMaster<'static>, T: 'static + time::Time> Component
+    for I2CMasterAnalyzerComponent<I, T>
+{
+    type StaticInput = &'static mut MaybeUninit<I2CMasterAnalyzer<'static, I, T>>;
+    type Output = &'static I2CMasterAnalyzer<'static, I, T>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let analyzer = static_buffer.write(I2CMasterAnalyzer::new(self.i2c, self.log, self.label));
+        self.i2c.set_master_client(analyzer);
+        analyzer
+    }
+}
+
+pub struct SpiDeviceAnalyzerComponent<
+    S: 'static + spi::SpiMasterDevice<'static>,
+    T: 'static + time::Time,
+> {
+    spi: &'static S,
+    log: &'static BusLog<'static, T>,
+    label: &'static str,
+}
+
+impl<S: 'static + spi::SpiMasterDevice<'static>, T: 'static + time::Time>
+    SpiDeviceAnalyzerComponent<S, T>
+{
+    pub fn new(spi: &'static S, log: &'static BusLog<'static, T>, label: &'static str) -> Self {
+        Self { spi, log, label }
+    }
+}
+
+impl<S: 'static + spi::SpiMasterDevice<'static>, T: 'static + time::Time> Component
+    for SpiDeviceAnalyzerComponent<S, T>
+{
+    type StaticInput = &'static mut MaybeUninit<SpiDeviceAnalyzer<'static, S, T>>;
+    type Output = &'static SpiDeviceAnalyzer<'static, S, T>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let analyzer = static_buffer.write(SpiDeviceAnalyzer::new(self.spi, self.log, self.label));
+        self.spi.set_client(analyzer);
+        analyzer
+    }
+}
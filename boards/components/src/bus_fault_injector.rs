@@ -0,0 +1,165 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Components for injecting faults into I2C and SPI devices.
+//!
+//! This provides three components.
+//!
+//! 1. `FaultInjectorComponent` provides the probabilities of the faults,
+//!    which is also the `faults` command of the process console.
+//!
+//! 2. `FaultyI2CDeviceComponent` provides a virtualized client to the I2C
+//!    bus, like `I2CComponent`, whose transactions fail at the rates of the
+//!    injector.
+//!
+//! 3. `FaultySpiDeviceComponent` wraps a virtual SPI device. The driver of
+//!    the device is then given the wrapper.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let fault_injector = components::bus_fault_injector::FaultInjectorComponent::new(0x2545_F491)
+//!     .finalize(components::fault_injector_component_static!());
+//! let i2c_device = components::bus_fault_injector::FaultyI2CDeviceComponent::new(
+//!     mux_i2c,
+//!     0x38,
+//!     fault_injector,
+//! )
+//! .finalize(components::faulty_i2c_device_component_static!(
+//!     stm32f412g::i2c::I2C<'static>
+//! ));
+//! let spi_device = components::bus_fault_injector::FaultySpiDeviceComponent::new(
+//!     spi_device,
+//!     fault_injector,
+//! )
+//! .finalize(components::faulty_spi_device_component_static!(
+//!     capsules_core::virtualizers::virtual_spi::VirtualSpiMasterDevice<
+//!         'static,
+//!         stm32f412g::spi::Spi<'static>,
+//!     >
+//! ));
+//! process_console.add_command(fault_injector).unwrap();
+//! ```
+
+use capsules_core::virtualizers::virtual_i2c::{I2CDevice, MuxI2C};
+use capsules_extra::bus_fault_injector::{FaultInjector, FaultyI2CDevice, FaultySpiDevice};
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::deferred_call::DeferredCallClient;
+use kernel::hil::i2c;
+use kernel::hil::spi;
+
+#[macro_export]
+macro_rules! fault_injector_component_static {
+    () => {{
+        kernel::static_buf!(capsules_extra::bus_fault_injector::FaultInjector)
+    };};
+}
+
+#[macro_export]
+macro_rules! faulty_i2c_device_component_static {
+    ($I:ty $(,)?) => {{
+        let i2c_device =
+            kernel::static_buf!(capsules_core::virtualizers::virtual_i2c::I2CDevice<'static, $I>);
+        let faulty_device = kernel::static_buf!(
+            capsules_extra::bus_fault_injector::FaultyI2CDevice<
+                'static,
+                capsules_core::virtualizers::virtual_i2c::I2CDevice<'static, $I>,
+            >
+        );
+
+        (i2c_device, faulty_device)
+    };};
+}
+
+#[macro_export]
+macro_rules! faulty_spi_device_component_static {
+    ($S:ty $(,)?) => {{
+        kernel::static_buf!(capsules_extra::bus_fault_injector::FaultySpiDevice<'static, $S>)
+    };};
+}
+
+pub struct FaultInjectorComponent {
+    seed: u32,
+}
+
+impl FaultInjectorComponent {
+    /// `seed` selects the sequence of faults, so that a run can be
+    /// reproduced.
+    pub fn new(seed: u32) -> Self {
+        Self { seed }
+    }
+}
+
+impl Component for FaultInjectorComponent {
+    type StaticInput = &'static mut MaybeUninit<FaultInjector>;
+    type Output = &'static FaultInjector;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        static_buffer.write(FaultInjector::new(self.seed))
+    }
+}
+
+pub struct FaultyI2CDeviceComponent<I: 'static + i2c::I2CMaster<'static>> {
+    i2c_mux: &'static MuxI2C<'static, I>,
+    address: u8,
+    injector: &'static FaultInjector,
+}
+
+impl<I: 'static + i2c::I2CMaster<'static>> FaultyI2CDeviceComponent<I> {
+    pub fn new(
+        i2c_mux: &'static MuxI2C<'static, I>,
+        address: u8,
+        injector: &'static FaultInjector,
+    ) -> Self {
+        Self {
+            i2c_mux,
+            address,
+            injector,
+        }
+    }
+}
+
+impl<I: 'static + i2c::I2CMaster<'static>> Component for FaultyI2CDeviceComponent<I> {
+    type StaticInput = (
+        &'static mut MaybeUninit<I2CDevice<'static, I>>,
+        &'static mut MaybeUninit<FaultyI2CDevice<'static, I2CDevice<'static, I>>>,
+    );
+    type Output = &'static FaultyI2CDevice<'static, I2CDevice<'static, I>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let i2c_device = static_buffer
+            .0
+            .write(I2CDevice::new(self.i2c_mux, self.address));
+        let faulty_device = static_buffer
+            .1
+            .write(FaultyI2CDevice::new(i2c_device, self.injector));
+        i2c_device.set_client(faulty_device);
+        faulty_device.register();
+        faulty_device
+    }
+}
+
+pub struct FaultySpiDeviceComponent<S: 'static + spi::SpiMasterDevice<'static>> {
+    spi: &'static S,
+    injector: &'static FaultInjector,
+}
+
+impl<S: 'static + spi::SpiMasterDevice<'static>> FaultySpiDeviceComponent<S> {
+    pub fn new(spi: &'static S, injector: &'static FaultInjector) -> Self {
+        Self { spi, injector }
+    }
+}
+
+impl<S: 'static + spi::SpiMasterDevice<'static>> Component for FaultySpiDeviceComponent<S> {
+    type StaticInput = &'static mut MaybeUninit<FaultySpiDevice<'static, S>>;
+    type Output = &'static FaultySpiDevice<'static, S>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let faulty_device = static_buffer.write(FaultySpiDevice::new(self.spi, self.injector));
+        self.spi.set_client(faulty_device);
+        faulty_device.register();
+        faulty_device
+    }
+}
@@ -0,0 +1,97 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for a cellular modem controlled with AT commands.
+//!
+//! The modem runs its commands on an AT engine, see the `at_engine`
+//! component, which it uses alone.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let modem = components::cellular_modem::CellularModemComponent::new(
+//!     board_kernel,
+//!     capsules_extra::cellular_modem::DRIVER_NUM,
+//!     at_engine,
+//!     capsules_extra::cellular_modem::Dialect::Quectel,
+//! )
+//! .finalize(components::cellular_modem_component_static!(
+//!     rp2040::uart::Uart<'static>,
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm;
+use capsules_extra::at_engine::AtEngine;
+use capsules_extra::cellular_modem::{CellularModem, Dialect, READ_LEN};
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::time::Alarm;
+use kernel::hil::uart;
+
+#[macro_export]
+macro_rules! cellular_modem_component_static {
+    ($U:ty, $A:ty $(,)?) => {{
+        let modem = kernel::static_buf!(
+            capsules_extra::cellular_modem::CellularModem<
+                'static,
+                $U,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+        let data = kernel::static_buf!([u8; capsules_extra::cellular_modem::READ_LEN]);
+
+        (modem, data)
+    };};
+}
+
+pub struct CellularModemComponent<U: 'static + uart::Uart<'static>, A: 'static + Alarm<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    engine: &'static AtEngine<'static, U, VirtualMuxAlarm<'static, A>>,
+    dialect: Dialect,
+}
+
+impl<U: 'static + uart::Uart<'static>, A: 'static + Alarm<'static>> CellularModemComponent<U, A> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        engine: &'static AtEngine<'static, U, VirtualMuxAlarm<'static, A>>,
+        dialect: Dialect,
+    ) -> Self {
+        CellularModemComponent {
+            board_kernel,
+            driver_num,
+            engine,
+            dialect,
+        }
+    }
+}
+
+impl<U: 'static + uart::Uart<'static>, A: 'static + Alarm<'static>> Component
+    for CellularModemComponent<U, A>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<CellularModem<'static, U, VirtualMuxAlarm<'static, A>>>,
+        &'static mut MaybeUninit<[u8; READ_LEN]>,
+    );
+    type Output = &'static CellularModem<'static, U, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let data = static_buffer.1.write([0; READ_LEN]);
+        let modem = static_buffer.0.write(CellularModem::new(
+            self.engine,
+            self.dialect,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+            data,
+        ));
+        self.engine.set_client(modem);
+
+        modem
+    }
+}
@@ -0,0 +1,44 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the `clocks` command of the process console.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let clocks = components::clock_tree::ClockTreeCommandComponent::new(&peripherals.rcc)
+//!     .finalize(components::clock_tree_command_component_static!());
+//! process_console.add_command(clocks).unwrap();
+//! ```
+
+use capsules_extra::clock_tree::ClockTreeCommand;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::clock_tree::ClockTree;
+
+#[macro_export]
+macro_rules! clock_tree_command_component_static {
+    () => {{
+        kernel::static_buf!(capsules_extra::clock_tree::ClockTreeCommand<'static>)
+    };};
+}
+
+pub struct ClockTreeCommandComponent {
+    clock_tree: &'static dyn ClockTree,
+}
+
+impl ClockTreeCommandComponent {
+    pub fn new(clock_tree: &'static dyn ClockTree) -> Self {
+        ClockTreeCommandComponent { clock_tree }
+    }
+}
+
+impl Component for ClockTreeCommandComponent {
+    type StaticInput = &'static mut MaybeUninit<ClockTreeCommand<'static>>;
+    type Output = &'static ClockTreeCommand<'static>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        static_buffer.write(ClockTreeCommand::new(self.clock_tree))
+    }
+}
@@ -37,7 +37,7 @@ use capsules_core::console;
 use capsules_core::console_ordered::ConsoleOrdered;
 
 use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
-use capsules_core::virtualizers::virtual_uart::{MuxUart, UartDevice};
+use capsules_core::virtualizers::virtual_uart::{MuxUart, UartDevice, FRAME_HEADER_LEN};
 use core::mem::MaybeUninit;
 use kernel::capabilities;
 use kernel::component::Component;
@@ -55,20 +55,25 @@ macro_rules! uart_mux_component_static {
         use kernel::static_buf;
         let UART_MUX = static_buf!(MuxUart<'static>);
         let RX_BUF = static_buf!([u8; capsules_core::virtualizers::virtual_uart::RX_BUF_LEN]);
-        (UART_MUX, RX_BUF)
+        let HEADER_BUF =
+            static_buf!([u8; capsules_core::virtualizers::virtual_uart::FRAME_HEADER_LEN]);
+        (UART_MUX, RX_BUF, HEADER_BUF)
     }};
     ($rx_buffer_len: literal) => {{
         use capsules_core::virtualizers::virtual_uart::MuxUart;
         use kernel::static_buf;
         let UART_MUX = static_buf!(MuxUart<'static>);
         let RX_BUF = static_buf!([u8; $rx_buffer_len]);
-        (UART_MUX, RX_BUF)
+        let HEADER_BUF =
+            static_buf!([u8; capsules_core::virtualizers::virtual_uart::FRAME_HEADER_LEN]);
+        (UART_MUX, RX_BUF, HEADER_BUF)
     }};
 }
 
 pub struct UartMuxComponent<const RX_BUF_LEN: usize> {
     uart: &'static dyn uart::Uart<'static>,
     baud_rate: u32,
+    framed: bool,
 }
 
 impl<const RX_BUF_LEN: usize> UartMuxComponent<RX_BUF_LEN> {
@@ -76,7 +81,25 @@ impl<const RX_BUF_LEN: usize> UartMuxComponent<RX_BUF_LEN> {
         uart: &'static dyn uart::Uart<'static>,
         baud_rate: u32,
     ) -> UartMuxComponent<RX_BUF_LEN> {
-        UartMuxComponent { uart, baud_rate }
+        UartMuxComponent {
+            uart,
+            baud_rate,
+            framed: false,
+        }
+    }
+
+    /// Create a mux that sends the output of each device, and of each process
+    /// using the console, in frames tagged with its channel. See
+    /// `capsules_core::virtualizers::virtual_uart` for the format.
+    pub fn new_framed(
+        uart: &'static dyn uart::Uart<'static>,
+        baud_rate: u32,
+    ) -> UartMuxComponent<RX_BUF_LEN> {
+        UartMuxComponent {
+            uart,
+            baud_rate,
+            framed: true,
+        }
     }
 }
 
@@ -84,6 +107,7 @@ impl<const RX_BUF_LEN: usize> Component for UartMuxComponent<RX_BUF_LEN> {
     type StaticInput = (
         &'static mut MaybeUninit<MuxUart<'static>>,
         &'static mut MaybeUninit<[u8; RX_BUF_LEN]>,
+        &'static mut MaybeUninit<[u8; FRAME_HEADER_LEN]>,
     );
     type Output = &'static MuxUart<'static>;
 
@@ -91,6 +115,9 @@ impl<const RX_BUF_LEN: usize> Component for UartMuxComponent<RX_BUF_LEN> {
         let rx_buf = s.1.write([0; RX_BUF_LEN]);
         let uart_mux = s.0.write(MuxUart::new(self.uart, rx_buf, self.baud_rate));
         kernel::deferred_call::DeferredCallClient::register(uart_mux);
+        if self.framed {
+            uart_mux.enable_framing(s.2.write([0; FRAME_HEADER_LEN]));
+        }
 
         uart_mux.initialize();
         hil::uart::Transmit::set_transmit_client(self.uart, uart_mux);
@@ -162,6 +189,7 @@ impl Component for ConsoleComponent {
         ));
         hil::uart::Transmit::set_transmit_client(console_uart, console);
         hil::uart::Receive::set_receive_client(console_uart, console);
+        console.set_app_channels(console_uart);
 
         console
     }
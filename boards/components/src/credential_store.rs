@@ -0,0 +1,137 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the encrypted credential store.
+//!
+//! The credential store gets its own user of the AES-CCM mux. Call `start()`
+//! on the output once the key-value store is set up.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let credentials = components::credential_store::CredentialStoreComponent::new(
+//!     kv_store,
+//!     ccm_mux,
+//!     &peripherals.ocotp,
+//!     &[b"wifi-ssid", b"wifi-password"],
+//! )
+//! .finalize(components::credential_store_component_static!(
+//!     capsules_extra::tickv::TicKVStore<
+//!         capsules_core::virtualizers::virtual_flash::FlashUser<lowrisc::flash_ctrl::FlashCtrl>,
+//!     >,
+//!     capsules_extra::tickv::TicKVKeyType,
+//!     lowrisc::aes::Aes<'static>,
+//! ));
+//! let _ = credentials.start();
+//! ```
+
+use capsules_core::virtualizers::virtual_aes_ccm::{MuxAES128CCM, VirtualAES128CCM};
+use capsules_extra::credential_store::{
+    CredentialStore, CCM_BUF_LEN, CRYPT_BUF_LEN, KEY_BUF_LEN, VALUE_BUF_LEN,
+};
+use capsules_extra::kv_store::KVStore;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::device_key::DeviceKey;
+use kernel::hil::kv_system::{KVSystem, KeyType};
+use kernel::hil::symmetric_encryption::{AES128Ctr, AES128, AES128CBC, AES128CCM, AES128ECB};
+use kernel::storage_permissions::StoragePermissions;
+
+#[macro_export]
+macro_rules! credential_store_component_static {
+    ($K:ty, $T:ty, $A:ty $(,)?) => {{
+        let ccm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_aes_ccm::VirtualAES128CCM<'static, $A>
+        );
+        let store = kernel::static_buf!(
+            capsules_extra::credential_store::CredentialStore<
+                'static,
+                $K,
+                $T,
+                capsules_core::virtualizers::virtual_aes_ccm::VirtualAES128CCM<'static, $A>,
+            >
+        );
+        let ccm_buf = kernel::static_buf!([u8; capsules_extra::credential_store::CCM_BUF_LEN]);
+        let key = kernel::static_buf!([u8; capsules_extra::credential_store::KEY_BUF_LEN]);
+        let value = kernel::static_buf!([u8; capsules_extra::credential_store::VALUE_BUF_LEN]);
+        let crypt = kernel::static_buf!([u8; capsules_extra::credential_store::CRYPT_BUF_LEN]);
+
+        (ccm, store, ccm_buf, key, value, crypt)
+    };};
+}
+
+pub struct CredentialStoreComponent<
+    K: 'static + KVSystem<'static, K = T>,
+    T: 'static + KeyType,
+    A: 'static + AES128<'static> + AES128Ctr + AES128CBC + AES128ECB,
+> {
+    kv: &'static KVStore<'static, K, T>,
+    ccm_mux: &'static MuxAES128CCM<'static, A>,
+    device_key: &'static dyn DeviceKey,
+    names: &'static [&'static [u8]],
+}
+
+impl<
+        K: 'static + KVSystem<'static, K = T>,
+        T: 'static + KeyType,
+        A: 'static + AES128<'static> + AES128Ctr + AES128CBC + AES128ECB,
+    > CredentialStoreComponent<K, T, A>
+{
+    pub fn new(
+        kv: &'static KVStore<'static, K, T>,
+        ccm_mux: &'static MuxAES128CCM<'static, A>,
+        device_key: &'static dyn DeviceKey,
+        names: &'static [&'static [u8]],
+    ) -> Self {
+        Self {
+            kv,
+            ccm_mux,
+            device_key,
+            names,
+        }
+    }
+}
+
+impl<
+        K: 'static + KVSystem<'static, K = T>,
+        T: 'static + KeyType,
+        A: 'static + AES128<'static> + AES128Ctr + AES128CBC + AES128ECB,
+    > Component for CredentialStoreComponent<K, T, A>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualAES128CCM<'static, A>>,
+        &'static mut MaybeUninit<CredentialStore<'static, K, T, VirtualAES128CCM<'static, A>>>,
+        &'static mut MaybeUninit<[u8; CCM_BUF_LEN]>,
+        &'static mut MaybeUninit<[u8; KEY_BUF_LEN]>,
+        &'static mut MaybeUninit<[u8; VALUE_BUF_LEN]>,
+        &'static mut MaybeUninit<[u8; CRYPT_BUF_LEN]>,
+    );
+    type Output = &'static CredentialStore<'static, K, T, VirtualAES128CCM<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let storage_cap = create_capability!(capabilities::KerneluserStorageCapability);
+
+        let ccm_buf = static_buffer.2.write([0; CCM_BUF_LEN]);
+        let ccm = static_buffer
+            .0
+            .write(VirtualAES128CCM::new(self.ccm_mux, ccm_buf));
+        ccm.setup();
+
+        let store = static_buffer.1.write(CredentialStore::new(
+            self.kv,
+            ccm,
+            self.device_key,
+            self.names,
+            StoragePermissions::new_kernel_permissions(&storage_cap),
+            static_buffer.3.write([0; KEY_BUF_LEN]),
+            static_buffer.4.write([0; VALUE_BUF_LEN]),
+            static_buffer.5.write([0; CRYPT_BUF_LEN]),
+        ));
+        AES128CCM::set_client(ccm, store);
+        self.kv.set_client(store);
+        store
+    }
+}
@@ -4,40 +4,89 @@
 
 //! Component for Digital to Analog Converters (DAC).
 //!
+//! The alarm paces the playback of waveforms.
+//!
 //! Usage
 //! -----
 //! ```rust
-//! let dac = components::dac::DacComponent::new(&peripherals.dac)
-//!      .finalize(components::dac_component_static!());
+//! let dac = components::dac::DacComponent::new(
+//!     board_kernel,
+//!     capsules_extra::dac::DRIVER_NUM,
+//!     &peripherals.dac,
+//!     mux_alarm,
+//! )
+//! .finalize(components::dac_component_static!(sam4l::ast::Ast));
 //! ```
 
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
 use capsules_extra::dac::Dac;
 use core::mem::MaybeUninit;
+use kernel::capabilities;
 use kernel::component::Component;
+use kernel::create_capability;
 use kernel::hil;
+use kernel::hil::time::Alarm;
 
 #[macro_export]
 macro_rules! dac_component_static {
-    () => {{
-        kernel::static_buf!(capsules_extra::dac::Dac<'static>)
+    ($A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let dac = kernel::static_buf!(
+            capsules_extra::dac::Dac<
+                'static,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+
+        (alarm, dac)
     };};
 }
 
-pub struct DacComponent {
+pub struct DacComponent<A: 'static + Alarm<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
     dac: &'static dyn hil::dac::DacChannel,
+    alarm_mux: &'static MuxAlarm<'static, A>,
 }
 
-impl DacComponent {
-    pub fn new(dac: &'static dyn hil::dac::DacChannel) -> Self {
-        Self { dac }
+impl<A: 'static + Alarm<'static>> DacComponent<A> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        dac: &'static dyn hil::dac::DacChannel,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+    ) -> Self {
+        Self {
+            board_kernel,
+            driver_num,
+            dac,
+            alarm_mux,
+        }
     }
 }
 
-impl Component for DacComponent {
-    type StaticInput = &'static mut MaybeUninit<Dac<'static>>;
-    type Output = &'static Dac<'static>;
+impl<A: 'static + Alarm<'static>> Component for DacComponent<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<Dac<'static, VirtualMuxAlarm<'static, A>>>,
+    );
+    type Output = &'static Dac<'static, VirtualMuxAlarm<'static, A>>;
 
     fn finalize(self, s: Self::StaticInput) -> Self::Output {
-        s.write(Dac::new(self.dac))
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let alarm = s.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let dac = s.1.write(Dac::new(
+            self.dac,
+            alarm,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+        alarm.set_alarm_client(dac);
+
+        dac
     }
 }
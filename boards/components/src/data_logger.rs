@@ -0,0 +1,108 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the data logger.
+//!
+//! The logger becomes the client of the log and the kernel client of the
+//! sensor aggregation driver. Logging starts when `start` is called on the
+//! logger, or from userspace.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let data_logger = components::data_logger::DataLoggerComponent::new(
+//!     board_kernel,
+//!     capsules_extra::data_logger::DRIVER_NUM,
+//!     log,
+//!     sensor_aggregator,
+//! )
+//! .finalize(components::data_logger_component_static!(
+//!     capsules_extra::log::Log<'static, nrf52840::nvmc::Nvmc>,
+//!     capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<
+//!         'static,
+//!         nrf52840::rtc::Rtc<'static>,
+//!     >,
+//! ));
+//! ```
+
+use capsules_extra::data_logger::{DataLogger, ENTRY_LEN};
+use capsules_extra::sensor_aggregator::SensorAggregator;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::log::{LogRead, LogWrite};
+use kernel::hil::time;
+
+#[macro_export]
+macro_rules! data_logger_component_static {
+    ($L:ty, $A:ty $(,)?) => {{
+        let entry = kernel::static_buf!([u8; capsules_extra::data_logger::ENTRY_LEN]);
+        let spare = kernel::static_buf!([u8; capsules_extra::data_logger::ENTRY_LEN]);
+        let read_buffer = kernel::static_buf!([u8; capsules_extra::data_logger::ENTRY_LEN]);
+        let data_logger =
+            kernel::static_buf!(capsules_extra::data_logger::DataLogger<'static, $L, $A>);
+
+        (entry, spare, read_buffer, data_logger)
+    };};
+}
+
+pub struct DataLoggerComponent<
+    L: 'static + LogRead<'static> + LogWrite<'static>,
+    A: 'static + time::Alarm<'static>,
+> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    log: &'static L,
+    aggregator: &'static SensorAggregator<'static, A>,
+}
+
+impl<L: 'static + LogRead<'static> + LogWrite<'static>, A: 'static + time::Alarm<'static>>
+    DataLoggerComponent<L, A>
+{
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        log: &'static L,
+        aggregator: &'static SensorAggregator<'static, A>,
+    ) -> Self {
+        DataLoggerComponent {
+            board_kernel,
+            driver_num,
+            log,
+            aggregator,
+        }
+    }
+}
+
+impl<L: 'static + LogRead<'static> + LogWrite<'static>, A: 'static + time::Alarm<'static>> Component
+    for DataLoggerComponent<L, A>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<[u8; ENTRY_LEN]>,
+        &'static mut MaybeUninit<[u8; ENTRY_LEN]>,
+        &'static mut MaybeUninit<[u8; ENTRY_LEN]>,
+        &'static mut MaybeUninit<DataLogger<'static, L, A>>,
+    );
+    type Output = &'static DataLogger<'static, L, A>;
+
+    fn finalize(self, s: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let data_logger = s.3.write(DataLogger::new(
+            self.log,
+            self.aggregator,
+            s.0.write([0; ENTRY_LEN]),
+            s.1.write([0; ENTRY_LEN]),
+            s.2.write([0; ENTRY_LEN]),
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+        self.log.set_read_client(data_logger);
+        self.log.set_append_client(data_logger);
+        self.aggregator.set_client(data_logger);
+
+        data_logger
+    }
+}
@@ -0,0 +1,83 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component limiting the rate of kernel debug output.
+//!
+//! The debug writer must be set up before this component is finalized.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! // At most 8000 bytes per second, reporting drops every 5 seconds.
+//! components::debug_rate_limit::DebugRateLimitComponent::new(mux_alarm, 8000, 5000)
+//!     .finalize(components::debug_rate_limit_component_static!(
+//!         rp2040::timer::RPTimer
+//!     ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::debug_rate_limit::DebugRateLimit;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::time::Alarm;
+
+#[macro_export]
+macro_rules! debug_rate_limit_component_static {
+    ($A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let rate_limit = kernel::static_buf!(
+            capsules_extra::debug_rate_limit::DebugRateLimit<
+                'static,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+
+        (alarm, rate_limit)
+    };};
+}
+
+pub struct DebugRateLimitComponent<A: 'static + Alarm<'static>> {
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    bytes_per_second: usize,
+    report_interval_ms: u32,
+}
+
+impl<A: 'static + Alarm<'static>> DebugRateLimitComponent<A> {
+    pub fn new(
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        bytes_per_second: usize,
+        report_interval_ms: u32,
+    ) -> Self {
+        Self {
+            alarm_mux,
+            bytes_per_second,
+            report_interval_ms,
+        }
+    }
+}
+
+impl<A: 'static + Alarm<'static>> Component for DebugRateLimitComponent<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<DebugRateLimit<'static, VirtualMuxAlarm<'static, A>>>,
+    );
+    type Output = &'static DebugRateLimit<'static, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let rate_limit = static_buffer.1.write(DebugRateLimit::new(
+            alarm,
+            self.bytes_per_second,
+            self.report_interval_ms,
+        ));
+        alarm.set_alarm_client(rate_limit);
+        rate_limit.start();
+
+        rate_limit
+    }
+}
@@ -22,7 +22,7 @@
 // Author: Brad Campbell <bradjc@virginia.edu>
 // Last modified: 11/07/2019
 
-use capsules_core::virtualizers::virtual_uart::{MuxUart, UartDevice};
+use capsules_core::virtualizers::virtual_uart::{channel, Channel, MuxUart, UartDevice};
 use core::mem::MaybeUninit;
 use kernel::capabilities;
 use kernel::collections::ring_buffer::RingBuffer;
@@ -114,6 +114,7 @@ impl<const BUF_SIZE_BYTES: usize> Component for DebugWriterComponent<BUF_SIZE_BY
         // Create virtual device for kernel debug.
         let debugger_uart = s.0.write(UartDevice::new(self.uart_mux, false));
         debugger_uart.setup();
+        debugger_uart.set_channel(channel::KERNEL_DEBUG);
         let ring_buffer = s.1.write(RingBuffer::new(internal_buf));
         let debugger = s.3.write(kernel::debug::DebugWriter::new(
             debugger_uart,
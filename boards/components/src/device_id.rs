@@ -0,0 +1,74 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the device ID syscall driver.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let device_id = components::device_id::DeviceIdComponent::new(
+//!     board_kernel,
+//!     capsules_extra::device_id::DRIVER_NUM,
+//!     Some(&base_peripherals.uid),
+//!     "stm32f412gdiscovery",
+//!     "MB1209",
+//! )
+//! .finalize(components::device_id_component_static!());
+//! ```
+
+use capsules_extra::device_id::DeviceIdDriver;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::device_id::DeviceId;
+
+#[macro_export]
+macro_rules! device_id_component_static {
+    () => {{
+        kernel::static_buf!(capsules_extra::device_id::DeviceIdDriver<'static>)
+    };};
+}
+
+pub struct DeviceIdComponent {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    id: Option<&'static dyn DeviceId>,
+    board_name: &'static str,
+    board_version: &'static str,
+}
+
+impl DeviceIdComponent {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        id: Option<&'static dyn DeviceId>,
+        board_name: &'static str,
+        board_version: &'static str,
+    ) -> Self {
+        DeviceIdComponent {
+            board_kernel,
+            driver_num,
+            id,
+            board_name,
+            board_version,
+        }
+    }
+}
+
+impl Component for DeviceIdComponent {
+    type StaticInput = &'static mut MaybeUninit<DeviceIdDriver<'static>>;
+    type Output = &'static DeviceIdDriver<'static>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        static_buffer.write(DeviceIdDriver::new(
+            self.id,
+            self.board_name,
+            self.board_version,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ))
+    }
+}
@@ -0,0 +1,103 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the DHCP client of the IPv4 stack over Ethernet.
+//!
+//! The stack should be created with `Ipv4Config::UNCONFIGURED`. Call
+//! `start()` on the client once the stack is started.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let dhcp = components::dhcp::DhcpClientComponent::new(ipv4_stack, mux_alarm).finalize(
+//!     components::dhcp_client_component_static!(
+//!         imxrt1050::enet::Enet<'static>,
+//!         imxrt1050::gpt::Gpt1<'static>,
+//!     ),
+//! );
+//! ipv4_stack.start().unwrap();
+//! dhcp.start();
+//! ```
+
+use crate::ipv4::Ipv4StackComponentType;
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::net::ipv4::dhcp::{DhcpClient, BUF_LEN, CLIENT_PORT};
+use capsules_extra::net::ipv4::stack::UdpSocket;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::ethernet::EthernetAdapter;
+use kernel::hil::mac_address::Configure;
+use kernel::hil::time::Alarm;
+
+#[macro_export]
+macro_rules! dhcp_client_component_static {
+    ($E:ty, $A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let socket = kernel::static_buf!(capsules_extra::net::ipv4::stack::UdpSocket<'static>);
+        let buffer = kernel::static_buf!([u8; capsules_extra::net::ipv4::dhcp::BUF_LEN]);
+        let dhcp = kernel::static_buf!(
+            capsules_extra::net::ipv4::dhcp::DhcpClient<
+                'static,
+                components::ipv4::Ipv4StackComponentType<$E, $A>,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+
+        (alarm, socket, buffer, dhcp)
+    };};
+}
+
+pub type DhcpClientComponentType<E, A> =
+    DhcpClient<'static, Ipv4StackComponentType<E, A>, VirtualMuxAlarm<'static, A>>;
+
+pub struct DhcpClientComponent<
+    E: 'static + EthernetAdapter<'static> + Configure,
+    A: 'static + Alarm<'static>,
+> {
+    stack: &'static Ipv4StackComponentType<E, A>,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+}
+
+impl<E: 'static + EthernetAdapter<'static> + Configure, A: 'static + Alarm<'static>>
+    DhcpClientComponent<E, A>
+{
+    pub fn new(
+        stack: &'static Ipv4StackComponentType<E, A>,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+    ) -> Self {
+        Self { stack, alarm_mux }
+    }
+}
+
+impl<E: 'static + EthernetAdapter<'static> + Configure, A: 'static + Alarm<'static>> Component
+    for DhcpClientComponent<E, A>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<UdpSocket<'static>>,
+        &'static mut MaybeUninit<[u8; BUF_LEN]>,
+        &'static mut MaybeUninit<DhcpClientComponentType<E, A>>,
+    );
+    type Output = &'static DhcpClientComponentType<E, A>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let socket = static_buffer.1.write(UdpSocket::new());
+        let buffer = static_buffer.2.write([0; BUF_LEN]);
+
+        let dhcp = static_buffer
+            .3
+            .write(DhcpClient::new(self.stack, socket, alarm, buffer));
+        socket.set_client(dhcp);
+        alarm.set_alarm_client(dhcp);
+        self.stack.add_socket(socket);
+        self.stack.bind(socket, CLIENT_PORT).unwrap();
+
+        dhcp
+    }
+}
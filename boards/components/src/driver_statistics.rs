@@ -0,0 +1,68 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the driver statistics syscall driver.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let sources = static_init!(
+//!     [(&'static str, &'static dyn DriverStatistics); 2],
+//!     [("usart2", &base_peripherals.usart2), ("i2c1", &base_peripherals.i2c1)]
+//! );
+//! let driver_statistics = components::driver_statistics::DriverStatisticsComponent::new(
+//!     board_kernel,
+//!     capsules_extra::driver_statistics::DRIVER_NUM,
+//!     sources,
+//! )
+//! .finalize(components::driver_statistics_component_static!());
+//! ```
+
+use capsules_extra::driver_statistics::DriverStatisticsDriver;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::statistics::DriverStatistics;
+
+#[macro_export]
+macro_rules! driver_statistics_component_static {
+    () => {{
+        kernel::static_buf!(capsules_extra::driver_statistics::DriverStatisticsDriver<'static>)
+    };};
+}
+
+pub struct DriverStatisticsComponent {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    sources: &'static [(&'static str, &'static dyn DriverStatistics)],
+}
+
+impl DriverStatisticsComponent {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        sources: &'static [(&'static str, &'static dyn DriverStatistics)],
+    ) -> Self {
+        DriverStatisticsComponent {
+            board_kernel,
+            driver_num,
+            sources,
+        }
+    }
+}
+
+impl Component for DriverStatisticsComponent {
+    type StaticInput = &'static mut MaybeUninit<DriverStatisticsDriver<'static>>;
+    type Output = &'static DriverStatisticsDriver<'static>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        static_buffer.write(DriverStatisticsDriver::new(
+            self.sources,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ))
+    }
+}
@@ -0,0 +1,65 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for FlashHealth, which counts erases and replaces bad pages in
+//! a region of flash.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let flash_health = components::flash_health::FlashHealthComponent::new(
+//!     &base_peripherals.nvmc,
+//!     0x60,
+//!     4,
+//! )
+//! .finalize(components::flash_health_component_static!(nrf52840::nvmc::Nvmc, 32));
+//! ```
+
+use capsules_extra::flash_health::FlashHealth;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::flash::{Flash, HasClient};
+
+// Setup static space for the objects.
+#[macro_export]
+macro_rules! flash_health_component_static {
+    ($F:ty, $N:expr $(,)?) => {{
+        kernel::static_buf!(capsules_extra::flash_health::FlashHealth<'static, $F, $N>)
+    };};
+}
+
+pub struct FlashHealthComponent<
+    F: 'static + Flash + HasClient<'static, FlashHealth<'static, F, N>>,
+    const N: usize,
+> {
+    flash: &'static F,
+    first_page: usize,
+    spares: usize,
+}
+
+impl<F: 'static + Flash + HasClient<'static, FlashHealth<'static, F, N>>, const N: usize>
+    FlashHealthComponent<F, N>
+{
+    pub fn new(flash: &'static F, first_page: usize, spares: usize) -> Self {
+        Self {
+            flash,
+            first_page,
+            spares,
+        }
+    }
+}
+
+impl<F: 'static + Flash + HasClient<'static, FlashHealth<'static, F, N>>, const N: usize> Component
+    for FlashHealthComponent<F, N>
+{
+    type StaticInput = &'static mut MaybeUninit<FlashHealth<'static, F, N>>;
+    type Output = &'static FlashHealth<'static, F, N>;
+
+    fn finalize(self, s: Self::StaticInput) -> Self::Output {
+        let flash_health = s.write(FlashHealth::new(self.flash, self.first_page, self.spares));
+        HasClient::set_client(self.flash, flash_health);
+
+        flash_health
+    }
+}
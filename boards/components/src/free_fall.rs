@@ -0,0 +1,141 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Components for free fall detection.
+//!
+//! `FreeFallComponent` creates the syscall driver and listens to the free
+//! fall interrupt pin of the accelerometer, if it has one.
+//! `SoftwareFreeFallComponent` detects free falls by reading an
+//! accelerometer, and reports them to the driver.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let free_fall = components::free_fall::FreeFallComponent::new(
+//!     board_kernel,
+//!     capsules_extra::free_fall::DRIVER_NUM,
+//!     None,
+//! )
+//! .finalize(components::free_fall_component_static!());
+//!
+//! components::free_fall::SoftwareFreeFallComponent::new(mux_alarm, lsm6dsoxtr, free_fall)
+//!     .finalize(components::software_free_fall_component_static!(RPTimer));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::free_fall::{FreeFall, FreeFallClient, SoftwareFreeFall};
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::gpio::InterruptPin;
+use kernel::hil::sensors::NineDof;
+use kernel::hil::time::Alarm;
+
+#[macro_export]
+macro_rules! free_fall_component_static {
+    () => {{
+        kernel::static_buf!(capsules_extra::free_fall::FreeFall<'static>)
+    };};
+}
+
+#[macro_export]
+macro_rules! software_free_fall_component_static {
+    ($A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let free_fall = kernel::static_buf!(
+            capsules_extra::free_fall::SoftwareFreeFall<
+                'static,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+
+        (alarm, free_fall)
+    };};
+}
+
+pub struct FreeFallComponent {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    pin: Option<&'static dyn InterruptPin<'static>>,
+}
+
+impl FreeFallComponent {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        pin: Option<&'static dyn InterruptPin<'static>>,
+    ) -> Self {
+        FreeFallComponent {
+            board_kernel,
+            driver_num,
+            pin,
+        }
+    }
+}
+
+impl Component for FreeFallComponent {
+    type StaticInput = &'static mut MaybeUninit<FreeFall<'static>>;
+    type Output = &'static FreeFall<'static>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let free_fall = static_buffer.write(FreeFall::new(
+            self.pin,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+        if let Some(pin) = self.pin {
+            pin.set_client(free_fall);
+        }
+        free_fall.start();
+
+        free_fall
+    }
+}
+
+pub struct SoftwareFreeFallComponent<A: 'static + Alarm<'static>> {
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    sensor: &'static dyn NineDof<'static>,
+    client: &'static dyn FreeFallClient,
+}
+
+impl<A: 'static + Alarm<'static>> SoftwareFreeFallComponent<A> {
+    pub fn new(
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        sensor: &'static dyn NineDof<'static>,
+        client: &'static dyn FreeFallClient,
+    ) -> Self {
+        SoftwareFreeFallComponent {
+            alarm_mux,
+            sensor,
+            client,
+        }
+    }
+}
+
+impl<A: 'static + Alarm<'static>> Component for SoftwareFreeFallComponent<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<SoftwareFreeFall<'static, VirtualMuxAlarm<'static, A>>>,
+    );
+    type Output = &'static SoftwareFreeFall<'static, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let free_fall = static_buffer
+            .1
+            .write(SoftwareFreeFall::new(self.sensor, alarm));
+        alarm.set_alarm_client(free_fall);
+        self.sensor.set_client(free_fall);
+        free_fall.set_client(self.client);
+        free_fall.start();
+
+        free_fall
+    }
+}
@@ -85,6 +85,11 @@ impl<I: 'static + i2c::I2CMaster<'static>> Component for Ft6x06Component<I> {
             buffer,
             events_buffer,
         ));
+        crate::i2c::check_bus_speed(
+            self.i2c_mux,
+            self.i2c_address,
+            capsules_extra::ft6x06::MAX_BUS_SPEED,
+        );
         ft6x06_i2c.set_client(ft6x06);
         self.interrupt_pin.set_client(ft6x06);
 
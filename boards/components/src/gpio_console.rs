@@ -0,0 +1,44 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the `gpio` command of the process console.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let gpio = components::gpio_console::GpioCommandComponent::new(&peripherals.pins)
+//!     .finalize(components::gpio_command_component_static!());
+//! process_console.add_command(gpio).unwrap();
+//! ```
+
+use capsules_extra::gpio_console::GpioCommand;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::gpio::PinIntrospection;
+
+#[macro_export]
+macro_rules! gpio_command_component_static {
+    () => {{
+        kernel::static_buf!(capsules_extra::gpio_console::GpioCommand<'static>)
+    };};
+}
+
+pub struct GpioCommandComponent {
+    pins: &'static dyn PinIntrospection,
+}
+
+impl GpioCommandComponent {
+    pub fn new(pins: &'static dyn PinIntrospection) -> Self {
+        GpioCommandComponent { pins }
+    }
+}
+
+impl Component for GpioCommandComponent {
+    type StaticInput = &'static mut MaybeUninit<GpioCommand<'static>>;
+    type Output = &'static GpioCommand<'static>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        static_buffer.write(GpioCommand::new(self.pins))
+    }
+}
@@ -0,0 +1,123 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for debounced GPIO interrupt pins.
+//!
+//! This provides one Component, `DebouncedPinComponent`, which wraps a GPIO
+//! interrupt pin with a virtual alarm that filters out contact bounce. The
+//! resulting pin can be handed to the button component in place of the raw
+//! GPIO pin.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let debounced_pin = components::gpio_debounce::DebouncedPinComponent::new(
+//!     &sam4l::gpio::PC[24],
+//!     mux_alarm,
+//!     capsules_extra::gpio_debounce::DEFAULT_DEBOUNCE_MS,
+//! )
+//! .finalize(components::debounced_pin_component_static!(
+//!     sam4l::gpio::GPIOPin,
+//!     sam4l::ast::Ast
+//! ));
+//!
+//! let button = components::button::ButtonComponent::new(
+//!     board_kernel,
+//!     capsules_core::button::DRIVER_NUM,
+//!     components::button_component_helper!(
+//!         capsules_extra::gpio_debounce::DebouncedPin<
+//!             'static,
+//!             sam4l::gpio::GPIOPin,
+//!             VirtualMuxAlarm<'static, sam4l::ast::Ast>,
+//!         >,
+//!         (
+//!             debounced_pin,
+//!             kernel::hil::gpio::ActivationMode::ActiveLow,
+//!             kernel::hil::gpio::FloatingState::PullUp
+//!         )
+//!     ),
+//! )
+//! .finalize(components::button_component_static!(
+//!     capsules_extra::gpio_debounce::DebouncedPin<
+//!         'static,
+//!         sam4l::gpio::GPIOPin,
+//!         VirtualMuxAlarm<'static, sam4l::ast::Ast>,
+//!     >
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::gpio_debounce::DebouncedPin;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::gpio;
+use kernel::hil::time::{self, Alarm};
+
+#[macro_export]
+macro_rules! debounced_pin_component_static {
+    ($P:ty, $A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let pin = kernel::static_buf!(
+            capsules_extra::gpio_debounce::DebouncedPin<
+                'static,
+                $P,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+
+        (alarm, pin)
+    };};
+}
+
+pub struct DebouncedPinComponent<
+    P: 'static + gpio::InterruptPin<'static>,
+    A: 'static + time::Alarm<'static>,
+> {
+    pin: &'static P,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    interval_ms: u32,
+}
+
+impl<P: 'static + gpio::InterruptPin<'static>, A: 'static + time::Alarm<'static>>
+    DebouncedPinComponent<P, A>
+{
+    pub fn new(
+        pin: &'static P,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        interval_ms: u32,
+    ) -> Self {
+        DebouncedPinComponent {
+            pin,
+            alarm_mux,
+            interval_ms,
+        }
+    }
+}
+
+impl<P: 'static + gpio::InterruptPin<'static>, A: 'static + time::Alarm<'static>> Component
+    for DebouncedPinComponent<P, A>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<DebouncedPin<'static, P, VirtualMuxAlarm<'static, A>>>,
+    );
+    type Output = &'static DebouncedPin<'static, P, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let debounced_pin =
+            static_buffer
+                .1
+                .write(DebouncedPin::new(self.pin, alarm, self.interval_ms));
+
+        alarm.set_alarm_client(debounced_pin);
+        gpio::Interrupt::set_client(self.pin, debounced_pin);
+
+        debounced_pin
+    }
+}
@@ -0,0 +1,58 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for edge capture in software, on a GPIO interrupt pin.
+//!
+//! The pin must not be used by another driver, such as the GPIO driver.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let capture = components::gpio_pulse_capture::GpioPulseCaptureComponent::new(
+//!     peripherals.pins.get_pin(RPGpio::GPIO16),
+//!     &peripherals.timer,
+//! )
+//! .finalize(components::gpio_pulse_capture_component_static!(
+//!     rp2040::gpio::RPGpioPin<'static>,
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+
+use capsules_extra::gpio_pulse_capture::GpioPulseCapture;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::gpio;
+use kernel::hil::time::Time;
+
+#[macro_export]
+macro_rules! gpio_pulse_capture_component_static {
+    ($P:ty, $T:ty $(,)?) => {{
+        kernel::static_buf!(capsules_extra::gpio_pulse_capture::GpioPulseCapture<'static, $P, $T>)
+    };};
+}
+
+pub struct GpioPulseCaptureComponent<P: 'static + gpio::InterruptPin<'static>, T: 'static + Time> {
+    pin: &'static P,
+    time: &'static T,
+}
+
+impl<P: 'static + gpio::InterruptPin<'static>, T: 'static + Time> GpioPulseCaptureComponent<P, T> {
+    pub fn new(pin: &'static P, time: &'static T) -> Self {
+        GpioPulseCaptureComponent { pin, time }
+    }
+}
+
+impl<P: 'static + gpio::InterruptPin<'static>, T: 'static + Time> Component
+    for GpioPulseCaptureComponent<P, T>
+{
+    type StaticInput = &'static mut MaybeUninit<GpioPulseCapture<'static, P, T>>;
+    type Output = &'static GpioPulseCapture<'static, P, T>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let capture = static_buffer.write(GpioPulseCapture::new(self.pin, self.time));
+        self.pin.set_client(capture);
+
+        capture
+    }
+}
@@ -0,0 +1,79 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the Graphics capsule.
+//!
+//! The buffer holds the pixels generated for each chunk pushed to the
+//! screen. A larger buffer means fewer bus writes, but unlike the buffer of
+//! the `Screen` capsule it does not need to hold a whole frame.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let graphics = components::graphics::GraphicsComponent::new(
+//!     board_kernel,
+//!     capsules_extra::graphics::DRIVER_NUM,
+//!     tft,
+//! )
+//! .finalize(components::graphics_component_static!(1024));
+//! ```
+
+use capsules_extra::graphics::Graphics;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+
+#[macro_export]
+macro_rules! graphics_component_static {
+    ($s:literal $(,)?) => {{
+        let buffer = kernel::static_buf!([u8; $s]);
+        let graphics = kernel::static_buf!(capsules_extra::graphics::Graphics);
+
+        (buffer, graphics)
+    };};
+}
+
+pub struct GraphicsComponent<const BUF_LEN: usize> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    screen: &'static dyn kernel::hil::screen::Screen<'static>,
+}
+
+impl<const BUF_LEN: usize> GraphicsComponent<BUF_LEN> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        screen: &'static dyn kernel::hil::screen::Screen,
+    ) -> GraphicsComponent<BUF_LEN> {
+        GraphicsComponent {
+            board_kernel,
+            driver_num,
+            screen,
+        }
+    }
+}
+
+impl<const BUF_LEN: usize> Component for GraphicsComponent<BUF_LEN> {
+    type StaticInput = (
+        &'static mut MaybeUninit<[u8; BUF_LEN]>,
+        &'static mut MaybeUninit<Graphics<'static>>,
+    );
+    type Output = &'static Graphics<'static>;
+
+    fn finalize(self, static_input: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+        let grant = self.board_kernel.create_grant(self.driver_num, &grant_cap);
+
+        let buffer = static_input.0.write([0; BUF_LEN]);
+
+        let graphics = static_input
+            .1
+            .write(Graphics::new(self.screen, buffer, grant));
+        kernel::hil::screen::Screen::set_client(self.screen, Some(graphics));
+
+        graphics
+    }
+}
@@ -4,11 +4,18 @@
 
 //! Components for I2C.
 //!
-//! This provides two components.
+//! This provides three components.
 //!
-//! 1. `I2CMuxComponent` provides a virtualization layer for a I2C bus.
+//! 1. `I2CMuxComponent` provides a virtualization layer for a I2C bus. The
+//!    bus frequency can be set with `with_bus_speed` if the I2C master
+//!    implements `I2CBusSpeed`.
 //!
-//! 2. `I2CComponent` provides a virtualized client to the I2C bus.
+//! 2. `I2CComponent` provides a virtualized client to the I2C bus. The
+//!    fastest bus the device supports can be given with
+//!    `with_max_bus_speed`, and is checked against the speed of the mux.
+//!
+//! 3. `I2CBitBangComponent` provides a software I2C master on two GPIO pins,
+//!    which can be used in place of a hardware I2C peripheral.
 //!
 //! Usage
 //! -----
@@ -17,15 +24,27 @@
 //!     .finalize(components::i2c_mux_component_static!());
 //! let client_i2c = components::i2c::I2CComponent::new(mux_i2c, 0x19)
 //!     .finalize(components::i2c_component_static!());
+//!
+//! let mux_i2c = components::i2c::I2CMuxComponent::new(&stm32f3xx::i2c::I2C1, None)
+//!     .with_bus_speed(kernel::hil::i2c::BusSpeed::Fast400k)
+//!     .finalize(components::i2c_mux_component_static!(stm32f303xc::i2c::I2C));
+//! let client_i2c = components::i2c::I2CComponent::new(mux_i2c, 0x19)
+//!     .with_max_bus_speed(kernel::hil::i2c::BusSpeed::Fast400k)
+//!     .finalize(components::i2c_component_static!(stm32f303xc::i2c::I2C));
+//!
+//! let i2c_bitbang = components::i2c::I2CBitBangComponent::new(sda_pin, scl_pin, 20, 10000)
+//!     .finalize(components::i2c_bitbang_component_static!(lpc55s6x::gpio::Pin));
 //! ```
 
 // Author: Alexandru Radovici <msg4alex@gmail.com>
 
 use capsules_core::virtualizers::virtual_i2c::{I2CDevice, MuxI2C};
+use capsules_extra::i2c_bitbang::I2CMasterBitBang;
 use core::mem::MaybeUninit;
 use kernel::capabilities;
 use kernel::component::Component;
 use kernel::create_capability;
+use kernel::hil::gpio;
 use kernel::hil::i2c::{self, NoSMBus};
 
 // Setup static space for the objects.
@@ -66,19 +85,52 @@ macro_rules! i2c_master_slave_component_static {
     };};
 }
 
+#[macro_export]
+macro_rules! i2c_bitbang_component_static {
+    ($P:ty $(,)?) => {{
+        kernel::static_buf!(capsules_extra::i2c_bitbang::I2CMasterBitBang<'static, $P>)
+    };};
+}
+
 pub struct I2CMuxComponent<
     I: 'static + i2c::I2CMaster<'static>,
     S: 'static + i2c::SMBusMaster<'static> = NoSMBus,
 > {
     i2c: &'static I,
     smbus: Option<&'static S>,
+    bus_speed: Option<i2c::BusSpeed>,
 }
 
 impl<I: 'static + i2c::I2CMaster<'static>, S: 'static + i2c::SMBusMaster<'static>>
     I2CMuxComponent<I, S>
 {
     pub fn new(i2c: &'static I, smbus: Option<&'static S>) -> Self {
-        I2CMuxComponent { i2c, smbus }
+        I2CMuxComponent {
+            i2c,
+            smbus,
+            bus_speed: None,
+        }
+    }
+}
+
+impl<
+        I: 'static + i2c::I2CMaster<'static> + i2c::I2CBusSpeed,
+        S: 'static + i2c::SMBusMaster<'static>,
+    > I2CMuxComponent<I, S>
+{
+    /// Run the bus at `bus_speed`. Panics if the I2C master does not support
+    /// it.
+    pub fn with_bus_speed(mut self, bus_speed: i2c::BusSpeed) -> Self {
+        if let Err(error) = self.i2c.set_bus_speed(bus_speed) {
+            panic!(
+                "I2C bus speed {:?} not supported, the fastest is {:?} ({:?})",
+                bus_speed,
+                self.i2c.max_bus_speed(),
+                error
+            );
+        }
+        self.bus_speed = Some(bus_speed);
+        self
     }
 }
 
@@ -91,6 +143,9 @@ impl<I: 'static + i2c::I2CMaster<'static>, S: 'static + i2c::SMBusMaster<'static
     fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
         let mux_i2c = static_buffer.write(MuxI2C::new(self.i2c, self.smbus));
         kernel::deferred_call::DeferredCallClient::register(mux_i2c);
+        if let Some(bus_speed) = self.bus_speed {
+            mux_i2c.set_bus_speed(bus_speed);
+        }
 
         self.i2c.set_master_client(mux_i2c);
 
@@ -101,6 +156,7 @@ impl<I: 'static + i2c::I2CMaster<'static>, S: 'static + i2c::SMBusMaster<'static
 pub struct I2CComponent<I: 'static + i2c::I2CMaster<'static>> {
     i2c_mux: &'static MuxI2C<'static, I>,
     address: u8,
+    max_bus_speed: Option<i2c::BusSpeed>,
 }
 
 impl<I: 'static + i2c::I2CMaster<'static>> I2CComponent<I> {
@@ -108,6 +164,31 @@ impl<I: 'static + i2c::I2CMaster<'static>> I2CComponent<I> {
         I2CComponent {
             i2c_mux: mux,
             address: address,
+            max_bus_speed: None,
+        }
+    }
+
+    /// The fastest bus the device supports. `finalize` panics if the mux was
+    /// set to a faster speed.
+    pub fn with_max_bus_speed(mut self, max_bus_speed: i2c::BusSpeed) -> Self {
+        self.max_bus_speed = Some(max_bus_speed);
+        self
+    }
+}
+
+/// Panic if the bus behind `mux` was set to a speed faster than the device at
+/// `address` supports. Buses without a recorded speed are not checked.
+pub fn check_bus_speed<I: i2c::I2CMaster<'static>, S: i2c::SMBusMaster<'static>>(
+    mux: &MuxI2C<'static, I, S>,
+    address: u8,
+    max_bus_speed: i2c::BusSpeed,
+) {
+    if let Some(bus_speed) = mux.bus_speed() {
+        if bus_speed > max_bus_speed {
+            panic!(
+                "I2C device 0x{:02x} supports at most {:?}, the bus runs at {:?}",
+                address, max_bus_speed, bus_speed
+            );
         }
     }
 }
@@ -117,6 +198,9 @@ impl<I: 'static + i2c::I2CMaster<'static>> Component for I2CComponent<I> {
     type Output = &'static I2CDevice<'static, I>;
 
     fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        if let Some(max_bus_speed) = self.max_bus_speed {
+            check_bus_speed(self.i2c_mux, self.address, max_bus_speed);
+        }
         let i2c_device = static_buffer.write(I2CDevice::new(self.i2c_mux, self.address));
 
         i2c_device
@@ -173,3 +257,38 @@ impl<I: 'static + i2c::I2CMasterSlave<'static>> Component for I2CMasterSlaveDriv
         i2c_master_slave_driver
     }
 }
+
+pub struct I2CBitBangComponent<P: 'static + gpio::Pin> {
+    sda: &'static P,
+    scl: &'static P,
+    half_period: u32,
+    stretch_timeout: u32,
+}
+
+impl<P: 'static + gpio::Pin> I2CBitBangComponent<P> {
+    pub fn new(sda: &'static P, scl: &'static P, half_period: u32, stretch_timeout: u32) -> Self {
+        I2CBitBangComponent {
+            sda,
+            scl,
+            half_period,
+            stretch_timeout,
+        }
+    }
+}
+
+impl<P: 'static + gpio::Pin> Component for I2CBitBangComponent<P> {
+    type StaticInput = &'static mut MaybeUninit<I2CMasterBitBang<'static, P>>;
+    type Output = &'static I2CMasterBitBang<'static, P>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let i2c = static_buffer.write(I2CMasterBitBang::new(
+            self.sda,
+            self.scl,
+            self.half_period,
+            self.stretch_timeout,
+        ));
+        kernel::deferred_call::DeferredCallClient::register(i2c);
+
+        i2c
+    }
+}
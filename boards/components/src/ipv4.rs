@@ -0,0 +1,206 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Components for the IPv4 stack over Ethernet and its userspace UDP driver.
+//!
+//! The stack does not enable the Ethernet adapter: call `start()` on it once
+//! all sockets have been added.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let ipv4_stack = components::ipv4::Ipv4StackComponent::new(
+//!     &peripherals.enet,
+//!     mux_alarm,
+//!     capsules_extra::net::ipv4::Ipv4Config {
+//!         address: Ipv4Addr::new(192, 168, 1, 50),
+//!         netmask: Ipv4Addr::new(255, 255, 255, 0),
+//!         gateway: Some(Ipv4Addr::new(192, 168, 1, 1)),
+//!     },
+//! )
+//! .finalize(components::ipv4_stack_component_static!(
+//!     imxrt1050::enet::Enet<'static>,
+//!     imxrt1050::gpt::Gpt1<'static>,
+//! ));
+//!
+//! let udp = components::ipv4::Ipv4UdpDriverComponent::new(
+//!     board_kernel,
+//!     capsules_extra::net::ipv4::driver::DRIVER_NUM,
+//!     ipv4_stack,
+//! )
+//! .finalize(components::ipv4_udp_driver_component_static!(
+//!     imxrt1050::enet::Enet<'static>,
+//!     imxrt1050::gpt::Gpt1<'static>,
+//! ));
+//!
+//! ipv4_stack.start().unwrap();
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::net::ipv4::driver::UdpDriver;
+use capsules_extra::net::ipv4::stack::{
+    Ipv4Stack, UdpSocket, ARP_BUF_LEN, FRAME_BUF_LEN, MAX_UDP_PAYLOAD,
+};
+use capsules_extra::net::ipv4::Ipv4Config;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::ethernet::EthernetAdapter;
+use kernel::hil::mac_address::Configure;
+use kernel::hil::time::Alarm;
+
+#[macro_export]
+macro_rules! ipv4_stack_component_static {
+    ($E:ty, $A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let frame = kernel::static_buf!([u8; capsules_extra::net::ipv4::stack::FRAME_BUF_LEN]);
+        let arp_frame = kernel::static_buf!([u8; capsules_extra::net::ipv4::stack::ARP_BUF_LEN]);
+        let stack = kernel::static_buf!(
+            capsules_extra::net::ipv4::stack::Ipv4Stack<
+                'static,
+                $E,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+
+        (alarm, frame, arp_frame, stack)
+    };};
+}
+
+#[macro_export]
+macro_rules! ipv4_udp_driver_component_static {
+    ($E:ty, $A:ty $(,)?) => {{
+        let socket = kernel::static_buf!(capsules_extra::net::ipv4::stack::UdpSocket<'static>);
+        let buffer = kernel::static_buf!([u8; capsules_extra::net::ipv4::stack::MAX_UDP_PAYLOAD]);
+        let driver = kernel::static_buf!(
+            capsules_extra::net::ipv4::driver::UdpDriver<
+                'static,
+                $E,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+
+        (socket, buffer, driver)
+    };};
+}
+
+pub type Ipv4StackComponentType<E, A> = Ipv4Stack<'static, E, VirtualMuxAlarm<'static, A>>;
+
+pub struct Ipv4StackComponent<
+    E: 'static + EthernetAdapter<'static> + Configure,
+    A: 'static + Alarm<'static>,
+> {
+    ethernet: &'static E,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    config: Ipv4Config,
+}
+
+impl<E: 'static + EthernetAdapter<'static> + Configure, A: 'static + Alarm<'static>>
+    Ipv4StackComponent<E, A>
+{
+    pub fn new(
+        ethernet: &'static E,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        config: Ipv4Config,
+    ) -> Self {
+        Self {
+            ethernet,
+            alarm_mux,
+            config,
+        }
+    }
+}
+
+impl<E: 'static + EthernetAdapter<'static> + Configure, A: 'static + Alarm<'static>> Component
+    for Ipv4StackComponent<E, A>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<[u8; FRAME_BUF_LEN]>,
+        &'static mut MaybeUninit<[u8; ARP_BUF_LEN]>,
+        &'static mut MaybeUninit<Ipv4StackComponentType<E, A>>,
+    );
+    type Output = &'static Ipv4StackComponentType<E, A>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let frame = static_buffer.1.write([0; FRAME_BUF_LEN]);
+        let arp_frame = static_buffer.2.write([0; ARP_BUF_LEN]);
+
+        let stack = static_buffer.3.write(Ipv4Stack::new(
+            self.ethernet,
+            alarm,
+            self.config,
+            frame,
+            arp_frame,
+        ));
+        self.ethernet.set_receive_client(stack);
+        self.ethernet.set_transmit_client(stack);
+        alarm.set_alarm_client(stack);
+
+        stack
+    }
+}
+
+pub struct Ipv4UdpDriverComponent<
+    E: 'static + EthernetAdapter<'static> + Configure,
+    A: 'static + Alarm<'static>,
+> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    stack: &'static Ipv4StackComponentType<E, A>,
+}
+
+impl<E: 'static + EthernetAdapter<'static> + Configure, A: 'static + Alarm<'static>>
+    Ipv4UdpDriverComponent<E, A>
+{
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        stack: &'static Ipv4StackComponentType<E, A>,
+    ) -> Self {
+        Self {
+            board_kernel,
+            driver_num,
+            stack,
+        }
+    }
+}
+
+impl<E: 'static + EthernetAdapter<'static> + Configure, A: 'static + Alarm<'static>> Component
+    for Ipv4UdpDriverComponent<E, A>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<UdpSocket<'static>>,
+        &'static mut MaybeUninit<[u8; MAX_UDP_PAYLOAD]>,
+        &'static mut MaybeUninit<UdpDriver<'static, E, VirtualMuxAlarm<'static, A>>>,
+    );
+    type Output = &'static UdpDriver<'static, E, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let socket = static_buffer.0.write(UdpSocket::new());
+        let buffer = static_buffer.1.write([0; MAX_UDP_PAYLOAD]);
+
+        let driver = static_buffer.2.write(UdpDriver::new(
+            self.stack,
+            socket,
+            buffer,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+        socket.set_client(driver);
+        // Processes bind their own ports, so the driver receives every
+        // datagram no in-kernel socket is bound to.
+        self.stack.add_socket(socket);
+        self.stack.bind_any(socket);
+
+        driver
+    }
+}
@@ -0,0 +1,104 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for NEC infrared remote control codes.
+//!
+//! The pulse capture timestamps the output of a demodulating IR receiver,
+//! and the PWM pin drives an IR LED. Either can be `None`.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let ir_remote = components::ir_remote::IrRemoteComponent::new(
+//!     board_kernel,
+//!     capsules_extra::ir_remote::DRIVER_NUM,
+//!     mux_alarm,
+//!     Some(ir_receiver),
+//!     Some(ir_led),
+//! )
+//! .finalize(components::ir_remote_component_static!(
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::ir_remote::IrRemote;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::pulse_capture::PulseCapture;
+use kernel::hil::pwm;
+use kernel::hil::time::Alarm;
+
+#[macro_export]
+macro_rules! ir_remote_component_static {
+    ($A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let ir_remote = kernel::static_buf!(
+            capsules_extra::ir_remote::IrRemote<
+                'static,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+
+        (alarm, ir_remote)
+    };};
+}
+
+pub struct IrRemoteComponent<A: 'static + Alarm<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    receiver: Option<&'static dyn PulseCapture<'static>>,
+    carrier: Option<&'static dyn pwm::PwmPin>,
+}
+
+impl<A: 'static + Alarm<'static>> IrRemoteComponent<A> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        receiver: Option<&'static dyn PulseCapture<'static>>,
+        carrier: Option<&'static dyn pwm::PwmPin>,
+    ) -> Self {
+        IrRemoteComponent {
+            board_kernel,
+            driver_num,
+            alarm_mux,
+            receiver,
+            carrier,
+        }
+    }
+}
+
+impl<A: 'static + Alarm<'static>> Component for IrRemoteComponent<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<IrRemote<'static, VirtualMuxAlarm<'static, A>>>,
+    );
+    type Output = &'static IrRemote<'static, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let ir_remote = static_buffer.1.write(IrRemote::new(
+            alarm,
+            self.receiver,
+            self.carrier,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+        alarm.set_alarm_client(ir_remote);
+        if let Some(receiver) = self.receiver {
+            receiver.set_client(ir_remote);
+        }
+
+        ir_remote
+    }
+}
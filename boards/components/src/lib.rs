@@ -6,71 +6,119 @@
 
 pub mod adc;
 pub mod adc_microphone;
+pub mod aes;
 pub mod air_quality;
 pub mod alarm;
 pub mod analog_comparator;
 pub mod apds9960;
 pub mod app_flash_driver;
+pub mod app_loader;
+pub mod app_watchdog;
+pub mod at24c_eeprom;
+pub mod at_engine;
+pub mod auto_brightness;
+pub mod backoff;
 pub mod ble;
+pub mod bluetooth_serial;
 pub mod bme280;
 pub mod bmp280;
+pub mod boot_counter;
 pub mod bus;
+pub mod bus_analyzer;
+pub mod bus_fault_injector;
 pub mod button;
 pub mod can;
 pub mod ccs811;
 pub mod cdc;
+pub mod cellular_modem;
+pub mod clock_tree;
 pub mod console;
 pub mod crc;
+pub mod credential_store;
 pub mod ctap;
 pub mod dac;
+pub mod data_logger;
 pub mod debug_queue;
+pub mod debug_rate_limit;
 pub mod debug_writer;
+pub mod device_id;
+pub mod dhcp;
 pub mod digest;
+pub mod driver_statistics;
 pub mod flash;
+pub mod flash_health;
 pub mod fm25cl;
+pub mod free_fall;
 pub mod ft6x06;
 pub mod fxos8700;
 pub mod gpio;
+pub mod gpio_console;
+pub mod gpio_debounce;
+pub mod gpio_pulse_capture;
+pub mod graphics;
 pub mod hd44780;
 pub mod hmac;
 pub mod hts221;
 pub mod humidity;
 pub mod i2c;
 pub mod ieee802154;
+pub mod ipv4;
+pub mod ir_remote;
 pub mod isl29035;
 pub mod keyboard_hid;
 pub mod kv_system;
 pub mod l3gd20;
 pub mod led;
 pub mod led_matrix;
+pub mod lin;
 pub mod lldb;
+pub mod location;
 pub mod lpm013m126;
 pub mod lps25hb;
 pub mod lsm303agr;
 pub mod lsm303dlhc;
 pub mod lsm6dsox;
 pub mod ltc294x;
+pub mod mac_address_provisioning;
+pub mod mdns;
 pub mod mlx90614;
+pub mod modbus_rtu;
 pub mod mx25r6435f;
 pub mod ninedof;
+pub mod nmea;
 pub mod nonvolatile_storage;
 pub mod nrf51822;
+pub mod onscreen_keyboard;
 pub mod panic_button;
+pub mod pedometer;
+pub mod photoresistor;
+pub mod power_manager;
+pub mod pressure;
 pub mod process_console;
+pub mod process_load_log;
 pub mod process_printer;
 pub mod proximity;
 pub mod pwm;
+pub mod pwm_capture;
+pub mod reset_command;
+pub mod resistive_touch;
 pub mod rf233;
+pub mod rgb_led;
 pub mod rng;
 pub mod sched;
 pub mod screen;
 pub mod segger_rtt;
+pub mod sensor_aggregator;
 pub mod sha;
 pub mod sht3x;
 pub mod si7021;
 pub mod sound_pressure;
 pub mod spi;
+pub mod spi_flash;
 pub mod st77xx;
+pub mod syscall_trace;
+pub mod system_time;
+pub mod tcp_stream;
 pub mod temperature;
 pub mod temperature_rp2040;
 pub mod temperature_stm;
@@ -78,6 +126,8 @@ pub mod test;
 pub mod text_screen;
 pub mod tickv;
 pub mod touch;
+pub mod touch_calibration;
 pub mod udp_driver;
 pub mod udp_mux;
 pub mod usb;
+pub mod vibration;
@@ -0,0 +1,119 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for a LIN bus master on a UART which can send breaks.
+//!
+//! The UART is configured for `baud_rate` 8N1 and is used only by the LIN
+//! capsule.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let lin = components::lin::LinComponent::new(
+//!     board_kernel,
+//!     capsules_extra::lin::DRIVER_NUM,
+//!     &peripherals.uart1,
+//!     mux_alarm,
+//!     19200,
+//! )
+//! .finalize(components::lin_component_static!(
+//!     rp2040::uart::Uart<'static>,
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::lin::{Lin, FRAME_LEN};
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::time::Alarm;
+use kernel::hil::uart;
+
+#[macro_export]
+macro_rules! lin_component_static {
+    ($U:ty, $A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let lin = kernel::static_buf!(
+            capsules_extra::lin::Lin<
+                'static,
+                $U,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+        let tx_buffer = kernel::static_buf!([u8; capsules_extra::lin::FRAME_LEN]);
+        let rx_buffer = kernel::static_buf!([u8; capsules_extra::lin::FRAME_LEN]);
+
+        (alarm, lin, tx_buffer, rx_buffer)
+    };};
+}
+
+pub struct LinComponent<U: 'static + uart::Uart<'static> + uart::Break, A: 'static + Alarm<'static>>
+{
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    uart: &'static U,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    baud_rate: u32,
+}
+
+impl<U: 'static + uart::Uart<'static> + uart::Break, A: 'static + Alarm<'static>>
+    LinComponent<U, A>
+{
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        uart: &'static U,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        baud_rate: u32,
+    ) -> Self {
+        LinComponent {
+            board_kernel,
+            driver_num,
+            uart,
+            alarm_mux,
+            baud_rate,
+        }
+    }
+}
+
+impl<U: 'static + uart::Uart<'static> + uart::Break, A: 'static + Alarm<'static>> Component
+    for LinComponent<U, A>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<Lin<'static, U, VirtualMuxAlarm<'static, A>>>,
+        &'static mut MaybeUninit<[u8; FRAME_LEN]>,
+        &'static mut MaybeUninit<[u8; FRAME_LEN]>,
+    );
+    type Output = &'static Lin<'static, U, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let tx_buffer = static_buffer.2.write([0; FRAME_LEN]);
+        let rx_buffer = static_buffer.3.write([0; FRAME_LEN]);
+
+        let lin = static_buffer.1.write(Lin::new(
+            self.uart,
+            alarm,
+            self.baud_rate,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+            tx_buffer,
+            rx_buffer,
+        ));
+        alarm.set_alarm_client(lin);
+        uart::Transmit::set_transmit_client(self.uart, lin);
+        uart::Receive::set_receive_client(self.uart, lin);
+        let _ = lin.initialize();
+
+        lin
+    }
+}
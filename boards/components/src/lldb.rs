@@ -21,7 +21,7 @@
 // Last modified: 12/04/2019
 
 use capsules_core::low_level_debug::LowLevelDebug;
-use capsules_core::virtualizers::virtual_uart::{MuxUart, UartDevice};
+use capsules_core::virtualizers::virtual_uart::{channel, Channel, MuxUart, UartDevice};
 use core::mem::MaybeUninit;
 use kernel::capabilities;
 use kernel::component::Component;
@@ -78,6 +78,7 @@ impl Component for LowLevelDebugComponent {
 
         let lldb_uart = s.0.write(UartDevice::new(self.uart_mux, true));
         lldb_uart.setup();
+        lldb_uart.set_channel(channel::LOW_LEVEL_DEBUG);
 
         let buffer = s.1.write([0; capsules_core::low_level_debug::BUF_LEN]);
 
@@ -0,0 +1,67 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for any satellite positioning receiver.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let location = components::location::LocationComponent::new(
+//!     board_kernel,
+//!     capsules_extra::location::DRIVER_NUM,
+//!     nmea,
+//! )
+//! .finalize(components::location_component_static!());
+//! ```
+
+use capsules_extra::location::LocationDriver;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil;
+
+#[macro_export]
+macro_rules! location_component_static {
+    () => {{
+        kernel::static_buf!(capsules_extra::location::LocationDriver<'static>)
+    };};
+}
+
+pub struct LocationComponent<L: 'static + hil::location::Location<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    receiver: &'static L,
+}
+
+impl<L: 'static + hil::location::Location<'static>> LocationComponent<L> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        receiver: &'static L,
+    ) -> LocationComponent<L> {
+        LocationComponent {
+            board_kernel,
+            driver_num,
+            receiver,
+        }
+    }
+}
+
+impl<L: 'static + hil::location::Location<'static>> Component for LocationComponent<L> {
+    type StaticInput = &'static mut MaybeUninit<LocationDriver<'static>>;
+    type Output = &'static LocationDriver<'static>;
+
+    fn finalize(self, s: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let location = s.write(LocationDriver::new(
+            self.receiver,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+
+        hil::location::Location::set_client(self.receiver, location);
+        location
+    }
+}
@@ -90,6 +90,17 @@ impl<I: 'static + i2c::I2CMaster<'static>> Component for Lsm303dlhcI2CComponent<
         let grant_cap =
             kernel::create_capability!(kernel::capabilities::MemoryAllocationCapability);
 
+        for address in [
+            self.accelerometer_i2c_address,
+            self.magnetometer_i2c_address,
+        ] {
+            crate::i2c::check_bus_speed(
+                self.i2c_mux,
+                address,
+                capsules_extra::lsm303dlhc::MAX_BUS_SPEED,
+            );
+        }
+
         let buffer = static_buffer.2.write([0; 8]);
 
         let accelerometer_i2c = static_buffer
@@ -29,18 +29,20 @@
 //! Author: Cristiana Andrei <cristiana.andrei@stud.fils.upb.ro>
 
 use capsules_core::virtualizers::virtual_i2c::{I2CDevice, MuxI2C};
-use capsules_extra::lsm6dsoxtr::Lsm6dsoxtrI2C;
+use capsules_core::virtualizers::virtual_spi::{MuxSpiMaster, VirtualSpiMasterDevice};
+use capsules_extra::lsm6dsoxtr::{Lsm6dsoxtrI2C, Lsm6dsoxtrSpi, BUFFER_LEN};
 use core::mem::MaybeUninit;
 use kernel::capabilities;
 use kernel::component::Component;
 use kernel::create_capability;
 use kernel::hil::i2c;
+use kernel::hil::spi::{self, SpiMasterDevice};
 
 // Setup static space for the objects.
 #[macro_export]
 macro_rules! lsm6ds_i2c_component_static {
     ($I:ty $(,)?) => {{
-        let buffer = kernel::static_buf!([u8; 8]);
+        let buffer = kernel::static_buf!([u8; capsules_extra::lsm6dsoxtr::BUFFER_LEN]);
         let i2c_device =
             kernel::static_buf!(capsules_core::virtualizers::virtual_i2c::I2CDevice<'static, $I>);
         let lsm6dsoxtr = kernel::static_buf!(
@@ -54,6 +56,34 @@ macro_rules! lsm6ds_i2c_component_static {
     };};
 }
 
+#[macro_export]
+macro_rules! lsm6ds_spi_component_static {
+    ($S:ty $(,)?) => {{
+        let buffer = kernel::static_buf!([u8; capsules_extra::lsm6dsoxtr::BUFFER_LEN]);
+        let read_buffer = kernel::static_buf!([u8; capsules_extra::lsm6dsoxtr::BUFFER_LEN]);
+        let spi_device = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_spi::VirtualSpiMasterDevice<'static, $S>
+        );
+        let lsm6dsoxtr_spi = kernel::static_buf!(
+            capsules_extra::lsm6dsoxtr::Lsm6dsoxtrSpi<
+                'static,
+                capsules_core::virtualizers::virtual_spi::VirtualSpiMasterDevice<'static, $S>,
+            >
+        );
+        let lsm6dsoxtr = kernel::static_buf!(
+            capsules_extra::lsm6dsoxtr::Lsm6dsoxtrI2C<
+                'static,
+                capsules_extra::lsm6dsoxtr::Lsm6dsoxtrSpi<
+                    'static,
+                    capsules_core::virtualizers::virtual_spi::VirtualSpiMasterDevice<'static, $S>,
+                >,
+            >
+        );
+
+        (spi_device, lsm6dsoxtr_spi, buffer, read_buffer, lsm6dsoxtr)
+    };};
+}
+
 pub struct Lsm6dsoxtrI2CComponent<I: 'static + i2c::I2CMaster<'static>> {
     i2c_mux: &'static MuxI2C<'static, I>,
     i2c_address: u8,
@@ -80,7 +110,7 @@ impl<I: 'static + i2c::I2CMaster<'static>> Lsm6dsoxtrI2CComponent<I> {
 impl<I: 'static + i2c::I2CMaster<'static>> Component for Lsm6dsoxtrI2CComponent<I> {
     type StaticInput = (
         &'static mut MaybeUninit<I2CDevice<'static, I>>,
-        &'static mut MaybeUninit<[u8; 8]>,
+        &'static mut MaybeUninit<[u8; BUFFER_LEN]>,
         &'static mut MaybeUninit<Lsm6dsoxtrI2C<'static, I2CDevice<'static, I>>>,
     );
     type Output = &'static Lsm6dsoxtrI2C<'static, I2CDevice<'static, I>>;
@@ -92,7 +122,7 @@ impl<I: 'static + i2c::I2CMaster<'static>> Component for Lsm6dsoxtrI2CComponent<
         let lsm6dsox_i2c = static_buffer
             .0
             .write(I2CDevice::new(self.i2c_mux, self.i2c_address));
-        let buffer = static_buffer.1.write([0; 8]);
+        let buffer = static_buffer.1.write([0; BUFFER_LEN]);
 
         let lsm6dsox = static_buffer
             .2
@@ -102,3 +132,69 @@ impl<I: 'static + i2c::I2CMaster<'static>> Component for Lsm6dsoxtrI2CComponent<
         lsm6dsox
     }
 }
+
+/// Rate of the SPI clock, below the 10 MHz maximum of the sensor.
+const SPI_RATE: u32 = 8_000_000;
+
+pub struct Lsm6dsoxtrSpiComponent<S: 'static + spi::SpiMaster<'static>> {
+    spi_mux: &'static MuxSpiMaster<'static, S>,
+    chip_select: S::ChipSelect,
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+}
+
+impl<S: 'static + spi::SpiMaster<'static>> Lsm6dsoxtrSpiComponent<S> {
+    pub fn new(
+        spi_mux: &'static MuxSpiMaster<'static, S>,
+        chip_select: S::ChipSelect,
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+    ) -> Lsm6dsoxtrSpiComponent<S> {
+        Lsm6dsoxtrSpiComponent {
+            spi_mux,
+            chip_select,
+            board_kernel,
+            driver_num,
+        }
+    }
+}
+
+impl<S: 'static + spi::SpiMaster<'static>> Component for Lsm6dsoxtrSpiComponent<S> {
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualSpiMasterDevice<'static, S>>,
+        &'static mut MaybeUninit<Lsm6dsoxtrSpi<'static, VirtualSpiMasterDevice<'static, S>>>,
+        &'static mut MaybeUninit<[u8; BUFFER_LEN]>,
+        &'static mut MaybeUninit<[u8; BUFFER_LEN]>,
+        &'static mut MaybeUninit<
+            Lsm6dsoxtrI2C<'static, Lsm6dsoxtrSpi<'static, VirtualSpiMasterDevice<'static, S>>>,
+        >,
+    );
+    type Output =
+        &'static Lsm6dsoxtrI2C<'static, Lsm6dsoxtrSpi<'static, VirtualSpiMasterDevice<'static, S>>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+        let grant = self.board_kernel.create_grant(self.driver_num, &grant_cap);
+
+        let spi_device = static_buffer
+            .0
+            .write(VirtualSpiMasterDevice::new(self.spi_mux, self.chip_select));
+        spi_device.setup();
+
+        let read_buffer = static_buffer.3.write([0; BUFFER_LEN]);
+        let lsm6dsox_spi = static_buffer
+            .1
+            .write(Lsm6dsoxtrSpi::new(spi_device, read_buffer));
+        spi_device.set_client(lsm6dsox_spi);
+        // TODO verify SPI return value
+        let _ = lsm6dsox_spi.configure(SPI_RATE);
+
+        let buffer = static_buffer.2.write([0; BUFFER_LEN]);
+        let lsm6dsox = static_buffer
+            .4
+            .write(Lsm6dsoxtrI2C::new(lsm6dsox_spi, buffer, grant));
+        lsm6dsox_spi.set_client(lsm6dsox);
+
+        lsm6dsox
+    }
+}
@@ -0,0 +1,122 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for MAC address provisioning.
+//!
+//! The interfaces to configure are given to the static macro. Call `start()`
+//! on the output once the key-value store is set up.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let mac_provisioning =
+//!     components::mac_address_provisioning::MacAddressProvisioningComponent::new(
+//!         kv_store,
+//!         Some(&peripherals.ocotp),
+//!         Some(&peripherals.ocotp),
+//!     )
+//!     .finalize(components::mac_address_provisioning_component_static!(
+//!         capsules_extra::tickv::TicKVStore<
+//!             capsules_core::virtualizers::virtual_flash::FlashUser<lowrisc::flash_ctrl::FlashCtrl>,
+//!         >,
+//!         capsules_extra::tickv::TicKVKeyType,
+//!         &peripherals.enet,
+//!     ));
+//! let _ = mac_provisioning.start();
+//! ```
+
+use capsules_extra::kv_store::KVStore;
+use capsules_extra::mac_address_provisioning::{MacAddressProvisioning, KEY, VALUE_BUF_LEN};
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::device_id::DeviceId;
+use kernel::hil::kv_system::{KVSystem, KeyType};
+use kernel::hil::mac_address::{Configure, MacAddressSource};
+use kernel::storage_permissions::StoragePermissions;
+
+#[macro_export]
+macro_rules! mac_address_provisioning_component_static {
+    ($K:ty, $T:ty $(, $I:expr)* $(,)?) => {{
+        use kernel::count_expressions;
+        use kernel::static_init;
+        const NUM_INTERFACES: usize = count_expressions!($($I),*);
+
+        let interfaces = static_init!(
+            [&'static dyn kernel::hil::mac_address::Configure; NUM_INTERFACES],
+            [
+                $($I,)*
+            ]
+        );
+        let provisioning = kernel::static_buf!(
+            capsules_extra::mac_address_provisioning::MacAddressProvisioning<'static, $K, $T>
+        );
+        let key = kernel::static_buf!(
+            [u8; capsules_extra::mac_address_provisioning::KEY.len()]
+        );
+        let value = kernel::static_buf!(
+            [u8; capsules_extra::mac_address_provisioning::VALUE_BUF_LEN]
+        );
+
+        (provisioning, interfaces, key, value)
+    };};
+}
+
+pub struct MacAddressProvisioningComponent<
+    K: 'static + KVSystem<'static, K = T>,
+    T: 'static + KeyType,
+> {
+    kv: &'static KVStore<'static, K, T>,
+    fuses: Option<&'static dyn MacAddressSource>,
+    device_id: Option<&'static dyn DeviceId>,
+}
+
+impl<K: 'static + KVSystem<'static, K = T>, T: 'static + KeyType>
+    MacAddressProvisioningComponent<K, T>
+{
+    pub fn new(
+        kv: &'static KVStore<'static, K, T>,
+        fuses: Option<&'static dyn MacAddressSource>,
+        device_id: Option<&'static dyn DeviceId>,
+    ) -> Self {
+        Self {
+            kv,
+            fuses,
+            device_id,
+        }
+    }
+}
+
+impl<K: 'static + KVSystem<'static, K = T>, T: 'static + KeyType> Component
+    for MacAddressProvisioningComponent<K, T>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<MacAddressProvisioning<'static, K, T>>,
+        &'static [&'static dyn Configure],
+        &'static mut MaybeUninit<[u8; KEY.len()]>,
+        &'static mut MaybeUninit<[u8; VALUE_BUF_LEN]>,
+    );
+    type Output = &'static MacAddressProvisioning<'static, K, T>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let storage_cap = create_capability!(capabilities::KerneluserStorageCapability);
+
+        let key = static_buffer.2.write([0; KEY.len()]);
+        key.copy_from_slice(KEY);
+        let value = static_buffer.3.write([0; VALUE_BUF_LEN]);
+
+        let provisioning = static_buffer.0.write(MacAddressProvisioning::new(
+            self.kv,
+            self.fuses,
+            self.device_id,
+            static_buffer.1,
+            StoragePermissions::new_kernel_permissions(&storage_cap),
+            key,
+            value,
+        ));
+        self.kv.set_client(provisioning);
+        provisioning
+    }
+}
@@ -0,0 +1,97 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the mDNS responder of the IPv4 stack over Ethernet.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let mdns = components::mdns::MdnsResponderComponent::new(ipv4_stack, "tock-evkb", None)
+//!     .finalize(components::mdns_responder_component_static!(
+//!         imxrt1050::enet::Enet<'static>,
+//!         imxrt1050::gpt::Gpt1<'static>,
+//!     ));
+//! mdns.start();
+//! ```
+
+use crate::ipv4::Ipv4StackComponentType;
+use capsules_extra::net::ipv4::mdns::{MdnsResponder, MdnsService, BUF_LEN, PORT};
+use capsules_extra::net::ipv4::stack::UdpSocket;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::ethernet::EthernetAdapter;
+use kernel::hil::mac_address::Configure;
+use kernel::hil::time::Alarm;
+
+#[macro_export]
+macro_rules! mdns_responder_component_static {
+    ($E:ty, $A:ty $(,)?) => {{
+        let socket = kernel::static_buf!(capsules_extra::net::ipv4::stack::UdpSocket<'static>);
+        let buffer = kernel::static_buf!([u8; capsules_extra::net::ipv4::mdns::BUF_LEN]);
+        let mdns = kernel::static_buf!(
+            capsules_extra::net::ipv4::mdns::MdnsResponder<
+                'static,
+                components::ipv4::Ipv4StackComponentType<$E, $A>,
+            >
+        );
+
+        (socket, buffer, mdns)
+    };};
+}
+
+pub type MdnsResponderComponentType<E, A> = MdnsResponder<'static, Ipv4StackComponentType<E, A>>;
+
+pub struct MdnsResponderComponent<
+    E: 'static + EthernetAdapter<'static> + Configure,
+    A: 'static + Alarm<'static>,
+> {
+    stack: &'static Ipv4StackComponentType<E, A>,
+    hostname: &'static str,
+    service: Option<MdnsService>,
+}
+
+impl<E: 'static + EthernetAdapter<'static> + Configure, A: 'static + Alarm<'static>>
+    MdnsResponderComponent<E, A>
+{
+    pub fn new(
+        stack: &'static Ipv4StackComponentType<E, A>,
+        hostname: &'static str,
+        service: Option<MdnsService>,
+    ) -> Self {
+        Self {
+            stack,
+            hostname,
+            service,
+        }
+    }
+}
+
+impl<E: 'static + EthernetAdapter<'static> + Configure, A: 'static + Alarm<'static>> Component
+    for MdnsResponderComponent<E, A>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<UdpSocket<'static>>,
+        &'static mut MaybeUninit<[u8; BUF_LEN]>,
+        &'static mut MaybeUninit<MdnsResponderComponentType<E, A>>,
+    );
+    type Output = &'static MdnsResponderComponentType<E, A>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let socket = static_buffer.0.write(UdpSocket::new());
+        let buffer = static_buffer.1.write([0; BUF_LEN]);
+
+        let mdns = static_buffer.2.write(MdnsResponder::new(
+            self.stack,
+            socket,
+            self.hostname,
+            self.service,
+            buffer,
+        ));
+        socket.set_client(mdns);
+        self.stack.add_socket(socket);
+        self.stack.bind(socket, PORT).unwrap();
+
+        mdns
+    }
+}
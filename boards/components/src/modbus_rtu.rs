@@ -0,0 +1,126 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for a Modbus RTU slave on a UART.
+//!
+//! The UART is configured with `parameters` and is used only by the Modbus
+//! capsule.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let modbus = components::modbus_rtu::ModbusRtuComponent::new(
+//!     board_kernel,
+//!     capsules_extra::modbus_rtu::DRIVER_NUM,
+//!     &peripherals.uart1,
+//!     mux_alarm,
+//!     uart::Parameters {
+//!         baud_rate: 19200,
+//!         width: uart::Width::Eight,
+//!         parity: uart::Parity::Even,
+//!         stop_bits: uart::StopBits::One,
+//!         hw_flow_control: false,
+//!     },
+//! )
+//! .finalize(components::modbus_rtu_component_static!(
+//!     rp2040::uart::Uart<'static>,
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::modbus_rtu::{ModbusRtu, FRAME_LEN};
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::time::Alarm;
+use kernel::hil::uart;
+
+#[macro_export]
+macro_rules! modbus_rtu_component_static {
+    ($U:ty, $A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let modbus = kernel::static_buf!(
+            capsules_extra::modbus_rtu::ModbusRtu<
+                'static,
+                $U,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+        let rx_byte = kernel::static_buf!([u8; 1]);
+        let frame = kernel::static_buf!([u8; capsules_extra::modbus_rtu::FRAME_LEN]);
+        let tx_buffer = kernel::static_buf!([u8; capsules_extra::modbus_rtu::FRAME_LEN]);
+
+        (alarm, modbus, rx_byte, frame, tx_buffer)
+    };};
+}
+
+pub struct ModbusRtuComponent<U: 'static + uart::Uart<'static>, A: 'static + Alarm<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    uart: &'static U,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    parameters: uart::Parameters,
+}
+
+impl<U: 'static + uart::Uart<'static>, A: 'static + Alarm<'static>> ModbusRtuComponent<U, A> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        uart: &'static U,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        parameters: uart::Parameters,
+    ) -> Self {
+        ModbusRtuComponent {
+            board_kernel,
+            driver_num,
+            uart,
+            alarm_mux,
+            parameters,
+        }
+    }
+}
+
+impl<U: 'static + uart::Uart<'static>, A: 'static + Alarm<'static>> Component
+    for ModbusRtuComponent<U, A>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<ModbusRtu<'static, U, VirtualMuxAlarm<'static, A>>>,
+        &'static mut MaybeUninit<[u8; 1]>,
+        &'static mut MaybeUninit<[u8; FRAME_LEN]>,
+        &'static mut MaybeUninit<[u8; FRAME_LEN]>,
+    );
+    type Output = &'static ModbusRtu<'static, U, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let rx_byte = static_buffer.2.write([0; 1]);
+        let frame = static_buffer.3.write([0; FRAME_LEN]);
+        let tx_buffer = static_buffer.4.write([0; FRAME_LEN]);
+
+        let modbus = static_buffer.1.write(ModbusRtu::new(
+            self.uart,
+            alarm,
+            self.parameters,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+            rx_byte,
+            frame,
+            tx_buffer,
+        ));
+        alarm.set_alarm_client(modbus);
+        uart::Transmit::set_transmit_client(self.uart, modbus);
+        uart::Receive::set_receive_client(self.uart, modbus);
+        let _ = modbus.initialize();
+
+        modbus
+    }
+}
@@ -0,0 +1,64 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for a satellite positioning receiver sending NMEA sentences.
+//!
+//! The UART is configured for `baud_rate` 8N1 and is only received by the
+//! NMEA capsule.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let nmea = components::nmea::NmeaComponent::new(&peripherals.uart0, 9600)
+//!     .finalize(components::nmea_component_static!(rp2040::uart::Uart<'static>));
+//! ```
+
+use capsules_extra::nmea::{Nmea, SENTENCE_LEN};
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::uart;
+
+#[macro_export]
+macro_rules! nmea_component_static {
+    ($U:ty $(,)?) => {{
+        let nmea = kernel::static_buf!(capsules_extra::nmea::Nmea<'static, $U>);
+        let rx_byte = kernel::static_buf!([u8; 1]);
+        let sentence = kernel::static_buf!([u8; capsules_extra::nmea::SENTENCE_LEN]);
+
+        (nmea, rx_byte, sentence)
+    };};
+}
+
+pub struct NmeaComponent<U: 'static + uart::Uart<'static>> {
+    uart: &'static U,
+    baud_rate: u32,
+}
+
+impl<U: 'static + uart::Uart<'static>> NmeaComponent<U> {
+    pub fn new(uart: &'static U, baud_rate: u32) -> Self {
+        NmeaComponent { uart, baud_rate }
+    }
+}
+
+impl<U: 'static + uart::Uart<'static>> Component for NmeaComponent<U> {
+    type StaticInput = (
+        &'static mut MaybeUninit<Nmea<'static, U>>,
+        &'static mut MaybeUninit<[u8; 1]>,
+        &'static mut MaybeUninit<[u8; SENTENCE_LEN]>,
+    );
+    type Output = &'static Nmea<'static, U>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let rx_byte = static_buffer.1.write([0; 1]);
+        let sentence = static_buffer.2.write([0; SENTENCE_LEN]);
+
+        let nmea = static_buffer
+            .0
+            .write(Nmea::new(self.uart, self.baud_rate, rx_byte, sentence));
+        uart::Receive::set_receive_client(self.uart, nmea);
+        let _ = nmea.initialize();
+
+        nmea
+    }
+}
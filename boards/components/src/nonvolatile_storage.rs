@@ -17,13 +17,14 @@
 //!     0x20000,
 //!     &_sstorage as *const u8 as usize,
 //!     &_estorage as *const u8 as usize,
+//!     &[],
 //! )
 //! .finalize(components::nonvolatile_storage_component_static!(
 //!     sam4l::flashcalw::FLASHCALW
 //! ));
 //! ```
 
-use capsules_extra::nonvolatile_storage_driver::NonvolatileStorage;
+use capsules_extra::nonvolatile_storage_driver::{AppRegion, NonvolatileStorage};
 use capsules_extra::nonvolatile_to_pages::NonvolatileToPages;
 use core::mem::MaybeUninit;
 use kernel::capabilities;
@@ -58,6 +59,7 @@ pub struct NonvolatileStorageComponent<
     userspace_length: usize,
     kernel_start: usize,
     kernel_length: usize,
+    app_regions: &'static [AppRegion],
 }
 
 impl<
@@ -74,6 +76,7 @@ impl<
         userspace_length: usize,
         kernel_start: usize,
         kernel_length: usize,
+        app_regions: &'static [AppRegion],
     ) -> Self {
         Self {
             board_kernel,
@@ -83,6 +86,7 @@ impl<
             userspace_length,
             kernel_start,
             kernel_length,
+            app_regions,
         }
     }
 }
@@ -124,6 +128,7 @@ impl<
             self.userspace_length, // Length of userspace accessible region
             self.kernel_start,    // Start address of kernel region
             self.kernel_length,   // Length of kernel region
+            self.app_regions,     // Regions of the apps in the userspace region
             buffer,
         ));
         hil::nonvolatile_storage::NonvolatileStorage::set_client(nv_to_page, nonvolatile_storage);
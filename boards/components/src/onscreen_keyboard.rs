@@ -0,0 +1,83 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the on-screen keyboard.
+//!
+//! The keyboard becomes the client of the graphics capsule, for the drawings
+//! of the kernel, and the single touch client of the touch panel.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let keyboard = components::onscreen_keyboard::OnScreenKeyboardComponent::new(
+//!     board_kernel,
+//!     capsules_extra::onscreen_keyboard::DRIVER_NUM,
+//!     graphics,
+//!     ft6x06,
+//!     tft,
+//! )
+//! .finalize(components::onscreen_keyboard_component_static!());
+//! ```
+
+use capsules_extra::graphics::Graphics;
+use capsules_extra::onscreen_keyboard::OnScreenKeyboard;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+
+#[macro_export]
+macro_rules! onscreen_keyboard_component_static {
+    () => {{
+        kernel::static_buf!(capsules_extra::onscreen_keyboard::OnScreenKeyboard<'static>)
+    };};
+}
+
+pub struct OnScreenKeyboardComponent {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    graphics: &'static Graphics<'static>,
+    touch: &'static dyn kernel::hil::touch::Touch<'static>,
+    screen: &'static dyn kernel::hil::screen::Screen<'static>,
+}
+
+impl OnScreenKeyboardComponent {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        graphics: &'static Graphics<'static>,
+        touch: &'static dyn kernel::hil::touch::Touch<'static>,
+        screen: &'static dyn kernel::hil::screen::Screen<'static>,
+    ) -> OnScreenKeyboardComponent {
+        OnScreenKeyboardComponent {
+            board_kernel,
+            driver_num,
+            graphics,
+            touch,
+            screen,
+        }
+    }
+}
+
+impl Component for OnScreenKeyboardComponent {
+    type StaticInput = &'static mut MaybeUninit<OnScreenKeyboard<'static>>;
+    type Output = &'static OnScreenKeyboard<'static>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+        let grant = self.board_kernel.create_grant(self.driver_num, &grant_cap);
+
+        let keyboard = static_buffer.write(OnScreenKeyboard::new(
+            self.graphics,
+            self.touch,
+            self.screen,
+            grant,
+        ));
+        self.graphics.set_client(keyboard);
+        kernel::hil::touch::Touch::set_client(self.touch, keyboard);
+
+        keyboard
+    }
+}
@@ -0,0 +1,112 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the step counter.
+//!
+//! The component becomes the client of the accelerometer and of the
+//! storage, and starts loading the stored count. Steps are counted once it
+//! is loaded.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let pedometer = components::pedometer::PedometerComponent::new(
+//!     board_kernel,
+//!     capsules_extra::pedometer::DRIVER_NUM,
+//!     mux_alarm,
+//!     lsm6dsoxtr,
+//!     nonvolatile_storage,
+//!     0xFFF000,
+//! )
+//! .finalize(components::pedometer_component_static!(RPTimer));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::pedometer::{Pedometer, STORAGE_LEN};
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::nonvolatile_storage::NonvolatileStorage;
+use kernel::hil::sensors::NineDof;
+use kernel::hil::time::Alarm;
+
+#[macro_export]
+macro_rules! pedometer_component_static {
+    ($A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let pedometer = kernel::static_buf!(
+            capsules_extra::pedometer::Pedometer<
+                'static,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+        let buffer = kernel::static_buf!([u8; capsules_extra::pedometer::STORAGE_LEN]);
+
+        (alarm, pedometer, buffer)
+    };};
+}
+
+pub struct PedometerComponent<A: 'static + Alarm<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    sensor: &'static dyn NineDof<'static>,
+    storage: &'static dyn NonvolatileStorage<'static>,
+    address: usize,
+}
+
+impl<A: 'static + Alarm<'static>> PedometerComponent<A> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        sensor: &'static dyn NineDof<'static>,
+        storage: &'static dyn NonvolatileStorage<'static>,
+        address: usize,
+    ) -> Self {
+        PedometerComponent {
+            board_kernel,
+            driver_num,
+            alarm_mux,
+            sensor,
+            storage,
+            address,
+        }
+    }
+}
+
+impl<A: 'static + Alarm<'static>> Component for PedometerComponent<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<Pedometer<'static, VirtualMuxAlarm<'static, A>>>,
+        &'static mut MaybeUninit<[u8; STORAGE_LEN]>,
+    );
+    type Output = &'static Pedometer<'static, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let buffer = static_buffer.2.write([0; STORAGE_LEN]);
+        let pedometer = static_buffer.1.write(Pedometer::new(
+            self.sensor,
+            alarm,
+            self.storage,
+            self.address,
+            buffer,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+        alarm.set_alarm_client(pedometer);
+        self.sensor.set_client(pedometer);
+        self.storage.set_client(pedometer);
+        let _ = pedometer.start();
+
+        pedometer
+    }
+}
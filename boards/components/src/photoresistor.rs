@@ -0,0 +1,89 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for an ambient light sensor made of a photoresistor in a
+//! voltage divider, read through an ADC channel shared with the ADC mux.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let photoresistor = components::photoresistor::PhotoresistorComponent::new(
+//!     adc_mux,
+//!     imxrt1050::adc::Channel::In15,
+//!     capsules_extra::analog_sensor::Photoresistor::new(
+//!         capsules_extra::analog_sensor::DividerPosition::LowSide,
+//!         10_000,
+//!         15_000,
+//!         700,
+//!     ),
+//! )
+//! .finalize(components::photoresistor_component_static!(
+//!     imxrt1050::adc::Adc<'static>
+//! ));
+//!
+//! let ambient_light = components::isl29035::AmbientLightComponent::new(
+//!     board_kernel,
+//!     capsules_extra::ambient_light::DRIVER_NUM,
+//!     photoresistor,
+//! )
+//! .finalize(components::ambient_light_component_static!());
+//! ```
+
+use capsules_core::virtualizers::virtual_adc::{AdcDevice, MuxAdc};
+use capsules_extra::analog_sensor::{Photoresistor, PhotoresistorLightSensor};
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::adc::{self, AdcChannel};
+
+#[macro_export]
+macro_rules! photoresistor_component_static {
+    ($A:ty $(,)?) => {{
+        let adc_device = components::adc_component_static!($A);
+        let sensor =
+            kernel::static_buf!(capsules_extra::analog_sensor::PhotoresistorLightSensor<'static>);
+
+        (adc_device, sensor)
+    };};
+}
+
+pub struct PhotoresistorComponent<A: 'static + adc::Adc<'static>> {
+    adc_mux: &'static MuxAdc<'static, A>,
+    adc_channel: A::Channel,
+    photoresistor: Photoresistor,
+}
+
+impl<A: 'static + adc::Adc<'static>> PhotoresistorComponent<A> {
+    pub fn new(
+        adc_mux: &'static MuxAdc<'static, A>,
+        adc_channel: A::Channel,
+        photoresistor: Photoresistor,
+    ) -> PhotoresistorComponent<A> {
+        PhotoresistorComponent {
+            adc_mux,
+            adc_channel,
+            photoresistor,
+        }
+    }
+}
+
+impl<A: 'static + adc::Adc<'static>> Component for PhotoresistorComponent<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<AdcDevice<'static, A>>,
+        &'static mut MaybeUninit<PhotoresistorLightSensor<'static>>,
+    );
+    type Output = &'static PhotoresistorLightSensor<'static>;
+
+    fn finalize(self, s: Self::StaticInput) -> Self::Output {
+        let adc_device =
+            crate::adc::AdcComponent::new(self.adc_mux, self.adc_channel).finalize(s.0);
+
+        let sensor = s.1.write(PhotoresistorLightSensor::new(
+            adc_device,
+            self.photoresistor,
+        ));
+        adc_device.set_client(sensor);
+
+        sensor
+    }
+}
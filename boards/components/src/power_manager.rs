@@ -0,0 +1,99 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the power manager.
+//!
+//! The domains stay powered until an application starts the schedule.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let power_manager = components::power_manager::PowerManagerComponent::new(
+//!     board_kernel,
+//!     capsules_extra::power_manager::DRIVER_NUM,
+//!     mux_alarm,
+//!     &[lsm303agr],
+//!     &[nina_reset],
+//! )
+//! .finalize(components::power_manager_component_static!(
+//!     nrf52840::rtc::Rtc<'static>
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::power_manager::{PowerDomain, PowerManager};
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::time::{self, Alarm};
+
+#[macro_export]
+macro_rules! power_manager_component_static {
+    ($A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let power_manager = kernel::static_buf!(
+            capsules_extra::power_manager::PowerManager<
+                'static,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+
+        (alarm, power_manager)
+    };};
+}
+
+pub struct PowerManagerComponent<A: 'static + time::Alarm<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    sensors: &'static [&'static dyn PowerDomain],
+    radio: &'static [&'static dyn PowerDomain],
+}
+
+impl<A: 'static + time::Alarm<'static>> PowerManagerComponent<A> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        sensors: &'static [&'static dyn PowerDomain],
+        radio: &'static [&'static dyn PowerDomain],
+    ) -> Self {
+        PowerManagerComponent {
+            board_kernel,
+            driver_num,
+            alarm_mux,
+            sensors,
+            radio,
+        }
+    }
+}
+
+impl<A: 'static + time::Alarm<'static>> Component for PowerManagerComponent<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<PowerManager<'static, VirtualMuxAlarm<'static, A>>>,
+    );
+    type Output = &'static PowerManager<'static, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, s: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let alarm = s.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let power_manager = s.1.write(PowerManager::new(
+            alarm,
+            self.sensors,
+            self.radio,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+        alarm.set_alarm_client(power_manager);
+
+        power_manager
+    }
+}
@@ -0,0 +1,67 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for any pressure sensor.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let pressure = PressureComponent::new(
+//!     board_kernel,
+//!     capsules_extra::pressure::DRIVER_NUM,
+//!     bme280,
+//! )
+//! .finalize(components::pressure_component_static!());
+//! ```
+
+use capsules_extra::pressure::PressureSensor;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil;
+
+#[macro_export]
+macro_rules! pressure_component_static {
+    () => {{
+        kernel::static_buf!(capsules_extra::pressure::PressureSensor<'static>)
+    };};
+}
+
+pub struct PressureComponent<T: 'static + hil::sensors::PressureDriver<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    sensor: &'static T,
+}
+
+impl<T: 'static + hil::sensors::PressureDriver<'static>> PressureComponent<T> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        sensor: &'static T,
+    ) -> PressureComponent<T> {
+        PressureComponent {
+            board_kernel,
+            driver_num,
+            sensor,
+        }
+    }
+}
+
+impl<T: 'static + hil::sensors::PressureDriver<'static>> Component for PressureComponent<T> {
+    type StaticInput = &'static mut MaybeUninit<PressureSensor<'static>>;
+    type Output = &'static PressureSensor<'static>;
+
+    fn finalize(self, s: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let pressure = s.write(PressureSensor::new(
+            self.sensor,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+
+        hil::sensors::PressureDriver::set_client(self.sensor, pressure);
+        pressure
+    }
+}
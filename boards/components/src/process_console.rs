@@ -8,11 +8,17 @@
 //! command console for controlling processes over a UART bus. On imix this is
 //! typically USART3 (the DEBUG USB connector).
 //!
+//! The console can also run over any other byte stream implementing the UART
+//! HIL, such as a TCP connection, with `new_with_stream`.
+//!
 //! Usage
 //! -----
 //! ```rust
 //! let pconsole = ProcessConsoleComponent::new(board_kernel, uart_mux, alarm_mux, process_printer, Some(reset_function))
 //!     .finalize(process_console_component_static!());
+//!
+//! let net_console = ProcessConsoleComponent::new_with_stream(board_kernel, tcp_stream, alarm_mux, process_printer, None)
+//!     .finalize(process_console_component_static!());
 //! ```
 
 // Author: Philip Levis <pal@cs.stanford.edu>
@@ -20,7 +26,7 @@
 
 use capsules_core::process_console::{self, ProcessConsole};
 use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
-use capsules_core::virtualizers::virtual_uart::{MuxUart, UartDevice};
+use capsules_core::virtualizers::virtual_uart::{channel, Channel, MuxUart, UartDevice};
 use core::mem::MaybeUninit;
 use kernel::capabilities;
 use kernel::component::Component;
@@ -65,9 +71,17 @@ macro_rules! process_console_component_static {
     };};
 }
 
+/// Byte stream the console runs over.
+enum Transport {
+    /// A virtual device on the UART mux, shared with other users of the UART.
+    Mux(&'static MuxUart<'static>),
+    /// A byte stream the console uses exclusively.
+    Stream(&'static dyn hil::uart::UartData<'static>),
+}
+
 pub struct ProcessConsoleComponent<const COMMAND_HISTORY_LEN: usize, A: 'static + Alarm<'static>> {
     board_kernel: &'static kernel::Kernel,
-    uart_mux: &'static MuxUart<'static>,
+    transport: Transport,
     alarm_mux: &'static MuxAlarm<'static, A>,
     process_printer: &'static dyn ProcessPrinter,
     reset_function: Option<fn() -> !>,
@@ -85,7 +99,24 @@ impl<const COMMAND_HISTORY_LEN: usize, A: 'static + Alarm<'static>>
     ) -> ProcessConsoleComponent<COMMAND_HISTORY_LEN, A> {
         ProcessConsoleComponent {
             board_kernel,
-            uart_mux,
+            transport: Transport::Mux(uart_mux),
+            alarm_mux,
+            process_printer,
+            reset_function,
+        }
+    }
+
+    /// Run the console over `stream` instead of a virtual UART device.
+    pub fn new_with_stream(
+        board_kernel: &'static kernel::Kernel,
+        stream: &'static dyn hil::uart::UartData<'static>,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        process_printer: &'static dyn ProcessPrinter,
+        reset_function: Option<fn() -> !>,
+    ) -> ProcessConsoleComponent<COMMAND_HISTORY_LEN, A> {
+        ProcessConsoleComponent {
+            board_kernel,
+            transport: Transport::Stream(stream),
             alarm_mux,
             process_printer,
             reset_function,
@@ -133,9 +164,16 @@ impl<const COMMAND_HISTORY_LEN: usize, A: 'static + Alarm<'static>> Component
     >;
 
     fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
-        // Create virtual device for console.
-        let console_uart = static_buffer.1.write(UartDevice::new(self.uart_mux, true));
-        console_uart.setup();
+        let console_uart: &'static dyn hil::uart::UartData<'static> = match self.transport {
+            Transport::Mux(uart_mux) => {
+                // Create virtual device for console.
+                let device = static_buffer.1.write(UartDevice::new(uart_mux, true));
+                device.setup();
+                device.set_channel(channel::PROCESS_CONSOLE);
+                device
+            }
+            Transport::Stream(stream) => stream,
+        };
 
         // Get addresses of where the kernel is placed to enable additional
         // debugging in process console.
@@ -0,0 +1,60 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the process load log.
+//!
+//! The log keeps the load reports of the first `N` processes. The board
+//! provides it to the kernel as its process load monitor, from
+//! `KernelHooks::process_load_monitor()`, and adds it to the process console
+//! as the `loading` command.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let process_load_log = components::process_load_log::ProcessLoadLogComponent::new()
+//!     .finalize(components::process_load_log_component_static!(4));
+//! process_console.add_command(process_load_log).unwrap();
+//! ```
+
+use capsules_extra::process_load_log::ProcessLoadLog;
+use core::cell::Cell;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::process_load_report::ProcessLoadReport;
+
+#[macro_export]
+macro_rules! process_load_log_component_static {
+    ($N:expr $(,)?) => {{
+        let log = kernel::static_buf!(
+            [core::cell::Cell<Option<kernel::process_load_report::ProcessLoadReport>>; $N]
+        );
+        let process_load_log =
+            kernel::static_buf!(capsules_extra::process_load_log::ProcessLoadLog<'static>);
+
+        (log, process_load_log)
+    };};
+}
+
+pub struct ProcessLoadLogComponent<const N: usize> {}
+
+impl<const N: usize> ProcessLoadLogComponent<N> {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<const N: usize> Component for ProcessLoadLogComponent<N> {
+    type StaticInput = (
+        &'static mut MaybeUninit<[Cell<Option<ProcessLoadReport>>; N]>,
+        &'static mut MaybeUninit<ProcessLoadLog<'static>>,
+    );
+    type Output = &'static ProcessLoadLog<'static>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let log = static_buffer
+            .0
+            .write(core::array::from_fn(|_| Cell::new(None)));
+        static_buffer.1.write(ProcessLoadLog::new(log))
+    }
+}
@@ -0,0 +1,81 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the input capture syscall driver.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let pwm_capture = components::pwm_capture::PwmCaptureComponent::new(
+//!     board_kernel,
+//!     capsules_extra::pwm_capture::DRIVER_NUM,
+//! )
+//! .finalize(components::pwm_capture_component_static!(
+//!     &peripherals.qtmr3.channels[0],
+//! ));
+//! ```
+
+use capsules_extra::pwm_capture::PwmCaptureDriver;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::pwm_capture::PwmCapture;
+
+#[macro_export]
+macro_rules! pwm_capture_component_static {
+    ($($P:expr),+ $(,)?) => {{
+        use kernel::count_expressions;
+        use kernel::static_init;
+        const NUM_PINS: usize = count_expressions!($($P),+);
+
+        let pins = static_init!(
+            [&'static dyn kernel::hil::pwm_capture::PwmCapture<'static>; NUM_PINS],
+            [
+                $($P,)*
+            ]
+        );
+        let driver = kernel::static_buf!(
+            capsules_extra::pwm_capture::PwmCaptureDriver<'static, NUM_PINS>
+        );
+        (driver, pins)
+    };};
+}
+
+pub struct PwmCaptureComponent<const NUM_PINS: usize> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+}
+
+impl<const NUM_PINS: usize> PwmCaptureComponent<NUM_PINS> {
+    pub fn new(board_kernel: &'static kernel::Kernel, driver_num: usize) -> Self {
+        PwmCaptureComponent {
+            board_kernel,
+            driver_num,
+        }
+    }
+}
+
+impl<const NUM_PINS: usize> Component for PwmCaptureComponent<NUM_PINS> {
+    type StaticInput = (
+        &'static mut MaybeUninit<PwmCaptureDriver<'static, NUM_PINS>>,
+        &'static [&'static dyn PwmCapture<'static>; NUM_PINS],
+    );
+    type Output = &'static PwmCaptureDriver<'static, NUM_PINS>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+        let grant = self.board_kernel.create_grant(self.driver_num, &grant_cap);
+
+        let driver = static_buffer
+            .0
+            .write(PwmCaptureDriver::new(static_buffer.1, grant));
+
+        for pin in static_buffer.1.iter() {
+            pin.set_client(driver);
+        }
+
+        driver
+    }
+}
@@ -0,0 +1,56 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the `reset` command of the process console.
+//!
+//! The component gives the command the `ResetCapability`, so it can reset the
+//! chip into its ROM bootloader.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let reset_command = components::reset_command::ResetCommandComponent::new(&peripherals.src)
+//!     .finalize(components::reset_command_component_static!());
+//! process_console.add_command(reset_command).unwrap();
+//! ```
+
+use capsules_extra::reset_command::ResetCommand;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::hil::reset::Reset;
+
+#[macro_export]
+macro_rules! reset_command_component_static {
+    () => {{
+        kernel::static_buf!(
+            capsules_extra::reset_command::ResetCommand<
+                'static,
+                components::reset_command::Capability,
+            >
+        )
+    };};
+}
+
+pub struct Capability;
+unsafe impl capabilities::ResetCapability for Capability {}
+
+pub struct ResetCommandComponent {
+    reset: &'static dyn Reset,
+}
+
+impl ResetCommandComponent {
+    pub fn new(reset: &'static dyn Reset) -> Self {
+        Self { reset }
+    }
+}
+
+impl Component for ResetCommandComponent {
+    type StaticInput = &'static mut MaybeUninit<ResetCommand<'static, Capability>>;
+    type Output = &'static ResetCommand<'static, Capability>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        static_buffer.write(ResetCommand::new(self.reset, Capability))
+    }
+}
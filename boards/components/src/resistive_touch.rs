@@ -0,0 +1,150 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for 4-wire resistive touch panels.
+//!
+//! The panel is sampled with two virtual ADC channels, for the X+ and Y+
+//! pins, and paced with a virtual alarm. The resulting touch is used with
+//! the `TouchComponent`.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let resistive_touch = components::resistive_touch::ResistiveTouchComponent::new(
+//!     adc_mux,
+//!     rp2040::adc::Channel::Channel0,
+//!     rp2040::adc::Channel::Channel1,
+//!     &peripherals.pins.get_pin(RPGpio::GPIO26),
+//!     &peripherals.pins.get_pin(RPGpio::GPIO22),
+//!     &peripherals.pins.get_pin(RPGpio::GPIO27),
+//!     &peripherals.pins.get_pin(RPGpio::GPIO21),
+//!     mux_alarm,
+//!     capsules_extra::resistive_touch::DEFAULT_SAMPLE_INTERVAL_MS,
+//! )
+//! .finalize(components::resistive_touch_component_static!(
+//!     rp2040::adc::Adc<'static>,
+//!     rp2040::gpio::RPGpioPin<'static>,
+//!     rp2040::timer::RPTimer<'static>
+//! ));
+//!
+//! let touch = components::touch::TouchComponent::new(
+//!     board_kernel,
+//!     capsules_extra::touch::DRIVER_NUM,
+//!     resistive_touch,
+//!     None,
+//!     None,
+//! )
+//! .finalize(components::touch_component_static!());
+//! ```
+
+use capsules_core::virtualizers::virtual_adc::{AdcDevice, MuxAdc};
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::resistive_touch::ResistiveTouch;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::adc::{self, AdcChannel};
+use kernel::hil::gpio;
+use kernel::hil::time::{self, Alarm};
+
+#[macro_export]
+macro_rules! resistive_touch_component_static {
+    ($A:ty, $P:ty, $T:ty $(,)?) => {{
+        let x_adc = components::adc_component_static!($A);
+        let y_adc = components::adc_component_static!($A);
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $T>
+        );
+        let touch = kernel::static_buf!(
+            capsules_extra::resistive_touch::ResistiveTouch<
+                'static,
+                $P,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $T>,
+            >
+        );
+
+        (x_adc, y_adc, alarm, touch)
+    };};
+}
+
+pub struct ResistiveTouchComponent<
+    A: 'static + adc::Adc<'static>,
+    P: 'static + gpio::Pin,
+    T: 'static + time::Alarm<'static>,
+> {
+    adc_mux: &'static MuxAdc<'static, A>,
+    x_channel: A::Channel,
+    y_channel: A::Channel,
+    x_plus: &'static P,
+    x_minus: &'static P,
+    y_plus: &'static P,
+    y_minus: &'static P,
+    alarm_mux: &'static MuxAlarm<'static, T>,
+    interval_ms: u32,
+}
+
+impl<A: 'static + adc::Adc<'static>, P: 'static + gpio::Pin, T: 'static + time::Alarm<'static>>
+    ResistiveTouchComponent<A, P, T>
+{
+    pub fn new(
+        adc_mux: &'static MuxAdc<'static, A>,
+        x_channel: A::Channel,
+        y_channel: A::Channel,
+        x_plus: &'static P,
+        x_minus: &'static P,
+        y_plus: &'static P,
+        y_minus: &'static P,
+        alarm_mux: &'static MuxAlarm<'static, T>,
+        interval_ms: u32,
+    ) -> Self {
+        ResistiveTouchComponent {
+            adc_mux,
+            x_channel,
+            y_channel,
+            x_plus,
+            x_minus,
+            y_plus,
+            y_minus,
+            alarm_mux,
+            interval_ms,
+        }
+    }
+}
+
+impl<A: 'static + adc::Adc<'static>, P: 'static + gpio::Pin, T: 'static + time::Alarm<'static>>
+    Component for ResistiveTouchComponent<A, P, T>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<AdcDevice<'static, A>>,
+        &'static mut MaybeUninit<AdcDevice<'static, A>>,
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, T>>,
+        &'static mut MaybeUninit<ResistiveTouch<'static, P, VirtualMuxAlarm<'static, T>>>,
+    );
+    type Output = &'static ResistiveTouch<'static, P, VirtualMuxAlarm<'static, T>>;
+
+    fn finalize(self, s: Self::StaticInput) -> Self::Output {
+        let x_adc = crate::adc::AdcComponent::new(self.adc_mux, self.x_channel).finalize(s.0);
+        let y_adc = crate::adc::AdcComponent::new(self.adc_mux, self.y_channel).finalize(s.1);
+
+        let alarm = s.2.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let touch = s.3.write(ResistiveTouch::new(
+            self.x_plus,
+            self.x_minus,
+            self.y_plus,
+            self.y_minus,
+            x_adc,
+            y_adc,
+            alarm,
+            self.interval_ms,
+        ));
+
+        x_adc.set_client(touch);
+        y_adc.set_client(touch);
+        alarm.set_alarm_client(touch);
+
+        touch
+    }
+}
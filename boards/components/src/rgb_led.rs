@@ -0,0 +1,113 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for an RGB LED driven by three PWM pins.
+//!
+//! The LED starts off.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let mux_pwm = components::pwm::PwmMuxComponent::new(&peripherals.pwm)
+//!     .finalize(components::pwm_mux_component_static!(rp2040::pwm::Pwm));
+//! let red = components::pwm::PwmPinUserComponent::new(mux_pwm, RPGpio::GPIO15)
+//!     .finalize(components::pwm_pin_user_component_static!(rp2040::pwm::Pwm));
+//! let green = components::pwm::PwmPinUserComponent::new(mux_pwm, RPGpio::GPIO16)
+//!     .finalize(components::pwm_pin_user_component_static!(rp2040::pwm::Pwm));
+//! let blue = components::pwm::PwmPinUserComponent::new(mux_pwm, RPGpio::GPIO17)
+//!     .finalize(components::pwm_pin_user_component_static!(rp2040::pwm::Pwm));
+//!
+//! let rgb_led = components::rgb_led::RgbLedComponent::new(
+//!     board_kernel,
+//!     capsules_extra::rgb_led::DRIVER_NUM,
+//!     [red, green, blue],
+//!     mux_alarm,
+//!     false,
+//! )
+//! .finalize(components::rgb_led_component_static!(
+//!     capsules_core::virtualizers::virtual_pwm::PwmPinUser<'static, rp2040::pwm::Pwm>,
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::rgb_led::{Color, RgbLed};
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::pwm;
+use kernel::hil::time::{self, Alarm};
+
+#[macro_export]
+macro_rules! rgb_led_component_static {
+    ($P:ty, $T:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $T>
+        );
+        let rgb_led = kernel::static_buf!(
+            capsules_extra::rgb_led::RgbLed<
+                'static,
+                $P,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $T>,
+            >
+        );
+
+        (alarm, rgb_led)
+    };};
+}
+
+pub struct RgbLedComponent<P: 'static + pwm::PwmPin, T: 'static + time::Alarm<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    pins: [&'static P; 3],
+    alarm_mux: &'static MuxAlarm<'static, T>,
+    active_low: bool,
+}
+
+impl<P: 'static + pwm::PwmPin, T: 'static + time::Alarm<'static>> RgbLedComponent<P, T> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        pins: [&'static P; 3],
+        alarm_mux: &'static MuxAlarm<'static, T>,
+        active_low: bool,
+    ) -> Self {
+        RgbLedComponent {
+            board_kernel,
+            driver_num,
+            pins,
+            alarm_mux,
+            active_low,
+        }
+    }
+}
+
+impl<P: 'static + pwm::PwmPin, T: 'static + time::Alarm<'static>> Component
+    for RgbLedComponent<P, T>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, T>>,
+        &'static mut MaybeUninit<RgbLed<'static, P, VirtualMuxAlarm<'static, T>>>,
+    );
+    type Output = &'static RgbLed<'static, P, VirtualMuxAlarm<'static, T>>;
+
+    fn finalize(self, s: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let alarm = s.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let rgb_led = s.1.write(RgbLed::new(
+            self.pins,
+            alarm,
+            self.active_low,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+        alarm.set_alarm_client(rgb_led);
+        let _ = rgb_led.set_color(Color::OFF);
+
+        rgb_led
+    }
+}
@@ -16,6 +16,10 @@
 //!     .finalize(components::segger_rtt_memory_component_static!());
 //! let rtt = components::segger_rtt::SeggerRttComponent::new(mux_alarm, rtt_memory)
 //!     .finalize(components::segger_rtt_component_static!(nrf52832::rtc::Rtc));
+//!
+//! // Use RTT instead of a hardware UART for the console and `debug!()`.
+//! let uart_mux = components::console::UartMuxComponent::new(rtt, 115200)
+//!     .finalize(components::uart_mux_component_static!());
 //! ```
 
 // Author: Guillaume Endignoux <guillaumee@google.com>
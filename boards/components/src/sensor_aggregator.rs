@@ -0,0 +1,116 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the sensor aggregation driver.
+//!
+//! The driver becomes the client of the sensors given to it, so they must
+//! not also be given to their own syscall drivers.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let sensor_aggregator = components::sensor_aggregator::SensorAggregatorComponent::new(
+//!     board_kernel,
+//!     capsules_extra::sensor_aggregator::DRIVER_NUM,
+//!     mux_alarm,
+//!     Some(temperature_sensor),
+//!     None,
+//!     Some(battery_adc_channel),
+//! )
+//! .finalize(components::sensor_aggregator_component_static!(
+//!     nrf52840::rtc::Rtc<'static>
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::sensor_aggregator::SensorAggregator;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::adc;
+use kernel::hil::sensors;
+use kernel::hil::time::{self, Alarm};
+
+#[macro_export]
+macro_rules! sensor_aggregator_component_static {
+    ($A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let sensor_aggregator = kernel::static_buf!(
+            capsules_extra::sensor_aggregator::SensorAggregator<
+                'static,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+
+        (alarm, sensor_aggregator)
+    };};
+}
+
+pub struct SensorAggregatorComponent<A: 'static + time::Alarm<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    temperature: Option<&'static dyn sensors::TemperatureDriver<'static>>,
+    ninedof: Option<&'static dyn sensors::NineDof<'static>>,
+    battery: Option<&'static dyn adc::AdcChannel<'static>>,
+}
+
+impl<A: 'static + time::Alarm<'static>> SensorAggregatorComponent<A> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        temperature: Option<&'static dyn sensors::TemperatureDriver<'static>>,
+        ninedof: Option<&'static dyn sensors::NineDof<'static>>,
+        battery: Option<&'static dyn adc::AdcChannel<'static>>,
+    ) -> Self {
+        SensorAggregatorComponent {
+            board_kernel,
+            driver_num,
+            alarm_mux,
+            temperature,
+            ninedof,
+            battery,
+        }
+    }
+}
+
+impl<A: 'static + time::Alarm<'static>> Component for SensorAggregatorComponent<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<SensorAggregator<'static, VirtualMuxAlarm<'static, A>>>,
+    );
+    type Output = &'static SensorAggregator<'static, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, s: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let alarm = s.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let sensor_aggregator = s.1.write(SensorAggregator::new(
+            alarm,
+            self.temperature,
+            self.ninedof,
+            self.battery,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+        alarm.set_alarm_client(sensor_aggregator);
+        if let Some(temperature) = self.temperature {
+            temperature.set_client(sensor_aggregator);
+        }
+        if let Some(ninedof) = self.ninedof {
+            ninedof.set_client(sensor_aggregator);
+        }
+        if let Some(battery) = self.battery {
+            battery.set_client(sensor_aggregator);
+        }
+
+        sensor_aggregator
+    }
+}
@@ -0,0 +1,123 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for JEDEC SPI NOR flash chips, such as the W25Q and GD25Q.
+//!
+//! The component probes the chip, so its size is known shortly after boot.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let spi_flash = components::spi_flash::SpiFlashComponent::new(
+//!     mux_spi,
+//!     &nrf52840_peripherals.gpio_port[SPI_FLASH_CS],
+//!     mux_alarm,
+//! )
+//! .finalize(components::spi_flash_component_static!(
+//!     nrf52840::spi::SPIM,
+//!     nrf52840::rtc::Rtc
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_core::virtualizers::virtual_spi::{MuxSpiMaster, VirtualSpiMasterDevice};
+use capsules_extra::spi_flash::SpiFlash;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil;
+use kernel::hil::spi::SpiMasterDevice;
+use kernel::hil::time::Alarm;
+
+// Setup static space for the objects.
+#[macro_export]
+macro_rules! spi_flash_component_static {
+    ($S:ty, $A:ty $(,)?) => {{
+        let spi_device = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_spi::VirtualSpiMasterDevice<'static, $S>
+        );
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let spi_flash = kernel::static_buf!(
+            capsules_extra::spi_flash::SpiFlash<
+                'static,
+                capsules_core::virtualizers::virtual_spi::VirtualSpiMasterDevice<'static, $S>,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+
+        let tx_buf = kernel::static_buf!([u8; capsules_extra::spi_flash::TX_BUF_LEN]);
+        let rx_buf = kernel::static_buf!([u8; capsules_extra::spi_flash::RX_BUF_LEN]);
+
+        (spi_device, alarm, spi_flash, tx_buf, rx_buf)
+    };};
+}
+
+pub struct SpiFlashComponent<
+    S: 'static + hil::spi::SpiMaster<'static>,
+    A: 'static + hil::time::Alarm<'static>,
+> {
+    mux_spi: &'static MuxSpiMaster<'static, S>,
+    chip_select: S::ChipSelect,
+    mux_alarm: &'static MuxAlarm<'static, A>,
+}
+
+impl<S: 'static + hil::spi::SpiMaster<'static>, A: 'static + hil::time::Alarm<'static>>
+    SpiFlashComponent<S, A>
+{
+    pub fn new(
+        mux_spi: &'static MuxSpiMaster<'static, S>,
+        chip_select: S::ChipSelect,
+        mux_alarm: &'static MuxAlarm<'static, A>,
+    ) -> SpiFlashComponent<S, A> {
+        SpiFlashComponent {
+            mux_spi,
+            chip_select,
+            mux_alarm,
+        }
+    }
+}
+
+impl<S: 'static + hil::spi::SpiMaster<'static>, A: 'static + hil::time::Alarm<'static>> Component
+    for SpiFlashComponent<S, A>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualSpiMasterDevice<'static, S>>,
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<
+            SpiFlash<'static, VirtualSpiMasterDevice<'static, S>, VirtualMuxAlarm<'static, A>>,
+        >,
+        &'static mut MaybeUninit<[u8; capsules_extra::spi_flash::TX_BUF_LEN]>,
+        &'static mut MaybeUninit<[u8; capsules_extra::spi_flash::RX_BUF_LEN]>,
+    );
+    type Output =
+        &'static SpiFlash<'static, VirtualSpiMasterDevice<'static, S>, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let spi_device = static_buffer
+            .0
+            .write(VirtualSpiMasterDevice::new(self.mux_spi, self.chip_select));
+        let virtual_alarm = static_buffer.1.write(VirtualMuxAlarm::new(self.mux_alarm));
+        virtual_alarm.setup();
+
+        let tx_buf = static_buffer
+            .3
+            .write([0; capsules_extra::spi_flash::TX_BUF_LEN]);
+        let rx_buf = static_buffer
+            .4
+            .write([0; capsules_extra::spi_flash::RX_BUF_LEN]);
+
+        let spi_flash =
+            static_buffer
+                .2
+                .write(SpiFlash::new(spi_device, virtual_alarm, tx_buf, rx_buf));
+        spi_device.setup();
+        spi_device.set_client(spi_flash);
+        virtual_alarm.set_alarm_client(spi_flash);
+
+        // Without a chip, the flash reports `OFF` for every operation.
+        let _ = spi_flash.probe();
+        spi_flash
+    }
+}
@@ -0,0 +1,62 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the system call trace.
+//!
+//! The trace times the system calls with the hardware timer, which it only
+//! reads. The board then provides it to the kernel as its syscall tracer,
+//! from `KernelHooks::syscall_tracer()`, and adds it to the process console
+//! as the `syscalls` command.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let syscall_trace = components::syscall_trace::SyscallTraceComponent::new(&peripherals.gpt1)
+//!     .finalize(components::syscall_trace_component_static!(imxrt1050::gpt::Gpt1, 64));
+//! process_console.add_command(syscall_trace).unwrap();
+//! ```
+
+use capsules_extra::syscall_trace::{SyscallRecord, SyscallTrace};
+use core::cell::Cell;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::time;
+
+#[macro_export]
+macro_rules! syscall_trace_component_static {
+    ($T:ty, $N:expr $(,)?) => {{
+        let log = kernel::static_buf!(
+            [core::cell::Cell<Option<capsules_extra::syscall_trace::SyscallRecord>>; $N]
+        );
+        let syscall_trace =
+            kernel::static_buf!(capsules_extra::syscall_trace::SyscallTrace<'static, $T>);
+
+        (log, syscall_trace)
+    };};
+}
+
+pub struct SyscallTraceComponent<T: 'static + time::Time, const N: usize> {
+    time: &'static T,
+}
+
+impl<T: 'static + time::Time, const N: usize> SyscallTraceComponent<T, N> {
+    pub fn new(time: &'static T) -> Self {
+        Self { time }
+    }
+}
+
+impl<T: 'static + time::Time, const N: usize> Component for SyscallTraceComponent<T, N> {
+    type StaticInput = (
+        &'static mut MaybeUninit<[Cell<Option<SyscallRecord>>; N]>,
+        &'static mut MaybeUninit<SyscallTrace<'static, T>>,
+    );
+    type Output = &'static SyscallTrace<'static, T>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let log = static_buffer
+            .0
+            .write(core::array::from_fn(|_| Cell::new(None)));
+        static_buffer.1.write(SyscallTrace::new(self.time, log))
+    }
+}
@@ -0,0 +1,107 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the system time.
+//!
+//! The component restores the time stored in the backup registers before
+//! the last reset, if any.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let system_time = components::system_time::SystemTimeComponent::new(
+//!     board_kernel,
+//!     capsules_extra::system_time::DRIVER_NUM,
+//!     mux_alarm,
+//!     &peripherals.watchdog,
+//!     3,
+//! )
+//! .finalize(components::system_time_component_static!(
+//!     RPTimer,
+//!     rp2040::watchdog::Watchdog<'static>
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::system_time::{SystemTime, NUM_REGISTERS};
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::backup_registers::BackupRegisters;
+use kernel::hil::time::{self, Alarm};
+
+#[macro_export]
+macro_rules! system_time_component_static {
+    ($A:ty, $B:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let system_time = kernel::static_buf!(
+            capsules_extra::system_time::SystemTime<
+                'static,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+                $B,
+            >
+        );
+
+        (alarm, system_time)
+    };};
+}
+
+pub struct SystemTimeComponent<A: 'static + time::Alarm<'static>, B: 'static + BackupRegisters> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    registers: &'static B,
+    first_register: usize,
+}
+
+impl<A: 'static + time::Alarm<'static>, B: 'static + BackupRegisters> SystemTimeComponent<A, B> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        registers: &'static B,
+        first_register: usize,
+    ) -> Self {
+        SystemTimeComponent {
+            board_kernel,
+            driver_num,
+            alarm_mux,
+            registers,
+            first_register,
+        }
+    }
+}
+
+impl<A: 'static + time::Alarm<'static>, B: 'static + BackupRegisters> Component
+    for SystemTimeComponent<A, B>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<SystemTime<'static, VirtualMuxAlarm<'static, A>, B>>,
+    );
+    type Output = &'static SystemTime<'static, VirtualMuxAlarm<'static, A>, B>;
+
+    fn finalize(self, s: Self::StaticInput) -> Self::Output {
+        assert!(self.first_register + NUM_REGISTERS <= self.registers.num_registers());
+
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let alarm = s.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let system_time = s.1.write(SystemTime::new(
+            alarm,
+            self.registers,
+            self.first_register,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+        alarm.set_alarm_client(system_time);
+        system_time.restore();
+
+        system_time
+    }
+}
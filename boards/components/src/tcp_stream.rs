@@ -0,0 +1,111 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for a TCP server on the IPv4 stack over Ethernet, exposed as a
+//! byte stream.
+//!
+//! The stream implements the UART HIL, so it can be given to capsules such as
+//! the process console in place of a UART.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let tcp_stream = components::tcp_stream::TcpStreamComponent::new(ipv4_stack, mux_alarm, 2323)
+//!     .finalize(components::tcp_stream_component_static!(
+//!         imxrt1050::enet::Enet<'static>,
+//!         imxrt1050::gpt::Gpt1<'static>,
+//!     ));
+//! ```
+
+use crate::ipv4::Ipv4StackComponentType;
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::net::ipv4::stack::TcpPort;
+use capsules_extra::net::ipv4::tcp::{TcpStream, RX_BUF_LEN, SEGMENT_BUF_LEN};
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::deferred_call::DeferredCallClient;
+use kernel::hil::ethernet::EthernetAdapter;
+use kernel::hil::mac_address::Configure;
+use kernel::hil::time::Alarm;
+
+#[macro_export]
+macro_rules! tcp_stream_component_static {
+    ($E:ty, $A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let port = kernel::static_buf!(capsules_extra::net::ipv4::stack::TcpPort<'static>);
+        let segment = kernel::static_buf!([u8; capsules_extra::net::ipv4::tcp::SEGMENT_BUF_LEN]);
+        let rx_ring = kernel::static_buf!([u8; capsules_extra::net::ipv4::tcp::RX_BUF_LEN]);
+        let stream = kernel::static_buf!(
+            capsules_extra::net::ipv4::tcp::TcpStream<
+                'static,
+                components::ipv4::Ipv4StackComponentType<$E, $A>,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+
+        (alarm, port, segment, rx_ring, stream)
+    };};
+}
+
+pub type TcpStreamComponentType<E, A> =
+    TcpStream<'static, Ipv4StackComponentType<E, A>, VirtualMuxAlarm<'static, A>>;
+
+pub struct TcpStreamComponent<
+    E: 'static + EthernetAdapter<'static> + Configure,
+    A: 'static + Alarm<'static>,
+> {
+    stack: &'static Ipv4StackComponentType<E, A>,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    port: u16,
+}
+
+impl<E: 'static + EthernetAdapter<'static> + Configure, A: 'static + Alarm<'static>>
+    TcpStreamComponent<E, A>
+{
+    pub fn new(
+        stack: &'static Ipv4StackComponentType<E, A>,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        port: u16,
+    ) -> Self {
+        Self {
+            stack,
+            alarm_mux,
+            port,
+        }
+    }
+}
+
+impl<E: 'static + EthernetAdapter<'static> + Configure, A: 'static + Alarm<'static>> Component
+    for TcpStreamComponent<E, A>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<TcpPort<'static>>,
+        &'static mut MaybeUninit<[u8; SEGMENT_BUF_LEN]>,
+        &'static mut MaybeUninit<[u8; RX_BUF_LEN]>,
+        &'static mut MaybeUninit<TcpStreamComponentType<E, A>>,
+    );
+    type Output = &'static TcpStreamComponentType<E, A>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let port = static_buffer.1.write(TcpPort::new(self.port));
+        let segment = static_buffer.2.write([0; SEGMENT_BUF_LEN]);
+        let rx_ring = static_buffer.3.write([0; RX_BUF_LEN]);
+
+        let stream = static_buffer
+            .4
+            .write(TcpStream::new(self.stack, port, alarm, segment, rx_ring));
+        port.set_handler(stream);
+        alarm.set_alarm_client(stream);
+        stream.register();
+        self.stack.add_tcp_port(port).unwrap();
+
+        stream
+    }
+}
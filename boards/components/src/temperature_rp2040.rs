@@ -3,6 +3,23 @@
 // Copyright Tock Contributors 2022.
 
 //! Component for the RaspberryPI 2040 built-in temperature sensor.
+//!
+//! The sensor is ADC channel 4, and the ADC powers it on when the channel is
+//! first sampled.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let temp_sensor = components::temperature_rp2040::TemperatureRp2040Component::new(
+//!     adc_mux,
+//!     rp2040::adc::Channel::Channel4,
+//!     1.721,
+//!     0.706,
+//! )
+//! .finalize(components::temperature_rp2040_adc_component_static!(
+//!     rp2040::adc::Adc
+//! ));
+//! ```
 
 use capsules_core::virtualizers::virtual_adc::AdcDevice;
 use capsules_extra::temperature_rp2040::TemperatureRp2040;
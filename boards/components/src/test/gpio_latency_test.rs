@@ -0,0 +1,114 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the GPIO interrupt latency test.
+//!
+//! The output pin must be connected to the input pin with a jumper wire.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! components::test::gpio_latency_test::GpioLatencyTestComponent::new(
+//!     mux_alarm,
+//!     peripherals.pins.get_pin(RPGpio::GPIO14),
+//!     peripherals.pins.get_pin(RPGpio::GPIO15),
+//!     100,
+//! )
+//! .finalize(components::gpio_latency_test_component_static!(
+//!     RPTimer,
+//!     RPGpioPin<'static>,
+//!     RPGpioPin<'static>,
+//! ))
+//! .run();
+//! ```
+
+use core::mem::MaybeUninit;
+
+use capsules_core::test::gpio_latency::TestGpioLatency;
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use kernel::component::Component;
+use kernel::hil::gpio;
+use kernel::hil::time::{self, Alarm};
+
+#[macro_export]
+macro_rules! gpio_latency_test_component_static {
+    ($A:ty, $O:ty, $I:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let test = kernel::static_buf!(
+            capsules_core::test::gpio_latency::TestGpioLatency<
+                'static,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+                $O,
+                $I,
+            >
+        );
+
+        (alarm, test)
+    };};
+}
+
+pub struct GpioLatencyTestComponent<
+    A: 'static + time::Alarm<'static>,
+    O: 'static + gpio::Pin,
+    I: 'static + gpio::InterruptPin<'static>,
+> {
+    mux: &'static MuxAlarm<'static, A>,
+    output: &'static O,
+    input: &'static I,
+    iterations: u32,
+}
+
+impl<
+        A: 'static + time::Alarm<'static>,
+        O: 'static + gpio::Pin,
+        I: 'static + gpio::InterruptPin<'static>,
+    > GpioLatencyTestComponent<A, O, I>
+{
+    pub fn new(
+        mux: &'static MuxAlarm<'static, A>,
+        output: &'static O,
+        input: &'static I,
+        iterations: u32,
+    ) -> Self {
+        Self {
+            mux,
+            output,
+            input,
+            iterations,
+        }
+    }
+}
+
+impl<
+        A: 'static + time::Alarm<'static>,
+        O: 'static + gpio::Pin,
+        I: 'static + gpio::InterruptPin<'static>,
+    > Component for GpioLatencyTestComponent<A, O, I>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<TestGpioLatency<'static, VirtualMuxAlarm<'static, A>, O, I>>,
+    );
+    type Output = &'static TestGpioLatency<'static, VirtualMuxAlarm<'static, A>, O, I>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let virtual_alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.mux));
+        virtual_alarm.setup();
+
+        self.output.make_output();
+        self.input.make_input();
+
+        let test = static_buffer.1.write(TestGpioLatency::new(
+            virtual_alarm,
+            self.output,
+            self.input,
+            self.iterations,
+        ));
+        virtual_alarm.set_alarm_client(test);
+        self.input.set_client(test);
+        test
+    }
+}
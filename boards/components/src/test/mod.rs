@@ -2,4 +2,6 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 // Copyright Tock Contributors 2022.
 
+pub mod gpio_latency_test;
 pub mod multi_alarm_test;
+pub mod uart_throughput_test;
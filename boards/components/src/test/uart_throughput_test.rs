@@ -0,0 +1,108 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the UART throughput test.
+//!
+//! The test gets its own device on the UART mux, so it can saturate the
+//! console UART. Run it once with the UART in interrupt mode and once with
+//! DMA, changing the label, to compare both.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! components::test::uart_throughput_test::UartThroughputTestComponent::new(
+//!     lpuart_mux,
+//!     mux_alarm,
+//!     2000,
+//!     "DMA",
+//! )
+//! .finalize(components::uart_throughput_test_component_static!(
+//!     imxrt1050::gpt::Gpt1
+//! ))
+//! .run();
+//! ```
+
+use core::mem::MaybeUninit;
+
+use capsules_core::test::uart_throughput::TestUartThroughput;
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_core::virtualizers::virtual_uart::{MuxUart, UartDevice};
+use kernel::component::Component;
+use kernel::hil::time::{self, Alarm};
+use kernel::hil::uart::Transmit;
+
+/// Length of the buffers sent, 8 lines of the pattern.
+pub const BUFFER_LEN: usize = 224;
+
+#[macro_export]
+macro_rules! uart_throughput_test_component_static {
+    ($A:ty $(,)?) => {{
+        let uart = kernel::static_buf!(capsules_core::virtualizers::virtual_uart::UartDevice);
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let buffer = kernel::static_buf!([u8; components::test::uart_throughput_test::BUFFER_LEN]);
+        let test = kernel::static_buf!(
+            capsules_core::test::uart_throughput::TestUartThroughput<
+                'static,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+
+        (uart, alarm, buffer, test)
+    };};
+}
+
+pub struct UartThroughputTestComponent<A: 'static + time::Alarm<'static>> {
+    uart_mux: &'static MuxUart<'static>,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    duration_ms: u32,
+    label: &'static str,
+}
+
+impl<A: 'static + time::Alarm<'static>> UartThroughputTestComponent<A> {
+    pub fn new(
+        uart_mux: &'static MuxUart<'static>,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        duration_ms: u32,
+        label: &'static str,
+    ) -> Self {
+        Self {
+            uart_mux,
+            alarm_mux,
+            duration_ms,
+            label,
+        }
+    }
+}
+
+impl<A: 'static + time::Alarm<'static>> Component for UartThroughputTestComponent<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<UartDevice<'static>>,
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<[u8; BUFFER_LEN]>,
+        &'static mut MaybeUninit<TestUartThroughput<'static, VirtualMuxAlarm<'static, A>>>,
+    );
+    type Output = &'static TestUartThroughput<'static, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let uart = static_buffer.0.write(UartDevice::new(self.uart_mux, false));
+        uart.setup();
+
+        let virtual_alarm = static_buffer.1.write(VirtualMuxAlarm::new(self.alarm_mux));
+        virtual_alarm.setup();
+
+        let buffer = static_buffer.2.write([0; BUFFER_LEN]);
+        let test = static_buffer.3.write(TestUartThroughput::new(
+            uart,
+            virtual_alarm,
+            buffer,
+            self.duration_ms,
+            self.label,
+        ));
+        uart.set_transmit_client(test);
+        virtual_alarm.set_alarm_client(test);
+        test
+    }
+}
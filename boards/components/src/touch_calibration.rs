@@ -0,0 +1,89 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the touch calibration capsule.
+//!
+//! The component starts loading the stored transform. The `Touch` capsule
+//! applies it once the calibration is set on it.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let calibration = components::touch_calibration::TouchCalibrationComponent::new(
+//!     board_kernel,
+//!     capsules_extra::touch_calibration::DRIVER_NUM,
+//!     nonvolatile_storage,
+//!     0x0,
+//! )
+//! .finalize(components::touch_calibration_component_static!());
+//! touch.set_calibration(calibration);
+//! ```
+
+use capsules_extra::touch_calibration::{TouchCalibration, STORAGE_LEN};
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::nonvolatile_storage::NonvolatileStorage;
+
+#[macro_export]
+macro_rules! touch_calibration_component_static {
+    () => {{
+        let buffer = kernel::static_buf!([u8; capsules_extra::touch_calibration::STORAGE_LEN]);
+        let calibration =
+            kernel::static_buf!(capsules_extra::touch_calibration::TouchCalibration<'static>);
+
+        (buffer, calibration)
+    };};
+}
+
+pub struct TouchCalibrationComponent {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    storage: &'static dyn NonvolatileStorage<'static>,
+    address: usize,
+}
+
+impl TouchCalibrationComponent {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        storage: &'static dyn NonvolatileStorage<'static>,
+        address: usize,
+    ) -> TouchCalibrationComponent {
+        TouchCalibrationComponent {
+            board_kernel,
+            driver_num,
+            storage,
+            address,
+        }
+    }
+}
+
+impl Component for TouchCalibrationComponent {
+    type StaticInput = (
+        &'static mut MaybeUninit<[u8; STORAGE_LEN]>,
+        &'static mut MaybeUninit<TouchCalibration<'static>>,
+    );
+    type Output = &'static TouchCalibration<'static>;
+
+    fn finalize(self, static_input: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+        let grant = self.board_kernel.create_grant(self.driver_num, &grant_cap);
+
+        let buffer = static_input.0.write([0; STORAGE_LEN]);
+
+        let calibration = static_input.1.write(TouchCalibration::new(
+            self.storage,
+            self.address,
+            buffer,
+            grant,
+        ));
+        self.storage.set_client(calibration);
+        let _ = calibration.load();
+
+        calibration
+    }
+}
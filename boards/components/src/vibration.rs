@@ -0,0 +1,104 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for vibration analysis of accelerometer samples.
+//!
+//! The component becomes the client of the accelerometer, so the sensor
+//! cannot also be given to the 9DOF driver.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let vibration = components::vibration::VibrationComponent::new(
+//!     board_kernel,
+//!     capsules_extra::vibration::DRIVER_NUM,
+//!     mux_alarm,
+//!     fxos8700,
+//! )
+//! .finalize(components::vibration_component_static!(
+//!     imxrt1050::gpt::Gpt1<'static>,
+//! ));
+//! ```
+
+use capsules_core::virtualizers::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use capsules_extra::vibration::{Vibration, MAX_WINDOW_LEN};
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::sensors::NineDof;
+use kernel::hil::time::Alarm;
+
+#[macro_export]
+macro_rules! vibration_component_static {
+    ($A:ty $(,)?) => {{
+        let alarm = kernel::static_buf!(
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>
+        );
+        let vibration = kernel::static_buf!(
+            capsules_extra::vibration::Vibration<
+                'static,
+                capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<'static, $A>,
+            >
+        );
+        let re = kernel::static_buf!([i32; capsules_extra::vibration::MAX_WINDOW_LEN]);
+        let im = kernel::static_buf!([i32; capsules_extra::vibration::MAX_WINDOW_LEN]);
+
+        (alarm, vibration, re, im)
+    };};
+}
+
+pub struct VibrationComponent<A: 'static + Alarm<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    sensor: &'static dyn NineDof<'static>,
+}
+
+impl<A: 'static + Alarm<'static>> VibrationComponent<A> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        sensor: &'static dyn NineDof<'static>,
+    ) -> Self {
+        VibrationComponent {
+            board_kernel,
+            driver_num,
+            alarm_mux,
+            sensor,
+        }
+    }
+}
+
+impl<A: 'static + Alarm<'static>> Component for VibrationComponent<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<Vibration<'static, VirtualMuxAlarm<'static, A>>>,
+        &'static mut MaybeUninit<[i32; MAX_WINDOW_LEN]>,
+        &'static mut MaybeUninit<[i32; MAX_WINDOW_LEN]>,
+    );
+    type Output = &'static Vibration<'static, VirtualMuxAlarm<'static, A>>;
+
+    fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let alarm = static_buffer.0.write(VirtualMuxAlarm::new(self.alarm_mux));
+        alarm.setup();
+
+        let re = static_buffer.2.write([0; MAX_WINDOW_LEN]);
+        let im = static_buffer.3.write([0; MAX_WINDOW_LEN]);
+        let vibration = static_buffer.1.write(Vibration::new(
+            self.sensor,
+            alarm,
+            re,
+            im,
+            self.board_kernel.create_grant(self.driver_num, &grant_cap),
+        ));
+        alarm.set_alarm_client(vibration);
+        self.sensor.set_client(vibration);
+
+        vibration
+    }
+}
@@ -92,7 +92,13 @@ struct Hail {
     rng: &'static capsules_core::rng::RngDriver<'static>,
     ipc: kernel::ipc::IPC<{ NUM_PROCS as u8 }>,
     crc: &'static capsules_extra::crc::CrcDriver<'static, sam4l::crccu::Crccu<'static>>,
-    dac: &'static capsules_extra::dac::Dac<'static>,
+    dac: &'static capsules_extra::dac::Dac<
+        'static,
+        capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<
+            'static,
+            sam4l::ast::Ast<'static>,
+        >,
+    >,
     scheduler: &'static RoundRobinSched<'static>,
     systick: cortexm4::systick::SysTick,
 }
@@ -138,6 +144,7 @@ impl KernelResources<sam4l::chip::Sam4l<Sam4lDefaultPeripherals>> for Hail {
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm4::systick::SysTick;
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -161,6 +168,9 @@ impl KernelResources<sam4l::chip::Sam4l<Sam4lDefaultPeripherals>> for Hail {
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -471,8 +481,13 @@ pub unsafe fn main() {
     .finalize(components::crc_component_static!(sam4l::crccu::Crccu));
 
     // DAC
-    let dac = components::dac::DacComponent::new(&peripherals.dac)
-        .finalize(components::dac_component_static!());
+    let dac = components::dac::DacComponent::new(
+        board_kernel,
+        capsules_extra::dac::DRIVER_NUM,
+        &peripherals.dac,
+        mux_alarm,
+    )
+    .finalize(components::dac_component_static!(sam4l::ast::Ast));
 
     // // DEBUG Restart All Apps
     // //
@@ -552,6 +567,7 @@ pub unsafe fn main() {
 
     kernel::process::load_processes(
         board_kernel,
+        &hail,
         chip,
         core::slice::from_raw_parts(
             &_sapps as *const u8,
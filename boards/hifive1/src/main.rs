@@ -100,6 +100,7 @@ impl KernelResources<e310_g002::chip::E310x<'static, E310G002DefaultPeripherals<
     type SchedulerTimer =
         VirtualSchedulerTimer<VirtualMuxAlarm<'static, e310_g002::chip::E310xClint<'static>>>;
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -123,6 +124,9 @@ impl KernelResources<e310_g002::chip::E310x<'static, E310G002DefaultPeripherals<
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -147,7 +151,11 @@ unsafe fn create_peripherals(
 /// main. By wrapping it in a non-inlined function, this reduces the stack utilization once
 /// processes are running.
 #[inline(never)]
-fn load_processes_not_inlined<C: Chip>(board_kernel: &'static Kernel, chip: &'static C) {
+fn load_processes_not_inlined<KR: KernelResources<C>, C: Chip>(
+    board_kernel: &'static Kernel,
+    kernel_resources: &KR,
+    chip: &'static C,
+) {
     // These symbols are defined in the linker script.
     extern "C" {
         /// Beginning of the ROM region containing app images.
@@ -177,6 +185,7 @@ fn load_processes_not_inlined<C: Chip>(board_kernel: &'static Kernel, chip: &'st
     let process_mgmt_cap = create_capability!(capabilities::ProcessManagementCapability);
     kernel::process::load_processes(
         board_kernel,
+        kernel_resources,
         chip,
         app_flash,
         app_memory,
@@ -354,7 +363,7 @@ pub unsafe fn main() {
         scheduler_timer,
     };
 
-    load_processes_not_inlined(board_kernel, chip);
+    load_processes_not_inlined(board_kernel, &hifive1, chip);
 
     board_kernel.kernel_loop(&hifive1, chip, None::<&kernel::ipc::IPC<0>>, &main_loop_cap);
 }
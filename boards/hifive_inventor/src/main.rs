@@ -89,6 +89,7 @@ impl KernelResources<e310_g003::chip::E310x<'static, E310G003DefaultPeripherals<
     type SchedulerTimer =
         VirtualSchedulerTimer<VirtualMuxAlarm<'static, e310_g003::chip::E310xClint<'static>>>;
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -114,6 +115,9 @@ impl KernelResources<e310_g003::chip::E310x<'static, E310G003DefaultPeripherals<
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -291,6 +295,7 @@ pub unsafe fn main() {
 
     kernel::process::load_processes(
         board_kernel,
+        &hifive1,
         chip,
         core::slice::from_raw_parts(
             &_sapps as *const u8,
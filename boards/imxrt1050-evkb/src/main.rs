@@ -11,14 +11,24 @@
 #![deny(missing_docs)]
 
 use capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm;
+#[cfg(feature = "ethernet")]
+use capsules_extra::net::ipv4::mdns::MdnsService;
+#[cfg(feature = "ethernet")]
+use capsules_extra::net::ipv4::Ipv4Config;
 use components::gpio::GpioComponent;
 use kernel::capabilities;
 use kernel::component::Component;
 use kernel::debug;
 use kernel::hil::gpio::Configure;
 use kernel::hil::led::LedLow;
-use kernel::platform::{KernelResources, SyscallDriverLookup};
+#[cfg(feature = "ethernet")]
+use kernel::hil::mac_address::{Configure as _, MacAddress, MacAddressSource};
+use kernel::hil::reset_reason::ResetReasonQuery;
+use kernel::platform::{KernelHooks, KernelResources, SyscallDriverLookup};
+use kernel::process_load_report::ProcessLoadMonitor;
 use kernel::scheduler::round_robin::RoundRobinSched;
+use kernel::syscall_trace::SyscallTracer;
+use kernel::trace::TraceSink;
 use kernel::{create_capability, static_init};
 
 // use components::fxos8700::Fxos8700Component;
@@ -51,6 +61,22 @@ static mut PROCESSES: [Option<&'static dyn kernel::process::Process>; NUM_PROCS]
     [None; NUM_PROCS];
 
 type Chip = imxrt1050::chip::Imxrt10xx<imxrt1050::chip::Imxrt10xxDefaultPeripherals>;
+type ButtonPin = capsules_extra::gpio_debounce::DebouncedPin<
+    'static,
+    imxrt1050::gpio::Pin<'static>,
+    VirtualMuxAlarm<'static, imxrt1050::gpt::Gpt1<'static>>,
+>;
+#[cfg(feature = "ethernet")]
+type UdpDriver = capsules_extra::net::ipv4::driver::UdpDriver<
+    'static,
+    imxrt1050::enet::Enet<'static>,
+    VirtualMuxAlarm<'static, imxrt1050::gpt::Gpt1<'static>>,
+>;
+#[cfg(feature = "ethernet")]
+type NetworkConsoleStream = components::tcp_stream::TcpStreamComponentType<
+    imxrt1050::enet::Enet<'static>,
+    imxrt1050::gpt::Gpt1<'static>,
+>;
 static mut CHIP: Option<&'static Chip> = None;
 static mut PROCESS_PRINTER: Option<&'static kernel::process::ProcessPrinterText> = None;
 
@@ -62,6 +88,20 @@ const FAULT_RESPONSE: kernel::process::PanicFaultPolicy = kernel::process::Panic
 #[link_section = ".boot_hdr"]
 static BOOT_HDR: [u8; 8192] = boot_header::BOOT_HDR;
 
+/// TCP port of the network process console.
+#[cfg(feature = "ethernet")]
+const NETWORK_CONSOLE_PORT: u16 = 2323;
+
+/// Kernel debug output beyond this rate is dropped, so that it does not fill
+/// the debug buffer faster than the 115200 baud LPUART empties it.
+const DEBUG_BYTES_PER_SECOND: usize = 8000;
+/// Period at which the amount of debug output dropped is reported.
+const DEBUG_DROP_REPORT_MS: u32 = 5000;
+/// Number of trace events kept in RAM and printed on panic.
+const TRACE_EVENTS: usize = 64;
+/// Number of system calls kept for the `syscalls` console command.
+const SYSCALL_TRACE_LEN: usize = 64;
+
 /// Dummy buffer that causes the linker to reserve enough space for the stack.
 #[no_mangle]
 #[link_section = ".stack_buffer"]
@@ -76,7 +116,7 @@ struct Imxrt1050EVKB {
         'static,
         VirtualMuxAlarm<'static, imxrt1050::gpt::Gpt1<'static>>,
     >,
-    button: &'static capsules_core::button::Button<'static, imxrt1050::gpio::Pin<'static>>,
+    button: &'static capsules_core::button::Button<'static, ButtonPin>,
     console: &'static capsules_core::console::Console<'static>,
     gpio: &'static capsules_core::gpio::GPIO<'static, imxrt1050::gpio::Pin<'static>>,
     ipc: kernel::ipc::IPC<{ NUM_PROCS as u8 }>,
@@ -86,9 +126,21 @@ struct Imxrt1050EVKB {
         1,
     >,
     ninedof: &'static capsules_extra::ninedof::NineDof<'static>,
+    ambient_light: &'static capsules_extra::ambient_light::AmbientLight<'static>,
+    device_id: &'static capsules_extra::device_id::DeviceIdDriver<'static>,
+    boot_counter:
+        &'static capsules_extra::boot_counter::BootCounter<'static, imxrt1050::snvs::Snvs>,
+    #[cfg(feature = "ethernet")]
+    udp: &'static UdpDriver,
 
     scheduler: &'static RoundRobinSched<'static>,
     systick: cortexm7::systick::SysTick,
+    trace_buffer: &'static kernel::trace::TraceBuffer,
+    syscall_trace: &'static capsules_extra::syscall_trace::SyscallTrace<
+        'static,
+        imxrt1050::gpt::Gpt1<'static>,
+    >,
+    process_load_log: &'static capsules_extra::process_load_log::ProcessLoadLog<'static>,
 }
 
 /// Mapping of integer syscalls to objects that implement syscalls.
@@ -105,11 +157,30 @@ impl SyscallDriverLookup for Imxrt1050EVKB {
             kernel::ipc::DRIVER_NUM => f(Some(&self.ipc)),
             capsules_core::led::DRIVER_NUM => f(Some(self.led)),
             capsules_extra::ninedof::DRIVER_NUM => f(Some(self.ninedof)),
+            capsules_extra::ambient_light::DRIVER_NUM => f(Some(self.ambient_light)),
+            capsules_extra::boot_counter::DRIVER_NUM => f(Some(self.boot_counter)),
+            capsules_extra::device_id::DRIVER_NUM => f(Some(self.device_id)),
+            #[cfg(feature = "ethernet")]
+            capsules_extra::net::ipv4::driver::DRIVER_NUM => f(Some(self.udp)),
             _ => f(None),
         }
     }
 }
 
+impl KernelHooks for Imxrt1050EVKB {
+    fn trace_sink(&self) -> Option<&'static dyn TraceSink> {
+        Some(self.trace_buffer)
+    }
+
+    fn syscall_tracer(&self) -> Option<&dyn SyscallTracer> {
+        Some(self.syscall_trace)
+    }
+
+    fn process_load_monitor(&self) -> Option<&dyn ProcessLoadMonitor> {
+        Some(self.process_load_log)
+    }
+}
+
 impl KernelResources<imxrt1050::chip::Imxrt10xx<imxrt1050::chip::Imxrt10xxDefaultPeripherals>>
     for Imxrt1050EVKB
 {
@@ -120,6 +191,7 @@ impl KernelResources<imxrt1050::chip::Imxrt10xx<imxrt1050::chip::Imxrt10xxDefaul
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm7::systick::SysTick;
     type WatchDog = ();
+    type KernelHooks = Self;
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -143,6 +215,9 @@ impl KernelResources<imxrt1050::chip::Imxrt10xx<imxrt1050::chip::Imxrt10xxDefaul
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        self
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -222,6 +297,140 @@ unsafe fn setup_peripherals(peripherals: &imxrt1050::chip::Imxrt10xxDefaultPerip
     cortexm7::nvic::Nvic::new(imxrt1050::nvic::GPT1).enable();
 }
 
+/// Helper function that brings up the Ethernet interface, with DHCP, mDNS,
+/// and the connection of the network process console.
+#[cfg(feature = "ethernet")]
+unsafe fn setup_ethernet(
+    peripherals: &'static imxrt1050::chip::Imxrt10xxDefaultPeripherals,
+    board_kernel: &'static kernel::Kernel,
+    mux_alarm: &'static capsules_core::virtualizers::virtual_alarm::MuxAlarm<
+        'static,
+        imxrt1050::gpt::Gpt1<'static>,
+    >,
+) -> (&'static UdpDriver, &'static NetworkConsoleStream) {
+    // The KSZ8081 PHY is clocked by the 50MHz RMII reference clock output on
+    // GPIO_B1_10. Its reset is shared with the user LED on GPIO_AD_B0_09,
+    // which is high, and so releases the PHY from reset, while the LED is off.
+    // Values set accordingly to the evkbimxrt1050_enet SDK example
+    peripherals.ccm_analog.enable_enet_pll_50mhz();
+    peripherals.iomuxc_gpr.enable_enet1_ref_clock_output();
+
+    // GPIO_B1_04 to GPIO_B1_09 and GPIO_B1_11 are RXD0, RXD1, CRS_DV,
+    // TXD0, TXD1, TX_EN and RX_ER.
+    for pin in [4, 5, 6, 7, 8, 9, 11] {
+        peripherals.iomuxc.enable_sw_mux_ctl_pad_gpio(
+            PadId::B1,
+            MuxMode::ALT3, // ALT3: ENET signals of instance: enet
+            Sion::Disabled,
+            pin,
+        );
+    }
+    peripherals.iomuxc.enable_sw_mux_ctl_pad_gpio(
+        PadId::B1,
+        MuxMode::ALT6, // ALT6: ENET_REF_CLK of instance: enet
+        Sion::Enabled,
+        10,
+    );
+    // GPIO_EMC_40 is MDC and GPIO_EMC_41 is MDIO.
+    for pin in [40, 41] {
+        peripherals.iomuxc.enable_sw_mux_ctl_pad_gpio(
+            PadId::EMC,
+            MuxMode::ALT4, // ALT4: ENET_MDC and ENET_MDIO of instance: enet
+            Sion::Disabled,
+            pin,
+        );
+    }
+    for pin in [4, 5, 6, 7, 8, 9, 10, 11] {
+        peripherals.iomuxc.configure_sw_pad_ctl_pad_gpio(
+            PadId::B1,
+            pin,
+            PullUpDown::Pus2_100kOhmPullUp,     // 100K Ohm Pull Up
+            PullKeepEn::Pke1PullKeeperEnabled,  // Pull-down resistor or keep the previous value
+            OpenDrainEn::Ode0OpenDrainDisabled, // Output is CMOS, either 0 logic or 1 logic
+            Speed::Maximum,                     // Operating frequency: 150MHz - 200MHz
+            DriveStrength::DSE6, // Dual/Single voltage: 43/43 Ohm @ 1.8V, 40/26 Ohm @ 3.3V
+        );
+    }
+    peripherals.iomuxc.enable_enet_rmii_select_inputs();
+
+    let enet_buffers = static_init!(
+        imxrt1050::enet::EnetBuffers,
+        imxrt1050::enet::EnetBuffers::new()
+    );
+    peripherals.enet.set_buffers(enet_buffers);
+    peripherals.enet.set_phy_address(2);
+    // Boards without a MAC address in the fuses use a locally administered
+    // one.
+    let _ = peripherals.enet.set_mac_address(
+        peripherals
+            .ocotp
+            .mac_address()
+            .unwrap_or(MacAddress::new([0x02, 0x00, 0x00, 0x00, 0x10, 0x50])),
+    );
+    cortexm7::nvic::Nvic::new(imxrt1050::nvic::ENET).enable();
+
+    let ipv4_stack = components::ipv4::Ipv4StackComponent::new(
+        &peripherals.enet,
+        mux_alarm,
+        // The address is configured with DHCP.
+        Ipv4Config::UNCONFIGURED,
+    )
+    .finalize(components::ipv4_stack_component_static!(
+        imxrt1050::enet::Enet<'static>,
+        imxrt1050::gpt::Gpt1<'static>,
+    ));
+
+    let udp = components::ipv4::Ipv4UdpDriverComponent::new(
+        board_kernel,
+        capsules_extra::net::ipv4::driver::DRIVER_NUM,
+        ipv4_stack,
+    )
+    .finalize(components::ipv4_udp_driver_component_static!(
+        imxrt1050::enet::Enet<'static>,
+        imxrt1050::gpt::Gpt1<'static>,
+    ));
+
+    let dhcp = components::dhcp::DhcpClientComponent::new(ipv4_stack, mux_alarm).finalize(
+        components::dhcp_client_component_static!(
+            imxrt1050::enet::Enet<'static>,
+            imxrt1050::gpt::Gpt1<'static>,
+        ),
+    );
+
+    // Connection for the network process console, set up below.
+    let console_stream = components::tcp_stream::TcpStreamComponent::new(
+        ipv4_stack,
+        mux_alarm,
+        NETWORK_CONSOLE_PORT,
+    )
+    .finalize(components::tcp_stream_component_static!(
+        imxrt1050::enet::Enet<'static>,
+        imxrt1050::gpt::Gpt1<'static>,
+    ));
+
+    // The board can be reached as tock-evkb.local once it has an address,
+    // and advertises the network process console.
+    let mdns = components::mdns::MdnsResponderComponent::new(
+        ipv4_stack,
+        "tock-evkb",
+        Some(MdnsService {
+            service: "_tock-console",
+            protocol: "_tcp",
+            port: NETWORK_CONSOLE_PORT,
+        }),
+    )
+    .finalize(components::mdns_responder_component_static!(
+        imxrt1050::enet::Enet<'static>,
+        imxrt1050::gpt::Gpt1<'static>,
+    ));
+    dhcp.set_client(mdns);
+
+    let _ = ipv4_stack.start();
+    dhcp.start();
+
+    (udp, console_stream)
+}
+
 /// This is in a separate, inline(never) function so that its stack frame is
 /// removed when this function returns. Otherwise, the stack space used for
 /// these static_inits is wasted.
@@ -262,6 +471,13 @@ pub unsafe fn main() {
     let chip = static_init!(Chip, Chip::new(peripherals));
     CHIP = Some(chip);
 
+    // The User_Button (GPIO5 pin 0) is the only source waking the chip up
+    // from STOP mode, which it enters when a capsule such as the power
+    // manager allows deep sleep.
+    peripherals.gpc.init();
+    let _ = peripherals.gpc.enable_wakeup(imxrt1050::nvic::GPIO5_1);
+    chip.set_gpc(&peripherals.gpc);
+
     // LPUART1
 
     // Enable tx and rx from iomuxc
@@ -308,6 +524,9 @@ pub unsafe fn main() {
     // Enable clock
     peripherals.lpuart1.enable_clock();
 
+    // Use `UartMuxComponent::new_framed` instead to tag the output of the
+    // kernel, of the process console and of each process with its channel,
+    // and read it with `tools/console_demux.py`.
     let lpuart_mux = components::console::UartMuxComponent::new(&peripherals.lpuart1, 115200)
         .finalize(components::uart_mux_component_static!());
     io::WRITER.set_initialized();
@@ -330,6 +549,20 @@ pub unsafe fn main() {
     components::debug_writer::DebugWriterComponent::new(lpuart_mux)
         .finalize(components::debug_writer_component_static!());
 
+    // Keep the most recent trace points in RAM, to be printed on panic. With
+    // a probe reading SWO, `cortexm7::itm::ItmTrace` can be used instead.
+    let trace_events = static_init!(
+        kernel::collections::ring_buffer::RingBuffer<'static, kernel::trace::TraceEvent>,
+        kernel::collections::ring_buffer::RingBuffer::new(static_init!(
+            [kernel::trace::TraceEvent; TRACE_EVENTS],
+            [kernel::trace::TraceEvent::default(); TRACE_EVENTS]
+        ))
+    );
+    let trace_buffer = static_init!(
+        kernel::trace::TraceBuffer,
+        kernel::trace::TraceBuffer::new(trace_events)
+    );
+
     // LEDs
 
     // Clock to Port A is enabled in `set_pin_primary_functions()
@@ -338,26 +571,45 @@ pub unsafe fn main() {
         LedLow::new(peripherals.ports.pin(imxrt1050::gpio::PinId::AdB0_09)),
     ));
 
+    // ALARM
+    let gpt1 = &peripherals.gpt1;
+    let mux_alarm = components::alarm::AlarmMuxComponent::new(gpt1).finalize(
+        components::alarm_mux_component_static!(imxrt1050::gpt::Gpt1),
+    );
+
+    components::debug_rate_limit::DebugRateLimitComponent::new(
+        mux_alarm,
+        DEBUG_BYTES_PER_SECOND,
+        DEBUG_DROP_REPORT_MS,
+    )
+    .finalize(components::debug_rate_limit_component_static!(
+        imxrt1050::gpt::Gpt1
+    ));
+
     // BUTTONs
+    let button_pin = components::gpio_debounce::DebouncedPinComponent::new(
+        peripherals.ports.pin(imxrt1050::gpio::PinId::Wakeup),
+        mux_alarm,
+        capsules_extra::gpio_debounce::DEFAULT_DEBOUNCE_MS,
+    )
+    .finalize(components::debounced_pin_component_static!(
+        imxrt1050::gpio::Pin,
+        imxrt1050::gpt::Gpt1
+    ));
+
     let button = components::button::ButtonComponent::new(
         board_kernel,
         capsules_core::button::DRIVER_NUM,
         components::button_component_helper!(
-            imxrt1050::gpio::Pin,
+            ButtonPin,
             (
-                peripherals.ports.pin(imxrt1050::gpio::PinId::Wakeup),
+                button_pin,
                 kernel::hil::gpio::ActivationMode::ActiveHigh,
                 kernel::hil::gpio::FloatingState::PullDown
             )
         ),
     )
-    .finalize(components::button_component_static!(imxrt1050::gpio::Pin));
-
-    // ALARM
-    let gpt1 = &peripherals.gpt1;
-    let mux_alarm = components::alarm::AlarmMuxComponent::new(gpt1).finalize(
-        components::alarm_mux_component_static!(imxrt1050::gpt::Gpt1),
-    );
+    .finalize(components::button_component_static!(ButtonPin));
 
     let alarm = components::alarm::AlarmDriverComponent::new(
         board_kernel,
@@ -366,6 +618,31 @@ pub unsafe fn main() {
     )
     .finalize(components::alarm_component_static!(imxrt1050::gpt::Gpt1));
 
+    // DEVICE ID
+
+    let device_id = components::device_id::DeviceIdComponent::new(
+        board_kernel,
+        capsules_extra::device_id::DRIVER_NUM,
+        Some(&peripherals.ocotp),
+        "imxrt1050-evkb",
+        "MIMXRT1050-EVKB",
+    )
+    .finalize(components::device_id_component_static!());
+
+    // BOOT COUNTER
+
+    let boot_counter = components::boot_counter::BootCounterComponent::new(
+        &peripherals.snvs,
+        0,
+        peripherals.src.reset_reason(),
+    )
+    .finalize(components::boot_counter_component_static!(
+        imxrt1050::snvs::Snvs
+    ));
+
+    #[cfg(feature = "ethernet")]
+    let (udp, console_stream) = setup_ethernet(peripherals, board_kernel, mux_alarm);
+
     // GPIO
     // For now we expose only two pins
     let gpio = GpioComponent::new(
@@ -456,9 +733,95 @@ pub unsafe fn main() {
     )
     .finalize(components::ninedof_component_static!(fxos8700));
 
+    // Ambient light
+    // AD_B1_10 (Arduino A0) is ADC1_IN15. A photoresistor (GL5528) goes from
+    // A0 to ground, and a 10 kOhm resistor from A0 to 3.3V.
+    // The pad is muxed to GPIO, with the pull/keeper disabled, so it does not
+    // load the divider.
+    peripherals.iomuxc.enable_sw_mux_ctl_pad_gpio(
+        PadId::AdB1,
+        MuxMode::ALT5, // ALT5: GPIO1_IO26, the ADC input stays connected
+        Sion::Disabled,
+        10,
+    );
+    peripherals.iomuxc.configure_sw_pad_ctl_pad_gpio(
+        PadId::AdB1,
+        10,
+        PullUpDown::Pus0_100kOhmPullDown, // Unused, the pull/keeper is disabled
+        PullKeepEn::Pke0PullKeeperDisabled, // Pull/Keeper Disabled
+        OpenDrainEn::Ode0OpenDrainDisabled, // Open Drain Disabled
+        Speed::Low,                       // Operating frequency: 50MHz
+        DriveStrength::DSE0,              // HI-Z, the output is not used
+    );
+
+    let adc_mux = components::adc::AdcMuxComponent::new(&peripherals.adc1)
+        .finalize(components::adc_mux_component_static!(imxrt1050::adc::Adc));
+
+    let photoresistor = components::photoresistor::PhotoresistorComponent::new(
+        adc_mux,
+        imxrt1050::adc::Channel::In15,
+        capsules_extra::analog_sensor::Photoresistor::new(
+            capsules_extra::analog_sensor::DividerPosition::LowSide,
+            10_000,
+            15_000,
+            700,
+        ),
+    )
+    .finalize(components::photoresistor_component_static!(
+        imxrt1050::adc::Adc<'static>
+    ));
+
+    let ambient_light = components::isl29035::AmbientLightComponent::new(
+        board_kernel,
+        capsules_extra::ambient_light::DRIVER_NUM,
+        photoresistor,
+    )
+    .finalize(components::ambient_light_component_static!());
+
+    // Uncomment this block to analyze the vibrations measured by the
+    // accelerometer of the Fxos8700. The vibration driver becomes the client
+    // of the sensor, so remove the Ninedof driver above and add
+    // `capsules_extra::vibration::DRIVER_NUM` to `with_driver`.
+    // let vibration = components::vibration::VibrationComponent::new(
+    //     board_kernel,
+    //     capsules_extra::vibration::DRIVER_NUM,
+    //     mux_alarm,
+    //     fxos8700,
+    // )
+    // .finalize(components::vibration_component_static!(
+    //     imxrt1050::gpt::Gpt1<'static>
+    // ));
+
+    // FlexIO UART
+    // AD_B1_06 (Arduino D1) is FLEXIO1_FLEXIO06, the TX pin
+    // AD_B1_07 (Arduino D0) is FLEXIO1_FLEXIO07, the RX pin
+    // Uncomment to add a serial port emulated by the FlexIO1, for capsules
+    // using a UART mux. The FlexIO clock root is 480 MHz / 64 = 7.5 MHz.
+    /*peripherals.flexio1.set_clock_root(8, 8);
+    peripherals.flexio1.mux_pin(&peripherals.iomuxc, 6);
+    peripherals.flexio1.mux_pin(&peripherals.iomuxc, 7);
+    let flexio_uart = static_init!(
+        imxrt1050::flexio::FlexioUart<'static>,
+        imxrt1050::flexio::FlexioUart::new(&peripherals.flexio1, 0, 6, 7)
+    );
+    peripherals.flexio1.add_client(flexio_uart).unwrap();
+    let _flexio_uart_mux = components::console::UartMuxComponent::new(flexio_uart, 115200)
+        .finalize(components::uart_mux_component_static!());*/
+
     let scheduler = components::sched::round_robin::RoundRobinComponent::new(&PROCESSES)
         .finalize(components::round_robin_component_static!(NUM_PROCS));
 
+    // System calls of processes, for the `syscalls` command of the process
+    // console.
+    let syscall_trace =
+        components::syscall_trace::SyscallTraceComponent::new(&peripherals.gpt1).finalize(
+            components::syscall_trace_component_static!(imxrt1050::gpt::Gpt1, SYSCALL_TRACE_LEN),
+        );
+    // Reports of the processes loaded below, to check which apps fit in the
+    // 64 KiB application region.
+    let process_load_log = components::process_load_log::ProcessLoadLogComponent::new()
+        .finalize(components::process_load_log_component_static!(NUM_PROCS));
+
     let imxrt1050 = Imxrt1050EVKB {
         console: console,
         ipc: kernel::ipc::IPC::new(
@@ -469,17 +832,52 @@ pub unsafe fn main() {
         led: led,
         button: button,
         ninedof: ninedof,
+        ambient_light,
         alarm: alarm,
         gpio: gpio,
+        device_id,
+        boot_counter,
+        #[cfg(feature = "ethernet")]
+        udp,
 
         scheduler,
         systick: cortexm7::systick::SysTick::new_with_calibration(792_000_000),
+        trace_buffer,
+        syscall_trace,
+        process_load_log,
     };
 
     // Optional kernel tests
     //
     // See comment in `boards/imix/src/main.rs`
     // virtual_uart_rx_test::run_virtual_uart_receive(mux_uart);
+    //
+    // GPIO interrupt latency, with AD_B1_02 connected to AD_B1_03 and both
+    // pins muxed to GPIO in the IOMUXC:
+    /*components::test::gpio_latency_test::GpioLatencyTestComponent::new(
+        mux_alarm,
+        peripherals.ports.pin(imxrt1050::gpio::PinId::AdB1_02),
+        peripherals.ports.pin(imxrt1050::gpio::PinId::AdB1_03),
+        100,
+    )
+    .finalize(components::gpio_latency_test_component_static!(
+        imxrt1050::gpt::Gpt1,
+        imxrt1050::gpio::Pin<'static>,
+        imxrt1050::gpio::Pin<'static>,
+    ))
+    .run();*/
+    //
+    // Throughput of the console LPUART, without DMA:
+    /*components::test::uart_throughput_test::UartThroughputTestComponent::new(
+        lpuart_mux,
+        mux_alarm,
+        2000,
+        "interrupt",
+    )
+    .finalize(components::uart_throughput_test_component_static!(
+        imxrt1050::gpt::Gpt1
+    ))
+    .run();*/
 
     //--------------------------------------------------------------------------
     // Process Console
@@ -498,8 +896,47 @@ pub unsafe fn main() {
     .finalize(components::process_console_component_static!(
         imxrt1050::gpt::Gpt1
     ));
+    let clock_tree = static_init!(
+        imxrt1050::clock_tree::CcmClockTree,
+        imxrt1050::clock_tree::CcmClockTree::new(peripherals.ccm, &peripherals.ccm_analog)
+    );
+    let clocks = components::clock_tree::ClockTreeCommandComponent::new(clock_tree)
+        .finalize(components::clock_tree_command_component_static!());
+    let _ = process_console.add_command(clocks);
+    let gpio_command = components::gpio_console::GpioCommandComponent::new(&peripherals.ports)
+        .finalize(components::gpio_command_component_static!());
+    let _ = process_console.add_command(gpio_command);
+    let _ = process_console.add_command(syscall_trace);
+    let _ = process_console.add_command(process_load_log);
+    // `reset bootloader` resets into the ROM serial downloader, to flash the
+    // board again over USB.
+    let reset_command = components::reset_command::ResetCommandComponent::new(&peripherals.src)
+        .finalize(components::reset_command_component_static!());
+    let _ = process_console.add_command(reset_command);
     let _ = process_console.start();
 
+    #[cfg(feature = "ethernet")]
+    {
+        // The same console, over TCP, for example with `nc tock-evkb.local 2323`.
+        let network_console =
+            components::process_console::ProcessConsoleComponent::new_with_stream(
+                board_kernel,
+                console_stream,
+                mux_alarm,
+                process_printer,
+                None,
+            )
+            .finalize(components::process_console_component_static!(
+                imxrt1050::gpt::Gpt1
+            ));
+        let _ = network_console.add_command(clocks);
+        let _ = network_console.add_command(gpio_command);
+        let _ = network_console.add_command(syscall_trace);
+        let _ = network_console.add_command(process_load_log);
+        let _ = network_console.add_command(reset_command);
+        let _ = network_console.start();
+    }
+
     debug!("Tock OS initialization complete. Entering main loop");
 
     extern "C" {
@@ -517,21 +954,26 @@ pub unsafe fn main() {
         static _eappmem: u8;
     }
 
-    kernel::process::load_processes(
-        board_kernel,
-        chip,
-        core::slice::from_raw_parts(
-            &_sapps as *const u8,
-            &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
-        ),
-        core::slice::from_raw_parts_mut(
-            &mut _sappmem as *mut u8,
-            &_eappmem as *const u8 as usize - &_sappmem as *const u8 as usize,
-        ),
-        &mut PROCESSES,
-        &FAULT_RESPONSE,
-        &process_management_capability,
+    // Processes are only loaded from the regions the linker reserved for
+    // them, which are checked first.
+    kernel::process::app_regions(
+        &_sapps as *const u8,
+        &_eapps as *const u8,
+        &mut _sappmem as *mut u8,
+        &_eappmem as *const u8,
     )
+    .and_then(|(app_flash, app_memory)| {
+        kernel::process::load_processes(
+            board_kernel,
+            &imxrt1050,
+            chip,
+            app_flash,
+            app_memory,
+            &mut PROCESSES,
+            &FAULT_RESPONSE,
+            &process_management_capability,
+        )
+    })
     .unwrap_or_else(|err| {
         debug!("Error loading processes!");
         debug!("{:?}", err);
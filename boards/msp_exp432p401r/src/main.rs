@@ -79,6 +79,7 @@ impl KernelResources<msp432::chip::Msp432<'static, msp432::chip::Msp432DefaultPe
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm4::systick::SysTick;
     type WatchDog = msp432::wdt::Wdt;
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -102,6 +103,9 @@ impl KernelResources<msp432::chip::Msp432<'static, msp432::chip::Msp432DefaultPe
     fn watchdog(&self) -> &Self::WatchDog {
         &self.wdt
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -450,6 +454,7 @@ pub unsafe fn main() {
 
     kernel::process::load_processes(
         board_kernel,
+        &msp_exp432p4014,
         chip,
         core::slice::from_raw_parts(
             &_sapps as *const u8,
@@ -22,6 +22,8 @@ use enum_primitive::cast::FromPrimitive;
 use kernel::component::Component;
 use kernel::debug;
 use kernel::hil::led::LedHigh;
+use kernel::hil::reset_reason::ResetReasonQuery;
+use kernel::hil::time::ConvertTicks;
 use kernel::hil::usb::Client;
 use kernel::platform::{KernelResources, SyscallDriverLookup};
 use kernel::scheduler::round_robin::RoundRobinSched;
@@ -40,6 +42,11 @@ use rp2040::resets::Peripheral;
 use rp2040::timer::RPTimer;
 mod io;
 
+#[cfg(all(feature = "gps", feature = "cellular_modem"))]
+compile_error!("The gps and cellular_modem features both use UART0.");
+#[cfg(all(feature = "sensor_aggregator", feature = "pedometer"))]
+compile_error!("The sensor_aggregator and pedometer features both read the LSM6DSOXTR.");
+
 use rp2040::sysinfo;
 
 mod flash_bootloader;
@@ -71,6 +78,15 @@ const FAULT_RESPONSE: kernel::process::PanicFaultPolicy = kernel::process::Panic
 // Number of concurrent processes this platform supports.
 const NUM_PROCS: usize = 4;
 
+/// Timers expiring this close to each other share a single wakeup.
+const ALARM_SLACK_MS: u32 = 2;
+
+/// Kernel debug output beyond this rate is dropped, so that it does not fill
+/// the debug buffer faster than the console empties it.
+const DEBUG_BYTES_PER_SECOND: usize = 8000;
+/// Period at which the amount of debug output dropped is reported.
+const DEBUG_DROP_REPORT_MS: u32 = 5000;
+
 static mut PROCESSES: [Option<&'static dyn kernel::process::Process>; NUM_PROCS] =
     [None; NUM_PROCS];
 
@@ -87,9 +103,43 @@ pub struct NanoRP2040Connect {
     >,
     gpio: &'static capsules_core::gpio::GPIO<'static, RPGpioPin<'static>>,
     led: &'static capsules_core::led::LedDriver<'static, LedHigh<'static, RPGpioPin<'static>>, 1>,
+    #[cfg(feature = "rgb_led")]
+    rgb_led: &'static capsules_extra::rgb_led::RgbLed<
+        'static,
+        capsules_core::virtualizers::virtual_pwm::PwmPinUser<'static, rp2040::pwm::Pwm<'static>>,
+        VirtualMuxAlarm<'static, RPTimer<'static>>,
+    >,
     adc: &'static capsules_core::adc::AdcVirtualized<'static>,
+    #[cfg(not(feature = "sensor_aggregator"))]
     temperature: &'static capsules_extra::temperature::TemperatureSensor<'static>,
+    #[cfg(not(any(feature = "sensor_aggregator", feature = "pedometer")))]
     ninedof: &'static capsules_extra::ninedof::NineDof<'static>,
+    #[cfg(feature = "cellular_modem")]
+    cellular_modem: &'static capsules_extra::cellular_modem::CellularModem<
+        'static,
+        rp2040::uart::Uart<'static>,
+        VirtualMuxAlarm<'static, RPTimer<'static>>,
+    >,
+    #[cfg(feature = "gps")]
+    location: &'static capsules_extra::location::LocationDriver<'static>,
+    #[cfg(feature = "power_manager")]
+    power_manager: &'static capsules_extra::power_manager::PowerManager<
+        'static,
+        VirtualMuxAlarm<'static, RPTimer<'static>>,
+    >,
+    #[cfg(feature = "sensor_aggregator")]
+    sensor_aggregator: &'static capsules_extra::sensor_aggregator::SensorAggregator<
+        'static,
+        VirtualMuxAlarm<'static, RPTimer<'static>>,
+    >,
+    #[cfg(feature = "pedometer")]
+    pedometer: &'static capsules_extra::pedometer::Pedometer<
+        'static,
+        VirtualMuxAlarm<'static, RPTimer<'static>>,
+    >,
+    #[cfg(feature = "pedometer")]
+    nonvolatile_storage:
+        &'static capsules_extra::nonvolatile_storage_driver::NonvolatileStorage<'static>,
     lsm6dsoxtr: &'static capsules_extra::lsm6dsoxtr::Lsm6dsoxtrI2C<
         'static,
         capsules_core::virtualizers::virtual_i2c::I2CDevice<
@@ -98,6 +148,12 @@ pub struct NanoRP2040Connect {
         >,
     >,
 
+    device_id: &'static capsules_extra::device_id::DeviceIdDriver<'static>,
+    boot_counter: &'static capsules_extra::boot_counter::BootCounter<
+        'static,
+        rp2040::watchdog::Watchdog<'static>,
+    >,
+
     scheduler: &'static RoundRobinSched<'static>,
     systick: cortexm0p::systick::SysTick,
 }
@@ -112,11 +168,31 @@ impl SyscallDriverLookup for NanoRP2040Connect {
             capsules_core::alarm::DRIVER_NUM => f(Some(self.alarm)),
             capsules_core::gpio::DRIVER_NUM => f(Some(self.gpio)),
             capsules_core::led::DRIVER_NUM => f(Some(self.led)),
+            #[cfg(feature = "rgb_led")]
+            capsules_extra::rgb_led::DRIVER_NUM => f(Some(self.rgb_led)),
             kernel::ipc::DRIVER_NUM => f(Some(&self.ipc)),
             capsules_core::adc::DRIVER_NUM => f(Some(self.adc)),
+            #[cfg(not(feature = "sensor_aggregator"))]
             capsules_extra::temperature::DRIVER_NUM => f(Some(self.temperature)),
             capsules_extra::lsm6dsoxtr::DRIVER_NUM => f(Some(self.lsm6dsoxtr)),
+            #[cfg(not(any(feature = "sensor_aggregator", feature = "pedometer")))]
             capsules_extra::ninedof::DRIVER_NUM => f(Some(self.ninedof)),
+            #[cfg(feature = "cellular_modem")]
+            capsules_extra::cellular_modem::DRIVER_NUM => f(Some(self.cellular_modem)),
+            #[cfg(feature = "gps")]
+            capsules_extra::location::DRIVER_NUM => f(Some(self.location)),
+            #[cfg(feature = "power_manager")]
+            capsules_extra::power_manager::DRIVER_NUM => f(Some(self.power_manager)),
+            #[cfg(feature = "sensor_aggregator")]
+            capsules_extra::sensor_aggregator::DRIVER_NUM => f(Some(self.sensor_aggregator)),
+            #[cfg(feature = "pedometer")]
+            capsules_extra::pedometer::DRIVER_NUM => f(Some(self.pedometer)),
+            #[cfg(feature = "pedometer")]
+            capsules_extra::nonvolatile_storage_driver::DRIVER_NUM => {
+                f(Some(self.nonvolatile_storage))
+            }
+            capsules_extra::boot_counter::DRIVER_NUM => f(Some(self.boot_counter)),
+            capsules_extra::device_id::DRIVER_NUM => f(Some(self.device_id)),
             _ => f(None),
         }
     }
@@ -130,6 +206,7 @@ impl KernelResources<Rp2040<'static, Rp2040DefaultPeripherals<'static>>> for Nan
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm0p::systick::SysTick;
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -153,6 +230,9 @@ impl KernelResources<Rp2040<'static, Rp2040DefaultPeripherals<'static>>> for Nan
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -331,6 +411,7 @@ pub unsafe fn main() {
 
     let mux_alarm = components::alarm::AlarmMuxComponent::new(&peripherals.timer)
         .finalize(components::alarm_mux_component_static!(RPTimer));
+    mux_alarm.set_slack(peripherals.timer.ticks_from_ms(ALARM_SLACK_MS));
 
     let alarm = components::alarm::AlarmDriverComponent::new(
         board_kernel,
@@ -339,6 +420,29 @@ pub unsafe fn main() {
     )
     .finalize(components::alarm_component_static!(RPTimer));
 
+    // DEVICE ID
+
+    // The RP2040 has no unique ID of its own; only the board is identified.
+    let device_id = components::device_id::DeviceIdComponent::new(
+        board_kernel,
+        capsules_extra::device_id::DRIVER_NUM,
+        None,
+        "nano_rp2040_connect",
+        "ABX00053",
+    )
+    .finalize(components::device_id_component_static!());
+
+    // BOOT COUNTER
+
+    let boot_counter = components::boot_counter::BootCounterComponent::new(
+        &peripherals.watchdog,
+        0,
+        peripherals.watchdog.reset_reason(),
+    )
+    .finalize(components::boot_counter_component_static!(
+        rp2040::watchdog::Watchdog<'static>
+    ));
+
     // CDC
     let strings = static_init!(
         [&str; 3],
@@ -366,6 +470,9 @@ pub unsafe fn main() {
 
     // UART
     // Create a shared UART channel for kernel debug.
+    // Use `UartMuxComponent::new_framed` instead to tag the output of the
+    // kernel, of the process console and of each process with its channel,
+    // and read it with `tools/console_demux.py`.
     let uart_mux = components::console::UartMuxComponent::new(cdc, 115200)
         .finalize(components::uart_mux_component_static!());
 
@@ -383,17 +490,59 @@ pub unsafe fn main() {
         uart_mux,
     )
     .finalize(components::console_component_static!());
+
+    // The `gps` feature reads a u-blox GPS receiver on UART0 (the TX and RX
+    // pins).
+    #[cfg(feature = "gps")]
+    let location = {
+        let nmea = components::nmea::NmeaComponent::new(&peripherals.uart0, 9600).finalize(
+            components::nmea_component_static!(rp2040::uart::Uart<'static>),
+        );
+        components::location::LocationComponent::new(
+            board_kernel,
+            capsules_extra::location::DRIVER_NUM,
+            nmea,
+        )
+        .finalize(components::location_component_static!())
+    };
+
+    // The `cellular_modem` feature reaches the network through a SIM800 or
+    // Quectel cellular modem on the same pins instead, where the WiFi is out
+    // of range.
+    #[cfg(feature = "cellular_modem")]
+    let cellular_modem = {
+        let at_engine =
+            components::at_engine::AtEngineComponent::new(&peripherals.uart0, mux_alarm, 115200)
+                .finalize(components::at_engine_component_static!(
+                    rp2040::uart::Uart<'static>,
+                    RPTimer<'static>,
+                ));
+        components::cellular_modem::CellularModemComponent::new(
+            board_kernel,
+            capsules_extra::cellular_modem::DRIVER_NUM,
+            at_engine,
+            capsules_extra::cellular_modem::Dialect::Sim800,
+        )
+        .finalize(components::cellular_modem_component_static!(
+            rp2040::uart::Uart<'static>,
+            RPTimer<'static>,
+        ))
+    };
+
     // Create the debugger object that handles calls to `debug!()`.
     components::debug_writer::DebugWriterComponent::new(uart_mux)
         .finalize(components::debug_writer_component_static!());
+    components::debug_rate_limit::DebugRateLimitComponent::new(
+        mux_alarm,
+        DEBUG_BYTES_PER_SECOND,
+        DEBUG_DROP_REPORT_MS,
+    )
+    .finalize(components::debug_rate_limit_component_static!(RPTimer));
 
     cdc.enable();
     cdc.attach();
 
-    let gpio = GpioComponent::new(
-        board_kernel,
-        capsules_core::gpio::DRIVER_NUM,
-        components::gpio_component_helper!(
+    let gpio_pins = components::gpio_component_helper!(
             RPGpioPin,
             // Used for serial communication. Comment them in if you don't use serial.
             // 0 => &peripherals.pins.get_pin(RPGpio::GPIO0),
@@ -429,15 +578,61 @@ pub unsafe fn main() {
             // 27 => &peripherals.pins.get_pin(RPGpio::GPIO27),
             // 28 => &peripherals.pins.get_pin(RPGpio::GPIO28),
             // 29 => &peripherals.pins.get_pin(RPGpio::GPIO29)
-        ),
-    )
-    .finalize(components::gpio_component_static!(RPGpioPin<'static>));
+    );
+    // Used for the RGB LED.
+    #[cfg(feature = "rgb_led")]
+    for pin in 15..=17 {
+        gpio_pins[pin] = None;
+    }
+    // Used to reset the NINA module.
+    #[cfg(feature = "power_manager")]
+    {
+        gpio_pins[3] = None;
+    }
+    // Used to wake the chip up from dormant.
+    #[cfg(feature = "dormant")]
+    {
+        gpio_pins[18] = None;
+    }
+    let gpio = GpioComponent::new(board_kernel, capsules_core::gpio::DRIVER_NUM, gpio_pins)
+        .finalize(components::gpio_component_static!(RPGpioPin<'static>));
 
     let led = LedsComponent::new().finalize(components::led_component_static!(
         LedHigh<'static, RPGpioPin<'static>>,
         LedHigh::new(&peripherals.pins.get_pin(RPGpio::GPIO6))
     ));
 
+    // The RGB LED of the board is driven by the NINA module. The `rgb_led`
+    // feature drives an RGB LED wired to D3, D4 and D5 instead.
+    #[cfg(feature = "rgb_led")]
+    let rgb_led = {
+        for pin in [RPGpio::GPIO15, RPGpio::GPIO16, RPGpio::GPIO17] {
+            peripherals
+                .pins
+                .get_pin(pin)
+                .set_function(GpioFunction::PWM);
+        }
+        let mux_pwm = components::pwm::PwmMuxComponent::new(&peripherals.pwm)
+            .finalize(components::pwm_mux_component_static!(rp2040::pwm::Pwm));
+        let red = components::pwm::PwmPinUserComponent::new(mux_pwm, RPGpio::GPIO15)
+            .finalize(components::pwm_pin_user_component_static!(rp2040::pwm::Pwm));
+        let green = components::pwm::PwmPinUserComponent::new(mux_pwm, RPGpio::GPIO16)
+            .finalize(components::pwm_pin_user_component_static!(rp2040::pwm::Pwm));
+        let blue = components::pwm::PwmPinUserComponent::new(mux_pwm, RPGpio::GPIO17)
+            .finalize(components::pwm_pin_user_component_static!(rp2040::pwm::Pwm));
+        components::rgb_led::RgbLedComponent::new(
+            board_kernel,
+            capsules_extra::rgb_led::DRIVER_NUM,
+            [red, green, blue],
+            mux_alarm,
+            false,
+        )
+        .finalize(components::rgb_led_component_static!(
+            capsules_core::virtualizers::virtual_pwm::PwmPinUser<'static, rp2040::pwm::Pwm>,
+            RPTimer<'static>,
+        ))
+    };
+
     peripherals.adc.init();
 
     let adc_mux = components::adc::AdcMuxComponent::new(&peripherals.adc)
@@ -473,25 +668,34 @@ pub unsafe fn main() {
         rp2040::i2c::I2c<'static, 'static>
     ));
 
+    #[cfg(not(any(feature = "sensor_aggregator", feature = "pedometer")))]
     let ninedof = components::ninedof::NineDofComponent::new(
         board_kernel,
         capsules_extra::ninedof::DRIVER_NUM,
     )
     .finalize(components::ninedof_component_static!(lsm6dsoxtr));
 
-    let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
-    let grant_temperature =
-        board_kernel.create_grant(capsules_extra::temperature::DRIVER_NUM, &grant_cap);
+    #[cfg(not(feature = "sensor_aggregator"))]
+    let temp = {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+        let grant_temperature =
+            board_kernel.create_grant(capsules_extra::temperature::DRIVER_NUM, &grant_cap);
 
-    let temp = static_init!(
-        capsules_extra::temperature::TemperatureSensor<'static>,
-        capsules_extra::temperature::TemperatureSensor::new(temp_sensor, grant_temperature)
-    );
+        static_init!(
+            capsules_extra::temperature::TemperatureSensor<'static>,
+            capsules_extra::temperature::TemperatureSensor::new(temp_sensor, grant_temperature)
+        )
+    };
 
+    // The pedometer reads the accelerometer at 25 Hz.
+    #[cfg(not(feature = "pedometer"))]
+    let accel_rate = capsules_extra::lsm6dsoxtr::LSM6DSOXAccelDataRate::LSM6DSOX_ACCEL_RATE_12_5_HZ;
+    #[cfg(feature = "pedometer")]
+    let accel_rate = capsules_extra::lsm6dsoxtr::LSM6DSOXAccelDataRate::LSM6DSOX_ACCEL_RATE_26_HZ;
     let _ = lsm6dsoxtr
         .configure(
             capsules_extra::lsm6dsoxtr::LSM6DSOXGyroDataRate::LSM6DSOX_GYRO_RATE_12_5_HZ,
-            capsules_extra::lsm6dsoxtr::LSM6DSOXAccelDataRate::LSM6DSOX_ACCEL_RATE_12_5_HZ,
+            accel_rate,
             capsules_extra::lsm6dsoxtr::LSM6DSOXAccelRange::LSM6DSOX_ACCEL_RANGE_2_G,
             capsules_extra::lsm6dsoxtr::LSM6DSOXTRGyroRange::LSM6DSOX_GYRO_RANGE_250_DPS,
             true,
@@ -512,8 +716,94 @@ pub unsafe fn main() {
     //     capsules_extra::temperature::TemperatureSensor::new(lsm6dsoxtr, grant_temperature)
     // );
 
+    #[cfg(not(feature = "sensor_aggregator"))]
     kernel::hil::sensors::TemperatureDriver::set_client(temp_sensor, temp);
 
+    // The `sensor_aggregator` feature samples the temperature and the
+    // LSM6DSOXTR periodically into batches of records for userspace. The
+    // aggregator becomes the client of the sensors, in place of the
+    // temperature and 9DOF drivers.
+    #[cfg(feature = "sensor_aggregator")]
+    let sensor_aggregator = components::sensor_aggregator::SensorAggregatorComponent::new(
+        board_kernel,
+        capsules_extra::sensor_aggregator::DRIVER_NUM,
+        mux_alarm,
+        Some(temp_sensor),
+        Some(lsm6dsoxtr),
+        None,
+    )
+    .finalize(components::sensor_aggregator_component_static!(RPTimer));
+
+    // The `pedometer` feature counts steps with the LSM6DSOXTR, and stores
+    // the count of the day in the last 4 KiB of the flash. The pedometer
+    // becomes the client of the accelerometer, in place of the 9DOF driver.
+    #[cfg(feature = "pedometer")]
+    let nonvolatile_storage = components::nonvolatile_storage::NonvolatileStorageComponent::new(
+        board_kernel,
+        capsules_extra::nonvolatile_storage_driver::DRIVER_NUM,
+        &peripherals.flash,
+        0xC0000,  // Start address for userspace accessible region
+        0xF3F000, // Length of userspace accessible region
+        0xFFF000, // Start address of kernel region
+        0x1000,   // Length of kernel region
+        &[],      // All apps share the userspace region
+    )
+    .finalize(components::nonvolatile_storage_component_static!(
+        rp2040::flash::Flash
+    ));
+    #[cfg(feature = "pedometer")]
+    let pedometer = components::pedometer::PedometerComponent::new(
+        board_kernel,
+        capsules_extra::pedometer::DRIVER_NUM,
+        mux_alarm,
+        lsm6dsoxtr,
+        nonvolatile_storage,
+        0xFFF000,
+    )
+    .finalize(components::pedometer_component_static!(RPTimer));
+
+    // The `power_manager` feature notifies an application of the sample
+    // and upload windows of a battery-powered node, and powers the NINA WiFi
+    // module down outside of the upload windows by holding its RESETN pin,
+    // GPIO3, low.
+    #[cfg(feature = "power_manager")]
+    let power_manager = {
+        let nina_reset = static_init!(
+            capsules_extra::power_manager::GpioPowerDomain<'static, RPGpioPin<'static>>,
+            capsules_extra::power_manager::GpioPowerDomain::new(
+                peripherals.pins.get_pin(RPGpio::GPIO3),
+                false,
+            )
+        );
+        let radio_domains = static_init!(
+            [&'static dyn capsules_extra::power_manager::PowerDomain; 1],
+            [nina_reset]
+        );
+        components::power_manager::PowerManagerComponent::new(
+            board_kernel,
+            capsules_extra::power_manager::DRIVER_NUM,
+            mux_alarm,
+            &[],
+            radio_domains,
+        )
+        .finalize(components::power_manager_component_static!(RPTimer))
+    };
+    // The `dormant` feature also lets the chip go dormant between windows,
+    // waking up on a falling edge of the alarm output of an external real
+    // time clock on D6 (GPIO18).
+    #[cfg(feature = "dormant")]
+    {
+        let dormant = static_init!(
+            rp2040::dormant::Dormant<'static>,
+            rp2040::dormant::Dormant::new(&peripherals.clocks, &peripherals.xosc)
+        );
+        let rtc_alarm = peripherals.pins.get_pin(RPGpio::GPIO18);
+        kernel::hil::gpio::Configure::make_input(rtc_alarm);
+        dormant.set_wake_pin(rtc_alarm, kernel::hil::gpio::InterruptEdge::FallingEdge);
+        chip.set_dormant(dormant);
+        power_manager.set_deep_sleep(dormant);
+    }
+
     let adc_channel_0 = components::adc::AdcComponent::new(&adc_mux, Channel::Channel0)
         .finalize(components::adc_component_static!(Adc));
 
@@ -548,6 +838,12 @@ pub unsafe fn main() {
         Some(reset_function),
     )
     .finalize(components::process_console_component_static!(RPTimer));
+    // `reset bootloader` reboots into the UF2 bootloader, as if BOOTSEL was
+    // held.
+    let reset_command =
+        components::reset_command::ResetCommandComponent::new(&peripherals.watchdog)
+            .finalize(components::reset_command_component_static!());
+    let _ = process_console.add_command(reset_command);
     let _ = process_console.start();
 
     let scheduler = components::sched::round_robin::RoundRobinComponent::new(&PROCESSES)
@@ -562,12 +858,30 @@ pub unsafe fn main() {
         alarm: alarm,
         gpio: gpio,
         led: led,
+        #[cfg(feature = "rgb_led")]
+        rgb_led,
         console: console,
         adc: adc_syscall,
+        #[cfg(not(feature = "sensor_aggregator"))]
         temperature: temp,
 
         lsm6dsoxtr: lsm6dsoxtr,
+        #[cfg(not(any(feature = "sensor_aggregator", feature = "pedometer")))]
         ninedof: ninedof,
+        #[cfg(feature = "cellular_modem")]
+        cellular_modem,
+        #[cfg(feature = "gps")]
+        location,
+        #[cfg(feature = "power_manager")]
+        power_manager,
+        #[cfg(feature = "sensor_aggregator")]
+        sensor_aggregator,
+        #[cfg(feature = "pedometer")]
+        pedometer,
+        #[cfg(feature = "pedometer")]
+        nonvolatile_storage,
+        device_id,
+        boot_counter,
 
         scheduler,
         systick: cortexm0p::systick::SysTick::new_with_calibration(125_000_000),
@@ -597,21 +911,26 @@ pub unsafe fn main() {
         static _eappmem: u8;
     }
 
-    kernel::process::load_processes(
-        board_kernel,
-        chip,
-        core::slice::from_raw_parts(
-            &_sapps as *const u8,
-            &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
-        ),
-        core::slice::from_raw_parts_mut(
-            &mut _sappmem as *mut u8,
-            &_eappmem as *const u8 as usize - &_sappmem as *const u8 as usize,
-        ),
-        &mut PROCESSES,
-        &FAULT_RESPONSE,
-        &process_management_capability,
+    // Processes are only loaded from the regions the linker reserved for
+    // them, which are checked first.
+    kernel::process::app_regions(
+        &_sapps as *const u8,
+        &_eapps as *const u8,
+        &mut _sappmem as *mut u8,
+        &_eappmem as *const u8,
     )
+    .and_then(|(app_flash, app_memory)| {
+        kernel::process::load_processes(
+            board_kernel,
+            &nano_rp2040_connect,
+            chip,
+            app_flash,
+            app_memory,
+            &mut PROCESSES,
+            &FAULT_RESPONSE,
+            &process_management_capability,
+        )
+    })
     .unwrap_or_else(|err| {
         debug!("Error loading processes!");
         debug!("{:?}", err);
@@ -76,6 +76,7 @@
 use capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm;
 use capsules_extra::net::ieee802154::MacAddress;
 use capsules_extra::net::ipv6::ip_utils::IPAddr;
+use capsules_extra::nonvolatile_storage_driver::AppRegion;
 use kernel::component::Component;
 use kernel::hil::led::LedLow;
 use kernel::hil::time::Counter;
@@ -120,6 +121,21 @@ const SPI_MX25R6435F_HOLD_PIN: Pin = Pin::P0_23;
 const I2C_SDA_PIN: Pin = Pin::P0_26;
 const I2C_SCL_PIN: Pin = Pin::P0_27;
 
+// Regions of the external flash reserved for the apps with `write_id` 1 and
+// 2, from the start of the userspace region. Other apps have no access.
+const NONVOLATILE_STORAGE_APP_REGIONS: &[AppRegion] = &[
+    AppRegion {
+        write_id: 0x1,
+        offset: 0,
+        length: 0x10000,
+    },
+    AppRegion {
+        write_id: 0x2,
+        offset: 0x10000,
+        length: 0x10000,
+    },
+];
+
 // Constants related to the configuration of the 15.4 network stack
 const PAN_ID: u16 = 0xABCD;
 const DST_MAC_ADDR: capsules_extra::net::ieee802154::MacAddress =
@@ -269,6 +285,7 @@ impl KernelResources<nrf52840::chip::NRF52<'static, Nrf52840DefaultPeripherals<'
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm4::systick::SysTick;
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -292,6 +309,9 @@ impl KernelResources<nrf52840::chip::NRF52<'static, Nrf52840DefaultPeripherals<'
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -667,6 +687,7 @@ pub unsafe fn main() {
         0x3FA0000, // Length of userspace accessible region
         0,         // Start address of kernel region
         0x60000,   // Length of kernel region
+        NONVOLATILE_STORAGE_APP_REGIONS,
     )
     .finalize(components::nonvolatile_storage_component_static!(
         capsules_extra::mx25r6435f::MX25R6435F<
@@ -832,6 +853,7 @@ pub unsafe fn main() {
 
     kernel::process::load_processes(
         board_kernel,
+        &platform,
         chip,
         core::slice::from_raw_parts(
             &_sapps as *const u8,
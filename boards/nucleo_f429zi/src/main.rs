@@ -17,8 +17,9 @@ use components::gpio::GpioComponent;
 use kernel::capabilities;
 use kernel::component::Component;
 use kernel::hil::led::LedHigh;
-use kernel::platform::{KernelResources, SyscallDriverLookup};
+use kernel::platform::{KernelHooks, KernelResources, SyscallDriverLookup};
 use kernel::scheduler::round_robin::RoundRobinSched;
+use kernel::trace::TraceSink;
 use kernel::{create_capability, debug, static_init};
 
 use stm32f429zi::gpio::{AlternateFunction, Mode, PinId, PortId};
@@ -30,6 +31,9 @@ pub mod io;
 // Number of concurrent processes this platform supports.
 const NUM_PROCS: usize = 4;
 
+/// Baud rate of the SWO trace output, from the 16 MHz core clock.
+const SWO_BAUD_RATE: u32 = 2_000_000;
+
 // Actual memory for holding the active process structures.
 static mut PROCESSES: [Option<&'static dyn kernel::process::Process>; NUM_PROCS] =
     [None, None, None, None];
@@ -79,6 +83,8 @@ struct NucleoF429ZI {
     scheduler: &'static RoundRobinSched<'static>,
     systick: cortexm4::systick::SysTick,
     can: &'static capsules_extra::can::CanCapsule<'static, stm32f429zi::can::Can<'static>>,
+    crc: &'static capsules_extra::crc::CrcDriver<'static, stm32f429zi::crc::Crc<'static>>,
+    itm_trace: &'static cortexm4::itm::ItmTrace,
 }
 
 /// Mapping of integer syscalls to objects that implement syscalls.
@@ -98,11 +104,18 @@ impl SyscallDriverLookup for NucleoF429ZI {
             capsules_core::gpio::DRIVER_NUM => f(Some(self.gpio)),
             capsules_core::rng::DRIVER_NUM => f(Some(self.rng)),
             capsules_extra::can::DRIVER_NUM => f(Some(self.can)),
+            capsules_extra::crc::DRIVER_NUM => f(Some(self.crc)),
             _ => f(None),
         }
     }
 }
 
+impl KernelHooks for NucleoF429ZI {
+    fn trace_sink(&self) -> Option<&'static dyn TraceSink> {
+        Some(self.itm_trace)
+    }
+}
+
 impl
     KernelResources<
         stm32f429zi::chip::Stm32f4xx<
@@ -118,6 +131,7 @@ impl
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm4::systick::SysTick;
     type WatchDog = ();
+    type KernelHooks = Self;
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -141,6 +155,9 @@ impl
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        self
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -375,6 +392,13 @@ pub unsafe fn main() {
     components::debug_writer::DebugWriterComponent::new(uart_mux)
         .finalize(components::debug_writer_component_static!());
 
+    // Send trace points to the ITM. The ST-LINK reads them from the SWO pin
+    // (PB3), which is in its trace function after reset.
+    stm32f429zi::dbg::Dbg::new().enable_async_trace();
+    cortexm4::itm::configure_swo(16_000_000, SWO_BAUD_RATE);
+    let itm_trace = static_init!(cortexm4::itm::ItmTrace, cortexm4::itm::ItmTrace::new());
+    itm_trace.enable();
+
     // LEDs
 
     // Clock to Port A is enabled in `set_pin_primary_functions()`
@@ -593,6 +617,16 @@ pub unsafe fn main() {
         stm32f429zi::can::Can<'static>
     ));
 
+    // CRC
+    let crc = components::crc::CrcComponent::new(
+        board_kernel,
+        capsules_extra::crc::DRIVER_NUM,
+        &base_peripherals.crc,
+    )
+    .finalize(components::crc_component_static!(
+        stm32f429zi::crc::Crc<'static>
+    ));
+
     // PROCESS CONSOLE
     let process_console = components::process_console::ProcessConsoleComponent::new(
         board_kernel,
@@ -627,6 +661,8 @@ pub unsafe fn main() {
         scheduler,
         systick: cortexm4::systick::SysTick::new(),
         can: can,
+        crc: crc,
+        itm_trace,
     };
 
     // // Optional kernel tests
@@ -650,6 +686,7 @@ pub unsafe fn main() {
 
     kernel::process::load_processes(
         board_kernel,
+        &nucleo_f429zi,
         chip,
         core::slice::from_raw_parts(
             &_sapps as *const u8,
@@ -121,6 +121,7 @@ impl
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm4::systick::SysTick;
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -144,6 +145,9 @@ impl
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -542,6 +546,7 @@ pub unsafe fn main() {
 
     kernel::process::load_processes(
         board_kernel,
+        &nucleo_f446re,
         chip,
         core::slice::from_raw_parts(
             &_sapps as *const u8,
@@ -224,6 +224,7 @@ impl KernelResources<earlgrey::chip::EarlGrey<'static, EarlGreyDefaultPeripheral
     type SchedulerTimer =
         VirtualSchedulerTimer<VirtualMuxAlarm<'static, earlgrey::timer::RvTimer<'static>>>;
     type WatchDog = lowrisc::aon_timer::AonTimer;
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -247,6 +248,9 @@ impl KernelResources<earlgrey::chip::EarlGrey<'static, EarlGreyDefaultPeripheral
     fn watchdog(&self) -> &Self::WatchDog {
         &self.watchdog
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -818,6 +822,7 @@ unsafe fn setup() -> (
 
     kernel::process::load_processes(
         board_kernel,
+        earlgrey,
         chip,
         core::slice::from_raw_parts(
             &_sapps as *const u8,
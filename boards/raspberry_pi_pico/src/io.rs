@@ -10,6 +10,7 @@ use kernel::hil::led::LedHigh;
 use kernel::hil::uart::{Configure, Parameters, Parity, StopBits, Width};
 use kernel::utilities::cells::OptionalCell;
 
+use capsules_extra::segger_rtt::SeggerRttMemory;
 use rp2040::gpio::{GpioFunction, RPGpio, RPGpioPin};
 use rp2040::uart::Uart;
 
@@ -17,9 +18,11 @@ use crate::CHIP;
 use crate::PROCESSES;
 use crate::PROCESS_PRINTER;
 
-/// Writer is used by kernel::debug to panic message to the serial port.
+/// Writer is used by kernel::debug to panic message to the serial port, or
+/// to the Segger RTT up buffer if the console uses RTT.
 pub struct Writer {
     uart: OptionalCell<&'static Uart<'static>>,
+    rtt_memory: OptionalCell<&'static SeggerRttMemory<'static>>,
 }
 
 impl Writer {
@@ -27,6 +30,30 @@ impl Writer {
         self.uart.set(uart);
     }
 
+    /// Set the RTT memory buffer used to output panic messages.
+    pub fn set_rtt_memory(&self, rtt_memory: &'static SeggerRttMemory<'static>) {
+        self.rtt_memory.set(rtt_memory);
+    }
+
+    fn write_to_rtt(&self, rtt_memory: &SeggerRttMemory, buf: &[u8]) {
+        let up_buffer = unsafe { &*rtt_memory.get_up_buffer_ptr() };
+        let buffer_len = up_buffer.length.get();
+        let buffer = unsafe {
+            core::slice::from_raw_parts_mut(up_buffer.buffer.get() as *mut u8, buffer_len as usize)
+        };
+
+        let mut write_position = up_buffer.write_position.get();
+        for &c in buf {
+            buffer[write_position as usize] = c;
+            write_position = (write_position + 1) % buffer_len;
+            up_buffer.write_position.set(write_position);
+            // Give the probe some time to read the buffer.
+            for _ in 0..100 {
+                cortexm0p::support::nop();
+            }
+        }
+    }
+
     fn configure_uart<'a>(&self, uart: &'a Uart) {
         if !uart.is_configured() {
             let parameters = Parameters {
@@ -56,6 +83,7 @@ impl Writer {
 /// Global static for debug writer
 pub static mut WRITER: Writer = Writer {
     uart: OptionalCell::empty(),
+    rtt_memory: OptionalCell::empty(),
 };
 
 impl Write for Writer {
@@ -67,6 +95,10 @@ impl Write for Writer {
 
 impl IoWrite for Writer {
     fn write(&mut self, buf: &[u8]) -> usize {
+        if let Some(rtt_memory) = self.rtt_memory.extract() {
+            self.write_to_rtt(rtt_memory, buf);
+            return buf.len();
+        }
         self.uart.map_or_else(
             || {
                 let uart = Uart::new_uart0();
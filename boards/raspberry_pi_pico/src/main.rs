@@ -15,8 +15,11 @@
 
 use core::arch::asm;
 
+use capsules_aes_gcm::aes_gcm;
 use capsules_core::i2c_master::I2CMasterDriver;
+use capsules_core::virtualizers::virtual_aes_ccm;
 use capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm;
+use capsules_extra::symmetric_encryption::aes_fallback::Aes128Fallback;
 use components::gpio::GpioComponent;
 use components::led::LedsComponent;
 use enum_primitive::cast::FromPrimitive;
@@ -25,6 +28,8 @@ use kernel::debug;
 use kernel::hil::gpio::{Configure, FloatingState};
 use kernel::hil::i2c::I2CMaster;
 use kernel::hil::led::LedHigh;
+use kernel::hil::symmetric_encryption::{AES128, AES128GCM, AES128_BLOCK_SIZE};
+use kernel::hil::time::ConvertTicks;
 use kernel::hil::usb::Client;
 use kernel::platform::{KernelResources, SyscallDriverLookup};
 use kernel::scheduler::round_robin::RoundRobinSched;
@@ -47,6 +52,13 @@ use rp2040::timer::RPTimer;
 
 mod io;
 
+#[cfg(any(
+    all(feature = "lin", feature = "modbus_rtu"),
+    all(feature = "lin", feature = "bluetooth_serial"),
+    all(feature = "modbus_rtu", feature = "bluetooth_serial"),
+))]
+compile_error!("Only one of the lin, modbus_rtu and bluetooth_serial features can use UART1.");
+
 mod flash_bootloader;
 
 /// Allocate memory for the stack
@@ -76,6 +88,15 @@ const FAULT_RESPONSE: kernel::process::PanicFaultPolicy = kernel::process::Panic
 // Number of concurrent processes this platform supports.
 const NUM_PROCS: usize = 4;
 
+/// Timers expiring this close to each other share a single wakeup.
+const ALARM_SLACK_MS: u32 = 2;
+
+// Whether the console and kernel debug output use USB CDC or Segger RTT.
+// - Set to false to use USB CDC.
+// - Set to true to use Segger RTT through a debug probe, for example a second
+//   Pico running picoprobe with `probe-rs`.
+const RTT_CONSOLE: bool = false;
+
 static mut PROCESSES: [Option<&'static dyn kernel::process::Process>; NUM_PROCS] =
     [None; NUM_PROCS];
 
@@ -95,6 +116,45 @@ pub struct RaspberryPiPico {
     adc: &'static capsules_core::adc::AdcVirtualized<'static>,
     temperature: &'static capsules_extra::temperature::TemperatureSensor<'static>,
     i2c: &'static capsules_core::i2c_master::I2CMasterDriver<'static, I2c<'static, 'static>>,
+    crc: &'static capsules_extra::crc::CrcDriver<'static, rp2040::crc::Crc<'static>>,
+    aes: &'static capsules_extra::symmetric_encryption::aes::AesDriver<
+        'static,
+        aes_gcm::Aes128Gcm<
+            'static,
+            virtual_aes_ccm::VirtualAES128CCM<'static, Aes128Fallback<'static>>,
+        >,
+    >,
+    nonvolatile_storage:
+        &'static capsules_extra::nonvolatile_storage_driver::NonvolatileStorage<'static>,
+    pwm: &'static capsules_extra::pwm::Pwm<'static, 4>,
+    #[cfg(feature = "bluetooth_serial")]
+    bluetooth_serial: &'static capsules_extra::bluetooth_serial::BluetoothSerial<
+        'static,
+        rp2040::uart::Uart<'static>,
+        VirtualMuxAlarm<'static, RPTimer<'static>>,
+    >,
+    #[cfg(feature = "ir_remote")]
+    ir_remote: &'static capsules_extra::ir_remote::IrRemote<
+        'static,
+        VirtualMuxAlarm<'static, RPTimer<'static>>,
+    >,
+    #[cfg(feature = "lin")]
+    lin: &'static capsules_extra::lin::Lin<
+        'static,
+        rp2040::uart::Uart<'static>,
+        VirtualMuxAlarm<'static, RPTimer<'static>>,
+    >,
+    #[cfg(feature = "modbus_rtu")]
+    modbus_rtu: &'static capsules_extra::modbus_rtu::ModbusRtu<
+        'static,
+        rp2040::uart::Uart<'static>,
+        VirtualMuxAlarm<'static, RPTimer<'static>>,
+    >,
+    #[cfg(feature = "power_manager")]
+    power_manager: &'static capsules_extra::power_manager::PowerManager<
+        'static,
+        VirtualMuxAlarm<'static, RPTimer<'static>>,
+    >,
 
     scheduler: &'static RoundRobinSched<'static>,
     systick: cortexm0p::systick::SysTick,
@@ -114,6 +174,22 @@ impl SyscallDriverLookup for RaspberryPiPico {
             capsules_core::adc::DRIVER_NUM => f(Some(self.adc)),
             capsules_extra::temperature::DRIVER_NUM => f(Some(self.temperature)),
             capsules_core::i2c_master::DRIVER_NUM => f(Some(self.i2c)),
+            capsules_extra::crc::DRIVER_NUM => f(Some(self.crc)),
+            capsules_extra::symmetric_encryption::aes::DRIVER_NUM => f(Some(self.aes)),
+            capsules_extra::nonvolatile_storage_driver::DRIVER_NUM => {
+                f(Some(self.nonvolatile_storage))
+            }
+            capsules_extra::pwm::DRIVER_NUM => f(Some(self.pwm)),
+            #[cfg(feature = "bluetooth_serial")]
+            capsules_extra::bluetooth_serial::DRIVER_NUM => f(Some(self.bluetooth_serial)),
+            #[cfg(feature = "ir_remote")]
+            capsules_extra::ir_remote::DRIVER_NUM => f(Some(self.ir_remote)),
+            #[cfg(feature = "lin")]
+            capsules_extra::lin::DRIVER_NUM => f(Some(self.lin)),
+            #[cfg(feature = "modbus_rtu")]
+            capsules_extra::modbus_rtu::DRIVER_NUM => f(Some(self.modbus_rtu)),
+            #[cfg(feature = "power_manager")]
+            capsules_extra::power_manager::DRIVER_NUM => f(Some(self.power_manager)),
             _ => f(None),
         }
     }
@@ -127,6 +203,7 @@ impl KernelResources<Rp2040<'static, Rp2040DefaultPeripherals<'static>>> for Ras
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm0p::systick::SysTick;
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -150,6 +227,9 @@ impl KernelResources<Rp2040<'static, Rp2040DefaultPeripherals<'static>>> for Ras
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -298,6 +378,19 @@ pub unsafe fn main() {
     // Set the UART used for panic
     io::WRITER.set_uart(&peripherals.uart0);
 
+    // Initialize early so any panic beyond this point can use the RTT memory
+    // object.
+    let rtt_memory_refs = if RTT_CONSOLE {
+        let mut rtt_memory_refs = components::segger_rtt::SeggerRttMemoryComponent::new()
+            .finalize(components::segger_rtt_memory_component_static!());
+        // The panic handler only reads the up buffer description, see
+        // `io::Writer`.
+        io::WRITER.set_rtt_memory(&*rtt_memory_refs.get_rtt_memory_ptr());
+        Some(rtt_memory_refs)
+    } else {
+        None
+    };
+
     //set RX and TX pins in UART mode
     let gpio_tx = peripherals.pins.get_pin(RPGpio::GPIO0);
     let gpio_rx = peripherals.pins.get_pin(RPGpio::GPIO1);
@@ -328,6 +421,7 @@ pub unsafe fn main() {
 
     let mux_alarm = components::alarm::AlarmMuxComponent::new(&peripherals.timer)
         .finalize(components::alarm_mux_component_static!(RPTimer));
+    mux_alarm.set_slack(peripherals.timer.ticks_from_ms(ALARM_SLACK_MS));
 
     let alarm = components::alarm::AlarmDriverComponent::new(
         board_kernel,
@@ -363,7 +457,14 @@ pub unsafe fn main() {
 
     // UART
     // Create a shared UART channel for kernel debug.
-    let uart_mux = components::console::UartMuxComponent::new(cdc, 115200)
+    let console_uart: &'static dyn kernel::hil::uart::Uart<'static> = match rtt_memory_refs {
+        Some(rtt_memory_refs) => {
+            components::segger_rtt::SeggerRttComponent::new(mux_alarm, rtt_memory_refs)
+                .finalize(components::segger_rtt_component_static!(RPTimer))
+        }
+        None => cdc,
+    };
+    let uart_mux = components::console::UartMuxComponent::new(console_uart, 115200)
         .finalize(components::uart_mux_component_static!());
 
     // Uncomment this to use UART as an output
@@ -380,17 +481,100 @@ pub unsafe fn main() {
         uart_mux,
     )
     .finalize(components::console_component_static!());
+
+    // The `lin` feature makes UART1 a LIN bus master, with a LIN transceiver
+    // on GPIO8 (TX) and GPIO9 (RX).
+    #[cfg(feature = "lin")]
+    let lin = {
+        peripherals
+            .pins
+            .get_pin(RPGpio::GPIO8)
+            .set_function(GpioFunction::UART);
+        peripherals
+            .pins
+            .get_pin(RPGpio::GPIO9)
+            .set_function(GpioFunction::UART);
+        components::lin::LinComponent::new(
+            board_kernel,
+            capsules_extra::lin::DRIVER_NUM,
+            &peripherals.uart1,
+            mux_alarm,
+            19200,
+        )
+        .finalize(components::lin_component_static!(
+            rp2040::uart::Uart<'static>,
+            RPTimer<'static>,
+        ))
+    };
+    // The `modbus_rtu` feature makes UART1 a Modbus RTU slave instead, with
+    // an RS-485 transceiver on the same pins.
+    #[cfg(feature = "modbus_rtu")]
+    let modbus_rtu = {
+        peripherals
+            .pins
+            .get_pin(RPGpio::GPIO8)
+            .set_function(GpioFunction::UART);
+        peripherals
+            .pins
+            .get_pin(RPGpio::GPIO9)
+            .set_function(GpioFunction::UART);
+        components::modbus_rtu::ModbusRtuComponent::new(
+            board_kernel,
+            capsules_extra::modbus_rtu::DRIVER_NUM,
+            &peripherals.uart1,
+            mux_alarm,
+            kernel::hil::uart::Parameters {
+                baud_rate: 19200,
+                width: kernel::hil::uart::Width::Eight,
+                parity: kernel::hil::uart::Parity::Even,
+                stop_bits: kernel::hil::uart::StopBits::One,
+                hw_flow_control: false,
+            },
+        )
+        .finalize(components::modbus_rtu_component_static!(
+            rp2040::uart::Uart<'static>,
+            RPTimer<'static>,
+        ))
+    };
+    // The `bluetooth_serial` feature talks to a phone through an HC-05
+    // Bluetooth module on the same pins instead, with its KEY pin on GPIO6
+    // and its STATE pin on GPIO7.
+    #[cfg(feature = "bluetooth_serial")]
+    let bluetooth_serial = {
+        peripherals
+            .pins
+            .get_pin(RPGpio::GPIO8)
+            .set_function(GpioFunction::UART);
+        peripherals
+            .pins
+            .get_pin(RPGpio::GPIO9)
+            .set_function(GpioFunction::UART);
+        components::bluetooth_serial::BluetoothSerialComponent::new(
+            board_kernel,
+            capsules_extra::bluetooth_serial::DRIVER_NUM,
+            &peripherals.uart1,
+            mux_alarm,
+            9600,
+            capsules_extra::bluetooth_serial::Module::Hc05,
+            Some(peripherals.pins.get_pin(RPGpio::GPIO6)),
+            Some(peripherals.pins.get_pin(RPGpio::GPIO7)),
+        )
+        .finalize(components::bluetooth_serial_component_static!(
+            rp2040::uart::Uart<'static>,
+            RPTimer<'static>,
+        ))
+    };
+
     // Create the debugger object that handles calls to `debug!()`.
     components::debug_writer::DebugWriterComponent::new(uart_mux)
         .finalize(components::debug_writer_component_static!());
 
-    cdc.enable();
-    cdc.attach();
+    if !RTT_CONSOLE {
+        cdc.enable();
+        cdc.attach();
+    }
 
-    let gpio = GpioComponent::new(
-        board_kernel,
-        capsules_core::gpio::DRIVER_NUM,
-        components::gpio_component_helper!(
+    let gpio_pins = components::gpio_component_helper!(
             RPGpioPin,
             // Used for serial communication. Comment them in if you don't use serial.
             // 0 => &peripherals.pins.get_pin(RPGpio::GPIO0),
@@ -412,10 +596,11 @@ pub unsafe fn main() {
             15 => &peripherals.pins.get_pin(RPGpio::GPIO15),
             16 => &peripherals.pins.get_pin(RPGpio::GPIO16),
             17 => &peripherals.pins.get_pin(RPGpio::GPIO17),
-            18 => &peripherals.pins.get_pin(RPGpio::GPIO18),
-            19 => &peripherals.pins.get_pin(RPGpio::GPIO19),
-            20 => &peripherals.pins.get_pin(RPGpio::GPIO20),
-            21 => &peripherals.pins.get_pin(RPGpio::GPIO21),
+            // Used for PWM. Comment them in if you don't use PWM.
+            // 18 => &peripherals.pins.get_pin(RPGpio::GPIO18),
+            // 19 => &peripherals.pins.get_pin(RPGpio::GPIO19),
+            // 20 => &peripherals.pins.get_pin(RPGpio::GPIO20),
+            // 21 => &peripherals.pins.get_pin(RPGpio::GPIO21),
             22 => &peripherals.pins.get_pin(RPGpio::GPIO22),
             23 => &peripherals.pins.get_pin(RPGpio::GPIO23),
             24 => &peripherals.pins.get_pin(RPGpio::GPIO24),
@@ -427,15 +612,95 @@ pub unsafe fn main() {
             // 27 => &peripherals.pins.get_pin(RPGpio::GPIO27),
             // 28 => &peripherals.pins.get_pin(RPGpio::GPIO28),
             // 29 => &peripherals.pins.get_pin(RPGpio::GPIO29)
-        ),
-    )
-    .finalize(components::gpio_component_static!(RPGpioPin<'static>));
+    );
+    // Used by UART1.
+    #[cfg(any(feature = "lin", feature = "modbus_rtu", feature = "bluetooth_serial"))]
+    for pin in 8..=9 {
+        gpio_pins[pin] = None;
+    }
+    // Used for the KEY and STATE pins of the Bluetooth module.
+    #[cfg(feature = "bluetooth_serial")]
+    for pin in 6..=7 {
+        gpio_pins[pin] = None;
+    }
+    // Used for the IR receiver and LED.
+    #[cfg(feature = "ir_remote")]
+    for pin in 16..=17 {
+        gpio_pins[pin] = None;
+    }
+    // Used to wake the chip up from dormant.
+    #[cfg(feature = "dormant")]
+    {
+        gpio_pins[22] = None;
+    }
+    let gpio = GpioComponent::new(board_kernel, capsules_core::gpio::DRIVER_NUM, gpio_pins)
+        .finalize(components::gpio_component_static!(RPGpioPin<'static>));
 
     let led = LedsComponent::new().finalize(components::led_component_static!(
         LedHigh<'static, RPGpioPin<'static>>,
         LedHigh::new(&peripherals.pins.get_pin(RPGpio::GPIO25))
     ));
 
+    let mux_pwm = components::pwm::PwmMuxComponent::new(&peripherals.pwm)
+        .finalize(components::pwm_mux_component_static!(rp2040::pwm::Pwm));
+
+    // The `ir_remote` feature receives and sends NEC infrared remote codes,
+    // with an IR receiver module on GPIO16 and an IR LED, through a
+    // transistor, on GPIO17.
+    #[cfg(feature = "ir_remote")]
+    let ir_remote = {
+        peripherals
+            .pins
+            .get_pin(RPGpio::GPIO17)
+            .set_function(GpioFunction::PWM);
+        let ir_led = components::pwm::PwmPinUserComponent::new(mux_pwm, RPGpio::GPIO17)
+            .finalize(components::pwm_pin_user_component_static!(rp2040::pwm::Pwm));
+        let ir_receiver = components::gpio_pulse_capture::GpioPulseCaptureComponent::new(
+            peripherals.pins.get_pin(RPGpio::GPIO16),
+            &peripherals.timer,
+        )
+        .finalize(components::gpio_pulse_capture_component_static!(
+            RPGpioPin<'static>,
+            RPTimer<'static>,
+        ));
+        components::ir_remote::IrRemoteComponent::new(
+            board_kernel,
+            capsules_extra::ir_remote::DRIVER_NUM,
+            mux_alarm,
+            Some(ir_receiver),
+            Some(ir_led),
+        )
+        .finalize(components::ir_remote_component_static!(RPTimer<'static>))
+    };
+
+    // PWM on GPIO18 to GPIO21, to drive LEDs, buzzers and servos from
+    // userspace. GPIO18 and GPIO19, and GPIO20 and GPIO21, share a PWM
+    // channel, and so a frequency.
+    for gpio in [
+        RPGpio::GPIO18,
+        RPGpio::GPIO19,
+        RPGpio::GPIO20,
+        RPGpio::GPIO21,
+    ] {
+        peripherals
+            .pins
+            .get_pin(gpio)
+            .set_function(GpioFunction::PWM);
+    }
+    let pwm_pin_18 = components::pwm::PwmPinUserComponent::new(mux_pwm, RPGpio::GPIO18)
+        .finalize(components::pwm_pin_user_component_static!(rp2040::pwm::Pwm));
+    let pwm_pin_19 = components::pwm::PwmPinUserComponent::new(mux_pwm, RPGpio::GPIO19)
+        .finalize(components::pwm_pin_user_component_static!(rp2040::pwm::Pwm));
+    let pwm_pin_20 = components::pwm::PwmPinUserComponent::new(mux_pwm, RPGpio::GPIO20)
+        .finalize(components::pwm_pin_user_component_static!(rp2040::pwm::Pwm));
+    let pwm_pin_21 = components::pwm::PwmPinUserComponent::new(mux_pwm, RPGpio::GPIO21)
+        .finalize(components::pwm_pin_user_component_static!(rp2040::pwm::Pwm));
+    let pwm =
+        components::pwm::PwmDriverComponent::new(board_kernel, capsules_extra::pwm::DRIVER_NUM)
+            .finalize(components::pwm_driver_component_helper!(
+                pwm_pin_18, pwm_pin_19, pwm_pin_20, pwm_pin_21
+            ));
+
     peripherals.adc.init();
 
     let adc_mux = components::adc::AdcMuxComponent::new(&peripherals.adc)
@@ -461,6 +726,34 @@ pub unsafe fn main() {
     );
     kernel::hil::sensors::TemperatureDriver::set_client(temp_sensor, temp);
 
+    // The `power_manager` feature notifies an application of the sample
+    // and upload windows of a battery-powered node.
+    #[cfg(feature = "power_manager")]
+    let power_manager = components::power_manager::PowerManagerComponent::new(
+        board_kernel,
+        capsules_extra::power_manager::DRIVER_NUM,
+        mux_alarm,
+        &[],
+        &[],
+    )
+    .finalize(components::power_manager_component_static!(RPTimer));
+    // The `dormant` feature also lets the chip go dormant between windows.
+    // The timer stops while the chip is dormant, so the alarm output of an
+    // external real time clock on GPIO22 must wake the chip up for the next
+    // window.
+    #[cfg(feature = "dormant")]
+    {
+        let dormant = static_init!(
+            rp2040::dormant::Dormant<'static>,
+            rp2040::dormant::Dormant::new(&peripherals.clocks, &peripherals.xosc)
+        );
+        let rtc_alarm = peripherals.pins.get_pin(RPGpio::GPIO22);
+        rtc_alarm.make_input();
+        dormant.set_wake_pin(rtc_alarm, kernel::hil::gpio::InterruptEdge::FallingEdge);
+        chip.set_dormant(dormant);
+        power_manager.set_deep_sleep(dormant);
+    }
+
     let adc_channel_0 = components::adc::AdcComponent::new(&adc_mux, Channel::Channel0)
         .finalize(components::adc_component_static!(Adc));
 
@@ -494,6 +787,18 @@ pub unsafe fn main() {
         Some(reset_function),
     )
     .finalize(components::process_console_component_static!(RPTimer));
+    let clocks = components::clock_tree::ClockTreeCommandComponent::new(&peripherals.clocks)
+        .finalize(components::clock_tree_command_component_static!());
+    let _ = process_console.add_command(clocks);
+    let gpio_command = components::gpio_console::GpioCommandComponent::new(&peripherals.pins)
+        .finalize(components::gpio_command_component_static!());
+    let _ = process_console.add_command(gpio_command);
+    // `reset bootloader` reboots into the UF2 bootloader, as if BOOTSEL was
+    // held.
+    let reset_command =
+        components::reset_command::ResetCommandComponent::new(&peripherals.watchdog)
+            .finalize(components::reset_command_component_static!());
+    let _ = process_console.add_command(reset_command);
     let _ = process_console.start();
 
     let sda_pin = peripherals.pins.get_pin(RPGpio::GPIO4);
@@ -524,6 +829,83 @@ pub unsafe fn main() {
     i2c0.init(10 * 1000);
     i2c0.set_master_client(i2c);
 
+    let crc = components::crc::CrcComponent::new(
+        board_kernel,
+        capsules_extra::crc::DRIVER_NUM,
+        &peripherals.crc,
+    )
+    .finalize(components::crc_component_static!(rp2040::crc::Crc<'static>));
+
+    // The RP2040 has no AES engine, so AES is computed in software.
+    const CRYPT_SIZE: usize = 7 * AES128_BLOCK_SIZE;
+
+    let aes_engine = components::aes::AesFallbackComponent::new(None)
+        .finalize(components::aes_fallback_component_static!());
+
+    let ccm_mux = static_init!(
+        virtual_aes_ccm::MuxAES128CCM<'static, Aes128Fallback<'static>>,
+        virtual_aes_ccm::MuxAES128CCM::new(aes_engine)
+    );
+    kernel::deferred_call::DeferredCallClient::register(ccm_mux);
+    aes_engine.set_client(ccm_mux);
+
+    let crypt_buf1 = static_init!([u8; CRYPT_SIZE], [0x00; CRYPT_SIZE]);
+    let ccm_client = static_init!(
+        virtual_aes_ccm::VirtualAES128CCM<'static, Aes128Fallback<'static>>,
+        virtual_aes_ccm::VirtualAES128CCM::new(ccm_mux, crypt_buf1)
+    );
+    ccm_client.setup();
+
+    let aes_source_buffer = static_init!([u8; 16], [0; 16]);
+    let aes_dest_buffer = static_init!([u8; CRYPT_SIZE], [0; CRYPT_SIZE]);
+
+    let crypt_buf2 = static_init!([u8; CRYPT_SIZE], [0x00; CRYPT_SIZE]);
+    let gcm_client = static_init!(
+        aes_gcm::Aes128Gcm<
+            'static,
+            virtual_aes_ccm::VirtualAES128CCM<'static, Aes128Fallback<'static>>,
+        >,
+        aes_gcm::Aes128Gcm::new(ccm_client, crypt_buf2)
+    );
+    ccm_client.set_client(gcm_client);
+
+    let aes = static_init!(
+        capsules_extra::symmetric_encryption::aes::AesDriver<
+            'static,
+            aes_gcm::Aes128Gcm<
+                'static,
+                virtual_aes_ccm::VirtualAES128CCM<'static, Aes128Fallback<'static>>,
+            >,
+        >,
+        capsules_extra::symmetric_encryption::aes::AesDriver::new(
+            gcm_client,
+            aes_source_buffer,
+            aes_dest_buffer,
+            board_kernel.create_grant(
+                capsules_extra::symmetric_encryption::aes::DRIVER_NUM,
+                &memory_allocation_capability
+            )
+        )
+    );
+    AES128GCM::set_client(gcm_client, aes);
+    AES128::set_client(gcm_client, ccm_client);
+
+    // The 2 MiB flash holds the kernel and the apps in its first 512 KiB, the
+    // rest is storage.
+    let nonvolatile_storage = components::nonvolatile_storage::NonvolatileStorageComponent::new(
+        board_kernel,
+        capsules_extra::nonvolatile_storage_driver::DRIVER_NUM,
+        &peripherals.flash,
+        0x80000,  // Start address for userspace accessible region
+        0x100000, // Length of userspace accessible region
+        0x180000, // Start address of kernel region
+        0x80000,  // Length of kernel region
+        &[],      // All apps share the userspace region
+    )
+    .finalize(components::nonvolatile_storage_component_static!(
+        rp2040::flash::Flash
+    ));
+
     let scheduler = components::sched::round_robin::RoundRobinComponent::new(&PROCESSES)
         .finalize(components::round_robin_component_static!(NUM_PROCS));
 
@@ -540,6 +922,20 @@ pub unsafe fn main() {
         adc: adc_syscall,
         temperature: temp,
         i2c,
+        crc,
+        aes,
+        nonvolatile_storage,
+        pwm,
+        #[cfg(feature = "bluetooth_serial")]
+        bluetooth_serial,
+        #[cfg(feature = "ir_remote")]
+        ir_remote,
+        #[cfg(feature = "lin")]
+        lin,
+        #[cfg(feature = "modbus_rtu")]
+        modbus_rtu,
+        #[cfg(feature = "power_manager")]
+        power_manager,
 
         scheduler,
         systick: cortexm0p::systick::SysTick::new_with_calibration(125_000_000),
@@ -556,6 +952,31 @@ pub unsafe fn main() {
         platform_type
     );
 
+    // Optional kernel test of the GPIO interrupt latency, with GPIO14
+    // connected to GPIO15 (both must be removed from the GPIO driver).
+    /*components::test::gpio_latency_test::GpioLatencyTestComponent::new(
+        mux_alarm,
+        peripherals.pins.get_pin(RPGpio::GPIO14),
+        peripherals.pins.get_pin(RPGpio::GPIO15),
+        100,
+    )
+    .finalize(components::gpio_latency_test_component_static!(
+        RPTimer,
+        RPGpioPin<'static>,
+        RPGpioPin<'static>,
+    ))
+    .run();*/
+
+    // Optional kernel test of the throughput of the console UART.
+    /*components::test::uart_throughput_test::UartThroughputTestComponent::new(
+        uart_mux,
+        mux_alarm,
+        2000,
+        "interrupt",
+    )
+    .finalize(components::uart_throughput_test_component_static!(RPTimer))
+    .run();*/
+
     debug!("Initialization complete. Enter main loop");
 
     // These symbols are defined in the linker script.
@@ -570,21 +991,26 @@ pub unsafe fn main() {
         static _eappmem: u8;
     }
 
-    kernel::process::load_processes(
-        board_kernel,
-        chip,
-        core::slice::from_raw_parts(
-            &_sapps as *const u8,
-            &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
-        ),
-        core::slice::from_raw_parts_mut(
-            &mut _sappmem as *mut u8,
-            &_eappmem as *const u8 as usize - &_sappmem as *const u8 as usize,
-        ),
-        &mut PROCESSES,
-        &FAULT_RESPONSE,
-        &process_management_capability,
+    // Processes are only loaded from the regions the linker reserved for
+    // them, which are checked first.
+    kernel::process::app_regions(
+        &_sapps as *const u8,
+        &_eapps as *const u8,
+        &mut _sappmem as *mut u8,
+        &_eappmem as *const u8,
     )
+    .and_then(|(app_flash, app_memory)| {
+        kernel::process::load_processes(
+            board_kernel,
+            &raspberry_pi_pico,
+            chip,
+            app_flash,
+            app_memory,
+            &mut PROCESSES,
+            &FAULT_RESPONSE,
+            &process_management_capability,
+        )
+    })
     .unwrap_or_else(|err| {
         debug!("Error loading processes!");
         debug!("{:?}", err);
@@ -98,6 +98,7 @@ impl KernelResources<e310_g002::chip::E310x<'static, E310G002DefaultPeripherals<
     type SchedulerTimer =
         VirtualSchedulerTimer<VirtualMuxAlarm<'static, e310_g002::chip::E310xClint<'static>>>;
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -121,6 +122,9 @@ impl KernelResources<e310_g002::chip::E310x<'static, E310G002DefaultPeripherals<
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -309,6 +313,7 @@ pub unsafe fn main() {
 
     kernel::process::load_processes(
         board_kernel,
+        &redv,
         chip,
         core::slice::from_raw_parts(
             &_sapps as *const u8,
@@ -154,6 +154,7 @@ impl KernelResources<nrf52840::chip::NRF52<'static, Nrf52840DefaultPeripherals<'
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm4::systick::SysTick;
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -177,6 +178,9 @@ impl KernelResources<nrf52840::chip::NRF52<'static, Nrf52840DefaultPeripherals<'
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -461,6 +465,7 @@ pub unsafe fn main() {
     /// but when pressed again, they are loaded anew.
     fn load_processes(
         board_kernel: &'static kernel::Kernel,
+        platform: &Platform,
         chip: &'static nrf52840::chip::NRF52<'static, Nrf52840DefaultPeripherals<'static>>,
     ) {
         let process_management_capability =
@@ -468,6 +473,7 @@ pub unsafe fn main() {
         unsafe {
             kernel::process::load_processes(
                 board_kernel,
+                platform,
                 chip,
                 core::slice::from_raw_parts(
                     &_sapps as *const u8,
@@ -492,7 +498,7 @@ pub unsafe fn main() {
     debug!("Initialization complete. Entering main loop\r");
     debug!("{}", &nrf52840::ficr::FICR_INSTANCE);
 
-    load_processes(board_kernel, chip);
+    load_processes(board_kernel, &platform, chip);
     // These symbols are defined in the linker script.
     extern "C" {
         /// Beginning of the ROM region containing app images.
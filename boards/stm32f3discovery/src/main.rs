@@ -64,6 +64,13 @@ fn reset() -> ! {
     }
 }
 
+type AppWatchdog = capsules_extra::app_watchdog::AppWatchdog<
+    'static,
+    VirtualMuxAlarm<'static, stm32f303xc::tim2::Tim2<'static>>,
+    wdt::WindoWdg<'static>,
+    components::app_watchdog::Capability,
+>;
+
 /// A structure representing this platform that holds references to all
 /// capsules for this platform.
 struct STM32F3Discovery {
@@ -93,10 +100,12 @@ struct STM32F3Discovery {
     adc: &'static capsules_core::adc::AdcVirtualized<'static>,
     nonvolatile_storage:
         &'static capsules_extra::nonvolatile_storage_driver::NonvolatileStorage<'static>,
+    #[cfg(feature = "free_fall")]
+    free_fall: &'static capsules_extra::free_fall::FreeFall<'static>,
 
     scheduler: &'static RoundRobinSched<'static>,
     systick: cortexm4::systick::SysTick,
-    watchdog: &'static wdt::WindoWdg<'static>,
+    app_watchdog: &'static AppWatchdog,
 }
 
 /// Mapping of integer syscalls to objects that implement syscalls.
@@ -117,9 +126,12 @@ impl SyscallDriverLookup for STM32F3Discovery {
             capsules_extra::temperature::DRIVER_NUM => f(Some(self.temp)),
             kernel::ipc::DRIVER_NUM => f(Some(&self.ipc)),
             capsules_core::adc::DRIVER_NUM => f(Some(self.adc)),
+            capsules_extra::app_watchdog::DRIVER_NUM => f(Some(self.app_watchdog)),
             capsules_extra::nonvolatile_storage_driver::DRIVER_NUM => {
                 f(Some(self.nonvolatile_storage))
             }
+            #[cfg(feature = "free_fall")]
+            capsules_extra::free_fall::DRIVER_NUM => f(Some(self.free_fall)),
             _ => f(None),
         }
     }
@@ -139,7 +151,8 @@ impl
     type CredentialsCheckingPolicy = ();
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm4::systick::SysTick;
-    type WatchDog = wdt::WindoWdg<'static>;
+    type WatchDog = AppWatchdog;
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -161,7 +174,10 @@ impl
         &self.systick
     }
     fn watchdog(&self) -> &Self::WatchDog {
-        self.watchdog
+        self.app_watchdog
+    }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
     }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
@@ -221,6 +237,12 @@ unsafe fn set_pin_primary_functions(
         pin.enable_interrupt();
     });
 
+    // INT1 of the LSM303DLHC is connected on pe04
+    #[cfg(feature = "free_fall")]
+    gpio_ports.get_pin(PinId::PE04).map(|pin| {
+        pin.enable_interrupt();
+    });
+
     // SPI1 has the l3gd20 sensor connected
     gpio_ports.get_pin(PinId::PA06).map(|pin| {
         pin.set_mode(Mode::AlternateFunctionMode);
@@ -545,10 +567,7 @@ pub unsafe fn main() {
 
     let gpio_ports = &peripherals.gpio_ports;
     // GPIO
-    let gpio = GpioComponent::new(
-        board_kernel,
-        capsules_core::gpio::DRIVER_NUM,
-        components::gpio_component_helper!(
+    let gpio_pins = components::gpio_component_helper!(
             stm32f303xc::gpio::Pin<'static>,
             // Left outer connector
             0 => &gpio_ports.get_pin(stm32f303xc::gpio::PinId::PC01).unwrap(),
@@ -641,11 +660,16 @@ pub unsafe fn main() {
             84 => &gpio_ports.get_pin(stm32f303xc::gpio::PinId::PA11).unwrap(),
             85 => &gpio_ports.get_pin(stm32f303xc::gpio::PinId::PA09).unwrap(),
             86 => &gpio_ports.get_pin(stm32f303xc::gpio::PinId::PC09).unwrap()
-        ),
-    )
-    .finalize(components::gpio_component_static!(
-        stm32f303xc::gpio::Pin<'static>
-    ));
+    );
+    // Used for the INT1 pin of the LSM303DLHC.
+    #[cfg(feature = "free_fall")]
+    {
+        gpio_pins[48] = None;
+    }
+    let gpio = GpioComponent::new(board_kernel, capsules_core::gpio::DRIVER_NUM, gpio_pins)
+        .finalize(components::gpio_component_static!(
+            stm32f303xc::gpio::Pin<'static>
+        ));
 
     // L3GD20 sensor
     let spi_mux = components::spi::SpiMuxComponent::new(&peripherals.spi1)
@@ -678,6 +702,7 @@ pub unsafe fn main() {
     // LSM303DLHC
 
     let mux_i2c = components::i2c::I2CMuxComponent::new(&peripherals.i2c1, None)
+        .with_bus_speed(kernel::hil::i2c::BusSpeed::Fast400k)
         .finalize(components::i2c_mux_component_static!(stm32f303xc::i2c::I2C));
 
     let lsm303dlhc = components::lsm303dlhc::Lsm303dlhcI2CComponent::new(
@@ -703,6 +728,22 @@ pub unsafe fn main() {
         debug!("Failed to configure LSM303DLHC sensor ({:?})", error);
     }
 
+    // The `free_fall` feature notifies applications of free falls detected
+    // by the LSM303DLHC, whose INT1 pin is PE04. The board drives no motors,
+    // so it sets no safety action with `free_fall.set_action`.
+    #[cfg(feature = "free_fall")]
+    let free_fall = {
+        if let Err(error) = lsm303dlhc.set_free_fall(350, 30) {
+            debug!("Failed to set up LSM303DLHC free fall ({:?})", error);
+        }
+        components::free_fall::FreeFallComponent::new(
+            board_kernel,
+            capsules_extra::free_fall::DRIVER_NUM,
+            Some(gpio_ports.get_pin(stm32f303xc::gpio::PinId::PE04).unwrap()),
+        )
+        .finalize(components::free_fall_component_static!())
+    };
+
     let ninedof = components::ninedof::NineDofComponent::new(
         board_kernel,
         capsules_extra::ninedof::DRIVER_NUM,
@@ -777,11 +818,26 @@ pub unsafe fn main() {
         0x8000,     // Length of userspace accesible region (16 pages)
         &_sstorage as *const u8 as usize,
         &_estorage as *const u8 as usize - &_sstorage as *const u8 as usize,
+        &[], // All apps share the userspace region
     )
     .finalize(components::nonvolatile_storage_component_static!(
         stm32f303xc::flash::Flash
     ));
 
+    // APP WATCHDOG
+
+    let app_watchdog = components::app_watchdog::AppWatchdogComponent::new(
+        board_kernel,
+        capsules_extra::app_watchdog::DRIVER_NUM,
+        mux_alarm,
+        &peripherals.watchdog,
+        capsules_extra::app_watchdog::MissedDeadlinePolicy::RestartProcess,
+    )
+    .finalize(components::app_watchdog_component_static!(
+        stm32f303xc::tim2::Tim2,
+        wdt::WindoWdg<'static>,
+    ));
+
     let process_printer = components::process_printer::ProcessPrinterTextComponent::new()
         .finalize(components::process_printer_text_component_static!());
     PROCESS_PRINTER = Some(process_printer);
@@ -819,10 +875,12 @@ pub unsafe fn main() {
         temp: temp,
         adc: adc_syscall,
         nonvolatile_storage: nonvolatile_storage,
+        #[cfg(feature = "free_fall")]
+        free_fall,
 
         scheduler,
         systick: cortexm4::systick::SysTick::new(),
-        watchdog: &peripherals.watchdog,
+        app_watchdog,
     };
 
     // // Optional kernel tests
@@ -844,26 +902,86 @@ pub unsafe fn main() {
         static _eappmem: u8;
     }
 
-    kernel::process::load_processes(
-        board_kernel,
-        chip,
-        core::slice::from_raw_parts(
-            &_sapps as *const u8,
-            &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
-        ),
-        core::slice::from_raw_parts_mut(
-            &mut _sappmem as *mut u8,
-            &_eappmem as *const u8 as usize - &_sappmem as *const u8 as usize,
-        ),
-        &mut PROCESSES,
-        &FAULT_RESPONSE,
-        &process_management_capability,
+    // Processes are only loaded from the regions the linker reserved for
+    // them, which are checked first.
+    kernel::process::app_regions(
+        &_sapps as *const u8,
+        &_eapps as *const u8,
+        &mut _sappmem as *mut u8,
+        &_eappmem as *const u8,
     )
+    .and_then(|(app_flash, app_memory)| {
+        kernel::process::load_processes(
+            board_kernel,
+            &stm32f3discovery,
+            chip,
+            app_flash,
+            app_memory,
+            &mut PROCESSES,
+            &FAULT_RESPONSE,
+            &process_management_capability,
+        )
+    })
     .unwrap_or_else(|err| {
         debug!("Error loading processes!");
         debug!("{:?}", err);
     });
 
+    // To load applications from the process console without rebooting, load
+    // the processes with a `DynamicProcessLoader` instead of
+    // `load_processes` above. The flash has a single client, so the
+    // nonvolatile storage driver must be removed first.
+    /*let (app_flash, app_memory) = kernel::process::app_regions(
+        &_sapps as *const u8,
+        &_eapps as *const u8,
+        &mut _sappmem as *mut u8,
+        &_eappmem as *const u8,
+    )
+    .unwrap();
+    let loader = static_init!(
+        kernel::process::DynamicProcessLoader<
+            stm32f303xc::chip::Stm32f3xx<Stm32f3xxDefaultPeripherals>,
+        >,
+        kernel::process::DynamicProcessLoader::new(
+            board_kernel,
+            chip,
+            &mut PROCESSES,
+            &FAULT_RESPONSE,
+            &process_management_capability,
+        )
+    );
+    loader
+        .load_processes(&stm32f3discovery, app_flash, app_memory)
+        .unwrap_or_else(|err| {
+            debug!("Error loading processes!");
+            debug!("{:?}", err);
+        });
+    // The images are checked against their SHA-256 footer.
+    let sha = static_init!(
+        capsules_extra::sha256::Sha256Software<'static>,
+        capsules_extra::sha256::Sha256Software::new()
+    );
+    kernel::deferred_call::DeferredCallClient::register(sha);
+    let checker = static_init!(
+        kernel::process_checker::basic::AppCheckerSha256,
+        kernel::process_checker::basic::AppCheckerSha256::new(
+            sha,
+            static_init!([u8; 32], [0; 32])
+        )
+    );
+    kernel::hil::digest::Digest::set_client(sha, checker);
+    let app_loader = components::app_loader::AppLoaderComponent::new(
+        &peripherals.flash,
+        0x08000000,
+        app_flash,
+        loader,
+        checker,
+    )
+    .finalize(components::app_loader_component_static!(
+        stm32f303xc::flash::Flash
+    ));
+    let _ = process_console.add_command(app_loader);*/
+
     // Uncomment this to enable the watchdog
     peripherals.watchdog.enable();
 
@@ -7,6 +7,8 @@ use core::panic::PanicInfo;
 
 use cortexm4;
 
+use capsules_extra::panic_screen::{PanicScreen, PanicScreenBus};
+
 use kernel::debug;
 use kernel::debug::IoWrite;
 use kernel::hil::led;
@@ -14,19 +16,43 @@ use kernel::hil::uart;
 use kernel::hil::uart::Configure;
 
 use stm32f412g;
+use stm32f412g::fsmc::FSMC_BANK1;
 use stm32f412g::gpio::PinId;
 
 use crate::CHIP;
 use crate::PROCESSES;
 use crate::PROCESS_PRINTER;
 
-/// Writer is used by kernel::debug to panic message to the serial port.
+/// The ST7789H2 LCD, on bank 1 of the FSMC.
+pub struct Lcd;
+
+impl PanicScreenBus for Lcd {
+    fn command(&self, command: u8, parameters: &[u8]) {
+        FSMC_BANK1.write_reg(command as u16);
+        for &parameter in parameters {
+            FSMC_BANK1.write_data(parameter as u16);
+        }
+    }
+
+    fn pixel(&self, color: u16) {
+        FSMC_BANK1.write_data(color);
+    }
+}
+
+/// Writer is used by kernel::debug to panic message to the serial port, and
+/// to the LCD once the FSMC is enabled.
 pub struct Writer {
     initialized: bool,
+    screen_enabled: bool,
+    screen: PanicScreen<Lcd>,
 }
 
 /// Global static for debug writer
-pub static mut WRITER: Writer = Writer { initialized: false };
+pub static mut WRITER: Writer = Writer {
+    initialized: false,
+    screen_enabled: false,
+    screen: PanicScreen::new(Lcd, 240, 240),
+};
 
 impl Writer {
     /// Indicate that USART has already been initialized. Trying to double
@@ -34,6 +60,12 @@ impl Writer {
     pub fn set_initialized(&mut self) {
         self.initialized = true;
     }
+
+    /// Indicate that the FSMC is enabled, so that panic messages can be
+    /// written to the LCD. Accessing the FSMC before causes a bus fault.
+    pub fn set_screen_enabled(&mut self) {
+        self.screen_enabled = true;
+    }
 }
 
 impl Write for Writer {
@@ -64,6 +96,10 @@ impl IoWrite for Writer {
             uart.send_byte(c);
         }
 
+        if self.screen_enabled {
+            self.screen.write(buf);
+        }
+
         buf.len()
     }
 }
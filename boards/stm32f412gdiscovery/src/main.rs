@@ -18,7 +18,9 @@ use kernel::capabilities;
 use kernel::component::Component;
 use kernel::hil::gpio;
 use kernel::hil::led::LedLow;
+use kernel::hil::reset_reason::ResetReasonQuery;
 use kernel::hil::screen::ScreenRotation;
+use kernel::hil::statistics::DriverStatistics;
 use kernel::platform::{KernelResources, SyscallDriverLookup};
 use kernel::scheduler::round_robin::RoundRobinSched;
 use kernel::{create_capability, debug, static_init};
@@ -30,6 +32,12 @@ pub mod io;
 // Number of concurrent processes this platform supports.
 const NUM_PROCS: usize = 4;
 
+type ButtonPin = capsules_extra::gpio_debounce::DebouncedPin<
+    'static,
+    stm32f412g::gpio::Pin<'static>,
+    VirtualMuxAlarm<'static, stm32f412g::tim2::Tim2<'static>>,
+>;
+
 // Actual memory for holding the active process structures.
 static mut PROCESSES: [Option<&'static dyn kernel::process::Process>; NUM_PROCS] =
     [None, None, None, None];
@@ -65,7 +73,7 @@ struct STM32F412GDiscovery {
         LedLow<'static, stm32f412g::gpio::Pin<'static>>,
         4,
     >,
-    button: &'static capsules_core::button::Button<'static, stm32f412g::gpio::Pin<'static>>,
+    button: &'static capsules_core::button::Button<'static, ButtonPin>,
     alarm: &'static capsules_core::alarm::AlarmDriver<
         'static,
         VirtualMuxAlarm<'static, stm32f412g::tim2::Tim2<'static>>,
@@ -76,6 +84,15 @@ struct STM32F412GDiscovery {
     screen: &'static capsules_extra::screen::Screen<'static>,
     temperature: &'static capsules_extra::temperature::TemperatureSensor<'static>,
     rng: &'static capsules_core::rng::RngDriver<'static>,
+    device_id: &'static capsules_extra::device_id::DeviceIdDriver<'static>,
+    driver_statistics: &'static capsules_extra::driver_statistics::DriverStatisticsDriver<'static>,
+    boot_counter:
+        &'static capsules_extra::boot_counter::BootCounter<'static, stm32f412g::rtc::Rtc<'static>>,
+    system_time: &'static capsules_extra::system_time::SystemTime<
+        'static,
+        VirtualMuxAlarm<'static, stm32f412g::tim2::Tim2<'static>>,
+        stm32f412g::rtc::Rtc<'static>,
+    >,
 
     scheduler: &'static RoundRobinSched<'static>,
     systick: cortexm4::systick::SysTick,
@@ -99,6 +116,10 @@ impl SyscallDriverLookup for STM32F412GDiscovery {
             capsules_extra::screen::DRIVER_NUM => f(Some(self.screen)),
             capsules_extra::temperature::DRIVER_NUM => f(Some(self.temperature)),
             capsules_core::rng::DRIVER_NUM => f(Some(self.rng)),
+            capsules_extra::boot_counter::DRIVER_NUM => f(Some(self.boot_counter)),
+            capsules_extra::system_time::DRIVER_NUM => f(Some(self.system_time)),
+            capsules_extra::device_id::DRIVER_NUM => f(Some(self.device_id)),
+            capsules_extra::driver_statistics::DRIVER_NUM => f(Some(self.driver_statistics)),
             _ => f(None),
         }
     }
@@ -119,6 +140,7 @@ impl
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm4::systick::SysTick;
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -142,6 +164,9 @@ impl
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -211,37 +236,6 @@ unsafe fn set_pin_primary_functions(
         pin.set_alternate_function(AlternateFunction::AF7);
     });
 
-    // uncomment this if you do not plan to use the joystick up, as they both use Exti0
-    // joystick selection is connected on pa00
-    // gpio_ports.get_pin(PinId::PA00).map(|pin| {
-    //     pin.enable_interrupt();
-    // });
-
-    // joystick down is connected on pg01
-    gpio_ports.get_pin(PinId::PG01).map(|pin| {
-        pin.enable_interrupt();
-    });
-
-    // joystick left is connected on pf15
-    gpio_ports.get_pin(PinId::PF15).map(|pin| {
-        pin.enable_interrupt();
-    });
-
-    // joystick right is connected on pf14
-    gpio_ports.get_pin(PinId::PF14).map(|pin| {
-        pin.enable_interrupt();
-    });
-
-    // joystick up is connected on pg00
-    gpio_ports.get_pin(PinId::PG00).map(|pin| {
-        pin.enable_interrupt();
-    });
-
-    // enable interrupt for D0
-    gpio_ports.get_pin(PinId::PG09).map(|pin| {
-        pin.enable_interrupt();
-    });
-
     // Enable clocks for GPIO Ports
     // Disable some of them if you don't need some of the GPIOs
     gpio_ports.get_port_from_port_id(PortId::B).enable_clock();
@@ -271,12 +265,6 @@ unsafe fn set_pin_primary_functions(
     });
 
     i2c1.enable_clock();
-    i2c1.set_speed(stm32f412g::i2c::I2CSpeed::Speed100k, 16);
-
-    // FT6206 interrupt
-    gpio_ports.get_pin(PinId::PG05).map(|pin| {
-        pin.enable_interrupt();
-    });
 
     // ADC
 
@@ -310,9 +298,6 @@ unsafe fn set_pin_primary_functions(
         pin.set_mode(stm32f412g::gpio::Mode::AnalogMode);
     });
 
-    // EXTI9_5 interrupts is delivered at IRQn 23 (EXTI9_5)
-    cortexm4::nvic::Nvic::new(stm32f412g::nvic::EXTI9_5).enable();
-
     // LCD
 
     let pins = [
@@ -376,6 +361,7 @@ unsafe fn setup_peripherals(
 
     // FSMC
     fsmc.enable();
+    io::WRITER.set_screen_enabled();
 
     // RNG
     trng.enable_clock();
@@ -429,6 +415,23 @@ pub unsafe fn main() {
     // We use the default HSI 16Mhz clock
     set_pin_primary_functions(syscfg, &base_peripherals.i2c1, &base_peripherals.gpio_ports);
 
+    // GPIO interrupts
+    stm32f4xx_components::ExtiComponent::new(
+        base_peripherals.exti,
+        &base_peripherals.gpio_ports,
+        [
+            // The joystick selection, on PA00, uses the same EXTI line as
+            // the joystick up, on PG00.
+            stm32f412g::gpio::PinId::PG01, // joystick down
+            stm32f412g::gpio::PinId::PF15, // joystick left
+            stm32f412g::gpio::PinId::PF14, // joystick right
+            stm32f412g::gpio::PinId::PG00, // joystick up
+            stm32f412g::gpio::PinId::PG09, // D0
+            stm32f412g::gpio::PinId::PG05, // FT6206 interrupt
+        ],
+    )
+    .finalize(());
+
     setup_dma(
         dma1,
         &base_peripherals.dma1_streams,
@@ -502,67 +505,113 @@ pub unsafe fn main() {
         ),
     ));
 
+    // ALARM
+
+    let tim2 = &base_peripherals.tim2;
+    let mux_alarm = components::alarm::AlarmMuxComponent::new(tim2).finalize(
+        components::alarm_mux_component_static!(stm32f412g::tim2::Tim2),
+    );
+
     // BUTTONs
+    let button_select = components::gpio_debounce::DebouncedPinComponent::new(
+        base_peripherals
+            .gpio_ports
+            .get_pin(stm32f412g::gpio::PinId::PA00)
+            .unwrap(),
+        mux_alarm,
+        capsules_extra::gpio_debounce::DEFAULT_DEBOUNCE_MS,
+    )
+    .finalize(components::debounced_pin_component_static!(
+        stm32f412g::gpio::Pin,
+        stm32f412g::tim2::Tim2
+    ));
+    let button_down = components::gpio_debounce::DebouncedPinComponent::new(
+        base_peripherals
+            .gpio_ports
+            .get_pin(stm32f412g::gpio::PinId::PG01)
+            .unwrap(),
+        mux_alarm,
+        capsules_extra::gpio_debounce::DEFAULT_DEBOUNCE_MS,
+    )
+    .finalize(components::debounced_pin_component_static!(
+        stm32f412g::gpio::Pin,
+        stm32f412g::tim2::Tim2
+    ));
+    let button_left = components::gpio_debounce::DebouncedPinComponent::new(
+        base_peripherals
+            .gpio_ports
+            .get_pin(stm32f412g::gpio::PinId::PF15)
+            .unwrap(),
+        mux_alarm,
+        capsules_extra::gpio_debounce::DEFAULT_DEBOUNCE_MS,
+    )
+    .finalize(components::debounced_pin_component_static!(
+        stm32f412g::gpio::Pin,
+        stm32f412g::tim2::Tim2
+    ));
+    let button_right = components::gpio_debounce::DebouncedPinComponent::new(
+        base_peripherals
+            .gpio_ports
+            .get_pin(stm32f412g::gpio::PinId::PF14)
+            .unwrap(),
+        mux_alarm,
+        capsules_extra::gpio_debounce::DEFAULT_DEBOUNCE_MS,
+    )
+    .finalize(components::debounced_pin_component_static!(
+        stm32f412g::gpio::Pin,
+        stm32f412g::tim2::Tim2
+    ));
+    let button_up = components::gpio_debounce::DebouncedPinComponent::new(
+        base_peripherals
+            .gpio_ports
+            .get_pin(stm32f412g::gpio::PinId::PG00)
+            .unwrap(),
+        mux_alarm,
+        capsules_extra::gpio_debounce::DEFAULT_DEBOUNCE_MS,
+    )
+    .finalize(components::debounced_pin_component_static!(
+        stm32f412g::gpio::Pin,
+        stm32f412g::tim2::Tim2
+    ));
+
     let button = components::button::ButtonComponent::new(
         board_kernel,
         capsules_core::button::DRIVER_NUM,
         components::button_component_helper!(
-            stm32f412g::gpio::Pin,
+            ButtonPin,
             // Select
             (
-                base_peripherals
-                    .gpio_ports
-                    .get_pin(stm32f412g::gpio::PinId::PA00)
-                    .unwrap(),
+                button_select,
                 kernel::hil::gpio::ActivationMode::ActiveHigh,
                 kernel::hil::gpio::FloatingState::PullNone
             ),
             // Down
             (
-                base_peripherals
-                    .gpio_ports
-                    .get_pin(stm32f412g::gpio::PinId::PG01)
-                    .unwrap(),
+                button_down,
                 kernel::hil::gpio::ActivationMode::ActiveHigh,
                 kernel::hil::gpio::FloatingState::PullNone
             ),
             // Left
             (
-                base_peripherals
-                    .gpio_ports
-                    .get_pin(stm32f412g::gpio::PinId::PF15)
-                    .unwrap(),
+                button_left,
                 kernel::hil::gpio::ActivationMode::ActiveHigh,
                 kernel::hil::gpio::FloatingState::PullNone
             ),
             // Right
             (
-                base_peripherals
-                    .gpio_ports
-                    .get_pin(stm32f412g::gpio::PinId::PF14)
-                    .unwrap(),
+                button_right,
                 kernel::hil::gpio::ActivationMode::ActiveHigh,
                 kernel::hil::gpio::FloatingState::PullNone
             ),
             // Up
             (
-                base_peripherals
-                    .gpio_ports
-                    .get_pin(stm32f412g::gpio::PinId::PG00)
-                    .unwrap(),
+                button_up,
                 kernel::hil::gpio::ActivationMode::ActiveHigh,
                 kernel::hil::gpio::FloatingState::PullNone
             )
         ),
     )
-    .finalize(components::button_component_static!(stm32f412g::gpio::Pin));
-
-    // ALARM
-
-    let tim2 = &base_peripherals.tim2;
-    let mux_alarm = components::alarm::AlarmMuxComponent::new(tim2).finalize(
-        components::alarm_mux_component_static!(stm32f412g::tim2::Tim2),
-    );
+    .finalize(components::button_component_static!(ButtonPin));
 
     let alarm = components::alarm::AlarmDriverComponent::new(
         board_kernel,
@@ -571,6 +620,78 @@ pub unsafe fn main() {
     )
     .finalize(components::alarm_component_static!(stm32f412g::tim2::Tim2));
 
+    // DEVICE ID
+
+    let device_id = components::device_id::DeviceIdComponent::new(
+        board_kernel,
+        capsules_extra::device_id::DRIVER_NUM,
+        Some(&base_peripherals.uid),
+        "stm32f412gdiscovery",
+        "MB1209",
+    )
+    .finalize(components::device_id_component_static!());
+
+    // DRIVER STATISTICS
+
+    let driver_statistics_sources = static_init!(
+        [(&'static str, &'static dyn DriverStatistics); 2],
+        [
+            ("usart2", &base_peripherals.usart2),
+            ("i2c1", &base_peripherals.i2c1),
+        ]
+    );
+    let driver_statistics = components::driver_statistics::DriverStatisticsComponent::new(
+        board_kernel,
+        capsules_extra::driver_statistics::DRIVER_NUM,
+        driver_statistics_sources,
+    )
+    .finalize(components::driver_statistics_component_static!());
+
+    // BOOT COUNTER
+
+    let boot_counter = components::boot_counter::BootCounterComponent::new(
+        &base_peripherals.rtc,
+        0,
+        base_peripherals.rcc.reset_reason(),
+    )
+    .finalize(components::boot_counter_component_static!(
+        stm32f412g::rtc::Rtc<'static>
+    ));
+
+    // SYSTEM TIME
+
+    // The time is kept in the RTC backup registers after those of the boot
+    // counter, so it survives resets, and loss of main power with a battery
+    // on VBAT.
+    let system_time = components::system_time::SystemTimeComponent::new(
+        board_kernel,
+        capsules_extra::system_time::DRIVER_NUM,
+        mux_alarm,
+        &base_peripherals.rtc,
+        capsules_extra::boot_counter::NUM_REGISTERS,
+    )
+    .finalize(components::system_time_component_static!(
+        stm32f412g::tim2::Tim2,
+        stm32f412g::rtc::Rtc<'static>
+    ));
+
+    // LOW POWER
+
+    // The chip enters Stop mode when idle once a capsule allows deep sleep
+    // through the system time, and an EXTI line can wake it up. Uncomment
+    // to also wake it up every minute from the RTC.
+    //
+    // base_peripherals
+    //     .rtc
+    //     .enable_clock(stm32f412g::rcc::RtcClockSource::Lsi)
+    //     .unwrap();
+    // base_peripherals.rtc.start_wakeup_timer(60).unwrap();
+    base_peripherals
+        .pwr
+        .set_deep_sleep_mode(stm32f412g::pwr::DeepSleepMode::StopLowPower);
+    chip.set_pwr(&base_peripherals.pwr);
+    system_time.set_deep_sleep(&base_peripherals.pwr);
+
     // GPIO
     let gpio = GpioComponent::new(
         board_kernel,
@@ -616,7 +737,10 @@ pub unsafe fn main() {
 
     // FT6206
 
+    // I2C1 also reaches the WM8994 audio codec, at address 0x1A, which has
+    // no driver.
     let mux_i2c = components::i2c::I2CMuxComponent::new(&base_peripherals.i2c1, None)
+        .with_bus_speed(kernel::hil::i2c::BusSpeed::Standard100k)
         .finalize(components::i2c_mux_component_static!(stm32f412g::i2c::I2C));
 
     let ft6x06 = components::ft6x06::Ft6x06Component::new(
@@ -672,11 +796,6 @@ pub unsafe fn main() {
 
     touch.set_screen_rotation_offset(ScreenRotation::Rotated90);
 
-    // Uncomment this for multi touch support
-    // let touch =
-    //     components::touch::MultiTouchComponent::new(board_kernel, ft6x06, Some(ft6x06), None)
-    //         .finalize(());
-
     // ADC
     let adc_mux = components::adc::AdcMuxComponent::new(&base_peripherals.adc1)
         .finalize(components::adc_mux_component_static!(stm32f412g::adc::Adc));
@@ -750,6 +869,9 @@ pub unsafe fn main() {
     .finalize(components::process_console_component_static!(
         stm32f412g::tim2::Tim2
     ));
+    let clocks = components::clock_tree::ClockTreeCommandComponent::new(base_peripherals.rcc)
+        .finalize(components::clock_tree_command_component_static!());
+    let _ = process_console.add_command(clocks);
     let _ = process_console.start();
 
     let scheduler = components::sched::round_robin::RoundRobinComponent::new(&PROCESSES)
@@ -771,6 +893,10 @@ pub unsafe fn main() {
         screen,
         temperature: temp,
         rng,
+        device_id,
+        driver_statistics,
+        boot_counter,
+        system_time,
 
         scheduler,
         systick: cortexm4::systick::SysTick::new(),
@@ -807,21 +933,26 @@ pub unsafe fn main() {
         static _eappmem: u8;
     }
 
-    kernel::process::load_processes(
-        board_kernel,
-        chip,
-        core::slice::from_raw_parts(
-            &_sapps as *const u8,
-            &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
-        ),
-        core::slice::from_raw_parts_mut(
-            &mut _sappmem as *mut u8,
-            &_eappmem as *const u8 as usize - &_sappmem as *const u8 as usize,
-        ),
-        &mut PROCESSES,
-        &FAULT_RESPONSE,
-        &process_management_capability,
+    // Processes are only loaded from the regions the linker reserved for
+    // them, which are checked first.
+    kernel::process::app_regions(
+        &_sapps as *const u8,
+        &_eapps as *const u8,
+        &mut _sappmem as *mut u8,
+        &_eappmem as *const u8,
     )
+    .and_then(|(app_flash, app_memory)| {
+        kernel::process::load_processes(
+            board_kernel,
+            &stm32f412g,
+            chip,
+            app_flash,
+            app_memory,
+            &mut PROCESSES,
+            &FAULT_RESPONSE,
+            &process_management_capability,
+        )
+    })
     .unwrap_or_else(|err| {
         debug!("Error loading processes!");
         debug!("{:?}", err);
@@ -0,0 +1,102 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Component for the GPIO interrupts of STM32F4 chips.
+//!
+//! Every pin with interrupts needs an EXTI line, and pin `n` of every port
+//! can only use line `n`, so two pins with the same number cannot both have
+//! interrupts. This component claims the lines of a list of pins, panicking
+//! if two of them need the same line, and enables the NVIC interrupts on
+//! which the lines are delivered. The pins can then be given to the button,
+//! touch or GPIO components, which enable the interrupts of the pins.
+//!
+//! The clocks of the GPIO ports of the pins must already be enabled.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! stm32f4xx_components::ExtiComponent::new(
+//!     base_peripherals.exti,
+//!     &base_peripherals.gpio_ports,
+//!     [
+//!         PinId::PG01, // joystick down
+//!         PinId::PG05, // touch panel
+//!     ],
+//! )
+//! .finalize(());
+//! ```
+
+use core::fmt;
+
+use enum_primitive::cast::FromPrimitive;
+use kernel::component::Component;
+use stm32f4xx::exti::{Exti, LineId};
+use stm32f4xx::gpio::{GpioPorts, PinId};
+
+/// Name of a pin in panic messages, for example `PG05`.
+struct PinName(PinId);
+
+impl fmt::Display for PinName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "P{}{:02}",
+            (b'A' + self.0.get_port_number()) as char,
+            self.0.get_pin_number()
+        )
+    }
+}
+
+pub struct ExtiComponent<const N: usize> {
+    exti: &'static Exti<'static>,
+    gpio_ports: &'static GpioPorts<'static>,
+    pins: [PinId; N],
+}
+
+impl<const N: usize> ExtiComponent<N> {
+    pub fn new(
+        exti: &'static Exti<'static>,
+        gpio_ports: &'static GpioPorts<'static>,
+        pins: [PinId; N],
+    ) -> Self {
+        Self {
+            exti,
+            gpio_ports,
+            pins,
+        }
+    }
+}
+
+impl<const N: usize> Component for ExtiComponent<N> {
+    type StaticInput = ();
+    type Output = ();
+
+    fn finalize(self, _s: Self::StaticInput) -> Self::Output {
+        // The EXTI lines are selected in SYSCFG.
+        self.exti.enable_clock();
+
+        for pinid in self.pins {
+            let pin = self
+                .gpio_ports
+                .get_pin(pinid)
+                .unwrap_or_else(|| panic!("EXTI: {} does not exist", PinName(pinid)));
+            let line = LineId::from_u8(pinid.get_pin_number()).unwrap();
+            if let Some(other) = self.exti.line_gpiopin(line) {
+                if other.get_pinid() as u8 != pinid as u8 {
+                    panic!(
+                        "EXTI: {} and {} both need line {}",
+                        PinName(other.get_pinid()),
+                        PinName(pinid),
+                        line as u8
+                    );
+                }
+            }
+
+            unsafe {
+                pin.enable_interrupt();
+                cortexm4::nvic::Nvic::new(line.irqn()).enable();
+            }
+        }
+    }
+}
@@ -81,6 +81,7 @@ impl KernelResources<imxrt1060::chip::Imxrt10xx<imxrt1060::chip::Imxrt10xxDefaul
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm7::systick::SysTick;
     type WatchDog = ();
+    type KernelHooks = ();
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -104,6 +105,9 @@ impl KernelResources<imxrt1060::chip::Imxrt10xx<imxrt1060::chip::Imxrt10xxDefaul
     fn watchdog(&self) -> &Self::WatchDog {
         &()
     }
+    fn kernel_hooks(&self) -> &Self::KernelHooks {
+        &()
+    }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
     }
@@ -292,6 +296,19 @@ pub unsafe fn main() {
     )
     .finalize(components::alarm_component_static!(imxrt1060::gpt::Gpt1));
 
+    // Optional kernel test of the throughput of the console LPUART, which
+    // transmits with DMA.
+    /*components::test::uart_throughput_test::UartThroughputTestComponent::new(
+        uart_mux,
+        mux_alarm,
+        2000,
+        "DMA",
+    )
+    .finalize(components::uart_throughput_test_component_static!(
+        imxrt1060::gpt::Gpt1
+    ))
+    .run();*/
+
     //
     // Capabilities
     //
@@ -346,6 +363,7 @@ pub unsafe fn main() {
 
     kernel::process::load_processes(
         board_kernel,
+        &teensy40,
         chip,
         core::slice::from_raw_parts(
             &_sapps as *const u8,
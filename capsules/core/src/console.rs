@@ -40,7 +40,11 @@
 //! When the buffer has been written successfully, the buffer is released from
 //! the driver. Successive writes must call `allow` each time a buffer is to be
 //! written.
+//!
+//! If the device the console writes to is on a framed UART mux, the output of
+//! each process can be sent on its own channel with `set_app_channels`.
 
+use crate::virtualizers::virtual_uart::{channel, Channel};
 use kernel::grant::{AllowRoCount, AllowRwCount, Grant, GrantKernelData, UpcallCount};
 use kernel::hil::uart;
 use kernel::processbuffer::{ReadableProcessBuffer, WriteableProcessBuffer};
@@ -96,6 +100,7 @@ pub struct Console<'a> {
     tx_buffer: TakeCell<'static, [u8]>,
     rx_in_progress: OptionalCell<ProcessId>,
     rx_buffer: TakeCell<'static, [u8]>,
+    channels: OptionalCell<&'a dyn Channel>,
 }
 
 impl<'a> Console<'a> {
@@ -117,9 +122,16 @@ impl<'a> Console<'a> {
             tx_buffer: TakeCell::new(tx_buffer),
             rx_in_progress: OptionalCell::empty(),
             rx_buffer: TakeCell::new(rx_buffer),
+            channels: OptionalCell::empty(),
         }
     }
 
+    /// Tag the output of each process with its own channel on `device`, which
+    /// must be the device the console writes to.
+    pub fn set_app_channels(&self, device: &'a dyn Channel) {
+        self.channels.set(device);
+    }
+
     /// Internal helper function for setting up a new send transaction
     fn send_new(
         &self,
@@ -193,6 +205,8 @@ impl<'a> Console<'a> {
                     })
                     .unwrap_or(0);
                 app.write_remaining -= transaction_len;
+                self.channels
+                    .map(|device| device.set_channel(channel::app(processid.id())));
                 let _ = self.uart.transmit_buffer(buffer, transaction_len);
             });
         } else {
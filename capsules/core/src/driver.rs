@@ -23,6 +23,7 @@ pub enum NUM {
     LowLevelDebug         = 0x00008,
     ReadOnlyState         = 0x00009,
     Pwm                   = 0x00010,
+    PwmCapture            = 0x00011,
 
     // Kernel
     Ipc                   = 0x10000,
@@ -34,6 +35,8 @@ pub enum NUM {
     UsbUser               = 0x20005,
     I2cMasterSlave        = 0x20006,
     Can                   = 0x20007,
+    Lin                   = 0x20008,
+    ModbusRtu             = 0x20009,
 
     // Radio
     BleAdvertising        = 0x30000,
@@ -41,6 +44,9 @@ pub enum NUM {
     Udp                   = 0x30002,
     LoRaPhySPI            = 0x30003,
     LoRaPhyGPIO           = 0x30004,
+    UdpIpv4               = 0x30005,
+    CellularModem         = 0x30006,
+    BluetoothSerial       = 0x30007,
 
     // Cryptography
     Rng                   = 0x40001,
@@ -55,15 +61,19 @@ pub enum NUM {
     NvmStorage            = 0x50001,
     SdCard                = 0x50002,
     KVSystem              = 0x50003,
+    DataLogger            = 0x50004,
 
     // Sensors
     Temperature           = 0x60000,
     Humidity              = 0x60001,
     AmbientLight          = 0x60002,
+    Pressure              = 0x60003,
     NINEDOF               = 0x60004,
     Proximity             = 0x60005,
     SoundPressure         = 0x60006,
     AirQuality            = 0x60007,
+    SensorAggregator      = 0x60008,
+    Location              = 0x60009,
 
     // Sensor ICs
     Tsl2561               = 0x70000,
@@ -88,5 +98,20 @@ pub enum NUM {
     TextScreen            = 0x90003,
     SevenSegment          = 0x90004,
     KeyboardHid           = 0x90005,
+    AppWatchdog           = 0x90006,
+    BootCounter           = 0x90007,
+    DeviceId              = 0x90008,
+    Graphics              = 0x90009,
+    TouchCalibration      = 0x9000A,
+    RgbLed                = 0x9000B,
+    PowerManager          = 0x9000C,
+    SystemTime            = 0x9000D,
+    DriverStatistics      = 0x9000E,
+    OnScreenKeyboard      = 0x9000F,
+    IrRemote              = 0x90010,
+    Vibration             = 0x90011,
+    Pedometer             = 0x90012,
+    FreeFall              = 0x90013,
+    AutoBrightness        = 0x90014,
 }
 }
@@ -5,6 +5,9 @@
 //! Implements a text console over the UART that allows
 //! a terminal to inspect and control userspace processes.
 //!
+//! The console only needs a byte stream: it runs over any device that
+//! implements the UART HIL, such as a TCP connection.
+//!
 //! For a more in-depth documentation check /doc/Process_Console.md
 use core::cell::Cell;
 use core::cmp;
@@ -14,6 +17,7 @@ use core::str;
 use kernel::capabilities::ProcessManagementCapability;
 use kernel::hil::time::ConvertTicks;
 use kernel::utilities::cells::MapCell;
+use kernel::utilities::cells::OptionalCell;
 use kernel::utilities::cells::TakeCell;
 use kernel::ProcessId;
 
@@ -40,10 +44,13 @@ pub const COMMAND_BUF_LEN: usize = 32;
 /// Default size for the history command.
 pub const DEFAULT_COMMAND_HISTORY_LEN: usize = 10;
 
+/// Number of commands other capsules can add to the console.
+pub const MAX_ADDED_COMMANDS: usize = 8;
+
 /// List of valid commands for printing help. Consolidated as these are
 /// displayed in a few different cases.
 const VALID_COMMANDS_STR: &[u8] =
-    b"help status list stop start fault boot terminate process kernel reset panic\r\n";
+    b"help status list stop start fault boot terminate process kernel reset panic";
 
 /// Escape character for ANSI escape sequences.
 const ESC: u8 = '\x1B' as u8;
@@ -69,6 +76,25 @@ const NLINE: u8 = '\x0A' as u8;
 /// Upper limit for ASCII characters
 const ASCII_LIMIT: u8 = 128;
 
+/// Command added to the process console by another capsule, for example to
+/// inspect its state.
+///
+/// The output of the command is printed one line at a time, as the UART
+/// becomes free, so a command can print more than the buffers of the console
+/// hold. Each line must fit in a `ConsoleWriter`.
+pub trait ConsoleCommand {
+    /// Name of the command, as typed on the console.
+    fn name(&self) -> &'static str;
+
+    /// Run the command with the rest of the command line, before its output
+    /// is printed.
+    fn execute(&self, arguments: &str);
+
+    /// Write line `index` of the output to `writer`. Returns `false`, without
+    /// writing anything, once `index` is past the last line.
+    fn write_line(&self, index: usize, writer: &mut dyn fmt::Write) -> bool;
+}
+
 /// States used for state machine to allow printing large strings asynchronously
 /// across multiple calls. This reduces the size of the buffer needed to print
 /// each section of the debug message.
@@ -89,6 +115,11 @@ enum WriterState {
         index: isize,
         total: isize,
     },
+    /// Output of an added command.
+    Command {
+        command: usize,
+        index: usize,
+    },
 }
 
 impl Default for WriterState {
@@ -256,6 +287,9 @@ pub struct ProcessConsole<
     /// This capsule needs to use potentially dangerous APIs related to
     /// processes, and requires a capability to access those APIs.
     capability: C,
+
+    /// Commands added by other capsules.
+    commands: [OptionalCell<&'a dyn ConsoleCommand>; MAX_ADDED_COMMANDS],
 }
 
 #[derive(Copy, Clone)]
@@ -480,9 +514,29 @@ impl<'a, const COMMAND_HISTORY_LEN: usize, A: Alarm<'a>, C: ProcessManagementCap
             kernel_addresses: kernel_addresses,
             reset_function: reset_function,
             capability: capability,
+            commands: core::array::from_fn(|_| OptionalCell::empty()),
         }
     }
 
+    /// Add `command` to the commands of the console. Returns `NOMEM` if
+    /// `MAX_ADDED_COMMANDS` commands were already added.
+    pub fn add_command(&self, command: &'a dyn ConsoleCommand) -> Result<(), ErrorCode> {
+        let slot = self
+            .commands
+            .iter()
+            .find(|slot| slot.is_none())
+            .ok_or(ErrorCode::NOMEM)?;
+        slot.set(command);
+        Ok(())
+    }
+
+    /// Index of the added command named `name`.
+    fn find_command(&self, name: &str) -> Option<usize> {
+        self.commands
+            .iter()
+            .position(|slot| slot.map_or(false, |command| command.name() == name))
+    }
+
     /// Start the process console listening for user commands.
     pub fn start(&self) -> Result<(), ErrorCode> {
         if self.running.get() == false {
@@ -519,9 +573,21 @@ impl<'a, const COMMAND_HISTORY_LEN: usize, A: Alarm<'a>, C: ProcessManagementCap
         let _ = self.write_bytes(&(console_writer.buf)[..console_writer.size]);
 
         let _ = self.write_bytes(b"Welcome to the process console.\r\n");
+        self.write_valid_commands();
+        self.prompt();
+    }
+
+    /// Print the list of valid commands, including the added commands.
+    fn write_valid_commands(&self) {
         let _ = self.write_bytes(b"Valid commands are: ");
         let _ = self.write_bytes(VALID_COMMANDS_STR);
-        self.prompt();
+        for slot in self.commands.iter() {
+            slot.map(|command| {
+                let _ = self.write_bytes(b" ");
+                let _ = self.write_bytes(command.name().as_bytes());
+            });
+        }
+        let _ = self.write_bytes(b"\r\n");
     }
 
     /// Simple state machine helper function that identifies the next state for
@@ -553,6 +619,10 @@ impl<'a, const COMMAND_HISTORY_LEN: usize, A: Alarm<'a>, C: ProcessManagementCap
                     }
                 }
             }
+            WriterState::Command { command, index } => WriterState::Command {
+                command,
+                index: index + 1,
+            },
             WriterState::Empty => WriterState::Empty,
         }
     }
@@ -719,6 +789,19 @@ impl<'a, const COMMAND_HISTORY_LEN: usize, A: Alarm<'a>, C: ProcessManagementCap
                         }
                     });
             }
+            WriterState::Command { command, index } => {
+                let mut console_writer = ConsoleWriter::new();
+                let more = self.commands[command].map_or(false, |command| {
+                    command.write_line(index, &mut console_writer)
+                });
+                if more {
+                    let _ = self.write_bytes(&(console_writer.buf)[..console_writer.size]);
+                } else {
+                    // As for `ProcessPrint`, the prompt must be printed here.
+                    self.writer_state.replace(WriterState::Empty);
+                    self.prompt();
+                }
+            }
             WriterState::Empty => {
                 self.prompt();
             }
@@ -760,8 +843,19 @@ impl<'a, const COMMAND_HISTORY_LEN: usize, A: Alarm<'a>, C: ProcessManagementCap
 
                         if clean_str.starts_with("help") {
                             let _ = self.write_bytes(b"Welcome to the process console.\r\n");
-                            let _ = self.write_bytes(b"Valid commands are: ");
-                            let _ = self.write_bytes(VALID_COMMANDS_STR);
+                            self.write_valid_commands();
+                        } else if let Some(command) = clean_str
+                            .split_whitespace()
+                            .next()
+                            .and_then(|name| self.find_command(name))
+                        {
+                            self.commands[command].map(|added| {
+                                let arguments = clean_str[added.name().len()..].trim_start();
+                                added.execute(arguments);
+                            });
+                            let state = WriterState::Command { command, index: 0 };
+                            self.writer_state.replace(state);
+                            self.create_state_buffer(state);
                         } else if clean_str.starts_with("start") {
                             let argument = clean_str.split_whitespace().nth(1);
                             argument.map(|name| {
@@ -971,8 +1065,7 @@ impl<'a, const COMMAND_HISTORY_LEN: usize, A: Alarm<'a>, C: ProcessManagementCap
                         } else if clean_str.starts_with("panic") {
                             panic!("Process Console forced a kernel panic.");
                         } else {
-                            let _ = self.write_bytes(b"Valid commands are: ");
-                            let _ = self.write_bytes(VALID_COMMANDS_STR);
+                            self.write_valid_commands();
                         }
                     }
                     Err(_e) => {
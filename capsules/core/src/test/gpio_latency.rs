@@ -0,0 +1,158 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Measure the latency of GPIO interrupts.
+//!
+//! The test toggles an output pin, which must be connected to an input pin
+//! with a jumper wire, and measures with an alarm the time until the
+//! interrupt of the input pin reaches its client. The latency includes the
+//! time the chip takes to service the interrupt and to call the client from
+//! the kernel loop, which is what capsules observe. After `iterations`
+//! edges, the minimum, average and maximum latencies are printed on the
+//! debug output:
+//!
+//! ```text
+//! GPIO interrupt latency over 100 edges: min 3us avg 4us max 9us (alarm at 1000000Hz)
+//! ```
+//!
+//! The resolution is the resolution of the alarm: boards should give it the
+//! fastest alarm of the chip. Depends on a working UART and debug! macro.
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::debug;
+use kernel::hil::gpio;
+use kernel::hil::time::{Alarm, AlarmClient, ConvertTicks, Frequency, Ticks};
+
+/// Time between two edges, so that the debug output and other interrupts
+/// settle.
+const GAP_MS: u32 = 10;
+/// Time to wait for an interrupt before giving up.
+const TIMEOUT_MS: u32 = 100;
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    /// Waiting for the next edge.
+    Gap,
+    /// Waiting for the interrupt of the input pin.
+    Edge,
+}
+
+pub struct TestGpioLatency<'a, A: Alarm<'a>, O: gpio::Output, I: gpio::InterruptPin<'a>> {
+    alarm: &'a A,
+    output: &'a O,
+    input: &'a I,
+    iterations: u32,
+    state: Cell<State>,
+    /// Time the output was toggled.
+    start: Cell<A::Ticks>,
+    count: Cell<u32>,
+    min: Cell<u32>,
+    max: Cell<u32>,
+    total: Cell<u64>,
+}
+
+impl<'a, A: Alarm<'a>, O: gpio::Output, I: gpio::InterruptPin<'a>> TestGpioLatency<'a, A, O, I> {
+    pub fn new(alarm: &'a A, output: &'a O, input: &'a I, iterations: u32) -> Self {
+        TestGpioLatency {
+            alarm,
+            output,
+            input,
+            iterations,
+            state: Cell::new(State::Idle),
+            start: Cell::new(A::Ticks::from(0)),
+            count: Cell::new(0),
+            min: Cell::new(u32::MAX),
+            max: Cell::new(0),
+            total: Cell::new(0),
+        }
+    }
+
+    /// Start the measurement. The output must already be configured as an
+    /// output, and the input as an input.
+    pub fn run(&self) {
+        self.count.set(0);
+        self.min.set(u32::MAX);
+        self.max.set(0);
+        self.total.set(0);
+        self.output.clear();
+        self.input
+            .enable_interrupts(gpio::InterruptEdge::EitherEdge);
+        self.wait(GAP_MS, State::Gap);
+    }
+
+    fn wait(&self, ms: u32, state: State) {
+        self.state.set(state);
+        self.alarm
+            .set_alarm(self.alarm.now(), self.alarm.ticks_from_ms(ms));
+    }
+
+    fn report(&self) {
+        self.state.set(State::Idle);
+        self.input.disable_interrupts();
+        let count = self.count.get();
+        let average = (self.total.get() / cmp::max(count, 1) as u64) as u32;
+        debug!(
+            "GPIO interrupt latency over {} edges: min {}us avg {}us max {}us (alarm at {}Hz)",
+            count,
+            self.alarm.ticks_to_us(A::Ticks::from(self.min.get())),
+            self.alarm.ticks_to_us(A::Ticks::from(average)),
+            self.alarm.ticks_to_us(A::Ticks::from(self.max.get())),
+            A::Frequency::frequency()
+        );
+    }
+}
+
+impl<'a, A: Alarm<'a>, O: gpio::Output, I: gpio::InterruptPin<'a>> AlarmClient
+    for TestGpioLatency<'a, A, O, I>
+{
+    fn alarm(&self) {
+        match self.state.get() {
+            State::Idle => {}
+            State::Gap => {
+                self.state.set(State::Edge);
+                let start = self.alarm.now();
+                self.start.set(start);
+                self.output.toggle();
+                self.alarm
+                    .set_alarm(start, self.alarm.ticks_from_ms(TIMEOUT_MS));
+            }
+            State::Edge => {
+                self.state.set(State::Idle);
+                self.input.disable_interrupts();
+                debug!(
+                    "GPIO interrupt latency: no interrupt after {}ms, is the output connected to the input?",
+                    TIMEOUT_MS
+                );
+            }
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>, O: gpio::Output, I: gpio::InterruptPin<'a>> gpio::Client
+    for TestGpioLatency<'a, A, O, I>
+{
+    fn fired(&self) {
+        let end = self.alarm.now();
+        if self.state.get() != State::Edge {
+            // Noise on the input between edges.
+            return;
+        }
+        let _ = self.alarm.disarm();
+
+        let latency = end.wrapping_sub(self.start.get()).into_u32();
+        self.min.set(cmp::min(self.min.get(), latency));
+        self.max.set(cmp::max(self.max.get(), latency));
+        self.total.set(self.total.get() + latency as u64);
+        self.count.set(self.count.get() + 1);
+
+        if self.count.get() >= self.iterations {
+            self.report();
+        } else {
+            self.wait(GAP_MS, State::Gap);
+        }
+    }
+}
@@ -5,8 +5,10 @@
 pub mod alarm;
 pub mod alarm_edge_cases;
 pub mod double_grant_entry;
+pub mod gpio_latency;
 pub mod random_alarm;
 pub mod random_timer;
 pub mod rng;
+pub mod uart_throughput;
 pub mod virtual_rng;
 pub mod virtual_uart;
@@ -4,7 +4,21 @@
 
 //! Virtual ADC Capsule
 //!
-//! Support Single Sample for now.
+//! Shares one ADC between several capsules, each using an `AdcDevice` bound
+//! to a channel. Only single samples are supported.
+//!
+//! Requests are queued, one per device, and served in turn: after a sample,
+//! the mux continues with the next device in the list that has a request, so
+//! a device sampling again from its callback does not keep the others
+//! waiting. Devices waiting on the channel being sampled all receive the same
+//! sample.
+//!
+//! `sample()` returns `BUSY` if the device already has a request queued. If
+//! the ADC refuses a request when its turn comes, for example because it is
+//! used outside of the mux, the request is dropped; `sample()` returns the
+//! error when this happens immediately.
+
+use core::cell::Cell;
 
 use kernel::collections::list::{List, ListLink, ListNode};
 use kernel::hil;
@@ -16,6 +30,8 @@ pub struct MuxAdc<'a, A: hil::adc::Adc<'a>> {
     adc: &'a A,
     devices: List<'a, AdcDevice<'a, A>>,
     inflight: OptionalCell<&'a AdcDevice<'a, A>>,
+    /// Position in `devices` from which to look for the next request.
+    next_device: Cell<usize>,
 }
 
 impl<'a, A: hil::adc::Adc<'a>> hil::adc::Client for MuxAdc<'a, A> {
@@ -41,22 +57,36 @@ impl<'a, A: hil::adc::Adc<'a>> MuxAdc<'a, A> {
             adc: adc,
             devices: List::new(),
             inflight: OptionalCell::empty(),
+            next_device: Cell::new(0),
+        }
+    }
+
+    /// The first device with a request from `next_device`, wrapping around
+    /// to the head of the list, with its position.
+    fn next_request(&self) -> Option<(usize, &'a AdcDevice<'a, A>)> {
+        let mut first = None;
+        for (index, node) in self.devices.iter().enumerate() {
+            if node.operation.is_some() {
+                if index >= self.next_device.get() {
+                    return Some((index, node));
+                }
+                first = first.or(Some((index, node)));
+            }
         }
+        first
     }
 
     fn do_next_op(&self) {
         if self.inflight.is_none() {
-            let mnode = self.devices.iter().find(|node| node.operation.is_some());
-            mnode.map(|node| {
+            self.next_request().map(|(index, node)| {
+                self.next_device.set(index + 1);
                 let started = node.operation.map_or(false, |operation| match operation {
-                    Operation::OneSample => {
-                        let _ = self.adc.sample(&node.channel);
-                        true
-                    }
+                    Operation::OneSample => self.adc.sample(&node.channel).is_ok(),
                 });
                 if started {
                     self.inflight.set(node);
                 } else {
+                    node.operation.clear();
                     self.do_next_op();
                 }
             });
@@ -111,8 +141,15 @@ impl<'a, A: hil::adc::Adc<'a>> ListNode<'a, AdcDevice<'a, A>> for AdcDevice<'a,
 
 impl<'a, A: hil::adc::Adc<'a>> hil::adc::AdcChannel<'a> for AdcDevice<'a, A> {
     fn sample(&self) -> Result<(), ErrorCode> {
+        if self.operation.is_some() {
+            return Err(ErrorCode::BUSY);
+        }
         self.operation.set(Operation::OneSample);
         self.mux.do_next_op();
+        // The request is dropped if the ADC refused it.
+        if self.operation.is_none() {
+            return Err(ErrorCode::FAIL);
+        }
         Ok(())
     }
 
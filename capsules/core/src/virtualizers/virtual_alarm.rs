@@ -4,6 +4,14 @@
 
 //! Virtualize the Alarm interface to enable multiple users of an underlying
 //! alarm hardware peripheral.
+//!
+//! The hardware alarm is only ever programmed for the nearest deadline of all
+//! virtual alarms, so the chip sleeps until a client actually needs to run
+//! (tickless operation). Boards that favour fewer wakeups over precise timing
+//! can additionally configure a slack window with `MuxAlarm::set_slack`:
+//! deadlines that fall within the window after the nearest one are served by
+//! the same hardware wakeup. Such alarms may fire up to `slack` ticks late,
+//! but never early. `MuxAlarm::stats` reports how well this works.
 
 use core::cell::Cell;
 
@@ -151,6 +159,10 @@ impl<'a, A: Alarm<'a>> Alarm<'a> for VirtualMuxAlarm<'a, A> {
         if enabled == 0 {
             //debug!("virtual_alarm: first alarm: set it.");
             self.mux.set_alarm(reference, dt);
+        } else if self.mux.firing.get() == false && self.mux.slack.get().is_some() {
+            // Coalescing depends on the deadlines of all alarms, not only
+            // on the current earliest one.
+            self.mux.reschedule();
         } else if self.mux.firing.get() == false {
             // If firing is true, the mux will scan all the alarms after
             // firing and pick the soonest one so do not need to modify the
@@ -202,6 +214,18 @@ impl<'a, A: Alarm<'a>> time::AlarmClient for VirtualMuxAlarm<'a, A> {
     }
 }
 
+/// Counters describing how often the underlying alarm wakes up the system.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MuxAlarmStats {
+    /// Number of times the underlying alarm was programmed.
+    pub programmed: u32,
+    /// Number of times the underlying alarm fired.
+    pub wakeups: u32,
+    /// Number of virtual alarms that fired. Any excess over `wakeups` are
+    /// alarms that shared a wakeup with another one.
+    pub fired: u32,
+}
+
 /// Structure to control a set of virtual alarms multiplexed together on top of a single alarm.
 pub struct MuxAlarm<'a, A: Alarm<'a>> {
     /// Head of the linked list of virtual alarms multiplexed together.
@@ -214,6 +238,11 @@ pub struct MuxAlarm<'a, A: Alarm<'a>> {
     firing: Cell<bool>,
     /// Reference to next alarm
     next_tick_vals: Cell<Option<(A::Ticks, A::Ticks)>>,
+    /// Window after the nearest deadline in which other deadlines are
+    /// served by the same wakeup, `None` to fire every alarm on time.
+    slack: Cell<Option<A::Ticks>>,
+    /// Wakeup statistics
+    stats: Cell<MuxAlarmStats>,
 }
 
 impl<'a, A: Alarm<'a>> MuxAlarm<'a, A> {
@@ -224,10 +253,40 @@ impl<'a, A: Alarm<'a>> MuxAlarm<'a, A> {
             alarm: alarm,
             firing: Cell::new(false),
             next_tick_vals: Cell::new(None),
+            slack: Cell::new(None),
+            stats: Cell::new(MuxAlarmStats {
+                programmed: 0,
+                wakeups: 0,
+                fired: 0,
+            }),
+        }
+    }
+
+    /// Allow virtual alarms to fire up to `slack` ticks late so that alarms
+    /// with nearby deadlines share a single wakeup. A slack of 0 disables
+    /// coalescing.
+    pub fn set_slack(&self, slack: A::Ticks) {
+        if slack == A::Ticks::from(0u32) {
+            self.slack.set(None);
+        } else {
+            self.slack.set(Some(slack));
         }
     }
 
+    /// Return the wakeup statistics since boot or the last `reset_stats`.
+    pub fn stats(&self) -> MuxAlarmStats {
+        self.stats.get()
+    }
+
+    /// Clear the wakeup statistics, to measure them over a new period.
+    pub fn reset_stats(&self) {
+        self.stats.set(MuxAlarmStats::default());
+    }
+
     pub fn set_alarm(&self, reference: A::Ticks, dt: A::Ticks) {
+        let mut stats = self.stats.get();
+        stats.programmed = stats.programmed.wrapping_add(1);
+        self.stats.set(stats);
         self.next_tick_vals.set(Some((reference, dt)));
         self.alarm.set_alarm(reference, dt);
     }
@@ -236,12 +295,67 @@ impl<'a, A: Alarm<'a>> MuxAlarm<'a, A> {
         self.next_tick_vals.set(None);
         let _ = self.alarm.disarm();
     }
+
+    /// Program the underlying alarm for the next virtual alarm to expire,
+    /// taking the slack window into account, or disarm it if no virtual
+    /// alarm is armed.
+    fn reschedule(&self) {
+        // Find the soonest alarm client (if any) and set the "next" underlying
+        // alarm based on it.  This needs to happen after firing all expired
+        // alarms since those may have reset new alarms.
+        let now = self.alarm.now();
+        let remaining = |cur: &VirtualMuxAlarm<'a, A>| {
+            let when = cur.dt_reference.get();
+            // If the alarm has already expired, then it should be
+            // considered as the earliest possible (0 ticks), so it
+            // will trigger as soon as possible. This can happen
+            // if the alarm expired *after* it was examined when
+            // firing the expired alarms.
+            if !now.within_range(when.reference, when.reference_plus_dt()) {
+                A::Ticks::from(0u32)
+            } else {
+                when.reference_plus_dt().wrapping_sub(now)
+            }
+        };
+        let mut next = self
+            .virtual_alarms
+            .iter()
+            .filter(|cur| cur.armed.get())
+            .min_by_key(|cur| remaining(cur));
+
+        // Defer the wakeup to the latest deadline within the slack window of
+        // the nearest one, so all of them are handled at once.
+        if let (Some(nearest), Some(slack)) = (next, self.slack.get()) {
+            let nearest_remaining = remaining(nearest);
+            let limit = nearest_remaining.wrapping_add(slack);
+            if limit >= nearest_remaining {
+                next = self
+                    .virtual_alarms
+                    .iter()
+                    .filter(|cur| cur.armed.get() && remaining(cur) <= limit)
+                    .max_by_key(|cur| remaining(cur))
+                    .or(next);
+            }
+        }
+
+        // Set the alarm.
+        if let Some(valrm) = next {
+            let dt_reference = valrm.dt_reference.get();
+            self.set_alarm(dt_reference.reference, dt_reference.dt);
+        } else {
+            self.disarm();
+        }
+    }
 }
 
 impl<'a, A: Alarm<'a>> time::AlarmClient for MuxAlarm<'a, A> {
     /// When the underlying alarm has fired, we have to multiplex this event back to the virtual
     /// alarms that should now fire.
     fn alarm(&self) {
+        let mut stats = self.stats.get();
+        stats.wakeups = stats.wakeups.wrapping_add(1);
+        self.stats.set(stats);
+
         // Check whether to fire each alarm. At this level, alarms are one-shot,
         // so a repeating client will set it again in the alarm() callback.
         self.firing.set(true);
@@ -269,40 +383,15 @@ impl<'a, A: Alarm<'a>> time::AlarmClient for MuxAlarm<'a, A> {
                     // Alarm fully expired, disarm and fire callback
                     cur.armed.set(false);
                     self.enabled.set(self.enabled.get() - 1);
+                    let mut stats = self.stats.get();
+                    stats.fired = stats.fired.wrapping_add(1);
+                    self.stats.set(stats);
                     //debug!("  Virtualizer: {:?} outside {:?}-{:?}, fire!", now, cur.reference.get(), cur.reference.get().wrapping_add(cur.dt.get()));
                     cur.alarm();
                 }
             });
         self.firing.set(false);
-        // Find the soonest alarm client (if any) and set the "next" underlying
-        // alarm based on it.  This needs to happen after firing all expired
-        // alarms since those may have reset new alarms.
-        let now = self.alarm.now();
-        let next = self
-            .virtual_alarms
-            .iter()
-            .filter(|cur| cur.armed.get())
-            .min_by_key(|cur| {
-                let when = cur.dt_reference.get();
-                // If the alarm has already expired, then it should be
-                // considered as the earliest possible (0 ticks), so it
-                // will trigger as soon as possible. This can happen
-                // if the alarm expired *after* it was examined in the
-                // above loop.
-                if !now.within_range(when.reference, when.reference_plus_dt()) {
-                    A::Ticks::from(0u32)
-                } else {
-                    when.reference_plus_dt().wrapping_sub(now)
-                }
-            });
-
-        // Set the alarm.
-        if let Some(valrm) = next {
-            let dt_reference = valrm.dt_reference.get();
-            self.set_alarm(dt_reference.reference, dt_reference.dt);
-        } else {
-            self.disarm();
-        }
+        self.reschedule();
     }
 }
 
@@ -585,4 +674,40 @@ mod tests {
         alarm.run_for_ticks(Ticks32::from(750));
         assert_eq!(client.count(), v_alarms.len());
     }
+
+    #[test]
+    fn test_slack_coalesces_nearby_alarms() {
+        let alarm = FakeAlarm::new();
+        let client = ClientCounter::new();
+
+        let mux = MuxAlarm::new(&alarm);
+        alarm.set_alarm_client(&mux);
+        mux.set_slack(20.into());
+
+        let v_alarms = &[
+            VirtualMuxAlarm::new(&mux),
+            VirtualMuxAlarm::new(&mux),
+            VirtualMuxAlarm::new(&mux),
+        ];
+
+        let now = alarm.now();
+        for (v, dt) in v_alarms.iter().zip([100u32, 115, 500]) {
+            v.setup();
+            v.set_alarm_client(&client);
+            v.set_alarm(now, dt.into());
+        }
+
+        // The first two alarms are within the slack window of each other and
+        // fire on the same wakeup, the third one needs its own.
+        alarm.trigger_next_alarm();
+        assert_eq!(client.count(), 2);
+        assert!(alarm.now().into_u32() >= now.into_u32() + 115);
+
+        run_until_disarmed(&alarm);
+        assert_eq!(client.count(), 3);
+
+        let stats = mux.stats();
+        assert_eq!(stats.wakeups, 2);
+        assert_eq!(stats.fired, 3);
+    }
 }
@@ -23,6 +23,7 @@ pub struct MuxI2C<'a, I: i2c::I2CMaster<'a>, S: i2c::SMBusMaster<'a> = NoSMBus>
     i2c_inflight: OptionalCell<&'a I2CDevice<'a, I, S>>,
     smbus_inflight: OptionalCell<&'a SMBusDevice<'a, I, S>>,
     deferred_call: DeferredCall,
+    bus_speed: OptionalCell<i2c::BusSpeed>,
 }
 
 impl<'a, I: i2c::I2CMaster<'a>, S: i2c::SMBusMaster<'a>> I2CHwMasterClient for MuxI2C<'a, I, S> {
@@ -51,9 +52,21 @@ impl<'a, I: i2c::I2CMaster<'a>, S: i2c::SMBusMaster<'a>> MuxI2C<'a, I, S> {
             i2c_inflight: OptionalCell::empty(),
             smbus_inflight: OptionalCell::empty(),
             deferred_call: DeferredCall::new(),
+            bus_speed: OptionalCell::empty(),
         }
     }
 
+    /// Record the speed the bus was configured for, so that it can be
+    /// checked against the fastest speed each device supports.
+    pub fn set_bus_speed(&self, speed: i2c::BusSpeed) {
+        self.bus_speed.set(speed);
+    }
+
+    /// The speed the bus was configured for, if it was recorded.
+    pub fn bus_speed(&self) -> Option<i2c::BusSpeed> {
+        self.bus_speed.extract()
+    }
+
     fn enable(&self) {
         let enabled = self.enabled.get();
         self.enabled.set(enabled + 1);
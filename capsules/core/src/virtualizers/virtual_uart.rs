@@ -14,6 +14,23 @@
 //! `MuxUart` provides shared access to a single UART bus for multiple users.
 //! `UartDevice` provides access for a single client.
 //!
+//! Framing
+//! -------
+//!
+//! By default the output of all devices is interleaved on the bus. With
+//! `MuxUart::enable_framing`, every transmission is instead sent as a frame
+//! tagged with the channel of the device that sent it, so that a host tool
+//! such as `tools/console_demux.py` can demultiplex the output. A frame is:
+//!
+//! ```text
+//! +------------+-----------------+----------------+---------------+
+//! | FRAME_SYNC | channel (u16le) | length (u16le) | length bytes  |
+//! +------------+-----------------+----------------+---------------+
+//! ```
+//!
+//! The channels are listed in [`channel`]. Received data is not framed, and
+//! word transmissions are sent as is.
+//!
 //! Usage
 //! -----
 //!
@@ -56,6 +73,37 @@ use kernel::ErrorCode;
 
 pub const RX_BUF_LEN: usize = 64;
 
+/// First byte of every frame.
+pub const FRAME_SYNC: u8 = 0xA5;
+/// Length of the header sent before the data of each frame.
+pub const FRAME_HEADER_LEN: usize = 5;
+
+/// Channels tagging the frames.
+pub mod channel {
+    /// Kernel debug output.
+    pub const KERNEL_DEBUG: u16 = 0;
+    /// The process console.
+    pub const PROCESS_CONSOLE: u16 = 1;
+    /// The low level debug driver.
+    pub const LOW_LEVEL_DEBUG: u16 = 2;
+    /// Devices whose channel is not set.
+    pub const OTHER: u16 = 0xFF;
+    /// Output of the process with identifier `n` is on channel `APP_BASE + n`.
+    /// Identifiers wrap around within the channels from `APP_BASE` up.
+    pub const APP_BASE: u16 = 0x100;
+
+    /// Channel of the process with identifier `id`.
+    pub fn app(id: usize) -> u16 {
+        APP_BASE + (id % (0x10000 - APP_BASE as usize)) as u16
+    }
+}
+
+/// A device whose output can be tagged with a channel.
+pub trait Channel {
+    /// Tag transmissions started from now on with `channel`.
+    fn set_channel(&self, channel: u16);
+}
+
 pub struct MuxUart<'a> {
     uart: &'a dyn uart::Uart<'a>,
     speed: u32,
@@ -64,6 +112,13 @@ pub struct MuxUart<'a> {
     buffer: TakeCell<'static, [u8]>,
     completing_read: Cell<bool>,
     deferred_call: DeferredCall,
+    /// Frame header buffer, present when framing is enabled and no header
+    /// is being sent.
+    header: TakeCell<'static, [u8]>,
+    framing: Cell<bool>,
+    /// Data of the frame whose header is being sent.
+    frame_data: TakeCell<'static, [u8]>,
+    frame_len: Cell<usize>,
 }
 
 impl<'a> uart::TransmitClient for MuxUart<'a> {
@@ -73,10 +128,26 @@ impl<'a> uart::TransmitClient for MuxUart<'a> {
         tx_len: usize,
         rcode: Result<(), ErrorCode>,
     ) {
-        self.inflight.map(move |device| {
-            self.inflight.clear();
-            device.transmitted_buffer(tx_buffer, tx_len, rcode);
-        });
+        if let Some(data) = self.frame_data.take() {
+            // The header of a frame was sent: send its data.
+            self.header.replace(tx_buffer);
+            let result = match rcode {
+                Ok(()) => self.uart.transmit_buffer(data, self.frame_len.get()),
+                Err(ecode) => Err((ecode, data)),
+            };
+            if let Err((ecode, data)) = result {
+                self.inflight.take().map(move |device| {
+                    device.transmitted_buffer(data, 0, Err(ecode));
+                });
+            } else {
+                return;
+            }
+        } else {
+            self.inflight.map(move |device| {
+                self.inflight.clear();
+                device.transmitted_buffer(tx_buffer, tx_len, rcode);
+            });
+        }
         self.do_next_op();
     }
 }
@@ -199,6 +270,45 @@ impl<'a> MuxUart<'a> {
             buffer: TakeCell::new(buffer),
             completing_read: Cell::new(false),
             deferred_call: DeferredCall::new(),
+            header: TakeCell::empty(),
+            framing: Cell::new(false),
+            frame_data: TakeCell::empty(),
+            frame_len: Cell::new(0),
+        }
+    }
+
+    /// Send each transmission as a frame tagged with the channel of its
+    /// device. `header` must be at least `FRAME_HEADER_LEN` bytes long.
+    pub fn enable_framing(&self, header: &'static mut [u8]) {
+        self.header.replace(header);
+        self.framing.set(true);
+    }
+
+    /// Start sending `buf` as a frame on `channel`, beginning with its header.
+    fn transmit_frame(
+        &self,
+        channel: u16,
+        buf: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        let header = match self.header.take() {
+            Some(header) => header,
+            None => return Err((ErrorCode::BUSY, buf)),
+        };
+        let frame_len = cmp::min(len, u16::MAX as usize);
+        header[0] = FRAME_SYNC;
+        header[1..3].copy_from_slice(&channel.to_le_bytes());
+        header[3..5].copy_from_slice(&(frame_len as u16).to_le_bytes());
+        match self.uart.transmit_buffer(header, FRAME_HEADER_LEN) {
+            Ok(()) => {
+                self.frame_data.replace(buf);
+                self.frame_len.set(frame_len);
+                Ok(())
+            }
+            Err((ecode, header)) => {
+                self.header.replace(header);
+                Err((ecode, buf))
+            }
         }
     }
 
@@ -218,15 +328,18 @@ impl<'a> MuxUart<'a> {
             mnode.map(|node| {
                 node.tx_buffer.take().map(|buf| {
                     node.operation.map(move |op| match op {
-                        Operation::Transmit { len } => {
-                            let _ = self.uart.transmit_buffer(buf, *len).map_err(
-                                move |(ecode, buf)| {
-                                    node.tx_client.map(move |client| {
-                                        node.transmitting.set(false);
-                                        client.transmitted_buffer(buf, 0, Err(ecode));
-                                    });
-                                },
-                            );
+                        Operation::Transmit { len, channel } => {
+                            let result = if self.framing.get() {
+                                self.transmit_frame(*channel, buf, *len)
+                            } else {
+                                self.uart.transmit_buffer(buf, *len)
+                            };
+                            let _ = result.map_err(move |(ecode, buf)| {
+                                node.tx_client.map(move |client| {
+                                    node.transmitting.set(false);
+                                    client.transmitted_buffer(buf, 0, Err(ecode));
+                                });
+                            });
                         }
                         Operation::TransmitWord { word } => {
                             let rcode = self.uart.transmit_word(*word);
@@ -306,7 +419,7 @@ impl DeferredCallClient for MuxUart<'_> {
 
 #[derive(Copy, Clone, PartialEq)]
 enum Operation {
-    Transmit { len: usize },
+    Transmit { len: usize, channel: u16 },
     TransmitWord { word: u32 },
 }
 
@@ -330,6 +443,7 @@ pub struct UartDevice<'a> {
     next: ListLink<'a, UartDevice<'a>>,
     rx_client: OptionalCell<&'a dyn uart::ReceiveClient>,
     tx_client: OptionalCell<&'a dyn uart::TransmitClient>,
+    channel: Cell<u16>,
 }
 
 impl<'a> UartDevice<'a> {
@@ -347,6 +461,7 @@ impl<'a> UartDevice<'a> {
             next: ListLink::empty(),
             rx_client: OptionalCell::empty(),
             tx_client: OptionalCell::empty(),
+            channel: Cell::new(channel::OTHER),
         }
     }
 
@@ -391,6 +506,12 @@ impl<'a> uart::ReceiveClient for UartDevice<'a> {
     }
 }
 
+impl Channel for UartDevice<'_> {
+    fn set_channel(&self, channel: u16) {
+        self.channel.set(channel);
+    }
+}
+
 impl<'a> ListNode<'a, UartDevice<'a>> for UartDevice<'a> {
     fn next(&'a self) -> &'a ListLink<'a, UartDevice<'a>> {
         &self.next
@@ -417,7 +538,10 @@ impl<'a> uart::Transmit<'a> for UartDevice<'a> {
         } else {
             self.tx_buffer.replace(tx_data);
             self.transmitting.set(true);
-            self.operation.set(Operation::Transmit { len: tx_len });
+            self.operation.set(Operation::Transmit {
+                len: tx_len,
+                channel: self.channel.get(),
+            });
             self.mux.do_next_op_async();
             Ok(())
         }
@@ -8,6 +8,11 @@
 //! an ADC.
 //!
 //! It includes support for analog light sensors and analog temperature sensors.
+//!
+//! `PhotoresistorLightSensor` reads a photoresistor in a voltage divider
+//! through a virtualized ADC channel, so it can share the ADC with other
+//! users, and converts the reading to lux with the curve of the
+//! photoresistor.
 
 use kernel::hil;
 use kernel::utilities::cells::OptionalCell;
@@ -65,6 +70,162 @@ impl<'a, A: hil::adc::Adc<'a>> hil::sensors::AmbientLight<'a> for AnalogLightSen
     }
 }
 
+/// Where the photoresistor sits in its voltage divider.
+#[derive(Clone, Copy)]
+pub enum DividerPosition {
+    /// Between the ADC input and ground, with the fixed resistor to the
+    /// supply. The reading rises as the light dims.
+    LowSide,
+    /// Between the supply and the ADC input, with the fixed resistor to
+    /// ground. The reading rises with the light.
+    HighSide,
+}
+
+/// A photoresistor (LDR) in a voltage divider with a fixed resistor, whose
+/// resistance follows `R = R10 * (lux / 10) ^ -gamma`.
+///
+/// `R10` is the resistance at 10 lux and `gamma` the slope of the log-log
+/// curve in the datasheet, 0.7 for a GL5528 for instance.
+#[derive(Clone, Copy)]
+pub struct Photoresistor {
+    position: DividerPosition,
+    fixed_ohms: u32,
+    ohms_at_10_lux: u32,
+    gamma_milli: u32,
+}
+
+/// `2 ^ (2 ^ -k)` for k in 1..=16, in Q31.
+const EXP2_STEPS: [u32; 16] = [
+    0xb504f334, 0x9837f052, 0x8b95c1e4, 0x85aac368, 0x82cd8699, 0x8164d1f4, 0x80b1ed50, 0x8058d7d3,
+    0x802c6437, 0x8016302f, 0x800b179d, 0x80058baf, 0x8002c5d0, 0x800162e6, 0x8000b173, 0x800058b9,
+];
+
+/// Base 2 logarithm of `x`, which must not be zero, in Q16.
+fn log2_q16(x: u64) -> i64 {
+    let integer = 63 - x.leading_zeros() as i64;
+    // Normalize to [1, 2) in Q31
+    let mut mantissa = if integer >= 31 {
+        x >> (integer - 31)
+    } else {
+        x << (31 - integer)
+    };
+    let mut fraction = 0;
+    for _ in 0..16 {
+        mantissa = (mantissa * mantissa) >> 31;
+        fraction <<= 1;
+        if mantissa >= 1 << 32 {
+            mantissa >>= 1;
+            fraction |= 1;
+        }
+    }
+    (integer << 16) | fraction
+}
+
+/// `scale * 2 ^ x`, with `x` in Q16, saturated to `u32::MAX`.
+fn exp2_q16(scale: u32, x: i64) -> u32 {
+    let mut mantissa: u64 = 1 << 31;
+    for (k, step) in EXP2_STEPS.iter().enumerate() {
+        if x & (0x8000 >> k) != 0 {
+            mantissa = (mantissa * *step as u64) >> 31;
+        }
+    }
+    let value = scale as u64 * mantissa;
+    let shift = (x >> 16) - 31;
+    let value = if shift >= 0 {
+        if value.leading_zeros() as i64 <= shift {
+            u64::MAX
+        } else {
+            value << shift
+        }
+    } else if shift <= -64 {
+        0
+    } else {
+        value >> -shift
+    };
+    value.min(u32::MAX as u64) as u32
+}
+
+impl Photoresistor {
+    pub const fn new(
+        position: DividerPosition,
+        fixed_ohms: u32,
+        ohms_at_10_lux: u32,
+        gamma_milli: u32,
+    ) -> Photoresistor {
+        Photoresistor {
+            position,
+            fixed_ohms,
+            ohms_at_10_lux,
+            gamma_milli,
+        }
+    }
+
+    /// Convert a left-aligned ADC sample to lux, saturated to `u32::MAX`.
+    ///
+    /// As the divider is ratiometric, the voltage reference of the ADC does
+    /// not matter, as long as it is the supply of the divider.
+    pub fn lux(&self, sample: u16) -> usize {
+        let low = sample as u64;
+        let high = (1 << 16) - low;
+        // R = fixed * numerator / denominator
+        let (numerator, denominator) = match self.position {
+            DividerPosition::LowSide => (low, high),
+            DividerPosition::HighSide => (high, low),
+        };
+        if denominator == 0 || self.ohms_at_10_lux == 0 {
+            // No current through the photoresistor: dark
+            return 0;
+        }
+        if numerator == 0 || self.fixed_ohms == 0 {
+            return u32::MAX as usize;
+        }
+
+        // lux = 10 * (R10 / R) ^ (1 / gamma)
+        let ratio = log2_q16(self.ohms_at_10_lux as u64 * denominator)
+            - log2_q16(self.fixed_ohms as u64 * numerator);
+        let exponent = ratio * 1000 / self.gamma_milli.max(1) as i64;
+        exp2_q16(10, exponent) as usize
+    }
+}
+
+/// An ambient light sensor made of a photoresistor in a voltage divider,
+/// read through an ADC channel.
+pub struct PhotoresistorLightSensor<'a> {
+    adc: &'a dyn hil::adc::AdcChannel<'a>,
+    photoresistor: Photoresistor,
+    client: OptionalCell<&'a dyn hil::sensors::AmbientLightClient>,
+}
+
+impl<'a> PhotoresistorLightSensor<'a> {
+    pub fn new(
+        adc: &'a dyn hil::adc::AdcChannel<'a>,
+        photoresistor: Photoresistor,
+    ) -> PhotoresistorLightSensor<'a> {
+        PhotoresistorLightSensor {
+            adc,
+            photoresistor,
+            client: OptionalCell::empty(),
+        }
+    }
+}
+
+impl hil::adc::Client for PhotoresistorLightSensor<'_> {
+    fn sample_ready(&self, sample: u16) {
+        let lux = self.photoresistor.lux(sample);
+        self.client.map(|client| client.callback(lux));
+    }
+}
+
+impl<'a> hil::sensors::AmbientLight<'a> for PhotoresistorLightSensor<'a> {
+    fn set_client(&self, client: &'a dyn hil::sensors::AmbientLightClient) {
+        self.client.set(client);
+    }
+
+    fn read_light_intensity(&self) -> Result<(), ErrorCode> {
+        self.adc.sample()
+    }
+}
+
 /// The type of the sensor implies how the raw ADC reading should be converted
 /// to a temperature value.
 pub enum AnalogTemperatureSensorType {
@@ -0,0 +1,570 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Loads new applications into flash while the kernel is running.
+//!
+//! `AppLoader` receives a TBF object in chunks, writes it to the application
+//! flash region after the last application with `hil::flash`, checks it, and
+//! asks a `kernel::process::ProcessLoader` to load and start it. The other
+//! processes keep running, and the board does not reboot.
+//!
+//! The image is checked as a whole before it is loaded. Its TBF header must
+//! be valid, and one of its credentials footers must be accepted by the
+//! `AppCredentialsChecker` of the loader, which checks it against the
+//! application binary. Images without credentials, such as those with a Main
+//! header rather than a Program header, are not loaded. For instance, with
+//! `AppCheckerSha256` the images must be built with a SHA-256 footer
+//! (`elf2tab --sha256`). The checker must not be the credentials checking
+//! policy of the kernel, whose client is the kernel.
+//!
+//! Pages are erased before they are written. The page the new object starts
+//! in is read first, so the end of the previous application is kept.
+//!
+//! The image is sent with the `appload` command of the process console, so
+//! it can come over the UART or over a network console:
+//!
+//! ```text
+//! tock$ appload begin 2048
+//! App loader: 0/2048 bytes at 0x08021000
+//! tock$ appload w 0200200000080000
+//! App loader: 8/2048 bytes at 0x08021000
+//! ...
+//! tock$ appload
+//! App loader: loaded 2048 bytes at 0x08021000
+//! ```
+//!
+//! `w` takes the next bytes of the image in hexadecimal, at most 11 per
+//! command to fit in the console's command buffer. `abort` drops the image
+//! being received.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let loader = static_init!(
+//!     kernel::process::DynamicProcessLoader<stm32f303xc::chip::Stm32f3xx<Stm32f3xxDefaultPeripherals>>,
+//!     kernel::process::DynamicProcessLoader::new(
+//!         board_kernel,
+//!         chip,
+//!         &mut PROCESSES,
+//!         &FAULT_RESPONSE,
+//!         &process_management_capability,
+//!     )
+//! );
+//! let sha = static_init!(Sha256Software<'static>, Sha256Software::new());
+//! kernel::deferred_call::DeferredCallClient::register(sha);
+//! let checker = static_init!(
+//!     AppCheckerSha256,
+//!     AppCheckerSha256::new(sha, static_init!([u8; 32], [0; 32]))
+//! );
+//! sha.set_client(checker);
+//! let app_loader = components::app_loader::AppLoaderComponent::new(
+//!     &peripherals.flash,
+//!     0x08000000,
+//!     app_flash,
+//!     loader,
+//!     checker,
+//! )
+//! .finalize(components::app_loader_component_static!(
+//!     stm32f303xc::flash::Flash
+//! ));
+//! process_console.add_command(app_loader).unwrap();
+//! ```
+
+use core::cell::Cell;
+use core::fmt;
+
+use capsules_core::process_console::ConsoleCommand;
+use kernel::hil;
+use kernel::process::{ProcessLoadError, ProcessLoader};
+use kernel::process_checker::{AppCredentialsChecker, CheckResult, Client};
+use kernel::utilities::cells::TakeCell;
+use kernel::ErrorCode;
+use tock_tbf::types::{InitialTbfParseError, TbfFooterV2Credentials};
+
+/// Largest chunk of the image `write` accepts.
+pub const MAX_CHUNK: usize = 32;
+
+/// Why an image was not loaded.
+#[derive(Clone, Copy, Debug)]
+pub enum AppLoadError {
+    /// Reading, erasing or writing flash failed.
+    Flash(hil::flash::Error),
+    /// The TBF header gives a total size different from the image's.
+    Length(u32),
+    /// The image is not a valid TBF object, or the kernel could not load it.
+    Load(ProcessLoadError),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    /// Reading the page the image starts in.
+    ReadingFirstPage,
+    Receiving,
+    Erasing,
+    Writing,
+    /// Waiting for the checker to check a credentials footer.
+    Checking,
+}
+
+pub struct AppLoader<'a, F: hil::flash::Flash + 'static> {
+    flash: &'a F,
+    /// Address of page 0 of `flash`.
+    flash_address: usize,
+    /// Application flash region.
+    app_flash: &'static [u8],
+    loader: &'a dyn ProcessLoader,
+    checker: &'a dyn AppCredentialsChecker<'static>,
+    buffer: TakeCell<'static, F::Page>,
+    page_size: usize,
+    state: Cell<State>,
+    /// Address of the image.
+    start: Cell<usize>,
+    length: Cell<usize>,
+    /// Bytes of the image copied into the page buffer.
+    received: Cell<usize>,
+    /// Page in the page buffer.
+    page: Cell<usize>,
+    /// Bytes of the last chunk which belong to the next page.
+    pending: Cell<[u8; MAX_CHUNK]>,
+    pending_len: Cell<usize>,
+    /// Image being checked, and the end of its binary, which the credentials
+    /// footers cover.
+    image: Cell<Option<&'static [u8]>>,
+    binary_end: Cell<usize>,
+    /// Footer being checked.
+    footer: Cell<u32>,
+    /// Result of the last image.
+    result: Cell<Option<Result<(), AppLoadError>>>,
+    /// Result of the last console command.
+    command_result: Cell<Result<(), ErrorCode>>,
+}
+
+impl<'a, F: hil::flash::Flash> AppLoader<'a, F> {
+    pub fn new(
+        flash: &'a F,
+        flash_address: usize,
+        app_flash: &'static [u8],
+        loader: &'a dyn ProcessLoader,
+        checker: &'a dyn AppCredentialsChecker<'static>,
+        buffer: &'static mut F::Page,
+    ) -> Self {
+        let page_size = buffer.as_mut().len();
+        Self {
+            flash,
+            flash_address,
+            app_flash,
+            loader,
+            checker,
+            buffer: TakeCell::new(buffer),
+            page_size,
+            state: Cell::new(State::Idle),
+            start: Cell::new(0),
+            length: Cell::new(0),
+            received: Cell::new(0),
+            page: Cell::new(0),
+            pending: Cell::new([0; MAX_CHUNK]),
+            pending_len: Cell::new(0),
+            image: Cell::new(None),
+            binary_end: Cell::new(0),
+            footer: Cell::new(0),
+            result: Cell::new(None),
+            command_result: Cell::new(Ok(())),
+        }
+    }
+
+    /// Start receiving an image of `length` bytes, to be written after the
+    /// last application.
+    pub fn begin(&self, length: usize) -> Result<(), ErrorCode> {
+        if self.state.get() != State::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        let start = self.end_of_apps();
+        let end = self.app_flash.as_ptr() as usize + self.app_flash.len();
+        if length < 8 || start.saturating_add(length) > end {
+            return Err(ErrorCode::SIZE);
+        }
+
+        self.start.set(start);
+        self.length.set(length);
+        self.received.set(0);
+        self.pending_len.set(0);
+        self.result.set(None);
+        self.page.set(self.page_number(start));
+
+        let buffer = self.buffer.take().ok_or(ErrorCode::FAIL)?;
+        if self.page_offset(start) == 0 {
+            buffer.as_mut().fill(0xFF);
+            self.buffer.replace(buffer);
+            self.state.set(State::Receiving);
+            Ok(())
+        } else {
+            match self.flash.read_page(self.page.get(), buffer) {
+                Ok(()) => {
+                    self.state.set(State::ReadingFirstPage);
+                    Ok(())
+                }
+                Err((e, buffer)) => {
+                    self.buffer.replace(buffer);
+                    Err(e)
+                }
+            }
+        }
+    }
+
+    /// Receive the next bytes of the image. Returns `BUSY` while a page is
+    /// being written.
+    pub fn write(&self, data: &[u8]) -> Result<(), ErrorCode> {
+        match self.state.get() {
+            State::Receiving => {}
+            State::Idle => return Err(ErrorCode::OFF),
+            _ => return Err(ErrorCode::BUSY),
+        }
+        if data.len() > MAX_CHUNK || self.received.get() + data.len() > self.length.get() {
+            return Err(ErrorCode::SIZE);
+        }
+        self.receive(data);
+        Ok(())
+    }
+
+    /// Drop the image being received. Pages already written are left in
+    /// flash, but the image is not loaded.
+    pub fn abort(&self) -> Result<(), ErrorCode> {
+        match self.state.get() {
+            State::Idle => Err(ErrorCode::OFF),
+            State::Receiving => {
+                self.state.set(State::Idle);
+                Ok(())
+            }
+            _ => Err(ErrorCode::BUSY),
+        }
+    }
+
+    /// Check `image`, a TBF object already in the application region, and
+    /// load it. The result is reported like that of a received image.
+    pub fn load(&self, image: &'static [u8]) -> Result<(), ErrorCode> {
+        if self.state.get() != State::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        self.start.set(image.as_ptr() as usize);
+        self.length.set(image.len());
+        self.result.set(None);
+        self.check(image);
+        Ok(())
+    }
+
+    /// Result of the last image, `None` until it is loaded or fails.
+    pub fn result(&self) -> Option<Result<(), AppLoadError>> {
+        match self.state.get() {
+            State::Idle => self.result.get(),
+            _ => None,
+        }
+    }
+
+    /// Address after the last TBF object in the application region.
+    fn end_of_apps(&self) -> usize {
+        let mut offset = 0;
+        while let Some(lengths) = self
+            .app_flash
+            .get(offset..offset + 8)
+            .and_then(|lengths| lengths.try_into().ok())
+        {
+            let entry_length = match tock_tbf::parse::parse_tbf_header_lengths(lengths) {
+                Ok((_, _, entry_length)) => entry_length,
+                Err(InitialTbfParseError::InvalidHeader(entry_length)) => entry_length,
+                Err(InitialTbfParseError::UnableToParse) => break,
+            };
+            if entry_length == 0 {
+                break;
+            }
+            offset += entry_length as usize;
+        }
+        self.app_flash.as_ptr() as usize + offset
+    }
+
+    fn page_number(&self, address: usize) -> usize {
+        (address - self.flash_address) / self.page_size
+    }
+
+    fn page_offset(&self, address: usize) -> usize {
+        (address - self.flash_address) % self.page_size
+    }
+
+    /// Copy `data` into the page buffer, and write the page once it is full
+    /// or the image is complete.
+    fn receive(&self, data: &[u8]) {
+        self.buffer.map(|buffer| {
+            let buffer = buffer.as_mut();
+            for (i, byte) in data.iter().enumerate() {
+                let address = self.start.get() + self.received.get();
+                if self.page_number(address) != self.page.get() {
+                    // The page is full, keep the rest for the next one.
+                    let mut pending = [0; MAX_CHUNK];
+                    pending[..data.len() - i].copy_from_slice(&data[i..]);
+                    self.pending.set(pending);
+                    self.pending_len.set(data.len() - i);
+                    break;
+                }
+                buffer[self.page_offset(address)] = *byte;
+                self.received.set(self.received.get() + 1);
+            }
+        });
+
+        let address = self.start.get() + self.received.get();
+        if self.page_number(address) != self.page.get() || self.received.get() == self.length.get()
+        {
+            self.state.set(State::Erasing);
+            if self.flash.erase_page(self.page.get()).is_err() {
+                self.fail(AppLoadError::Flash(hil::flash::Error::FlashError));
+            }
+        }
+    }
+
+    fn fail(&self, error: AppLoadError) {
+        self.state.set(State::Idle);
+        self.result.set(Some(Err(error)));
+    }
+
+    /// Check the image written to flash and load it.
+    fn complete(&self) {
+        let offset = self.start.get() - self.app_flash.as_ptr() as usize;
+        self.check(&self.app_flash[offset..offset + self.length.get()]);
+    }
+
+    /// Check the header of `image`, then its credentials footers.
+    fn check(&self, image: &'static [u8]) {
+        match verify(image) {
+            Ok(binary_end) => {
+                self.image.set(Some(image));
+                self.binary_end.set(binary_end);
+                self.footer.set(0);
+                self.state.set(State::Checking);
+                self.check_footers();
+            }
+            Err(error) => self.fail(error),
+        }
+    }
+
+    /// Give the checker the credentials footers of the image, from footer
+    /// `self.footer`, until it checks one. Fails if none is left.
+    fn check_footers(&self) {
+        let image = match self.image.get() {
+            Some(image) => image,
+            None => return self.fail(AppLoadError::Load(ProcessLoadError::InternalError)),
+        };
+        let binary = &image[..self.binary_end.get()];
+        while let Some(credentials) = footer(image, binary.len(), self.footer.get()) {
+            match self.checker.check_credentials(credentials, binary) {
+                Ok(()) => return,
+                // Not a kind of credentials the checker supports.
+                Err(_) => self.footer.set(self.footer.get() + 1),
+            }
+        }
+        self.image.set(None);
+        self.fail(AppLoadError::Load(ProcessLoadError::CredentialsNoAccept));
+    }
+
+    /// Run the console command `arguments`.
+    fn run(&self, arguments: &str) -> Result<(), ErrorCode> {
+        let mut words = arguments.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (None, _, _) => Ok(()),
+            (Some("begin"), Some(length), None) => {
+                let length = length.parse::<usize>().or(Err(ErrorCode::INVAL))?;
+                self.begin(length)
+            }
+            (Some("w"), Some(hex), None) => {
+                let mut data = [0; MAX_CHUNK];
+                let length = decode_hex(hex, &mut data)?;
+                self.write(&data[..length])
+            }
+            (Some("abort"), None, _) => self.abort(),
+            _ => Err(ErrorCode::INVAL),
+        }
+    }
+}
+
+/// Check that `image` is a whole TBF object with a valid header, and return
+/// the end of its application binary, after which are the footers.
+///
+/// The header is checked against its checksum. The binary is checked by the
+/// credentials in the footers, which `AppLoader` gives to its checker.
+pub fn verify(image: &'static [u8]) -> Result<usize, AppLoadError> {
+    let lengths = image
+        .get(0..8)
+        .and_then(|lengths| lengths.try_into().ok())
+        .ok_or(AppLoadError::Load(ProcessLoadError::TbfHeaderNotFound))?;
+    let (version, header_length, total_length) = tock_tbf::parse::parse_tbf_header_lengths(lengths)
+        .or(Err(AppLoadError::Load(ProcessLoadError::TbfHeaderNotFound)))?;
+    if total_length as usize != image.len() {
+        return Err(AppLoadError::Length(total_length));
+    }
+    let header = image
+        .get(0..header_length as usize)
+        .ok_or(AppLoadError::Load(ProcessLoadError::NotEnoughFlash))?;
+    // Also checks the checksum of the header.
+    let header = tock_tbf::parse::parse_tbf_header(header, version)
+        .map_err(|err| AppLoadError::Load(err.into()))?;
+    let binary_end = header.get_binary_end() as usize;
+    if binary_end < header_length as usize || binary_end > image.len() {
+        return Err(AppLoadError::Load(ProcessLoadError::NotEnoughFlash));
+    }
+    Ok(binary_end)
+}
+
+/// Credentials footer number `index` of `image`, whose footers start at
+/// `binary_end`. Returns `None` after the last footer, or at an invalid one.
+fn footer(image: &'static [u8], binary_end: usize, index: u32) -> Option<TbfFooterV2Credentials> {
+    let mut footers = image.get(binary_end..)?;
+    for _ in 0..index {
+        let (_, length) = tock_tbf::parse::parse_tbf_footer(footers).ok()?;
+        footers = footers.get(length as usize + 4..)?;
+    }
+    tock_tbf::parse::parse_tbf_footer(footers)
+        .ok()
+        .map(|(credentials, _)| credentials)
+}
+
+/// Decode the hexadecimal string `hex` into `data`, returning the number of
+/// bytes.
+fn decode_hex(hex: &str, data: &mut [u8]) -> Result<usize, ErrorCode> {
+    let hex = hex.as_bytes();
+    if hex.len() % 2 != 0 {
+        return Err(ErrorCode::INVAL);
+    }
+    if hex.len() / 2 > data.len() {
+        return Err(ErrorCode::SIZE);
+    }
+    for (byte, digits) in data.iter_mut().zip(hex.chunks(2)) {
+        let digits = core::str::from_utf8(digits).or(Err(ErrorCode::INVAL))?;
+        *byte = u8::from_str_radix(digits, 16).or(Err(ErrorCode::INVAL))?;
+    }
+    Ok(hex.len() / 2)
+}
+
+impl<'a, F: hil::flash::Flash> hil::flash::Client<F> for AppLoader<'a, F> {
+    fn read_complete(&self, read_buffer: &'static mut F::Page, error: hil::flash::Error) {
+        self.buffer.replace(read_buffer);
+        match error {
+            hil::flash::Error::CommandComplete => self.state.set(State::Receiving),
+            _ => self.fail(AppLoadError::Flash(error)),
+        }
+    }
+
+    fn write_complete(&self, write_buffer: &'static mut F::Page, error: hil::flash::Error) {
+        self.buffer.replace(write_buffer);
+        if error != hil::flash::Error::CommandComplete {
+            self.fail(AppLoadError::Flash(error));
+            return;
+        }
+        if self.received.get() == self.length.get() {
+            self.complete();
+            return;
+        }
+
+        self.buffer.map(|buffer| buffer.as_mut().fill(0xFF));
+        self.page.set(self.page.get() + 1);
+        self.state.set(State::Receiving);
+        let pending_len = self.pending_len.replace(0);
+        if pending_len > 0 {
+            self.receive(&self.pending.get()[..pending_len]);
+        }
+    }
+
+    fn erase_complete(&self, error: hil::flash::Error) {
+        if error != hil::flash::Error::CommandComplete {
+            self.fail(AppLoadError::Flash(error));
+            return;
+        }
+        if let Some(buffer) = self.buffer.take() {
+            self.state.set(State::Writing);
+            if let Err((_, buffer)) = self.flash.write_page(self.page.get(), buffer) {
+                self.buffer.replace(buffer);
+                self.fail(AppLoadError::Flash(hil::flash::Error::FlashError));
+            }
+        }
+    }
+}
+
+impl<'a, F: hil::flash::Flash> Client<'static> for AppLoader<'a, F> {
+    fn check_done(
+        &self,
+        result: Result<CheckResult, ErrorCode>,
+        _credentials: TbfFooterV2Credentials,
+        _binary: &'static [u8],
+    ) {
+        if self.state.get() != State::Checking {
+            return;
+        }
+        match result {
+            Ok(CheckResult::Accept) => {
+                self.state.set(State::Idle);
+                self.result.set(Some(self.image.take().map_or(
+                    Err(AppLoadError::Load(ProcessLoadError::InternalError)),
+                    |image| self.loader.load_process(image).map_err(AppLoadError::Load),
+                )));
+            }
+            Ok(CheckResult::Reject) => {
+                self.image.set(None);
+                self.fail(AppLoadError::Load(ProcessLoadError::CredentialsReject(
+                    self.footer.get(),
+                )));
+            }
+            // Try the next footer.
+            Ok(CheckResult::Pass) | Err(_) => {
+                self.footer.set(self.footer.get() + 1);
+                self.check_footers();
+            }
+        }
+    }
+}
+
+impl<'a, F: hil::flash::Flash> ConsoleCommand for AppLoader<'a, F> {
+    fn name(&self) -> &'static str {
+        "appload"
+    }
+
+    fn execute(&self, arguments: &str) {
+        self.command_result.set(self.run(arguments));
+    }
+
+    fn write_line(&self, index: usize, writer: &mut dyn fmt::Write) -> bool {
+        match index {
+            0 => {
+                let _ = match (self.state.get(), self.result.get()) {
+                    (State::Idle, None) => writer.write_str("App loader: idle\r\n"),
+                    (State::Idle, Some(Ok(()))) => writer.write_fmt(format_args!(
+                        "App loader: loaded {} bytes at {:#010x}\r\n",
+                        self.length.get(),
+                        self.start.get()
+                    )),
+                    (State::Idle, Some(Err(error))) => {
+                        writer.write_fmt(format_args!("App loader: failed: {:?}\r\n", error))
+                    }
+                    (State::Checking, _) => writer.write_fmt(format_args!(
+                        "App loader: checking {} bytes at {:#010x}\r\n",
+                        self.length.get(),
+                        self.start.get()
+                    )),
+                    _ => writer.write_fmt(format_args!(
+                        "App loader: {}/{} bytes at {:#010x}\r\n",
+                        self.received.get(),
+                        self.length.get(),
+                        self.start.get()
+                    )),
+                };
+                true
+            }
+            1 => match self.command_result.get() {
+                Ok(()) => false,
+                Err(error) => {
+                    let _ = writer.write_fmt(format_args!(" Error: {:?}\r\n", error));
+                    true
+                }
+            },
+            _ => false,
+        }
+    }
+}
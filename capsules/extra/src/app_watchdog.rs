@@ -0,0 +1,293 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Per-application software watchdogs backed by the hardware watchdog.
+//!
+//! Each application can enable its own watchdog with a timeout and must then
+//! pet it with a command before the timeout expires. The capsule keeps a
+//! single virtual alarm armed for the earliest deadline of all applications.
+//!
+//! What happens when an application misses its deadline is decided by the
+//! board through `MissedDeadlinePolicy`:
+//!
+//! - `RestartProcess`: only the offending process is restarted.
+//! - `ResetBoard`: the capsule stops tickling the hardware watchdog, which
+//!   then resets the chip.
+//!
+//! For `ResetBoard` to work, the board must use `AppWatchdog` as the
+//! `WatchDog` of its `KernelResources`, wrapping the chip watchdog. The
+//! kernel loop then tickles the hardware through this capsule.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let app_watchdog = components::app_watchdog::AppWatchdogComponent::new(
+//!     board_kernel,
+//!     capsules_extra::app_watchdog::DRIVER_NUM,
+//!     mux_alarm,
+//!     &peripherals.watchdog,
+//!     capsules_extra::app_watchdog::MissedDeadlinePolicy::RestartProcess,
+//! )
+//! .finalize(components::app_watchdog_component_static!(
+//!     stm32f303xc::tim2::Tim2,
+//!     stm32f303xc::wdt::WindoWdg<'static>,
+//! ));
+//! ```
+//!
+//! Syscall interface
+//! -----------------
+//!
+//! - Command 0: driver existence check.
+//! - Command 1: enable the watchdog of the calling app, with a timeout of
+//!   `data1` milliseconds.
+//! - Command 2: pet the watchdog, restarting the timeout.
+//! - Command 3: disable the watchdog of the calling app.
+
+use core::cell::Cell;
+
+use kernel::capabilities::ProcessManagementCapability;
+use kernel::debug;
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::time::{self, Alarm, ConvertTicks, Ticks, Ticks32};
+use kernel::platform::watchdog::WatchDog;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::{ErrorCode, Kernel, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::AppWatchdog as usize;
+
+/// What to do when an application does not pet its watchdog in time.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MissedDeadlinePolicy {
+    /// Restart the process that missed its deadline.
+    RestartProcess,
+    /// Let the hardware watchdog reset the board.
+    ResetBoard,
+}
+
+#[derive(Default)]
+pub struct App {
+    /// Whether the watchdog of this app is enabled.
+    enabled: bool,
+    /// Lower 32 bits of the time the watchdog was last pet, in ticks.
+    reference: u32,
+    /// Timeout, in ticks.
+    timeout: u32,
+}
+
+impl App {
+    fn expired(&self, now: Ticks32) -> bool {
+        let reference = Ticks32::from(self.reference);
+        !now.within_range(
+            reference,
+            reference.wrapping_add(Ticks32::from(self.timeout)),
+        )
+    }
+
+    fn remaining(&self, now: Ticks32) -> u32 {
+        Ticks32::from(self.reference)
+            .wrapping_add(Ticks32::from(self.timeout))
+            .wrapping_sub(now)
+            .into_u32()
+    }
+}
+
+pub struct AppWatchdog<'a, A: Alarm<'a>, W: WatchDog, C: ProcessManagementCapability> {
+    kernel: &'static Kernel,
+    alarm: &'a A,
+    watchdog: &'a W,
+    capability: C,
+    policy: MissedDeadlinePolicy,
+    apps: Grant<App, UpcallCount<0>, AllowRoCount<0>, AllowRwCount<0>>,
+    /// Set once an app missed its deadline under `ResetBoard`; the hardware
+    /// watchdog is no longer tickled.
+    expired: Cell<bool>,
+}
+
+impl<'a, A: Alarm<'a>, W: WatchDog, C: ProcessManagementCapability> AppWatchdog<'a, A, W, C> {
+    pub fn new(
+        kernel: &'static Kernel,
+        alarm: &'a A,
+        watchdog: &'a W,
+        capability: C,
+        policy: MissedDeadlinePolicy,
+        grant: Grant<App, UpcallCount<0>, AllowRoCount<0>, AllowRwCount<0>>,
+    ) -> Self {
+        AppWatchdog {
+            kernel,
+            alarm,
+            watchdog,
+            capability,
+            policy,
+            apps: grant,
+            expired: Cell::new(false),
+        }
+    }
+
+    fn now(&self) -> Ticks32 {
+        Ticks32::from(self.alarm.now().into_u32())
+    }
+
+    /// Arm the alarm for the earliest deadline of all enabled watchdogs.
+    fn reschedule(&self) {
+        let now = self.now();
+        let mut earliest: Option<u32> = None;
+        for app in self.apps.iter() {
+            app.enter(|app, _| {
+                if app.enabled {
+                    let remaining = if app.expired(now) {
+                        0
+                    } else {
+                        app.remaining(now)
+                    };
+                    earliest = Some(earliest.map_or(remaining, |e| e.min(remaining)));
+                }
+            });
+        }
+
+        match earliest {
+            Some(remaining) => self
+                .alarm
+                .set_alarm(self.alarm.now(), A::Ticks::from(remaining)),
+            None => {
+                let _ = self.alarm.disarm();
+            }
+        }
+    }
+
+    /// Find a process whose watchdog expired and disable its watchdog.
+    fn take_expired_process(&self, now: Ticks32) -> Option<ProcessId> {
+        for app in self.apps.iter() {
+            let processid = app.processid();
+            let expired = app.enter(|app, _| {
+                if app.enabled && app.expired(now) {
+                    app.enabled = false;
+                    true
+                } else {
+                    false
+                }
+            });
+            if expired {
+                return Some(processid);
+            }
+        }
+        None
+    }
+}
+
+impl<'a, A: Alarm<'a>, W: WatchDog, C: ProcessManagementCapability> time::AlarmClient
+    for AppWatchdog<'a, A, W, C>
+{
+    fn alarm(&self) {
+        let now = self.now();
+        // Restarting a process frees its grant, so the grant iteration must
+        // be finished before acting on an expired process.
+        while let Some(processid) = self.take_expired_process(now) {
+            match self.policy {
+                MissedDeadlinePolicy::RestartProcess => {
+                    self.kernel.process_map_or_external(
+                        (),
+                        processid,
+                        |process| process.try_restart(None),
+                        &self.capability,
+                    );
+                }
+                MissedDeadlinePolicy::ResetBoard => {
+                    debug!(
+                        "app_watchdog: {:?} missed its deadline, resetting",
+                        processid
+                    );
+                    self.expired.set(true);
+                }
+            }
+        }
+        self.reschedule();
+    }
+}
+
+impl<'a, A: Alarm<'a>, W: WatchDog, C: ProcessManagementCapability> WatchDog
+    for AppWatchdog<'a, A, W, C>
+{
+    fn setup(&self) {
+        self.watchdog.setup();
+    }
+
+    fn tickle(&self) {
+        if !self.expired.get() {
+            self.watchdog.tickle();
+        }
+    }
+
+    fn suspend(&self) {
+        self.watchdog.suspend();
+    }
+
+    fn resume(&self) {
+        self.watchdog.resume();
+    }
+}
+
+impl<'a, A: Alarm<'a>, W: WatchDog, C: ProcessManagementCapability> SyscallDriver
+    for AppWatchdog<'a, A, W, C>
+{
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        _data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        let now = self.now();
+        let result = self.apps.enter(processid, |app, _| match command_num {
+            0 => Ok(false),
+
+            1 => {
+                let timeout = self.alarm.ticks_from_ms(data1 as u32);
+                // Deadlines are tracked with 32-bit ticks.
+                if data1 == 0 || timeout.into_u64() > (u32::MAX / 2) as u64 {
+                    return Err(ErrorCode::INVAL);
+                }
+                app.enabled = true;
+                app.reference = now.into_u32();
+                app.timeout = timeout.into_u32();
+                Ok(true)
+            }
+
+            2 => {
+                if !app.enabled {
+                    return Err(ErrorCode::OFF);
+                }
+                app.reference = now.into_u32();
+                Ok(true)
+            }
+
+            3 => {
+                if !app.enabled {
+                    return Err(ErrorCode::ALREADY);
+                }
+                app.enabled = false;
+                Ok(true)
+            }
+
+            _ => Err(ErrorCode::NOSUPPORT),
+        });
+
+        match result {
+            Ok(Ok(reschedule)) => {
+                if reschedule {
+                    self.reschedule();
+                }
+                CommandReturn::success()
+            }
+            Ok(Err(e)) => CommandReturn::failure(e),
+            Err(e) => CommandReturn::failure(e.into()),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
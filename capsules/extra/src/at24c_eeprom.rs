@@ -0,0 +1,332 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Driver for AT24C and M24C I2C EEPROMs.
+//!
+//! <https://ww1.microchip.com/downloads/en/DeviceDoc/AT24C32-AT24C64-Data-Sheet-DS20006150.pdf>
+//!
+//! These EEPROMs are small, byte-addressable nonvolatile memories, which
+//! boards can use to store configuration when they have no flash to spare.
+//! The driver implements `hil::nonvolatile_storage::NonvolatileStorage`, so
+//! it can be used with the `nonvolatile_storage_driver` capsule.
+//!
+//! Writes are split at the boundaries of the EEPROM pages, as a write which
+//! crosses a page boundary wraps around to the start of the page. After each
+//! page write, the EEPROM does not answer while it commits the data, which
+//! takes up to 5 ms. The driver polls the EEPROM with an alarm until it
+//! acknowledges its address again.
+//!
+//! Chips of up to 256 bytes use 1-byte memory addresses, and larger chips
+//! 2-byte addresses. The AT24C04 to AT24C16, which take the high bits of the
+//! memory address in the I2C address, are only accessible as a 256 byte
+//! EEPROM.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let eeprom = components::at24c_eeprom::At24cComponent::new(
+//!     mux_i2c,
+//!     0x50,
+//!     mux_alarm,
+//!     8192, // AT24C64
+//!     32,
+//! )
+//! .finalize(components::at24c_component_static!(
+//!     nrf52840::rtc::Rtc<'static>,
+//!     nrf52840::i2c::TWI
+//! ));
+//! ```
+
+use core::cell::Cell;
+use core::cmp;
+use kernel::hil;
+use kernel::hil::i2c;
+use kernel::hil::time::ConvertTicks;
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+/// Largest supported page size.
+pub const MAX_PAGE_SIZE: usize = 64;
+
+/// Memory address followed by up to a page of data.
+pub const BUF_LEN: usize = MAX_PAGE_SIZE + 2;
+
+/// Interval between two polls of the EEPROM while it commits a page.
+const POLL_INTERVAL_MS: u32 = 1;
+
+/// Number of polls after which the write is abandoned, well above the 5 ms
+/// maximum write cycle time.
+const MAX_POLLS: usize = 20;
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    Read,
+    Write,
+    /// Waiting for the end of the write cycle
+    Poll,
+}
+
+pub struct AT24C<'a, A: hil::time::Alarm<'a>, I: i2c::I2CDevice> {
+    i2c: &'a I,
+    alarm: &'a A,
+    state: Cell<State>,
+    buffer: TakeCell<'static, [u8]>,
+    client: OptionalCell<&'a dyn hil::nonvolatile_storage::NonvolatileStorageClient>,
+    client_buffer: TakeCell<'static, [u8]>,
+    /// Size of the EEPROM in bytes
+    size: usize,
+    page_size: usize,
+    /// Length of the memory address sent before the data
+    address_len: usize,
+    /// EEPROM address of the client buffer
+    address: Cell<usize>,
+    /// Total length of the current operation
+    length: Cell<usize>,
+    /// Number of bytes of the client buffer already transferred
+    offset: Cell<usize>,
+    /// Length of the transfer in progress
+    chunk: Cell<usize>,
+    polls: Cell<usize>,
+}
+
+impl<'a, A: hil::time::Alarm<'a>, I: i2c::I2CDevice> AT24C<'a, A, I> {
+    /// Create a driver for an EEPROM of `size` bytes, written in pages of
+    /// `page_size` bytes. Pages larger than `MAX_PAGE_SIZE` are written
+    /// in several parts.
+    pub fn new(
+        i2c: &'a I,
+        alarm: &'a A,
+        buffer: &'static mut [u8],
+        size: usize,
+        page_size: usize,
+    ) -> AT24C<'a, A, I> {
+        AT24C {
+            i2c,
+            alarm,
+            state: Cell::new(State::Idle),
+            buffer: TakeCell::new(buffer),
+            client: OptionalCell::empty(),
+            client_buffer: TakeCell::empty(),
+            size,
+            page_size,
+            address_len: if size > 256 { 2 } else { 1 },
+            address: Cell::new(0),
+            length: Cell::new(0),
+            offset: Cell::new(0),
+            chunk: Cell::new(0),
+            polls: Cell::new(0),
+        }
+    }
+
+    /// Check and record a new operation on the client buffer.
+    fn setup(
+        &self,
+        state: State,
+        buffer: &'static mut [u8],
+        address: usize,
+        length: usize,
+    ) -> Result<(), ErrorCode> {
+        if self.state.get() != State::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        if length == 0 || length > buffer.len() || address + length > self.size {
+            return Err(ErrorCode::INVAL);
+        }
+        self.client_buffer.replace(buffer);
+        self.address.set(address);
+        self.length.set(length);
+        self.offset.set(0);
+        self.state.set(state);
+        self.i2c.enable();
+        Ok(())
+    }
+
+    /// Write the memory address of the next transfer at the start of
+    /// `buffer`.
+    fn fill_address(&self, buffer: &mut [u8]) {
+        let address = self.address.get() + self.offset.get();
+        if self.address_len == 2 {
+            buffer[0] = (address >> 8) as u8;
+            buffer[1] = address as u8;
+        } else {
+            buffer[0] = address as u8;
+        }
+    }
+
+    fn read_chunk(&self) -> Result<(), ErrorCode> {
+        let buffer = self.buffer.take().ok_or(ErrorCode::RESERVE)?;
+        let chunk = cmp::min(self.length.get() - self.offset.get(), buffer.len());
+        self.chunk.set(chunk);
+        self.fill_address(buffer);
+        self.i2c
+            .write_read(buffer, self.address_len, chunk)
+            .map_err(|(error, buffer)| {
+                self.buffer.replace(buffer);
+                error.into()
+            })
+    }
+
+    fn write_chunk(&self) -> Result<(), ErrorCode> {
+        let buffer = self.buffer.take().ok_or(ErrorCode::RESERVE)?;
+        let offset = self.offset.get();
+        let address = self.address.get() + offset;
+        let chunk = cmp::min(
+            cmp::min(
+                self.length.get() - offset,
+                self.page_size - address % self.page_size,
+            ),
+            buffer.len() - self.address_len,
+        );
+        self.chunk.set(chunk);
+        self.fill_address(buffer);
+        self.client_buffer.map(|client_buffer| {
+            buffer[self.address_len..self.address_len + chunk]
+                .copy_from_slice(&client_buffer[offset..offset + chunk]);
+        });
+        self.i2c
+            .write(buffer, self.address_len + chunk)
+            .map_err(|(error, buffer)| {
+                self.buffer.replace(buffer);
+                error.into()
+            })
+    }
+
+    /// Address the EEPROM, which it only acknowledges once the write cycle
+    /// is over.
+    fn poll(&self) -> Result<(), ErrorCode> {
+        let buffer = self.buffer.take().ok_or(ErrorCode::RESERVE)?;
+        self.fill_address(buffer);
+        self.i2c
+            .write(buffer, self.address_len)
+            .map_err(|(error, buffer)| {
+                self.buffer.replace(buffer);
+                error.into()
+            })
+    }
+
+    fn wait_write_cycle(&self) {
+        self.alarm
+            .set_alarm(self.alarm.now(), self.alarm.ticks_from_ms(POLL_INTERVAL_MS));
+    }
+
+    /// End the current operation and return the client buffer, with the
+    /// number of bytes transferred.
+    fn done(&self) {
+        let state = self.state.replace(State::Idle);
+        self.i2c.disable();
+        let length = self.offset.get();
+        self.client_buffer.take().map(|buffer| {
+            self.client.map(move |client| match state {
+                State::Read => client.read_done(buffer, length),
+                _ => client.write_done(buffer, length),
+            });
+        });
+    }
+}
+
+impl<'a, A: hil::time::Alarm<'a>, I: i2c::I2CDevice> i2c::I2CClient for AT24C<'a, A, I> {
+    fn command_complete(&self, buffer: &'static mut [u8], status: Result<(), i2c::Error>) {
+        let chunk = self.chunk.get();
+        let offset = self.offset.get();
+
+        let result = match (self.state.get(), status) {
+            (State::Read, Ok(())) => {
+                self.client_buffer.map(|client_buffer| {
+                    client_buffer[offset..offset + chunk].copy_from_slice(&buffer[..chunk]);
+                });
+                self.buffer.replace(buffer);
+                self.offset.set(offset + chunk);
+                if offset + chunk == self.length.get() {
+                    self.done();
+                    Ok(())
+                } else {
+                    self.read_chunk()
+                }
+            }
+            (State::Write, Ok(())) => {
+                self.buffer.replace(buffer);
+                self.offset.set(offset + chunk);
+                self.polls.set(0);
+                self.state.set(State::Poll);
+                self.wait_write_cycle();
+                Ok(())
+            }
+            (State::Poll, Ok(())) => {
+                self.buffer.replace(buffer);
+                if offset == self.length.get() {
+                    self.done();
+                    Ok(())
+                } else {
+                    self.state.set(State::Write);
+                    self.write_chunk()
+                }
+            }
+            (State::Poll, Err(i2c::Error::AddressNak)) => {
+                self.buffer.replace(buffer);
+                let polls = self.polls.get() + 1;
+                self.polls.set(polls);
+                if polls < MAX_POLLS {
+                    self.wait_write_cycle();
+                    Ok(())
+                } else {
+                    Err(ErrorCode::FAIL)
+                }
+            }
+            (_, status) => {
+                self.buffer.replace(buffer);
+                status.map_err(|error| error.into())
+            }
+        };
+
+        if result.is_err() {
+            self.done();
+        }
+    }
+}
+
+impl<'a, A: hil::time::Alarm<'a>, I: i2c::I2CDevice> hil::time::AlarmClient for AT24C<'a, A, I> {
+    fn alarm(&self) {
+        if self.state.get() == State::Poll && self.poll().is_err() {
+            self.done();
+        }
+    }
+}
+
+impl<'a, A: hil::time::Alarm<'a>, I: i2c::I2CDevice>
+    hil::nonvolatile_storage::NonvolatileStorage<'a> for AT24C<'a, A, I>
+{
+    fn set_client(&self, client: &'a dyn hil::nonvolatile_storage::NonvolatileStorageClient) {
+        self.client.set(client);
+    }
+
+    fn read(
+        &self,
+        buffer: &'static mut [u8],
+        address: usize,
+        length: usize,
+    ) -> Result<(), ErrorCode> {
+        self.setup(State::Read, buffer, address, length)?;
+        self.read_chunk().map_err(|e| {
+            self.state.set(State::Idle);
+            self.i2c.disable();
+            e
+        })
+    }
+
+    fn write(
+        &self,
+        buffer: &'static mut [u8],
+        address: usize,
+        length: usize,
+    ) -> Result<(), ErrorCode> {
+        self.setup(State::Write, buffer, address, length)?;
+        self.write_chunk().map_err(|e| {
+            self.state.set(State::Idle);
+            self.i2c.disable();
+            e
+        })
+    }
+}
@@ -0,0 +1,367 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Runs AT commands over a UART, for the capsules of modules controlled with
+//! them: cellular modems, WiFi and Bluetooth modules.
+//!
+//! The engine sends one command at a time and splits what the module sends
+//! into lines, which it dispatches to its client:
+//!
+//! - the echo of the command, a line starting with `AT`, is dropped;
+//! - unsolicited result codes (URCs) go to `AtClient::unsolicited`, which
+//!   recognizes them, at any time;
+//! - other lines are responses of the command, which go to
+//!   `AtClient::response` until it returns the result of the command.
+//!
+//! A `>` at the start of a line while a command runs is the prompt for the
+//! data of the command, which the client sends with `send_data`. The client
+//! can also ask for a number of raw bytes after a response line, such as the
+//! data read from a socket, with `receive_data`.
+//!
+//! Each command has a timeout, timed with an alarm, after which it ends with
+//! `NOACK`.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let engine = components::at_engine::AtEngineComponent::new(
+//!     &peripherals.uart1,
+//!     mux_alarm,
+//!     115200,
+//! )
+//! .finalize(components::at_engine_component_static!(
+//!     rp2040::uart::Uart<'static>,
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+
+use core::cell::Cell;
+use core::fmt;
+
+use kernel::hil::time::{Alarm, AlarmClient, ConvertTicks};
+use kernel::hil::uart;
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+/// Longest line kept, longer lines are truncated.
+pub const LINE_LEN: usize = 128;
+/// Size of the transmit buffer, the longest command or data sent at once.
+pub const BUFFER_LEN: usize = 512;
+
+/// The result of a line if it is a final result code of V.250 or 27.007.
+pub fn result_code(line: &[u8]) -> Option<Result<(), ErrorCode>> {
+    match line {
+        b"OK" => Some(Ok(())),
+        b"ERROR" | b"NO CARRIER" | b"NO DIALTONE" | b"BUSY" | b"NO ANSWER" => {
+            Some(Err(ErrorCode::FAIL))
+        }
+        _ if line.starts_with(b"+CME ERROR:") || line.starts_with(b"+CMS ERROR:") => {
+            Some(Err(ErrorCode::FAIL))
+        }
+        _ => None,
+    }
+}
+
+/// Number after `prefix` in `line`, and after `skip` more commas, such as
+/// the length in `+QIRD: 12`.
+pub fn parse_number(line: &[u8], prefix: &[u8], skip: usize) -> Option<usize> {
+    let field = line.strip_prefix(prefix)?.split(|c| *c == b',').nth(skip)?;
+    let field = core::str::from_utf8(field).ok()?;
+    field.trim().parse().ok()
+}
+
+/// Writes a command in the transmit buffer of the engine.
+pub struct CommandWriter<'b> {
+    buffer: &'b mut [u8],
+    len: usize,
+}
+
+impl CommandWriter<'_> {
+    /// Append `bytes`, returns `SIZE` if they do not fit.
+    pub fn push(&mut self, bytes: &[u8]) -> Result<(), ErrorCode> {
+        let end = self.len + bytes.len();
+        self.buffer
+            .get_mut(self.len..end)
+            .ok_or(ErrorCode::SIZE)?
+            .copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl fmt::Write for CommandWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push(s.as_bytes()).or(Err(fmt::Error))
+    }
+}
+
+/// Client of the engine, the capsule of a module.
+pub trait AtClient {
+    /// Called with every line but echoes. Returns `true` if the line is an
+    /// unsolicited result code, which is then not a response.
+    fn unsolicited(&self, line: &[u8]) -> bool;
+
+    /// Called with the response lines of the command. Returns the result of
+    /// the command if the line ends it. By default, the command ends with
+    /// the standard final result codes.
+    fn response(&self, line: &[u8]) -> Option<Result<(), ErrorCode>> {
+        result_code(line)
+    }
+
+    /// The module prompts for the data of the command.
+    fn prompt(&self) {}
+
+    /// A byte of the data asked for with `receive_data`.
+    fn data_received(&self, _byte: u8) {}
+
+    /// The command is over, with `Err(NOACK)` if it timed out. The client
+    /// can send the next command from this callback.
+    fn command_done(&self, result: Result<(), ErrorCode>);
+}
+
+pub struct AtEngine<'a, U: uart::Uart<'a>, A: Alarm<'a>> {
+    uart: &'a U,
+    alarm: &'a A,
+    baud_rate: u32,
+    client: OptionalCell<&'a dyn AtClient>,
+    running: Cell<bool>,
+    /// Bytes of raw data left to receive
+    data_remaining: Cell<usize>,
+    rx_byte: TakeCell<'static, [u8]>,
+    line: TakeCell<'static, [u8]>,
+    line_len: Cell<usize>,
+    tx_buffer: TakeCell<'static, [u8]>,
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> AtEngine<'a, U, A> {
+    pub fn new(
+        uart: &'a U,
+        alarm: &'a A,
+        baud_rate: u32,
+        rx_byte: &'static mut [u8; 1],
+        line: &'static mut [u8; LINE_LEN],
+        tx_buffer: &'static mut [u8; BUFFER_LEN],
+    ) -> AtEngine<'a, U, A> {
+        AtEngine {
+            uart,
+            alarm,
+            baud_rate,
+            client: OptionalCell::empty(),
+            running: Cell::new(false),
+            data_remaining: Cell::new(0),
+            rx_byte: TakeCell::new(rx_byte),
+            line: TakeCell::new(line),
+            line_len: Cell::new(0),
+            tx_buffer: TakeCell::new(tx_buffer),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn AtClient) {
+        self.client.set(client);
+    }
+
+    /// Configure the UART for `baud_rate` 8N1 and start receiving what the
+    /// module sends.
+    pub fn initialize(&self) -> Result<(), ErrorCode> {
+        self.uart.configure(uart::Parameters {
+            baud_rate: self.baud_rate,
+            width: uart::Width::Eight,
+            parity: uart::Parity::None,
+            stop_bits: uart::StopBits::One,
+            hw_flow_control: false,
+        })?;
+        self.receive_byte()
+    }
+
+    fn receive_byte(&self) -> Result<(), ErrorCode> {
+        self.rx_byte.take().map_or(Err(ErrorCode::BUSY), |rx_byte| {
+            self.uart
+                .receive_buffer(rx_byte, 1)
+                .map_err(|(err, rx_byte)| {
+                    self.rx_byte.replace(rx_byte);
+                    err
+                })
+        })
+    }
+
+    /// Whether a command runs.
+    pub fn is_busy(&self) -> bool {
+        self.running.get()
+    }
+
+    /// Send the command written by `write`, such as `AT+CGATT=1`, which the
+    /// engine ends with a carriage return. The command fails after
+    /// `timeout_ms`.
+    pub fn command<F>(&self, timeout_ms: u32, write: F) -> Result<(), ErrorCode>
+    where
+        F: FnOnce(&mut CommandWriter) -> Result<(), ErrorCode>,
+    {
+        if self.running.get() {
+            return Err(ErrorCode::BUSY);
+        }
+        let tx_buffer = self.tx_buffer.take().ok_or(ErrorCode::BUSY)?;
+        let mut writer = CommandWriter {
+            buffer: tx_buffer,
+            len: 0,
+        };
+        if let Err(err) = write(&mut writer).and_then(|()| writer.push(b"\r")) {
+            self.tx_buffer.replace(writer.buffer);
+            return Err(err);
+        }
+
+        let len = writer.len;
+        self.uart
+            .transmit_buffer(writer.buffer, len)
+            .map_err(|(err, tx_buffer)| {
+                self.tx_buffer.replace(tx_buffer);
+                err
+            })?;
+        self.running.set(true);
+        self.data_remaining.set(0);
+        self.alarm
+            .set_alarm(self.alarm.now(), self.alarm.ticks_from_ms(timeout_ms));
+        Ok(())
+    }
+
+    /// Send the data of the command after its prompt. `write` fills the
+    /// buffer and returns the number of bytes to send.
+    pub fn send_data<F>(&self, write: F) -> Result<(), ErrorCode>
+    where
+        F: FnOnce(&mut [u8]) -> Result<usize, ErrorCode>,
+    {
+        if !self.running.get() {
+            return Err(ErrorCode::OFF);
+        }
+        let tx_buffer = self.tx_buffer.take().ok_or(ErrorCode::BUSY)?;
+        match write(tx_buffer) {
+            Ok(len) => self
+                .uart
+                .transmit_buffer(tx_buffer, len.min(BUFFER_LEN))
+                .map_err(|(err, tx_buffer)| {
+                    self.tx_buffer.replace(tx_buffer);
+                    err
+                }),
+            Err(err) => {
+                self.tx_buffer.replace(tx_buffer);
+                Err(err)
+            }
+        }
+    }
+
+    /// Pass the next `len` bytes to the client as raw data. Called from
+    /// `AtClient::response` for the line announcing the data.
+    pub fn receive_data(&self, len: usize) {
+        self.data_remaining.set(len);
+    }
+
+    /// End the command without calling the client, cancelling its prompt.
+    pub fn abort(&self) {
+        if self.running.replace(false) {
+            let _ = self.alarm.disarm();
+            self.data_remaining.set(0);
+            self.tx_buffer.take().map(|tx_buffer| {
+                tx_buffer[0] = 0x1B;
+                if let Err((_, tx_buffer)) = self.uart.transmit_buffer(tx_buffer, 1) {
+                    self.tx_buffer.replace(tx_buffer);
+                }
+            });
+        }
+    }
+
+    fn finish(&self, result: Result<(), ErrorCode>) {
+        let _ = self.alarm.disarm();
+        self.running.set(false);
+        self.data_remaining.set(0);
+        self.client.map(|client| client.command_done(result));
+    }
+
+    fn line_received(&self, line: &[u8]) {
+        let running = self.running.get();
+        if running && (line.starts_with(b"AT") || line.starts_with(b"at")) {
+            // Echo of the command
+            return;
+        }
+        let result = self.client.and_then(|client| {
+            if client.unsolicited(line) || !running {
+                None
+            } else {
+                client.response(line)
+            }
+        });
+        if let Some(result) = result {
+            self.finish(result);
+        }
+    }
+
+    fn byte_received(&self, byte: u8) {
+        let remaining = self.data_remaining.get();
+        if remaining > 0 {
+            self.data_remaining.set(remaining - 1);
+            self.client.map(|client| client.data_received(byte));
+            return;
+        }
+
+        let len = self.line_len.get();
+        match byte {
+            b'>' if len == 0 && self.running.get() => {
+                self.client.map(|client| client.prompt());
+            }
+            // Leading spaces, such as after the prompt
+            b' ' if len == 0 => {}
+            b'\r' => {}
+            b'\n' => {
+                self.line_len.set(0);
+                if len > 0 {
+                    self.line.map(|line| self.line_received(&line[..len]));
+                }
+            }
+            _ => {
+                if len < LINE_LEN {
+                    self.line.map(|line| line[len] = byte);
+                    self.line_len.set(len + 1);
+                }
+            }
+        }
+    }
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> AlarmClient for AtEngine<'a, U, A> {
+    fn alarm(&self) {
+        if self.running.get() {
+            self.finish(Err(ErrorCode::NOACK));
+        }
+    }
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> uart::TransmitClient for AtEngine<'a, U, A> {
+    fn transmitted_buffer(
+        &self,
+        tx_buffer: &'static mut [u8],
+        _tx_len: usize,
+        rval: Result<(), ErrorCode>,
+    ) {
+        self.tx_buffer.replace(tx_buffer);
+        if rval.is_err() && self.running.get() {
+            self.finish(rval);
+        }
+    }
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> uart::ReceiveClient for AtEngine<'a, U, A> {
+    fn received_buffer(
+        &self,
+        rx_byte: &'static mut [u8],
+        rx_len: usize,
+        rval: Result<(), ErrorCode>,
+        _error: uart::Error,
+    ) {
+        let byte = rx_byte[0];
+        self.rx_byte.replace(rx_byte);
+        if rval.is_ok() && rx_len == 1 {
+            self.byte_received(byte);
+        }
+        let _ = self.receive_byte();
+    }
+}
@@ -0,0 +1,369 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Automatic screen brightness.
+//!
+//! Reads an ambient light sensor periodically through
+//! `hil::sensors::AmbientLight`, filters the readings, and sets the PWM of
+//! the screen backlight to one of `NUM_LEVELS` brightness levels.
+//!
+//! The level rises above threshold `i` when the filtered light exceeds it by
+//! the hysteresis, and falls below it when the light is lower than it by the
+//! hysteresis, so the backlight does not flicker when the light stays close
+//! to a threshold. The readings are filtered with an exponential moving
+//! average, so a shadow passing over the sensor does not change the level.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let auto_brightness = components::auto_brightness::AutoBrightnessComponent::new(
+//!     board_kernel,
+//!     capsules_extra::auto_brightness::DRIVER_NUM,
+//!     mux_alarm,
+//!     photoresistor,
+//!     backlight_pwm_pin,
+//! )
+//! .finalize(components::auto_brightness_component_static!(
+//!     imxrt1050::gpt::Gpt1<'static>
+//! ));
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! The settings are shared by all applications.
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: return the brightness level and the filtered light, in lux.
+//! - Command 2: enable automatic brightness if `data1` is not 0, disable it
+//!   otherwise. The backlight stays at its brightness when disabled.
+//! - Command 3: disable automatic brightness, and set the backlight to
+//!   `data1` percent.
+//! - Command 4: set threshold `data1` to `data2` lux. The thresholds must
+//!   stay in increasing order.
+//! - Command 5: set the brightness of level `data1` to `data2` percent.
+//! - Command 6: set the hysteresis to `data1` percent of the thresholds.
+//! - Command 7: read the sensor every `data1` milliseconds.
+//!
+//! Upcall 0 is scheduled for every application when the level changes, with
+//! the level and the filtered light.
+
+use core::cell::Cell;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::pwm::PwmPin;
+use kernel::hil::sensors::{AmbientLight, AmbientLightClient};
+use kernel::hil::time::{Alarm, AlarmClient, ConvertTicks};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::AutoBrightness as usize;
+
+/// Number of brightness levels.
+pub const NUM_LEVELS: usize = 4;
+/// Light, in lux, between the levels.
+pub const DEFAULT_THRESHOLDS_LUX: [u32; NUM_LEVELS - 1] = [10, 100, 1000];
+/// Brightness of each level, in percent.
+pub const DEFAULT_BRIGHTNESS_PERCENT: [u32; NUM_LEVELS] = [10, 35, 65, 100];
+pub const DEFAULT_HYSTERESIS_PERCENT: u32 = 20;
+pub const DEFAULT_PERIOD_MS: u32 = 500;
+/// Frequency of the backlight PWM, if the pin supports it.
+pub const BACKLIGHT_FREQUENCY_HZ: usize = 1000;
+
+/// Filters the light readings and selects the brightness level.
+#[derive(Clone, Copy)]
+pub struct LevelSelector {
+    thresholds: [u32; NUM_LEVELS - 1],
+    hysteresis_percent: u32,
+    /// No reading is filtered yet if `None`.
+    filtered: Option<u32>,
+    level: usize,
+}
+
+impl LevelSelector {
+    pub const fn new() -> LevelSelector {
+        LevelSelector {
+            thresholds: DEFAULT_THRESHOLDS_LUX,
+            hysteresis_percent: DEFAULT_HYSTERESIS_PERCENT,
+            filtered: None,
+            level: 0,
+        }
+    }
+
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    pub fn filtered_lux(&self) -> Option<u32> {
+        self.filtered
+    }
+
+    /// Set the threshold between levels `index` and `index + 1`.
+    pub fn set_threshold(&mut self, index: usize, lux: u32) -> Result<(), ErrorCode> {
+        if index >= self.thresholds.len() {
+            return Err(ErrorCode::INVAL);
+        }
+        let above_previous = index == 0 || self.thresholds[index - 1] < lux;
+        let below_next = self
+            .thresholds
+            .get(index + 1)
+            .map_or(true, |&next| lux < next);
+        if !above_previous || !below_next {
+            return Err(ErrorCode::INVAL);
+        }
+        self.thresholds[index] = lux;
+        Ok(())
+    }
+
+    pub fn set_hysteresis(&mut self, percent: u32) -> Result<(), ErrorCode> {
+        if percent > 100 {
+            return Err(ErrorCode::INVAL);
+        }
+        self.hysteresis_percent = percent;
+        Ok(())
+    }
+
+    /// Forget the filtered light, so the next reading selects the level
+    /// from scratch.
+    pub fn reset(&mut self) {
+        self.filtered = None;
+    }
+
+    /// Filter a reading, in lux. Returns the level if it changes, or if it
+    /// is the first reading.
+    pub fn sample(&mut self, lux: u32) -> Option<usize> {
+        let filtered = match self.filtered {
+            // Weight of 1/4 for the new reading
+            Some(filtered) => ((3 * filtered as u64 + lux as u64) / 4) as u32,
+            None => {
+                self.filtered = Some(lux);
+                self.level = self.thresholds.iter().filter(|&&t| lux >= t).count();
+                return Some(self.level);
+            }
+        };
+        self.filtered = Some(filtered);
+
+        let light = filtered as u64 * 100;
+        let above = |threshold: u32| threshold as u64 * (100 + self.hysteresis_percent) as u64;
+        let below = |threshold: u32| threshold as u64 * (100 - self.hysteresis_percent) as u64;
+        let mut level = self.level;
+        while level < NUM_LEVELS - 1 && light >= above(self.thresholds[level]) {
+            level += 1;
+        }
+        while level > 0 && light < below(self.thresholds[level - 1]) {
+            level -= 1;
+        }
+
+        if level != self.level {
+            self.level = level;
+            Some(level)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct AutoBrightness<'a, A: Alarm<'a>> {
+    sensor: &'a dyn AmbientLight<'a>,
+    backlight: &'a dyn PwmPin,
+    alarm: &'a A,
+    apps: Grant<(), UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    selector: Cell<LevelSelector>,
+    brightness_percent: Cell<[u32; NUM_LEVELS]>,
+    period_ms: Cell<u32>,
+    automatic: Cell<bool>,
+    /// A reading of the sensor has not completed.
+    reading: Cell<bool>,
+}
+
+impl<'a, A: Alarm<'a>> AutoBrightness<'a, A> {
+    pub fn new(
+        sensor: &'a dyn AmbientLight<'a>,
+        backlight: &'a dyn PwmPin,
+        alarm: &'a A,
+        grant: Grant<(), UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    ) -> AutoBrightness<'a, A> {
+        AutoBrightness {
+            sensor,
+            backlight,
+            alarm,
+            apps: grant,
+            selector: Cell::new(LevelSelector::new()),
+            brightness_percent: Cell::new(DEFAULT_BRIGHTNESS_PERCENT),
+            period_ms: Cell::new(DEFAULT_PERIOD_MS),
+            automatic: Cell::new(false),
+            reading: Cell::new(false),
+        }
+    }
+
+    /// Start adjusting the brightness to the ambient light.
+    pub fn start(&self) {
+        if self.automatic.get() {
+            return;
+        }
+        self.automatic.set(true);
+        let mut selector = self.selector.get();
+        selector.reset();
+        self.selector.set(selector);
+        if !self.alarm.is_armed() {
+            self.read_light();
+        }
+    }
+
+    /// Stop adjusting the brightness. The backlight keeps its brightness.
+    pub fn stop(&self) {
+        self.automatic.set(false);
+    }
+
+    /// Disable automatic brightness, and set the backlight to `percent`.
+    pub fn set_manual_brightness(&self, percent: u32) -> Result<(), ErrorCode> {
+        if percent > 100 {
+            return Err(ErrorCode::INVAL);
+        }
+        self.stop();
+        self.set_backlight(percent)
+    }
+
+    /// Set the brightness of `level`, in percent.
+    pub fn set_level_brightness(&self, level: usize, percent: u32) -> Result<(), ErrorCode> {
+        if level >= NUM_LEVELS || percent > 100 {
+            return Err(ErrorCode::INVAL);
+        }
+        let mut brightness = self.brightness_percent.get();
+        brightness[level] = percent;
+        self.brightness_percent.set(brightness);
+
+        // Apply it at once if it is the level of the backlight
+        let selector = self.selector.get();
+        if self.automatic.get() && selector.filtered_lux().is_some() && selector.level() == level {
+            self.set_level(level);
+        }
+        Ok(())
+    }
+
+    fn read_light(&self) {
+        // Skip the reading if the sensor is too slow
+        if !self.reading.get() && self.sensor.read_light_intensity().is_ok() {
+            self.reading.set(true);
+        }
+        self.alarm.set_alarm(
+            self.alarm.now(),
+            self.alarm.ticks_from_ms(self.period_ms.get()),
+        );
+    }
+
+    fn set_backlight(&self, percent: u32) -> Result<(), ErrorCode> {
+        if percent == 0 {
+            return self.backlight.stop();
+        }
+        let frequency = BACKLIGHT_FREQUENCY_HZ.min(self.backlight.get_maximum_frequency_hz());
+        let duty_cycle = self.backlight.get_maximum_duty_cycle() * percent as usize / 100;
+        self.backlight.start(frequency, duty_cycle)
+    }
+
+    fn set_level(&self, level: usize) {
+        let _ = self.set_backlight(self.brightness_percent.get()[level]);
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for AutoBrightness<'a, A> {
+    fn alarm(&self) {
+        if self.automatic.get() {
+            self.read_light();
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> AmbientLightClient for AutoBrightness<'a, A> {
+    fn callback(&self, lux: usize) {
+        self.reading.set(false);
+        if !self.automatic.get() {
+            return;
+        }
+
+        let mut selector = self.selector.get();
+        let change = selector.sample(lux.min(u32::MAX as usize) as u32);
+        self.selector.set(selector);
+
+        if let Some(level) = change {
+            self.set_level(level);
+            let filtered = selector.filtered_lux().unwrap_or(0);
+            self.apps.each(|_, _, kernel_data| {
+                kernel_data
+                    .schedule_upcall(0, (level, filtered as usize, 0))
+                    .ok();
+            });
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> SyscallDriver for AutoBrightness<'a, A> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        data2: usize,
+        _processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            1 => {
+                let selector = self.selector.get();
+                CommandReturn::success_u32_u32(
+                    selector.level() as u32,
+                    selector.filtered_lux().unwrap_or(0),
+                )
+            }
+
+            2 => {
+                if data1 != 0 {
+                    self.start();
+                } else {
+                    self.stop();
+                }
+                CommandReturn::success()
+            }
+
+            3 => self
+                .set_manual_brightness(data1.min(u32::MAX as usize) as u32)
+                .into(),
+
+            4 => {
+                let mut selector = self.selector.get();
+                let result = selector.set_threshold(data1, data2.min(u32::MAX as usize) as u32);
+                self.selector.set(selector);
+                result.into()
+            }
+
+            5 => self
+                .set_level_brightness(data1, data2.min(u32::MAX as usize) as u32)
+                .into(),
+
+            6 => {
+                let mut selector = self.selector.get();
+                let result = selector.set_hysteresis(data1.min(u32::MAX as usize) as u32);
+                self.selector.set(selector);
+                result.into()
+            }
+
+            7 => {
+                if data1 == 0 || data1 > u32::MAX as usize {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                self.period_ms.set(data1 as u32);
+                CommandReturn::success()
+            }
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
@@ -0,0 +1,678 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Provides userspace with a serial link to a phone through a UART Bluetooth
+//! module, such as the HC-05 (Bluetooth Classic SPP) or the HM-10 (BLE).
+//!
+//! The module is transparent: the bytes written by the process are sent to
+//! the connected phone, and the bytes the phone sends are kept in a ring
+//! buffer until the process reads them. A read completes once the number of
+//! bytes asked for is there, or `READ_IDLE_MS` after the last byte.
+//!
+//! The module is configured with AT commands, which the capsule sends while
+//! no phone is connected. The HC-05 only takes them while its KEY (or EN)
+//! pin is high, so the capsule holds the pin high during each setting; the
+//! HM-10 takes them whenever it is not connected and needs no pin. The
+//! response to a command ends when the module has been silent for
+//! `QUIET_MS`, as the HM-10 ends its responses without a newline.
+//!
+//! The STATE pin of the module, if wired, reports the connection to the
+//! phone.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let bluetooth = components::bluetooth_serial::BluetoothSerialComponent::new(
+//!     board_kernel,
+//!     capsules_extra::bluetooth_serial::DRIVER_NUM,
+//!     &peripherals.uart1,
+//!     mux_alarm,
+//!     9600,
+//!     capsules_extra::bluetooth_serial::Module::Hc05,
+//!     Some(key_pin),
+//!     Some(state_pin),
+//! )
+//! .finalize(components::bluetooth_serial_component_static!(
+//!     rp2040::uart::Uart<'static>,
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! The first process to use the driver owns the module until it exits, as
+//! for the console, other processes get RESERVE.
+//!
+//! - Read-only allow 0: the value of a setting.
+//! - Read-only allow 1: data to write.
+//! - Read-write allow 1: buffer data is read into.
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: write `data1` bytes.
+//! - Command 2: read up to `data1` bytes.
+//! - Command 3: abort the read.
+//! - Command 4: set the name the module advertises, at most 12 characters
+//!   for the HM-10 and 32 for the HC-05.
+//! - Command 5: set the PIN, 4 digits for the HC-05 and 6 for the HM-10.
+//! - Command 6: whether a phone is connected, NOSUPPORT without the STATE
+//!   pin.
+//!
+//! - Upcall 1: the write is done, with the number of bytes written.
+//! - Upcall 2: the read is done, with its status and the number of bytes
+//!   read.
+//! - Upcall 3: the setting is done, with its status. NOACK if the module
+//!   did not answer.
+//! - Upcall 4: a phone connected (1) or disconnected (0).
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::collections::queue::Queue;
+use kernel::collections::ring_buffer::RingBuffer;
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::gpio;
+use kernel::hil::time::{Alarm, AlarmClient, ConvertTicks};
+use kernel::hil::uart;
+use kernel::processbuffer::{ReadableProcessBuffer, WriteableProcessBuffer};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::{MapCell, OptionalCell, TakeCell};
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::BluetoothSerial as usize;
+
+/// Ids for read-only allow buffers
+mod ro_allow {
+    pub const SETTING: usize = 0;
+    pub const WRITE: usize = 1;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 2;
+}
+
+/// Ids for read-write allow buffers
+mod rw_allow {
+    pub const READ: usize = 1;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 2;
+}
+
+/// Size of the ring buffer of received bytes. Bytes received while it is
+/// full are dropped.
+pub const RX_LEN: usize = 128;
+/// Size of the transmit buffer, writes are sent in chunks of this size.
+pub const TX_LEN: usize = 64;
+/// Longest response to an AT command kept.
+pub const RESPONSE_LEN: usize = 32;
+
+/// Time the module has to start answering an AT command.
+const RESPONSE_TIMEOUT_MS: u32 = 1000;
+/// Silence which ends the response to an AT command.
+const QUIET_MS: u32 = 50;
+/// Silence after which a read completes with fewer bytes than asked for.
+const READ_IDLE_MS: u32 = 20;
+
+/// Bluetooth module.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Module {
+    /// HC-05 and HC-06 Bluetooth Classic modules, firmware 2.0
+    Hc05,
+    /// HM-10 and CC41 BLE modules
+    Hm10,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Setting {
+    Name,
+    Pin,
+}
+
+#[derive(Default)]
+pub struct App;
+
+pub struct BluetoothSerial<'a, U: uart::Uart<'a>, A: Alarm<'a>> {
+    uart: &'a U,
+    alarm: &'a A,
+    baud_rate: u32,
+    module: Module,
+    key_pin: Option<&'a dyn gpio::Pin>,
+    state_pin: Option<&'a dyn gpio::InterruptPin<'a>>,
+    apps: Grant<
+        App,
+        UpcallCount<5>,
+        AllowRoCount<{ ro_allow::COUNT }>,
+        AllowRwCount<{ rw_allow::COUNT }>,
+    >,
+    owner: OptionalCell<ProcessId>,
+    rx_byte: TakeCell<'static, [u8]>,
+    rx_ring: MapCell<RingBuffer<'static, u8>>,
+    /// Length of the read in progress
+    read_len: OptionalCell<usize>,
+    tx_buffer: TakeCell<'static, [u8]>,
+    /// Length of the write in progress, and bytes already sent
+    write_len: OptionalCell<usize>,
+    written: Cell<usize>,
+    setting: OptionalCell<Setting>,
+    /// AT command of the setting being sent
+    step: Cell<usize>,
+    response: TakeCell<'static, [u8]>,
+    response_len: Cell<usize>,
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> BluetoothSerial<'a, U, A> {
+    pub fn new(
+        uart: &'a U,
+        alarm: &'a A,
+        baud_rate: u32,
+        module: Module,
+        key_pin: Option<&'a dyn gpio::Pin>,
+        state_pin: Option<&'a dyn gpio::InterruptPin<'a>>,
+        grant: Grant<
+            App,
+            UpcallCount<5>,
+            AllowRoCount<{ ro_allow::COUNT }>,
+            AllowRwCount<{ rw_allow::COUNT }>,
+        >,
+        rx_byte: &'static mut [u8; 1],
+        rx_ring: &'static mut [u8; RX_LEN],
+        tx_buffer: &'static mut [u8; TX_LEN],
+        response: &'static mut [u8; RESPONSE_LEN],
+    ) -> BluetoothSerial<'a, U, A> {
+        BluetoothSerial {
+            uart,
+            alarm,
+            baud_rate,
+            module,
+            key_pin,
+            state_pin,
+            apps: grant,
+            owner: OptionalCell::empty(),
+            rx_byte: TakeCell::new(rx_byte),
+            rx_ring: MapCell::new(RingBuffer::new(rx_ring)),
+            read_len: OptionalCell::empty(),
+            tx_buffer: TakeCell::new(tx_buffer),
+            write_len: OptionalCell::empty(),
+            written: Cell::new(0),
+            setting: OptionalCell::empty(),
+            step: Cell::new(0),
+            response: TakeCell::new(response),
+            response_len: Cell::new(0),
+        }
+    }
+
+    /// Configure the UART and the pins, and start receiving.
+    pub fn initialize(&self) -> Result<(), ErrorCode> {
+        if let Some(key_pin) = self.key_pin {
+            key_pin.make_output();
+            key_pin.clear();
+        }
+        if let Some(state_pin) = self.state_pin {
+            state_pin.make_input();
+            state_pin.enable_interrupts(gpio::InterruptEdge::EitherEdge);
+        }
+        self.uart.configure(uart::Parameters {
+            baud_rate: self.baud_rate,
+            width: uart::Width::Eight,
+            parity: uart::Parity::None,
+            stop_bits: uart::StopBits::One,
+            hw_flow_control: false,
+        })?;
+        self.receive_byte()
+    }
+
+    fn receive_byte(&self) -> Result<(), ErrorCode> {
+        self.rx_byte.take().map_or(Err(ErrorCode::BUSY), |rx_byte| {
+            self.uart
+                .receive_buffer(rx_byte, 1)
+                .map_err(|(err, rx_byte)| {
+                    self.rx_byte.replace(rx_byte);
+                    err
+                })
+        })
+    }
+
+    fn connected(&self) -> Option<bool> {
+        self.state_pin.map(|state_pin| state_pin.read())
+    }
+
+    /// Make `processid` the owner if there is none or its owner is gone.
+    fn claim(&self, processid: ProcessId) -> Result<(), ErrorCode> {
+        let owned = self.owner.map_or(false, |owner| {
+            *owner != processid && self.apps.enter(*owner, |_, _| ()).is_ok()
+        });
+        if owned {
+            return Err(ErrorCode::RESERVE);
+        }
+        if !self.owner.contains(&processid) {
+            // Drop the operations of the previous owner
+            self.read_len.clear();
+            self.owner.set(processid);
+        }
+        Ok(())
+    }
+
+    fn upcall(&self, upcall: usize, data: (usize, usize, usize)) {
+        self.owner.map(|processid| {
+            let _ = self.apps.enter(*processid, |_, kernel_data| {
+                kernel_data.schedule_upcall(upcall, data).ok();
+            });
+        });
+    }
+
+    /// Send the next chunk of the write, or notify the owner that it is done.
+    fn write_next(&self) {
+        let len = match self.write_len.extract() {
+            Some(len) => len,
+            None => return,
+        };
+        let written = self.written.get();
+        if written == len {
+            self.write_len.clear();
+            return self.upcall(1, (written, 0, 0));
+        }
+
+        let sent = self
+            .tx_buffer
+            .take()
+            .map_or(Err(ErrorCode::BUSY), |tx_buffer| {
+                let chunk = self.owner.map_or(0, |processid| {
+                    self.apps
+                        .enter(*processid, |_, kernel_data| {
+                            kernel_data
+                                .get_readonly_processbuffer(ro_allow::WRITE)
+                                .and_then(|write| {
+                                    write.enter(|write| {
+                                        let end = cmp::min(len, write.len());
+                                        let chunk = cmp::min(end.saturating_sub(written), TX_LEN);
+                                        write[written..written + chunk]
+                                            .copy_to_slice(&mut tx_buffer[..chunk]);
+                                        chunk
+                                    })
+                                })
+                                .unwrap_or(0)
+                        })
+                        .unwrap_or(0)
+                });
+                if chunk == 0 {
+                    // The buffer shrank, stop at what was written
+                    self.tx_buffer.replace(tx_buffer);
+                    self.write_len.set(written);
+                    return Ok(false);
+                }
+                self.uart
+                    .transmit_buffer(tx_buffer, chunk)
+                    .map(|()| true)
+                    .map_err(|(err, tx_buffer)| {
+                        self.tx_buffer.replace(tx_buffer);
+                        err
+                    })
+            });
+        match sent {
+            Ok(true) => {}
+            Ok(false) => self.write_next(),
+            Err(_) => {
+                self.write_len.clear();
+                self.upcall(1, (written, 0, 0));
+            }
+        }
+    }
+
+    /// Copy the received bytes to the owner and complete its read.
+    fn read_done(&self, result: Result<(), ErrorCode>) {
+        let len = match self.read_len.take() {
+            Some(len) => len,
+            None => return,
+        };
+        let mut read = 0;
+        self.owner.map(|processid| {
+            let _ = self.apps.enter(*processid, |_, kernel_data| {
+                if result.is_ok() {
+                    let _ = kernel_data
+                        .get_readwrite_processbuffer(rw_allow::READ)
+                        .and_then(|buffer| {
+                            buffer.mut_enter(|buffer| {
+                                self.rx_ring.map(|ring| {
+                                    let len = cmp::min(len, buffer.len());
+                                    while read < len {
+                                        match ring.dequeue() {
+                                            Some(byte) => buffer[read].set(byte),
+                                            None => break,
+                                        }
+                                        read += 1;
+                                    }
+                                });
+                            })
+                        });
+                }
+                kernel_data
+                    .schedule_upcall(2, (kernel::errorcode::into_statuscode(result), read, 0))
+                    .ok();
+            });
+        });
+    }
+
+    /// Complete the read if it has all its bytes, or wait for more.
+    fn check_read(&self) {
+        let len = match self.read_len.extract() {
+            Some(len) => len,
+            None => return,
+        };
+        let available = self.rx_ring.map_or(0, |ring| ring.len());
+        if available >= len {
+            let _ = self.alarm.disarm();
+            self.read_done(Ok(()));
+        } else if available > 0 && self.setting.is_none() {
+            self.alarm
+                .set_alarm(self.alarm.now(), self.alarm.ticks_from_ms(READ_IDLE_MS));
+        }
+    }
+
+    fn setting_steps(&self, setting: Setting) -> usize {
+        match (self.module, setting) {
+            (Module::Hc05, _) => 1,
+            (Module::Hm10, Setting::Name) => 2,
+            (Module::Hm10, Setting::Pin) => 3,
+        }
+    }
+
+    /// Write AT command `step` of `setting` in `buffer`, and return its
+    /// length.
+    fn write_command(
+        &self,
+        setting: Setting,
+        step: usize,
+        buffer: &mut [u8],
+    ) -> Result<usize, ErrorCode> {
+        let (prefix, suffix): (&[u8], &[u8]) = match (self.module, setting, step) {
+            (Module::Hc05, Setting::Name, _) => (b"AT+NAME=", b"\r\n"),
+            (Module::Hc05, Setting::Pin, _) => (b"AT+PSWD=", b"\r\n"),
+            (Module::Hm10, Setting::Name, 0) => (b"AT+NAME", b""),
+            (Module::Hm10, Setting::Pin, 0) => (b"AT+PASS", b""),
+            // Require the PIN to pair
+            (Module::Hm10, Setting::Pin, 1) => (b"AT+TYPE3", b""),
+            // Apply the setting
+            (Module::Hm10, _, _) => (b"AT+RESET", b""),
+        };
+        let with_value = step == 0;
+
+        let mut len = prefix.len();
+        buffer[..len].copy_from_slice(prefix);
+        if with_value {
+            let value_len = self.owner.map_or(Err(ErrorCode::RESERVE), |processid| {
+                self.apps
+                    .enter(*processid, |_, kernel_data| {
+                        kernel_data
+                            .get_readonly_processbuffer(ro_allow::SETTING)
+                            .and_then(|value| {
+                                value.enter(|value| {
+                                    let value_len = value.len();
+                                    if len + value_len + suffix.len() > buffer.len() {
+                                        return Err(ErrorCode::SIZE);
+                                    }
+                                    value.copy_to_slice(&mut buffer[len..len + value_len]);
+                                    Ok(value_len)
+                                })
+                            })
+                            .unwrap_or(Err(ErrorCode::RESERVE))
+                    })
+                    .unwrap_or(Err(ErrorCode::RESERVE))
+            })?;
+            Self::check_value(self.module, setting, &buffer[len..len + value_len])?;
+            len += value_len;
+        }
+        buffer[len..len + suffix.len()].copy_from_slice(suffix);
+        Ok(len + suffix.len())
+    }
+
+    fn check_value(module: Module, setting: Setting, value: &[u8]) -> Result<(), ErrorCode> {
+        let valid = match (setting, module) {
+            (Setting::Name, Module::Hc05) => value.len() <= 32,
+            (Setting::Name, Module::Hm10) => value.len() <= 12,
+            (Setting::Pin, Module::Hc05) => value.len() == 4,
+            (Setting::Pin, Module::Hm10) => value.len() == 6,
+        };
+        let characters = match setting {
+            Setting::Name => value.iter().all(|c| c.is_ascii_graphic() || *c == b' '),
+            Setting::Pin => value.iter().all(u8::is_ascii_digit),
+        };
+        if valid && characters && !value.is_empty() {
+            Ok(())
+        } else {
+            Err(ErrorCode::INVAL)
+        }
+    }
+
+    /// Send the next AT command of the setting, or finish it.
+    fn setting_next(&self) {
+        let setting = match self.setting.extract() {
+            Some(setting) => setting,
+            None => return,
+        };
+        let step = self.step.get();
+        if step == self.setting_steps(setting) {
+            return self.setting_done(Ok(()));
+        }
+
+        let sent = self
+            .tx_buffer
+            .take()
+            .map_or(Err(ErrorCode::BUSY), |tx_buffer| {
+                match self.write_command(setting, step, tx_buffer) {
+                    Ok(len) => {
+                        self.uart
+                            .transmit_buffer(tx_buffer, len)
+                            .map_err(|(err, tx_buffer)| {
+                                self.tx_buffer.replace(tx_buffer);
+                                err
+                            })
+                    }
+                    Err(err) => {
+                        self.tx_buffer.replace(tx_buffer);
+                        Err(err)
+                    }
+                }
+            });
+        match sent {
+            Ok(()) => {
+                self.step.set(step + 1);
+                self.response_len.set(0);
+                self.alarm.set_alarm(
+                    self.alarm.now(),
+                    self.alarm.ticks_from_ms(RESPONSE_TIMEOUT_MS),
+                );
+            }
+            Err(err) => self.setting_done(Err(err)),
+        }
+    }
+
+    fn setting_done(&self, result: Result<(), ErrorCode>) {
+        let _ = self.alarm.disarm();
+        self.setting.clear();
+        if let Some(key_pin) = self.key_pin {
+            key_pin.clear();
+        }
+        self.upcall(3, (kernel::errorcode::into_statuscode(result), 0, 0));
+        // Bytes which arrived during the setting
+        self.check_read();
+    }
+
+    /// The module was silent: evaluate its response to the AT command.
+    fn response_received(&self) {
+        let len = self.response_len.get();
+        let ok = self.response.map_or(false, |response| {
+            response[..len].windows(2).any(|bytes| bytes == b"OK")
+        });
+        if ok {
+            self.setting_next();
+        } else if len == 0 {
+            self.setting_done(Err(ErrorCode::NOACK));
+        } else {
+            self.setting_done(Err(ErrorCode::FAIL));
+        }
+    }
+
+    fn start_setting(&self, setting: Setting) -> Result<(), ErrorCode> {
+        if self.setting.is_some() || self.write_len.is_some() {
+            return Err(ErrorCode::BUSY);
+        } else if self.connected() == Some(true) {
+            // The module would send the commands to the phone
+            return Err(ErrorCode::BUSY);
+        }
+        if let Some(key_pin) = self.key_pin {
+            key_pin.set();
+        }
+        self.setting.set(setting);
+        self.step.set(0);
+        self.setting_next();
+        Ok(())
+    }
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> AlarmClient for BluetoothSerial<'a, U, A> {
+    fn alarm(&self) {
+        if self.setting.is_some() {
+            self.response_received();
+        } else {
+            self.read_done(Ok(()));
+        }
+    }
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> gpio::Client for BluetoothSerial<'a, U, A> {
+    fn fired(&self) {
+        if let Some(connected) = self.connected() {
+            self.upcall(4, (connected as usize, 0, 0));
+        }
+    }
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> uart::TransmitClient for BluetoothSerial<'a, U, A> {
+    fn transmitted_buffer(
+        &self,
+        tx_buffer: &'static mut [u8],
+        tx_len: usize,
+        rval: Result<(), ErrorCode>,
+    ) {
+        self.tx_buffer.replace(tx_buffer);
+        if self.setting.is_some() {
+            if rval.is_err() {
+                self.setting_done(rval);
+            }
+        } else if self.write_len.is_some() {
+            if rval.is_ok() {
+                self.written.set(self.written.get() + tx_len);
+            } else {
+                // Report what was written
+                self.write_len.set(self.written.get());
+            }
+            self.write_next();
+        }
+    }
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> uart::ReceiveClient for BluetoothSerial<'a, U, A> {
+    fn received_buffer(
+        &self,
+        rx_byte: &'static mut [u8],
+        rx_len: usize,
+        rval: Result<(), ErrorCode>,
+        _error: uart::Error,
+    ) {
+        let byte = rx_byte[0];
+        self.rx_byte.replace(rx_byte);
+
+        if rval.is_ok() && rx_len == 1 {
+            if self.setting.is_some() {
+                let len = self.response_len.get();
+                if len < RESPONSE_LEN {
+                    self.response.map(|response| response[len] = byte);
+                    self.response_len.set(len + 1);
+                }
+                self.alarm
+                    .set_alarm(self.alarm.now(), self.alarm.ticks_from_ms(QUIET_MS));
+            } else {
+                self.rx_ring.map(|ring| ring.enqueue(byte));
+                self.check_read();
+            }
+        }
+        let _ = self.receive_byte();
+    }
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> SyscallDriver for BluetoothSerial<'a, U, A> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        _data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        if command_num == 0 {
+            return CommandReturn::success();
+        }
+        if let Err(err) = self.claim(processid) {
+            return CommandReturn::failure(err);
+        }
+
+        let result = match command_num {
+            // Write
+            1 => {
+                if self.write_len.is_some() || self.setting.is_some() {
+                    Err(ErrorCode::BUSY)
+                } else {
+                    self.write_len.set(data1);
+                    self.written.set(0);
+                    self.write_next();
+                    Ok(())
+                }
+            }
+
+            // Read
+            2 => {
+                if self.read_len.is_some() {
+                    Err(ErrorCode::BUSY)
+                } else if data1 == 0 {
+                    Err(ErrorCode::SIZE)
+                } else {
+                    self.read_len.set(data1);
+                    self.check_read();
+                    Ok(())
+                }
+            }
+
+            // Abort the read
+            3 => {
+                if self.setting.is_none() {
+                    let _ = self.alarm.disarm();
+                }
+                self.read_done(Err(ErrorCode::CANCEL));
+                Ok(())
+            }
+
+            // Set the name
+            4 => self.start_setting(Setting::Name),
+
+            // Set the PIN
+            5 => self.start_setting(Setting::Pin),
+
+            // Connection state
+            6 => {
+                return self
+                    .connected()
+                    .map_or(CommandReturn::failure(ErrorCode::NOSUPPORT), |connected| {
+                        CommandReturn::success_u32(connected as u32)
+                    })
+            }
+
+            _ => Err(ErrorCode::NOSUPPORT),
+        };
+        CommandReturn::from(result)
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
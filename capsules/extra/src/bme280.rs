@@ -10,13 +10,15 @@
 
 use core::cell::Cell;
 use kernel::hil::i2c::{self, I2CClient, I2CDevice};
-use kernel::hil::sensors::{HumidityClient, HumidityDriver, TemperatureClient, TemperatureDriver};
+use kernel::hil::sensors::{
+    HumidityClient, HumidityDriver, PressureClient, PressureDriver, TemperatureClient,
+    TemperatureDriver,
+};
 use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::ErrorCode;
 
 const HUM_MSB: u8 = 0xFD;
 const TEMP_MSB: u8 = 0xFA;
-#[allow(dead_code)]
 const PRESS_MSB: u8 = 0xF7;
 #[allow(dead_code)]
 const CONFIG: u8 = 0xF5;
@@ -44,7 +46,6 @@ enum DeviceState {
     Normal,
 }
 
-#[allow(dead_code)]
 #[derive(Clone, Copy, PartialEq)]
 enum Operation {
     None,
@@ -104,12 +105,67 @@ impl Default for CalibrationData {
     }
 }
 
+impl CalibrationData {
+    /// The fine temperature used by the compensation of the pressure, from
+    /// the raw temperature, as in section 4.2.3 of the datasheet.
+    fn t_fine(&self, adc_temperature: i32) -> i32 {
+        let temp1 = self.temp1 as i32;
+        let temp2 = self.temp2 as i16 as i32;
+        let temp3 = self.temp3 as i16 as i32;
+
+        let var1 = (((adc_temperature >> 3) - (temp1 << 1)) * temp2) >> 11;
+        let var2 = (((((adc_temperature >> 4) - temp1) * ((adc_temperature >> 4) - temp1)) >> 12)
+            * temp3)
+            >> 14;
+        var1 + var2
+    }
+
+    /// The pressure in pascals, from the raw pressure, as in section 4.2.3
+    /// of the datasheet.
+    fn pressure(&self, t_fine: i32, adc_pressure: i32) -> u32 {
+        let press1 = self.press1 as i32;
+        let press2 = self.press2 as i16 as i32;
+        let press3 = self.press3 as i16 as i32;
+        let press4 = self.press4 as i16 as i32;
+        let press5 = self.press5 as i16 as i32;
+        let press6 = self.press6 as i16 as i32;
+        let press7 = self.press7 as i16 as i32;
+        let press8 = self.press8 as i16 as i32;
+        let press9 = self.press9 as i16 as i32;
+
+        let mut var1 = (t_fine >> 1) - 64000;
+        let mut var2 = (((var1 >> 2) * (var1 >> 2)) >> 11) * press6;
+        var2 += (var1 * press5) << 1;
+        var2 = (var2 >> 2) + (press4 << 16);
+        var1 =
+            (((press3 * (((var1 >> 2) * (var1 >> 2)) >> 13)) >> 3) + ((press2 * var1) >> 1)) >> 18;
+        var1 = ((32768 + var1) * press1) >> 15;
+        if var1 == 0 {
+            // Avoid a division by zero
+            return 0;
+        }
+
+        let mut pressure = ((1048576 - adc_pressure) as u32)
+            .wrapping_sub((var2 >> 12) as u32)
+            .wrapping_mul(3125);
+        pressure = if pressure < 0x8000_0000 {
+            (pressure << 1) / var1 as u32
+        } else {
+            (pressure / var1 as u32) * 2
+        };
+        let var1 = (press9 * ((((pressure >> 3) * (pressure >> 3)) >> 13) as i32)) >> 12;
+        let var2 = (((pressure >> 2) as i32) * press8) >> 13;
+        (pressure as i32 + ((var1 + var2 + press7) >> 4)) as u32
+    }
+}
+
 pub struct Bme280<'a> {
     buffer: TakeCell<'static, [u8]>,
     i2c: &'a dyn I2CDevice,
     calibration: Cell<CalibrationData>,
     temperature_client: OptionalCell<&'a dyn TemperatureClient>,
     humidity_client: OptionalCell<&'a dyn HumidityClient>,
+    pressure_client: OptionalCell<&'a dyn PressureClient>,
     state: Cell<DeviceState>,
     op: Cell<Operation>,
     t_fine: Cell<usize>,
@@ -123,6 +179,7 @@ impl<'a> Bme280<'a> {
             calibration: Cell::new(CalibrationData::default()),
             temperature_client: OptionalCell::empty(),
             humidity_client: OptionalCell::empty(),
+            pressure_client: OptionalCell::empty(),
             state: Cell::new(DeviceState::Identify),
             op: Cell::new(Operation::None),
             t_fine: Cell::new(0),
@@ -190,6 +247,32 @@ impl<'a> HumidityDriver<'a> for Bme280<'a> {
     }
 }
 
+impl<'a> PressureDriver<'a> for Bme280<'a> {
+    fn set_client(&self, client: &'a dyn PressureClient) {
+        self.pressure_client.set(client);
+    }
+
+    fn read_pressure(&self) -> Result<(), ErrorCode> {
+        if self.state.get() != DeviceState::Normal {
+            return Err(ErrorCode::BUSY);
+        }
+
+        if self.op.get() != Operation::None {
+            return Err(ErrorCode::BUSY);
+        }
+
+        self.buffer.take().map(|buffer| {
+            // The pressure and the temperature, which compensates it
+            buffer[0] = PRESS_MSB;
+
+            self.op.set(Operation::Pressure);
+            self.i2c.write_read(buffer, 1, 6).unwrap();
+        });
+
+        Ok(())
+    }
+}
+
 impl<'a> I2CClient for Bme280<'a> {
     fn command_complete(&self, buffer: &'static mut [u8], status: Result<(), i2c::Error>) {
         if let Err(i2c_err) = status {
@@ -201,7 +284,8 @@ impl<'a> I2CClient for Bme280<'a> {
                         .map(|client| client.callback(Err(i2c_err.into())));
                 }
                 Operation::Pressure => {
-                    unimplemented!();
+                    self.pressure_client
+                        .map(|client| client.callback(Err(i2c_err.into())));
                 }
                 Operation::Humidity => {
                     self.humidity_client.map(|client| client.callback(0));
@@ -326,7 +410,25 @@ impl<'a> I2CClient for Bme280<'a> {
                             .map(|client| client.callback(Ok(temperature as i32)));
                     }
                     Operation::Pressure => {
-                        unimplemented!();
+                        let calib = self.calibration.get();
+                        let adc_pressure = (buffer[0] as i32) << 12
+                            | (buffer[1] as i32) << 4
+                            | (((buffer[2] as i32) >> 4) & 0x0F);
+                        let adc_temperature = (buffer[3] as i32) << 12
+                            | (buffer[4] as i32) << 4
+                            | (((buffer[5] as i32) >> 4) & 0x0F);
+
+                        if adc_pressure == 0 || adc_temperature == 0 {
+                            // We got a misread, try again
+                            self.buffer.replace(buffer);
+                            self.op.set(Operation::None);
+                            let _ = self.read_pressure();
+                            return;
+                        }
+
+                        let pressure = calib.pressure(calib.t_fine(adc_temperature), adc_pressure);
+                        self.pressure_client
+                            .map(|client| client.callback(Ok(pressure)));
                     }
                     Operation::Humidity => {
                         let calib = self.calibration.get();
@@ -0,0 +1,147 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Counts boots and watchdog resets in registers retained across resets.
+//!
+//! The counters are kept in three consecutive backup registers: the number
+//! of boots, the number of watchdog resets and a check word derived from
+//! both. If the check word does not match, for example because power was
+//! lost without a backup battery or a reset interrupted an update, the
+//! counters start again from zero. `record_boot()` must be called once
+//! during board initialization; the component does this.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let boot_counter = components::boot_counter::BootCounterComponent::new(
+//!     &peripherals.watchdog,
+//!     0,
+//!     peripherals.watchdog.reset_reason(),
+//! )
+//! .finalize(components::boot_counter_component_static!(
+//!     rp2040::watchdog::Watchdog<'static>
+//! ));
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! ### Command
+//!
+//! All operations are synchronous, so this capsule only uses the `command`
+//! syscall.
+//!
+//! #### `command_num`
+//!
+//! - `0`: Driver existence check.
+//! - `1`: Return the number of boots, including the current one.
+//! - `2`: Return the number of boots caused by a watchdog reset.
+//! - `3`: Return the cause of the last reset: 0 power-on, 1 reset pin,
+//!   2 watchdog, 3 software, 4 unknown.
+//! - `4`: Clear both counters.
+
+use core::cell::Cell;
+
+use kernel::hil::backup_registers::BackupRegisters;
+use kernel::hil::reset_reason::ResetReason;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::BootCounter as usize;
+
+/// Number of backup registers used by the counters.
+pub const NUM_REGISTERS: usize = 3;
+
+/// Mixed into the check word so that all-zero and all-one registers are not
+/// mistaken for valid counters.
+const CHECK_MAGIC: u32 = 0xB007_C0DE;
+
+fn check_word(boots: u32, watchdog_resets: u32) -> u32 {
+    CHECK_MAGIC ^ boots ^ watchdog_resets.rotate_left(16)
+}
+
+pub struct BootCounter<'a, B: BackupRegisters> {
+    registers: &'a B,
+    /// Index of the first of the `NUM_REGISTERS` registers used.
+    first_register: usize,
+    reset_reason: Cell<ResetReason>,
+    boots: Cell<u32>,
+    watchdog_resets: Cell<u32>,
+}
+
+impl<'a, B: BackupRegisters> BootCounter<'a, B> {
+    pub fn new(registers: &'a B, first_register: usize) -> Self {
+        Self {
+            registers,
+            first_register,
+            reset_reason: Cell::new(ResetReason::Unknown),
+            boots: Cell::new(0),
+            watchdog_resets: Cell::new(0),
+        }
+    }
+
+    /// Load the counters, account for the current boot and store them back.
+    pub fn record_boot(&self, reason: ResetReason) -> Result<(), ErrorCode> {
+        self.reset_reason.set(reason);
+
+        let (boots, watchdog_resets) = self.load().unwrap_or((0, 0));
+        let watchdog = if reason == ResetReason::Watchdog {
+            1
+        } else {
+            0
+        };
+        self.store(
+            boots.saturating_add(1),
+            watchdog_resets.saturating_add(watchdog),
+        )
+    }
+
+    fn load(&self) -> Option<(u32, u32)> {
+        let boots = self.registers.read(self.first_register).ok()?;
+        let watchdog_resets = self.registers.read(self.first_register + 1).ok()?;
+        let check = self.registers.read(self.first_register + 2).ok()?;
+        (check == check_word(boots, watchdog_resets)).then_some((boots, watchdog_resets))
+    }
+
+    fn store(&self, boots: u32, watchdog_resets: u32) -> Result<(), ErrorCode> {
+        self.boots.set(boots);
+        self.watchdog_resets.set(watchdog_resets);
+        self.registers.write(self.first_register, boots)?;
+        self.registers
+            .write(self.first_register + 1, watchdog_resets)?;
+        self.registers
+            .write(self.first_register + 2, check_word(boots, watchdog_resets))
+    }
+}
+
+impl<'a, B: BackupRegisters> SyscallDriver for BootCounter<'a, B> {
+    fn command(
+        &self,
+        command_num: usize,
+        _data1: usize,
+        _data2: usize,
+        _processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            1 => CommandReturn::success_u32(self.boots.get()),
+
+            2 => CommandReturn::success_u32(self.watchdog_resets.get()),
+
+            3 => CommandReturn::success_u32(self.reset_reason.get().into()),
+
+            4 => self.store(0, 0).into(),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, _processid: ProcessId) -> Result<(), kernel::process::Error> {
+        Ok(())
+    }
+}
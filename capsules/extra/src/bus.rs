@@ -86,6 +86,15 @@ pub trait Bus<'a> {
         len: usize,
     ) -> Result<(), (ErrorCode, &'static mut [u8])>;
 
+    /// Abort the operation in progress, for example after a timeout.
+    ///
+    /// Return values:
+    /// - Ok(()): the operation is aborted, and the client's
+    ///   `command_complete` is called with `Err(CANCEL)` and the buffer
+    /// - Err(INVAL): no operation is in progress
+    /// - Err(NOSUPPORT): the underlying bus cannot abort operations
+    fn abort(&self) -> Result<(), ErrorCode>;
+
     fn set_client(&self, client: &'a dyn Client);
 }
 
@@ -95,6 +104,7 @@ pub trait Client {
     /// set_address does not return a buffer
     /// write and read return a buffer
     /// len should be set to the number of data elements written
+    /// status is `Err(CANCEL)` if the operation was aborted
     fn command_complete(
         &self,
         buffer: Option<&'static mut [u8]>,
@@ -226,6 +236,11 @@ impl<'a, S: SpiMasterDevice<'a>> Bus<'a> for SpiMasterBus<'a, S> {
         )
     }
 
+    fn abort(&self) -> Result<(), ErrorCode> {
+        // SPI devices cannot abort a transfer.
+        Err(ErrorCode::NOSUPPORT)
+    }
+
     fn set_client(&self, client: &'a dyn Client) {
         self.client.replace(client);
     }
@@ -349,6 +364,11 @@ impl<'a, I: I2CDevice> Bus<'a> for I2CMasterBus<'a, I> {
         }
     }
 
+    fn abort(&self) -> Result<(), ErrorCode> {
+        // I2C devices cannot abort a transfer.
+        Err(ErrorCode::NOSUPPORT)
+    }
+
     fn set_client(&self, client: &'a dyn Client) {
         self.client.replace(client);
     }
@@ -442,6 +462,10 @@ impl<'a, B: Bus8080<'static>> Bus<'a> for Bus8080Bus<'a, B> {
         }
     }
 
+    fn abort(&self) -> Result<(), ErrorCode> {
+        self.bus.abort()
+    }
+
     fn set_client(&self, client: &'a dyn Client) {
         self.client.replace(client);
     }
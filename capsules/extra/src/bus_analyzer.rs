@@ -0,0 +1,456 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Passive logging of I2C and SPI transactions.
+//!
+//! The analyzer sits between a bus and its users and records, for every
+//! transaction, the device, the number of bytes written and read, the
+//! duration and the result in a ring buffer. The data itself is not
+//! recorded. When the ring buffer is full, the oldest transactions are
+//! overwritten.
+//!
+//! - `I2CMasterAnalyzer` wraps an I2C controller, below the `MuxI2C`, so it
+//!   logs the transactions of every device on the bus, by address.
+//! - `SpiDeviceAnalyzer` wraps one `VirtualSpiMasterDevice`, and logs its
+//!   transactions with a label, since chip selects differ between chips.
+//!
+//! `BusLog` implements the `bus` command of the process console, which prints
+//! the transactions, oldest first. `bus clear` empties the log.
+//!
+//! ```text
+//! tock$ bus
+//! Bus log: 3 transactions, 0 overwritten
+//!  i2c   0x38 write_read w1 r16   412us Ok
+//!  i2c   0x38 write_read w1 r16   409us Ok
+//!  flash      transfer   w4 r0     18us Err(BUSY)
+//! ```
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let bus_log = components::bus_analyzer::BusLogComponent::new(&base_peripherals.tim2)
+//!     .finalize(components::bus_log_component_static!(stm32f412g::tim2::Tim2, 32));
+//! let flash_spi = components::bus_analyzer::SpiDeviceAnalyzerComponent::new(
+//!     spi_device,
+//!     bus_log,
+//!     "flash",
+//! )
+//! .finalize(components::spi_device_analyzer_component_static!(
+//!     capsules_core::virtualizers::virtual_spi::VirtualSpiMasterDevice<
+//!         'static,
+//!         stm32f412g::spi::Spi<'static>,
+//!     >,
+//!     stm32f412g::tim2::Tim2,
+//! ));
+//! process_console.add_command(bus_log).unwrap();
+//! ```
+
+use core::cell::Cell;
+use core::fmt;
+
+use capsules_core::process_console::ConsoleCommand;
+use kernel::hil::i2c;
+use kernel::hil::spi::{self, ClockPhase, ClockPolarity};
+use kernel::hil::time::{ConvertTicks, Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Kind of a transaction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    Write,
+    Read,
+    WriteRead,
+    /// Full-duplex SPI transfer.
+    Transfer,
+}
+
+impl Operation {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Write => "write",
+            Operation::Read => "read",
+            Operation::WriteRead => "write_read",
+            Operation::Transfer => "transfer",
+        }
+    }
+}
+
+/// Metadata of a transaction.
+#[derive(Clone, Copy, Debug)]
+pub struct Transaction {
+    /// Label of the bus or device.
+    pub bus: &'static str,
+    /// Address of the device, on I2C.
+    pub address: Option<u8>,
+    pub operation: Operation,
+    pub write_len: usize,
+    pub read_len: usize,
+    /// Zero for transactions refused by the bus.
+    pub duration_us: u32,
+    pub result: Result<(), ErrorCode>,
+}
+
+/// Transaction started, waiting for its completion.
+#[derive(Clone, Copy)]
+struct Pending<T: Ticks> {
+    address: Option<u8>,
+    operation: Operation,
+    write_len: usize,
+    read_len: usize,
+    start: T,
+}
+
+/// Ring buffer of the transactions of the analyzed buses.
+pub struct BusLog<'a, T: Time> {
+    time: &'a T,
+    log: &'a [Cell<Option<Transaction>>],
+    /// Index of the next transaction written.
+    next: Cell<usize>,
+    /// Transactions in the log.
+    count: Cell<usize>,
+    /// Transactions overwritten since the log was cleared.
+    overwritten: Cell<usize>,
+}
+
+impl<'a, T: Time> BusLog<'a, T> {
+    pub fn new(time: &'a T, log: &'a [Cell<Option<Transaction>>]) -> Self {
+        Self {
+            time,
+            log,
+            next: Cell::new(0),
+            count: Cell::new(0),
+            overwritten: Cell::new(0),
+        }
+    }
+
+    /// Number of transactions in the log.
+    pub fn len(&self) -> usize {
+        self.count.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count.get() == 0
+    }
+
+    /// Transaction `index` of the log, the oldest first.
+    pub fn get(&self, index: usize) -> Option<Transaction> {
+        let count = self.count.get();
+        if index >= count {
+            return None;
+        }
+        let oldest = (self.next.get() + self.log.len() - count) % self.log.len();
+        self.log[(oldest + index) % self.log.len()].get()
+    }
+
+    pub fn clear(&self) {
+        self.next.set(0);
+        self.count.set(0);
+        self.overwritten.set(0);
+    }
+
+    pub fn record(&self, transaction: Transaction) {
+        if self.log.is_empty() {
+            return;
+        }
+        let next = self.next.get();
+        self.log[next].set(Some(transaction));
+        self.next.set((next + 1) % self.log.len());
+        if self.count.get() < self.log.len() {
+            self.count.set(self.count.get() + 1);
+        } else {
+            self.overwritten.set(self.overwritten.get() + 1);
+        }
+    }
+
+    fn now(&self) -> T::Ticks {
+        self.time.now()
+    }
+
+    fn complete(
+        &self,
+        bus: &'static str,
+        pending: Pending<T::Ticks>,
+        result: Result<(), ErrorCode>,
+    ) {
+        let elapsed = self.time.now().wrapping_sub(pending.start);
+        self.record(Transaction {
+            bus,
+            address: pending.address,
+            operation: pending.operation,
+            write_len: pending.write_len,
+            read_len: pending.read_len,
+            duration_us: self.time.ticks_to_us(elapsed),
+            result,
+        });
+    }
+
+    /// Record a transaction the bus refused, so never started.
+    fn refused(&self, bus: &'static str, pending: Pending<T::Ticks>, error: ErrorCode) {
+        self.record(Transaction {
+            bus,
+            address: pending.address,
+            operation: pending.operation,
+            write_len: pending.write_len,
+            read_len: pending.read_len,
+            duration_us: 0,
+            result: Err(error),
+        });
+    }
+}
+
+impl<'a, T: Time> ConsoleCommand for BusLog<'a, T> {
+    fn name(&self) -> &'static str {
+        "bus"
+    }
+
+    fn execute(&self, arguments: &str) {
+        if arguments.trim() == "clear" {
+            self.clear();
+        }
+    }
+
+    fn write_line(&self, index: usize, writer: &mut dyn fmt::Write) -> bool {
+        if index == 0 {
+            let _ = writer.write_fmt(format_args!(
+                "Bus log: {} transactions, {} overwritten\r\n",
+                self.count.get(),
+                self.overwritten.get()
+            ));
+            return true;
+        }
+        match self.get(index - 1) {
+            Some(transaction) => {
+                let _ = writer.write_fmt(format_args!(" {:<5} ", transaction.bus));
+                let _ = match transaction.address {
+                    Some(address) => writer.write_fmt(format_args!("{:#04x} ", address)),
+                    None => writer.write_str("     "),
+                };
+                let _ = writer.write_fmt(format_args!(
+                    "{:<10} w{} r{:<4} {:>5}us {:?}\r\n",
+                    transaction.operation.as_str(),
+                    transaction.write_len,
+                    transaction.read_len,
+                    transaction.duration_us,
+                    transaction.result
+                ));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// I2C controller which logs the transactions of every device on the bus.
+pub struct I2CMasterAnalyzer<'a, I: i2c::I2CMaster<'a>, T: Time> {
+    i2c: &'a I,
+    log: &'a BusLog<'a, T>,
+    label: &'static str,
+    client: OptionalCell<&'a dyn i2c::I2CHwMasterClient>,
+    pending: Cell<Option<Pending<T::Ticks>>>,
+}
+
+impl<'a, I: i2c::I2CMaster<'a>, T: Time> I2CMasterAnalyzer<'a, I, T> {
+    pub fn new(i2c: &'a I, log: &'a BusLog<'a, T>, label: &'static str) -> Self {
+        Self {
+            i2c,
+            log,
+            label,
+            client: OptionalCell::empty(),
+            pending: Cell::new(None),
+        }
+    }
+
+    /// Record the start of a transaction, then start it with `f`.
+    fn start<F>(
+        &self,
+        address: u8,
+        operation: Operation,
+        write_len: usize,
+        read_len: usize,
+        f: F,
+    ) -> Result<(), (i2c::Error, &'static mut [u8])>
+    where
+        F: FnOnce() -> Result<(), (i2c::Error, &'static mut [u8])>,
+    {
+        let pending = Pending {
+            address: Some(address),
+            operation,
+            write_len,
+            read_len,
+            start: self.log.now(),
+        };
+        // Set before the call, in case the transaction completes
+        // synchronously.
+        self.pending.set(Some(pending));
+        let result = f();
+        if let Err((error, _)) = result {
+            self.pending.set(None);
+            self.log.refused(self.label, pending, error.into());
+        }
+        result
+    }
+}
+
+impl<'a, I: i2c::I2CMaster<'a>, T: Time> i2c::I2CMaster<'a> for I2CMasterAnalyzer<'a, I, T> {
+    fn set_master_client(&self, master_client: &'a dyn i2c::I2CHwMasterClient) {
+        self.client.set(master_client);
+    }
+
+    fn enable(&self) {
+        self.i2c.enable();
+    }
+
+    fn disable(&self) {
+        self.i2c.disable();
+    }
+
+    fn write_read(
+        &self,
+        addr: u8,
+        data: &'static mut [u8],
+        write_len: usize,
+        read_len: usize,
+    ) -> Result<(), (i2c::Error, &'static mut [u8])> {
+        self.start(addr, Operation::WriteRead, write_len, read_len, || {
+            self.i2c.write_read(addr, data, write_len, read_len)
+        })
+    }
+
+    fn write(
+        &self,
+        addr: u8,
+        data: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (i2c::Error, &'static mut [u8])> {
+        self.start(addr, Operation::Write, len, 0, || {
+            self.i2c.write(addr, data, len)
+        })
+    }
+
+    fn read(
+        &self,
+        addr: u8,
+        buffer: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (i2c::Error, &'static mut [u8])> {
+        self.start(addr, Operation::Read, 0, len, || {
+            self.i2c.read(addr, buffer, len)
+        })
+    }
+}
+
+impl<'a, I: i2c::I2CMaster<'a>, T: Time> i2c::I2CHwMasterClient for I2CMasterAnalyzer<'a, I, T> {
+    fn command_complete(&self, buffer: &'static mut [u8], status: Result<(), i2c::Error>) {
+        if let Some(pending) = self.pending.take() {
+            self.log
+                .complete(self.label, pending, status.map_err(|e| e.into()));
+        }
+        self.client
+            .map(|client| client.command_complete(buffer, status));
+    }
+}
+
+/// SPI device which logs its transactions under `label`.
+pub struct SpiDeviceAnalyzer<'a, S: spi::SpiMasterDevice<'a>, T: Time> {
+    spi: &'a S,
+    log: &'a BusLog<'a, T>,
+    label: &'static str,
+    client: OptionalCell<&'a dyn spi::SpiMasterClient>,
+    pending: Cell<Option<Pending<T::Ticks>>>,
+}
+
+impl<'a, S: spi::SpiMasterDevice<'a>, T: Time> SpiDeviceAnalyzer<'a, S, T> {
+    pub fn new(spi: &'a S, log: &'a BusLog<'a, T>, label: &'static str) -> Self {
+        Self {
+            spi,
+            log,
+            label,
+            client: OptionalCell::empty(),
+            pending: Cell::new(None),
+        }
+    }
+}
+
+impl<'a, S: spi::SpiMasterDevice<'a>, T: Time> spi::SpiMasterDevice<'a>
+    for SpiDeviceAnalyzer<'a, S, T>
+{
+    fn set_client(&self, client: &'a dyn spi::SpiMasterClient) {
+        self.client.set(client);
+    }
+
+    fn configure(&self, cpol: ClockPolarity, cpal: ClockPhase, rate: u32) -> Result<(), ErrorCode> {
+        self.spi.configure(cpol, cpal, rate)
+    }
+
+    fn read_write_bytes(
+        &self,
+        write_buffer: &'static mut [u8],
+        read_buffer: Option<&'static mut [u8]>,
+        len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8], Option<&'static mut [u8]>)> {
+        let (operation, read_len) = match read_buffer {
+            Some(_) => (Operation::Transfer, len),
+            None => (Operation::Write, 0),
+        };
+        let pending = Pending {
+            address: None,
+            operation,
+            write_len: len,
+            read_len,
+            start: self.log.now(),
+        };
+        // Set before the call, in case the transfer completes synchronously.
+        self.pending.set(Some(pending));
+        let result = self.spi.read_write_bytes(write_buffer, read_buffer, len);
+        if let Err((error, _, _)) = result {
+            self.pending.set(None);
+            self.log.refused(self.label, pending, error);
+        }
+        result
+    }
+
+    fn set_rate(&self, rate: u32) -> Result<(), ErrorCode> {
+        self.spi.set_rate(rate)
+    }
+
+    fn get_rate(&self) -> u32 {
+        self.spi.get_rate()
+    }
+
+    fn set_polarity(&self, polarity: ClockPolarity) -> Result<(), ErrorCode> {
+        self.spi.set_polarity(polarity)
+    }
+
+    fn get_polarity(&self) -> ClockPolarity {
+        self.spi.get_polarity()
+    }
+
+    fn set_phase(&self, phase: ClockPhase) -> Result<(), ErrorCode> {
+        self.spi.set_phase(phase)
+    }
+
+    fn get_phase(&self) -> ClockPhase {
+        self.spi.get_phase()
+    }
+}
+
+impl<'a, S: spi::SpiMasterDevice<'a>, T: Time> spi::SpiMasterClient
+    for SpiDeviceAnalyzer<'a, S, T>
+{
+    fn read_write_done(
+        &self,
+        write_buffer: &'static mut [u8],
+        read_buffer: Option<&'static mut [u8]>,
+        len: usize,
+        status: Result<(), ErrorCode>,
+    ) {
+        if let Some(pending) = self.pending.take() {
+            self.log.complete(self.label, pending, status);
+        }
+        self.client
+            .map(|client| client.read_write_done(write_buffer, read_buffer, len, status));
+    }
+}
@@ -0,0 +1,505 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Fault injection into virtual I2C and SPI devices.
+//!
+//! The wrappers sit between a virtual bus device and the capsule driving it,
+//! and make some of the transactions of the capsule fail, so that its error
+//! paths can be exercised on the board rather than waiting for a flaky bus:
+//!
+//! - a NACK completes the transaction with `AddressNak` on I2C, and with
+//!   `FAIL` on SPI, without reaching the bus;
+//! - a spurious completion reports the transaction as successful without
+//!   reaching the bus, so the capsule reads whatever its buffer held;
+//! - a timeout never completes the transaction, as when a device holds the
+//!   bus, and the wrapper then refuses every transaction with `Busy`. Only
+//!   capsules with their own timeout recover from it, by a reset.
+//!
+//! `FaultInjector` holds the probability of each fault, in per mille of the
+//! transactions, and is shared by the wrappers. It draws the faults from a
+//! pseudo-random generator, seeded by the board, so that a run can be
+//! reproduced. It is also the `faults` command of the process console:
+//!
+//! - `faults` prints the probabilities and the number of faults injected;
+//! - `faults <nack|spurious|timeout> <per mille>` sets a probability;
+//! - `faults off` stops injecting faults.
+//!
+//! ```text
+//! tock$ faults nack 100
+//! Faults: nack 100/1000, spurious 0/1000, timeout 0/1000
+//! Injected: 0 nack, 0 spurious, 0 timeout
+//! tock$ faults
+//! Faults: nack 100/1000, spurious 0/1000, timeout 0/1000
+//! Injected: 7 nack, 0 spurious, 0 timeout
+//! ```
+//!
+//! Every probability starts at zero, so a board can keep the wrappers in
+//! place and enable the faults from the console.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let fault_injector = components::bus_fault_injector::FaultInjectorComponent::new(0x2545_F491)
+//!     .finalize(components::fault_injector_component_static!());
+//! let i2c_device = components::bus_fault_injector::FaultyI2CDeviceComponent::new(
+//!     mux_i2c,
+//!     0x38,
+//!     fault_injector,
+//! )
+//! .finalize(components::faulty_i2c_device_component_static!(
+//!     stm32f412g::i2c::I2C<'static>
+//! ));
+//! let ft6x06 = static_init!(
+//!     capsules_extra::ft6x06::Ft6x06<
+//!         'static,
+//!         capsules_extra::bus_fault_injector::FaultyI2CDevice<
+//!             'static,
+//!             capsules_core::virtualizers::virtual_i2c::I2CDevice<
+//!                 'static,
+//!                 stm32f412g::i2c::I2C<'static>,
+//!             >,
+//!         >,
+//!     >,
+//!     capsules_extra::ft6x06::Ft6x06::new(i2c_device, ts_irq, buffer, events)
+//! );
+//! i2c_device.set_client(ft6x06);
+//! process_console.add_command(fault_injector).unwrap();
+//! ```
+
+use core::cell::Cell;
+use core::fmt;
+
+use capsules_core::process_console::ConsoleCommand;
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
+use kernel::hil::i2c;
+use kernel::hil::spi::{self, ClockPhase, ClockPolarity};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+/// Fault injected into a transaction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fault {
+    Nack,
+    Spurious,
+    Timeout,
+}
+
+/// Probability of each fault, in per mille of the transactions.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FaultRates {
+    pub nack: u16,
+    pub spurious: u16,
+    pub timeout: u16,
+}
+
+impl FaultRates {
+    fn total(&self) -> u32 {
+        self.nack as u32 + self.spurious as u32 + self.timeout as u32
+    }
+}
+
+/// What the last command line asked for.
+#[derive(Clone, Copy)]
+enum Request {
+    Status,
+    Error(&'static str),
+}
+
+/// Probabilities of the faults, shared by the wrappers.
+pub struct FaultInjector {
+    rates: Cell<FaultRates>,
+    /// State of the xorshift generator, never zero.
+    state: Cell<u32>,
+    nacks: Cell<usize>,
+    spurious: Cell<usize>,
+    timeouts: Cell<usize>,
+    request: Cell<Request>,
+}
+
+impl FaultInjector {
+    pub fn new(seed: u32) -> FaultInjector {
+        FaultInjector {
+            rates: Cell::new(FaultRates::default()),
+            state: Cell::new(if seed == 0 { 1 } else { seed }),
+            nacks: Cell::new(0),
+            spurious: Cell::new(0),
+            timeouts: Cell::new(0),
+            request: Cell::new(Request::Status),
+        }
+    }
+
+    pub fn rates(&self) -> FaultRates {
+        self.rates.get()
+    }
+
+    /// Set the probabilities of the faults. Fails with `INVAL` if they add
+    /// up to more than 1000 per mille.
+    pub fn set_rates(&self, rates: FaultRates) -> Result<(), ErrorCode> {
+        if rates.total() > 1000 {
+            return Err(ErrorCode::INVAL);
+        }
+        self.rates.set(rates);
+        Ok(())
+    }
+
+    /// Number of faults of each kind injected so far.
+    pub fn injected(&self, fault: Fault) -> usize {
+        match fault {
+            Fault::Nack => self.nacks.get(),
+            Fault::Spurious => self.spurious.get(),
+            Fault::Timeout => self.timeouts.get(),
+        }
+    }
+
+    fn random(&self) -> u32 {
+        let mut x = self.state.get();
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state.set(x);
+        x
+    }
+
+    /// Decide whether to inject a fault into the next transaction.
+    fn draw(&self) -> Option<Fault> {
+        let rates = self.rates.get();
+        if rates.total() == 0 {
+            return None;
+        }
+        let draw = self.random() % 1000;
+        let fault = if draw < rates.nack as u32 {
+            Fault::Nack
+        } else if draw < rates.nack as u32 + rates.spurious as u32 {
+            Fault::Spurious
+        } else if draw < rates.total() {
+            Fault::Timeout
+        } else {
+            return None;
+        };
+        let counter = match fault {
+            Fault::Nack => &self.nacks,
+            Fault::Spurious => &self.spurious,
+            Fault::Timeout => &self.timeouts,
+        };
+        counter.set(counter.get() + 1);
+        Some(fault)
+    }
+
+    fn parse(&self, arguments: &str) -> Request {
+        let mut words = arguments.split_whitespace();
+        let mut rates = self.rates.get();
+        let rate = match words.next() {
+            None => return Request::Status,
+            Some("off") => {
+                self.rates.set(FaultRates::default());
+                return Request::Status;
+            }
+            Some("nack") => &mut rates.nack,
+            Some("spurious") => &mut rates.spurious,
+            Some("timeout") => &mut rates.timeout,
+            Some(_) => return Request::Error("faults: faults are nack, spurious, timeout\r\n"),
+        };
+        match words.next().and_then(|word| word.parse::<u16>().ok()) {
+            Some(value) => *rate = value,
+            None => return Request::Error("faults: expected a rate in per mille\r\n"),
+        }
+        match self.set_rates(rates) {
+            Ok(()) => Request::Status,
+            Err(_) => Request::Error("faults: rates add up to more than 1000\r\n"),
+        }
+    }
+}
+
+impl ConsoleCommand for FaultInjector {
+    fn name(&self) -> &'static str {
+        "faults"
+    }
+
+    fn execute(&self, arguments: &str) {
+        self.request.set(self.parse(arguments));
+    }
+
+    fn write_line(&self, index: usize, writer: &mut dyn fmt::Write) -> bool {
+        let rates = self.rates.get();
+        match (self.request.get(), index) {
+            (Request::Status, 0) => {
+                let _ = writer.write_fmt(format_args!(
+                    "Faults: nack {}/1000, spurious {}/1000, timeout {}/1000\r\n",
+                    rates.nack, rates.spurious, rates.timeout
+                ));
+                true
+            }
+            (Request::Status, 1) => {
+                let _ = writer.write_fmt(format_args!(
+                    "Injected: {} nack, {} spurious, {} timeout\r\n",
+                    self.nacks.get(),
+                    self.spurious.get(),
+                    self.timeouts.get()
+                ));
+                true
+            }
+            (Request::Error(message), 0) => {
+                let _ = writer.write_str(message);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// State of a wrapper.
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    /// No transaction, or the transaction was passed on to the device.
+    Idle,
+    /// An injected completion waits for the deferred call.
+    Completing,
+    /// The transaction timed out: its buffers are kept.
+    TimedOut,
+}
+
+/// I2C device whose transactions fail at the rates of the `FaultInjector`.
+pub struct FaultyI2CDevice<'a, D: i2c::I2CDevice> {
+    device: &'a D,
+    injector: &'a FaultInjector,
+    client: OptionalCell<&'a dyn i2c::I2CClient>,
+    state: Cell<State>,
+    buffer: TakeCell<'static, [u8]>,
+    status: Cell<Result<(), i2c::Error>>,
+    deferred_call: DeferredCall,
+}
+
+impl<'a, D: i2c::I2CDevice> FaultyI2CDevice<'a, D> {
+    pub fn new(device: &'a D, injector: &'a FaultInjector) -> FaultyI2CDevice<'a, D> {
+        FaultyI2CDevice {
+            device,
+            injector,
+            client: OptionalCell::empty(),
+            state: Cell::new(State::Idle),
+            buffer: TakeCell::empty(),
+            status: Cell::new(Ok(())),
+            deferred_call: DeferredCall::new(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn i2c::I2CClient) {
+        self.client.set(client);
+    }
+
+    /// Inject a fault into the transaction, or pass it on with `f`.
+    fn start<F>(
+        &self,
+        buffer: &'static mut [u8],
+        f: F,
+    ) -> Result<(), (i2c::Error, &'static mut [u8])>
+    where
+        F: FnOnce(&'static mut [u8]) -> Result<(), (i2c::Error, &'static mut [u8])>,
+    {
+        if self.state.get() != State::Idle {
+            return Err((i2c::Error::Busy, buffer));
+        }
+        let status = match self.injector.draw() {
+            None => return f(buffer),
+            Some(Fault::Nack) => Err(i2c::Error::AddressNak),
+            Some(Fault::Spurious) => Ok(()),
+            Some(Fault::Timeout) => {
+                self.buffer.replace(buffer);
+                self.state.set(State::TimedOut);
+                return Ok(());
+            }
+        };
+        self.buffer.replace(buffer);
+        self.status.set(status);
+        self.state.set(State::Completing);
+        self.deferred_call.set();
+        Ok(())
+    }
+}
+
+impl<'a, D: i2c::I2CDevice> i2c::I2CDevice for FaultyI2CDevice<'a, D> {
+    fn enable(&self) {
+        self.device.enable();
+    }
+
+    fn disable(&self) {
+        self.device.disable();
+    }
+
+    fn write_read(
+        &self,
+        data: &'static mut [u8],
+        write_len: usize,
+        read_len: usize,
+    ) -> Result<(), (i2c::Error, &'static mut [u8])> {
+        self.start(data, |data| {
+            self.device.write_read(data, write_len, read_len)
+        })
+    }
+
+    fn write(
+        &self,
+        data: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (i2c::Error, &'static mut [u8])> {
+        self.start(data, |data| self.device.write(data, len))
+    }
+
+    fn read(
+        &self,
+        buffer: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (i2c::Error, &'static mut [u8])> {
+        self.start(buffer, |buffer| self.device.read(buffer, len))
+    }
+}
+
+impl<'a, D: i2c::I2CDevice> i2c::I2CClient for FaultyI2CDevice<'a, D> {
+    fn command_complete(&self, buffer: &'static mut [u8], status: Result<(), i2c::Error>) {
+        self.client
+            .map(|client| client.command_complete(buffer, status));
+    }
+}
+
+impl<'a, D: i2c::I2CDevice> DeferredCallClient for FaultyI2CDevice<'a, D> {
+    fn handle_deferred_call(&self) {
+        if self.state.get() != State::Completing {
+            return;
+        }
+        self.state.set(State::Idle);
+        if let Some(buffer) = self.buffer.take() {
+            let status = self.status.get();
+            self.client
+                .map(|client| client.command_complete(buffer, status));
+        }
+    }
+
+    fn register(&'static self) {
+        self.deferred_call.register(self);
+    }
+}
+
+/// SPI device whose transfers fail at the rates of the `FaultInjector`.
+pub struct FaultySpiDevice<'a, S: spi::SpiMasterDevice<'a>> {
+    spi: &'a S,
+    injector: &'a FaultInjector,
+    client: OptionalCell<&'a dyn spi::SpiMasterClient>,
+    state: Cell<State>,
+    write_buffer: TakeCell<'static, [u8]>,
+    read_buffer: TakeCell<'static, [u8]>,
+    len: Cell<usize>,
+    status: Cell<Result<(), ErrorCode>>,
+    deferred_call: DeferredCall,
+}
+
+impl<'a, S: spi::SpiMasterDevice<'a>> FaultySpiDevice<'a, S> {
+    pub fn new(spi: &'a S, injector: &'a FaultInjector) -> FaultySpiDevice<'a, S> {
+        FaultySpiDevice {
+            spi,
+            injector,
+            client: OptionalCell::empty(),
+            state: Cell::new(State::Idle),
+            write_buffer: TakeCell::empty(),
+            read_buffer: TakeCell::empty(),
+            len: Cell::new(0),
+            status: Cell::new(Ok(())),
+            deferred_call: DeferredCall::new(),
+        }
+    }
+}
+
+impl<'a, S: spi::SpiMasterDevice<'a>> spi::SpiMasterDevice<'a> for FaultySpiDevice<'a, S> {
+    fn set_client(&self, client: &'a dyn spi::SpiMasterClient) {
+        self.client.set(client);
+    }
+
+    fn configure(&self, cpol: ClockPolarity, cpal: ClockPhase, rate: u32) -> Result<(), ErrorCode> {
+        self.spi.configure(cpol, cpal, rate)
+    }
+
+    fn read_write_bytes(
+        &self,
+        write_buffer: &'static mut [u8],
+        read_buffer: Option<&'static mut [u8]>,
+        len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8], Option<&'static mut [u8]>)> {
+        if self.state.get() != State::Idle {
+            return Err((ErrorCode::BUSY, write_buffer, read_buffer));
+        }
+        let status = match self.injector.draw() {
+            None => return self.spi.read_write_bytes(write_buffer, read_buffer, len),
+            Some(Fault::Nack) => Err(ErrorCode::FAIL),
+            Some(Fault::Spurious) => Ok(()),
+            Some(Fault::Timeout) => {
+                self.write_buffer.replace(write_buffer);
+                read_buffer.map(|buffer| self.read_buffer.replace(buffer));
+                self.state.set(State::TimedOut);
+                return Ok(());
+            }
+        };
+        self.write_buffer.replace(write_buffer);
+        read_buffer.map(|buffer| self.read_buffer.replace(buffer));
+        self.len.set(len);
+        self.status.set(status);
+        self.state.set(State::Completing);
+        self.deferred_call.set();
+        Ok(())
+    }
+
+    fn set_rate(&self, rate: u32) -> Result<(), ErrorCode> {
+        self.spi.set_rate(rate)
+    }
+
+    fn get_rate(&self) -> u32 {
+        self.spi.get_rate()
+    }
+
+    fn set_polarity(&self, polarity: ClockPolarity) -> Result<(), ErrorCode> {
+        self.spi.set_polarity(polarity)
+    }
+
+    fn get_polarity(&self) -> ClockPolarity {
+        self.spi.get_polarity()
+    }
+
+    fn set_phase(&self, phase: ClockPhase) -> Result<(), ErrorCode> {
+        self.spi.set_phase(phase)
+    }
+
+    fn get_phase(&self) -> ClockPhase {
+        self.spi.get_phase()
+    }
+}
+
+impl<'a, S: spi::SpiMasterDevice<'a>> spi::SpiMasterClient for FaultySpiDevice<'a, S> {
+    fn read_write_done(
+        &self,
+        write_buffer: &'static mut [u8],
+        read_buffer: Option<&'static mut [u8]>,
+        len: usize,
+        status: Result<(), ErrorCode>,
+    ) {
+        self.client
+            .map(|client| client.read_write_done(write_buffer, read_buffer, len, status));
+    }
+}
+
+impl<'a, S: spi::SpiMasterDevice<'a>> DeferredCallClient for FaultySpiDevice<'a, S> {
+    fn handle_deferred_call(&self) {
+        if self.state.get() != State::Completing {
+            return;
+        }
+        self.state.set(State::Idle);
+        if let Some(write_buffer) = self.write_buffer.take() {
+            let read_buffer = self.read_buffer.take();
+            let (len, status) = (self.len.get(), self.status.get());
+            self.client
+                .map(|client| client.read_write_done(write_buffer, read_buffer, len, status));
+        }
+    }
+
+    fn register(&'static self) {
+        self.deferred_call.register(self);
+    }
+}
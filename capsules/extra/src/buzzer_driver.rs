@@ -9,6 +9,9 @@
 //! can specify the frequency and duration of the square wave buzz, but the
 //! duration is capped to prevent this from being annoying.
 //!
+//! Apps can also request a frequency sweep, which the buzzer plays as a
+//! series of tones without a system call per tone.
+//!
 //! Apps can subscribe to an optional callback if they care about getting
 //! buzz done events.
 //!
@@ -85,6 +88,12 @@ pub enum BuzzerCommand {
         frequency_hz: usize,
         duration_ms: usize,
     },
+    Sweep {
+        start_hz: usize,
+        end_hz: usize,
+        duration_ms: usize,
+        steps: usize,
+    },
 }
 
 #[derive(Default)]
@@ -133,6 +142,12 @@ impl<'a, B: hil::buzzer::Buzzer<'a>> Buzzer<'a, B> {
                     frequency_hz,
                     duration_ms,
                 } => self.buzzer.buzz(frequency_hz, duration_ms),
+                BuzzerCommand::Sweep {
+                    start_hz,
+                    end_hz,
+                    duration_ms,
+                    steps,
+                } => self.buzzer.sweep(start_hz, end_hz, duration_ms, steps),
             }
         } else {
             // There is an active app, so queue this request (if possible).
@@ -167,6 +182,12 @@ impl<'a, B: hil::buzzer::Buzzer<'a>> Buzzer<'a, B> {
                             frequency_hz,
                             duration_ms,
                         } => self.buzzer.buzz(frequency_hz, duration_ms) == Ok(()),
+                        BuzzerCommand::Sweep {
+                            start_hz,
+                            end_hz,
+                            duration_ms,
+                            steps,
+                        } => self.buzzer.sweep(start_hz, end_hz, duration_ms, steps) == Ok(()),
                     }
                 })
             });
@@ -231,6 +252,11 @@ impl<'a, B: hil::buzzer::Buzzer<'a>> SyscallDriver for Buzzer<'a, B> {
     ///   `data2` is the duration in ms. Note the duration is capped at 5000
     ///   milliseconds.
     /// - `3`: Stop the buzzer.
+    /// - `4`: Sweep the buzzer when available. The lower 16 bits of `data1`
+    ///   are the start frequency in hertz and the upper 16 bits the end
+    ///   frequency. The lower 16 bits of `data2` are the duration in ms,
+    ///   capped at 5000 milliseconds, and the upper 16 bits the number of
+    ///   tones.
     fn command(
         &self,
         command_num: usize,
@@ -282,6 +308,27 @@ impl<'a, B: hil::buzzer::Buzzer<'a>> SyscallDriver for Buzzer<'a, B> {
                 }
             }
 
+            // Play a sweep when available.
+            4 => {
+                let start_hz = data1 & 0xFFFF;
+                let end_hz = (data1 >> 16) & 0xFFFF;
+                let duration_ms = cmp::min(data2 & 0xFFFF, self.max_duration_ms);
+                let steps = (data2 >> 16) & 0xFFFF;
+                if steps == 0 || duration_ms / steps == 0 {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                self.enqueue_command(
+                    BuzzerCommand::Sweep {
+                        start_hz,
+                        end_hz,
+                        duration_ms,
+                        steps,
+                    },
+                    processid,
+                )
+                .into()
+            }
+
             _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
         }
     }
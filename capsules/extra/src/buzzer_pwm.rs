@@ -49,7 +49,12 @@
 //! virtual_alarm_buzzer.set_alarm_client(pwm_buzzer);
 //!
 //! ```
+//!
+//! Besides single tones, the buzzer can sweep from one frequency to another in
+//! a number of steps. The alarm fires at the end of every step and moves the
+//! PWM pin to the next frequency, so a siren takes a single request.
 
+use core::cell::Cell;
 use core::cmp;
 
 use kernel::hil;
@@ -61,6 +66,34 @@ use kernel::ErrorCode;
 /// Standard max buzz time.
 pub const DEFAULT_MAX_BUZZ_TIME_MS: usize = 5000;
 
+/// A frequency sweep in progress.
+#[derive(Clone, Copy)]
+struct Sweep {
+    start_hz: usize,
+    end_hz: usize,
+    /// Number of tones in the sweep.
+    steps: usize,
+    /// Index of the tone currently playing.
+    step: usize,
+    /// Alarm ticks each tone lasts.
+    interval: u32,
+}
+
+impl Sweep {
+    /// Frequency of tone `step`, going linearly from `start_hz` to `end_hz`.
+    fn frequency_hz(&self, step: usize) -> usize {
+        if self.steps <= 1 {
+            return self.start_hz;
+        }
+        let last = self.steps - 1;
+        if self.end_hz >= self.start_hz {
+            self.start_hz + (self.end_hz - self.start_hz) * step / last
+        } else {
+            self.start_hz - (self.start_hz - self.end_hz) * step / last
+        }
+    }
+}
+
 pub struct PwmBuzzer<'a, A: hil::time::Alarm<'a>, P: hil::pwm::PwmPin> {
     /// The underlying PWM generator to make the buzzer buzz.
     pwm_pin: &'a P,
@@ -70,6 +103,8 @@ pub struct PwmBuzzer<'a, A: hil::time::Alarm<'a>, P: hil::pwm::PwmPin> {
     max_duration_ms: usize,
     /// The client currently using the service capsule.
     client: OptionalCell<&'a dyn BuzzerClient>,
+    /// The sweep playing, if any.
+    sweep: Cell<Option<Sweep>>,
 }
 
 impl<'a, A: hil::time::Alarm<'a>, P: hil::pwm::PwmPin> PwmBuzzer<'a, A, P> {
@@ -79,8 +114,14 @@ impl<'a, A: hil::time::Alarm<'a>, P: hil::pwm::PwmPin> PwmBuzzer<'a, A, P> {
             alarm: alarm,
             client: OptionalCell::empty(),
             max_duration_ms: max_duration_ms,
+            sweep: Cell::new(None),
         }
     }
+
+    /// Convert a duration to alarm ticks.
+    fn ms_to_ticks(duration_ms: usize) -> u32 {
+        (duration_ms as u32) * <A::Frequency>::frequency() / 1000
+    }
 }
 
 impl<'a, A: hil::time::Alarm<'a>, P: hil::pwm::PwmPin> hil::buzzer::Buzzer<'a>
@@ -92,17 +133,48 @@ impl<'a, A: hil::time::Alarm<'a>, P: hil::pwm::PwmPin> hil::buzzer::Buzzer<'a>
 
     fn buzz(&self, frequency_hz: usize, duration_ms: usize) -> Result<(), ErrorCode> {
         let duration_ms_cmp = cmp::min(duration_ms, self.max_duration_ms);
+        self.sweep.set(None);
         self.pwm_pin
             .start(frequency_hz, self.pwm_pin.get_maximum_duty_cycle() / 2)?;
 
         // Set an alarm for the given duration.
-        let interval = (duration_ms_cmp as u32) * <A::Frequency>::frequency() / 1000;
+        let interval = Self::ms_to_ticks(duration_ms_cmp);
+        self.alarm
+            .set_alarm(self.alarm.now(), A::Ticks::from(interval));
+        Ok(())
+    }
+
+    fn sweep(
+        &self,
+        start_hz: usize,
+        end_hz: usize,
+        duration_ms: usize,
+        steps: usize,
+    ) -> Result<(), ErrorCode> {
+        let duration_ms_cmp = cmp::min(duration_ms, self.max_duration_ms);
+        if steps == 0 || duration_ms_cmp / steps == 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        self.sweep.set(None);
+        self.pwm_pin
+            .start(start_hz, self.pwm_pin.get_maximum_duty_cycle() / 2)?;
+
+        // Set an alarm for the end of the first tone.
+        let interval = Self::ms_to_ticks(duration_ms_cmp / steps);
+        self.sweep.set(Some(Sweep {
+            start_hz,
+            end_hz,
+            steps,
+            step: 0,
+            interval,
+        }));
         self.alarm
             .set_alarm(self.alarm.now(), A::Ticks::from(interval));
         Ok(())
     }
 
     fn stop(&self) -> Result<(), ErrorCode> {
+        self.sweep.set(None);
         // Disarm the current alarm and instantly fire another.
         self.alarm.disarm()?;
         // This method was used to reduce the size of the code.
@@ -115,6 +187,33 @@ impl<'a, A: hil::time::Alarm<'a>, P: hil::pwm::PwmPin> hil::time::AlarmClient
     for PwmBuzzer<'a, A, P>
 {
     fn alarm(&self) {
+        // Move a sweep to its next tone, counting the interval from the
+        // previous alarm so the steps do not drift.
+        if let Some(mut sweep) = self.sweep.get() {
+            sweep.step += 1;
+            if sweep.step < sweep.steps {
+                let frequency_hz = sweep.frequency_hz(sweep.step);
+                let started = self
+                    .pwm_pin
+                    .start(frequency_hz, self.pwm_pin.get_maximum_duty_cycle() / 2);
+                match started {
+                    Ok(()) => {
+                        self.sweep.set(Some(sweep));
+                        self.alarm
+                            .set_alarm(self.alarm.get_alarm(), A::Ticks::from(sweep.interval));
+                    }
+                    Err(e) => {
+                        self.sweep.set(None);
+                        let _ = self.pwm_pin.stop();
+                        self.client
+                            .map(|buzz_client| buzz_client.buzzer_done(Err(e)));
+                    }
+                }
+                return;
+            }
+            self.sweep.set(None);
+        }
+
         // Stop the pin output and signal that the buzzer has finished
         // playing.
         self.client
@@ -0,0 +1,594 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Provides userspace with a cellular modem controlled with AT commands,
+//! such as the SIMCom SIM800 or the Quectel BG96 and EC21.
+//!
+//! The commands run on an `AtEngine`, which the modem uses alone.
+//! Applications use the modem through a few operations, each a sequence of
+//! AT commands which depends on the command set of the modem:
+//!
+//! - attach to the packet network with an APN,
+//! - send an SMS in text mode,
+//! - open, send on, read and close one TCP connection.
+//!
+//! Received TCP data stays in the modem until it is read: the capsule
+//! notifies the application, which reads it in chunks.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let engine = components::at_engine::AtEngineComponent::new(
+//!     &peripherals.uart1,
+//!     mux_alarm,
+//!     115200,
+//! )
+//! .finalize(components::at_engine_component_static!(
+//!     rp2040::uart::Uart<'static>,
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! let modem = components::cellular_modem::CellularModemComponent::new(
+//!     board_kernel,
+//!     capsules_extra::cellular_modem::DRIVER_NUM,
+//!     engine,
+//!     capsules_extra::cellular_modem::Dialect::Quectel,
+//! )
+//! .finalize(components::cellular_modem_component_static!(
+//!     rp2040::uart::Uart<'static>,
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! - Read-only allow 0: argument of the operation, the APN, the phone number
+//!   or the host name.
+//! - Read-only allow 1: data to send, the text of an SMS or TCP data.
+//! - Read-write allow 0: buffer TCP data is read into.
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: attach to the packet network. The calling process becomes
+//!   the owner of the modem, other processes get RESERVE.
+//! - Command 2: send an SMS.
+//! - Command 3: open a TCP connection to port `data1`.
+//! - Command 4: send data on the connection.
+//! - Command 5: read received data.
+//! - Command 6: close the connection.
+//!
+//! - Upcall 0: an operation is done, with its command number, its status,
+//!   and for reads the number of bytes read. Timeouts are reported as NOACK.
+//! - Upcall 1: an event, 1 for data received on the connection and 2 for
+//!   the connection closed by the server.
+
+use core::cell::Cell;
+use core::fmt::Write;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::time::Alarm;
+use kernel::hil::uart;
+use kernel::processbuffer::{ReadableProcessBuffer, WriteableProcessBuffer};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::{ErrorCode, ProcessId};
+
+use crate::at_engine::{self, AtClient, AtEngine, CommandWriter, BUFFER_LEN};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::CellularModem as usize;
+
+/// Ids for read-only allow buffers
+mod ro_allow {
+    pub const ARGUMENT: usize = 0;
+    pub const DATA: usize = 1;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 2;
+}
+
+/// Ids for read-write allow buffers
+mod rw_allow {
+    pub const READ: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+/// Size of the buffer TCP data is read into, the most read at once.
+pub const READ_LEN: usize = BUFFER_LEN;
+
+/// Longest SMS in text mode.
+const SMS_LEN: usize = 160;
+/// Ends the text of an SMS.
+const CTRL_Z: u8 = 0x1A;
+
+const TIMEOUT_MS: u32 = 5_000;
+/// Timeout of the commands which wait for the network.
+const NETWORK_TIMEOUT_MS: u32 = 150_000;
+
+const EVENT_DATA: usize = 1;
+const EVENT_CLOSED: usize = 2;
+
+/// AT command set of the modem.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// SIMCom SIM800 and SIM900
+    Sim800,
+    /// Quectel BG95, BG96, EC21 and EC25
+    Quectel,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Attach = 1,
+    SendSms = 2,
+    Open = 3,
+    Send = 4,
+    Read = 5,
+    Close = 6,
+}
+
+/// What ends the command being run.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Ending {
+    /// A final result code
+    Result,
+    /// Any response line
+    Line,
+    /// The result of opening the connection, reported after `OK`
+    Connection,
+}
+
+#[derive(Default)]
+pub struct App;
+
+pub struct CellularModem<'a, U: uart::Uart<'a>, A: Alarm<'a>> {
+    engine: &'a AtEngine<'a, U, A>,
+    dialect: Dialect,
+    apps: Grant<
+        App,
+        UpcallCount<2>,
+        AllowRoCount<{ ro_allow::COUNT }>,
+        AllowRwCount<{ rw_allow::COUNT }>,
+    >,
+    owner: OptionalCell<ProcessId>,
+    operation: OptionalCell<Operation>,
+    /// Command of the operation being run
+    step: Cell<usize>,
+    ending: Cell<Ending>,
+    /// Port of the connection being opened
+    port: Cell<usize>,
+    /// Bytes sent or read by the operation
+    data_len: Cell<usize>,
+    data: TakeCell<'static, [u8]>,
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> CellularModem<'a, U, A> {
+    pub fn new(
+        engine: &'a AtEngine<'a, U, A>,
+        dialect: Dialect,
+        grant: Grant<
+            App,
+            UpcallCount<2>,
+            AllowRoCount<{ ro_allow::COUNT }>,
+            AllowRwCount<{ rw_allow::COUNT }>,
+        >,
+        data: &'static mut [u8; READ_LEN],
+    ) -> CellularModem<'a, U, A> {
+        CellularModem {
+            engine,
+            dialect,
+            apps: grant,
+            owner: OptionalCell::empty(),
+            operation: OptionalCell::empty(),
+            step: Cell::new(0),
+            ending: Cell::new(Ending::Result),
+            port: Cell::new(0),
+            data_len: Cell::new(0),
+            data: TakeCell::new(data),
+        }
+    }
+
+    /// Write the argument of the owner, which is quoted in commands.
+    fn write_argument(&self, writer: &mut CommandWriter) -> Result<(), ErrorCode> {
+        self.owner.map_or(Err(ErrorCode::RESERVE), |processid| {
+            self.apps
+                .enter(*processid, |_, kernel_data| {
+                    kernel_data
+                        .get_readonly_processbuffer(ro_allow::ARGUMENT)
+                        .and_then(|argument| {
+                            argument.enter(|argument| {
+                                if argument.len() == 0 {
+                                    return Err(ErrorCode::INVAL);
+                                }
+                                for byte in argument.iter() {
+                                    match byte.get() {
+                                        b'"' | b'\r' | b'\n' => return Err(ErrorCode::INVAL),
+                                        byte => writer.push(&[byte])?,
+                                    }
+                                }
+                                Ok(())
+                            })
+                        })
+                        .unwrap_or(Err(ErrorCode::RESERVE))
+                })
+                .unwrap_or(Err(ErrorCode::RESERVE))
+        })
+    }
+
+    /// Length of the data the owner sends.
+    fn send_len(&self) -> Result<usize, ErrorCode> {
+        self.owner.map_or(Err(ErrorCode::RESERVE), |processid| {
+            self.apps
+                .enter(*processid, |_, kernel_data| {
+                    kernel_data
+                        .get_readonly_processbuffer(ro_allow::DATA)
+                        .map_or(Err(ErrorCode::RESERVE), |data| Ok(data.len()))
+                })
+                .unwrap_or(Err(ErrorCode::RESERVE))
+        })
+    }
+
+    /// Number of commands of `operation`.
+    fn steps(&self, operation: Operation) -> usize {
+        match (operation, self.dialect) {
+            (Operation::Attach, Dialect::Sim800) => 6,
+            (Operation::Attach, Dialect::Quectel) => 4,
+            (Operation::SendSms, _) => 2,
+            _ => 1,
+        }
+    }
+
+    /// Timeout of command `step` of `operation`.
+    fn timeout_ms(&self, operation: Operation, step: usize) -> u32 {
+        match (operation, step, self.dialect) {
+            (Operation::Attach, 1, _)
+            | (Operation::Attach, 4, Dialect::Sim800)
+            | (Operation::Attach, 3, Dialect::Quectel)
+            | (Operation::SendSms, 1, _)
+            | (Operation::Open, _, _)
+            | (Operation::Send, _, _) => NETWORK_TIMEOUT_MS,
+            _ => TIMEOUT_MS,
+        }
+    }
+
+    /// Write command `step` of `operation`.
+    fn write_command(
+        &self,
+        operation: Operation,
+        step: usize,
+        writer: &mut CommandWriter,
+    ) -> Result<(), ErrorCode> {
+        let overflow = |_| ErrorCode::SIZE;
+        let sim800 = self.dialect == Dialect::Sim800;
+        match (operation, step) {
+            (Operation::Attach, 0) => writer.push(b"ATE0"),
+            (Operation::Attach, 1) => writer.push(b"AT+CGATT=1"),
+            // Keep received data in the modem until it is read
+            (Operation::Attach, 2) if sim800 => writer.push(b"AT+CIPRXGET=1"),
+            (Operation::Attach, 3) if sim800 => {
+                writer.push(b"AT+CSTT=\"")?;
+                self.write_argument(writer)?;
+                writer.push(b"\"")
+            }
+            (Operation::Attach, 4) if sim800 => writer.push(b"AT+CIICR"),
+            (Operation::Attach, 5) if sim800 => writer.push(b"AT+CIFSR"),
+            (Operation::Attach, 2) => {
+                writer.push(b"AT+QICSGP=1,1,\"")?;
+                self.write_argument(writer)?;
+                writer.push(b"\",\"\",\"\",1")
+            }
+            (Operation::Attach, 3) => writer.push(b"AT+QIACT=1"),
+            (Operation::SendSms, 0) => writer.push(b"AT+CMGF=1"),
+            (Operation::SendSms, 1) => {
+                writer.push(b"AT+CMGS=\"")?;
+                self.write_argument(writer)?;
+                writer.push(b"\"")
+            }
+            (Operation::Open, _) => {
+                if sim800 {
+                    writer.push(b"AT+CIPSTART=\"TCP\",\"")?;
+                } else {
+                    writer.push(b"AT+QIOPEN=1,0,\"TCP\",\"")?;
+                }
+                self.write_argument(writer)?;
+                write!(writer, "\",{}", self.port.get()).map_err(overflow)?;
+                if sim800 {
+                    Ok(())
+                } else {
+                    writer.push(b",0,0")
+                }
+            }
+            (Operation::Send, _) if sim800 => {
+                write!(writer, "AT+CIPSEND={}", self.data_len.get()).map_err(overflow)
+            }
+            (Operation::Send, _) => {
+                write!(writer, "AT+QISEND=0,{}", self.data_len.get()).map_err(overflow)
+            }
+            (Operation::Read, _) if sim800 => {
+                write!(writer, "AT+CIPRXGET=2,{}", self.data_len.get()).map_err(overflow)
+            }
+            (Operation::Read, _) => {
+                write!(writer, "AT+QIRD=0,{}", self.data_len.get()).map_err(overflow)
+            }
+            (Operation::Close, _) if sim800 => writer.push(b"AT+CIPCLOSE"),
+            (Operation::Close, _) => writer.push(b"AT+QICLOSE=0"),
+            _ => Err(ErrorCode::FAIL),
+        }
+    }
+
+    /// Send the next command of the operation, or finish it.
+    fn next_command(&self) {
+        let operation = match self.operation.extract() {
+            Some(operation) => operation,
+            None => return,
+        };
+        let step = self.step.get();
+        if step == self.steps(operation) {
+            return self.finish(Ok(()));
+        }
+
+        self.step.set(step + 1);
+        self.ending.set(match (operation, step) {
+            (Operation::Attach, 5) => Ending::Line,
+            (Operation::Open, _) => Ending::Connection,
+            _ => Ending::Result,
+        });
+        let result = self
+            .engine
+            .command(self.timeout_ms(operation, step), |writer| {
+                self.write_command(operation, step, writer)
+            });
+        if let Err(err) = result {
+            self.finish(Err(err));
+        }
+    }
+
+    /// End the operation and notify the owner.
+    fn finish(&self, result: Result<(), ErrorCode>) {
+        let operation = match self.operation.take() {
+            Some(operation) => operation,
+            None => return,
+        };
+
+        let mut len = 0;
+        self.owner.map(|processid| {
+            let _ = self.apps.enter(*processid, |_, kernel_data| {
+                if operation == Operation::Read && result.is_ok() {
+                    len = self.data_len.get();
+                    self.data.map(|data| {
+                        let _ = kernel_data
+                            .get_readwrite_processbuffer(rw_allow::READ)
+                            .and_then(|read| {
+                                read.mut_enter(|read| {
+                                    len = len.min(read.len());
+                                    read[..len].copy_from_slice(&data[..len]);
+                                })
+                            });
+                    });
+                }
+                kernel_data
+                    .schedule_upcall(
+                        0,
+                        (
+                            operation as usize,
+                            kernel::errorcode::into_statuscode(result),
+                            len,
+                        ),
+                    )
+                    .ok();
+            });
+        });
+    }
+
+    fn event(&self, event: usize) {
+        self.owner.map(|processid| {
+            let _ = self.apps.enter(*processid, |_, kernel_data| {
+                kernel_data.schedule_upcall(1, (event, 0, 0)).ok();
+            });
+        });
+    }
+
+    /// Start `operation` for `processid`, sending or reading `data_len`
+    /// bytes.
+    fn start(
+        &self,
+        operation: Operation,
+        processid: ProcessId,
+        data_len: usize,
+    ) -> Result<(), ErrorCode> {
+        if !self.owner.contains(&processid) {
+            return Err(ErrorCode::RESERVE);
+        } else if self.operation.is_some() {
+            return Err(ErrorCode::BUSY);
+        }
+        self.operation.set(operation);
+        self.step.set(0);
+        self.data_len.set(data_len);
+        self.next_command();
+        Ok(())
+    }
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> AtClient for CellularModem<'a, U, A> {
+    fn unsolicited(&self, line: &[u8]) -> bool {
+        if line == b"+CIPRXGET: 1" || line.starts_with(b"+QIURC: \"recv\"") {
+            self.event(EVENT_DATA);
+            true
+        } else if line == b"CLOSED" || line.starts_with(b"+QIURC: \"closed\"") {
+            self.event(EVENT_CLOSED);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn response(&self, line: &[u8]) -> Option<Result<(), ErrorCode>> {
+        match self.ending.get() {
+            Ending::Connection => match line {
+                b"CONNECT OK" | b"+QIOPEN: 0,0" => Some(Ok(())),
+                b"CONNECT FAIL" | b"ALREADY CONNECT" => Some(Err(ErrorCode::FAIL)),
+                _ if line.starts_with(b"+QIOPEN:") => Some(Err(ErrorCode::FAIL)),
+                // The connection is opened after `OK`
+                b"OK" => None,
+                _ => at_engine::result_code(line),
+            },
+            // The IP address, which `AT+CIFSR` answers without `OK`
+            Ending::Line => Some(at_engine::result_code(line).unwrap_or(Ok(()))),
+            Ending::Result => match line {
+                b"SEND OK" | b"CLOSE OK" => Some(Ok(())),
+                b"SEND FAIL" => Some(Err(ErrorCode::FAIL)),
+                _ if self.operation.contains(&Operation::Read) => {
+                    // Header of the data read, which follows the line
+                    let len = match self.dialect {
+                        Dialect::Sim800 => at_engine::parse_number(line, b"+CIPRXGET: 2,", 0),
+                        Dialect::Quectel => at_engine::parse_number(line, b"+QIRD: ", 0),
+                    };
+                    match len {
+                        Some(len) => {
+                            self.data_len.set(0);
+                            self.engine.receive_data(len);
+                            None
+                        }
+                        None => at_engine::result_code(line),
+                    }
+                }
+                _ => at_engine::result_code(line),
+            },
+        }
+    }
+
+    fn prompt(&self) {
+        let len = self.data_len.get();
+        let sms = self.operation.contains(&Operation::SendSms);
+        let result = self.engine.send_data(|tx_buffer| {
+            self.owner.map_or(Err(ErrorCode::RESERVE), |processid| {
+                self.apps
+                    .enter(*processid, |_, kernel_data| {
+                        kernel_data
+                            .get_readonly_processbuffer(ro_allow::DATA)
+                            .and_then(|data| {
+                                data.enter(|data| {
+                                    data.get(0..len).map(|data| {
+                                        data.copy_to_slice(&mut tx_buffer[..len]);
+                                    })
+                                })
+                            })
+                            .ok()
+                            .flatten()
+                            .ok_or(ErrorCode::SIZE)
+                    })
+                    .unwrap_or(Err(ErrorCode::RESERVE))
+            })?;
+            if sms {
+                tx_buffer[len] = CTRL_Z;
+                Ok(len + 1)
+            } else {
+                Ok(len)
+            }
+        });
+        if let Err(err) = result {
+            self.engine.abort();
+            self.finish(Err(err));
+        }
+    }
+
+    fn data_received(&self, byte: u8) {
+        let len = self.data_len.get();
+        if len < READ_LEN {
+            self.data.map(|data| data[len] = byte);
+            self.data_len.set(len + 1);
+        }
+    }
+
+    fn command_done(&self, result: Result<(), ErrorCode>) {
+        match result {
+            Ok(()) => self.next_command(),
+            Err(err) => self.finish(Err(err)),
+        }
+    }
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> SyscallDriver for CellularModem<'a, U, A> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        _data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        let result = match command_num {
+            0 => return CommandReturn::success(),
+
+            // Attach, and take the modem if its owner is gone
+            1 => {
+                let owned = self.owner.map_or(false, |owner| {
+                    *owner != processid && self.apps.enter(*owner, |_, _| ()).is_ok()
+                });
+                if owned {
+                    Err(ErrorCode::RESERVE)
+                } else {
+                    if !self.owner.contains(&processid) && self.operation.is_none() {
+                        self.owner.set(processid);
+                    }
+                    self.start(Operation::Attach, processid, 0)
+                }
+            }
+
+            // Send an SMS
+            2 => match self.send_len() {
+                Ok(len) if len > SMS_LEN => Err(ErrorCode::SIZE),
+                Ok(len) => self.start(Operation::SendSms, processid, len),
+                Err(err) => Err(err),
+            },
+
+            // Open the connection
+            3 => {
+                if data1 == 0 || data1 > 0xFFFF {
+                    Err(ErrorCode::INVAL)
+                } else if self.operation.is_some() {
+                    Err(ErrorCode::BUSY)
+                } else {
+                    self.port.set(data1);
+                    self.start(Operation::Open, processid, 0)
+                }
+            }
+
+            // Send data on the connection
+            4 => match self.send_len() {
+                Ok(len) if len == 0 || len > BUFFER_LEN => Err(ErrorCode::SIZE),
+                Ok(len) => self.start(Operation::Send, processid, len),
+                Err(err) => Err(err),
+            },
+
+            // Read received data
+            5 => {
+                let len = self
+                    .apps
+                    .enter(processid, |_, kernel_data| {
+                        kernel_data
+                            .get_readwrite_processbuffer(rw_allow::READ)
+                            .map_or(0, |read| read.len())
+                    })
+                    .unwrap_or(0)
+                    .min(READ_LEN);
+                if len == 0 {
+                    Err(ErrorCode::SIZE)
+                } else {
+                    self.start(Operation::Read, processid, len)
+                }
+            }
+
+            // Close the connection
+            6 => self.start(Operation::Close, processid, 0),
+
+            _ => Err(ErrorCode::NOSUPPORT),
+        };
+        CommandReturn::from(result)
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
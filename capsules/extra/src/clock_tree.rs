@@ -0,0 +1,124 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Process console command printing the clock tree of the chip.
+//!
+//! `ClockTreeCommand` implements the `clocks` command, which prints every
+//! clock the chip reports with its source, multiplier, divider and
+//! frequency, followed by the peripheral clock gates. Gates marked with `-`
+//! are off, so registers of their peripheral read as zero and writes are
+//! ignored, usually without any error.
+//!
+//! ```text
+//! tock$ clocks
+//! Clocks:
+//!  hsi        on                            16000000 Hz
+//!  pll        on  <- hsi       x192  /32    96000000 Hz
+//!  sysclk     on  <- pll       x1    /1     96000000 Hz
+//!  apb1       on  <- ahb       x1    /2     48000000 Hz
+//! Clock gates (+ on, - off):
+//!  +gpioa      +gpiob      -gpioc      -gpiod
+//!  +i2c1       -spi3       +tim2       +usart2
+//! ```
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let clocks = components::clock_tree::ClockTreeCommandComponent::new(&peripherals.rcc)
+//!     .finalize(components::clock_tree_command_component_static!());
+//! process_console.add_command(clocks).unwrap();
+//! ```
+
+use core::fmt;
+
+use capsules_core::process_console::ConsoleCommand;
+use kernel::hil::clock_tree::ClockTree;
+
+/// Number of clock gates printed on each line.
+const GATES_PER_LINE: usize = 4;
+
+pub struct ClockTreeCommand<'a> {
+    clock_tree: &'a dyn ClockTree,
+}
+
+impl<'a> ClockTreeCommand<'a> {
+    pub fn new(clock_tree: &'a dyn ClockTree) -> ClockTreeCommand<'a> {
+        ClockTreeCommand { clock_tree }
+    }
+
+    fn num_clocks(&self) -> usize {
+        (0..)
+            .take_while(|&index| self.clock_tree.clock(index).is_some())
+            .count()
+    }
+
+    fn write_clock(&self, index: usize, writer: &mut dyn fmt::Write) {
+        if let Some(clock) = self.clock_tree.clock(index) {
+            let _ = writer.write_fmt(format_args!(
+                " {:<10} {:<3} ",
+                clock.name,
+                if clock.enabled { "on" } else { "off" }
+            ));
+            let _ = match clock.source {
+                Some(source) => writer.write_fmt(format_args!(
+                    "<- {:<8} x{:<4} /{:<4}",
+                    source, clock.multiplier, clock.divider
+                )),
+                None => writer.write_fmt(format_args!("{:<25}", "")),
+            };
+            let _ = match clock.frequency_hz {
+                Some(hz) => writer.write_fmt(format_args!(" {:>10} Hz\r\n", hz)),
+                None => writer.write_str("          ? Hz\r\n"),
+            };
+        }
+    }
+
+    /// Write the gates of line `line`. Returns `false` if there are none.
+    fn write_gates(&self, line: usize, writer: &mut dyn fmt::Write) -> bool {
+        let first = line * GATES_PER_LINE;
+        let mut written = false;
+        for index in first..first + GATES_PER_LINE {
+            match self.clock_tree.clock_gate(index) {
+                Some(gate) => {
+                    let _ = writer.write_fmt(format_args!(
+                        " {}{:<10}",
+                        if gate.enabled { '+' } else { '-' },
+                        gate.name
+                    ));
+                    written = true;
+                }
+                None => break,
+            }
+        }
+        if written {
+            let _ = writer.write_str("\r\n");
+        }
+        written
+    }
+}
+
+impl ConsoleCommand for ClockTreeCommand<'_> {
+    fn name(&self) -> &'static str {
+        "clocks"
+    }
+
+    fn execute(&self, _arguments: &str) {}
+
+    fn write_line(&self, index: usize, writer: &mut dyn fmt::Write) -> bool {
+        let num_clocks = self.num_clocks();
+        if index == 0 {
+            let _ = writer.write_str("Clocks:\r\n");
+            true
+        } else if index <= num_clocks {
+            self.write_clock(index - 1, writer);
+            true
+        } else if index == num_clocks + 1 {
+            let _ = writer.write_str("Clock gates (+ on, - off):\r\n");
+            true
+        } else {
+            self.write_gates(index - num_clocks - 2, writer)
+        }
+    }
+}
@@ -0,0 +1,805 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Encrypted storage of credentials in the key-value store.
+//!
+//! `CredentialStore` keeps small secrets, such as the SSID and password of
+//! the WiFi network a NINA-W102 module joins, in a `KVStore`. Each credential
+//! is encrypted and authenticated with AES-CCM under a key unique to the
+//! device, read from a `hil::device_key::DeviceKey` (OTP fuses or a PUF
+//! where the chip has one), so a dump of the flash does not reveal it, and a
+//! record which was modified or moved to another name is rejected. Clients
+//! see the plaintext only: `load()` decrypts the record transparently.
+//!
+//! ```text
+//!  +------------------------+
+//!  | WiFi driver, ...       |
+//!  +------------------------+
+//!   load(name) | store(name, credential)
+//!  +------------------------+      +--------------------+
+//!  | CredentialStore (this) |------| AES-CCM            |
+//!  +------------------------+      +--------------------+
+//!              |
+//!  +------------------------+      +--------------------+
+//!  | KVStore                |------| DeviceKey          |
+//!  +------------------------+      +--------------------+
+//! ```
+//!
+//! Each record holds a version, the length of the credential, the counter
+//! used as CCM nonce, the ciphertext and an 8-byte tag. The name of the
+//! credential is authenticated as additional data. Nonces must never repeat
+//! under a key, so the counter is persisted in the store: `start()` reserves
+//! a block of counter values by storing the end of the block, and a new block
+//! is reserved when it runs out. The end of the block alternates between the
+//! two records of `NONCE_KEYS`, and the new end is stored before the record
+//! holding the previous one is replaced, so a reset at any point leaves the
+//! end of the block in use in the store. `start()` counts from the highest
+//! end stored, and above the counters of the credentials listed in `names`.
+//! It fails if a record cannot be read for another reason than being
+//! missing, rather than risk reusing a nonce.
+//!
+//! `rotate_key()` re-encrypts the credentials listed in `names` under a new
+//! key. Until the rotation completes, records are decrypted with the new key
+//! or, failing that, with the previous key, and an interrupted rotation can
+//! be resumed by calling `rotate_key()` again with the same key. The board
+//! is responsible for the new key being returned by the `DeviceKey` on the
+//! next boot; `set_previous_key()` gives the old key back if a rotation was
+//! interrupted by a reset.
+//!
+//! Names are padded with zeros to `MAX_NAME_LEN` bytes to form the keys of
+//! the records in the store.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let credentials = components::credential_store::CredentialStoreComponent::new(
+//!     kv_store,
+//!     ccm_mux,
+//!     &peripherals.ocotp,
+//!     &[b"wifi-ssid", b"wifi-password"],
+//! )
+//! .finalize(components::credential_store_component_static!(
+//!     KVStoreType,
+//!     KeyType,
+//!     AesType,
+//! ));
+//! credentials.set_client(wifi);
+//! let _ = credentials.start();
+//! ```
+
+use core::cell::Cell;
+use core::cmp;
+
+use crate::kv_store::{self, KVStore};
+use kernel::hil::device_key::{DeviceKey, DEVICE_KEY_LEN};
+use kernel::hil::kv_system::{self, KVSystem};
+use kernel::hil::symmetric_encryption::{
+    CCMClient, AES128CCM, AES128_BLOCK_SIZE, AES128_KEY_SIZE, CCM_NONCE_LENGTH,
+};
+use kernel::storage_permissions::StoragePermissions;
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+/// Longest name of a credential.
+pub const MAX_NAME_LEN: usize = 30;
+/// Longest credential.
+pub const MAX_CREDENTIAL_LEN: usize = 64;
+/// Keys of the two records holding the end of the reserved nonces.
+pub const NONCE_KEYS: [&[u8]; 2] = [b"credential-nonce", b"credential-nonce-b"];
+
+/// Length of the authentication tag.
+const MIC_LEN: usize = 8;
+/// Number of nonces reserved at once.
+const NONCE_RESERVATION: u64 = 256;
+const RECORD_VERSION: u8 = 1;
+/// Version, credential length and nonce counter.
+const RECORD_HEADER_LEN: usize = 10;
+
+/// Length of the buffer holding the key of a record.
+pub const KEY_BUF_LEN: usize = MAX_NAME_LEN;
+/// Length of the buffer holding a record and the header the store prepends
+/// to it.
+pub const VALUE_BUF_LEN: usize =
+    kv_store::HEADER_LENGTH + RECORD_HEADER_LEN + MAX_CREDENTIAL_LEN + MIC_LEN;
+/// Length of the buffer holding the name, the credential and the tag given to
+/// the CCM engine.
+pub const CRYPT_BUF_LEN: usize = MAX_NAME_LEN + MAX_CREDENTIAL_LEN + MIC_LEN;
+/// Length of the buffer of the `VirtualAES128CCM` user, which also holds the
+/// CCM header blocks and the padding of the name and the credential.
+pub const CCM_BUF_LEN: usize = 3 * AES128_BLOCK_SIZE + CRYPT_BUF_LEN;
+
+/// Receives the results of the operations of a `CredentialStore`.
+pub trait CredentialClient {
+    /// The device key was read and nonces were reserved.
+    fn start_done(&self, result: Result<(), ErrorCode>);
+
+    /// `load()` completed. On success, `credential` holds the plaintext.
+    fn load_done(&self, result: Result<(), ErrorCode>, name: &[u8], credential: &[u8]);
+
+    /// `store()` completed.
+    fn store_done(&self, result: Result<(), ErrorCode>, name: &[u8]);
+
+    /// `delete()` completed.
+    fn delete_done(&self, result: Result<(), ErrorCode>, name: &[u8]);
+
+    /// `rotate_key()` completed. On failure, the rotation can be resumed by
+    /// calling `rotate_key()` again with the same key.
+    fn rotate_done(&self, result: Result<(), ErrorCode>);
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Operation {
+    Start,
+    Load,
+    Store,
+    Delete,
+    Rotate,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    /// Reading the end of the reserved nonces stored in one of the records
+    /// of `NONCE_KEYS`.
+    ReadNonce(usize),
+    /// Reading the credentials listed in `names` for the nonces they used.
+    ReadCounter,
+    /// Removing the record of `NONCE_KEYS` which is not in use before storing
+    /// the end of the new reservation in it.
+    DeleteNonce,
+    WriteNonce,
+    ReadRecord,
+    Decrypt,
+    Encrypt,
+    /// Removing the previous record before writing the new one, or for good
+    /// when deleting.
+    DeleteRecord,
+    WriteRecord,
+}
+
+fn nonce(counter: u64) -> [u8; CCM_NONCE_LENGTH] {
+    let mut nonce = [0; CCM_NONCE_LENGTH];
+    nonce[..8].copy_from_slice(&counter.to_le_bytes());
+    nonce
+}
+
+pub struct CredentialStore<
+    'a,
+    K: KVSystem<'a> + KVSystem<'a, K = T>,
+    T: 'static + kv_system::KeyType,
+    A: AES128CCM<'a>,
+> {
+    kv: &'a KVStore<'a, K, T>,
+    ccm: &'a A,
+    device_key: &'a dyn DeviceKey,
+    /// Credentials re-encrypted by `rotate_key()`, and whose nonces are not
+    /// reused after `start()`.
+    names: &'a [&'a [u8]],
+    permissions: StoragePermissions,
+    client: OptionalCell<&'a dyn CredentialClient>,
+    /// Key of the record being accessed.
+    key: TakeCell<'static, [u8]>,
+    /// Record being read or written.
+    value: TakeCell<'static, [u8]>,
+    /// Name of the credential, followed by the credential and its tag.
+    crypt: TakeCell<'static, [u8]>,
+    state: Cell<State>,
+    operation: Cell<Operation>,
+    name_len: Cell<usize>,
+    credential_len: Cell<usize>,
+    current_key: OptionalCell<[u8; AES128_KEY_SIZE]>,
+    previous_key: OptionalCell<[u8; AES128_KEY_SIZE]>,
+    /// Whether the record is being decrypted with the previous key.
+    using_previous: Cell<bool>,
+    /// Nonce counter of the record being accessed.
+    record_nonce: Cell<u64>,
+    next_nonce: Cell<u64>,
+    /// End of the reserved nonces, once stored.
+    nonce_limit: Cell<u64>,
+    /// Record of `NONCE_KEYS` holding `nonce_limit`.
+    nonce_slot: Cell<usize>,
+    /// Next credential of `names` to rotate, or to read at start.
+    rotate_index: Cell<usize>,
+}
+
+impl<
+        'a,
+        K: KVSystem<'a> + KVSystem<'a, K = T>,
+        T: 'static + kv_system::KeyType,
+        A: AES128CCM<'a>,
+    > CredentialStore<'a, K, T, A>
+{
+    pub fn new(
+        kv: &'a KVStore<'a, K, T>,
+        ccm: &'a A,
+        device_key: &'a dyn DeviceKey,
+        names: &'a [&'a [u8]],
+        permissions: StoragePermissions,
+        key: &'static mut [u8],
+        value: &'static mut [u8],
+        crypt: &'static mut [u8],
+    ) -> Self {
+        Self {
+            kv,
+            ccm,
+            device_key,
+            names,
+            permissions,
+            client: OptionalCell::empty(),
+            key: TakeCell::new(key),
+            value: TakeCell::new(value),
+            crypt: TakeCell::new(crypt),
+            state: Cell::new(State::Idle),
+            operation: Cell::new(Operation::Start),
+            name_len: Cell::new(0),
+            credential_len: Cell::new(0),
+            current_key: OptionalCell::empty(),
+            previous_key: OptionalCell::empty(),
+            using_previous: Cell::new(false),
+            record_nonce: Cell::new(0),
+            next_nonce: Cell::new(0),
+            nonce_limit: Cell::new(0),
+            nonce_slot: Cell::new(0),
+            rotate_index: Cell::new(0),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn CredentialClient) {
+        self.client.set(client);
+    }
+
+    /// Read the device key and reserve nonces. Must be called once at boot,
+    /// before any other operation: they fail with `RESERVE` until a start
+    /// succeeds.
+    pub fn start(&self) -> Result<(), ErrorCode> {
+        if self.state.get() != State::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        let mut key = [0; DEVICE_KEY_LEN];
+        self.device_key.read_key(&mut key)?;
+        self.current_key.set(key);
+        self.operation.set(Operation::Start);
+        self.name_len.set(0);
+        // The first reservation goes to the first record, unless one of them
+        // holds a reservation already.
+        self.nonce_slot.set(1);
+        self.load_key(Some(0));
+        self.kv_get(State::ReadNonce(0)).map_err(|e| {
+            self.current_key.clear();
+            e
+        })
+    }
+
+    /// Also try `key` for the records which cannot be decrypted with the
+    /// device key, for example after a reset interrupted a rotation.
+    pub fn set_previous_key(&self, key: &[u8]) -> Result<(), ErrorCode> {
+        self.previous_key
+            .set(key.try_into().or(Err(ErrorCode::INVAL))?);
+        Ok(())
+    }
+
+    /// Read and decrypt the credential `name`.
+    pub fn load(&self, name: &[u8]) -> Result<(), ErrorCode> {
+        self.begin(Operation::Load, name)?;
+        self.read_record().map_err(|e| {
+            self.state.set(State::Idle);
+            e
+        })
+    }
+
+    /// Encrypt `credential` and store it as `name`, replacing the previous
+    /// value.
+    pub fn store(&self, name: &[u8], credential: &[u8]) -> Result<(), ErrorCode> {
+        if credential.len() > MAX_CREDENTIAL_LEN {
+            return Err(ErrorCode::SIZE);
+        }
+        self.begin(Operation::Store, name)?;
+        self.crypt.map(|crypt| {
+            crypt[name.len()..name.len() + credential.len()].copy_from_slice(credential);
+        });
+        self.credential_len.set(credential.len());
+        self.encrypt().map_err(|e| {
+            self.state.set(State::Idle);
+            e
+        })
+    }
+
+    /// Remove the credential `name`.
+    pub fn delete(&self, name: &[u8]) -> Result<(), ErrorCode> {
+        self.begin(Operation::Delete, name)?;
+        self.load_key(None);
+        self.kv_delete(State::DeleteRecord).map_err(|e| {
+            self.state.set(State::Idle);
+            e
+        })
+    }
+
+    /// Re-encrypt the credentials listed in `names` under `new_key`, which
+    /// is used for all operations from now on.
+    pub fn rotate_key(&self, new_key: &[u8]) -> Result<(), ErrorCode> {
+        if self.state.get() != State::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        let new_key: [u8; AES128_KEY_SIZE] = new_key.try_into().or(Err(ErrorCode::INVAL))?;
+        let current_key = self.current_key.extract().ok_or(ErrorCode::RESERVE)?;
+        if current_key != new_key {
+            self.previous_key.set(current_key);
+            self.current_key.set(new_key);
+        }
+        self.operation.set(Operation::Rotate);
+        self.rotate_index.set(0);
+        self.rotate_next()
+    }
+
+    /// Check that an operation on `name` can start, and keep the name at the
+    /// start of the crypt buffer.
+    fn begin(&self, operation: Operation, name: &[u8]) -> Result<(), ErrorCode> {
+        if self.state.get() != State::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        if self.current_key.is_none() {
+            return Err(ErrorCode::RESERVE);
+        }
+        if name.is_empty() || name.len() > MAX_NAME_LEN || NONCE_KEYS.contains(&name) {
+            return Err(ErrorCode::INVAL);
+        }
+        self.crypt
+            .map(|crypt| crypt[..name.len()].copy_from_slice(name))
+            .ok_or(ErrorCode::BUSY)?;
+        self.operation.set(operation);
+        self.name_len.set(name.len());
+        self.using_previous.set(false);
+        Ok(())
+    }
+
+    /// Put the padded name of the record to access in the key buffer: record
+    /// `nonce_slot` of `NONCE_KEYS`, or the credential being accessed.
+    fn load_key(&self, nonce_slot: Option<usize>) {
+        self.key.map(|key| {
+            key.iter_mut().for_each(|b| *b = 0);
+            if let Some(slot) = nonce_slot {
+                key[..NONCE_KEYS[slot].len()].copy_from_slice(NONCE_KEYS[slot]);
+            } else {
+                let name_len = self.name_len.get();
+                self.crypt.map(|crypt| {
+                    key[..name_len].copy_from_slice(&crypt[..name_len]);
+                });
+            }
+        });
+    }
+
+    fn kv_get(&self, state: State) -> Result<(), ErrorCode> {
+        let key = self.key.take().ok_or(ErrorCode::BUSY)?;
+        let value = match self.value.take() {
+            Some(value) => value,
+            None => {
+                self.key.replace(key);
+                return Err(ErrorCode::BUSY);
+            }
+        };
+        self.kv
+            .get(key, value, self.permissions)
+            .map(|()| self.state.set(state))
+            .map_err(|(key, value, e)| {
+                self.key.replace(key);
+                self.value.replace(value);
+                e.err().unwrap_or(ErrorCode::FAIL)
+            })
+    }
+
+    fn kv_delete(&self, state: State) -> Result<(), ErrorCode> {
+        let key = self.key.take().ok_or(ErrorCode::BUSY)?;
+        self.kv
+            .delete(key, self.permissions)
+            .map(|()| self.state.set(state))
+            .map_err(|(key, e)| {
+                self.key.replace(key);
+                e.err().unwrap_or(ErrorCode::FAIL)
+            })
+    }
+
+    fn kv_set(&self, state: State, length: usize) -> Result<(), ErrorCode> {
+        let key = self.key.take().ok_or(ErrorCode::BUSY)?;
+        let value = match self.value.take() {
+            Some(value) => value,
+            None => {
+                self.key.replace(key);
+                return Err(ErrorCode::BUSY);
+            }
+        };
+        self.kv
+            .set(key, value, length, self.permissions)
+            .map(|()| self.state.set(state))
+            .map_err(|(key, value, e)| {
+                self.key.replace(key);
+                self.value.replace(value);
+                e.err().unwrap_or(ErrorCode::FAIL)
+            })
+    }
+
+    /// Store the end of a new reservation in the record of `NONCE_KEYS` which
+    /// does not hold the current one, removing it first.
+    fn reserve_nonces(&self) -> Result<(), ErrorCode> {
+        self.load_key(Some(1 - self.nonce_slot.get()));
+        self.kv_delete(State::DeleteNonce)
+    }
+
+    /// Read the next credential of `names` for its nonce at start, or reserve
+    /// nonces above the highest one seen.
+    fn read_counter_next(&self) -> Result<(), ErrorCode> {
+        let index = self.rotate_index.get();
+        let name = match self.names.get(index) {
+            Some(name) => name,
+            None => {
+                self.name_len.set(0);
+                return self.reserve_nonces();
+            }
+        };
+        self.rotate_index.set(index + 1);
+        if name.is_empty() || name.len() > MAX_NAME_LEN {
+            return Err(ErrorCode::INVAL);
+        }
+        self.crypt
+            .map(|crypt| crypt[..name.len()].copy_from_slice(name))
+            .ok_or(ErrorCode::BUSY)?;
+        self.name_len.set(name.len());
+        self.load_key(None);
+        self.kv_get(State::ReadCounter)
+    }
+
+    fn read_record(&self) -> Result<(), ErrorCode> {
+        self.load_key(None);
+        self.kv_get(State::ReadRecord)
+    }
+
+    /// Encrypt the credential in the crypt buffer under the current key,
+    /// reserving nonces first if they ran out.
+    fn encrypt(&self) -> Result<(), ErrorCode> {
+        let counter = self.next_nonce.get();
+        if counter >= self.nonce_limit.get() {
+            return self.reserve_nonces();
+        }
+        self.next_nonce.set(counter + 1);
+        self.record_nonce.set(counter);
+        self.crypt_with(self.current_key.extract(), true)
+    }
+
+    /// Decrypt the record with the current key, or the previous key if
+    /// `using_previous` is set.
+    fn decrypt(&self) -> Result<(), ErrorCode> {
+        let name_len = self.name_len.get();
+        let length = self.credential_len.get() + MIC_LEN;
+        self.value
+            .map(|value| {
+                self.crypt.map(|crypt| {
+                    crypt[name_len..name_len + length]
+                        .copy_from_slice(&value[RECORD_HEADER_LEN..RECORD_HEADER_LEN + length]);
+                })
+            })
+            .flatten()
+            .ok_or(ErrorCode::BUSY)?;
+        let key = if self.using_previous.get() {
+            self.previous_key.extract()
+        } else {
+            self.current_key.extract()
+        };
+        self.crypt_with(key, false)
+    }
+
+    fn crypt_with(
+        &self,
+        key: Option<[u8; AES128_KEY_SIZE]>,
+        encrypting: bool,
+    ) -> Result<(), ErrorCode> {
+        let key = key.ok_or(ErrorCode::RESERVE)?;
+        self.ccm.set_key(&key)?;
+        self.ccm.set_nonce(&nonce(self.record_nonce.get()))?;
+        let crypt = self.crypt.take().ok_or(ErrorCode::BUSY)?;
+        self.ccm
+            .crypt(
+                crypt,
+                0,
+                self.name_len.get(),
+                self.credential_len.get(),
+                MIC_LEN,
+                true,
+                encrypting,
+            )
+            .map(|()| {
+                self.state.set(if encrypting {
+                    State::Encrypt
+                } else {
+                    State::Decrypt
+                })
+            })
+            .map_err(|(e, crypt)| {
+                self.crypt.replace(crypt);
+                e
+            })
+    }
+
+    /// Read the next credential to rotate, or complete the rotation.
+    fn rotate_next(&self) -> Result<(), ErrorCode> {
+        let index = self.rotate_index.get();
+        let name = match self.names.get(index) {
+            Some(name) => name,
+            None => {
+                self.finish(Ok(()));
+                return Ok(());
+            }
+        };
+        self.rotate_index.set(index + 1);
+        if name.is_empty() || name.len() > MAX_NAME_LEN {
+            return Err(ErrorCode::INVAL);
+        }
+        self.crypt
+            .map(|crypt| crypt[..name.len()].copy_from_slice(name))
+            .ok_or(ErrorCode::BUSY)?;
+        self.name_len.set(name.len());
+        self.using_previous.set(false);
+        self.read_record()
+    }
+
+    /// Parse the header of the record read from the store.
+    fn parse_record(&self) -> Result<(), ErrorCode> {
+        self.value
+            .map(|value| {
+                if value[0] != RECORD_VERSION || value[1] as usize > MAX_CREDENTIAL_LEN {
+                    return Err(ErrorCode::FAIL);
+                }
+                let counter = u64::from_le_bytes(value[2..10].try_into().unwrap_or([0; 8]));
+                self.credential_len.set(value[1] as usize);
+                self.record_nonce.set(counter);
+
+                // Never reuse a nonce seen in a record, and reserve new nonces
+                // before the next write if it is past the reservation.
+                if counter >= self.nonce_limit.get() {
+                    self.next_nonce.set(counter + 1);
+                    self.nonce_limit.set(counter + 1);
+                }
+                Ok(())
+            })
+            .unwrap_or(Err(ErrorCode::BUSY))
+    }
+
+    /// Continue a rotation after a step failed, or complete the operation
+    /// with `result`.
+    fn step_done(&self, result: Result<(), ErrorCode>) {
+        let result = match result {
+            Ok(()) => return,
+            // A credential which was never stored has nothing to rotate.
+            Err(_)
+                if self.operation.get() == Operation::Rotate
+                    && self.state.get() == State::ReadRecord =>
+            {
+                self.rotate_next()
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            self.finish(Err(e));
+        }
+    }
+
+    fn finish(&self, result: Result<(), ErrorCode>) {
+        let operation = self.operation.get();
+        self.state.set(State::Idle);
+
+        let mut name = [0; MAX_NAME_LEN];
+        let mut credential = [0; MAX_CREDENTIAL_LEN];
+        let name_len = self.name_len.get();
+        let credential_len = match (operation, result) {
+            (Operation::Load, Ok(())) => self.credential_len.get(),
+            _ => 0,
+        };
+        self.crypt.map(|crypt| {
+            name[..name_len].copy_from_slice(&crypt[..name_len]);
+            credential[..credential_len]
+                .copy_from_slice(&crypt[name_len..name_len + credential_len]);
+            // Do not keep plaintext credentials around.
+            crypt.iter_mut().for_each(|b| *b = 0);
+        });
+        if operation == Operation::Rotate && result.is_ok() {
+            self.previous_key.clear();
+        }
+        // The nonces used are unknown until a start succeeds.
+        if operation == Operation::Start && result.is_err() {
+            self.current_key.clear();
+        }
+
+        self.client.map(|client| match operation {
+            Operation::Start => client.start_done(result),
+            Operation::Load => {
+                client.load_done(result, &name[..name_len], &credential[..credential_len])
+            }
+            Operation::Store => client.store_done(result, &name[..name_len]),
+            Operation::Delete => client.delete_done(result, &name[..name_len]),
+            Operation::Rotate => client.rotate_done(result),
+        });
+    }
+}
+
+impl<
+        'a,
+        K: KVSystem<'a> + KVSystem<'a, K = T>,
+        T: 'static + kv_system::KeyType,
+        A: AES128CCM<'a>,
+    > kv_system::StoreClient<T> for CredentialStore<'a, K, T, A>
+{
+    fn get_complete(
+        &self,
+        result: Result<(), ErrorCode>,
+        key: &'static mut [u8],
+        ret_buf: &'static mut [u8],
+    ) {
+        self.value.replace(ret_buf);
+
+        // The store reports some failures to delete through `get_complete`.
+        if matches!(self.state.get(), State::DeleteNonce | State::DeleteRecord) {
+            self.delete_complete(result, key);
+            return;
+        }
+        self.key.replace(key);
+
+        let result = match self.state.get() {
+            // A missing record is not an error: nothing was stored yet. Any
+            // other failure stops the start, as the nonces used are unknown.
+            State::ReadNonce(slot) => match result {
+                Ok(()) | Err(ErrorCode::NOSUPPORT) => {
+                    if result.is_ok() {
+                        let stored = self
+                            .value
+                            .map(|value| {
+                                u64::from_le_bytes(value[..8].try_into().unwrap_or([0; 8]))
+                            })
+                            .unwrap_or(0);
+                        if stored > self.next_nonce.get() {
+                            self.next_nonce.set(stored);
+                            self.nonce_slot.set(slot);
+                        }
+                    }
+                    if slot + 1 < NONCE_KEYS.len() {
+                        self.load_key(Some(slot + 1));
+                        self.kv_get(State::ReadNonce(slot + 1))
+                    } else {
+                        self.rotate_index.set(0);
+                        self.read_counter_next()
+                    }
+                }
+                Err(e) => Err(e),
+            },
+            State::ReadCounter => match result {
+                Ok(()) | Err(ErrorCode::NOSUPPORT) => {
+                    self.value.map(|value| {
+                        // Records of another version do not use these nonces.
+                        if result.is_ok() && value[0] == RECORD_VERSION {
+                            let counter =
+                                u64::from_le_bytes(value[2..10].try_into().unwrap_or([0; 8]));
+                            self.next_nonce
+                                .set(cmp::max(self.next_nonce.get(), counter + 1));
+                        }
+                    });
+                    self.read_counter_next()
+                }
+                Err(e) => Err(e),
+            },
+            // A missing record is reported like the others.
+            State::ReadRecord => result
+                .or(Err(ErrorCode::FAIL))
+                .and_then(|()| self.parse_record())
+                .and_then(|()| self.decrypt()),
+            _ => Err(ErrorCode::FAIL),
+        };
+        self.step_done(result);
+    }
+
+    fn set_complete(
+        &self,
+        result: Result<(), ErrorCode>,
+        key: &'static mut [u8],
+        value: &'static mut [u8],
+    ) {
+        self.key.replace(key);
+        self.value.replace(value);
+
+        // The new reservation is only used once it is stored.
+        if self.state.get() == State::WriteNonce && result.is_ok() {
+            self.nonce_slot.set(1 - self.nonce_slot.get());
+            self.nonce_limit
+                .set(self.next_nonce.get() + NONCE_RESERVATION);
+        }
+        let result = match (self.state.get(), self.operation.get()) {
+            (State::WriteNonce, Operation::Start) => {
+                self.finish(result);
+                return;
+            }
+            (State::WriteNonce, _) => result.and_then(|()| self.encrypt()),
+            (State::WriteRecord, Operation::Rotate) => result.and_then(|()| self.rotate_next()),
+            (State::WriteRecord, _) => {
+                self.finish(result);
+                return;
+            }
+            _ => Err(ErrorCode::FAIL),
+        };
+        self.step_done(result);
+    }
+
+    fn delete_complete(&self, result: Result<(), ErrorCode>, key: &'static mut [u8]) {
+        self.key.replace(key);
+
+        // A missing record is not an error before a write: there was no
+        // previous value.
+        let result = match (self.state.get(), self.operation.get()) {
+            (State::DeleteNonce, _) => match result {
+                Ok(()) | Err(ErrorCode::NOSUPPORT) => {
+                    let limit = self.next_nonce.get() + NONCE_RESERVATION;
+                    self.value
+                        .map(|value| value[..8].copy_from_slice(&limit.to_le_bytes()));
+                    self.load_key(Some(1 - self.nonce_slot.get()));
+                    self.kv_set(State::WriteNonce, 8)
+                }
+                Err(e) => Err(e),
+            },
+            (State::DeleteRecord, Operation::Delete) => {
+                self.finish(result);
+                return;
+            }
+            (State::DeleteRecord, _) => {
+                let length = RECORD_HEADER_LEN + self.credential_len.get() + MIC_LEN;
+                self.load_key(None);
+                self.kv_set(State::WriteRecord, length)
+            }
+            _ => Err(ErrorCode::FAIL),
+        };
+        self.step_done(result);
+    }
+}
+
+impl<
+        'a,
+        K: KVSystem<'a> + KVSystem<'a, K = T>,
+        T: 'static + kv_system::KeyType,
+        A: AES128CCM<'a>,
+    > CCMClient for CredentialStore<'a, K, T, A>
+{
+    fn crypt_done(&self, buf: &'static mut [u8], res: Result<(), ErrorCode>, tag_is_valid: bool) {
+        self.crypt.replace(buf);
+
+        let result = match self.state.get() {
+            State::Encrypt => res.and_then(|()| {
+                let name_len = self.name_len.get();
+                let credential_len = self.credential_len.get();
+                let length = credential_len + MIC_LEN;
+                self.value.map(|value| {
+                    value[0] = RECORD_VERSION;
+                    value[1] = credential_len as u8;
+                    value[2..10].copy_from_slice(&self.record_nonce.get().to_le_bytes());
+                    self.crypt.map(|crypt| {
+                        value[RECORD_HEADER_LEN..RECORD_HEADER_LEN + length]
+                            .copy_from_slice(&crypt[name_len..name_len + length]);
+                    });
+                });
+                self.load_key(None);
+                self.kv_delete(State::DeleteRecord)
+            }),
+            State::Decrypt if res.is_ok() && tag_is_valid => match self.operation.get() {
+                Operation::Rotate if self.using_previous.get() => self.encrypt(),
+                // Already encrypted under the new key.
+                Operation::Rotate => self.rotate_next(),
+                _ => {
+                    self.finish(Ok(()));
+                    return;
+                }
+            },
+            State::Decrypt if !self.using_previous.get() && self.previous_key.is_some() => {
+                self.using_previous.set(true);
+                self.decrypt()
+            }
+            // The record was modified, or encrypted under another key.
+            _ => Err(ErrorCode::FAIL),
+        };
+        self.step_done(result);
+    }
+}
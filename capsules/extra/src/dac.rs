@@ -4,36 +4,191 @@
 
 //! Provides a DAC interface for userspace.
 //!
+//! Processes can set the output directly, as a raw value or as a voltage, or
+//! play a waveform: the samples of a read-only allowed buffer are output one
+//! after the other, paced by an alarm. One process at a time can play a
+//! waveform, and the output can not be set by others while it plays.
+//!
 //! Usage
 //! -----
 //!
 //! ```rust
-//! # use kernel::static_init;
-//!
-//! let dac = static_init!(
-//!     capsules::dac::Dac<'static>,
-//!     capsules::dac::Dac::new(&mut sam4l::dac::DAC));
+//! let dac = components::dac::DacComponent::new(
+//!     board_kernel,
+//!     capsules_extra::dac::DRIVER_NUM,
+//!     &peripherals.dac,
+//!     mux_alarm,
+//! )
+//! .finalize(components::dac_component_static!(sam4l::ast::Ast));
 //! ```
 
-/// Syscall driver number.
-use capsules_core::driver;
-pub const DRIVER_NUM: usize = driver::NUM::Dac as usize;
+use core::cell::Cell;
 
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
 use kernel::hil;
+use kernel::hil::time::{self, ConvertTicks};
+use kernel::processbuffer::ReadableProcessBuffer;
 use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::OptionalCell;
 use kernel::{ErrorCode, ProcessId};
 
-pub struct Dac<'a> {
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::Dac as usize;
+
+/// Ids for read-only allow buffers
+mod ro_allow {
+    /// Waveform samples, as little-endian `u16` output values.
+    pub const SAMPLES: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+pub struct Dac<'a, A: time::Alarm<'a>> {
     dac: &'a dyn hil::dac::DacChannel,
+    alarm: &'a A,
+    apps: Grant<(), UpcallCount<1>, AllowRoCount<{ ro_allow::COUNT }>, AllowRwCount<0>>,
+    /// The process playing a waveform.
+    playing: OptionalCell<ProcessId>,
+    /// Index of the sample being output.
+    index: Cell<usize>,
+    period: Cell<A::Ticks>,
+    repeat: Cell<bool>,
 }
 
-impl<'a> Dac<'a> {
-    pub fn new(dac: &'a dyn hil::dac::DacChannel) -> Dac<'a> {
-        Dac { dac: dac }
+impl<'a, A: time::Alarm<'a>> Dac<'a, A> {
+    pub fn new(
+        dac: &'a dyn hil::dac::DacChannel,
+        alarm: &'a A,
+        grant: Grant<(), UpcallCount<1>, AllowRoCount<{ ro_allow::COUNT }>, AllowRwCount<0>>,
+    ) -> Dac<'a, A> {
+        Dac {
+            dac,
+            alarm,
+            apps: grant,
+            playing: OptionalCell::empty(),
+            index: Cell::new(0),
+            period: Cell::new(A::Ticks::from(0)),
+            repeat: Cell::new(false),
+        }
+    }
+
+    /// Returns whether a waveform is playing. Playback by a process which
+    /// has exited is stopped.
+    fn is_playing(&self) -> bool {
+        match self.playing.extract() {
+            Some(owner) if self.apps.enter(owner, |_, _| {}).is_ok() => true,
+            Some(_) => {
+                self.stop();
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Convert `voltage_mv` to an output value, rounded to the nearest one.
+    fn voltage_to_value(&self, voltage_mv: usize) -> Result<usize, ErrorCode> {
+        let reference_mv = self
+            .dac
+            .get_voltage_reference_mv()
+            .ok_or(ErrorCode::NOSUPPORT)?;
+        if voltage_mv > reference_mv {
+            return Err(ErrorCode::INVAL);
+        }
+        let max_value = (1 << self.dac.get_resolution_bits()) - 1;
+        Ok((voltage_mv * max_value + reference_mv / 2) / reference_mv)
+    }
+
+    /// Returns the sample at `index` in the buffer allowed by `processid`,
+    /// or `None` if the buffer is shorter.
+    fn sample(&self, processid: ProcessId, index: usize) -> Option<u16> {
+        self.apps
+            .enter(processid, |_, kernel_data| {
+                kernel_data
+                    .get_readonly_processbuffer(ro_allow::SAMPLES)
+                    .and_then(|samples| {
+                        samples.enter(|samples| {
+                            samples.get(2 * index..2 * index + 2).map(|sample| {
+                                u16::from_le_bytes([sample[0].get(), sample[1].get()])
+                            })
+                        })
+                    })
+                    .unwrap_or(None)
+            })
+            .unwrap_or(None)
+    }
+
+    /// Output the first sample of the buffer of `processid`, and schedule
+    /// the next one every `period_us` microseconds.
+    fn play(&self, processid: ProcessId, period_us: usize, repeat: bool) -> Result<(), ErrorCode> {
+        if period_us == 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        let sample = self.sample(processid, 0).ok_or(ErrorCode::INVAL)?;
+        self.dac.set_value(sample as usize)?;
+
+        let period = self.alarm.ticks_from_us(period_us as u32);
+        self.playing.set(processid);
+        self.index.set(0);
+        self.period.set(period);
+        self.repeat.set(repeat);
+        self.alarm.set_alarm(self.alarm.now(), period);
+        Ok(())
+    }
+
+    fn stop(&self) {
+        self.playing.clear();
+        let _ = self.alarm.disarm();
+    }
+
+    /// End the playback of `processid` and notify it.
+    fn finish(&self, processid: ProcessId, result: Result<(), ErrorCode>) {
+        self.stop();
+        let _ = self.apps.enter(processid, |_, kernel_data| {
+            kernel_data
+                .schedule_upcall(
+                    0,
+                    (
+                        kernel::errorcode::into_statuscode(result),
+                        self.index.get() + 1,
+                        0,
+                    ),
+                )
+                .ok();
+        });
+    }
+}
+
+impl<'a, A: time::Alarm<'a>> time::AlarmClient for Dac<'a, A> {
+    fn alarm(&self) {
+        let processid = match self.playing.extract() {
+            Some(processid) => processid,
+            None => return,
+        };
+
+        let mut index = self.index.get() + 1;
+        let mut sample = self.sample(processid, index);
+        if sample.is_none() && self.repeat.get() {
+            index = 0;
+            sample = self.sample(processid, index);
+        }
+        match sample {
+            Some(sample) => match self.dac.set_value(sample as usize) {
+                Ok(()) => {
+                    self.index.set(index);
+                    // Pace samples from the previous alarm, so that the
+                    // period does not drift.
+                    self.alarm
+                        .set_alarm(self.alarm.get_alarm(), self.period.get());
+                }
+                Err(e) => self.finish(processid, Err(e)),
+            },
+            None => self.finish(processid, Ok(())),
+        }
     }
 }
 
-impl SyscallDriver for Dac<'_> {
+impl<'a, A: time::Alarm<'a>> SyscallDriver for Dac<'a, A> {
     /// Control the DAC.
     ///
     /// ### `command_num`
@@ -41,7 +196,22 @@ impl SyscallDriver for Dac<'_> {
     /// - `0`: Driver check.
     /// - `1`: Initialize and enable the DAC.
     /// - `2`: Set the output to `data1`, a scaled output value.
-    fn command(&self, command_num: usize, data: usize, _: usize, _: ProcessId) -> CommandReturn {
+    /// - `3`: Set the output to `data1` millivolts. Returns `NOSUPPORT` if
+    ///   the reference voltage of the DAC is unknown.
+    /// - `4`: Play the waveform of the read-only allowed buffer, outputting
+    ///   one sample every `data1` microseconds. The samples are repeated
+    ///   until stopped if `data2` is not 0. Upcall 0 is scheduled when the
+    ///   playback ends, with the status and the number of samples output.
+    /// - `5`: Stop playing the waveform. No upcall is scheduled.
+    /// - `6`: Get the resolution of the output values, in bits.
+    /// - `7`: Get the reference voltage, in millivolts.
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
         match command_num {
             0 /* check if present */ => CommandReturn::success(),
 
@@ -49,13 +219,59 @@ impl SyscallDriver for Dac<'_> {
             1 => CommandReturn::success(),
 
             // set the dac output
-            2 => CommandReturn::from(self.dac.set_value(data)),
+            2 => {
+                if self.is_playing() {
+                    CommandReturn::failure(ErrorCode::BUSY)
+                } else {
+                    CommandReturn::from(self.dac.set_value(data1))
+                }
+            }
+
+            // set the dac output voltage
+            3 => {
+                if self.is_playing() {
+                    CommandReturn::failure(ErrorCode::BUSY)
+                } else {
+                    CommandReturn::from(
+                        self.voltage_to_value(data1)
+                            .and_then(|value| self.dac.set_value(value)),
+                    )
+                }
+            }
+
+            // play a waveform
+            4 => {
+                if self.is_playing() {
+                    CommandReturn::failure(ErrorCode::BUSY)
+                } else {
+                    CommandReturn::from(self.play(processid, data1, data2 != 0))
+                }
+            }
+
+            // stop the waveform
+            5 => {
+                if !self.is_playing() {
+                    CommandReturn::failure(ErrorCode::ALREADY)
+                } else if self.playing.contains(&processid) {
+                    self.stop();
+                    CommandReturn::success()
+                } else {
+                    CommandReturn::failure(ErrorCode::BUSY)
+                }
+            }
+
+            6 => CommandReturn::success_u32(self.dac.get_resolution_bits() as u32),
+
+            7 => match self.dac.get_voltage_reference_mv() {
+                Some(reference_mv) => CommandReturn::success_u32(reference_mv as u32),
+                None => CommandReturn::failure(ErrorCode::NOSUPPORT),
+            },
 
             _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
         }
     }
 
-    fn allocate_grant(&self, _processid: ProcessId) -> Result<(), kernel::process::Error> {
-        Ok(())
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
     }
 }
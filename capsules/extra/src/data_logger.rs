@@ -0,0 +1,433 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Persistent log of the records of the sensor aggregation driver.
+//!
+//! The logger is the kernel client of a `SensorAggregator`. It gathers the
+//! records in entries of up to `RECORDS_PER_ENTRY` records, and appends the
+//! entries to a log, such as the flash `Log` capsule, so a device records
+//! data while it is offline. An application retrieves the records later,
+//! for example to upload them once connected, and erases the log.
+//!
+//! Entries are framed with the number of records, in one byte, and end
+//! with the CRC-32 of the number of records and the records, in little
+//! endian, so that corrupted entries are detected when read.
+//!
+//! Records are gathered in one buffer while the other is appended. Records
+//! arriving while both are full are dropped. The log is not synced after
+//! every entry: sync it to make the entries persistent.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let data_logger = components::data_logger::DataLoggerComponent::new(
+//!     board_kernel,
+//!     capsules_extra::data_logger::DRIVER_NUM,
+//!     log,
+//!     sensor_aggregator,
+//! )
+//! .finalize(components::data_logger_component_static!(
+//!     capsules_extra::log::Log<'static, nrf52840::nvmc::Nvmc>,
+//!     capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<
+//!         'static,
+//!         nrf52840::rtc::Rtc<'static>,
+//!     >,
+//! ));
+//! let _ = data_logger.start(60_000, 0b1);
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! - Read-write allow 0: the buffer receiving the records of an entry.
+//! - Command 0: the driver exists.
+//! - Command 1: start logging every period, in milliseconds, given as the
+//!   first argument, the sensors selected by the bit mask given as the
+//!   second argument, as with the sensor aggregation driver.
+//! - Command 2: stop logging.
+//! - Command 3: read the next entry. Upcall 0 reports the result and the
+//!   number of records copied to the buffer. Entries whose CRC does not
+//!   match report `FAIL` and are skipped.
+//! - Command 4: read from the oldest entry again.
+//! - Command 5: append the records gathered and sync the log.
+//! - Command 6: erase the log, and the records gathered.
+//! - Command 7: the number of records dropped since the last time.
+//!
+//! Upcall 1 reports the result of commands 4, 5 and 6, with the command
+//! number as its second argument. Only one of commands 3 to 6 may be in
+//! progress at a time.
+
+use core::cell::Cell;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::log::{LogRead, LogReadClient, LogWrite, LogWriteClient};
+use kernel::hil::time;
+use kernel::processbuffer::WriteableProcessBuffer;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::{ErrorCode, ProcessId};
+
+use crate::sensor_aggregator::{SensorAggregator, SensorAggregatorClient, RECORD_LEN};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::DataLogger as usize;
+
+/// The most records in an entry of the log.
+pub const RECORDS_PER_ENTRY: usize = 8;
+
+/// Length of the buffers holding an entry.
+pub const ENTRY_LEN: usize = 1 + RECORDS_PER_ENTRY * RECORD_LEN + CRC_LEN;
+
+const CRC_LEN: usize = 4;
+
+/// Ids of the read-write allow buffers.
+mod rw_allow {
+    pub const RECORDS: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant.
+    pub const COUNT: u8 = 1;
+}
+
+mod upcall {
+    pub const READ_DONE: usize = 0;
+    pub const COMMAND_DONE: usize = 1;
+    /// The number of upcalls the kernel stores for this grant.
+    pub const COUNT: u8 = 2;
+}
+
+/// CRC-32 (IEEE 802.3) of `data`.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Operation {
+    Read,
+    Rewind,
+    Sync,
+    Erase,
+}
+
+impl Operation {
+    fn command_num(self) -> usize {
+        match self {
+            Operation::Read => 3,
+            Operation::Rewind => 4,
+            Operation::Sync => 5,
+            Operation::Erase => 6,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct App;
+
+pub struct DataLogger<'a, L: LogRead<'a> + LogWrite<'a>, A: time::Alarm<'a>> {
+    log: &'a L,
+    aggregator: &'a SensorAggregator<'a, A>,
+    /// The buffer gathering records, and the number of records in it.
+    entry: TakeCell<'static, [u8]>,
+    entry_records: Cell<usize>,
+    /// The other buffer, while no entry is being appended.
+    spare: TakeCell<'static, [u8]>,
+    read_buffer: TakeCell<'static, [u8]>,
+    dropped: Cell<u32>,
+    sync_after_append: Cell<bool>,
+    /// The operation of an application in progress.
+    operation: OptionalCell<(Operation, ProcessId)>,
+    apps: Grant<
+        App,
+        UpcallCount<{ upcall::COUNT }>,
+        AllowRoCount<0>,
+        AllowRwCount<{ rw_allow::COUNT }>,
+    >,
+}
+
+impl<'a, L: LogRead<'a> + LogWrite<'a>, A: time::Alarm<'a>> DataLogger<'a, L, A> {
+    pub fn new(
+        log: &'a L,
+        aggregator: &'a SensorAggregator<'a, A>,
+        entry: &'static mut [u8; ENTRY_LEN],
+        spare: &'static mut [u8; ENTRY_LEN],
+        read_buffer: &'static mut [u8; ENTRY_LEN],
+        grant: Grant<
+            App,
+            UpcallCount<{ upcall::COUNT }>,
+            AllowRoCount<0>,
+            AllowRwCount<{ rw_allow::COUNT }>,
+        >,
+    ) -> DataLogger<'a, L, A> {
+        DataLogger {
+            log,
+            aggregator,
+            entry: TakeCell::new(entry),
+            entry_records: Cell::new(0),
+            spare: TakeCell::new(spare),
+            read_buffer: TakeCell::new(read_buffer),
+            dropped: Cell::new(0),
+            sync_after_append: Cell::new(false),
+            operation: OptionalCell::empty(),
+            apps: grant,
+        }
+    }
+
+    /// Log the sensors selected by the bit mask `sensors` every `period_ms`.
+    pub fn start(&self, period_ms: u32, sensors: u8) -> Result<(), ErrorCode> {
+        self.aggregator.start_sampling(period_ms, sensors)
+    }
+
+    pub fn stop(&self) {
+        self.aggregator.stop_sampling();
+    }
+
+    /// Append the records gathered as an entry, if the log is not appending
+    /// another entry.
+    fn append_entry(&self) -> Result<(), ErrorCode> {
+        let records = self.entry_records.get();
+        if records == 0 {
+            return Ok(());
+        }
+        let spare = self.spare.take().ok_or(ErrorCode::BUSY)?;
+        let entry = match self.entry.take() {
+            Some(entry) => entry,
+            None => {
+                self.spare.replace(spare);
+                return Err(ErrorCode::RESERVE);
+            }
+        };
+
+        entry[0] = records as u8;
+        let length = 1 + records * RECORD_LEN;
+        let crc = crc32(&entry[..length]);
+        entry[length..length + CRC_LEN].copy_from_slice(&crc.to_le_bytes());
+
+        match self.log.append(entry, length + CRC_LEN) {
+            Ok(()) => {
+                self.entry.replace(spare);
+                self.entry_records.set(0);
+                Ok(())
+            }
+            Err((err, entry)) => {
+                // Keep the records to append them later.
+                self.entry.replace(entry);
+                self.spare.replace(spare);
+                Err(err)
+            }
+        }
+    }
+
+    /// Append the records gathered if the buffer is full, now that the log
+    /// may be idle.
+    fn retry_append(&self) {
+        if self.entry_records.get() == RECORDS_PER_ENTRY {
+            let _ = self.append_entry();
+        }
+    }
+
+    fn start_operation(&self, operation: Operation, processid: ProcessId) -> Result<(), ErrorCode> {
+        if self.operation.is_some() {
+            return Err(ErrorCode::BUSY);
+        }
+        match operation {
+            Operation::Read => self
+                .read_buffer
+                .take()
+                .map_or(Err(ErrorCode::BUSY), |buffer| {
+                    self.log.read(buffer, ENTRY_LEN).map_err(|(err, buffer)| {
+                        self.read_buffer.replace(buffer);
+                        err
+                    })
+                }),
+            Operation::Rewind => self.log.seek(self.log.log_start()),
+            Operation::Sync => {
+                if self.entry_records.get() > 0 {
+                    // Sync once the records gathered are appended.
+                    self.append_entry()?;
+                    self.sync_after_append.set(true);
+                    Ok(())
+                } else {
+                    self.log.sync()
+                }
+            }
+            Operation::Erase => self.log.erase(),
+        }?;
+        self.operation.set((operation, processid));
+        Ok(())
+    }
+
+    /// Report the end of the operation in progress to its application.
+    fn operation_done(&self, result: Result<(), ErrorCode>, records: usize) {
+        if let Some((operation, processid)) = self.operation.take() {
+            let _ = self.apps.enter(processid, |_, kernel_data| {
+                let status = kernel::errorcode::into_statuscode(result);
+                let _ = match operation {
+                    Operation::Read => {
+                        kernel_data.schedule_upcall(upcall::READ_DONE, (status, records, 0))
+                    }
+                    _ => kernel_data.schedule_upcall(
+                        upcall::COMMAND_DONE,
+                        (status, operation.command_num(), 0),
+                    ),
+                };
+            });
+        }
+    }
+}
+
+impl<'a, L: LogRead<'a> + LogWrite<'a>, A: time::Alarm<'a>> SensorAggregatorClient
+    for DataLogger<'a, L, A>
+{
+    fn record(&self, record: &[u8; RECORD_LEN]) {
+        let records = self.entry_records.get();
+        if records == RECORDS_PER_ENTRY {
+            self.dropped.set(self.dropped.get().saturating_add(1));
+            return;
+        }
+        self.entry.map(|entry| {
+            let offset = 1 + records * RECORD_LEN;
+            entry[offset..offset + RECORD_LEN].copy_from_slice(record);
+            self.entry_records.set(records + 1);
+        });
+        self.retry_append();
+    }
+}
+
+impl<'a, L: LogRead<'a> + LogWrite<'a>, A: time::Alarm<'a>> LogReadClient for DataLogger<'a, L, A> {
+    fn read_done(&self, buffer: &'static mut [u8], length: usize, error: Result<(), ErrorCode>) {
+        let mut copied = 0;
+        let result = error.and_then(|()| {
+            let records = buffer[0] as usize;
+            let data_len = 1 + records * RECORD_LEN;
+            if records > RECORDS_PER_ENTRY || length != data_len + CRC_LEN {
+                return Err(ErrorCode::FAIL);
+            }
+            let crc = u32::from_le_bytes([
+                buffer[data_len],
+                buffer[data_len + 1],
+                buffer[data_len + 2],
+                buffer[data_len + 3],
+            ]);
+            if crc != crc32(&buffer[..data_len]) {
+                return Err(ErrorCode::FAIL);
+            }
+
+            self.operation
+                .map_or(Err(ErrorCode::FAIL), |(_, processid)| {
+                    self.apps
+                        .enter(*processid, |_, kernel_data| {
+                            kernel_data
+                                .get_readwrite_processbuffer(rw_allow::RECORDS)
+                                .and_then(|records_buffer| {
+                                    records_buffer.mut_enter(|records_buffer| {
+                                        copied = core::cmp::min(
+                                            records,
+                                            records_buffer.len() / RECORD_LEN,
+                                        );
+                                        records_buffer[..copied * RECORD_LEN]
+                                            .copy_from_slice(&buffer[1..1 + copied * RECORD_LEN]);
+                                    })
+                                })
+                                .map_err(ErrorCode::from)
+                        })
+                        .map_err(ErrorCode::from)
+                        .and_then(|result| result)
+                })
+        });
+        self.read_buffer.replace(buffer);
+        self.operation_done(result, copied);
+        self.retry_append();
+    }
+
+    fn seek_done(&self, error: Result<(), ErrorCode>) {
+        self.operation_done(error, 0);
+        self.retry_append();
+    }
+}
+
+impl<'a, L: LogRead<'a> + LogWrite<'a>, A: time::Alarm<'a>> LogWriteClient
+    for DataLogger<'a, L, A>
+{
+    fn append_done(
+        &self,
+        buffer: &'static mut [u8],
+        _length: usize,
+        _records_lost: bool,
+        error: Result<(), ErrorCode>,
+    ) {
+        self.spare.replace(buffer);
+        if self.sync_after_append.replace(false) {
+            if let Err(err) = error.and_then(|()| self.log.sync()) {
+                self.operation_done(Err(err), 0);
+            }
+            return;
+        }
+        self.retry_append();
+    }
+
+    fn sync_done(&self, error: Result<(), ErrorCode>) {
+        self.operation_done(error, 0);
+        self.retry_append();
+    }
+
+    fn erase_done(&self, error: Result<(), ErrorCode>) {
+        if error.is_ok() {
+            self.entry_records.set(0);
+        }
+        self.operation_done(error, 0);
+    }
+}
+
+impl<'a, L: LogRead<'a> + LogWrite<'a>, A: time::Alarm<'a>> SyscallDriver for DataLogger<'a, L, A> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            1 => {
+                if data1 > u32::MAX as usize || data2 > u8::MAX as usize {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                self.start(data1 as u32, data2 as u8).into()
+            }
+
+            2 => {
+                self.stop();
+                CommandReturn::success()
+            }
+
+            3 => self.start_operation(Operation::Read, processid).into(),
+
+            4 => self.start_operation(Operation::Rewind, processid).into(),
+
+            5 => self.start_operation(Operation::Sync, processid).into(),
+
+            6 => self.start_operation(Operation::Erase, processid).into(),
+
+            7 => CommandReturn::success_u32(self.dropped.replace(0)),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
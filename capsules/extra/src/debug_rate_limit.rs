@@ -0,0 +1,93 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Limit the rate of kernel debug output, and report the output dropped.
+//!
+//! Heavy logging, for example while bringing up a driver, can write to the
+//! debug buffer much faster than a 115200 baud UART empties it. The buffer
+//! then stays full, and most messages are truncated. With this capsule, the
+//! debug writer accepts at most `bytes_per_second` bytes per second, in bursts
+//! of up to one second of output, and drops the rest. Every
+//! `report_interval_ms` milliseconds, the number of bytes dropped since the
+//! last report is printed, if any.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! components::debug_rate_limit::DebugRateLimitComponent::new(mux_alarm, 8000, 5000)
+//!     .finalize(components::debug_rate_limit_component_static!(
+//!         rp2040::timer::RPTimer
+//!     ));
+//! ```
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::debug;
+use kernel::hil::time::{self, ConvertTicks};
+
+/// Period at which the budget of the debug writer is refilled.
+const REFILL_INTERVAL_MS: u32 = 100;
+
+pub struct DebugRateLimit<'a, A: time::Alarm<'a>> {
+    alarm: &'a A,
+    bytes_per_second: usize,
+    report_interval_ms: u32,
+    /// Time since the last report.
+    since_report_ms: Cell<u32>,
+    /// Bytes dropped since the last report.
+    dropped: Cell<usize>,
+}
+
+impl<'a, A: time::Alarm<'a>> DebugRateLimit<'a, A> {
+    pub fn new(alarm: &'a A, bytes_per_second: usize, report_interval_ms: u32) -> Self {
+        Self {
+            alarm,
+            bytes_per_second,
+            report_interval_ms,
+            since_report_ms: Cell::new(0),
+            dropped: Cell::new(0),
+        }
+    }
+
+    /// Start limiting the debug output.
+    pub fn start(&self) {
+        debug::debug_set_rate_limit(Some(self.bytes_per_second));
+        self.alarm.set_alarm(
+            self.alarm.now(),
+            self.alarm.ticks_from_ms(REFILL_INTERVAL_MS),
+        );
+    }
+}
+
+impl<'a, A: time::Alarm<'a>> time::AlarmClient for DebugRateLimit<'a, A> {
+    fn alarm(&self) {
+        self.alarm.set_alarm(
+            self.alarm.get_alarm(),
+            self.alarm.ticks_from_ms(REFILL_INTERVAL_MS),
+        );
+
+        self.dropped
+            .set(self.dropped.get() + debug::debug_take_dropped());
+        let refill = self.bytes_per_second * REFILL_INTERVAL_MS as usize / 1000;
+        let budget = debug::debug_rate_limit().unwrap_or(0);
+        let budget = cmp::min(budget + refill, self.bytes_per_second);
+
+        let since_report_ms = self.since_report_ms.get() + REFILL_INTERVAL_MS;
+        if since_report_ms >= self.report_interval_ms && self.dropped.get() > 0 {
+            self.since_report_ms.set(0);
+            // The report is not limited, so that it is not dropped itself.
+            debug::debug_set_rate_limit(None);
+            debug!(
+                "*** {} bytes of debug output dropped ***",
+                self.dropped.replace(0)
+            );
+        } else {
+            self.since_report_ms
+                .set(cmp::min(since_report_ms, self.report_interval_ms));
+        }
+        debug::debug_set_rate_limit(Some(budget));
+    }
+}
@@ -0,0 +1,156 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Provides userspace with the unique ID of the chip and the name and
+//! version of the board.
+//!
+//! This lets provisioning applications derive device identities, such as
+//! MQTT client IDs, without hardcoding them in each image. Boards whose chip
+//! has no unique ID can still expose the board name and version.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let device_id = components::device_id::DeviceIdComponent::new(
+//!     board_kernel,
+//!     capsules_extra::device_id::DRIVER_NUM,
+//!     Some(&base_peripherals.uid),
+//!     "stm32f412gdiscovery",
+//!     "MB1209",
+//! )
+//! .finalize(components::device_id_component_static!());
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! ### Allow ReadWrite
+//!
+//! - `0`: Buffer the identifier or string is copied into by commands 2, 3
+//!   and 4.
+//!
+//! ### Command
+//!
+//! - `0`: Driver existence check.
+//! - `1`: Return the length of the unique ID in bytes, 0 if there is none.
+//! - `2`: Copy the unique ID into the allowed buffer. Returns the number of
+//!   bytes copied, or `NOSUPPORT` if the chip has no unique ID.
+//! - `3`: Copy the board name, as UTF-8 without a terminator, into the
+//!   allowed buffer. Returns the number of bytes copied.
+//! - `4`: Copy the board version, as for the name.
+//!
+//! Commands 2 to 4 return `SIZE` if the allowed buffer is too short.
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::device_id::DeviceId;
+use kernel::processbuffer::WriteableProcessBuffer;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::DeviceId as usize;
+
+/// Ids for read-write allow buffers
+mod rw_allow {
+    pub const BUFFER: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+/// Longest chip identifier supported.
+const MAX_ID_LEN: usize = 16;
+
+#[derive(Default)]
+pub struct App;
+
+pub struct DeviceIdDriver<'a> {
+    id: Option<&'a dyn DeviceId>,
+    board_name: &'static str,
+    board_version: &'static str,
+    apps: Grant<App, UpcallCount<0>, AllowRoCount<0>, AllowRwCount<{ rw_allow::COUNT }>>,
+}
+
+impl<'a> DeviceIdDriver<'a> {
+    pub fn new(
+        id: Option<&'a dyn DeviceId>,
+        board_name: &'static str,
+        board_version: &'static str,
+        grant: Grant<App, UpcallCount<0>, AllowRoCount<0>, AllowRwCount<{ rw_allow::COUNT }>>,
+    ) -> DeviceIdDriver<'a> {
+        DeviceIdDriver {
+            id,
+            board_name,
+            board_version,
+            apps: grant,
+        }
+    }
+
+    /// Copy `data` into the buffer allowed by `processid`.
+    fn copy_to_app(&self, processid: ProcessId, data: &[u8]) -> CommandReturn {
+        let result = self
+            .apps
+            .enter(processid, |_, kernel_data| {
+                kernel_data
+                    .get_readwrite_processbuffer(rw_allow::BUFFER)
+                    .map_err(ErrorCode::from)
+                    .and_then(|buffer| {
+                        buffer
+                            .mut_enter(|dest| {
+                                if dest.len() < data.len() {
+                                    Err(ErrorCode::SIZE)
+                                } else {
+                                    dest[..data.len()].copy_from_slice(data);
+                                    Ok(data.len())
+                                }
+                            })
+                            .unwrap_or(Err(ErrorCode::SIZE))
+                    })
+            })
+            .unwrap_or_else(|err| Err(err.into()));
+
+        match result {
+            Ok(len) => CommandReturn::success_u32(len as u32),
+            Err(e) => CommandReturn::failure(e),
+        }
+    }
+}
+
+impl<'a> SyscallDriver for DeviceIdDriver<'a> {
+    fn command(
+        &self,
+        command_num: usize,
+        _data1: usize,
+        _data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            1 => CommandReturn::success_u32(self.id.map_or(0, |id| id.id_len()) as u32),
+
+            2 => match self.id {
+                Some(id) => {
+                    let mut buf = [0; MAX_ID_LEN];
+                    match id.read_id(&mut buf) {
+                        Ok(len) => self.copy_to_app(processid, &buf[..len]),
+                        Err(e) => CommandReturn::failure(e),
+                    }
+                }
+                None => CommandReturn::failure(ErrorCode::NOSUPPORT),
+            },
+
+            3 => self.copy_to_app(processid, self.board_name.as_bytes()),
+
+            4 => self.copy_to_app(processid, self.board_version.as_bytes()),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
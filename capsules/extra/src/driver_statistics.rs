@@ -0,0 +1,168 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Provides userspace with the operation counters of bus drivers.
+//!
+//! The board gives the capsule a list of named drivers implementing
+//! `kernel::hil::statistics::DriverStatistics`, such as its UARTs, SPI and
+//! I2C controllers. Applications read the operations each driver started,
+//! completed and failed, and the bytes it moved, to watch the health of the
+//! buses without a debugger.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let sources = static_init!(
+//!     [(&'static str, &'static dyn DriverStatistics); 2],
+//!     [("usart2", &base_peripherals.usart2), ("i2c1", &base_peripherals.i2c1)]
+//! );
+//! let driver_statistics = components::driver_statistics::DriverStatisticsComponent::new(
+//!     board_kernel,
+//!     capsules_extra::driver_statistics::DRIVER_NUM,
+//!     sources,
+//! )
+//! .finalize(components::driver_statistics_component_static!());
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! ### Allow ReadWrite
+//!
+//! - `0`: Buffer the name of a driver is copied into by command 5.
+//!
+//! ### Command
+//!
+//! Drivers are numbered from 0, in the order the board lists them.
+//!
+//! - `0`: Driver existence check.
+//! - `1`: Return the number of drivers.
+//! - `2`: Return the operations started, completed and failed by driver
+//!   `data1`.
+//! - `3`: Return the bytes moved by driver `data1`.
+//! - `4`: Set the counters of driver `data1` back to zero.
+//! - `5`: Copy the name of driver `data1`, as UTF-8 without a terminator,
+//!   into the allowed buffer. Returns the number of bytes copied, or `SIZE`
+//!   if the buffer is too short.
+//!
+//! Commands 2 to 5 return `INVAL` if there is no driver `data1`. Counters
+//! wrap around.
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::statistics::DriverStatistics;
+use kernel::processbuffer::WriteableProcessBuffer;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::DriverStatistics as usize;
+
+/// Ids for read-write allow buffers
+mod rw_allow {
+    pub const NAME: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+#[derive(Default)]
+pub struct App;
+
+pub struct DriverStatisticsDriver<'a> {
+    sources: &'a [(&'static str, &'a dyn DriverStatistics)],
+    apps: Grant<App, UpcallCount<0>, AllowRoCount<0>, AllowRwCount<{ rw_allow::COUNT }>>,
+}
+
+impl<'a> DriverStatisticsDriver<'a> {
+    pub fn new(
+        sources: &'a [(&'static str, &'a dyn DriverStatistics)],
+        grant: Grant<App, UpcallCount<0>, AllowRoCount<0>, AllowRwCount<{ rw_allow::COUNT }>>,
+    ) -> DriverStatisticsDriver<'a> {
+        DriverStatisticsDriver {
+            sources,
+            apps: grant,
+        }
+    }
+
+    /// Copy `name` into the buffer allowed by `processid`.
+    fn copy_name(&self, processid: ProcessId, name: &str) -> CommandReturn {
+        let name = name.as_bytes();
+        let result = self
+            .apps
+            .enter(processid, |_, kernel_data| {
+                kernel_data
+                    .get_readwrite_processbuffer(rw_allow::NAME)
+                    .map_err(ErrorCode::from)
+                    .and_then(|buffer| {
+                        buffer
+                            .mut_enter(|dest| {
+                                if dest.len() < name.len() {
+                                    Err(ErrorCode::SIZE)
+                                } else {
+                                    dest[..name.len()].copy_from_slice(name);
+                                    Ok(name.len())
+                                }
+                            })
+                            .unwrap_or(Err(ErrorCode::SIZE))
+                    })
+            })
+            .unwrap_or_else(|err| Err(err.into()));
+
+        match result {
+            Ok(len) => CommandReturn::success_u32(len as u32),
+            Err(e) => CommandReturn::failure(e),
+        }
+    }
+}
+
+impl<'a> SyscallDriver for DriverStatisticsDriver<'a> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        _data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        if command_num == 0 {
+            return CommandReturn::success();
+        }
+        if command_num == 1 {
+            return CommandReturn::success_u32(self.sources.len() as u32);
+        }
+        if command_num > 5 {
+            return CommandReturn::failure(ErrorCode::NOSUPPORT);
+        }
+
+        let (name, source) = match self.sources.get(data1) {
+            Some(&(name, source)) => (name, source),
+            None => return CommandReturn::failure(ErrorCode::INVAL),
+        };
+        match command_num {
+            2 => {
+                let statistics = source.statistics();
+                CommandReturn::success_u32_u32_u32(
+                    statistics.started,
+                    statistics.completed,
+                    statistics.errors,
+                )
+            }
+
+            3 => CommandReturn::success_u32(source.statistics().bytes),
+
+            4 => {
+                source.reset_statistics();
+                CommandReturn::success()
+            }
+
+            5 => self.copy_name(processid, name),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
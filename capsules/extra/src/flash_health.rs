@@ -0,0 +1,315 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Erase counting and bad page replacement for a region of flash.
+//!
+//! `FlashHealth` sits between a flash driver and a flash consumer, such as
+//! the KV store (`tickv`) or `nonvolatile_to_pages`, and implements
+//! `hil::flash::Flash` itself. It manages the `N` pages of flash starting at
+//! `first_page`, and passes the accesses to other pages through unchanged.
+//!
+//! For each managed page, it counts the erases and writes, so a board can
+//! see how a workload wears the flash. The last `spares` pages of the region
+//! are kept in reserve: when an erase or a write of a page fails, the page is
+//! marked as bad and replaced by a spare page, and the operation is retried
+//! on the spare. Later accesses to the page go to its replacement. The
+//! consumer must not use the spare pages, and accesses to them are refused.
+//!
+//! The statistics and the replacements are kept in RAM, and are lost on a
+//! reset. A bad page is then replaced again on its next failed erase or
+//! write, so consumers should rewrite a page before they trust its contents
+//! after a failure.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let flash_health = components::flash_health::FlashHealthComponent::new(
+//!     &base_peripherals.nvmc,
+//!     0x60,   // First page of the region
+//!     4,      // Spare pages
+//! )
+//! .finalize(components::flash_health_component_static!(nrf52840::nvmc::Nvmc, 32));
+//!
+//! // Then use `flash_health` as the flash of the KV store.
+//! ```
+
+use core::cell::Cell;
+
+use kernel::debug;
+use kernel::hil;
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Wear statistics of a managed page.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct PageStatistics {
+    /// Number of successful erases since boot.
+    pub erase_count: u32,
+    /// Number of successful writes since boot.
+    pub write_count: u32,
+    /// Whether an erase or a write of the page failed.
+    pub bad: bool,
+    /// Index in the region of the page which replaces this page.
+    pub replacement: Option<usize>,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Operation {
+    Idle,
+    Read,
+    Write,
+    Erase,
+}
+
+pub struct FlashHealth<'a, F: hil::flash::Flash + 'static, const N: usize> {
+    flash: &'a F,
+    client: OptionalCell<&'a dyn hil::flash::Client<FlashHealth<'a, F, N>>>,
+    first_page: usize,
+    /// Number of pages at the end of the region kept in reserve.
+    spares: usize,
+    pages: [Cell<PageStatistics>; N],
+    /// Next spare page to use for a replacement.
+    next_spare: Cell<usize>,
+    operation: Cell<Operation>,
+    /// Whether the page being accessed is in the region.
+    managed: Cell<bool>,
+    /// Index in the region of the page being accessed.
+    physical: Cell<usize>,
+}
+
+impl<'a, F: hil::flash::Flash, const N: usize> FlashHealth<'a, F, N> {
+    pub fn new(flash: &'a F, first_page: usize, spares: usize) -> FlashHealth<'a, F, N> {
+        let spares = core::cmp::min(spares, N);
+        FlashHealth {
+            flash,
+            client: OptionalCell::empty(),
+            first_page,
+            spares,
+            pages: core::array::from_fn(|_| Cell::new(PageStatistics::default())),
+            next_spare: Cell::new(N - spares),
+            operation: Cell::new(Operation::Idle),
+            managed: Cell::new(false),
+            physical: Cell::new(0),
+        }
+    }
+
+    /// Returns the statistics of page `page_number` of the flash, if it is
+    /// in the managed region.
+    pub fn page_statistics(&self, page_number: usize) -> Option<PageStatistics> {
+        page_number
+            .checked_sub(self.first_page)
+            .and_then(|index| self.pages.get(index))
+            .map(|page| page.get())
+    }
+
+    /// Returns the number of pages which are marked as bad.
+    pub fn bad_pages(&self) -> usize {
+        self.pages.iter().filter(|page| page.get().bad).count()
+    }
+
+    /// Returns the number of spare pages still available for replacements.
+    pub fn free_spares(&self) -> usize {
+        N - self.next_spare.get()
+    }
+
+    /// Print a summary of the wear of the region on the debug output.
+    pub fn debug_statistics(&self) {
+        let (min, max, total) = self.pages.iter().fold((u32::MAX, 0, 0), |acc, page| {
+            let erases = page.get().erase_count;
+            (
+                core::cmp::min(acc.0, erases),
+                core::cmp::max(acc.1, erases),
+                acc.2 + erases as usize,
+            )
+        });
+        debug!(
+            "Flash pages {}-{}: erases min {} max {} total {}, {} bad, {} spares free",
+            self.first_page,
+            self.first_page + N - 1,
+            if N == 0 { 0 } else { min },
+            max,
+            total,
+            self.bad_pages(),
+            self.free_spares()
+        );
+    }
+
+    /// Returns the index in the region of `page_number`, or `None` if the
+    /// page is outside of the region. Spare pages are refused.
+    fn index(&self, page_number: usize) -> Result<Option<usize>, ErrorCode> {
+        match page_number.checked_sub(self.first_page) {
+            Some(index) if index < N - self.spares => Ok(Some(index)),
+            Some(index) if index < N => Err(ErrorCode::INVAL),
+            _ => Ok(None),
+        }
+    }
+
+    /// Follow the replacements of the page at `index`.
+    fn resolve(&self, index: usize) -> usize {
+        let mut index = index;
+        while let Some(replacement) = self.pages[index].get().replacement {
+            index = replacement;
+        }
+        index
+    }
+
+    /// Start `operation` on `page_number`, and return the physical page to
+    /// access.
+    fn start(&self, operation: Operation, page_number: usize) -> Result<usize, ErrorCode> {
+        if self.operation.get() != Operation::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        let index = self.index(page_number)?;
+        self.managed.set(index.is_some());
+        let page = index.map_or(page_number, |index| {
+            let physical = self.resolve(index);
+            self.physical.set(physical);
+            self.first_page + physical
+        });
+        self.operation.set(operation);
+        Ok(page)
+    }
+
+    /// Mark the page being accessed as bad and replace it with a spare.
+    /// Returns the physical page to retry the operation on.
+    fn replace(&self) -> Option<usize> {
+        let physical = self.physical.get();
+        let spare = self.next_spare.get();
+        let replacement = if spare < N { Some(spare) } else { None };
+        self.pages[physical].set(PageStatistics {
+            bad: true,
+            replacement,
+            ..self.pages[physical].get()
+        });
+
+        let spare = replacement?;
+        self.next_spare.set(spare + 1);
+        self.physical.set(spare);
+        debug!(
+            "Flash page {} is bad, replaced by page {}",
+            self.first_page + physical,
+            self.first_page + spare
+        );
+        Some(self.first_page + spare)
+    }
+
+    fn count(&self, operation: Operation) {
+        let page = &self.pages[self.physical.get()];
+        let mut statistics = page.get();
+        match operation {
+            Operation::Erase => statistics.erase_count = statistics.erase_count.wrapping_add(1),
+            Operation::Write => statistics.write_count = statistics.write_count.wrapping_add(1),
+            _ => {}
+        }
+        page.set(statistics);
+    }
+}
+
+impl<F: hil::flash::Flash, const N: usize> hil::flash::Client<F> for FlashHealth<'_, F, N> {
+    fn read_complete(&self, pagebuffer: &'static mut F::Page, error: hil::flash::Error) {
+        self.operation.set(Operation::Idle);
+        self.client.map(move |client| {
+            client.read_complete(pagebuffer, error);
+        });
+    }
+
+    fn write_complete(&self, pagebuffer: &'static mut F::Page, error: hil::flash::Error) {
+        let mut error = error;
+        let mut pagebuffer = Some(pagebuffer);
+        if self.managed.get() {
+            match error {
+                hil::flash::Error::CommandComplete => self.count(Operation::Write),
+                hil::flash::Error::FlashError => {
+                    if let Some(page) = self.replace() {
+                        match self.flash.write_page(page, pagebuffer.take().unwrap()) {
+                            Ok(()) => return,
+                            Err((_, buffer)) => pagebuffer = Some(buffer),
+                        }
+                    }
+                    error = hil::flash::Error::FlashError;
+                }
+                hil::flash::Error::FlashMemoryProtectionError => {}
+            }
+        }
+
+        self.operation.set(Operation::Idle);
+        if let Some(pagebuffer) = pagebuffer {
+            self.client.map(move |client| {
+                client.write_complete(pagebuffer, error);
+            });
+        }
+    }
+
+    fn erase_complete(&self, error: hil::flash::Error) {
+        if self.managed.get() {
+            match error {
+                hil::flash::Error::CommandComplete => self.count(Operation::Erase),
+                hil::flash::Error::FlashError => {
+                    if let Some(page) = self.replace() {
+                        if self.flash.erase_page(page).is_ok() {
+                            return;
+                        }
+                    }
+                }
+                hil::flash::Error::FlashMemoryProtectionError => {}
+            }
+        }
+
+        self.operation.set(Operation::Idle);
+        self.client.map(move |client| {
+            client.erase_complete(error);
+        });
+    }
+}
+
+impl<'a, F: hil::flash::Flash, const N: usize, C: hil::flash::Client<Self>>
+    hil::flash::HasClient<'a, C> for FlashHealth<'a, F, N>
+{
+    fn set_client(&'a self, client: &'a C) {
+        self.client.set(client);
+    }
+}
+
+impl<F: hil::flash::Flash, const N: usize> hil::flash::Flash for FlashHealth<'_, F, N> {
+    type Page = F::Page;
+
+    fn read_page(
+        &self,
+        page_number: usize,
+        buf: &'static mut Self::Page,
+    ) -> Result<(), (ErrorCode, &'static mut Self::Page)> {
+        let page = match self.start(Operation::Read, page_number) {
+            Ok(page) => page,
+            Err(e) => return Err((e, buf)),
+        };
+        self.flash.read_page(page, buf).map_err(|e| {
+            self.operation.set(Operation::Idle);
+            e
+        })
+    }
+
+    fn write_page(
+        &self,
+        page_number: usize,
+        buf: &'static mut Self::Page,
+    ) -> Result<(), (ErrorCode, &'static mut Self::Page)> {
+        let page = match self.start(Operation::Write, page_number) {
+            Ok(page) => page,
+            Err(e) => return Err((e, buf)),
+        };
+        self.flash.write_page(page, buf).map_err(|e| {
+            self.operation.set(Operation::Idle);
+            e
+        })
+    }
+
+    fn erase_page(&self, page_number: usize) -> Result<(), ErrorCode> {
+        let page = self.start(Operation::Erase, page_number)?;
+        self.flash.erase_page(page).map_err(|e| {
+            self.operation.set(Operation::Idle);
+            e
+        })
+    }
+}
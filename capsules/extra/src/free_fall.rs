@@ -0,0 +1,295 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Free fall detection, with a safety action taken by the kernel.
+//!
+//! A free fall is detected either by the accelerometer, which raises an
+//! interrupt pin (see `Lsm303dlhcI2C::set_free_fall`), or in software by
+//! `SoftwareFreeFall`, which reads an accelerometer through
+//! `hil::sensors::NineDof` and reports a free fall when the magnitude of the
+//! acceleration stays below `THRESHOLD_MG` for `MIN_DURATION_MS`.
+//!
+//! At each free fall the board configured action, such as `StopPwm` which
+//! stops the PWM of the motors, is taken at once by the kernel, then every
+//! application is notified. The action does not wait for any application to
+//! be scheduled, so it is taken at most:
+//!
+//! - with the interrupt pin, the duration configured in the accelerometer
+//!   plus the time the kernel takes to handle a GPIO interrupt after the fall
+//!   starts;
+//! - in software, `MIN_DURATION_MS` plus a sampling period and the time of a
+//!   read of the accelerometer after the fall starts.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let free_fall = components::free_fall::FreeFallComponent::new(
+//!     board_kernel,
+//!     capsules_extra::free_fall::DRIVER_NUM,
+//!     Some(lsm303dlhc_int1),
+//! )
+//! .finalize(components::free_fall_component_static!());
+//!
+//! let stop_motors = static_init!(
+//!     capsules_extra::free_fall::StopPwm<'static, 2>,
+//!     capsules_extra::free_fall::StopPwm::new(motor_pins)
+//! );
+//! free_fall.set_action(stop_motors);
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: return the number of free falls since boot.
+//!
+//! Upcall 0 is scheduled for every application at each free fall, with the
+//! number of free falls since boot.
+
+use core::cell::Cell;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::gpio;
+use kernel::hil::pwm::PwmPin;
+use kernel::hil::sensors::{NineDof, NineDofClient};
+use kernel::hil::time::{Alarm, AlarmClient, ConvertTicks, Ticks};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::OptionalCell;
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::FreeFall as usize;
+
+/// Rate at which `SoftwareFreeFall` reads the accelerometer.
+pub const SAMPLE_RATE_HZ: u32 = 100;
+/// Magnitude of the acceleration below which the sensor falls.
+pub const THRESHOLD_MG: u32 = 350;
+/// Magnitude of the acceleration above which the fall is over.
+pub const REARM_MG: u32 = 600;
+/// Shortest fall detected.
+pub const MIN_DURATION_MS: u32 = 30;
+
+/// Samples below `THRESHOLD_MG` that make a fall, at least one.
+const MIN_SAMPLES: u32 = match (MIN_DURATION_MS * SAMPLE_RATE_HZ + 999) / 1000 {
+    0 => 1,
+    samples => samples,
+};
+
+/// Notified of free falls.
+pub trait FreeFallClient {
+    fn free_fall(&self);
+}
+
+/// Detects free falls in the magnitude of the acceleration.
+#[derive(Clone, Copy)]
+pub struct FallDetector {
+    /// Samples below the threshold in a row.
+    below: u32,
+    /// A fall was reported and is not over yet.
+    falling: bool,
+}
+
+impl FallDetector {
+    pub const fn new() -> FallDetector {
+        FallDetector {
+            below: 0,
+            falling: false,
+        }
+    }
+
+    /// Called with each sample of the acceleration along the three axes, in
+    /// mg, taken at `SAMPLE_RATE_HZ`. Returns whether a fall starts, once per
+    /// fall.
+    pub fn sample(&mut self, x: i32, y: i32, z: i32) -> bool {
+        let square = |value: i32| (value as i64 * value as i64) as u64;
+        let magnitude = square(x) + square(y) + square(z);
+
+        if magnitude < square(THRESHOLD_MG as i32) {
+            self.below = self.below.saturating_add(1);
+        } else {
+            self.below = 0;
+            if magnitude > square(REARM_MG as i32) {
+                self.falling = false;
+            }
+        }
+
+        if !self.falling && self.below >= MIN_SAMPLES {
+            self.falling = true;
+            return true;
+        }
+        false
+    }
+}
+
+/// Detects free falls by reading an accelerometer, for sensors without a
+/// free fall interrupt.
+pub struct SoftwareFreeFall<'a, A: Alarm<'a>> {
+    sensor: &'a dyn NineDof<'a>,
+    alarm: &'a A,
+    client: OptionalCell<&'a dyn FreeFallClient>,
+    detector: Cell<FallDetector>,
+    /// Time of the last sample.
+    sample_start: Cell<A::Ticks>,
+    /// A read of the accelerometer has not completed.
+    reading: Cell<bool>,
+}
+
+impl<'a, A: Alarm<'a>> SoftwareFreeFall<'a, A> {
+    pub fn new(sensor: &'a dyn NineDof<'a>, alarm: &'a A) -> SoftwareFreeFall<'a, A> {
+        SoftwareFreeFall {
+            sensor,
+            alarm,
+            client: OptionalCell::empty(),
+            detector: Cell::new(FallDetector::new()),
+            sample_start: Cell::new(A::Ticks::from(0)),
+            reading: Cell::new(false),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn FreeFallClient) {
+        self.client.set(client);
+    }
+
+    /// Start reading the accelerometer.
+    pub fn start(&self) {
+        self.sample_start.set(self.alarm.now());
+        self.schedule_sample();
+    }
+
+    /// Time the next sample from the previous one, so they do not drift.
+    fn schedule_sample(&self) {
+        let start = self.sample_start.get();
+        let period = self.alarm.ticks_from_us(1_000_000 / SAMPLE_RATE_HZ);
+        self.sample_start.set(start.wrapping_add(period));
+        self.alarm.set_alarm(start, period);
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for SoftwareFreeFall<'a, A> {
+    fn alarm(&self) {
+        // Skip the sample if the sensor is too slow
+        if !self.reading.get() && self.sensor.read_accelerometer().is_ok() {
+            self.reading.set(true);
+        }
+        self.schedule_sample();
+    }
+}
+
+impl<'a, A: Alarm<'a>> NineDofClient for SoftwareFreeFall<'a, A> {
+    fn callback(&self, x: usize, y: usize, z: usize) {
+        self.reading.set(false);
+
+        // The values are signed
+        let mut detector = self.detector.get();
+        let fall = detector.sample(x as i32, y as i32, z as i32);
+        self.detector.set(detector);
+
+        if fall {
+            self.client.map(|client| client.free_fall());
+        }
+    }
+}
+
+/// Stops PWM pins at each free fall, to park the motors they drive.
+///
+/// The pins may be the ones of the PWM driver, and applications start them
+/// again through it once the fall is over.
+pub struct StopPwm<'a, const NUM_PINS: usize> {
+    pins: &'a [&'a dyn PwmPin; NUM_PINS],
+}
+
+impl<'a, const NUM_PINS: usize> StopPwm<'a, NUM_PINS> {
+    pub fn new(pins: &'a [&'a dyn PwmPin; NUM_PINS]) -> StopPwm<'a, NUM_PINS> {
+        StopPwm { pins }
+    }
+}
+
+impl<const NUM_PINS: usize> FreeFallClient for StopPwm<'_, NUM_PINS> {
+    fn free_fall(&self) {
+        for pin in self.pins.iter() {
+            let _ = pin.stop();
+        }
+    }
+}
+
+pub struct FreeFall<'a> {
+    /// Raised by the accelerometer at each free fall, if it has a free fall
+    /// interrupt.
+    pin: Option<&'a dyn gpio::InterruptPin<'a>>,
+    /// The safety action of the board.
+    action: OptionalCell<&'a dyn FreeFallClient>,
+    apps: Grant<(), UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    falls: Cell<u32>,
+}
+
+impl<'a> FreeFall<'a> {
+    pub fn new(
+        pin: Option<&'a dyn gpio::InterruptPin<'a>>,
+        grant: Grant<(), UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    ) -> FreeFall<'a> {
+        FreeFall {
+            pin,
+            action: OptionalCell::empty(),
+            apps: grant,
+            falls: Cell::new(0),
+        }
+    }
+
+    /// Set the action taken at each free fall, before applications are
+    /// notified.
+    pub fn set_action(&self, action: &'a dyn FreeFallClient) {
+        self.action.set(action);
+    }
+
+    /// Listen to the interrupt pin, which is raised at each free fall.
+    pub fn start(&self) {
+        self.pin.map(|pin| {
+            pin.make_input();
+            pin.set_floating_state(gpio::FloatingState::PullNone);
+            pin.enable_interrupts(gpio::InterruptEdge::RisingEdge);
+        });
+    }
+}
+
+impl FreeFallClient for FreeFall<'_> {
+    fn free_fall(&self) {
+        self.action.map(|action| action.free_fall());
+
+        let falls = self.falls.get().wrapping_add(1);
+        self.falls.set(falls);
+        self.apps.each(|_, _, kernel_data| {
+            kernel_data.schedule_upcall(0, (falls as usize, 0, 0)).ok();
+        });
+    }
+}
+
+impl gpio::Client for FreeFall<'_> {
+    fn fired(&self) {
+        self.free_fall();
+    }
+}
+
+impl SyscallDriver for FreeFall<'_> {
+    fn command(
+        &self,
+        command_num: usize,
+        _data1: usize,
+        _data2: usize,
+        _processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            1 => CommandReturn::success_u32(self.falls.get()),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
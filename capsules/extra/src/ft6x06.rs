@@ -2,7 +2,8 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 // Copyright Tock Contributors 2022.
 
-//! SyscallDriver for the FT6x06 Touch Panel.
+//! Driver for the FT6x06 Touch Panel. It implements the touch HIL and is exposed
+//! to applications through the `touch` capsule.
 //!
 //! I2C Interface
 //!
@@ -33,6 +34,9 @@ use kernel::hil::touch::{self, GestureEvent, TouchEvent, TouchStatus};
 use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::ErrorCode;
 
+/// The fastest I2C bus the FT6x06 supports.
+pub const MAX_BUS_SPEED: i2c::BusSpeed = i2c::BusSpeed::Fast400k;
+
 pub static NO_TOUCH: TouchEvent = TouchEvent {
     id: 0,
     x: 0,
@@ -0,0 +1,261 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Process console command inspecting and driving the GPIO pins of the chip.
+//!
+//! `GpioCommand` implements the `gpio` command, which helps bringing up a
+//! board without flashing test applications:
+//!
+//! - `gpio` prints every pin which is not in low power mode, with its
+//!   configuration, the level it reads and its pull resistor;
+//! - `gpio <pin>` prints a single pin;
+//! - `gpio <pin> <action>` first applies `action` to the pin, one of
+//!   `input`, `output`, `set`, `clear` and `toggle`, then prints it.
+//!
+//! Pins are named as the chip names them, ignoring case. Pins the chip
+//! cannot access, for example because their port has no clock, are left
+//! out. The command bypasses the capsules using the pins, so driving a pin
+//! that one of them owns can confuse it.
+//!
+//! ```text
+//! tock$ gpio gpio25 output
+//!  gpio25     output   low
+//! tock$ gpio gpio25 set
+//!  gpio25     output   high
+//! tock$ gpio
+//! Pins:
+//!  gpio0      function high  pull-up
+//!  gpio1      function high  pull-up
+//!  gpio25     output   high
+//! ```
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let gpio = components::gpio_console::GpioCommandComponent::new(&peripherals.pins)
+//!     .finalize(components::gpio_command_component_static!());
+//! process_console.add_command(gpio).unwrap();
+//! ```
+
+use core::cell::Cell;
+use core::fmt;
+
+use capsules_core::process_console::ConsoleCommand;
+use kernel::hil::gpio::{Configuration, FloatingState, Pin, PinIntrospection};
+
+/// Width of the pin name column.
+const NAME_WIDTH: usize = 10;
+
+#[derive(Copy, Clone)]
+enum Action {
+    Input,
+    Output,
+    Set,
+    Clear,
+    Toggle,
+}
+
+impl Action {
+    fn parse(name: &str) -> Option<Action> {
+        match name {
+            "input" => Some(Action::Input),
+            "output" => Some(Action::Output),
+            "set" => Some(Action::Set),
+            "clear" => Some(Action::Clear),
+            "toggle" => Some(Action::Toggle),
+            _ => None,
+        }
+    }
+
+    fn apply(self, pin: &dyn Pin) {
+        match self {
+            Action::Input => {
+                pin.make_input();
+            }
+            Action::Output => {
+                pin.make_output();
+            }
+            Action::Set => pin.set(),
+            Action::Clear => pin.clear(),
+            Action::Toggle => {
+                pin.toggle();
+            }
+        }
+    }
+}
+
+/// What the last command line asked for.
+#[derive(Copy, Clone)]
+enum Request {
+    /// Print every pin.
+    All,
+    /// Print pin `index`.
+    Pin(usize),
+    /// Print an error message.
+    Error(&'static str),
+}
+
+/// Compares the text written to it with `name`, ignoring case.
+struct NameMatcher<'a> {
+    name: &'a [u8],
+    position: usize,
+    matches: bool,
+}
+
+impl fmt::Write for NameMatcher<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.position + s.len();
+        self.matches = self.matches
+            && self
+                .name
+                .get(self.position..end)
+                .map_or(false, |part| part.eq_ignore_ascii_case(s.as_bytes()));
+        self.position = end;
+        Ok(())
+    }
+}
+
+/// Forwards the text written to it and counts its characters.
+struct CountingWriter<'a> {
+    writer: &'a mut dyn fmt::Write,
+    count: usize,
+}
+
+impl fmt::Write for CountingWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.count += s.chars().count();
+        self.writer.write_str(s)
+    }
+}
+
+pub struct GpioCommand<'a> {
+    pins: &'a dyn PinIntrospection,
+    request: Cell<Request>,
+}
+
+impl<'a> GpioCommand<'a> {
+    pub fn new(pins: &'a dyn PinIntrospection) -> GpioCommand<'a> {
+        GpioCommand {
+            pins,
+            request: Cell::new(Request::All),
+        }
+    }
+
+    fn find_pin(&self, name: &str) -> Option<usize> {
+        (0..self.pins.num_pins()).find(|&index| {
+            let mut matcher = NameMatcher {
+                name: name.as_bytes(),
+                position: 0,
+                matches: true,
+            };
+            self.pins.write_pin_name(index, &mut matcher).is_ok()
+                && matcher.matches
+                && matcher.position == name.len()
+        })
+    }
+
+    fn parse(&self, arguments: &str) -> Request {
+        let mut words = arguments.split_whitespace();
+        let index = match words.next() {
+            Some(name) => match self.find_pin(name) {
+                Some(index) => index,
+                None => return Request::Error("gpio: unknown pin\r\n"),
+            },
+            None => return Request::All,
+        };
+        let pin = match self.pins.pin(index) {
+            Some(pin) => pin,
+            None => return Request::Error("gpio: pin not accessible\r\n"),
+        };
+        match words.next().map(Action::parse) {
+            Some(Some(action)) => action.apply(pin),
+            Some(None) => {
+                return Request::Error("gpio: actions are input, output, set, clear, toggle\r\n")
+            }
+            None => {}
+        }
+        Request::Pin(index)
+    }
+
+    /// Index of the `n`th pin printed by `gpio`.
+    fn nth_listed_pin(&self, n: usize) -> Option<usize> {
+        (0..self.pins.num_pins())
+            .filter(|&index| {
+                self.pins.pin(index).map_or(false, |pin| {
+                    !matches!(pin.configuration(), Configuration::LowPower)
+                })
+            })
+            .nth(n)
+    }
+
+    fn write_pin(&self, index: usize, writer: &mut dyn fmt::Write) {
+        let pin = match self.pins.pin(index) {
+            Some(pin) => pin,
+            None => return,
+        };
+        let _ = writer.write_str(" ");
+        let mut counter = CountingWriter { writer, count: 0 };
+        let _ = self.pins.write_pin_name(index, &mut counter);
+        let padding = NAME_WIDTH.saturating_sub(counter.count) + 1;
+        let _ = writer.write_fmt(format_args!("{:1$}", "", padding));
+
+        let configuration = pin.configuration();
+        let _ = writer.write_fmt(format_args!(
+            "{:<8} ",
+            match configuration {
+                Configuration::LowPower => "off",
+                Configuration::Input => "input",
+                Configuration::Output => "output",
+                Configuration::InputOutput => "in/out",
+                Configuration::Function => "function",
+                Configuration::Other => "other",
+            }
+        ));
+        let _ = match configuration {
+            Configuration::LowPower => writer.write_str("     "),
+            _ => writer.write_str(if pin.read() { "high " } else { "low  " }),
+        };
+        let _ = writer.write_str(match pin.floating_state() {
+            FloatingState::PullUp => " pull-up\r\n",
+            FloatingState::PullDown => " pull-down\r\n",
+            FloatingState::PullNone => "\r\n",
+        });
+    }
+}
+
+impl ConsoleCommand for GpioCommand<'_> {
+    fn name(&self) -> &'static str {
+        "gpio"
+    }
+
+    fn execute(&self, arguments: &str) {
+        self.request.set(self.parse(arguments));
+    }
+
+    fn write_line(&self, index: usize, writer: &mut dyn fmt::Write) -> bool {
+        match self.request.get() {
+            Request::All if index == 0 => {
+                let _ = writer.write_str("Pins:\r\n");
+                true
+            }
+            Request::All => match self.nth_listed_pin(index - 1) {
+                Some(pin) => {
+                    self.write_pin(pin, writer);
+                    true
+                }
+                None => false,
+            },
+            Request::Pin(pin) if index == 0 => {
+                self.write_pin(pin, writer);
+                true
+            }
+            Request::Error(message) if index == 0 => {
+                let _ = writer.write_str(message);
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,188 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Debounced GPIO interrupt pin.
+//!
+//! Mechanical buttons bounce for a few milliseconds when pressed or released,
+//! producing a burst of interrupts for what is a single change of state.
+//! `DebouncedPin` wraps an interrupt pin and only reports an interrupt once
+//! the pin has been stable for the configured debounce interval.
+//!
+//! Every edge seen on the underlying pin (re)starts an alarm. When the alarm
+//! expires the pin is sampled, and the client is notified if the stable level
+//! differs from the last one reported and matches the enabled interrupt edge.
+//!
+//! `DebouncedPin` itself implements `hil::gpio::InterruptPin`, so it can be
+//! placed transparently between the GPIO driver and any capsule that takes an
+//! interrupt pin, such as `capsules_core::button::Button`.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let debounced = components::gpio_debounce::DebouncedPinComponent::new(
+//!     &sam4l::gpio::PC[24],
+//!     mux_alarm,
+//!     20, // ms
+//! )
+//! .finalize(components::debounced_pin_component_static!(
+//!     sam4l::gpio::GPIOPin,
+//!     sam4l::ast::Ast
+//! ));
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::gpio;
+use kernel::hil::time::{self, ConvertTicks};
+use kernel::utilities::cells::OptionalCell;
+
+/// Debounce interval used when none is specified.
+pub const DEFAULT_DEBOUNCE_MS: u32 = 20;
+
+pub struct DebouncedPin<'a, P: gpio::InterruptPin<'a>, A: time::Alarm<'a>> {
+    pin: &'a P,
+    alarm: &'a A,
+    interval_ms: Cell<u32>,
+    /// Edge the client asked to be notified about, `None` if interrupts are
+    /// disabled.
+    mode: OptionalCell<gpio::InterruptEdge>,
+    /// Last stable level of the pin.
+    stable: Cell<bool>,
+    client: OptionalCell<&'a dyn gpio::Client>,
+}
+
+impl<'a, P: gpio::InterruptPin<'a>, A: time::Alarm<'a>> DebouncedPin<'a, P, A> {
+    pub fn new(pin: &'a P, alarm: &'a A, interval_ms: u32) -> Self {
+        Self {
+            pin,
+            alarm,
+            interval_ms: Cell::new(interval_ms),
+            mode: OptionalCell::empty(),
+            stable: Cell::new(false),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Change the debounce interval for this pin.
+    pub fn set_interval_ms(&self, interval_ms: u32) {
+        self.interval_ms.set(interval_ms);
+    }
+
+    pub fn interval_ms(&self) -> u32 {
+        self.interval_ms.get()
+    }
+}
+
+impl<'a, P: gpio::InterruptPin<'a>, A: time::Alarm<'a>> gpio::Input for DebouncedPin<'a, P, A> {
+    fn read(&self) -> bool {
+        self.pin.read()
+    }
+}
+
+impl<'a, P: gpio::InterruptPin<'a>, A: time::Alarm<'a>> gpio::Output for DebouncedPin<'a, P, A> {
+    fn set(&self) {
+        self.pin.set();
+    }
+
+    fn clear(&self) {
+        self.pin.clear();
+    }
+
+    fn toggle(&self) -> bool {
+        self.pin.toggle()
+    }
+}
+
+impl<'a, P: gpio::InterruptPin<'a>, A: time::Alarm<'a>> gpio::Configure for DebouncedPin<'a, P, A> {
+    fn configuration(&self) -> gpio::Configuration {
+        self.pin.configuration()
+    }
+
+    fn make_output(&self) -> gpio::Configuration {
+        self.pin.make_output()
+    }
+
+    fn disable_output(&self) -> gpio::Configuration {
+        self.pin.disable_output()
+    }
+
+    fn make_input(&self) -> gpio::Configuration {
+        self.pin.make_input()
+    }
+
+    fn disable_input(&self) -> gpio::Configuration {
+        self.pin.disable_input()
+    }
+
+    fn deactivate_to_low_power(&self) {
+        self.pin.deactivate_to_low_power();
+    }
+
+    fn set_floating_state(&self, state: gpio::FloatingState) {
+        self.pin.set_floating_state(state);
+    }
+
+    fn floating_state(&self) -> gpio::FloatingState {
+        self.pin.floating_state()
+    }
+}
+
+impl<'a, P: gpio::InterruptPin<'a>, A: time::Alarm<'a>> gpio::Interrupt<'a>
+    for DebouncedPin<'a, P, A>
+{
+    fn set_client(&self, client: &'a dyn gpio::Client) {
+        self.client.set(client);
+    }
+
+    fn enable_interrupts(&self, mode: gpio::InterruptEdge) {
+        self.stable.set(self.pin.read());
+        self.mode.set(mode);
+        // Bounces are only filtered if we see every edge of the raw signal.
+        self.pin.enable_interrupts(gpio::InterruptEdge::EitherEdge);
+    }
+
+    fn disable_interrupts(&self) {
+        self.mode.clear();
+        self.pin.disable_interrupts();
+        let _ = self.alarm.disarm();
+    }
+
+    fn is_pending(&self) -> bool {
+        self.pin.is_pending() || self.alarm.is_armed()
+    }
+}
+
+impl<'a, P: gpio::InterruptPin<'a>, A: time::Alarm<'a>> gpio::Client for DebouncedPin<'a, P, A> {
+    fn fired(&self) {
+        // Restart the debounce window on every edge, so the alarm only
+        // expires once the line has settled.
+        self.alarm.set_alarm(
+            self.alarm.now(),
+            self.alarm.ticks_from_ms(self.interval_ms.get()),
+        );
+    }
+}
+
+impl<'a, P: gpio::InterruptPin<'a>, A: time::Alarm<'a>> time::AlarmClient
+    for DebouncedPin<'a, P, A>
+{
+    fn alarm(&self) {
+        let level = self.pin.read();
+        if level == self.stable.get() {
+            // The pin bounced back to where it was, nothing happened.
+            return;
+        }
+        self.stable.set(level);
+
+        let notify = self.mode.map_or(false, |mode| match mode {
+            gpio::InterruptEdge::RisingEdge => level,
+            gpio::InterruptEdge::FallingEdge => !level,
+            gpio::InterruptEdge::EitherEdge => true,
+        });
+        if notify {
+            self.client.map(|client| client.fired());
+        }
+    }
+}
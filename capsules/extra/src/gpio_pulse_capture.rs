@@ -0,0 +1,105 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Edge capture in software, with a GPIO interrupt and a clock.
+//!
+//! `GpioPulseCapture` implements `hil::pulse_capture::PulseCapture` on any
+//! interrupt pin, for chips without input capture hardware. Each interrupt
+//! is timestamped with the clock, and its edge is the level the pin reads
+//! in the interrupt. The timestamps are as precise as the interrupt latency
+//! allows, a few microseconds, which is enough for protocols timed in tens
+//! of microseconds or more.
+//!
+//! Timestamps count the microseconds between edges, so they wrap around at
+//! `u32::MAX` whatever the width and the frequency of the clock. The time
+//! between two edges is measured modulo the period of the clock.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let capture = components::gpio_pulse_capture::GpioPulseCaptureComponent::new(
+//!     peripherals.pins.get_pin(RPGpio::GPIO16),
+//!     &peripherals.timer,
+//! )
+//! .finalize(components::gpio_pulse_capture_component_static!(
+//!     rp2040::gpio::RPGpioPin<'static>,
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::gpio;
+use kernel::hil::pulse_capture::{Edge, PulseCapture, PulseCaptureClient};
+use kernel::hil::time::{ConvertTicks, Ticks, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+pub struct GpioPulseCapture<'a, P: gpio::InterruptPin<'a>, T: Time> {
+    pin: &'a P,
+    time: &'a T,
+    running: Cell<bool>,
+    /// Clock time of the last edge.
+    last_ticks: Cell<T::Ticks>,
+    /// Timestamp of the last edge.
+    last_us: Cell<u32>,
+    client: OptionalCell<&'a dyn PulseCaptureClient>,
+}
+
+impl<'a, P: gpio::InterruptPin<'a>, T: Time> GpioPulseCapture<'a, P, T> {
+    pub fn new(pin: &'a P, time: &'a T) -> GpioPulseCapture<'a, P, T> {
+        GpioPulseCapture {
+            pin,
+            time,
+            running: Cell::new(false),
+            last_ticks: Cell::new(T::Ticks::from(0)),
+            last_us: Cell::new(0),
+            client: OptionalCell::empty(),
+        }
+    }
+}
+
+impl<'a, P: gpio::InterruptPin<'a>, T: Time> PulseCapture<'a> for GpioPulseCapture<'a, P, T> {
+    fn set_client(&self, client: &'a dyn PulseCaptureClient) {
+        self.client.set(client);
+    }
+
+    fn start(&self) -> Result<(), ErrorCode> {
+        if self.running.replace(true) {
+            return Err(ErrorCode::ALREADY);
+        }
+        self.last_ticks.set(self.time.now());
+        self.pin.make_input();
+        self.pin.enable_interrupts(gpio::InterruptEdge::EitherEdge);
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), ErrorCode> {
+        if !self.running.replace(false) {
+            return Err(ErrorCode::OFF);
+        }
+        self.pin.disable_interrupts();
+        Ok(())
+    }
+}
+
+impl<'a, P: gpio::InterruptPin<'a>, T: Time> gpio::Client for GpioPulseCapture<'a, P, T> {
+    fn fired(&self) {
+        let now = self.time.now();
+        let elapsed = now.wrapping_sub(self.last_ticks.replace(now));
+        let timestamp_us = self
+            .last_us
+            .get()
+            .wrapping_add(self.time.ticks_to_us(elapsed));
+        self.last_us.set(timestamp_us);
+
+        let edge = if self.pin.read() {
+            Edge::Rising
+        } else {
+            Edge::Falling
+        };
+        self.client.map(|client| client.edge(edge, timestamp_us));
+    }
+}
@@ -0,0 +1,826 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Provides userspace with immediate-mode drawing on a screen.
+//!
+//! With the `Screen` capsule, applications send every pixel they draw, and so
+//! need a buffer as large as the area they update. This capsule instead draws
+//! filled rectangles, lines and text itself. Each primitive is split into
+//! rectangular frames (a rectangle, a run of a line, a character cell) whose
+//! pixels are generated into a small tile buffer and pushed to the screen in
+//! chunks. Applications only share the text they draw.
+//!
+//! Colors are given as `0xRRGGBB` and converted to the pixel format of the
+//! screen. Mono screens are not supported.
+//!
+//! This capsule is the client of the screen, so boards use it instead of the
+//! `Screen` capsule.
+//!
+//! Capsules can draw too, with `draw`, one `Drawing` at a time. A drawing
+//! of the kernel is started before the pending commands of applications,
+//! and its end is signaled to the `GraphicsClient`.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let graphics = components::graphics::GraphicsComponent::new(
+//!     board_kernel,
+//!     capsules_extra::graphics::DRIVER_NUM,
+//!     tft,
+//! )
+//! .finalize(components::graphics_component_static!(1024));
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! Coordinates and sizes are packed in the command arguments as
+//! `(x << 16) | y` and `(width << 16) | height`.
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: get the resolution, as `(width, height)`.
+//! - Command 2: set the foreground color.
+//! - Command 3: set the background color, used around the glyphs of text.
+//! - Command 4: set the text scale, from 1 to `MAX_TEXT_SCALE`.
+//! - Command 10: fill the rectangle `(x, y)`, `(width, height)` with the
+//!   foreground color.
+//! - Command 11: draw a line from `(x0, y0)` to `(x1, y1)` with the
+//!   foreground color.
+//! - Command 12: draw the first `len` (second argument) characters of the
+//!   read-only allow buffer 0 from `(x, y)`. Characters past the right edge
+//!   of the screen are not drawn.
+//!
+//! Drawing commands complete with upcall 0, with the status as its first
+//! argument. Each application can have one drawing command outstanding.
+
+use core::cell::Cell;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil;
+use kernel::hil::screen::ScreenPixelFormat;
+use kernel::processbuffer::ReadableProcessBuffer;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::{ErrorCode, ProcessId};
+
+use crate::font;
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::Graphics as usize;
+
+/// Ids for read-only allow buffers
+mod ro_allow {
+    pub const TEXT: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+/// Largest text scale.
+pub const MAX_TEXT_SCALE: usize = 8;
+
+/// Size of a character cell, with one column and one row of spacing.
+pub const CHAR_WIDTH: usize = font::GLYPH_WIDTH + 1;
+pub const CHAR_HEIGHT: usize = font::GLYPH_HEIGHT + 1;
+
+/// A drawing requested by a capsule. Colors are given as `0xRRGGBB`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Drawing {
+    /// Fill a rectangle with `color`.
+    Rect {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        color: u32,
+    },
+    /// Draw the character `c` in a cell of `CHAR_WIDTH` by `CHAR_HEIGHT`
+    /// pixels times `scale`.
+    Char {
+        x: usize,
+        y: usize,
+        c: u8,
+        foreground: u32,
+        background: u32,
+        scale: usize,
+    },
+}
+
+/// Receives the end of the drawings of a capsule.
+pub trait GraphicsClient {
+    fn drawing_done(&self, result: Result<(), ErrorCode>);
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Command {
+    Rect {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    },
+    Line {
+        x0: usize,
+        y0: usize,
+        x1: usize,
+        y1: usize,
+    },
+    Text {
+        x: usize,
+        y: usize,
+        len: usize,
+    },
+}
+
+pub struct App {
+    command: Option<Command>,
+    foreground: u32,
+    background: u32,
+    scale: usize,
+}
+
+impl Default for App {
+    fn default() -> App {
+        App {
+            command: None,
+            foreground: 0xFFFFFF,
+            background: 0x000000,
+            scale: 1,
+        }
+    }
+}
+
+/// Progress through the frames of the command being drawn.
+#[derive(Clone, Copy)]
+enum Progress {
+    Done,
+    Rect {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    },
+    /// Bresenham's algorithm, at the point `(x, y)`.
+    Line {
+        x: isize,
+        y: isize,
+        x1: isize,
+        y1: isize,
+        dx: isize,
+        dy: isize,
+        error: isize,
+    },
+    Text {
+        x: usize,
+        y: usize,
+        len: usize,
+        index: usize,
+    },
+    Char {
+        x: usize,
+        y: usize,
+        c: u8,
+    },
+}
+
+/// Source of the pixels of the current frame.
+#[derive(Clone, Copy)]
+enum Pixels {
+    Solid,
+    Glyph(u8),
+}
+
+/// A rectangle of the screen, and the number of its pixels already written.
+#[derive(Clone, Copy)]
+struct Frame {
+    width: usize,
+    height: usize,
+    pixels: Pixels,
+    position: usize,
+}
+
+fn unpack(data: usize) -> (usize, usize) {
+    ((data >> 16) & 0xFFFF, data & 0xFFFF)
+}
+
+fn bytes_per_pixel(format: ScreenPixelFormat) -> Option<usize> {
+    match format {
+        ScreenPixelFormat::RGB_233 => Some(1),
+        ScreenPixelFormat::RGB_565 => Some(2),
+        ScreenPixelFormat::RGB_888 => Some(3),
+        ScreenPixelFormat::ARGB_8888 => Some(4),
+        _ => None,
+    }
+}
+
+/// Write the `0xRRGGBB` `color` to `buffer` in the pixel `format`.
+fn encode(color: u32, format: ScreenPixelFormat, buffer: &mut [u8]) {
+    let (r, g, b) = ((color >> 16) as u8, (color >> 8) as u8, color as u8);
+    match format {
+        ScreenPixelFormat::RGB_233 => {
+            buffer[0] = (r & 0xC0) | ((g >> 5) << 3) | (b >> 5);
+        }
+        ScreenPixelFormat::RGB_565 => {
+            let pixel = ((r as u16 >> 3) << 11) | ((g as u16 >> 2) << 5) | (b as u16 >> 3);
+            buffer[0] = (pixel >> 8) as u8;
+            buffer[1] = pixel as u8;
+        }
+        ScreenPixelFormat::RGB_888 => {
+            buffer[0] = r;
+            buffer[1] = g;
+            buffer[2] = b;
+        }
+        ScreenPixelFormat::ARGB_8888 => {
+            buffer[0] = 0xFF;
+            buffer[1] = r;
+            buffer[2] = g;
+            buffer[3] = b;
+        }
+        _ => {}
+    }
+}
+
+pub struct Graphics<'a> {
+    screen: &'a dyn hil::screen::Screen<'a>,
+    apps: Grant<App, UpcallCount<1>, AllowRoCount<{ ro_allow::COUNT }>, AllowRwCount<0>>,
+    current_process: OptionalCell<ProcessId>,
+    /// Drawing of the kernel waiting for the screen.
+    kernel_drawing: OptionalCell<Drawing>,
+    /// Whether the frames being drawn belong to the kernel.
+    kernel_active: Cell<bool>,
+    kernel_client: OptionalCell<&'a dyn GraphicsClient>,
+    progress: Cell<Progress>,
+    frame: OptionalCell<Frame>,
+    foreground: Cell<u32>,
+    background: Cell<u32>,
+    scale: Cell<usize>,
+    buffer: TakeCell<'static, [u8]>,
+}
+
+impl<'a> Graphics<'a> {
+    pub fn new(
+        screen: &'a dyn hil::screen::Screen<'a>,
+        buffer: &'static mut [u8],
+        grant: Grant<App, UpcallCount<1>, AllowRoCount<{ ro_allow::COUNT }>, AllowRwCount<0>>,
+    ) -> Graphics<'a> {
+        Graphics {
+            screen,
+            apps: grant,
+            current_process: OptionalCell::empty(),
+            kernel_drawing: OptionalCell::empty(),
+            kernel_active: Cell::new(false),
+            kernel_client: OptionalCell::empty(),
+            progress: Cell::new(Progress::Done),
+            frame: OptionalCell::empty(),
+            foreground: Cell::new(0),
+            background: Cell::new(0),
+            scale: Cell::new(1),
+            buffer: TakeCell::new(buffer),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn GraphicsClient) {
+        self.kernel_client.set(client);
+    }
+
+    /// The resolution of the screen.
+    pub fn get_resolution(&self) -> (usize, usize) {
+        self.screen.get_resolution()
+    }
+
+    /// Draw `drawing` once the screen is free. Only one drawing of the
+    /// kernel can be outstanding, its end is signaled with `drawing_done`.
+    pub fn draw(&self, drawing: Drawing) -> Result<(), ErrorCode> {
+        if let Drawing::Char { scale, .. } = drawing {
+            if scale == 0 || scale > MAX_TEXT_SCALE {
+                return Err(ErrorCode::INVAL);
+            }
+        }
+        if self.kernel_drawing.is_some() || self.kernel_active.get() {
+            return Err(ErrorCode::BUSY);
+        }
+        self.kernel_drawing.set(drawing);
+        if !self.busy() {
+            if let Err(e) = self.start_kernel() {
+                self.kernel_drawing.clear();
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    fn busy(&self) -> bool {
+        self.current_process.is_some() || self.kernel_active.get()
+    }
+
+    fn enqueue_command(&self, command: Command, process_id: ProcessId) -> CommandReturn {
+        let result = self
+            .apps
+            .enter(process_id, |app, _| {
+                if app.command.is_some() {
+                    Err(ErrorCode::BUSY)
+                } else {
+                    app.command = Some(command);
+                    Ok(())
+                }
+            })
+            .unwrap_or_else(|err| Err(err.into()));
+        if let Err(e) = result {
+            return CommandReturn::failure(e);
+        }
+
+        if !self.busy() {
+            if let Err(e) = self.start(process_id) {
+                let _ = self.apps.enter(process_id, |app, _| app.command = None);
+                return CommandReturn::failure(e);
+            }
+        }
+        CommandReturn::success()
+    }
+
+    /// Start drawing the command of `process_id`.
+    fn start(&self, process_id: ProcessId) -> Result<(), ErrorCode> {
+        if bytes_per_pixel(self.screen.get_pixel_format()).is_none() {
+            return Err(ErrorCode::NOSUPPORT);
+        }
+
+        let command = self
+            .apps
+            .enter(process_id, |app, _| {
+                self.foreground.set(app.foreground);
+                self.background.set(app.background);
+                self.scale.set(app.scale);
+                app.command
+            })
+            .map_err(ErrorCode::from)?
+            .ok_or(ErrorCode::FAIL)?;
+
+        self.progress.set(match command {
+            Command::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => Progress::Rect {
+                x,
+                y,
+                width,
+                height,
+            },
+            Command::Line { x0, y0, x1, y1 } => {
+                let (x0, y0, x1, y1) = (x0 as isize, y0 as isize, x1 as isize, y1 as isize);
+                let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+                Progress::Line {
+                    x: x0,
+                    y: y0,
+                    x1,
+                    y1,
+                    dx,
+                    dy,
+                    error: dx + dy,
+                }
+            }
+            Command::Text { x, y, len } => Progress::Text {
+                x,
+                y,
+                len,
+                index: 0,
+            },
+        });
+        self.current_process.set(process_id);
+        let result = self.next_frame();
+        if result.is_err() {
+            self.frame.clear();
+            self.progress.set(Progress::Done);
+            self.current_process.clear();
+        }
+        result
+    }
+
+    /// Start drawing the pending drawing of the kernel.
+    fn start_kernel(&self) -> Result<(), ErrorCode> {
+        if bytes_per_pixel(self.screen.get_pixel_format()).is_none() {
+            return Err(ErrorCode::NOSUPPORT);
+        }
+
+        let drawing = self.kernel_drawing.take().ok_or(ErrorCode::FAIL)?;
+        self.progress.set(match drawing {
+            Drawing::Rect {
+                x,
+                y,
+                width,
+                height,
+                color,
+            } => {
+                self.foreground.set(color & 0xFFFFFF);
+                Progress::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                }
+            }
+            Drawing::Char {
+                x,
+                y,
+                c,
+                foreground,
+                background,
+                scale,
+            } => {
+                self.foreground.set(foreground & 0xFFFFFF);
+                self.background.set(background & 0xFFFFFF);
+                self.scale.set(scale);
+                Progress::Char { x, y, c }
+            }
+        });
+        self.kernel_active.set(true);
+        let result = self.next_frame();
+        if result.is_err() {
+            self.frame.clear();
+            self.progress.set(Progress::Done);
+            self.kernel_active.set(false);
+        }
+        result
+    }
+
+    /// Clip the rectangle to the screen.
+    fn clip(&self, x: usize, y: usize, width: usize, height: usize) -> Option<(usize, usize)> {
+        let (screen_width, screen_height) = self.screen.get_resolution();
+        if x >= screen_width || y >= screen_height {
+            return None;
+        }
+        let width = width.min(screen_width - x);
+        let height = height.min(screen_height - y);
+        if width == 0 || height == 0 {
+            None
+        } else {
+            Some((width, height))
+        }
+    }
+
+    /// Get the next run of the line, as `(x, y, width, height)`.
+    fn next_line_run(&self) -> Option<(isize, isize, isize, isize)> {
+        let Progress::Line {
+            mut x,
+            mut y,
+            x1,
+            y1,
+            dx,
+            dy,
+            mut error,
+        } = self.progress.get()
+        else {
+            return None;
+        };
+        let (sx, sy) = (if x < x1 { 1 } else { -1 }, if y < y1 { 1 } else { -1 });
+        let horizontal = dx >= -dy;
+        let (start_x, start_y) = (x, y);
+
+        let done = loop {
+            if x == x1 && y == y1 {
+                break true;
+            }
+            let (last_x, last_y) = (x, y);
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                error += dx;
+                y += sy;
+            }
+            if (horizontal && y != last_y) || (!horizontal && x != last_x) {
+                // (x, y) starts the next run.
+                self.progress.set(Progress::Line {
+                    x,
+                    y,
+                    x1,
+                    y1,
+                    dx,
+                    dy,
+                    error,
+                });
+                x = last_x;
+                y = last_y;
+                break false;
+            }
+        };
+        if done {
+            self.progress.set(Progress::Done);
+        }
+
+        Some((
+            start_x.min(x),
+            start_y.min(y),
+            (x - start_x).abs() + 1,
+            (y - start_y).abs() + 1,
+        ))
+    }
+
+    /// Find the next visible frame of the command and set it as the write
+    /// frame of the screen, or complete the command. Errors are left to the
+    /// caller to report.
+    fn next_frame(&self) -> Result<(), ErrorCode> {
+        loop {
+            let (x, y, width, height, pixels) = match self.progress.get() {
+                Progress::Done => {
+                    self.command_done(Ok(()));
+                    return Ok(());
+                }
+                Progress::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => {
+                    self.progress.set(Progress::Done);
+                    (x, y, width, height, Pixels::Solid)
+                }
+                Progress::Line { .. } => match self.next_line_run() {
+                    Some((x, y, width, height)) if x >= 0 && y >= 0 => (
+                        x as usize,
+                        y as usize,
+                        width as usize,
+                        height as usize,
+                        Pixels::Solid,
+                    ),
+                    _ => continue,
+                },
+                Progress::Text { x, y, len, index } => {
+                    let scale = self.scale.get();
+                    let (width, height) = (CHAR_WIDTH * scale, CHAR_HEIGHT * scale);
+                    let cell_x = x + index * width;
+                    let (screen_width, _) = self.screen.get_resolution();
+                    let c = self.current_process.map_or(None, |process_id| {
+                        self.apps
+                            .enter(*process_id, |_, kernel_data| {
+                                kernel_data
+                                    .get_readonly_processbuffer(ro_allow::TEXT)
+                                    .and_then(|text| {
+                                        text.enter(|text| text.iter().nth(index).map(|c| c.get()))
+                                    })
+                                    .unwrap_or(None)
+                            })
+                            .unwrap_or(None)
+                    });
+                    match c {
+                        Some(c) if index < len && cell_x + width <= screen_width => {
+                            self.progress.set(Progress::Text {
+                                x,
+                                y,
+                                len,
+                                index: index + 1,
+                            });
+                            (cell_x, y, width, height, Pixels::Glyph(c))
+                        }
+                        _ => {
+                            self.progress.set(Progress::Done);
+                            continue;
+                        }
+                    }
+                }
+                Progress::Char { x, y, c } => {
+                    let scale = self.scale.get();
+                    self.progress.set(Progress::Done);
+                    (
+                        x,
+                        y,
+                        CHAR_WIDTH * scale,
+                        CHAR_HEIGHT * scale,
+                        Pixels::Glyph(c),
+                    )
+                }
+            };
+
+            if let Some((width, height)) = self.clip(x, y, width, height) {
+                self.frame.set(Frame {
+                    width,
+                    height,
+                    pixels,
+                    position: 0,
+                });
+                return self.screen.set_write_frame(x, y, width, height);
+            }
+        }
+    }
+
+    /// Generate the next pixels of the frame into `buffer`. Returns the number
+    /// of bytes.
+    fn fill_buffer(&self, buffer: &mut [u8]) -> usize {
+        let format = self.screen.get_pixel_format();
+        let bytes = match bytes_per_pixel(format) {
+            Some(bytes) => bytes,
+            None => return 0,
+        };
+        let mut frame = match self.frame.extract() {
+            Some(frame) => frame,
+            None => return 0,
+        };
+
+        let scale = self.scale.get();
+        let total = frame.width * frame.height;
+        let count = (total - frame.position).min(buffer.len() / bytes);
+        for i in 0..count {
+            let position = frame.position + i;
+            let color = match frame.pixels {
+                Pixels::Solid => self.foreground.get(),
+                Pixels::Glyph(c) => {
+                    let column = position % frame.width / scale;
+                    let row = position / frame.width / scale;
+                    let glyph = font::glyph(c);
+                    if column < font::GLYPH_WIDTH && (glyph[column] >> row) & 1 == 1 {
+                        self.foreground.get()
+                    } else {
+                        self.background.get()
+                    }
+                }
+            };
+            encode(color, format, &mut buffer[i * bytes..]);
+        }
+        frame.position += count;
+        self.frame.set(frame);
+        count * bytes
+    }
+
+    /// Push the next chunk of the frame to the screen, or move to the next
+    /// frame.
+    fn write_chunk(&self, buffer: &'static mut [u8], first: bool) {
+        let len = self.fill_buffer(buffer);
+        if len == 0 {
+            self.buffer.replace(buffer);
+            if let Err(e) = self.next_frame() {
+                self.command_done(Err(e));
+            }
+            return;
+        }
+
+        let result = if first {
+            self.screen.write(buffer, len)
+        } else {
+            self.screen.write_continue(buffer, len)
+        };
+        if let Err(e) = result {
+            self.command_done(Err(e));
+        }
+    }
+
+    fn schedule_callback(&self, process_id: ProcessId, result: Result<(), ErrorCode>) {
+        let _ = self.apps.enter(process_id, |app, upcalls| {
+            app.command = None;
+            upcalls
+                .schedule_upcall(0, (kernel::errorcode::into_statuscode(result), 0, 0))
+                .ok();
+        });
+    }
+
+    /// Signal the end of the current command to its process or to the
+    /// client, and start the next pending command.
+    fn command_done(&self, result: Result<(), ErrorCode>) {
+        self.frame.clear();
+        self.progress.set(Progress::Done);
+        if self.kernel_active.replace(false) {
+            self.kernel_client.map(|client| client.drawing_done(result));
+        } else {
+            self.current_process.take().map(|process_id| {
+                self.schedule_callback(process_id, result);
+            });
+        }
+
+        // The client may have started its next drawing.
+        if self.busy() {
+            return;
+        }
+        if self.kernel_drawing.is_some() {
+            match self.start_kernel() {
+                Ok(()) => return,
+                Err(e) => self.kernel_client.map(|client| client.drawing_done(Err(e))),
+            };
+            if self.busy() {
+                return;
+            }
+        }
+        for app in self.apps.iter() {
+            let process_id = app.processid();
+            if app.enter(|app, _| app.command.is_some()) {
+                match self.start(process_id) {
+                    Ok(()) => break,
+                    Err(e) => self.schedule_callback(process_id, Err(e)),
+                }
+            }
+        }
+    }
+}
+
+impl<'a> hil::screen::ScreenClient for Graphics<'a> {
+    fn command_complete(&self, r: Result<(), ErrorCode>) {
+        // The write frame is set.
+        match r {
+            Ok(()) => match self.buffer.take() {
+                Some(buffer) => self.write_chunk(buffer, true),
+                None => self.command_done(Err(ErrorCode::NOMEM)),
+            },
+            Err(e) => self.command_done(Err(e)),
+        }
+    }
+
+    fn write_complete(&self, buffer: &'static mut [u8], r: Result<(), ErrorCode>) {
+        match r {
+            Ok(()) => self.write_chunk(buffer, false),
+            Err(e) => {
+                self.buffer.replace(buffer);
+                self.command_done(Err(e));
+            }
+        }
+    }
+
+    fn screen_is_ready(&self) {}
+}
+
+impl<'a> SyscallDriver for Graphics<'a> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        data2: usize,
+        process_id: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            // Get resolution
+            1 => {
+                let (width, height) = self.screen.get_resolution();
+                CommandReturn::success_u32_u32(width as u32, height as u32)
+            }
+
+            // Set foreground color
+            2 => self
+                .apps
+                .enter(process_id, |app, _| {
+                    app.foreground = data1 as u32 & 0xFFFFFF;
+                    CommandReturn::success()
+                })
+                .unwrap_or_else(|err| CommandReturn::failure(err.into())),
+
+            // Set background color
+            3 => self
+                .apps
+                .enter(process_id, |app, _| {
+                    app.background = data1 as u32 & 0xFFFFFF;
+                    CommandReturn::success()
+                })
+                .unwrap_or_else(|err| CommandReturn::failure(err.into())),
+
+            // Set text scale
+            4 => {
+                if data1 == 0 || data1 > MAX_TEXT_SCALE {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                self.apps
+                    .enter(process_id, |app, _| {
+                        app.scale = data1;
+                        CommandReturn::success()
+                    })
+                    .unwrap_or_else(|err| CommandReturn::failure(err.into()))
+            }
+
+            // Fill rectangle
+            10 => {
+                let (x, y) = unpack(data1);
+                let (width, height) = unpack(data2);
+                self.enqueue_command(
+                    Command::Rect {
+                        x,
+                        y,
+                        width,
+                        height,
+                    },
+                    process_id,
+                )
+            }
+
+            // Line
+            11 => {
+                let (x0, y0) = unpack(data1);
+                let (x1, y1) = unpack(data2);
+                self.enqueue_command(Command::Line { x0, y0, x1, y1 }, process_id)
+            }
+
+            // Text
+            12 => {
+                let (x, y) = unpack(data1);
+                self.enqueue_command(Command::Text { x, y, len: data2 }, process_id)
+            }
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
@@ -0,0 +1,318 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Software (bit-banged) I2C master.
+//!
+//! Implements `hil::i2c::I2CMaster` on top of two GPIO pins, so I2C devices
+//! can be attached to arbitrary pins, or used on chips that do not yet have a
+//! hardware I2C driver in Tock.
+//!
+//! The lines are driven open-drain style: a line is pulled low by configuring
+//! the pin as an output driving `0`, and released by switching the pin back
+//! to an input. External pull-up resistors are required on both SDA and SCL.
+//!
+//! Slaves that hold SCL low to stretch the clock are supported. After
+//! releasing SCL the driver waits until the line actually reads high, and
+//! aborts the transfer with `Error::Busy` if that does not happen within
+//! `stretch_timeout` polls.
+//!
+//! Transfers run synchronously (busy-waiting between clock edges) and the
+//! completion is delivered to the client from a deferred call. The bus speed
+//! is set by `half_period`, the number of spin-loop iterations between clock
+//! edges, which has to be tuned for the core clock of the chip.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let i2c = components::i2c::I2CBitBangComponent::new(sda_pin, scl_pin, 20, 10000)
+//!     .finalize(components::i2c_bitbang_component_static!(lpc55s6x::gpio::Pin));
+//!
+//! let mux_i2c = components::i2c::I2CMuxComponent::new(i2c, None)
+//!     .finalize(components::i2c_mux_component_static!(
+//!         capsules_extra::i2c_bitbang::I2CMasterBitBang<'static, lpc55s6x::gpio::Pin>
+//!     ));
+//! ```
+
+use core::cell::Cell;
+
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
+use kernel::hil::gpio;
+use kernel::hil::i2c::{Error, I2CHwMasterClient, I2CMaster};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+
+pub struct I2CMasterBitBang<'a, P: gpio::Pin> {
+    sda: &'a P,
+    scl: &'a P,
+    /// Number of spin-loop iterations between two clock edges.
+    half_period: Cell<u32>,
+    /// Maximum number of polls while waiting for a slave to release SCL.
+    stretch_timeout: Cell<u32>,
+    enabled: Cell<bool>,
+    client: OptionalCell<&'a dyn I2CHwMasterClient>,
+    buffer: TakeCell<'static, [u8]>,
+    status: Cell<Result<(), Error>>,
+    deferred_call: DeferredCall,
+}
+
+impl<'a, P: gpio::Pin> I2CMasterBitBang<'a, P> {
+    pub fn new(sda: &'a P, scl: &'a P, half_period: u32, stretch_timeout: u32) -> Self {
+        Self {
+            sda,
+            scl,
+            half_period: Cell::new(half_period),
+            stretch_timeout: Cell::new(stretch_timeout),
+            enabled: Cell::new(false),
+            client: OptionalCell::empty(),
+            buffer: TakeCell::empty(),
+            status: Cell::new(Ok(())),
+            deferred_call: DeferredCall::new(),
+        }
+    }
+
+    /// Change the bus timing. `half_period` is the number of spin-loop
+    /// iterations between clock edges, `stretch_timeout` the number of polls
+    /// a slave may hold SCL low for.
+    pub fn set_timing(&self, half_period: u32, stretch_timeout: u32) {
+        self.half_period.set(half_period);
+        self.stretch_timeout.set(stretch_timeout);
+    }
+
+    fn delay(&self) {
+        for _ in 0..self.half_period.get() {
+            core::hint::spin_loop();
+        }
+    }
+
+    fn release(&self, pin: &P) {
+        pin.make_input();
+    }
+
+    fn pull_low(&self, pin: &P) {
+        pin.clear();
+        pin.make_output();
+        pin.clear();
+    }
+
+    /// Release SCL and wait for the line to go high, honoring clock
+    /// stretching by the slave.
+    fn release_scl(&self) -> Result<(), Error> {
+        self.release(self.scl);
+        let mut timeout = self.stretch_timeout.get();
+        while !self.scl.read() {
+            if timeout == 0 {
+                return Err(Error::Busy);
+            }
+            timeout -= 1;
+            core::hint::spin_loop();
+        }
+        Ok(())
+    }
+
+    fn start(&self) -> Result<(), Error> {
+        self.release(self.sda);
+        self.delay();
+        self.release_scl()?;
+        if !self.sda.read() {
+            // Somebody else is holding the data line.
+            return Err(Error::ArbitrationLost);
+        }
+        self.delay();
+        self.pull_low(self.sda);
+        self.delay();
+        self.pull_low(self.scl);
+        Ok(())
+    }
+
+    fn stop(&self) {
+        self.pull_low(self.sda);
+        self.delay();
+        // If the slave never releases SCL there is nothing more we can do,
+        // release SDA anyway so the bus is left idle from our side.
+        let _ = self.release_scl();
+        self.delay();
+        self.release(self.sda);
+        self.delay();
+    }
+
+    fn write_bit(&self, bit: bool) -> Result<(), Error> {
+        if bit {
+            self.release(self.sda);
+        } else {
+            self.pull_low(self.sda);
+        }
+        self.delay();
+        self.release_scl()?;
+        if bit && !self.sda.read() {
+            return Err(Error::ArbitrationLost);
+        }
+        self.delay();
+        self.pull_low(self.scl);
+        Ok(())
+    }
+
+    fn read_bit(&self) -> Result<bool, Error> {
+        self.release(self.sda);
+        self.delay();
+        self.release_scl()?;
+        let bit = self.sda.read();
+        self.delay();
+        self.pull_low(self.scl);
+        Ok(bit)
+    }
+
+    /// Write a byte, returning whether the slave acknowledged it.
+    fn write_byte(&self, byte: u8) -> Result<bool, Error> {
+        for i in (0..8).rev() {
+            self.write_bit((byte >> i) & 1 == 1)?;
+        }
+        // The acknowledge bit is active low.
+        self.read_bit().map(|nack| !nack)
+    }
+
+    fn read_byte(&self, ack: bool) -> Result<u8, Error> {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = (byte << 1) | (self.read_bit()? as u8);
+        }
+        self.write_bit(!ack)?;
+        self.release(self.sda);
+        Ok(byte)
+    }
+
+    fn write_bytes(&self, addr: u8, data: &[u8]) -> Result<(), Error> {
+        if !self.write_byte(addr << 1)? {
+            return Err(Error::AddressNak);
+        }
+        for byte in data.iter() {
+            if !self.write_byte(*byte)? {
+                return Err(Error::DataNak);
+            }
+        }
+        Ok(())
+    }
+
+    fn read_bytes(&self, addr: u8, data: &mut [u8]) -> Result<(), Error> {
+        if !self.write_byte((addr << 1) | 1)? {
+            return Err(Error::AddressNak);
+        }
+        let len = data.len();
+        for (i, byte) in data.iter_mut().enumerate() {
+            // NACK the last byte to tell the slave we are done.
+            *byte = self.read_byte(i + 1 < len)?;
+        }
+        Ok(())
+    }
+
+    fn transfer(
+        &self,
+        addr: u8,
+        data: &mut [u8],
+        write_len: usize,
+        read_len: usize,
+    ) -> Result<(), Error> {
+        self.start()?;
+        let result = (|| {
+            if write_len > 0 {
+                self.write_bytes(addr, &data[..write_len])?;
+                if read_len > 0 {
+                    // Repeated start.
+                    self.start()?;
+                }
+            }
+            if read_len > 0 {
+                self.read_bytes(addr, &mut data[..read_len])?;
+            }
+            Ok(())
+        })();
+        self.stop();
+        result
+    }
+
+    fn start_transfer(
+        &self,
+        addr: u8,
+        data: &'static mut [u8],
+        write_len: usize,
+        read_len: usize,
+    ) -> Result<(), (Error, &'static mut [u8])> {
+        if !self.enabled.get() {
+            return Err((Error::NotSupported, data));
+        }
+        if self.buffer.is_some() {
+            return Err((Error::Busy, data));
+        }
+        if write_len > data.len() || read_len > data.len() {
+            return Err((Error::Overrun, data));
+        }
+
+        self.status
+            .set(self.transfer(addr, data, write_len, read_len));
+        self.buffer.replace(data);
+        self.deferred_call.set();
+        Ok(())
+    }
+}
+
+impl<'a, P: gpio::Pin> I2CMaster<'a> for I2CMasterBitBang<'a, P> {
+    fn set_master_client(&self, master_client: &'a dyn I2CHwMasterClient) {
+        self.client.set(master_client);
+    }
+
+    fn enable(&self) {
+        self.sda.set_floating_state(gpio::FloatingState::PullUp);
+        self.scl.set_floating_state(gpio::FloatingState::PullUp);
+        self.release(self.sda);
+        self.release(self.scl);
+        self.enabled.set(true);
+    }
+
+    fn disable(&self) {
+        self.enabled.set(false);
+        self.release(self.sda);
+        self.release(self.scl);
+    }
+
+    fn write_read(
+        &self,
+        addr: u8,
+        data: &'static mut [u8],
+        write_len: usize,
+        read_len: usize,
+    ) -> Result<(), (Error, &'static mut [u8])> {
+        self.start_transfer(addr, data, write_len, read_len)
+    }
+
+    fn write(
+        &self,
+        addr: u8,
+        data: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (Error, &'static mut [u8])> {
+        self.start_transfer(addr, data, len, 0)
+    }
+
+    fn read(
+        &self,
+        addr: u8,
+        buffer: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (Error, &'static mut [u8])> {
+        self.start_transfer(addr, buffer, 0, len)
+    }
+}
+
+impl<'a, P: gpio::Pin> DeferredCallClient for I2CMasterBitBang<'a, P> {
+    fn handle_deferred_call(&self) {
+        self.buffer.take().map(|buffer| {
+            self.client.map(move |client| {
+                client.command_complete(buffer, self.status.get());
+            });
+        });
+    }
+
+    fn register(&'static self) {
+        self.deferred_call.register(self);
+    }
+}
@@ -0,0 +1,500 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Infrared remote control codes, in the NEC protocol.
+//!
+//! Codes are received from a demodulating IR receiver, such as a TSOP38238,
+//! whose output is low while it sees the 38 kHz carrier. The edges of the
+//! output are timestamped by a pulse capture, such as a `GpioPulseCapture`,
+//! and the time between them is decoded into frames. Codes are sent by an IR
+//! LED driven by a PWM pin: the carrier is switched on for each mark and off
+//! for each space, with an alarm timing the pulses.
+//!
+//! A NEC frame is a 9 ms mark and a 4.5 ms space, then 32 bits sent least
+//! significant bit first, each a 562 µs mark and a space of 562 µs for a 0
+//! or 1687 µs for a 1, and a last 562 µs mark. The bits are the address and
+//! its inverse, then the command and its inverse. In extended frames the
+//! address is 16 bits, without inverse. While a button is held the remote
+//! sends a repeat code, a 9 ms mark, a 2.25 ms space and a 562 µs mark,
+//! every 108 ms.
+//!
+//! Either the receiver or the LED can be left out.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let ir_remote = components::ir_remote::IrRemoteComponent::new(
+//!     board_kernel,
+//!     capsules_extra::ir_remote::DRIVER_NUM,
+//!     mux_alarm,
+//!     Some(ir_receiver_capture),
+//!     Some(ir_led_pwm_pin),
+//! )
+//! .finalize(components::ir_remote_component_static!(
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: receive codes. Upcall 1 reports each code received with the
+//!   address, the command, and flags: bit 0 is set for repeat codes, which
+//!   carry the code they repeat, and bit 1 for extended addresses.
+//! - Command 2: stop receiving codes.
+//! - Command 3: send the code with the address given as the first argument.
+//!   The second argument is the command in bits 0 to 7, bit 8 to send the
+//!   address as an extended address, and the number of repeat codes to send
+//!   after the frame in bits 16 to 23. Upcall 0 reports, with a status code,
+//!   when the last code has been sent.
+//! - Command 4: stop sending, without an upcall.
+//!
+//! Any number of apps can receive codes, one app sends at a time.
+
+use core::cell::Cell;
+
+use kernel::errorcode::into_statuscode;
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::pulse_capture::{Edge, PulseCapture, PulseCaptureClient};
+use kernel::hil::pwm;
+use kernel::hil::time::{Alarm, AlarmClient, ConvertTicks, Ticks};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::OptionalCell;
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::IrRemote as usize;
+
+/// Frequency of the carrier.
+pub const CARRIER_HZ: usize = 38000;
+
+const LEADER_MARK_US: u32 = 9000;
+const LEADER_SPACE_US: u32 = 4500;
+const REPEAT_SPACE_US: u32 = 2250;
+const BIT_MARK_US: u32 = 562;
+const ZERO_SPACE_US: u32 = 562;
+const ONE_SPACE_US: u32 = 1687;
+/// Time from the start of a code to the start of the next.
+const PERIOD_US: u32 = 108000;
+/// Longest time from the end of a code to a repeat code which repeats it.
+const REPEAT_GAP_US: u32 = 120000;
+
+/// Whether `duration_us` is within 30% of `nominal_us`, to allow for the
+/// delay of the receiver and of the capture.
+fn near(duration_us: u32, nominal_us: u32) -> bool {
+    duration_us >= nominal_us * 7 / 10 && duration_us <= nominal_us * 13 / 10
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct NecCode {
+    /// The address, 8 bits unless `extended`.
+    pub address: u16,
+    pub command: u8,
+    /// The address is 16 bits, sent without its inverse.
+    pub extended: bool,
+    /// The code is a repeat code, repeating this code.
+    pub repeat: bool,
+}
+
+impl NecCode {
+    /// The code sent as the 32 bits of `frame`, `None` if the inverse of the
+    /// command does not match. An address whose inverse does not match is
+    /// extended.
+    pub fn from_frame(frame: u32) -> Option<NecCode> {
+        let [address_low, address_high, command, inverse] = frame.to_le_bytes();
+        if command != !inverse {
+            return None;
+        }
+        let extended = address_high != !address_low;
+        let address = if extended {
+            u16::from_le_bytes([address_low, address_high])
+        } else {
+            address_low as u16
+        };
+        Some(NecCode {
+            address,
+            command,
+            extended,
+            repeat: false,
+        })
+    }
+
+    /// The 32 bits of the frame sending the code.
+    pub fn frame(&self) -> u32 {
+        let [address_low, address_high] = if self.extended {
+            self.address.to_le_bytes()
+        } else {
+            [self.address as u8, !(self.address as u8)]
+        };
+        u32::from_le_bytes([address_low, address_high, self.command, !self.command])
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DecoderState {
+    Idle,
+    LeaderMark,
+    LeaderSpace,
+    BitMark,
+    BitSpace,
+    StopMark,
+    RepeatMark,
+}
+
+/// Decodes NEC codes from the length of the marks and spaces received.
+#[derive(Clone, Copy)]
+pub struct NecDecoder {
+    state: DecoderState,
+    frame: u32,
+    bits: u32,
+    /// The code a repeat code repeats.
+    last: Option<NecCode>,
+}
+
+impl NecDecoder {
+    pub const fn new() -> NecDecoder {
+        NecDecoder {
+            state: DecoderState::Idle,
+            frame: 0,
+            bits: 0,
+            last: None,
+        }
+    }
+
+    /// Called at each edge: a mark starts if `mark` is set, after a space of
+    /// `duration_us`, otherwise a mark of `duration_us` ended. Returns the
+    /// code the edge completes.
+    pub fn edge(&mut self, mark: bool, duration_us: u32) -> Option<NecCode> {
+        use DecoderState::*;
+        let mut code = None;
+        self.state = match (self.state, mark) {
+            (Idle, true) => {
+                if duration_us > REPEAT_GAP_US {
+                    self.last = None;
+                }
+                LeaderMark
+            }
+            (LeaderMark, false) if near(duration_us, LEADER_MARK_US) => LeaderSpace,
+            (LeaderSpace, true) if near(duration_us, LEADER_SPACE_US) => {
+                self.frame = 0;
+                self.bits = 0;
+                BitMark
+            }
+            (LeaderSpace, true) if near(duration_us, REPEAT_SPACE_US) => RepeatMark,
+            (BitMark, false) if near(duration_us, BIT_MARK_US) => BitSpace,
+            (BitSpace, true)
+                if near(duration_us, ZERO_SPACE_US) || near(duration_us, ONE_SPACE_US) =>
+            {
+                if near(duration_us, ONE_SPACE_US) {
+                    self.frame |= 1 << self.bits;
+                }
+                self.bits += 1;
+                if self.bits == 32 {
+                    StopMark
+                } else {
+                    BitMark
+                }
+            }
+            (StopMark, false) if near(duration_us, BIT_MARK_US) => {
+                code = NecCode::from_frame(self.frame);
+                self.last = code;
+                Idle
+            }
+            (RepeatMark, false) if near(duration_us, BIT_MARK_US) => {
+                code = self.last.map(|last| NecCode {
+                    repeat: true,
+                    ..last
+                });
+                Idle
+            }
+            // The code is broken. A mark may be the leader of the next.
+            (_, true) => {
+                self.last = None;
+                LeaderMark
+            }
+            (_, false) => {
+                self.last = None;
+                Idle
+            }
+        };
+        code
+    }
+}
+
+/// The codes being sent.
+#[derive(Clone, Copy)]
+struct Transmission {
+    /// The frame being sent, `None` for a repeat code.
+    frame: Option<u32>,
+    /// Repeat codes left to send after this code.
+    repeats: usize,
+    /// The next mark or space of the code.
+    step: usize,
+    /// Time since the start of the code.
+    elapsed_us: u32,
+}
+
+impl Transmission {
+    /// The next mark or space of the code, with its length. The last space
+    /// lasts until the end of the period. `None` once the code is sent.
+    fn pulse(&self) -> Option<(bool, u32)> {
+        let pulse = match (self.frame, self.step) {
+            (_, 0) => (true, LEADER_MARK_US),
+            (Some(_), 1) => (false, LEADER_SPACE_US),
+            (Some(frame), 2..=65) => {
+                let bit = (self.step - 2) / 2;
+                if self.step % 2 == 0 {
+                    (true, BIT_MARK_US)
+                } else if frame >> bit & 1 == 1 {
+                    (false, ONE_SPACE_US)
+                } else {
+                    (false, ZERO_SPACE_US)
+                }
+            }
+            (Some(_), 66) => (true, BIT_MARK_US),
+            (Some(_), 67) => (false, PERIOD_US.saturating_sub(self.elapsed_us)),
+            (None, 1) => (false, REPEAT_SPACE_US),
+            (None, 2) => (true, BIT_MARK_US),
+            (None, 3) => (false, PERIOD_US.saturating_sub(self.elapsed_us)),
+            _ => return None,
+        };
+        Some(pulse)
+    }
+}
+
+#[derive(Default)]
+pub struct App {
+    receiving: bool,
+}
+
+pub struct IrRemote<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    receiver: Option<&'a dyn PulseCapture<'a>>,
+    carrier: Option<&'a dyn pwm::PwmPin>,
+    apps: Grant<App, UpcallCount<2>, AllowRoCount<0>, AllowRwCount<0>>,
+    decoder: Cell<NecDecoder>,
+    receiving: Cell<bool>,
+    /// Timestamp of the last edge received.
+    last_edge_us: Cell<u32>,
+    transmission: Cell<Option<Transmission>>,
+    /// Time the pulse being sent started.
+    pulse_start: Cell<A::Ticks>,
+    sender: OptionalCell<ProcessId>,
+}
+
+impl<'a, A: Alarm<'a>> IrRemote<'a, A> {
+    pub fn new(
+        alarm: &'a A,
+        receiver: Option<&'a dyn PulseCapture<'a>>,
+        carrier: Option<&'a dyn pwm::PwmPin>,
+        grant: Grant<App, UpcallCount<2>, AllowRoCount<0>, AllowRwCount<0>>,
+    ) -> IrRemote<'a, A> {
+        IrRemote {
+            alarm,
+            receiver,
+            carrier,
+            apps: grant,
+            decoder: Cell::new(NecDecoder::new()),
+            receiving: Cell::new(false),
+            last_edge_us: Cell::new(0),
+            transmission: Cell::new(None),
+            pulse_start: Cell::new(A::Ticks::from(0)),
+            sender: OptionalCell::empty(),
+        }
+    }
+
+    /// Capture the edges of the receiver if any app receives codes.
+    fn update_receiver(&self) {
+        let receiving = self
+            .apps
+            .iter()
+            .any(|app| app.enter(|app, _| app.receiving));
+        if receiving == self.receiving.replace(receiving) {
+            return;
+        }
+        if let Some(receiver) = self.receiver {
+            if receiving {
+                self.decoder.set(NecDecoder::new());
+                let _ = receiver.start();
+            } else {
+                let _ = receiver.stop();
+            }
+        }
+    }
+
+    fn send(&self, code: NecCode, repeats: usize) -> Result<(), ErrorCode> {
+        if self.carrier.is_none() {
+            return Err(ErrorCode::NOSUPPORT);
+        }
+        self.pulse_start.set(self.alarm.now());
+        self.next_pulse(Transmission {
+            frame: Some(code.frame()),
+            repeats,
+            step: 0,
+            elapsed_us: 0,
+        })
+    }
+
+    /// Start the next pulse of `transmission`, or a repeat code once the
+    /// code is sent.
+    fn next_pulse(&self, mut transmission: Transmission) -> Result<(), ErrorCode> {
+        let carrier = self.carrier.ok_or(ErrorCode::NOSUPPORT)?;
+        let (mark, duration_us) = match transmission.pulse() {
+            Some(pulse) => pulse,
+            None => {
+                transmission = Transmission {
+                    frame: None,
+                    repeats: transmission.repeats.saturating_sub(1),
+                    step: 0,
+                    elapsed_us: 0,
+                };
+                (true, LEADER_MARK_US)
+            }
+        };
+        if mark {
+            carrier.start(CARRIER_HZ, carrier.get_maximum_duty_cycle() / 3)?;
+        } else {
+            carrier.stop()?;
+        }
+        transmission.step += 1;
+        transmission.elapsed_us += duration_us;
+        self.transmission.set(Some(transmission));
+
+        // Time pulses from the start of the previous one so they do not
+        // drift.
+        let start = self.pulse_start.get();
+        let duration = self.alarm.ticks_from_us(duration_us);
+        self.pulse_start.set(start.wrapping_add(duration));
+        self.alarm.set_alarm(start, duration);
+        Ok(())
+    }
+
+    fn stop_sending(&self) {
+        self.transmission.set(None);
+        let _ = self.alarm.disarm();
+        if let Some(carrier) = self.carrier {
+            let _ = carrier.stop();
+        }
+    }
+
+    fn sending_done(&self, result: Result<(), ErrorCode>) {
+        self.stop_sending();
+        self.sender.take().map(|processid| {
+            let _ = self.apps.enter(processid, |_, kernel_data| {
+                kernel_data
+                    .schedule_upcall(0, (into_statuscode(result), 0, 0))
+                    .ok();
+            });
+        });
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for IrRemote<'a, A> {
+    fn alarm(&self) {
+        if let Some(transmission) = self.transmission.get() {
+            if transmission.pulse().is_none() && transmission.repeats == 0 {
+                self.sending_done(Ok(()));
+            } else if let Err(err) = self.next_pulse(transmission) {
+                self.sending_done(Err(err));
+            }
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> PulseCaptureClient for IrRemote<'a, A> {
+    fn edge(&self, edge: Edge, timestamp_us: u32) {
+        let duration_us = timestamp_us.wrapping_sub(self.last_edge_us.replace(timestamp_us));
+        // The receiver output is low during marks
+        let mark = edge == Edge::Falling;
+
+        let mut decoder = self.decoder.get();
+        let code = decoder.edge(mark, duration_us);
+        self.decoder.set(decoder);
+
+        if let Some(code) = code {
+            let flags = (code.repeat as usize) | (code.extended as usize) << 1;
+            self.apps.each(|_, app, kernel_data| {
+                if app.receiving {
+                    kernel_data
+                        .schedule_upcall(1, (code.address as usize, code.command as usize, flags))
+                        .ok();
+                }
+            });
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> SyscallDriver for IrRemote<'a, A> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            // Receive codes
+            1 | 2 => {
+                if self.receiver.is_none() {
+                    return CommandReturn::failure(ErrorCode::NOSUPPORT);
+                }
+                let result = self
+                    .apps
+                    .enter(processid, |app, _| app.receiving = command_num == 1)
+                    .map_err(ErrorCode::from);
+                if result.is_ok() {
+                    self.update_receiver();
+                }
+                CommandReturn::from(result)
+            }
+
+            // Send a code
+            3 => {
+                if self.sender.is_some() {
+                    return CommandReturn::failure(ErrorCode::BUSY);
+                } else if data1 > u16::MAX as usize {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                let extended = data2 & (1 << 8) != 0;
+                if !extended && data1 > u8::MAX as usize {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                let code = NecCode {
+                    address: data1 as u16,
+                    command: data2 as u8,
+                    extended,
+                    repeat: false,
+                };
+                let result = self.send(code, (data2 >> 16) & 0xFF);
+                match result {
+                    Ok(()) => self.sender.set(processid),
+                    Err(_) => self.stop_sending(),
+                }
+                CommandReturn::from(result)
+            }
+
+            // Stop sending
+            4 => match self.sender.extract() {
+                Some(sender) if sender == processid => {
+                    self.sender.clear();
+                    self.stop_sending();
+                    CommandReturn::success()
+                }
+                Some(_) => CommandReturn::failure(ErrorCode::RESERVE),
+                None => CommandReturn::failure(ErrorCode::OFF),
+            },
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
@@ -48,7 +48,8 @@ enum Operation {
 }
 
 const HEADER_VERSION: u8 = 0;
-const HEADER_LENGTH: usize = 9;
+/// Length of the header the store prepends to each value.
+pub const HEADER_LENGTH: usize = 9;
 
 /// This is the header used for KV stores
 struct KeyHeader {
@@ -296,6 +297,7 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
         self.mux_kv.operation.map(|op| {
             if result.is_err() {
                 self.hashed_key.replace(hashed_key);
+                self.mux_kv.operation.clear();
 
                 self.unhashed_key.take().map(|unhashed_key| match op {
                     Operation::Get => {
@@ -327,6 +329,7 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
                             {
                                 self.unhashed_key.take().map(|unhashed_key| {
                                     self.hashed_key.replace(key);
+                                    self.mux_kv.operation.clear();
                                     self.client.map(move |cb| {
                                         cb.get_complete(e, unhashed_key, value);
                                     });
@@ -341,6 +344,7 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
                             {
                                 self.hashed_key.replace(key);
                                 self.unhashed_key.take().map(|unhashed_key| {
+                                    self.mux_kv.operation.clear();
                                     self.client.map(move |cb| {
                                         cb.set_complete(e, unhashed_key, value);
                                     });
@@ -355,6 +359,7 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
                             {
                                 self.unhashed_key.take().map(|unhashed_key| {
                                     self.hashed_key.replace(key);
+                                    self.mux_kv.operation.clear();
                                     self.client.map(move |cb| {
                                         cb.get_complete(e, unhashed_key, value);
                                     });
@@ -381,6 +386,7 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
         self.mux_kv.operation.map(|op| match op {
             Operation::Get | Operation::Delete => {}
             Operation::Set => {
+                self.mux_kv.operation.clear();
                 self.unhashed_key.take().map(|unhashed_key| {
                     self.value.take().map(|value| {
                         self.client.map(move |cb| {
@@ -388,7 +394,6 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
                         });
                     });
                 });
-                self.mux_kv.operation.clear();
             }
         });
 
@@ -410,7 +415,7 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
 
                 let header = KeyHeader::new_from_buf(ret_buf);
 
-                if header.version == HEADER_VERSION {
+                if result.is_ok() && header.version == HEADER_VERSION {
                     self.valid_ids.map(|perms| {
                         access_allowed = perms.check_write_permission(header.write_id);
                     });
@@ -423,6 +428,7 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
                         if let Err((key, e)) = self.mux_kv.kv.invalidate_key(hashed_key) {
                             self.hashed_key.replace(key);
                             self.unhashed_key.take().map(|unhashed_key| {
+                                self.mux_kv.operation.clear();
                                 self.client.map(move |cb| {
                                     cb.delete_complete(e, unhashed_key);
                                 });
@@ -431,8 +437,9 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
                     });
                 } else {
                     self.unhashed_key.take().map(|unhashed_key| {
+                        self.mux_kv.operation.clear();
                         self.client.map(move |cb| {
-                            cb.delete_complete(Err(ErrorCode::FAIL), unhashed_key);
+                            cb.delete_complete(result.and(Err(ErrorCode::FAIL)), unhashed_key);
                         });
                     });
                 }
@@ -462,18 +469,25 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
                     ret_buf.iter_mut().for_each(|m| *m = 0)
                 }
 
+                // The operation is over before the client is called back, so
+                // the client can start the next one from the callback.
+                self.mux_kv.operation.clear();
                 self.unhashed_key.take().map(|unhashed_key| {
                     self.client.map(move |cb| {
                         if read_allowed {
                             cb.get_complete(result, unhashed_key, ret_buf);
                         } else {
                             // The operation failed or the caller doesn't have permission,
-                            // just return an error (and an empty buffer)
-                            cb.get_complete(Err(ErrorCode::FAIL), unhashed_key, ret_buf);
+                            // just return an error (and an empty buffer). A missing key
+                            // keeps its error, `NOSUPPORT`.
+                            cb.get_complete(
+                                result.and(Err(ErrorCode::FAIL)),
+                                unhashed_key,
+                                ret_buf,
+                            );
                         }
                     });
                 });
-                self.mux_kv.operation.clear();
             }
         });
 
@@ -486,12 +500,12 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
         self.mux_kv.operation.map(|op| match op {
             Operation::Set | Operation::Get => {}
             Operation::Delete => {
+                self.mux_kv.operation.clear();
                 self.unhashed_key.take().map(|unhashed_key| {
                     self.client.map(move |cb| {
                         cb.delete_complete(result, unhashed_key);
                     });
                 });
-                self.mux_kv.operation.clear();
             }
         });
 
@@ -17,31 +17,58 @@ pub mod analog_comparator;
 pub mod analog_sensor;
 pub mod apds9960;
 pub mod app_flash_driver;
+pub mod app_loader;
+pub mod app_watchdog;
+pub mod at24c_eeprom;
+pub mod at_engine;
+pub mod auto_brightness;
 pub mod ble_advertising_driver;
+pub mod bluetooth_serial;
 pub mod bme280;
 pub mod bmp280;
+pub mod boot_counter;
 pub mod bus;
+pub mod bus_analyzer;
+pub mod bus_fault_injector;
 pub mod buzzer_driver;
 pub mod buzzer_pwm;
 pub mod can;
 pub mod ccs811;
+pub mod cellular_modem;
+pub mod clock_tree;
 pub mod crc;
+pub mod credential_store;
 pub mod dac;
+pub mod data_logger;
 pub mod debug_process_restart;
+pub mod debug_rate_limit;
+pub mod device_id;
+pub mod driver_statistics;
+pub mod flash_health;
 pub mod fm25cl;
+pub mod font;
+pub mod free_fall;
 pub mod ft6x06;
 pub mod fxos8700cq;
 pub mod gpio_async;
+pub mod gpio_console;
+pub mod gpio_debounce;
+pub mod gpio_pulse_capture;
+pub mod graphics;
 pub mod hd44780;
 pub mod hmac;
 pub mod hts221;
 pub mod humidity;
+pub mod i2c_bitbang;
 pub mod ieee802154;
+pub mod ir_remote;
 pub mod isl29035;
 pub mod kv_driver;
 pub mod kv_store;
 pub mod l3gd20;
 pub mod led_matrix;
+pub mod lin;
+pub mod location;
 pub mod log;
 pub mod lpm013m126;
 pub mod lps25hb;
@@ -50,25 +77,39 @@ pub mod lsm303dlhc;
 pub mod lsm303xx;
 pub mod lsm6dsoxtr;
 pub mod ltc294x;
+pub mod mac_address_provisioning;
 pub mod max17205;
 pub mod mcp230xx;
 pub mod mlx90614;
+pub mod modbus_rtu;
 pub mod mx25r6435f;
 pub mod ninedof;
+pub mod nmea;
 pub mod nonvolatile_storage_driver;
 pub mod nonvolatile_to_pages;
 pub mod nrf51822_serialization;
+pub mod onscreen_keyboard;
 pub mod panic_button;
+pub mod panic_screen;
 pub mod pca9544a;
+pub mod pedometer;
+pub mod power_manager;
+pub mod pressure;
+pub mod process_load_log;
 pub mod proximity;
 pub mod public_key_crypto;
 pub mod pwm;
+pub mod pwm_capture;
 pub mod read_only_state;
+pub mod reset_command;
+pub mod resistive_touch;
 pub mod rf233;
 pub mod rf233_const;
+pub mod rgb_led;
 pub mod screen;
 pub mod sdcard;
 pub mod segger_rtt;
+pub mod sensor_aggregator;
 pub mod seven_segment;
 pub mod sha;
 pub mod sha256;
@@ -76,14 +117,19 @@ pub mod sht3x;
 pub mod si7021;
 pub mod sip_hash;
 pub mod sound_pressure;
+pub mod spi_flash;
 pub mod st77xx;
 pub mod symmetric_encryption;
+pub mod syscall_trace;
+pub mod system_time;
 pub mod temperature;
 pub mod temperature_rp2040;
 pub mod temperature_stm;
 pub mod text_screen;
 pub mod tickv;
 pub mod touch;
+pub mod touch_calibration;
 pub mod tsl2561;
 pub mod usb;
 pub mod usb_hid_driver;
+pub mod vibration;
@@ -0,0 +1,412 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Provides userspace with a LIN bus master.
+//!
+//! The master starts every LIN 2.x frame with a header: a break of 13 bit
+//! times, a one bit delimiter, the `0x55` sync byte and the protected
+//! identifier (PID) of the frame. The response, up to eight data bytes and a
+//! checksum, is then either published by the master or sent by a slave. The
+//! break is timed with an alarm, so the UART must implement
+//! `hil::uart::Break`.
+//!
+//! LIN is a single wire bus, so the UART also receives the bytes the master
+//! sends. The capsule reads back the whole frame and reports an error if the
+//! echo differs from what was sent, which is how LIN detects bit errors.
+//!
+//! The checksum is the enhanced checksum of LIN 2.x, which includes the PID,
+//! except for the diagnostic frames `0x3C` and `0x3D`, which use the classic
+//! checksum of the data only.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let lin = components::lin::LinComponent::new(
+//!     board_kernel,
+//!     capsules_extra::lin::DRIVER_NUM,
+//!     &peripherals.uart1,
+//!     mux_alarm,
+//!     19200,
+//! )
+//! .finalize(components::lin_component_static!(
+//!     rp2040::uart::Uart<'static>,
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! - Read-only allow 0: data of the frames the master publishes.
+//! - Read-write allow 0: buffer for the responses of slaves.
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: send the header of frame `data1` (0 to 63) and publish the
+//!   first `data2` (1 to 8) bytes of the read-only buffer.
+//! - Command 2: send the header of frame `data1` (0 to 63) and read a
+//!   response of `data2` (1 to 8) bytes into the read-write buffer.
+//!
+//! - Upcall 0: the frame is done, with 0 or an error code, and the number of
+//!   data bytes. NOACK means no slave responded in time, FAIL means the echo
+//!   of the frame or the checksum of the response is wrong.
+
+use core::cell::Cell;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::time::{Alarm, AlarmClient, ConvertTicks};
+use kernel::hil::uart;
+use kernel::processbuffer::{ReadableProcessBuffer, WriteableProcessBuffer};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::Lin as usize;
+
+/// Ids for read-only allow buffers
+mod ro_allow {
+    pub const DATA: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+/// Ids for read-write allow buffers
+mod rw_allow {
+    pub const RESPONSE: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+/// Largest frame after the break: sync, PID, 8 data bytes and checksum.
+pub const FRAME_LEN: usize = 11;
+
+const SYNC: u8 = 0x55;
+const BREAK_BITS: u32 = 13;
+const DELIMITER_BITS: u32 = 1;
+
+#[derive(Copy, Clone, PartialEq)]
+enum State {
+    Idle,
+    /// Holding the line low.
+    Break,
+    /// Waiting out the break delimiter.
+    Delimiter,
+    /// Sending the frame and reading back the bus.
+    Frame,
+}
+
+#[derive(Default)]
+pub struct App;
+
+/// Protected identifier of frame `id`, with its two parity bits.
+pub fn protected_id(id: u8) -> u8 {
+    let bit = |n: u8| (id >> n) & 1;
+    let p0 = bit(0) ^ bit(1) ^ bit(2) ^ bit(4);
+    let p1 = !(bit(1) ^ bit(3) ^ bit(4) ^ bit(5)) & 1;
+    (id & 0x3F) | p0 << 6 | p1 << 7
+}
+
+/// Checksum of the response of frame `pid`.
+pub fn checksum(pid: u8, data: &[u8]) -> u8 {
+    // Diagnostic frames use the classic checksum
+    let id = pid & 0x3F;
+    let initial = if id == 0x3C || id == 0x3D { 0 } else { pid };
+    let sum = data.iter().fold(initial as u16, |sum, byte| {
+        let sum = sum + *byte as u16;
+        if sum > 0xFF {
+            sum - 0xFF
+        } else {
+            sum
+        }
+    });
+    !(sum as u8)
+}
+
+pub struct Lin<'a, U: uart::Uart<'a> + uart::Break, A: Alarm<'a>> {
+    uart: &'a U,
+    alarm: &'a A,
+    baud_rate: u32,
+    apps: Grant<
+        App,
+        UpcallCount<1>,
+        AllowRoCount<{ ro_allow::COUNT }>,
+        AllowRwCount<{ rw_allow::COUNT }>,
+    >,
+    current_app: OptionalCell<ProcessId>,
+    state: Cell<State>,
+    /// The frame sent, to check its echo.
+    frame: Cell<[u8; FRAME_LEN]>,
+    /// Number of data bytes of the frame.
+    data_len: Cell<usize>,
+    /// Whether the master sends the response.
+    publish: Cell<bool>,
+    /// Why the reception of the frame was aborted.
+    error: OptionalCell<ErrorCode>,
+    tx_buffer: TakeCell<'static, [u8]>,
+    rx_buffer: TakeCell<'static, [u8]>,
+}
+
+impl<'a, U: uart::Uart<'a> + uart::Break, A: Alarm<'a>> Lin<'a, U, A> {
+    pub fn new(
+        uart: &'a U,
+        alarm: &'a A,
+        baud_rate: u32,
+        grant: Grant<
+            App,
+            UpcallCount<1>,
+            AllowRoCount<{ ro_allow::COUNT }>,
+            AllowRwCount<{ rw_allow::COUNT }>,
+        >,
+        tx_buffer: &'static mut [u8; FRAME_LEN],
+        rx_buffer: &'static mut [u8; FRAME_LEN],
+    ) -> Lin<'a, U, A> {
+        Lin {
+            uart,
+            alarm,
+            baud_rate,
+            apps: grant,
+            current_app: OptionalCell::empty(),
+            state: Cell::new(State::Idle),
+            frame: Cell::new([0; FRAME_LEN]),
+            data_len: Cell::new(0),
+            publish: Cell::new(false),
+            error: OptionalCell::empty(),
+            tx_buffer: TakeCell::new(tx_buffer),
+            rx_buffer: TakeCell::new(rx_buffer),
+        }
+    }
+
+    pub fn initialize(&self) -> Result<(), ErrorCode> {
+        self.uart.configure(uart::Parameters {
+            baud_rate: self.baud_rate,
+            width: uart::Width::Eight,
+            stop_bits: uart::StopBits::One,
+            parity: uart::Parity::None,
+            hw_flow_control: false,
+        })
+    }
+
+    /// Set the alarm to fire in `bits` bit times.
+    fn wait_bits(&self, bits: u32) {
+        let us = (bits * 1_000_000 + self.baud_rate - 1) / self.baud_rate;
+        self.alarm
+            .set_alarm(self.alarm.now(), self.alarm.ticks_from_us(us));
+    }
+
+    /// Send the frame `id`, with a response of `data_len` bytes. The data is
+    /// read from the process if the master publishes it.
+    fn send_frame(
+        &self,
+        processid: ProcessId,
+        id: usize,
+        data_len: usize,
+        publish: bool,
+    ) -> Result<(), ErrorCode> {
+        if self.state.get() != State::Idle {
+            return Err(ErrorCode::BUSY);
+        } else if id > 0x3F || data_len == 0 || data_len > 8 {
+            return Err(ErrorCode::INVAL);
+        }
+
+        let pid = protected_id(id as u8);
+        let mut frame = [0; FRAME_LEN];
+        frame[0] = SYNC;
+        frame[1] = pid;
+        if publish {
+            self.apps.enter(processid, |_, kernel_data| {
+                kernel_data
+                    .get_readonly_processbuffer(ro_allow::DATA)
+                    .and_then(|data| {
+                        data.enter(|data| {
+                            if data.len() < data_len {
+                                return Err(ErrorCode::SIZE);
+                            }
+                            data[..data_len].copy_to_slice(&mut frame[2..2 + data_len]);
+                            Ok(())
+                        })
+                    })
+                    .unwrap_or(Err(ErrorCode::RESERVE))
+            })??;
+            frame[2 + data_len] = checksum(pid, &frame[2..2 + data_len]);
+        }
+
+        self.uart.start_break()?;
+        self.frame.set(frame);
+        self.data_len.set(data_len);
+        self.publish.set(publish);
+        self.current_app.set(processid);
+        self.state.set(State::Break);
+        self.wait_bits(BREAK_BITS);
+        Ok(())
+    }
+
+    /// Report the end of the frame to the process which sent it.
+    fn frame_done(&self, result: Result<(), ErrorCode>) {
+        self.state.set(State::Idle);
+        self.current_app.take().map(|processid| {
+            let _ = self.apps.enter(processid, |_, kernel_data| {
+                kernel_data
+                    .schedule_upcall(
+                        0,
+                        (
+                            kernel::errorcode::into_statuscode(result),
+                            self.data_len.get(),
+                            0,
+                        ),
+                    )
+                    .ok();
+            });
+        });
+    }
+
+    /// Check the frame read back from the bus, and copy the response of a
+    /// slave to the process.
+    fn check_frame(&self, received: &[u8]) -> Result<(), ErrorCode> {
+        let frame = self.frame.get();
+        let data_len = self.data_len.get();
+        if received.len() < data_len + 3 {
+            return Err(ErrorCode::FAIL);
+        } else if self.publish.get() {
+            if received[..data_len + 3] != frame[..data_len + 3] {
+                return Err(ErrorCode::FAIL);
+            }
+            return Ok(());
+        }
+
+        let data = &received[2..2 + data_len];
+        if received[..2] != frame[..2] || received[2 + data_len] != checksum(frame[1], data) {
+            return Err(ErrorCode::FAIL);
+        }
+        self.current_app.map_or(Err(ErrorCode::FAIL), |processid| {
+            self.apps
+                .enter(*processid, |_, kernel_data| {
+                    kernel_data
+                        .get_readwrite_processbuffer(rw_allow::RESPONSE)
+                        .and_then(|response| {
+                            response.mut_enter(|response| {
+                                let len = response.len().min(data_len);
+                                response[..len].copy_from_slice(&data[..len]);
+                            })
+                        })
+                        .map_err(ErrorCode::from)
+                })
+                .unwrap_or_else(|err| Err(err.into()))
+        })
+    }
+}
+
+impl<'a, U: uart::Uart<'a> + uart::Break, A: Alarm<'a>> AlarmClient for Lin<'a, U, A> {
+    fn alarm(&self) {
+        match self.state.get() {
+            State::Break => {
+                let _ = self.uart.stop_break();
+                self.state.set(State::Delimiter);
+                self.wait_bits(DELIMITER_BITS);
+            }
+            State::Delimiter => {
+                // Read back the header and the response, whoever sends it
+                let rx_len = self.data_len.get() + 3;
+                let tx_len = if self.publish.get() { rx_len } else { 2 };
+                let result = self.rx_buffer.take().map_or(Err(ErrorCode::BUSY), |rx| {
+                    self.uart.receive_buffer(rx, rx_len).map_err(|(err, rx)| {
+                        self.rx_buffer.replace(rx);
+                        err
+                    })
+                });
+                if let Err(err) = result {
+                    self.frame_done(Err(err));
+                    return;
+                }
+                let result = self.tx_buffer.take().map_or(Err(ErrorCode::BUSY), |tx| {
+                    tx[..tx_len].copy_from_slice(&self.frame.get()[..tx_len]);
+                    self.uart.transmit_buffer(tx, tx_len).map_err(|(err, tx)| {
+                        self.tx_buffer.replace(tx);
+                        err
+                    })
+                });
+                self.state.set(State::Frame);
+                match result {
+                    // The maximum frame time of LIN is 40% longer than the
+                    // nominal one
+                    Ok(()) => self.wait_bits(14 * rx_len as u32),
+                    Err(err) => {
+                        self.error.set(err);
+                        let _ = self.uart.receive_abort();
+                    }
+                }
+            }
+            State::Frame => {
+                self.error.set(ErrorCode::NOACK);
+                let _ = self.uart.receive_abort();
+            }
+            State::Idle => {}
+        }
+    }
+}
+
+impl<'a, U: uart::Uart<'a> + uart::Break, A: Alarm<'a>> uart::TransmitClient for Lin<'a, U, A> {
+    fn transmitted_buffer(
+        &self,
+        tx_buffer: &'static mut [u8],
+        _tx_len: usize,
+        rval: Result<(), ErrorCode>,
+    ) {
+        self.tx_buffer.replace(tx_buffer);
+        if let Err(err) = rval {
+            if self.state.get() == State::Frame {
+                self.error.set(err);
+                let _ = self.uart.receive_abort();
+            }
+        }
+    }
+}
+
+impl<'a, U: uart::Uart<'a> + uart::Break, A: Alarm<'a>> uart::ReceiveClient for Lin<'a, U, A> {
+    fn received_buffer(
+        &self,
+        rx_buffer: &'static mut [u8],
+        rx_len: usize,
+        rval: Result<(), ErrorCode>,
+        _error: uart::Error,
+    ) {
+        let _ = self.alarm.disarm();
+        let result = match rval {
+            Ok(()) => self.check_frame(&rx_buffer[..rx_len]),
+            Err(_) => Err(self.error.take().unwrap_or(ErrorCode::FAIL)),
+        };
+        self.error.clear();
+        self.rx_buffer.replace(rx_buffer);
+        if self.state.get() == State::Frame {
+            self.frame_done(result);
+        }
+    }
+}
+
+impl<'a, U: uart::Uart<'a> + uart::Break, A: Alarm<'a>> SyscallDriver for Lin<'a, U, A> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            // Publish a frame
+            1 => self.send_frame(processid, data1, data2, true).into(),
+
+            // Read the response of a slave
+            2 => self.send_frame(processid, data1, data2, false).into(),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
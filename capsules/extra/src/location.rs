@@ -0,0 +1,185 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Provides userspace with the fixes of a satellite positioning receiver.
+//!
+//! You need a device that provides the `hil::location::Location` trait, such
+//! as the `Nmea` capsule.
+//!
+//! The receiver is started while at least one process listens to fixes.
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! - Read-write allow 0: buffer the driver writes each fix into, as a
+//!   32-byte little-endian record:
+//!
+//!   | Offset | Type | Value                                          |
+//!   |--------|------|------------------------------------------------|
+//!   | 0      | i32  | latitude, 1e-7 degrees                         |
+//!   | 4      | i32  | longitude, 1e-7 degrees                        |
+//!   | 8      | i32  | altitude, millimeters                          |
+//!   | 12     | u32  | speed, millimeters per second                  |
+//!   | 16     | u32  | course, hundredths of a degree                 |
+//!   | 20     | u16  | year                                           |
+//!   | 22     | u8   | month, day, hour, minute, second               |
+//!   | 27     | u8   | flags: bit 0 altitude, bit 1 speed and bit 2   |
+//!   |        |      | course are valid                               |
+//!   | 28     | u16  | millisecond                                    |
+//!   | 30     | u8   | quality: 0 none, 1 GPS, 2 differential, 3 RTK, |
+//!   |        |      | 4 estimated                                    |
+//!   | 31     | u8   | satellites                                     |
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: send fixes to the calling process.
+//! - Command 2: stop sending fixes to the calling process.
+//!
+//! - Upcall 0: a fix, with its quality, the number of satellites, and the
+//!   number of bytes written in the buffer.
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::location::{Fix, Location, LocationClient};
+use kernel::processbuffer::WriteableProcessBuffer;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::Location as usize;
+
+/// Ids for read-write allow buffers
+mod rw_allow {
+    pub const FIX: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+/// Length of a fix record.
+pub const FIX_LEN: usize = 32;
+
+fn fix_record(fix: &Fix) -> [u8; FIX_LEN] {
+    let mut record = [0; FIX_LEN];
+    record[0..4].copy_from_slice(&fix.latitude.to_le_bytes());
+    record[4..8].copy_from_slice(&fix.longitude.to_le_bytes());
+    record[8..12].copy_from_slice(&fix.altitude.unwrap_or(0).to_le_bytes());
+    record[12..16].copy_from_slice(&fix.speed.unwrap_or(0).to_le_bytes());
+    record[16..20].copy_from_slice(&fix.course.unwrap_or(0).to_le_bytes());
+    record[20..22].copy_from_slice(&fix.time.year.to_le_bytes());
+    record[22] = fix.time.month;
+    record[23] = fix.time.day;
+    record[24] = fix.time.hour;
+    record[25] = fix.time.minute;
+    record[26] = fix.time.second;
+    record[27] = fix.altitude.is_some() as u8
+        | (fix.speed.is_some() as u8) << 1
+        | (fix.course.is_some() as u8) << 2;
+    record[28..30].copy_from_slice(&fix.time.millisecond.to_le_bytes());
+    record[30] = u32::from(fix.quality) as u8;
+    record[31] = fix.satellites;
+    record
+}
+
+#[derive(Default)]
+pub struct App {
+    listening: bool,
+}
+
+pub struct LocationDriver<'a> {
+    receiver: &'a dyn Location<'a>,
+    apps: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<{ rw_allow::COUNT }>>,
+}
+
+impl<'a> LocationDriver<'a> {
+    pub fn new(
+        receiver: &'a dyn Location<'a>,
+        grant: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<{ rw_allow::COUNT }>>,
+    ) -> LocationDriver<'a> {
+        LocationDriver {
+            receiver,
+            apps: grant,
+        }
+    }
+
+    fn listening(&self) -> bool {
+        self.apps
+            .iter()
+            .any(|app| app.enter(|app, _| app.listening))
+    }
+}
+
+impl LocationClient for LocationDriver<'_> {
+    fn fix(&self, fix: &Fix) {
+        let record = fix_record(fix);
+        self.apps.each(|_, app, kernel_data| {
+            if !app.listening {
+                return;
+            }
+            let written = kernel_data
+                .get_readwrite_processbuffer(rw_allow::FIX)
+                .and_then(|buffer| {
+                    buffer.mut_enter(|buffer| {
+                        let len = buffer.len().min(FIX_LEN);
+                        buffer[..len].copy_from_slice(&record[..len]);
+                        len
+                    })
+                })
+                .unwrap_or(0);
+            kernel_data
+                .schedule_upcall(
+                    0,
+                    (
+                        u32::from(fix.quality) as usize,
+                        fix.satellites as usize,
+                        written,
+                    ),
+                )
+                .ok();
+        });
+    }
+}
+
+impl SyscallDriver for LocationDriver<'_> {
+    fn command(
+        &self,
+        command_num: usize,
+        _data1: usize,
+        _data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            // Send fixes to the process
+            1 => {
+                if let Err(err) = self.apps.enter(processid, |app, _| app.listening = true) {
+                    return CommandReturn::failure(err.into());
+                }
+                match self.receiver.start() {
+                    Ok(()) => CommandReturn::success(),
+                    Err(err) => {
+                        let _ = self.apps.enter(processid, |app, _| app.listening = false);
+                        CommandReturn::failure(err)
+                    }
+                }
+            }
+
+            // Stop sending fixes to the process
+            2 => {
+                if let Err(err) = self.apps.enter(processid, |app, _| app.listening = false) {
+                    return CommandReturn::failure(err.into());
+                }
+                if !self.listening() {
+                    let _ = self.receiver.stop();
+                }
+                CommandReturn::success()
+            }
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
@@ -99,6 +99,7 @@ use crate::lsm303xx::{
     AccelerometerRegisters, Lsm303AccelDataRate, Lsm303MagnetoDataRate, Lsm303Range, Lsm303Scale,
     CTRL_REG1, CTRL_REG4, RANGE_FACTOR_X_Y, RANGE_FACTOR_Z, SCALE_FACTOR,
 };
+use crate::power_manager::PowerDomain;
 use capsules_core::driver;
 
 /// Syscall driver number.
@@ -727,6 +728,19 @@ impl<I: i2c::I2CDevice> SyscallDriver for Lsm303agrI2C<'_, I> {
     }
 }
 
+/// Powers the accelerometer down by setting its data rate to 0, and back up
+/// at the configured data rate. The magnetometer is not affected.
+impl<I: i2c::I2CDevice> PowerDomain for Lsm303agrI2C<'_, I> {
+    fn set_powered(&self, powered: bool) -> Result<(), ErrorCode> {
+        let data_rate = if powered {
+            self.accel_data_rate.get()
+        } else {
+            Lsm303AccelDataRate::Off
+        };
+        self.set_power_mode(data_rate, self.low_power.get())
+    }
+}
+
 impl<'a, I: i2c::I2CDevice> sensors::NineDof<'a> for Lsm303agrI2C<'a, I> {
     fn set_client(&self, nine_dof_client: &'a dyn sensors::NineDofClient) {
         self.nine_dof_client.replace(nine_dof_client);
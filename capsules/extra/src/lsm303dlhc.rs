@@ -96,14 +96,18 @@ use kernel::{ErrorCode, ProcessId};
 
 use crate::lsm303xx::{
     AccelerometerRegisters, Lsm303AccelDataRate, Lsm303MagnetoDataRate, Lsm303Range, Lsm303Scale,
-    CTRL_REG1, CTRL_REG4, RANGE_FACTOR_X_Y, RANGE_FACTOR_Z, SCALE_FACTOR,
+    CTRL_REG1, CTRL_REG3, CTRL_REG4, INT1_CFG, RANGE_FACTOR_X_Y, RANGE_FACTOR_Z, SCALE_FACTOR,
 };
+use crate::power_manager::PowerDomain;
 
 use capsules_core::driver;
 
 /// Syscall driver number.
 pub const DRIVER_NUM: usize = driver::NUM::Lsm303dlch as usize;
 
+/// The fastest I2C bus both the accelerometer and the magnetometer support.
+pub const MAX_BUS_SPEED: i2c::BusSpeed = i2c::BusSpeed::Fast400k;
+
 /// Register values
 const REGISTER_AUTO_INCREMENT: u8 = 0x80;
 
@@ -125,6 +129,14 @@ enum_from_primitive! {
 // Experimental
 const TEMP_OFFSET: i32 = 17;
 
+/// mg per LSB of INT1_THS_A for each scale
+const THRESHOLD_FACTOR: [u32; 4] = [16, 32, 62, 186];
+
+/// Accelerometer output data rates in Hz, one LSB of INT1_DURATION_A each,
+/// with the low power rate of the last one apart
+const DATA_RATE_HZ: [u32; 10] = [0, 1, 10, 25, 50, 100, 200, 400, 1620, 1344];
+const LOW_POWER_MAX_DATA_RATE_HZ: u32 = 5376;
+
 #[derive(Clone, Copy, PartialEq)]
 enum State {
     Idle,
@@ -136,6 +148,9 @@ enum State {
     SetRange,
     ReadTemperature,
     ReadMagnetometerXYZ,
+    SetFreeFallInterrupt,
+    SetFreeFallThreshold,
+    SetFreeFallConfig,
 }
 
 pub struct Lsm303dlhcI2C<'a, I: i2c::I2CDevice> {
@@ -150,6 +165,9 @@ pub struct Lsm303dlhcI2C<'a, I: i2c::I2CDevice> {
     accel_data_rate: Cell<Lsm303AccelDataRate>,
     low_power: Cell<bool>,
     temperature: Cell<bool>,
+    /// Free fall threshold in mg and duration in ms
+    free_fall: Cell<Option<(u32, u32)>>,
+    free_fall_pending: Cell<bool>,
     buffer: TakeCell<'static, [u8]>,
     nine_dof_client: OptionalCell<&'a dyn sensors::NineDofClient>,
     temperature_client: OptionalCell<&'a dyn sensors::TemperatureClient>,
@@ -180,6 +198,8 @@ impl<'a, I: i2c::I2CDevice> Lsm303dlhcI2C<'a, I> {
             accel_data_rate: Cell::new(Lsm303AccelDataRate::DataRate1Hz),
             low_power: Cell::new(false),
             temperature: Cell::new(false),
+            free_fall: Cell::new(None),
+            free_fall_pending: Cell::new(false),
             buffer: TakeCell::new(buffer),
             nine_dof_client: OptionalCell::empty(),
             temperature_client: OptionalCell::empty(),
@@ -215,6 +235,100 @@ impl<'a, I: i2c::I2CDevice> Lsm303dlhcI2C<'a, I> {
         }
     }
 
+    /// Raise the INT1 pin of the accelerometer while the acceleration is
+    /// below `threshold_mg` on all three axes for at least `duration_ms`,
+    /// which is the case when the sensor falls freely.
+    ///
+    /// The interrupt is set up as soon as the sensor is idle, after the
+    /// configuration if one is in progress, with the threshold and the
+    /// duration converted for the scale and the data rate configured then.
+    pub fn set_free_fall(&self, threshold_mg: u32, duration_ms: u32) -> Result<(), ErrorCode> {
+        self.free_fall.set(Some((threshold_mg, duration_ms)));
+        self.free_fall_pending.set(true);
+        if self.state.get() == State::Idle && !self.config_in_progress.get() {
+            self.set_free_fall_interrupt()
+        } else {
+            Ok(())
+        }
+    }
+
+    fn set_free_fall_interrupt(&self) -> Result<(), ErrorCode> {
+        if self.state.get() == State::Idle {
+            self.state.set(State::SetFreeFallInterrupt);
+            self.free_fall_pending.set(false);
+            self.buffer.take().map_or(Err(ErrorCode::NOMEM), |buf| {
+                buf[0] = AccelerometerRegisters::CTRL_REG3 as u8;
+                buf[1] = CTRL_REG3::I1_AOI1::SET.value;
+                self.i2c_accelerometer.enable();
+                if let Err((error, buf)) = self.i2c_accelerometer.write(buf, 2) {
+                    self.state.set(State::Idle);
+                    self.buffer.replace(buf);
+                    Err(error.into())
+                } else {
+                    Ok(())
+                }
+            })
+        } else {
+            Err(ErrorCode::BUSY)
+        }
+    }
+
+    fn set_free_fall_threshold(&self) -> Result<(), ErrorCode> {
+        if self.state.get() == State::Idle {
+            self.state.set(State::SetFreeFallThreshold);
+            let (threshold_mg, duration_ms) = self.free_fall.get().unwrap_or((0, 0));
+            let threshold = threshold_mg / THRESHOLD_FACTOR[self.accel_scale.get() as usize];
+            let data_rate = self.accel_data_rate.get();
+            let data_rate_hz = if data_rate == Lsm303AccelDataRate::Normal1344LowPower5376Hz
+                && self.low_power.get()
+            {
+                LOW_POWER_MAX_DATA_RATE_HZ
+            } else {
+                DATA_RATE_HZ[data_rate as usize]
+            };
+            let duration = duration_ms.saturating_mul(data_rate_hz) / 1000;
+            self.buffer.take().map_or(Err(ErrorCode::NOMEM), |buf| {
+                buf[0] = AccelerometerRegisters::INT1_THS_A as u8 | REGISTER_AUTO_INCREMENT;
+                buf[1] = threshold.clamp(1, 0x7F) as u8;
+                buf[2] = duration.min(0x7F) as u8;
+                self.i2c_accelerometer.enable();
+                if let Err((error, buf)) = self.i2c_accelerometer.write(buf, 3) {
+                    self.state.set(State::Idle);
+                    self.buffer.replace(buf);
+                    Err(error.into())
+                } else {
+                    Ok(())
+                }
+            })
+        } else {
+            Err(ErrorCode::BUSY)
+        }
+    }
+
+    fn set_free_fall_config(&self) -> Result<(), ErrorCode> {
+        if self.state.get() == State::Idle {
+            self.state.set(State::SetFreeFallConfig);
+            self.buffer.take().map_or(Err(ErrorCode::NOMEM), |buf| {
+                buf[0] = AccelerometerRegisters::INT1_CFG_A as u8;
+                buf[1] = (INT1_CFG::AOI::SET
+                    + INT1_CFG::ZLIE::SET
+                    + INT1_CFG::YLIE::SET
+                    + INT1_CFG::XLIE::SET)
+                    .value;
+                self.i2c_accelerometer.enable();
+                if let Err((error, buf)) = self.i2c_accelerometer.write(buf, 2) {
+                    self.state.set(State::Idle);
+                    self.buffer.replace(buf);
+                    Err(error.into())
+                } else {
+                    Ok(())
+                }
+            })
+        } else {
+            Err(ErrorCode::BUSY)
+        }
+    }
+
     fn is_present(&self) -> Result<(), ErrorCode> {
         if self.state.get() != State::Idle {
             self.state.set(State::IsPresent);
@@ -632,12 +746,41 @@ impl<I: i2c::I2CDevice> i2c::I2CClient for Lsm303dlhcI2C<'_, I> {
                 self.i2c_magnetometer.disable();
                 self.state.set(State::Idle);
             }
+            State::SetFreeFallInterrupt => {
+                self.buffer.replace(buffer);
+                self.i2c_accelerometer.disable();
+                self.state.set(State::Idle);
+                if status == Ok(()) {
+                    let _ = self.set_free_fall_threshold();
+                }
+            }
+            State::SetFreeFallThreshold => {
+                self.buffer.replace(buffer);
+                self.i2c_accelerometer.disable();
+                self.state.set(State::Idle);
+                if status == Ok(()) {
+                    let _ = self.set_free_fall_config();
+                }
+            }
+            State::SetFreeFallConfig => {
+                self.buffer.replace(buffer);
+                self.i2c_accelerometer.disable();
+                self.state.set(State::Idle);
+            }
             _ => {
                 self.i2c_magnetometer.disable();
                 self.i2c_accelerometer.disable();
                 self.buffer.replace(buffer);
             }
         }
+
+        // The free fall interrupt was set up while the sensor was busy
+        if self.free_fall_pending.get()
+            && self.state.get() == State::Idle
+            && !self.config_in_progress.get()
+        {
+            let _ = self.set_free_fall_interrupt();
+        }
     }
 }
 
@@ -757,6 +900,19 @@ impl<I: i2c::I2CDevice> SyscallDriver for Lsm303dlhcI2C<'_, I> {
     }
 }
 
+/// Powers the accelerometer down by setting its data rate to 0, and back up
+/// at the configured data rate. The magnetometer is not affected.
+impl<I: i2c::I2CDevice> PowerDomain for Lsm303dlhcI2C<'_, I> {
+    fn set_powered(&self, powered: bool) -> Result<(), ErrorCode> {
+        let data_rate = if powered {
+            self.accel_data_rate.get()
+        } else {
+            Lsm303AccelDataRate::Off
+        };
+        self.set_power_mode(data_rate, self.low_power.get())
+    }
+}
+
 impl<'a, I: i2c::I2CDevice> sensors::NineDof<'a> for Lsm303dlhcI2C<'a, I> {
     fn set_client(&self, nine_dof_client: &'a dyn sensors::NineDofClient) {
         self.nine_dof_client.replace(nine_dof_client);
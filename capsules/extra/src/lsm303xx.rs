@@ -100,6 +100,22 @@ register_bitfields![u8,
         /// X enable
         XEN OFFSET(0) NUMBITS(1) []
     ],
+    pub (crate) CTRL_REG3 [
+        /// Click interrupt on INT1
+        I1_CLICK OFFSET(7) NUMBITS(1) [],
+        /// AOI1 interrupt on INT1
+        I1_AOI1 OFFSET(6) NUMBITS(1) [],
+        /// AOI2 interrupt on INT1
+        I1_AOI2 OFFSET(5) NUMBITS(1) [],
+        /// Data ready 1 on INT1
+        I1_DRDY1 OFFSET(4) NUMBITS(1) [],
+        /// Data ready 2 on INT1
+        I1_DRDY2 OFFSET(3) NUMBITS(1) [],
+        /// FIFO watermark on INT1
+        I1_WTM OFFSET(2) NUMBITS(1) [],
+        /// FIFO overrun on INT1
+        I1_OVERRUN OFFSET(1) NUMBITS(1) []
+    ],
     pub (crate) CTRL_REG4 [
         /// Block Data update
         BDU OFFSET(7) NUMBITS(2) [],
@@ -111,12 +127,31 @@ register_bitfields![u8,
         HR OFFSET(3) NUMBITS(1) [],
         /// SPI Serial Interface
         SIM OFFSET(0) NUMBITS(1) []
+    ],
+    pub (crate) INT1_CFG [
+        /// AND combination of the interrupt events
+        AOI OFFSET(7) NUMBITS(1) [],
+        /// 6 direction detection
+        SIXD OFFSET(6) NUMBITS(1) [],
+        /// Z high event
+        ZHIE OFFSET(5) NUMBITS(1) [],
+        /// Z low event
+        ZLIE OFFSET(4) NUMBITS(1) [],
+        /// Y high event
+        YHIE OFFSET(3) NUMBITS(1) [],
+        /// Y low event
+        YLIE OFFSET(2) NUMBITS(1) [],
+        /// X high event
+        XHIE OFFSET(1) NUMBITS(1) [],
+        /// X low event
+        XLIE OFFSET(0) NUMBITS(1) []
     ]
 ];
 
 enum_from_primitive! {
     pub enum AccelerometerRegisters {
         CTRL_REG1 = 0x20,
+        CTRL_REG3 = 0x22,
         CTRL_REG4 = 0x23,
         OUT_X_L_A = 0x28,
         OUT_X_H_A = 0x29,
@@ -124,5 +159,9 @@ enum_from_primitive! {
         OUT_Y_H_A = 0x2B,
         OUT_Z_L_A = 0x2C,
         OUT_Z_H_A = 0x2D,
+        INT1_CFG_A = 0x30,
+        INT1_SRC_A = 0x31,
+        INT1_THS_A = 0x32,
+        INT1_DURATION_A = 0x33,
     }
 }
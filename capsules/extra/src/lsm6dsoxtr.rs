@@ -8,7 +8,40 @@
 //!
 //! May be used with NineDof and Temperature
 //!
-//! I2C Interface
+//! The sensor is connected over I2C, or over SPI through `Lsm6dsoxtrSpi`,
+//! which gives the driver the same register interface.
+//!
+//! The data rates and full scales of both sensors are configurable. The
+//! samples can also be batched in the FIFO of the sensor, and read by
+//! applications in bursts of up to `FIFO_BURST_WORDS` samples, so that they
+//! do not need to wake up for every sample at high data rates.
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: check that the sensor is present. Upcall 0 reports the
+//!   status and whether the sensor is present.
+//! - Command 2: set the accelerometer data rate, as the first argument, and
+//!   the low power mode, as the second argument.
+//! - Command 3: set the gyroscope data rate and low power mode, as command 2.
+//! - Command 4: set the accelerometer full scale, as a value of
+//!   `LSM6DSOXAccelRange`.
+//! - Command 5: set the gyroscope full scale, as a value of
+//!   `LSM6DSOXTRGyroRange`.
+//! - Command 6: batch samples in the FIFO, in continuous mode, with the
+//!   accelerometer batch data rate as the first argument and the gyroscope
+//!   batch data rate as the second argument. Rates are given as data rates,
+//!   and 0 does not batch the samples of a sensor.
+//! - Command 7: stop batching samples and empty the FIFO.
+//! - Command 8: read samples from the FIFO into read-write allow 0. Upcall 0
+//!   reports the status, the number of samples read and the number of
+//!   samples left in the FIFO. Each sample takes `FIFO_WORD_LEN` bytes: the
+//!   tag, of which the upper 5 bits identify the sensor, followed by the raw
+//!   X, Y and Z values as little endian `i16`.
+//!
+//! Commands 2 to 8 report their completion with upcall 0, with the status
+//! as its first argument.
 //!
 //! Datasheet: <https://www.digikey.sg/product-detail/en/stmicroelectronics/LSM6DSOXTR/497-18367-1-ND/9841887>
 //!
@@ -25,6 +58,8 @@ use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
 use kernel::hil::i2c;
 use kernel::hil::sensors;
 use kernel::hil::sensors::{NineDof, NineDofClient};
+use kernel::hil::spi::{self, SpiMasterClient, SpiMasterDevice};
+use kernel::processbuffer::{ReadableProcessBuffer, WriteableProcessBuffer};
 use kernel::syscall::{CommandReturn, SyscallDriver};
 use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::utilities::registers::LocalRegisterCopy;
@@ -37,6 +72,22 @@ use kernel::utilities::registers::register_bitfields;
 pub const CHIP_ID: u8 = 0x6C;
 pub const ACCELEROMETER_BASE_ADDRESS: u8 = 0x6A;
 
+/// Length of a FIFO word: the tag and the X, Y and Z values.
+pub const FIFO_WORD_LEN: usize = 7;
+/// Maximum number of FIFO words read in one burst.
+pub const FIFO_BURST_WORDS: usize = 16;
+/// Length of the buffers, which hold a FIFO burst and, over SPI, the byte
+/// transferred with the register address.
+pub const BUFFER_LEN: usize = 1 + FIFO_WORD_LEN * FIFO_BURST_WORDS;
+
+/// Tag of FIFO words holding gyroscope samples.
+pub const FIFO_TAG_GYROSCOPE: u8 = 0x01;
+/// Tag of FIFO words holding accelerometer samples.
+pub const FIFO_TAG_ACCELEROMETER: u8 = 0x02;
+
+/// The register address is ORed with this bit for reads over SPI.
+const SPI_READ: u8 = 0x80;
+
 enum_from_primitive! {
     #[derive(Clone, Copy, PartialEq)]
     pub enum LSM6DSOXGyroDataRate {
@@ -113,6 +164,17 @@ enum_from_primitive! {
     }
 }
 
+enum_from_primitive! {
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum LSM6DSOXTRFifoRegisters {
+        FIFO_CTRL3 = 0x09,
+        FIFO_CTRL4 = 0x0A,
+        FIFO_STATUS1 = 0x3A,
+        FIFO_STATUS2 = 0x3B,
+        FIFO_DATA_OUT_TAG = 0x78
+    }
+}
+
 pub const SCALE_FACTOR_ACCEL: [u16; 4] = [61, 488, 122, 244];
 pub const SCALE_FACTOR_GYRO: [u16; 4] = [875, 1750, 3500, 7000];
 pub const TEMP_SENSITIVITY_FACTOR: u16 = 256;
@@ -156,6 +218,25 @@ register_bitfields![u8,
     ],
 ];
 
+register_bitfields![u8,
+    pub (crate) FIFO_CTRL3 [
+        /// Batch data rate of the gyroscope
+        BDR_GY OFFSET(4) NUMBITS(4) [],
+
+        /// Batch data rate of the accelerometer
+        BDR_XL OFFSET(0) NUMBITS(4) [],
+    ],
+];
+
+register_bitfields![u8,
+    pub (crate) FIFO_CTRL4 [
+        FIFO_MODE OFFSET(0) NUMBITS(3) [
+            Bypass = 0,
+            Continuous = 6,
+        ],
+    ],
+];
+
 #[derive(Clone, Copy, PartialEq, Debug)]
 enum State {
     Idle,
@@ -165,6 +246,9 @@ enum State {
     ReadTemperature,
     SetPowerModeAccel,
     SetPowerModeGyro,
+    SetFifoMode,
+    ReadFifoStatus,
+    ReadFifo(usize, usize),
 }
 #[derive(Default)]
 pub struct App {}
@@ -183,7 +267,7 @@ pub struct Lsm6dsoxtrI2C<'a, I: i2c::I2CDevice> {
     temperature_client: OptionalCell<&'a dyn sensors::TemperatureClient>,
     is_present: Cell<bool>,
     buffer: TakeCell<'static, [u8]>,
-    apps: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    apps: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<1>>,
     syscall_process: OptionalCell<ProcessId>,
 }
 
@@ -191,7 +275,7 @@ impl<'a, I: i2c::I2CDevice> Lsm6dsoxtrI2C<'a, I> {
     pub fn new(
         i2c: &'a I,
         buffer: &'static mut [u8],
-        grant: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+        grant: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<1>>,
     ) -> Lsm6dsoxtrI2C<'a, I> {
         Lsm6dsoxtrI2C {
             i2c: i2c,
@@ -267,11 +351,13 @@ impl<'a, I: i2c::I2CDevice> Lsm6dsoxtrI2C<'a, I> {
         if self.state.get() == State::Idle {
             self.buffer.take().map_or(Err(ErrorCode::NOMEM), |buf| {
                 self.state.set(State::SetPowerModeAccel);
+                self.accel_data_rate.set(data_rate);
+                self.low_power.set(low_power);
                 buf[0] = LSM6DSOXTRAccelRegisters::CTRL1_XL as u8;
                 let mut reg: LocalRegisterCopy<u8, CTRL1_XL::Register> = LocalRegisterCopy::new(0);
                 reg.modify(CTRL1_XL::ODR.val(data_rate as u8));
                 reg.modify(CTRL1_XL::LPF.val(low_power as u8));
-                reg.modify(CTRL1_XL::FS.val(0));
+                reg.modify(CTRL1_XL::FS.val(self.accel_scale.get() as u8));
 
                 buf[1] = reg.get();
                 self.i2c.enable();
@@ -297,11 +383,13 @@ impl<'a, I: i2c::I2CDevice> Lsm6dsoxtrI2C<'a, I> {
         if self.state.get() == State::Idle {
             self.buffer.take().map_or(Err(ErrorCode::NOMEM), |buf| {
                 self.state.set(State::SetPowerModeGyro);
+                self.gyro_data_rate.set(data_rate);
+                self.low_power.set(low_power);
                 buf[0] = LSM6DSOXTRGyroRegisters::CTRL2_G as u8;
                 let mut reg: LocalRegisterCopy<u8, CTRL2_G::Register> = LocalRegisterCopy::new(0);
                 reg.modify(CTRL2_G::ODR.val(data_rate as u8));
                 reg.modify(CTRL2_G::LPF.val(low_power as u8));
-                reg.modify(CTRL2_G::FS.val(0));
+                reg.modify(CTRL2_G::FS.val(self.gyro_range.get() as u8));
 
                 buf[1] = reg.get();
                 self.i2c.enable();
@@ -319,6 +407,112 @@ impl<'a, I: i2c::I2CDevice> Lsm6dsoxtrI2C<'a, I> {
         }
     }
 
+    /// Set the full scale of the accelerometer, keeping its data rate.
+    pub fn set_accelerometer_scale(&self, scale: LSM6DSOXAccelRange) -> Result<(), ErrorCode> {
+        if self.state.get() != State::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        let previous = self.accel_scale.replace(scale);
+        self.set_accelerometer_power_mode(self.accel_data_rate.get(), self.low_power.get())
+            .map_err(|error| {
+                self.accel_scale.set(previous);
+                error
+            })
+    }
+
+    /// Set the full scale of the gyroscope, keeping its data rate.
+    pub fn set_gyroscope_scale(&self, range: LSM6DSOXTRGyroRange) -> Result<(), ErrorCode> {
+        if self.state.get() != State::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        let previous = self.gyro_range.replace(range);
+        self.set_gyroscope_power_mode(self.gyro_data_rate.get(), self.low_power.get())
+            .map_err(|error| {
+                self.gyro_range.set(previous);
+                error
+            })
+    }
+
+    /// Batch the samples of the sensors in the FIFO at the given rates, in
+    /// continuous mode, or stop batching and empty the FIFO if `batch` is
+    /// `None`. A rate of `SHUTDOWN` does not batch the samples of a sensor.
+    pub fn set_fifo_mode(
+        &self,
+        batch: Option<(LSM6DSOXAccelDataRate, LSM6DSOXGyroDataRate)>,
+    ) -> Result<(), ErrorCode> {
+        if self.state.get() != State::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        self.buffer.take().map_or(Err(ErrorCode::NOMEM), |buf| {
+            self.state.set(State::SetFifoMode);
+            let mut ctrl3: LocalRegisterCopy<u8, FIFO_CTRL3::Register> = LocalRegisterCopy::new(0);
+            let mut ctrl4: LocalRegisterCopy<u8, FIFO_CTRL4::Register> = LocalRegisterCopy::new(0);
+            if let Some((accel_rate, gyro_rate)) = batch {
+                ctrl3.modify(FIFO_CTRL3::BDR_XL.val(accel_rate as u8));
+                ctrl3.modify(FIFO_CTRL3::BDR_GY.val(gyro_rate as u8));
+                ctrl4.modify(FIFO_CTRL4::FIFO_MODE::Continuous);
+            }
+            // FIFO_CTRL4 follows FIFO_CTRL3, so both are written at once.
+            buf[0] = LSM6DSOXTRFifoRegisters::FIFO_CTRL3 as u8;
+            buf[1] = ctrl3.get();
+            buf[2] = ctrl4.get();
+            self.i2c.enable();
+            if let Err((error, buf)) = self.i2c.write(buf, 3) {
+                self.state.set(State::Idle);
+                self.i2c.disable();
+                self.buffer.replace(buf);
+                Err(error.into())
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    /// Read the number of samples in the FIFO, before reading them in a
+    /// burst.
+    fn read_fifo(&self) -> Result<(), ErrorCode> {
+        if self.state.get() != State::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        self.buffer.take().map_or(Err(ErrorCode::NOMEM), |buf| {
+            self.state.set(State::ReadFifoStatus);
+            buf[0] = LSM6DSOXTRFifoRegisters::FIFO_STATUS1 as u8;
+            self.i2c.enable();
+            if let Err((error, buf)) = self.i2c.write_read(buf, 1, 2) {
+                self.state.set(State::Idle);
+                self.i2c.disable();
+                self.buffer.replace(buf);
+                Err(error.into())
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    /// Number of FIFO words that fit in the read-write allow of the process.
+    fn process_fifo_capacity(&self) -> usize {
+        self.syscall_process.map_or(0, |pid| {
+            self.apps
+                .enter(*pid, |_app, kernel_data| {
+                    kernel_data
+                        .get_readwrite_processbuffer(0)
+                        .map_or(0, |buffer| buffer.len() / FIFO_WORD_LEN)
+                })
+                .unwrap_or(0)
+        })
+    }
+
+    /// Complete the syscall in progress with upcall 0.
+    fn schedule_upcall(&self, status: Result<(), ErrorCode>, arg1: usize, arg2: usize) {
+        self.syscall_process.take().map(|pid| {
+            let _res = self.apps.enter(pid, |_app, upcalls| {
+                upcalls
+                    .schedule_upcall(0, (into_statuscode(status), arg1, arg2))
+                    .ok();
+            });
+        });
+    }
+
     pub fn read_acceleration_xyz(&self) -> Result<(), ErrorCode> {
         if self.state.get() == State::Idle {
             self.state.set(State::ReadAccelerationXYZ);
@@ -529,6 +723,69 @@ impl<I: i2c::I2CDevice> i2c::I2CClient for Lsm6dsoxtrI2C<'_, I> {
                 });
             }
 
+            State::SetFifoMode => {
+                self.buffer.replace(buffer);
+                self.i2c.disable();
+                self.state.set(State::Idle);
+                self.schedule_upcall(status.map_err(|i2c_error| i2c_error.into()), 0, 0);
+            }
+
+            State::ReadFifoStatus => {
+                self.state.set(State::Idle);
+                if let Err(i2c_error) = status {
+                    self.buffer.replace(buffer);
+                    self.i2c.disable();
+                    self.schedule_upcall(Err(i2c_error.into()), 0, 0);
+                    return;
+                }
+                let level = buffer[0] as usize | ((buffer[1] as usize & 0x03) << 8);
+                let words = level
+                    .min(FIFO_BURST_WORDS)
+                    .min(self.process_fifo_capacity());
+                if words == 0 {
+                    self.buffer.replace(buffer);
+                    self.i2c.disable();
+                    self.schedule_upcall(Ok(()), 0, level);
+                    return;
+                }
+                // The address wraps from the last FIFO data register back to
+                // the tag, so the words are read in a single burst.
+                self.state.set(State::ReadFifo(words, level - words));
+                buffer[0] = LSM6DSOXTRFifoRegisters::FIFO_DATA_OUT_TAG as u8;
+                if let Err((i2c_error, buffer)) =
+                    self.i2c.write_read(buffer, 1, words * FIFO_WORD_LEN)
+                {
+                    self.state.set(State::Idle);
+                    self.buffer.replace(buffer);
+                    self.i2c.disable();
+                    self.schedule_upcall(Err(i2c_error.into()), 0, level);
+                }
+            }
+
+            State::ReadFifo(words, remaining) => {
+                if status == Ok(()) {
+                    self.syscall_process.map(|pid| {
+                        let _ = self.apps.enter(*pid, |_app, kernel_data| {
+                            let _ = kernel_data.get_readwrite_processbuffer(0).and_then(|dest| {
+                                dest.mut_enter(|dest| {
+                                    let len = (words * FIFO_WORD_LEN).min(dest.len());
+                                    dest[0..len].copy_from_slice(&buffer[0..len]);
+                                })
+                            });
+                        });
+                    });
+                }
+                self.buffer.replace(buffer);
+                self.i2c.disable();
+                self.state.set(State::Idle);
+                match status {
+                    Ok(()) => self.schedule_upcall(Ok(()), words, remaining),
+                    Err(i2c_error) => {
+                        self.schedule_upcall(Err(i2c_error.into()), 0, words + remaining)
+                    }
+                }
+            }
+
             State::SetPowerModeGyro => {
                 self.buffer.replace(buffer);
                 self.i2c.disable();
@@ -553,6 +810,19 @@ impl<I: i2c::I2CDevice> i2c::I2CClient for Lsm6dsoxtrI2C<'_, I> {
     }
 }
 
+impl<I: i2c::I2CDevice> Lsm6dsoxtrI2C<'_, I> {
+    /// Return the result of a command which completes with upcall 0.
+    fn start_syscall(&self, result: Result<(), ErrorCode>, process_id: ProcessId) -> CommandReturn {
+        match result {
+            Ok(()) => {
+                self.syscall_process.set(process_id);
+                CommandReturn::success()
+            }
+            Err(error) => CommandReturn::failure(error),
+        }
+    }
+}
+
 impl<I: i2c::I2CDevice> SyscallDriver for Lsm6dsoxtrI2C<'_, I> {
     fn command(
         &self,
@@ -624,6 +894,45 @@ impl<I: i2c::I2CDevice> SyscallDriver for Lsm6dsoxtrI2C<'_, I> {
                     CommandReturn::failure(ErrorCode::BUSY)
                 }
             }
+            // Set Accelerometer Scale
+            4 => match LSM6DSOXAccelRange::from_usize(data1) {
+                Some(scale) => self.start_syscall(self.set_accelerometer_scale(scale), process_id),
+                None => CommandReturn::failure(ErrorCode::INVAL),
+            },
+            // Set Gyroscope Scale
+            5 => match LSM6DSOXTRGyroRange::from_usize(data1) {
+                Some(range) => self.start_syscall(self.set_gyroscope_scale(range), process_id),
+                None => CommandReturn::failure(ErrorCode::INVAL),
+            },
+            // Batch Samples in the FIFO
+            6 => match (
+                LSM6DSOXAccelDataRate::from_usize(data1),
+                LSM6DSOXGyroDataRate::from_usize(data2),
+            ) {
+                (Some(accel_rate), Some(gyro_rate)) => self.start_syscall(
+                    self.set_fifo_mode(Some((accel_rate, gyro_rate))),
+                    process_id,
+                ),
+                _ => CommandReturn::failure(ErrorCode::INVAL),
+            },
+            // Stop Batching Samples
+            7 => self.start_syscall(self.set_fifo_mode(None), process_id),
+            // Read the FIFO
+            8 => {
+                // The capacity of the allow is checked once the process is
+                // known.
+                if self.state.get() != State::Idle {
+                    return CommandReturn::failure(ErrorCode::BUSY);
+                }
+                self.syscall_process.set(process_id);
+                match self.read_fifo() {
+                    Ok(()) => CommandReturn::success(),
+                    Err(error) => {
+                        self.syscall_process.clear();
+                        CommandReturn::failure(error)
+                    }
+                }
+            }
 
             _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
         }
@@ -656,3 +965,115 @@ impl<'a, I: i2c::I2CDevice> sensors::TemperatureDriver<'a> for Lsm6dsoxtrI2C<'a,
         self.read_temperature()
     }
 }
+
+/// Register access to the sensor over SPI, for `Lsm6dsoxtrI2C`.
+///
+/// Register reads are SPI transfers starting with the address ORed with
+/// the read bit, during which the data is received after the first byte.
+/// The data is copied back to the buffer of the driver, as an I2C read
+/// would.
+pub struct Lsm6dsoxtrSpi<'a, S: SpiMasterDevice<'a>> {
+    spi: &'a S,
+    read_buffer: TakeCell<'static, [u8]>,
+    read_len: Cell<Option<usize>>,
+    client: OptionalCell<&'a dyn i2c::I2CClient>,
+}
+
+impl<'a, S: SpiMasterDevice<'a>> Lsm6dsoxtrSpi<'a, S> {
+    pub fn new(spi: &'a S, read_buffer: &'static mut [u8]) -> Lsm6dsoxtrSpi<'a, S> {
+        Lsm6dsoxtrSpi {
+            spi,
+            read_buffer: TakeCell::new(read_buffer),
+            read_len: Cell::new(None),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn i2c::I2CClient) {
+        self.client.set(client);
+    }
+
+    /// The sensor samples on the rising edge of the clock, which idles
+    /// high.
+    pub fn configure(&self, rate: u32) -> Result<(), ErrorCode> {
+        self.spi.configure(
+            spi::ClockPolarity::IdleHigh,
+            spi::ClockPhase::SampleTrailing,
+            rate,
+        )
+    }
+}
+
+impl<'a, S: SpiMasterDevice<'a>> i2c::I2CDevice for Lsm6dsoxtrSpi<'a, S> {
+    fn enable(&self) {}
+
+    fn disable(&self) {}
+
+    fn write_read(
+        &self,
+        data: &'static mut [u8],
+        write_len: usize,
+        read_len: usize,
+    ) -> Result<(), (i2c::Error, &'static mut [u8])> {
+        // Only the register address is written before reading.
+        if write_len != 1 || data.len() <= read_len {
+            return Err((i2c::Error::NotSupported, data));
+        }
+        let read_buffer = match self.read_buffer.take() {
+            Some(read_buffer) if read_buffer.len() > read_len => read_buffer,
+            Some(read_buffer) => {
+                self.read_buffer.replace(read_buffer);
+                return Err((i2c::Error::NotSupported, data));
+            }
+            None => return Err((i2c::Error::Busy, data)),
+        };
+        data[0] |= SPI_READ;
+        self.read_len.set(Some(read_len));
+        self.spi
+            .read_write_bytes(data, Some(read_buffer), read_len + 1)
+            .map_err(|(_, data, read_buffer)| {
+                self.read_len.set(None);
+                read_buffer.map(|read_buffer| self.read_buffer.replace(read_buffer));
+                (i2c::Error::Busy, data)
+            })
+    }
+
+    fn write(
+        &self,
+        data: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (i2c::Error, &'static mut [u8])> {
+        self.read_len.set(None);
+        self.spi
+            .read_write_bytes(data, None, len)
+            .map_err(|(_, data, _)| (i2c::Error::Busy, data))
+    }
+
+    fn read(
+        &self,
+        buffer: &'static mut [u8],
+        _len: usize,
+    ) -> Result<(), (i2c::Error, &'static mut [u8])> {
+        // Reads always start with the register address.
+        Err((i2c::Error::NotSupported, buffer))
+    }
+}
+
+impl<'a, S: SpiMasterDevice<'a>> SpiMasterClient for Lsm6dsoxtrSpi<'a, S> {
+    fn read_write_done(
+        &self,
+        write_buffer: &'static mut [u8],
+        read_buffer: Option<&'static mut [u8]>,
+        _len: usize,
+        status: Result<(), ErrorCode>,
+    ) {
+        if let (Some(read_len), Some(read_buffer)) = (self.read_len.take(), read_buffer) {
+            write_buffer[0..read_len].copy_from_slice(&read_buffer[1..=read_len]);
+            self.read_buffer.replace(read_buffer);
+        }
+        // SPI transfers only fail if the bus is not available.
+        let status = status.map_err(|_| i2c::Error::Busy);
+        self.client
+            .map(|client| client.command_complete(write_buffer, status));
+    }
+}
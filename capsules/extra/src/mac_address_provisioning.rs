@@ -0,0 +1,289 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Provisions the MAC address of network interfaces.
+//!
+//! The address is chosen, in order of preference, from:
+//!
+//! 1. An override stored in the key-value store under `KEY`, for boards
+//!    without programmed fuses or that need a different address.
+//! 2. A `MacAddressSource`, usually the fuses of the chip.
+//! 3. A locally administered address derived from the unique ID of the
+//!    chip, if a `DeviceId` is given.
+//!
+//! The chosen address is then given to every interface, such as an Ethernet
+//! controller or a WiFi radio, through `hil::mac_address::Configure`.
+//!
+//! ```text
+//!  +------------+   +------------+   +-----------+
+//!  |  KV store  |   |   Fuses    |   | Device ID |
+//!  +------------+   +------------+   +-----------+
+//!         \               |               /
+//!          +---------------------------------+
+//!          |  MacAddressProvisioning (this)  |
+//!          +---------------------------------+
+//!                  /                 \
+//!           +------------+    +------------+
+//!           |  Ethernet  |    |    WiFi    |
+//!           +------------+    +------------+
+//! ```
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let mac_provisioning =
+//!     components::mac_address_provisioning::MacAddressProvisioningComponent::new(
+//!         kv_store,
+//!         Some(&peripherals.ocotp),
+//!         Some(&peripherals.ocotp),
+//!     )
+//!     .finalize(components::mac_address_provisioning_component_static!(
+//!         KVStoreType,
+//!         KeyType,
+//!         &peripherals.enet,
+//!     ));
+//! let _ = mac_provisioning.start();
+//! ```
+
+use core::cell::Cell;
+
+use crate::kv_store::KVStore;
+use kernel::hil::device_id::DeviceId;
+use kernel::hil::kv_system::{self, KVSystem};
+use kernel::hil::mac_address::{Configure, MacAddress, MacAddressSource};
+use kernel::storage_permissions::StoragePermissions;
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+/// Key of the MAC address override in the key-value store.
+pub const KEY: &[u8] = b"mac-address";
+/// Length of the value buffer: the address and the header the store prepends
+/// to it.
+pub const VALUE_BUF_LEN: usize = 16;
+
+const MAX_ID_LEN: usize = 16;
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    /// Reading the override at startup.
+    Loading,
+    /// Removing the previous override before storing `pending`, or for good
+    /// if there is nothing pending.
+    Deleting,
+    /// Storing `pending` as the new override.
+    Storing,
+}
+
+pub struct MacAddressProvisioning<
+    'a,
+    K: KVSystem<'a> + KVSystem<'a, K = T>,
+    T: 'static + kv_system::KeyType,
+> {
+    kv: &'a KVStore<'a, K, T>,
+    fuses: Option<&'a dyn MacAddressSource>,
+    device_id: Option<&'a dyn DeviceId>,
+    interfaces: &'a [&'a dyn Configure],
+    permissions: StoragePermissions,
+    key: TakeCell<'static, [u8]>,
+    value: TakeCell<'static, [u8]>,
+    state: Cell<State>,
+    /// Override being stored.
+    pending: OptionalCell<MacAddress>,
+    /// Address given to the interfaces.
+    current: OptionalCell<MacAddress>,
+}
+
+impl<'a, K: KVSystem<'a> + KVSystem<'a, K = T>, T: 'static + kv_system::KeyType>
+    MacAddressProvisioning<'a, K, T>
+{
+    pub fn new(
+        kv: &'a KVStore<'a, K, T>,
+        fuses: Option<&'a dyn MacAddressSource>,
+        device_id: Option<&'a dyn DeviceId>,
+        interfaces: &'a [&'a dyn Configure],
+        permissions: StoragePermissions,
+        key: &'static mut [u8],
+        value: &'static mut [u8],
+    ) -> Self {
+        Self {
+            kv,
+            fuses,
+            device_id,
+            interfaces,
+            permissions,
+            key: TakeCell::new(key),
+            value: TakeCell::new(value),
+            state: Cell::new(State::Idle),
+            pending: OptionalCell::empty(),
+            current: OptionalCell::empty(),
+        }
+    }
+
+    /// Read the override from the key-value store and configure the
+    /// interfaces.
+    ///
+    /// Until the store answers, the interfaces already use the address from
+    /// the fuses or the device ID.
+    pub fn start(&self) -> Result<(), ErrorCode> {
+        self.apply(None);
+        self.begin(State::Loading)
+    }
+
+    /// The address given to the interfaces, if any.
+    pub fn mac_address(&self) -> Option<MacAddress> {
+        self.current.extract()
+    }
+
+    /// Store `address` as the override and configure the interfaces with it.
+    pub fn set_override(&self, address: MacAddress) -> Result<(), ErrorCode> {
+        if !address.is_valid_unicast() {
+            return Err(ErrorCode::INVAL);
+        }
+        if self.state.get() != State::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        self.pending.set(address);
+        self.begin(State::Deleting).map_err(|e| {
+            self.pending.clear();
+            e
+        })
+    }
+
+    /// Remove the override and go back to the address from the fuses.
+    pub fn clear_override(&self) -> Result<(), ErrorCode> {
+        if self.state.get() != State::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        self.begin(State::Deleting)
+    }
+
+    /// The address to use when there is no override.
+    fn default_address(&self) -> Option<MacAddress> {
+        self.fuses
+            .and_then(|fuses| fuses.mac_address())
+            .filter(|mac| mac.is_valid_unicast())
+            .or_else(|| {
+                self.device_id.and_then(|id| {
+                    let mut buf = [0; MAX_ID_LEN];
+                    id.read_id(&mut buf)
+                        .ok()
+                        .map(|len| MacAddress::new_local(&buf[..len]))
+                })
+            })
+    }
+
+    /// Configure the interfaces with `address`, or the default address.
+    fn apply(&self, address: Option<MacAddress>) {
+        if let Some(address) = address.or_else(|| self.default_address()) {
+            self.current.set(address);
+            for interface in self.interfaces.iter() {
+                let _ = interface.set_mac_address(address);
+            }
+        }
+    }
+
+    fn begin(&self, state: State) -> Result<(), ErrorCode> {
+        let key = self.key.take().ok_or(ErrorCode::BUSY)?;
+        let result = match state {
+            State::Loading => self.value.take().map_or(Err(ErrorCode::BUSY), |value| {
+                self.kv
+                    .get(key, value, self.permissions)
+                    .map_err(|(key, value, e)| {
+                        self.key.replace(key);
+                        self.value.replace(value);
+                        e.err().unwrap_or(ErrorCode::FAIL)
+                    })
+            }),
+            State::Deleting => self.kv.delete(key, self.permissions).map_err(|(key, e)| {
+                self.key.replace(key);
+                e.err().unwrap_or(ErrorCode::FAIL)
+            }),
+            State::Storing => self.value.take().map_or(Err(ErrorCode::BUSY), |value| {
+                let address = self.pending.extract().unwrap_or_default();
+                value[..6].copy_from_slice(address.as_bytes());
+                self.kv
+                    .set(key, value, 6, self.permissions)
+                    .map_err(|(key, value, e)| {
+                        self.key.replace(key);
+                        self.value.replace(value);
+                        e.err().unwrap_or(ErrorCode::FAIL)
+                    })
+            }),
+            State::Idle => Ok(()),
+        };
+        if result.is_ok() {
+            self.state.set(state);
+        }
+        result
+    }
+}
+
+impl<'a, K: KVSystem<'a> + KVSystem<'a, K = T>, T: 'static + kv_system::KeyType>
+    kv_system::StoreClient<T> for MacAddressProvisioning<'a, K, T>
+{
+    fn get_complete(
+        &self,
+        result: Result<(), ErrorCode>,
+        key: &'static mut [u8],
+        ret_buf: &'static mut [u8],
+    ) {
+        self.value.replace(ret_buf);
+
+        // The store reports some failures to delete through `get_complete`.
+        if self.state.get() == State::Deleting {
+            self.delete_complete(result, key);
+            return;
+        }
+
+        self.key.replace(key);
+        self.state.set(State::Idle);
+
+        let address = self
+            .value
+            .map(|value| {
+                result
+                    .ok()
+                    .and_then(|()| value.get(..6))
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .map(MacAddress::new)
+            })
+            .flatten()
+            .filter(|mac| mac.is_valid_unicast());
+        if address.is_some() {
+            self.apply(address);
+        }
+    }
+
+    fn set_complete(
+        &self,
+        result: Result<(), ErrorCode>,
+        key: &'static mut [u8],
+        value: &'static mut [u8],
+    ) {
+        self.key.replace(key);
+        self.value.replace(value);
+        self.state.set(State::Idle);
+
+        let address = self.pending.take();
+        if result.is_ok() {
+            self.apply(address);
+        }
+    }
+
+    fn delete_complete(&self, _result: Result<(), ErrorCode>, key: &'static mut [u8]) {
+        self.key.replace(key);
+        self.state.set(State::Idle);
+
+        // A missing key is not an error here: there was no override yet.
+        if self.pending.is_some() {
+            if self.begin(State::Storing).is_err() {
+                self.pending.clear();
+            }
+        } else {
+            self.apply(None);
+        }
+    }
+}
@@ -0,0 +1,498 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Provides userspace with a Modbus RTU slave.
+//!
+//! A process shares its holding registers and input registers with the
+//! capsule, and chooses the slave address. The capsule answers the requests
+//! of the Modbus master, typically a PLC on an RS-485 bus, with these
+//! registers:
+//!
+//! - 0x03: Read Holding Registers
+//! - 0x04: Read Input Registers
+//! - 0x06: Write Single Register
+//! - 0x10: Write Multiple Registers
+//!
+//! Other functions are answered with the Illegal Function exception, and
+//! registers outside the buffers with the Illegal Data Address exception.
+//! Writes to the broadcast address 0 are done without a response.
+//!
+//! Frames end with a silence of 3.5 characters, timed with an alarm, and are
+//! checked with their CRC16. The UART is used only by this capsule. The
+//! RS-485 transceiver must switch direction by itself, or with the hardware
+//! flow control of the UART.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let modbus = components::modbus_rtu::ModbusRtuComponent::new(
+//!     board_kernel,
+//!     capsules_extra::modbus_rtu::DRIVER_NUM,
+//!     &peripherals.uart1,
+//!     mux_alarm,
+//!     kernel::hil::uart::Parameters {
+//!         baud_rate: 19200,
+//!         width: kernel::hil::uart::Width::Eight,
+//!         parity: kernel::hil::uart::Parity::Even,
+//!         stop_bits: kernel::hil::uart::StopBits::One,
+//!         hw_flow_control: false,
+//!     },
+//! )
+//! .finalize(components::modbus_rtu_component_static!(
+//!     rp2040::uart::Uart<'static>,
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! Registers are 16 bit values in the native byte order of the process, the
+//! register `n` at offset `2 * n` of its buffer.
+//!
+//! - Read-only allow 0: input registers.
+//! - Read-write allow 0: holding registers.
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: answer requests to the slave address `data1` (1 to 247).
+//!   Only one process can be the slave, others get RESERVE.
+//! - Command 2: stop answering requests.
+//!
+//! - Upcall 0: the master wrote holding registers, with the first register
+//!   and the number of registers written.
+
+use core::cell::Cell;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::time::{Alarm, AlarmClient, ConvertTicks};
+use kernel::hil::uart;
+use kernel::processbuffer::{ReadableProcessBuffer, WriteableProcessBuffer};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::ModbusRtu as usize;
+
+/// Ids for read-only allow buffers
+mod ro_allow {
+    pub const INPUT_REGISTERS: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+/// Ids for read-write allow buffers
+mod rw_allow {
+    pub const HOLDING_REGISTERS: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+/// Largest RTU frame, with the address and the CRC.
+pub const FRAME_LEN: usize = 256;
+
+const READ_HOLDING_REGISTERS: u8 = 0x03;
+const READ_INPUT_REGISTERS: u8 = 0x04;
+const WRITE_SINGLE_REGISTER: u8 = 0x06;
+const WRITE_MULTIPLE_REGISTERS: u8 = 0x10;
+
+const ILLEGAL_FUNCTION: u8 = 0x01;
+const ILLEGAL_DATA_ADDRESS: u8 = 0x02;
+const ILLEGAL_DATA_VALUE: u8 = 0x03;
+const SLAVE_DEVICE_FAILURE: u8 = 0x04;
+
+const MAX_READ_REGISTERS: usize = 125;
+const MAX_WRITE_REGISTERS: usize = 123;
+
+/// CRC16 of RTU frames, sent low byte first.
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u16, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+#[derive(Default)]
+pub struct App;
+
+pub struct ModbusRtu<'a, U: uart::Uart<'a>, A: Alarm<'a>> {
+    uart: &'a U,
+    alarm: &'a A,
+    parameters: uart::Parameters,
+    apps: Grant<
+        App,
+        UpcallCount<1>,
+        AllowRoCount<{ ro_allow::COUNT }>,
+        AllowRwCount<{ rw_allow::COUNT }>,
+    >,
+    /// Process whose registers are served.
+    slave: OptionalCell<ProcessId>,
+    address: Cell<u8>,
+    receiving: Cell<bool>,
+    responding: Cell<bool>,
+    rx_byte: TakeCell<'static, [u8]>,
+    frame: TakeCell<'static, [u8]>,
+    frame_len: Cell<usize>,
+    /// Whether a byte of the frame was lost or received with an error.
+    frame_error: Cell<bool>,
+    tx_buffer: TakeCell<'static, [u8]>,
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> ModbusRtu<'a, U, A> {
+    pub fn new(
+        uart: &'a U,
+        alarm: &'a A,
+        parameters: uart::Parameters,
+        grant: Grant<
+            App,
+            UpcallCount<1>,
+            AllowRoCount<{ ro_allow::COUNT }>,
+            AllowRwCount<{ rw_allow::COUNT }>,
+        >,
+        rx_byte: &'static mut [u8; 1],
+        frame: &'static mut [u8; FRAME_LEN],
+        tx_buffer: &'static mut [u8; FRAME_LEN],
+    ) -> ModbusRtu<'a, U, A> {
+        ModbusRtu {
+            uart,
+            alarm,
+            parameters,
+            apps: grant,
+            slave: OptionalCell::empty(),
+            address: Cell::new(0),
+            receiving: Cell::new(false),
+            responding: Cell::new(false),
+            rx_byte: TakeCell::new(rx_byte),
+            frame: TakeCell::new(frame),
+            frame_len: Cell::new(0),
+            frame_error: Cell::new(false),
+            tx_buffer: TakeCell::new(tx_buffer),
+        }
+    }
+
+    pub fn initialize(&self) -> Result<(), ErrorCode> {
+        self.uart.configure(self.parameters)
+    }
+
+    /// Silence which ends a frame: 3.5 characters of 11 bits, or a fixed
+    /// 1.75 ms above 19200 baud.
+    fn silence_us(&self) -> u32 {
+        let baud_rate = self.parameters.baud_rate;
+        if baud_rate > 19200 {
+            1750
+        } else {
+            (38_500_000 + baud_rate - 1) / baud_rate
+        }
+    }
+
+    fn receive_byte(&self) -> Result<(), ErrorCode> {
+        self.rx_byte.take().map_or(Err(ErrorCode::BUSY), |rx_byte| {
+            self.uart
+                .receive_buffer(rx_byte, 1)
+                .map_err(|(err, rx_byte)| {
+                    self.rx_byte.replace(rx_byte);
+                    err
+                })
+        })
+    }
+
+    /// Copy registers `start..start + out.len() / 2` of the slave to `out`,
+    /// big-endian.
+    fn read_registers(&self, holding: bool, start: usize, out: &mut [u8]) -> Result<(), u8> {
+        let range = 2 * start..2 * start + out.len();
+        self.slave.map_or(Err(SLAVE_DEVICE_FAILURE), |processid| {
+            self.apps
+                .enter(*processid, |_, kernel_data| {
+                    let copied = if holding {
+                        kernel_data
+                            .get_readwrite_processbuffer(rw_allow::HOLDING_REGISTERS)
+                            .and_then(|registers| {
+                                registers.enter(|registers| {
+                                    registers
+                                        .get(range.clone())
+                                        .map(|registers| registers.copy_to_slice(out))
+                                })
+                            })
+                    } else {
+                        kernel_data
+                            .get_readonly_processbuffer(ro_allow::INPUT_REGISTERS)
+                            .and_then(|registers| {
+                                registers.enter(|registers| {
+                                    registers
+                                        .get(range.clone())
+                                        .map(|registers| registers.copy_to_slice(out))
+                                })
+                            })
+                    };
+                    match copied {
+                        Ok(Some(())) => Ok(()),
+                        _ => Err(ILLEGAL_DATA_ADDRESS),
+                    }
+                })
+                .unwrap_or(Err(SLAVE_DEVICE_FAILURE))
+        })?;
+
+        for register in out.chunks_mut(2) {
+            register.swap(0, 1);
+        }
+        Ok(())
+    }
+
+    /// Write the big-endian `values` to the holding registers of the slave
+    /// from `start`, and notify it.
+    fn write_registers(&self, start: usize, values: &[u8]) -> Result<(), u8> {
+        let mut registers = [0; 2 * MAX_WRITE_REGISTERS];
+        let registers = &mut registers[..values.len()];
+        registers.copy_from_slice(values);
+        for register in registers.chunks_mut(2) {
+            register.swap(0, 1);
+        }
+
+        let range = 2 * start..2 * start + registers.len();
+        self.slave.map_or(Err(SLAVE_DEVICE_FAILURE), |processid| {
+            self.apps
+                .enter(*processid, |_, kernel_data| {
+                    let written = kernel_data
+                        .get_readwrite_processbuffer(rw_allow::HOLDING_REGISTERS)
+                        .and_then(|holding| {
+                            holding.mut_enter(|holding| {
+                                holding
+                                    .get(range.clone())
+                                    .map(|holding| holding.copy_from_slice(registers))
+                            })
+                        });
+                    match written {
+                        Ok(Some(())) => {
+                            kernel_data
+                                .schedule_upcall(0, (start, registers.len() / 2, 0))
+                                .ok();
+                            Ok(())
+                        }
+                        _ => Err(ILLEGAL_DATA_ADDRESS),
+                    }
+                })
+                .unwrap_or(Err(SLAVE_DEVICE_FAILURE))
+        })
+    }
+
+    /// Execute `request`, a frame without its CRC, and write the response
+    /// without its CRC. Returns the length of the response, if any.
+    fn process(&self, request: &[u8], response: &mut [u8]) -> Option<usize> {
+        let address = request[0];
+        let broadcast = address == 0;
+        if !broadcast && address != self.address.get() {
+            return None;
+        }
+
+        let function = request[1];
+        let field = |i: usize| u16::from_be_bytes([request[i], request[i + 1]]) as usize;
+        let result = match function {
+            READ_HOLDING_REGISTERS | READ_INPUT_REGISTERS if request.len() == 6 => {
+                let (start, count) = (field(2), field(4));
+                if broadcast {
+                    return None;
+                } else if count == 0 || count > MAX_READ_REGISTERS {
+                    Err(ILLEGAL_DATA_VALUE)
+                } else {
+                    response[2] = (2 * count) as u8;
+                    self.read_registers(
+                        function == READ_HOLDING_REGISTERS,
+                        start,
+                        &mut response[3..3 + 2 * count],
+                    )
+                    .map(|()| 1 + 2 * count)
+                }
+            }
+            WRITE_SINGLE_REGISTER if request.len() == 6 => {
+                self.write_registers(field(2), &request[4..6]).map(|()| {
+                    response[2..6].copy_from_slice(&request[2..6]);
+                    4
+                })
+            }
+            WRITE_MULTIPLE_REGISTERS if request.len() >= 7 => {
+                let (start, count) = (field(2), field(4));
+                if count == 0
+                    || count > MAX_WRITE_REGISTERS
+                    || request[6] as usize != 2 * count
+                    || request.len() != 7 + 2 * count
+                {
+                    Err(ILLEGAL_DATA_VALUE)
+                } else {
+                    self.write_registers(start, &request[7..]).map(|()| {
+                        response[2..6].copy_from_slice(&request[2..6]);
+                        4
+                    })
+                }
+            }
+            READ_HOLDING_REGISTERS
+            | READ_INPUT_REGISTERS
+            | WRITE_SINGLE_REGISTER
+            | WRITE_MULTIPLE_REGISTERS => Err(ILLEGAL_DATA_VALUE),
+            _ => Err(ILLEGAL_FUNCTION),
+        };
+
+        if broadcast {
+            return None;
+        }
+        response[0] = address;
+        match result {
+            Ok(len) => {
+                response[1] = function;
+                Some(2 + len)
+            }
+            Err(exception) => {
+                response[1] = function | 0x80;
+                response[2] = exception;
+                Some(3)
+            }
+        }
+    }
+
+    /// Answer the frame received, if it is valid.
+    fn frame_done(&self) {
+        let len = self.frame_len.replace(0);
+        if self.frame_error.replace(false) || len < 4 || self.slave.is_none() {
+            return;
+        }
+
+        self.frame.map(|frame| {
+            let crc = crc16(&frame[..len - 2]);
+            if frame[len - 2..len] != crc.to_le_bytes() {
+                return;
+            }
+            self.tx_buffer.take().map(|tx_buffer| {
+                match self.process(&frame[..len - 2], tx_buffer) {
+                    Some(response_len) => {
+                        let crc = crc16(&tx_buffer[..response_len]);
+                        tx_buffer[response_len..response_len + 2]
+                            .copy_from_slice(&crc.to_le_bytes());
+                        self.responding.set(true);
+                        if let Err((_, tx_buffer)) =
+                            self.uart.transmit_buffer(tx_buffer, response_len + 2)
+                        {
+                            self.responding.set(false);
+                            self.tx_buffer.replace(tx_buffer);
+                        }
+                    }
+                    None => {
+                        self.tx_buffer.replace(tx_buffer);
+                    }
+                }
+            });
+        });
+    }
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> AlarmClient for ModbusRtu<'a, U, A> {
+    fn alarm(&self) {
+        self.frame_done();
+    }
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> uart::TransmitClient for ModbusRtu<'a, U, A> {
+    fn transmitted_buffer(
+        &self,
+        tx_buffer: &'static mut [u8],
+        _tx_len: usize,
+        _rval: Result<(), ErrorCode>,
+    ) {
+        self.tx_buffer.replace(tx_buffer);
+        self.responding.set(false);
+    }
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> uart::ReceiveClient for ModbusRtu<'a, U, A> {
+    fn received_buffer(
+        &self,
+        rx_byte: &'static mut [u8],
+        rx_len: usize,
+        rval: Result<(), ErrorCode>,
+        _error: uart::Error,
+    ) {
+        let byte = rx_byte[0];
+        self.rx_byte.replace(rx_byte);
+
+        // Bytes received while responding are the echo of the response
+        if self.slave.is_some() && !self.responding.get() {
+            if rval.is_ok() && rx_len == 1 {
+                let len = self.frame_len.get();
+                if len < FRAME_LEN {
+                    self.frame.map(|frame| frame[len] = byte);
+                    self.frame_len.set(len + 1);
+                } else {
+                    self.frame_error.set(true);
+                }
+            } else {
+                self.frame_error.set(true);
+            }
+            self.alarm.set_alarm(
+                self.alarm.now(),
+                self.alarm.ticks_from_us(self.silence_us()),
+            );
+        }
+
+        if self.slave.is_none() || self.receive_byte().is_err() {
+            self.receiving.set(false);
+        }
+    }
+}
+
+impl<'a, U: uart::Uart<'a>, A: Alarm<'a>> SyscallDriver for ModbusRtu<'a, U, A> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        _data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            // Start answering requests
+            1 => {
+                if self.slave.map_or(false, |slave| *slave != processid) {
+                    return CommandReturn::failure(ErrorCode::RESERVE);
+                } else if data1 == 0 || data1 > 247 {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                self.slave.set(processid);
+                self.address.set(data1 as u8);
+                if !self.receiving.get() {
+                    if let Err(err) = self.receive_byte() {
+                        self.slave.clear();
+                        return CommandReturn::failure(err);
+                    }
+                    self.receiving.set(true);
+                }
+                CommandReturn::success()
+            }
+
+            // Stop answering requests
+            2 => {
+                if !self.slave.contains(&processid) {
+                    return CommandReturn::failure(ErrorCode::RESERVE);
+                }
+                self.slave.clear();
+                let _ = self.alarm.disarm();
+                self.frame_len.set(0);
+                self.frame_error.set(false);
+                let _ = self.uart.receive_abort();
+                CommandReturn::success()
+            }
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
@@ -0,0 +1,149 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Random delays for the retries of network protocols.
+//!
+//! Devices which lose their network at the same time, when an access point
+//! or a server restarts, retry in lockstep if they all wait the same time
+//! before retrying, and the retries of a whole fleet of devices reach the
+//! network at once. The `Jitter` trait gives protocols such as the DHCP
+//! client, WiFi reconnection or MQTT keepalives bounded random delays
+//! instead.
+//!
+//! `RandomBackoff` implements `Jitter` with an `Entropy32` source. Entropy
+//! arrives asynchronously while protocols need their delays when they arm a
+//! timer, so it keeps a small pool of random words, refilled in the
+//! background. When the pool is empty, the delays are not randomized: a
+//! value in the middle of the range is returned.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let backoff = components::backoff::RandomBackoffComponent::new(&peripherals.trng)
+//!     .finalize(components::random_backoff_component_static!());
+//! dhcp.set_jitter(backoff);
+//! ```
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::hil::entropy::{Client32, Continue, Entropy32};
+use kernel::ErrorCode;
+
+/// Source of random delays.
+pub trait Jitter {
+    /// Returns a random word, or `None` if no entropy is available.
+    fn random(&self) -> Option<u32>;
+
+    /// Returns a random value between `min` and `max` included. Without
+    /// entropy, returns the middle of the range.
+    fn random_between(&self, min: u32, max: u32) -> u32 {
+        let (min, max) = (cmp::min(min, max), cmp::max(min, max));
+        let span = max - min;
+        match self.random() {
+            Some(word) if span == u32::MAX => word,
+            Some(word) => min + word % (span + 1),
+            None => min + span / 2,
+        }
+    }
+
+    /// Returns `value` moved by a random amount of at most `spread` in either
+    /// direction.
+    fn jitter(&self, value: u32, spread: u32) -> u32 {
+        self.random_between(value.saturating_sub(spread), value.saturating_add(spread))
+    }
+
+    /// Returns the delay before retry number `attempt`, counting from 0: the
+    /// ceiling starts at `base` and doubles after every attempt up to `max`,
+    /// and the delay is random between half of the ceiling and the ceiling.
+    fn backoff(&self, attempt: u32, base: u32, max: u32) -> u32 {
+        let ceiling = cmp::min(
+            base.saturating_mul(1u32.checked_shl(attempt).unwrap_or(u32::MAX)),
+            max,
+        );
+        self.random_between(ceiling - ceiling / 2, ceiling)
+    }
+}
+
+/// Number of random words kept in advance.
+const POOL_LEN: usize = 8;
+
+pub struct RandomBackoff<'a> {
+    entropy: &'a dyn Entropy32<'a>,
+    pool: Cell<[u32; POOL_LEN]>,
+    available: Cell<usize>,
+    /// Whether entropy was requested from the source.
+    requested: Cell<bool>,
+}
+
+impl<'a> RandomBackoff<'a> {
+    pub fn new(entropy: &'a dyn Entropy32<'a>) -> Self {
+        Self {
+            entropy,
+            pool: Cell::new([0; POOL_LEN]),
+            available: Cell::new(0),
+            requested: Cell::new(false),
+        }
+    }
+
+    /// Fill the pool. Delays are not randomized until entropy arrives.
+    pub fn start(&self) {
+        self.refill();
+    }
+
+    fn refill(&self) {
+        if !self.requested.get() && self.available.get() < POOL_LEN {
+            self.requested.set(self.entropy.get().is_ok());
+        }
+    }
+}
+
+impl Jitter for RandomBackoff<'_> {
+    fn random(&self) -> Option<u32> {
+        let available = self.available.get();
+        let word = available.checked_sub(1).map(|index| {
+            self.available.set(index);
+            self.pool.get()[index]
+        });
+        if self.available.get() <= POOL_LEN / 2 {
+            self.refill();
+        }
+        word
+    }
+}
+
+impl Client32 for RandomBackoff<'_> {
+    fn entropy_available(
+        &self,
+        entropy: &mut dyn Iterator<Item = u32>,
+        error: Result<(), ErrorCode>,
+    ) -> Continue {
+        if error.is_err() {
+            self.requested.set(false);
+            return Continue::Done;
+        }
+
+        let mut pool = self.pool.get();
+        let mut available = self.available.get();
+        while available < POOL_LEN {
+            match entropy.next() {
+                Some(word) => {
+                    pool[available] = word;
+                    available += 1;
+                }
+                None => break,
+            }
+        }
+        self.pool.set(pool);
+        self.available.set(available);
+
+        if available < POOL_LEN {
+            Continue::More
+        } else {
+            self.requested.set(false);
+            Continue::Done
+        }
+    }
+}
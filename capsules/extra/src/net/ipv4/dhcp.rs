@@ -0,0 +1,546 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! DHCP client (RFC 2131).
+//!
+//! The client obtains a lease for the interface it is given, configures the
+//! interface with it, and renews it before it expires. The lease is kept in
+//! RAM only, so a new one is requested after every reset.
+//!
+//! The client is generic over [`Ipv4Interface`], so that any interface that
+//! can send UDP datagrams can use it: it is not tied to Ethernet.
+//!
+//! Lease times are counted in seconds by re-arming the alarm at most every
+//! `MAX_ALARM_S` seconds, so that they do not overflow the ticks of the
+//! alarm.
+//!
+//! With a `Jitter` source, given with `set_jitter()`, the transaction IDs are
+//! random and the retransmission timeouts are randomized by up to one second
+//! in either direction, as the RFC recommends, so that clients which start
+//! together do not keep retransmitting together.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let dhcp = components::dhcp::DhcpClientComponent::new(ipv4_stack, mux_alarm)
+//!     .finalize(components::dhcp_client_component_static!(
+//!         imxrt1050::enet::Enet<'static>,
+//!         imxrt1050::gpt::Gpt1<'static>,
+//!     ));
+//! dhcp.start();
+//! ```
+
+use core::cell::Cell;
+use core::cmp;
+
+use super::stack::{UdpClient, UdpSocket};
+use super::{Ipv4Addr, Ipv4Config, Ipv4Interface};
+use crate::net::backoff::Jitter;
+use kernel::hil::time::{self, ConvertTicks, Ticks};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+/// UDP port of DHCP servers.
+pub const SERVER_PORT: u16 = 67;
+/// UDP port of DHCP clients.
+pub const CLIENT_PORT: u16 = 68;
+
+/// Length of the buffer messages are built in: the minimum size of a BOOTP
+/// message.
+pub const BUF_LEN: usize = 300;
+
+const OP_BOOTREQUEST: u8 = 1;
+const OP_BOOTREPLY: u8 = 2;
+const HTYPE_ETHERNET: u8 = 1;
+const FLAG_BROADCAST: u16 = 0x8000;
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+/// Offset of the options, after the fixed fields and the magic cookie.
+const OPTIONS_OFFSET: usize = 240;
+
+const OPTION_PAD: u8 = 0;
+const OPTION_SUBNET_MASK: u8 = 1;
+const OPTION_ROUTER: u8 = 3;
+const OPTION_REQUESTED_ADDRESS: u8 = 50;
+const OPTION_LEASE_TIME: u8 = 51;
+const OPTION_MESSAGE_TYPE: u8 = 53;
+const OPTION_SERVER_ID: u8 = 54;
+const OPTION_PARAMETER_LIST: u8 = 55;
+const OPTION_RENEWAL_TIME: u8 = 58;
+const OPTION_REBINDING_TIME: u8 = 59;
+const OPTION_END: u8 = 255;
+
+const DHCPDISCOVER: u8 = 1;
+const DHCPOFFER: u8 = 2;
+const DHCPREQUEST: u8 = 3;
+const DHCPACK: u8 = 5;
+const DHCPNAK: u8 = 6;
+
+/// First retransmission timeout, doubled after every retransmission.
+const INITIAL_TIMEOUT_S: u32 = 4;
+const MAX_TIMEOUT_S: u32 = 64;
+/// Requests sent for an offer before discovering servers again.
+const MAX_REQUESTS: u8 = 4;
+/// Shortest time between requests while renewing or rebinding.
+const MIN_RENEW_INTERVAL_S: u32 = 60;
+/// Lease time assumed if the server does not give one.
+const DEFAULT_LEASE_S: u32 = 3600;
+/// Longest time the alarm is set for.
+const MAX_ALARM_S: u32 = 60;
+
+/// Client notified when the lease, and so the configuration of the
+/// interface, changes.
+pub trait LeaseClient {
+    /// The interface now has `config`. It is `Ipv4Config::UNCONFIGURED` if
+    /// the lease was lost.
+    fn lease_changed(&self, config: Ipv4Config);
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum State {
+    Stopped,
+    /// Waiting for offers.
+    Selecting,
+    /// Waiting for the server to acknowledge the offered address.
+    Requesting,
+    Bound,
+    /// Renewing the lease with the server that gave it.
+    Renewing,
+    /// Renewing the lease with any server.
+    Rebinding,
+}
+
+/// Times of a lease, in seconds from when it was acknowledged. The renewal
+/// time only sets the first timer.
+#[derive(Clone, Copy)]
+struct Lease {
+    rebinding: u32,
+    expiry: u32,
+}
+
+pub struct DhcpClient<'a, I: Ipv4Interface<'a>, A: time::Alarm<'a>> {
+    interface: &'a I,
+    socket: &'a UdpSocket<'a>,
+    alarm: &'a A,
+    client: OptionalCell<&'a dyn LeaseClient>,
+    jitter: OptionalCell<&'a dyn Jitter>,
+    buffer: TakeCell<'static, [u8]>,
+    state: Cell<State>,
+    xid: Cell<u32>,
+    server: Cell<Ipv4Addr>,
+    offered: Cell<Ipv4Addr>,
+    lease: Cell<Lease>,
+    /// Seconds since the lease was acknowledged.
+    elapsed: Cell<u32>,
+    /// Current retransmission timeout.
+    timeout: Cell<u32>,
+    requests: Cell<u8>,
+    /// Seconds left before the timer expires.
+    remaining: Cell<u32>,
+    /// Seconds the alarm is set for.
+    armed: Cell<u32>,
+}
+
+impl<'a, I: Ipv4Interface<'a>, A: time::Alarm<'a>> DhcpClient<'a, I, A> {
+    pub fn new(
+        interface: &'a I,
+        socket: &'a UdpSocket<'a>,
+        alarm: &'a A,
+        buffer: &'static mut [u8],
+    ) -> Self {
+        Self {
+            interface,
+            socket,
+            alarm,
+            client: OptionalCell::empty(),
+            jitter: OptionalCell::empty(),
+            buffer: TakeCell::new(buffer),
+            state: Cell::new(State::Stopped),
+            xid: Cell::new(0),
+            server: Cell::new(Ipv4Addr::UNSPECIFIED),
+            offered: Cell::new(Ipv4Addr::UNSPECIFIED),
+            lease: Cell::new(Lease {
+                rebinding: 0,
+                expiry: 0,
+            }),
+            elapsed: Cell::new(0),
+            timeout: Cell::new(INITIAL_TIMEOUT_S),
+            requests: Cell::new(0),
+            remaining: Cell::new(0),
+            armed: Cell::new(0),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn LeaseClient) {
+        self.client.set(client);
+    }
+
+    /// Randomize the transaction IDs and the retransmission timeouts.
+    pub fn set_jitter(&self, jitter: &'a dyn Jitter) {
+        self.jitter.set(jitter);
+    }
+
+    pub fn state(&self) -> State {
+        self.state.get()
+    }
+
+    /// Start looking for a DHCP server. The interface is unconfigured until
+    /// a lease is acknowledged.
+    pub fn start(&self) {
+        if self.state.get() == State::Stopped {
+            self.discover();
+        }
+    }
+
+    /// Stop renewing the lease and unconfigure the interface.
+    pub fn stop(&self) {
+        let _ = self.alarm.disarm();
+        let bound = self.is_bound();
+        self.state.set(State::Stopped);
+        if bound {
+            self.lose_lease();
+        }
+    }
+
+    fn is_bound(&self) -> bool {
+        matches!(
+            self.state.get(),
+            State::Bound | State::Renewing | State::Rebinding
+        )
+    }
+
+    fn discover(&self) {
+        // Without a random number generator, the time and the hardware
+        // address are enough to tell transactions apart.
+        let mac = self.interface.mac_address();
+        let mac = mac.as_bytes();
+        let xid = self.alarm.now().into_u32()
+            ^ u32::from_be_bytes([mac[2], mac[3], mac[4], mac[5]])
+            ^ self.xid.get().rotate_left(7);
+        let xid = self
+            .jitter
+            .map_or(None, |jitter| jitter.random())
+            .unwrap_or(xid);
+        self.xid.set(xid);
+        self.state.set(State::Selecting);
+        self.timeout.set(INITIAL_TIMEOUT_S);
+        self.send(DHCPDISCOVER);
+        self.wait_randomized(INITIAL_TIMEOUT_S);
+    }
+
+    fn request_offer(&self) {
+        self.state.set(State::Requesting);
+        self.requests.set(1);
+        self.timeout.set(INITIAL_TIMEOUT_S);
+        self.send(DHCPREQUEST);
+        self.wait_randomized(INITIAL_TIMEOUT_S);
+    }
+
+    /// Retransmit the current message after doubling the timeout.
+    fn retransmit(&self, message_type: u8) {
+        let timeout = cmp::min(self.timeout.get() * 2, MAX_TIMEOUT_S);
+        self.timeout.set(timeout);
+        self.send(message_type);
+        self.wait_randomized(timeout);
+    }
+
+    /// Send a request while renewing or rebinding, and wait for half of the
+    /// time left before `deadline`.
+    fn renew(&self, deadline: u32) {
+        self.send(DHCPREQUEST);
+        let left = deadline.saturating_sub(self.elapsed.get());
+        self.wait(cmp::min(cmp::max(left / 2, MIN_RENEW_INTERVAL_S), left));
+    }
+
+    fn lose_lease(&self) {
+        self.interface.set_config(Ipv4Config::UNCONFIGURED);
+        self.client
+            .map(|client| client.lease_changed(Ipv4Config::UNCONFIGURED));
+    }
+
+    fn send(&self, message_type: u8) {
+        let state = self.state.get();
+        let config = self.interface.config();
+        let dst = if state == State::Renewing {
+            self.server.get()
+        } else {
+            Ipv4Addr::BROADCAST
+        };
+        self.buffer.take().map(|buffer| {
+            let len = self.build(buffer, message_type, state, config);
+            // Lost messages are retransmitted when the timer expires.
+            let _ =
+                self.interface
+                    .send_udp(self.socket, CLIENT_PORT, dst, SERVER_PORT, &buffer[..len]);
+            self.buffer.replace(buffer);
+        });
+    }
+
+    fn build(
+        &self,
+        buffer: &mut [u8],
+        message_type: u8,
+        state: State,
+        config: Ipv4Config,
+    ) -> usize {
+        for byte in buffer.iter_mut() {
+            *byte = 0;
+        }
+        buffer[0] = OP_BOOTREQUEST;
+        buffer[1] = HTYPE_ETHERNET;
+        buffer[2] = 6;
+        buffer[4..8].copy_from_slice(&self.xid.get().to_be_bytes());
+        // Until the interface has an address, replies cannot be unicast to
+        // it.
+        if !config.is_configured() {
+            buffer[10..12].copy_from_slice(&FLAG_BROADCAST.to_be_bytes());
+        } else {
+            buffer[12..16].copy_from_slice(&config.address.0);
+        }
+        buffer[28..34].copy_from_slice(self.interface.mac_address().as_bytes());
+        buffer[236..240].copy_from_slice(&MAGIC_COOKIE);
+
+        let mut options = OptionWriter {
+            buffer,
+            offset: OPTIONS_OFFSET,
+        };
+        options.write(OPTION_MESSAGE_TYPE, &[message_type]);
+        if state == State::Requesting {
+            options.write(OPTION_REQUESTED_ADDRESS, &self.offered.get().0);
+            options.write(OPTION_SERVER_ID, &self.server.get().0);
+        }
+        options.write(
+            OPTION_PARAMETER_LIST,
+            &[
+                OPTION_SUBNET_MASK,
+                OPTION_ROUTER,
+                OPTION_LEASE_TIME,
+                OPTION_RENEWAL_TIME,
+                OPTION_REBINDING_TIME,
+            ],
+        );
+        let end = options.offset;
+        buffer[end] = OPTION_END;
+        cmp::max(end + 1, BUF_LEN)
+    }
+
+    fn wait(&self, seconds: u32) {
+        self.remaining.set(cmp::max(seconds, 1));
+        self.arm();
+    }
+
+    /// Wait for a retransmission timeout, moved by up to a second.
+    fn wait_randomized(&self, seconds: u32) {
+        let seconds = self
+            .jitter
+            .map_or(seconds, |jitter| jitter.jitter(seconds, 1));
+        self.wait(seconds);
+    }
+
+    fn arm(&self) {
+        let seconds = cmp::min(self.remaining.get(), MAX_ALARM_S);
+        self.armed.set(seconds);
+        self.alarm
+            .set_alarm(self.alarm.now(), self.alarm.ticks_from_seconds(seconds));
+    }
+
+    fn receive_ack(&self, reply: &Reply) {
+        let lease = reply.lease_time.unwrap_or(DEFAULT_LEASE_S);
+        let renewal = reply.renewal_time.unwrap_or(lease / 2);
+        let rebinding = reply
+            .rebinding_time
+            .unwrap_or((lease as u64 * 7 / 8) as u32);
+        self.lease.set(Lease {
+            rebinding,
+            expiry: lease,
+        });
+        if let Some(server) = reply.server {
+            self.server.set(server);
+        }
+        self.elapsed.set(0);
+        self.state.set(State::Bound);
+
+        let config = Ipv4Config {
+            address: reply.address,
+            // Without a subnet mask, every destination is reached through
+            // the router.
+            netmask: reply.netmask.unwrap_or(Ipv4Addr::BROADCAST),
+            gateway: reply.router,
+        };
+        if self.interface.config() != config {
+            self.interface.set_config(config);
+            self.client.map(|client| client.lease_changed(config));
+        }
+        self.wait(renewal);
+    }
+}
+
+struct OptionWriter<'b> {
+    buffer: &'b mut [u8],
+    offset: usize,
+}
+
+impl OptionWriter<'_> {
+    fn write(&mut self, code: u8, value: &[u8]) {
+        let offset = self.offset;
+        self.buffer[offset] = code;
+        self.buffer[offset + 1] = value.len() as u8;
+        self.buffer[offset + 2..offset + 2 + value.len()].copy_from_slice(value);
+        self.offset += 2 + value.len();
+    }
+}
+
+/// Fields of a reply from a server.
+struct Reply {
+    message_type: u8,
+    address: Ipv4Addr,
+    server: Option<Ipv4Addr>,
+    netmask: Option<Ipv4Addr>,
+    router: Option<Ipv4Addr>,
+    lease_time: Option<u32>,
+    renewal_time: Option<u32>,
+    rebinding_time: Option<u32>,
+}
+
+impl Reply {
+    fn parse(message: &[u8]) -> Option<Reply> {
+        if message.len() < OPTIONS_OFFSET || message[236..240] != MAGIC_COOKIE {
+            return None;
+        }
+        let mut reply = Reply {
+            message_type: 0,
+            address: Ipv4Addr([message[16], message[17], message[18], message[19]]),
+            server: None,
+            netmask: None,
+            router: None,
+            lease_time: None,
+            renewal_time: None,
+            rebinding_time: None,
+        };
+
+        let mut options = &message[OPTIONS_OFFSET..];
+        while let Some((&code, rest)) = options.split_first() {
+            if code == OPTION_END {
+                break;
+            }
+            if code == OPTION_PAD {
+                options = rest;
+                continue;
+            }
+            let (&len, rest) = rest.split_first()?;
+            let value = rest.get(..len as usize)?;
+            options = &rest[len as usize..];
+
+            let addr = <[u8; 4]>::try_from(value).ok().map(Ipv4Addr);
+            let seconds = <[u8; 4]>::try_from(value).ok().map(u32::from_be_bytes);
+            match code {
+                OPTION_MESSAGE_TYPE => reply.message_type = *value.first()?,
+                OPTION_SERVER_ID => reply.server = addr,
+                OPTION_SUBNET_MASK => reply.netmask = addr,
+                // Only the first router is used.
+                OPTION_ROUTER => {
+                    reply.router = value
+                        .get(..4)
+                        .and_then(|v| <[u8; 4]>::try_from(v).ok())
+                        .map(Ipv4Addr)
+                }
+                OPTION_LEASE_TIME => reply.lease_time = seconds,
+                OPTION_RENEWAL_TIME => reply.renewal_time = seconds,
+                OPTION_REBINDING_TIME => reply.rebinding_time = seconds,
+                _ => {}
+            }
+        }
+        Some(reply)
+    }
+}
+
+impl<'a, I: Ipv4Interface<'a>, A: time::Alarm<'a>> UdpClient for DhcpClient<'a, I, A> {
+    fn receive(&self, _src_addr: Ipv4Addr, src_port: u16, _dst_port: u16, payload: &[u8]) {
+        if src_port != SERVER_PORT
+            || payload.len() < OPTIONS_OFFSET
+            || payload[0] != OP_BOOTREPLY
+            || payload[4..8] != self.xid.get().to_be_bytes()
+            || payload[28..34] != *self.interface.mac_address().as_bytes()
+        {
+            return;
+        }
+        let reply = match Reply::parse(payload) {
+            Some(reply) => reply,
+            None => return,
+        };
+
+        match (self.state.get(), reply.message_type) {
+            (State::Selecting, DHCPOFFER) => {
+                let server = match reply.server {
+                    Some(server) => server,
+                    None => return,
+                };
+                let _ = self.alarm.disarm();
+                self.server.set(server);
+                self.offered.set(reply.address);
+                self.request_offer();
+            }
+            (State::Requesting | State::Renewing | State::Rebinding, DHCPACK) => {
+                let _ = self.alarm.disarm();
+                self.receive_ack(&reply);
+            }
+            (State::Requesting | State::Renewing | State::Rebinding, DHCPNAK) => {
+                let _ = self.alarm.disarm();
+                if self.is_bound() {
+                    self.lose_lease();
+                }
+                self.discover();
+            }
+            _ => {}
+        }
+    }
+
+    fn send_done(&self, _result: Result<(), ErrorCode>) {}
+}
+
+impl<'a, I: Ipv4Interface<'a>, A: time::Alarm<'a>> time::AlarmClient for DhcpClient<'a, I, A> {
+    fn alarm(&self) {
+        let armed = self.armed.get();
+        self.elapsed.set(self.elapsed.get().saturating_add(armed));
+        self.remaining.set(self.remaining.get() - armed);
+        if self.remaining.get() > 0 {
+            self.arm();
+            return;
+        }
+
+        let lease = self.lease.get();
+        match self.state.get() {
+            State::Stopped => {}
+            State::Selecting => self.retransmit(DHCPDISCOVER),
+            State::Requesting => {
+                if self.requests.get() >= MAX_REQUESTS {
+                    self.discover();
+                } else {
+                    self.requests.set(self.requests.get() + 1);
+                    self.retransmit(DHCPREQUEST);
+                }
+            }
+            State::Bound => {
+                self.state.set(State::Renewing);
+                self.renew(lease.rebinding);
+            }
+            State::Renewing => {
+                if self.elapsed.get() >= lease.rebinding {
+                    self.state.set(State::Rebinding);
+                    self.renew(lease.expiry);
+                } else {
+                    self.renew(lease.rebinding);
+                }
+            }
+            State::Rebinding => {
+                if self.elapsed.get() >= lease.expiry {
+                    self.lose_lease();
+                    self.discover();
+                } else {
+                    self.renew(lease.expiry);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,316 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Userspace interface for UDP over the IPv4 stack.
+//!
+//! Each process binds one UDP port, receives the datagrams sent to it, and
+//! sends datagrams from it. Datagrams from several processes are sent one at
+//! a time, in the order of the grant iteration.
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! ### Allow ReadOnly
+//!
+//! - `0`: Payload of the datagram to send.
+//!
+//! ### Allow ReadWrite
+//!
+//! - `0`: Buffer received datagrams are copied into: the source address (4
+//!   bytes), the source port (2 bytes, big-endian), then the payload,
+//!   truncated to the size of the buffer.
+//!
+//! ### Subscribe
+//!
+//! - `0`: Send complete. Called with the status of the send.
+//! - `1`: Datagram received. Called with the length of the payload and the
+//!   source port.
+//!
+//! ### Command
+//!
+//! - `0`: Driver existence check.
+//! - `1`: Bind to the port `data1`. Returns `BUSY` if the port is used by
+//!   another process or by the kernel.
+//! - `2`: Unbind.
+//! - `3`: Send the allowed payload to the address `data1`, for example
+//!   `0xC0A80001` for 192.168.0.1, and the port `data2`. Returns `RESERVE`
+//!   if the process has not bound a port.
+//! - `4`: Return the address of the interface, 0 if it has none.
+//! - `5`: Return 1 if the link is up, 0 otherwise.
+
+use core::cmp;
+
+use super::stack::{Ipv4Stack, UdpClient, UdpSocket};
+use super::Ipv4Addr;
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::ethernet::EthernetAdapter;
+use kernel::hil::mac_address::Configure;
+use kernel::hil::time;
+use kernel::processbuffer::{ReadableProcessBuffer, WriteableProcessBuffer};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::UdpIpv4 as usize;
+
+/// Ids for read-only allow buffers
+mod ro_allow {
+    pub const SEND: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+/// Ids for read-write allow buffers
+mod rw_allow {
+    pub const RECEIVE: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+/// Ids for subscribe upcalls
+mod upcall {
+    pub const SEND_DONE: usize = 0;
+    pub const RECEIVED: usize = 1;
+    /// The number of subscribe upcalls the kernel stores for this grant
+    pub const COUNT: u8 = 2;
+}
+
+/// Length of the source address and port written before received payloads.
+const RECEIVE_HEADER_LEN: usize = 6;
+
+#[derive(Default)]
+pub struct App {
+    port: Option<u16>,
+    /// Destination of a datagram waiting to be sent.
+    pending: Option<(Ipv4Addr, u16)>,
+}
+
+pub struct UdpDriver<'a, E: EthernetAdapter<'a> + Configure, A: time::Alarm<'a>> {
+    stack: &'a Ipv4Stack<'a, E, A>,
+    socket: &'a UdpSocket<'a>,
+    apps: Grant<
+        App,
+        UpcallCount<{ upcall::COUNT }>,
+        AllowRoCount<{ ro_allow::COUNT }>,
+        AllowRwCount<{ rw_allow::COUNT }>,
+    >,
+    buffer: TakeCell<'static, [u8]>,
+    /// Process whose datagram is being sent.
+    current: OptionalCell<ProcessId>,
+}
+
+impl<'a, E: EthernetAdapter<'a> + Configure, A: time::Alarm<'a>> UdpDriver<'a, E, A> {
+    pub fn new(
+        stack: &'a Ipv4Stack<'a, E, A>,
+        socket: &'a UdpSocket<'a>,
+        buffer: &'static mut [u8],
+        grant: Grant<
+            App,
+            UpcallCount<{ upcall::COUNT }>,
+            AllowRoCount<{ ro_allow::COUNT }>,
+            AllowRwCount<{ rw_allow::COUNT }>,
+        >,
+    ) -> Self {
+        Self {
+            stack,
+            socket,
+            apps: grant,
+            buffer: TakeCell::new(buffer),
+            current: OptionalCell::empty(),
+        }
+    }
+
+    fn port_in_use(&self, port: u16, processid: ProcessId) -> bool {
+        self.stack.port_in_use(port)
+            || self.apps.iter().any(|app| {
+                app.processid() != processid && app.enter(|app, _| app.port == Some(port))
+            })
+    }
+
+    /// Copy the payload of `processid` into the kernel buffer and give it to
+    /// the stack.
+    fn send(&self, processid: ProcessId) -> Result<(), ErrorCode> {
+        let buffer = self.buffer.take().ok_or(ErrorCode::BUSY)?;
+        let result = self
+            .apps
+            .enter(processid, |app, kernel_data| {
+                let (dst, dst_port) = app.pending.take().ok_or(ErrorCode::FAIL)?;
+                let src_port = app.port.ok_or(ErrorCode::RESERVE)?;
+                let len = kernel_data
+                    .get_readonly_processbuffer(ro_allow::SEND)
+                    .map_err(ErrorCode::from)
+                    .and_then(|payload| {
+                        payload
+                            .enter(|payload| {
+                                if payload.len() > buffer.len() {
+                                    return Err(ErrorCode::SIZE);
+                                }
+                                payload.copy_to_slice(&mut buffer[..payload.len()]);
+                                Ok(payload.len())
+                            })
+                            .unwrap_or(Err(ErrorCode::RESERVE))
+                    })?;
+                self.stack
+                    .send(self.socket, src_port, dst, dst_port, &buffer[..len])
+            })
+            .unwrap_or_else(|err| Err(err.into()));
+        self.buffer.replace(buffer);
+        if result.is_ok() {
+            self.current.set(processid);
+        }
+        result
+    }
+
+    /// Send the next waiting datagram, reporting failures to their process.
+    fn send_next(&self) {
+        while self.current.is_none() {
+            let next = self.apps.iter().find_map(|app| {
+                let processid = app.processid();
+                app.enter(|app, _| app.pending.is_some())
+                    .then_some(processid)
+            });
+            let processid = match next {
+                Some(processid) => processid,
+                None => return,
+            };
+            if let Err(e) = self.send(processid) {
+                let _ = self.apps.enter(processid, |app, kernel_data| {
+                    app.pending = None;
+                    kernel_data
+                        .schedule_upcall(
+                            upcall::SEND_DONE,
+                            (kernel::errorcode::into_statuscode(Err(e)), 0, 0),
+                        )
+                        .ok();
+                });
+            }
+        }
+    }
+}
+
+impl<'a, E: EthernetAdapter<'a> + Configure, A: time::Alarm<'a>> UdpClient for UdpDriver<'a, E, A> {
+    fn receive(&self, src_addr: Ipv4Addr, src_port: u16, dst_port: u16, payload: &[u8]) {
+        for app in self.apps.iter() {
+            app.enter(|app, kernel_data| {
+                if app.port != Some(dst_port) {
+                    return;
+                }
+                let len = kernel_data
+                    .get_readwrite_processbuffer(rw_allow::RECEIVE)
+                    .and_then(|buffer| {
+                        buffer.mut_enter(|buffer| {
+                            if buffer.len() < RECEIVE_HEADER_LEN {
+                                return 0;
+                            }
+                            buffer[0..4].copy_from_slice(&src_addr.0);
+                            buffer[4..6].copy_from_slice(&src_port.to_be_bytes());
+                            let len = cmp::min(buffer.len() - RECEIVE_HEADER_LEN, payload.len());
+                            buffer[RECEIVE_HEADER_LEN..RECEIVE_HEADER_LEN + len]
+                                .copy_from_slice(&payload[..len]);
+                            len
+                        })
+                    })
+                    .unwrap_or(0);
+                kernel_data
+                    .schedule_upcall(upcall::RECEIVED, (len, src_port as usize, 0))
+                    .ok();
+            });
+        }
+    }
+
+    fn send_done(&self, result: Result<(), ErrorCode>) {
+        self.current.take().map(|processid| {
+            let _ = self.apps.enter(processid, |_, kernel_data| {
+                kernel_data
+                    .schedule_upcall(
+                        upcall::SEND_DONE,
+                        (kernel::errorcode::into_statuscode(result), 0, 0),
+                    )
+                    .ok();
+            });
+        });
+        self.send_next();
+    }
+}
+
+impl<'a, E: EthernetAdapter<'a> + Configure, A: time::Alarm<'a>> SyscallDriver
+    for UdpDriver<'a, E, A>
+{
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            1 => {
+                let port = data1 as u16;
+                if port == 0 || data1 > u16::MAX as usize {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                if self.port_in_use(port, processid) {
+                    return CommandReturn::failure(ErrorCode::BUSY);
+                }
+                self.apps
+                    .enter(processid, |app, _| {
+                        app.port = Some(port);
+                        CommandReturn::success()
+                    })
+                    .unwrap_or_else(|err| CommandReturn::failure(err.into()))
+            }
+
+            2 => self
+                .apps
+                .enter(processid, |app, _| {
+                    app.port = None;
+                    CommandReturn::success()
+                })
+                .unwrap_or_else(|err| CommandReturn::failure(err.into())),
+
+            3 => {
+                let dst = Ipv4Addr::from(data1 as u32);
+                let dst_port = data2 as u16;
+                let queued = self
+                    .apps
+                    .enter(processid, |app, _| {
+                        if app.port.is_none() {
+                            Err(ErrorCode::RESERVE)
+                        } else if app.pending.is_some() {
+                            Err(ErrorCode::BUSY)
+                        } else {
+                            app.pending = Some((dst, dst_port));
+                            Ok(())
+                        }
+                    })
+                    .unwrap_or_else(|err| Err(err.into()));
+                if let Err(e) = queued {
+                    return CommandReturn::failure(e);
+                }
+                // Send right away if nothing else is being sent, so that
+                // errors are returned synchronously.
+                if self.current.is_none() {
+                    if let Err(e) = self.send(processid) {
+                        return CommandReturn::failure(e);
+                    }
+                }
+                CommandReturn::success()
+            }
+
+            4 => CommandReturn::success_u32(u32::from(self.stack.config().address)),
+
+            5 => CommandReturn::success_u32(self.stack.link_up() as u32),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
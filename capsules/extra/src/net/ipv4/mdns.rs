@@ -0,0 +1,474 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Multicast DNS responder (RFC 6762) with DNS service discovery (RFC 6763).
+//!
+//! The responder answers queries for `<hostname>.local` with the address of
+//! the interface, so that boards can be found on the local network without
+//! a serial console. It can also advertise one service, with the hostname as
+//! instance name: `<hostname>.<service>.<protocol>.local`.
+//!
+//! This is a minimal responder: it does not probe for conflicting names and
+//! does not suppress known answers. It announces its records once, when
+//! started and whenever the address of the interface changes, for example
+//! when it implements the [`LeaseClient`] of a DHCP client.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let mdns = components::mdns::MdnsResponderComponent::new(
+//!     ipv4_stack,
+//!     "tock-evkb",
+//!     Some(MdnsService {
+//!         service: "_tock-console",
+//!         protocol: "_tcp",
+//!         port: 2323,
+//!     }),
+//! )
+//! .finalize(components::mdns_responder_component_static!(
+//!     imxrt1050::enet::Enet<'static>,
+//!     imxrt1050::gpt::Gpt1<'static>,
+//! ));
+//! dhcp.set_client(mdns);
+//! mdns.start();
+//! ```
+
+use super::dhcp::LeaseClient;
+use super::stack::{UdpClient, UdpSocket};
+use super::{Ipv4Addr, Ipv4Config, Ipv4Interface};
+use kernel::utilities::cells::TakeCell;
+use kernel::ErrorCode;
+
+/// UDP port of mDNS.
+pub const PORT: u16 = 5353;
+/// Multicast group of mDNS.
+pub const GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+
+/// Length of the buffer responses are built in.
+pub const BUF_LEN: usize = 512;
+
+const HEADER_LEN: usize = 12;
+/// Authoritative response.
+const FLAGS_RESPONSE: u16 = 0x8400;
+/// Set in the class of records that are unique to this host.
+const CACHE_FLUSH: u16 = 0x8000;
+/// Set in the class of questions that ask for a unicast response.
+const UNICAST_RESPONSE: u16 = 0x8000;
+const CLASS_IN: u16 = 1;
+const CLASS_ANY: u16 = 255;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+
+/// TTL of records that depend on the address of the host.
+const HOST_TTL: u32 = 120;
+/// TTL of the other records.
+const OTHER_TTL: u32 = 4500;
+
+/// Longest chain of compression pointers followed in a name.
+const MAX_POINTERS: usize = 16;
+
+const LOCAL: &[u8] = b"local";
+const SERVICES: [&[u8]; 4] = [b"_services", b"_dns-sd", b"_udp", LOCAL];
+
+/// A service advertised with DNS-SD.
+#[derive(Clone, Copy)]
+pub struct MdnsService {
+    /// Service name, for example `"_http"`.
+    pub service: &'static str,
+    /// `"_tcp"` or `"_udp"`.
+    pub protocol: &'static str,
+    pub port: u16,
+}
+
+/// Records to send in a response.
+#[derive(Clone, Copy, Default)]
+struct Records {
+    a: bool,
+    services_ptr: bool,
+    ptr: bool,
+    srv: bool,
+    txt: bool,
+}
+
+impl Records {
+    const ALL: Records = Records {
+        a: true,
+        services_ptr: false,
+        ptr: true,
+        srv: true,
+        txt: true,
+    };
+
+    fn is_empty(&self) -> bool {
+        !(self.a || self.services_ptr || self.ptr || self.srv || self.txt)
+    }
+
+    fn count(&self) -> u16 {
+        [self.a, self.services_ptr, self.ptr, self.srv, self.txt]
+            .iter()
+            .filter(|record| **record)
+            .count() as u16
+    }
+
+    fn union(self, other: Records) -> Records {
+        Records {
+            a: self.a || other.a,
+            services_ptr: self.services_ptr || other.services_ptr,
+            ptr: self.ptr || other.ptr,
+            srv: self.srv || other.srv,
+            txt: self.txt || other.txt,
+        }
+    }
+
+    /// Records not in `other`.
+    fn without(self, other: Records) -> Records {
+        Records {
+            a: self.a && !other.a,
+            services_ptr: self.services_ptr && !other.services_ptr,
+            ptr: self.ptr && !other.ptr,
+            srv: self.srv && !other.srv,
+            txt: self.txt && !other.txt,
+        }
+    }
+}
+
+pub struct MdnsResponder<'a, I: Ipv4Interface<'a>> {
+    interface: &'a I,
+    socket: &'a UdpSocket<'a>,
+    hostname: &'static str,
+    service: Option<MdnsService>,
+    buffer: TakeCell<'static, [u8]>,
+}
+
+impl<'a, I: Ipv4Interface<'a>> MdnsResponder<'a, I> {
+    pub fn new(
+        interface: &'a I,
+        socket: &'a UdpSocket<'a>,
+        hostname: &'static str,
+        service: Option<MdnsService>,
+        buffer: &'static mut [u8],
+    ) -> Self {
+        Self {
+            interface,
+            socket,
+            hostname,
+            service,
+            buffer: TakeCell::new(buffer),
+        }
+    }
+
+    /// Announce the records of the host, if the interface has an address.
+    pub fn start(&self) {
+        self.announce();
+    }
+
+    fn announce(&self) {
+        let records = if self.service.is_some() {
+            Records::ALL
+        } else {
+            Records {
+                a: true,
+                ..Records::default()
+            }
+        };
+        self.respond(0, records, Records::default(), GROUP, PORT);
+    }
+
+    fn host_name(&self) -> [&[u8]; 2] {
+        [self.hostname.as_bytes(), LOCAL]
+    }
+
+    fn service_name(&self, service: &MdnsService) -> [&[u8]; 3] {
+        [
+            service.service.as_bytes(),
+            service.protocol.as_bytes(),
+            LOCAL,
+        ]
+    }
+
+    fn instance_name(&self, service: &MdnsService) -> [&[u8]; 4] {
+        [
+            self.hostname.as_bytes(),
+            service.service.as_bytes(),
+            service.protocol.as_bytes(),
+            LOCAL,
+        ]
+    }
+
+    /// The records that answer a question for `name` of type `qtype`.
+    fn answers(&self, message: &[u8], name: usize, qtype: u16) -> Records {
+        let is = |wanted: u16| qtype == wanted || qtype == TYPE_ANY;
+        let mut records = Records::default();
+        if is(TYPE_A) && name_matches(message, name, &self.host_name()) {
+            records.a = true;
+        }
+        if let Some(service) = &self.service {
+            if is(TYPE_PTR) && name_matches(message, name, &SERVICES) {
+                records.services_ptr = true;
+            }
+            if is(TYPE_PTR) && name_matches(message, name, &self.service_name(service)) {
+                records.ptr = true;
+            }
+            let instance = self.instance_name(service);
+            if is(TYPE_SRV) && name_matches(message, name, &instance) {
+                records.srv = true;
+            }
+            if is(TYPE_TXT) && name_matches(message, name, &instance) {
+                records.txt = true;
+            }
+        }
+        records
+    }
+
+    /// Send `answers`, and `additional` records that are not already
+    /// answers.
+    fn respond(&self, id: u16, answers: Records, additional: Records, dst: Ipv4Addr, port: u16) {
+        let address = self.interface.config().address;
+        if address.is_unspecified() {
+            return;
+        }
+        let additional = additional.without(answers);
+        self.buffer.take().map(|buffer| {
+            let mut writer = Writer {
+                buffer,
+                offset: HEADER_LEN,
+            };
+            let written = self
+                .write_records(&mut writer, answers, address)
+                .and_then(|()| self.write_records(&mut writer, additional, address));
+            let len = writer.offset;
+            let buffer = writer.buffer;
+            if written.is_some() {
+                buffer[0..2].copy_from_slice(&id.to_be_bytes());
+                buffer[2..4].copy_from_slice(&FLAGS_RESPONSE.to_be_bytes());
+                buffer[4..6].copy_from_slice(&0u16.to_be_bytes());
+                buffer[6..8].copy_from_slice(&answers.count().to_be_bytes());
+                buffer[8..10].copy_from_slice(&0u16.to_be_bytes());
+                buffer[10..12].copy_from_slice(&additional.count().to_be_bytes());
+                // Responses are not retransmitted: peers query again.
+                let _ = self
+                    .interface
+                    .send_udp(self.socket, PORT, dst, port, &buffer[..len]);
+            }
+            self.buffer.replace(buffer);
+        });
+    }
+
+    fn write_records(
+        &self,
+        writer: &mut Writer,
+        records: Records,
+        address: Ipv4Addr,
+    ) -> Option<()> {
+        if records.a {
+            writer.record(
+                &self.host_name(),
+                TYPE_A,
+                CLASS_IN | CACHE_FLUSH,
+                HOST_TTL,
+                |w| w.bytes(&address.0),
+            )?;
+        }
+        if let Some(service) = &self.service {
+            let service_name = self.service_name(service);
+            let instance = self.instance_name(service);
+            if records.services_ptr {
+                writer.record(&SERVICES, TYPE_PTR, CLASS_IN, OTHER_TTL, |w| {
+                    w.name(&service_name)
+                })?;
+            }
+            if records.ptr {
+                writer.record(&service_name, TYPE_PTR, CLASS_IN, OTHER_TTL, |w| {
+                    w.name(&instance)
+                })?;
+            }
+            if records.srv {
+                writer.record(&instance, TYPE_SRV, CLASS_IN | CACHE_FLUSH, HOST_TTL, |w| {
+                    // Priority and weight.
+                    w.bytes(&[0, 0, 0, 0])?;
+                    w.bytes(&service.port.to_be_bytes())?;
+                    w.name(&self.host_name())
+                })?;
+            }
+            if records.txt {
+                writer.record(
+                    &instance,
+                    TYPE_TXT,
+                    CLASS_IN | CACHE_FLUSH,
+                    OTHER_TTL,
+                    |w| {
+                        // An empty TXT record is a single empty string.
+                        w.bytes(&[0])
+                    },
+                )?;
+            }
+        }
+        Some(())
+    }
+}
+
+/// Writes records after the header of a response.
+struct Writer {
+    buffer: &'static mut [u8],
+    offset: usize,
+}
+
+impl Writer {
+    fn bytes(&mut self, bytes: &[u8]) -> Option<()> {
+        self.buffer
+            .get_mut(self.offset..self.offset + bytes.len())?
+            .copy_from_slice(bytes);
+        self.offset += bytes.len();
+        Some(())
+    }
+
+    fn name(&mut self, labels: &[&[u8]]) -> Option<()> {
+        for label in labels {
+            self.bytes(&[label.len() as u8])?;
+            self.bytes(label)?;
+        }
+        self.bytes(&[0])
+    }
+
+    fn record<F: FnOnce(&mut Self) -> Option<()>>(
+        &mut self,
+        name: &[&[u8]],
+        rtype: u16,
+        class: u16,
+        ttl: u32,
+        rdata: F,
+    ) -> Option<()> {
+        self.name(name)?;
+        self.bytes(&rtype.to_be_bytes())?;
+        self.bytes(&class.to_be_bytes())?;
+        self.bytes(&ttl.to_be_bytes())?;
+        let len_offset = self.offset;
+        self.bytes(&[0, 0])?;
+        rdata(self)?;
+        let len = (self.offset - len_offset - 2) as u16;
+        self.buffer[len_offset..len_offset + 2].copy_from_slice(&len.to_be_bytes());
+        Some(())
+    }
+}
+
+/// Return the offset after the name at `offset` of `message`.
+fn skip_name(message: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let len = *message.get(offset)?;
+        if len & 0xC0 == 0xC0 {
+            return Some(offset + 2);
+        }
+        if len == 0 {
+            return Some(offset + 1);
+        }
+        offset += 1 + len as usize;
+    }
+}
+
+/// Whether the name at `offset` of `message` is `expected`, ignoring case
+/// and following compression pointers.
+fn name_matches(message: &[u8], mut offset: usize, expected: &[&[u8]]) -> bool {
+    let mut index = 0;
+    let mut pointers = 0;
+    loop {
+        let len = match message.get(offset) {
+            Some(len) => *len as usize,
+            None => return false,
+        };
+        if len & 0xC0 == 0xC0 {
+            pointers += 1;
+            match message.get(offset + 1) {
+                Some(low) if pointers <= MAX_POINTERS => {
+                    offset = (len & 0x3F) << 8 | *low as usize;
+                    continue;
+                }
+                _ => return false,
+            }
+        }
+        if len == 0 {
+            return index == expected.len();
+        }
+        let label = match message.get(offset + 1..offset + 1 + len) {
+            Some(label) => label,
+            None => return false,
+        };
+        match expected.get(index) {
+            Some(expected) if expected.eq_ignore_ascii_case(label) => {}
+            _ => return false,
+        }
+        index += 1;
+        offset += 1 + len;
+    }
+}
+
+impl<'a, I: Ipv4Interface<'a>> UdpClient for MdnsResponder<'a, I> {
+    fn receive(&self, src_addr: Ipv4Addr, src_port: u16, _dst_port: u16, payload: &[u8]) {
+        if payload.len() < HEADER_LEN {
+            return;
+        }
+        let id = u16::from_be_bytes([payload[0], payload[1]]);
+        let flags = u16::from_be_bytes([payload[2], payload[3]]);
+        // Only standard queries are answered.
+        if flags & 0xF800 != 0 {
+            return;
+        }
+        let questions = u16::from_be_bytes([payload[4], payload[5]]);
+
+        let mut answers = Records::default();
+        let mut unicast = false;
+        let mut offset = HEADER_LEN;
+        for _ in 0..questions {
+            let name = offset;
+            offset = match skip_name(payload, offset) {
+                Some(offset) if offset + 4 <= payload.len() => offset,
+                _ => return,
+            };
+            let qtype = u16::from_be_bytes([payload[offset], payload[offset + 1]]);
+            let qclass = u16::from_be_bytes([payload[offset + 2], payload[offset + 3]]);
+            offset += 4;
+            let class = qclass & !UNICAST_RESPONSE;
+            if class != CLASS_IN && class != CLASS_ANY {
+                continue;
+            }
+            let records = self.answers(payload, name, qtype);
+            if !records.is_empty() && qclass & UNICAST_RESPONSE != 0 {
+                unicast = true;
+            }
+            answers = answers.union(records);
+        }
+        if answers.is_empty() {
+            return;
+        }
+
+        let mut additional = Records::default();
+        if answers.ptr {
+            additional = Records::ALL;
+        } else if answers.srv {
+            additional.a = true;
+        }
+
+        if src_port != PORT {
+            // Legacy unicast query, from a resolver that is not an mDNS
+            // querier: reply to it directly, with its query ID.
+            self.respond(id, answers, additional, src_addr, src_port);
+        } else if unicast {
+            self.respond(0, answers, additional, src_addr, PORT);
+        } else {
+            self.respond(0, answers, additional, GROUP, PORT);
+        }
+    }
+
+    fn send_done(&self, _result: Result<(), ErrorCode>) {}
+}
+
+impl<'a, I: Ipv4Interface<'a>> LeaseClient for MdnsResponder<'a, I> {
+    fn lease_changed(&self, _config: Ipv4Config) {
+        self.announce();
+    }
+}
@@ -0,0 +1,173 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Minimal IPv4 and UDP stack over Ethernet.
+//!
+//! Unlike the IPv6 stack, which runs over 6LoWPAN, this stack sends and
+//! receives Ethernet frames through `hil::ethernet`. It supports ARP, ICMP
+//! echo, UDP and a minimal TCP, without fragmentation, which is enough for
+//! applications to send telemetry over a wired network, and for the board to
+//! be administered over it. The address of the interface is
+//! either configured statically or set later, for example by a DHCP client.
+//!
+//! - [`stack`] resolves addresses and sends and receives UDP datagrams for
+//!   in-kernel sockets.
+//! - [`driver`] exposes UDP sockets to userspace.
+//! - [`dhcp`] configures the address of an interface with DHCP.
+//! - [`mdns`] answers mDNS queries for the hostname of the board.
+//! - [`tcp`] accepts a TCP connection and exposes it as a byte stream.
+
+pub mod dhcp;
+pub mod driver;
+pub mod mdns;
+pub mod stack;
+pub mod tcp;
+
+use core::fmt;
+
+use kernel::hil::mac_address::MacAddress;
+use kernel::ErrorCode;
+
+use stack::{TcpPort, UdpSocket};
+
+/// An IPv4 address.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct Ipv4Addr(pub [u8; 4]);
+
+impl Ipv4Addr {
+    pub const UNSPECIFIED: Ipv4Addr = Ipv4Addr([0; 4]);
+    pub const BROADCAST: Ipv4Addr = Ipv4Addr([255; 4]);
+
+    pub const fn new(a: u8, b: u8, c: u8, d: u8) -> Self {
+        Ipv4Addr([a, b, c, d])
+    }
+
+    pub fn is_unspecified(&self) -> bool {
+        *self == Self::UNSPECIFIED
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        *self == Self::BROADCAST
+    }
+
+    /// Whether the address is in 224.0.0.0/4.
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 0xF0 == 0xE0
+    }
+}
+
+impl From<u32> for Ipv4Addr {
+    fn from(addr: u32) -> Self {
+        Ipv4Addr(addr.to_be_bytes())
+    }
+}
+
+impl From<Ipv4Addr> for u32 {
+    fn from(addr: Ipv4Addr) -> Self {
+        u32::from_be_bytes(addr.0)
+    }
+}
+
+impl fmt::Debug for Ipv4Addr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for Ipv4Addr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}.{}", self.0[0], self.0[1], self.0[2], self.0[3])
+    }
+}
+
+/// Address configuration of an interface.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Ipv4Config {
+    pub address: Ipv4Addr,
+    pub netmask: Ipv4Addr,
+    /// Router for destinations outside of the subnet, if any.
+    pub gateway: Option<Ipv4Addr>,
+}
+
+impl Ipv4Config {
+    /// Configuration of an interface without an address, for example
+    /// before DHCP completes.
+    pub const UNCONFIGURED: Ipv4Config = Ipv4Config {
+        address: Ipv4Addr::UNSPECIFIED,
+        netmask: Ipv4Addr::UNSPECIFIED,
+        gateway: None,
+    };
+
+    pub fn is_configured(&self) -> bool {
+        !self.address.is_unspecified()
+    }
+
+    /// Whether `addr` is on the subnet of the interface.
+    pub fn is_local(&self, addr: Ipv4Addr) -> bool {
+        let mask = u32::from(self.netmask);
+        u32::from(addr) & mask == u32::from(self.address) & mask
+    }
+
+    /// The broadcast address of the subnet.
+    pub fn subnet_broadcast(&self) -> Ipv4Addr {
+        Ipv4Addr::from(u32::from(self.address) | !u32::from(self.netmask))
+    }
+}
+
+/// An IPv4 network interface, used by protocols such as DHCP that
+/// configure the interface itself rather than only exchange datagrams.
+///
+/// [`stack::Ipv4Stack`] implements it for Ethernet.
+pub trait Ipv4Interface<'a> {
+    /// The current address configuration of the interface.
+    fn config(&self) -> Ipv4Config;
+
+    /// Change the address configuration of the interface.
+    fn set_config(&self, config: Ipv4Config);
+
+    /// The hardware address of the interface.
+    fn mac_address(&self) -> MacAddress;
+
+    /// Send `payload` from `src_port` to `dst_port` of `dst`. Completion is
+    /// reported to the client of `socket`.
+    fn send_udp(
+        &self,
+        socket: &'a UdpSocket<'a>,
+        src_port: u16,
+        dst: Ipv4Addr,
+        dst_port: u16,
+        payload: &[u8],
+    ) -> Result<(), ErrorCode>;
+
+    /// Send a TCP `segment`, with its header, to `dst`. The checksum is
+    /// filled in by the interface. Completion is reported to the handler of
+    /// `port`.
+    fn send_tcp(
+        &self,
+        port: &'a TcpPort<'a>,
+        dst: Ipv4Addr,
+        segment: &[u8],
+    ) -> Result<(), ErrorCode>;
+}
+
+/// Add `data` to a ones' complement sum, as used by the IPv4, ICMP and UDP
+/// checksums.
+pub(crate) fn checksum_add(mut sum: u32, data: &[u8]) -> u32 {
+    let mut chunks = data.chunks_exact(2);
+    for chunk in &mut chunks {
+        sum += u16::from_be_bytes([chunk[0], chunk[1]]) as u32;
+    }
+    if let [last] = chunks.remainder() {
+        sum += (*last as u32) << 8;
+    }
+    sum
+}
+
+/// Fold a ones' complement sum into the final checksum.
+pub(crate) fn checksum_finish(mut sum: u32) -> u16 {
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
@@ -0,0 +1,891 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! IPv4 over Ethernet with ARP, ICMP echo and UDP.
+//!
+//! The stack sends one frame at a time from a single frame buffer. A UDP
+//! datagram given to `send()` is built in that buffer right away. If the MAC
+//! address of the next hop is not in the ARP table yet, an ARP request is
+//! sent from a second, small buffer, and the datagram waits until the reply
+//! arrives or the request has been retried `ARP_RETRIES` times.
+//!
+//! In-kernel users, such as a DHCP client or the userspace driver, own a
+//! [`UdpSocket`]. A socket is bound to one port, or to every port no other
+//! socket is bound to with `bind_any()`.
+//!
+//! TCP is not implemented here: segments for the port of a [`TcpPort`] are
+//! given to its handler, such as [`super::tcp::TcpStream`], which runs the
+//! protocol and sends segments with `send_tcp()`.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let ipv4_stack = components::ipv4::Ipv4StackComponent::new(
+//!     &peripherals.enet,
+//!     mux_alarm,
+//!     capsules_extra::net::ipv4::Ipv4Config {
+//!         address: Ipv4Addr::new(192, 168, 1, 50),
+//!         netmask: Ipv4Addr::new(255, 255, 255, 0),
+//!         gateway: Some(Ipv4Addr::new(192, 168, 1, 1)),
+//!     },
+//! )
+//! .finalize(components::ipv4_stack_component_static!(
+//!     imxrt1050::enet::Enet<'static>,
+//!     imxrt1050::gpt::Gpt1<'static>,
+//! ));
+//! ```
+
+use core::cell::Cell;
+
+use super::{checksum_add, checksum_finish, Ipv4Addr, Ipv4Config, Ipv4Interface};
+use kernel::collections::list::{List, ListLink, ListNode};
+use kernel::hil::ethernet::{self, EthernetAdapter, HEADER_LEN, MAX_FRAME_LEN};
+use kernel::hil::mac_address::{Configure, MacAddress};
+use kernel::hil::time::{self, ConvertTicks};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+/// Length of the buffer datagrams are built in.
+pub const FRAME_BUF_LEN: usize = MAX_FRAME_LEN;
+/// Length of the buffer ARP messages are built in: a minimum size frame.
+pub const ARP_BUF_LEN: usize = 60;
+
+const IPV4_HEADER_LEN: usize = 20;
+const UDP_HEADER_LEN: usize = 8;
+const ICMP_HEADER_LEN: usize = 8;
+const TCP_HEADER_LEN: usize = 20;
+const ARP_LEN: usize = 28;
+
+/// Largest UDP payload that fits in a frame without fragmentation.
+pub const MAX_UDP_PAYLOAD: usize = MAX_FRAME_LEN - HEADER_LEN - IPV4_HEADER_LEN - UDP_HEADER_LEN;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_ARP: u16 = 0x0806;
+
+const PROTOCOL_ICMP: u8 = 1;
+const PROTOCOL_TCP: u8 = 6;
+const PROTOCOL_UDP: u8 = 17;
+
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_ECHO_REQUEST: u8 = 8;
+
+const ARP_REQUEST: u16 = 1;
+const ARP_REPLY: u16 = 2;
+
+const DEFAULT_TTL: u8 = 64;
+/// Don't Fragment flag of the IPv4 header.
+const FLAG_DF: u16 = 0x4000;
+
+const ARP_TABLE_LEN: usize = 4;
+const ARP_RETRIES: u8 = 3;
+const ARP_TIMEOUT_MS: u32 = 500;
+
+const BROADCAST_MAC: MacAddress = MacAddress::new([0xFF; 6]);
+
+/// Client of a UDP socket.
+pub trait UdpClient {
+    /// A datagram was received for `dst_port`, from `src_port` of
+    /// `src_addr`.
+    fn receive(&self, src_addr: Ipv4Addr, src_port: u16, dst_port: u16, payload: &[u8]);
+
+    /// The datagram given to `Ipv4Stack::send()` has been sent, or could not
+    /// be. `NOACK` means the MAC address of the next hop could not be
+    /// resolved.
+    fn send_done(&self, result: Result<(), ErrorCode>);
+}
+
+/// An in-kernel UDP socket.
+pub struct UdpSocket<'a> {
+    port: Cell<Option<u16>>,
+    any_port: Cell<bool>,
+    client: OptionalCell<&'a dyn UdpClient>,
+    next: ListLink<'a, UdpSocket<'a>>,
+}
+
+impl<'a> UdpSocket<'a> {
+    pub fn new() -> Self {
+        Self {
+            port: Cell::new(None),
+            any_port: Cell::new(false),
+            client: OptionalCell::empty(),
+            next: ListLink::empty(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn UdpClient) {
+        self.client.set(client);
+    }
+
+    /// The port the socket is bound to, if any.
+    pub fn port(&self) -> Option<u16> {
+        self.port.get()
+    }
+}
+
+impl<'a> ListNode<'a, UdpSocket<'a>> for UdpSocket<'a> {
+    fn next(&'a self) -> &'a ListLink<'a, UdpSocket<'a>> {
+        &self.next
+    }
+}
+
+/// Handler of the TCP segments for a port.
+pub trait TcpHandler {
+    /// A segment, with its TCP header and a valid checksum, was received from
+    /// `src_addr`.
+    fn receive_segment(&self, src_addr: Ipv4Addr, segment: &[u8]);
+
+    /// The segment given to `Ipv4Stack::send_tcp()` has been sent, or could
+    /// not be.
+    fn segment_sent(&self, result: Result<(), ErrorCode>);
+}
+
+/// A TCP port of the stack.
+pub struct TcpPort<'a> {
+    port: u16,
+    handler: OptionalCell<&'a dyn TcpHandler>,
+    next: ListLink<'a, TcpPort<'a>>,
+}
+
+impl<'a> TcpPort<'a> {
+    pub fn new(port: u16) -> Self {
+        Self {
+            port,
+            handler: OptionalCell::empty(),
+            next: ListLink::empty(),
+        }
+    }
+
+    pub fn set_handler(&self, handler: &'a dyn TcpHandler) {
+        self.handler.set(handler);
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl<'a> ListNode<'a, TcpPort<'a>> for TcpPort<'a> {
+    fn next(&'a self) -> &'a ListLink<'a, TcpPort<'a>> {
+        &self.next
+    }
+}
+
+/// User of the stack whose packet is in the frame buffer.
+#[derive(Clone, Copy)]
+enum Sender<'a> {
+    Udp(&'a UdpSocket<'a>),
+    Tcp(&'a TcpPort<'a>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Datagram {
+    Idle,
+    /// Waiting for the ARP reply of `next_hop`.
+    Resolving {
+        next_hop: Ipv4Addr,
+        retries: u8,
+    },
+    /// Built and addressed, waiting for the adapter.
+    Ready,
+    Sending,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum InFlight {
+    None,
+    Arp,
+    Datagram,
+}
+
+#[derive(Clone, Copy)]
+enum ArpMessage {
+    Request(Ipv4Addr),
+    Reply(Ipv4Addr, MacAddress),
+}
+
+enum NextHop {
+    Known(MacAddress),
+    Resolve(Ipv4Addr),
+}
+
+pub struct Ipv4Stack<'a, E: EthernetAdapter<'a> + Configure, A: time::Alarm<'a>> {
+    ethernet: &'a E,
+    alarm: &'a A,
+    config: Cell<Ipv4Config>,
+    sockets: List<'a, UdpSocket<'a>>,
+    tcp_ports: List<'a, TcpPort<'a>>,
+    frame: TakeCell<'static, [u8]>,
+    frame_len: Cell<usize>,
+    arp_frame: TakeCell<'static, [u8]>,
+    datagram: Cell<Datagram>,
+    in_flight: Cell<InFlight>,
+    arp_message: OptionalCell<ArpMessage>,
+    /// User whose packet is in `frame`. Empty for ICMP replies.
+    sender: OptionalCell<Sender<'a>>,
+    arp_table: [Cell<Option<(Ipv4Addr, MacAddress)>>; ARP_TABLE_LEN],
+    arp_next: Cell<usize>,
+    ip_id: Cell<u16>,
+}
+
+impl<'a, E: EthernetAdapter<'a> + Configure, A: time::Alarm<'a>> Ipv4Stack<'a, E, A> {
+    pub fn new(
+        ethernet: &'a E,
+        alarm: &'a A,
+        config: Ipv4Config,
+        frame: &'static mut [u8],
+        arp_frame: &'static mut [u8],
+    ) -> Self {
+        Self {
+            ethernet,
+            alarm,
+            config: Cell::new(config),
+            sockets: List::new(),
+            tcp_ports: List::new(),
+            frame: TakeCell::new(frame),
+            frame_len: Cell::new(0),
+            arp_frame: TakeCell::new(arp_frame),
+            datagram: Cell::new(Datagram::Idle),
+            in_flight: Cell::new(InFlight::None),
+            arp_message: OptionalCell::empty(),
+            sender: OptionalCell::empty(),
+            arp_table: Default::default(),
+            arp_next: Cell::new(0),
+            ip_id: Cell::new(0),
+        }
+    }
+
+    /// Enable the Ethernet adapter.
+    pub fn start(&self) -> Result<(), ErrorCode> {
+        self.ethernet.enable()
+    }
+
+    pub fn config(&self) -> Ipv4Config {
+        self.config.get()
+    }
+
+    /// Change the address of the interface. The ARP table is kept.
+    pub fn set_config(&self, config: Ipv4Config) {
+        self.config.set(config);
+    }
+
+    pub fn link_up(&self) -> bool {
+        self.ethernet.link_up()
+    }
+
+    /// Make `socket` able to send and receive datagrams. This must be
+    /// called once for each socket, before it is bound.
+    pub fn add_socket(&self, socket: &'a UdpSocket<'a>) {
+        self.sockets.push_head(socket);
+    }
+
+    /// Whether a socket is bound to `port`.
+    pub fn port_in_use(&self, port: u16) -> bool {
+        self.sockets
+            .iter()
+            .any(|socket| socket.port() == Some(port))
+    }
+
+    /// Bind `socket` to `port`. Returns `BUSY` if another socket is bound to
+    /// it.
+    pub fn bind(&self, socket: &'a UdpSocket<'a>, port: u16) -> Result<(), ErrorCode> {
+        if port == 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        if self
+            .sockets
+            .iter()
+            .any(|other| other.port() == Some(port) && !core::ptr::eq(other, socket))
+        {
+            return Err(ErrorCode::BUSY);
+        }
+        socket.port.set(Some(port));
+        Ok(())
+    }
+
+    /// Give `socket` the datagrams for every port no socket is bound to.
+    pub fn bind_any(&self, socket: &'a UdpSocket<'a>) {
+        socket.any_port.set(true);
+    }
+
+    pub fn unbind(&self, socket: &'a UdpSocket<'a>) {
+        socket.port.set(None);
+        socket.any_port.set(false);
+    }
+
+    /// Send `payload` from `src_port` to `dst_port` of `dst`. Completion is
+    /// reported to the client of `socket`.
+    ///
+    /// Returns `BUSY` if another datagram is being sent, `SIZE` if `payload`
+    /// does not fit in a frame, and `INVAL` if `dst` is outside of the subnet
+    /// and there is no gateway.
+    pub fn send(
+        &self,
+        socket: &'a UdpSocket<'a>,
+        src_port: u16,
+        dst: Ipv4Addr,
+        dst_port: u16,
+        payload: &[u8],
+    ) -> Result<(), ErrorCode> {
+        if payload.len() > MAX_UDP_PAYLOAD {
+            return Err(ErrorCode::SIZE);
+        }
+        self.send_packet(Sender::Udp(socket), dst, |frame| {
+            self.build_udp(frame, src_port, dst, dst_port, payload)
+        })
+    }
+
+    /// Make `port` receive the TCP segments sent to it. Returns `BUSY` if
+    /// another handler has the same port.
+    pub fn add_tcp_port(&self, port: &'a TcpPort<'a>) -> Result<(), ErrorCode> {
+        if self.tcp_ports.iter().any(|other| other.port == port.port) {
+            return Err(ErrorCode::BUSY);
+        }
+        self.tcp_ports.push_head(port);
+        Ok(())
+    }
+
+    /// Send a TCP `segment`, with its header, to `dst`. The checksum of the
+    /// segment is filled in by the stack. Completion is reported to the
+    /// handler of `port`.
+    ///
+    /// Returns the same errors as `send()`.
+    pub fn send_tcp(
+        &self,
+        port: &'a TcpPort<'a>,
+        dst: Ipv4Addr,
+        segment: &[u8],
+    ) -> Result<(), ErrorCode> {
+        if segment.len() > MAX_FRAME_LEN - HEADER_LEN - IPV4_HEADER_LEN {
+            return Err(ErrorCode::SIZE);
+        }
+        self.send_packet(Sender::Tcp(port), dst, |frame| {
+            self.build_tcp(frame, dst, segment)
+        })
+    }
+
+    /// Build a packet for `dst` in the frame buffer with `build`, and send it
+    /// once the MAC address of the next hop is known.
+    fn send_packet<F: FnOnce(&mut [u8]) -> Result<usize, ErrorCode>>(
+        &self,
+        sender: Sender<'a>,
+        dst: Ipv4Addr,
+        build: F,
+    ) -> Result<(), ErrorCode> {
+        if self.datagram.get() != Datagram::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        let next_hop = self.next_hop(dst)?;
+
+        let len = self.frame.map_or(Err(ErrorCode::BUSY), build)?;
+        self.frame_len.set(len);
+        self.sender.set(sender);
+
+        match next_hop {
+            NextHop::Known(mac) => {
+                self.address_frame(mac);
+                if self.in_flight.get() == InFlight::None {
+                    self.transmit_datagram().map_err(|e| {
+                        self.datagram.set(Datagram::Idle);
+                        self.sender.clear();
+                        e
+                    })?;
+                }
+            }
+            NextHop::Resolve(next_hop) => {
+                self.datagram.set(Datagram::Resolving {
+                    next_hop,
+                    retries: 0,
+                });
+                self.request_mac(next_hop);
+            }
+        }
+        Ok(())
+    }
+
+    fn next_hop(&self, dst: Ipv4Addr) -> Result<NextHop, ErrorCode> {
+        let config = self.config.get();
+        if dst.is_broadcast() || (config.is_configured() && dst == config.subnet_broadcast()) {
+            return Ok(NextHop::Known(BROADCAST_MAC));
+        }
+        if dst.is_multicast() {
+            return Ok(NextHop::Known(MacAddress::new([
+                0x01,
+                0x00,
+                0x5E,
+                dst.0[1] & 0x7F,
+                dst.0[2],
+                dst.0[3],
+            ])));
+        }
+        let next_hop = if config.is_local(dst) {
+            dst
+        } else {
+            config.gateway.ok_or(ErrorCode::INVAL)?
+        };
+        Ok(self
+            .arp_lookup(next_hop)
+            .map_or(NextHop::Resolve(next_hop), NextHop::Known))
+    }
+
+    fn arp_lookup(&self, addr: Ipv4Addr) -> Option<MacAddress> {
+        self.arp_table
+            .iter()
+            .filter_map(|entry| entry.get())
+            .find(|(ip, _)| *ip == addr)
+            .map(|(_, mac)| mac)
+    }
+
+    fn arp_insert(&self, addr: Ipv4Addr, mac: MacAddress) {
+        if let Some(entry) = self
+            .arp_table
+            .iter()
+            .find(|entry| entry.get().map_or(false, |(ip, _)| ip == addr))
+        {
+            entry.set(Some((addr, mac)));
+        } else {
+            let index = self.arp_next.get();
+            self.arp_table[index].set(Some((addr, mac)));
+            self.arp_next.set((index + 1) % ARP_TABLE_LEN);
+        }
+    }
+
+    fn request_mac(&self, next_hop: Ipv4Addr) {
+        self.arp_message.set(ArpMessage::Request(next_hop));
+        self.alarm
+            .set_alarm(self.alarm.now(), self.alarm.ticks_from_ms(ARP_TIMEOUT_MS));
+        self.send_pending();
+    }
+
+    /// Write the Ethernet header of the datagram in `frame`, which is now
+    /// ready to be sent.
+    fn address_frame(&self, dst: MacAddress) {
+        let src = self.ethernet.get_mac_address();
+        self.frame.map(|frame| {
+            write_ethernet_header(frame, dst, src, ETHERTYPE_IPV4);
+        });
+        self.datagram.set(Datagram::Ready);
+    }
+
+    fn transmit_datagram(&self) -> Result<(), ErrorCode> {
+        let frame = self.frame.take().ok_or(ErrorCode::BUSY)?;
+        match self.ethernet.transmit(frame, self.frame_len.get()) {
+            Ok(()) => {
+                self.datagram.set(Datagram::Sending);
+                self.in_flight.set(InFlight::Datagram);
+                Ok(())
+            }
+            Err((e, frame)) => {
+                self.frame.replace(frame);
+                Err(e)
+            }
+        }
+    }
+
+    /// Send the pending ARP message, if the interface is idle.
+    fn send_pending(&self) {
+        if self.in_flight.get() != InFlight::None {
+            return;
+        }
+        if let Some(message) = self.arp_message.take() {
+            if let Some(frame) = self.arp_frame.take() {
+                let len = self.build_arp(frame, message);
+                match self.ethernet.transmit(frame, len) {
+                    Ok(()) => {
+                        self.in_flight.set(InFlight::Arp);
+                    }
+                    Err((_, frame)) => {
+                        // Requests are retried when the alarm fires, and the
+                        // peer retries its own requests.
+                        self.arp_frame.replace(frame);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send the datagram if it is ready, from a callback.
+    fn send_ready_datagram(&self) {
+        if self.in_flight.get() == InFlight::None && self.datagram.get() == Datagram::Ready {
+            if let Err(e) = self.transmit_datagram() {
+                self.finish_datagram(Err(e));
+            }
+        }
+    }
+
+    fn finish_datagram(&self, result: Result<(), ErrorCode>) {
+        self.datagram.set(Datagram::Idle);
+        match self.sender.take() {
+            Some(Sender::Udp(socket)) => {
+                socket.client.map(|client| client.send_done(result));
+            }
+            Some(Sender::Tcp(port)) => {
+                port.handler.map(|handler| handler.segment_sent(result));
+            }
+            None => {}
+        }
+    }
+
+    fn next_ip_id(&self) -> u16 {
+        let id = self.ip_id.get();
+        self.ip_id.set(id.wrapping_add(1));
+        id
+    }
+
+    fn write_ipv4_header(&self, header: &mut [u8], protocol: u8, dst: Ipv4Addr, total_len: usize) {
+        let src = self.config.get().address;
+        header[0] = 0x45;
+        header[1] = 0;
+        header[2..4].copy_from_slice(&(total_len as u16).to_be_bytes());
+        header[4..6].copy_from_slice(&self.next_ip_id().to_be_bytes());
+        header[6..8].copy_from_slice(&FLAG_DF.to_be_bytes());
+        header[8] = DEFAULT_TTL;
+        header[9] = protocol;
+        header[10..12].copy_from_slice(&[0, 0]);
+        header[12..16].copy_from_slice(&src.0);
+        header[16..20].copy_from_slice(&dst.0);
+        let checksum = checksum_finish(checksum_add(0, &header[..IPV4_HEADER_LEN]));
+        header[10..12].copy_from_slice(&checksum.to_be_bytes());
+    }
+
+    /// Build a UDP datagram after the Ethernet header of `frame`, and return
+    /// the length of the frame.
+    fn build_udp(
+        &self,
+        frame: &mut [u8],
+        src_port: u16,
+        dst: Ipv4Addr,
+        dst_port: u16,
+        payload: &[u8],
+    ) -> Result<usize, ErrorCode> {
+        let udp_len = UDP_HEADER_LEN + payload.len();
+        let ip_len = IPV4_HEADER_LEN + udp_len;
+        let packet = frame
+            .get_mut(HEADER_LEN..HEADER_LEN + ip_len)
+            .ok_or(ErrorCode::SIZE)?;
+        let (header, udp) = packet.split_at_mut(IPV4_HEADER_LEN);
+        self.write_ipv4_header(header, PROTOCOL_UDP, dst, ip_len);
+
+        udp[0..2].copy_from_slice(&src_port.to_be_bytes());
+        udp[2..4].copy_from_slice(&dst_port.to_be_bytes());
+        udp[4..6].copy_from_slice(&(udp_len as u16).to_be_bytes());
+        udp[6..8].copy_from_slice(&[0, 0]);
+        udp[UDP_HEADER_LEN..].copy_from_slice(payload);
+
+        let sum = pseudo_header_sum(self.config.get().address, dst, PROTOCOL_UDP, udp_len);
+        let checksum = match checksum_finish(checksum_add(sum, udp)) {
+            // Zero means that there is no checksum.
+            0 => 0xFFFF,
+            checksum => checksum,
+        };
+        udp[6..8].copy_from_slice(&checksum.to_be_bytes());
+        Ok(HEADER_LEN + ip_len)
+    }
+
+    /// Build a TCP segment after the Ethernet header of `frame`, and return
+    /// the length of the frame.
+    fn build_tcp(
+        &self,
+        frame: &mut [u8],
+        dst: Ipv4Addr,
+        segment: &[u8],
+    ) -> Result<usize, ErrorCode> {
+        let ip_len = IPV4_HEADER_LEN + segment.len();
+        let packet = frame
+            .get_mut(HEADER_LEN..HEADER_LEN + ip_len)
+            .ok_or(ErrorCode::SIZE)?;
+        let (header, tcp) = packet.split_at_mut(IPV4_HEADER_LEN);
+        self.write_ipv4_header(header, PROTOCOL_TCP, dst, ip_len);
+
+        tcp.copy_from_slice(segment);
+        tcp[16..18].copy_from_slice(&[0, 0]);
+        let sum = pseudo_header_sum(self.config.get().address, dst, PROTOCOL_TCP, segment.len());
+        let checksum = checksum_finish(checksum_add(sum, tcp));
+        tcp[16..18].copy_from_slice(&checksum.to_be_bytes());
+        Ok(HEADER_LEN + ip_len)
+    }
+
+    fn build_arp(&self, frame: &mut [u8], message: ArpMessage) -> usize {
+        let src_mac = self.ethernet.get_mac_address();
+        let src_ip = self.config.get().address;
+        let (operation, dst_mac, target_mac, target_ip) = match message {
+            ArpMessage::Request(ip) => (ARP_REQUEST, BROADCAST_MAC, MacAddress::default(), ip),
+            ArpMessage::Reply(ip, mac) => (ARP_REPLY, mac, mac, ip),
+        };
+
+        write_ethernet_header(frame, dst_mac, src_mac, ETHERTYPE_ARP);
+        let arp = &mut frame[HEADER_LEN..HEADER_LEN + ARP_LEN];
+        arp[0..2].copy_from_slice(&1u16.to_be_bytes());
+        arp[2..4].copy_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        arp[4] = 6;
+        arp[5] = 4;
+        arp[6..8].copy_from_slice(&operation.to_be_bytes());
+        arp[8..14].copy_from_slice(src_mac.as_bytes());
+        arp[14..18].copy_from_slice(&src_ip.0);
+        arp[18..24].copy_from_slice(target_mac.as_bytes());
+        arp[24..28].copy_from_slice(&target_ip.0);
+        // Pad to the minimum frame size.
+        for byte in frame[HEADER_LEN + ARP_LEN..ARP_BUF_LEN].iter_mut() {
+            *byte = 0;
+        }
+        ARP_BUF_LEN
+    }
+
+    fn receive_arp(&self, arp: &[u8]) {
+        if arp.len() < ARP_LEN
+            || arp[0..2] != 1u16.to_be_bytes()
+            || arp[2..4] != ETHERTYPE_IPV4.to_be_bytes()
+            || arp[4] != 6
+            || arp[5] != 4
+        {
+            return;
+        }
+        let operation = u16::from_be_bytes([arp[6], arp[7]]);
+        let sender_mac = MacAddress::new([arp[8], arp[9], arp[10], arp[11], arp[12], arp[13]]);
+        let sender_ip = Ipv4Addr([arp[14], arp[15], arp[16], arp[17]]);
+        let target_ip = Ipv4Addr([arp[24], arp[25], arp[26], arp[27]]);
+
+        let config = self.config.get();
+        let for_us = config.is_configured() && target_ip == config.address;
+        if for_us || self.arp_lookup(sender_ip).is_some() {
+            self.arp_insert(sender_ip, sender_mac);
+        }
+
+        if let Datagram::Resolving { next_hop, .. } = self.datagram.get() {
+            if next_hop == sender_ip {
+                self.arp_insert(sender_ip, sender_mac);
+                let _ = self.alarm.disarm();
+                self.address_frame(sender_mac);
+            }
+        }
+
+        if operation == ARP_REQUEST && for_us {
+            self.arp_message
+                .set(ArpMessage::Reply(sender_ip, sender_mac));
+        }
+        self.send_pending();
+        self.send_ready_datagram();
+    }
+
+    fn receive_ipv4(&self, src_mac: MacAddress, packet: &[u8]) {
+        if packet.len() < IPV4_HEADER_LEN || packet[0] >> 4 != 4 {
+            return;
+        }
+        let header_len = (packet[0] & 0x0F) as usize * 4;
+        let total_len = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+        if header_len < IPV4_HEADER_LEN || total_len < header_len || total_len > packet.len() {
+            return;
+        }
+        // Fragments are not reassembled.
+        if u16::from_be_bytes([packet[6], packet[7]]) & 0x3FFF != 0 {
+            return;
+        }
+        if checksum_finish(checksum_add(0, &packet[..header_len])) != 0 {
+            return;
+        }
+
+        let src = Ipv4Addr([packet[12], packet[13], packet[14], packet[15]]);
+        let dst = Ipv4Addr([packet[16], packet[17], packet[18], packet[19]]);
+        let config = self.config.get();
+        let unicast = config.is_configured() && dst == config.address;
+        let accepted = unicast
+            || dst.is_broadcast()
+            || dst.is_multicast()
+            || (config.is_configured() && dst == config.subnet_broadcast());
+        if !accepted {
+            return;
+        }
+
+        let payload = &packet[header_len..total_len];
+        match packet[9] {
+            PROTOCOL_UDP => self.receive_udp(src, dst, payload),
+            PROTOCOL_TCP if unicast => self.receive_tcp(src, dst, payload),
+            PROTOCOL_ICMP if unicast => self.receive_icmp(src_mac, src, payload),
+            _ => {}
+        }
+    }
+
+    fn receive_udp(&self, src: Ipv4Addr, dst: Ipv4Addr, segment: &[u8]) {
+        if segment.len() < UDP_HEADER_LEN {
+            return;
+        }
+        let udp_len = u16::from_be_bytes([segment[4], segment[5]]) as usize;
+        if udp_len < UDP_HEADER_LEN || udp_len > segment.len() {
+            return;
+        }
+        let segment = &segment[..udp_len];
+        if segment[6..8] != [0, 0] {
+            let sum = pseudo_header_sum(src, dst, PROTOCOL_UDP, udp_len);
+            if checksum_finish(checksum_add(sum, segment)) != 0 {
+                return;
+            }
+        }
+
+        let src_port = u16::from_be_bytes([segment[0], segment[1]]);
+        let dst_port = u16::from_be_bytes([segment[2], segment[3]]);
+        let payload = &segment[UDP_HEADER_LEN..];
+
+        if let Some(socket) = self
+            .sockets
+            .iter()
+            .find(|socket| socket.port() == Some(dst_port))
+        {
+            socket
+                .client
+                .map(|client| client.receive(src, src_port, dst_port, payload));
+        } else {
+            for socket in self.sockets.iter().filter(|socket| socket.any_port.get()) {
+                socket
+                    .client
+                    .map(|client| client.receive(src, src_port, dst_port, payload));
+            }
+        }
+    }
+
+    fn receive_tcp(&self, src: Ipv4Addr, dst: Ipv4Addr, segment: &[u8]) {
+        if segment.len() < TCP_HEADER_LEN {
+            return;
+        }
+        let sum = pseudo_header_sum(src, dst, PROTOCOL_TCP, segment.len());
+        if checksum_finish(checksum_add(sum, segment)) != 0 {
+            return;
+        }
+        let dst_port = u16::from_be_bytes([segment[2], segment[3]]);
+        if let Some(port) = self.tcp_ports.iter().find(|port| port.port == dst_port) {
+            port.handler
+                .map(|handler| handler.receive_segment(src, segment));
+        }
+    }
+
+    /// Answer echo requests, if the frame buffer is free.
+    fn receive_icmp(&self, src_mac: MacAddress, src: Ipv4Addr, message: &[u8]) {
+        if message.len() < ICMP_HEADER_LEN
+            || message[0] != ICMP_ECHO_REQUEST
+            || message[1] != 0
+            || self.datagram.get() != Datagram::Idle
+        {
+            return;
+        }
+        let ip_len = IPV4_HEADER_LEN + message.len();
+        let built = self.frame.map_or(false, |frame| {
+            let packet = match frame.get_mut(HEADER_LEN..HEADER_LEN + ip_len) {
+                Some(packet) => packet,
+                None => return false,
+            };
+            let (header, reply) = packet.split_at_mut(IPV4_HEADER_LEN);
+            self.write_ipv4_header(header, PROTOCOL_ICMP, src, ip_len);
+            reply.copy_from_slice(message);
+            reply[0] = ICMP_ECHO_REPLY;
+            reply[2..4].copy_from_slice(&[0, 0]);
+            let checksum = checksum_finish(checksum_add(0, reply));
+            reply[2..4].copy_from_slice(&checksum.to_be_bytes());
+            true
+        });
+        if built {
+            self.frame_len.set(HEADER_LEN + ip_len);
+            self.address_frame(src_mac);
+            self.send_ready_datagram();
+        }
+    }
+}
+
+fn write_ethernet_header(frame: &mut [u8], dst: MacAddress, src: MacAddress, ethertype: u16) {
+    frame[0..6].copy_from_slice(dst.as_bytes());
+    frame[6..12].copy_from_slice(src.as_bytes());
+    frame[12..14].copy_from_slice(&ethertype.to_be_bytes());
+}
+
+fn pseudo_header_sum(src: Ipv4Addr, dst: Ipv4Addr, protocol: u8, len: usize) -> u32 {
+    let sum = checksum_add(0, &src.0);
+    let sum = checksum_add(sum, &dst.0);
+    sum + protocol as u32 + len as u32
+}
+
+impl<'a, E: EthernetAdapter<'a> + Configure, A: time::Alarm<'a>> Ipv4Interface<'a>
+    for Ipv4Stack<'a, E, A>
+{
+    fn config(&self) -> Ipv4Config {
+        self.config.get()
+    }
+
+    fn set_config(&self, config: Ipv4Config) {
+        self.config.set(config);
+    }
+
+    fn mac_address(&self) -> MacAddress {
+        self.ethernet.get_mac_address()
+    }
+
+    fn send_udp(
+        &self,
+        socket: &'a UdpSocket<'a>,
+        src_port: u16,
+        dst: Ipv4Addr,
+        dst_port: u16,
+        payload: &[u8],
+    ) -> Result<(), ErrorCode> {
+        self.send(socket, src_port, dst, dst_port, payload)
+    }
+
+    fn send_tcp(
+        &self,
+        port: &'a TcpPort<'a>,
+        dst: Ipv4Addr,
+        segment: &[u8],
+    ) -> Result<(), ErrorCode> {
+        Ipv4Stack::send_tcp(self, port, dst, segment)
+    }
+}
+
+impl<'a, E: EthernetAdapter<'a> + Configure, A: time::Alarm<'a>> ethernet::RxClient
+    for Ipv4Stack<'a, E, A>
+{
+    fn received_frame(&self, frame: &[u8]) {
+        if frame.len() < HEADER_LEN {
+            return;
+        }
+        let src_mac =
+            MacAddress::new([frame[6], frame[7], frame[8], frame[9], frame[10], frame[11]]);
+        match u16::from_be_bytes([frame[12], frame[13]]) {
+            ETHERTYPE_ARP => self.receive_arp(&frame[HEADER_LEN..]),
+            ETHERTYPE_IPV4 => self.receive_ipv4(src_mac, &frame[HEADER_LEN..]),
+            _ => {}
+        }
+    }
+}
+
+impl<'a, E: EthernetAdapter<'a> + Configure, A: time::Alarm<'a>> ethernet::TxClient
+    for Ipv4Stack<'a, E, A>
+{
+    fn transmit_done(&self, frame: &'static mut [u8], result: Result<(), ErrorCode>) {
+        let in_flight = self.in_flight.replace(InFlight::None);
+        match in_flight {
+            InFlight::Arp => self.arp_frame.replace(frame),
+            _ => self.frame.replace(frame),
+        };
+        if in_flight == InFlight::Datagram {
+            self.finish_datagram(result);
+        }
+        self.send_pending();
+        self.send_ready_datagram();
+    }
+}
+
+impl<'a, E: EthernetAdapter<'a> + Configure, A: time::Alarm<'a>> time::AlarmClient
+    for Ipv4Stack<'a, E, A>
+{
+    fn alarm(&self) {
+        if let Datagram::Resolving { next_hop, retries } = self.datagram.get() {
+            if retries + 1 >= ARP_RETRIES {
+                self.finish_datagram(Err(ErrorCode::NOACK));
+            } else {
+                self.datagram.set(Datagram::Resolving {
+                    next_hop,
+                    retries: retries + 1,
+                });
+                self.request_mac(next_hop);
+            }
+        }
+    }
+}
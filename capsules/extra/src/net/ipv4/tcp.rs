@@ -0,0 +1,613 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Single connection TCP server, exposed as a byte stream.
+//!
+//! `TcpStream` listens on one port and accepts one connection at a time. The
+//! connection is exposed through the `hil::uart` traits, like the USB CDC and
+//! Segger RTT consoles, so that capsules written for a UART, such as the
+//! process console, can be used over the network.
+//!
+//! The implementation is minimal, as it is meant for interactive consoles:
+//!
+//! - One segment is in flight at a time. It is retransmitted with an
+//!   exponential backoff, and the connection is dropped after `MAX_RETRIES`
+//!   retransmissions.
+//! - Received data is buffered in a small ring, whose free space is the
+//!   advertised window. Out of order segments are dropped.
+//! - The connection is closed when the peer closes it. Data written while no
+//!   peer is connected is dropped.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let tcp_stream = components::tcp_stream::TcpStreamComponent::new(ipv4_stack, mux_alarm, 2323)
+//!     .finalize(components::tcp_stream_component_static!(
+//!         imxrt1050::enet::Enet<'static>,
+//!         imxrt1050::gpt::Gpt1<'static>,
+//!     ));
+//! ```
+
+use core::cell::Cell;
+use core::cmp;
+
+use super::stack::{TcpHandler, TcpPort};
+use super::{Ipv4Addr, Ipv4Interface};
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
+use kernel::hil::time::{self, ConvertTicks, Ticks};
+use kernel::hil::uart;
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+/// Largest payload of the segments sent, which is also the MSS advertised
+/// to peers. This is the default MSS of TCP, so that it does not need to be
+/// negotiated down.
+pub const MSS: usize = 536;
+
+const HEADER_LEN: usize = 20;
+const MSS_OPTION_LEN: usize = 4;
+
+/// Length of the buffer segments are built in.
+pub const SEGMENT_BUF_LEN: usize = HEADER_LEN + MSS_OPTION_LEN + MSS;
+/// Length of the buffer received data waits in until it is read.
+pub const RX_BUF_LEN: usize = 256;
+
+const FIN: u8 = 0x01;
+const SYN: u8 = 0x02;
+const RST: u8 = 0x04;
+const PSH: u8 = 0x08;
+const ACK: u8 = 0x10;
+
+const OPTION_END: u8 = 0;
+const OPTION_NOP: u8 = 1;
+const OPTION_MSS: u8 = 2;
+
+const INITIAL_RTO_MS: u32 = 1000;
+const MAX_RTO_MS: u32 = 16000;
+const MAX_RETRIES: u8 = 6;
+/// Delay before sending again when the stack was busy.
+const BUSY_RETRY_MS: u32 = 10;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum State {
+    Listen,
+    SynReceived,
+    Established,
+    /// The peer closed the connection, and our FIN has not been acknowledged
+    /// yet.
+    LastAck,
+}
+
+/// Segment that occupies sequence space and has not been acknowledged.
+#[derive(Clone, Copy, PartialEq)]
+enum Flight {
+    None,
+    Syn,
+    Data(usize),
+    Fin,
+}
+
+pub struct TcpStream<'a, I: Ipv4Interface<'a>, A: time::Alarm<'a>> {
+    interface: &'a I,
+    port: &'a TcpPort<'a>,
+    alarm: &'a A,
+    deferred_call: DeferredCall,
+
+    state: Cell<State>,
+    remote_addr: Cell<Ipv4Addr>,
+    remote_port: Cell<u16>,
+    /// Oldest unacknowledged sequence number.
+    snd_una: Cell<u32>,
+    /// Next sequence number expected from the peer.
+    rcv_nxt: Cell<u32>,
+    peer_window: Cell<u16>,
+    peer_mss: Cell<u16>,
+    flight: Cell<Flight>,
+    retries: Cell<u8>,
+    rto_ms: Cell<u32>,
+    /// The last attempt to send `flight` failed, and will be repeated.
+    send_failed: Cell<bool>,
+    /// A zero window was advertised, so the peer must be told when data is
+    /// read.
+    window_closed: Cell<bool>,
+    segment: TakeCell<'static, [u8]>,
+
+    tx_client: OptionalCell<&'a dyn uart::TransmitClient>,
+    tx_buffer: TakeCell<'static, [u8]>,
+    tx_len: Cell<usize>,
+    tx_acked: Cell<usize>,
+    /// `tx_buffer` was dropped because there is no connection.
+    tx_dropped: Cell<bool>,
+
+    rx_client: OptionalCell<&'a dyn uart::ReceiveClient>,
+    rx_ring: TakeCell<'static, [u8]>,
+    rx_head: Cell<usize>,
+    rx_count: Cell<usize>,
+    rx_buffer: TakeCell<'static, [u8]>,
+    rx_len: Cell<usize>,
+    rx_pos: Cell<usize>,
+    rx_done: Cell<bool>,
+    rx_aborted: Cell<bool>,
+}
+
+impl<'a, I: Ipv4Interface<'a>, A: time::Alarm<'a>> TcpStream<'a, I, A> {
+    pub fn new(
+        interface: &'a I,
+        port: &'a TcpPort<'a>,
+        alarm: &'a A,
+        segment: &'static mut [u8],
+        rx_ring: &'static mut [u8],
+    ) -> Self {
+        Self {
+            interface,
+            port,
+            alarm,
+            deferred_call: DeferredCall::new(),
+            state: Cell::new(State::Listen),
+            remote_addr: Cell::new(Ipv4Addr::UNSPECIFIED),
+            remote_port: Cell::new(0),
+            snd_una: Cell::new(0),
+            rcv_nxt: Cell::new(0),
+            peer_window: Cell::new(0),
+            peer_mss: Cell::new(MSS as u16),
+            flight: Cell::new(Flight::None),
+            retries: Cell::new(0),
+            rto_ms: Cell::new(INITIAL_RTO_MS),
+            send_failed: Cell::new(false),
+            window_closed: Cell::new(false),
+            segment: TakeCell::new(segment),
+            tx_client: OptionalCell::empty(),
+            tx_buffer: TakeCell::empty(),
+            tx_len: Cell::new(0),
+            tx_acked: Cell::new(0),
+            tx_dropped: Cell::new(false),
+            rx_client: OptionalCell::empty(),
+            rx_ring: TakeCell::new(rx_ring),
+            rx_head: Cell::new(0),
+            rx_count: Cell::new(0),
+            rx_buffer: TakeCell::empty(),
+            rx_len: Cell::new(0),
+            rx_pos: Cell::new(0),
+            rx_done: Cell::new(false),
+            rx_aborted: Cell::new(false),
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state.get()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state.get() == State::Established
+    }
+
+    fn window(&self) -> usize {
+        let capacity = self.rx_ring.map_or(0, |ring| ring.len());
+        cmp::min(capacity - self.rx_count.get(), u16::MAX as usize)
+    }
+
+    /// Send a segment with `len` bytes of the transmit buffer that have not
+    /// been acknowledged yet.
+    fn send_segment(&self, flags: u8, seq: u32, len: usize) -> Result<(), ErrorCode> {
+        let window = self.window();
+        self.window_closed.set(window == 0);
+        self.segment.map_or(Err(ErrorCode::BUSY), |segment| {
+            let options_len = if flags & SYN != 0 { MSS_OPTION_LEN } else { 0 };
+            let header_len = HEADER_LEN + options_len;
+            let ack = if flags & ACK != 0 {
+                self.rcv_nxt.get()
+            } else {
+                0
+            };
+            segment[0..2].copy_from_slice(&self.port.port().to_be_bytes());
+            segment[2..4].copy_from_slice(&self.remote_port.get().to_be_bytes());
+            segment[4..8].copy_from_slice(&seq.to_be_bytes());
+            segment[8..12].copy_from_slice(&ack.to_be_bytes());
+            segment[12] = ((header_len / 4) as u8) << 4;
+            segment[13] = flags;
+            segment[14..16].copy_from_slice(&(window as u16).to_be_bytes());
+            // Checksum, filled in by the interface, and urgent pointer.
+            segment[16..20].copy_from_slice(&[0; 4]);
+            if options_len > 0 {
+                segment[20] = OPTION_MSS;
+                segment[21] = MSS_OPTION_LEN as u8;
+                segment[22..24].copy_from_slice(&(MSS as u16).to_be_bytes());
+            }
+            if len > 0 {
+                let offset = self.tx_acked.get();
+                self.tx_buffer.map(|data| {
+                    segment[header_len..header_len + len]
+                        .copy_from_slice(&data[offset..offset + len]);
+                });
+            }
+            self.interface.send_tcp(
+                self.port,
+                self.remote_addr.get(),
+                &segment[..header_len + len],
+            )
+        })
+    }
+
+    fn send_ack(&self) {
+        // Lost acknowledgements are recovered by the retransmissions of the
+        // peer.
+        let _ = self.send_segment(ACK, self.snd_una.get(), 0);
+    }
+
+    /// Send the segment in flight, and set the alarm to send it again.
+    fn send_flight(&self) {
+        let snd_una = self.snd_una.get();
+        let result = match self.flight.get() {
+            Flight::None => return,
+            Flight::Syn => self.send_segment(SYN | ACK, snd_una, 0),
+            Flight::Data(len) => self.send_segment(PSH | ACK, snd_una, len),
+            Flight::Fin => self.send_segment(FIN | ACK, snd_una, 0),
+        };
+        self.send_failed.set(result.is_err());
+        let delay = if result.is_ok() {
+            self.rto_ms.get()
+        } else {
+            BUSY_RETRY_MS
+        };
+        self.alarm
+            .set_alarm(self.alarm.now(), self.alarm.ticks_from_ms(delay));
+    }
+
+    /// Start sending the next segment, if there is one and no other segment
+    /// is in flight. Returns whether a segment was sent.
+    fn transmit_next(&self) -> bool {
+        if self.flight.get() != Flight::None {
+            return false;
+        }
+        let flight = match self.state.get() {
+            State::Established => {
+                let left = self.tx_len.get() - self.tx_acked.get();
+                let len = cmp::min(
+                    cmp::min(left, self.peer_mss.get() as usize),
+                    cmp::min(self.peer_window.get() as usize, MSS),
+                );
+                if self.tx_buffer.is_none() || len == 0 {
+                    return false;
+                }
+                Flight::Data(len)
+            }
+            State::LastAck => Flight::Fin,
+            State::Listen | State::SynReceived => return false,
+        };
+        self.flight.set(flight);
+        self.retries.set(0);
+        self.rto_ms.set(INITIAL_RTO_MS);
+        self.send_flight();
+        true
+    }
+
+    /// Drop the connection and listen for a new one.
+    fn reset(&self) {
+        let _ = self.alarm.disarm();
+        self.state.set(State::Listen);
+        self.flight.set(Flight::None);
+        self.rx_head.set(0);
+        self.rx_count.set(0);
+        self.tx_buffer.take().map(|buffer| {
+            self.tx_client.map(move |client| {
+                client.transmitted_buffer(buffer, self.tx_len.get(), Err(ErrorCode::FAIL))
+            });
+        });
+    }
+
+    fn accept(&self, src_addr: Ipv4Addr, src_port: u16, seq: u32, window: u16, mss: u16) {
+        self.remote_addr.set(src_addr);
+        self.remote_port.set(src_port);
+        self.rcv_nxt.set(seq.wrapping_add(1));
+        self.peer_window.set(window);
+        self.peer_mss.set(cmp::max(mss, 1));
+        // There is no random number generator here: the time is enough to
+        // tell consecutive connections apart.
+        self.snd_una.set(self.alarm.now().into_u32());
+        self.state.set(State::SynReceived);
+        self.flight.set(Flight::Syn);
+        self.retries.set(0);
+        self.rto_ms.set(INITIAL_RTO_MS);
+        self.send_flight();
+    }
+
+    /// Process an acknowledgement of `ack`.
+    fn acknowledged(&self, ack: u32) {
+        let flight_len = match self.flight.get() {
+            Flight::None => return,
+            Flight::Data(len) => len as u32,
+            Flight::Syn | Flight::Fin => 1,
+        };
+        // Only whole segments are acknowledged, since they are sent one at a
+        // time.
+        if ack != self.snd_una.get().wrapping_add(flight_len) {
+            return;
+        }
+        let _ = self.alarm.disarm();
+        self.snd_una.set(ack);
+        match self.flight.replace(Flight::None) {
+            Flight::Syn => self.state.set(State::Established),
+            Flight::Data(len) => {
+                let acked = self.tx_acked.get() + len;
+                self.tx_acked.set(acked);
+                if acked == self.tx_len.get() {
+                    self.tx_buffer.take().map(|buffer| {
+                        self.tx_client
+                            .map(move |client| client.transmitted_buffer(buffer, acked, Ok(())));
+                    });
+                }
+            }
+            Flight::Fin => self.reset(),
+            Flight::None => {}
+        }
+    }
+
+    /// Store `data`, as much as fits in the ring. Returns the number of bytes
+    /// stored.
+    fn store(&self, data: &[u8]) -> usize {
+        self.rx_ring.map_or(0, |ring| {
+            let len = cmp::min(data.len(), ring.len() - self.rx_count.get());
+            for (i, byte) in data[..len].iter().enumerate() {
+                let index = (self.rx_head.get() + self.rx_count.get() + i) % ring.len();
+                ring[index] = *byte;
+            }
+            self.rx_count.set(self.rx_count.get() + len);
+            len
+        })
+    }
+
+    /// Copy buffered data to the pending receive, and complete it once it
+    /// is full.
+    fn deliver(&self) {
+        if self.rx_done.get() {
+            return;
+        }
+        self.rx_buffer.map(|buffer| {
+            self.rx_ring.map(|ring| {
+                while self.rx_pos.get() < self.rx_len.get() && self.rx_count.get() > 0 {
+                    buffer[self.rx_pos.get()] = ring[self.rx_head.get()];
+                    self.rx_pos.set(self.rx_pos.get() + 1);
+                    self.rx_head.set((self.rx_head.get() + 1) % ring.len());
+                    self.rx_count.set(self.rx_count.get() - 1);
+                }
+            });
+            if self.rx_pos.get() == self.rx_len.get() {
+                self.rx_done.set(true);
+                self.deferred_call.set();
+            }
+        });
+        if self.window_closed.get() && self.window() > 0 && self.is_connected() {
+            self.send_ack();
+        }
+    }
+}
+
+impl<'a, I: Ipv4Interface<'a>, A: time::Alarm<'a>> TcpHandler for TcpStream<'a, I, A> {
+    fn receive_segment(&self, src_addr: Ipv4Addr, segment: &[u8]) {
+        let header_len = (segment[12] >> 4) as usize * 4;
+        if header_len < HEADER_LEN || header_len > segment.len() {
+            return;
+        }
+        let src_port = u16::from_be_bytes([segment[0], segment[1]]);
+        let seq = u32::from_be_bytes([segment[4], segment[5], segment[6], segment[7]]);
+        let ack = u32::from_be_bytes([segment[8], segment[9], segment[10], segment[11]]);
+        let flags = segment[13];
+        let window = u16::from_be_bytes([segment[14], segment[15]]);
+        let payload = &segment[header_len..];
+
+        if self.state.get() == State::Listen {
+            if flags & (SYN | ACK | RST) == SYN {
+                let mss = parse_mss(&segment[HEADER_LEN..header_len]).unwrap_or(MSS as u16);
+                self.accept(src_addr, src_port, seq, window, mss);
+            }
+            return;
+        }
+
+        // Only one connection is accepted at a time.
+        if src_addr != self.remote_addr.get() || src_port != self.remote_port.get() {
+            return;
+        }
+        if flags & RST != 0 {
+            if seq == self.rcv_nxt.get() {
+                self.reset();
+            }
+            return;
+        }
+        if flags & SYN != 0 {
+            // Our SYN-ACK was lost, and is retransmitted by the alarm.
+            return;
+        }
+        if flags & ACK != 0 {
+            self.peer_window.set(window);
+            self.acknowledged(ack);
+        }
+        if self.state.get() == State::Listen {
+            // The FIN was acknowledged.
+            return;
+        }
+
+        let mut need_ack = false;
+        if !payload.is_empty() {
+            need_ack = true;
+            if seq == self.rcv_nxt.get() && self.state.get() == State::Established {
+                let stored = self.store(payload);
+                self.rcv_nxt.set(seq.wrapping_add(stored as u32));
+                self.deliver();
+            }
+        }
+        if flags & FIN != 0 && seq.wrapping_add(payload.len() as u32) == self.rcv_nxt.get() {
+            need_ack = true;
+            self.rcv_nxt.set(self.rcv_nxt.get().wrapping_add(1));
+            if self.state.get() == State::Established {
+                // Close our side right away. Data not sent yet is dropped.
+                let _ = self.alarm.disarm();
+                self.flight.set(Flight::None);
+                self.state.set(State::LastAck);
+            }
+        }
+
+        // Data segments acknowledge what was received too.
+        if !self.transmit_next() && need_ack {
+            self.send_ack();
+        }
+    }
+
+    fn segment_sent(&self, _result: Result<(), ErrorCode>) {}
+}
+
+/// Return the MSS in the `options` of a SYN segment, if any.
+pub fn parse_mss(mut options: &[u8]) -> Option<u16> {
+    while let Some((&kind, rest)) = options.split_first() {
+        match kind {
+            OPTION_END => return None,
+            OPTION_NOP => options = rest,
+            _ => {
+                let len = *rest.first()? as usize;
+                if len < 2 || len > options.len() {
+                    return None;
+                }
+                if kind == OPTION_MSS && len == MSS_OPTION_LEN {
+                    return Some(u16::from_be_bytes([options[2], options[3]]));
+                }
+                options = &options[len..];
+            }
+        }
+    }
+    None
+}
+
+impl<'a, I: Ipv4Interface<'a>, A: time::Alarm<'a>> time::AlarmClient for TcpStream<'a, I, A> {
+    fn alarm(&self) {
+        if self.flight.get() == Flight::None {
+            return;
+        }
+        if !self.send_failed.get() {
+            if self.retries.get() >= MAX_RETRIES {
+                self.reset();
+                return;
+            }
+            self.retries.set(self.retries.get() + 1);
+            self.rto_ms.set(cmp::min(self.rto_ms.get() * 2, MAX_RTO_MS));
+        }
+        self.send_flight();
+    }
+}
+
+impl<'a, I: Ipv4Interface<'a>, A: time::Alarm<'a>> DeferredCallClient for TcpStream<'a, I, A> {
+    fn handle_deferred_call(&self) {
+        if self.tx_dropped.replace(false) {
+            self.tx_buffer.take().map(|buffer| {
+                self.tx_client.map(move |client| {
+                    client.transmitted_buffer(buffer, self.tx_len.get(), Ok(()))
+                });
+            });
+        }
+        let aborted = self.rx_aborted.replace(false);
+        if self.rx_done.replace(false) || aborted {
+            self.rx_buffer.take().map(|buffer| {
+                let (rcode, error) = if aborted {
+                    (Err(ErrorCode::CANCEL), uart::Error::Aborted)
+                } else {
+                    (Ok(()), uart::Error::None)
+                };
+                self.rx_client.map(move |client| {
+                    client.received_buffer(buffer, self.rx_pos.get(), rcode, error)
+                });
+            });
+        }
+    }
+
+    fn register(&'static self) {
+        self.deferred_call.register(self);
+    }
+}
+
+impl<'a, I: Ipv4Interface<'a>, A: time::Alarm<'a>> uart::Configure for TcpStream<'a, I, A> {
+    fn configure(&self, _params: uart::Parameters) -> Result<(), ErrorCode> {
+        // There is no line to configure.
+        Ok(())
+    }
+}
+
+impl<'a, I: Ipv4Interface<'a>, A: time::Alarm<'a>> uart::Transmit<'a> for TcpStream<'a, I, A> {
+    fn set_transmit_client(&self, client: &'a dyn uart::TransmitClient) {
+        self.tx_client.set(client);
+    }
+
+    fn transmit_buffer(
+        &self,
+        tx_buffer: &'static mut [u8],
+        tx_len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        if self.tx_buffer.is_some() {
+            return Err((ErrorCode::BUSY, tx_buffer));
+        }
+        if tx_len > tx_buffer.len() {
+            return Err((ErrorCode::SIZE, tx_buffer));
+        }
+        self.tx_buffer.replace(tx_buffer);
+        self.tx_len.set(tx_len);
+        self.tx_acked.set(0);
+        if self.is_connected() {
+            self.transmit_next();
+        } else {
+            self.tx_dropped.set(true);
+            self.deferred_call.set();
+        }
+        Ok(())
+    }
+
+    fn transmit_word(&self, _word: u32) -> Result<(), ErrorCode> {
+        Err(ErrorCode::FAIL)
+    }
+
+    fn transmit_abort(&self) -> Result<(), ErrorCode> {
+        if self.tx_buffer.is_some() {
+            // The buffer is returned once it is sent or the connection is
+            // dropped.
+            Err(ErrorCode::FAIL)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'a, I: Ipv4Interface<'a>, A: time::Alarm<'a>> uart::Receive<'a> for TcpStream<'a, I, A> {
+    fn set_receive_client(&self, client: &'a dyn uart::ReceiveClient) {
+        self.rx_client.set(client);
+    }
+
+    fn receive_buffer(
+        &self,
+        rx_buffer: &'static mut [u8],
+        rx_len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        if self.rx_buffer.is_some() {
+            return Err((ErrorCode::BUSY, rx_buffer));
+        }
+        if rx_len == 0 || rx_len > rx_buffer.len() {
+            return Err((ErrorCode::SIZE, rx_buffer));
+        }
+        self.rx_buffer.replace(rx_buffer);
+        self.rx_len.set(rx_len);
+        self.rx_pos.set(0);
+        self.deliver();
+        Ok(())
+    }
+
+    fn receive_word(&self) -> Result<(), ErrorCode> {
+        Err(ErrorCode::FAIL)
+    }
+
+    fn receive_abort(&self) -> Result<(), ErrorCode> {
+        if self.rx_buffer.is_some() && !self.rx_done.get() {
+            self.rx_aborted.set(true);
+            self.deferred_call.set();
+            Err(ErrorCode::BUSY)
+        } else {
+            Ok(())
+        }
+    }
+}
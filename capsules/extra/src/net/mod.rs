@@ -2,8 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 // Copyright Tock Contributors 2022.
 
-//! Modules for IPv6 over 6LoWPAN stack
+//! Modules for IPv6 over 6LoWPAN stack, and a minimal IPv4 stack over
+//! Ethernet
 
+pub mod backoff;
 pub mod frag_utils;
 pub mod sixlowpan;
 pub mod util;
@@ -11,6 +13,7 @@ pub mod util;
 pub mod stream;
 pub mod icmpv6;
 pub mod ieee802154;
+pub mod ipv4;
 pub mod ipv6;
 pub mod network_capabilities;
 pub mod tcp;
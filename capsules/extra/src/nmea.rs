@@ -0,0 +1,334 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Satellite positioning receiver sending NMEA 0183 sentences over a UART,
+//! such as the u-blox modules.
+//!
+//! The capsule receives the UART one byte at a time and parses the RMC and
+//! GGA sentences of any talker (`$GPRMC`, `$GNGGA`, ...), checking their
+//! checksums. Other sentences are ignored. A fix is reported once both
+//! sentences of an epoch, which have the same time, are received: GGA gives
+//! the position, its quality, the altitude and the number of satellites, and
+//! RMC the date, the speed and the course. u-blox modules send both every
+//! second by default.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let nmea = components::nmea::NmeaComponent::new(&peripherals.uart0, 9600)
+//!     .finalize(components::nmea_component_static!(rp2040::uart::Uart<'static>));
+//! let location = components::location::LocationComponent::new(
+//!     board_kernel,
+//!     capsules_extra::location::DRIVER_NUM,
+//!     nmea,
+//! )
+//! .finalize(components::location_component_static!());
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::location::{Fix, FixQuality, Location, LocationClient, UtcTime};
+use kernel::hil::uart;
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+/// Longest sentence, from `$` to the checksum.
+pub const SENTENCE_LEN: usize = 82;
+
+const RMC: u8 = 1 << 0;
+const GGA: u8 = 1 << 1;
+
+/// Parse a decimal field, such as `-12.345`, scaled by `10^decimals`.
+/// Digits beyond `decimals` are truncated. Returns `None` for empty or
+/// invalid fields.
+fn parse_decimal(field: &[u8], decimals: u32) -> Option<i64> {
+    let (negative, digits) = match field.split_first() {
+        Some((b'-', digits)) => (true, digits),
+        Some(_) => (false, field),
+        None => return None,
+    };
+
+    let mut value: i64 = 0;
+    let mut fraction_digits = None;
+    for &c in digits {
+        match (c, fraction_digits) {
+            (b'.', None) => fraction_digits = Some(0),
+            (b'0'..=b'9', Some(n)) if n >= decimals => {}
+            (b'0'..=b'9', _) => {
+                value = value.checked_mul(10)?.checked_add((c - b'0') as i64)?;
+                fraction_digits = fraction_digits.map(|n| n + 1);
+            }
+            _ => return None,
+        }
+    }
+    value = value.checked_mul(10_i64.pow(decimals - fraction_digits.unwrap_or(0)))?;
+    Some(if negative { -value } else { value })
+}
+
+/// Parse a `ddmm.mmmm` or `dddmm.mmmm` angle and its hemisphere into 1e-7
+/// degrees.
+fn parse_angle(field: &[u8], hemisphere: &[u8]) -> Option<i32> {
+    // Minutes in 1e-5
+    let value = parse_decimal(field, 5)?;
+    let degrees = value / 10_000_000;
+    let minutes = value % 10_000_000;
+    let angle = degrees * 10_000_000 + minutes * 5 / 3;
+    match hemisphere {
+        b"N" | b"E" => Some(angle as i32),
+        b"S" | b"W" => Some(-angle as i32),
+        _ => None,
+    }
+}
+
+/// Parse a `hhmmss.sss` time into `time`.
+fn parse_time(field: &[u8], time: &mut UtcTime) -> Option<()> {
+    let value = parse_decimal(field, 3)?;
+    time.hour = (value / 10_000_000) as u8;
+    time.minute = (value / 100_000 % 100) as u8;
+    time.second = (value / 1000 % 100) as u8;
+    time.millisecond = (value % 1000) as u16;
+    Some(())
+}
+
+pub struct Nmea<'a, U: uart::Uart<'a>> {
+    uart: &'a U,
+    baud_rate: u32,
+    client: OptionalCell<&'a dyn LocationClient>,
+    started: Cell<bool>,
+    receiving: Cell<bool>,
+    rx_byte: TakeCell<'static, [u8]>,
+    sentence: TakeCell<'static, [u8]>,
+    /// Length of the sentence being received, `None` until a `$`.
+    sentence_len: Cell<Option<usize>>,
+    fix: Cell<Fix>,
+    /// Sentences received for the epoch of `fix.time`.
+    epoch_sentences: Cell<u8>,
+}
+
+impl<'a, U: uart::Uart<'a>> Nmea<'a, U> {
+    pub fn new(
+        uart: &'a U,
+        baud_rate: u32,
+        rx_byte: &'static mut [u8; 1],
+        sentence: &'static mut [u8; SENTENCE_LEN],
+    ) -> Nmea<'a, U> {
+        Nmea {
+            uart,
+            baud_rate,
+            client: OptionalCell::empty(),
+            started: Cell::new(false),
+            receiving: Cell::new(false),
+            rx_byte: TakeCell::new(rx_byte),
+            sentence: TakeCell::new(sentence),
+            sentence_len: Cell::new(None),
+            fix: Cell::new(Fix {
+                quality: FixQuality::None,
+                time: UtcTime::default(),
+                latitude: 0,
+                longitude: 0,
+                altitude: None,
+                satellites: 0,
+                speed: None,
+                course: None,
+            }),
+            epoch_sentences: Cell::new(0),
+        }
+    }
+
+    pub fn initialize(&self) -> Result<(), ErrorCode> {
+        self.uart.configure(uart::Parameters {
+            baud_rate: self.baud_rate,
+            width: uart::Width::Eight,
+            parity: uart::Parity::None,
+            stop_bits: uart::StopBits::One,
+            hw_flow_control: false,
+        })
+    }
+
+    fn receive_byte(&self) -> Result<(), ErrorCode> {
+        self.rx_byte.take().map_or(Err(ErrorCode::BUSY), |rx_byte| {
+            self.uart
+                .receive_buffer(rx_byte, 1)
+                .map_err(|(err, rx_byte)| {
+                    self.rx_byte.replace(rx_byte);
+                    err
+                })
+        })
+    }
+
+    /// Add `byte` to the sentence, and parse the sentence at its end.
+    fn sentence_byte(&self, byte: u8) {
+        match (byte, self.sentence_len.get()) {
+            (b'$', _) => self.sentence_len.set(Some(0)),
+            (b'\r' | b'\n', Some(len)) => {
+                self.sentence_len.set(None);
+                self.sentence.map(|sentence| {
+                    if let Some(data) = Self::check_sentence(&sentence[..len]) {
+                        self.parse_sentence(data);
+                    }
+                });
+            }
+            (_, Some(len)) if len < SENTENCE_LEN => {
+                self.sentence.map(|sentence| sentence[len] = byte);
+                self.sentence_len.set(Some(len + 1));
+            }
+            // Too long, or outside a sentence
+            _ => self.sentence_len.set(None),
+        }
+    }
+
+    /// Data of `sentence`, without `$` and the checksum, if the checksum is
+    /// right.
+    fn check_sentence(sentence: &[u8]) -> Option<&[u8]> {
+        let star = sentence.iter().position(|c| *c == b'*')?;
+        let (data, checksum) = (&sentence[..star], &sentence[star + 1..]);
+        let checksum = core::str::from_utf8(checksum).ok()?;
+        let checksum = u8::from_str_radix(checksum, 16).ok()?;
+        if data.iter().fold(0, |sum, c| sum ^ c) == checksum {
+            Some(data)
+        } else {
+            None
+        }
+    }
+
+    fn parse_sentence(&self, data: &[u8]) {
+        let mut fields = data.split(|c| *c == b',');
+        let address = fields.next().unwrap_or(&[]);
+        if address.len() != 5 {
+            return;
+        }
+        let mut fix = self.fix.get();
+        let mut field = || fields.next().unwrap_or(&[]);
+        let mut time = fix.time;
+        let sentence = match &address[2..] {
+            b"RMC" => {
+                if parse_time(field(), &mut time).is_none() {
+                    return;
+                }
+                let valid = field() == b"A";
+                let (latitude, north) = (field(), field());
+                let (longitude, east) = (field(), field());
+                if valid {
+                    if let (Some(latitude), Some(longitude)) =
+                        (parse_angle(latitude, north), parse_angle(longitude, east))
+                    {
+                        fix.latitude = latitude;
+                        fix.longitude = longitude;
+                    }
+                }
+                // Knots to millimeters per second
+                fix.speed =
+                    parse_decimal(field(), 3).map(|knots| (knots * 514_444 / 1_000_000) as u32);
+                fix.course = parse_decimal(field(), 2).map(|course| course as u32);
+                if let Some(date) = parse_decimal(field(), 0) {
+                    time.day = (date / 10_000) as u8;
+                    time.month = (date / 100 % 100) as u8;
+                    time.year = 2000 + (date % 100) as u16;
+                }
+                RMC
+            }
+            b"GGA" => {
+                if parse_time(field(), &mut time).is_none() {
+                    return;
+                }
+                let (latitude, north) = (field(), field());
+                let (longitude, east) = (field(), field());
+                fix.quality = match field() {
+                    b"1" | b"3" => FixQuality::Gps,
+                    b"2" => FixQuality::Differential,
+                    b"4" | b"5" => FixQuality::Rtk,
+                    b"6" => FixQuality::Estimated,
+                    _ => FixQuality::None,
+                };
+                if let (Some(latitude), Some(longitude)) =
+                    (parse_angle(latitude, north), parse_angle(longitude, east))
+                {
+                    fix.latitude = latitude;
+                    fix.longitude = longitude;
+                } else {
+                    fix.quality = FixQuality::None;
+                }
+                fix.satellites = parse_decimal(field(), 0).map_or(0, |n| n as u8);
+                let _hdop = field();
+                fix.altitude = parse_decimal(field(), 3).map(|altitude| altitude as i32);
+                GGA
+            }
+            _ => return,
+        };
+
+        let same_epoch = (time.hour, time.minute, time.second, time.millisecond)
+            == (
+                fix.time.hour,
+                fix.time.minute,
+                fix.time.second,
+                fix.time.millisecond,
+            );
+        if !same_epoch {
+            self.epoch_sentences.set(0);
+        }
+        fix.time = time;
+        self.fix.set(fix);
+        self.epoch_sentences
+            .set(self.epoch_sentences.get() | sentence);
+        if self.epoch_sentences.get() == RMC | GGA {
+            self.epoch_sentences.set(0);
+            if self.started.get() {
+                self.client.map(|client| client.fix(&fix));
+            }
+        }
+    }
+}
+
+impl<'a, U: uart::Uart<'a>> Location<'a> for Nmea<'a, U> {
+    fn set_client(&self, client: &'a dyn LocationClient) {
+        self.client.set(client);
+    }
+
+    fn start(&self) -> Result<(), ErrorCode> {
+        self.started.set(true);
+        if !self.receiving.get() {
+            self.sentence_len.set(None);
+            self.epoch_sentences.set(0);
+            if let Err(err) = self.receive_byte() {
+                self.started.set(false);
+                return Err(err);
+            }
+            self.receiving.set(true);
+        }
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), ErrorCode> {
+        self.started.set(false);
+        if self.receiving.get() {
+            let _ = self.uart.receive_abort();
+        }
+        Ok(())
+    }
+}
+
+impl<'a, U: uart::Uart<'a>> uart::ReceiveClient for Nmea<'a, U> {
+    fn received_buffer(
+        &self,
+        rx_byte: &'static mut [u8],
+        rx_len: usize,
+        rval: Result<(), ErrorCode>,
+        _error: uart::Error,
+    ) {
+        let byte = rx_byte[0];
+        self.rx_byte.replace(rx_byte);
+
+        if rval.is_ok() && rx_len == 1 {
+            self.sentence_byte(byte);
+        } else {
+            // Drop the sentence with the error
+            self.sentence_len.set(None);
+        }
+
+        if !self.started.get() || self.receive_byte().is_err() {
+            self.receiving.set(false);
+        }
+    }
+}
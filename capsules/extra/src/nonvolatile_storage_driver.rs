@@ -4,10 +4,15 @@
 
 //! This provides kernel and userspace access to nonvolatile memory.
 //!
-//! This is an initial implementation that does not provide safety for
-//! individual userland applications. Each application has full access to
-//! the entire memory space that has been provided to userland. Future revisions
-//! should update this to limit applications to only their allocated regions.
+//! The memory provided to userland can be split into per-app regions with a
+//! table of `AppRegion`s. Apps are matched to their region by the `write_id`
+//! of their TBF persistent ACL header, and each app sees its region as
+//! starting at address 0. The capsule checks every access against the bounds
+//! of the region, so an app can not read or overwrite the data of another
+//! app. Apps without a region in the table can not access the storage. If the
+//! table is empty, every app has access to all of the userland memory. The
+//! regions must be within the userland memory, must not overlap, and must
+//! belong to different `write_id`s: `new()` panics otherwise.
 //!
 //! However, the kernel accessible memory does not have to be the same range
 //! as the userspace accessible address space. The kernel memory can overlap
@@ -54,6 +59,10 @@
 //!         0,                           // The byte start address of the region
 //!                                      // that is accessible by the kernel.
 //!         3000,                        // The length of the kernel region.
+//!         &[                           // The regions of the apps.
+//!             AppRegion { write_id: 0x1, offset: 0, length: 1000 },
+//!             AppRegion { write_id: 0x2, offset: 1000, length: 1000 },
+//!         ],
 //!         &mut capsules::nonvolatile_storage_driver::BUFFER));
 //! hil::nonvolatile_storage::NonvolatileStorage::set_client(fm25cl, nonvolatile_storage);
 //! ```
@@ -88,6 +97,42 @@ mod rw_allow {
 
 pub const BUF_LEN: usize = 512;
 
+/// The `write_id` of the TBF persistent ACL header of the process, if any.
+fn write_id(processid: ProcessId) -> Option<u32> {
+    processid
+        .get_storage_permissions()
+        .and_then(|permissions| permissions.get_write_id())
+}
+
+/// Check that the regions are within the userspace memory, do not overlap and
+/// belong to different apps.
+fn app_regions_valid(app_regions: &[AppRegion], userspace_length: usize) -> bool {
+    app_regions.iter().enumerate().all(|(i, region)| {
+        let end = match region.offset.checked_add(region.length) {
+            Some(end) if end <= userspace_length => end,
+            _ => return false,
+        };
+        app_regions[i + 1..].iter().all(|other| {
+            other.write_id != region.write_id
+                && (other.length == 0
+                    || region.length == 0
+                    || other.offset >= end
+                    || other.offset + other.length <= region.offset)
+        })
+    })
+}
+
+/// The region of the userspace memory of the apps with a given `write_id`.
+#[derive(Clone, Copy)]
+pub struct AppRegion {
+    /// `write_id` of the TBF persistent ACL header of the apps.
+    pub write_id: u32,
+    /// Start of the region, from the start of the userspace memory.
+    pub offset: usize,
+    /// Length of the region in bytes.
+    pub length: usize,
+}
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum NonvolatileCommand {
     UserspaceRead,
@@ -105,7 +150,8 @@ pub enum NonvolatileUser {
 pub struct App {
     pending_command: bool,
     command: NonvolatileCommand,
-    offset: usize,
+    // Physical address of the pending command.
+    address: usize,
     length: usize,
 }
 
@@ -114,7 +160,7 @@ impl Default for App {
         App {
             pending_command: false,
             command: NonvolatileCommand::UserspaceRead,
-            offset: 0,
+            address: 0,
             length: 0,
         }
     }
@@ -144,6 +190,8 @@ pub struct NonvolatileStorage<'a> {
     kernel_start_address: usize,
     // How many bytes allocated to kernel.
     kernel_length: usize,
+    // The regions of the userspace memory reserved for each app.
+    app_regions: &'static [AppRegion],
 
     // Optional client for the kernel. Only needed if the kernel intends to use
     // this nonvolatile storage.
@@ -173,8 +221,12 @@ impl<'a> NonvolatileStorage<'a> {
         userspace_length: usize,
         kernel_start_address: usize,
         kernel_length: usize,
+        app_regions: &'static [AppRegion],
         buffer: &'static mut [u8],
     ) -> NonvolatileStorage<'a> {
+        if !app_regions_valid(app_regions, userspace_length) {
+            panic!("Nonvolatile Storage: app regions overlap or are out of bounds");
+        }
         NonvolatileStorage {
             driver: driver,
             apps: grant,
@@ -184,6 +236,7 @@ impl<'a> NonvolatileStorage<'a> {
             userspace_length: userspace_length,
             kernel_start_address: kernel_start_address,
             kernel_length: kernel_length,
+            app_regions,
             kernel_client: OptionalCell::empty(),
             kernel_pending_command: Cell::new(false),
             kernel_command: Cell::new(NonvolatileCommand::KernelRead),
@@ -193,6 +246,37 @@ impl<'a> NonvolatileStorage<'a> {
         }
     }
 
+    /// Find the physical start address and the length of the memory that
+    /// the apps with `write_id` can access, if any.
+    pub fn app_region(&self, write_id: Option<u32>) -> Option<(usize, usize)> {
+        if self.app_regions.is_empty() {
+            return Some((self.userspace_start_address, self.userspace_length));
+        }
+        let write_id = write_id?;
+        self.app_regions
+            .iter()
+            .find(|region| region.write_id == write_id)
+            .map(|region| (self.userspace_start_address + region.offset, region.length))
+    }
+
+    /// Find the physical address of `length` bytes at `offset` in the region
+    /// of the apps with `write_id`. Fails with `NOSUPPORT` if the apps have
+    /// no region, and with `INVAL` if the bytes are not in their region.
+    pub fn app_address(
+        &self,
+        write_id: Option<u32>,
+        offset: usize,
+        length: usize,
+    ) -> Result<usize, ErrorCode> {
+        let (start, region_length) = self.app_region(write_id).ok_or(ErrorCode::NOSUPPORT)?;
+        // Userspace sees memory that starts at address 0 even if it is
+        // offset in the physical memory.
+        if offset >= region_length || length > region_length || offset + length > region_length {
+            return Err(ErrorCode::INVAL);
+        }
+        Ok(start + offset)
+    }
+
     // Check so see if we are doing something. If not, go ahead and do this
     // command. If so, this is queued and will be run when the pending
     // command completes.
@@ -203,17 +287,12 @@ impl<'a> NonvolatileStorage<'a> {
         length: usize,
         processid: Option<ProcessId>,
     ) -> Result<(), ErrorCode> {
-        // Do bounds check.
+        // Do bounds check, and find the physical address of an app access.
+        let mut address = offset;
         match command {
             NonvolatileCommand::UserspaceRead | NonvolatileCommand::UserspaceWrite => {
-                // Userspace sees memory that starts at address 0 even if it
-                // is offset in the physical memory.
-                if offset >= self.userspace_length
-                    || length > self.userspace_length
-                    || offset + length > self.userspace_length
-                {
-                    return Err(ErrorCode::INVAL);
-                }
+                let processid = processid.ok_or(ErrorCode::NOSUPPORT)?;
+                address = self.app_address(write_id(processid), offset, length)?;
             }
             NonvolatileCommand::KernelRead | NonvolatileCommand::KernelWrite => {
                 // Because the kernel uses the NonvolatileStorage interface,
@@ -288,7 +367,7 @@ impl<'a> NonvolatileStorage<'a> {
                                         });
                                 }
 
-                                self.userspace_call_driver(command, offset, active_len)
+                                self.userspace_call_driver(command, address, active_len)
                             } else {
                                 // Some app is using the storage, we must wait.
                                 if app.pending_command == true {
@@ -299,7 +378,7 @@ impl<'a> NonvolatileStorage<'a> {
                                     // We can store this, so lets do it.
                                     app.pending_command = true;
                                     app.command = command;
-                                    app.offset = offset;
+                                    app.address = address;
                                     app.length = active_len;
                                     Ok(())
                                 }
@@ -348,13 +427,9 @@ impl<'a> NonvolatileStorage<'a> {
     fn userspace_call_driver(
         &self,
         command: NonvolatileCommand,
-        offset: usize,
+        physical_address: usize,
         length: usize,
     ) -> Result<(), ErrorCode> {
-        // Calculate where we want to actually read from in the physical
-        // storage.
-        let physical_address = offset + self.userspace_start_address;
-
         self.buffer
             .take()
             .map_or(Err(ErrorCode::RESERVE), |buffer| {
@@ -407,7 +482,7 @@ impl<'a> NonvolatileStorage<'a> {
                             processid: processid,
                         });
                         if let Ok(()) =
-                            self.userspace_call_driver(app.command, app.offset, app.length)
+                            self.userspace_call_driver(app.command, app.address, app.length)
                         {
                             true
                         } else {
@@ -545,7 +620,7 @@ impl SyscallDriver for NonvolatileStorage<'_> {
     /// ### `command_num`
     ///
     /// - `0`: Return Ok(()) if this driver is included on the platform.
-    /// - `1`: Return the number of bytes available to userspace.
+    /// - `1`: Return the number of bytes available to the app.
     /// - `2`: Start a read from the nonvolatile storage.
     /// - `3`: Start a write to the nonvolatile_storage.
     fn command(
@@ -560,9 +635,12 @@ impl SyscallDriver for NonvolatileStorage<'_> {
                 CommandReturn::success()
             }
 
-            1 /* How many bytes are accessible from userspace */ => {
+            1 /* How many bytes are accessible from this app */ => {
                 // TODO: Would break on 64-bit platforms
-                CommandReturn::success_u32(self.userspace_length as u32)
+                let length = self
+                    .app_region(write_id(processid))
+                    .map_or(0, |(_, length)| length);
+                CommandReturn::success_u32(length as u32)
             },
 
             2 /* Issue a read command */ => {
@@ -0,0 +1,325 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Provides userspace with an on-screen keyboard.
+//!
+//! The keyboard is drawn at the bottom of the screen with the `Graphics`
+//! capsule, as four rows of ten keys: digits, three rows of lowercase
+//! letters, space, `.`, backspace (drawn as `<`) and enter (drawn as `>`).
+//! Touches are read from a single touch panel and mapped to the screen like
+//! the `Touch` capsule does. The key under the finger is highlighted, and
+//! the key pressed is sent to applications when the finger is lifted.
+//!
+//! The keyboard only uses the single touch client of the panel, so the
+//! `Touch` capsule can still report multi touch events to applications.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let keyboard = components::onscreen_keyboard::OnScreenKeyboardComponent::new(
+//!     board_kernel,
+//!     capsules_extra::onscreen_keyboard::DRIVER_NUM,
+//!     graphics,
+//!     ft6x06,
+//!     tft,
+//! )
+//! .finalize(components::onscreen_keyboard_component_static!());
+//! keyboard.set_screen_rotation_offset(ScreenRotation::Rotated90);
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: draw the keyboard, and send key events to the calling
+//!   process. Returns the height of the keyboard in pixels, so applications
+//!   can draw above it.
+//! - Command 2: stop sending key events to the calling process.
+//!
+//! - Upcall 0: key pressed, with its ASCII code. Backspace is `0x08` and
+//!   enter is `'\n'`.
+
+use core::cell::Cell;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil;
+use kernel::hil::screen::ScreenRotation;
+use kernel::hil::touch::{TouchEvent, TouchStatus};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::OptionalCell;
+use kernel::{ErrorCode, ProcessId};
+
+use crate::graphics::{self, Drawing, Graphics};
+use crate::touch::rotate_to_screen;
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::OnScreenKeyboard as usize;
+
+const ROWS: usize = 4;
+const COLUMNS: usize = 10;
+
+/// Keys, row by row from the top.
+const LAYOUT: [&[u8; COLUMNS]; ROWS] = [
+    b"1234567890",
+    b"qwertyuiop",
+    b"asdfghjkl\x08",
+    b"zxcvbnm .\n",
+];
+
+const KEY_COLOR: u32 = 0x303030;
+const PRESSED_COLOR: u32 = 0x2060C0;
+const LABEL_COLOR: u32 = 0xFFFFFF;
+
+/// Character drawn for `key`.
+fn label(key: u8) -> u8 {
+    match key {
+        0x08 => b'<',
+        b'\n' => b'>',
+        _ => key,
+    }
+}
+
+#[derive(Default)]
+pub struct App {
+    enabled: bool,
+}
+
+pub struct OnScreenKeyboard<'a> {
+    graphics: &'a Graphics<'a>,
+    touch: &'a dyn hil::touch::Touch<'a>,
+    screen: &'a dyn hil::screen::Screen<'a>,
+    screen_rotation_offset: Cell<ScreenRotation>,
+    apps: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    /// Keys to draw, one bit per key.
+    dirty: Cell<u64>,
+    /// Key being drawn, and whether its label is drawn next.
+    drawing: Cell<Option<(usize, bool)>>,
+    /// Key under the finger.
+    pressed: OptionalCell<usize>,
+}
+
+impl<'a> OnScreenKeyboard<'a> {
+    pub fn new(
+        graphics: &'a Graphics<'a>,
+        touch: &'a dyn hil::touch::Touch<'a>,
+        screen: &'a dyn hil::screen::Screen<'a>,
+        grant: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    ) -> OnScreenKeyboard<'a> {
+        OnScreenKeyboard {
+            graphics,
+            touch,
+            screen,
+            screen_rotation_offset: Cell::new(ScreenRotation::Normal),
+            apps: grant,
+            dirty: Cell::new(0),
+            drawing: Cell::new(None),
+            pressed: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_screen_rotation_offset(&self, screen_rotation_offset: ScreenRotation) {
+        self.screen_rotation_offset.set(screen_rotation_offset);
+    }
+
+    /// Size of a key, as `(width, height)`.
+    fn key_size(&self) -> (usize, usize) {
+        let (width, height) = self.graphics.get_resolution();
+        let key_width = width / COLUMNS;
+        (key_width, key_width.min(height / ROWS))
+    }
+
+    /// Top of the keyboard.
+    fn top(&self) -> usize {
+        let (_, height) = self.graphics.get_resolution();
+        height - self.key_size().1 * ROWS
+    }
+
+    /// Position of `key`, as `(x, y)`.
+    fn key_position(&self, key: usize) -> (usize, usize) {
+        let (key_width, key_height) = self.key_size();
+        (
+            (key % COLUMNS) * key_width,
+            self.top() + (key / COLUMNS) * key_height,
+        )
+    }
+
+    /// Key at the point `(x, y)` of the screen.
+    fn key_at(&self, x: usize, y: usize) -> Option<usize> {
+        let (key_width, key_height) = self.key_size();
+        let top = self.top();
+        if key_width == 0 || key_height == 0 || y < top {
+            return None;
+        }
+        let (column, row) = (x / key_width, (y - top) / key_height);
+        if column < COLUMNS && row < ROWS {
+            Some(row * COLUMNS + column)
+        } else {
+            None
+        }
+    }
+
+    /// Draw `key` again.
+    fn redraw(&self, key: usize) {
+        self.dirty.set(self.dirty.get() | 1 << key);
+        if self.drawing.get().is_none() {
+            self.draw_next();
+        }
+    }
+
+    /// Start the next drawing of the keyboard: the background of a key,
+    /// then its label.
+    fn draw_next(&self) {
+        let (key, label_next) = match self.drawing.get() {
+            Some(drawing) => drawing,
+            None => {
+                let dirty = self.dirty.get();
+                if dirty == 0 {
+                    return;
+                }
+                let key = dirty.trailing_zeros() as usize;
+                self.dirty.set(dirty & !(1 << key));
+                (key, false)
+            }
+        };
+
+        let (x, y) = self.key_position(key);
+        let (key_width, key_height) = self.key_size();
+        let color = if self.pressed.contains(&key) {
+            PRESSED_COLOR
+        } else {
+            KEY_COLOR
+        };
+        let drawing = if label_next {
+            let scale = (key_width.saturating_sub(2) / graphics::CHAR_WIDTH)
+                .min(key_height.saturating_sub(2) / graphics::CHAR_HEIGHT)
+                .clamp(1, graphics::MAX_TEXT_SCALE);
+            Drawing::Char {
+                x: x + key_width.saturating_sub(graphics::CHAR_WIDTH * scale) / 2,
+                y: y + key_height.saturating_sub(graphics::CHAR_HEIGHT * scale) / 2,
+                c: label(LAYOUT[key / COLUMNS][key % COLUMNS]),
+                foreground: LABEL_COLOR,
+                background: color,
+                scale,
+            }
+        } else {
+            // Leave a one pixel gap around the key.
+            Drawing::Rect {
+                x: x + 1,
+                y: y + 1,
+                width: key_width.saturating_sub(2),
+                height: key_height.saturating_sub(2),
+                color,
+            }
+        };
+
+        self.drawing.set(Some((key, label_next)));
+        if self.graphics.draw(drawing).is_err() {
+            // Drop this key, it is drawn again when it changes.
+            self.drawing.set(None);
+            self.draw_next();
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.apps.iter().any(|app| app.enter(|app, _| app.enabled))
+    }
+
+    /// Send `key` to the processes which enabled key events.
+    fn key_pressed(&self, key: u8) {
+        for app in self.apps.iter() {
+            app.enter(|app, upcalls| {
+                if app.enabled {
+                    upcalls.schedule_upcall(0, (key as usize, 0, 0)).ok();
+                }
+            });
+        }
+    }
+}
+
+impl<'a> graphics::GraphicsClient for OnScreenKeyboard<'a> {
+    fn drawing_done(&self, _result: Result<(), ErrorCode>) {
+        if let Some((key, label_next)) = self.drawing.get() {
+            self.drawing
+                .set(if label_next { None } else { Some((key, true)) });
+        }
+        self.draw_next();
+    }
+}
+
+impl<'a> hil::touch::TouchClient for OnScreenKeyboard<'a> {
+    fn touch_event(&self, mut event: TouchEvent) {
+        if !self.enabled() {
+            return;
+        }
+        rotate_to_screen(self.screen, self.screen_rotation_offset.get(), &mut event);
+        let key = self.key_at(event.x as usize, event.y as usize);
+
+        match event.status {
+            TouchStatus::Pressed | TouchStatus::Moved => {
+                if key != self.pressed.extract() {
+                    self.pressed.take().map(|previous| self.redraw(previous));
+                    if let Some(key) = key {
+                        self.pressed.set(key);
+                        self.redraw(key);
+                    }
+                }
+            }
+            TouchStatus::Released => {
+                self.pressed.take().map(|key| {
+                    self.redraw(key);
+                    self.key_pressed(LAYOUT[key / COLUMNS][key % COLUMNS]);
+                });
+            }
+            TouchStatus::Unstarted => {}
+        }
+    }
+}
+
+impl<'a> SyscallDriver for OnScreenKeyboard<'a> {
+    fn command(
+        &self,
+        command_num: usize,
+        _data1: usize,
+        _data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            // Show the keyboard and enable key events
+            1 => {
+                if let Err(err) = self.apps.enter(processid, |app, _| app.enabled = true) {
+                    return CommandReturn::failure(err.into());
+                }
+                // The panel is shared with the touch driver, so it is never
+                // disabled.
+                if let Err(e) = self.touch.enable() {
+                    return CommandReturn::failure(e);
+                }
+                self.dirty.set((1 << (ROWS * COLUMNS)) - 1);
+                if self.drawing.get().is_none() {
+                    self.draw_next();
+                }
+                CommandReturn::success_u32((self.key_size().1 * ROWS) as u32)
+            }
+
+            // Disable key events
+            2 => self
+                .apps
+                .enter(processid, |app, _| {
+                    app.enabled = false;
+                    CommandReturn::success()
+                })
+                .unwrap_or_else(|err| CommandReturn::failure(err.into())),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
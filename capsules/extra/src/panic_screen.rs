@@ -0,0 +1,183 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Text output on a display from the panic handler.
+//!
+//! The screen capsules work with callbacks, which never come once the kernel
+//! has panicked. `PanicScreen` instead draws text with a built-in 5x7 font,
+//! writing each character synchronously to a display controller using the
+//! MIPI DCS commands (ST77xx, ILI9341 and similar controllers). This makes
+//! panics visible on devices without a serial cable.
+//!
+//! The board implements `PanicScreenBus` for the bus of its display, for
+//! example with direct writes to a memory-mapped FSMC bank, or with a
+//! blocking SPI transfer. The display must have been initialized by its
+//! driver before the panic.
+//!
+//! Usage
+//! -----
+//!
+//! In the board `io.rs`, write the panic messages to the screen as well as
+//! to the UART:
+//!
+//! ```rust,ignore
+//! impl IoWrite for Writer {
+//!     fn write(&mut self, buf: &[u8]) -> usize {
+//!         // Write to the UART.
+//!         ...
+//!         if self.screen_enabled {
+//!             let _ = self.screen.write(buf);
+//!         }
+//!         buf.len()
+//!     }
+//! }
+//! ```
+
+use core::fmt::Write;
+use kernel::debug::IoWrite;
+
+use crate::font;
+
+/// Synchronous access to the controller of a display.
+pub trait PanicScreenBus {
+    /// Send `command` followed by its `parameters`.
+    fn command(&self, command: u8, parameters: &[u8]);
+
+    /// Write an RGB565 pixel to the display memory, after a `WRITE_RAM`
+    /// command.
+    fn pixel(&self, color: u16);
+}
+
+/// Column Address Set
+const CASET: u8 = 0x2A;
+/// Row Address Set
+const RASET: u8 = 0x2B;
+/// Memory Write
+const WRITE_RAM: u8 = 0x2C;
+
+/// Size of a character cell, with one column and one row of spacing.
+const CHAR_WIDTH: usize = font::GLYPH_WIDTH + 1;
+const CHAR_HEIGHT: usize = font::GLYPH_HEIGHT + 1;
+
+/// White text on a dark red background.
+const FOREGROUND: u16 = 0xFFFF;
+const BACKGROUND: u16 = 0x8000;
+
+pub struct PanicScreen<B: PanicScreenBus> {
+    bus: B,
+    width: usize,
+    height: usize,
+    column: usize,
+    row: usize,
+    cleared: bool,
+}
+
+impl<B: PanicScreenBus> PanicScreen<B> {
+    pub const fn new(bus: B, width: usize, height: usize) -> PanicScreen<B> {
+        PanicScreen {
+            bus,
+            width,
+            height,
+            column: 0,
+            row: 0,
+            cleared: false,
+        }
+    }
+
+    fn columns(&self) -> usize {
+        self.width / CHAR_WIDTH
+    }
+
+    fn rows(&self) -> usize {
+        self.height / CHAR_HEIGHT
+    }
+
+    /// Select the area written by the next pixels.
+    fn set_window(&self, x: usize, y: usize, width: usize, height: usize) {
+        let (x_end, y_end) = (x + width - 1, y + height - 1);
+        self.bus.command(
+            CASET,
+            &[(x >> 8) as u8, x as u8, (x_end >> 8) as u8, x_end as u8],
+        );
+        self.bus.command(
+            RASET,
+            &[(y >> 8) as u8, y as u8, (y_end >> 8) as u8, y_end as u8],
+        );
+        self.bus.command(WRITE_RAM, &[]);
+    }
+
+    fn fill(&self, x: usize, y: usize, width: usize, height: usize, color: u16) {
+        self.set_window(x, y, width, height);
+        for _ in 0..width * height {
+            self.bus.pixel(color);
+        }
+    }
+
+    fn draw_char(&self, c: u8) {
+        let glyph = font::glyph(c);
+        self.set_window(
+            self.column * CHAR_WIDTH,
+            self.row * CHAR_HEIGHT,
+            CHAR_WIDTH,
+            CHAR_HEIGHT,
+        );
+        for y in 0..CHAR_HEIGHT {
+            for x in 0..CHAR_WIDTH {
+                let on = x < font::GLYPH_WIDTH && (glyph[x] >> y) & 1 == 1;
+                self.bus.pixel(if on { FOREGROUND } else { BACKGROUND });
+            }
+        }
+    }
+
+    /// Move to the next line, wrapping around to the top of the screen, and
+    /// clear it.
+    fn new_line(&mut self) {
+        self.column = 0;
+        self.row = (self.row + 1) % self.rows();
+        self.fill(
+            0,
+            self.row * CHAR_HEIGHT,
+            self.width,
+            CHAR_HEIGHT,
+            BACKGROUND,
+        );
+    }
+
+    fn write_byte(&mut self, c: u8) {
+        match c {
+            b'\r' => self.column = 0,
+            b'\n' => self.new_line(),
+            _ => {
+                if self.column == self.columns() {
+                    self.new_line();
+                }
+                self.draw_char(c);
+                self.column += 1;
+            }
+        }
+    }
+}
+
+impl<B: PanicScreenBus> Write for PanicScreen<B> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+impl<B: PanicScreenBus> IoWrite for PanicScreen<B> {
+    fn write(&mut self, buf: &[u8]) -> usize {
+        if self.columns() == 0 || self.rows() == 0 {
+            return buf.len();
+        }
+        if !self.cleared {
+            self.cleared = true;
+            self.fill(0, 0, self.width, self.height, BACKGROUND);
+        }
+        for &c in buf {
+            self.write_byte(c);
+        }
+        buf.len()
+    }
+}
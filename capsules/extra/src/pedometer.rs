@@ -0,0 +1,341 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Step counter on an accelerometer.
+//!
+//! The accelerometer is read `SAMPLE_RATE_HZ` times a second through
+//! `hil::sensors::NineDof`, and steps are detected as peaks of the magnitude
+//! of the acceleration, whatever the orientation of the sensor. The
+//! magnitude is smoothed, and a step is counted each time it rises
+//! `THRESHOLD_MG` above its slow moving average after having fallen as far
+//! below it, at most one step every `MIN_STEP_INTERVAL_MS`.
+//!
+//! The steps are counted for the current day. The count and the number of
+//! the day are stored in nonvolatile storage, at most once every
+//! `STORE_INTERVAL_S` seconds while steps are counted and at once when the
+//! day changes or the count is reset, and loaded at boot. The kernel does
+//! not know the date, so applications tell the capsule when a new day
+//! starts, with a day number of their choice.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let pedometer = components::pedometer::PedometerComponent::new(
+//!     board_kernel,
+//!     capsules_extra::pedometer::DRIVER_NUM,
+//!     mux_alarm,
+//!     lsm6dsoxtr,
+//!     nonvolatile_storage,
+//!     0xFFF000,
+//! )
+//! .finalize(components::pedometer_component_static!(RPTimer));
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: return the number of steps of the current day, and the
+//!   number of the day.
+//! - Command 2: start the day with the number given as the first argument,
+//!   with no steps. Nothing changes if it is the current day.
+//! - Command 3: reset the number of steps of the current day.
+//!
+//! Upcall 0 is scheduled for every application at each step, with the
+//! number of steps of the day.
+
+use core::cell::Cell;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::nonvolatile_storage::{NonvolatileStorage, NonvolatileStorageClient};
+use kernel::hil::sensors::{NineDof, NineDofClient};
+use kernel::hil::time::{Alarm, AlarmClient, ConvertTicks, Ticks};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::TakeCell;
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::Pedometer as usize;
+
+/// Number of bytes of nonvolatile storage used for the count.
+pub const STORAGE_LEN: usize = 12;
+
+/// Rate at which the accelerometer is read.
+pub const SAMPLE_RATE_HZ: u32 = 25;
+/// How far the magnitude of the acceleration swings around its average at
+/// each step.
+pub const THRESHOLD_MG: i32 = 100;
+/// Shortest time between two steps.
+pub const MIN_STEP_INTERVAL_MS: u32 = 250;
+/// Shortest time between two writes of the count while steps are counted.
+pub const STORE_INTERVAL_S: u32 = 60;
+
+/// Marks a stored count, so that erased or unrelated storage is ignored.
+const MAGIC: u32 = 0x57E9_C0DE;
+
+fn isqrt(value: u64) -> u64 {
+    if value < 2 {
+        return value;
+    }
+    // Newton's method, from a guess above the root
+    let mut root = 1 << ((64 - value.leading_zeros() + 1) / 2);
+    loop {
+        let next = (root + value / root) / 2;
+        if next >= root {
+            return root;
+        }
+        root = next;
+    }
+}
+
+/// Detects steps in the magnitude of the acceleration.
+#[derive(Clone, Copy)]
+pub struct StepDetector {
+    /// The smoothed magnitude.
+    filtered: i32,
+    /// The slow moving average of the magnitude.
+    average: i32,
+    /// The magnitude fell below the average since the last step.
+    armed: bool,
+    /// Samples since the last step.
+    since_step: u32,
+    started: bool,
+}
+
+impl StepDetector {
+    pub const fn new() -> StepDetector {
+        StepDetector {
+            filtered: 0,
+            average: 0,
+            armed: false,
+            since_step: 0,
+            started: false,
+        }
+    }
+
+    /// Called with each sample of the acceleration along the three axes, in
+    /// mg, taken at `SAMPLE_RATE_HZ`. Returns whether the sample completes a
+    /// step.
+    pub fn sample(&mut self, x: i32, y: i32, z: i32) -> bool {
+        let square = |value: i32| (value as i64 * value as i64) as u64;
+        let magnitude = isqrt(square(x) + square(y) + square(z)).min(i32::MAX as u64) as i32;
+        if !self.started {
+            self.started = true;
+            self.filtered = magnitude;
+            self.average = magnitude;
+        }
+        self.filtered += (magnitude - self.filtered) / 4;
+        self.average += (self.filtered - self.average) / 16;
+        self.since_step = self.since_step.saturating_add(1);
+
+        if self.filtered < self.average - THRESHOLD_MG {
+            self.armed = true;
+        } else if self.armed
+            && self.filtered > self.average + THRESHOLD_MG
+            && self.since_step >= MIN_STEP_INTERVAL_MS * SAMPLE_RATE_HZ / 1000
+        {
+            self.armed = false;
+            self.since_step = 0;
+            return true;
+        }
+        false
+    }
+}
+
+pub struct Pedometer<'a, A: Alarm<'a>> {
+    sensor: &'a dyn NineDof<'a>,
+    alarm: &'a A,
+    storage: &'a dyn NonvolatileStorage<'a>,
+    /// Address of the count in the storage.
+    address: usize,
+    buffer: TakeCell<'static, [u8]>,
+    apps: Grant<(), UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    detector: Cell<StepDetector>,
+    steps: Cell<u32>,
+    day: Cell<u32>,
+    /// The count changed since it was stored.
+    changed: Cell<bool>,
+    /// Samples left until the count may be stored again.
+    samples_to_store: Cell<u32>,
+    /// Time of the last sample.
+    sample_start: Cell<A::Ticks>,
+    /// A read of the accelerometer has not completed.
+    reading: Cell<bool>,
+}
+
+impl<'a, A: Alarm<'a>> Pedometer<'a, A> {
+    pub fn new(
+        sensor: &'a dyn NineDof<'a>,
+        alarm: &'a A,
+        storage: &'a dyn NonvolatileStorage<'a>,
+        address: usize,
+        buffer: &'static mut [u8],
+        grant: Grant<(), UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    ) -> Pedometer<'a, A> {
+        Pedometer {
+            sensor,
+            alarm,
+            storage,
+            address,
+            buffer: TakeCell::new(buffer),
+            apps: grant,
+            detector: Cell::new(StepDetector::new()),
+            steps: Cell::new(0),
+            day: Cell::new(0),
+            changed: Cell::new(false),
+            samples_to_store: Cell::new(0),
+            sample_start: Cell::new(A::Ticks::from(0)),
+            reading: Cell::new(false),
+        }
+    }
+
+    /// Load the stored count, and start counting steps once it is loaded.
+    pub fn start(&self) -> Result<(), ErrorCode> {
+        let buffer = self.buffer.take().ok_or(ErrorCode::BUSY)?;
+        if let Err(err) = self.storage.read(buffer, self.address, STORAGE_LEN) {
+            self.start_sampling();
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    fn start_sampling(&self) {
+        self.sample_start.set(self.alarm.now());
+        self.schedule_sample();
+    }
+
+    /// Time the next sample from the previous one, so they do not drift.
+    fn schedule_sample(&self) {
+        let start = self.sample_start.get();
+        let period = self.alarm.ticks_from_us(1_000_000 / SAMPLE_RATE_HZ);
+        self.sample_start.set(start.wrapping_add(period));
+        self.alarm.set_alarm(start, period);
+    }
+
+    /// Store the count if it changed, unless it is being stored.
+    fn store(&self) {
+        if !self.changed.get() {
+            return;
+        }
+        self.buffer.take().map(|buffer| {
+            buffer[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+            buffer[4..8].copy_from_slice(&self.day.get().to_le_bytes());
+            buffer[8..12].copy_from_slice(&self.steps.get().to_le_bytes());
+            self.changed.set(false);
+            self.samples_to_store.set(STORE_INTERVAL_S * SAMPLE_RATE_HZ);
+            if self
+                .storage
+                .write(buffer, self.address, STORAGE_LEN)
+                .is_err()
+            {
+                self.changed.set(true);
+            }
+        });
+    }
+
+    /// Set the count and store it at once.
+    fn set_count(&self, day: u32, steps: u32) {
+        self.day.set(day);
+        self.steps.set(steps);
+        self.changed.set(true);
+        // Retried at each sample while the count is being stored
+        self.samples_to_store.set(0);
+        self.store();
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for Pedometer<'a, A> {
+    fn alarm(&self) {
+        // Skip the sample if the sensor is too slow
+        if !self.reading.get() && self.sensor.read_accelerometer().is_ok() {
+            self.reading.set(true);
+        }
+        self.schedule_sample();
+    }
+}
+
+impl<'a, A: Alarm<'a>> NineDofClient for Pedometer<'a, A> {
+    fn callback(&self, x: usize, y: usize, z: usize) {
+        self.reading.set(false);
+
+        // The values are signed
+        let mut detector = self.detector.get();
+        let step = detector.sample(x as i32, y as i32, z as i32);
+        self.detector.set(detector);
+
+        if step {
+            let steps = self.steps.get().saturating_add(1);
+            self.steps.set(steps);
+            self.changed.set(true);
+            self.apps.each(|_, _, kernel_data| {
+                kernel_data.schedule_upcall(0, (steps as usize, 0, 0)).ok();
+            });
+        }
+
+        let samples_to_store = self.samples_to_store.get().saturating_sub(1);
+        self.samples_to_store.set(samples_to_store);
+        if samples_to_store == 0 {
+            self.store();
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> NonvolatileStorageClient for Pedometer<'a, A> {
+    fn read_done(&self, buffer: &'static mut [u8], _length: usize) {
+        if buffer[0..4] == MAGIC.to_le_bytes() {
+            let word = |i: usize| {
+                u32::from_le_bytes([buffer[i], buffer[i + 1], buffer[i + 2], buffer[i + 3]])
+            };
+            self.day.set(word(4));
+            self.steps.set(word(8));
+        }
+        self.buffer.replace(buffer);
+        self.start_sampling();
+    }
+
+    fn write_done(&self, buffer: &'static mut [u8], _length: usize) {
+        self.buffer.replace(buffer);
+    }
+}
+
+impl<'a, A: Alarm<'a>> SyscallDriver for Pedometer<'a, A> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        _data2: usize,
+        _processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            1 => CommandReturn::success_u32_u32(self.steps.get(), self.day.get()),
+
+            // Start a day
+            2 => {
+                if data1 > u32::MAX as usize {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                if data1 as u32 != self.day.get() {
+                    self.set_count(data1 as u32, 0);
+                }
+                CommandReturn::success()
+            }
+
+            // Reset the count
+            3 => {
+                self.set_count(self.day.get(), 0);
+                CommandReturn::success()
+            }
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
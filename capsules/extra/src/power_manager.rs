@@ -0,0 +1,436 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Duty cycling of the devices of battery-powered sensor nodes.
+//!
+//! A sensor node spends most of its time waiting: it samples its sensors
+//! every few minutes, and connects to upload the samples less often. The
+//! power manager keeps the devices used by these activities in their
+//! lowest power state outside of the windows in which they are used:
+//!
+//! - every sample period, it powers the sensor domains up and opens a
+//!   sample window;
+//! - every upload period, it powers the radio domains up, for example by
+//!   releasing the reset of a WiFi module, and opens an upload window.
+//!
+//! The application owning the schedule is notified when a window opens, and
+//! closes it when it is done. A window which is not closed within the
+//! window timeout is closed by the power manager. While every domain is
+//! down, the kernel has nothing to do until the next window, so the chip
+//! stays in the sleep state of its idle loop.
+//!
+//! Boards whose chip has a deep sleep state can also pass it to the power
+//! manager with `set_deep_sleep`: deep sleep is then allowed while the
+//! schedule runs and no window is open. The alarm stops on chips which stop
+//! their timers in deep sleep, so the board must arm a wakeup source which
+//! fires for the next window, such as a real time clock alarm.
+//!
+//! Devices are controlled through the `PowerDomain` trait. `GpioPowerDomain`
+//! controls a device powered or held in reset by a pin, and the LSM303
+//! drivers power their accelerometer down.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let nina_reset = static_init!(
+//!     capsules_extra::power_manager::GpioPowerDomain<'static, RPGpioPin<'static>>,
+//!     capsules_extra::power_manager::GpioPowerDomain::new(
+//!         peripherals.pins.get_pin(RPGpio::GPIO3),
+//!         false,
+//!     )
+//! );
+//! let power_manager = components::power_manager::PowerManagerComponent::new(
+//!     board_kernel,
+//!     capsules_extra::power_manager::DRIVER_NUM,
+//!     mux_alarm,
+//!     &[],
+//!     &[nina_reset],
+//! )
+//! .finalize(components::power_manager_component_static!(RPTimer));
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! Activities are numbered 0 for sampling and 1 for uploading.
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: set the period of the activity given as the first argument
+//!   to the number of milliseconds given as the second argument. A period
+//!   of 0 disables the activity.
+//! - Command 2: set the window timeout, in milliseconds.
+//! - Command 3: start the schedule, and power every domain down until its
+//!   first window. The calling application becomes the owner of the
+//!   schedule.
+//! - Command 4: stop the schedule, and power every domain up.
+//! - Command 5: close the window of the activity given as the first
+//!   argument.
+//!
+//! Upcall 0 reports, with the activity as its first argument, that a window
+//! opened, and with the activity as its first argument and 1 as its second
+//! argument that the window timed out.
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::deep_sleep::DeepSleep;
+use kernel::hil::gpio;
+use kernel::hil::time::{self, ConvertTicks, Ticks};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::OptionalCell;
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::PowerManager as usize;
+
+/// Default window timeout.
+pub const DEFAULT_WINDOW_TIMEOUT_MS: u32 = 60_000;
+
+/// A device, or a group of devices, which the power manager powers down
+/// outside of the windows in which it is used.
+pub trait PowerDomain {
+    /// Power the domain up, or put it in its lowest power state. The change
+    /// may complete after the call returns.
+    fn set_powered(&self, powered: bool) -> Result<(), ErrorCode>;
+}
+
+/// A device powered by a pin, or held in reset by it.
+pub struct GpioPowerDomain<'a, P: gpio::Pin> {
+    pin: &'a P,
+    /// The device is powered when the pin is low.
+    active_low: bool,
+}
+
+impl<'a, P: gpio::Pin> GpioPowerDomain<'a, P> {
+    /// Devices held in reset while the pin is low, such as the NINA WiFi
+    /// modules, are powered when the pin is high, so `active_low` is
+    /// `false`. The domain starts powered.
+    pub fn new(pin: &'a P, active_low: bool) -> GpioPowerDomain<'a, P> {
+        let domain = GpioPowerDomain { pin, active_low };
+        pin.make_output();
+        let _ = domain.set_powered(true);
+        domain
+    }
+}
+
+impl<'a, P: gpio::Pin> PowerDomain for GpioPowerDomain<'a, P> {
+    fn set_powered(&self, powered: bool) -> Result<(), ErrorCode> {
+        if powered != self.active_low {
+            self.pin.set();
+        } else {
+            self.pin.clear();
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Activity {
+    Sample = 0,
+    Upload = 1,
+}
+
+impl Activity {
+    fn from_usize(activity: usize) -> Option<Activity> {
+        match activity {
+            0 => Some(Activity::Sample),
+            1 => Some(Activity::Upload),
+            _ => None,
+        }
+    }
+}
+
+/// The timing of an activity, in ticks.
+#[derive(Clone, Copy, Default)]
+struct Timing {
+    /// 0 when the activity is disabled.
+    period: u32,
+    /// Lower bits of the ticks at the start of the current period.
+    reference: u32,
+    /// Lower bits of the ticks at which the window opened, while it is open.
+    window: Option<u32>,
+}
+
+#[derive(Default)]
+pub struct App;
+
+pub struct PowerManager<'a, A: time::Alarm<'a>> {
+    alarm: &'a A,
+    sensors: &'a [&'a dyn PowerDomain],
+    radio: &'a [&'a dyn PowerDomain],
+    /// The timings of sampling and uploading.
+    timings: [Cell<Timing>; 2],
+    window_timeout: Cell<u32>,
+    running: Cell<bool>,
+    deep_sleep: OptionalCell<&'a dyn DeepSleep>,
+    owning_process: OptionalCell<ProcessId>,
+    apps: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+}
+
+impl<'a, A: time::Alarm<'a>> PowerManager<'a, A> {
+    pub fn new(
+        alarm: &'a A,
+        sensors: &'a [&'a dyn PowerDomain],
+        radio: &'a [&'a dyn PowerDomain],
+        grant: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    ) -> PowerManager<'a, A> {
+        PowerManager {
+            alarm,
+            sensors,
+            radio,
+            timings: [Cell::new(Timing::default()), Cell::new(Timing::default())],
+            window_timeout: Cell::new(alarm.ticks_from_ms(DEFAULT_WINDOW_TIMEOUT_MS).into_u32()),
+            running: Cell::new(false),
+            deep_sleep: OptionalCell::empty(),
+            owning_process: OptionalCell::empty(),
+            apps: grant,
+        }
+    }
+
+    /// Allow deep sleep between windows.
+    pub fn set_deep_sleep(&self, deep_sleep: &'a dyn DeepSleep) {
+        self.deep_sleep.set(deep_sleep);
+    }
+
+    /// Set the period of `activity`, 0 disabling it. Takes effect when the
+    /// schedule starts.
+    pub fn set_period(&self, activity: Activity, period_ms: u32) -> Result<(), ErrorCode> {
+        if self.running.get() {
+            return Err(ErrorCode::BUSY);
+        }
+        let period = match period_ms {
+            0 => 0,
+            _ => cmp::max(self.alarm.ticks_from_ms(period_ms).into_u32(), 1),
+        };
+        let timing = &self.timings[activity as usize];
+        timing.set(Timing {
+            period,
+            ..timing.get()
+        });
+        Ok(())
+    }
+
+    pub fn set_window_timeout(&self, timeout_ms: u32) {
+        self.window_timeout
+            .set(cmp::max(self.alarm.ticks_from_ms(timeout_ms).into_u32(), 1));
+    }
+
+    /// Power every domain down and open the windows of the activities every
+    /// period.
+    pub fn start(&self) -> Result<(), ErrorCode> {
+        if self.running.get() {
+            return Err(ErrorCode::ALREADY);
+        }
+        let now = self.alarm.now().into_u32();
+        for timing in self.timings.iter() {
+            timing.set(Timing {
+                reference: now,
+                window: None,
+                ..timing.get()
+            });
+        }
+        self.running.set(true);
+        self.power(Activity::Sample, false);
+        self.power(Activity::Upload, false);
+        self.schedule();
+        Ok(())
+    }
+
+    /// Stop the schedule and power every domain up.
+    pub fn stop(&self) {
+        self.running.set(false);
+        let _ = self.alarm.disarm();
+        for timing in self.timings.iter() {
+            timing.set(Timing {
+                window: None,
+                ..timing.get()
+            });
+        }
+        self.power(Activity::Sample, true);
+        self.power(Activity::Upload, true);
+        self.update_deep_sleep();
+    }
+
+    /// Close the window of `activity`, powering its domains down.
+    pub fn close_window(&self, activity: Activity) -> Result<(), ErrorCode> {
+        let timing = &self.timings[activity as usize];
+        if timing.get().window.is_none() {
+            return Err(ErrorCode::ALREADY);
+        }
+        timing.set(Timing {
+            window: None,
+            ..timing.get()
+        });
+        self.power(activity, false);
+        self.schedule();
+        Ok(())
+    }
+
+    fn power(&self, activity: Activity, powered: bool) {
+        let domains = match activity {
+            Activity::Sample => self.sensors,
+            Activity::Upload => self.radio,
+        };
+        for domain in domains {
+            let _ = domain.set_powered(powered);
+        }
+    }
+
+    fn notify(&self, activity: Activity, timed_out: bool) {
+        self.owning_process.map(|processid| {
+            let _ = self.apps.enter(*processid, |_, kernel_data| {
+                let _ = kernel_data.schedule_upcall(0, (activity as usize, timed_out as usize, 0));
+            });
+        });
+    }
+
+    /// Allow deep sleep while the schedule runs and every window is closed.
+    fn update_deep_sleep(&self) {
+        let allowed = self.running.get()
+            && self
+                .timings
+                .iter()
+                .all(|timing| timing.get().window.is_none());
+        self.deep_sleep
+            .map(|deep_sleep| deep_sleep.set_deep_sleep_allowed(allowed));
+    }
+
+    /// Arm the alarm for the next window to open or time out.
+    fn schedule(&self) {
+        self.update_deep_sleep();
+        if !self.running.get() {
+            return;
+        }
+        let now = self.alarm.now();
+        let now_lower_bits = now.into_u32();
+        let timeout = self.window_timeout.get();
+        let earliest = self
+            .timings
+            .iter()
+            .flat_map(|timing| {
+                let timing = timing.get();
+                let open = (timing.period != 0).then(|| {
+                    timing
+                        .period
+                        .saturating_sub(now_lower_bits.wrapping_sub(timing.reference))
+                });
+                let close = timing
+                    .window
+                    .map(|window| timeout.saturating_sub(now_lower_bits.wrapping_sub(window)));
+                [open, close]
+            })
+            .flatten()
+            .min();
+        match earliest {
+            Some(dt) => self.alarm.set_alarm(now, A::Ticks::from(dt)),
+            None => {
+                let _ = self.alarm.disarm();
+            }
+        }
+    }
+}
+
+impl<'a, A: time::Alarm<'a>> time::AlarmClient for PowerManager<'a, A> {
+    fn alarm(&self) {
+        if !self.running.get() {
+            return;
+        }
+        let now = self.alarm.now().into_u32();
+        let timeout = self.window_timeout.get();
+        for activity in [Activity::Sample, Activity::Upload] {
+            let cell = &self.timings[activity as usize];
+            let mut timing = cell.get();
+
+            if let Some(window) = timing.window {
+                if now.wrapping_sub(window) >= timeout {
+                    timing.window = None;
+                    cell.set(timing);
+                    self.power(activity, false);
+                    self.notify(activity, true);
+                }
+            }
+
+            if timing.period != 0 && now.wrapping_sub(timing.reference) >= timing.period {
+                timing.reference = timing.reference.wrapping_add(timing.period);
+                // Skip the periods missed while the kernel was busy.
+                if now.wrapping_sub(timing.reference) >= timing.period {
+                    timing.reference = now;
+                }
+                // A window still open is extended rather than reopened.
+                let opened = timing.window.is_none();
+                timing.window = Some(now);
+                cell.set(timing);
+                if opened {
+                    self.power(activity, true);
+                }
+                self.notify(activity, false);
+            }
+        }
+        self.schedule();
+    }
+}
+
+impl<'a, A: time::Alarm<'a>> SyscallDriver for PowerManager<'a, A> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        if command_num == 0 {
+            return CommandReturn::success();
+        }
+
+        // The first process to use the driver owns the schedule, as long as
+        // it exists.
+        let match_or_empty_or_nonexistant = self.owning_process.map_or(true, |current_process| {
+            self.apps
+                .enter(*current_process, |_, _| current_process == &processid)
+                .unwrap_or(true)
+        });
+        if !match_or_empty_or_nonexistant {
+            return CommandReturn::failure(ErrorCode::RESERVE);
+        }
+        self.owning_process.set(processid);
+
+        match command_num {
+            1 => match Activity::from_usize(data1) {
+                Some(activity) if data2 <= u32::MAX as usize => {
+                    self.set_period(activity, data2 as u32).into()
+                }
+                _ => CommandReturn::failure(ErrorCode::INVAL),
+            },
+
+            2 => {
+                if data1 > u32::MAX as usize {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                self.set_window_timeout(data1 as u32);
+                CommandReturn::success()
+            }
+
+            3 => self.start().into(),
+
+            4 => {
+                self.stop();
+                CommandReturn::success()
+            }
+
+            5 => match Activity::from_usize(data1) {
+                Some(activity) => self.close_window(activity).into(),
+                None => CommandReturn::failure(ErrorCode::INVAL),
+            },
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
@@ -0,0 +1,162 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Provides userspace with access to barometric pressure sensors.
+//!
+//! Userspace Interface
+//! -------------------
+//!
+//! ### `subscribe` System Call
+//!
+//! The `subscribe` system call supports the single `subscribe_number` zero,
+//! which is used to provide a callback that will return back the result of
+//! a pressure sensor reading.
+//! The `subscribe`call return codes indicate the following:
+//!
+//! * `Ok(())`: the callback been successfully been configured.
+//! * `ENOSUPPORT`: Invalid allow_num.
+//! * `NOMEM`: No sufficient memory available.
+//! * `INVAL`: Invalid address of the buffer or other error.
+//!
+//!
+//! ### `command` System Call
+//!
+//! The `command` system call support one argument `cmd` which is used to specify the specific
+//! operation, currently the following cmd's are supported:
+//!
+//! * `0`: check whether the driver exist
+//! * `1`: read the pressure
+//!
+//!
+//! The possible return from the 'command' system call indicates the following:
+//!
+//! * `Ok(())`:    The operation has been successful.
+//! * `BUSY`:      The driver is busy.
+//! * `ENOSUPPORT`: Invalid `cmd`.
+//! * `NOMEM`:     No sufficient memory available.
+//! * `INVAL`:     Invalid address of the buffer or other error.
+//!
+//! The callback receives the status of the reading and the pressure in
+//! pascals.
+//!
+//! Usage
+//! -----
+//!
+//! You need a device that provides the `hil::sensors::PressureDriver` trait.
+//!
+//! ```rust
+//! # use kernel::static_init;
+//!
+//! let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+//!
+//! let pressure = static_init!(
+//!        capsules::pressure::PressureSensor<'static>,
+//!        capsules::pressure::PressureSensor::new(bme280,
+//!                                                board_kernel.create_grant(&grant_cap)));
+//!
+//! kernel::hil::sensors::PressureDriver::set_client(bme280, pressure);
+//! ```
+
+use core::cell::Cell;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::Pressure as usize;
+
+#[derive(Default)]
+pub struct App {
+    subscribed: bool,
+}
+
+pub struct PressureSensor<'a> {
+    driver: &'a dyn hil::sensors::PressureDriver<'a>,
+    apps: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    busy: Cell<bool>,
+}
+
+impl<'a> PressureSensor<'a> {
+    pub fn new(
+        driver: &'a dyn hil::sensors::PressureDriver<'a>,
+        grant: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    ) -> PressureSensor<'a> {
+        PressureSensor {
+            driver,
+            apps: grant,
+            busy: Cell::new(false),
+        }
+    }
+
+    fn enqueue_command(&self, processid: ProcessId) -> CommandReturn {
+        self.apps
+            .enter(processid, |app, _| {
+                // Share the reading in progress, if any
+                app.subscribed = true;
+                if !self.busy.get() {
+                    let result = self.driver.read_pressure();
+                    if result.is_ok() {
+                        self.busy.set(true);
+                    } else {
+                        app.subscribed = false;
+                    }
+                    result.into()
+                } else {
+                    CommandReturn::success()
+                }
+            })
+            .unwrap_or_else(|err| CommandReturn::failure(err.into()))
+    }
+}
+
+impl hil::sensors::PressureClient for PressureSensor<'_> {
+    fn callback(&self, value: Result<u32, ErrorCode>) {
+        self.busy.set(false);
+        let pressure = value.unwrap_or(0);
+        for cntr in self.apps.iter() {
+            cntr.enter(|app, upcalls| {
+                if app.subscribed {
+                    app.subscribed = false;
+                    upcalls
+                        .schedule_upcall(
+                            0,
+                            (
+                                kernel::errorcode::into_statuscode(value.map(|_| ())),
+                                pressure as usize,
+                                0,
+                            ),
+                        )
+                        .ok();
+                }
+            });
+        }
+    }
+}
+
+impl SyscallDriver for PressureSensor<'_> {
+    fn command(
+        &self,
+        command_num: usize,
+        _: usize,
+        _: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            // check whether the driver exists
+            0 => CommandReturn::success(),
+
+            // read the pressure
+            1 => self.enqueue_command(processid),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
@@ -0,0 +1,118 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Keeps the load reports of processes for the process console.
+//!
+//! `ProcessLoadLog` is a `kernel::process_load_report::ProcessLoadMonitor`
+//! which stores the reports of the processes the kernel tried to load: the
+//! flash and RAM each one required, and what was left of the application
+//! regions of the board. Processes which did not fit are printed by the
+//! kernel when they are loaded, but the console can show them again later.
+//!
+//! It implements the `loading` command of the process console:
+//!
+//! ```text
+//! tock$ loading
+//! Process loading: 2 loaded, 1 failed
+//!  [0] "blink" flash 2048/65536 B at 0x60040000, RAM 5120/131072 B at 0x20200000: loaded
+//!  [1] "c_hello" flash 4096/63488 B at 0x60040800, RAM 9216/125952 B at 0x20201400: loaded
+//!  [2] "sensors" flash 65536/59392 B at 0x60041800, RAM ?/116736 B at 0x20203800: Not enough flash available for TBF
+//! ```
+//!
+//! Flash and RAM are printed as `required/available` bytes. Only the first
+//! reports are kept once the log is full.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let process_load_log = components::process_load_log::ProcessLoadLogComponent::new()
+//!     .finalize(components::process_load_log_component_static!(4));
+//! process_console.add_command(process_load_log).unwrap();
+//!
+//! impl KernelHooks for Board {
+//!     fn process_load_monitor(&self) -> Option<&dyn ProcessLoadMonitor> {
+//!         Some(self.process_load_log)
+//!     }
+//! }
+//! ```
+
+use core::cell::Cell;
+use core::fmt;
+
+use capsules_core::process_console::ConsoleCommand;
+use kernel::process_load_report::{ProcessLoadMonitor, ProcessLoadReport};
+
+pub struct ProcessLoadLog<'a> {
+    log: &'a [Cell<Option<ProcessLoadReport>>],
+    /// Reports in the log.
+    count: Cell<usize>,
+    loaded: Cell<usize>,
+    failed: Cell<usize>,
+}
+
+impl<'a> ProcessLoadLog<'a> {
+    pub fn new(log: &'a [Cell<Option<ProcessLoadReport>>]) -> Self {
+        Self {
+            log,
+            count: Cell::new(0),
+            loaded: Cell::new(0),
+            failed: Cell::new(0),
+        }
+    }
+
+    /// Report `index` of the log, in the order processes were loaded.
+    pub fn get(&self, index: usize) -> Option<ProcessLoadReport> {
+        if index < self.count.get() {
+            self.log[index].get()
+        } else {
+            None
+        }
+    }
+
+    /// Number of processes which were not loaded.
+    pub fn failed(&self) -> usize {
+        self.failed.get()
+    }
+}
+
+impl<'a> ProcessLoadMonitor for ProcessLoadLog<'a> {
+    fn process_load_attempted(&self, report: &ProcessLoadReport) {
+        match report.result {
+            Ok(()) => self.loaded.set(self.loaded.get() + 1),
+            Err(_) => self.failed.set(self.failed.get() + 1),
+        }
+        let count = self.count.get();
+        if count < self.log.len() {
+            self.log[count].set(Some(*report));
+            self.count.set(count + 1);
+        }
+    }
+}
+
+impl<'a> ConsoleCommand for ProcessLoadLog<'a> {
+    fn name(&self) -> &'static str {
+        "loading"
+    }
+
+    fn execute(&self, _arguments: &str) {}
+
+    fn write_line(&self, index: usize, writer: &mut dyn fmt::Write) -> bool {
+        if index == 0 {
+            let _ = writer.write_fmt(format_args!(
+                "Process loading: {} loaded, {} failed\r\n",
+                self.loaded.get(),
+                self.failed.get()
+            ));
+            return true;
+        }
+        match self.get(index - 1) {
+            Some(report) => {
+                let _ = writer.write_fmt(format_args!(" {}\r\n", report));
+                true
+            }
+            None => false,
+        }
+    }
+}
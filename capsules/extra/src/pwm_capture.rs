@@ -0,0 +1,131 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Provides userspace with access to input capture pins, to measure the
+//! frequency and duty cycle of an external signal.
+//!
+//! Only one measurement can be in progress at a time. The result is
+//! delivered through upcall 0 as `(statuscode, period_ns, high_ns)`.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let pwm_capture = components::pwm_capture::PwmCaptureComponent::new(
+//!     board_kernel,
+//!     capsules_extra::pwm_capture::DRIVER_NUM,
+//! )
+//! .finalize(components::pwm_capture_component_static!(
+//!     &peripherals.qtmr1.channels[0],
+//!     &peripherals.qtmr1.channels[1],
+//! ));
+//! ```
+
+use kernel::errorcode::into_statuscode;
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::pwm_capture::{PwmCapture, PwmCaptureClient};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::OptionalCell;
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::PwmCapture as usize;
+
+#[derive(Default)]
+pub struct App;
+
+pub struct PwmCaptureDriver<'a, const NUM_PINS: usize> {
+    /// The usable input capture pins.
+    pins: &'a [&'a dyn PwmCapture<'a>; NUM_PINS],
+    /// Per-app state.
+    apps: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    /// The app and pin of the measurement in progress.
+    current: OptionalCell<(ProcessId, usize)>,
+}
+
+impl<'a, const NUM_PINS: usize> PwmCaptureDriver<'a, NUM_PINS> {
+    pub fn new(
+        pins: &'a [&'a dyn PwmCapture<'a>; NUM_PINS],
+        grant: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    ) -> PwmCaptureDriver<'a, NUM_PINS> {
+        PwmCaptureDriver {
+            pins,
+            apps: grant,
+            current: OptionalCell::empty(),
+        }
+    }
+}
+
+impl<'a, const NUM_PINS: usize> PwmCaptureClient for PwmCaptureDriver<'a, NUM_PINS> {
+    fn capture_done(&self, result: Result<(), ErrorCode>, period_ns: u32, high_ns: u32) {
+        self.current.take().map(|(processid, _)| {
+            let _ = self.apps.enter(processid, |_, kernel_data| {
+                kernel_data
+                    .schedule_upcall(
+                        0,
+                        (
+                            into_statuscode(result),
+                            period_ns as usize,
+                            high_ns as usize,
+                        ),
+                    )
+                    .ok();
+            });
+        });
+    }
+}
+
+impl<'a, const NUM_PINS: usize> SyscallDriver for PwmCaptureDriver<'a, NUM_PINS> {
+    /// Command interface.
+    ///
+    /// ### `command_num`
+    ///
+    /// - `0`: Return number of input capture pins if this driver is included
+    ///   on the platform.
+    /// - `1`: Start a measurement on the pin `data1`. Upcall 0 is scheduled
+    ///   with the period and the high time of the signal, in nanoseconds.
+    /// - `2`: Abort the measurement started by this app.
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        _data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success_u32(NUM_PINS as u32),
+
+            1 => {
+                let pin = data1;
+                if pin >= NUM_PINS {
+                    CommandReturn::failure(ErrorCode::INVAL)
+                } else if self.current.is_some() {
+                    CommandReturn::failure(ErrorCode::BUSY)
+                } else {
+                    let result = self.pins[pin].start_capture();
+                    if result.is_ok() {
+                        self.current.set((processid, pin));
+                    }
+                    result.into()
+                }
+            }
+
+            2 => match self.current.extract() {
+                Some((owner, pin)) if owner == processid => {
+                    self.current.clear();
+                    self.pins[pin].stop_capture().into()
+                }
+                Some(_) => CommandReturn::failure(ErrorCode::RESERVE),
+                None => CommandReturn::failure(ErrorCode::OFF),
+            },
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
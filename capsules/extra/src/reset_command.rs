@@ -0,0 +1,66 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Resets the chip from the process console, optionally into its ROM
+//! bootloader.
+//!
+//! `ResetCommand` replaces the `reset` command of the process console:
+//! `reset` resets the chip and boots the kernel again, and `reset bootloader`
+//! resets it into its ROM bootloader, so that a board which is out of reach
+//! can be flashed again, for example over a network console and a USB or
+//! serial link to another device.
+//!
+//! ```text
+//! tock$ reset bootloader
+//! ```
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let reset_command = components::reset_command::ResetCommandComponent::new(&peripherals.src)
+//!     .finalize(components::reset_command_component_static!());
+//! process_console.add_command(reset_command).unwrap();
+//! ```
+
+use core::fmt;
+
+use capsules_core::process_console::ConsoleCommand;
+use kernel::capabilities::ResetCapability;
+use kernel::hil::reset::Reset;
+
+pub struct ResetCommand<'a, C: ResetCapability> {
+    reset: &'a dyn Reset,
+    capability: C,
+}
+
+impl<'a, C: ResetCapability> ResetCommand<'a, C> {
+    pub fn new(reset: &'a dyn Reset, capability: C) -> Self {
+        Self { reset, capability }
+    }
+}
+
+impl<'a, C: ResetCapability> ConsoleCommand for ResetCommand<'a, C> {
+    fn name(&self) -> &'static str {
+        "reset"
+    }
+
+    fn execute(&self, arguments: &str) {
+        match arguments.trim() {
+            "" => self.reset.reset(&self.capability),
+            "bootloader" => self.reset.reset_to_bootloader(&self.capability),
+            _ => {}
+        }
+    }
+
+    fn write_line(&self, index: usize, writer: &mut dyn fmt::Write) -> bool {
+        // Only reached if the arguments were not understood.
+        if index == 0 {
+            let _ = writer.write_str("Usage: reset [bootloader]\r\n");
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,281 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Driver for 4-wire resistive touch panels.
+//!
+//! A resistive panel is made of two resistive layers, connected to the X+
+//! and X- pins, and to the Y+ and Y- pins. Applying a voltage across one
+//! layer makes the other layer a voltage divider when the panel is pressed,
+//! so the position is read with the ADC on the other layer.
+//!
+//! X- and Y- are plain GPIOs. X+ and Y+ are GPIOs which are also ADC
+//! channels. At every sampling interval, the driver:
+//!
+//! 1. Checks for a touch, by driving Y- low and reading X+ with a pull-up.
+//! 2. Drives X+ high and X- low, and samples Y+ `SAMPLES` times.
+//! 3. Drives Y+ high and Y- low, and samples X+ `SAMPLES` times.
+//! 4. Checks that the panel is still pressed, so that readings taken while
+//!    it was being released are dropped, and reports the medians of the
+//!    samples.
+//!
+//! The coordinates are ADC readings. Use a `TouchCalibration` to map them to
+//! the screen.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let resistive_touch = components::resistive_touch::ResistiveTouchComponent::new(
+//!     adc_mux,
+//!     rp2040::adc::Channel::Channel0,
+//!     rp2040::adc::Channel::Channel1,
+//!     &peripherals.pins.get_pin(RPGpio::GPIO26),
+//!     &peripherals.pins.get_pin(RPGpio::GPIO22),
+//!     &peripherals.pins.get_pin(RPGpio::GPIO27),
+//!     &peripherals.pins.get_pin(RPGpio::GPIO21),
+//!     mux_alarm,
+//!     capsules_extra::resistive_touch::DEFAULT_SAMPLE_INTERVAL_MS,
+//! )
+//! .finalize(components::resistive_touch_component_static!(
+//!     rp2040::adc::Adc<'static>,
+//!     rp2040::gpio::RPGpioPin<'static>,
+//!     rp2040::timer::RPTimer<'static>
+//! ));
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::adc;
+use kernel::hil::gpio;
+use kernel::hil::time::{self, ConvertTicks};
+use kernel::hil::touch::{self, TouchEvent, TouchStatus};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Number of ADC samples of each coordinate, of which the median is kept.
+pub const SAMPLES: usize = 5;
+
+/// Default time between two readings of the panel.
+pub const DEFAULT_SAMPLE_INTERVAL_MS: u32 = 20;
+
+#[derive(Copy, Clone, PartialEq)]
+enum State {
+    Disabled,
+    Waiting,
+    SamplingX,
+    SamplingY,
+}
+
+pub struct ResistiveTouch<'a, P: gpio::Pin, A: time::Alarm<'a>> {
+    x_plus: &'a P,
+    x_minus: &'a P,
+    y_plus: &'a P,
+    y_minus: &'a P,
+    /// ADC channel of the X+ pin, which reads the Y position.
+    x_channel: &'a dyn adc::AdcChannel<'a>,
+    /// ADC channel of the Y+ pin, which reads the X position.
+    y_channel: &'a dyn adc::AdcChannel<'a>,
+    alarm: &'a A,
+    interval_ms: u32,
+    client: OptionalCell<&'a dyn touch::TouchClient>,
+    state: Cell<State>,
+    samples: Cell<[u16; SAMPLES]>,
+    num_samples: Cell<usize>,
+    x: Cell<u16>,
+    y: Cell<u16>,
+    pressed: Cell<bool>,
+}
+
+impl<'a, P: gpio::Pin, A: time::Alarm<'a>> ResistiveTouch<'a, P, A> {
+    pub fn new(
+        x_plus: &'a P,
+        x_minus: &'a P,
+        y_plus: &'a P,
+        y_minus: &'a P,
+        x_channel: &'a dyn adc::AdcChannel<'a>,
+        y_channel: &'a dyn adc::AdcChannel<'a>,
+        alarm: &'a A,
+        interval_ms: u32,
+    ) -> ResistiveTouch<'a, P, A> {
+        ResistiveTouch {
+            x_plus,
+            x_minus,
+            y_plus,
+            y_minus,
+            x_channel,
+            y_channel,
+            alarm,
+            interval_ms,
+            client: OptionalCell::empty(),
+            state: Cell::new(State::Disabled),
+            samples: Cell::new([0; SAMPLES]),
+            num_samples: Cell::new(0),
+            x: Cell::new(0),
+            y: Cell::new(0),
+            pressed: Cell::new(false),
+        }
+    }
+
+    /// Let `pin` float, so that the ADC can read it or so that it does not
+    /// load the layer it is connected to.
+    fn release(pin: &P) {
+        pin.make_input();
+        pin.set_floating_state(gpio::FloatingState::PullNone);
+    }
+
+    fn drive(high: &P, low: &P) {
+        high.make_output();
+        high.set();
+        low.make_output();
+        low.clear();
+    }
+
+    /// Returns whether the panel is pressed, which connects the pulled-up
+    /// X+ to the grounded Y-.
+    fn is_pressed(&self) -> bool {
+        Self::release(self.x_minus);
+        Self::release(self.y_plus);
+        self.y_minus.make_output();
+        self.y_minus.clear();
+        self.x_plus.make_input();
+        self.x_plus.set_floating_state(gpio::FloatingState::PullUp);
+        !self.x_plus.read()
+    }
+
+    fn schedule_sampling(&self) {
+        self.state.set(State::Waiting);
+        self.alarm
+            .set_alarm(self.alarm.now(), self.alarm.ticks_from_ms(self.interval_ms));
+    }
+
+    /// Switch the pins to read the coordinate sampled in `state`, and take
+    /// its first sample.
+    fn start_sampling(&self, state: State) {
+        self.num_samples.set(0);
+        self.state.set(state);
+        let result = match state {
+            State::SamplingX => {
+                Self::release(self.y_plus);
+                Self::release(self.y_minus);
+                Self::drive(self.x_plus, self.x_minus);
+                self.y_channel.sample()
+            }
+            _ => {
+                // Sampling Y.
+                Self::release(self.x_plus);
+                Self::release(self.x_minus);
+                Self::drive(self.y_plus, self.y_minus);
+                self.x_channel.sample()
+            }
+        };
+        if result.is_err() {
+            self.schedule_sampling();
+        }
+    }
+
+    fn median(&self) -> u16 {
+        let mut samples = self.samples.get();
+        samples.sort_unstable();
+        samples[SAMPLES / 2]
+    }
+
+    fn report(&self, status: TouchStatus) {
+        self.client.map(|client| {
+            client.touch_event(TouchEvent {
+                status,
+                x: self.x.get(),
+                y: self.y.get(),
+                id: 0,
+                size: None,
+                pressure: None,
+            })
+        });
+    }
+
+    /// Report a release if the panel was pressed.
+    fn released(&self) {
+        if self.pressed.get() {
+            self.pressed.set(false);
+            self.report(TouchStatus::Released);
+        }
+    }
+}
+
+impl<'a, P: gpio::Pin, A: time::Alarm<'a>> time::AlarmClient for ResistiveTouch<'a, P, A> {
+    fn alarm(&self) {
+        if self.state.get() != State::Waiting {
+            return;
+        }
+        if self.is_pressed() {
+            self.start_sampling(State::SamplingX);
+        } else {
+            self.released();
+            self.schedule_sampling();
+        }
+    }
+}
+
+impl<'a, P: gpio::Pin, A: time::Alarm<'a>> adc::Client for ResistiveTouch<'a, P, A> {
+    fn sample_ready(&self, sample: u16) {
+        let state = self.state.get();
+        if state != State::SamplingX && state != State::SamplingY {
+            return;
+        }
+
+        let index = self.num_samples.get();
+        let mut samples = self.samples.get();
+        samples[index] = sample;
+        self.samples.set(samples);
+        self.num_samples.set(index + 1);
+
+        if index + 1 < SAMPLES {
+            let channel = if state == State::SamplingX {
+                self.y_channel
+            } else {
+                self.x_channel
+            };
+            if channel.sample().is_err() {
+                self.schedule_sampling();
+            }
+        } else if state == State::SamplingX {
+            self.x.set(self.median());
+            self.start_sampling(State::SamplingY);
+        } else {
+            self.y.set(self.median());
+            if self.is_pressed() {
+                let status = if self.pressed.get() {
+                    TouchStatus::Moved
+                } else {
+                    TouchStatus::Pressed
+                };
+                self.pressed.set(true);
+                self.report(status);
+            } else {
+                self.released();
+            }
+            self.schedule_sampling();
+        }
+    }
+}
+
+impl<'a, P: gpio::Pin, A: time::Alarm<'a>> touch::Touch<'a> for ResistiveTouch<'a, P, A> {
+    fn enable(&self) -> Result<(), ErrorCode> {
+        if self.state.get() == State::Disabled {
+            self.schedule_sampling();
+        }
+        Ok(())
+    }
+
+    fn disable(&self) -> Result<(), ErrorCode> {
+        // A sample in progress is dropped when it completes.
+        self.state.set(State::Disabled);
+        let _ = self.alarm.disarm();
+        self.released();
+        Ok(())
+    }
+
+    fn set_client(&self, client: &'a dyn touch::TouchClient) {
+        self.client.set(client);
+    }
+}
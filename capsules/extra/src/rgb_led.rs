@@ -0,0 +1,282 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! RGB LED driven by three PWM channels.
+//!
+//! Each color of the LED is driven by its own PWM pin, with a duty cycle
+//! proportional to the 8-bit intensity of the color. Colors are set as RGB
+//! or HSV, and the LED can fade from its current color to another, in steps
+//! of `FADE_STEP_MS` timed with an alarm. This lets applications use the LED
+//! for status indication, for example a slow pulse while connecting and a
+//! solid color once connected.
+//!
+//! LEDs with a common anode are on when their pins are low, and are created
+//! with `active_low` set so that the duty cycles are inverted.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let rgb_led = components::rgb_led::RgbLedComponent::new(
+//!     board_kernel,
+//!     capsules_extra::rgb_led::DRIVER_NUM,
+//!     [red_pwm_pin, green_pwm_pin, blue_pwm_pin],
+//!     mux_alarm,
+//!     false,
+//! )
+//! .finalize(components::rgb_led_component_static!(
+//!     capsules_core::virtualizers::virtual_pwm::PwmPinUser<'static, rp2040::pwm::Pwm>,
+//!     rp2040::timer::RPTimer<'static>,
+//! ));
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! Colors are packed in the command arguments as `0xRRGGBB`.
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: set the color, given as the first argument.
+//! - Command 2: set the color as HSV, with the hue in degrees, from 0 to
+//!   359, as the first argument, and the saturation and the value, from 0 to
+//!   255, as the second argument packed as `(saturation << 8) | value`.
+//! - Command 3: fade to the color given as the first argument, in the
+//!   number of milliseconds given as the second argument. Upcall 0 reports,
+//!   with the color as its first argument, when the LED reaches the color.
+//! - Command 4: get the current color.
+//! - Command 5: turn the LED off.
+//!
+//! Any command which changes the color cancels the fade in progress, without
+//! an upcall.
+
+use core::cell::Cell;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::pwm;
+use kernel::hil::time::{self, ConvertTicks};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::OptionalCell;
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::RgbLed as usize;
+
+/// Frequency of the PWM signals, high enough to avoid visible flicker.
+pub const PWM_FREQUENCY_HZ: usize = 1000;
+
+/// Time between two colors of a fade.
+pub const FADE_STEP_MS: u32 = 20;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Color {
+    pub const OFF: Color = Color::new(0, 0, 0);
+
+    pub const fn new(red: u8, green: u8, blue: u8) -> Color {
+        Color { red, green, blue }
+    }
+
+    /// The color packed as `0xRRGGBB`. The upper byte is ignored.
+    pub fn from_packed(rgb: u32) -> Color {
+        Color::new((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+    }
+
+    pub fn packed(&self) -> u32 {
+        (self.red as u32) << 16 | (self.green as u32) << 8 | self.blue as u32
+    }
+
+    /// The color of hue `hue`, in degrees, and of saturation `saturation`
+    /// and value `value` from 0 to 255.
+    pub fn from_hsv(hue: u16, saturation: u8, value: u8) -> Color {
+        let hue = hue as u32 % 360;
+        let (s, v) = (saturation as u32, value as u32);
+        // Position in the sector of 60 degrees, from 0 to 255.
+        let f = (hue % 60) * 255 / 60;
+        let p = (v * (255 - s) / 255) as u8;
+        let q = (v * (255 - s * f / 255) / 255) as u8;
+        let t = (v * (255 - s * (255 - f) / 255) / 255) as u8;
+        let v = v as u8;
+        match hue / 60 {
+            0 => Color::new(v, t, p),
+            1 => Color::new(q, v, p),
+            2 => Color::new(p, v, t),
+            3 => Color::new(p, q, v),
+            4 => Color::new(t, p, v),
+            _ => Color::new(v, p, q),
+        }
+    }
+
+    /// The color `step / steps` of the way from `self` to `to`.
+    fn blend(&self, to: Color, step: u32, steps: u32) -> Color {
+        let mix = |from: u8, to: u8| {
+            let (from, to) = (from as i32, to as i32);
+            (from + (to - from) * step as i32 / steps as i32) as u8
+        };
+        Color::new(
+            mix(self.red, to.red),
+            mix(self.green, to.green),
+            mix(self.blue, to.blue),
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Fade {
+    from: Color,
+    to: Color,
+    step: u32,
+    steps: u32,
+}
+
+#[derive(Default)]
+pub struct App;
+
+pub struct RgbLed<'a, P: pwm::PwmPin, A: time::Alarm<'a>> {
+    /// The red, green and blue pins.
+    pins: [&'a P; 3],
+    alarm: &'a A,
+    active_low: bool,
+    color: Cell<Color>,
+    fade: Cell<Option<Fade>>,
+    /// The process notified at the end of the fade.
+    fade_process: OptionalCell<ProcessId>,
+    apps: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+}
+
+impl<'a, P: pwm::PwmPin, A: time::Alarm<'a>> RgbLed<'a, P, A> {
+    pub fn new(
+        pins: [&'a P; 3],
+        alarm: &'a A,
+        active_low: bool,
+        grant: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    ) -> RgbLed<'a, P, A> {
+        RgbLed {
+            pins,
+            alarm,
+            active_low,
+            color: Cell::new(Color::OFF),
+            fade: Cell::new(None),
+            fade_process: OptionalCell::empty(),
+            apps: grant,
+        }
+    }
+
+    /// Set the color, cancelling the fade in progress.
+    pub fn set_color(&self, color: Color) -> Result<(), ErrorCode> {
+        self.cancel_fade();
+        self.apply(color)
+    }
+
+    pub fn set_hsv(&self, hue: u16, saturation: u8, value: u8) -> Result<(), ErrorCode> {
+        self.set_color(Color::from_hsv(hue, saturation, value))
+    }
+
+    pub fn color(&self) -> Color {
+        self.color.get()
+    }
+
+    /// Fade from the current color to `to` in `duration_ms`.
+    pub fn fade_to(&self, to: Color, duration_ms: u32) {
+        self.cancel_fade();
+        // Short fades reach the color in a single step.
+        let steps = core::cmp::max(duration_ms / FADE_STEP_MS, 1);
+        self.fade.set(Some(Fade {
+            from: self.color.get(),
+            to,
+            step: 0,
+            steps,
+        }));
+        self.alarm
+            .set_alarm(self.alarm.now(), self.alarm.ticks_from_ms(FADE_STEP_MS));
+    }
+
+    fn cancel_fade(&self) {
+        if self.fade.take().is_some() {
+            let _ = self.alarm.disarm();
+        }
+        self.fade_process.clear();
+    }
+
+    fn apply(&self, color: Color) -> Result<(), ErrorCode> {
+        for (pin, intensity) in self.pins.iter().zip([color.red, color.green, color.blue]) {
+            let max = pin.get_maximum_duty_cycle();
+            let duty_cycle = max * intensity as usize / u8::MAX as usize;
+            let duty_cycle = if self.active_low {
+                max - duty_cycle
+            } else {
+                duty_cycle
+            };
+            pin.start(PWM_FREQUENCY_HZ, duty_cycle)?;
+        }
+        self.color.set(color);
+        Ok(())
+    }
+}
+
+impl<'a, P: pwm::PwmPin, A: time::Alarm<'a>> time::AlarmClient for RgbLed<'a, P, A> {
+    fn alarm(&self) {
+        if let Some(mut fade) = self.fade.get() {
+            fade.step += 1;
+            let _ = self.apply(fade.from.blend(fade.to, fade.step, fade.steps));
+            if fade.step < fade.steps {
+                self.fade.set(Some(fade));
+                self.alarm
+                    .set_alarm(self.alarm.now(), self.alarm.ticks_from_ms(FADE_STEP_MS));
+            } else {
+                self.fade.set(None);
+                self.fade_process.take().map(|process_id| {
+                    let _ = self.apps.enter(process_id, |_, kernel_data| {
+                        let _ = kernel_data.schedule_upcall(0, (fade.to.packed() as usize, 0, 0));
+                    });
+                });
+            }
+        }
+    }
+}
+
+impl<'a, P: pwm::PwmPin, A: time::Alarm<'a>> SyscallDriver for RgbLed<'a, P, A> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        data2: usize,
+        process_id: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            1 => self.set_color(Color::from_packed(data1 as u32)).into(),
+
+            2 => {
+                if data1 >= 360 {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                self.set_hsv(data1 as u16, (data2 >> 8) as u8, data2 as u8)
+                    .into()
+            }
+
+            3 => {
+                self.fade_to(Color::from_packed(data1 as u32), data2 as u32);
+                self.fade_process.set(process_id);
+                CommandReturn::success()
+            }
+
+            4 => CommandReturn::success_u32(self.color.get().packed()),
+
+            5 => self.set_color(Color::OFF).into(),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
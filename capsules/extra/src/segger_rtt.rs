@@ -26,18 +26,21 @@
 //! $ JLinkRTTClient
 //! ```
 //!
-//! Notes
-//! -----
+//! Sending Messages to the Board
+//! ------------------------------
 //!
-//! This capsule requires a timer, but the timer is only there to defer the
-//! `transmit_complete` callback until the next scheduler loop. In the future,
-//! if there is support for software interrupts or deferred calls in capsules,
-//! this timer should be removed.
+//! Text typed in `JLinkRTTClient` (or `probe-rs`, or OpenOCD's RTT server) is
+//! written to the down buffer, which this capsule polls while a receive is
+//! outstanding. This makes RTT a full console backend: it can be the UART of
+//! a `MuxUart`, so that boards get a console over their debug probe without
+//! using a hardware UART.
 //!
-//! Todo
-//! ----
+//! Notes
+//! -----
 //!
-//! - Implement receive functionality.
+//! This capsule requires a timer. It defers the `transmit_complete` callback
+//! to give the host time to read the up buffer, and it paces the polling of
+//! the down buffer.
 //!
 //! Usage
 //! -----
@@ -104,6 +107,10 @@ pub const DEFAULT_UP_BUFFER_LENGTH: usize = 1024;
 /// Suggested length for the down buffer to pass to the Segger RTT capsule.
 pub const DEFAULT_DOWN_BUFFER_LENGTH: usize = 32;
 
+/// Interval at which the down buffer is polled while a receive is
+/// outstanding.
+const RX_POLL_INTERVAL_MS: u32 = 10;
+
 /// This structure is defined by the segger RTT protocol. It must exist in
 /// memory in exactly this form so that the segger JTAG tool can find it in the
 /// chip's memory and read and write messages to the appropriate buffers.
@@ -178,13 +185,18 @@ impl<'a> SeggerRttMemory<'a> {
 }
 
 pub struct SeggerRtt<'a, A: hil::time::Alarm<'a>> {
-    alarm: &'a A, // Dummy alarm so we can get a callback.
+    alarm: &'a A, // Used to defer transmit callbacks and poll for received data.
     config: TakeCell<'a, SeggerRttMemory<'a>>,
     up_buffer: TakeCell<'a, [u8]>,
-    _down_buffer: TakeCell<'a, [u8]>,
+    down_buffer: TakeCell<'a, [u8]>,
     client: OptionalCell<&'a dyn uart::TransmitClient>,
     client_buffer: TakeCell<'static, [u8]>,
     tx_len: Cell<usize>,
+    rx_client: OptionalCell<&'a dyn uart::ReceiveClient>,
+    rx_buffer: TakeCell<'static, [u8]>,
+    rx_len: Cell<usize>,
+    rx_position: Cell<usize>,
+    rx_aborted: Cell<bool>,
 }
 
 impl<'a, A: hil::time::Alarm<'a>> SeggerRtt<'a, A> {
@@ -198,12 +210,55 @@ impl<'a, A: hil::time::Alarm<'a>> SeggerRtt<'a, A> {
             alarm: alarm,
             config: TakeCell::new(config),
             up_buffer: TakeCell::new(up_buffer),
-            _down_buffer: TakeCell::new(down_buffer),
+            down_buffer: TakeCell::new(down_buffer),
             client: OptionalCell::empty(),
             client_buffer: TakeCell::empty(),
             tx_len: Cell::new(0),
+            rx_client: OptionalCell::empty(),
+            rx_buffer: TakeCell::empty(),
+            rx_len: Cell::new(0),
+            rx_position: Cell::new(0),
+            rx_aborted: Cell::new(false),
         }
     }
+
+    /// Copy the bytes the host wrote to the down buffer into the pending
+    /// receive buffer. Returns whether the receive buffer is full.
+    fn poll_down_buffer(&self) -> bool {
+        self.rx_buffer.map_or(false, |rx_buffer| {
+            self.down_buffer.map_or(false, |buffer| {
+                self.config.map_or(false, |config| {
+                    let buffer_len = config.down_buffer.length.get() as usize;
+                    let write_position = config.down_buffer.write_position.get() as usize;
+                    let mut read_position = config.down_buffer.read_position.get() as usize;
+                    let mut rx_position = self.rx_position.get();
+
+                    while read_position != write_position && rx_position < self.rx_len.get() {
+                        rx_buffer[rx_position] = buffer[read_position];
+                        rx_position += 1;
+                        read_position = (read_position + 1) % buffer_len;
+                    }
+
+                    // Advancing the `read_position` gives the space back to
+                    // the host.
+                    config.down_buffer.read_position.set(read_position as u32);
+                    self.rx_position.set(rx_position);
+                    rx_position == self.rx_len.get()
+                })
+            })
+        })
+    }
+
+    fn receive_done(&self, rval: Result<(), ErrorCode>, error: uart::Error) {
+        self.rx_buffer.take().map(|buffer| {
+            let len = self.rx_position.get();
+            self.rx_position.set(0);
+            self.rx_len.set(0);
+            self.rx_client.map(move |client| {
+                client.received_buffer(buffer, len, rval, error);
+            });
+        });
+    }
 }
 
 impl<'a, A: hil::time::Alarm<'a>> uart::Transmit<'a> for SeggerRtt<'a, A> {
@@ -263,33 +318,67 @@ impl<'a, A: hil::time::Alarm<'a>> uart::Transmit<'a> for SeggerRtt<'a, A> {
 
 impl<'a, A: hil::time::Alarm<'a>> hil::time::AlarmClient for SeggerRtt<'a, A> {
     fn alarm(&self) {
+        // The alarm is shared by transmit and receive, so it may fire earlier
+        // than the one or the other needs. This is harmless for both.
         self.client.map(|client| {
             self.client_buffer.take().map(|buffer| {
                 client.transmitted_buffer(buffer, self.tx_len.get(), Ok(()));
             });
         });
+
+        if self.rx_buffer.is_some() {
+            if self.rx_aborted.take() {
+                self.receive_done(Err(ErrorCode::CANCEL), uart::Error::Aborted);
+            } else if self.poll_down_buffer() {
+                self.receive_done(Ok(()), uart::Error::None);
+            } else if !self.alarm.is_armed() {
+                self.alarm.set_alarm(
+                    self.alarm.now(),
+                    self.alarm.ticks_from_ms(RX_POLL_INTERVAL_MS),
+                );
+            }
+        }
     }
 }
 
-// Dummy implementation so this can act as the underlying UART for a
-// virtualized UART MUX. -pal 1/10/19
+// RTT has no line parameters, so any configuration is accepted. This lets the
+// capsule be the underlying UART of a virtualized UART MUX.
 impl<'a, A: hil::time::Alarm<'a>> uart::Configure for SeggerRtt<'a, A> {
     fn configure(&self, _parameters: uart::Parameters) -> Result<(), ErrorCode> {
-        Err(ErrorCode::FAIL)
+        Ok(())
     }
 }
 
-// Dummy implementation so this can act as the underlying UART for a
-// virtualized UART MUX.  -pal 1/10/19
 impl<'a, A: hil::time::Alarm<'a>> uart::Receive<'a> for SeggerRtt<'a, A> {
-    fn set_receive_client(&self, _client: &'a dyn uart::ReceiveClient) {}
+    fn set_receive_client(&self, client: &'a dyn uart::ReceiveClient) {
+        self.rx_client.set(client);
+    }
 
     fn receive_buffer(
         &self,
         buffer: &'static mut [u8],
-        _len: usize,
+        len: usize,
     ) -> Result<(), (ErrorCode, &'static mut [u8])> {
-        Err((ErrorCode::FAIL, buffer))
+        if self.rx_buffer.is_some() {
+            return Err((ErrorCode::BUSY, buffer));
+        }
+        if len == 0 || len > buffer.len() {
+            return Err((ErrorCode::SIZE, buffer));
+        }
+
+        self.rx_buffer.replace(buffer);
+        self.rx_len.set(len);
+        self.rx_position.set(0);
+        self.rx_aborted.set(false);
+
+        // A pending transmit callback will poll soon enough.
+        if !self.alarm.is_armed() {
+            self.alarm.set_alarm(
+                self.alarm.now(),
+                self.alarm.ticks_from_ms(RX_POLL_INTERVAL_MS),
+            );
+        }
+        Ok(())
     }
 
     fn receive_word(&self) -> Result<(), ErrorCode> {
@@ -297,6 +386,15 @@ impl<'a, A: hil::time::Alarm<'a>> uart::Receive<'a> for SeggerRtt<'a, A> {
     }
 
     fn receive_abort(&self) -> Result<(), ErrorCode> {
-        Ok(())
+        if self.rx_buffer.is_none() {
+            Ok(())
+        } else {
+            // Return the bytes received so far from the alarm callback.
+            self.rx_aborted.set(true);
+            if !self.alarm.is_armed() {
+                self.alarm.set_alarm(self.alarm.now(), A::Ticks::from(0));
+            }
+            Err(ErrorCode::BUSY)
+        }
     }
 }
@@ -0,0 +1,493 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Periodic sampling of several sensors into batches of records.
+//!
+//! Telemetry applications which read a temperature sensor, a 9DOF sensor and
+//! the battery every few seconds would otherwise issue a command and wait
+//! for an upcall for every reading. With this driver, an application selects
+//! the sensors and a period once, and the kernel samples the sensors on an
+//! alarm and writes timestamped records into a buffer shared by the
+//! application. The application is woken up once per batch of records.
+//!
+//! Every application has its own period and sensors. Applications whose
+//! period expires together share the readings of the sensors. A kernel
+//! client, such as a data logger, can also receive records with its own
+//! period and sensors, set with `start_sampling`.
+//!
+//! The driver must be the only client of the sensors it samples. The battery
+//! is an ADC channel, for example on a voltage divider.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let sensor_aggregator = components::sensor_aggregator::SensorAggregatorComponent::new(
+//!     board_kernel,
+//!     capsules_extra::sensor_aggregator::DRIVER_NUM,
+//!     mux_alarm,
+//!     Some(temperature_sensor),
+//!     Some(ninedof_sensor),
+//!     Some(battery_adc_channel),
+//! )
+//! .finalize(components::sensor_aggregator_component_static!(
+//!     nrf52840::rtc::Rtc<'static>
+//! ));
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! Records are `RECORD_LEN` bytes long, in little endian:
+//!
+//! - bytes 0 to 3: the lower 32 bits of the alarm ticks at the reading;
+//! - byte 4: the sensor, see `Sensor`;
+//! - bytes 5 to 7: reserved;
+//! - bytes 8 to 19: three signed 32-bit values. The temperature is in
+//!   hundredths of degrees Celsius, and the 9DOF readings are the three axes
+//!   reported by the sensor. The battery is the raw sample of the ADC,
+//!   left-justified in 16 bits, and its voltage in millivolts if the
+//!   reference voltage of the ADC is known, or 0 otherwise.
+//!
+//! - Read-write allow 0: the buffer receiving the records.
+//! - Command 0: the driver exists.
+//! - Command 1: start sampling every period, in milliseconds, given as the
+//!   first argument, the sensors selected by the bit mask given as the
+//!   second argument, where bit `n` selects the sensor of value `n`. The
+//!   first sample is taken one period later.
+//! - Command 2: stop sampling.
+//! - Command 3: set the number of records of a batch. 0, the default, makes
+//!   the batch the whole buffer.
+//! - Command 4: the records were read, so the next record is written at the
+//!   start of the buffer. Returns the number of records dropped since the
+//!   last time.
+//! - Command 5: the bit mask of the sensors available.
+//! - Command 6: the frequency of the alarm ticks, in hertz.
+//!
+//! Upcall 0 is scheduled when the buffer holds a batch of records, and when
+//! the buffer is full, with the number of records in the buffer and the
+//! number of records dropped because the buffer was full. Sensors which fail
+//! to provide a reading have no record.
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::adc;
+use kernel::hil::sensors;
+use kernel::hil::time::{self, ConvertTicks, Frequency, Ticks};
+use kernel::processbuffer::WriteableProcessBuffer;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::OptionalCell;
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::SensorAggregator as usize;
+
+/// Length of a record in the buffer of an application.
+pub const RECORD_LEN: usize = 20;
+
+/// Ids of the read-write allow buffers.
+mod rw_allow {
+    pub const RECORDS: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant.
+    pub const COUNT: u8 = 1;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Sensor {
+    Temperature = 0,
+    Accelerometer = 1,
+    Magnetometer = 2,
+    Gyroscope = 3,
+    Battery = 4,
+}
+
+const SENSORS: [Sensor; 5] = [
+    Sensor::Temperature,
+    Sensor::Accelerometer,
+    Sensor::Magnetometer,
+    Sensor::Gyroscope,
+    Sensor::Battery,
+];
+
+impl Sensor {
+    fn mask(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Receives the records of the kernel client.
+pub trait SensorAggregatorClient {
+    /// A record, in the format of the records of the applications.
+    fn record(&self, record: &[u8; RECORD_LEN]);
+}
+
+/// When a user of the driver samples which sensors.
+#[derive(Clone, Copy, Default)]
+struct Schedule {
+    /// Sampling period in ticks, 0 when sampling is stopped.
+    period: u32,
+    /// Lower bits of the ticks at the start of the current period.
+    reference: u32,
+    sensors: u8,
+    /// The period expired and the current round samples for this user.
+    pending: bool,
+}
+
+impl Schedule {
+    fn new(period: u32, now: u32, sensors: u8) -> Schedule {
+        Schedule {
+            period: cmp::max(period, 1),
+            reference: now,
+            sensors,
+            pending: false,
+        }
+    }
+
+    /// Ticks until the period expires, or `None` if sampling is stopped.
+    fn remaining(&self, now: u32) -> Option<u32> {
+        (self.period != 0).then(|| self.period.saturating_sub(now.wrapping_sub(self.reference)))
+    }
+
+    /// If the period expired, start the next one and return the sensors to
+    /// sample.
+    fn expire(&mut self, now: u32) -> u8 {
+        if self.period == 0 || now.wrapping_sub(self.reference) < self.period {
+            return 0;
+        }
+        self.pending = true;
+        self.reference = self.reference.wrapping_add(self.period);
+        // Skip the periods missed while the sensors were busy.
+        if now.wrapping_sub(self.reference) >= self.period {
+            self.reference = now;
+        }
+        self.sensors
+    }
+
+    fn wants(&self, sensor: Sensor) -> bool {
+        self.pending && self.sensors & sensor.mask() != 0
+    }
+}
+
+#[derive(Default)]
+pub struct App {
+    schedule: Schedule,
+    batch: usize,
+    count: usize,
+    dropped: u32,
+}
+
+pub struct SensorAggregator<'a, A: time::Alarm<'a>> {
+    alarm: &'a A,
+    temperature: Option<&'a dyn sensors::TemperatureDriver<'a>>,
+    ninedof: Option<&'a dyn sensors::NineDof<'a>>,
+    battery: Option<&'a dyn adc::AdcChannel<'a>>,
+    /// The sensors sampled in the current round.
+    round: Cell<u8>,
+    /// The sensor being read.
+    current: OptionalCell<Sensor>,
+    client: OptionalCell<&'a dyn SensorAggregatorClient>,
+    /// The schedule of the kernel client.
+    client_schedule: Cell<Schedule>,
+    apps: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<{ rw_allow::COUNT }>>,
+}
+
+impl<'a, A: time::Alarm<'a>> SensorAggregator<'a, A> {
+    pub fn new(
+        alarm: &'a A,
+        temperature: Option<&'a dyn sensors::TemperatureDriver<'a>>,
+        ninedof: Option<&'a dyn sensors::NineDof<'a>>,
+        battery: Option<&'a dyn adc::AdcChannel<'a>>,
+        grant: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<{ rw_allow::COUNT }>>,
+    ) -> SensorAggregator<'a, A> {
+        SensorAggregator {
+            alarm,
+            temperature,
+            ninedof,
+            battery,
+            round: Cell::new(0),
+            current: OptionalCell::empty(),
+            client: OptionalCell::empty(),
+            client_schedule: Cell::new(Schedule::default()),
+            apps: grant,
+        }
+    }
+
+    /// The bit mask of the sensors available.
+    pub fn available(&self) -> u8 {
+        SENSORS
+            .iter()
+            .filter(|sensor| match sensor {
+                Sensor::Temperature => self.temperature.is_some(),
+                Sensor::Accelerometer | Sensor::Magnetometer | Sensor::Gyroscope => {
+                    self.ninedof.is_some()
+                }
+                Sensor::Battery => self.battery.is_some(),
+            })
+            .fold(0, |mask, sensor| mask | sensor.mask())
+    }
+
+    pub fn set_client(&self, client: &'a dyn SensorAggregatorClient) {
+        self.client.set(client);
+    }
+
+    /// Sample the sensors selected by the bit mask `sensors` every
+    /// `period_ms` for the kernel client.
+    pub fn start_sampling(&self, period_ms: u32, sensors: u8) -> Result<(), ErrorCode> {
+        let schedule = self.new_schedule(period_ms, sensors)?;
+        self.client_schedule.set(schedule);
+        self.schedule();
+        Ok(())
+    }
+
+    /// Stop sampling for the kernel client.
+    pub fn stop_sampling(&self) {
+        self.client_schedule.set(Schedule::default());
+        self.schedule();
+    }
+
+    fn new_schedule(&self, period_ms: u32, sensors: u8) -> Result<Schedule, ErrorCode> {
+        if period_ms == 0 || sensors == 0 || sensors & !self.available() != 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        Ok(Schedule::new(
+            self.alarm.ticks_from_ms(period_ms).into_u32(),
+            self.alarm.now().into_u32(),
+            sensors,
+        ))
+    }
+
+    /// Arm the alarm for the first application whose period expires.
+    fn schedule(&self) {
+        // A round in progress schedules the next one when it ends.
+        if self.current.is_some() {
+            return;
+        }
+        let now = self.alarm.now();
+        let now_lower_bits = now.into_u32();
+        let mut earliest = self.client_schedule.get().remaining(now_lower_bits);
+        for app in self.apps.iter() {
+            app.enter(|app, _| {
+                if let Some(remaining) = app.schedule.remaining(now_lower_bits) {
+                    earliest = Some(earliest.map_or(remaining, |dt| cmp::min(dt, remaining)));
+                }
+            });
+        }
+        match earliest {
+            Some(dt) => self.alarm.set_alarm(now, A::Ticks::from(dt)),
+            None => {
+                let _ = self.alarm.disarm();
+            }
+        }
+    }
+
+    /// Start reading the next sensor of the round from `from`, or end the
+    /// round.
+    fn sample_from(&self, from: usize) {
+        for &sensor in SENSORS.iter().skip(from) {
+            if self.round.get() & sensor.mask() != 0 && self.start_reading(sensor).is_ok() {
+                self.current.set(sensor);
+                return;
+            }
+        }
+
+        self.current.clear();
+        self.round.set(0);
+        let mut client_schedule = self.client_schedule.get();
+        client_schedule.pending = false;
+        self.client_schedule.set(client_schedule);
+        for app in self.apps.iter() {
+            app.enter(|app, _| app.schedule.pending = false);
+        }
+        self.schedule();
+    }
+
+    fn start_reading(&self, sensor: Sensor) -> Result<(), ErrorCode> {
+        match sensor {
+            Sensor::Temperature => self
+                .temperature
+                .map_or(Err(ErrorCode::NODEVICE), |temperature| {
+                    temperature.read_temperature()
+                }),
+            Sensor::Accelerometer => self.ninedof.map_or(Err(ErrorCode::NODEVICE), |ninedof| {
+                ninedof.read_accelerometer()
+            }),
+            Sensor::Magnetometer => self.ninedof.map_or(Err(ErrorCode::NODEVICE), |ninedof| {
+                ninedof.read_magnetometer()
+            }),
+            Sensor::Gyroscope => self
+                .ninedof
+                .map_or(Err(ErrorCode::NODEVICE), |ninedof| ninedof.read_gyroscope()),
+            Sensor::Battery => self
+                .battery
+                .map_or(Err(ErrorCode::NODEVICE), |battery| battery.sample()),
+        }
+    }
+
+    /// Give the reading of the current sensor to the users sampling it, and
+    /// read the next sensor.
+    fn reading_done(&self, values: Option<[i32; 3]>) {
+        let sensor = match self.current.take() {
+            Some(sensor) => sensor,
+            None => return,
+        };
+        if let Some(values) = values {
+            self.record(sensor, values);
+        }
+        self.sample_from(sensor as usize + 1);
+    }
+
+    fn record(&self, sensor: Sensor, values: [i32; 3]) {
+        let mut record = [0; RECORD_LEN];
+        record[0..4].copy_from_slice(&self.alarm.now().into_u32().to_le_bytes());
+        record[4] = sensor as u8;
+        for (bytes, value) in record[8..].chunks_mut(4).zip(values) {
+            bytes.copy_from_slice(&value.to_le_bytes());
+        }
+
+        if self.client_schedule.get().wants(sensor) {
+            self.client.map(|client| client.record(&record));
+        }
+
+        for app in self.apps.iter() {
+            app.enter(|app, kernel_data| {
+                if !app.schedule.wants(sensor) {
+                    return;
+                }
+                let capacity = kernel_data
+                    .get_readwrite_processbuffer(rw_allow::RECORDS)
+                    .and_then(|records| {
+                        records.mut_enter(|records| {
+                            let offset = app.count * RECORD_LEN;
+                            if offset + RECORD_LEN <= records.len() {
+                                records[offset..offset + RECORD_LEN].copy_from_slice(&record);
+                                app.count += 1;
+                            } else {
+                                app.dropped = app.dropped.saturating_add(1);
+                            }
+                            records.len() / RECORD_LEN
+                        })
+                    })
+                    .unwrap_or(0);
+
+                if app.count > 0 && (app.count == app.batch || app.count == capacity) {
+                    let _ = kernel_data.schedule_upcall(0, (app.count, app.dropped as usize, 0));
+                }
+            });
+        }
+    }
+}
+
+impl<'a, A: time::Alarm<'a>> time::AlarmClient for SensorAggregator<'a, A> {
+    fn alarm(&self) {
+        let now_lower_bits = self.alarm.now().into_u32();
+        let mut client_schedule = self.client_schedule.get();
+        let mut round = client_schedule.expire(now_lower_bits);
+        self.client_schedule.set(client_schedule);
+        for app in self.apps.iter() {
+            app.enter(|app, _| round |= app.schedule.expire(now_lower_bits));
+        }
+        self.round.set(round);
+        self.sample_from(0);
+    }
+}
+
+impl<'a, A: time::Alarm<'a>> sensors::TemperatureClient for SensorAggregator<'a, A> {
+    fn callback(&self, value: Result<i32, ErrorCode>) {
+        self.reading_done(value.ok().map(|value| [value, 0, 0]));
+    }
+}
+
+impl<'a, A: time::Alarm<'a>> sensors::NineDofClient for SensorAggregator<'a, A> {
+    fn callback(&self, arg1: usize, arg2: usize, arg3: usize) {
+        self.reading_done(Some([arg1 as i32, arg2 as i32, arg3 as i32]));
+    }
+}
+
+impl<'a, A: time::Alarm<'a>> adc::Client for SensorAggregator<'a, A> {
+    fn sample_ready(&self, sample: u16) {
+        let millivolts = self.battery.map_or(0, |battery| {
+            battery
+                .get_voltage_reference_mv()
+                .map_or(0, |reference| (sample as usize * reference) >> 16)
+        });
+        self.reading_done(Some([sample as i32, millivolts as i32, 0]));
+    }
+}
+
+impl<'a, A: time::Alarm<'a>> SyscallDriver for SensorAggregator<'a, A> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            1 => {
+                if data1 > u32::MAX as usize || data2 > u8::MAX as usize {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                let schedule = match self.new_schedule(data1 as u32, data2 as u8) {
+                    Ok(schedule) => schedule,
+                    Err(err) => return CommandReturn::failure(err),
+                };
+                let result = self.apps.enter(processid, |app, _| app.schedule = schedule);
+                match result {
+                    Ok(()) => {
+                        self.schedule();
+                        CommandReturn::success()
+                    }
+                    Err(err) => CommandReturn::failure(err.into()),
+                }
+            }
+
+            2 => {
+                let result = self
+                    .apps
+                    .enter(processid, |app, _| app.schedule = Schedule::default());
+                match result {
+                    Ok(()) => {
+                        self.schedule();
+                        CommandReturn::success()
+                    }
+                    Err(err) => CommandReturn::failure(err.into()),
+                }
+            }
+
+            3 => self
+                .apps
+                .enter(processid, |app, _| app.batch = data1)
+                .map_or_else(
+                    |err| CommandReturn::failure(err.into()),
+                    |()| CommandReturn::success(),
+                ),
+
+            4 => self
+                .apps
+                .enter(processid, |app, _| {
+                    app.count = 0;
+                    core::mem::take(&mut app.dropped)
+                })
+                .map_or_else(
+                    |err| CommandReturn::failure(err.into()),
+                    CommandReturn::success_u32,
+                ),
+
+            5 => CommandReturn::success_u32(self.available() as u32),
+
+            6 => CommandReturn::success_u32(A::Frequency::frequency()),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
@@ -0,0 +1,544 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Driver for SPI NOR flash chips with the common JEDEC command set.
+//!
+//! This works with the Winbond W25Q and GigaDevice GD25Q families, and with
+//! most other serial NOR flash chips which support the standard read, page
+//! program, 4 kB sector erase and status commands. The size of the chip is
+//! read from its JEDEC ID with `probe`, which must have completed before
+//! the flash is used.
+//!
+//! Pages of the `hil::flash` interface are the 4 kB sectors, the smallest
+//! area that can be erased. Writing a sector erases it, then programs it 256
+//! bytes at a time. Before every erase and program, the driver enables
+//! writes and checks that the chip accepted it, so a chip whose status
+//! register protects it reports `FlashMemoryProtectionError`. The end of an
+//! erase or program is polled with an alarm rather than busy-waiting on the
+//! bus.
+//!
+//! Only 3-byte addresses are used, so at most the first 16 MB of a chip are
+//! accessible.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let spi_flash = components::spi_flash::SpiFlashComponent::new(
+//!     mux_spi,
+//!     &nrf52840_peripherals.gpio_port[SPI_FLASH_CS],
+//!     mux_alarm,
+//! )
+//! .finalize(components::spi_flash_component_static!(
+//!     nrf52840::spi::SPIM,
+//!     nrf52840::rtc::Rtc
+//! ));
+//! ```
+
+use core::cell::Cell;
+use kernel::hil;
+use kernel::hil::time::ConvertTicks;
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+/// Size of the erasable sectors, which are the pages of `hil::flash`.
+pub const SECTOR_SIZE: usize = 4096;
+/// Size of the programmable pages.
+const PAGE_SIZE: usize = 256;
+const PAGES_PER_SECTOR: usize = SECTOR_SIZE / PAGE_SIZE;
+
+/// An opcode and a 3-byte address.
+const HEADER_LEN: usize = 4;
+
+pub const TX_BUF_LEN: usize = PAGE_SIZE + HEADER_LEN;
+pub const RX_BUF_LEN: usize = PAGE_SIZE + HEADER_LEN;
+
+const SPI_SPEED: u32 = 8_000_000;
+
+/// Largest size addressable with 3-byte addresses, as a power of two.
+const MAX_CAPACITY_BITS: u8 = 24;
+
+pub const MANUFACTURER_WINBOND: u8 = 0xEF;
+pub const MANUFACTURER_GIGADEVICE: u8 = 0xC8;
+
+/// Status register bits
+const STATUS_BUSY: u8 = 1 << 0;
+const STATUS_WRITE_ENABLE_LATCH: u8 = 1 << 1;
+
+enum Opcode {
+    WriteEnable = 0x06,
+    ReadStatus = 0x05,
+    Read = 0x03,
+    PageProgram = 0x02,
+    SectorErase = 0x20,
+    ReadJedecId = 0x9F,
+}
+
+/// A sector of the flash, the page of the `hil::flash` interface.
+pub struct SpiFlashSector(pub [u8; SECTOR_SIZE]);
+
+impl SpiFlashSector {
+    pub const fn new() -> Self {
+        Self([0; SECTOR_SIZE])
+    }
+}
+
+impl Default for SpiFlashSector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsMut<[u8]> for SpiFlashSector {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+/// The identification returned by the JEDEC Read ID command.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct JedecId {
+    pub manufacturer: u8,
+    pub memory_type: u8,
+    /// Size of the chip in bytes, as a power of two
+    pub capacity: u8,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Operation {
+    Idle,
+    Probe,
+    Read,
+    Write,
+    Erase,
+}
+
+/// Commands which change the flash contents.
+#[derive(Copy, Clone, PartialEq)]
+enum Modify {
+    Erase,
+    Program,
+}
+
+impl Modify {
+    /// Typical duration of the command, before the first status poll.
+    fn typical_ms(self) -> u32 {
+        match self {
+            Modify::Erase => 40,
+            Modify::Program => 1,
+        }
+    }
+
+    fn poll_interval_ms(self) -> u32 {
+        match self {
+            Modify::Erase => 5,
+            Modify::Program => 1,
+        }
+    }
+
+    /// Number of polls after which the chip is considered stuck, well above
+    /// the maximum duration in the datasheets.
+    fn max_polls(self) -> usize {
+        match self {
+            Modify::Erase => 100,
+            Modify::Program => 10,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Step {
+    ReadId,
+    Read,
+    WriteEnable(Modify),
+    CheckWriteEnable(Modify),
+    Modify(Modify),
+    WaitBusy(Modify),
+}
+
+pub struct SpiFlash<'a, S: hil::spi::SpiMasterDevice<'a>, A: hil::time::Alarm<'a>> {
+    spi: &'a S,
+    alarm: &'a A,
+    txbuffer: TakeCell<'static, [u8]>,
+    rxbuffer: TakeCell<'static, [u8]>,
+    client: OptionalCell<&'a dyn hil::flash::Client<SpiFlash<'a, S, A>>>,
+    client_sector: TakeCell<'static, SpiFlashSector>,
+    jedec_id: OptionalCell<JedecId>,
+    /// Accessible size in bytes, 0 until the chip is probed.
+    size: Cell<usize>,
+    operation: Cell<Operation>,
+    step: Cell<Step>,
+    sector: Cell<usize>,
+    /// Page of the sector being read or programmed.
+    page: Cell<usize>,
+    polls: Cell<usize>,
+}
+
+impl<'a, S: hil::spi::SpiMasterDevice<'a>, A: hil::time::Alarm<'a>> SpiFlash<'a, S, A> {
+    pub fn new(
+        spi: &'a S,
+        alarm: &'a A,
+        txbuffer: &'static mut [u8],
+        rxbuffer: &'static mut [u8],
+    ) -> SpiFlash<'a, S, A> {
+        SpiFlash {
+            spi,
+            alarm,
+            txbuffer: TakeCell::new(txbuffer),
+            rxbuffer: TakeCell::new(rxbuffer),
+            client: OptionalCell::empty(),
+            client_sector: TakeCell::empty(),
+            jedec_id: OptionalCell::empty(),
+            size: Cell::new(0),
+            operation: Cell::new(Operation::Idle),
+            step: Cell::new(Step::ReadId),
+            sector: Cell::new(0),
+            page: Cell::new(0),
+            polls: Cell::new(0),
+        }
+    }
+
+    /// Returns the identification of the chip, once probed.
+    pub fn jedec_id(&self) -> Option<JedecId> {
+        self.jedec_id.extract()
+    }
+
+    /// Returns the number of sectors which can be accessed, 0 until the chip
+    /// is probed.
+    pub fn number_of_sectors(&self) -> usize {
+        self.size.get() / SECTOR_SIZE
+    }
+
+    /// Read the JEDEC ID of the chip, which gives its size.
+    pub fn probe(&self) -> Result<(), ErrorCode> {
+        if self.operation.get() != Operation::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        self.configure_spi()?;
+        self.operation.set(Operation::Probe);
+        self.transfer(Step::ReadId, HEADER_LEN, |buffer| {
+            buffer[0] = Opcode::ReadJedecId as u8;
+        })
+        .map_err(|e| {
+            self.operation.set(Operation::Idle);
+            e
+        })
+    }
+
+    fn configure_spi(&self) -> Result<(), ErrorCode> {
+        self.spi.configure(
+            hil::spi::ClockPolarity::IdleLow,
+            hil::spi::ClockPhase::SampleLeading,
+            SPI_SPEED,
+        )
+    }
+
+    fn address(&self) -> usize {
+        self.sector.get() * SECTOR_SIZE + self.page.get() * PAGE_SIZE
+    }
+
+    fn header(buffer: &mut [u8], opcode: Opcode, address: usize) {
+        buffer[0] = opcode as u8;
+        buffer[1] = (address >> 16) as u8;
+        buffer[2] = (address >> 8) as u8;
+        buffer[3] = address as u8;
+    }
+
+    /// Send the first `len` bytes of the transmit buffer, after `fill` wrote
+    /// them, and receive the same number of bytes.
+    fn transfer(
+        &self,
+        step: Step,
+        len: usize,
+        fill: impl FnOnce(&mut [u8]),
+    ) -> Result<(), ErrorCode> {
+        let txbuffer = self.txbuffer.take().ok_or(ErrorCode::RESERVE)?;
+        fill(txbuffer);
+        self.step.set(step);
+        self.spi
+            .read_write_bytes(txbuffer, self.rxbuffer.take(), len)
+            .map_err(|(e, txbuffer, rxbuffer)| {
+                self.txbuffer.replace(txbuffer);
+                rxbuffer.map(|rxbuffer| self.rxbuffer.replace(rxbuffer));
+                e
+            })
+    }
+
+    fn read_page(&self) -> Result<(), ErrorCode> {
+        let address = self.address();
+        self.transfer(Step::Read, PAGE_SIZE + HEADER_LEN, |buffer| {
+            Self::header(buffer, Opcode::Read, address)
+        })
+    }
+
+    fn write_enable(&self, modify: Modify) -> Result<(), ErrorCode> {
+        self.transfer(Step::WriteEnable(modify), 1, |buffer| {
+            buffer[0] = Opcode::WriteEnable as u8;
+        })
+    }
+
+    fn read_status(&self, step: Step) -> Result<(), ErrorCode> {
+        self.transfer(step, 2, |buffer| {
+            buffer[0] = Opcode::ReadStatus as u8;
+        })
+    }
+
+    fn modify(&self, modify: Modify) -> Result<(), ErrorCode> {
+        let address = self.address();
+        match modify {
+            Modify::Erase => self.transfer(Step::Modify(modify), HEADER_LEN, |buffer| {
+                Self::header(buffer, Opcode::SectorErase, address)
+            }),
+            Modify::Program => {
+                let start = self.page.get() * PAGE_SIZE;
+                self.transfer(Step::Modify(modify), PAGE_SIZE + HEADER_LEN, |buffer| {
+                    Self::header(buffer, Opcode::PageProgram, address);
+                    self.client_sector.map(|sector| {
+                        buffer[HEADER_LEN..HEADER_LEN + PAGE_SIZE]
+                            .copy_from_slice(&sector.0[start..start + PAGE_SIZE]);
+                    });
+                })
+            }
+        }
+    }
+
+    /// Start `operation` on `sector`, with the sector buffer already set.
+    fn start(&self, operation: Operation, sector: usize) -> Result<(), ErrorCode> {
+        if self.operation.get() != Operation::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        if self.size.get() == 0 {
+            return Err(ErrorCode::OFF);
+        }
+        if sector >= self.number_of_sectors() {
+            return Err(ErrorCode::INVAL);
+        }
+        self.configure_spi()?;
+
+        self.operation.set(operation);
+        self.sector.set(sector);
+        self.page.set(0);
+        let result = match operation {
+            Operation::Read => self.read_page(),
+            _ => self.write_enable(Modify::Erase),
+        };
+        if result.is_err() {
+            self.operation.set(Operation::Idle);
+        }
+        result
+    }
+
+    /// End the current operation and notify the client.
+    fn complete(&self, error: hil::flash::Error) {
+        let operation = self.operation.replace(Operation::Idle);
+        match operation {
+            Operation::Read => {
+                if let Some(sector) = self.client_sector.take() {
+                    self.client
+                        .map(move |client| client.read_complete(sector, error));
+                }
+            }
+            Operation::Write => {
+                if let Some(sector) = self.client_sector.take() {
+                    self.client
+                        .map(move |client| client.write_complete(sector, error));
+                }
+            }
+            Operation::Erase => {
+                self.client.map(|client| client.erase_complete(error));
+            }
+            Operation::Idle | Operation::Probe => {}
+        }
+    }
+
+    /// Returns the status register, received by the last status read.
+    fn status(&self) -> u8 {
+        self.rxbuffer.map_or(0, |buffer| buffer[1])
+    }
+
+    /// Continue the current operation once the status of the last erase or
+    /// program was read.
+    fn busy_polled(&self, modify: Modify) -> Result<(), ErrorCode> {
+        if self.status() & STATUS_BUSY != 0 {
+            let polls = self.polls.get() + 1;
+            if polls > modify.max_polls() {
+                return Err(ErrorCode::FAIL);
+            }
+            self.polls.set(polls);
+            self.alarm.set_alarm(
+                self.alarm.now(),
+                self.alarm.ticks_from_ms(modify.poll_interval_ms()),
+            );
+            return Ok(());
+        }
+
+        match (modify, self.operation.get()) {
+            (Modify::Erase, Operation::Erase) => {
+                self.complete(hil::flash::Error::CommandComplete);
+                Ok(())
+            }
+            (Modify::Erase, _) => self.write_enable(Modify::Program),
+            (Modify::Program, _) => {
+                let page = self.page.get() + 1;
+                if page == PAGES_PER_SECTOR {
+                    self.complete(hil::flash::Error::CommandComplete);
+                    Ok(())
+                } else {
+                    self.page.set(page);
+                    self.write_enable(Modify::Program)
+                }
+            }
+        }
+    }
+
+    fn probed(&self) {
+        self.operation.set(Operation::Idle);
+        let id = self.rxbuffer.map(|buffer| JedecId {
+            manufacturer: buffer[1],
+            memory_type: buffer[2],
+            capacity: buffer[3],
+        });
+        if let Some(id) = id {
+            // A missing chip reads as all zeros or all ones.
+            let present = id.manufacturer != 0x00 && id.manufacturer != 0xFF;
+            if present && (16..=32).contains(&id.capacity) {
+                self.jedec_id.set(id);
+                self.size
+                    .set(1 << core::cmp::min(id.capacity, MAX_CAPACITY_BITS));
+            }
+        }
+    }
+}
+
+impl<'a, S: hil::spi::SpiMasterDevice<'a>, A: hil::time::Alarm<'a>> hil::spi::SpiMasterClient
+    for SpiFlash<'a, S, A>
+{
+    fn read_write_done(
+        &self,
+        write_buffer: &'static mut [u8],
+        read_buffer: Option<&'static mut [u8]>,
+        _len: usize,
+        status: Result<(), ErrorCode>,
+    ) {
+        self.txbuffer.replace(write_buffer);
+        read_buffer.map(|read_buffer| self.rxbuffer.replace(read_buffer));
+        if self.operation.get() == Operation::Idle {
+            return;
+        }
+
+        let result = status.and_then(|()| match self.step.get() {
+            Step::ReadId => {
+                self.probed();
+                Ok(())
+            }
+            Step::Read => {
+                let start = self.page.get() * PAGE_SIZE;
+                self.rxbuffer.map(|buffer| {
+                    self.client_sector.map(|sector| {
+                        sector.0[start..start + PAGE_SIZE]
+                            .copy_from_slice(&buffer[HEADER_LEN..HEADER_LEN + PAGE_SIZE]);
+                    });
+                });
+                let page = self.page.get() + 1;
+                if page == PAGES_PER_SECTOR {
+                    self.complete(hil::flash::Error::CommandComplete);
+                    Ok(())
+                } else {
+                    self.page.set(page);
+                    self.read_page()
+                }
+            }
+            Step::WriteEnable(modify) => self.read_status(Step::CheckWriteEnable(modify)),
+            Step::CheckWriteEnable(modify) => {
+                if self.status() & STATUS_WRITE_ENABLE_LATCH == 0 {
+                    // The chip ignores write enable while it is protected.
+                    self.complete(hil::flash::Error::FlashMemoryProtectionError);
+                    Ok(())
+                } else {
+                    self.modify(modify)
+                }
+            }
+            Step::Modify(modify) => {
+                self.step.set(Step::WaitBusy(modify));
+                self.polls.set(0);
+                self.alarm.set_alarm(
+                    self.alarm.now(),
+                    self.alarm.ticks_from_ms(modify.typical_ms()),
+                );
+                Ok(())
+            }
+            Step::WaitBusy(modify) => self.busy_polled(modify),
+        });
+
+        if result.is_err() {
+            self.complete(hil::flash::Error::FlashError);
+        }
+    }
+}
+
+impl<'a, S: hil::spi::SpiMasterDevice<'a>, A: hil::time::Alarm<'a>> hil::time::AlarmClient
+    for SpiFlash<'a, S, A>
+{
+    fn alarm(&self) {
+        if self.operation.get() == Operation::Idle {
+            return;
+        }
+        if let Step::WaitBusy(modify) = self.step.get() {
+            if self.read_status(Step::WaitBusy(modify)).is_err() {
+                self.complete(hil::flash::Error::FlashError);
+            }
+        }
+    }
+}
+
+impl<
+        'a,
+        S: hil::spi::SpiMasterDevice<'a>,
+        A: hil::time::Alarm<'a>,
+        C: hil::flash::Client<Self>,
+    > hil::flash::HasClient<'a, C> for SpiFlash<'a, S, A>
+{
+    fn set_client(&self, client: &'a C) {
+        self.client.set(client);
+    }
+}
+
+impl<'a, S: hil::spi::SpiMasterDevice<'a>, A: hil::time::Alarm<'a>> hil::flash::Flash
+    for SpiFlash<'a, S, A>
+{
+    type Page = SpiFlashSector;
+
+    fn read_page(
+        &self,
+        page_number: usize,
+        buf: &'static mut Self::Page,
+    ) -> Result<(), (ErrorCode, &'static mut Self::Page)> {
+        // The sector of the operation in progress must not be replaced.
+        if self.operation.get() != Operation::Idle {
+            return Err((ErrorCode::BUSY, buf));
+        }
+        self.client_sector.replace(buf);
+        self.start(Operation::Read, page_number).map_err(|e| {
+            // The buffer was just stored, so it is there.
+            (e, self.client_sector.take().unwrap())
+        })
+    }
+
+    fn write_page(
+        &self,
+        page_number: usize,
+        buf: &'static mut Self::Page,
+    ) -> Result<(), (ErrorCode, &'static mut Self::Page)> {
+        if self.operation.get() != Operation::Idle {
+            return Err((ErrorCode::BUSY, buf));
+        }
+        self.client_sector.replace(buf);
+        self.start(Operation::Write, page_number)
+            .map_err(|e| (e, self.client_sector.take().unwrap()))
+    }
+
+    fn erase_page(&self, page_number: usize) -> Result<(), ErrorCode> {
+        self.start(Operation::Erase, page_number)
+    }
+}
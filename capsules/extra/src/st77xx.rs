@@ -46,6 +46,10 @@ use kernel::ErrorCode;
 
 pub const BUFFER_SIZE: usize = 24;
 
+/// Time after which an operation of the bus is aborted, long enough to send
+/// a full frame over SPI.
+const BUS_TIMEOUT_MS: u32 = 1000;
+
 #[derive(PartialEq)]
 pub struct Command {
     pub id: u8,
@@ -226,6 +230,8 @@ pub struct ST77XX<'a, A: Alarm<'a>, B: Bus<'a>, P: Pin> {
     buffer: TakeCell<'static, [u8]>,
 
     power_on: Cell<bool>,
+    /// An operation of the bus is in progress, and the alarm is its timeout.
+    bus_pending: Cell<bool>,
 
     write_buffer: TakeCell<'static, [u8]>,
 
@@ -268,6 +274,7 @@ impl<'a, A: Alarm<'a>, B: Bus<'a>, P: Pin> ST77XX<'a, A, B, P> {
             buffer: TakeCell::new(buffer),
 
             power_on: Cell::new(false),
+            bus_pending: Cell::new(false),
 
             write_buffer: TakeCell::empty(),
 
@@ -336,14 +343,16 @@ impl<'a, A: Alarm<'a>, B: Bus<'a>, P: Pin> ST77XX<'a, A, B, P> {
         self.command.set(cmd);
         self.status.set(Status::SendCommand(position, len, repeat));
         self.dc.map(|dc| dc.clear());
-        let _ = self.bus.set_addr(BusWidth::Bits8, cmd.id as usize);
+        let result = self.bus.set_addr(BusWidth::Bits8, cmd.id as usize);
+        self.start_bus_timeout(result);
     }
 
     fn send_command_slice(&self, cmd: &'static Command, len: usize) {
         self.command.set(cmd);
         self.dc.map(|dc| dc.clear());
         self.status.set(Status::SendCommandSlice(len));
-        let _ = self.bus.set_addr(BusWidth::Bits8, cmd.id as usize);
+        let result = self.bus.set_addr(BusWidth::Bits8, cmd.id as usize);
+        self.start_bus_timeout(result);
     }
 
     fn send_parameters(&self, position: usize, len: usize, repeat: usize) {
@@ -359,7 +368,8 @@ impl<'a, A: Alarm<'a>, B: Bus<'a>, P: Pin> ST77XX<'a, A, B, P> {
                         }
                     }
                     self.dc.map(|dc| dc.set());
-                    let _ = self.bus.write(BusWidth::Bits8, buffer, len);
+                    let result = self.bus.write(BusWidth::Bits8, buffer, len);
+                    self.start_bus_timeout(result.map_err(|(error, _)| error));
                 },
             );
         } else {
@@ -373,7 +383,8 @@ impl<'a, A: Alarm<'a>, B: Bus<'a>, P: Pin> ST77XX<'a, A, B, P> {
             |buffer| {
                 self.status.set(Status::SendParametersSlice);
                 self.dc.map(|dc| dc.set());
-                let _ = self.bus.write(BusWidth::Bits16BE, buffer, len / 2);
+                let result = self.bus.write(BusWidth::Bits16BE, buffer, len / 2);
+                self.start_bus_timeout(result.map_err(|(error, _)| error));
             },
         );
     }
@@ -666,6 +677,15 @@ impl<'a, A: Alarm<'a>, B: Bus<'a>, P: Pin> ST77XX<'a, A, B, P> {
         }
     }
 
+    /// Arm the timeout of an operation of the bus, if it started.
+    fn start_bus_timeout(&self, result: Result<(), ErrorCode>) {
+        if result.is_ok() {
+            self.bus_pending.set(true);
+            let interval = self.alarm.ticks_from_ms(BUS_TIMEOUT_MS);
+            self.alarm.set_alarm(self.alarm.now(), interval);
+        }
+    }
+
     /// set_delay sets an alarm and saved the next state after that.
     ///
     /// As argument, there are:
@@ -861,7 +881,14 @@ impl<'a, A: Alarm<'a>, B: Bus<'a>, P: Pin> screen::Screen<'a> for ST77XX<'a, A,
 
 impl<'a, A: Alarm<'a>, B: Bus<'a>, P: Pin> time::AlarmClient for ST77XX<'a, A, B, P> {
     fn alarm(&self) {
-        self.do_next_op();
+        if self.bus_pending.get() {
+            // The bus did not complete the operation in time. If it aborts
+            // it, `command_complete` reports `CANCEL` to the client. If not,
+            // the buffer stays with the bus until the operation completes.
+            let _ = self.bus.abort();
+        } else {
+            self.do_next_op();
+        }
     }
 }
 
@@ -872,6 +899,10 @@ impl<'a, A: Alarm<'a>, B: Bus<'a>, P: Pin> bus::Client for ST77XX<'a, A, B, P> {
         _len: usize,
         status: Result<(), ErrorCode>,
     ) {
+        if self.bus_pending.take() {
+            let _ = self.alarm.disarm();
+        }
+
         if let Some(buffer) = buffer {
             if self.status.get() == Status::SendParametersSlice {
                 self.write_buffer.replace(buffer);
@@ -0,0 +1,119 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Selection of the AES-128 engine of a board.
+//!
+//! `Aes128Fallback` implements the AES-128 HILs with the AES engine of the
+//! chip if it has one, and with `Aes128Software` otherwise. The rest of the
+//! AES stack (`MuxAES128CCM`, `Aes128Gcm` and the `AesDriver` syscall
+//! driver) is built over it, so it has the same type on every board, and a
+//! board only has to say which engine its chip has, if any.
+//!
+//! The engine is chosen once, when the board is set up: the software engine
+//! is not used as a fallback for the operations the hardware engine fails.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! // A chip with an AES engine
+//! let aes = static_init!(Aes128Fallback<'static>, Aes128Fallback::new(&peripherals.aes, true));
+//! // A chip without one
+//! let software = static_init!(Aes128Software<'static>, Aes128Software::new());
+//! kernel::deferred_call::DeferredCallClient::register(software);
+//! let aes = static_init!(Aes128Fallback<'static>, Aes128Fallback::new(software, false));
+//!
+//! let ccm_mux = static_init!(
+//!     virtual_aes_ccm::MuxAES128CCM<'static, Aes128Fallback<'static>>,
+//!     virtual_aes_ccm::MuxAES128CCM::new(aes)
+//! );
+//! kernel::deferred_call::DeferredCallClient::register(ccm_mux);
+//! aes.set_client(ccm_mux);
+//! ```
+//!
+//! `components::aes::AesFallbackComponent` does the same from an optional
+//! hardware engine.
+
+use kernel::hil::symmetric_encryption::{AES128Ctr, Client, AES128, AES128CBC, AES128ECB};
+use kernel::ErrorCode;
+
+/// An AES-128 engine with the modes the AES stack uses.
+pub trait Aes128Engine<'a>: AES128<'a> + AES128Ctr + AES128CBC + AES128ECB {}
+
+impl<'a, T: AES128<'a> + AES128Ctr + AES128CBC + AES128ECB> Aes128Engine<'a> for T {}
+
+pub struct Aes128Fallback<'a> {
+    engine: &'a dyn Aes128Engine<'a>,
+    hardware: bool,
+}
+
+impl<'a> Aes128Fallback<'a> {
+    /// Use `engine`, which is the engine of the chip if `hardware` is set.
+    pub fn new(engine: &'a dyn Aes128Engine<'a>, hardware: bool) -> Aes128Fallback<'a> {
+        Aes128Fallback { engine, hardware }
+    }
+
+    /// Whether the engine used is the engine of the chip.
+    pub fn is_hardware(&self) -> bool {
+        self.hardware
+    }
+}
+
+impl<'a> AES128<'a> for Aes128Fallback<'a> {
+    fn enable(&self) {
+        self.engine.enable();
+    }
+
+    fn disable(&self) {
+        self.engine.disable();
+    }
+
+    fn set_client(&'a self, client: &'a dyn Client<'a>) {
+        self.engine.set_client(client);
+    }
+
+    fn set_key(&self, key: &[u8]) -> Result<(), ErrorCode> {
+        self.engine.set_key(key)
+    }
+
+    fn set_iv(&self, iv: &[u8]) -> Result<(), ErrorCode> {
+        self.engine.set_iv(iv)
+    }
+
+    fn start_message(&self) {
+        self.engine.start_message();
+    }
+
+    fn crypt(
+        &self,
+        source: Option<&'static mut [u8]>,
+        dest: &'static mut [u8],
+        start_index: usize,
+        stop_index: usize,
+    ) -> Option<(
+        Result<(), ErrorCode>,
+        Option<&'static mut [u8]>,
+        &'static mut [u8],
+    )> {
+        self.engine.crypt(source, dest, start_index, stop_index)
+    }
+}
+
+impl AES128Ctr for Aes128Fallback<'_> {
+    fn set_mode_aes128ctr(&self, encrypting: bool) -> Result<(), ErrorCode> {
+        self.engine.set_mode_aes128ctr(encrypting)
+    }
+}
+
+impl AES128CBC for Aes128Fallback<'_> {
+    fn set_mode_aes128cbc(&self, encrypting: bool) -> Result<(), ErrorCode> {
+        self.engine.set_mode_aes128cbc(encrypting)
+    }
+}
+
+impl AES128ECB for Aes128Fallback<'_> {
+    fn set_mode_aes128ecb(&self, encrypting: bool) -> Result<(), ErrorCode> {
+        self.engine.set_mode_aes128ecb(encrypting)
+    }
+}
@@ -0,0 +1,419 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Software implementation of AES-128.
+//!
+//! `Aes128Software` implements the `AES128` HIL with the ECB, CBC and CTR
+//! modes for chips without an AES engine, such as the RP2040. It provides
+//! the same interface as the hardware engines, so the rest of the AES stack
+//! (`MuxAES128CCM`, `Aes128Gcm` and the `AesDriver` syscall driver) is built
+//! the same way on every board. Boards select between the AES engine of the
+//! chip and `Aes128Software` with `Aes128Fallback`.
+//!
+//! The implementation follows FIPS-197 and works a byte at a time with
+//! lookup tables. It is not hardened against timing or cache side channels,
+//! and each call to `crypt()` runs to completion before returning, so
+//! boards should keep the messages short. The client is called back from a
+//! deferred call.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let aes = static_init!(Aes128Software<'static>, Aes128Software::new());
+//! kernel::deferred_call::DeferredCallClient::register(aes);
+//!
+//! let ccm_mux = static_init!(
+//!     virtual_aes_ccm::MuxAES128CCM<'static, Aes128Software<'static>>,
+//!     virtual_aes_ccm::MuxAES128CCM::new(aes)
+//! );
+//! kernel::deferred_call::DeferredCallClient::register(ccm_mux);
+//! aes.set_client(ccm_mux);
+//! ```
+
+use core::cell::Cell;
+
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
+use kernel::hil::symmetric_encryption::{
+    AES128Ctr, Client, AES128, AES128CBC, AES128ECB, AES128_BLOCK_SIZE, AES128_KEY_SIZE,
+};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+const ROUNDS: usize = 10;
+
+type Block = [u8; AES128_BLOCK_SIZE];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Ecb,
+    Cbc,
+    Ctr,
+}
+
+const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+const INV_SBOX: [u8; 256] = [
+    0x52, 0x09, 0x6a, 0xd5, 0x30, 0x36, 0xa5, 0x38, 0xbf, 0x40, 0xa3, 0x9e, 0x81, 0xf3, 0xd7, 0xfb,
+    0x7c, 0xe3, 0x39, 0x82, 0x9b, 0x2f, 0xff, 0x87, 0x34, 0x8e, 0x43, 0x44, 0xc4, 0xde, 0xe9, 0xcb,
+    0x54, 0x7b, 0x94, 0x32, 0xa6, 0xc2, 0x23, 0x3d, 0xee, 0x4c, 0x95, 0x0b, 0x42, 0xfa, 0xc3, 0x4e,
+    0x08, 0x2e, 0xa1, 0x66, 0x28, 0xd9, 0x24, 0xb2, 0x76, 0x5b, 0xa2, 0x49, 0x6d, 0x8b, 0xd1, 0x25,
+    0x72, 0xf8, 0xf6, 0x64, 0x86, 0x68, 0x98, 0x16, 0xd4, 0xa4, 0x5c, 0xcc, 0x5d, 0x65, 0xb6, 0x92,
+    0x6c, 0x70, 0x48, 0x50, 0xfd, 0xed, 0xb9, 0xda, 0x5e, 0x15, 0x46, 0x57, 0xa7, 0x8d, 0x9d, 0x84,
+    0x90, 0xd8, 0xab, 0x00, 0x8c, 0xbc, 0xd3, 0x0a, 0xf7, 0xe4, 0x58, 0x05, 0xb8, 0xb3, 0x45, 0x06,
+    0xd0, 0x2c, 0x1e, 0x8f, 0xca, 0x3f, 0x0f, 0x02, 0xc1, 0xaf, 0xbd, 0x03, 0x01, 0x13, 0x8a, 0x6b,
+    0x3a, 0x91, 0x11, 0x41, 0x4f, 0x67, 0xdc, 0xea, 0x97, 0xf2, 0xcf, 0xce, 0xf0, 0xb4, 0xe6, 0x73,
+    0x96, 0xac, 0x74, 0x22, 0xe7, 0xad, 0x35, 0x85, 0xe2, 0xf9, 0x37, 0xe8, 0x1c, 0x75, 0xdf, 0x6e,
+    0x47, 0xf1, 0x1a, 0x71, 0x1d, 0x29, 0xc5, 0x89, 0x6f, 0xb7, 0x62, 0x0e, 0xaa, 0x18, 0xbe, 0x1b,
+    0xfc, 0x56, 0x3e, 0x4b, 0xc6, 0xd2, 0x79, 0x20, 0x9a, 0xdb, 0xc0, 0xfe, 0x78, 0xcd, 0x5a, 0xf4,
+    0x1f, 0xdd, 0xa8, 0x33, 0x88, 0x07, 0xc7, 0x31, 0xb1, 0x12, 0x10, 0x59, 0x27, 0x80, 0xec, 0x5f,
+    0x60, 0x51, 0x7f, 0xa9, 0x19, 0xb5, 0x4a, 0x0d, 0x2d, 0xe5, 0x7a, 0x9f, 0x93, 0xc9, 0x9c, 0xef,
+    0xa0, 0xe0, 0x3b, 0x4d, 0xae, 0x2a, 0xf5, 0xb0, 0xc8, 0xeb, 0xbb, 0x3c, 0x83, 0x53, 0x99, 0x61,
+    0x17, 0x2b, 0x04, 0x7e, 0xba, 0x77, 0xd6, 0x26, 0xe1, 0x69, 0x14, 0x63, 0x55, 0x21, 0x0c, 0x7d,
+];
+
+const RCON: [u8; ROUNDS] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+/// Multiply by `x` in GF(2^8).
+fn xtime(a: u8) -> u8 {
+    (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+/// Multiply two elements of GF(2^8).
+fn gmul(a: u8, b: u8) -> u8 {
+    let mut a = a;
+    let mut b = b;
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    product
+}
+
+fn expand_key(key: &[u8]) -> [Block; ROUNDS + 1] {
+    let mut round_keys = [[0; AES128_BLOCK_SIZE]; ROUNDS + 1];
+    round_keys[0].copy_from_slice(key);
+    for round in 1..=ROUNDS {
+        let previous = round_keys[round - 1];
+        let mut word = [
+            SBOX[previous[13] as usize] ^ RCON[round - 1],
+            SBOX[previous[14] as usize],
+            SBOX[previous[15] as usize],
+            SBOX[previous[12] as usize],
+        ];
+        for column in 0..4 {
+            for row in 0..4 {
+                word[row] ^= previous[4 * column + row];
+                round_keys[round][4 * column + row] = word[row];
+            }
+        }
+    }
+    round_keys
+}
+
+fn add_round_key(state: &mut Block, round_key: &Block) {
+    for (byte, key) in state.iter_mut().zip(round_key.iter()) {
+        *byte ^= key;
+    }
+}
+
+fn sub_bytes(state: &mut Block, sbox: &[u8; 256]) {
+    for byte in state.iter_mut() {
+        *byte = sbox[*byte as usize];
+    }
+}
+
+/// Rotate row `r` of the state left by `r` columns, or right when
+/// inverting.
+fn shift_rows(state: &mut Block, inverse: bool) {
+    let input = *state;
+    for row in 1..4 {
+        for column in 0..4 {
+            let shifted = (column + row) % 4;
+            if inverse {
+                state[row + 4 * shifted] = input[row + 4 * column];
+            } else {
+                state[row + 4 * column] = input[row + 4 * shifted];
+            }
+        }
+    }
+}
+
+fn mix_columns(state: &mut Block, inverse: bool) {
+    let coefficients: [u8; 4] = if inverse {
+        [14, 11, 13, 9]
+    } else {
+        [2, 3, 1, 1]
+    };
+    for column in state.chunks_mut(4) {
+        let input = [column[0], column[1], column[2], column[3]];
+        for row in 0..4 {
+            column[row] = (0..4).fold(0, |acc, i| {
+                acc ^ gmul(input[(row + i) % 4], coefficients[i])
+            });
+        }
+    }
+}
+
+fn encrypt_block(round_keys: &[Block; ROUNDS + 1], block: &Block) -> Block {
+    let mut state = *block;
+    add_round_key(&mut state, &round_keys[0]);
+    for round in 1..=ROUNDS {
+        sub_bytes(&mut state, &SBOX);
+        shift_rows(&mut state, false);
+        if round != ROUNDS {
+            mix_columns(&mut state, false);
+        }
+        add_round_key(&mut state, &round_keys[round]);
+    }
+    state
+}
+
+fn decrypt_block(round_keys: &[Block; ROUNDS + 1], block: &Block) -> Block {
+    let mut state = *block;
+    add_round_key(&mut state, &round_keys[ROUNDS]);
+    for round in (0..ROUNDS).rev() {
+        shift_rows(&mut state, true);
+        sub_bytes(&mut state, &INV_SBOX);
+        add_round_key(&mut state, &round_keys[round]);
+        if round != 0 {
+            mix_columns(&mut state, true);
+        }
+    }
+    state
+}
+
+fn xor_block(a: &Block, b: &Block) -> Block {
+    let mut output = *a;
+    for (byte, other) in output.iter_mut().zip(b.iter()) {
+        *byte ^= other;
+    }
+    output
+}
+
+/// Increment the big-endian counter of CTR mode.
+fn increment(counter: &mut Block) {
+    for byte in counter.iter_mut().rev() {
+        *byte = byte.wrapping_add(1);
+        if *byte != 0 {
+            break;
+        }
+    }
+}
+
+pub struct Aes128Software<'a> {
+    client: OptionalCell<&'a dyn Client<'a>>,
+    round_keys: Cell<[Block; ROUNDS + 1]>,
+    iv: Cell<Block>,
+    /// Chaining value in CBC mode, or counter in CTR mode, of the current
+    /// message
+    chain: Cell<Block>,
+    mode: OptionalCell<Mode>,
+    encrypting: Cell<bool>,
+    source: TakeCell<'static, [u8]>,
+    dest: TakeCell<'static, [u8]>,
+    deferred_call: DeferredCall,
+}
+
+impl<'a> Aes128Software<'a> {
+    pub fn new() -> Aes128Software<'a> {
+        Aes128Software {
+            client: OptionalCell::empty(),
+            round_keys: Cell::new([[0; AES128_BLOCK_SIZE]; ROUNDS + 1]),
+            iv: Cell::new([0; AES128_BLOCK_SIZE]),
+            chain: Cell::new([0; AES128_BLOCK_SIZE]),
+            mode: OptionalCell::empty(),
+            encrypting: Cell::new(true),
+            source: TakeCell::empty(),
+            dest: TakeCell::empty(),
+            deferred_call: DeferredCall::new(),
+        }
+    }
+
+    fn busy(&self) -> bool {
+        self.dest.is_some()
+    }
+
+    fn set_mode(&self, mode: Mode, encrypting: bool) -> Result<(), ErrorCode> {
+        if self.busy() {
+            return Err(ErrorCode::BUSY);
+        }
+        self.mode.set(mode);
+        self.encrypting.set(encrypting);
+        Ok(())
+    }
+
+    /// Process `input` in the current mode, updating the chaining value.
+    fn crypt_block(
+        &self,
+        mode: Mode,
+        round_keys: &[Block; ROUNDS + 1],
+        chain: &mut Block,
+        input: &Block,
+    ) -> Block {
+        match (mode, self.encrypting.get()) {
+            (Mode::Ecb, true) => encrypt_block(round_keys, input),
+            (Mode::Ecb, false) => decrypt_block(round_keys, input),
+            (Mode::Cbc, true) => {
+                *chain = encrypt_block(round_keys, &xor_block(input, chain));
+                *chain
+            }
+            (Mode::Cbc, false) => {
+                let output = xor_block(&decrypt_block(round_keys, input), chain);
+                *chain = *input;
+                output
+            }
+            (Mode::Ctr, _) => {
+                let output = xor_block(input, &encrypt_block(round_keys, chain));
+                increment(chain);
+                output
+            }
+        }
+    }
+}
+
+impl<'a> AES128<'a> for Aes128Software<'a> {
+    fn enable(&self) {}
+
+    fn disable(&self) {}
+
+    fn set_client(&'a self, client: &'a dyn Client<'a>) {
+        self.client.set(client);
+    }
+
+    fn set_key(&self, key: &[u8]) -> Result<(), ErrorCode> {
+        if self.busy() {
+            return Err(ErrorCode::BUSY);
+        }
+        if key.len() != AES128_KEY_SIZE {
+            return Err(ErrorCode::INVAL);
+        }
+        self.round_keys.set(expand_key(key));
+        Ok(())
+    }
+
+    fn set_iv(&self, iv: &[u8]) -> Result<(), ErrorCode> {
+        if self.busy() {
+            return Err(ErrorCode::BUSY);
+        }
+        if iv.len() != AES128_BLOCK_SIZE {
+            return Err(ErrorCode::INVAL);
+        }
+        let mut block = [0; AES128_BLOCK_SIZE];
+        block.copy_from_slice(iv);
+        self.iv.set(block);
+        self.chain.set(block);
+        Ok(())
+    }
+
+    fn start_message(&self) {
+        if !self.busy() {
+            self.chain.set(self.iv.get());
+        }
+    }
+
+    fn crypt(
+        &self,
+        source: Option<&'static mut [u8]>,
+        dest: &'static mut [u8],
+        start_index: usize,
+        stop_index: usize,
+    ) -> Option<(
+        Result<(), ErrorCode>,
+        Option<&'static mut [u8]>,
+        &'static mut [u8],
+    )> {
+        if self.busy() {
+            return Some((Err(ErrorCode::BUSY), source, dest));
+        }
+        let mode = match self.mode.extract() {
+            Some(mode) => mode,
+            None => return Some((Err(ErrorCode::INVAL), source, dest)),
+        };
+        let length = match stop_index.checked_sub(start_index) {
+            Some(length) if stop_index <= dest.len() && length % AES128_BLOCK_SIZE == 0 => length,
+            _ => return Some((Err(ErrorCode::INVAL), source, dest)),
+        };
+        if source
+            .as_ref()
+            .map_or(false, |source| source.len() < length)
+        {
+            return Some((Err(ErrorCode::INVAL), source, dest));
+        }
+
+        let round_keys = self.round_keys.get();
+        let mut chain = self.chain.get();
+        let mut input = [0; AES128_BLOCK_SIZE];
+        for offset in (0..length).step_by(AES128_BLOCK_SIZE) {
+            let block = start_index + offset..start_index + offset + AES128_BLOCK_SIZE;
+            match source.as_ref() {
+                Some(source) => input.copy_from_slice(&source[offset..offset + AES128_BLOCK_SIZE]),
+                None => input.copy_from_slice(&dest[block.clone()]),
+            }
+            dest[block].copy_from_slice(&self.crypt_block(mode, &round_keys, &mut chain, &input));
+        }
+        self.chain.set(chain);
+
+        if let Some(source) = source {
+            self.source.replace(source);
+        }
+        self.dest.replace(dest);
+        self.deferred_call.set();
+        None
+    }
+}
+
+impl AES128Ctr for Aes128Software<'_> {
+    fn set_mode_aes128ctr(&self, encrypting: bool) -> Result<(), ErrorCode> {
+        self.set_mode(Mode::Ctr, encrypting)
+    }
+}
+
+impl AES128ECB for Aes128Software<'_> {
+    fn set_mode_aes128ecb(&self, encrypting: bool) -> Result<(), ErrorCode> {
+        self.set_mode(Mode::Ecb, encrypting)
+    }
+}
+
+impl AES128CBC for Aes128Software<'_> {
+    fn set_mode_aes128cbc(&self, encrypting: bool) -> Result<(), ErrorCode> {
+        self.set_mode(Mode::Cbc, encrypting)
+    }
+}
+
+impl DeferredCallClient for Aes128Software<'_> {
+    fn register(&'static self) {
+        self.deferred_call.register(self);
+    }
+
+    fn handle_deferred_call(&self) {
+        if let Some(dest) = self.dest.take() {
+            let source = self.source.take();
+            self.client.map(move |client| {
+                client.crypt_done(source, dest);
+            });
+        }
+    }
+}
@@ -0,0 +1,308 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Ring buffer of the system calls of processes.
+//!
+//! `SyscallTrace` is a `kernel::syscall_trace::SyscallTracer` which records,
+//! for every system call but yield and exit, the process, the class, the
+//! driver and subdriver numbers, the result and the time the kernel took to
+//! handle it. Memop records its operand as the subdriver number. When the
+//! ring buffer is full, the oldest system calls are overwritten.
+//!
+//! It implements the `syscalls` command of the process console, which prints
+//! the system calls, oldest first. `syscalls clear` empties the trace, and
+//! `syscalls <driver>` only prints the system calls to one driver, given in
+//! hexadecimal.
+//!
+//! ```text
+//! tock$ syscalls
+//! Syscalls: 3 recorded, 0 overwritten
+//!  [0] subscribe 0x00000 1       4us Ok
+//!  [0] command   0x00000 1      11us Ok
+//!  [1] command   0x60000 1       2us Err(NODEVICE)
+//! tock$ syscalls 60000
+//! Syscalls: 3 recorded, 0 overwritten
+//!  [1] command   0x60000 1       2us Err(NODEVICE)
+//! ```
+//!
+//! The process is shown by its identifier, as printed by the `list` command.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let syscall_trace = components::syscall_trace::SyscallTraceComponent::new(&peripherals.gpt1)
+//!     .finalize(components::syscall_trace_component_static!(imxrt1050::gpt::Gpt1, 64));
+//! process_console.add_command(syscall_trace).unwrap();
+//!
+//! impl KernelHooks for Board {
+//!     fn syscall_tracer(&self) -> Option<&dyn SyscallTracer> {
+//!         Some(self.syscall_trace)
+//!     }
+//! }
+//! ```
+
+use core::cell::Cell;
+use core::fmt;
+
+use capsules_core::process_console::ConsoleCommand;
+use kernel::hil::time::{ConvertTicks, Ticks, Time};
+use kernel::syscall::{Syscall, SyscallClass, SyscallReturn};
+use kernel::syscall_trace::SyscallTracer;
+use kernel::{ErrorCode, ProcessId};
+
+/// A system call and its result.
+#[derive(Clone, Copy, Debug)]
+pub struct SyscallRecord {
+    pub processid: ProcessId,
+    pub class: SyscallClass,
+    pub driver_num: usize,
+    pub subdriver_num: usize,
+    pub result: Result<(), ErrorCode>,
+    pub duration_us: u32,
+}
+
+/// System call entered, waiting for its result.
+#[derive(Clone, Copy)]
+struct Pending<T: Ticks> {
+    processid: ProcessId,
+    class: SyscallClass,
+    driver_num: usize,
+    subdriver_num: usize,
+    start: T,
+}
+
+fn class_name(class: SyscallClass) -> &'static str {
+    match class {
+        SyscallClass::Yield => "yield",
+        SyscallClass::Subscribe => "subscribe",
+        SyscallClass::Command => "command",
+        SyscallClass::ReadWriteAllow => "allow_rw",
+        SyscallClass::ReadOnlyAllow => "allow_ro",
+        SyscallClass::Memop => "memop",
+        SyscallClass::Exit => "exit",
+        SyscallClass::UserspaceReadableAllow => "allow_ur",
+    }
+}
+
+fn result_of(result: &SyscallReturn) -> Result<(), ErrorCode> {
+    match *result {
+        SyscallReturn::Failure(error)
+        | SyscallReturn::FailureU32(error, _)
+        | SyscallReturn::FailureU32U32(error, _, _)
+        | SyscallReturn::FailureU64(error, _)
+        | SyscallReturn::AllowReadWriteFailure(error, _, _)
+        | SyscallReturn::UserspaceReadableAllowFailure(error, _, _)
+        | SyscallReturn::AllowReadOnlyFailure(error, _, _)
+        | SyscallReturn::SubscribeFailure(error, _, _) => Err(error),
+        _ => Ok(()),
+    }
+}
+
+pub struct SyscallTrace<'a, T: Time> {
+    time: &'a T,
+    log: &'a [Cell<Option<SyscallRecord>>],
+    /// Index of the next system call written.
+    next: Cell<usize>,
+    /// System calls in the log.
+    count: Cell<usize>,
+    /// System calls overwritten since the log was cleared.
+    overwritten: Cell<usize>,
+    pending: Cell<Option<Pending<T::Ticks>>>,
+    /// Driver the console prints the system calls of, or all if `None`.
+    filter: Cell<Option<usize>>,
+    /// Index in the log after the last system call the console printed.
+    cursor: Cell<usize>,
+}
+
+impl<'a, T: Time> SyscallTrace<'a, T> {
+    pub fn new(time: &'a T, log: &'a [Cell<Option<SyscallRecord>>]) -> Self {
+        Self {
+            time,
+            log,
+            next: Cell::new(0),
+            count: Cell::new(0),
+            overwritten: Cell::new(0),
+            pending: Cell::new(None),
+            filter: Cell::new(None),
+            cursor: Cell::new(0),
+        }
+    }
+
+    /// Number of system calls in the log.
+    pub fn len(&self) -> usize {
+        self.count.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count.get() == 0
+    }
+
+    /// System call `index` of the log, the oldest first.
+    pub fn get(&self, index: usize) -> Option<SyscallRecord> {
+        let count = self.count.get();
+        if index >= count {
+            return None;
+        }
+        let oldest = (self.next.get() + self.log.len() - count) % self.log.len();
+        self.log[(oldest + index) % self.log.len()].get()
+    }
+
+    pub fn clear(&self) {
+        self.next.set(0);
+        self.count.set(0);
+        self.overwritten.set(0);
+    }
+
+    fn record(&self, record: SyscallRecord) {
+        if self.log.is_empty() {
+            return;
+        }
+        let next = self.next.get();
+        self.log[next].set(Some(record));
+        self.next.set((next + 1) % self.log.len());
+        if self.count.get() < self.log.len() {
+            self.count.set(self.count.get() + 1);
+        } else {
+            self.overwritten.set(self.overwritten.get() + 1);
+        }
+    }
+
+    /// The next system call printed by the console, after the last one it
+    /// printed. The console prints the lines in order, so the log is only
+    /// scanned once.
+    fn next_listed(&self) -> Option<SyscallRecord> {
+        let filter = self.filter.get();
+        let listed = (self.cursor.get()..self.count.get())
+            .filter_map(|index| self.get(index).map(|record| (index, record)))
+            .find(|(_, record)| {
+                filter.map_or(true, |driver_num| {
+                    !matches!(record.class, SyscallClass::Memop) && record.driver_num == driver_num
+                })
+            });
+        match listed {
+            Some((index, record)) => {
+                self.cursor.set(index + 1);
+                Some(record)
+            }
+            None => {
+                self.cursor.set(self.count.get());
+                None
+            }
+        }
+    }
+}
+
+impl<'a, T: Time> SyscallTracer for SyscallTrace<'a, T> {
+    fn syscall_entered(&self, processid: ProcessId, syscall: &Syscall) {
+        let (class, driver_num, subdriver_num) = match *syscall {
+            Syscall::Subscribe {
+                driver_number,
+                subdriver_number,
+                ..
+            } => (SyscallClass::Subscribe, driver_number, subdriver_number),
+            Syscall::Command {
+                driver_number,
+                subdriver_number,
+                ..
+            } => (SyscallClass::Command, driver_number, subdriver_number),
+            Syscall::ReadWriteAllow {
+                driver_number,
+                subdriver_number,
+                ..
+            } => (
+                SyscallClass::ReadWriteAllow,
+                driver_number,
+                subdriver_number,
+            ),
+            Syscall::UserspaceReadableAllow {
+                driver_number,
+                subdriver_number,
+                ..
+            } => (
+                SyscallClass::UserspaceReadableAllow,
+                driver_number,
+                subdriver_number,
+            ),
+            Syscall::ReadOnlyAllow {
+                driver_number,
+                subdriver_number,
+                ..
+            } => (SyscallClass::ReadOnlyAllow, driver_number, subdriver_number),
+            Syscall::Memop { operand, .. } => (SyscallClass::Memop, 0, operand),
+            // Nothing is returned to the process.
+            Syscall::Yield { .. } | Syscall::Exit { .. } => {
+                self.pending.set(None);
+                return;
+            }
+        };
+        self.pending.set(Some(Pending {
+            processid,
+            class,
+            driver_num,
+            subdriver_num,
+            start: self.time.now(),
+        }));
+    }
+
+    fn syscall_returned(&self, processid: ProcessId, result: &SyscallReturn) {
+        let pending = match self.pending.take() {
+            Some(pending) if pending.processid == processid => pending,
+            _ => return,
+        };
+        let elapsed = self.time.now().wrapping_sub(pending.start);
+        self.record(SyscallRecord {
+            processid,
+            class: pending.class,
+            driver_num: pending.driver_num,
+            subdriver_num: pending.subdriver_num,
+            result: result_of(result),
+            duration_us: self.time.ticks_to_us(elapsed),
+        });
+    }
+}
+
+impl<'a, T: Time> ConsoleCommand for SyscallTrace<'a, T> {
+    fn name(&self) -> &'static str {
+        "syscalls"
+    }
+
+    fn execute(&self, arguments: &str) {
+        let argument = arguments.trim();
+        if argument == "clear" {
+            self.clear();
+            self.filter.set(None);
+        } else {
+            let argument = argument.trim_start_matches("0x");
+            self.filter.set(usize::from_str_radix(argument, 16).ok());
+        }
+    }
+
+    fn write_line(&self, index: usize, writer: &mut dyn fmt::Write) -> bool {
+        if index == 0 {
+            let _ = writer.write_fmt(format_args!(
+                "Syscalls: {} recorded, {} overwritten\r\n",
+                self.count.get(),
+                self.overwritten.get()
+            ));
+            self.cursor.set(0);
+            return true;
+        }
+        match self.next_listed() {
+            Some(record) => {
+                let _ = writer.write_fmt(format_args!(
+                    " [{}] {:<9} {:#07x} {:<4} {:>5}us {:?}\r\n",
+                    record.processid.id(),
+                    class_name(record.class),
+                    record.driver_num,
+                    record.subdriver_num,
+                    record.duration_us,
+                    record.result
+                ));
+                true
+            }
+            None => false,
+        }
+    }
+}
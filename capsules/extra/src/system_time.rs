@@ -0,0 +1,309 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! System time kept across sleep cycles and resets.
+//!
+//! An application, typically an SNTP client, sets the system time as seconds
+//! and milliseconds since the Unix epoch. The capsule keeps it running from
+//! an alarm, and:
+//!
+//! - estimates the drift of the alarm clock, in parts per million, each time
+//!   the time is set again at least `MIN_DRIFT_INTERVAL_S` seconds after the
+//!   previous measurement, and corrects the time for it;
+//! - stores the time and the drift in backup registers every minute and
+//!   before deep sleep, so that after a reset which keeps the backup
+//!   registers, the time continues from the last stored value instead of
+//!   being lost;
+//! - sits between the power manager and the chip's deep sleep state, as a
+//!   `DeepSleep` implementation forwarding to the chip.
+//!
+//! The chips supported so far stop the kernel timer in deep sleep, and have
+//! no clock running in it which could measure how long it lasted. The time
+//! therefore lags by the time spent in deep sleep until it is set again:
+//! its status then becomes "slept", and the interval is left out of the
+//! drift estimate.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let system_time = components::system_time::SystemTimeComponent::new(
+//!     board_kernel,
+//!     capsules_extra::system_time::DRIVER_NUM,
+//!     mux_alarm,
+//!     &peripherals.watchdog,
+//!     3,
+//! )
+//! .finalize(components::system_time_component_static!(
+//!     RPTimer,
+//!     rp2040::watchdog::Watchdog<'static>
+//! ));
+//! system_time.set_deep_sleep(dormant);
+//! power_manager.set_deep_sleep(system_time);
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: set the time to the number of seconds since the Unix epoch
+//!   given as the first argument, plus the number of milliseconds given as
+//!   the second argument.
+//! - Command 2: return the seconds and milliseconds of the current time.
+//!   Fails with `OFF` until the time has been set or restored.
+//! - Command 3: return the status of the time: 0 not set, 1 set, 2 restored
+//!   from the backup registers after a reset, 3 lagging after deep sleep.
+//! - Command 4: return the drift of the alarm clock in parts per million,
+//!   as a signed number, positive when the clock is slow.
+//!
+//! Upcall 0 is scheduled for every application when the time is set, with
+//! the drift in parts per million and the error of the time before it was
+//! set in milliseconds, both signed.
+
+use core::cell::Cell;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::backup_registers::BackupRegisters;
+use kernel::hil::deep_sleep::DeepSleep;
+use kernel::hil::time::{self, ConvertTicks, Ticks};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::OptionalCell;
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::SystemTime as usize;
+
+/// Number of backup registers used.
+pub const NUM_REGISTERS: usize = 3;
+
+/// Shortest interval over which the drift is measured.
+pub const MIN_DRIFT_INTERVAL_S: u64 = 600;
+
+/// Drifts beyond this are treated as measurement errors and clamped.
+const MAX_DRIFT_PPM: i64 = 1000;
+
+/// Interval at which the time is folded and stored.
+const UPDATE_INTERVAL_MS: u32 = 60_000;
+
+/// Mixed into the check word so that all-zero and all-one registers are not
+/// mistaken for a valid time.
+const CHECK_MAGIC: u32 = 0x7143_C10C;
+
+fn check_word(seconds: u32, drift: u32) -> u32 {
+    CHECK_MAGIC ^ seconds ^ drift.rotate_left(16)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Status {
+    NotSet = 0,
+    Set = 1,
+    Restored = 2,
+    Slept = 3,
+}
+
+#[derive(Default)]
+pub struct App;
+
+pub struct SystemTime<'a, A: time::Alarm<'a>, B: BackupRegisters> {
+    alarm: &'a A,
+    registers: &'a B,
+    /// Index of the first of the `NUM_REGISTERS` registers used.
+    first_register: usize,
+    /// Microseconds since the Unix epoch at `reference`.
+    time_us: Cell<u64>,
+    reference: Cell<A::Ticks>,
+    status: Cell<Status>,
+    drift_ppm: Cell<i64>,
+    /// Time at which the current drift measurement started, and the error
+    /// corrected since then.
+    measurement: Cell<Option<(u64, i64)>>,
+    deep_sleep: OptionalCell<&'a dyn DeepSleep>,
+    apps: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+}
+
+impl<'a, A: time::Alarm<'a>, B: BackupRegisters> SystemTime<'a, A, B> {
+    pub fn new(
+        alarm: &'a A,
+        registers: &'a B,
+        first_register: usize,
+        grant: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    ) -> SystemTime<'a, A, B> {
+        SystemTime {
+            alarm,
+            registers,
+            first_register,
+            time_us: Cell::new(0),
+            reference: Cell::new(A::Ticks::from(0)),
+            status: Cell::new(Status::NotSet),
+            drift_ppm: Cell::new(0),
+            measurement: Cell::new(None),
+            deep_sleep: OptionalCell::empty(),
+            apps: grant,
+        }
+    }
+
+    /// Forward deep sleep requests to the chip.
+    pub fn set_deep_sleep(&self, deep_sleep: &'a dyn DeepSleep) {
+        self.deep_sleep.set(deep_sleep);
+    }
+
+    /// Restore the time and the drift stored before a reset, if the backup
+    /// registers kept them.
+    pub fn restore(&self) {
+        if let Some((seconds, drift)) = self.load() {
+            self.time_us.set(seconds as u64 * 1_000_000);
+            self.reference.set(self.alarm.now());
+            self.drift_ppm.set(drift as i32 as i64);
+            self.status.set(Status::Restored);
+            self.schedule();
+        }
+    }
+
+    /// Microseconds since the Unix epoch, corrected for the drift.
+    fn now_us(&self) -> u64 {
+        let elapsed = self
+            .alarm
+            .ticks_to_us_u64(self.alarm.now().wrapping_sub(self.reference.get()))
+            as i64;
+        let corrected = elapsed + elapsed * self.drift_ppm.get() / 1_000_000;
+        (self.time_us.get() as i64 + corrected) as u64
+    }
+
+    /// Move the reference to now, so that the elapsed ticks never wrap.
+    fn fold(&self) {
+        self.time_us.set(self.now_us());
+        self.reference.set(self.alarm.now());
+    }
+
+    pub fn set_time(&self, seconds: u32, milliseconds: u32) -> Result<(), ErrorCode> {
+        if milliseconds >= 1000 {
+            return Err(ErrorCode::INVAL);
+        }
+        let time_us = seconds as u64 * 1_000_000 + milliseconds as u64 * 1000;
+        let error_us = match self.status.get() {
+            Status::NotSet => 0,
+            _ => time_us as i64 - self.now_us() as i64,
+        };
+
+        let measurement = match (self.status.get(), self.measurement.get()) {
+            (Status::Set, Some((start_us, corrected_us))) => {
+                let corrected_us = corrected_us + error_us;
+                let interval_us = time_us.saturating_sub(start_us);
+                if interval_us >= MIN_DRIFT_INTERVAL_S * 1_000_000 {
+                    let drift =
+                        self.drift_ppm.get() + corrected_us * 1_000_000 / interval_us as i64;
+                    self.drift_ppm
+                        .set(drift.clamp(-MAX_DRIFT_PPM, MAX_DRIFT_PPM));
+                    (time_us, 0)
+                } else {
+                    (start_us, corrected_us)
+                }
+            }
+            _ => (time_us, 0),
+        };
+        self.measurement.set(Some(measurement));
+
+        self.time_us.set(time_us);
+        self.reference.set(self.alarm.now());
+        self.status.set(Status::Set);
+        let _ = self.store();
+        self.schedule();
+
+        let drift = self.drift_ppm.get() as i32 as usize;
+        let error_ms = (error_us / 1000) as i32 as usize;
+        for app in self.apps.iter() {
+            app.enter(|_, kernel_data| {
+                let _ = kernel_data.schedule_upcall(0, (drift, error_ms, 0));
+            });
+        }
+        Ok(())
+    }
+
+    fn load(&self) -> Option<(u32, u32)> {
+        let seconds = self.registers.read(self.first_register).ok()?;
+        let drift = self.registers.read(self.first_register + 1).ok()?;
+        let check = self.registers.read(self.first_register + 2).ok()?;
+        (check == check_word(seconds, drift)).then_some((seconds, drift))
+    }
+
+    fn store(&self) -> Result<(), ErrorCode> {
+        let seconds = (self.now_us() / 1_000_000) as u32;
+        let drift = self.drift_ppm.get() as i32 as u32;
+        self.registers.write(self.first_register, seconds)?;
+        self.registers.write(self.first_register + 1, drift)?;
+        self.registers
+            .write(self.first_register + 2, check_word(seconds, drift))
+    }
+
+    fn schedule(&self) {
+        self.alarm.set_alarm(
+            self.alarm.now(),
+            self.alarm.ticks_from_ms(UPDATE_INTERVAL_MS),
+        );
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, B: BackupRegisters> time::AlarmClient for SystemTime<'a, A, B> {
+    fn alarm(&self) {
+        self.fold();
+        let _ = self.store();
+        self.schedule();
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, B: BackupRegisters> DeepSleep for SystemTime<'a, A, B> {
+    fn set_deep_sleep_allowed(&self, allowed: bool) {
+        if allowed && self.status.get() != Status::NotSet {
+            self.fold();
+            let _ = self.store();
+            self.status.set(Status::Slept);
+            self.measurement.set(None);
+        }
+        self.deep_sleep
+            .map(|deep_sleep| deep_sleep.set_deep_sleep_allowed(allowed));
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, B: BackupRegisters> SyscallDriver for SystemTime<'a, A, B> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        data2: usize,
+        _processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            1 => {
+                if data1 > u32::MAX as usize || data2 >= 1000 {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                self.set_time(data1 as u32, data2 as u32).into()
+            }
+
+            2 => {
+                if self.status.get() == Status::NotSet {
+                    return CommandReturn::failure(ErrorCode::OFF);
+                }
+                let now_us = self.now_us();
+                CommandReturn::success_u32_u32(
+                    (now_us / 1_000_000) as u32,
+                    (now_us % 1_000_000 / 1000) as u32,
+                )
+            }
+
+            3 => CommandReturn::success_u32(self.status.get() as u32),
+
+            4 => CommandReturn::success_u32(self.drift_ppm.get() as i32 as u32),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
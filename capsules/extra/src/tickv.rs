@@ -55,6 +55,15 @@ enum Operation {
     GarbageCollect,
 }
 
+/// Error of a get or an invalidation, as `hil::kv_system` reports it: a
+/// missing key is `NOSUPPORT`.
+fn error_code(error: tickv::error_codes::ErrorCode) -> ErrorCode {
+    match error {
+        tickv::error_codes::ErrorCode::KeyNotFound => ErrorCode::NOSUPPORT,
+        _ => ErrorCode::FAIL,
+    }
+}
+
 pub struct TickFSFlashCtrl<'a, F: Flash + 'static> {
     flash: &'a F,
     flash_read_buffer: TakeCell<'static, F::Page>,
@@ -283,11 +292,11 @@ impl<'a, F: Flash, H: Hasher<'a, 8>, const PAGE_SIZE: usize> flash::Client<F>
                     });
                 }
                 Err(tickv::error_codes::ErrorCode::EraseNotReady(_)) | Ok(_) => {}
-                _ => {
+                Err(e) => {
                     self.operation.set(Operation::None);
                     self.client.map(|cb| {
                         cb.get_value_complete(
-                            Err(ErrorCode::FAIL),
+                            Err(error_code(e)),
                             self.key_buffer.take().unwrap(),
                             self.ret_buffer.take().unwrap(),
                         );
@@ -489,7 +498,10 @@ impl<'a, F: Flash, H: Hasher<'a, 8>, const PAGE_SIZE: usize> KVSystem<'a>
                             self.key_buffer.replace(key);
                             Ok(())
                         }
-                        _ => Err((key, buf.unwrap(), Err(ErrorCode::FAIL))),
+                        _ => {
+                            self.operation.set(Operation::None);
+                            Err((key, buf.unwrap(), Err(error_code(e))))
+                        }
                     },
                 }
             }
@@ -527,7 +539,10 @@ impl<'a, F: Flash, H: Hasher<'a, 8>, const PAGE_SIZE: usize> KVSystem<'a>
                             self.key_buffer.replace(key);
                             Ok(())
                         }
-                        _ => Err((key, Err(ErrorCode::FAIL))),
+                        _ => {
+                            self.operation.set(Operation::None);
+                            Err((key, Err(error_code(e))))
+                        }
                     },
                 }
             }
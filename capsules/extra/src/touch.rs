@@ -14,6 +14,37 @@
 //! let touch =
 //!     components::touch::TouchComponent::new(board_kernel, ts, Some(ts), Some(screen)).finalize(());
 //! ```
+//!
+//! Raw touch coordinates can be mapped to the screen with a
+//! `TouchCalibration`, set with `set_calibration`.
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! Every process has its own state, and only receives the events it
+//! enabled. The touch panel is enabled while at least one process uses it.
+//!
+//! - Command 0: the driver exists.
+//! - Commands 1 and 2: enable and disable single touch events for the
+//!   calling process.
+//! - Command 10: acknowledge the last multi touch upcall, so that the events
+//!   buffer can be written again.
+//! - Commands 11 and 12: enable and disable multi touch events for the
+//!   calling process. Enabling fails with `NODEVICE` if the panel only
+//!   supports single touches, and with `SIZE` if the events buffer is
+//!   allowed but too small to hold one touch.
+//! - Command 100: the number of touches the panel supports.
+//!
+//! - Upcall 0: single touch, with the status, `x << 16 | y` and
+//!   `pressure << 16 | size`. Reported for the first touch of multi touch
+//!   panels.
+//! - Upcall 1: gesture, with the gesture id. Reported to processes which
+//!   enabled single or multi touch events.
+//! - Upcall 2: multi touch, with the number of touches written to the events
+//!   buffer, the number of upcalls dropped because the previous one was not
+//!   acknowledged, and the number of touches which did not fit the buffer.
+//!
+//! Read-write allow 2 is the events buffer, with 8 bytes per touch.
 
 use core::cell::Cell;
 use core::mem;
@@ -22,14 +53,29 @@ use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
 use kernel::hil;
 use kernel::hil::screen::ScreenRotation;
 use kernel::hil::touch::{GestureEvent, TouchClient, TouchEvent, TouchStatus};
-use kernel::processbuffer::WriteableProcessBuffer;
+use kernel::processbuffer::{ReadableProcessBuffer, WriteableProcessBuffer};
 use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::OptionalCell;
 use kernel::{ErrorCode, ProcessId};
 
+use crate::touch_calibration::TouchCalibration;
+
 /// Syscall driver number.
 use capsules_core::driver;
 pub const DRIVER_NUM: usize = driver::NUM::Touch as usize;
 
+/// Ids for subscribe upcalls
+mod upcall {
+    pub const TOUCH: usize = 0;
+    pub const GESTURE: usize = 1;
+    pub const MULTI_TOUCH: usize = 2;
+    /// The number of upcalls the kernel stores for this grant
+    pub const COUNT: u8 = 3;
+}
+
+/// Size of one touch in the events buffer
+const EVENT_LEN: usize = 8;
+
 /// Ids for read-write allow buffers
 mod rw_allow {
     /// Allow a buffer for the multi touch. See header for format
@@ -86,8 +132,15 @@ pub struct Touch<'a> {
     /// The touch gets the rotation from the screen and
     /// updates the touch (x, y) position
     screen: Option<&'a dyn hil::screen::Screen<'a>>,
-    apps: Grant<App, UpcallCount<3>, AllowRoCount<0>, AllowRwCount<{ rw_allow::COUNT }>>,
+    apps: Grant<
+        App,
+        UpcallCount<{ upcall::COUNT }>,
+        AllowRoCount<0>,
+        AllowRwCount<{ rw_allow::COUNT }>,
+    >,
     screen_rotation_offset: Cell<ScreenRotation>,
+    /// Maps raw touch coordinates to the screen
+    calibration: OptionalCell<&'a TouchCalibration<'a>>,
 }
 
 impl<'a> Touch<'a> {
@@ -95,7 +148,12 @@ impl<'a> Touch<'a> {
         touch: Option<&'a dyn hil::touch::Touch<'a>>,
         multi_touch: Option<&'a dyn hil::touch::MultiTouch<'a>>,
         screen: Option<&'a dyn hil::screen::Screen<'a>>,
-        grant: Grant<App, UpcallCount<3>, AllowRoCount<0>, AllowRwCount<{ rw_allow::COUNT }>>,
+        grant: Grant<
+            App,
+            UpcallCount<{ upcall::COUNT }>,
+            AllowRoCount<0>,
+            AllowRwCount<{ rw_allow::COUNT }>,
+        >,
     ) -> Touch<'a> {
         Touch {
             touch: touch,
@@ -103,9 +161,14 @@ impl<'a> Touch<'a> {
             screen: screen,
             screen_rotation_offset: Cell::new(ScreenRotation::Normal),
             apps: grant,
+            calibration: OptionalCell::empty(),
         }
     }
 
+    pub fn set_calibration(&self, calibration: &'a TouchCalibration<'a>) {
+        self.calibration.set(calibration);
+    }
+
     pub fn set_screen_rotation_offset(&self, screen_rotation_offset: ScreenRotation) {
         self.screen_rotation_offset.set(screen_rotation_offset);
     }
@@ -160,32 +223,43 @@ impl<'a> Touch<'a> {
             })
     }
 
-    /// Updates the (x, y) pf the touch event based on the
-    /// screen rotation (if there si a screen)
+    /// Maps the (x, y) of the touch event to the screen
+    /// (if there is a calibration), then updates them based
+    /// on the screen rotation (if there is a screen)
     fn update_rotation(&self, touch_event: &mut TouchEvent) {
+        self.calibration
+            .map(|calibration| calibration.apply(touch_event));
         if let Some(screen) = self.screen {
-            let rotation = screen.get_rotation() + self.screen_rotation_offset.get();
-            let (mut width, mut height) = screen.get_resolution();
+            rotate_to_screen(screen, self.screen_rotation_offset.get(), touch_event);
+        }
+    }
+}
 
-            let (x, y) = match rotation {
-                ScreenRotation::Rotated90 => {
-                    mem::swap(&mut width, &mut height);
-                    (touch_event.y, height as u16 - touch_event.x)
-                }
-                ScreenRotation::Rotated180 => {
-                    (width as u16 - touch_event.x, height as u16 - touch_event.y)
-                }
-                ScreenRotation::Rotated270 => {
-                    mem::swap(&mut width, &mut height);
-                    (width as u16 - touch_event.y as u16, touch_event.x)
-                }
-                _ => (touch_event.x, touch_event.y),
-            };
+/// Updates the (x, y) of the touch event for the rotation of the screen,
+/// plus `rotation_offset`, the rotation of the panel relative to the screen.
+pub fn rotate_to_screen(
+    screen: &dyn hil::screen::Screen,
+    rotation_offset: ScreenRotation,
+    touch_event: &mut TouchEvent,
+) {
+    let rotation = screen.get_rotation() + rotation_offset;
+    let (mut width, mut height) = screen.get_resolution();
 
-            touch_event.x = x;
-            touch_event.y = y;
+    let (x, y) = match rotation {
+        ScreenRotation::Rotated90 => {
+            mem::swap(&mut width, &mut height);
+            (touch_event.y, height as u16 - touch_event.x)
         }
-    }
+        ScreenRotation::Rotated180 => (width as u16 - touch_event.x, height as u16 - touch_event.y),
+        ScreenRotation::Rotated270 => {
+            mem::swap(&mut width, &mut height);
+            (width as u16 - touch_event.y as u16, touch_event.x)
+        }
+        _ => (touch_event.x, touch_event.y),
+    };
+
+    touch_event.x = x;
+    touch_event.y = y;
 }
 
 impl<'a> hil::touch::TouchClient for Touch<'a> {
@@ -199,6 +273,9 @@ impl<'a> hil::touch::TouchClient for Touch<'a> {
         for app in self.apps.iter() {
             app.enter(|app, kernel_data| {
                 let event_status = touch_status_to_number(&event.status);
+                if !app.touch_enable {
+                    return;
+                }
                 if app.x != event.x || app.y != event.y || app.status != event_status {
                     app.x = event.x;
                     app.y = event.y;
@@ -213,7 +290,7 @@ impl<'a> hil::touch::TouchClient for Touch<'a> {
                     };
                     kernel_data
                         .schedule_upcall(
-                            0,
+                            upcall::TOUCH,
                             (
                                 event_status,
                                 (event.x as usize) << 16 | event.y as usize,
@@ -241,15 +318,16 @@ impl<'a> hil::touch::MultiTouchClient for Touch<'a> {
             // debug!("{} touch(es)", len);
             for app in self.apps.iter() {
                 app.enter(|app, kernel_data| {
+                    if !app.multi_touch_enable {
+                        return;
+                    }
                     if app.ack {
-                        app.dropped_events = 0;
-
                         let num = kernel_data
                             .get_readwrite_processbuffer(rw_allow::EVENTS)
                             .and_then(|events| {
                                 events.mut_enter(|buffer| {
-                                    let num = if buffer.len() / 8 < len {
-                                        buffer.len() / 8
+                                    let num = if buffer.len() / EVENT_LEN < len {
+                                        buffer.len() / EVENT_LEN
                                     } else {
                                         len
                                     };
@@ -262,9 +340,8 @@ impl<'a> hil::touch::MultiTouchClient for Touch<'a> {
                                         //     " multitouch {:?} x {} y {} size {:?} pressure {:?}",
                                         //     event.status, event.x, event.y, event.size, event.pressure
                                         // );
-                                        // one touch entry is 8 bytes long
-                                        let offset = event_index * 8;
-                                        if buffer.len() > event_index + 8 {
+                                        let offset = event_index * EVENT_LEN;
+                                        if buffer.len() >= offset + EVENT_LEN {
                                             buffer[offset].set(event.id as u8);
                                             buffer[offset + 1].set(event_status as u8);
                                             buffer[offset + 2].set((event.x & 0xFF) as u8);
@@ -296,12 +373,16 @@ impl<'a> hil::touch::MultiTouchClient for Touch<'a> {
                         let dropped_events = app.dropped_events;
                         if num > 0 {
                             app.ack = false;
+                            app.dropped_events = 0;
                             kernel_data
                                 .schedule_upcall(
-                                    2,
+                                    upcall::MULTI_TOUCH,
                                     (num, dropped_events, if num < len { len - num } else { 0 }),
                                 )
                                 .ok();
+                        } else {
+                            // no events buffer to write the touches to
+                            app.dropped_events += 1;
                         }
                     } else {
                         app.dropped_events = app.dropped_events + 1;
@@ -315,7 +396,10 @@ impl<'a> hil::touch::MultiTouchClient for Touch<'a> {
 impl<'a> hil::touch::GestureClient for Touch<'a> {
     fn gesture_event(&self, event: GestureEvent) {
         for app in self.apps.iter() {
-            app.enter(|_app, kernel_data| {
+            app.enter(|app, kernel_data| {
+                if !app.touch_enable && !app.multi_touch_enable {
+                    return;
+                }
                 let gesture_id = match event {
                     GestureEvent::SwipeUp => 1,
                     GestureEvent::SwipeDown => 2,
@@ -324,7 +408,9 @@ impl<'a> hil::touch::GestureClient for Touch<'a> {
                     GestureEvent::ZoomIn => 5,
                     GestureEvent::ZoomOut => 6,
                 };
-                kernel_data.schedule_upcall(1, (gesture_id, 0, 0)).ok();
+                kernel_data
+                    .schedule_upcall(upcall::GESTURE, (gesture_id, 0, 0))
+                    .ok();
             });
         }
     }
@@ -379,13 +465,26 @@ impl<'a> SyscallDriver for Touch<'a> {
 
             // multi touch enable
             11 => {
-                self.apps
-                    .enter(processid, |app, _| {
+                if self.multi_touch.is_none() {
+                    return CommandReturn::failure(ErrorCode::NODEVICE);
+                }
+                let res = self
+                    .apps
+                    .enter(processid, |app, kernel_data| {
+                        let len = kernel_data
+                            .get_readwrite_processbuffer(rw_allow::EVENTS)
+                            .map_or(0, |events| events.len());
+                        if len > 0 && len < EVENT_LEN {
+                            return Err(ErrorCode::SIZE);
+                        }
                         app.multi_touch_enable = true;
+                        Ok(())
                     })
-                    .unwrap_or(());
-                let _ = self.multi_touch_enable();
-                CommandReturn::success()
+                    .unwrap_or_else(|err| Err(err.into()));
+                if res.is_ok() {
+                    let _ = self.multi_touch_enable();
+                }
+                res.into()
             }
 
             // multi touch disable
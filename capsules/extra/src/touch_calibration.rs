@@ -0,0 +1,346 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Calibration of touch panels against the screen under them.
+//!
+//! The coordinates reported by a touch panel rarely match the pixels of the
+//! screen: resistive panels report raw ADC readings, and capacitive panels
+//! may be shifted, scaled or mirrored by the way they are mounted. This
+//! capsule maps raw coordinates to screen coordinates with an affine
+//! transform computed from three touches at known screen positions. The
+//! transform is stored in nonvolatile storage, loaded at boot, and applied
+//! by the `Touch` capsule to every event before it reaches the
+//! applications.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let calibration = components::touch_calibration::TouchCalibrationComponent::new(
+//!     board_kernel,
+//!     capsules_extra::touch_calibration::DRIVER_NUM,
+//!     nonvolatile_storage,
+//!     0x0,
+//! )
+//! .finalize(components::touch_calibration_component_static!());
+//! touch.set_calibration(calibration);
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! Points are packed in the command arguments as `(x << 16) | y`.
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: start a calibration. The transform is suspended, so that
+//!   touch events report raw coordinates until the calibration finishes.
+//! - Command 2: add a calibration point, with the raw coordinates of the
+//!   touch as the first argument and the screen coordinates of the target
+//!   as the second.
+//! - Command 3: compute the transform from the three points added, apply it
+//!   and store it. Upcall 0 reports, with the status as its first argument,
+//!   when the transform is stored.
+//! - Command 4: cancel the calibration and restore the previous transform.
+//! - Command 5: whether a transform is applied.
+//!
+//! Only one process can calibrate at a time.
+
+use core::cell::Cell;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::nonvolatile_storage::{NonvolatileStorage, NonvolatileStorageClient};
+use kernel::hil::touch::TouchEvent;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::TouchCalibration as usize;
+
+/// Number of bytes of nonvolatile storage used for the transform.
+pub const STORAGE_LEN: usize = 4 + 7 * 8;
+
+/// Marks a stored transform, so that erased or unrelated storage is ignored.
+const MAGIC: u32 = 0x70C4_CA11;
+
+/// Number of points needed to compute the transform.
+const POINTS: usize = 3;
+
+/// Affine transform from raw to screen coordinates:
+///
+/// ```text
+/// x' = (a * x + b * y + c) / divisor
+/// y' = (d * x + e * y + f) / divisor
+/// ```
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Matrix {
+    a: i64,
+    b: i64,
+    c: i64,
+    d: i64,
+    e: i64,
+    f: i64,
+    divisor: i64,
+}
+
+impl Matrix {
+    /// Compute the transform mapping each of the `raw` points to the
+    /// corresponding `screen` point. Returns `None` if the raw points are on
+    /// a line.
+    pub fn from_points(
+        raw: &[(u16, u16); POINTS],
+        screen: &[(u16, u16); POINTS],
+    ) -> Option<Matrix> {
+        let [(x0, y0), (x1, y1), (x2, y2)] = raw.map(|(x, y)| (x as i64, y as i64));
+        let [(sx0, sy0), (sx1, sy1), (sx2, sy2)] = screen.map(|(x, y)| (x as i64, y as i64));
+
+        let divisor = (x0 - x2) * (y1 - y2) - (x1 - x2) * (y0 - y2);
+        if divisor == 0 {
+            return None;
+        }
+        // Coefficients of the transform of one screen coordinate, from its
+        // values `s0`, `s1` and `s2` at the three points.
+        let x_coefficient =
+            |s0: i64, s1: i64, s2: i64| (s0 - s2) * (y1 - y2) - (s1 - s2) * (y0 - y2);
+        let y_coefficient =
+            |s0: i64, s1: i64, s2: i64| (x0 - x2) * (s1 - s2) - (s0 - s2) * (x1 - x2);
+        let offset = |s0: i64, s1: i64, s2: i64| {
+            y0 * (x2 * s1 - x1 * s2) + y1 * (x0 * s2 - x2 * s0) + y2 * (x1 * s0 - x0 * s1)
+        };
+        Some(Matrix {
+            a: x_coefficient(sx0, sx1, sx2),
+            b: y_coefficient(sx0, sx1, sx2),
+            c: offset(sx0, sx1, sx2),
+            d: x_coefficient(sy0, sy1, sy2),
+            e: y_coefficient(sy0, sy1, sy2),
+            f: offset(sy0, sy1, sy2),
+            divisor,
+        })
+    }
+
+    /// Map the raw point `(x, y)` to the screen, clamping to the range of
+    /// touch coordinates.
+    pub fn apply(&self, x: u16, y: u16) -> (u16, u16) {
+        let (x, y) = (x as i64, y as i64);
+        let clamp = |v: i64| v.clamp(0, u16::MAX as i64) as u16;
+        (
+            clamp((self.a * x + self.b * y + self.c) / self.divisor),
+            clamp((self.d * x + self.e * y + self.f) / self.divisor),
+        )
+    }
+
+    fn coefficients(&self) -> [i64; 7] {
+        [self.a, self.b, self.c, self.d, self.e, self.f, self.divisor]
+    }
+
+    fn serialize(&self, buffer: &mut [u8]) {
+        buffer[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        for (coefficient, bytes) in self
+            .coefficients()
+            .iter()
+            .zip(buffer[4..STORAGE_LEN].chunks_exact_mut(8))
+        {
+            bytes.copy_from_slice(&coefficient.to_le_bytes());
+        }
+    }
+
+    fn deserialize(buffer: &[u8]) -> Option<Matrix> {
+        if buffer.len() < STORAGE_LEN || buffer[0..4] != MAGIC.to_le_bytes() {
+            return None;
+        }
+        let mut coefficients = [0; 7];
+        for (coefficient, bytes) in coefficients
+            .iter_mut()
+            .zip(buffer[4..STORAGE_LEN].chunks_exact(8))
+        {
+            *coefficient = i64::from_le_bytes(bytes.try_into().ok()?);
+        }
+        let [a, b, c, d, e, f, divisor] = coefficients;
+        (divisor != 0).then_some(Matrix {
+            a,
+            b,
+            c,
+            d,
+            e,
+            f,
+            divisor,
+        })
+    }
+}
+
+pub struct TouchCalibration<'a> {
+    storage: &'a dyn NonvolatileStorage<'a>,
+    /// Address of the transform in the storage.
+    address: usize,
+    buffer: TakeCell<'static, [u8]>,
+    apps: Grant<(), UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    /// Transform applied to touch events.
+    matrix: OptionalCell<Matrix>,
+    /// Process running a calibration.
+    calibrating: OptionalCell<ProcessId>,
+    raw_points: Cell<[(u16, u16); POINTS]>,
+    screen_points: Cell<[(u16, u16); POINTS]>,
+    num_points: Cell<usize>,
+    /// Process waiting for the transform to be stored.
+    storing: OptionalCell<ProcessId>,
+}
+
+impl<'a> TouchCalibration<'a> {
+    pub fn new(
+        storage: &'a dyn NonvolatileStorage<'a>,
+        address: usize,
+        buffer: &'static mut [u8],
+        grant: Grant<(), UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
+    ) -> TouchCalibration<'a> {
+        TouchCalibration {
+            storage,
+            address,
+            buffer: TakeCell::new(buffer),
+            apps: grant,
+            matrix: OptionalCell::empty(),
+            calibrating: OptionalCell::empty(),
+            raw_points: Cell::new([(0, 0); POINTS]),
+            screen_points: Cell::new([(0, 0); POINTS]),
+            num_points: Cell::new(0),
+            storing: OptionalCell::empty(),
+        }
+    }
+
+    /// Read the stored transform. Touch events are not transformed until it
+    /// is loaded.
+    pub fn load(&self) -> Result<(), ErrorCode> {
+        let buffer = self.buffer.take().ok_or(ErrorCode::BUSY)?;
+        self.storage.read(buffer, self.address, STORAGE_LEN)
+    }
+
+    /// Map `event` to screen coordinates, unless no transform is stored or a
+    /// calibration is running.
+    pub fn apply(&self, event: &mut TouchEvent) {
+        if self.calibrating.is_none() {
+            self.matrix.map(|matrix| {
+                let (x, y) = matrix.apply(event.x, event.y);
+                event.x = x;
+                event.y = y;
+            });
+        }
+    }
+
+    fn start(&self, process_id: ProcessId) -> Result<(), ErrorCode> {
+        // A process that stopped in the middle of a calibration loses it.
+        if self.calibrating.map_or(false, |owner| {
+            *owner != process_id && self.apps.enter(*owner, |_, _| {}).is_ok()
+        }) {
+            return Err(ErrorCode::BUSY);
+        }
+        self.calibrating.set(process_id);
+        self.num_points.set(0);
+        Ok(())
+    }
+
+    fn add_point(&self, raw: usize, screen: usize) -> Result<(), ErrorCode> {
+        let index = self.num_points.get();
+        if index == POINTS {
+            return Err(ErrorCode::SIZE);
+        }
+        let unpack = |point: usize| ((point >> 16) as u16, (point & 0xFFFF) as u16);
+
+        let mut raw_points = self.raw_points.get();
+        let mut screen_points = self.screen_points.get();
+        raw_points[index] = unpack(raw);
+        screen_points[index] = unpack(screen);
+        self.raw_points.set(raw_points);
+        self.screen_points.set(screen_points);
+        self.num_points.set(index + 1);
+        Ok(())
+    }
+
+    fn finish(&self, process_id: ProcessId) -> Result<(), ErrorCode> {
+        if self.num_points.get() < POINTS {
+            return Err(ErrorCode::SIZE);
+        }
+        let matrix = Matrix::from_points(&self.raw_points.get(), &self.screen_points.get())
+            .ok_or(ErrorCode::INVAL)?;
+        let buffer = self.buffer.take().ok_or(ErrorCode::BUSY)?;
+
+        matrix.serialize(buffer);
+        self.storage.write(buffer, self.address, STORAGE_LEN)?;
+        self.matrix.set(matrix);
+        self.calibrating.clear();
+        self.storing.set(process_id);
+        Ok(())
+    }
+
+    /// Run `f` if `process_id` is running the calibration.
+    fn with_calibration(
+        &self,
+        process_id: ProcessId,
+        f: impl FnOnce() -> Result<(), ErrorCode>,
+    ) -> Result<(), ErrorCode> {
+        if self.calibrating.contains(&process_id) {
+            f()
+        } else {
+            Err(ErrorCode::RESERVE)
+        }
+    }
+}
+
+impl<'a> NonvolatileStorageClient for TouchCalibration<'a> {
+    fn read_done(&self, buffer: &'static mut [u8], _length: usize) {
+        if let Some(matrix) = Matrix::deserialize(buffer) {
+            self.matrix.set(matrix);
+        }
+        self.buffer.replace(buffer);
+    }
+
+    fn write_done(&self, buffer: &'static mut [u8], _length: usize) {
+        self.buffer.replace(buffer);
+        self.storing.take().map(|process_id| {
+            let _ = self.apps.enter(process_id, |_, upcalls| {
+                upcalls
+                    .schedule_upcall(0, (kernel::errorcode::into_statuscode(Ok(())), 0, 0))
+                    .ok();
+            });
+        });
+    }
+}
+
+impl<'a> SyscallDriver for TouchCalibration<'a> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            1 => self.start(processid).into(),
+
+            2 => self
+                .with_calibration(processid, || self.add_point(data1, data2))
+                .into(),
+
+            3 => self
+                .with_calibration(processid, || self.finish(processid))
+                .into(),
+
+            4 => self
+                .with_calibration(processid, || {
+                    self.calibrating.clear();
+                    Ok(())
+                })
+                .into(),
+
+            5 => CommandReturn::success_u32(self.matrix.is_some() as u32),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
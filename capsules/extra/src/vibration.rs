@@ -0,0 +1,385 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Vibration analysis of accelerometer samples.
+//!
+//! A window of samples of one axis of an accelerometer is collected at a
+//! fixed rate, and transformed by a fixed-point FFT. Applications get the
+//! dominant frequency of the window, its amplitude, and the energy of
+//! frequency bands.
+//!
+//! The accelerometer is read one sample at a time through
+//! `hil::sensors::NineDof`, with an alarm timing the reads, so the rate is
+//! limited by how long the sensor takes to answer a read: a few hundred Hz
+//! over I2C.
+//!
+//! The FFT is a radix-2 decimation in time FFT, with Q15 twiddle factors,
+//! which scales the values down by 2 at each stage so that they do not
+//! overflow. The mean of the window is removed first, so gravity does not
+//! leak into the low bins. Windows are `MAX_WINDOW_LEN` samples.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let vibration = components::vibration::VibrationComponent::new(
+//!     board_kernel,
+//!     capsules_extra::vibration::DRIVER_NUM,
+//!     mux_alarm,
+//!     fxos8700,
+//! )
+//! .finalize(components::vibration_component_static!(
+//!     imxrt1050::gpt::Gpt1<'static>,
+//! ));
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! - Command 0: the driver exists.
+//! - Command 1: analyze a window sampled at the rate in Hz given as the first
+//!   argument, up to `MAX_RATE_HZ`, along the axis given as the second
+//!   argument: 0 for X, 1 for Y and 2 for Z. Upcall 0 reports, with a status
+//!   code, the dominant frequency in mHz and its amplitude in mg. The energy
+//!   of the bands is written to read-write allow 0, as a little endian `u32`
+//!   in mg² for each band: the bins above DC and below the Nyquist frequency
+//!   are shared evenly between as many bands as the buffer holds.
+//! - Command 2: stop the analysis, without an upcall.
+//!
+//! One app analyzes a window at a time.
+
+use core::cell::Cell;
+
+use kernel::errorcode::into_statuscode;
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::sensors::{NineDof, NineDofClient};
+use kernel::hil::time::{Alarm, AlarmClient, ConvertTicks, Ticks};
+use kernel::processbuffer::WriteableProcessBuffer;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::Vibration as usize;
+
+/// Ids for read-write allow buffers
+mod rw_allow {
+    pub const BANDS: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+/// Number of samples in a window, and largest FFT.
+pub const MAX_WINDOW_LEN: usize = 128;
+/// Highest sample rate.
+pub const MAX_RATE_HZ: usize = 1000;
+
+/// The first quarter of a sine wave of `MAX_WINDOW_LEN` samples, in Q15.
+const QUARTER_SINE: [i32; MAX_WINDOW_LEN / 4 + 1] = [
+    0, 1608, 3212, 4808, 6393, 7962, 9512, 11039, 12539, 14010, 15446, 16846, 18204, 19519, 20787,
+    22005, 23170, 24279, 25329, 26319, 27245, 28105, 28898, 29621, 30273, 30852, 31356, 31785,
+    32137, 32412, 32609, 32728, 32767,
+];
+
+/// The cosine and sine of `2π * step / MAX_WINDOW_LEN`, for steps in the
+/// first half turn.
+fn twiddle(step: usize) -> (i32, i32) {
+    let quarter = MAX_WINDOW_LEN / 4;
+    if step <= quarter {
+        (QUARTER_SINE[quarter - step], QUARTER_SINE[step])
+    } else {
+        (
+            -QUARTER_SINE[step - quarter],
+            QUARTER_SINE[2 * quarter - step],
+        )
+    }
+}
+
+/// Transform `re` and `im` in place, scaled down by their length, which must
+/// be the same power of two, up to `MAX_WINDOW_LEN`.
+pub fn fft(re: &mut [i32], im: &mut [i32]) -> Result<(), ErrorCode> {
+    let n = re.len();
+    if n != im.len() || !n.is_power_of_two() || n < 2 || n > MAX_WINDOW_LEN {
+        return Err(ErrorCode::INVAL);
+    }
+
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if j > i {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let half = len / 2;
+        let step = MAX_WINDOW_LEN / len;
+        for start in (0..n).step_by(len) {
+            for k in 0..half {
+                let (cos, sin) = twiddle(k * step);
+                let (even, odd) = (start + k, start + k + half);
+                // Multiply by e^(-iθ), rounding to the nearest
+                let t_re = ((re[odd] as i64 * cos as i64 + im[odd] as i64 * sin as i64 + (1 << 14))
+                    >> 15) as i32;
+                let t_im = ((im[odd] as i64 * cos as i64 - re[odd] as i64 * sin as i64 + (1 << 14))
+                    >> 15) as i32;
+                let (u_re, u_im) = (re[even], im[even]);
+                re[even] = (u_re + t_re) >> 1;
+                im[even] = (u_im + t_im) >> 1;
+                re[odd] = (u_re - t_re) >> 1;
+                im[odd] = (u_im - t_im) >> 1;
+            }
+        }
+        len *= 2;
+    }
+    Ok(())
+}
+
+fn isqrt(value: u64) -> u64 {
+    let mut root = 0;
+    let mut bit = 1 << 62;
+    let mut rest = value;
+    while bit > value {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if rest >= root + bit {
+            rest -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root
+}
+
+fn power(re: i32, im: i32) -> u64 {
+    (re as i64 * re as i64 + im as i64 * im as i64) as u64
+}
+
+/// The bin with the highest amplitude in the output of `fft` of real
+/// samples, above DC and below the Nyquist frequency, with the amplitude of
+/// the sine wave it holds, in the unit of the samples.
+pub fn dominant_bin(re: &[i32], im: &[i32]) -> (usize, u32) {
+    let (bin, power) = (1..re.len() / 2)
+        .map(|bin| (bin, power(re[bin], im[bin])))
+        .fold((0, 0), |max, bin| if bin.1 > max.1 { bin } else { max });
+    // Each bin holds half of the amplitude, the other half is in the
+    // mirrored bin.
+    (bin, (2 * isqrt(power)).min(u32::MAX as u64) as u32)
+}
+
+/// The energy of the sine waves in each of `energies.len()` bands sharing
+/// the bins of the output of `fft` of real samples, above DC and below the
+/// Nyquist frequency, in the unit of the samples squared.
+pub fn band_energies(re: &[i32], im: &[i32], energies: &mut [u32]) {
+    energies.iter_mut().for_each(|energy| *energy = 0);
+    let bins = re.len() / 2 - 1;
+    if energies.is_empty() || bins == 0 {
+        return;
+    }
+    for bin in 1..=bins {
+        let band = (bin - 1) * energies.len() / bins;
+        // The energy of a sine wave of amplitude 2 * |X| is 2 * |X|²
+        let energy = (2 * power(re[bin], im[bin])).min(u32::MAX as u64) as u32;
+        energies[band] = energies[band].saturating_add(energy);
+    }
+}
+
+#[derive(Default)]
+pub struct App;
+
+pub struct Vibration<'a, A: Alarm<'a>> {
+    sensor: &'a dyn NineDof<'a>,
+    alarm: &'a A,
+    apps: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<{ rw_allow::COUNT }>>,
+    re: TakeCell<'static, [i32]>,
+    im: TakeCell<'static, [i32]>,
+    /// Samples collected in the window.
+    samples: Cell<usize>,
+    axis: Cell<usize>,
+    rate_hz: Cell<usize>,
+    /// Time of the last sample.
+    sample_start: Cell<A::Ticks>,
+    /// A read of the accelerometer has not completed.
+    reading: Cell<bool>,
+    current_app: OptionalCell<ProcessId>,
+}
+
+impl<'a, A: Alarm<'a>> Vibration<'a, A> {
+    pub fn new(
+        sensor: &'a dyn NineDof<'a>,
+        alarm: &'a A,
+        re: &'static mut [i32; MAX_WINDOW_LEN],
+        im: &'static mut [i32; MAX_WINDOW_LEN],
+        grant: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<{ rw_allow::COUNT }>>,
+    ) -> Vibration<'a, A> {
+        Vibration {
+            sensor,
+            alarm,
+            apps: grant,
+            re: TakeCell::new(re),
+            im: TakeCell::new(im),
+            samples: Cell::new(0),
+            axis: Cell::new(0),
+            rate_hz: Cell::new(0),
+            sample_start: Cell::new(A::Ticks::from(0)),
+            reading: Cell::new(false),
+            current_app: OptionalCell::empty(),
+        }
+    }
+
+    fn start(&self, rate_hz: usize, axis: usize) -> Result<(), ErrorCode> {
+        if rate_hz == 0 || rate_hz > MAX_RATE_HZ || axis > 2 {
+            return Err(ErrorCode::INVAL);
+        }
+        self.rate_hz.set(rate_hz);
+        self.axis.set(axis);
+        self.samples.set(0);
+        self.sample_start.set(self.alarm.now());
+        self.sample()
+    }
+
+    /// Read a sample, and time the next.
+    fn sample(&self) -> Result<(), ErrorCode> {
+        if self.reading.get() {
+            // The sensor is too slow for the rate
+            return Err(ErrorCode::BUSY);
+        }
+        self.sensor.read_accelerometer()?;
+        self.reading.set(true);
+
+        // Time samples from the previous one so they do not drift.
+        let start = self.sample_start.get();
+        let period = self
+            .alarm
+            .ticks_from_us(1_000_000 / self.rate_hz.get() as u32);
+        self.sample_start.set(start.wrapping_add(period));
+        self.alarm.set_alarm(start, period);
+        Ok(())
+    }
+
+    fn stop(&self) {
+        self.current_app.clear();
+        let _ = self.alarm.disarm();
+    }
+
+    fn analyze(&self) {
+        let _ = self.alarm.disarm();
+        let result = self.re.map_or((0, 0), |re| {
+            self.im.map_or((0, 0), |im| {
+                let mean = re.iter().sum::<i32>() / MAX_WINDOW_LEN as i32;
+                re.iter_mut().for_each(|sample| *sample -= mean);
+                im.iter_mut().for_each(|sample| *sample = 0);
+                let _ = fft(re, im);
+                let (bin, amplitude) = dominant_bin(re, im);
+
+                self.current_app.map(|processid| {
+                    let _ = self.apps.enter(*processid, |_, kernel_data| {
+                        let _ = kernel_data
+                            .get_readwrite_processbuffer(rw_allow::BANDS)
+                            .and_then(|bands| {
+                                bands.mut_enter(|bands| {
+                                    let mut energies = [0; MAX_WINDOW_LEN / 2 - 1];
+                                    let count = (bands.len() / 4).min(energies.len());
+                                    band_energies(re, im, &mut energies[..count]);
+                                    for (chunk, energy) in bands.chunks(4).zip(&energies[..count]) {
+                                        chunk.copy_from_slice(&energy.to_le_bytes());
+                                    }
+                                })
+                            });
+                    });
+                });
+                (bin * self.rate_hz.get() * 1000 / MAX_WINDOW_LEN, amplitude)
+            })
+        });
+        self.done(Ok(()), result.0, result.1 as usize);
+    }
+
+    fn done(&self, result: Result<(), ErrorCode>, frequency_mhz: usize, amplitude: usize) {
+        let _ = self.alarm.disarm();
+        self.current_app.take().map(|processid| {
+            let _ = self.apps.enter(processid, |_, kernel_data| {
+                kernel_data
+                    .schedule_upcall(0, (into_statuscode(result), frequency_mhz, amplitude))
+                    .ok();
+            });
+        });
+    }
+}
+
+impl<'a, A: Alarm<'a>> AlarmClient for Vibration<'a, A> {
+    fn alarm(&self) {
+        if self.current_app.is_none() {
+            return;
+        }
+        if let Err(err) = self.sample() {
+            self.done(Err(err), 0, 0);
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> NineDofClient for Vibration<'a, A> {
+    fn callback(&self, x: usize, y: usize, z: usize) {
+        self.reading.set(false);
+        if self.current_app.is_none() {
+            return;
+        }
+        // The values are signed
+        let value = [x, y, z][self.axis.get()] as i32;
+        let index = self.samples.get();
+        self.re.map(|re| re[index] = value);
+        self.samples.set(index + 1);
+        if index + 1 == MAX_WINDOW_LEN {
+            self.analyze();
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>> SyscallDriver for Vibration<'a, A> {
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            // Analyze a window
+            1 => {
+                if self.current_app.is_some() {
+                    return CommandReturn::failure(ErrorCode::BUSY);
+                }
+                let result = self.start(data1, data2);
+                match result {
+                    Ok(()) => self.current_app.set(processid),
+                    Err(_) => self.stop(),
+                }
+                CommandReturn::from(result)
+            }
+
+            // Stop the analysis
+            2 => match self.current_app.extract() {
+                Some(current) if current == processid => {
+                    self.stop();
+                    CommandReturn::success()
+                }
+                Some(_) => CommandReturn::failure(ErrorCode::RESERVE),
+                None => CommandReturn::failure(ErrorCode::OFF),
+            },
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
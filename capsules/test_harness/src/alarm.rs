@@ -0,0 +1,100 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Mock alarm.
+
+use core::cell::Cell;
+
+use kernel::hil::time::{Alarm, AlarmClient, Freq1KHz, Ticks, Ticks32, Time};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// An alarm ticking at 1 kHz, so that ticks are milliseconds. Time starts at
+/// 0 and only moves when the test calls [`advance`](MockAlarm::advance).
+pub struct MockAlarm<'a> {
+    now: Cell<Ticks32>,
+    reference: Cell<Ticks32>,
+    dt: Cell<Ticks32>,
+    armed: Cell<bool>,
+    client: OptionalCell<&'a dyn AlarmClient>,
+}
+
+impl<'a> MockAlarm<'a> {
+    pub fn new() -> MockAlarm<'a> {
+        MockAlarm {
+            now: Cell::new(0.into()),
+            reference: Cell::new(0.into()),
+            dt: Cell::new(0.into()),
+            armed: Cell::new(false),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Move time forward by `ticks`, firing the alarm each time it expires
+    /// on the way, including when the client sets it again from its
+    /// callback.
+    pub fn advance(&self, ticks: u32) {
+        let end = self.now.get().wrapping_add(ticks.into());
+        while self.armed.get() {
+            let expiration = self.reference.get().wrapping_add(self.dt.get());
+            let remaining = end.wrapping_sub(self.now.get());
+            if expiration.wrapping_sub(self.now.get()) > remaining {
+                break;
+            }
+            self.now.set(expiration);
+            self.armed.set(false);
+            self.client.map(|client| client.alarm());
+        }
+        self.now.set(end);
+    }
+
+    /// Ticks until the alarm expires, `None` if it is not armed.
+    pub fn remaining(&self) -> Option<u32> {
+        self.armed.get().then(|| {
+            self.reference
+                .get()
+                .wrapping_add(self.dt.get())
+                .wrapping_sub(self.now.get())
+                .into_u32()
+        })
+    }
+}
+
+impl Time for MockAlarm<'_> {
+    type Frequency = Freq1KHz;
+    type Ticks = Ticks32;
+
+    fn now(&self) -> Ticks32 {
+        self.now.get()
+    }
+}
+
+impl<'a> Alarm<'a> for MockAlarm<'a> {
+    fn set_alarm_client(&self, client: &'a dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn set_alarm(&self, reference: Ticks32, dt: Ticks32) {
+        self.reference.set(reference);
+        self.dt.set(dt);
+        self.armed.set(true);
+    }
+
+    fn get_alarm(&self) -> Ticks32 {
+        self.reference.get().wrapping_add(self.dt.get())
+    }
+
+    fn disarm(&self) -> Result<(), ErrorCode> {
+        self.armed.set(false);
+        Ok(())
+    }
+
+    fn is_armed(&self) -> bool {
+        self.armed.get()
+    }
+
+    fn minimum_dt(&self) -> Ticks32 {
+        1.into()
+    }
+}
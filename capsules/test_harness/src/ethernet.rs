@@ -0,0 +1,120 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Mock Ethernet adapter.
+
+use core::cell::{Cell, RefCell};
+
+use kernel::hil::ethernet::{EthernetAdapter, RxClient, TxClient};
+use kernel::hil::mac_address::{Configure, MacAddress};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+/// An Ethernet adapter which logs the frames transmitted, and holds each
+/// transmission until the test calls
+/// [`complete_transmit`](MockEthernet::complete_transmit). Frames sent by
+/// the test with [`receive`](MockEthernet::receive) are given to the
+/// receive client right away.
+pub struct MockEthernet<'a> {
+    mac_address: Cell<MacAddress>,
+    enabled: Cell<bool>,
+    transmitted: RefCell<Vec<Vec<u8>>>,
+    tx_pending: TakeCell<'static, [u8]>,
+    tx_client: OptionalCell<&'a dyn TxClient>,
+    rx_client: OptionalCell<&'a dyn RxClient>,
+}
+
+impl<'a> MockEthernet<'a> {
+    pub fn new(mac_address: MacAddress) -> MockEthernet<'a> {
+        MockEthernet {
+            mac_address: Cell::new(mac_address),
+            enabled: Cell::new(false),
+            transmitted: RefCell::new(Vec::new()),
+            tx_pending: TakeCell::empty(),
+            tx_client: OptionalCell::empty(),
+            rx_client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    /// The frames transmitted since the last call.
+    pub fn take_transmitted(&self) -> Vec<Vec<u8>> {
+        self.transmitted.take()
+    }
+
+    pub fn is_transmitting(&self) -> bool {
+        self.tx_pending.is_some()
+    }
+
+    /// Complete the transmission in progress with `result`. Returns `false`
+    /// if there is none.
+    pub fn complete_transmit(&self, result: Result<(), ErrorCode>) -> bool {
+        match self.tx_pending.take() {
+            Some(frame) => {
+                self.tx_client
+                    .map(move |client| client.transmit_done(frame, result));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Receive `frame`, with its Ethernet header.
+    pub fn receive(&self, frame: &[u8]) {
+        self.rx_client.map(|client| client.received_frame(frame));
+    }
+}
+
+impl<'a> EthernetAdapter<'a> for MockEthernet<'a> {
+    fn set_receive_client(&self, client: &'a dyn RxClient) {
+        self.rx_client.set(client);
+    }
+
+    fn set_transmit_client(&self, client: &'a dyn TxClient) {
+        self.tx_client.set(client);
+    }
+
+    fn enable(&self) -> Result<(), ErrorCode> {
+        self.enabled.set(true);
+        Ok(())
+    }
+
+    fn disable(&self) -> Result<(), ErrorCode> {
+        self.enabled.set(false);
+        Ok(())
+    }
+
+    fn link_up(&self) -> bool {
+        self.enabled.get()
+    }
+
+    fn transmit(
+        &self,
+        frame: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        if self.tx_pending.is_some() {
+            return Err((ErrorCode::BUSY, frame));
+        } else if len > frame.len() {
+            return Err((ErrorCode::SIZE, frame));
+        }
+        self.transmitted.borrow_mut().push(frame[..len].to_vec());
+        self.tx_pending.replace(frame);
+        Ok(())
+    }
+}
+
+impl Configure for MockEthernet<'_> {
+    fn set_mac_address(&self, address: MacAddress) -> Result<(), ErrorCode> {
+        self.mac_address.set(address);
+        Ok(())
+    }
+
+    fn get_mac_address(&self) -> MacAddress {
+        self.mac_address.get()
+    }
+}
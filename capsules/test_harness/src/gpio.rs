@@ -0,0 +1,153 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Mock GPIO pin.
+
+use core::cell::Cell;
+
+use kernel::hil::gpio::{
+    Client, Configuration, Configure, FloatingState, Input, Interrupt, InterruptEdge, Output,
+};
+use kernel::utilities::cells::OptionalCell;
+
+/// A GPIO pin whose input level is set by the test. Changing it fires the
+/// interrupt if it is enabled for that edge. Outputs are recorded, and read
+/// back while the pin is an output.
+pub struct MockPin<'a> {
+    configuration: Cell<Configuration>,
+    floating_state: Cell<FloatingState>,
+    input: Cell<bool>,
+    output: Cell<bool>,
+    interrupt: Cell<Option<InterruptEdge>>,
+    client: OptionalCell<&'a dyn Client>,
+}
+
+impl<'a> MockPin<'a> {
+    pub fn new() -> MockPin<'a> {
+        MockPin {
+            configuration: Cell::new(Configuration::LowPower),
+            floating_state: Cell::new(FloatingState::PullNone),
+            input: Cell::new(false),
+            output: Cell::new(false),
+            interrupt: Cell::new(None),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Drive the input level of the pin from outside.
+    pub fn set_input(&self, level: bool) {
+        let previous = self.input.replace(level);
+        let fire = match self.interrupt.get() {
+            Some(InterruptEdge::RisingEdge) => !previous && level,
+            Some(InterruptEdge::FallingEdge) => previous && !level,
+            Some(InterruptEdge::EitherEdge) => previous != level,
+            None => false,
+        };
+        if fire {
+            self.client.map(|client| client.fired());
+        }
+    }
+
+    /// The level last written through `Output`.
+    pub fn output(&self) -> bool {
+        self.output.get()
+    }
+
+    /// The edge interrupts are enabled for, `None` if they are disabled.
+    pub fn interrupt_edge(&self) -> Option<InterruptEdge> {
+        self.interrupt.get()
+    }
+}
+
+impl Configure for MockPin<'_> {
+    fn configuration(&self) -> Configuration {
+        self.configuration.get()
+    }
+
+    fn make_output(&self) -> Configuration {
+        self.configuration.set(match self.configuration.get() {
+            Configuration::Input | Configuration::InputOutput => Configuration::InputOutput,
+            _ => Configuration::Output,
+        });
+        self.configuration.get()
+    }
+
+    fn disable_output(&self) -> Configuration {
+        self.configuration.set(match self.configuration.get() {
+            Configuration::Input | Configuration::InputOutput => Configuration::Input,
+            _ => Configuration::LowPower,
+        });
+        self.configuration.get()
+    }
+
+    fn make_input(&self) -> Configuration {
+        self.configuration.set(match self.configuration.get() {
+            Configuration::Output | Configuration::InputOutput => Configuration::InputOutput,
+            _ => Configuration::Input,
+        });
+        self.configuration.get()
+    }
+
+    fn disable_input(&self) -> Configuration {
+        self.configuration.set(match self.configuration.get() {
+            Configuration::Output | Configuration::InputOutput => Configuration::Output,
+            _ => Configuration::LowPower,
+        });
+        self.configuration.get()
+    }
+
+    fn deactivate_to_low_power(&self) {
+        self.configuration.set(Configuration::LowPower);
+    }
+
+    fn set_floating_state(&self, state: FloatingState) {
+        self.floating_state.set(state);
+    }
+
+    fn floating_state(&self) -> FloatingState {
+        self.floating_state.get()
+    }
+}
+
+impl Output for MockPin<'_> {
+    fn set(&self) {
+        self.output.set(true);
+    }
+
+    fn clear(&self) {
+        self.output.set(false);
+    }
+
+    fn toggle(&self) -> bool {
+        self.output.set(!self.output.get());
+        self.output.get()
+    }
+}
+
+impl Input for MockPin<'_> {
+    fn read(&self) -> bool {
+        match self.configuration.get() {
+            Configuration::Output => self.output.get(),
+            _ => self.input.get(),
+        }
+    }
+}
+
+impl<'a> Interrupt<'a> for MockPin<'a> {
+    fn set_client(&self, client: &'a dyn Client) {
+        self.client.set(client);
+    }
+
+    fn enable_interrupts(&self, mode: InterruptEdge) {
+        self.interrupt.set(Some(mode));
+    }
+
+    fn disable_interrupts(&self) {
+        self.interrupt.set(None);
+    }
+
+    fn is_pending(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,148 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Mock I2C device.
+
+use core::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+use kernel::hil::i2c::{Error, I2CClient, I2CDevice};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+
+/// A transfer started on the device.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Transfer {
+    /// The bytes written.
+    Write(Vec<u8>),
+    /// The number of bytes read.
+    Read(usize),
+    /// The bytes written, then the number of bytes read.
+    WriteRead(Vec<u8>, usize),
+}
+
+/// An I2C device which logs the transfers started on it, and holds each one
+/// until the test calls [`complete`](MockI2CDevice::complete). Only one
+/// transfer can be in progress: the device is `Busy` until it completes.
+pub struct MockI2CDevice<'a> {
+    enabled: Cell<bool>,
+    transfers: RefCell<Vec<Transfer>>,
+    responses: RefCell<VecDeque<Result<Vec<u8>, Error>>>,
+    /// Buffer of the transfer in progress, and where its read bytes go.
+    pending: TakeCell<'static, [u8]>,
+    read_range: Cell<(usize, usize)>,
+    client: OptionalCell<&'a dyn I2CClient>,
+}
+
+impl<'a> MockI2CDevice<'a> {
+    pub fn new() -> MockI2CDevice<'a> {
+        MockI2CDevice {
+            enabled: Cell::new(false),
+            transfers: RefCell::new(Vec::new()),
+            responses: RefCell::new(VecDeque::new()),
+            pending: TakeCell::empty(),
+            read_range: Cell::new((0, 0)),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn I2CClient) {
+        self.client.set(client);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    /// Queue the bytes the device returns for a transfer which reads.
+    pub fn queue_response(&self, bytes: &[u8]) {
+        self.responses.borrow_mut().push_back(Ok(bytes.to_vec()));
+    }
+
+    /// Queue an error to complete a transfer with.
+    pub fn queue_error(&self, error: Error) {
+        self.responses.borrow_mut().push_back(Err(error));
+    }
+
+    /// The transfers started since the last call.
+    pub fn take_transfers(&self) -> Vec<Transfer> {
+        self.transfers.take()
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Complete the transfer in progress with the next queued response,
+    /// copied to the bytes it reads, or successfully without changing the
+    /// buffer if there is none. Returns `false` if no transfer is in
+    /// progress.
+    pub fn complete(&self) -> bool {
+        let buffer = match self.pending.take() {
+            Some(buffer) => buffer,
+            None => return false,
+        };
+        let status = match self.responses.borrow_mut().pop_front() {
+            Some(Ok(bytes)) => {
+                let (start, len) = self.read_range.get();
+                let len = len.min(bytes.len());
+                buffer[start..start + len].copy_from_slice(&bytes[..len]);
+                Ok(())
+            }
+            Some(Err(error)) => Err(error),
+            None => Ok(()),
+        };
+        self.client
+            .map(move |client| client.command_complete(buffer, status));
+        true
+    }
+
+    fn start(
+        &self,
+        buffer: &'static mut [u8],
+        transfer: Transfer,
+        read_range: (usize, usize),
+    ) -> Result<(), (Error, &'static mut [u8])> {
+        if self.pending.is_some() {
+            return Err((Error::Busy, buffer));
+        }
+        self.transfers.borrow_mut().push(transfer);
+        self.read_range.set(read_range);
+        self.pending.replace(buffer);
+        Ok(())
+    }
+}
+
+impl I2CDevice for MockI2CDevice<'_> {
+    fn enable(&self) {
+        self.enabled.set(true);
+    }
+
+    fn disable(&self) {
+        self.enabled.set(false);
+    }
+
+    /// The bytes read replace the bytes written, at the start of the buffer.
+    fn write_read(
+        &self,
+        data: &'static mut [u8],
+        write_len: usize,
+        read_len: usize,
+    ) -> Result<(), (Error, &'static mut [u8])> {
+        let transfer = Transfer::WriteRead(data[..write_len].to_vec(), read_len);
+        self.start(data, transfer, (0, read_len))
+    }
+
+    fn write(&self, data: &'static mut [u8], len: usize) -> Result<(), (Error, &'static mut [u8])> {
+        let transfer = Transfer::Write(data[..len].to_vec());
+        self.start(data, transfer, (0, 0))
+    }
+
+    fn read(
+        &self,
+        buffer: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (Error, &'static mut [u8])> {
+        self.start(buffer, Transfer::Read(len), (0, len))
+    }
+}
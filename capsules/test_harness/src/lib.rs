@@ -0,0 +1,94 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Host-side test harness for capsules.
+//!
+//! This crate provides mock implementations of the HILs capsules are built
+//! on, so that their state machines can be driven deterministically in
+//! `cargo test`, without hardware:
+//!
+//! - [`alarm::MockAlarm`], an `Alarm` whose time only moves when the test
+//!   advances it;
+//! - [`ethernet::MockEthernet`], an Ethernet adapter logging the frames
+//!   transmitted and receiving the frames sent by the test;
+//! - [`gpio::MockPin`], an interrupt pin whose input level is set by the
+//!   test;
+//! - [`i2c::MockI2CDevice`], an `I2CDevice` logging every transfer and
+//!   completing it with a response queued by the test;
+//! - [`spi::MockSpiMaster`], a `SpiMaster` and `SpiMasterDevice` doing the
+//!   same for SPI transfers;
+//! - [`uart::MockUart`], a UART logging the bytes transmitted and receiving
+//!   the bytes sent by the test.
+//!
+//! Nothing completes on its own: the test calls `complete()` or `advance()`
+//! to deliver the next callback, and checks the transfers the capsule made
+//! in between. Unlike the rest of the kernel this crate uses `std`, and is
+//! only meant to be built for the host.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let i2c = leak(MockI2CDevice::new());
+//! let ft6x06 = leak(Ft6x06::new(i2c, pin, static_buffer(17), events));
+//! i2c.set_client(ft6x06);
+//!
+//! pin.set_input(false);
+//! assert_eq!(i2c.take_transfers(), [Transfer::WriteRead(vec![0x01], 15)]);
+//! i2c.queue_response(&[0x00, 0x01, 0x00, 0x64]);
+//! i2c.complete();
+//! ```
+//!
+//! The tests of the capsules using this harness are in `src/tests`.
+
+use std::sync::{Mutex, MutexGuard};
+
+use kernel::capabilities;
+use kernel::grant::{AllowRoSize, AllowRwSize, Grant, UpcallSize};
+use kernel::Kernel;
+
+pub mod alarm;
+pub mod ethernet;
+pub mod gpio;
+pub mod i2c;
+pub mod spi;
+pub mod uart;
+
+#[cfg(test)]
+mod tests;
+
+/// Move `value` to the heap for the rest of the test, as capsules and their
+/// clients hold references to each other.
+pub fn leak<T>(value: T) -> &'static T {
+    Box::leak(Box::new(value))
+}
+
+/// A zeroed buffer, for the `&'static mut [u8]` buffers capsules own.
+pub fn static_buffer(len: usize) -> &'static mut [u8] {
+    Box::leak(vec![0; len].into_boxed_slice())
+}
+
+/// A grant for capsules which need one, from a kernel without processes.
+/// Entering it always fails, so capsules schedule no upcalls.
+pub fn create_grant<
+    T: Default,
+    Upcalls: UpcallSize,
+    AllowROs: AllowRoSize,
+    AllowRWs: AllowRwSize,
+>(
+    driver_num: usize,
+) -> Grant<T, Upcalls, AllowROs, AllowRWs> {
+    let kernel: &'static Kernel = leak(Kernel::new(&[]));
+    let capability = kernel::create_capability!(capabilities::MemoryAllocationCapability);
+    kernel.create_grant(driver_num, &capability)
+}
+
+/// Deferred calls are global to the kernel, while tests run in parallel. A
+/// test completing operations from a deferred call holds this lock, so it
+/// does not service the deferred calls of another test.
+pub fn deferred_call_lock() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    // A failed test does not stop the others
+    LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,264 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Mock SPI controller.
+
+use core::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+use kernel::hil::spi::{ClockPhase, ClockPolarity, SpiMaster, SpiMasterClient, SpiMasterDevice};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+/// A transfer started on the bus.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transfer {
+    /// Chip select the transfer was made on.
+    pub chip_select: u8,
+    /// The bytes written.
+    pub write: Vec<u8>,
+    /// Whether the transfer reads the bytes clocked in.
+    pub read: bool,
+}
+
+/// A SPI controller which logs the transfers started on it, and holds each
+/// one until the test calls [`complete`](MockSpiMaster::complete). Only one
+/// transfer can be in progress: the controller is busy until it completes.
+///
+/// It implements both `SpiMaster`, with `u8` chip selects, and
+/// `SpiMasterDevice`, as a device on the selected chip select, so it can
+/// stand in for either.
+pub struct MockSpiMaster<'a> {
+    chip_select: Cell<u8>,
+    rate: Cell<u32>,
+    polarity: Cell<ClockPolarity>,
+    phase: Cell<ClockPhase>,
+    hold_low: Cell<bool>,
+    transfers: RefCell<Vec<Transfer>>,
+    responses: RefCell<VecDeque<Result<Vec<u8>, ErrorCode>>>,
+    write_buffer: TakeCell<'static, [u8]>,
+    read_buffer: TakeCell<'static, [u8]>,
+    len: Cell<usize>,
+    client: OptionalCell<&'a dyn SpiMasterClient>,
+}
+
+impl<'a> MockSpiMaster<'a> {
+    pub fn new() -> MockSpiMaster<'a> {
+        MockSpiMaster {
+            chip_select: Cell::new(0),
+            rate: Cell::new(1_000_000),
+            polarity: Cell::new(ClockPolarity::IdleLow),
+            phase: Cell::new(ClockPhase::SampleLeading),
+            hold_low: Cell::new(false),
+            transfers: RefCell::new(Vec::new()),
+            responses: RefCell::new(VecDeque::new()),
+            write_buffer: TakeCell::empty(),
+            read_buffer: TakeCell::empty(),
+            len: Cell::new(0),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Queue the bytes clocked in during a transfer which reads.
+    pub fn queue_response(&self, bytes: &[u8]) {
+        self.responses.borrow_mut().push_back(Ok(bytes.to_vec()));
+    }
+
+    /// Queue an error to complete a transfer with.
+    pub fn queue_error(&self, error: ErrorCode) {
+        self.responses.borrow_mut().push_back(Err(error));
+    }
+
+    /// The transfers started since the last call.
+    pub fn take_transfers(&self) -> Vec<Transfer> {
+        self.transfers.take()
+    }
+
+    /// Whether the chip select is held low after transfers.
+    pub fn is_held_low(&self) -> bool {
+        self.hold_low.get()
+    }
+
+    /// Complete the transfer in progress with the next queued response,
+    /// copied to the read buffer if there is one, or successfully without
+    /// changing it if there is none. Returns `false` if no transfer is in
+    /// progress.
+    pub fn complete(&self) -> bool {
+        let write_buffer = match self.write_buffer.take() {
+            Some(buffer) => buffer,
+            None => return false,
+        };
+        let mut read_buffer = self.read_buffer.take();
+        let len = self.len.get();
+        let status = match self.responses.borrow_mut().pop_front() {
+            Some(Ok(bytes)) => {
+                if let Some(read_buffer) = read_buffer.as_mut() {
+                    let count = len.min(bytes.len());
+                    read_buffer[..count].copy_from_slice(&bytes[..count]);
+                }
+                Ok(())
+            }
+            Some(Err(error)) => Err(error),
+            None => Ok(()),
+        };
+        self.client
+            .map(move |client| client.read_write_done(write_buffer, read_buffer, len, status));
+        true
+    }
+
+    fn start(
+        &self,
+        write_buffer: &'static mut [u8],
+        read_buffer: Option<&'static mut [u8]>,
+        len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8], Option<&'static mut [u8]>)> {
+        if self.write_buffer.is_some() {
+            return Err((ErrorCode::BUSY, write_buffer, read_buffer));
+        }
+        let len = match read_buffer.as_ref() {
+            Some(read_buffer) => len.min(write_buffer.len()).min(read_buffer.len()),
+            None => len.min(write_buffer.len()),
+        };
+        if len == 0 {
+            return Err((ErrorCode::INVAL, write_buffer, read_buffer));
+        }
+        self.transfers.borrow_mut().push(Transfer {
+            chip_select: self.chip_select.get(),
+            write: write_buffer[..len].to_vec(),
+            read: read_buffer.is_some(),
+        });
+        self.len.set(len);
+        self.write_buffer.replace(write_buffer);
+        if let Some(read_buffer) = read_buffer {
+            self.read_buffer.replace(read_buffer);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> SpiMaster<'a> for MockSpiMaster<'a> {
+    type ChipSelect = u8;
+
+    fn init(&self) -> Result<(), ErrorCode> {
+        Ok(())
+    }
+
+    fn set_client(&self, client: &'a dyn SpiMasterClient) {
+        self.client.set(client);
+    }
+
+    fn is_busy(&self) -> bool {
+        self.write_buffer.is_some()
+    }
+
+    fn read_write_bytes(
+        &self,
+        write_buffer: &'static mut [u8],
+        read_buffer: Option<&'static mut [u8]>,
+        len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8], Option<&'static mut [u8]>)> {
+        self.start(write_buffer, read_buffer, len)
+    }
+
+    fn write_byte(&self, _val: u8) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    fn read_byte(&self) -> Result<u8, ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    fn read_write_byte(&self, _val: u8) -> Result<u8, ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    fn specify_chip_select(&self, cs: u8) -> Result<(), ErrorCode> {
+        self.chip_select.set(cs);
+        Ok(())
+    }
+
+    fn set_rate(&self, rate: u32) -> Result<u32, ErrorCode> {
+        self.rate.set(rate);
+        Ok(rate)
+    }
+
+    fn get_rate(&self) -> u32 {
+        self.rate.get()
+    }
+
+    fn set_polarity(&self, polarity: ClockPolarity) -> Result<(), ErrorCode> {
+        self.polarity.set(polarity);
+        Ok(())
+    }
+
+    fn get_polarity(&self) -> ClockPolarity {
+        self.polarity.get()
+    }
+
+    fn set_phase(&self, phase: ClockPhase) -> Result<(), ErrorCode> {
+        self.phase.set(phase);
+        Ok(())
+    }
+
+    fn get_phase(&self) -> ClockPhase {
+        self.phase.get()
+    }
+
+    fn hold_low(&self) {
+        self.hold_low.set(true);
+    }
+
+    fn release_low(&self) {
+        self.hold_low.set(false);
+    }
+}
+
+impl<'a> SpiMasterDevice<'a> for MockSpiMaster<'a> {
+    fn set_client(&self, client: &'a dyn SpiMasterClient) {
+        self.client.set(client);
+    }
+
+    fn configure(&self, cpol: ClockPolarity, cpal: ClockPhase, rate: u32) -> Result<(), ErrorCode> {
+        self.polarity.set(cpol);
+        self.phase.set(cpal);
+        self.rate.set(rate);
+        Ok(())
+    }
+
+    fn read_write_bytes(
+        &self,
+        write_buffer: &'static mut [u8],
+        read_buffer: Option<&'static mut [u8]>,
+        len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8], Option<&'static mut [u8]>)> {
+        self.start(write_buffer, read_buffer, len)
+    }
+
+    fn set_rate(&self, rate: u32) -> Result<(), ErrorCode> {
+        self.rate.set(rate);
+        Ok(())
+    }
+
+    fn get_rate(&self) -> u32 {
+        self.rate.get()
+    }
+
+    fn set_polarity(&self, polarity: ClockPolarity) -> Result<(), ErrorCode> {
+        self.polarity.set(polarity);
+        Ok(())
+    }
+
+    fn get_polarity(&self) -> ClockPolarity {
+        self.polarity.get()
+    }
+
+    fn set_phase(&self, phase: ClockPhase) -> Result<(), ErrorCode> {
+        self.phase.set(phase);
+        Ok(())
+    }
+
+    fn get_phase(&self) -> ClockPhase {
+        self.phase.get()
+    }
+}
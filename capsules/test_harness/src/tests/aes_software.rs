@@ -0,0 +1,233 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Known answer tests from FIPS-197 Appendix C.1 and NIST SP 800-38A
+//! Appendix F.
+
+use core::cell::RefCell;
+
+use capsules_extra::symmetric_encryption::aes_fallback::Aes128Fallback;
+use capsules_extra::symmetric_encryption::aes_software::Aes128Software;
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
+use kernel::hil::symmetric_encryption::{AES128Ctr, Client, AES128, AES128CBC, AES128ECB};
+use kernel::ErrorCode;
+
+use crate::{deferred_call_lock, leak};
+
+const FIPS_197_KEY: [u8; 16] = [
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+];
+const FIPS_197_PLAINTEXT: [u8; 16] = [
+    0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff,
+];
+const FIPS_197_CIPHERTEXT: [u8; 16] = [
+    0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a,
+];
+
+const KEY: [u8; 16] = [
+    0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c,
+];
+const CBC_IV: [u8; 16] = [
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+];
+const CTR_COUNTER: [u8; 16] = [
+    0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa, 0xfb, 0xfc, 0xfd, 0xfe, 0xff,
+];
+const PLAINTEXT: [u8; 64] = [
+    0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a,
+    0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac, 0x45, 0xaf, 0x8e, 0x51,
+    0x30, 0xc8, 0x1c, 0x46, 0xa3, 0x5c, 0xe4, 0x11, 0xe5, 0xfb, 0xc1, 0x19, 0x1a, 0x0a, 0x52, 0xef,
+    0xf6, 0x9f, 0x24, 0x45, 0xdf, 0x4f, 0x9b, 0x17, 0xad, 0x2b, 0x41, 0x7b, 0xe6, 0x6c, 0x37, 0x10,
+];
+const ECB_CIPHERTEXT: [u8; 64] = [
+    0x3a, 0xd7, 0x7b, 0xb4, 0x0d, 0x7a, 0x36, 0x60, 0xa8, 0x9e, 0xca, 0xf3, 0x24, 0x66, 0xef, 0x97,
+    0xf5, 0xd3, 0xd5, 0x85, 0x03, 0xb9, 0x69, 0x9d, 0xe7, 0x85, 0x89, 0x5a, 0x96, 0xfd, 0xba, 0xaf,
+    0x43, 0xb1, 0xcd, 0x7f, 0x59, 0x8e, 0xce, 0x23, 0x88, 0x1b, 0x00, 0xe3, 0xed, 0x03, 0x06, 0x88,
+    0x7b, 0x0c, 0x78, 0x5e, 0x27, 0xe8, 0xad, 0x3f, 0x82, 0x23, 0x20, 0x71, 0x04, 0x72, 0x5d, 0xd4,
+];
+const CBC_CIPHERTEXT: [u8; 64] = [
+    0x76, 0x49, 0xab, 0xac, 0x81, 0x19, 0xb2, 0x46, 0xce, 0xe9, 0x8e, 0x9b, 0x12, 0xe9, 0x19, 0x7d,
+    0x50, 0x86, 0xcb, 0x9b, 0x50, 0x72, 0x19, 0xee, 0x95, 0xdb, 0x11, 0x3a, 0x91, 0x76, 0x78, 0xb2,
+    0x73, 0xbe, 0xd6, 0xb8, 0xe3, 0xc1, 0x74, 0x3b, 0x71, 0x16, 0xe6, 0x9e, 0x22, 0x22, 0x95, 0x16,
+    0x3f, 0xf1, 0xca, 0xa1, 0x68, 0x1f, 0xac, 0x09, 0x12, 0x0e, 0xca, 0x30, 0x75, 0x86, 0xe1, 0xa7,
+];
+const CTR_CIPHERTEXT: [u8; 64] = [
+    0x87, 0x4d, 0x61, 0x91, 0xb6, 0x20, 0xe3, 0x26, 0x1b, 0xef, 0x68, 0x64, 0x99, 0x0d, 0xb6, 0xce,
+    0x98, 0x06, 0xf6, 0x6b, 0x79, 0x70, 0xfd, 0xff, 0x86, 0x17, 0x18, 0x7b, 0xb9, 0xff, 0xfd, 0xff,
+    0x5a, 0xe4, 0xdf, 0x3e, 0xdb, 0xd5, 0xd3, 0x5e, 0x5b, 0x4f, 0x09, 0x02, 0x0d, 0xb0, 0x3e, 0xab,
+    0x1e, 0x03, 0x1d, 0xda, 0x2f, 0xbe, 0x03, 0xd1, 0x79, 0x21, 0x70, 0xa0, 0xf3, 0x00, 0x9c, 0xee,
+];
+
+/// Filler around the range crypted, which must not change.
+const FILLER: u8 = 0xa5;
+
+#[derive(Default)]
+struct Done {
+    source: RefCell<Option<Vec<u8>>>,
+    dest: RefCell<Option<Vec<u8>>>,
+}
+
+impl<'a> Client<'a> for Done {
+    fn crypt_done(&'a self, source: Option<&'static mut [u8]>, dest: &'static mut [u8]) {
+        *self.source.borrow_mut() = source.map(|source| source.to_vec());
+        *self.dest.borrow_mut() = Some(dest.to_vec());
+    }
+}
+
+fn setup(key: &[u8]) -> (&'static Aes128Software<'static>, &'static Done) {
+    let aes = leak(Aes128Software::new());
+    aes.register();
+    let done = leak(Done::default());
+    aes.set_client(done);
+    aes.set_key(key).unwrap();
+    (aes, done)
+}
+
+fn leak_buffer(data: &[u8]) -> &'static mut [u8] {
+    Box::leak(data.to_vec().into_boxed_slice())
+}
+
+/// Crypt `dest[start_index..stop_index]`, from `source` if any, and return
+/// the buffers passed back to the client.
+fn crypt(
+    aes: &dyn AES128<'static>,
+    done: &Done,
+    source: Option<&[u8]>,
+    dest: &[u8],
+    start_index: usize,
+    stop_index: usize,
+) -> (Option<Vec<u8>>, Vec<u8>) {
+    let result = aes.crypt(
+        source.map(leak_buffer),
+        leak_buffer(dest),
+        start_index,
+        stop_index,
+    );
+    assert!(result.is_none());
+    // The client is called back from a deferred call
+    assert!(done.dest.borrow().is_none());
+    while DeferredCall::service_next_pending().is_some() {}
+    (done.source.take(), done.dest.take().unwrap())
+}
+
+/// `data` between `before` and `after` bytes of filler.
+fn padded(before: usize, data: &[u8], after: usize) -> Vec<u8> {
+    let mut buffer = vec![FILLER; before];
+    buffer.extend_from_slice(data);
+    buffer.extend(vec![FILLER; after]);
+    buffer
+}
+
+#[test]
+fn fips_197_example_vector() {
+    let _lock = deferred_call_lock();
+    let (aes, done) = setup(&FIPS_197_KEY);
+
+    aes.set_mode_aes128ecb(true).unwrap();
+    let (_, ciphertext) = crypt(aes, done, None, &FIPS_197_PLAINTEXT, 0, 16);
+    assert_eq!(ciphertext, FIPS_197_CIPHERTEXT);
+
+    aes.set_mode_aes128ecb(false).unwrap();
+    let (_, plaintext) = crypt(aes, done, None, &FIPS_197_CIPHERTEXT, 0, 16);
+    assert_eq!(plaintext, FIPS_197_PLAINTEXT);
+}
+
+#[test]
+fn ecb_known_answers() {
+    let _lock = deferred_call_lock();
+    let (aes, done) = setup(&KEY);
+
+    aes.set_mode_aes128ecb(true).unwrap();
+    let (source, ciphertext) = crypt(aes, done, None, &PLAINTEXT, 0, 64);
+    assert_eq!(source, None);
+    assert_eq!(ciphertext, ECB_CIPHERTEXT);
+
+    // From a source buffer, which is passed back untouched
+    aes.set_mode_aes128ecb(false).unwrap();
+    let (source, plaintext) = crypt(aes, done, Some(&ECB_CIPHERTEXT), &[0; 64], 0, 64);
+    assert_eq!(source.as_deref(), Some(&ECB_CIPHERTEXT[..]));
+    assert_eq!(plaintext, PLAINTEXT);
+}
+
+#[test]
+fn cbc_known_answers() {
+    let _lock = deferred_call_lock();
+    let (aes, done) = setup(&KEY);
+    aes.set_iv(&CBC_IV).unwrap();
+
+    aes.set_mode_aes128cbc(true).unwrap();
+    aes.start_message();
+    let (_, ciphertext) = crypt(aes, done, None, &PLAINTEXT, 0, 64);
+    assert_eq!(ciphertext, CBC_CIPHERTEXT);
+
+    // In two parts of a buffer, chained across the calls
+    aes.set_mode_aes128cbc(false).unwrap();
+    aes.start_message();
+    let (_, first) = crypt(aes, done, None, &padded(16, &CBC_CIPHERTEXT, 16), 16, 48);
+    assert_eq!(
+        first,
+        padded(16, &[&PLAINTEXT[..32], &CBC_CIPHERTEXT[32..]].concat(), 16)
+    );
+    let (_, second) = crypt(aes, done, None, &first, 48, 80);
+    assert_eq!(second, padded(16, &PLAINTEXT, 16));
+
+    // A new message starts from the IV again
+    aes.start_message();
+    let (_, again) = crypt(aes, done, Some(&CBC_CIPHERTEXT[..16]), &[0; 16], 0, 16);
+    assert_eq!(again, PLAINTEXT[..16]);
+}
+
+#[test]
+fn ctr_known_answers() {
+    let _lock = deferred_call_lock();
+    let (aes, done) = setup(&KEY);
+    aes.set_iv(&CTR_COUNTER).unwrap();
+
+    // From the start of the source to the middle of the destination
+    aes.set_mode_aes128ctr(true).unwrap();
+    aes.start_message();
+    let (source, ciphertext) = crypt(aes, done, Some(&PLAINTEXT), &padded(0, &[], 96), 16, 80);
+    assert_eq!(source.as_deref(), Some(&PLAINTEXT[..]));
+    assert_eq!(ciphertext, padded(16, &CTR_CIPHERTEXT, 16));
+
+    aes.set_mode_aes128ctr(false).unwrap();
+    aes.start_message();
+    let (_, plaintext) = crypt(aes, done, None, &CTR_CIPHERTEXT, 0, 64);
+    assert_eq!(plaintext, PLAINTEXT);
+}
+
+#[test]
+fn fallback_uses_the_engine_selected() {
+    let _lock = deferred_call_lock();
+    let software = leak(Aes128Software::new());
+    software.register();
+    let aes = leak(Aes128Fallback::new(software, false));
+    assert!(!aes.is_hardware());
+    let done = leak(Done::default());
+    aes.set_client(done);
+    aes.set_key(&KEY).unwrap();
+
+    aes.set_mode_aes128ecb(true).unwrap();
+    let (_, ciphertext) = crypt(aes, done, None, &PLAINTEXT, 0, 64);
+    assert_eq!(ciphertext, ECB_CIPHERTEXT);
+
+    aes.set_mode_aes128ctr(true).unwrap();
+    aes.set_iv(&CTR_COUNTER).unwrap();
+    aes.start_message();
+    let (_, ciphertext) = crypt(aes, done, None, &PLAINTEXT, 0, 64);
+    assert_eq!(ciphertext, CTR_CIPHERTEXT);
+}
+
+#[test]
+fn partial_blocks_are_rejected() {
+    let aes = Aes128Software::new();
+    aes.set_key(&KEY).unwrap();
+    aes.set_mode_aes128ecb(true).unwrap();
+    let result = aes.crypt(None, leak_buffer(&[0; 32]), 0, 24);
+    assert!(matches!(result, Some((Err(ErrorCode::INVAL), None, _))));
+    let result = aes.crypt(None, leak_buffer(&[0; 32]), 16, 48);
+    assert!(matches!(result, Some((Err(ErrorCode::INVAL), None, _))));
+    let result = aes.crypt(Some(leak_buffer(&[0; 16])), leak_buffer(&[0; 32]), 0, 32);
+    assert!(matches!(result, Some((Err(ErrorCode::INVAL), Some(_), _))));
+}
@@ -0,0 +1,28 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use capsules_extra::analog_sensor::{DividerPosition, Photoresistor};
+
+/// A GL5528 with a 10 kOhm fixed resistor.
+const LOW_SIDE: Photoresistor = Photoresistor::new(DividerPosition::LowSide, 10_000, 15_000, 700);
+const HIGH_SIDE: Photoresistor = Photoresistor::new(DividerPosition::HighSide, 10_000, 15_000, 700);
+
+#[test]
+fn photoresistor_follows_its_curve() {
+    // Half the supply: the photoresistor matches the fixed resistor
+    assert_eq!(LOW_SIDE.lux(32768), 17);
+    assert_eq!(HIGH_SIDE.lux(32768), 17);
+
+    assert_eq!(LOW_SIDE.lux(16384), 85);
+    assert_eq!(HIGH_SIDE.lux(16384), 3);
+    assert_eq!(LOW_SIDE.lux(2000), 2496);
+    assert_eq!(HIGH_SIDE.lux(60000), 537);
+}
+
+#[test]
+fn photoresistor_saturates() {
+    assert_eq!(LOW_SIDE.lux(0), u32::MAX as usize);
+    assert_eq!(HIGH_SIDE.lux(0), 0);
+    assert_eq!(LOW_SIDE.lux(u16::MAX), 0);
+}
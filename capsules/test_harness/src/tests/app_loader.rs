@@ -0,0 +1,341 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::{Cell, RefCell};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+
+use capsules_extra::app_loader::{verify, AppLoadError, AppLoader};
+use kernel::hil::flash::Flash;
+use kernel::process::{ProcessLoadError, ProcessLoader};
+use kernel::process_checker::{AppCredentialsChecker, CheckResult, Client};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+use tock_tbf::types::{TbfFooterV2Credentials, TbfFooterV2CredentialsType, TbfParseError};
+
+use crate::leak;
+
+const HEADER_LENGTH: usize = 40;
+const BINARY_END: usize = 64;
+
+/// Stand-in for a SHA-256 digest of `binary`.
+fn digest(binary: &[u8]) -> [u8; 32] {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(binary);
+    let mut digest = [0; 32];
+    for chunk in digest.chunks_mut(8) {
+        chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+    }
+    digest
+}
+
+/// A credentials checker accepting the SHA-256 footers which match the
+/// `digest` of the binary, once the test calls `complete`.
+struct MockChecker {
+    client: OptionalCell<&'static dyn Client<'static>>,
+    pending: Cell<Option<(TbfFooterV2Credentials, &'static [u8])>>,
+}
+
+impl MockChecker {
+    /// Complete the check in progress. Returns `false` if there is none.
+    fn complete(&self) -> bool {
+        match self.pending.take() {
+            Some((credentials, binary)) => {
+                let result = if credentials.data() == digest(binary) {
+                    CheckResult::Accept
+                } else {
+                    CheckResult::Reject
+                };
+                self.client
+                    .map(|client| client.check_done(Ok(result), credentials, binary));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl AppCredentialsChecker<'static> for MockChecker {
+    fn set_client(&self, client: &'static dyn Client<'static>) {
+        self.client.set(client);
+    }
+
+    fn require_credentials(&self) -> bool {
+        true
+    }
+
+    fn check_credentials(
+        &self,
+        credentials: TbfFooterV2Credentials,
+        binary: &'static [u8],
+    ) -> Result<(), (ErrorCode, TbfFooterV2Credentials, &'static [u8])> {
+        if credentials.format() != TbfFooterV2CredentialsType::SHA256 {
+            return Err((ErrorCode::NOSUPPORT, credentials, binary));
+        }
+        self.pending.set(Some((credentials, binary)));
+        Ok(())
+    }
+}
+
+/// A process loader logging the addresses of the images it loads.
+#[derive(Default)]
+struct MockProcessLoader {
+    loaded: RefCell<Vec<usize>>,
+}
+
+impl ProcessLoader for MockProcessLoader {
+    fn load_process(&self, app_flash: &'static [u8]) -> Result<(), ProcessLoadError> {
+        self.loaded.borrow_mut().push(app_flash.as_ptr() as usize);
+        Ok(())
+    }
+}
+
+/// Flash the tests never write to, since they load images from memory.
+struct NoFlash;
+
+impl Flash for NoFlash {
+    type Page = [u8; 32];
+
+    fn read_page(
+        &self,
+        _page_number: usize,
+        buf: &'static mut Self::Page,
+    ) -> Result<(), (ErrorCode, &'static mut Self::Page)> {
+        Err((ErrorCode::NOSUPPORT, buf))
+    }
+
+    fn write_page(
+        &self,
+        _page_number: usize,
+        buf: &'static mut Self::Page,
+    ) -> Result<(), (ErrorCode, &'static mut Self::Page)> {
+        Err((ErrorCode::NOSUPPORT, buf))
+    }
+
+    fn erase_page(&self, _page_number: usize) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+}
+
+struct Setup {
+    checker: &'static MockChecker,
+    process_loader: &'static MockProcessLoader,
+    app_loader: &'static AppLoader<'static, NoFlash>,
+}
+
+impl Setup {
+    fn new() -> Setup {
+        let checker = leak(MockChecker {
+            client: OptionalCell::empty(),
+            pending: Cell::new(None),
+        });
+        let process_loader = leak(MockProcessLoader::default());
+        let app_loader = leak(AppLoader::new(
+            leak(NoFlash),
+            0,
+            &[],
+            process_loader,
+            checker,
+            Box::leak(Box::new([0; 32])),
+        ));
+        checker.set_client(app_loader);
+        Setup {
+            checker,
+            process_loader,
+            app_loader,
+        }
+    }
+
+    /// Load `image`, completing every check, and return the result.
+    fn load(&self, image: Vec<u8>) -> Result<(), AppLoadError> {
+        let image = &leak(image)[..];
+        assert_eq!(self.app_loader.load(image), Ok(()));
+        while self.checker.complete() {}
+        let result = self.app_loader.result().unwrap();
+        let loaded = self.process_loader.loaded.take();
+        assert_eq!(
+            loaded,
+            result.map_or(vec![], |()| vec![image.as_ptr() as usize])
+        );
+        result
+    }
+}
+
+/// A TBF object with a Program header, a binary of `0xA5` bytes and
+/// credentials footers of `formats`. Those of SHA-256 hold the digest of the
+/// binary, the others are empty.
+fn build(formats: &[u32]) -> Vec<u8> {
+    let footers_length: usize = formats
+        .iter()
+        .map(|format| if *format == 3 { 40 } else { 8 })
+        .sum();
+    let mut image = Vec::new();
+    image.extend(2u16.to_le_bytes());
+    image.extend((HEADER_LENGTH as u16).to_le_bytes());
+    image.extend(((BINARY_END + footers_length) as u32).to_le_bytes());
+    // Enabled.
+    image.extend(1u32.to_le_bytes());
+    // Checksum, set below.
+    image.extend(0u32.to_le_bytes());
+    // Program: type 9, length 20, then the init offset, protected size, RAM,
+    // end of the binary and version.
+    image.extend(9u16.to_le_bytes());
+    image.extend(20u16.to_le_bytes());
+    image.extend((HEADER_LENGTH as u32).to_le_bytes());
+    image.extend(0u32.to_le_bytes());
+    image.extend(4096u32.to_le_bytes());
+    image.extend((BINARY_END as u32).to_le_bytes());
+    image.extend(1u32.to_le_bytes());
+    image.resize(BINARY_END, 0xA5);
+    set_checksum(&mut image);
+    let digest = digest(&image);
+    for format in formats {
+        let data: &[u8] = if *format == 3 { &digest } else { &[] };
+        image.extend(128u16.to_le_bytes());
+        image.extend((4 + data.len() as u16).to_le_bytes());
+        image.extend(format.to_le_bytes());
+        image.extend(data);
+    }
+    image
+}
+
+/// An image with a SHA-256 footer.
+fn image() -> Vec<u8> {
+    build(&[3])
+}
+
+fn set_checksum(image: &mut [u8]) {
+    let checksum = image[..HEADER_LENGTH]
+        .chunks_exact(4)
+        .enumerate()
+        .filter(|(i, _)| *i != 3)
+        .fold(0, |checksum, (_, word)| {
+            checksum ^ u32::from_le_bytes(word.try_into().unwrap())
+        });
+    image[12..16].copy_from_slice(&checksum.to_le_bytes());
+}
+
+#[test]
+fn valid_image_is_loaded() {
+    let setup = Setup::new();
+    assert!(setup.load(image()).is_ok());
+    assert_eq!(verify(&leak(image())[..]).unwrap(), BINARY_END);
+
+    // A footer the checker does not support comes first.
+    assert!(setup.load(build(&[0, 3])).is_ok());
+}
+
+#[test]
+fn corrupt_binary_is_rejected() {
+    let setup = Setup::new();
+    // A bit flipped in the binary, after the header, or in the footer.
+    for offset in [HEADER_LENGTH + 5, BINARY_END - 1, BINARY_END + 20] {
+        let mut corrupted = image();
+        corrupted[offset] ^= 0x10;
+        assert!(
+            matches!(
+                setup.load(corrupted),
+                Err(AppLoadError::Load(ProcessLoadError::CredentialsReject(0)))
+            ),
+            "byte {offset}"
+        );
+    }
+
+    // The rejected footer is the second one.
+    let mut corrupted = build(&[0, 3]);
+    corrupted[HEADER_LENGTH] ^= 0x10;
+    assert!(matches!(
+        setup.load(corrupted),
+        Err(AppLoadError::Load(ProcessLoadError::CredentialsReject(1)))
+    ));
+}
+
+#[test]
+fn image_without_credentials_is_rejected() {
+    let setup = Setup::new();
+    // Without footers, or with only a footer the checker does not support.
+    for formats in [&[][..], &[0]] {
+        assert!(matches!(
+            setup.load(build(formats)),
+            Err(AppLoadError::Load(ProcessLoadError::CredentialsNoAccept))
+        ));
+    }
+}
+
+#[test]
+fn corrupt_header_is_rejected() {
+    let setup = Setup::new();
+    // A bit flipped in the flags, in the Program header, or in the checksum.
+    for offset in [8, 24, 32, 12] {
+        let mut corrupted = image();
+        corrupted[offset] ^= 0x01;
+        assert!(
+            matches!(
+                setup.load(corrupted),
+                Err(AppLoadError::Load(ProcessLoadError::TbfHeaderParseFailure(
+                    TbfParseError::ChecksumMismatch(..)
+                )))
+            ),
+            "byte {offset}"
+        );
+    }
+
+    // A bad Program header with a matching checksum is still rejected.
+    let mut corrupted = image();
+    corrupted[18] = 8;
+    set_checksum(&mut corrupted);
+    assert!(matches!(
+        setup.load(corrupted),
+        Err(AppLoadError::Load(ProcessLoadError::TbfHeaderParseFailure(
+            TbfParseError::BadTlvEntry(9)
+        )))
+    ));
+}
+
+#[test]
+fn lengths_are_checked() {
+    let setup = Setup::new();
+    let total_length = image().len() as u32;
+    // The image is shorter or longer than the header says.
+    let mut truncated = image();
+    truncated.truncate(BINARY_END);
+    assert!(matches!(
+        setup.load(truncated),
+        Err(AppLoadError::Length(length)) if length == total_length
+    ));
+    let mut extended = image();
+    extended.extend([0; 4]);
+    assert!(matches!(
+        setup.load(extended),
+        Err(AppLoadError::Length(length)) if length == total_length
+    ));
+
+    // The binary ends past the image.
+    let mut binary_end = image();
+    binary_end[32..36].copy_from_slice(&(total_length + 4).to_le_bytes());
+    set_checksum(&mut binary_end);
+    assert!(matches!(
+        setup.load(binary_end),
+        Err(AppLoadError::Load(ProcessLoadError::NotEnoughFlash))
+    ));
+
+    // Too short for the lengths, an unknown version, or a header shorter than
+    // its base.
+    assert!(matches!(
+        setup.load(image()[..6].to_vec()),
+        Err(AppLoadError::Load(ProcessLoadError::TbfHeaderNotFound))
+    ));
+    let mut version = image();
+    version[0] = 3;
+    assert!(matches!(
+        setup.load(version),
+        Err(AppLoadError::Load(ProcessLoadError::TbfHeaderNotFound))
+    ));
+    let mut header_length = image();
+    header_length[2] = 12;
+    assert!(matches!(
+        setup.load(header_length),
+        Err(AppLoadError::Load(ProcessLoadError::TbfHeaderNotFound))
+    ));
+}
@@ -0,0 +1,289 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::{Cell, RefCell};
+
+use capsules_extra::at24c_eeprom::{self, AT24C};
+use kernel::hil::i2c::Error;
+use kernel::hil::nonvolatile_storage::{NonvolatileStorage, NonvolatileStorageClient};
+use kernel::hil::time::Alarm;
+use kernel::ErrorCode;
+
+use crate::alarm::MockAlarm;
+use crate::i2c::{MockI2CDevice, Transfer};
+use crate::{leak, static_buffer};
+
+type Driver = AT24C<'static, MockAlarm<'static>, MockI2CDevice<'static>>;
+
+/// An EEPROM answering the transfers of the driver. Writes wrap around
+/// within a page, as on real chips, and the EEPROM ignores its address while
+/// it commits a page.
+struct Eeprom {
+    memory: RefCell<Vec<u8>>,
+    page_size: usize,
+    address_len: usize,
+    /// Polls left before the page being committed is written
+    write_cycle: Cell<usize>,
+}
+
+impl Eeprom {
+    fn new(size: usize, page_size: usize) -> Eeprom {
+        Eeprom {
+            memory: RefCell::new((0..size).map(|i| i as u8).collect()),
+            page_size,
+            address_len: if size > 256 { 2 } else { 1 },
+            write_cycle: Cell::new(0),
+        }
+    }
+
+    fn address(&self, bytes: &[u8]) -> usize {
+        bytes[..self.address_len]
+            .iter()
+            .fold(0, |address, &byte| address << 8 | byte as usize)
+    }
+
+    /// Queue the answer of the EEPROM to `transfer` on `i2c`.
+    fn answer(&self, i2c: &MockI2CDevice, transfer: &Transfer) {
+        if self.write_cycle.get() > 0 {
+            self.write_cycle.set(self.write_cycle.get() - 1);
+            i2c.queue_error(Error::AddressNak);
+            return;
+        }
+        match transfer {
+            Transfer::WriteRead(write, len) => {
+                let address = self.address(write);
+                i2c.queue_response(&self.memory.borrow()[address..address + len]);
+            }
+            Transfer::Write(write) if write.len() > self.address_len => {
+                let address = self.address(write);
+                let page = address - address % self.page_size;
+                let mut memory = self.memory.borrow_mut();
+                for (offset, &byte) in write[self.address_len..].iter().enumerate() {
+                    memory[page + (address + offset) % self.page_size] = byte;
+                }
+                self.write_cycle.set(3);
+                i2c.queue_response(&[]);
+            }
+            _ => i2c.queue_response(&[]),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Client {
+    reads: RefCell<Vec<Vec<u8>>>,
+    writes: RefCell<Vec<usize>>,
+}
+
+impl NonvolatileStorageClient for Client {
+    fn read_done(&self, buffer: &'static mut [u8], length: usize) {
+        self.reads.borrow_mut().push(buffer[..length].to_vec());
+    }
+
+    fn write_done(&self, _buffer: &'static mut [u8], length: usize) {
+        self.writes.borrow_mut().push(length);
+    }
+}
+
+struct Setup {
+    i2c: &'static MockI2CDevice<'static>,
+    alarm: &'static MockAlarm<'static>,
+    driver: &'static Driver,
+    eeprom: Eeprom,
+    client: &'static Client,
+    transfers: RefCell<Vec<Transfer>>,
+}
+
+impl Setup {
+    /// Answer the transfers of the driver, and let its polls expire, until
+    /// it starts nothing more. The transfers are kept for `take_transfers`.
+    fn run(&self) {
+        loop {
+            let transfers = self.i2c.take_transfers();
+            if let Some(transfer) = transfers.first() {
+                self.eeprom.answer(self.i2c, transfer);
+                self.transfers.borrow_mut().push(transfer.clone());
+                self.i2c.complete();
+            } else if let Some(ticks) = self.alarm.remaining() {
+                self.alarm.advance(ticks);
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// The transfers answered since the last call, without the polls which
+    /// only address the EEPROM.
+    fn take_transfers(&self) -> Vec<Transfer> {
+        let is_poll = |transfer: &Transfer| match transfer {
+            Transfer::Write(write) => write.len() == self.eeprom.address_len,
+            _ => false,
+        };
+        self.transfers
+            .take()
+            .into_iter()
+            .filter(|transfer| !is_poll(transfer))
+            .collect()
+    }
+}
+
+/// An AT24C64: 8 KiB in 32-byte pages.
+fn setup() -> Setup {
+    setup_eeprom(8192, 32)
+}
+
+fn setup_eeprom(size: usize, page_size: usize) -> Setup {
+    let i2c = leak(MockI2CDevice::new());
+    let alarm = leak(MockAlarm::new());
+    let driver = leak(AT24C::new(
+        i2c,
+        alarm,
+        static_buffer(at24c_eeprom::BUF_LEN),
+        size,
+        page_size,
+    ));
+    i2c.set_client(driver);
+    alarm.set_alarm_client(driver);
+    let client = leak(Client::default());
+    NonvolatileStorage::set_client(driver, client);
+    Setup {
+        i2c,
+        alarm,
+        driver,
+        eeprom: Eeprom::new(size, page_size),
+        client,
+        transfers: RefCell::new(Vec::new()),
+    }
+}
+
+fn write_bytes(address: &[u8], data: impl IntoIterator<Item = u8>) -> Transfer {
+    let mut bytes = address.to_vec();
+    bytes.extend(data);
+    Transfer::Write(bytes)
+}
+
+#[test]
+fn reads_with_a_two_byte_address() {
+    let s = setup();
+    assert_eq!(s.driver.read(static_buffer(16), 0x123, 10), Ok(()));
+    s.run();
+
+    assert_eq!(
+        s.take_transfers(),
+        [Transfer::WriteRead(vec![0x01, 0x23], 10)]
+    );
+    let expected: Vec<u8> = (0x123..0x12D).map(|i| i as u8).collect();
+    assert_eq!(*s.client.reads.borrow(), [expected]);
+    assert!(!s.i2c.is_enabled());
+}
+
+#[test]
+fn small_eeproms_use_a_one_byte_address() {
+    let s = setup_eeprom(256, 8);
+    assert_eq!(s.driver.read(static_buffer(4), 0xF0, 4), Ok(()));
+    s.run();
+    assert_eq!(s.take_transfers(), [Transfer::WriteRead(vec![0xF0], 4)]);
+    assert_eq!(*s.client.reads.borrow(), [vec![0xF0, 0xF1, 0xF2, 0xF3]]);
+}
+
+#[test]
+fn long_reads_are_split_at_the_buffer_length() {
+    let s = setup();
+    assert_eq!(s.driver.read(static_buffer(100), 0x1000, 100), Ok(()));
+    s.run();
+
+    assert_eq!(
+        s.take_transfers(),
+        [
+            Transfer::WriteRead(vec![0x10, 0x00], at24c_eeprom::BUF_LEN),
+            Transfer::WriteRead(vec![0x10, 0x42], 100 - at24c_eeprom::BUF_LEN),
+        ]
+    );
+    let expected: Vec<u8> = (0x1000..0x1064).map(|i| i as u8).collect();
+    assert_eq!(*s.client.reads.borrow(), [expected]);
+}
+
+#[test]
+fn writes_are_split_at_page_boundaries() {
+    let s = setup();
+    let buffer = static_buffer(40);
+    for (i, byte) in buffer.iter_mut().enumerate() {
+        *byte = 0x80 | i as u8;
+    }
+    assert_eq!(s.driver.write(buffer, 0x1C, 40), Ok(()));
+    s.run();
+
+    // The ends of the first and last pages, and the whole page between
+    assert_eq!(
+        s.take_transfers(),
+        [
+            write_bytes(&[0x00, 0x1C], 0x80..0x84),
+            write_bytes(&[0x00, 0x20], 0x84..0xA4),
+            write_bytes(&[0x00, 0x40], 0xA4..0xA8),
+        ]
+    );
+    assert_eq!(*s.client.writes.borrow(), [40]);
+    let memory = s.eeprom.memory.borrow();
+    let expected: Vec<u8> = (0x80..0xA8).collect();
+    assert_eq!(memory[0x1C..0x44], expected);
+    // The bytes around the write are unchanged
+    assert_eq!((memory[0x1B], memory[0x44]), (0x1B, 0x44));
+}
+
+#[test]
+fn write_waits_for_the_write_cycle_of_each_page() {
+    let s = setup();
+    assert_eq!(s.driver.write(static_buffer(8), 0x1E, 8), Ok(()));
+
+    // First page
+    s.i2c.take_transfers();
+    s.i2c.complete();
+    assert!(s.i2c.take_transfers().is_empty());
+    // The EEPROM does not answer while it commits the page
+    s.alarm.advance(1);
+    assert_eq!(s.i2c.take_transfers(), [Transfer::Write(vec![0x00, 0x20])]);
+    s.i2c.queue_error(Error::AddressNak);
+    s.i2c.complete();
+    assert!(s.i2c.take_transfers().is_empty());
+    s.alarm.advance(1);
+    assert_eq!(s.i2c.take_transfers(), [Transfer::Write(vec![0x00, 0x20])]);
+    s.i2c.complete();
+    // Second page, once the EEPROM answers again
+    assert_eq!(s.i2c.take_transfers(), [write_bytes(&[0x00, 0x20], [0; 6])]);
+    assert!(s.client.writes.borrow().is_empty());
+}
+
+#[test]
+fn write_fails_when_the_eeprom_never_answers() {
+    let s = setup();
+    s.eeprom.write_cycle.set(usize::MAX);
+    assert_eq!(s.driver.write(static_buffer(40), 0x1C, 40), Ok(()));
+    s.i2c.take_transfers();
+    s.i2c.complete();
+    s.run();
+
+    // Only the first page was written
+    assert_eq!(*s.client.writes.borrow(), [4]);
+    assert!(!s.i2c.is_enabled());
+}
+
+#[test]
+fn rejects_operations_out_of_the_eeprom() {
+    let s = setup();
+    assert_eq!(
+        s.driver.read(static_buffer(16), 8190, 4),
+        Err(ErrorCode::INVAL)
+    );
+    assert_eq!(
+        s.driver.write(static_buffer(16), 0, 0),
+        Err(ErrorCode::INVAL)
+    );
+    assert_eq!(
+        s.driver.write(static_buffer(4), 0, 8),
+        Err(ErrorCode::INVAL)
+    );
+
+    assert_eq!(s.driver.read(static_buffer(4), 0, 4), Ok(()));
+    assert_eq!(s.driver.write(static_buffer(4), 0, 4), Err(ErrorCode::BUSY));
+}
@@ -0,0 +1,235 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::{Cell, RefCell};
+
+use capsules_extra::at_engine::{self, AtClient, AtEngine, BUFFER_LEN, LINE_LEN};
+use kernel::hil::time::Alarm;
+use kernel::hil::uart::{Receive, Transmit};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+use crate::alarm::MockAlarm;
+use crate::leak;
+use crate::uart::MockUart;
+
+type Engine = AtEngine<'static, MockUart<'static>, MockAlarm<'static>>;
+
+/// A module client recording what the engine dispatches. `+CMTI` and `RING`
+/// are its URCs, and `+QIRD: <len>` announces raw data.
+#[derive(Default)]
+struct Client {
+    engine: OptionalCell<&'static Engine>,
+    urcs: RefCell<Vec<Vec<u8>>>,
+    responses: RefCell<Vec<Vec<u8>>>,
+    prompts: Cell<usize>,
+    data: RefCell<Vec<u8>>,
+    results: RefCell<Vec<Result<(), ErrorCode>>>,
+}
+
+impl AtClient for Client {
+    fn unsolicited(&self, line: &[u8]) -> bool {
+        let urc = line.starts_with(b"+CMTI:") || line == b"RING";
+        if urc {
+            self.urcs.borrow_mut().push(line.to_vec());
+        }
+        urc
+    }
+
+    fn response(&self, line: &[u8]) -> Option<Result<(), ErrorCode>> {
+        self.responses.borrow_mut().push(line.to_vec());
+        match at_engine::parse_number(line, b"+QIRD: ", 0) {
+            Some(len) => {
+                self.engine.map(|engine| engine.receive_data(len));
+                None
+            }
+            None => at_engine::result_code(line),
+        }
+    }
+
+    fn prompt(&self) {
+        self.prompts.set(self.prompts.get() + 1);
+        self.engine.map(|engine| {
+            engine.send_data(|buffer| {
+                buffer[..5].copy_from_slice(b"hello");
+                Ok(5)
+            })
+        });
+    }
+
+    fn data_received(&self, byte: u8) {
+        self.data.borrow_mut().push(byte);
+    }
+
+    fn command_done(&self, result: Result<(), ErrorCode>) {
+        self.results.borrow_mut().push(result);
+    }
+}
+
+fn setup() -> (
+    &'static MockUart<'static>,
+    &'static MockAlarm<'static>,
+    &'static Engine,
+    &'static Client,
+) {
+    let uart = leak(MockUart::new());
+    let alarm = leak(MockAlarm::new());
+    let engine = leak(AtEngine::new(
+        uart,
+        alarm,
+        115200,
+        Box::leak(Box::new([0; 1])),
+        Box::leak(Box::new([0; LINE_LEN])),
+        Box::leak(Box::new([0; BUFFER_LEN])),
+    ));
+    uart.set_transmit_client(engine);
+    uart.set_receive_client(engine);
+    alarm.set_alarm_client(engine);
+    let client = leak(Client::default());
+    client.engine.set(engine);
+    engine.set_client(client);
+    assert_eq!(engine.initialize(), Ok(()));
+    (uart, alarm, engine, client)
+}
+
+fn send(engine: &Engine, command: &[u8], timeout_ms: u32) -> Result<(), ErrorCode> {
+    engine.command(timeout_ms, |writer| writer.push(command))
+}
+
+fn lines(lines: &[&[u8]]) -> Vec<Vec<u8>> {
+    lines.iter().map(|line| line.to_vec()).collect()
+}
+
+#[test]
+fn command_ends_with_its_final_result() {
+    let (uart, alarm, engine, client) = setup();
+    assert_eq!(uart.parameters().map(|p| p.baud_rate), Some(115200));
+
+    assert_eq!(send(engine, b"AT+CSQ", 1000), Ok(()));
+    assert_eq!(uart.take_transmitted(), b"AT+CSQ\r");
+    assert!(uart.complete_transmit());
+    assert!(engine.is_busy());
+
+    // The echo is dropped
+    uart.receive(b"AT+CSQ\r\r\n+CSQ: 20,99\r\n\r\nOK\r\n");
+    assert_eq!(*client.responses.borrow(), lines(&[b"+CSQ: 20,99", b"OK"]));
+    assert_eq!(*client.results.borrow(), [Ok(())]);
+    assert!(!engine.is_busy());
+    assert!(!alarm.is_armed());
+}
+
+#[test]
+fn error_result_codes_fail_the_command() {
+    let (uart, _, engine, client) = setup();
+    send(engine, b"AT+CPIN?", 1000).unwrap();
+    uart.complete_transmit();
+    uart.receive(b"\r\n+CME ERROR: 10\r\n");
+    assert_eq!(*client.results.borrow(), [Err(ErrorCode::FAIL)]);
+}
+
+#[test]
+fn commands_time_out() {
+    let (uart, alarm, engine, client) = setup();
+    send(engine, b"AT+CGATT=1", 5000).unwrap();
+    uart.complete_transmit();
+
+    alarm.advance(4999);
+    assert!(client.results.borrow().is_empty());
+    alarm.advance(1);
+    assert_eq!(*client.results.borrow(), [Err(ErrorCode::NOACK)]);
+
+    // A late result ends nothing
+    uart.receive(b"\r\nOK\r\n");
+    assert_eq!(client.results.borrow().len(), 1);
+    assert!(client.responses.borrow().is_empty());
+}
+
+#[test]
+fn urcs_are_dispatched_at_any_time() {
+    let (uart, _, engine, client) = setup();
+    uart.receive(b"\r\n+CMTI: \"SM\",3\r\n");
+    assert_eq!(*client.urcs.borrow(), lines(&[b"+CMTI: \"SM\",3"]));
+
+    send(engine, b"AT", 1000).unwrap();
+    uart.complete_transmit();
+    uart.receive(b"\r\nRING\r\n\r\nOK\r\n");
+    assert_eq!(*client.urcs.borrow(), lines(&[b"+CMTI: \"SM\",3", b"RING"]));
+    assert_eq!(*client.responses.borrow(), lines(&[b"OK"]));
+    assert_eq!(*client.results.borrow(), [Ok(())]);
+}
+
+#[test]
+fn one_command_runs_at_a_time() {
+    let (uart, _, engine, client) = setup();
+    send(engine, b"ATI", 1000).unwrap();
+    uart.complete_transmit();
+    assert_eq!(send(engine, b"AT+GSN", 1000), Err(ErrorCode::BUSY));
+    assert_eq!(uart.take_transmitted(), b"ATI\r");
+
+    uart.receive(b"\r\nOK\r\n");
+    assert_eq!(send(engine, b"AT+GSN", 1000), Ok(()));
+    assert_eq!(uart.take_transmitted(), b"AT+GSN\r");
+    assert_eq!(*client.results.borrow(), [Ok(())]);
+}
+
+#[test]
+fn prompt_asks_for_the_data() {
+    let (uart, _, engine, client) = setup();
+    send(engine, b"AT+QISEND=0,5", 1000).unwrap();
+    uart.complete_transmit();
+    uart.take_transmitted();
+
+    uart.receive(b"\r\n> ");
+    assert_eq!(client.prompts.get(), 1);
+    assert_eq!(uart.take_transmitted(), b"hello");
+    uart.complete_transmit();
+
+    uart.receive(b"\r\nSEND OK\r\n\r\nOK\r\n");
+    assert_eq!(*client.responses.borrow(), lines(&[b"SEND OK", b"OK"]));
+    assert_eq!(*client.results.borrow(), [Ok(())]);
+}
+
+#[test]
+fn raw_data_follows_its_header() {
+    let (uart, _, engine, client) = setup();
+    send(engine, b"AT+QIRD=0,512", 1000).unwrap();
+    uart.complete_transmit();
+
+    // Data which looks like a result code is still data
+    uart.receive(b"\r\n+QIRD: 6\r\nOK\r\n>\n\r\nOK\r\n");
+    assert_eq!(*client.data.borrow(), b"OK\r\n>\n");
+    assert_eq!(client.prompts.get(), 0);
+    assert_eq!(*client.results.borrow(), [Ok(())]);
+}
+
+#[test]
+fn long_lines_are_truncated() {
+    let (uart, _, engine, client) = setup();
+    send(engine, b"AT+CGDCONT?", 1000).unwrap();
+    uart.complete_transmit();
+    uart.receive(&[b'x'; LINE_LEN + 10]);
+    uart.receive(b"\r\nOK\r\n");
+    assert_eq!(
+        *client.responses.borrow(),
+        [vec![b'x'; LINE_LEN], b"OK".to_vec()]
+    );
+}
+
+#[test]
+fn commands_longer_than_the_buffer_are_refused() {
+    let (uart, _, engine, _) = setup();
+    let command = [b'A'; BUFFER_LEN];
+    assert_eq!(send(engine, &command, 1000), Err(ErrorCode::SIZE));
+    assert!(!engine.is_busy());
+    assert!(uart.take_transmitted().is_empty());
+}
+
+#[test]
+fn numbers_are_parsed_after_their_prefix() {
+    let line = b"+CIPRXGET: 2,12,0";
+    assert_eq!(at_engine::parse_number(line, b"+CIPRXGET: 2,", 0), Some(12));
+    assert_eq!(at_engine::parse_number(line, b"+CIPRXGET: 2,", 1), Some(0));
+    assert_eq!(at_engine::parse_number(line, b"+CIPRXGET: 2,", 2), None);
+    assert_eq!(at_engine::parse_number(line, b"+QIRD: ", 0), None);
+}
@@ -0,0 +1,153 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::Cell;
+
+use capsules_extra::auto_brightness::{self, AutoBrightness, LevelSelector, DEFAULT_PERIOD_MS};
+use kernel::hil::pwm::PwmPin;
+use kernel::hil::sensors::{AmbientLight, AmbientLightClient};
+use kernel::hil::time::Alarm;
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+use crate::alarm::MockAlarm;
+use crate::{create_grant, leak};
+
+#[derive(Default)]
+struct Sensor {
+    client: OptionalCell<&'static dyn AmbientLightClient>,
+    reads: Cell<usize>,
+}
+
+impl Sensor {
+    fn complete(&self, lux: usize) {
+        self.client.map(|client| client.callback(lux));
+    }
+}
+
+impl AmbientLight<'static> for Sensor {
+    fn set_client(&self, client: &'static dyn AmbientLightClient) {
+        self.client.set(client);
+    }
+
+    fn read_light_intensity(&self) -> Result<(), ErrorCode> {
+        self.reads.set(self.reads.get() + 1);
+        Ok(())
+    }
+}
+
+/// Duty cycle of the backlight, in percent, 0 when stopped.
+#[derive(Default)]
+struct Backlight {
+    percent: Cell<usize>,
+}
+
+impl PwmPin for Backlight {
+    fn start(&self, _frequency_hz: usize, duty_cycle: usize) -> Result<(), ErrorCode> {
+        self.percent
+            .set(duty_cycle * 100 / self.get_maximum_duty_cycle());
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), ErrorCode> {
+        self.percent.set(0);
+        Ok(())
+    }
+
+    fn get_maximum_frequency_hz(&self) -> usize {
+        20_000
+    }
+
+    fn get_maximum_duty_cycle(&self) -> usize {
+        1000
+    }
+}
+
+/// Feed `count` readings of `lux`, and return the last change of level.
+fn feed(selector: &mut LevelSelector, count: usize, lux: u32) -> Option<usize> {
+    (0..count).filter_map(|_| selector.sample(lux)).last()
+}
+
+#[test]
+fn first_reading_selects_the_level() {
+    let mut selector = LevelSelector::new();
+    assert_eq!(selector.sample(500), Some(2));
+    let mut selector = LevelSelector::new();
+    assert_eq!(selector.sample(5), Some(0));
+    assert_eq!(selector.sample(5), None);
+}
+
+#[test]
+fn hysteresis_keeps_the_level_near_a_threshold() {
+    let mut selector = LevelSelector::new();
+    selector.sample(50);
+    // Around the 100 lux threshold, within the 20% hysteresis
+    assert_eq!(feed(&mut selector, 50, 115), None);
+    assert_eq!(feed(&mut selector, 50, 125), Some(2));
+    assert_eq!(feed(&mut selector, 50, 85), None);
+    assert_eq!(feed(&mut selector, 50, 75), Some(1));
+}
+
+#[test]
+fn short_shadows_are_filtered() {
+    let mut selector = LevelSelector::new();
+    selector.sample(2000);
+    assert_eq!(selector.level(), 3);
+    assert_eq!(feed(&mut selector, 1, 20), None);
+    assert_eq!(feed(&mut selector, 20, 2000), None);
+    // A long one is not
+    assert_eq!(feed(&mut selector, 20, 20), Some(1));
+}
+
+#[test]
+fn thresholds_stay_in_order() {
+    let mut selector = LevelSelector::new();
+    assert_eq!(selector.set_threshold(1, 10), Err(ErrorCode::INVAL));
+    assert_eq!(selector.set_threshold(1, 1000), Err(ErrorCode::INVAL));
+    assert_eq!(selector.set_threshold(3, 5000), Err(ErrorCode::INVAL));
+    assert_eq!(selector.set_threshold(1, 300), Ok(()));
+    assert_eq!(selector.sample(200), Some(1));
+}
+
+#[test]
+fn backlight_follows_the_light() {
+    let sensor = leak(Sensor::default());
+    let backlight = leak(Backlight::default());
+    let alarm = leak(MockAlarm::new());
+    let auto_brightness = leak(AutoBrightness::new(
+        sensor,
+        backlight,
+        alarm,
+        create_grant(auto_brightness::DRIVER_NUM),
+    ));
+    alarm.set_alarm_client(auto_brightness);
+    sensor.set_client(auto_brightness);
+    auto_brightness.start();
+    assert_eq!(sensor.reads.get(), 1);
+
+    sensor.complete(2000);
+    assert_eq!(backlight.percent.get(), 100);
+
+    // Darkness, read at each period
+    for _ in 0..20 {
+        alarm.advance(DEFAULT_PERIOD_MS);
+        sensor.complete(1);
+    }
+    assert_eq!(sensor.reads.get(), 21);
+    assert_eq!(backlight.percent.get(), 10);
+
+    assert_eq!(auto_brightness.set_level_brightness(0, 20), Ok(()));
+    assert_eq!(backlight.percent.get(), 20);
+    assert_eq!(
+        auto_brightness.set_level_brightness(4, 20),
+        Err(ErrorCode::INVAL)
+    );
+    assert_eq!(auto_brightness.set_manual_brightness(50), Ok(()));
+    assert_eq!(backlight.percent.get(), 50);
+
+    // Manual brightness stops the readings
+    alarm.advance(10 * DEFAULT_PERIOD_MS);
+    assert_eq!(sensor.reads.get(), 21);
+    assert_eq!(backlight.percent.get(), 50);
+}
@@ -0,0 +1,89 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::RefCell;
+
+use capsules_extra::bme280::Bme280;
+use kernel::hil::i2c::Error;
+use kernel::hil::sensors::{PressureClient, PressureDriver};
+use kernel::ErrorCode;
+
+use crate::i2c::{MockI2CDevice, Transfer};
+use crate::{leak, static_buffer};
+
+#[derive(Default)]
+struct Client {
+    pressures: RefCell<Vec<Result<u32, ErrorCode>>>,
+}
+
+impl PressureClient for Client {
+    fn callback(&self, value: Result<u32, ErrorCode>) {
+        self.pressures.borrow_mut().push(value);
+    }
+}
+
+/// The calibration of the example of the Bosch BMP280 datasheet, section
+/// 8.2, which the BME280 shares.
+const CALIBRATION: [u8; 26] = [
+    0x70, 0x6B, 0x43, 0x67, 0x18, 0xFC, // T1 to T3
+    0x7D, 0x8E, 0x43, 0xD6, 0xD0, 0x0B, 0x27, 0x0B, 0x8C, 0x00, // P1 to P5
+    0xF9, 0xFF, 0x8C, 0x3C, 0xF8, 0xC6, 0x70, 0x17, // P6 to P9
+    0x00, 0x4B, // reserved, H1
+];
+
+fn setup() -> (
+    &'static MockI2CDevice<'static>,
+    &'static Bme280<'static>,
+    &'static Client,
+) {
+    let i2c = leak(MockI2CDevice::new());
+    let bme280 = leak(Bme280::new(i2c, static_buffer(26)));
+    i2c.set_client(bme280);
+    let client = leak(Client::default());
+    PressureDriver::set_client(bme280, client);
+
+    bme280.startup();
+    i2c.queue_response(&[0x60]);
+    i2c.complete();
+    i2c.queue_response(&CALIBRATION);
+    i2c.complete();
+    i2c.queue_response(&[0x01, 0x60, 0x00, 0x13, 0x28, 0x03, 0x1E, 0x00]);
+    i2c.complete();
+    // Already in normal mode
+    i2c.queue_response(&[0x27]);
+    i2c.complete();
+    i2c.take_transfers();
+    (i2c, bme280, client)
+}
+
+#[test]
+fn reads_compensated_pressure() {
+    let (i2c, bme280, client) = setup();
+    assert_eq!(bme280.read_pressure(), Ok(()));
+    // PRESS_MSB to TEMP_XLSB
+    assert_eq!(i2c.take_transfers(), [Transfer::WriteRead(vec![0xF7], 6)]);
+
+    // adc_P = 415148 and adc_T = 519888, 100653 Pa in the datasheet with
+    // floating point, 100656 Pa with the 32 bit integer compensation
+    i2c.queue_response(&[0x65, 0x5A, 0xC0, 0x7E, 0xED, 0x00]);
+    i2c.complete();
+    assert_eq!(*client.pressures.borrow(), [Ok(100656)]);
+    assert_eq!(bme280.read_pressure(), Ok(()));
+}
+
+#[test]
+fn busy_while_reading() {
+    let (_, bme280, _) = setup();
+    assert_eq!(bme280.read_pressure(), Ok(()));
+    assert_eq!(bme280.read_pressure(), Err(ErrorCode::BUSY));
+}
+
+#[test]
+fn reports_bus_errors() {
+    let (i2c, bme280, client) = setup();
+    bme280.read_pressure().unwrap();
+    i2c.queue_error(Error::DataNak);
+    i2c.complete();
+    assert_eq!(*client.pressures.borrow(), [Err(ErrorCode::NOACK)]);
+}
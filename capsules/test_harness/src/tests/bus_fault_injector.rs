@@ -0,0 +1,136 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::RefCell;
+
+use capsules_core::process_console::ConsoleCommand;
+use capsules_extra::bus_fault_injector::{Fault, FaultInjector, FaultRates, FaultyI2CDevice};
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
+use kernel::hil::i2c::{Error, I2CClient, I2CDevice};
+
+use crate::i2c::{MockI2CDevice, Transfer};
+use crate::{deferred_call_lock, leak, static_buffer};
+
+#[derive(Default)]
+struct Client {
+    completions: RefCell<Vec<Result<(), Error>>>,
+}
+
+impl I2CClient for Client {
+    fn command_complete(&self, _buffer: &'static mut [u8], status: Result<(), Error>) {
+        self.completions.borrow_mut().push(status);
+    }
+}
+
+/// The lines the command prints after `arguments`.
+fn run(injector: &FaultInjector, arguments: &str) -> String {
+    injector.execute(arguments);
+    let mut output = String::new();
+    let mut index = 0;
+    while injector.write_line(index, &mut output) {
+        index += 1;
+    }
+    output
+}
+
+#[test]
+fn console_sets_rates() {
+    let injector = FaultInjector::new(1);
+    assert_eq!(
+        run(&injector, "nack 100"),
+        "Faults: nack 100/1000, spurious 0/1000, timeout 0/1000\r\n\
+         Injected: 0 nack, 0 spurious, 0 timeout\r\n"
+    );
+    run(&injector, "timeout 5");
+    assert_eq!(
+        injector.rates(),
+        FaultRates {
+            nack: 100,
+            spurious: 0,
+            timeout: 5,
+        }
+    );
+
+    assert_eq!(
+        run(&injector, "spurious 950"),
+        "faults: rates add up to more than 1000\r\n"
+    );
+    assert_eq!(
+        run(&injector, "stall 1"),
+        "faults: faults are nack, spurious, timeout\r\n"
+    );
+    assert_eq!(
+        run(&injector, "nack"),
+        "faults: expected a rate in per mille\r\n"
+    );
+    assert_eq!(injector.rates().spurious, 0);
+
+    run(&injector, "off");
+    assert_eq!(injector.rates(), FaultRates::default());
+}
+
+#[test]
+fn injects_faults_into_i2c_transactions() {
+    let _lock = deferred_call_lock();
+    let injector = leak(FaultInjector::new(0x2545_F491));
+    let i2c = leak(MockI2CDevice::new());
+    let device = leak(FaultyI2CDevice::new(i2c, injector));
+    i2c.set_client(device);
+    device.register();
+    let client = leak(Client::default());
+    device.set_client(client);
+
+    // Without faults, the transaction reaches the device.
+    device.write_read(static_buffer(2), 1, 1).unwrap();
+    assert_eq!(i2c.take_transfers(), [Transfer::WriteRead(vec![0], 1)]);
+    i2c.queue_response(&[0x42]);
+    assert!(i2c.complete());
+    assert_eq!(client.completions.take(), [Ok(())]);
+
+    // A NACK completes from a deferred call, without reaching the device.
+    injector
+        .set_rates(FaultRates {
+            nack: 1000,
+            ..FaultRates::default()
+        })
+        .unwrap();
+    device.write(static_buffer(2), 2).unwrap();
+    assert!(client.completions.borrow().is_empty());
+    assert!(DeferredCall::service_next_pending().is_some());
+    assert_eq!(client.completions.take(), [Err(Error::AddressNak)]);
+    assert!(i2c.take_transfers().is_empty());
+
+    // So does a spurious completion, reporting success.
+    injector
+        .set_rates(FaultRates {
+            spurious: 1000,
+            ..FaultRates::default()
+        })
+        .unwrap();
+    device.read(static_buffer(2), 2).unwrap();
+    assert!(DeferredCall::service_next_pending().is_some());
+    assert_eq!(client.completions.take(), [Ok(())]);
+    assert!(i2c.take_transfers().is_empty());
+
+    // A timeout never completes, and the device stays busy.
+    injector
+        .set_rates(FaultRates {
+            timeout: 1000,
+            ..FaultRates::default()
+        })
+        .unwrap();
+    device.read(static_buffer(2), 2).unwrap();
+    assert!(DeferredCall::service_next_pending().is_none());
+    injector.set_rates(FaultRates::default()).unwrap();
+    assert!(matches!(
+        device.read(static_buffer(2), 2),
+        Err((Error::Busy, _))
+    ));
+    assert!(client.completions.borrow().is_empty());
+    assert!(i2c.take_transfers().is_empty());
+
+    assert_eq!(injector.injected(Fault::Nack), 1);
+    assert_eq!(injector.injected(Fault::Spurious), 1);
+    assert_eq!(injector.injected(Fault::Timeout), 1);
+}
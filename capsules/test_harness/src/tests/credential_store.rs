@@ -0,0 +1,536 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::{Cell, RefCell};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::Hasher;
+
+use capsules_core::virtualizers::virtual_aes_ccm::{MuxAES128CCM, VirtualAES128CCM};
+use capsules_extra::credential_store::{self, CredentialClient, CredentialStore};
+use capsules_extra::kv_store::{self, KVStore, MuxKVStore};
+use capsules_extra::symmetric_encryption::aes_software::Aes128Software;
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
+use kernel::hil::device_key::{DeviceKey, DEVICE_KEY_LEN};
+use kernel::hil::kv_system::{Client, KVSystem};
+use kernel::hil::symmetric_encryption::{AES128, AES128CCM};
+use kernel::storage_permissions::StoragePermissions;
+use kernel::utilities::cells::OptionalCell;
+use kernel::{capabilities, create_capability, ErrorCode};
+
+use crate::{deferred_call_lock, leak, static_buffer};
+
+type Aes = VirtualAES128CCM<'static, Aes128Software<'static>>;
+type Store = CredentialStore<'static, MockKV, [u8; 8], Aes>;
+
+const DEVICE_KEY: [u8; DEVICE_KEY_LEN] = [0x42; DEVICE_KEY_LEN];
+
+/// An operation of the store, completed by `MockKV::complete`.
+enum Pending {
+    GenerateKey(&'static mut [u8], &'static mut [u8; 8]),
+    Append(
+        &'static mut [u8; 8],
+        &'static mut [u8],
+        Result<(), ErrorCode>,
+    ),
+    Get(
+        &'static mut [u8; 8],
+        &'static mut [u8],
+        Result<(), ErrorCode>,
+    ),
+    Invalidate(&'static mut [u8; 8], Result<(), ErrorCode>),
+    GarbageCollect,
+}
+
+/// A key-value system holding at most `capacity` records in memory. Like
+/// TicKV, it refuses to append a key which exists and starts one operation
+/// at a time. Reading the record of `unreadable` fails.
+struct MockKV {
+    records: RefCell<HashMap<[u8; 8], Vec<u8>>>,
+    capacity: Cell<usize>,
+    unreadable: Cell<Option<[u8; 8]>>,
+    pending: RefCell<Option<Pending>>,
+    client: OptionalCell<&'static dyn Client<[u8; 8]>>,
+}
+
+impl MockKV {
+    fn new() -> MockKV {
+        MockKV {
+            records: RefCell::new(HashMap::new()),
+            capacity: Cell::new(usize::MAX),
+            unreadable: Cell::new(None),
+            pending: RefCell::new(None),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// The hashed key of `name`, padded as the credential store pads it.
+    fn hash(name: &[u8]) -> [u8; 8] {
+        let mut key = [0; credential_store::KEY_BUF_LEN];
+        key[..name.len()].copy_from_slice(name);
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&key);
+        hasher.finish().to_le_bytes()
+    }
+
+    fn record(&self, name: &[u8]) -> Option<Vec<u8>> {
+        self.records.borrow().get(&Self::hash(name)).cloned()
+    }
+
+    fn remove(&self, name: &[u8]) {
+        self.records.borrow_mut().remove(&Self::hash(name));
+    }
+
+    /// Complete the operation in progress. Returns `false` if there is none.
+    fn complete(&self) -> bool {
+        let pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => return false,
+        };
+        self.client.map(move |client| match pending {
+            Pending::GenerateKey(unhashed_key, key) => {
+                *key = Self::hash(unhashed_key);
+                client.generate_key_complete(Ok(()), unhashed_key, key);
+            }
+            Pending::Append(key, value, result) => client.append_key_complete(result, key, value),
+            Pending::Get(key, value, result) => client.get_value_complete(result, key, value),
+            Pending::Invalidate(key, result) => client.invalidate_key_complete(result, key),
+            Pending::GarbageCollect => client.garbage_collect_complete(Ok(())),
+        });
+        true
+    }
+}
+
+impl KVSystem<'static> for MockKV {
+    type K = [u8; 8];
+
+    fn set_client(&self, client: &'static dyn Client<[u8; 8]>) {
+        self.client.set(client);
+    }
+
+    fn generate_key(
+        &self,
+        unhashed_key: &'static mut [u8],
+        key: &'static mut [u8; 8],
+    ) -> Result<
+        (),
+        (
+            &'static mut [u8],
+            &'static mut [u8; 8],
+            Result<(), ErrorCode>,
+        ),
+    > {
+        if self.pending.borrow().is_some() {
+            return Err((unhashed_key, key, Err(ErrorCode::BUSY)));
+        }
+        *self.pending.borrow_mut() = Some(Pending::GenerateKey(unhashed_key, key));
+        Ok(())
+    }
+
+    fn append_key(
+        &self,
+        key: &'static mut [u8; 8],
+        value: &'static mut [u8],
+    ) -> Result<
+        (),
+        (
+            &'static mut [u8; 8],
+            &'static mut [u8],
+            Result<(), ErrorCode>,
+        ),
+    > {
+        if self.pending.borrow().is_some() {
+            return Err((key, value, Err(ErrorCode::BUSY)));
+        }
+        let mut records = self.records.borrow_mut();
+        let result = if records.contains_key(key) {
+            Err(ErrorCode::NOSUPPORT)
+        } else if records.len() >= self.capacity.get() {
+            Err(ErrorCode::NOMEM)
+        } else {
+            // The store prepends its header, holding the length of the value
+            let length = u32::from_le_bytes(value[1..5].try_into().unwrap()) as usize;
+            records.insert(*key, value[..kv_store::HEADER_LENGTH + length].to_vec());
+            Ok(())
+        };
+        *self.pending.borrow_mut() = Some(Pending::Append(key, value, result));
+        Ok(())
+    }
+
+    fn get_value(
+        &self,
+        key: &'static mut [u8; 8],
+        value: &'static mut [u8],
+    ) -> Result<
+        (),
+        (
+            &'static mut [u8; 8],
+            &'static mut [u8],
+            Result<(), ErrorCode>,
+        ),
+    > {
+        if self.pending.borrow().is_some() {
+            return Err((key, value, Err(ErrorCode::BUSY)));
+        }
+        let result = match self.records.borrow().get(key) {
+            _ if self.unreadable.get() == Some(*key) => Err(ErrorCode::FAIL),
+            Some(record) => {
+                let len = record.len().min(value.len());
+                value[..len].copy_from_slice(&record[..len]);
+                Ok(())
+            }
+            None => Err(ErrorCode::NOSUPPORT),
+        };
+        *self.pending.borrow_mut() = Some(Pending::Get(key, value, result));
+        Ok(())
+    }
+
+    fn invalidate_key(
+        &self,
+        key: &'static mut [u8; 8],
+    ) -> Result<(), (&'static mut [u8; 8], Result<(), ErrorCode>)> {
+        if self.pending.borrow().is_some() {
+            return Err((key, Err(ErrorCode::BUSY)));
+        }
+        let result = match self.records.borrow_mut().remove(key) {
+            Some(_) => Ok(()),
+            None => Err(ErrorCode::NOSUPPORT),
+        };
+        *self.pending.borrow_mut() = Some(Pending::Invalidate(key, result));
+        Ok(())
+    }
+
+    fn garbage_collect(&self) -> Result<usize, Result<(), ErrorCode>> {
+        if self.pending.borrow().is_some() {
+            return Err(Err(ErrorCode::BUSY));
+        }
+        *self.pending.borrow_mut() = Some(Pending::GarbageCollect);
+        Ok(0)
+    }
+}
+
+struct FixedKey([u8; DEVICE_KEY_LEN]);
+
+impl DeviceKey for FixedKey {
+    fn read_key(&self, key: &mut [u8; DEVICE_KEY_LEN]) -> Result<(), ErrorCode> {
+        key.copy_from_slice(&self.0);
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Results {
+    starts: RefCell<Vec<Result<(), ErrorCode>>>,
+    loads: RefCell<Vec<(Result<(), ErrorCode>, Vec<u8>, Vec<u8>)>>,
+    stores: RefCell<Vec<(Result<(), ErrorCode>, Vec<u8>)>>,
+    deletes: RefCell<Vec<(Result<(), ErrorCode>, Vec<u8>)>>,
+}
+
+impl CredentialClient for Results {
+    fn start_done(&self, result: Result<(), ErrorCode>) {
+        self.starts.borrow_mut().push(result);
+    }
+
+    fn load_done(&self, result: Result<(), ErrorCode>, name: &[u8], credential: &[u8]) {
+        self.loads
+            .borrow_mut()
+            .push((result, name.to_vec(), credential.to_vec()));
+    }
+
+    fn store_done(&self, result: Result<(), ErrorCode>, name: &[u8]) {
+        self.stores.borrow_mut().push((result, name.to_vec()));
+    }
+
+    fn delete_done(&self, result: Result<(), ErrorCode>, name: &[u8]) {
+        self.deletes.borrow_mut().push((result, name.to_vec()));
+    }
+
+    fn rotate_done(&self, _result: Result<(), ErrorCode>) {}
+}
+
+struct Setup {
+    kv: &'static MockKV,
+    ccm: &'static Aes,
+    store: &'static Store,
+    results: &'static Results,
+}
+
+impl Setup {
+    /// A credential store over `kv` and `ccm`, not started yet.
+    fn new(kv: &'static MockKV, ccm: &'static Aes, names: &'static [&'static [u8]]) -> Setup {
+        let mux_kv = leak(MuxKVStore::new(kv));
+        let kv_store = leak(KVStore::new(
+            mux_kv,
+            leak_mut([0; 8]),
+            leak_mut([0; kv_store::HEADER_LENGTH]),
+        ));
+        KVSystem::set_client(kv, kv_store);
+
+        let store = leak(CredentialStore::new(
+            kv_store,
+            ccm,
+            leak(FixedKey(DEVICE_KEY)),
+            names,
+            StoragePermissions::new_kernel_permissions(&create_capability!(
+                capabilities::KerneluserStorageCapability
+            )),
+            static_buffer(credential_store::KEY_BUF_LEN),
+            static_buffer(credential_store::VALUE_BUF_LEN),
+            static_buffer(credential_store::CRYPT_BUF_LEN),
+        ));
+        AES128CCM::set_client(ccm, store);
+        kv_store.set_client(store);
+        let results = leak(Results::default());
+        store.set_client(results);
+        Setup {
+            kv,
+            ccm,
+            store,
+            results,
+        }
+    }
+
+    /// A new store over the same records, as after a reset, not started yet.
+    fn reboot(&self, names: &'static [&'static [u8]]) -> Setup {
+        Setup::new(self.kv, self.ccm, names)
+    }
+
+    /// Complete the operations of the key-value system and the encryptions
+    /// until the store starts nothing more.
+    fn run(&self) {
+        while self.kv.complete() || DeferredCall::service_next_pending().is_some() {}
+    }
+
+    fn store(&self, name: &[u8], credential: &[u8]) -> Result<(), ErrorCode> {
+        self.store.store(name, credential)?;
+        self.run();
+        self.results.stores.borrow_mut().pop().unwrap().0
+    }
+
+    fn start(&self) -> Result<(), ErrorCode> {
+        self.store.start()?;
+        self.run();
+        self.results.starts.borrow_mut().pop().unwrap()
+    }
+
+    fn load(&self, name: &[u8]) -> Result<Vec<u8>, ErrorCode> {
+        self.store.load(name)?;
+        self.run();
+        let (result, loaded_name, credential) = self.results.loads.borrow_mut().pop().unwrap();
+        assert_eq!(loaded_name, name);
+        result.map(|()| credential)
+    }
+}
+
+/// A credential store over an empty key-value system, started. The caller
+/// holds the deferred call lock, as encryptions complete from deferred
+/// calls.
+fn setup() -> Setup {
+    let aes = leak(Aes128Software::new());
+    aes.register();
+    let ccm_mux = leak(MuxAES128CCM::new(aes));
+    ccm_mux.register();
+    aes.set_client(ccm_mux);
+    let ccm = leak(VirtualAES128CCM::new(
+        ccm_mux,
+        static_buffer(credential_store::CCM_BUF_LEN),
+    ));
+    ccm.setup();
+
+    let s = Setup::new(leak(MockKV::new()), ccm, &[]);
+    assert_eq!(s.start(), Ok(()));
+    s
+}
+
+fn leak_mut<T>(value: T) -> &'static mut T {
+    Box::leak(Box::new(value))
+}
+
+/// The end of the reserved nonces stored in record `slot`.
+fn nonce_limit(kv: &MockKV, slot: usize) -> Option<u64> {
+    kv.record(credential_store::NONCE_KEYS[slot]).map(|record| {
+        // After the header of the store
+        assert_eq!(record.len(), kv_store::HEADER_LENGTH + 8);
+        u64::from_le_bytes(record[kv_store::HEADER_LENGTH..].try_into().unwrap())
+    })
+}
+
+/// The nonce counter of the record of `name`.
+fn record_nonce(kv: &MockKV, name: &[u8]) -> u64 {
+    let record = kv.record(name).unwrap();
+    let header = &record[kv_store::HEADER_LENGTH..];
+    u64::from_le_bytes(header[2..10].try_into().unwrap())
+}
+
+#[test]
+fn start_reserves_nonces() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+
+    let limit = nonce_limit(s.kv, 0).unwrap();
+    assert!(limit > 0);
+    assert_eq!(nonce_limit(s.kv, 1), None);
+
+    // After a reset, the next reservation goes to the other record and starts
+    // after the previous one, which is only replaced by the one after.
+    let s = s.reboot(&[]);
+    assert_eq!(s.start(), Ok(()));
+    assert_eq!(nonce_limit(s.kv, 0), Some(limit));
+    let next = nonce_limit(s.kv, 1).unwrap();
+    assert!(next > limit);
+    assert_eq!(s.store(b"wifi-ssid", b"home"), Ok(()));
+    assert_eq!(record_nonce(s.kv, b"wifi-ssid"), limit);
+
+    let s = s.reboot(&[]);
+    assert_eq!(s.start(), Ok(()));
+    assert!(nonce_limit(s.kv, 0).unwrap() > next);
+    assert_eq!(nonce_limit(s.kv, 1), Some(next));
+}
+
+#[test]
+fn failed_reservation_keeps_the_previous_one() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+    let limit = nonce_limit(s.kv, 0).unwrap();
+
+    // The new reservation cannot be stored, as after a reset between
+    // removing the spare record and writing it.
+    s.kv.capacity.set(1);
+    let s = s.reboot(&[]);
+    assert_eq!(s.start(), Err(ErrorCode::NOMEM));
+    assert_eq!(nonce_limit(s.kv, 0), Some(limit));
+    assert_eq!(nonce_limit(s.kv, 1), None);
+
+    s.kv.capacity.set(usize::MAX);
+    let s = s.reboot(&[]);
+    assert_eq!(s.start(), Ok(()));
+    assert_eq!(s.store(b"wifi-ssid", b"home"), Ok(()));
+    assert_eq!(record_nonce(s.kv, b"wifi-ssid"), limit);
+}
+
+#[test]
+fn unreadable_records_fail_start() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+    assert_eq!(s.store(b"wifi-ssid", b"home"), Ok(()));
+    let limit = nonce_limit(s.kv, 0);
+
+    // A counter record, or a credential whose nonce must not be reused
+    for name in [credential_store::NONCE_KEYS[1], b"wifi-ssid"] {
+        s.kv.unreadable.set(Some(MockKV::hash(name)));
+        let s = s.reboot(&[b"wifi-ssid"]);
+        assert_eq!(s.start(), Err(ErrorCode::FAIL));
+        assert_eq!(s.store(b"wifi-ssid", b"office"), Err(ErrorCode::RESERVE));
+        assert_eq!(nonce_limit(s.kv, 0), limit);
+        assert_eq!(nonce_limit(s.kv, 1), None);
+    }
+}
+
+#[test]
+fn start_counts_after_the_stored_credentials() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+    for _ in 0..3 {
+        assert_eq!(s.store(b"wifi-ssid", b"home"), Ok(()));
+    }
+    let nonce = record_nonce(s.kv, b"wifi-ssid");
+    assert_eq!(nonce, 2);
+
+    // Both counter records are lost, and one credential was never stored.
+    s.kv.remove(credential_store::NONCE_KEYS[0]);
+    s.kv.remove(credential_store::NONCE_KEYS[1]);
+    let s = s.reboot(&[b"wifi-password", b"wifi-ssid"]);
+    assert_eq!(s.start(), Ok(()));
+    assert_eq!(s.store(b"wifi-password", b"hunter2"), Ok(()));
+    assert_eq!(record_nonce(s.kv, b"wifi-password"), nonce + 1);
+    assert_eq!(s.load(b"wifi-ssid"), Ok(b"home".to_vec()));
+}
+
+#[test]
+fn stored_credential_is_encrypted_and_loads_back() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+
+    assert_eq!(s.store(b"wifi-password", b"hunter2 hunter2"), Ok(()));
+    let record = s.kv.record(b"wifi-password").unwrap();
+    // Header, ciphertext and tag
+    assert_eq!(record.len(), kv_store::HEADER_LENGTH + 10 + 15 + 8);
+    assert!(!record
+        .windows(b"hunter2".len())
+        .any(|window| window == b"hunter2"));
+
+    assert_eq!(s.load(b"wifi-password"), Ok(b"hunter2 hunter2".to_vec()));
+}
+
+#[test]
+fn store_replaces_the_previous_value() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+
+    assert_eq!(s.store(b"wifi-ssid", b"home"), Ok(()));
+    let first = s.kv.record(b"wifi-ssid").unwrap();
+    assert_eq!(s.store(b"wifi-ssid", b"office"), Ok(()));
+    assert_eq!(s.load(b"wifi-ssid"), Ok(b"office".to_vec()));
+
+    // The same credential is encrypted under a new nonce each time
+    assert_eq!(s.store(b"wifi-ssid", b"home"), Ok(()));
+    assert_ne!(s.kv.record(b"wifi-ssid").unwrap(), first);
+    assert_eq!(s.load(b"wifi-ssid"), Ok(b"home".to_vec()));
+}
+
+#[test]
+fn missing_and_deleted_credentials_do_not_load() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+
+    assert_eq!(s.load(b"wifi-ssid"), Err(ErrorCode::FAIL));
+    assert_eq!(s.store(b"wifi-ssid", b"home"), Ok(()));
+    assert_eq!(s.store.delete(b"wifi-ssid"), Ok(()));
+    s.run();
+    assert_eq!(
+        *s.results.deletes.borrow(),
+        [(Ok(()), b"wifi-ssid".to_vec())]
+    );
+    assert_eq!(s.load(b"wifi-ssid"), Err(ErrorCode::FAIL));
+}
+
+#[test]
+fn modified_or_moved_records_are_rejected() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+    assert_eq!(s.store(b"wifi-ssid", b"home"), Ok(()));
+    assert_eq!(s.store(b"wifi-password", b"hunter2"), Ok(()));
+
+    // A record copied under the name of another credential
+    let ssid = s.kv.record(b"wifi-ssid").unwrap();
+    s.kv.records
+        .borrow_mut()
+        .insert(MockKV::hash(b"wifi-password"), ssid.clone());
+    assert_eq!(s.load(b"wifi-password"), Err(ErrorCode::FAIL));
+
+    // A ciphertext with a bit flipped
+    let mut modified = ssid;
+    modified[kv_store::HEADER_LENGTH + 10] ^= 0x01;
+    s.kv.records
+        .borrow_mut()
+        .insert(MockKV::hash(b"wifi-ssid"), modified);
+    assert_eq!(s.load(b"wifi-ssid"), Err(ErrorCode::FAIL));
+}
+
+#[test]
+fn full_storage_fails_new_credentials_only() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+    // The nonce counter and one credential
+    s.kv.capacity.set(2);
+
+    assert_eq!(s.store(b"wifi-ssid", b"home"), Ok(()));
+    assert_eq!(s.store(b"wifi-password", b"hunter2"), Err(ErrorCode::NOMEM));
+    assert_eq!(s.kv.record(b"wifi-password"), None);
+
+    // A credential which exists is replaced in place
+    assert_eq!(s.store(b"wifi-ssid", b"office"), Ok(()));
+    assert_eq!(s.load(b"wifi-ssid"), Ok(b"office".to_vec()));
+    assert_eq!(s.load(b"wifi-password"), Err(ErrorCode::FAIL));
+}
@@ -0,0 +1,457 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::{Cell, RefCell};
+
+use capsules_extra::net::ipv4::dhcp::{self, DhcpClient, LeaseClient, State};
+use capsules_extra::net::ipv4::stack::{TcpPort, UdpClient, UdpSocket};
+use capsules_extra::net::ipv4::{Ipv4Addr, Ipv4Config, Ipv4Interface};
+use kernel::hil::mac_address::MacAddress;
+use kernel::hil::time::Alarm;
+use kernel::ErrorCode;
+
+use crate::alarm::MockAlarm;
+use crate::{leak, static_buffer};
+
+type Client = DhcpClient<'static, MockInterface, MockAlarm<'static>>;
+
+const MAC: MacAddress = MacAddress::new([0x02, 0, 0, 0x12, 0x34, 0x56]);
+const SERVER: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 1);
+const OFFERED: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 50);
+
+const DHCPDISCOVER: u8 = 1;
+const DHCPOFFER: u8 = 2;
+const DHCPREQUEST: u8 = 3;
+const DHCPACK: u8 = 5;
+const DHCPNAK: u8 = 6;
+
+/// Subnet mask, router, lease time, renewal time and rebinding time.
+const PARAMETERS: [u8; 5] = [1, 3, 51, 58, 59];
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+
+/// An interface logging the datagrams sent.
+struct MockInterface {
+    config: Cell<Ipv4Config>,
+    sent: RefCell<Vec<(Ipv4Addr, Vec<u8>)>>,
+}
+
+impl Ipv4Interface<'static> for MockInterface {
+    fn config(&self) -> Ipv4Config {
+        self.config.get()
+    }
+
+    fn set_config(&self, config: Ipv4Config) {
+        self.config.set(config);
+    }
+
+    fn mac_address(&self) -> MacAddress {
+        MAC
+    }
+
+    fn send_udp(
+        &self,
+        _socket: &'static UdpSocket<'static>,
+        src_port: u16,
+        dst: Ipv4Addr,
+        dst_port: u16,
+        payload: &[u8],
+    ) -> Result<(), ErrorCode> {
+        assert_eq!((src_port, dst_port), (dhcp::CLIENT_PORT, dhcp::SERVER_PORT));
+        self.sent.borrow_mut().push((dst, payload.to_vec()));
+        Ok(())
+    }
+
+    fn send_tcp(
+        &self,
+        _port: &'static TcpPort<'static>,
+        _dst: Ipv4Addr,
+        _segment: &[u8],
+    ) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+}
+
+#[derive(Default)]
+struct Leases(RefCell<Vec<Ipv4Config>>);
+
+impl LeaseClient for Leases {
+    fn lease_changed(&self, config: Ipv4Config) {
+        self.0.borrow_mut().push(config);
+    }
+}
+
+struct Setup {
+    interface: &'static MockInterface,
+    alarm: &'static MockAlarm<'static>,
+    dhcp: &'static Client,
+    leases: &'static Leases,
+}
+
+impl Setup {
+    /// The message sent, which must be the only one, and its destination.
+    fn sent_message(&self) -> (Ipv4Addr, Vec<u8>) {
+        let mut sent = self.interface.sent.take();
+        assert_eq!(sent.len(), 1);
+        sent.remove(0)
+    }
+
+    fn receive(&self, message: &[u8]) {
+        self.dhcp
+            .receive(SERVER, dhcp::SERVER_PORT, dhcp::CLIENT_PORT, message);
+    }
+
+    /// Start, and return the transaction ID of the DHCPDISCOVER.
+    fn discover(&self) -> u32 {
+        self.dhcp.start();
+        let (_, discover) = self.sent_message();
+        assert_eq!(message_type(&discover), DHCPDISCOVER);
+        xid(&discover)
+    }
+
+    /// Get a lease with `options` in the DHCPACK, and return the
+    /// transaction ID.
+    fn bind(&self, options: &[&[u8]]) -> u32 {
+        let xid = self.discover();
+        self.receive(&offer(xid));
+        let (_, request) = self.sent_message();
+        assert_eq!(message_type(&request), DHCPREQUEST);
+        self.receive(&reply(xid, OFFERED, options));
+        assert_eq!(self.dhcp.state(), State::Bound);
+        xid
+    }
+}
+
+fn setup() -> Setup {
+    let interface = leak(MockInterface {
+        config: Cell::new(Ipv4Config::UNCONFIGURED),
+        sent: RefCell::new(Vec::new()),
+    });
+    let alarm = leak(MockAlarm::new());
+    let socket = leak(UdpSocket::new());
+    let dhcp = leak(DhcpClient::new(
+        interface,
+        socket,
+        alarm,
+        static_buffer(dhcp::BUF_LEN),
+    ));
+    socket.set_client(dhcp);
+    alarm.set_alarm_client(dhcp);
+    let leases = leak(Leases::default());
+    dhcp.set_client(leases);
+    Setup {
+        interface,
+        alarm,
+        dhcp,
+        leases,
+    }
+}
+
+fn xid(message: &[u8]) -> u32 {
+    u32::from_be_bytes([message[4], message[5], message[6], message[7]])
+}
+
+/// The options of a message sent by the client, up to the end option.
+fn options(message: &[u8]) -> Vec<(u8, Vec<u8>)> {
+    let mut options = Vec::new();
+    let mut offset = 240;
+    while message[offset] != 255 {
+        let len = message[offset + 1] as usize;
+        options.push((
+            message[offset],
+            message[offset + 2..offset + 2 + len].to_vec(),
+        ));
+        offset += 2 + len;
+    }
+    options
+}
+
+fn message_type(message: &[u8]) -> u8 {
+    options(message)
+        .into_iter()
+        .find(|(code, _)| *code == 53)
+        .map(|(_, value)| value[0])
+        .unwrap()
+}
+
+/// A reply from the server offering `address`, with `options`.
+fn reply(xid: u32, address: Ipv4Addr, options: &[&[u8]]) -> Vec<u8> {
+    let mut message = vec![0; 240];
+    message[0..3].copy_from_slice(&[2, 1, 6]);
+    message[4..8].copy_from_slice(&xid.to_be_bytes());
+    message[16..20].copy_from_slice(&address.0);
+    message[28..34].copy_from_slice(MAC.as_bytes());
+    message[236..240].copy_from_slice(&MAGIC_COOKIE);
+    for option in options {
+        message.extend_from_slice(option);
+    }
+    message.push(255);
+    message.resize(dhcp::BUF_LEN, 0);
+    message
+}
+
+fn offer(xid: u32) -> Vec<u8> {
+    reply(
+        xid,
+        OFFERED,
+        &[&[53, 1, DHCPOFFER], &[54, 4, 192, 168, 1, 1]],
+    )
+}
+
+#[test]
+fn lease_is_obtained_with_a_discover_offer_request_ack_exchange() {
+    let s = setup();
+    s.dhcp.start();
+    assert_eq!(s.dhcp.state(), State::Selecting);
+    let (dst, discover) = s.sent_message();
+    assert_eq!(dst, Ipv4Addr::BROADCAST);
+    assert_eq!(discover.len(), dhcp::BUF_LEN);
+    // A request from an Ethernet address of 6 bytes, with no hops
+    assert_eq!(discover[0..4], [1, 1, 6, 0]);
+    // The reply is broadcast, as the client has no address yet
+    assert_eq!(discover[10..16], [0x80, 0, 0, 0, 0, 0]);
+    assert_eq!(discover[28..34], *MAC.as_bytes());
+    assert_eq!(discover[236..240], MAGIC_COOKIE);
+    assert_eq!(
+        options(&discover),
+        [(53, vec![DHCPDISCOVER]), (55, PARAMETERS.to_vec())]
+    );
+    let xid = xid(&discover);
+
+    s.receive(&offer(xid));
+    assert_eq!(s.dhcp.state(), State::Requesting);
+    let (dst, request) = s.sent_message();
+    assert_eq!(dst, Ipv4Addr::BROADCAST);
+    assert_eq!(self::xid(&request), xid);
+    assert_eq!(request[10..16], [0x80, 0, 0, 0, 0, 0]);
+    assert_eq!(
+        options(&request),
+        [
+            (53, vec![DHCPREQUEST]),
+            (50, OFFERED.0.to_vec()),
+            (54, SERVER.0.to_vec()),
+            (55, PARAMETERS.to_vec()),
+        ]
+    );
+
+    // Two routers, of which the first one is used
+    s.receive(&reply(
+        xid,
+        OFFERED,
+        &[
+            &[53, 1, DHCPACK],
+            &[54, 4, 192, 168, 1, 1],
+            &[1, 4, 255, 255, 255, 0],
+            &[3, 8, 192, 168, 1, 254, 192, 168, 1, 1],
+            &[51, 4, 0, 0, 0x0E, 0x10],
+        ],
+    ));
+    assert_eq!(s.dhcp.state(), State::Bound);
+    let config = Ipv4Config {
+        address: OFFERED,
+        netmask: Ipv4Addr::new(255, 255, 255, 0),
+        gateway: Some(Ipv4Addr::new(192, 168, 1, 254)),
+    };
+    assert_eq!(s.interface.config(), config);
+    assert_eq!(s.leases.0.take(), [config]);
+
+    // The lease is renewed with the server after half of its time, from
+    // the address it gave
+    s.alarm.advance(1800 * 1000 - 1);
+    assert!(s.interface.sent.borrow().is_empty());
+    s.alarm.advance(1);
+    assert_eq!(s.dhcp.state(), State::Renewing);
+    let (dst, request) = s.sent_message();
+    assert_eq!(dst, SERVER);
+    assert_eq!(request[10..16], [0, 0, 192, 168, 1, 50]);
+    assert_eq!(
+        options(&request),
+        [(53, vec![DHCPREQUEST]), (55, PARAMETERS.to_vec())]
+    );
+    s.receive(&reply(
+        self::xid(&request),
+        OFFERED,
+        &[
+            &[53, 1, DHCPACK],
+            &[1, 4, 255, 255, 255, 0],
+            &[3, 4, 192, 168, 1, 254],
+        ],
+    ));
+    assert_eq!(s.dhcp.state(), State::Bound);
+    // The configuration did not change
+    assert!(s.leases.0.borrow().is_empty());
+}
+
+#[test]
+fn lease_is_rebound_then_lost_when_it_expires() {
+    let s = setup();
+    s.bind(&[
+        &[53, 1, DHCPACK],
+        &[54, 4, 192, 168, 1, 1],
+        &[51, 4, 0, 0, 1, 144],
+        &[58, 4, 0, 0, 0, 100],
+        &[59, 4, 0, 0, 1, 44],
+    ]);
+    s.leases.0.take();
+
+    // Requests are sent after half of the time left, but at least a minute
+    // apart, and to any server once the rebinding time is reached
+    let renewals = [
+        (100, State::Renewing, SERVER),
+        (100, State::Renewing, SERVER),
+        (60, State::Renewing, SERVER),
+        (40, State::Rebinding, Ipv4Addr::BROADCAST),
+        (60, State::Rebinding, Ipv4Addr::BROADCAST),
+    ];
+    for (seconds, state, server) in renewals {
+        s.alarm.advance(seconds * 1000 - 1);
+        assert!(s.interface.sent.borrow().is_empty());
+        s.alarm.advance(1);
+        assert_eq!(s.dhcp.state(), state);
+        let (dst, request) = s.sent_message();
+        assert_eq!(dst, server);
+        assert_eq!(message_type(&request), DHCPREQUEST);
+        assert_eq!(request[12..16], OFFERED.0);
+    }
+
+    s.alarm.advance(40 * 1000);
+    assert_eq!(s.interface.config(), Ipv4Config::UNCONFIGURED);
+    assert_eq!(s.leases.0.take(), [Ipv4Config::UNCONFIGURED]);
+    assert_eq!(s.dhcp.state(), State::Selecting);
+    let (_, discover) = s.sent_message();
+    assert_eq!(message_type(&discover), DHCPDISCOVER);
+}
+
+#[test]
+fn unanswered_messages_are_sent_again() {
+    let s = setup();
+    let xid = s.discover();
+    // The alarm is set for a minute at most
+    for timeout in [4, 8, 16, 32, 64, 64] {
+        assert_eq!(s.alarm.remaining(), Some(timeout.min(60) * 1000));
+        s.alarm.advance(timeout * 1000 - 1);
+        assert!(s.interface.sent.borrow().is_empty());
+        s.alarm.advance(1);
+        let (_, discover) = s.sent_message();
+        assert_eq!(message_type(&discover), DHCPDISCOVER);
+        assert_eq!(self::xid(&discover), xid);
+    }
+
+    // After four requests for the offer, servers are discovered again
+    s.receive(&offer(xid));
+    s.sent_message();
+    for timeout in [4, 8, 16] {
+        s.alarm.advance(timeout * 1000);
+        let (_, request) = s.sent_message();
+        assert_eq!(message_type(&request), DHCPREQUEST);
+    }
+    s.alarm.advance(32 * 1000);
+    assert_eq!(s.dhcp.state(), State::Selecting);
+    let (_, discover) = s.sent_message();
+    assert_eq!(message_type(&discover), DHCPDISCOVER);
+    assert_ne!(self::xid(&discover), xid);
+}
+
+#[test]
+fn nak_restarts_the_discovery() {
+    let s = setup();
+    let xid = s.discover();
+    s.receive(&offer(xid));
+    s.sent_message();
+    s.receive(&reply(xid, Ipv4Addr::UNSPECIFIED, &[&[53, 1, DHCPNAK]]));
+    assert_eq!(s.dhcp.state(), State::Selecting);
+    let (_, discover) = s.sent_message();
+    assert_eq!(message_type(&discover), DHCPDISCOVER);
+    assert!(s.leases.0.borrow().is_empty());
+
+    // A NAK of a renewal loses the lease
+    s.dhcp.stop();
+    s.bind(&[&[53, 1, DHCPACK], &[54, 4, 192, 168, 1, 1]]);
+    s.alarm.advance(1800 * 1000);
+    let (_, request) = s.sent_message();
+    s.receive(&reply(
+        self::xid(&request),
+        Ipv4Addr::UNSPECIFIED,
+        &[&[53, 1, DHCPNAK]],
+    ));
+    assert_eq!(s.dhcp.state(), State::Selecting);
+    assert_eq!(s.interface.config(), Ipv4Config::UNCONFIGURED);
+    assert_eq!(s.leases.0.take().last(), Some(&Ipv4Config::UNCONFIGURED));
+}
+
+#[test]
+fn malformed_and_unrelated_replies_are_ignored() {
+    let s = setup();
+    let xid = s.discover();
+    let valid = offer(xid);
+    let with = |change: fn(&mut Vec<u8>)| {
+        let mut message = valid.clone();
+        change(&mut message);
+        message
+    };
+    let ignored = [
+        // Another transaction, or another client
+        offer(xid + 1),
+        with(|m| m[33] ^= 0x01),
+        // Not a reply
+        with(|m| m[0] = 1),
+        // Too short for the options, or without the magic cookie
+        valid[..239].to_vec(),
+        with(|m| m[236] = 0),
+        // An offer without a server identifier, or with the identifier
+        // after the end of the options
+        reply(xid, OFFERED, &[&[53, 1, DHCPOFFER]]),
+        reply(
+            xid,
+            OFFERED,
+            &[&[53, 1, DHCPOFFER], &[255], &[54, 4, 192, 168, 1, 1]],
+        ),
+        // An option longer than the message, and an empty message type
+        [&valid[..240], &[53, 1, DHCPOFFER, 54, 4, 192, 168]].concat(),
+        reply(xid, OFFERED, &[&[53, 0], &[54, 4, 192, 168, 1, 1]]),
+        // A reply which is not expected while selecting
+        reply(xid, OFFERED, &[&[53, 1, DHCPACK], &[54, 4, 192, 168, 1, 1]]),
+    ];
+    for message in ignored {
+        s.receive(&message);
+        assert!(s.interface.sent.borrow().is_empty(), "{:02x?}", message);
+        assert_eq!(s.dhcp.state(), State::Selecting);
+    }
+    // A reply from another port
+    s.dhcp.receive(SERVER, 1067, dhcp::CLIENT_PORT, &valid);
+    assert!(s.interface.sent.borrow().is_empty());
+
+    // Padding before the options
+    s.receive(&reply(
+        xid,
+        OFFERED,
+        &[&[0, 0, 53, 1, DHCPOFFER], &[54, 4, 192, 168, 1, 1]],
+    ));
+    assert_eq!(s.dhcp.state(), State::Requesting);
+}
+
+#[test]
+fn missing_or_malformed_lease_options_have_defaults() {
+    let s = setup();
+    // A subnet mask and router of the wrong length, and no lease time
+    s.bind(&[&[53, 1, DHCPACK], &[1, 3, 255, 255, 255], &[3, 2, 192, 168]]);
+    // Every destination is reached through the router, if any
+    assert_eq!(
+        s.leases.0.take(),
+        [Ipv4Config {
+            address: OFFERED,
+            netmask: Ipv4Addr::BROADCAST,
+            gateway: None,
+        }]
+    );
+    // A lease of an hour, renewed after half an hour
+    s.alarm.advance(1800 * 1000 - 1);
+    assert_eq!(s.dhcp.state(), State::Bound);
+    s.alarm.advance(1);
+    assert_eq!(s.dhcp.state(), State::Renewing);
+
+    s.dhcp.stop();
+    assert_eq!(s.dhcp.state(), State::Stopped);
+    assert!(s.alarm.remaining().is_none());
+    assert_eq!(s.leases.0.take(), [Ipv4Config::UNCONFIGURED]);
+}
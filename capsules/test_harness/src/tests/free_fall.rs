@@ -0,0 +1,115 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::Cell;
+
+use capsules_extra::free_fall::{
+    self, FallDetector, FreeFall, FreeFallClient, StopPwm, SAMPLE_RATE_HZ,
+};
+use kernel::hil::gpio::InterruptEdge;
+use kernel::hil::pwm::PwmPin;
+use kernel::ErrorCode;
+
+use crate::gpio::MockPin;
+use crate::{create_grant, leak};
+
+#[derive(Default)]
+struct Action {
+    count: Cell<usize>,
+}
+
+impl FreeFallClient for Action {
+    fn free_fall(&self) {
+        self.count.set(self.count.get() + 1);
+    }
+}
+
+#[derive(Default)]
+struct Motor {
+    running: Cell<bool>,
+}
+
+impl PwmPin for Motor {
+    fn start(&self, _frequency_hz: usize, _duty_cycle: usize) -> Result<(), ErrorCode> {
+        self.running.set(true);
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), ErrorCode> {
+        self.running.set(false);
+        Ok(())
+    }
+
+    fn get_maximum_frequency_hz(&self) -> usize {
+        20_000
+    }
+
+    fn get_maximum_duty_cycle(&self) -> usize {
+        1000
+    }
+}
+
+/// Feed `ms` of samples with the given acceleration, and count the falls
+/// reported.
+fn feed(detector: &mut FallDetector, ms: u32, (x, y, z): (i32, i32, i32)) -> usize {
+    (0..ms * SAMPLE_RATE_HZ / 1000)
+        .filter(|_| detector.sample(x, y, z))
+        .count()
+}
+
+#[test]
+fn fall_is_reported_once() {
+    let mut detector = FallDetector::new();
+    assert_eq!(feed(&mut detector, 1000, (20, -30, 1000)), 0);
+    assert_eq!(feed(&mut detector, 300, (10, 40, 60)), 1);
+    // The landing, then still again
+    assert_eq!(feed(&mut detector, 20, (900, 300, 2500)), 0);
+    assert_eq!(feed(&mut detector, 500, (20, -30, 1000)), 0);
+    assert_eq!(feed(&mut detector, 300, (0, 0, 100)), 1);
+}
+
+#[test]
+fn short_dips_are_not_falls() {
+    let mut detector = FallDetector::new();
+    for _ in 0..20 {
+        assert_eq!(feed(&mut detector, 20, (0, 100, 200)), 0);
+        assert_eq!(feed(&mut detector, 100, (0, 0, 1000)), 0);
+    }
+}
+
+#[test]
+fn interrupt_takes_the_action_first() {
+    let pin = leak(MockPin::new());
+    let free_fall = leak(FreeFall::new(
+        Some(pin),
+        create_grant(free_fall::DRIVER_NUM),
+    ));
+    kernel::hil::gpio::Interrupt::set_client(pin, free_fall);
+    let action = leak(Action::default());
+    free_fall.set_action(action);
+    free_fall.start();
+    assert!(matches!(
+        pin.interrupt_edge(),
+        Some(InterruptEdge::RisingEdge)
+    ));
+
+    pin.set_input(true);
+    assert_eq!(action.count.get(), 1);
+    pin.set_input(false);
+    assert_eq!(action.count.get(), 1);
+    pin.set_input(true);
+    assert_eq!(action.count.get(), 2);
+}
+
+#[test]
+fn stop_pwm_parks_the_motors() {
+    let left = leak(Motor::default());
+    let right = leak(Motor::default());
+    let pins: &'static [&'static dyn PwmPin; 2] = leak([left, right]);
+    left.start(1000, 500).unwrap();
+    right.start(1000, 500).unwrap();
+
+    StopPwm::new(pins).free_fall();
+    assert!(!left.running.get() && !right.running.get());
+}
@@ -0,0 +1,184 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::RefCell;
+
+use capsules_extra::ft6x06::{Ft6x06, NO_TOUCH};
+use kernel::hil::gpio::{Interrupt, InterruptEdge};
+use kernel::hil::touch::{
+    Gesture, GestureClient, GestureEvent, MultiTouch, MultiTouchClient, Touch, TouchClient,
+    TouchEvent, TouchStatus,
+};
+
+use crate::gpio::MockPin;
+use crate::i2c::{MockI2CDevice, Transfer};
+use crate::{leak, static_buffer};
+
+/// Status, x, y and id of a touch.
+type Touched = (&'static str, u16, u16, usize);
+
+fn touched(event: &TouchEvent) -> Touched {
+    let status = match event.status {
+        TouchStatus::Pressed => "pressed",
+        TouchStatus::Released => "released",
+        TouchStatus::Moved => "moved",
+        TouchStatus::Unstarted => "unstarted",
+    };
+    (status, event.x, event.y, event.id)
+}
+
+#[derive(Default)]
+struct Client {
+    touches: RefCell<Vec<Touched>>,
+    multi_touches: RefCell<Vec<Vec<Touched>>>,
+    gestures: RefCell<Vec<&'static str>>,
+}
+
+impl TouchClient for Client {
+    fn touch_event(&self, event: TouchEvent) {
+        self.touches.borrow_mut().push(touched(&event));
+    }
+}
+
+impl MultiTouchClient for Client {
+    fn touch_events(&self, events: &[TouchEvent], len: usize) {
+        self.multi_touches
+            .borrow_mut()
+            .push(events[..len].iter().map(touched).collect());
+    }
+}
+
+impl GestureClient for Client {
+    fn gesture_event(&self, event: GestureEvent) {
+        self.gestures.borrow_mut().push(match event {
+            GestureEvent::SwipeUp => "up",
+            GestureEvent::SwipeDown => "down",
+            GestureEvent::SwipeLeft => "left",
+            GestureEvent::SwipeRight => "right",
+            GestureEvent::ZoomIn => "in",
+            GestureEvent::ZoomOut => "out",
+        });
+    }
+}
+
+struct Setup {
+    pin: &'static MockPin<'static>,
+    i2c: &'static MockI2CDevice<'static>,
+    client: &'static Client,
+}
+
+fn setup() -> Setup {
+    let pin = leak(MockPin::new());
+    let i2c = leak(MockI2CDevice::new());
+    let events = Box::leak(Box::new([NO_TOUCH, NO_TOUCH]));
+    let ft6x06 = leak(Ft6x06::new(i2c, pin, static_buffer(17), events));
+    pin.set_client(ft6x06);
+    i2c.set_client(ft6x06);
+
+    let client = leak(Client::default());
+    Touch::set_client(ft6x06, client);
+    MultiTouch::set_client(ft6x06, client);
+    Gesture::set_client(ft6x06, client);
+    pin.set_input(true);
+    Setup { pin, i2c, client }
+}
+
+/// Registers from `GEST_ID` on, with touch 1 and touch 2.
+fn registers(gesture: u8, touches: &[(u8, u16, u16, u8)]) -> Vec<u8> {
+    let mut registers = vec![gesture, touches.len() as u8];
+    for &(event, x, y, id) in touches {
+        registers.extend_from_slice(&[
+            event << 6 | (x >> 8) as u8,
+            x as u8,
+            id << 4 | (y >> 8) as u8,
+            y as u8,
+            0x40,
+            0x10,
+        ]);
+    }
+    registers.resize(15, 0xFF);
+    registers
+}
+
+#[test]
+fn reads_touch_registers_on_falling_edge() {
+    let setup = setup();
+    assert!(matches!(
+        setup.pin.interrupt_edge(),
+        Some(InterruptEdge::FallingEdge)
+    ));
+
+    setup.pin.set_input(false);
+    assert_eq!(
+        setup.i2c.take_transfers(),
+        [Transfer::WriteRead(vec![0x01], 15)]
+    );
+    // No new transfer is started until this one completes.
+    assert!(setup.pin.interrupt_edge().is_none());
+}
+
+#[test]
+fn reports_single_touch() {
+    let setup = setup();
+    setup.pin.set_input(false);
+    setup
+        .i2c
+        .queue_response(&registers(0x00, &[(0b00, 0x123, 0x0AB, 0)]));
+    assert!(setup.i2c.complete());
+
+    assert_eq!(
+        *setup.client.touches.borrow(),
+        [("pressed", 0x123, 0x0AB, 0)]
+    );
+    assert!(setup.client.gestures.borrow().is_empty());
+    // Interrupts are enabled again for the next touch.
+    assert!(matches!(
+        setup.pin.interrupt_edge(),
+        Some(InterruptEdge::FallingEdge)
+    ));
+}
+
+#[test]
+fn reports_both_touches_to_multi_touch_client() {
+    let setup = setup();
+    setup.pin.set_input(false);
+    setup.i2c.queue_response(&registers(
+        0x00,
+        &[(0b10, 100, 200, 0), (0b00, 300, 400, 1)],
+    ));
+    setup.i2c.complete();
+
+    assert_eq!(
+        *setup.client.multi_touches.borrow(),
+        [vec![("moved", 100, 200, 0), ("pressed", 300, 400, 1)]]
+    );
+}
+
+#[test]
+fn reports_gestures() {
+    let setup = setup();
+    for (gesture, name) in [(0x10, "up"), (0x1C, "left"), (0x49, "out")] {
+        setup.pin.set_input(true);
+        setup.pin.set_input(false);
+        setup
+            .i2c
+            .queue_response(&registers(gesture, &[(0b01, 0, 0, 0)]));
+        setup.i2c.complete();
+        assert_eq!(setup.client.gestures.borrow().last(), Some(&name));
+    }
+}
+
+#[test]
+fn ignores_more_than_two_touches() {
+    let setup = setup();
+    setup.pin.set_input(false);
+    let mut registers = registers(0x10, &[(0b00, 1, 1, 0), (0b00, 2, 2, 1)]);
+    registers[1] = 3;
+    setup.i2c.queue_response(&registers);
+    setup.i2c.complete();
+
+    assert!(setup.client.touches.borrow().is_empty());
+    assert!(setup.client.multi_touches.borrow().is_empty());
+    assert!(setup.client.gestures.borrow().is_empty());
+}
@@ -0,0 +1,99 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::Cell;
+
+use capsules_extra::gpio_debounce::DebouncedPin;
+use kernel::hil::gpio::{Client, Interrupt, InterruptEdge};
+use kernel::hil::time::Alarm;
+
+use crate::alarm::MockAlarm;
+use crate::gpio::MockPin;
+use crate::leak;
+
+#[derive(Default)]
+struct Counter {
+    fired: Cell<usize>,
+}
+
+impl Client for Counter {
+    fn fired(&self) {
+        self.fired.set(self.fired.get() + 1);
+    }
+}
+
+type Debounced = DebouncedPin<'static, MockPin<'static>, MockAlarm<'static>>;
+
+fn setup(
+    edge: InterruptEdge,
+) -> (
+    &'static MockPin<'static>,
+    &'static MockAlarm<'static>,
+    &'static Debounced,
+    &'static Counter,
+) {
+    let pin = leak(MockPin::new());
+    let alarm = leak(MockAlarm::new());
+    let debounced = leak(DebouncedPin::new(pin, alarm, 20));
+    pin.set_client(debounced);
+    alarm.set_alarm_client(debounced);
+    let counter = leak(Counter::default());
+    debounced.set_client(counter);
+    debounced.enable_interrupts(edge);
+    (pin, alarm, debounced, counter)
+}
+
+#[test]
+fn bounces_are_reported_once() {
+    let (pin, alarm, _, counter) = setup(InterruptEdge::RisingEdge);
+    // Every edge of the raw signal restarts the window.
+    assert!(matches!(
+        pin.interrupt_edge(),
+        Some(InterruptEdge::EitherEdge)
+    ));
+
+    for level in [true, false, true, false, true] {
+        pin.set_input(level);
+        alarm.advance(3);
+    }
+    assert_eq!(alarm.remaining(), Some(17));
+    assert_eq!(counter.fired.get(), 0);
+
+    alarm.advance(17);
+    assert_eq!(counter.fired.get(), 1);
+    assert!(!alarm.is_armed());
+}
+
+#[test]
+fn bounce_back_is_not_reported() {
+    let (pin, alarm, _, counter) = setup(InterruptEdge::EitherEdge);
+    pin.set_input(true);
+    alarm.advance(5);
+    pin.set_input(false);
+    alarm.advance(100);
+    assert_eq!(counter.fired.get(), 0);
+}
+
+#[test]
+fn only_enabled_edge_is_reported() {
+    let (pin, alarm, _, counter) = setup(InterruptEdge::FallingEdge);
+    pin.set_input(true);
+    alarm.advance(20);
+    assert_eq!(counter.fired.get(), 0);
+
+    pin.set_input(false);
+    alarm.advance(20);
+    assert_eq!(counter.fired.get(), 1);
+}
+
+#[test]
+fn disabling_interrupts_cancels_the_window() {
+    let (pin, alarm, debounced, counter) = setup(InterruptEdge::EitherEdge);
+    pin.set_input(true);
+    debounced.disable_interrupts();
+    assert!(!alarm.is_armed());
+    assert!(pin.interrupt_edge().is_none());
+    alarm.advance(100);
+    assert_eq!(counter.fired.get(), 0);
+}
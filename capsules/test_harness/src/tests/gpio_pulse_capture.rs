@@ -0,0 +1,90 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::RefCell;
+
+use capsules_extra::gpio_pulse_capture::GpioPulseCapture;
+use kernel::hil::gpio::{Interrupt, InterruptEdge};
+use kernel::hil::pulse_capture::{Edge, PulseCapture, PulseCaptureClient};
+use kernel::ErrorCode;
+
+use crate::alarm::MockAlarm;
+use crate::gpio::MockPin;
+use crate::leak;
+
+#[derive(Default)]
+struct Edges {
+    edges: RefCell<Vec<(Edge, u32)>>,
+}
+
+impl PulseCaptureClient for Edges {
+    fn edge(&self, edge: Edge, timestamp_us: u32) {
+        self.edges.borrow_mut().push((edge, timestamp_us));
+    }
+}
+
+type Capture = GpioPulseCapture<'static, MockPin<'static>, MockAlarm<'static>>;
+
+fn setup() -> (
+    &'static MockPin<'static>,
+    &'static MockAlarm<'static>,
+    &'static Capture,
+    &'static Edges,
+) {
+    let pin = leak(MockPin::new());
+    let clock = leak(MockAlarm::new());
+    let capture = leak(GpioPulseCapture::new(pin, clock));
+    pin.set_client(capture);
+    let edges = leak(Edges::default());
+    capture.set_client(edges);
+    (pin, clock, capture, edges)
+}
+
+#[test]
+fn edges_are_timestamped() {
+    let (pin, clock, capture, edges) = setup();
+    assert_eq!(capture.start(), Ok(()));
+    assert!(matches!(
+        pin.interrupt_edge(),
+        Some(InterruptEdge::EitherEdge)
+    ));
+
+    // The mock clock ticks in milliseconds
+    clock.advance(2);
+    pin.set_input(true);
+    clock.advance(3);
+    pin.set_input(false);
+    assert_eq!(
+        *edges.edges.borrow(),
+        [(Edge::Rising, 2000), (Edge::Falling, 5000)]
+    );
+}
+
+#[test]
+fn timestamps_wrap_around() {
+    let (pin, clock, capture, edges) = setup();
+    capture.start().unwrap();
+    // 2 * 2147484 ms is just over u32::MAX microseconds
+    clock.advance(2147484);
+    pin.set_input(true);
+    clock.advance(2147484);
+    pin.set_input(false);
+    assert_eq!(
+        *edges.edges.borrow(),
+        [(Edge::Rising, 2147484000), (Edge::Falling, 704)]
+    );
+}
+
+#[test]
+fn stopped_capture_reports_nothing() {
+    let (pin, _, capture, edges) = setup();
+    assert_eq!(capture.stop(), Err(ErrorCode::OFF));
+    capture.start().unwrap();
+    assert_eq!(capture.start(), Err(ErrorCode::ALREADY));
+    assert_eq!(capture.stop(), Ok(()));
+    assert!(pin.interrupt_edge().is_none());
+
+    pin.set_input(true);
+    assert!(edges.edges.borrow().is_empty());
+}
@@ -0,0 +1,444 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::RefCell;
+
+use capsules_extra::net::ipv4::stack::{self, Ipv4Stack, UdpClient, UdpSocket};
+use capsules_extra::net::ipv4::{Ipv4Addr, Ipv4Config};
+use kernel::hil::ethernet::EthernetAdapter;
+use kernel::hil::mac_address::MacAddress;
+use kernel::hil::time::Alarm;
+use kernel::ErrorCode;
+
+use crate::alarm::MockAlarm;
+use crate::ethernet::MockEthernet;
+use crate::{leak, static_buffer};
+
+type Stack = Ipv4Stack<'static, MockEthernet<'static>, MockAlarm<'static>>;
+
+const MAC: MacAddress = MacAddress::new([0x02, 0, 0, 0, 0, 0x01]);
+const ADDRESS: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 50);
+const GATEWAY: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 1);
+const PEER_MAC: MacAddress = MacAddress::new([0x02, 0, 0, 0, 0, 0x02]);
+const PEER: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 10);
+const PORT: u16 = 5000;
+
+const CONFIG: Ipv4Config = Ipv4Config {
+    address: ADDRESS,
+    netmask: Ipv4Addr::new(255, 255, 255, 0),
+    gateway: Some(GATEWAY),
+};
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_ARP: u16 = 0x0806;
+
+type Datagram = (Ipv4Addr, u16, u16, Vec<u8>);
+
+#[derive(Default)]
+struct Datagrams {
+    received: RefCell<Vec<Datagram>>,
+    sent: RefCell<Vec<Result<(), ErrorCode>>>,
+}
+
+impl UdpClient for Datagrams {
+    fn receive(&self, src_addr: Ipv4Addr, src_port: u16, dst_port: u16, payload: &[u8]) {
+        self.received
+            .borrow_mut()
+            .push((src_addr, src_port, dst_port, payload.to_vec()));
+    }
+
+    fn send_done(&self, result: Result<(), ErrorCode>) {
+        self.sent.borrow_mut().push(result);
+    }
+}
+
+struct Setup {
+    ethernet: &'static MockEthernet<'static>,
+    alarm: &'static MockAlarm<'static>,
+    stack: &'static Stack,
+    socket: &'static UdpSocket<'static>,
+    datagrams: &'static Datagrams,
+}
+
+impl Setup {
+    /// Receive `packet` from the peer.
+    fn receive(&self, packet: &[u8]) {
+        self.ethernet
+            .receive(&ethernet_frame(MAC, PEER_MAC, ETHERTYPE_IPV4, packet));
+    }
+
+    fn take_received(&self) -> Vec<Datagram> {
+        self.datagrams.received.take()
+    }
+
+    /// Send the frame transmitted, which must be the only one.
+    fn transmit(&self) -> Vec<u8> {
+        let mut frames = self.ethernet.take_transmitted();
+        assert_eq!(frames.len(), 1);
+        assert!(self.ethernet.complete_transmit(Ok(())));
+        frames.remove(0)
+    }
+}
+
+fn setup() -> Setup {
+    let ethernet = leak(MockEthernet::new(MAC));
+    let alarm = leak(MockAlarm::new());
+    let stack = leak(Ipv4Stack::new(
+        ethernet,
+        alarm,
+        CONFIG,
+        static_buffer(stack::FRAME_BUF_LEN),
+        static_buffer(stack::ARP_BUF_LEN),
+    ));
+    ethernet.set_receive_client(stack);
+    ethernet.set_transmit_client(stack);
+    alarm.set_alarm_client(stack);
+    assert_eq!(stack.start(), Ok(()));
+    assert!(stack.link_up());
+
+    let datagrams = leak(Datagrams::default());
+    let socket = leak(UdpSocket::new());
+    socket.set_client(datagrams);
+    stack.add_socket(socket);
+    assert_eq!(stack.bind(socket, PORT), Ok(()));
+    Setup {
+        ethernet,
+        alarm,
+        stack,
+        socket,
+        datagrams,
+    }
+}
+
+/// The ones' complement checksum of `data`, zero if it includes a valid
+/// checksum.
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|chunk| u16::from_be_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)]) as u32)
+        .sum();
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn ethernet_frame(dst: MacAddress, src: MacAddress, ethertype: u16, payload: &[u8]) -> Vec<u8> {
+    [
+        &dst.as_bytes()[..],
+        src.as_bytes(),
+        &ethertype.to_be_bytes(),
+        payload,
+    ]
+    .concat()
+}
+
+/// Fill in the checksum of the IPv4 header of `packet`.
+fn set_header_checksum(packet: &mut [u8]) {
+    let header_len = (packet[0] & 0x0F) as usize * 4;
+    packet[10..12].copy_from_slice(&[0, 0]);
+    let checksum = checksum(&packet[..header_len]);
+    packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+}
+
+/// An IPv4 packet, with the Don't Fragment flag.
+fn ipv4(protocol: u8, src: Ipv4Addr, dst: Ipv4Addr, payload: &[u8]) -> Vec<u8> {
+    let total_len = (20 + payload.len()) as u16;
+    let mut packet = [
+        &[0x45, 0][..],
+        &total_len.to_be_bytes(),
+        &[0x12, 0x34, 0x40, 0x00, 64, protocol, 0, 0],
+        &src.0,
+        &dst.0,
+        payload,
+    ]
+    .concat();
+    set_header_checksum(&mut packet);
+    packet
+}
+
+fn udp(src: Ipv4Addr, dst: Ipv4Addr, src_port: u16, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+    let udp_len = (8 + payload.len()) as u16;
+    let mut segment = [
+        &src_port.to_be_bytes()[..],
+        &dst_port.to_be_bytes(),
+        &udp_len.to_be_bytes(),
+        &[0, 0],
+        payload,
+    ]
+    .concat();
+    let pseudo_header = [&src.0[..], &dst.0, &[0, 17], &udp_len.to_be_bytes()].concat();
+    let checksum = checksum(&[pseudo_header, segment.clone()].concat());
+    segment[6..8].copy_from_slice(&checksum.to_be_bytes());
+    ipv4(17, src, dst, &segment)
+}
+
+/// `packet` changed by `change`, with a valid header checksum.
+fn modified(packet: &[u8], change: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+    let mut packet = packet.to_vec();
+    change(&mut packet);
+    set_header_checksum(&mut packet);
+    packet
+}
+
+fn arp(operation: u16, sender: (MacAddress, Ipv4Addr), target: (MacAddress, Ipv4Addr)) -> Vec<u8> {
+    [
+        &[0, 1, 0x08, 0x00, 6, 4][..],
+        &operation.to_be_bytes(),
+        sender.0.as_bytes(),
+        &sender.1 .0,
+        target.0.as_bytes(),
+        &target.1 .0,
+    ]
+    .concat()
+}
+
+#[test]
+fn datagrams_are_given_to_the_socket_bound_to_their_port() {
+    let s = setup();
+    s.receive(&udp(PEER, ADDRESS, 4000, PORT, b"hello"));
+    assert_eq!(s.take_received(), [(PEER, 4000, PORT, b"hello".to_vec())]);
+
+    // Datagrams for other ports only reach a socket bound to any port
+    s.receive(&udp(PEER, ADDRESS, 4000, PORT + 1, b"hello"));
+    assert!(s.take_received().is_empty());
+    let any = leak(UdpSocket::new());
+    let any_datagrams = leak(Datagrams::default());
+    any.set_client(any_datagrams);
+    s.stack.add_socket(any);
+    s.stack.bind_any(any);
+    s.receive(&udp(PEER, ADDRESS, 4000, PORT + 1, b"hello"));
+    s.receive(&udp(PEER, ADDRESS, 4000, PORT, b"bound"));
+    assert_eq!(
+        any_datagrams.received.take(),
+        [(PEER, 4000, PORT + 1, b"hello".to_vec())]
+    );
+    assert_eq!(s.take_received(), [(PEER, 4000, PORT, b"bound".to_vec())]);
+
+    // Broadcasts, and a datagram without a checksum
+    s.receive(&udp(PEER, Ipv4Addr::BROADCAST, 68, PORT, b"all"));
+    s.receive(&udp(
+        PEER,
+        Ipv4Addr::new(192, 168, 1, 255),
+        68,
+        PORT,
+        b"subnet",
+    ));
+    s.receive(&modified(&udp(PEER, ADDRESS, 4000, PORT, b"none"), |p| {
+        p[26..28].copy_from_slice(&[0, 0])
+    }));
+    assert_eq!(s.take_received().len(), 3);
+
+    // Datagrams for another host
+    s.receive(&udp(
+        PEER,
+        Ipv4Addr::new(192, 168, 1, 51),
+        4000,
+        PORT,
+        b"other",
+    ));
+    assert!(s.take_received().is_empty());
+}
+
+#[test]
+fn malformed_packets_are_dropped() {
+    let s = setup();
+    let valid = udp(PEER, ADDRESS, 4000, PORT, b"data");
+    let malformed = [
+        // Not IPv4
+        modified(&valid, |p| p[0] = 0x65),
+        // A header shorter than 20 bytes, or longer than the packet
+        modified(&valid, |p| p[0] = 0x44),
+        [&[0x4F][..], &valid[1..]].concat(),
+        // A total length shorter than the header, or longer than the packet
+        modified(&valid, |p| p[2..4].copy_from_slice(&19u16.to_be_bytes())),
+        modified(&valid, |p| p[2..4].copy_from_slice(&33u16.to_be_bytes())),
+        // A UDP length longer than the packet, or shorter than its header.
+        // Without a UDP checksum, only the length is wrong.
+        modified(&valid, |p| {
+            p[24..26].copy_from_slice(&13u16.to_be_bytes());
+            p[26..28].copy_from_slice(&[0, 0]);
+        }),
+        modified(&valid, |p| {
+            p[24..26].copy_from_slice(&7u16.to_be_bytes());
+            p[26..28].copy_from_slice(&[0, 0]);
+        }),
+        // Packets cut short
+        valid[..19].to_vec(),
+        valid[..27].to_vec(),
+    ];
+    for packet in malformed {
+        s.receive(&packet);
+        assert!(s.take_received().is_empty(), "{:02x?}", packet);
+    }
+    // A frame shorter than the Ethernet header
+    s.ethernet.receive(&[0xFF; 13]);
+
+    s.receive(&valid);
+    assert_eq!(s.take_received().len(), 1);
+}
+
+#[test]
+fn header_options_and_frame_padding_are_skipped() {
+    let s = setup();
+    let packet = udp(PEER, ADDRESS, 4000, PORT, b"data");
+    let mut with_options = modified(&packet, |p| {
+        p[0] = 0x46;
+        p[2..4].copy_from_slice(&36u16.to_be_bytes());
+        p.splice(20..20, [0x01; 4]);
+    });
+    // Padding of a minimum size frame
+    with_options.extend_from_slice(&[0; 10]);
+    s.receive(&with_options);
+    assert_eq!(s.take_received(), [(PEER, 4000, PORT, b"data".to_vec())]);
+}
+
+#[test]
+fn fragments_are_not_reassembled() {
+    let s = setup();
+    let valid = udp(PEER, ADDRESS, 4000, PORT, b"data");
+    // The first fragment, with More Fragments, and the last one, at an
+    // offset
+    s.receive(&modified(&valid, |p| {
+        p[6..8].copy_from_slice(&[0x20, 0x00])
+    }));
+    s.receive(&modified(&valid, |p| {
+        p[6..8].copy_from_slice(&[0x00, 0x01])
+    }));
+    assert!(s.take_received().is_empty());
+
+    // Neither Don't Fragment nor More Fragments
+    s.receive(&modified(&valid, |p| p[6..8].copy_from_slice(&[0, 0])));
+    assert_eq!(s.take_received().len(), 1);
+}
+
+#[test]
+fn checksum_errors_are_dropped() {
+    let s = setup();
+    let valid = udp(PEER, ADDRESS, 4000, PORT, b"data");
+
+    let mut header_error = valid.clone();
+    header_error[8] -= 1;
+    let mut payload_error = valid.clone();
+    *payload_error.last_mut().unwrap() ^= 0x01;
+    // The UDP checksum covers the source address, in the pseudo header
+    let spoofed = modified(&valid, |p| p[15] = 11);
+    for packet in [header_error, payload_error, spoofed] {
+        s.receive(&packet);
+        assert!(s.take_received().is_empty());
+    }
+}
+
+#[test]
+fn echo_requests_are_answered() {
+    let s = setup();
+    let mut request = vec![
+        8, 0, 0, 0, 0x12, 0x34, 0x00, 0x01, b'p', b'i', b'n', b'g', b'!',
+    ];
+    let request_checksum = checksum(&request);
+    request[2..4].copy_from_slice(&request_checksum.to_be_bytes());
+    s.receive(&ipv4(1, PEER, ADDRESS, &request));
+
+    let frame = s.transmit();
+    assert_eq!(
+        frame[..14],
+        ethernet_frame(PEER_MAC, MAC, ETHERTYPE_IPV4, &[])
+    );
+    let packet = &frame[14..];
+    assert_eq!(packet.len(), 20 + request.len());
+    assert_eq!(checksum(&packet[..20]), 0);
+    assert_eq!(
+        u16::from_be_bytes([packet[2], packet[3]]) as usize,
+        packet.len()
+    );
+    assert_eq!(packet[9], 1);
+    assert_eq!((&packet[12..16], &packet[16..20]), (&ADDRESS.0[..], &PEER.0[..]));
+    // An echo reply with the identifier, sequence number and data of the
+    // request
+    let reply = &packet[20..];
+    assert_eq!(reply[..2], [0, 0]);
+    assert_eq!(reply[4..], request[4..]);
+    assert_eq!(checksum(reply), 0);
+    // The reply is not a datagram of a socket
+    assert!(s.datagrams.sent.borrow().is_empty());
+
+    // Only requests for the address of the interface are answered, and
+    // replies are not
+    s.receive(&ipv4(1, PEER, Ipv4Addr::BROADCAST, &request));
+    let mut reply = request.clone();
+    reply[0] = 0;
+    s.receive(&ipv4(1, PEER, ADDRESS, &reply));
+    s.receive(&ipv4(1, PEER, ADDRESS, &request[..7]));
+    assert!(s.ethernet.take_transmitted().is_empty());
+}
+
+#[test]
+fn datagrams_wait_for_the_mac_address_of_the_next_hop() {
+    let s = setup();
+    assert_eq!(s.stack.send(s.socket, PORT, PEER, 4000, b"hello"), Ok(()));
+    assert_eq!(
+        s.stack.send(s.socket, PORT, PEER, 4000, b"again"),
+        Err(ErrorCode::BUSY)
+    );
+    let request = s.transmit();
+    assert_eq!(
+        request[..42],
+        ethernet_frame(
+            MacAddress::new([0xFF; 6]),
+            MAC,
+            ETHERTYPE_ARP,
+            &arp(1, (MAC, ADDRESS), (MacAddress::default(), PEER)),
+        )
+    );
+    assert_eq!(request.len(), stack::ARP_BUF_LEN);
+
+    s.ethernet.receive(&ethernet_frame(
+        MAC,
+        PEER_MAC,
+        ETHERTYPE_ARP,
+        &arp(2, (PEER_MAC, PEER), (MAC, ADDRESS)),
+    ));
+    assert!(s.alarm.remaining().is_none());
+    let frame = s.transmit();
+    assert_eq!(
+        frame[..14],
+        ethernet_frame(PEER_MAC, MAC, ETHERTYPE_IPV4, &[])
+    );
+    // The datagram, but for the identification of the packet
+    let expected = udp(ADDRESS, PEER, PORT, 4000, b"hello");
+    let packet = modified(&frame[14..], |p| p[4..6].copy_from_slice(&[0x12, 0x34]));
+    assert_eq!(packet, expected);
+    assert_eq!(s.datagrams.sent.take(), [Ok(())]);
+
+    // The MAC address is remembered
+    assert_eq!(s.stack.send(s.socket, PORT, PEER, 4000, b"again"), Ok(()));
+    assert_eq!(s.transmit()[..6], *PEER_MAC.as_bytes());
+    assert_eq!(s.datagrams.sent.take(), [Ok(())]);
+}
+
+#[test]
+fn datagrams_fail_if_the_next_hop_does_not_answer() {
+    let s = setup();
+    // Outside of the subnet, the next hop is the gateway
+    let remote = Ipv4Addr::new(10, 0, 0, 1);
+    assert_eq!(s.stack.send(s.socket, PORT, remote, 4000, b"hello"), Ok(()));
+    for _ in 0..3 {
+        let request = s.transmit();
+        assert_eq!(request[14 + 24..14 + 28], GATEWAY.0);
+        assert!(s.datagrams.sent.borrow().is_empty());
+        assert_eq!(s.alarm.remaining(), Some(500));
+        s.alarm.advance(500);
+    }
+    assert!(s.ethernet.take_transmitted().is_empty());
+    assert_eq!(s.datagrams.sent.take(), [Err(ErrorCode::NOACK)]);
+
+    s.stack.set_config(Ipv4Config {
+        gateway: None,
+        ..CONFIG
+    });
+    assert_eq!(
+        s.stack.send(s.socket, PORT, remote, 4000, b"hello"),
+        Err(ErrorCode::INVAL)
+    );
+}
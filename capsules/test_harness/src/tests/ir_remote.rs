@@ -0,0 +1,112 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use capsules_extra::ir_remote::{NecCode, NecDecoder};
+
+/// The marks and spaces of a frame, as `(mark, duration_us)` edges: the
+/// leader mark starts after `gap_us` of silence.
+fn frame_edges(frame: u32, gap_us: u32) -> Vec<(bool, u32)> {
+    let mut edges = vec![(true, gap_us), (false, 9000)];
+    let mut space = 4500;
+    for bit in 0..32 {
+        edges.push((true, space));
+        edges.push((false, 562));
+        space = if frame >> bit & 1 == 1 { 1687 } else { 562 };
+    }
+    edges.push((true, space));
+    edges.push((false, 562));
+    edges
+}
+
+fn repeat_edges(gap_us: u32) -> Vec<(bool, u32)> {
+    vec![(true, gap_us), (false, 9000), (true, 2250), (false, 562)]
+}
+
+fn decode(decoder: &mut NecDecoder, edges: &[(bool, u32)]) -> Vec<NecCode> {
+    edges
+        .iter()
+        .filter_map(|(mark, duration_us)| decoder.edge(*mark, *duration_us))
+        .collect()
+}
+
+fn code(address: u16, command: u8, extended: bool) -> NecCode {
+    NecCode {
+        address,
+        command,
+        extended,
+        repeat: false,
+    }
+}
+
+#[test]
+fn frames_carry_inverted_bytes() {
+    assert_eq!(code(0x04, 0x08, false).frame(), 0xF708FB04);
+    assert_eq!(code(0x1234, 0x08, true).frame(), 0xF7081234);
+    assert_eq!(
+        NecCode::from_frame(0xF708FB04),
+        Some(code(0x04, 0x08, false))
+    );
+    assert_eq!(
+        NecCode::from_frame(0xF7081234),
+        Some(code(0x1234, 0x08, true))
+    );
+    // The command and its inverse do not match
+    assert_eq!(NecCode::from_frame(0xF608FB04), None);
+}
+
+#[test]
+fn frame_is_decoded() {
+    let mut decoder = NecDecoder::new();
+    let sent = code(0x04, 0x08, false);
+    assert_eq!(
+        decode(&mut decoder, &frame_edges(sent.frame(), 500000)),
+        [sent]
+    );
+}
+
+#[test]
+fn timing_tolerates_the_receiver() {
+    // Receivers stretch marks and shorten spaces
+    let mut decoder = NecDecoder::new();
+    let sent = code(0xA5, 0x3C, false);
+    let edges: Vec<_> = frame_edges(sent.frame(), 500000)
+        .into_iter()
+        .map(|(mark, us)| (mark, if mark { us - 120 } else { us + 120 }))
+        .collect();
+    assert_eq!(decode(&mut decoder, &edges), [sent]);
+}
+
+#[test]
+fn repeat_codes_repeat_the_last_frame() {
+    let mut decoder = NecDecoder::new();
+    let sent = code(0x1234, 0x42, true);
+    let mut edges = frame_edges(sent.frame(), 500000);
+    edges.extend(repeat_edges(40000));
+    edges.extend(repeat_edges(96000));
+    let repeated = NecCode {
+        repeat: true,
+        ..sent
+    };
+    assert_eq!(decode(&mut decoder, &edges), [sent, repeated, repeated]);
+
+    // Too long after the last code, a repeat code repeats nothing
+    assert!(decode(&mut decoder, &repeat_edges(500000)).is_empty());
+}
+
+#[test]
+fn broken_frames_are_dropped() {
+    let mut decoder = NecDecoder::new();
+    let sent = code(0x04, 0x08, false);
+    let mut edges = frame_edges(sent.frame(), 500000);
+    // A bit space of 1 ms is neither a 0 nor a 1
+    edges[10].1 = 1000;
+    assert!(decode(&mut decoder, &edges).is_empty());
+    assert!(decode(&mut decoder, &repeat_edges(40000)).is_empty());
+
+    // The next frame is decoded
+    assert_eq!(
+        decode(&mut decoder, &frame_edges(sent.frame(), 40000)),
+        [sent]
+    );
+}
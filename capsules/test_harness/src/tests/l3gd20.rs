@@ -0,0 +1,77 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use capsules_extra::l3gd20::{self, L3gd20Spi};
+use kernel::hil::sensors::{NineDof, TemperatureDriver};
+use kernel::hil::spi::{ClockPhase, ClockPolarity, SpiMasterDevice};
+use kernel::ErrorCode;
+
+use crate::spi::{MockSpiMaster, Transfer};
+use crate::{create_grant, leak};
+
+fn setup() -> (&'static MockSpiMaster<'static>, &'static L3gd20Spi<'static>) {
+    let spi = leak(MockSpiMaster::new());
+    let l3gd20 = leak(L3gd20Spi::new(
+        spi,
+        Box::leak(Box::new([0; l3gd20::TX_BUF_LEN])),
+        Box::leak(Box::new([0; l3gd20::RX_BUF_LEN])),
+        create_grant(l3gd20::DRIVER_NUM),
+    ));
+    SpiMasterDevice::set_client(spi, l3gd20);
+    (spi, l3gd20)
+}
+
+fn write(bytes: &[u8], read: bool) -> Transfer {
+    Transfer {
+        chip_select: 0,
+        write: bytes.to_vec(),
+        read,
+    }
+}
+
+#[test]
+fn configures_spi_mode_3() {
+    let (spi, l3gd20) = setup();
+    assert_eq!(l3gd20.configure(), Ok(()));
+    assert_eq!(SpiMasterDevice::get_polarity(spi), ClockPolarity::IdleHigh);
+    assert_eq!(SpiMasterDevice::get_phase(spi), ClockPhase::SampleTrailing);
+    assert_eq!(SpiMasterDevice::get_rate(spi), 1_000_000);
+}
+
+#[test]
+fn reads_who_am_i() {
+    let (spi, l3gd20) = setup();
+    l3gd20.is_present();
+    assert_eq!(spi.take_transfers(), [write(&[0x8F, 0x00], true)]);
+}
+
+#[test]
+fn powers_on_all_axes() {
+    let (spi, l3gd20) = setup();
+    l3gd20.power_on();
+    assert_eq!(spi.take_transfers(), [write(&[0x20, 0x0F], false)]);
+}
+
+#[test]
+fn reads_all_axes_in_one_burst() {
+    let (spi, l3gd20) = setup();
+    assert_eq!(l3gd20.read_gyroscope(), Ok(()));
+    // Read, with the address incremented after each byte.
+    assert_eq!(
+        spi.take_transfers(),
+        [write(&[0xE8, 0, 0, 0, 0, 0, 0], true)]
+    );
+    assert_eq!(l3gd20.read_gyroscope(), Err(ErrorCode::BUSY));
+    assert_eq!(
+        TemperatureDriver::read_temperature(l3gd20),
+        Err(ErrorCode::BUSY)
+    );
+}
+
+#[test]
+fn reads_temperature() {
+    let (spi, l3gd20) = setup();
+    assert_eq!(TemperatureDriver::read_temperature(l3gd20), Ok(()));
+    assert_eq!(spi.take_transfers(), [write(&[0xA6, 0x00], true)]);
+}
@@ -0,0 +1,162 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Frames are sent for a process which does not exist, so the responses are
+//! not copied anywhere and only the bus is checked.
+
+use capsules_extra::lin::{self, Lin};
+use kernel::hil::time::Alarm;
+use kernel::hil::uart::{Receive, Transmit};
+use kernel::syscall::SyscallDriver;
+use kernel::{capabilities, create_capability, ErrorCode, Kernel, ProcessId};
+
+use crate::alarm::MockAlarm;
+use crate::uart::MockUart;
+use crate::{create_grant, leak};
+
+type Driver = Lin<'static, MockUart<'static>, MockAlarm<'static>>;
+
+/// The slowest LIN bus, so that a bit lasts one tick of the alarm.
+const BAUD_RATE: u32 = 1000;
+
+struct Setup {
+    uart: &'static MockUart<'static>,
+    alarm: &'static MockAlarm<'static>,
+    driver: &'static Driver,
+    process: ProcessId,
+}
+
+impl Setup {
+    /// Start reading the response of `data_len` bytes to frame `id`, and
+    /// send its header. Returns the bytes sent after the break.
+    fn send_header(&self, id: usize, data_len: usize) -> Vec<u8> {
+        assert!(self
+            .driver
+            .command(2, id, data_len, self.process)
+            .is_success());
+        assert!(self.uart.is_breaking());
+        // Break of 13 bits, then the delimiter
+        assert_eq!(self.alarm.remaining(), Some(13));
+        self.alarm.advance(13);
+        assert!(!self.uart.is_breaking());
+        assert!(self.uart.take_transmitted().is_empty());
+        self.alarm.advance(1);
+        let header = self.uart.take_transmitted();
+        self.uart.complete_transmit();
+        header
+    }
+
+    /// Whether the previous frame is over, in which case another one is
+    /// started.
+    fn is_idle(&self) -> bool {
+        self.driver.command(2, 0x3F, 1, self.process).get_failure() != Some(ErrorCode::BUSY)
+    }
+}
+
+fn setup() -> Setup {
+    let uart = leak(MockUart::new());
+    let alarm = leak(MockAlarm::new());
+    let driver = leak(Lin::new(
+        uart,
+        alarm,
+        BAUD_RATE,
+        create_grant(lin::DRIVER_NUM),
+        Box::leak(Box::new([0; lin::FRAME_LEN])),
+        Box::leak(Box::new([0; lin::FRAME_LEN])),
+    ));
+    uart.set_transmit_client(driver);
+    uart.set_receive_client(driver);
+    alarm.set_alarm_client(driver);
+    assert_eq!(driver.initialize(), Ok(()));
+    assert_eq!(uart.parameters().unwrap().baud_rate, BAUD_RATE);
+
+    let kernel: &'static Kernel = leak(Kernel::new(&[]));
+    let process = ProcessId::new_external(
+        kernel,
+        0,
+        0,
+        &create_capability!(capabilities::ExternalProcessCapability),
+    );
+    Setup {
+        uart,
+        alarm,
+        driver,
+        process,
+    }
+}
+
+#[test]
+fn protected_identifiers_have_two_parity_bits() {
+    // From the table of valid frame identifiers of the LIN specification
+    let pids = [
+        (0x00, 0x80),
+        (0x01, 0xC1),
+        (0x02, 0x42),
+        (0x03, 0x03),
+        (0x10, 0x50),
+        (0x3C, 0x3C),
+        (0x3D, 0x7D),
+        (0x3F, 0xBF),
+    ];
+    for (id, pid) in pids {
+        assert_eq!(lin::protected_id(id), pid, "frame {:#04x}", id);
+    }
+}
+
+#[test]
+fn checksum_is_the_inverted_sum_with_carry() {
+    // The example of the LIN specification, with the classic checksum of
+    // the diagnostic frames
+    let data = [0x4A, 0x55, 0x93, 0xE5];
+    assert_eq!(lin::checksum(0x3C, &data), 0xE6);
+    assert_eq!(lin::checksum(0x7D, &data), 0xE6);
+    // The enhanced checksum of the other frames includes the PID
+    assert_eq!(lin::checksum(0x50, &data), 0x96);
+    // A carry out of every byte
+    assert_eq!(lin::checksum(0xFF, &[0xFF; 8]), 0x00);
+}
+
+#[test]
+fn header_is_a_break_the_sync_byte_and_the_pid() {
+    let s = setup();
+    assert_eq!(s.send_header(0x10, 4), [0x55, 0x50]);
+    // The frame lasts until the response is read back, or times out
+    assert!(!s.is_idle());
+
+    let mut response = vec![0x55, 0x50, 0x4A, 0x55, 0x93, 0xE5];
+    response.push(lin::checksum(0x50, &response[2..]));
+    assert_eq!(s.uart.receive(&response), response.len());
+    assert!(s.is_idle());
+}
+
+#[test]
+fn frame_times_out_without_a_response() {
+    let s = setup();
+    assert_eq!(s.send_header(0x3D, 8), [0x55, 0x7D]);
+
+    // 40% longer than the 10 bits of each byte of the frame
+    s.uart.receive(&[0x55, 0x7D, 0x01]);
+    assert_eq!(s.alarm.remaining(), Some(14 * 11));
+    s.alarm.advance(14 * 11 - 1);
+    assert!(!s.is_idle());
+    s.alarm.advance(1);
+    // The reception was aborted
+    assert_eq!(s.uart.receive(&[0x00]), 0);
+    assert!(s.is_idle());
+}
+
+#[test]
+fn invalid_frames_are_rejected() {
+    let s = setup();
+    for (id, data_len) in [(0x40, 1), (0x10, 0), (0x10, 9)] {
+        assert_eq!(
+            s.driver.command(2, id, data_len, s.process).get_failure(),
+            Some(ErrorCode::INVAL)
+        );
+    }
+    // The data to publish is read from the process
+    assert!(!s.driver.command(1, 0x10, 4, s.process).is_success());
+    assert!(!s.uart.is_breaking());
+    assert!(s.alarm.remaining().is_none());
+}
@@ -0,0 +1,244 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::RefCell;
+
+use capsules_extra::lsm303dlhc::{self, Lsm303dlhcI2C};
+use capsules_extra::lsm303xx::{
+    Lsm303AccelDataRate, Lsm303MagnetoDataRate, Lsm303Range, Lsm303Scale,
+};
+use kernel::hil::i2c::Error;
+use kernel::hil::sensors::{NineDof, NineDofClient, TemperatureClient, TemperatureDriver};
+use kernel::ErrorCode;
+
+use crate::i2c::{MockI2CDevice, Transfer};
+use crate::{create_grant, leak, static_buffer};
+
+#[derive(Default)]
+struct Client {
+    samples: RefCell<Vec<(usize, usize, usize)>>,
+    temperatures: RefCell<Vec<Result<i32, ErrorCode>>>,
+}
+
+impl NineDofClient for Client {
+    fn callback(&self, x: usize, y: usize, z: usize) {
+        self.samples.borrow_mut().push((x, y, z));
+    }
+}
+
+impl TemperatureClient for Client {
+    fn callback(&self, value: Result<i32, ErrorCode>) {
+        self.temperatures.borrow_mut().push(value);
+    }
+}
+
+type Lsm303dlhc = Lsm303dlhcI2C<'static, MockI2CDevice<'static>>;
+
+struct Setup {
+    accelerometer: &'static MockI2CDevice<'static>,
+    magnetometer: &'static MockI2CDevice<'static>,
+    lsm303dlhc: &'static Lsm303dlhc,
+    client: &'static Client,
+}
+
+fn setup() -> Setup {
+    let accelerometer = leak(MockI2CDevice::new());
+    let magnetometer = leak(MockI2CDevice::new());
+    let lsm303dlhc = leak(Lsm303dlhcI2C::new(
+        accelerometer,
+        magnetometer,
+        static_buffer(8),
+        create_grant(lsm303dlhc::DRIVER_NUM),
+    ));
+    accelerometer.set_client(lsm303dlhc);
+    magnetometer.set_client(lsm303dlhc);
+    let client = leak(Client::default());
+    NineDof::set_client(lsm303dlhc, client);
+    TemperatureDriver::set_client(lsm303dlhc, client);
+    Setup {
+        accelerometer,
+        magnetometer,
+        lsm303dlhc,
+        client,
+    }
+}
+
+fn configure(setup: &Setup) -> Result<(), ErrorCode> {
+    setup.lsm303dlhc.configure(
+        Lsm303AccelDataRate::DataRate10Hz,
+        false,
+        Lsm303Scale::Scale4G,
+        true,
+        true,
+        Lsm303MagnetoDataRate::DataRate15_0Hz,
+        Lsm303Range::Range1_3G,
+    )
+}
+
+#[test]
+fn configure_writes_both_sensors_in_order() {
+    let setup = setup();
+    assert_eq!(configure(&setup), Ok(()));
+
+    // CTRL_REG1_A: 10 Hz, normal mode, all axes.
+    assert_eq!(
+        setup.accelerometer.take_transfers(),
+        [Transfer::Write(vec![0x20, 0x27])]
+    );
+    assert!(setup.accelerometer.is_enabled());
+    setup.accelerometer.complete();
+
+    // CTRL_REG4_A: 4 g, high resolution.
+    assert_eq!(
+        setup.accelerometer.take_transfers(),
+        [Transfer::Write(vec![0x23, 0x18])]
+    );
+    setup.accelerometer.complete();
+
+    // CRA_REG_M: temperature sensor on, 15 Hz.
+    assert_eq!(
+        setup.magnetometer.take_transfers(),
+        [Transfer::Write(vec![0x00, 0x90])]
+    );
+    setup.magnetometer.complete();
+
+    // CRB_REG_M: 1.3 gauss.
+    assert_eq!(
+        setup.magnetometer.take_transfers(),
+        [Transfer::Write(vec![0x01, 0x20, 0x00])]
+    );
+    setup.magnetometer.complete();
+
+    assert!(!setup.accelerometer.is_enabled() && !setup.magnetometer.is_enabled());
+    assert!(setup.accelerometer.take_transfers().is_empty());
+    assert!(setup.magnetometer.take_transfers().is_empty());
+    // Idle again.
+    assert_eq!(setup.lsm303dlhc.read_accelerometer(), Ok(()));
+}
+
+#[test]
+fn busy_while_configuring() {
+    let setup = setup();
+    assert_eq!(configure(&setup), Ok(()));
+    assert_eq!(configure(&setup), Err(ErrorCode::BUSY));
+    assert_eq!(setup.lsm303dlhc.read_accelerometer(), Err(ErrorCode::BUSY));
+}
+
+#[test]
+fn reads_acceleration_in_milli_g() {
+    let setup = setup();
+    assert_eq!(setup.lsm303dlhc.read_accelerometer(), Ok(()));
+    // OUT_X_L_A, with the address incremented after each byte.
+    assert_eq!(
+        setup.accelerometer.take_transfers(),
+        [Transfer::WriteRead(vec![0xA8], 6)]
+    );
+
+    // Half of the full scale of 2 g on x, a quarter on y, the negative full
+    // scale on z.
+    setup
+        .accelerometer
+        .queue_response(&[0x00, 0x40, 0x00, 0x20, 0x00, 0x80]);
+    setup.accelerometer.complete();
+    assert_eq!(
+        *setup.client.samples.borrow(),
+        [(1000, 500, -2000isize as usize)]
+    );
+}
+
+#[test]
+fn reads_magnetometer_big_endian() {
+    let setup = setup();
+    assert_eq!(setup.lsm303dlhc.read_magnetometer(), Ok(()));
+    assert_eq!(
+        setup.magnetometer.take_transfers(),
+        [Transfer::WriteRead(vec![0x03], 6)]
+    );
+
+    // X, Z, Y, each high byte first, at 1000 LSB per gauss in the default
+    // range.
+    setup
+        .magnetometer
+        .queue_response(&[0x03, 0xE8, 0x00, 0x0A, 0xFF, 0x9C]);
+    setup.magnetometer.complete();
+    assert_eq!(
+        *setup.client.samples.borrow(),
+        [(100, -10isize as usize, 1)]
+    );
+}
+
+#[test]
+fn reports_zero_on_bus_error() {
+    let setup = setup();
+    setup.lsm303dlhc.read_accelerometer().unwrap();
+    setup.accelerometer.queue_error(Error::DataNak);
+    setup.accelerometer.complete();
+    assert_eq!(*setup.client.samples.borrow(), [(0, 0, 0)]);
+    assert_eq!(setup.lsm303dlhc.read_accelerometer(), Ok(()));
+}
+
+#[test]
+fn reads_temperature() {
+    let setup = setup();
+    assert_eq!(
+        TemperatureDriver::read_temperature(setup.lsm303dlhc),
+        Ok(())
+    );
+    assert_eq!(
+        setup.magnetometer.take_transfers(),
+        [Transfer::WriteRead(vec![0x31], 2)]
+    );
+
+    // 12 bits, 8 LSB per degree, from an offset.
+    setup.magnetometer.queue_response(&[0x01, 0x00]);
+    setup.magnetometer.complete();
+
+    TemperatureDriver::read_temperature(setup.lsm303dlhc).unwrap();
+    setup.magnetometer.queue_error(Error::AddressNak);
+    setup.magnetometer.complete();
+    assert_eq!(
+        *setup.client.temperatures.borrow(),
+        [Ok(19), Err(ErrorCode::NOACK)]
+    );
+}
+
+#[test]
+fn free_fall_interrupt_is_set_up_after_the_configuration() {
+    let setup = setup();
+    assert_eq!(configure(&setup), Ok(()));
+    assert_eq!(setup.lsm303dlhc.set_free_fall(350, 200), Ok(()));
+    setup.accelerometer.take_transfers();
+    setup.accelerometer.complete();
+    setup.accelerometer.take_transfers();
+    setup.accelerometer.complete();
+    setup.magnetometer.take_transfers();
+    setup.magnetometer.complete();
+    setup.magnetometer.take_transfers();
+    setup.magnetometer.complete();
+
+    // CTRL_REG3_A: AOI1 on INT1.
+    assert_eq!(
+        setup.accelerometer.take_transfers(),
+        [Transfer::Write(vec![0x22, 0x40])]
+    );
+    setup.accelerometer.complete();
+
+    // INT1_THS_A and INT1_DURATION_A: 32 mg per LSB at 4 g, 10 Hz.
+    assert_eq!(
+        setup.accelerometer.take_transfers(),
+        [Transfer::Write(vec![0xB2, 10, 2])]
+    );
+    setup.accelerometer.complete();
+
+    // INT1_CFG_A: all axes low at once.
+    assert_eq!(
+        setup.accelerometer.take_transfers(),
+        [Transfer::Write(vec![0x30, 0x95])]
+    );
+    setup.accelerometer.complete();
+
+    assert!(!setup.accelerometer.is_enabled());
+    assert!(setup.accelerometer.take_transfers().is_empty());
+    assert_eq!(setup.lsm303dlhc.read_accelerometer(), Ok(()));
+}
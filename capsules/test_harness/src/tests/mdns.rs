@@ -0,0 +1,355 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::{Cell, RefCell};
+
+use capsules_extra::net::ipv4::dhcp::LeaseClient;
+use capsules_extra::net::ipv4::mdns::{self, MdnsResponder, MdnsService};
+use capsules_extra::net::ipv4::stack::{TcpPort, UdpClient, UdpSocket};
+use capsules_extra::net::ipv4::{Ipv4Addr, Ipv4Config, Ipv4Interface};
+use kernel::hil::mac_address::MacAddress;
+use kernel::ErrorCode;
+
+use crate::{leak, static_buffer};
+
+type Responder = MdnsResponder<'static, MockInterface>;
+
+const HOSTNAME: &str = "tock-evkb";
+const SERVICE: MdnsService = MdnsService {
+    service: "_tock-console",
+    protocol: "_tcp",
+    port: 2323,
+};
+
+const CONFIG: Ipv4Config = Ipv4Config {
+    address: Ipv4Addr::new(192, 168, 1, 50),
+    netmask: Ipv4Addr::new(255, 255, 255, 0),
+    gateway: None,
+};
+const QUERIER: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 10);
+
+const HOST: [&str; 2] = [HOSTNAME, "local"];
+const SERVICE_NAME: [&str; 3] = ["_tock-console", "_tcp", "local"];
+const INSTANCE: [&str; 4] = [HOSTNAME, "_tock-console", "_tcp", "local"];
+const SERVICES: [&str; 4] = ["_services", "_dns-sd", "_udp", "local"];
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Class of unique records, which flush the caches of the querier.
+const CLASS_IN_FLUSH: u16 = 0x8001;
+/// Class of questions asking for a unicast response.
+const CLASS_IN_QU: u16 = 0x8001;
+
+/// An interface logging the datagrams sent.
+struct MockInterface {
+    config: Cell<Ipv4Config>,
+    sent: RefCell<Vec<(Ipv4Addr, u16, Vec<u8>)>>,
+}
+
+impl Ipv4Interface<'static> for MockInterface {
+    fn config(&self) -> Ipv4Config {
+        self.config.get()
+    }
+
+    fn set_config(&self, config: Ipv4Config) {
+        self.config.set(config);
+    }
+
+    fn mac_address(&self) -> MacAddress {
+        MacAddress::default()
+    }
+
+    fn send_udp(
+        &self,
+        _socket: &'static UdpSocket<'static>,
+        src_port: u16,
+        dst: Ipv4Addr,
+        dst_port: u16,
+        payload: &[u8],
+    ) -> Result<(), ErrorCode> {
+        assert_eq!(src_port, mdns::PORT);
+        self.sent
+            .borrow_mut()
+            .push((dst, dst_port, payload.to_vec()));
+        Ok(())
+    }
+
+    fn send_tcp(
+        &self,
+        _port: &'static TcpPort<'static>,
+        _dst: Ipv4Addr,
+        _segment: &[u8],
+    ) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+}
+
+struct Setup {
+    interface: &'static MockInterface,
+    mdns: &'static Responder,
+}
+
+impl Setup {
+    /// Receive a multicast query from the mDNS port of the querier.
+    fn query(&self, message: &[u8]) {
+        self.mdns.receive(QUERIER, mdns::PORT, mdns::PORT, message);
+    }
+
+    fn take_sent(&self) -> Vec<(Ipv4Addr, u16, Vec<u8>)> {
+        self.interface.sent.take()
+    }
+
+    /// The response sent to the mDNS group, which must be the only one.
+    fn multicast_response(&self) -> Vec<u8> {
+        let mut sent = self.take_sent();
+        assert_eq!(sent.len(), 1);
+        let (dst, port, response) = sent.remove(0);
+        assert_eq!((dst, port), (mdns::GROUP, mdns::PORT));
+        response
+    }
+}
+
+fn setup(service: Option<MdnsService>) -> Setup {
+    let interface = leak(MockInterface {
+        config: Cell::new(CONFIG),
+        sent: RefCell::new(Vec::new()),
+    });
+    let socket = leak(UdpSocket::new());
+    let mdns = leak(MdnsResponder::new(
+        interface,
+        socket,
+        HOSTNAME,
+        service,
+        static_buffer(mdns::BUF_LEN),
+    ));
+    socket.set_client(mdns);
+    Setup { interface, mdns }
+}
+
+/// `labels` encoded as a DNS name, without compression.
+fn name(labels: &[&str]) -> Vec<u8> {
+    let mut name = Vec::new();
+    for label in labels {
+        name.push(label.len() as u8);
+        name.extend_from_slice(label.as_bytes());
+    }
+    name.push(0);
+    name
+}
+
+fn question(labels: &[&str], qtype: u16, qclass: u16) -> Vec<u8> {
+    [
+        name(labels),
+        qtype.to_be_bytes().to_vec(),
+        qclass.to_be_bytes().to_vec(),
+    ]
+    .concat()
+}
+
+/// A standard query with `questions`, already encoded.
+fn query(id: u16, questions: &[Vec<u8>]) -> Vec<u8> {
+    let header = [
+        &id.to_be_bytes()[..],
+        &[0, 0],
+        &(questions.len() as u16).to_be_bytes(),
+        &[0; 6],
+    ]
+    .concat();
+    [header, questions.concat()].concat()
+}
+
+fn record(labels: &[&str], rtype: u16, class: u16, ttl: u32, rdata: &[u8]) -> Vec<u8> {
+    [
+        &name(labels)[..],
+        &rtype.to_be_bytes(),
+        &class.to_be_bytes(),
+        &ttl.to_be_bytes(),
+        &(rdata.len() as u16).to_be_bytes(),
+        rdata,
+    ]
+    .concat()
+}
+
+fn a() -> Vec<u8> {
+    record(&HOST, TYPE_A, CLASS_IN_FLUSH, 120, &CONFIG.address.0)
+}
+
+fn ptr() -> Vec<u8> {
+    record(&SERVICE_NAME, TYPE_PTR, CLASS_IN, 4500, &name(&INSTANCE))
+}
+
+fn srv() -> Vec<u8> {
+    let rdata = [&[0, 0, 0, 0, 0x09, 0x13][..], &name(&HOST)].concat();
+    record(&INSTANCE, TYPE_SRV, CLASS_IN_FLUSH, 120, &rdata)
+}
+
+fn txt() -> Vec<u8> {
+    record(&INSTANCE, TYPE_TXT, CLASS_IN_FLUSH, 4500, &[0])
+}
+
+/// An authoritative response with `answers` and `additional` records.
+fn response(id: u16, answers: &[Vec<u8>], additional: &[Vec<u8>]) -> Vec<u8> {
+    let header = [
+        &id.to_be_bytes()[..],
+        &[0x84, 0x00, 0, 0],
+        &(answers.len() as u16).to_be_bytes(),
+        &[0, 0],
+        &(additional.len() as u16).to_be_bytes(),
+    ]
+    .concat();
+    [header, answers.concat(), additional.concat()].concat()
+}
+
+#[test]
+fn records_are_announced_once_the_interface_has_an_address() {
+    let s = setup(Some(SERVICE));
+    s.interface.config.set(Ipv4Config::UNCONFIGURED);
+    s.mdns.start();
+    assert!(s.take_sent().is_empty());
+
+    s.interface.config.set(CONFIG);
+    s.mdns.lease_changed(CONFIG);
+    assert_eq!(
+        s.multicast_response(),
+        response(0, &[a(), ptr(), srv(), txt()], &[])
+    );
+
+    // Without a service, only the address is announced
+    let s = setup(None);
+    s.mdns.start();
+    assert_eq!(s.multicast_response(), response(0, &[a()], &[]));
+}
+
+#[test]
+fn queries_for_the_hostname_are_answered_with_its_address() {
+    let s = setup(Some(SERVICE));
+    // Names are not case sensitive
+    s.query(&query(
+        0,
+        &[question(&["TOCK-EVKB", "Local"], TYPE_A, CLASS_IN)],
+    ));
+    assert_eq!(s.multicast_response(), response(0, &[a()], &[]));
+    s.query(&query(0, &[question(&HOST, TYPE_ANY, 255)]));
+    assert_eq!(s.multicast_response(), response(0, &[a()], &[]));
+
+    // A question asking for a unicast response
+    s.query(&query(0, &[question(&HOST, TYPE_A, CLASS_IN_QU)]));
+    assert_eq!(
+        s.take_sent(),
+        [(QUERIER, mdns::PORT, response(0, &[a()], &[]))]
+    );
+
+    // A legacy resolver, which is answered with the ID of its query
+    s.mdns.receive(
+        QUERIER,
+        49152,
+        mdns::PORT,
+        &query(0x1234, &[question(&HOST, TYPE_A, CLASS_IN)]),
+    );
+    assert_eq!(
+        s.take_sent(),
+        [(QUERIER, 49152, response(0x1234, &[a()], &[]))]
+    );
+
+    // Other names, types and classes
+    let unanswered = [
+        question(&["tock-evkb2", "local"], TYPE_A, CLASS_IN),
+        question(&[HOSTNAME], TYPE_A, CLASS_IN),
+        question(&[HOSTNAME, "local", "local"], TYPE_A, CLASS_IN),
+        question(&HOST, TYPE_AAAA, CLASS_IN),
+        question(&HOST, TYPE_A, 3),
+    ];
+    for question in unanswered {
+        s.query(&query(0, &[question]));
+    }
+    assert!(s.take_sent().is_empty());
+}
+
+#[test]
+fn service_queries_are_answered_with_the_records_to_connect() {
+    let s = setup(Some(SERVICE));
+    s.query(&query(0, &[question(&SERVICE_NAME, TYPE_PTR, CLASS_IN)]));
+    assert_eq!(
+        s.multicast_response(),
+        response(0, &[ptr()], &[a(), srv(), txt()])
+    );
+
+    s.query(&query(0, &[question(&INSTANCE, TYPE_SRV, CLASS_IN)]));
+    assert_eq!(s.multicast_response(), response(0, &[srv()], &[a()]));
+    s.query(&query(0, &[question(&INSTANCE, TYPE_ANY, CLASS_IN)]));
+    assert_eq!(s.multicast_response(), response(0, &[srv(), txt()], &[a()]));
+
+    // Service type enumeration
+    s.query(&query(0, &[question(&SERVICES, TYPE_PTR, CLASS_IN)]));
+    let services = record(&SERVICES, TYPE_PTR, CLASS_IN, 4500, &name(&SERVICE_NAME));
+    assert_eq!(s.multicast_response(), response(0, &[services], &[]));
+
+    // A responder without a service only knows its address
+    let s = setup(None);
+    s.query(&query(0, &[question(&SERVICE_NAME, TYPE_PTR, CLASS_IN)]));
+    s.query(&query(0, &[question(&INSTANCE, TYPE_ANY, CLASS_IN)]));
+    assert!(s.take_sent().is_empty());
+}
+
+#[test]
+fn answers_to_every_question_are_sent_together() {
+    let s = setup(Some(SERVICE));
+    // The second question is for the hostname, with "local" compressed
+    // into a pointer to the first one
+    let first = question(&SERVICE_NAME, TYPE_PTR, CLASS_IN);
+    let local = 12 + first.len() - 4 - 7;
+    let second = [
+        &name(&[HOSTNAME])[..name(&[HOSTNAME]).len() - 1],
+        &[0xC0, local as u8],
+        &TYPE_A.to_be_bytes(),
+        &CLASS_IN.to_be_bytes(),
+    ]
+    .concat();
+    s.query(&query(0, &[first, second]));
+    // Records answering a question are not repeated as additional records
+    assert_eq!(
+        s.multicast_response(),
+        response(0, &[a(), ptr()], &[srv(), txt()])
+    );
+}
+
+#[test]
+fn malformed_messages_are_ignored() {
+    let s = setup(Some(SERVICE));
+    let valid = query(0, &[question(&HOST, TYPE_A, CLASS_IN)]);
+    let with = |change: fn(&mut Vec<u8>)| {
+        let mut message = valid.clone();
+        change(&mut message);
+        message
+    };
+    let ignored = [
+        // Shorter than the header
+        valid[..11].to_vec(),
+        // A response, and another opcode
+        with(|m| m[2] = 0x84),
+        with(|m| m[2] = 0x28),
+        // A question cut in its name, or before its class
+        valid[..20].to_vec(),
+        valid[..valid.len() - 1].to_vec(),
+        // More questions than there are, after a valid one
+        with(|m| m[5] = 2),
+        // A label longer than the message
+        with(|m| m[12] = 0x3F),
+        // A pointer out of the message, and a pointer to itself
+        query(0, &[[&[0xC0, 0xFF][..], &[0, 1, 0, 1]].concat()]),
+        query(0, &[[&[0xC0, 12][..], &[0, 1, 0, 1]].concat()]),
+    ];
+    for message in ignored {
+        s.query(&message);
+        assert!(s.take_sent().is_empty(), "{:02x?}", message);
+    }
+
+    s.query(&valid);
+    assert_eq!(s.multicast_response(), response(0, &[a()], &[]));
+}
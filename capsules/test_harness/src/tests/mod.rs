@@ -0,0 +1,33 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Tests of capsules, driven through the mocks of this crate.
+
+mod aes_software;
+mod analog_sensor;
+mod app_loader;
+mod at24c_eeprom;
+mod at_engine;
+mod auto_brightness;
+mod bme280;
+mod bus_fault_injector;
+mod credential_store;
+mod dhcp;
+mod free_fall;
+mod ft6x06;
+mod gpio_debounce;
+mod gpio_pulse_capture;
+mod ipv4_stack;
+mod ir_remote;
+mod l3gd20;
+mod lin;
+mod lsm303dlhc;
+mod mdns;
+mod modbus_rtu;
+mod nmea;
+mod nonvolatile_storage_driver;
+mod pedometer;
+mod spi_flash;
+mod tcp;
+mod vibration;
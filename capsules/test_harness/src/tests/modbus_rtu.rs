@@ -0,0 +1,263 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! The slave is served by a process which does not exist, so reading or
+//! writing its registers fails with the Slave Device Failure exception.
+
+use capsules_extra::modbus_rtu::{self, ModbusRtu};
+use kernel::hil::time::Alarm;
+use kernel::hil::uart::{self, Receive, Transmit};
+use kernel::syscall::SyscallDriver;
+use kernel::{capabilities, create_capability, ErrorCode, Kernel, ProcessId};
+
+use crate::alarm::MockAlarm;
+use crate::uart::MockUart;
+use crate::{create_grant, leak};
+
+type Driver = ModbusRtu<'static, MockUart<'static>, MockAlarm<'static>>;
+
+const ADDRESS: usize = 0x11;
+
+/// Read Holding Registers 0 to 9 from slave 0x01, from the Modbus
+/// specification, with its CRC.
+const READ_REQUEST: [u8; 8] = [0x01, 0x03, 0x00, 0x00, 0x00, 0x0A, 0xC5, 0xCD];
+
+struct Setup {
+    uart: &'static MockUart<'static>,
+    alarm: &'static MockAlarm<'static>,
+    driver: &'static Driver,
+    process: ProcessId,
+}
+
+impl Setup {
+    /// Receive `frame` and the silence which ends it, and return the
+    /// response.
+    fn request(&self, frame: &[u8]) -> Vec<u8> {
+        assert_eq!(self.uart.receive(frame), frame.len());
+        self.end_frame();
+        let response = self.uart.take_transmitted();
+        self.uart.complete_transmit();
+        response
+    }
+
+    fn end_frame(&self) {
+        if let Some(ticks) = self.alarm.remaining() {
+            self.alarm.advance(ticks);
+        }
+    }
+}
+
+fn setup() -> Setup {
+    let uart = leak(MockUart::new());
+    let alarm = leak(MockAlarm::new());
+    let driver = leak(ModbusRtu::new(
+        uart,
+        alarm,
+        uart::Parameters {
+            baud_rate: 9600,
+            width: uart::Width::Eight,
+            parity: uart::Parity::Even,
+            stop_bits: uart::StopBits::One,
+            hw_flow_control: false,
+        },
+        create_grant(modbus_rtu::DRIVER_NUM),
+        Box::leak(Box::new([0; 1])),
+        Box::leak(Box::new([0; modbus_rtu::FRAME_LEN])),
+        Box::leak(Box::new([0; modbus_rtu::FRAME_LEN])),
+    ));
+    uart.set_transmit_client(driver);
+    uart.set_receive_client(driver);
+    alarm.set_alarm_client(driver);
+    assert_eq!(driver.initialize(), Ok(()));
+
+    let kernel: &'static Kernel = leak(Kernel::new(&[]));
+    let process = ProcessId::new_external(
+        kernel,
+        0,
+        0,
+        &create_capability!(capabilities::ExternalProcessCapability),
+    );
+    let setup = Setup {
+        uart,
+        alarm,
+        driver,
+        process,
+    };
+    assert!(driver.command(1, ADDRESS, 0, process).is_success());
+    setup
+}
+
+/// `bytes` followed by their CRC, low byte first.
+fn frame(bytes: &[u8]) -> Vec<u8> {
+    let crc = bytes.iter().fold(0xFFFF_u16, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u16, |crc, _| {
+            (crc >> 1) ^ if crc & 1 != 0 { 0xA001 } else { 0 }
+        })
+    });
+    let mut frame = bytes.to_vec();
+    frame.extend_from_slice(&crc.to_le_bytes());
+    frame
+}
+
+#[test]
+fn frames_are_checked_with_the_modbus_crc() {
+    let s = setup();
+    assert_eq!(frame(&READ_REQUEST[..6]), READ_REQUEST);
+
+    let mut request = READ_REQUEST;
+    request[0] = ADDRESS as u8;
+    let request = frame(&request[..6]);
+    assert_eq!(s.request(&request), frame(&[ADDRESS as u8, 0x83, 0x04]));
+
+    // Each byte of the CRC matters
+    for i in [6, 7] {
+        let mut corrupted = request.clone();
+        corrupted[i] ^= 0x01;
+        assert!(s.request(&corrupted).is_empty());
+    }
+}
+
+#[test]
+fn frames_end_with_a_silence_of_three_and_a_half_characters() {
+    let s = setup();
+    let request = frame(&[ADDRESS as u8, 0x2B, 0x0E, 0x01, 0x00]);
+
+    // 3.5 characters of 11 bits at 9600 baud are 4 ms
+    s.uart.receive(&request[..3]);
+    assert_eq!(s.alarm.remaining(), Some(4));
+    s.alarm.advance(3);
+    s.uart.receive(&request[3..]);
+    s.alarm.advance(3);
+    assert!(s.uart.take_transmitted().is_empty());
+    s.alarm.advance(1);
+    assert_eq!(
+        s.uart.take_transmitted(),
+        frame(&[ADDRESS as u8, 0xAB, 0x01])
+    );
+    s.uart.complete_transmit();
+
+    // A silence in the middle splits the request in two frames, both
+    // incomplete
+    s.uart.receive(&request[..3]);
+    s.end_frame();
+    s.uart.receive(&request[3..]);
+    s.end_frame();
+    assert!(s.uart.take_transmitted().is_empty());
+}
+
+#[test]
+fn echo_of_the_response_is_not_a_request() {
+    let s = setup();
+    let request = frame(&[ADDRESS as u8, 0x07]);
+    s.uart.receive(&request);
+    s.end_frame();
+    let response = s.uart.take_transmitted();
+    assert_eq!(response, frame(&[ADDRESS as u8, 0x87, 0x01]));
+
+    // A half-duplex bus echoes the response while it is sent
+    s.uart.receive(&response);
+    s.end_frame();
+    s.uart.complete_transmit();
+    assert!(s.uart.take_transmitted().is_empty());
+    assert_eq!(s.request(&request), response);
+}
+
+#[test]
+fn invalid_requests_get_exception_responses() {
+    let s = setup();
+    let address = ADDRESS as u8;
+    let exception = |function: u8, code: u8| frame(&[address, function | 0x80, code]);
+
+    // Illegal Function
+    assert_eq!(
+        s.request(&frame(&[address, 0x05, 0, 1, 0xFF, 0])),
+        exception(0x05, 0x01)
+    );
+    // Illegal Data Value: no register, too many registers, a byte count
+    // which does not match, and a request of the wrong length
+    assert_eq!(
+        s.request(&frame(&[address, 0x04, 0, 0, 0, 0])),
+        exception(0x04, 0x03)
+    );
+    assert_eq!(
+        s.request(&frame(&[address, 0x03, 0, 0, 0, 126])),
+        exception(0x03, 0x03)
+    );
+    assert_eq!(
+        s.request(&frame(&[address, 0x10, 0, 0, 0, 1, 4, 0, 1, 0, 2])),
+        exception(0x10, 0x03)
+    );
+    assert_eq!(
+        s.request(&frame(&[address, 0x06, 0, 1, 0])),
+        exception(0x06, 0x03)
+    );
+    // Slave Device Failure: the registers of the process are out of reach
+    assert_eq!(
+        s.request(&frame(&[address, 0x06, 0, 1, 0, 1])),
+        exception(0x06, 0x04)
+    );
+}
+
+#[test]
+fn requests_to_other_slaves_and_broadcasts_are_not_answered() {
+    let s = setup();
+    assert!(s.request(&READ_REQUEST).is_empty());
+    assert!(s.request(&frame(&[0x00, 0x06, 0, 1, 0, 1])).is_empty());
+    assert!(s.request(&frame(&[0x00, 0x07])).is_empty());
+}
+
+#[test]
+fn stopped_slave_ignores_requests() {
+    let s = setup();
+    let request = frame(&[ADDRESS as u8, 0x07]);
+    s.uart.receive(&request[..2]);
+    assert!(s.driver.command(2, 0, 0, s.process).is_success());
+    s.end_frame();
+    assert_eq!(s.uart.receive(&request), 0);
+    assert!(s.uart.take_transmitted().is_empty());
+
+    assert_eq!(
+        s.driver.command(1, 248, 0, s.process).get_failure(),
+        Some(ErrorCode::INVAL)
+    );
+    assert!(s.driver.command(1, ADDRESS, 0, s.process).is_success());
+    assert_eq!(s.request(&request), frame(&[ADDRESS as u8, 0x87, 0x01]));
+}
+
+#[test]
+fn receive_errors_drop_the_frame() {
+    let s = setup();
+    let request = frame(&[ADDRESS as u8, 0x07]);
+    s.uart.receive(&request[..2]);
+    // A reception which fails is a byte lost
+    assert_eq!(s.uart.receive_abort(), Err(ErrorCode::BUSY));
+    s.uart.receive(&request[2..]);
+    s.end_frame();
+    assert!(s.uart.take_transmitted().is_empty());
+
+    assert_eq!(s.request(&request), frame(&[ADDRESS as u8, 0x87, 0x01]));
+}
+
+#[test]
+fn only_one_process_is_the_slave() {
+    let s = setup();
+    let kernel: &'static Kernel = leak(Kernel::new(&[]));
+    let other = ProcessId::new_external(
+        kernel,
+        1,
+        1,
+        &create_capability!(capabilities::ExternalProcessCapability),
+    );
+    assert_eq!(
+        s.driver.command(1, 0x12, 0, other).get_failure(),
+        Some(ErrorCode::RESERVE)
+    );
+    assert_eq!(
+        s.driver.command(2, 0, 0, other).get_failure(),
+        Some(ErrorCode::RESERVE)
+    );
+    // The slave can change its address
+    assert!(s.driver.command(1, 0x12, 0, s.process).is_success());
+    assert_eq!(s.request(&frame(&[0x12, 0x07])), frame(&[0x12, 0x87, 0x01]));
+}
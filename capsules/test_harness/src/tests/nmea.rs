@@ -0,0 +1,233 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::RefCell;
+
+use capsules_extra::nmea::{self, Nmea};
+use kernel::hil::location::{Fix, FixQuality, Location, LocationClient, UtcTime};
+use kernel::hil::uart::Receive;
+use kernel::ErrorCode;
+
+use crate::leak;
+use crate::uart::MockUart;
+
+/// The classic example sentences of an epoch, with their checksums.
+const GGA: &[u8] = b"$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47\r\n";
+const RMC: &[u8] = b"$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A\r\n";
+
+#[derive(Default)]
+struct Fixes(RefCell<Vec<Fix>>);
+
+impl LocationClient for Fixes {
+    fn fix(&self, fix: &Fix) {
+        self.0.borrow_mut().push(*fix);
+    }
+}
+
+struct Setup {
+    uart: &'static MockUart<'static>,
+    nmea: &'static Nmea<'static, MockUart<'static>>,
+    fixes: &'static Fixes,
+}
+
+impl Setup {
+    fn take_fixes(&self) -> Vec<Fix> {
+        self.fixes.0.take()
+    }
+}
+
+fn setup() -> Setup {
+    let uart = leak(MockUart::new());
+    let nmea = leak(Nmea::new(
+        uart,
+        9600,
+        Box::leak(Box::new([0; 1])),
+        Box::leak(Box::new([0; nmea::SENTENCE_LEN])),
+    ));
+    uart.set_receive_client(nmea);
+    let fixes = leak(Fixes::default());
+    nmea.set_client(fixes);
+    assert_eq!(nmea.initialize(), Ok(()));
+    assert_eq!(nmea.start(), Ok(()));
+    Setup { uart, nmea, fixes }
+}
+
+/// `$data*checksum`, and the end of the line.
+fn sentence(data: &str) -> Vec<u8> {
+    let checksum = data.bytes().fold(0, |sum, c| sum ^ c);
+    format!("${}*{:02X}\r\n", data, checksum).into_bytes()
+}
+
+#[test]
+fn fix_is_reported_once_both_sentences_of_an_epoch_are_received() {
+    let s = setup();
+    assert_eq!(
+        sentence("GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,"),
+        GGA
+    );
+
+    s.uart.receive(GGA);
+    assert!(s.take_fixes().is_empty());
+    s.uart.receive(RMC);
+    let fixes = s.take_fixes();
+    assert_eq!(fixes.len(), 1);
+    let fix = fixes[0];
+    assert_eq!(fix.quality, FixQuality::Gps);
+    assert_eq!(
+        (fix.time.hour, fix.time.minute, fix.time.second),
+        (12, 35, 19)
+    );
+    // 48°07.038' N and 11°31.000' E
+    assert_eq!(fix.latitude, 481_173_000);
+    assert_eq!(fix.longitude, 115_166_666);
+    assert_eq!(fix.altitude, Some(545_400));
+    assert_eq!(fix.satellites, 8);
+    // 22.4 knots, 84.4°
+    assert_eq!(fix.speed, Some(11_523));
+    assert_eq!(fix.course, Some(8_440));
+}
+
+#[test]
+fn parses_the_date_and_southern_and_western_positions() {
+    let s = setup();
+    s.uart.receive(&sentence(
+        "GNRMC,083559.25,A,3351.123,S,15112.456,W,0.0,,160723,,,A",
+    ));
+    s.uart.receive(&sentence(
+        "GNGGA,083559.25,3351.123,S,15112.456,W,2,12,0.8,-12.5,M,,M,,",
+    ));
+
+    assert_eq!(
+        s.take_fixes(),
+        [Fix {
+            quality: FixQuality::Differential,
+            time: UtcTime {
+                year: 2023,
+                month: 7,
+                day: 16,
+                hour: 8,
+                minute: 35,
+                second: 59,
+                millisecond: 250,
+            },
+            latitude: -338_520_500,
+            longitude: -1_512_076_000,
+            altitude: Some(-12_500),
+            satellites: 12,
+            speed: Some(0),
+            course: None,
+        }]
+    );
+}
+
+#[test]
+fn sentences_with_a_wrong_checksum_are_ignored() {
+    let s = setup();
+    let mut corrupted = RMC.to_vec();
+    corrupted[20] = b'9';
+    let without_checksum = [&RMC[..RMC.len() - 5], b"\r\n"].concat();
+    let bad_hex = [&RMC[..RMC.len() - 4], b"XY\r\n"].concat();
+
+    s.uart.receive(GGA);
+    for rmc in [corrupted, without_checksum, bad_hex] {
+        s.uart.receive(&rmc);
+        assert!(s.take_fixes().is_empty());
+    }
+    s.uart.receive(RMC);
+    assert_eq!(s.take_fixes().len(), 1);
+}
+
+#[test]
+fn sentences_of_another_epoch_start_a_new_fix() {
+    let s = setup();
+    s.uart.receive(GGA);
+    // The RMC of the next second
+    s.uart.receive(&sentence(
+        "GPRMC,123520,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W",
+    ));
+    assert!(s.take_fixes().is_empty());
+    s.uart.receive(&sentence(
+        "GPGGA,123520,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,",
+    ));
+    let fixes = s.take_fixes();
+    assert_eq!(fixes.len(), 1);
+    assert_eq!(fixes[0].time.second, 20);
+}
+
+#[test]
+fn empty_and_malformed_fields_are_unknown_values() {
+    let s = setup();
+    s.uart.receive(GGA);
+    s.uart.receive(RMC);
+    s.take_fixes();
+
+    // No fix: the receiver leaves the position empty
+    s.uart
+        .receive(&sentence("GPGGA,123520,,,,,0,00,99.99,,,,,,"));
+    s.uart.receive(&sentence("GPRMC,123520,V,,,,,,,230394,,,N"));
+    let fix = s.take_fixes()[0];
+    assert_eq!(fix.quality, FixQuality::None);
+    assert_eq!((fix.satellites, fix.altitude), (0, None));
+    assert_eq!((fix.speed, fix.course), (None, None));
+
+    // Fields which are not numbers, or an unknown hemisphere
+    s.uart.receive(&sentence(
+        "GPGGA,123521,48O7.038,N,01131.000,E,1,x8,0.9,5-45.4,M,46.9,M,,",
+    ));
+    s.uart.receive(&sentence(
+        "GPRMC,123521,A,4807.038,Q,01131.000,E,2.2.4,084.4,230394,003.1,W",
+    ));
+    let fix = s.take_fixes()[0];
+    assert_eq!(fix.quality, FixQuality::None);
+    assert_eq!((fix.satellites, fix.altitude), (0, None));
+    assert_eq!((fix.speed, fix.course), (None, Some(8_440)));
+
+    // A sentence without a time is not part of any epoch
+    s.uart.receive(&sentence(
+        "GPGGA,,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,",
+    ));
+    s.uart.receive(&sentence(
+        "GPRMC,,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W",
+    ));
+    assert!(s.take_fixes().is_empty());
+}
+
+#[test]
+fn truncated_and_overlong_sentences_are_dropped() {
+    let s = setup();
+    // A sentence cut short by the start of the next one
+    s.uart.receive(&GGA[..30]);
+    s.uart.receive(GGA);
+    // A sentence too long to be valid
+    let mut long = RMC[..RMC.len() - 5].to_vec();
+    long.extend_from_slice(&[b','; nmea::SENTENCE_LEN]);
+    s.uart.receive(&long);
+    s.uart.receive(b"*6A\r\n");
+    assert!(s.take_fixes().is_empty());
+
+    // A byte lost by the UART drops the sentence
+    s.uart.receive(&RMC[..30]);
+    assert_eq!(s.uart.receive_abort(), Err(ErrorCode::BUSY));
+    s.uart.receive(&RMC[30..]);
+    assert!(s.take_fixes().is_empty());
+
+    s.uart.receive(RMC);
+    assert_eq!(s.take_fixes().len(), 1);
+}
+
+#[test]
+fn stopped_receiver_reports_nothing() {
+    let s = setup();
+    s.uart.receive(GGA);
+    assert_eq!(s.nmea.stop(), Ok(()));
+    assert_eq!(s.uart.receive(RMC), 0);
+    assert!(s.take_fixes().is_empty());
+
+    // The epoch starts again
+    assert_eq!(s.nmea.start(), Ok(()));
+    s.uart.receive(RMC);
+    assert!(s.take_fixes().is_empty());
+    s.uart.receive(GGA);
+    assert_eq!(s.take_fixes().len(), 1);
+}
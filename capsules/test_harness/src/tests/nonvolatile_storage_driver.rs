@@ -0,0 +1,184 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use capsules_extra::nonvolatile_storage_driver::{self, AppRegion, NonvolatileStorage};
+use kernel::hil::nonvolatile_storage::{self as hil, NonvolatileStorageClient};
+use kernel::ErrorCode;
+
+use crate::{create_grant, leak, static_buffer};
+
+const USERSPACE_START: usize = 3000;
+const USERSPACE_LENGTH: usize = 2000;
+
+const APP_A: u32 = 0x1;
+const APP_B: u32 = 0x2;
+
+/// Storage the tests never access, as they only check the addresses the
+/// capsule computes.
+struct NoStorage;
+
+impl<'a> hil::NonvolatileStorage<'a> for NoStorage {
+    fn set_client(&self, _client: &'a dyn NonvolatileStorageClient) {}
+
+    fn read(
+        &self,
+        _buffer: &'static mut [u8],
+        _address: usize,
+        _length: usize,
+    ) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    fn write(
+        &self,
+        _buffer: &'static mut [u8],
+        _address: usize,
+        _length: usize,
+    ) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+}
+
+fn storage(app_regions: &'static [AppRegion]) -> NonvolatileStorage<'static> {
+    NonvolatileStorage::new(
+        leak(NoStorage),
+        create_grant(nonvolatile_storage_driver::DRIVER_NUM),
+        USERSPACE_START,
+        USERSPACE_LENGTH,
+        0,
+        USERSPACE_START,
+        app_regions,
+        static_buffer(nonvolatile_storage_driver::BUF_LEN),
+    )
+}
+
+/// Whether `new()` accepts `app_regions`.
+fn accepts(app_regions: &'static [AppRegion]) -> bool {
+    panic::catch_unwind(AssertUnwindSafe(|| storage(app_regions))).is_ok()
+}
+
+fn region(write_id: u32, offset: usize, length: usize) -> AppRegion {
+    AppRegion {
+        write_id,
+        offset,
+        length,
+    }
+}
+
+#[test]
+fn apps_only_access_their_region() {
+    let storage = storage(leak([region(APP_A, 0, 1000), region(APP_B, 1000, 500)]));
+
+    assert_eq!(
+        storage.app_region(Some(APP_A)),
+        Some((USERSPACE_START, 1000))
+    );
+    assert_eq!(
+        storage.app_region(Some(APP_B)),
+        Some((USERSPACE_START + 1000, 500))
+    );
+
+    // Each app sees its region at address 0.
+    assert_eq!(
+        storage.app_address(Some(APP_A), 0, 1000),
+        Ok(USERSPACE_START)
+    );
+    assert_eq!(
+        storage.app_address(Some(APP_B), 0, 500),
+        Ok(USERSPACE_START + 1000)
+    );
+    assert_eq!(
+        storage.app_address(Some(APP_B), 499, 1),
+        Ok(USERSPACE_START + 1499)
+    );
+
+    // The data of B is past the end of the region of A, and the rest of the
+    // userspace memory past the end of the region of B.
+    assert_eq!(
+        storage.app_address(Some(APP_A), 1000, 1),
+        Err(ErrorCode::INVAL)
+    );
+    assert_eq!(
+        storage.app_address(Some(APP_A), 900, 200),
+        Err(ErrorCode::INVAL)
+    );
+    assert_eq!(
+        storage.app_address(Some(APP_B), 500, 1),
+        Err(ErrorCode::INVAL)
+    );
+    assert_eq!(
+        storage.app_address(Some(APP_B), 0, 1000),
+        Err(ErrorCode::INVAL)
+    );
+}
+
+#[test]
+fn unlisted_apps_have_no_access() {
+    let storage = storage(leak([region(APP_A, 0, 1000)]));
+
+    // Apps with another write_id, or without one
+    for write_id in [Some(APP_B), None] {
+        assert_eq!(storage.app_region(write_id), None);
+        assert_eq!(
+            storage.app_address(write_id, 0, 1),
+            Err(ErrorCode::NOSUPPORT)
+        );
+    }
+}
+
+#[test]
+fn without_regions_apps_share_the_memory() {
+    let storage = storage(&[]);
+
+    for write_id in [Some(APP_A), Some(APP_B), None] {
+        assert_eq!(
+            storage.app_region(write_id),
+            Some((USERSPACE_START, USERSPACE_LENGTH))
+        );
+        assert_eq!(
+            storage.app_address(write_id, 1000, 1000),
+            Ok(USERSPACE_START + 1000)
+        );
+        assert_eq!(
+            storage.app_address(write_id, 1000, 1001),
+            Err(ErrorCode::INVAL)
+        );
+    }
+}
+
+#[test]
+fn invalid_regions_are_rejected() {
+    // Regions which touch, and empty regions
+    assert!(accepts(leak([
+        region(APP_A, 0, 1000),
+        region(APP_B, 1000, 1000)
+    ])));
+    assert!(accepts(leak([
+        region(APP_A, 500, 0),
+        region(APP_B, 0, 1000)
+    ])));
+
+    // Past the end of the userspace memory
+    assert!(!accepts(leak([region(APP_A, 1000, 1001)])));
+    assert!(!accepts(leak([region(APP_A, 2001, 0)])));
+    assert!(!accepts(leak([region(APP_A, usize::MAX, 2)])));
+
+    // Overlapping, in either order
+    assert!(!accepts(leak([
+        region(APP_A, 0, 1000),
+        region(APP_B, 999, 10)
+    ])));
+    assert!(!accepts(leak([
+        region(APP_A, 500, 1000),
+        region(APP_B, 0, 501)
+    ])));
+
+    // Two regions for the same apps
+    assert!(!accepts(leak([
+        region(APP_A, 0, 1000),
+        region(APP_A, 1000, 1000)
+    ])));
+}
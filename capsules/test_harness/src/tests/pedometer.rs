@@ -0,0 +1,42 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use capsules_extra::pedometer::{StepDetector, SAMPLE_RATE_HZ};
+
+/// Count the steps in `seconds` of samples of gravity along Z, with a
+/// vertical swing of `amplitude_mg` at `frequency_hz` and some noise.
+fn count_steps(seconds: u32, frequency_hz: f64, amplitude_mg: f64) -> usize {
+    let mut detector = StepDetector::new();
+    (0..seconds * SAMPLE_RATE_HZ)
+        .filter(|&i| {
+            let t = i as f64 / SAMPLE_RATE_HZ as f64;
+            let swing = amplitude_mg * (2.0 * core::f64::consts::PI * frequency_hz * t).sin();
+            // Noise of +-20 mg, and the sensor tilted around X
+            let noise = [-20.0, 13.0, -7.0, 20.0, 2.0][i as usize % 5];
+            let z = 1000.0 + swing + noise;
+            detector.sample(40, (z * 0.3) as i32, (z * 0.954) as i32)
+        })
+        .count()
+}
+
+#[test]
+fn walking_is_counted() {
+    // 20 s at 1.8 steps per second
+    let steps = count_steps(20, 1.8, 400.0);
+    assert!((34..=36).contains(&steps), "{}", steps);
+}
+
+#[test]
+fn standing_still_is_not_counted() {
+    assert_eq!(count_steps(20, 1.8, 0.0), 0);
+    // Swaying
+    assert_eq!(count_steps(20, 0.5, 60.0), 0);
+}
+
+#[test]
+fn steps_are_at_least_the_minimum_interval_apart() {
+    // Shaking at 8 Hz counts at most 4 steps per second
+    let steps = count_steps(10, 8.0, 800.0);
+    assert!(steps <= 40, "{}", steps);
+}
@@ -0,0 +1,311 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::{Cell, RefCell};
+
+use capsules_extra::spi_flash::{self, SpiFlash, SpiFlashSector, SECTOR_SIZE};
+use kernel::hil::flash::{self, Flash, HasClient};
+use kernel::hil::spi::SpiMasterDevice;
+use kernel::hil::time::Alarm;
+use kernel::ErrorCode;
+
+use crate::alarm::MockAlarm;
+use crate::leak;
+use crate::spi::MockSpiMaster;
+
+type Driver = SpiFlash<'static, MockSpiMaster<'static>, MockAlarm<'static>>;
+
+const PAGE_SIZE: usize = 256;
+/// 64 KiB, the capacity of the chip as a power of two.
+const CAPACITY: u8 = 16;
+
+/// A NOR flash chip answering the commands of the driver. Programs wrap
+/// around within a 256-byte page, as on real chips.
+struct Chip {
+    memory: RefCell<Vec<u8>>,
+    write_enabled: Cell<bool>,
+    protected: Cell<bool>,
+    /// Status reads left before the erase or program in progress ends,
+    /// `usize::MAX` for a chip which never ends it.
+    busy_reads: Cell<usize>,
+    commands: RefCell<Vec<(u8, usize, usize)>>,
+}
+
+impl Chip {
+    fn new() -> Chip {
+        Chip {
+            memory: RefCell::new(vec![0xFF; 1 << CAPACITY]),
+            write_enabled: Cell::new(false),
+            protected: Cell::new(false),
+            busy_reads: Cell::new(0),
+            commands: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// The bytes clocked out by the chip for the command in `write`.
+    fn answer(&self, write: &[u8]) -> Vec<u8> {
+        let mut response = vec![0; write.len()];
+        let address = match write.get(1..4) {
+            Some(bytes) => (bytes[0] as usize) << 16 | (bytes[1] as usize) << 8 | bytes[2] as usize,
+            None => 0,
+        };
+        let mut memory = self.memory.borrow_mut();
+        match write[0] {
+            0x9F => {
+                response[1..4].copy_from_slice(&[spi_flash::MANUFACTURER_WINBOND, 0x40, CAPACITY])
+            }
+            0x06 => self.write_enabled.set(!self.protected.get()),
+            0x05 => {
+                let busy = self.busy_reads.get();
+                let mut status = (self.write_enabled.get() as u8) << 1;
+                if busy > 0 {
+                    status |= 1;
+                    if busy != usize::MAX {
+                        self.busy_reads.set(busy - 1);
+                    }
+                }
+                response[1] = status;
+            }
+            0x03 => {
+                self.commands
+                    .borrow_mut()
+                    .push((0x03, address, write.len() - 4));
+                let len = write.len() - 4;
+                response[4..].copy_from_slice(&memory[address..address + len]);
+            }
+            0x02 if self.write_enabled.take() => {
+                self.commands
+                    .borrow_mut()
+                    .push((0x02, address, write.len() - 4));
+                let page = address & !(PAGE_SIZE - 1);
+                for (offset, byte) in write[4..].iter().enumerate() {
+                    memory[page + (address + offset) % PAGE_SIZE] &= byte;
+                }
+            }
+            0x20 if self.write_enabled.take() => {
+                self.commands.borrow_mut().push((0x20, address, 0));
+                let sector = address & !(SECTOR_SIZE - 1);
+                memory[sector..sector + SECTOR_SIZE].fill(0xFF);
+            }
+            _ => {}
+        }
+        response
+    }
+}
+
+#[derive(Default)]
+struct Client {
+    reads: RefCell<Vec<flash::Error>>,
+    writes: RefCell<Vec<flash::Error>>,
+    erases: RefCell<Vec<flash::Error>>,
+    sector: RefCell<Option<&'static mut SpiFlashSector>>,
+}
+
+impl flash::Client<Driver> for Client {
+    fn read_complete(&self, sector: &'static mut SpiFlashSector, error: flash::Error) {
+        self.reads.borrow_mut().push(error);
+        *self.sector.borrow_mut() = Some(sector);
+    }
+
+    fn write_complete(&self, sector: &'static mut SpiFlashSector, error: flash::Error) {
+        self.writes.borrow_mut().push(error);
+        *self.sector.borrow_mut() = Some(sector);
+    }
+
+    fn erase_complete(&self, error: flash::Error) {
+        self.erases.borrow_mut().push(error);
+    }
+}
+
+struct Setup {
+    spi: &'static MockSpiMaster<'static>,
+    alarm: &'static MockAlarm<'static>,
+    driver: &'static Driver,
+    chip: &'static Chip,
+    client: &'static Client,
+}
+
+impl Setup {
+    /// Answer the transfers of the driver, and let its polls expire, until
+    /// it starts nothing more.
+    fn run(&self) {
+        loop {
+            let transfers = self.spi.take_transfers();
+            if let Some(transfer) = transfers.first() {
+                self.spi.queue_response(&self.chip.answer(&transfer.write));
+                self.spi.complete();
+            } else if let Some(ticks) = self.alarm.remaining() {
+                self.alarm.advance(ticks);
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn sector(&self) -> &'static mut SpiFlashSector {
+        self.client.sector.borrow_mut().take().unwrap()
+    }
+}
+
+fn setup() -> Setup {
+    let spi = leak(MockSpiMaster::new());
+    let alarm = leak(MockAlarm::new());
+    let driver = leak(SpiFlash::new(
+        spi,
+        alarm,
+        Box::leak(Box::new([0; spi_flash::TX_BUF_LEN])),
+        Box::leak(Box::new([0; spi_flash::RX_BUF_LEN])),
+    ));
+    SpiMasterDevice::set_client(spi, driver);
+    alarm.set_alarm_client(driver);
+    let client = leak(Client::default());
+    HasClient::set_client(driver, client);
+    let setup = Setup {
+        spi,
+        alarm,
+        driver,
+        chip: leak(Chip::new()),
+        client,
+    };
+    assert_eq!(driver.probe(), Ok(()));
+    setup.run();
+    setup
+}
+
+/// A sector whose bytes differ from one page to the next.
+fn pattern(seed: u8) -> &'static mut SpiFlashSector {
+    let sector = Box::leak(Box::new(SpiFlashSector::new()));
+    for (i, byte) in sector.0.iter_mut().enumerate() {
+        *byte = (i as u8) ^ (i / PAGE_SIZE) as u8 ^ seed;
+    }
+    sector
+}
+
+#[test]
+fn probe_reads_the_size_from_the_jedec_id() {
+    let s = setup();
+    let id = s.driver.jedec_id().unwrap();
+    assert_eq!(id.manufacturer, spi_flash::MANUFACTURER_WINBOND);
+    assert_eq!(id.capacity, CAPACITY);
+    assert_eq!(s.driver.number_of_sectors(), 16);
+}
+
+#[test]
+fn sectors_need_a_probed_chip() {
+    let spi = leak(MockSpiMaster::new());
+    let alarm = leak(MockAlarm::new());
+    let driver: &Driver = leak(SpiFlash::new(
+        spi,
+        alarm,
+        Box::leak(Box::new([0; spi_flash::TX_BUF_LEN])),
+        Box::leak(Box::new([0; spi_flash::RX_BUF_LEN])),
+    ));
+    assert_eq!(driver.erase_page(0), Err(ErrorCode::OFF));
+
+    let s = setup();
+    assert_eq!(s.driver.erase_page(16), Err(ErrorCode::INVAL));
+    let (error, _) = s.driver.read_page(16, pattern(0)).unwrap_err();
+    assert_eq!(error, ErrorCode::INVAL);
+}
+
+#[test]
+fn read_reads_the_sector_a_page_at_a_time() {
+    let s = setup();
+    let data = pattern(0x5A);
+    s.chip.memory.borrow_mut()[3 * SECTOR_SIZE..4 * SECTOR_SIZE].copy_from_slice(&data.0);
+
+    assert!(s
+        .driver
+        .read_page(3, Box::leak(Box::new(SpiFlashSector::new())))
+        .is_ok());
+    assert!(s.driver.read_page(3, pattern(0)).is_err());
+    s.run();
+
+    assert_eq!(*s.client.reads.borrow(), [flash::Error::CommandComplete]);
+    assert_eq!(s.sector().0, data.0);
+    let expected: Vec<_> = (0..SECTOR_SIZE / PAGE_SIZE)
+        .map(|page| (0x03, 3 * SECTOR_SIZE + page * PAGE_SIZE, PAGE_SIZE))
+        .collect();
+    assert_eq!(*s.chip.commands.borrow(), expected);
+}
+
+#[test]
+fn write_erases_then_programs_each_page_on_its_own() {
+    let s = setup();
+    // The chip stays busy for a few polls after each command
+    s.chip.busy_reads.set(3);
+    let data = pattern(0xA5);
+    let expected_data = data.0;
+
+    assert!(s.driver.write_page(1, data).is_ok());
+    s.run();
+
+    assert_eq!(*s.client.writes.borrow(), [flash::Error::CommandComplete]);
+    // Programs never cross a page boundary, or the chip would wrap them
+    let mut expected = vec![(0x20, SECTOR_SIZE, 0)];
+    expected.extend(
+        (0..SECTOR_SIZE / PAGE_SIZE).map(|page| (0x02, SECTOR_SIZE + page * PAGE_SIZE, PAGE_SIZE)),
+    );
+    assert_eq!(*s.chip.commands.borrow(), expected);
+    let memory = s.chip.memory.borrow();
+    assert_eq!(memory[SECTOR_SIZE..2 * SECTOR_SIZE], expected_data);
+    // The neighbouring sectors are left alone
+    assert!(memory[..SECTOR_SIZE].iter().all(|&byte| byte == 0xFF));
+    assert!(memory[2 * SECTOR_SIZE..3 * SECTOR_SIZE]
+        .iter()
+        .all(|&byte| byte == 0xFF));
+}
+
+#[test]
+fn erase_clears_the_sector() {
+    let s = setup();
+    s.chip.memory.borrow_mut()[..3 * SECTOR_SIZE].fill(0);
+
+    assert_eq!(s.driver.erase_page(1), Ok(()));
+    s.run();
+
+    assert_eq!(*s.client.erases.borrow(), [flash::Error::CommandComplete]);
+    let memory = s.chip.memory.borrow();
+    assert!(memory[SECTOR_SIZE..2 * SECTOR_SIZE]
+        .iter()
+        .all(|&byte| byte == 0xFF));
+    assert!(memory[..SECTOR_SIZE].iter().all(|&byte| byte == 0));
+    assert!(memory[2 * SECTOR_SIZE..3 * SECTOR_SIZE]
+        .iter()
+        .all(|&byte| byte == 0));
+}
+
+#[test]
+fn protected_chip_is_not_modified() {
+    let s = setup();
+    s.chip.protected.set(true);
+
+    assert!(s.driver.write_page(0, pattern(0)).is_ok());
+    s.run();
+
+    assert_eq!(
+        *s.client.writes.borrow(),
+        [flash::Error::FlashMemoryProtectionError]
+    );
+    assert!(s.chip.commands.borrow().is_empty());
+}
+
+#[test]
+fn chip_stuck_busy_fails_the_erase() {
+    let s = setup();
+    s.chip.busy_reads.set(usize::MAX);
+
+    assert_eq!(s.driver.erase_page(0), Ok(()));
+    s.run();
+
+    assert_eq!(*s.client.erases.borrow(), [flash::Error::FlashError]);
+    // The flash can be used again
+    s.chip.busy_reads.set(0);
+    assert_eq!(s.driver.erase_page(0), Ok(()));
+    s.run();
+    assert_eq!(
+        *s.client.erases.borrow(),
+        [flash::Error::FlashError, flash::Error::CommandComplete]
+    );
+}
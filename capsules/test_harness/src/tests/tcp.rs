@@ -0,0 +1,469 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use core::cell::{Cell, RefCell};
+
+use capsules_extra::net::ipv4::stack::{TcpHandler, TcpPort, UdpSocket};
+use capsules_extra::net::ipv4::tcp::{self, State, TcpStream};
+use capsules_extra::net::ipv4::{Ipv4Addr, Ipv4Config, Ipv4Interface};
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
+use kernel::hil::mac_address::MacAddress;
+use kernel::hil::time::Alarm;
+use kernel::hil::uart::{self, Receive, ReceiveClient, Transmit, TransmitClient};
+use kernel::ErrorCode;
+
+use crate::alarm::MockAlarm;
+use crate::{deferred_call_lock, leak, static_buffer};
+
+type Stream = TcpStream<'static, MockInterface, MockAlarm<'static>>;
+
+const PORT: u16 = 2323;
+const PEER: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 10);
+const PEER_PORT: u16 = 49152;
+/// Initial sequence number of the peer.
+const PEER_ISN: u32 = 1000;
+
+const FIN: u8 = 0x01;
+const SYN: u8 = 0x02;
+const RST: u8 = 0x04;
+const PSH: u8 = 0x08;
+const ACK: u8 = 0x10;
+
+/// An MSS option of 536 bytes.
+const MSS_536: [u8; 4] = [2, 4, 0x02, 0x18];
+
+/// An interface logging the segments sent, which fails to send them while
+/// `result` is an error.
+struct MockInterface {
+    sent: RefCell<Vec<(Ipv4Addr, Vec<u8>)>>,
+    result: Cell<Result<(), ErrorCode>>,
+}
+
+impl Ipv4Interface<'static> for MockInterface {
+    fn config(&self) -> Ipv4Config {
+        Ipv4Config::UNCONFIGURED
+    }
+
+    fn set_config(&self, _config: Ipv4Config) {}
+
+    fn mac_address(&self) -> MacAddress {
+        MacAddress::default()
+    }
+
+    fn send_udp(
+        &self,
+        _socket: &'static UdpSocket<'static>,
+        _src_port: u16,
+        _dst: Ipv4Addr,
+        _dst_port: u16,
+        _payload: &[u8],
+    ) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    fn send_tcp(
+        &self,
+        _port: &'static TcpPort<'static>,
+        dst: Ipv4Addr,
+        segment: &[u8],
+    ) -> Result<(), ErrorCode> {
+        self.sent.borrow_mut().push((dst, segment.to_vec()));
+        self.result.get()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Segment {
+    seq: u32,
+    ack: u32,
+    flags: u8,
+    window: u16,
+    options: Vec<u8>,
+    payload: Vec<u8>,
+}
+
+impl Segment {
+    /// Parse a segment sent to the peer.
+    fn parse(bytes: &[u8]) -> Segment {
+        assert_eq!(bytes[0..2], PORT.to_be_bytes());
+        assert_eq!(bytes[2..4], PEER_PORT.to_be_bytes());
+        let header_len = (bytes[12] >> 4) as usize * 4;
+        Segment {
+            seq: u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+            ack: u32::from_be_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]),
+            flags: bytes[13],
+            window: u16::from_be_bytes([bytes[14], bytes[15]]),
+            options: bytes[20..header_len].to_vec(),
+            payload: bytes[header_len..].to_vec(),
+        }
+    }
+}
+
+/// A segment from `PEER_PORT`.
+fn segment(seq: u32, ack: u32, flags: u8, window: u16, options: &[u8], payload: &[u8]) -> Vec<u8> {
+    let header_len = 20 + options.len();
+    [
+        &PEER_PORT.to_be_bytes()[..],
+        &PORT.to_be_bytes(),
+        &seq.to_be_bytes(),
+        &ack.to_be_bytes(),
+        &[((header_len / 4) as u8) << 4, flags],
+        &window.to_be_bytes(),
+        &[0; 4],
+        options,
+        payload,
+    ]
+    .concat()
+}
+
+/// Transmissions and receptions completed by the stream.
+#[derive(Default)]
+struct Completions {
+    transmitted: RefCell<Vec<(usize, Result<(), ErrorCode>)>>,
+    received: RefCell<Vec<(Vec<u8>, Result<(), ErrorCode>)>>,
+}
+
+impl TransmitClient for Completions {
+    fn transmitted_buffer(
+        &self,
+        _tx_buffer: &'static mut [u8],
+        tx_len: usize,
+        rval: Result<(), ErrorCode>,
+    ) {
+        self.transmitted.borrow_mut().push((tx_len, rval));
+    }
+}
+
+impl ReceiveClient for Completions {
+    fn received_buffer(
+        &self,
+        rx_buffer: &'static mut [u8],
+        rx_len: usize,
+        rval: Result<(), ErrorCode>,
+        _error: uart::Error,
+    ) {
+        self.received
+            .borrow_mut()
+            .push((rx_buffer[..rx_len].to_vec(), rval));
+    }
+}
+
+struct Setup {
+    interface: &'static MockInterface,
+    alarm: &'static MockAlarm<'static>,
+    stream: &'static Stream,
+    completions: &'static Completions,
+}
+
+impl Setup {
+    fn receive(&self, seq: u32, ack: u32, flags: u8, window: u16, payload: &[u8]) {
+        self.stream
+            .receive_segment(PEER, &segment(seq, ack, flags, window, &[], payload));
+    }
+
+    fn take_sent(&self) -> Vec<Segment> {
+        self.interface
+            .sent
+            .take()
+            .into_iter()
+            .map(|(dst, bytes)| {
+                assert_eq!(dst, PEER);
+                Segment::parse(&bytes)
+            })
+            .collect()
+    }
+
+    /// The segment sent, which must be the only one.
+    fn sent_segment(&self) -> Segment {
+        let mut sent = self.take_sent();
+        assert_eq!(sent.len(), 1);
+        sent.remove(0)
+    }
+
+    /// Accept a connection from a peer with `options` in its SYN, and
+    /// `window`. Returns the next sequence number of the stream.
+    fn connect(&self, options: &[u8], window: u16) -> u32 {
+        self.stream
+            .receive_segment(PEER, &segment(PEER_ISN, 0, SYN, window, options, &[]));
+        let syn_ack = self.sent_segment();
+        assert_eq!(syn_ack.flags, SYN | ACK);
+        let seq = syn_ack.seq.wrapping_add(1);
+        self.receive(PEER_ISN + 1, seq, ACK, window, &[]);
+        assert!(self.stream.is_connected());
+        seq
+    }
+
+    fn run(&self) {
+        while DeferredCall::service_next_pending().is_some() {}
+    }
+}
+
+fn setup() -> Setup {
+    let interface = leak(MockInterface {
+        sent: RefCell::new(Vec::new()),
+        result: Cell::new(Ok(())),
+    });
+    let alarm = leak(MockAlarm::new());
+    let port = leak(TcpPort::new(PORT));
+    let stream = leak(TcpStream::new(
+        interface,
+        port,
+        alarm,
+        static_buffer(tcp::SEGMENT_BUF_LEN),
+        static_buffer(tcp::RX_BUF_LEN),
+    ));
+    port.set_handler(stream);
+    alarm.set_alarm_client(stream);
+    stream.register();
+
+    let completions = leak(Completions::default());
+    stream.set_transmit_client(completions);
+    stream.set_receive_client(completions);
+    Setup {
+        interface,
+        alarm,
+        stream,
+        completions,
+    }
+}
+
+#[test]
+fn mss_is_read_from_the_options_of_the_syn() {
+    let options: [(&[u8], Option<u16>); 12] = [
+        (&[], None),
+        (&MSS_536, Some(536)),
+        // Padding, and other options before the MSS
+        (&[1, 1, 2, 4, 0x05, 0xB4], Some(1460)),
+        (
+            &[4, 2, 8, 10, 0, 0, 0, 1, 0, 0, 0, 0, 2, 4, 0x05, 0xB4],
+            Some(1460),
+        ),
+        // An MSS option of the wrong length is skipped
+        (&[2, 3, 0x05, 2, 4, 0x05, 0xB4], Some(0x05B4)),
+        // Nothing is read after the end of the list
+        (&[0, 2, 4, 0x05, 0xB4], None),
+        (&[1, 1, 1, 1], None),
+        // Truncated options
+        (&[2, 4, 0x05], None),
+        (&[2], None),
+        (&[8, 10, 0, 0], None),
+        // Lengths which do not cover the option itself
+        (&[3, 0, 2, 4, 0x05, 0xB4], None),
+        (&[3, 1, 2, 4, 0x05, 0xB4], None),
+    ];
+    for (options, mss) in options {
+        assert_eq!(tcp::parse_mss(options), mss, "{:?}", options);
+    }
+}
+
+#[test]
+fn handshake_establishes_the_connection() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+    // Only a SYN opens a connection
+    for flags in [ACK, SYN | ACK, SYN | RST, FIN] {
+        s.receive(PEER_ISN, 0, flags, 1024, &[]);
+    }
+    assert!(s.take_sent().is_empty());
+    assert_eq!(s.stream.state(), State::Listen);
+
+    s.stream
+        .receive_segment(PEER, &segment(PEER_ISN, 0, SYN, 1024, &MSS_536, &[]));
+    assert_eq!(s.stream.state(), State::SynReceived);
+    let syn_ack = s.sent_segment();
+    assert_eq!(
+        syn_ack,
+        Segment {
+            seq: syn_ack.seq,
+            ack: PEER_ISN + 1,
+            flags: SYN | ACK,
+            window: tcp::RX_BUF_LEN as u16,
+            options: MSS_536.to_vec(),
+            payload: Vec::new(),
+        }
+    );
+
+    // The SYN-ACK is sent again until it is acknowledged
+    assert_eq!(s.alarm.remaining(), Some(1000));
+    s.alarm.advance(1000);
+    assert_eq!(s.sent_segment(), syn_ack);
+    s.receive(PEER_ISN, 0, SYN, 1024, &[]);
+    assert!(s.take_sent().is_empty());
+
+    // Another peer, or the wrong acknowledgement, do not complete it
+    s.stream.receive_segment(
+        Ipv4Addr::new(192, 168, 1, 11),
+        &segment(PEER_ISN + 1, syn_ack.seq + 1, ACK, 1024, &[], &[]),
+    );
+    s.receive(PEER_ISN + 1, syn_ack.seq + 2, ACK, 1024, &[]);
+    assert_eq!(s.stream.state(), State::SynReceived);
+
+    s.receive(PEER_ISN + 1, syn_ack.seq + 1, ACK, 1024, &[]);
+    assert_eq!(s.stream.state(), State::Established);
+    assert!(s.alarm.remaining().is_none());
+    assert!(s.take_sent().is_empty());
+}
+
+#[test]
+fn received_data_is_acknowledged_and_read() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+    let seq = s.connect(&MSS_536, 1024);
+
+    s.receive(PEER_ISN + 1, seq, PSH | ACK, 1024, b"hello");
+    let ack = s.sent_segment();
+    assert_eq!((ack.seq, ack.ack, ack.flags), (seq, PEER_ISN + 6, ACK));
+    assert_eq!(ack.window as usize, tcp::RX_BUF_LEN - 5);
+
+    // Out of order and repeated segments are dropped, and the next
+    // sequence number expected is acknowledged again
+    s.receive(PEER_ISN + 10, seq, PSH | ACK, 1024, b"later");
+    s.receive(PEER_ISN + 1, seq, PSH | ACK, 1024, b"hello");
+    for ack in s.take_sent() {
+        assert_eq!(ack.ack, PEER_ISN + 6);
+    }
+
+    assert_eq!(s.stream.receive_buffer(static_buffer(8), 3), Ok(()));
+    s.run();
+    assert_eq!(s.completions.received.take(), [(b"hel".to_vec(), Ok(()))]);
+    // The rest waits for the next read
+    assert_eq!(s.stream.receive_buffer(static_buffer(8), 4), Ok(()));
+    s.run();
+    assert!(s.completions.received.borrow().is_empty());
+    s.receive(PEER_ISN + 6, seq, PSH | ACK, 1024, b"!!");
+    s.run();
+    assert_eq!(s.completions.received.take(), [(b"lo!!".to_vec(), Ok(()))]);
+}
+
+#[test]
+fn full_receive_buffer_closes_the_window() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+    let seq = s.connect(&MSS_536, 1024);
+
+    let data = [0x5A; tcp::RX_BUF_LEN];
+    s.receive(PEER_ISN + 1, seq, PSH | ACK, 1024, &data);
+    let ack = s.sent_segment();
+    let rcv_nxt = PEER_ISN + 1 + tcp::RX_BUF_LEN as u32;
+    assert_eq!((ack.ack, ack.window), (rcv_nxt, 0));
+
+    // Data beyond the window is dropped
+    s.receive(rcv_nxt, seq, PSH | ACK, 1024, b"more");
+    let ack = s.sent_segment();
+    assert_eq!((ack.ack, ack.window), (rcv_nxt, 0));
+
+    // Reading data opens the window again, and the peer is told
+    assert_eq!(s.stream.receive_buffer(static_buffer(100), 100), Ok(()));
+    let update = s.sent_segment();
+    assert_eq!(
+        (update.ack, update.window, update.flags),
+        (rcv_nxt, 100, ACK)
+    );
+    s.run();
+    assert_eq!(s.completions.received.take(), [(vec![0x5A; 100], Ok(()))]);
+}
+
+#[test]
+fn data_is_sent_within_the_mss_and_window_of_the_peer() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+    // An MSS of 4 bytes, and a closed window
+    let mut seq = s.connect(&[2, 4, 0, 4], 0);
+
+    assert_eq!(s.stream.transmit_buffer(static_buffer(10), 10), Ok(()));
+    assert_eq!(
+        s.stream
+            .transmit_buffer(static_buffer(10), 10)
+            .map_err(|(e, _)| e),
+        Err(ErrorCode::BUSY)
+    );
+    assert!(s.take_sent().is_empty());
+
+    // A window update lets data be sent
+    s.receive(PEER_ISN + 1, seq, ACK, 3, &[]);
+    for len in [3, 4, 3] {
+        let data = s.sent_segment();
+        assert_eq!((data.seq, data.flags), (seq, PSH | ACK));
+        assert_eq!(data.payload.len(), len);
+        assert!(s.completions.transmitted.borrow().is_empty());
+        seq += len as u32;
+        s.receive(PEER_ISN + 1, seq, ACK, 1024, &[]);
+    }
+    assert!(s.take_sent().is_empty());
+    assert_eq!(s.completions.transmitted.take(), [(10, Ok(()))]);
+}
+
+#[test]
+fn unacknowledged_segments_are_sent_again_until_the_connection_is_dropped() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+    let seq = s.connect(&MSS_536, 1024);
+    assert_eq!(s.stream.transmit_buffer(static_buffer(10), 10), Ok(()));
+    let data = s.sent_segment();
+
+    // A busy interface does not count as a retransmission
+    s.interface.result.set(Err(ErrorCode::BUSY));
+    s.alarm.advance(1000);
+    assert_eq!(s.sent_segment(), data);
+    assert_eq!(s.alarm.remaining(), Some(10));
+    s.interface.result.set(Ok(()));
+    s.alarm.advance(10);
+    assert_eq!(s.sent_segment(), data);
+
+    // Exponential backoff, up to 16 s
+    for rto in [2000, 4000, 8000, 16000, 16000] {
+        assert_eq!(s.alarm.remaining(), Some(rto));
+        s.alarm.advance(rto);
+        assert_eq!(s.sent_segment(), data);
+    }
+    // Partial acknowledgements are ignored, as segments are sent whole
+    s.receive(PEER_ISN + 1, seq + 5, ACK, 1024, &[]);
+    assert_eq!(s.alarm.remaining(), Some(16000));
+
+    s.alarm.advance(16000);
+    assert!(s.take_sent().is_empty());
+    assert_eq!(s.stream.state(), State::Listen);
+    assert_eq!(
+        s.completions.transmitted.take(),
+        [(10, Err(ErrorCode::FAIL))]
+    );
+}
+
+#[test]
+fn connection_is_closed_by_the_peer_or_reset() {
+    let _lock = deferred_call_lock();
+    let s = setup();
+    let seq = s.connect(&MSS_536, 1024);
+
+    // The FIN is acknowledged along with ours
+    s.receive(PEER_ISN + 1, seq, FIN | ACK, 1024, b"bye");
+    assert_eq!(s.stream.state(), State::LastAck);
+    let fin = s.sent_segment();
+    assert_eq!(
+        (fin.seq, fin.ack, fin.flags),
+        (seq, PEER_ISN + 5, FIN | ACK)
+    );
+    s.receive(PEER_ISN + 5, seq + 1, ACK, 1024, &[]);
+    assert_eq!(s.stream.state(), State::Listen);
+    assert!(s.alarm.remaining().is_none());
+
+    // Data written without a connection is dropped
+    assert_eq!(s.stream.transmit_buffer(static_buffer(4), 4), Ok(()));
+    s.run();
+    assert_eq!(s.completions.transmitted.take(), [(4, Ok(()))]);
+
+    // A reset is only accepted at the next sequence number
+    let seq = s.connect(&MSS_536, 1024);
+    assert_eq!(s.stream.transmit_buffer(static_buffer(4), 4), Ok(()));
+    s.take_sent();
+    s.receive(PEER_ISN + 2, 0, RST, 0, &[]);
+    assert!(s.stream.is_connected());
+    s.receive(PEER_ISN + 1, seq, RST, 0, &[]);
+    assert_eq!(s.stream.state(), State::Listen);
+    assert_eq!(
+        s.completions.transmitted.take(),
+        [(4, Err(ErrorCode::FAIL))]
+    );
+    assert!(s.take_sent().is_empty());
+}
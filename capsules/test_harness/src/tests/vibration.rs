@@ -0,0 +1,62 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+use capsules_extra::vibration::{band_energies, dominant_bin, fft, MAX_WINDOW_LEN};
+use kernel::ErrorCode;
+
+/// A window of `len` samples of a sine wave completing `cycles` cycles, with
+/// an offset.
+fn sine(len: usize, cycles: usize, amplitude: f64, offset: i32) -> (Vec<i32>, Vec<i32>) {
+    let re = (0..len)
+        .map(|i| {
+            let phase = 2.0 * core::f64::consts::PI * (cycles * i) as f64 / len as f64;
+            (amplitude * phase.sin()).round() as i32 + offset
+        })
+        .collect();
+    (re, vec![0; len])
+}
+
+#[test]
+fn only_powers_of_two_are_transformed() {
+    let mut re = vec![0; 48];
+    let mut im = vec![0; 48];
+    assert_eq!(fft(&mut re, &mut im), Err(ErrorCode::INVAL));
+    let mut re = vec![0; 2 * MAX_WINDOW_LEN];
+    let mut im = vec![0; 2 * MAX_WINDOW_LEN];
+    assert_eq!(fft(&mut re, &mut im), Err(ErrorCode::INVAL));
+    let mut im = vec![0; 32];
+    assert_eq!(fft(&mut re[..64], &mut im), Err(ErrorCode::INVAL));
+}
+
+#[test]
+fn constant_is_dc() {
+    let (mut re, mut im) = sine(MAX_WINDOW_LEN, 0, 0.0, 1000);
+    fft(&mut re, &mut im).unwrap();
+    // Each stage rounds down
+    assert!((993..=1000).contains(&re[0]), "{}", re[0]);
+    assert!(re[1..].iter().chain(&im).all(|value| value.abs() <= 1));
+}
+
+#[test]
+fn sine_wave_is_found() {
+    for (len, cycles) in [(MAX_WINDOW_LEN, 8), (MAX_WINDOW_LEN, 37), (32, 5)] {
+        let (mut re, mut im) = sine(len, cycles, 1000.0, 0);
+        fft(&mut re, &mut im).unwrap();
+        let (bin, amplitude) = dominant_bin(&re, &im);
+        assert_eq!(bin, cycles);
+        assert!((990..=1010).contains(&amplitude), "{}", amplitude);
+    }
+}
+
+#[test]
+fn energy_is_in_the_band_of_the_sine_wave() {
+    // 63 bins above DC, in 4 bands of 16 bins except the last
+    let (mut re, mut im) = sine(MAX_WINDOW_LEN, 40, 1000.0, 0);
+    fft(&mut re, &mut im).unwrap();
+    let mut energies = [0; 4];
+    band_energies(&re, &im, &mut energies);
+    assert!((490000..=510000).contains(&energies[2]), "{}", energies[2]);
+    // Rounding noise leaks into the other bands
+    assert!(energies[0] < 100 && energies[1] < 100 && energies[3] < 100);
+}
@@ -0,0 +1,191 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Mock UART.
+
+use core::cell::{Cell, RefCell};
+
+use kernel::hil::uart::{
+    Break, Configure, Error, Parameters, Receive, ReceiveClient, Transmit, TransmitClient,
+};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+/// A UART which logs the bytes transmitted, and holds each transmission
+/// until the test calls [`complete_transmit`](MockUart::complete_transmit).
+/// Bytes sent by the test with [`receive`](MockUart::receive) fill the
+/// buffer of the reception in progress.
+pub struct MockUart<'a> {
+    parameters: Cell<Option<Parameters>>,
+    breaking: Cell<bool>,
+    transmitted: RefCell<Vec<u8>>,
+    tx_pending: TakeCell<'static, [u8]>,
+    tx_len: Cell<usize>,
+    rx_pending: TakeCell<'static, [u8]>,
+    /// Bytes received in the buffer, and its length
+    rx_progress: Cell<(usize, usize)>,
+    tx_client: OptionalCell<&'a dyn TransmitClient>,
+    rx_client: OptionalCell<&'a dyn ReceiveClient>,
+}
+
+impl<'a> MockUart<'a> {
+    pub fn new() -> MockUart<'a> {
+        MockUart {
+            parameters: Cell::new(None),
+            breaking: Cell::new(false),
+            transmitted: RefCell::new(Vec::new()),
+            tx_pending: TakeCell::empty(),
+            tx_len: Cell::new(0),
+            rx_pending: TakeCell::empty(),
+            rx_progress: Cell::new((0, 0)),
+            tx_client: OptionalCell::empty(),
+            rx_client: OptionalCell::empty(),
+        }
+    }
+
+    /// The last configuration, `None` until the UART is configured.
+    pub fn parameters(&self) -> Option<Parameters> {
+        self.parameters.get()
+    }
+
+    /// Whether the transmit line is held low by a break.
+    pub fn is_breaking(&self) -> bool {
+        self.breaking.get()
+    }
+
+    /// The bytes of the transmissions started since the last call.
+    pub fn take_transmitted(&self) -> Vec<u8> {
+        self.transmitted.take()
+    }
+
+    pub fn is_transmitting(&self) -> bool {
+        self.tx_pending.is_some()
+    }
+
+    /// Complete the transmission in progress. Returns `false` if there is
+    /// none.
+    pub fn complete_transmit(&self) -> bool {
+        match self.tx_pending.take() {
+            Some(buffer) => {
+                let len = self.tx_len.get();
+                self.tx_client
+                    .map(move |client| client.transmitted_buffer(buffer, len, Ok(())));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Receive `bytes`, completing each reception once its buffer is full.
+    /// Returns the number of bytes received: the rest is dropped when no
+    /// reception is in progress.
+    pub fn receive(&self, bytes: &[u8]) -> usize {
+        for (received, byte) in bytes.iter().enumerate() {
+            let buffer = match self.rx_pending.take() {
+                Some(buffer) => buffer,
+                None => return received,
+            };
+            let (len, rx_len) = self.rx_progress.get();
+            buffer[len] = *byte;
+            if len + 1 < rx_len {
+                self.rx_progress.set((len + 1, rx_len));
+                self.rx_pending.replace(buffer);
+            } else {
+                self.rx_client
+                    .map(move |client| client.received_buffer(buffer, rx_len, Ok(()), Error::None));
+            }
+        }
+        bytes.len()
+    }
+}
+
+impl Configure for MockUart<'_> {
+    fn configure(&self, params: Parameters) -> Result<(), ErrorCode> {
+        self.parameters.set(Some(params));
+        Ok(())
+    }
+}
+
+impl<'a> Transmit<'a> for MockUart<'a> {
+    fn set_transmit_client(&self, client: &'a dyn TransmitClient) {
+        self.tx_client.set(client);
+    }
+
+    fn transmit_buffer(
+        &self,
+        tx_buffer: &'static mut [u8],
+        tx_len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        if self.tx_pending.is_some() {
+            return Err((ErrorCode::BUSY, tx_buffer));
+        } else if tx_len > tx_buffer.len() {
+            return Err((ErrorCode::SIZE, tx_buffer));
+        }
+        self.transmitted
+            .borrow_mut()
+            .extend_from_slice(&tx_buffer[..tx_len]);
+        self.tx_len.set(tx_len);
+        self.tx_pending.replace(tx_buffer);
+        Ok(())
+    }
+
+    fn transmit_word(&self, _word: u32) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    fn transmit_abort(&self) -> Result<(), ErrorCode> {
+        Ok(())
+    }
+}
+
+impl<'a> Receive<'a> for MockUart<'a> {
+    fn set_receive_client(&self, client: &'a dyn ReceiveClient) {
+        self.rx_client.set(client);
+    }
+
+    fn receive_buffer(
+        &self,
+        rx_buffer: &'static mut [u8],
+        rx_len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        if self.rx_pending.is_some() {
+            return Err((ErrorCode::BUSY, rx_buffer));
+        } else if rx_len == 0 || rx_len > rx_buffer.len() {
+            return Err((ErrorCode::SIZE, rx_buffer));
+        }
+        self.rx_progress.set((0, rx_len));
+        self.rx_pending.replace(rx_buffer);
+        Ok(())
+    }
+
+    fn receive_word(&self) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    /// Completes the reception in progress with `CANCEL` right away.
+    fn receive_abort(&self) -> Result<(), ErrorCode> {
+        match self.rx_pending.take() {
+            Some(buffer) => {
+                let (len, _) = self.rx_progress.get();
+                self.rx_client.map(move |client| {
+                    client.received_buffer(buffer, len, Err(ErrorCode::CANCEL), Error::Aborted)
+                });
+                Err(ErrorCode::BUSY)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Break for MockUart<'_> {
+    fn start_break(&self) -> Result<(), ErrorCode> {
+        self.breaking.set(true);
+        Ok(())
+    }
+
+    fn stop_break(&self) -> Result<(), ErrorCode> {
+        self.breaking.set(false);
+        Ok(())
+    }
+}
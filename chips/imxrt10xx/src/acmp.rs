@@ -0,0 +1,341 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Analog Comparator (ACMP).
+//!
+//! The chip has four analog comparators, ACMP1 to ACMP4, which are the
+//! channels of the `hil::analog_comparator` interface. Each comparator
+//! selects its positive and negative inputs among eight inputs: IN0 to IN6
+//! are pins, which the board routes through the IOMUXC, and IN7 is the
+//! output of an internal 6-bit DAC. The DAC divides one of two reference
+//! voltages, so an input can be compared to a threshold without an external
+//! reference.
+//!
+//! Interrupts are raised on the rising edge of the comparator output, when
+//! the positive input rises above the negative input, or on the falling or
+//! both edges as chosen with `set_edge`. The channel reported to the client
+//! is the index of the comparator, from 0 for ACMP1 to 3 for ACMP4.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! use imxrt10xx::acmp;
+//!
+//! // Compare IN0 with 20/64 of the reference voltage, and interrupt when
+//! // the input falls below this threshold.
+//! let acmp = &peripherals.acmp;
+//! acmp.set_inputs(&acmp::CHANNEL_ACMP1, acmp::Input::In0, acmp::Input::Dac);
+//! acmp.set_reference(&acmp::CHANNEL_ACMP1, acmp::VoltageReference::Vin1, 19)?;
+//! acmp.set_hysteresis(&acmp::CHANNEL_ACMP1, acmp::Hysteresis::Level1);
+//! acmp.set_edge(&acmp::CHANNEL_ACMP1, acmp::Edge::Falling);
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::analog_comparator;
+use kernel::platform::chip::ClockInterface;
+use kernel::utilities::cells::OptionalCell;
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
+use kernel::utilities::registers::{register_bitfields, ReadWrite};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+use crate::ccm;
+
+/// Registers of one comparator
+#[repr(C)]
+struct AcmpRegisters {
+    /// CMP Control Register 0
+    cr0: ReadWrite<u8, CR0::Register>,
+    /// CMP Control Register 1
+    cr1: ReadWrite<u8, CR1::Register>,
+    /// CMP Filter Period Register
+    fpr: ReadWrite<u8>,
+    /// CMP Status and Control Register
+    scr: ReadWrite<u8, SCR::Register>,
+    /// DAC Control Register
+    daccr: ReadWrite<u8, DACCR::Register>,
+    /// MUX Control Register
+    muxcr: ReadWrite<u8, MUXCR::Register>,
+}
+
+register_bitfields![u8,
+    CR0 [
+        /// Filter Sample Count
+        FILTER_CNT OFFSET(4) NUMBITS(3) [],
+        /// Comparator hard block hysteresis control
+        HYSTCTR OFFSET(0) NUMBITS(2) []
+    ],
+    CR1 [
+        /// Sample Enable
+        SE OFFSET(7) NUMBITS(1) [],
+        /// Windowing Enable
+        WE OFFSET(6) NUMBITS(1) [],
+        /// Power Mode Select
+        PMODE OFFSET(4) NUMBITS(1) [
+            LowSpeed = 0,
+            HighSpeed = 1
+        ],
+        /// Comparator INVERT
+        INV OFFSET(3) NUMBITS(1) [],
+        /// Comparator Output Select
+        COS OFFSET(2) NUMBITS(1) [],
+        /// Comparator Output Pin Enable
+        OPE OFFSET(1) NUMBITS(1) [],
+        /// Comparator Module Enable
+        EN OFFSET(0) NUMBITS(1) []
+    ],
+    SCR [
+        /// DMA Enable Control
+        DMAEN OFFSET(6) NUMBITS(1) [],
+        /// Comparator Interrupt Enable Rising
+        IER OFFSET(4) NUMBITS(1) [],
+        /// Comparator Interrupt Enable Falling
+        IEF OFFSET(3) NUMBITS(1) [],
+        /// Analog Comparator Flag Rising, write 1 to clear
+        CFR OFFSET(2) NUMBITS(1) [],
+        /// Analog Comparator Flag Falling, write 1 to clear
+        CFF OFFSET(1) NUMBITS(1) [],
+        /// Analog Comparator Output
+        COUT OFFSET(0) NUMBITS(1) []
+    ],
+    DACCR [
+        /// DAC Enable
+        DACEN OFFSET(7) NUMBITS(1) [],
+        /// Supply Voltage Reference Source Select
+        VRSEL OFFSET(6) NUMBITS(1) [
+            Vin1 = 0,
+            Vin2 = 1
+        ],
+        /// DAC Output Voltage Select
+        VOSEL OFFSET(0) NUMBITS(6) []
+    ],
+    MUXCR [
+        /// Plus Input Mux Control
+        PSEL OFFSET(3) NUMBITS(3) [],
+        /// Minus Input Mux Control
+        MSEL OFFSET(0) NUMBITS(3) []
+    ]
+];
+
+const ACMP1_BASE: StaticRef<AcmpRegisters> =
+    unsafe { StaticRef::new(0x4009_4000 as *const AcmpRegisters) };
+const ACMP2_BASE: StaticRef<AcmpRegisters> =
+    unsafe { StaticRef::new(0x4009_4008 as *const AcmpRegisters) };
+const ACMP3_BASE: StaticRef<AcmpRegisters> =
+    unsafe { StaticRef::new(0x4009_4010 as *const AcmpRegisters) };
+const ACMP4_BASE: StaticRef<AcmpRegisters> =
+    unsafe { StaticRef::new(0x4009_4018 as *const AcmpRegisters) };
+
+const NUMBER_COMPARATORS: usize = 4;
+
+/// Largest value of the DAC level.
+pub const DAC_MAX_LEVEL: u8 = 63;
+
+/// A comparator, used as a channel of the `AnalogComparator` interface.
+pub struct AcmpChannel {
+    index: usize,
+}
+
+pub static CHANNEL_ACMP1: AcmpChannel = AcmpChannel { index: 0 };
+pub static CHANNEL_ACMP2: AcmpChannel = AcmpChannel { index: 1 };
+pub static CHANNEL_ACMP3: AcmpChannel = AcmpChannel { index: 2 };
+pub static CHANNEL_ACMP4: AcmpChannel = AcmpChannel { index: 3 };
+
+/// Comparator inputs
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum Input {
+    In0 = 0,
+    In1 = 1,
+    In2 = 2,
+    In3 = 3,
+    In4 = 4,
+    In5 = 5,
+    In6 = 6,
+    /// Output of the internal 6-bit DAC
+    Dac = 7,
+}
+
+/// Reference voltage divided by the internal DAC
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VoltageReference {
+    Vin1,
+    Vin2,
+}
+
+/// Hysteresis of the comparator, from the smallest to the largest. See the
+/// datasheet for the voltages, which depend on the power mode.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum Hysteresis {
+    Level0 = 0,
+    Level1 = 1,
+    Level2 = 2,
+    Level3 = 3,
+}
+
+/// Edges of the comparator output which raise interrupts
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Edge {
+    /// The positive input rises above the negative input.
+    Rising,
+    /// The positive input falls below the negative input.
+    Falling,
+    Both,
+}
+
+pub struct Acmp<'a> {
+    registers: [StaticRef<AcmpRegisters>; NUMBER_COMPARATORS],
+    clocks: [AcmpClock<'a>; NUMBER_COMPARATORS],
+    edges: [Cell<Edge>; NUMBER_COMPARATORS],
+    client: OptionalCell<&'a dyn analog_comparator::Client>,
+}
+
+impl<'a> Acmp<'a> {
+    pub const fn new(ccm: &'a ccm::Ccm) -> Self {
+        Self {
+            registers: [ACMP1_BASE, ACMP2_BASE, ACMP3_BASE, ACMP4_BASE],
+            clocks: [
+                AcmpClock(ccm::PeripheralClock::ccgr3(ccm, ccm::HCLK3::ACMP1)),
+                AcmpClock(ccm::PeripheralClock::ccgr3(ccm, ccm::HCLK3::ACMP2)),
+                AcmpClock(ccm::PeripheralClock::ccgr3(ccm, ccm::HCLK3::ACMP3)),
+                AcmpClock(ccm::PeripheralClock::ccgr3(ccm, ccm::HCLK3::ACMP4)),
+            ],
+            edges: [
+                Cell::new(Edge::Rising),
+                Cell::new(Edge::Rising),
+                Cell::new(Edge::Rising),
+                Cell::new(Edge::Rising),
+            ],
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Returns the registers of the comparator of `channel`, with its clock
+    /// enabled.
+    fn registers(&self, channel: &AcmpChannel) -> &AcmpRegisters {
+        let clock = &self.clocks[channel.index];
+        if !clock.is_enabled() {
+            clock.enable();
+        }
+        &self.registers[channel.index]
+    }
+
+    /// Select the inputs compared by `channel`.
+    pub fn set_inputs(&self, channel: &AcmpChannel, positive: Input, negative: Input) {
+        self.registers(channel)
+            .muxcr
+            .write(MUXCR::PSEL.val(positive as u8) + MUXCR::MSEL.val(negative as u8));
+    }
+
+    /// Enable the DAC of `channel`, which outputs `(level + 1) / 64` of
+    /// `reference` on the `Input::Dac` input.
+    pub fn set_reference(
+        &self,
+        channel: &AcmpChannel,
+        reference: VoltageReference,
+        level: u8,
+    ) -> Result<(), ErrorCode> {
+        if level > DAC_MAX_LEVEL {
+            return Err(ErrorCode::INVAL);
+        }
+        let vrsel = match reference {
+            VoltageReference::Vin1 => DACCR::VRSEL::Vin1,
+            VoltageReference::Vin2 => DACCR::VRSEL::Vin2,
+        };
+        self.registers(channel)
+            .daccr
+            .write(DACCR::DACEN::SET + vrsel + DACCR::VOSEL.val(level));
+        Ok(())
+    }
+
+    pub fn set_hysteresis(&self, channel: &AcmpChannel, hysteresis: Hysteresis) {
+        self.registers(channel)
+            .cr0
+            .modify(CR0::HYSTCTR.val(hysteresis as u8));
+    }
+
+    /// Select the edges raising interrupts, which apply from the next call
+    /// to `start_comparing`.
+    pub fn set_edge(&self, channel: &AcmpChannel, edge: Edge) {
+        self.edges[channel.index].set(edge);
+    }
+
+    /// Enable the comparator in high-speed mode, without filtering.
+    fn enable(&self, channel: &AcmpChannel) {
+        let registers = self.registers(channel);
+        if !registers.cr1.is_set(CR1::EN) {
+            registers.cr0.modify(CR0::FILTER_CNT.val(0));
+            registers.fpr.set(0);
+            registers.cr1.write(CR1::PMODE::HighSpeed + CR1::EN::SET);
+        }
+    }
+
+    pub fn handle_interrupt(&self, index: usize) {
+        let registers = &self.registers[index];
+        let scr = registers.scr.extract();
+        // Clear the flags, keeping the interrupts enabled.
+        registers.scr.modify(SCR::CFR::SET + SCR::CFF::SET);
+
+        let rising = scr.is_set(SCR::CFR) && scr.is_set(SCR::IER);
+        let falling = scr.is_set(SCR::CFF) && scr.is_set(SCR::IEF);
+        if rising || falling {
+            self.client.map(|client| client.fired(index));
+        }
+    }
+}
+
+impl<'a> analog_comparator::AnalogComparator<'a> for Acmp<'a> {
+    type Channel = AcmpChannel;
+
+    fn comparison(&self, channel: &Self::Channel) -> bool {
+        self.enable(channel);
+        self.registers[channel.index].scr.is_set(SCR::COUT)
+    }
+
+    fn start_comparing(&self, channel: &Self::Channel) -> Result<(), ErrorCode> {
+        self.enable(channel);
+        let (rising, falling) = match self.edges[channel.index].get() {
+            Edge::Rising => (1, 0),
+            Edge::Falling => (0, 1),
+            Edge::Both => (1, 1),
+        };
+        // Clear the flags of the edges seen before.
+        self.registers[channel.index]
+            .scr
+            .write(SCR::CFR::SET + SCR::CFF::SET + SCR::IER.val(rising) + SCR::IEF.val(falling));
+        Ok(())
+    }
+
+    fn stop_comparing(&self, channel: &Self::Channel) -> Result<(), ErrorCode> {
+        let registers = self.registers(channel);
+        registers
+            .scr
+            .write(SCR::CFR::SET + SCR::CFF::SET + SCR::IER::CLEAR + SCR::IEF::CLEAR);
+        registers.cr1.modify(CR1::EN::CLEAR);
+        Ok(())
+    }
+
+    fn set_client(&self, client: &'a dyn analog_comparator::Client) {
+        self.client.set(client);
+    }
+}
+
+struct AcmpClock<'a>(ccm::PeripheralClock<'a>);
+
+impl ClockInterface for AcmpClock<'_> {
+    fn is_enabled(&self) -> bool {
+        self.0.is_enabled()
+    }
+
+    fn enable(&self) {
+        self.0.enable();
+    }
+
+    fn disable(&self) {
+        self.0.disable();
+    }
+}
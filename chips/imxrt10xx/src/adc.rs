@@ -0,0 +1,354 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Analog to digital converters (ADC1 and ADC2).
+//!
+//! Each ADC converts one of its 16 inputs to a 12-bit value. Conversions are
+//! either started by software, through the `hil::adc::Adc` interface, or by
+//! the ADC External Trigger Control (`adc_etc`) when the ADC is switched to
+//! hardware triggers with `set_hardware_trigger`. The ADC can not be used
+//! through the HIL while it is hardware triggered.
+//!
+//! The ADC is calibrated when it is first used.
+
+use core::cell::Cell;
+
+use kernel::hil;
+use kernel::platform::chip::ClockInterface;
+use kernel::utilities::cells::OptionalCell;
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
+use kernel::utilities::registers::{register_bitfields, ReadOnly, ReadWrite};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+use crate::ccm;
+
+#[repr(C)]
+struct AdcRegisters {
+    /// Control register for hardware triggers
+    hc: [ReadWrite<u32, HC::Register>; 8],
+    /// Status register for HW triggers
+    hs: ReadOnly<u32, HS::Register>,
+    /// Data result register for HW triggers
+    r: [ReadOnly<u32, R::Register>; 8],
+    /// Configuration register
+    cfg: ReadWrite<u32, CFG::Register>,
+    /// General control register
+    gc: ReadWrite<u32, GC::Register>,
+    /// General status register
+    gs: ReadWrite<u32, GS::Register>,
+    /// Compare value register
+    cv: ReadWrite<u32>,
+    /// Offset correction value register
+    ofs: ReadWrite<u32>,
+    /// Calibration value register
+    cal: ReadWrite<u32>,
+}
+
+register_bitfields![u32,
+    HC [
+        /// Conversion complete interrupt enable
+        AIEN OFFSET(7) NUMBITS(1) [],
+        /// Input channel select
+        ADCH OFFSET(0) NUMBITS(5) [
+            /// The channel is selected by the ADC_ETC
+            External = 0b10000,
+            Disabled = 0b11111
+        ]
+    ],
+    HS [
+        /// Conversion complete flag of HC0
+        COCO0 OFFSET(0) NUMBITS(1) []
+    ],
+    R [
+        /// Data (result of an ADC conversion)
+        CDATA OFFSET(0) NUMBITS(12) []
+    ],
+    CFG [
+        /// Data overwrite enable
+        OVWREN OFFSET(16) NUMBITS(1) [],
+        /// Hardware average select
+        AVGS OFFSET(14) NUMBITS(2) [],
+        /// Conversion trigger select
+        ADTRG OFFSET(13) NUMBITS(1) [
+            Software = 0,
+            Hardware = 1
+        ],
+        /// Voltage reference selection
+        REFSEL OFFSET(11) NUMBITS(2) [],
+        /// High speed configuration
+        ADHSC OFFSET(10) NUMBITS(1) [],
+        /// Defines the sample time duration
+        ADSTS OFFSET(8) NUMBITS(2) [],
+        /// Low-power configuration
+        ADLPC OFFSET(7) NUMBITS(1) [],
+        /// Clock divide select
+        ADIV OFFSET(5) NUMBITS(2) [
+            Div1 = 0,
+            Div2 = 1,
+            Div4 = 2,
+            Div8 = 3
+        ],
+        /// Long sample time configuration
+        ADLSMP OFFSET(4) NUMBITS(1) [],
+        /// Conversion mode selection
+        MODE OFFSET(2) NUMBITS(2) [
+            Bits8 = 0,
+            Bits10 = 1,
+            Bits12 = 2
+        ],
+        /// Input clock select
+        ADICLK OFFSET(0) NUMBITS(2) [
+            Ipg = 0,
+            IpgDiv2 = 1,
+            Adack = 3
+        ]
+    ],
+    GC [
+        /// Calibration
+        CAL OFFSET(7) NUMBITS(1) [],
+        /// Continuous conversion enable
+        ADCO OFFSET(6) NUMBITS(1) [],
+        /// Hardware average enable
+        AVGE OFFSET(5) NUMBITS(1) [],
+        /// DMA enable
+        DMAEN OFFSET(1) NUMBITS(1) [],
+        /// Asynchronous clock output enable
+        ADACKEN OFFSET(0) NUMBITS(1) []
+    ],
+    GS [
+        /// Calibration failed flag, write 1 to clear
+        CALF OFFSET(1) NUMBITS(1) [],
+        /// Conversion active
+        ADACT OFFSET(0) NUMBITS(1) []
+    ]
+];
+
+const ADC1_BASE: StaticRef<AdcRegisters> =
+    unsafe { StaticRef::new(0x400C_4000 as *const AdcRegisters) };
+const ADC2_BASE: StaticRef<AdcRegisters> =
+    unsafe { StaticRef::new(0x400C_8000 as *const AdcRegisters) };
+
+/// The ADC inputs
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum Channel {
+    In0 = 0,
+    In1 = 1,
+    In2 = 2,
+    In3 = 3,
+    In4 = 4,
+    In5 = 5,
+    In6 = 6,
+    In7 = 7,
+    In8 = 8,
+    In9 = 9,
+    In10 = 10,
+    In11 = 11,
+    In12 = 12,
+    In13 = 13,
+    In14 = 14,
+    In15 = 15,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Status {
+    Off,
+    Idle,
+    Sampling,
+    HardwareTriggered,
+}
+
+pub struct Adc<'a> {
+    registers: StaticRef<AdcRegisters>,
+    clock: AdcClock<'a>,
+    status: Cell<Status>,
+    client: OptionalCell<&'a dyn hil::adc::Client>,
+}
+
+impl<'a> Adc<'a> {
+    pub const fn new_adc1(ccm: &'a ccm::Ccm) -> Self {
+        Adc::new(
+            ADC1_BASE,
+            ccm::PeripheralClock::ccgr1(ccm, ccm::HCLK1::ADC1),
+        )
+    }
+
+    pub const fn new_adc2(ccm: &'a ccm::Ccm) -> Self {
+        Adc::new(
+            ADC2_BASE,
+            ccm::PeripheralClock::ccgr1(ccm, ccm::HCLK1::ADC2),
+        )
+    }
+
+    const fn new(registers: StaticRef<AdcRegisters>, clock_gate: ccm::PeripheralClock<'a>) -> Self {
+        Adc {
+            registers,
+            clock: AdcClock(clock_gate),
+            status: Cell::new(Status::Off),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn is_enabled_clock(&self) -> bool {
+        self.clock.is_enabled()
+    }
+
+    pub fn enable_clock(&self) {
+        self.clock.enable();
+    }
+
+    pub fn disable_clock(&self) {
+        self.clock.disable();
+    }
+
+    /// Configure 12-bit conversions and calibrate the ADC.
+    fn initialize(&self) -> Result<(), ErrorCode> {
+        if self.status.get() != Status::Off {
+            return Ok(());
+        }
+        self.enable_clock();
+
+        // The ADC clock is the IPG clock divided by 4, which stays below the
+        // 40 MHz limit of the high speed configuration.
+        self.registers.cfg.write(
+            CFG::ADICLK::IpgDiv2
+                + CFG::ADIV::Div2
+                + CFG::MODE::Bits12
+                + CFG::ADHSC::SET
+                + CFG::ADTRG::Software,
+        );
+
+        // Calibration takes a few thousand ADC clock cycles, and is only done
+        // once.
+        self.registers.gs.write(GS::CALF::SET);
+        self.registers.gc.modify(GC::CAL::SET);
+        while self.registers.gc.is_set(GC::CAL) {}
+        if self.registers.gs.is_set(GS::CALF) {
+            return Err(ErrorCode::FAIL);
+        }
+
+        self.status.set(Status::Idle);
+        Ok(())
+    }
+
+    /// Let the ADC_ETC start the conversions of this ADC and select their
+    /// inputs if `enabled`, or switch back to software started conversions.
+    pub fn set_hardware_trigger(&self, enabled: bool) -> Result<(), ErrorCode> {
+        self.initialize()?;
+        match self.status.get() {
+            Status::Sampling => Err(ErrorCode::BUSY),
+            _ => {
+                if enabled {
+                    self.registers.cfg.modify(CFG::ADTRG::Hardware);
+                    for hc in self.registers.hc.iter() {
+                        hc.write(HC::ADCH::External);
+                    }
+                    self.status.set(Status::HardwareTriggered);
+                } else {
+                    for hc in self.registers.hc.iter() {
+                        hc.write(HC::ADCH::Disabled);
+                    }
+                    self.registers.cfg.modify(CFG::ADTRG::Software);
+                    self.status.set(Status::Idle);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Let the touch screen controller start the conversions of this ADC,
+    /// which must be ADC2. The TSC converts `x` with HC3 and `y` with HC1.
+    pub fn set_touch_screen_trigger(&self, x: Channel, y: Channel) -> Result<(), ErrorCode> {
+        self.initialize()?;
+        match self.status.get() {
+            Status::Sampling => Err(ErrorCode::BUSY),
+            _ => {
+                self.registers.cfg.modify(CFG::ADTRG::Hardware);
+                for hc in self.registers.hc.iter() {
+                    hc.write(HC::ADCH::Disabled);
+                }
+                self.registers.hc[3].write(HC::ADCH.val(x as u32));
+                self.registers.hc[1].write(HC::ADCH.val(y as u32));
+                self.status.set(Status::HardwareTriggered);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn handle_interrupt(&self) {
+        if self.status.get() != Status::Sampling || !self.registers.hs.is_set(HS::COCO0) {
+            return;
+        }
+        // Reading the result clears the conversion complete flag.
+        let sample = self.registers.r[0].read(R::CDATA) as u16;
+        self.registers.hc[0].write(HC::ADCH::Disabled);
+        self.status.set(Status::Idle);
+        // Left-align the sample, as the HIL expects.
+        self.client.map(|client| client.sample_ready(sample << 4));
+    }
+}
+
+struct AdcClock<'a>(ccm::PeripheralClock<'a>);
+
+impl ClockInterface for AdcClock<'_> {
+    fn is_enabled(&self) -> bool {
+        self.0.is_enabled()
+    }
+
+    fn enable(&self) {
+        self.0.enable();
+    }
+
+    fn disable(&self) {
+        self.0.disable();
+    }
+}
+
+impl<'a> hil::adc::Adc<'a> for Adc<'a> {
+    type Channel = Channel;
+
+    fn sample(&self, channel: &Self::Channel) -> Result<(), ErrorCode> {
+        self.initialize()?;
+        match self.status.get() {
+            Status::Idle => {
+                self.status.set(Status::Sampling);
+                // Writing the channel starts the conversion.
+                self.registers.hc[0].write(HC::AIEN::SET + HC::ADCH.val(*channel as u32));
+                Ok(())
+            }
+            Status::HardwareTriggered => Err(ErrorCode::RESERVE),
+            _ => Err(ErrorCode::BUSY),
+        }
+    }
+
+    fn sample_continuous(
+        &self,
+        _channel: &Self::Channel,
+        _frequency: u32,
+    ) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    fn stop_sampling(&self) -> Result<(), ErrorCode> {
+        if self.status.get() == Status::Sampling {
+            self.registers.hc[0].write(HC::ADCH::Disabled);
+            self.status.set(Status::Idle);
+        }
+        Ok(())
+    }
+
+    fn get_resolution_bits(&self) -> usize {
+        12
+    }
+
+    fn get_voltage_reference_mv(&self) -> Option<usize> {
+        // VREFH is connected to the 3.3V supply on the evaluation boards.
+        Some(3300)
+    }
+
+    fn set_client(&self, client: &'a dyn hil::adc::Client) {
+        self.client.set(client);
+    }
+}
@@ -0,0 +1,361 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! ADC External Trigger Control (ADC_ETC).
+//!
+//! The ADC_ETC starts chains of ADC conversions on trigger events, without
+//! software involvement. It has eight triggers: triggers 0 to 3 convert on
+//! ADC1, and triggers 4 to 7 on ADC2. Each trigger converts a chain of up to
+//! eight inputs, back to back, and keeps the results in its own result
+//! registers until the next conversion of the chain.
+//!
+//! A trigger is started either by software, or by an external event routed
+//! to it through the crossbar (`xbar`), such as a PWM or PIT trigger. In
+//! synchronous mode, triggers 0 to 3 also start triggers 4 to 7 at the same
+//! time, so that ADC1 and ADC2 sample two inputs simultaneously, for example
+//! two phase currents of a motor.
+//!
+//! The ADCs must be switched to hardware triggers with
+//! `Adc::set_hardware_trigger` before the triggers fire.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! use imxrt10xx::{adc, adc_etc, xbar};
+//!
+//! peripherals.adc1.set_hardware_trigger(true)?;
+//! peripherals.adc2.set_hardware_trigger(true)?;
+//! // Sample ADC1 IN3 and ADC2 IN4 together on every PIT trigger 0.
+//! peripherals.adc_etc.configure_trigger(
+//!     0,
+//!     &[adc::Channel::In3],
+//!     adc_etc::TriggerSource::External,
+//!     true,
+//! )?;
+//! peripherals.adc_etc.configure_trigger(
+//!     4,
+//!     &[adc::Channel::In4],
+//!     adc_etc::TriggerSource::External,
+//!     false,
+//! )?;
+//! peripherals.xbar1.connect(PIT_TRIGGER0, xbar::adc_etc_trigger_output(0))?;
+//! peripherals.adc_etc.set_client(motor_control);
+//! peripherals.adc_etc.enable_trigger(0)?;
+//! ```
+
+use core::cell::Cell;
+
+use kernel::utilities::cells::OptionalCell;
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
+use kernel::utilities::registers::{register_bitfields, FieldValue, ReadOnly, ReadWrite};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+use crate::adc;
+
+/// Number of triggers
+pub const NUMBER_TRIGGERS: usize = 8;
+
+/// Maximum number of conversions in the chain of a trigger
+pub const MAX_CHAIN_LENGTH: usize = 8;
+
+/// Registers of one trigger
+#[repr(C)]
+struct TriggerRegisters {
+    /// ETC_TRIG Control Register
+    ctrl: ReadWrite<u32, TRIG_CTRL::Register>,
+    /// ETC_TRIG Counter Register
+    counter: ReadWrite<u32, TRIG_COUNTER::Register>,
+    /// ETC_TRIG Chain Registers, with two segments each
+    chain: [ReadWrite<u32, TRIG_CHAIN::Register>; MAX_CHAIN_LENGTH / 2],
+    /// ETC_TRIG Result Data Registers, with two results each
+    result: [ReadOnly<u32, TRIG_RESULT::Register>; MAX_CHAIN_LENGTH / 2],
+}
+
+#[repr(C)]
+struct AdcEtcRegisters {
+    /// ADC_ETC Global Control Register
+    ctrl: ReadWrite<u32, CTRL::Register>,
+    /// ETC DONE0 and DONE1 IRQ State Register
+    done0_1_irq: ReadWrite<u32, DONE0_1_IRQ::Register>,
+    /// ETC DONE_2 and DONE_ERR IRQ State Register
+    done2_err_irq: ReadWrite<u32, DONE2_ERR_IRQ::Register>,
+    /// ETC DMA control Register
+    dma_ctrl: ReadWrite<u32>,
+    trig: [TriggerRegisters; NUMBER_TRIGGERS],
+}
+
+register_bitfields![u32,
+    CTRL [
+        /// Software reset, high active
+        SOFTRST OFFSET(31) NUMBITS(1) [],
+        /// Do not let the TSC use ADC2
+        TSC_BYPASS OFFSET(30) NUMBITS(1) [],
+        /// Pre-divider for trig delay and interval
+        PRE_DIVIDER OFFSET(16) NUMBITS(8) [],
+        /// Enable the external XBAR triggers
+        TRIG_ENABLE OFFSET(0) NUMBITS(8) []
+    ],
+    DONE0_1_IRQ [
+        /// DONE1 interrupts of the triggers, write 1 to clear
+        TRIG_DONE1 OFFSET(16) NUMBITS(8) [],
+        /// DONE0 interrupts of the triggers, write 1 to clear
+        TRIG_DONE0 OFFSET(0) NUMBITS(8) []
+    ],
+    DONE2_ERR_IRQ [
+        /// Error interrupts of the triggers, write 1 to clear
+        TRIG_ERR OFFSET(16) NUMBITS(8) [],
+        /// DONE2 interrupts of the triggers, write 1 to clear
+        TRIG_DONE2 OFFSET(0) NUMBITS(8) []
+    ],
+    TRIG_CTRL [
+        /// Synchronous mode, where the trigger also starts trigger + 4
+        SYNC_MODE OFFSET(16) NUMBITS(1) [],
+        /// Priority of the external trigger
+        TRIG_PRIORITY OFFSET(12) NUMBITS(3) [],
+        /// Length of the trigger chain, minus one
+        TRIG_CHAIN OFFSET(8) NUMBITS(3) [],
+        /// Trigger mode
+        TRIG_MODE OFFSET(4) NUMBITS(1) [
+            Hardware = 0,
+            Software = 1
+        ],
+        /// Software write 1 as the trigger
+        SW_TRIG OFFSET(0) NUMBITS(1) []
+    ],
+    TRIG_COUNTER [
+        /// Interval between two hardware triggers
+        SAMPLE_INTERVAL OFFSET(16) NUMBITS(16) [],
+        /// Delay before the first conversion
+        INIT_DELAY OFFSET(0) NUMBITS(16) []
+    ],
+    TRIG_CHAIN [
+        /// Interrupt of segment 1
+        IE1 OFFSET(29) NUMBITS(2) [],
+        /// Back to back conversion of segment 1
+        B2B1 OFFSET(28) NUMBITS(1) [],
+        /// ADC hardware trigger selection of segment 1
+        HWTS1 OFFSET(20) NUMBITS(8) [],
+        /// ADC input of segment 1
+        CSEL1 OFFSET(16) NUMBITS(4) [],
+        /// Interrupt of segment 0
+        IE0 OFFSET(13) NUMBITS(2) [],
+        /// Back to back conversion of segment 0
+        B2B0 OFFSET(12) NUMBITS(1) [],
+        /// ADC hardware trigger selection of segment 0
+        HWTS0 OFFSET(4) NUMBITS(8) [],
+        /// ADC input of segment 0
+        CSEL0 OFFSET(0) NUMBITS(4) []
+    ],
+    TRIG_RESULT [
+        /// Result of segment 1
+        DATA1 OFFSET(16) NUMBITS(12) [],
+        /// Result of segment 0
+        DATA0 OFFSET(0) NUMBITS(12) []
+    ]
+];
+
+const ADC_ETC_BASE: StaticRef<AdcEtcRegisters> =
+    unsafe { StaticRef::new(0x403B_0000 as *const AdcEtcRegisters) };
+
+/// Interrupt of a chain segment which raises the DONE0 interrupt
+const IE_DONE0: u32 = 0b01;
+
+/// What starts the conversions of a trigger
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TriggerSource {
+    /// The crossbar output of the trigger, once enabled with
+    /// `enable_trigger`
+    External,
+    /// `software_trigger`
+    Software,
+}
+
+pub trait AdcEtcClient {
+    /// The chain of `trigger` was converted, with the 12-bit `results` in the
+    /// order of the chain.
+    fn conversions_done(&self, trigger: usize, results: &[u16]);
+
+    /// `trigger` fired while its previous conversions were pending, and this
+    /// event was dropped.
+    fn trigger_dropped(&self, _trigger: usize) {}
+}
+
+pub struct AdcEtc<'a> {
+    registers: StaticRef<AdcEtcRegisters>,
+    chain_lengths: [Cell<usize>; NUMBER_TRIGGERS],
+    client: OptionalCell<&'a dyn AdcEtcClient>,
+}
+
+impl<'a> AdcEtc<'a> {
+    pub const fn new() -> Self {
+        AdcEtc {
+            registers: ADC_ETC_BASE,
+            chain_lengths: [
+                Cell::new(0),
+                Cell::new(0),
+                Cell::new(0),
+                Cell::new(0),
+                Cell::new(0),
+                Cell::new(0),
+                Cell::new(0),
+                Cell::new(0),
+            ],
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn AdcEtcClient) {
+        self.client.set(client);
+    }
+
+    /// Take the ADC_ETC out of reset. ADC2 stays dedicated to the ADC_ETC,
+    /// rather than to the touch screen controller.
+    fn enable(&self) {
+        if self.registers.ctrl.is_set(CTRL::SOFTRST) {
+            self.registers.ctrl.write(CTRL::TSC_BYPASS::SET);
+        }
+    }
+
+    /// Let the touch screen controller start the conversions of ADC2 if
+    /// `bypass` is cleared. ADC2 then stops converting for the ADC_ETC
+    /// triggers.
+    pub fn set_tsc_bypass(&self, bypass: bool) {
+        self.enable();
+        self.registers
+            .ctrl
+            .modify(CTRL::TSC_BYPASS.val(bypass as u32));
+    }
+
+    /// Set the `channels` converted, in order, when `trigger` fires. If
+    /// `sync` is set, which is only possible for triggers 0 to 3, trigger
+    /// `trigger + 4` is also started on ADC2, with its own chain.
+    pub fn configure_trigger(
+        &self,
+        trigger: usize,
+        channels: &[adc::Channel],
+        source: TriggerSource,
+        sync: bool,
+    ) -> Result<(), ErrorCode> {
+        if trigger >= NUMBER_TRIGGERS
+            || channels.is_empty()
+            || channels.len() > MAX_CHAIN_LENGTH
+            || (sync && trigger >= NUMBER_TRIGGERS / 2)
+        {
+            return Err(ErrorCode::INVAL);
+        }
+        self.enable();
+
+        let registers = &self.registers.trig[trigger];
+        let mode = match source {
+            TriggerSource::External => TRIG_CTRL::TRIG_MODE::Hardware,
+            TriggerSource::Software => TRIG_CTRL::TRIG_MODE::Software,
+        };
+        registers.ctrl.write(
+            TRIG_CTRL::TRIG_CHAIN.val(channels.len() as u32 - 1)
+                + TRIG_CTRL::SYNC_MODE.val(sync as u32)
+                + mode,
+        );
+        registers.counter.set(0);
+
+        // Segment `i` converts with the ADC hardware trigger `i`, and only the
+        // last segment raises an interrupt.
+        for (index, chain) in registers.chain.iter().enumerate() {
+            let segment = |i: usize| {
+                let channel = channels.get(i).map_or(0, |channel| *channel as u32);
+                let ie = if i == channels.len() - 1 { IE_DONE0 } else { 0 };
+                (channel, 1 << i, ie)
+            };
+            let (csel0, hwts0, ie0) = segment(2 * index);
+            let (csel1, hwts1, ie1) = segment(2 * index + 1);
+            let value: FieldValue<u32, TRIG_CHAIN::Register> = TRIG_CHAIN::CSEL0.val(csel0)
+                + TRIG_CHAIN::HWTS0.val(hwts0)
+                + TRIG_CHAIN::B2B0::SET
+                + TRIG_CHAIN::IE0.val(ie0)
+                + TRIG_CHAIN::CSEL1.val(csel1)
+                + TRIG_CHAIN::HWTS1.val(hwts1)
+                + TRIG_CHAIN::B2B1::SET
+                + TRIG_CHAIN::IE1.val(ie1);
+            chain.write(value);
+        }
+
+        self.chain_lengths[trigger].set(channels.len());
+        Ok(())
+    }
+
+    /// Start the conversions of `trigger` on the events of its crossbar
+    /// output.
+    pub fn enable_trigger(&self, trigger: usize) -> Result<(), ErrorCode> {
+        if trigger >= NUMBER_TRIGGERS || self.chain_lengths[trigger].get() == 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        let enabled = self.registers.ctrl.read(CTRL::TRIG_ENABLE);
+        self.registers
+            .ctrl
+            .modify(CTRL::TRIG_ENABLE.val(enabled | (1 << trigger)));
+        Ok(())
+    }
+
+    pub fn disable_trigger(&self, trigger: usize) -> Result<(), ErrorCode> {
+        if trigger >= NUMBER_TRIGGERS {
+            return Err(ErrorCode::INVAL);
+        }
+        let enabled = self.registers.ctrl.read(CTRL::TRIG_ENABLE);
+        self.registers
+            .ctrl
+            .modify(CTRL::TRIG_ENABLE.val(enabled & !(1 << trigger)));
+        Ok(())
+    }
+
+    /// Start the conversions of a trigger configured with
+    /// `TriggerSource::Software`.
+    pub fn software_trigger(&self, trigger: usize) -> Result<(), ErrorCode> {
+        if trigger >= NUMBER_TRIGGERS || self.chain_lengths[trigger].get() == 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        let registers = &self.registers.trig[trigger];
+        if !registers.ctrl.matches_all(TRIG_CTRL::TRIG_MODE::Software) {
+            return Err(ErrorCode::INVAL);
+        }
+        registers.ctrl.modify(TRIG_CTRL::SW_TRIG::SET);
+        Ok(())
+    }
+
+    /// Returns the results of the last conversions of `trigger`.
+    fn results(&self, trigger: usize) -> ([u16; MAX_CHAIN_LENGTH], usize) {
+        let mut results = [0; MAX_CHAIN_LENGTH];
+        let length = self.chain_lengths[trigger].get();
+        for (index, result) in self.registers.trig[trigger].result.iter().enumerate() {
+            results[2 * index] = result.read(TRIG_RESULT::DATA0) as u16;
+            results[2 * index + 1] = result.read(TRIG_RESULT::DATA1) as u16;
+        }
+        (results, length)
+    }
+
+    /// Handle the DONE0 interrupt, raised at the end of the chains.
+    pub fn handle_interrupt(&self) {
+        let done = self.registers.done0_1_irq.read(DONE0_1_IRQ::TRIG_DONE0);
+        self.registers
+            .done0_1_irq
+            .write(DONE0_1_IRQ::TRIG_DONE0.val(done));
+
+        for trigger in (0..NUMBER_TRIGGERS).filter(|trigger| done & (1 << trigger) != 0) {
+            let (results, length) = self.results(trigger);
+            self.client
+                .map(|client| client.conversions_done(trigger, &results[..length]));
+        }
+    }
+
+    pub fn handle_error_interrupt(&self) {
+        let errors = self.registers.done2_err_irq.read(DONE2_ERR_IRQ::TRIG_ERR);
+        self.registers
+            .done2_err_irq
+            .write(DONE2_ERR_IRQ::TRIG_ERR.val(errors));
+
+        for trigger in (0..NUMBER_TRIGGERS).filter(|trigger| errors & (1 << trigger) != 0) {
+            self.client.map(|client| client.trigger_dropped(trigger));
+        }
+    }
+}
@@ -26,15 +26,15 @@ register_structs! {
         /// CCM Serial Clock Multiplexer Register 1
         (0x01C => cscmr1: ReadWrite<u32, CSCMR1::Register>),
         /// CCM Serial Clock Multiplexer Register 2
-        (0x020 => cscmr2: ReadWrite<u32>),
+        (0x020 => cscmr2: ReadWrite<u32, CSCMR2::Register>),
         /// CCM Serial Clock Divider Register 1
         (0x024 => cscdr1: ReadWrite<u32, CSCDR1::Register>),
         /// CCM Clock Divider Register
-        (0x028 => cs1cdr: ReadWrite<u32>),
+        (0x028 => cs1cdr: ReadWrite<u32, CS1CDR::Register>),
         /// CCM Clock Divider Register
         (0x02C => cs2cdr: ReadWrite<u32>),
         /// CCM D1 Clock Divider Register
-        (0x030 => cdcdr: ReadWrite<u32>),
+        (0x030 => cdcdr: ReadWrite<u32, CDCDR::Register>),
         (0x034 => _reserved1),
         /// CCM Serial Clock Divider Register 2
         (0x038 => cscdr2: ReadWrite<u32>),
@@ -168,9 +168,42 @@ register_bitfields![u32,
         UART_CLK_PODF OFFSET(0) NUMBITS(6) []
     ],
 
+    CSCMR2 [
+        // Selector for the flexio2 clock multiplexer
+        FLEXIO2_CLK_SEL OFFSET(19) NUMBITS(2) []
+    ],
+
+    CS1CDR [
+        // Divider for flexio2 clock podf
+        FLEXIO2_CLK_PODF OFFSET(25) NUMBITS(3) [],
+        // Divider for flexio2 clock pred
+        FLEXIO2_CLK_PRED OFFSET(9) NUMBITS(3) []
+    ],
+
+    CDCDR [
+        // Divider for flexio1 clock podf
+        FLEXIO1_CLK_PODF OFFSET(12) NUMBITS(3) [],
+        // Divider for flexio1 clock pred
+        FLEXIO1_CLK_PRED OFFSET(9) NUMBITS(3) [],
+        // Selector for the flexio1 clock multiplexer
+        FLEXIO1_CLK_SEL OFFSET(7) NUMBITS(2) []
+    ],
+
     CLPCR [
-        WHATEVER OFFSET(2) NUMBITS(30) [],
-        LPM OFFSET(0) NUMBITS(2) []
+        /// Mask L2 cache idle in the low power mode entry conditions
+        MASK_L2CC_IDLE OFFSET(27) NUMBITS(1) [],
+        /// Mask SCU idle in the low power mode entry conditions
+        MASK_SCU_IDLE OFFSET(26) NUMBITS(1) [],
+        /// Standby counter, in cycles of the 32 kHz clock
+        STBY_COUNT OFFSET(9) NUMBITS(2) [],
+        /// Disable the ARM clock in low power mode
+        ARM_CLK_DIS_ON_LPM OFFSET(5) NUMBITS(1) [],
+        /// Low power mode entered on the next WFI
+        LPM OFFSET(0) NUMBITS(2) [
+            Run = 0,
+            Wait = 1,
+            Stop = 2
+        ]
     ],
 
     // Supports al clock gate registers
@@ -210,6 +243,29 @@ pub enum UartClockSelection {
     Oscillator = 1,
 }
 
+/// Describes the FlexIO clock selection
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[repr(u32)]
+pub enum FlexioClockSelection {
+    /// PLL4
+    PLL4 = 0,
+    /// PLL3 PFD2
+    PLL3PFD2 = 1,
+    /// PLL5
+    PLL5 = 2,
+    /// pll3_sw_clk, 480 MHz
+    PLL3 = 3,
+}
+
+/// Describes the mode entered on WFI with SLEEPDEEP set
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WfiMode {
+    /// The core sleeps, everything else keeps running
+    Run,
+    /// The core and the peripheral clocks stop
+    Stop,
+}
+
 impl Ccm {
     pub const fn new() -> Ccm {
         Ccm {
@@ -221,6 +277,28 @@ impl Ccm {
         self.registers.clpcr.modify(CLPCR::LPM.val(0b00 as u32));
     }
 
+    /// Select the mode entered when the core executes WFI with SLEEPDEEP
+    /// set. In STOP mode, the clocks of every peripheral and of the core are
+    /// stopped until an interrupt unmasked in the GPC arrives.
+    ///
+    /// Changing away from RUN must be done through the GPC, which works
+    /// around erratum ERR007265.
+    pub fn set_wfi_mode(&self, mode: WfiMode) {
+        match mode {
+            WfiMode::Run => self
+                .registers
+                .clpcr
+                .modify(CLPCR::LPM::Run + CLPCR::ARM_CLK_DIS_ON_LPM::CLEAR),
+            WfiMode::Stop => self.registers.clpcr.modify(
+                CLPCR::LPM::Stop
+                    + CLPCR::ARM_CLK_DIS_ON_LPM::SET
+                    + CLPCR::STBY_COUNT.val(0b11)
+                    + CLPCR::MASK_SCU_IDLE::SET
+                    + CLPCR::MASK_L2CC_IDLE::SET,
+            ),
+        }
+    }
+
     // Iomuxc_snvs clock
     pub fn is_enabled_iomuxc_snvs_clock(&self) -> bool {
         self.registers.ccgr[2].is_set(CCGR::CG2)
@@ -251,6 +329,19 @@ impl Ccm {
         self.registers.ccgr[4].modify(CCGR::CG1::CLEAR)
     }
 
+    /// TSC_DIG clock
+    pub fn is_enabled_tsc_clock(&self) -> bool {
+        self.registers.ccgr[4].is_set(CCGR::CG5)
+    }
+
+    pub fn enable_tsc_clock(&self) {
+        self.registers.ccgr[4].modify(CCGR::CG5.val(0b11 as u32))
+    }
+
+    pub fn disable_tsc_clock(&self) {
+        self.registers.ccgr[4].modify(CCGR::CG5::CLEAR)
+    }
+
     /// GPIO1 clock
     pub fn is_enabled_gpio1_clock(&self) -> bool {
         self.registers.ccgr[1].is_set(CCGR::CG13)
@@ -303,6 +394,97 @@ impl Ccm {
         self.registers.ccgr[3].modify(CCGR::CG6::CLEAR)
     }
 
+    /// ACMP1 clock
+    pub fn is_enabled_acmp1_clock(&self) -> bool {
+        self.registers.ccgr[3].is_set(CCGR::CG10)
+    }
+
+    pub fn enable_acmp1_clock(&self) {
+        self.registers.ccgr[3].modify(CCGR::CG10.val(0b11 as u32))
+    }
+
+    pub fn disable_acmp1_clock(&self) {
+        self.registers.ccgr[3].modify(CCGR::CG10::CLEAR)
+    }
+
+    /// ACMP2 clock
+    pub fn is_enabled_acmp2_clock(&self) -> bool {
+        self.registers.ccgr[3].is_set(CCGR::CG11)
+    }
+
+    pub fn enable_acmp2_clock(&self) {
+        self.registers.ccgr[3].modify(CCGR::CG11.val(0b11 as u32))
+    }
+
+    pub fn disable_acmp2_clock(&self) {
+        self.registers.ccgr[3].modify(CCGR::CG11::CLEAR)
+    }
+
+    /// ACMP3 clock
+    pub fn is_enabled_acmp3_clock(&self) -> bool {
+        self.registers.ccgr[3].is_set(CCGR::CG12)
+    }
+
+    pub fn enable_acmp3_clock(&self) {
+        self.registers.ccgr[3].modify(CCGR::CG12.val(0b11 as u32))
+    }
+
+    pub fn disable_acmp3_clock(&self) {
+        self.registers.ccgr[3].modify(CCGR::CG12::CLEAR)
+    }
+
+    /// ACMP4 clock
+    pub fn is_enabled_acmp4_clock(&self) -> bool {
+        self.registers.ccgr[3].is_set(CCGR::CG13)
+    }
+
+    pub fn enable_acmp4_clock(&self) {
+        self.registers.ccgr[3].modify(CCGR::CG13.val(0b11 as u32))
+    }
+
+    pub fn disable_acmp4_clock(&self) {
+        self.registers.ccgr[3].modify(CCGR::CG13::CLEAR)
+    }
+
+    /// ADC1 clock
+    pub fn is_enabled_adc1_clock(&self) -> bool {
+        self.registers.ccgr[1].is_set(CCGR::CG8)
+    }
+
+    pub fn enable_adc1_clock(&self) {
+        self.registers.ccgr[1].modify(CCGR::CG8.val(0b11 as u32))
+    }
+
+    pub fn disable_adc1_clock(&self) {
+        self.registers.ccgr[1].modify(CCGR::CG8::CLEAR)
+    }
+
+    /// ADC2 clock
+    pub fn is_enabled_adc2_clock(&self) -> bool {
+        self.registers.ccgr[1].is_set(CCGR::CG4)
+    }
+
+    pub fn enable_adc2_clock(&self) {
+        self.registers.ccgr[1].modify(CCGR::CG4.val(0b11 as u32))
+    }
+
+    pub fn disable_adc2_clock(&self) {
+        self.registers.ccgr[1].modify(CCGR::CG4::CLEAR)
+    }
+
+    /// XBAR1 clock
+    pub fn is_enabled_xbar1_clock(&self) -> bool {
+        self.registers.ccgr[2].is_set(CCGR::CG11)
+    }
+
+    pub fn enable_xbar1_clock(&self) {
+        self.registers.ccgr[2].modify(CCGR::CG11.val(0b11 as u32))
+    }
+
+    pub fn disable_xbar1_clock(&self) {
+        self.registers.ccgr[2].modify(CCGR::CG11::CLEAR)
+    }
+
     /// GPIO5 clock
     pub fn is_enabled_gpio5_clock(&self) -> bool {
         self.registers.ccgr[1].is_set(CCGR::CG15)
@@ -359,6 +541,32 @@ impl Ccm {
         self.registers.ccgr[2].modify(CCGR::CG3::CLEAR);
     }
 
+    // OCOTP clock
+    pub fn is_enabled_ocotp_clock(&self) -> bool {
+        self.registers.ccgr[2].is_set(CCGR::CG6)
+    }
+
+    pub fn enable_ocotp_clock(&self) {
+        self.registers.ccgr[2].modify(CCGR::CG6.val(0b11 as u32));
+    }
+
+    pub fn disable_ocotp_clock(&self) {
+        self.registers.ccgr[2].modify(CCGR::CG6::CLEAR);
+    }
+
+    // ENET clock
+    pub fn is_enabled_enet_clock(&self) -> bool {
+        self.registers.ccgr[1].is_set(CCGR::CG5)
+    }
+
+    pub fn enable_enet_clock(&self) {
+        self.registers.ccgr[1].modify(CCGR::CG5.val(0b11 as u32));
+    }
+
+    pub fn disable_enet_clock(&self) {
+        self.registers.ccgr[1].modify(CCGR::CG5::CLEAR);
+    }
+
     // LPUART1 clock
     pub fn is_enabled_lpuart1_clock(&self) -> bool {
         self.registers.ccgr[5].is_set(CCGR::CG12)
@@ -425,6 +633,52 @@ impl Ccm {
     pub fn uart_clock_podf(&self) -> u32 {
         (self.registers.cscdr1.read(CSCDR1::UART_CLK_PODF) + 1) as u32
     }
+
+    //
+    // FlexIO clocks
+    //
+
+    /// Set the FlexIO1 clock selection and dividers
+    ///
+    /// Should only be called when the FlexIO1 clock gate is disabled.
+    /// `pred` and `podf` are clamped between [1, 8].
+    pub fn set_flexio1_clock(&self, selection: FlexioClockSelection, pred: u32, podf: u32) {
+        let pred = pred.min(8).max(1) - 1;
+        let podf = podf.min(8).max(1) - 1;
+        self.registers.cdcdr.modify(
+            CDCDR::FLEXIO1_CLK_SEL.val(selection as u32)
+                + CDCDR::FLEXIO1_CLK_PRED.val(pred)
+                + CDCDR::FLEXIO1_CLK_PODF.val(podf),
+        );
+    }
+
+    /// Returns the FlexIO1 clock root divider, `pred * podf`
+    pub fn flexio1_clock_divider(&self) -> u32 {
+        (self.registers.cdcdr.read(CDCDR::FLEXIO1_CLK_PRED) + 1)
+            * (self.registers.cdcdr.read(CDCDR::FLEXIO1_CLK_PODF) + 1)
+    }
+
+    /// Set the FlexIO2 clock selection and dividers
+    ///
+    /// Should only be called when the FlexIO2 clock gate is disabled.
+    /// `pred` and `podf` are clamped between [1, 8].
+    pub fn set_flexio2_clock(&self, selection: FlexioClockSelection, pred: u32, podf: u32) {
+        let pred = pred.min(8).max(1) - 1;
+        let podf = podf.min(8).max(1) - 1;
+        self.registers
+            .cscmr2
+            .modify(CSCMR2::FLEXIO2_CLK_SEL.val(selection as u32));
+        self.registers
+            .cs1cdr
+            .modify(CS1CDR::FLEXIO2_CLK_PRED.val(pred) + CS1CDR::FLEXIO2_CLK_PODF.val(podf));
+    }
+
+    /// Returns the FlexIO2 clock root divider, `pred * podf`
+    pub fn flexio2_clock_divider(&self) -> u32 {
+        (self.registers.cs1cdr.read(CS1CDR::FLEXIO2_CLK_PRED) + 1)
+            * (self.registers.cs1cdr.read(CS1CDR::FLEXIO2_CLK_PODF) + 1)
+    }
+
     //
     // PERCLK
     //
@@ -523,6 +777,11 @@ impl Ccm {
         self.registers.cbcdr.modify(CBCDR::IPG_PODF.val(podf));
     }
 
+    /// Returns the IPG clock divider
+    pub fn ipg_divider(&self) -> u32 {
+        self.registers.cbcdr.read(CBCDR::IPG_PODF) + 1
+    }
+
     /// Set the peripheral clock selection
     pub fn set_peripheral_clock_selection(&self, selection: PeripheralClockSelection) {
         let selection = match selection {
@@ -619,6 +878,96 @@ impl Ccm {
     pub fn is_enabled_dma_clock(&self) -> bool {
         self.registers.ccgr[5].read(CCGR::CG3) != 0
     }
+
+    /// Enable the FLEXIO1 clock gate
+    pub fn enable_flexio1_clock(&self) {
+        self.registers.ccgr[5].modify(CCGR::CG1.val(0b11));
+    }
+
+    /// Disable the FLEXIO1 clock gate
+    pub fn disable_flexio1_clock(&self) {
+        self.registers.ccgr[5].modify(CCGR::CG1.val(0b00));
+    }
+
+    /// Indicates if the FLEXIO1 clock gate is enabled
+    pub fn is_enabled_flexio1_clock(&self) -> bool {
+        self.registers.ccgr[5].read(CCGR::CG1) != 0
+    }
+
+    /// Enable the FLEXIO2 clock gate
+    pub fn enable_flexio2_clock(&self) {
+        self.registers.ccgr[3].modify(CCGR::CG0.val(0b11));
+    }
+
+    /// Disable the FLEXIO2 clock gate
+    pub fn disable_flexio2_clock(&self) {
+        self.registers.ccgr[3].modify(CCGR::CG0.val(0b00));
+    }
+
+    /// Indicates if the FLEXIO2 clock gate is enabled
+    pub fn is_enabled_flexio2_clock(&self) -> bool {
+        self.registers.ccgr[3].read(CCGR::CG0) != 0
+    }
+
+    /// Enable the QTIMER1 clock gate
+    pub fn enable_qtimer1_clock(&self) {
+        self.registers.ccgr[6].modify(CCGR::CG13.val(0b11));
+    }
+
+    /// Disable the QTIMER1 clock gate
+    pub fn disable_qtimer1_clock(&self) {
+        self.registers.ccgr[6].modify(CCGR::CG13.val(0b00));
+    }
+
+    /// Indicates if the QTIMER1 clock gate is enabled
+    pub fn is_enabled_qtimer1_clock(&self) -> bool {
+        self.registers.ccgr[6].read(CCGR::CG13) != 0
+    }
+
+    /// Enable the QTIMER2 clock gate
+    pub fn enable_qtimer2_clock(&self) {
+        self.registers.ccgr[6].modify(CCGR::CG14.val(0b11));
+    }
+
+    /// Disable the QTIMER2 clock gate
+    pub fn disable_qtimer2_clock(&self) {
+        self.registers.ccgr[6].modify(CCGR::CG14.val(0b00));
+    }
+
+    /// Indicates if the QTIMER2 clock gate is enabled
+    pub fn is_enabled_qtimer2_clock(&self) -> bool {
+        self.registers.ccgr[6].read(CCGR::CG14) != 0
+    }
+
+    /// Enable the QTIMER3 clock gate
+    pub fn enable_qtimer3_clock(&self) {
+        self.registers.ccgr[6].modify(CCGR::CG15.val(0b11));
+    }
+
+    /// Disable the QTIMER3 clock gate
+    pub fn disable_qtimer3_clock(&self) {
+        self.registers.ccgr[6].modify(CCGR::CG15.val(0b00));
+    }
+
+    /// Indicates if the QTIMER3 clock gate is enabled
+    pub fn is_enabled_qtimer3_clock(&self) -> bool {
+        self.registers.ccgr[6].read(CCGR::CG15) != 0
+    }
+
+    /// Enable the QTIMER4 clock gate
+    pub fn enable_qtimer4_clock(&self) {
+        self.registers.ccgr[6].modify(CCGR::CG8.val(0b11));
+    }
+
+    /// Disable the QTIMER4 clock gate
+    pub fn disable_qtimer4_clock(&self) {
+        self.registers.ccgr[6].modify(CCGR::CG8.val(0b00));
+    }
+
+    /// Indicates if the QTIMER4 clock gate is enabled
+    pub fn is_enabled_qtimer4_clock(&self) -> bool {
+        self.registers.ccgr[6].read(CCGR::CG8) != 0
+    }
 }
 
 /// Clock selections for the main peripheral
@@ -723,32 +1072,48 @@ pub enum HCLK0 {
 pub enum HCLK1 {
     GPIO1,
     GPIO5,
-    GPT1, // and others ...
+    GPT1,
+    ENET,
+    ADC1,
+    ADC2, // and others ...
 }
 pub enum HCLK2 {
     LPI2C1,
     GPIO3,
-    IOMUXCSNVS, // and others ...
+    IOMUXCSNVS,
+    OCOTP,
+    XBAR1, // and others ...
 }
 
 pub enum HCLK3 {
     GPIO4,
+    ACMP1,
+    ACMP2,
+    ACMP3,
+    ACMP4,
+    FLEXIO2,
     // and others ...
 }
 
 pub enum HCLK4 {
     IOMUXC,
+    TSC,
     // and others ...
 }
 
 pub enum HCLK5 {
     LPUART1,
     DMA,
+    FLEXIO1,
     // and others ...
 }
 
 pub enum HCLK6 {
     DCDC,
+    QTIMER1,
+    QTIMER2,
+    QTIMER3,
+    QTIMER4,
 }
 
 /// Periodic clock selection for GPTs and PITs
@@ -772,24 +1137,40 @@ impl ClockInterface for PeripheralClock<'_> {
                 HCLK1::GPIO1 => self.ccm.is_enabled_gpio1_clock(),
                 HCLK1::GPIO5 => self.ccm.is_enabled_gpio5_clock(),
                 HCLK1::GPT1 => self.ccm.is_enabled_gpt1_clock(),
+                HCLK1::ENET => self.ccm.is_enabled_enet_clock(),
+                HCLK1::ADC1 => self.ccm.is_enabled_adc1_clock(),
+                HCLK1::ADC2 => self.ccm.is_enabled_adc2_clock(),
             },
             ClockGate::CCGR2(ref v) => match v {
                 HCLK2::LPI2C1 => self.ccm.is_enabled_lpi2c1_clock(),
                 HCLK2::GPIO3 => self.ccm.is_enabled_gpio3_clock(),
                 HCLK2::IOMUXCSNVS => self.ccm.is_enabled_iomuxc_snvs_clock(),
+                HCLK2::OCOTP => self.ccm.is_enabled_ocotp_clock(),
+                HCLK2::XBAR1 => self.ccm.is_enabled_xbar1_clock(),
             },
             ClockGate::CCGR3(ref v) => match v {
                 HCLK3::GPIO4 => self.ccm.is_enabled_gpio4_clock(),
+                HCLK3::ACMP1 => self.ccm.is_enabled_acmp1_clock(),
+                HCLK3::ACMP2 => self.ccm.is_enabled_acmp2_clock(),
+                HCLK3::ACMP3 => self.ccm.is_enabled_acmp3_clock(),
+                HCLK3::ACMP4 => self.ccm.is_enabled_acmp4_clock(),
+                HCLK3::FLEXIO2 => self.ccm.is_enabled_flexio2_clock(),
             },
             ClockGate::CCGR4(ref v) => match v {
                 HCLK4::IOMUXC => self.ccm.is_enabled_iomuxc_clock(),
+                HCLK4::TSC => self.ccm.is_enabled_tsc_clock(),
             },
             ClockGate::CCGR5(ref v) => match v {
                 HCLK5::LPUART1 => self.ccm.is_enabled_lpuart1_clock(),
                 HCLK5::DMA => self.ccm.is_enabled_dma_clock(),
+                HCLK5::FLEXIO1 => self.ccm.is_enabled_flexio1_clock(),
             },
             ClockGate::CCGR6(ref v) => match v {
                 HCLK6::DCDC => self.ccm.is_enabled_dcdc_clock(),
+                HCLK6::QTIMER1 => self.ccm.is_enabled_qtimer1_clock(),
+                HCLK6::QTIMER2 => self.ccm.is_enabled_qtimer2_clock(),
+                HCLK6::QTIMER3 => self.ccm.is_enabled_qtimer3_clock(),
+                HCLK6::QTIMER4 => self.ccm.is_enabled_qtimer4_clock(),
             },
         }
     }
@@ -805,24 +1186,40 @@ impl ClockInterface for PeripheralClock<'_> {
                 HCLK1::GPIO1 => self.ccm.enable_gpio1_clock(),
                 HCLK1::GPIO5 => self.ccm.enable_gpio5_clock(),
                 HCLK1::GPT1 => self.ccm.enable_gpt1_clock(),
+                HCLK1::ENET => self.ccm.enable_enet_clock(),
+                HCLK1::ADC1 => self.ccm.enable_adc1_clock(),
+                HCLK1::ADC2 => self.ccm.enable_adc2_clock(),
             },
             ClockGate::CCGR2(ref v) => match v {
                 HCLK2::LPI2C1 => self.ccm.enable_lpi2c1_clock(),
                 HCLK2::GPIO3 => self.ccm.enable_gpio3_clock(),
                 HCLK2::IOMUXCSNVS => self.ccm.enable_iomuxc_snvs_clock(),
+                HCLK2::OCOTP => self.ccm.enable_ocotp_clock(),
+                HCLK2::XBAR1 => self.ccm.enable_xbar1_clock(),
             },
             ClockGate::CCGR3(ref v) => match v {
                 HCLK3::GPIO4 => self.ccm.enable_gpio4_clock(),
+                HCLK3::ACMP1 => self.ccm.enable_acmp1_clock(),
+                HCLK3::ACMP2 => self.ccm.enable_acmp2_clock(),
+                HCLK3::ACMP3 => self.ccm.enable_acmp3_clock(),
+                HCLK3::ACMP4 => self.ccm.enable_acmp4_clock(),
+                HCLK3::FLEXIO2 => self.ccm.enable_flexio2_clock(),
             },
             ClockGate::CCGR4(ref v) => match v {
                 HCLK4::IOMUXC => self.ccm.enable_iomuxc_clock(),
+                HCLK4::TSC => self.ccm.enable_tsc_clock(),
             },
             ClockGate::CCGR5(ref v) => match v {
                 HCLK5::LPUART1 => self.ccm.enable_lpuart1_clock(),
                 HCLK5::DMA => self.ccm.enable_dma_clock(),
+                HCLK5::FLEXIO1 => self.ccm.enable_flexio1_clock(),
             },
             ClockGate::CCGR6(ref v) => match v {
                 HCLK6::DCDC => self.ccm.enable_dcdc_clock(),
+                HCLK6::QTIMER1 => self.ccm.enable_qtimer1_clock(),
+                HCLK6::QTIMER2 => self.ccm.enable_qtimer2_clock(),
+                HCLK6::QTIMER3 => self.ccm.enable_qtimer3_clock(),
+                HCLK6::QTIMER4 => self.ccm.enable_qtimer4_clock(),
             },
         }
     }
@@ -838,24 +1235,40 @@ impl ClockInterface for PeripheralClock<'_> {
                 HCLK1::GPIO1 => self.ccm.disable_gpio1_clock(),
                 HCLK1::GPIO5 => self.ccm.disable_gpio5_clock(),
                 HCLK1::GPT1 => self.ccm.disable_gpt1_clock(),
+                HCLK1::ENET => self.ccm.disable_enet_clock(),
+                HCLK1::ADC1 => self.ccm.disable_adc1_clock(),
+                HCLK1::ADC2 => self.ccm.disable_adc2_clock(),
             },
             ClockGate::CCGR2(ref v) => match v {
                 HCLK2::LPI2C1 => self.ccm.disable_lpi2c1_clock(),
                 HCLK2::GPIO3 => self.ccm.disable_gpio3_clock(),
                 HCLK2::IOMUXCSNVS => self.ccm.disable_iomuxc_snvs_clock(),
+                HCLK2::OCOTP => self.ccm.disable_ocotp_clock(),
+                HCLK2::XBAR1 => self.ccm.disable_xbar1_clock(),
             },
             ClockGate::CCGR3(ref v) => match v {
                 HCLK3::GPIO4 => self.ccm.disable_gpio4_clock(),
+                HCLK3::ACMP1 => self.ccm.disable_acmp1_clock(),
+                HCLK3::ACMP2 => self.ccm.disable_acmp2_clock(),
+                HCLK3::ACMP3 => self.ccm.disable_acmp3_clock(),
+                HCLK3::ACMP4 => self.ccm.disable_acmp4_clock(),
+                HCLK3::FLEXIO2 => self.ccm.disable_flexio2_clock(),
             },
             ClockGate::CCGR4(ref v) => match v {
                 HCLK4::IOMUXC => self.ccm.disable_iomuxc_clock(),
+                HCLK4::TSC => self.ccm.disable_tsc_clock(),
             },
             ClockGate::CCGR5(ref v) => match v {
                 HCLK5::LPUART1 => self.ccm.disable_lpuart1_clock(),
                 HCLK5::DMA => self.ccm.disable_dma_clock(),
+                HCLK5::FLEXIO1 => self.ccm.disable_flexio1_clock(),
             },
             ClockGate::CCGR6(ref v) => match v {
                 HCLK6::DCDC => self.ccm.disable_dcdc_clock(),
+                HCLK6::QTIMER1 => self.ccm.disable_qtimer1_clock(),
+                HCLK6::QTIMER2 => self.ccm.disable_qtimer2_clock(),
+                HCLK6::QTIMER3 => self.ccm.disable_qtimer3_clock(),
+                HCLK6::QTIMER4 => self.ccm.disable_qtimer4_clock(),
             },
         }
     }
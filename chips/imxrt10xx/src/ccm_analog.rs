@@ -609,6 +609,36 @@ impl CcmAnalog {
         self.registers.pll_arm.reg.read(PLL_ARM::DIV_SELECT)
     }
 
+    /// Indicates if PLL1 (ARM PLL) is enabled and locked
+    pub fn is_pll1_locked(&self) -> bool {
+        let pll = &self.registers.pll_arm.reg;
+        pll.is_set(PLL_ARM::ENABLE) && pll.is_set(PLL_ARM::LOCK)
+    }
+
+    /// Returns the PLL2 (system PLL) `DIV_SEL` value: 0 for 20 times the
+    /// oscillator, 1 for 22 times
+    pub fn pll2_div_sel(&self) -> u32 {
+        self.registers.pll_sys.reg.read(PLL_SYS::DIV_SELECT)
+    }
+
+    /// Indicates if PLL2 (system PLL) is enabled and locked
+    pub fn is_pll2_locked(&self) -> bool {
+        let pll = &self.registers.pll_sys.reg;
+        pll.is_set(PLL_SYS::ENABLE) && pll.is_set(PLL_SYS::LOCK)
+    }
+
+    /// Returns the PLL3 (USB1 PLL) `DIV_SEL` value: 0 for 20 times the
+    /// oscillator, 1 for 22 times
+    pub fn pll3_div_sel(&self) -> u32 {
+        self.registers.pll_usb1.reg.read(PLL_USB1::DIV_SELECT)
+    }
+
+    /// Indicates if PLL3 (USB1 PLL) is enabled and locked
+    pub fn is_pll3_locked(&self) -> bool {
+        let pll = &self.registers.pll_usb1.reg;
+        pll.is_set(PLL_USB1::ENABLE) && pll.is_set(PLL_USB1::LOCK)
+    }
+
     /// Restart PLL1 using the new `div_sel`
     ///
     /// Clamps `div_sel` to [54, 108].
@@ -627,4 +657,15 @@ impl CcmAnalog {
         // Wait for lock
         while self.registers.pll_arm.reg.read(PLL_ARM::LOCK) == 0 {}
     }
+
+    /// Start the ENET PLL and output a 50MHz reference clock, as used by
+    /// RMII PHYs.
+    pub fn enable_enet_pll_50mhz(&self) {
+        self.registers.pll_enet.reg.write(
+            PLL_ENET::DIV_SELECT.val(1)
+                + PLL_ENET::BYPASS_CLK_SRC::SelectThe24MHzOscillatorAsSource,
+        );
+        while self.registers.pll_enet.reg.read(PLL_ENET::LOCK) == 0 {}
+        self.registers.pll_enet.set.write(PLL_ENET::ENABLE::SET);
+    }
 }
@@ -8,13 +8,16 @@ use core::fmt::Write;
 use cortexm7::{self, CortexM7, CortexMVariant};
 use kernel::debug;
 use kernel::platform::chip::{Chip, InterruptService};
+use kernel::utilities::cells::OptionalCell;
 
+use crate::ccm::WfiMode;
 use crate::nvic;
 
 pub struct Imxrt10xx<I: InterruptService + 'static> {
     mpu: cortexm7::mpu::MPU,
     userspace_kernel_boundary: cortexm7::syscall::SysCall,
     interrupt_service: &'static I,
+    gpc: OptionalCell<&'static crate::gpc::Gpc<'static>>,
 }
 
 impl<I: InterruptService + 'static> Imxrt10xx<I> {
@@ -23,40 +26,82 @@ impl<I: InterruptService + 'static> Imxrt10xx<I> {
             mpu: cortexm7::mpu::MPU::new(),
             userspace_kernel_boundary: cortexm7::syscall::SysCall::new(),
             interrupt_service,
+            gpc: OptionalCell::empty(),
         }
     }
+
+    /// Enter STOP mode when idle whenever the GPC is ready for deep sleep.
+    pub fn set_gpc(&self, gpc: &'static crate::gpc::Gpc<'static>) {
+        self.gpc.set(gpc);
+    }
 }
 
 pub struct Imxrt10xxDefaultPeripherals {
+    pub acmp: crate::acmp::Acmp<'static>,
+    pub adc1: crate::adc::Adc<'static>,
+    pub adc2: crate::adc::Adc<'static>,
+    pub adc_etc: crate::adc_etc::AdcEtc<'static>,
     pub iomuxc: crate::iomuxc::Iomuxc,
     pub iomuxc_snvs: crate::iomuxc_snvs::IomuxcSnvs,
+    pub iomuxc_gpr: crate::iomuxc_gpr::IomuxcGpr,
     pub ccm: &'static crate::ccm::Ccm,
     pub dcdc: crate::dcdc::Dcdc<'static>,
     pub dma: crate::dma::Dma<'static>,
+    pub enet: crate::enet::Enet<'static>,
+    pub flexio1: crate::flexio::Flexio<'static>,
+    pub flexio2: crate::flexio::Flexio<'static>,
+    pub gpc: crate::gpc::Gpc<'static>,
     pub ccm_analog: crate::ccm_analog::CcmAnalog,
     pub ports: crate::gpio::Ports<'static>,
     pub lpi2c1: crate::lpi2c::Lpi2c<'static>,
     pub lpuart1: crate::lpuart::Lpuart<'static>,
     pub lpuart2: crate::lpuart::Lpuart<'static>,
+    pub ocotp: crate::ocotp::Ocotp<'static>,
     pub gpt1: crate::gpt::Gpt1<'static>,
     pub gpt2: crate::gpt::Gpt2<'static>,
+    pub qtmr1: crate::qtmr::Qtmr<'static>,
+    pub qtmr2: crate::qtmr::Qtmr<'static>,
+    pub qtmr3: crate::qtmr::Qtmr<'static>,
+    pub qtmr4: crate::qtmr::Qtmr<'static>,
+    pub snvs: crate::snvs::Snvs,
+    pub src: crate::src::Src,
+    pub tsc: crate::tsc::Tsc<'static>,
+    pub xbar1: crate::xbar::Xbar<'static>,
 }
 
 impl Imxrt10xxDefaultPeripherals {
     pub fn new(ccm: &'static crate::ccm::Ccm) -> Self {
         Self {
+            acmp: crate::acmp::Acmp::new(ccm),
+            adc1: crate::adc::Adc::new_adc1(ccm),
+            adc2: crate::adc::Adc::new_adc2(ccm),
+            adc_etc: crate::adc_etc::AdcEtc::new(),
             iomuxc: crate::iomuxc::Iomuxc::new(),
             iomuxc_snvs: crate::iomuxc_snvs::IomuxcSnvs::new(),
+            iomuxc_gpr: crate::iomuxc_gpr::IomuxcGpr::new(),
             ccm,
             dcdc: crate::dcdc::Dcdc::new(ccm),
             dma: crate::dma::Dma::new(ccm),
+            enet: crate::enet::Enet::new(ccm),
+            flexio1: crate::flexio::Flexio::new_flexio1(ccm),
+            flexio2: crate::flexio::Flexio::new_flexio2(ccm),
+            gpc: crate::gpc::Gpc::new(ccm),
             ccm_analog: crate::ccm_analog::CcmAnalog::new(),
             ports: crate::gpio::Ports::new(ccm),
             lpi2c1: crate::lpi2c::Lpi2c::new_lpi2c1(ccm),
             lpuart1: crate::lpuart::Lpuart::new_lpuart1(ccm),
             lpuart2: crate::lpuart::Lpuart::new_lpuart2(ccm),
+            ocotp: crate::ocotp::Ocotp::new(ccm),
             gpt1: crate::gpt::Gpt1::new_gpt1(ccm),
             gpt2: crate::gpt::Gpt2::new_gpt2(ccm),
+            qtmr1: crate::qtmr::Qtmr::new_qtmr1(ccm),
+            qtmr2: crate::qtmr::Qtmr::new_qtmr2(ccm),
+            qtmr3: crate::qtmr::Qtmr::new_qtmr3(ccm),
+            qtmr4: crate::qtmr::Qtmr::new_qtmr4(ccm),
+            snvs: crate::snvs::Snvs::new(),
+            src: crate::src::Src::new(),
+            tsc: crate::tsc::Tsc::new(ccm),
+            xbar1: crate::xbar::Xbar::new_xbar1(ccm),
         }
     }
 }
@@ -69,6 +114,10 @@ impl InterruptService for Imxrt10xxDefaultPeripherals {
             nvic::LPI2C1 => self.lpi2c1.handle_event(),
             nvic::GPT1 => self.gpt1.handle_interrupt(),
             nvic::GPT2 => self.gpt2.handle_interrupt(),
+            nvic::QTIMER1 => self.qtmr1.handle_interrupt(),
+            nvic::QTIMER2 => self.qtmr2.handle_interrupt(),
+            nvic::QTIMER3 => self.qtmr3.handle_interrupt(),
+            nvic::QTIMER4 => self.qtmr4.handle_interrupt(),
             nvic::GPIO1_1 => self.ports.gpio1.handle_interrupt(),
             nvic::GPIO1_2 => self.ports.gpio1.handle_interrupt(),
             nvic::GPIO2_1 => self.ports.gpio2.handle_interrupt(),
@@ -79,6 +128,18 @@ impl InterruptService for Imxrt10xxDefaultPeripherals {
             nvic::GPIO4_2 => self.ports.gpio4.handle_interrupt(),
             nvic::GPIO5_1 => self.ports.gpio5.handle_interrupt(),
             nvic::GPIO5_2 => self.ports.gpio5.handle_interrupt(),
+            nvic::ENET => self.enet.handle_interrupt(),
+            nvic::FLEXIO1 => self.flexio1.handle_interrupt(),
+            nvic::FLEXIO2 => self.flexio2.handle_interrupt(),
+            nvic::ADC1 => self.adc1.handle_interrupt(),
+            nvic::ADC2 => self.adc2.handle_interrupt(),
+            nvic::ADC_ETC_IRQ0 => self.adc_etc.handle_interrupt(),
+            nvic::ADC_ETC_ERROR_IRQ => self.adc_etc.handle_error_interrupt(),
+            nvic::ACMP1 => self.acmp.handle_interrupt(0),
+            nvic::ACMP2 => self.acmp.handle_interrupt(1),
+            nvic::ACMP3 => self.acmp.handle_interrupt(2),
+            nvic::ACMP4 => self.acmp.handle_interrupt(3),
+            nvic::TSC_DIG => self.tsc.handle_interrupt(),
             nvic::SNVS_LP_WRAPPER => debug!("Interrupt: SNVS_LP_WRAPPER"),
             nvic::DMA0_16..=nvic::DMA15_31 => {
                 let low = (interrupt - nvic::DMA0_16) as usize;
@@ -135,9 +196,18 @@ impl<I: InterruptService + 'static> Chip for Imxrt10xx<I> {
     }
 
     fn sleep(&self) {
+        let stop = self.gpc.map_or(false, |gpc| gpc.deep_sleep_ready());
         unsafe {
-            cortexm7::scb::unset_sleepdeep();
+            if stop {
+                self.gpc.map(|gpc| gpc.set_wfi_mode(WfiMode::Stop));
+                cortexm7::scb::set_sleepdeep();
+            } else {
+                cortexm7::scb::unset_sleepdeep();
+            }
             cortexm7::support::wfi();
+            if stop {
+                self.gpc.map(|gpc| gpc.set_wfi_mode(WfiMode::Run));
+            }
         }
     }
 
@@ -0,0 +1,226 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Clock tree reporting, from the CCM and CCM_ANALOG settings.
+//!
+//! The frequencies of the clocks running from the PLL2 phase fractional
+//! dividers (PFD) are not computed and reported as unknown.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let clock_tree = static_init!(
+//!     imxrt1050::clock_tree::CcmClockTree,
+//!     imxrt1050::clock_tree::CcmClockTree::new(peripherals.ccm, &peripherals.ccm_analog)
+//! );
+//! ```
+
+use kernel::hil::clock_tree::{self, Clock, ClockGate};
+
+use crate::ccm::{
+    Ccm, PerclkClockSel, PeripheralClock2Selection, PeripheralClockSelection,
+    PrePeripheralClockSelection, UartClockSelection,
+};
+use crate::ccm_analog::CcmAnalog;
+
+/// Frequency of the crystal oscillator
+const OSC_FREQUENCY_HZ: u32 = 24_000_000;
+
+/// Peripheral clock gates reported by the `clocks` console command.
+const CLOCK_GATES: [(&str, fn(&Ccm) -> bool); 28] = [
+    ("iomuxc", Ccm::is_enabled_iomuxc_clock),
+    ("iomuxcsnvs", Ccm::is_enabled_iomuxc_snvs_clock),
+    ("gpio1", Ccm::is_enabled_gpio1_clock),
+    ("gpio2", Ccm::is_enabled_gpio2_clock),
+    ("gpio3", Ccm::is_enabled_gpio3_clock),
+    ("gpio4", Ccm::is_enabled_gpio4_clock),
+    ("gpio5", Ccm::is_enabled_gpio5_clock),
+    ("lpuart1", Ccm::is_enabled_lpuart1_clock),
+    ("lpuart2", Ccm::is_enabled_lpuart2_clock),
+    ("lpi2c1", Ccm::is_enabled_lpi2c1_clock),
+    ("gpt1", Ccm::is_enabled_gpt1_clock),
+    ("gpt2", Ccm::is_enabled_gpt2_clock),
+    ("qtimer1", Ccm::is_enabled_qtimer1_clock),
+    ("qtimer2", Ccm::is_enabled_qtimer2_clock),
+    ("qtimer3", Ccm::is_enabled_qtimer3_clock),
+    ("qtimer4", Ccm::is_enabled_qtimer4_clock),
+    ("adc1", Ccm::is_enabled_adc1_clock),
+    ("adc2", Ccm::is_enabled_adc2_clock),
+    ("acmp1", Ccm::is_enabled_acmp1_clock),
+    ("acmp2", Ccm::is_enabled_acmp2_clock),
+    ("acmp3", Ccm::is_enabled_acmp3_clock),
+    ("acmp4", Ccm::is_enabled_acmp4_clock),
+    ("xbar1", Ccm::is_enabled_xbar1_clock),
+    ("tsc", Ccm::is_enabled_tsc_clock),
+    ("dma", Ccm::is_enabled_dma_clock),
+    ("enet", Ccm::is_enabled_enet_clock),
+    ("ocotp", Ccm::is_enabled_ocotp_clock),
+    ("dcdc", Ccm::is_enabled_dcdc_clock),
+];
+
+pub struct CcmClockTree<'a> {
+    ccm: &'a Ccm,
+    ccm_analog: &'a CcmAnalog,
+}
+
+impl<'a> CcmClockTree<'a> {
+    pub const fn new(ccm: &'a Ccm, ccm_analog: &'a CcmAnalog) -> CcmClockTree<'a> {
+        CcmClockTree { ccm, ccm_analog }
+    }
+
+    fn pll1_frequency(&self) -> u32 {
+        OSC_FREQUENCY_HZ / 2 * self.ccm_analog.pll1_div_sel()
+    }
+
+    fn pll2_frequency(&self) -> u32 {
+        OSC_FREQUENCY_HZ * (20 + 2 * self.ccm_analog.pll2_div_sel())
+    }
+
+    fn pll3_frequency(&self) -> u32 {
+        OSC_FREQUENCY_HZ * (20 + 2 * self.ccm_analog.pll3_div_sel())
+    }
+
+    fn arm_frequency(&self) -> u32 {
+        self.pll1_frequency() / self.ccm.arm_divider()
+    }
+
+    fn pre_periph(&self) -> (&'static str, Option<u32>) {
+        match self.ccm.pre_peripheral_clock_selection() {
+            PrePeripheralClockSelection::Pll2 => ("pll2", Some(self.pll2_frequency())),
+            PrePeripheralClockSelection::Pll2Pfd2 => ("pll2pfd2", None),
+            PrePeripheralClockSelection::Pll2Pfd0 => ("pll2pfd0", None),
+            PrePeripheralClockSelection::Pll1 => ("arm", Some(self.arm_frequency())),
+        }
+    }
+
+    /// Source of PERIPH_CLK2 and its frequency, before the divider.
+    fn periph_clk2_source(&self) -> (&'static str, u32) {
+        match self.ccm.peripheral_clock2_selection() {
+            PeripheralClock2Selection::Pll3 => ("pll3", self.pll3_frequency()),
+            // PLL2 bypassed outputs its reference, the oscillator
+            PeripheralClock2Selection::Oscillator | PeripheralClock2Selection::Pll2Bypass => {
+                ("osc", OSC_FREQUENCY_HZ)
+            }
+        }
+    }
+
+    fn periph(&self) -> (&'static str, Option<u32>) {
+        match self.ccm.peripheral_clock_selection() {
+            PeripheralClockSelection::PrePeripheralClock => ("preperiph", self.pre_periph().1),
+            PeripheralClockSelection::PeripheralClock2Divided => (
+                "periphclk2",
+                Some(self.periph_clk2_source().1 / self.ccm.peripheral_clock2_divider()),
+            ),
+        }
+    }
+
+    fn ahb_frequency(&self) -> Option<u32> {
+        self.periph().1.map(|hz| hz / self.ccm.ahb_divider())
+    }
+
+    fn ipg_frequency(&self) -> Option<u32> {
+        self.ahb_frequency().map(|hz| hz / self.ccm.ipg_divider())
+    }
+}
+
+impl clock_tree::ClockTree for CcmClockTree<'_> {
+    fn clock(&self, index: usize) -> Option<Clock> {
+        let pll = |name, enabled, div_sel: u32, frequency_hz| Clock {
+            name,
+            enabled,
+            source: Some("osc"),
+            multiplier: div_sel,
+            divider: 1,
+            frequency_hz: Some(frequency_hz),
+        };
+        let derived = |name, (source, frequency_hz): (&'static str, Option<u32>), divider| Clock {
+            name,
+            enabled: true,
+            source: Some(source),
+            multiplier: 1,
+            divider,
+            frequency_hz: frequency_hz.map(|hz| hz / divider),
+        };
+        match index {
+            0 => Some(Clock {
+                name: "osc",
+                enabled: true,
+                source: None,
+                multiplier: 1,
+                divider: 1,
+                frequency_hz: Some(OSC_FREQUENCY_HZ),
+            }),
+            1 => Some(Clock {
+                divider: 2,
+                ..pll(
+                    "pll1",
+                    self.ccm_analog.is_pll1_locked(),
+                    self.ccm_analog.pll1_div_sel(),
+                    self.pll1_frequency(),
+                )
+            }),
+            2 => Some(pll(
+                "pll2",
+                self.ccm_analog.is_pll2_locked(),
+                20 + 2 * self.ccm_analog.pll2_div_sel(),
+                self.pll2_frequency(),
+            )),
+            3 => Some(pll(
+                "pll3",
+                self.ccm_analog.is_pll3_locked(),
+                20 + 2 * self.ccm_analog.pll3_div_sel(),
+                self.pll3_frequency(),
+            )),
+            4 => Some(derived(
+                "arm",
+                ("pll1", Some(self.pll1_frequency())),
+                self.ccm.arm_divider(),
+            )),
+            5 => Some(derived("preperiph", self.pre_periph(), 1)),
+            6 => {
+                let (source, frequency_hz) = self.periph_clk2_source();
+                Some(derived(
+                    "periphclk2",
+                    (source, Some(frequency_hz)),
+                    self.ccm.peripheral_clock2_divider(),
+                ))
+            }
+            7 => Some(derived("periph", self.periph(), 1)),
+            8 => Some(derived(
+                "ahb",
+                ("periph", self.periph().1),
+                self.ccm.ahb_divider(),
+            )),
+            9 => Some(derived(
+                "ipg",
+                ("ahb", self.ahb_frequency()),
+                self.ccm.ipg_divider(),
+            )),
+            10 => {
+                let source = match self.ccm.perclk_sel() {
+                    PerclkClockSel::IPG => ("ipg", self.ipg_frequency()),
+                    PerclkClockSel::Oscillator => ("osc", Some(OSC_FREQUENCY_HZ)),
+                };
+                Some(derived("perclk", source, self.ccm.perclk_divider() as u32))
+            }
+            11 => {
+                // PLL3 reaches the UART clock divided by 6
+                let source = match self.ccm.uart_clock_sel() {
+                    UartClockSelection::PLL3 => ("pll3", Some(self.pll3_frequency() / 6)),
+                    UartClockSelection::Oscillator => ("osc", Some(OSC_FREQUENCY_HZ)),
+                };
+                Some(derived("uart", source, self.ccm.uart_clock_podf()))
+            }
+            _ => None,
+        }
+    }
+
+    fn clock_gate(&self, index: usize) -> Option<ClockGate> {
+        CLOCK_GATES.get(index).map(|&(name, is_enabled)| ClockGate {
+            name,
+            enabled: is_enabled(self.ccm),
+        })
+    }
+}
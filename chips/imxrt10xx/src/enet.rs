@@ -0,0 +1,615 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! 10/100 Mbps Ethernet MAC (ENET).
+//!
+//! The MAC is used in RMII mode with a reference clock from the ENET PLL.
+//! Received frames are written by the controller into a small ring of
+//! buffers in [`EnetBuffers`], which the board allocates and gives to the
+//! driver with `set_buffers()`. Frames are sent one at a time, straight from
+//! the buffer of the client.
+//!
+//! The PHY is accessed through the MDIO interface only to read the link
+//! status: it is expected to come out of reset with auto-negotiation
+//! enabled, as the KSZ8081 of the EVKB does.
+
+use core::cell::{Cell, UnsafeCell};
+use kernel::hil::ethernet::{EthernetAdapter, RxClient, TxClient, MAX_FRAME_LEN};
+use kernel::hil::mac_address::{Configure, MacAddress};
+use kernel::platform::chip::ClockInterface;
+use kernel::utilities::cells::{OptionalCell, TakeCell, VolatileCell};
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
+use kernel::utilities::registers::{register_bitfields, register_structs, ReadWrite};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+use crate::ccm;
+
+/// Number of receive buffers.
+pub const NUM_RX_BUFFERS: usize = 4;
+/// Size of each receive buffer. It must be a multiple of 16 and hold a
+/// frame with its frame check sequence.
+pub const RX_BUFFER_LEN: usize = 1536;
+
+/// Iterations to wait for a reset or an MDIO access to complete.
+const TIMEOUT: usize = 100_000;
+
+/// Largest frame accepted by the receiver, with the frame check sequence.
+const MAX_RX_FRAME_LEN: u32 = 1518;
+
+/// Highest MDIO clock frequency allowed by IEEE 802.3.
+const MDC_MAX_HZ: u32 = 2_500_000;
+
+/// PHY Basic Status Register and its link status bit.
+const PHY_BMSR: u8 = 0x01;
+const PHY_BMSR_LINK_STATUS: u16 = 1 << 2;
+
+/// Trace point identifiers, see `kernel::trace`.
+pub mod trace {
+    /// Interrupt, with the value of the EIR register.
+    pub const INTERRUPT: u8 = 0x30;
+    /// Frame received, with its length.
+    pub const RX_FRAME: u8 = 0x31;
+    /// Frame received with errors, with the descriptor control field.
+    pub const RX_ERROR: u8 = 0x32;
+}
+
+register_structs! {
+    /// Ethernet MAC
+    EnetRegisters {
+        (0x000 => _reserved0),
+        /// Interrupt Event Register
+        (0x004 => eir: ReadWrite<u32, EIR::Register>),
+        /// Interrupt Mask Register
+        (0x008 => eimr: ReadWrite<u32, EIR::Register>),
+        (0x00C => _reserved1),
+        /// Receive Descriptor Active Register
+        (0x010 => rdar: ReadWrite<u32, DAR::Register>),
+        /// Transmit Descriptor Active Register
+        (0x014 => tdar: ReadWrite<u32, DAR::Register>),
+        (0x018 => _reserved2),
+        /// Ethernet Control Register
+        (0x024 => ecr: ReadWrite<u32, ECR::Register>),
+        (0x028 => _reserved3),
+        /// MII Management Frame Register
+        (0x040 => mmfr: ReadWrite<u32, MMFR::Register>),
+        /// MII Speed Control Register
+        (0x044 => mscr: ReadWrite<u32, MSCR::Register>),
+        (0x048 => _reserved4),
+        /// Receive Control Register
+        (0x084 => rcr: ReadWrite<u32, RCR::Register>),
+        (0x088 => _reserved5),
+        /// Transmit Control Register
+        (0x0C4 => tcr: ReadWrite<u32, TCR::Register>),
+        (0x0C8 => _reserved6),
+        /// Physical Address Lower Register
+        (0x0E4 => palr: ReadWrite<u32>),
+        /// Physical Address Upper Register
+        (0x0E8 => paur: ReadWrite<u32, PAUR::Register>),
+        /// Opcode/Pause Duration Register
+        (0x0EC => opd: ReadWrite<u32>),
+        (0x0F0 => _reserved7),
+        /// Descriptor Individual Upper Address Register
+        (0x118 => iaur: ReadWrite<u32>),
+        /// Descriptor Individual Lower Address Register
+        (0x11C => ialr: ReadWrite<u32>),
+        /// Descriptor Group Upper Address Register
+        (0x120 => gaur: ReadWrite<u32>),
+        /// Descriptor Group Lower Address Register
+        (0x124 => galr: ReadWrite<u32>),
+        (0x128 => _reserved8),
+        /// Transmit FIFO Watermark Register
+        (0x144 => tfwr: ReadWrite<u32, TFWR::Register>),
+        (0x148 => _reserved9),
+        /// Receive Descriptor Ring Start Register
+        (0x180 => rdsr: ReadWrite<u32>),
+        /// Transmit Buffer Descriptor Ring Start Register
+        (0x184 => tdsr: ReadWrite<u32>),
+        /// Maximum Receive Buffer Size Register
+        (0x188 => mrbr: ReadWrite<u32>),
+        (0x18C => @END),
+    }
+}
+
+register_bitfields![u32,
+    EIR [
+        /// Babbling receive error
+        BABR OFFSET(30) NUMBITS(1) [],
+        /// Babbling transmit error
+        BABT OFFSET(29) NUMBITS(1) [],
+        /// Graceful stop complete
+        GRA OFFSET(28) NUMBITS(1) [],
+        /// Transmit frame interrupt
+        TXF OFFSET(27) NUMBITS(1) [],
+        /// Transmit buffer interrupt
+        TXB OFFSET(26) NUMBITS(1) [],
+        /// Receive frame interrupt
+        RXF OFFSET(25) NUMBITS(1) [],
+        /// Receive buffer interrupt
+        RXB OFFSET(24) NUMBITS(1) [],
+        /// MII interrupt
+        MII OFFSET(23) NUMBITS(1) [],
+        /// Ethernet bus error
+        EBERR OFFSET(22) NUMBITS(1) [],
+        /// Late collision
+        LC OFFSET(21) NUMBITS(1) [],
+        /// Collision retry limit
+        RL OFFSET(20) NUMBITS(1) [],
+        /// Transmit FIFO underrun
+        UN OFFSET(19) NUMBITS(1) []
+    ],
+    DAR [
+        /// Descriptors are ready to be processed
+        ACTIVE OFFSET(24) NUMBITS(1) []
+    ],
+    ECR [
+        /// Descriptor byte swapping enable
+        DBSWP OFFSET(8) NUMBITS(1) [],
+        /// Enable the MAC
+        ETHEREN OFFSET(1) NUMBITS(1) [],
+        /// Reset the MAC
+        RESET OFFSET(0) NUMBITS(1) []
+    ],
+    MMFR [
+        /// Start of frame delimiter, must be 0b01
+        ST OFFSET(30) NUMBITS(2) [],
+        /// Operation code
+        OP OFFSET(28) NUMBITS(2) [
+            Write = 0b01,
+            Read = 0b10
+        ],
+        /// PHY address
+        PA OFFSET(23) NUMBITS(5) [],
+        /// Register address
+        RA OFFSET(18) NUMBITS(5) [],
+        /// Turn around, must be 0b10
+        TA OFFSET(16) NUMBITS(2) [],
+        /// Management frame data
+        DATA OFFSET(0) NUMBITS(16) []
+    ],
+    MSCR [
+        /// Hold time on MDIO output, in internal clock cycles minus one
+        HOLDTIME OFFSET(8) NUMBITS(3) [],
+        /// MDC frequency divider
+        MII_SPEED OFFSET(1) NUMBITS(6) []
+    ],
+    RCR [
+        /// Maximum frame length
+        MAX_FL OFFSET(16) NUMBITS(14) [],
+        /// Strip the frame check sequence of received frames
+        CRCFWD OFFSET(14) NUMBITS(1) [],
+        /// Remove the padding of received frames
+        PADEN OFFSET(12) NUMBITS(1) [],
+        /// 10 Mbps mode in RMII
+        RMII_10T OFFSET(9) NUMBITS(1) [],
+        /// RMII mode
+        RMII_MODE OFFSET(8) NUMBITS(1) [],
+        /// Flow control enable
+        FCE OFFSET(5) NUMBITS(1) [],
+        /// Reject broadcast frames
+        BC_REJ OFFSET(4) NUMBITS(1) [],
+        /// Promiscuous mode
+        PROM OFFSET(3) NUMBITS(1) [],
+        /// MII or RMII mode, must be 1
+        MII_MODE OFFSET(2) NUMBITS(1) []
+    ],
+    TCR [
+        /// Insert the MAC address in transmitted frames
+        ADDINS OFFSET(8) NUMBITS(1) [],
+        /// Full-duplex enable
+        FDEN OFFSET(2) NUMBITS(1) []
+    ],
+    PAUR [
+        /// Bytes 4 and 5 of the MAC address
+        PADDR2 OFFSET(16) NUMBITS(16) [],
+        /// Type field of pause frames
+        TYPE OFFSET(0) NUMBITS(16) []
+    ],
+    TFWR [
+        /// Only start sending when a whole frame is in the FIFO
+        STRFWD OFFSET(8) NUMBITS(1) []
+    ]
+];
+
+register_bitfields![u16,
+    RX_CONTROL [
+        /// The buffer is empty and owned by the controller
+        E OFFSET(15) NUMBITS(1) [],
+        /// Last descriptor of the ring
+        W OFFSET(13) NUMBITS(1) [],
+        /// Last buffer of a frame
+        L OFFSET(11) NUMBITS(1) [],
+        /// Frame too long
+        LG OFFSET(5) NUMBITS(1) [],
+        /// Non-octet aligned frame
+        NO OFFSET(4) NUMBITS(1) [],
+        /// CRC error
+        CR OFFSET(2) NUMBITS(1) [],
+        /// FIFO overrun
+        OV OFFSET(1) NUMBITS(1) [],
+        /// Frame truncated
+        TR OFFSET(0) NUMBITS(1) []
+    ],
+    TX_CONTROL [
+        /// The buffer is ready and owned by the controller
+        R OFFSET(15) NUMBITS(1) [],
+        /// Last descriptor of the ring
+        W OFFSET(13) NUMBITS(1) [],
+        /// Last buffer of a frame
+        L OFFSET(11) NUMBITS(1) [],
+        /// Append the frame check sequence
+        TC OFFSET(10) NUMBITS(1) []
+    ]
+];
+
+const ENET_BASE: StaticRef<EnetRegisters> =
+    unsafe { StaticRef::new(0x402D8000 as *const EnetRegisters) };
+
+/// A legacy buffer descriptor, in the layout used with `ECR::DBSWP` set on
+/// this little-endian core.
+#[repr(C)]
+struct BufferDescriptor {
+    length: VolatileCell<u16>,
+    control: VolatileCell<u16>,
+    buffer: VolatileCell<u32>,
+}
+
+impl BufferDescriptor {
+    const fn new() -> Self {
+        Self {
+            length: VolatileCell::new(0),
+            control: VolatileCell::new(0),
+            buffer: VolatileCell::new(0),
+        }
+    }
+}
+
+#[repr(C, align(64))]
+struct RxBuffer(UnsafeCell<[u8; RX_BUFFER_LEN]>);
+
+/// Memory shared with the controller: the buffer descriptors and the
+/// receive buffers.
+#[repr(C, align(64))]
+pub struct EnetBuffers {
+    rx_descriptors: [BufferDescriptor; NUM_RX_BUFFERS],
+    tx_descriptor: BufferDescriptor,
+    rx_buffers: [RxBuffer; NUM_RX_BUFFERS],
+}
+
+impl EnetBuffers {
+    pub const fn new() -> Self {
+        const EMPTY: RxBuffer = RxBuffer(UnsafeCell::new([0; RX_BUFFER_LEN]));
+        const DESCRIPTOR: BufferDescriptor = BufferDescriptor::new();
+        Self {
+            rx_descriptors: [DESCRIPTOR; NUM_RX_BUFFERS],
+            tx_descriptor: BufferDescriptor::new(),
+            rx_buffers: [EMPTY; NUM_RX_BUFFERS],
+        }
+    }
+
+    fn rx_control(index: usize) -> u16 {
+        let mut control = RX_CONTROL::E::SET;
+        if index == NUM_RX_BUFFERS - 1 {
+            control += RX_CONTROL::W::SET;
+        }
+        control.value
+    }
+}
+
+pub struct Enet<'a> {
+    registers: StaticRef<EnetRegisters>,
+    clock: ccm::PeripheralClock<'a>,
+    buffers: OptionalCell<&'static EnetBuffers>,
+    next_rx: Cell<usize>,
+    tx_frame: TakeCell<'static, [u8]>,
+    mac_address: Cell<MacAddress>,
+    phy_address: Cell<u8>,
+    ipg_clock_hz: Cell<Option<u32>>,
+    enabled: Cell<bool>,
+    rx_client: OptionalCell<&'a dyn RxClient>,
+    tx_client: OptionalCell<&'a dyn TxClient>,
+}
+
+impl<'a> Enet<'a> {
+    pub fn new(ccm: &'a ccm::Ccm) -> Self {
+        Self {
+            registers: ENET_BASE,
+            clock: ccm::PeripheralClock::ccgr1(ccm, ccm::HCLK1::ENET),
+            buffers: OptionalCell::empty(),
+            next_rx: Cell::new(0),
+            tx_frame: TakeCell::empty(),
+            mac_address: Cell::new(MacAddress::new([0; 6])),
+            phy_address: Cell::new(0),
+            ipg_clock_hz: Cell::new(None),
+            enabled: Cell::new(false),
+            rx_client: OptionalCell::empty(),
+            tx_client: OptionalCell::empty(),
+        }
+    }
+
+    /// Give the driver the memory it shares with the controller. This must
+    /// be called before `enable()`.
+    pub fn set_buffers(&self, buffers: &'static EnetBuffers) {
+        self.buffers.set(buffers);
+    }
+
+    /// Set the address of the PHY on the MDIO bus.
+    pub fn set_phy_address(&self, address: u8) {
+        self.phy_address.set(address & 0x1F);
+    }
+
+    /// Set the frequency of the IPG clock, from which the MDIO clock is
+    /// derived.
+    pub fn set_ipg_clock_frequency(&self, hz: u32) {
+        self.ipg_clock_hz.set(Some(hz));
+    }
+
+    pub fn is_enabled_clock(&self) -> bool {
+        self.clock.is_enabled()
+    }
+
+    pub fn enable_clock(&self) {
+        self.clock.enable();
+    }
+
+    pub fn disable_clock(&self) {
+        self.clock.disable();
+    }
+
+    fn write_mac_address(&self, address: MacAddress) {
+        let bytes = address.as_bytes();
+        self.registers
+            .palr
+            .set(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+        self.registers.paur.write(
+            PAUR::PADDR2.val(u16::from_be_bytes([bytes[4], bytes[5]]) as u32)
+                + PAUR::TYPE.val(0x8808),
+        );
+    }
+
+    /// Run an MDIO management frame and return the data read.
+    fn mdio_transfer(&self, frame: u32) -> Result<u16, ErrorCode> {
+        self.registers.eir.write(EIR::MII::SET);
+        self.registers.mmfr.set(frame);
+        for _ in 0..TIMEOUT {
+            if self.registers.eir.is_set(EIR::MII) {
+                self.registers.eir.write(EIR::MII::SET);
+                return Ok(self.registers.mmfr.read(MMFR::DATA) as u16);
+            }
+        }
+        Err(ErrorCode::BUSY)
+    }
+
+    /// Read the PHY register `register`.
+    pub fn mdio_read(&self, register: u8) -> Result<u16, ErrorCode> {
+        let frame = MMFR::ST.val(0b01)
+            + MMFR::OP::Read
+            + MMFR::PA.val(self.phy_address.get() as u32)
+            + MMFR::RA.val(register as u32)
+            + MMFR::TA.val(0b10);
+        self.mdio_transfer(frame.value)
+    }
+
+    /// Write `value` to the PHY register `register`.
+    pub fn mdio_write(&self, register: u8, value: u16) -> Result<(), ErrorCode> {
+        let frame = MMFR::ST.val(0b01)
+            + MMFR::OP::Write
+            + MMFR::PA.val(self.phy_address.get() as u32)
+            + MMFR::RA.val(register as u32)
+            + MMFR::TA.val(0b10)
+            + MMFR::DATA.val(value as u32);
+        self.mdio_transfer(frame.value).map(|_| ())
+    }
+
+    fn reset(&self) -> Result<(), ErrorCode> {
+        self.registers.ecr.write(ECR::RESET::SET);
+        for _ in 0..TIMEOUT {
+            if !self.registers.ecr.is_set(ECR::RESET) {
+                return Ok(());
+            }
+        }
+        Err(ErrorCode::FAIL)
+    }
+
+    fn setup_descriptors(&self, buffers: &EnetBuffers) {
+        for (index, (descriptor, buffer)) in buffers
+            .rx_descriptors
+            .iter()
+            .zip(buffers.rx_buffers.iter())
+            .enumerate()
+        {
+            descriptor.length.set(0);
+            descriptor.buffer.set(buffer.0.get() as u32);
+            descriptor.control.set(EnetBuffers::rx_control(index));
+        }
+        buffers.tx_descriptor.length.set(0);
+        buffers.tx_descriptor.buffer.set(0);
+        buffers.tx_descriptor.control.set(TX_CONTROL::W::SET.value);
+        self.next_rx.set(0);
+
+        self.registers
+            .rdsr
+            .set(buffers.rx_descriptors.as_ptr() as u32);
+        self.registers
+            .tdsr
+            .set(&buffers.tx_descriptor as *const BufferDescriptor as u32);
+        self.registers.mrbr.set(RX_BUFFER_LEN as u32);
+    }
+
+    /// Give the received frames to the client and the buffers back to the
+    /// controller.
+    fn receive_frames(&self, buffers: &EnetBuffers) {
+        loop {
+            let index = self.next_rx.get();
+            let descriptor = &buffers.rx_descriptors[index];
+            let control = descriptor.control.get();
+            if control & RX_CONTROL::E::SET.value != 0 {
+                break;
+            }
+
+            let errors = (RX_CONTROL::LG::SET
+                + RX_CONTROL::NO::SET
+                + RX_CONTROL::CR::SET
+                + RX_CONTROL::OV::SET
+                + RX_CONTROL::TR::SET)
+                .value;
+            if control & RX_CONTROL::L::SET.value != 0 && control & errors == 0 {
+                let len = (descriptor.length.get() as usize).min(RX_BUFFER_LEN);
+                // The controller does not write to the buffer until the
+                // descriptor is given back to it below.
+                let buffer: &[u8; RX_BUFFER_LEN] = unsafe { &*buffers.rx_buffers[index].0.get() };
+                let frame = &buffer[..len];
+                kernel::trace_point!(trace::RX_FRAME, len as u32);
+                self.rx_client.map(|client| client.received_frame(frame));
+            } else {
+                kernel::trace_point!(trace::RX_ERROR, control as u32);
+            }
+
+            descriptor.control.set(EnetBuffers::rx_control(index));
+            self.next_rx.set((index + 1) % NUM_RX_BUFFERS);
+            self.registers.rdar.write(DAR::ACTIVE::SET);
+        }
+    }
+
+    pub fn handle_interrupt(&self) {
+        let events = self.registers.eir.extract();
+        self.registers.eir.set(events.get());
+        kernel::trace_point!(trace::INTERRUPT, events.get());
+
+        if events.is_set(EIR::RXF) {
+            self.buffers.map(|buffers| self.receive_frames(buffers));
+        }
+
+        if events.is_set(EIR::TXF) || events.is_set(EIR::EBERR) {
+            let result = if events.is_set(EIR::EBERR) {
+                Err(ErrorCode::FAIL)
+            } else {
+                Ok(())
+            };
+            self.tx_frame.take().map(|frame| {
+                self.tx_client
+                    .map(move |client| client.transmit_done(frame, result));
+            });
+        }
+    }
+}
+
+impl<'a> EthernetAdapter<'a> for Enet<'a> {
+    fn set_receive_client(&self, client: &'a dyn RxClient) {
+        self.rx_client.set(client);
+    }
+
+    fn set_transmit_client(&self, client: &'a dyn TxClient) {
+        self.tx_client.set(client);
+    }
+
+    fn enable(&self) -> Result<(), ErrorCode> {
+        let buffers = self.buffers.extract().ok_or(ErrorCode::NOMEM)?;
+        self.enable_clock();
+        self.reset()?;
+
+        if let Some(hz) = self.ipg_clock_hz.get() {
+            let divider = (hz + 2 * MDC_MAX_HZ - 1) / (2 * MDC_MAX_HZ);
+            self.registers.mscr.write(
+                MSCR::MII_SPEED.val(divider.saturating_sub(1).max(1)) + MSCR::HOLDTIME.val(1),
+            );
+        }
+
+        self.setup_descriptors(buffers);
+
+        self.registers.rcr.write(
+            RCR::MAX_FL.val(MAX_RX_FRAME_LEN)
+                + RCR::CRCFWD::SET
+                + RCR::RMII_MODE::SET
+                + RCR::MII_MODE::SET
+                + RCR::FCE::SET,
+        );
+        self.registers.tcr.write(TCR::FDEN::SET);
+        self.registers.tfwr.write(TFWR::STRFWD::SET);
+        self.write_mac_address(self.mac_address.get());
+        self.registers.opd.set(0x0001_0000);
+        // Accept all multicast frames and let the network stack filter them.
+        self.registers.iaur.set(0);
+        self.registers.ialr.set(0);
+        self.registers.gaur.set(0xFFFF_FFFF);
+        self.registers.galr.set(0xFFFF_FFFF);
+
+        self.registers.eir.set(0xFFFF_FFFF);
+        self.registers
+            .eimr
+            .write(EIR::RXF::SET + EIR::TXF::SET + EIR::EBERR::SET);
+        self.registers
+            .ecr
+            .write(ECR::DBSWP::SET + ECR::ETHEREN::SET);
+        self.registers.rdar.write(DAR::ACTIVE::SET);
+        self.enabled.set(true);
+        Ok(())
+    }
+
+    fn disable(&self) -> Result<(), ErrorCode> {
+        self.registers.eimr.set(0);
+        self.registers.ecr.modify(ECR::ETHEREN::CLEAR);
+        self.enabled.set(false);
+        self.tx_frame.take().map(|frame| {
+            self.tx_client
+                .map(move |client| client.transmit_done(frame, Err(ErrorCode::CANCEL)));
+        });
+        Ok(())
+    }
+
+    fn link_up(&self) -> bool {
+        self.enabled.get()
+            && self
+                .mdio_read(PHY_BMSR)
+                .map_or(false, |bmsr| bmsr & PHY_BMSR_LINK_STATUS != 0)
+    }
+
+    fn transmit(
+        &self,
+        frame: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        if !self.enabled.get() {
+            return Err((ErrorCode::OFF, frame));
+        }
+        if len > MAX_FRAME_LEN || len > frame.len() {
+            return Err((ErrorCode::SIZE, frame));
+        }
+        if self.tx_frame.is_some() {
+            return Err((ErrorCode::BUSY, frame));
+        }
+        let buffers = match self.buffers.extract() {
+            Some(buffers) => buffers,
+            None => return Err((ErrorCode::OFF, frame)),
+        };
+
+        let descriptor = &buffers.tx_descriptor;
+        descriptor.buffer.set(frame.as_ptr() as u32);
+        descriptor.length.set(len as u16);
+        descriptor.control.set(
+            (TX_CONTROL::R::SET + TX_CONTROL::W::SET + TX_CONTROL::L::SET + TX_CONTROL::TC::SET)
+                .value,
+        );
+        self.tx_frame.replace(frame);
+        self.registers.tdar.write(DAR::ACTIVE::SET);
+        Ok(())
+    }
+}
+
+impl Configure for Enet<'_> {
+    fn set_mac_address(&self, address: MacAddress) -> Result<(), ErrorCode> {
+        if !address.is_valid_unicast() {
+            return Err(ErrorCode::INVAL);
+        }
+        self.mac_address.set(address);
+        if self.enabled.get() {
+            self.write_mac_address(address);
+        }
+        Ok(())
+    }
+
+    fn get_mac_address(&self) -> MacAddress {
+        self.mac_address.get()
+    }
+}
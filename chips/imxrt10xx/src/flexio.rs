@@ -0,0 +1,1034 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Flexible Input/Output (FlexIO).
+//!
+//! FlexIO is a set of shifters and timers which emulate serial peripherals
+//! on its pins. A shifter moves the bits of its buffer in or out of a pin,
+//! clocked by a timer, and a timer counts the clock of the FlexIO, a pin or
+//! a trigger to generate a baud rate, a chip select or a PWM. FlexIO1 has
+//! four shifters, four timers and 16 pins, GPIO_AD_B1_00 to GPIO_AD_B1_15.
+//! FlexIO2 has eight shifters, eight timers and 32 pins, GPIO_B0_00 to
+//! GPIO_B0_15 then GPIO_B1_00 to GPIO_B1_15. The pads are in `ALT4` when
+//! muxed to the FlexIO, which `Flexio::mux_pin` configures.
+//!
+//! `Flexio` configures the shifters and the timers with the fields of their
+//! registers, and forwards the interrupt of the module to the emulated
+//! peripherals, its clients. `FlexioUart` emulates a UART with two shifters
+//! and two timers: the FlexIO1 can run two of them, and the FlexIO2 four.
+//!
+//! The FlexIO clock is `pll3_sw_clk`, 480 MHz, divided by `set_clock_root`.
+//! The timers divide it by an even number, from 2 to 512, for the baud rate
+//! of a UART. With the 7.5 MHz root of the example, UARTs run from about
+//! 15 kbit/s to 3.75 Mbit/s.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! // A UART on GPIO_AD_B1_06 (TX) and GPIO_AD_B1_07 (RX)
+//! let flexio = &peripherals.flexio1;
+//! flexio.set_clock_root(8, 8);
+//! flexio.mux_pin(&peripherals.iomuxc, 6);
+//! flexio.mux_pin(&peripherals.iomuxc, 7);
+//! let uart = static_init!(
+//!     imxrt10xx::flexio::FlexioUart<'static>,
+//!     imxrt10xx::flexio::FlexioUart::new(flexio, 0, 6, 7)
+//! );
+//! flexio.add_client(uart).unwrap();
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil;
+use kernel::platform::chip::ClockInterface;
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
+use kernel::utilities::registers::{
+    register_bitfields, register_structs, FieldValue, ReadOnly, ReadWrite,
+};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+use crate::ccm;
+use crate::iomuxc::{Iomuxc, MuxMode, PadId, Sion};
+
+register_structs! {
+    /// Flexible Input/Output
+    FlexioRegisters {
+        /// Version ID Register
+        (0x000 => verid: ReadOnly<u32>),
+        /// Parameter Register
+        (0x004 => param: ReadOnly<u32, PARAM::Register>),
+        /// FlexIO Control Register
+        (0x008 => ctrl: ReadWrite<u32, CTRL::Register>),
+        /// Pin State Register
+        (0x00C => pin: ReadOnly<u32>),
+        /// Shifter Status Register
+        (0x010 => shiftstat: ReadWrite<u32>),
+        /// Shifter Error Register
+        (0x014 => shifterr: ReadWrite<u32>),
+        /// Timer Status Register
+        (0x018 => timstat: ReadWrite<u32>),
+        (0x01C => _reserved0),
+        /// Shifter Status Interrupt Enable
+        (0x020 => shiftsien: ReadWrite<u32>),
+        /// Shifter Error Interrupt Enable
+        (0x024 => shifteien: ReadWrite<u32>),
+        /// Timer Interrupt Enable Register
+        (0x028 => timien: ReadWrite<u32>),
+        (0x02C => _reserved1),
+        /// Shifter Status DMA Enable
+        (0x030 => shiftsden: ReadWrite<u32>),
+        (0x034 => _reserved2),
+        /// Shifter Control Registers
+        (0x080 => shiftctl: [ReadWrite<u32, SHIFTCTL::Register>; 8]),
+        (0x0A0 => _reserved3),
+        /// Shifter Configuration Registers
+        (0x100 => shiftcfg: [ReadWrite<u32, SHIFTCFG::Register>; 8]),
+        (0x120 => _reserved4),
+        /// Shifter Buffer Registers
+        (0x200 => shiftbuf: [ReadWrite<u32>; 8]),
+        (0x220 => _reserved5),
+        /// Shifter Buffer Bit Swapped Registers
+        (0x280 => shiftbufbis: [ReadWrite<u32>; 8]),
+        (0x2A0 => _reserved6),
+        /// Shifter Buffer Byte Swapped Registers
+        (0x300 => shiftbufbys: [ReadWrite<u32>; 8]),
+        (0x320 => _reserved7),
+        /// Shifter Buffer Bit Byte Swapped Registers
+        (0x380 => shiftbufbbs: [ReadWrite<u32>; 8]),
+        (0x3A0 => _reserved8),
+        /// Timer Control Registers
+        (0x400 => timctl: [ReadWrite<u32, TIMCTL::Register>; 8]),
+        (0x420 => _reserved9),
+        /// Timer Configuration Registers
+        (0x480 => timcfg: [ReadWrite<u32, TIMCFG::Register>; 8]),
+        (0x4A0 => _reserved10),
+        /// Timer Compare Registers
+        (0x500 => timcmp: [ReadWrite<u32>; 8]),
+        (0x520 => @END),
+    }
+}
+
+register_bitfields![u32,
+    PARAM [
+        /// Trigger Number
+        TRIGGER OFFSET(24) NUMBITS(8) [],
+        /// Pin Number
+        PIN OFFSET(16) NUMBITS(8) [],
+        /// Timer Number
+        TIMER OFFSET(8) NUMBITS(8) [],
+        /// Shifter Number
+        SHIFTER OFFSET(0) NUMBITS(8) []
+    ],
+
+    CTRL [
+        /// Doze Enable
+        DOZEN OFFSET(31) NUMBITS(1) [],
+        /// Debug Enable
+        DBGE OFFSET(30) NUMBITS(1) [],
+        /// Fast Access
+        FASTACC OFFSET(2) NUMBITS(1) [],
+        /// Software Reset
+        SWRST OFFSET(1) NUMBITS(1) [],
+        /// FlexIO Enable
+        FLEXEN OFFSET(0) NUMBITS(1) []
+    ],
+
+    pub SHIFTCTL [
+        /// Timer Select
+        TIMSEL OFFSET(24) NUMBITS(3) [],
+        /// Timer Polarity
+        TIMPOL OFFSET(23) NUMBITS(1) [
+            PositiveEdge = 0,
+            NegativeEdge = 1
+        ],
+        /// Shifter Pin Configuration
+        PINCFG OFFSET(16) NUMBITS(2) [
+            OutputDisabled = 0,
+            OpenDrain = 1,
+            Bidirectional = 2,
+            Output = 3
+        ],
+        /// Shifter Pin Select
+        PINSEL OFFSET(8) NUMBITS(5) [],
+        /// Shifter Pin Polarity
+        PINPOL OFFSET(7) NUMBITS(1) [
+            ActiveHigh = 0,
+            ActiveLow = 1
+        ],
+        /// Shifter Mode
+        SMOD OFFSET(0) NUMBITS(3) [
+            Disabled = 0,
+            Receive = 1,
+            Transmit = 2,
+            MatchStore = 4,
+            MatchContinuous = 5,
+            State = 6,
+            Logic = 7
+        ]
+    ],
+
+    pub SHIFTCFG [
+        /// Parallel Width
+        PWIDTH OFFSET(16) NUMBITS(5) [],
+        /// Input Source
+        INSRC OFFSET(8) NUMBITS(1) [
+            Pin = 0,
+            NextShifter = 1
+        ],
+        /// Shifter Stop Bit
+        SSTOP OFFSET(4) NUMBITS(2) [
+            Disabled = 0,
+            Low = 2,
+            High = 3
+        ],
+        /// Shifter Start Bit
+        SSTART OFFSET(0) NUMBITS(2) [
+            Disabled = 0,
+            DisabledLoadOnEnable = 1,
+            Low = 2,
+            High = 3
+        ]
+    ],
+
+    pub TIMCTL [
+        /// Trigger Select
+        TRGSEL OFFSET(24) NUMBITS(6) [],
+        /// Trigger Polarity
+        TRGPOL OFFSET(23) NUMBITS(1) [
+            ActiveHigh = 0,
+            ActiveLow = 1
+        ],
+        /// Trigger Source
+        TRGSRC OFFSET(22) NUMBITS(1) [
+            External = 0,
+            Internal = 1
+        ],
+        /// Timer Pin Configuration
+        PINCFG OFFSET(16) NUMBITS(2) [
+            OutputDisabled = 0,
+            OpenDrain = 1,
+            Bidirectional = 2,
+            Output = 3
+        ],
+        /// Timer Pin Select
+        PINSEL OFFSET(8) NUMBITS(5) [],
+        /// Timer Pin Polarity
+        PINPOL OFFSET(7) NUMBITS(1) [
+            ActiveHigh = 0,
+            ActiveLow = 1
+        ],
+        /// Timer Mode
+        TIMOD OFFSET(0) NUMBITS(2) [
+            Disabled = 0,
+            DualBaud = 1,
+            DualPwm = 2,
+            Single = 3
+        ]
+    ],
+
+    pub TIMCFG [
+        /// Timer Output
+        TIMOUT OFFSET(24) NUMBITS(2) [
+            One = 0,
+            Zero = 1,
+            OneOnReset = 2,
+            ZeroOnReset = 3
+        ],
+        /// Timer Decrement
+        TIMDEC OFFSET(20) NUMBITS(2) [
+            FlexioClock = 0,
+            TriggerInput = 1,
+            PinInput = 2,
+            TriggerInputShiftTrigger = 3
+        ],
+        /// Timer Reset
+        TIMRST OFFSET(16) NUMBITS(3) [
+            Never = 0,
+            PinEqualsOutput = 2,
+            TriggerEqualsOutput = 3,
+            PinRisingEdge = 4,
+            TriggerRisingEdge = 6,
+            TriggerBothEdges = 7
+        ],
+        /// Timer Disable
+        TIMDIS OFFSET(12) NUMBITS(3) [
+            Never = 0,
+            PreviousTimerDisable = 1,
+            TimerCompare = 2,
+            TimerCompareTriggerLow = 3,
+            PinBothEdges = 4,
+            PinBothEdgesTriggerHigh = 5,
+            TriggerFallingEdge = 6
+        ],
+        /// Timer Enable
+        TIMENA OFFSET(8) NUMBITS(3) [
+            Always = 0,
+            PreviousTimerEnable = 1,
+            TriggerHigh = 2,
+            TriggerHighPinHigh = 3,
+            PinRisingEdge = 4,
+            PinRisingEdgeTriggerHigh = 5,
+            TriggerRisingEdge = 6,
+            TriggerBothEdges = 7
+        ],
+        /// Timer Stop Bit
+        TSTOP OFFSET(4) NUMBITS(2) [
+            Disabled = 0,
+            TimerCompare = 1,
+            TimerDisable = 2,
+            TimerCompareAndDisable = 3
+        ],
+        /// Timer Start Bit
+        TSTART OFFSET(1) NUMBITS(1) []
+    ]
+];
+
+const FLEXIO1_BASE: StaticRef<FlexioRegisters> =
+    unsafe { StaticRef::new(0x401AC000 as *const FlexioRegisters) };
+const FLEXIO2_BASE: StaticRef<FlexioRegisters> =
+    unsafe { StaticRef::new(0x401B0000 as *const FlexioRegisters) };
+
+/// Frequency of `pll3_sw_clk`, the source of the FlexIO clock root
+const PLL3_HZ: u32 = 480_000_000;
+
+/// Number of peripherals emulated on one FlexIO
+pub const MAX_CLIENTS: usize = 4;
+
+/// The trigger of a timer on the status flag of shifter `shifter`
+pub const fn shifter_status_trigger(shifter: usize) -> u32 {
+    (shifter as u32) * 4 + 1
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Instance {
+    Flexio1,
+    Flexio2,
+}
+
+/// A peripheral emulated on the FlexIO
+pub trait FlexioClient {
+    /// Called on each interrupt of the FlexIO, to check the flags of the
+    /// shifters and the timers the peripheral uses
+    fn handle_interrupt(&self);
+}
+
+struct FlexioClock<'a>(ccm::PeripheralClock<'a>);
+
+impl ClockInterface for FlexioClock<'_> {
+    fn is_enabled(&self) -> bool {
+        self.0.is_enabled()
+    }
+
+    fn enable(&self) {
+        self.0.enable();
+    }
+
+    fn disable(&self) {
+        self.0.disable();
+    }
+}
+
+pub struct Flexio<'a> {
+    registers: StaticRef<FlexioRegisters>,
+    clock: FlexioClock<'a>,
+    ccm: &'a ccm::Ccm,
+    instance: Instance,
+    clients: [OptionalCell<&'a dyn FlexioClient>; MAX_CLIENTS],
+}
+
+impl<'a> Flexio<'a> {
+    pub fn new_flexio1(ccm: &'a ccm::Ccm) -> Self {
+        Flexio::new(
+            FLEXIO1_BASE,
+            FlexioClock(ccm::PeripheralClock::ccgr5(ccm, ccm::HCLK5::FLEXIO1)),
+            ccm,
+            Instance::Flexio1,
+        )
+    }
+
+    pub fn new_flexio2(ccm: &'a ccm::Ccm) -> Self {
+        Flexio::new(
+            FLEXIO2_BASE,
+            FlexioClock(ccm::PeripheralClock::ccgr3(ccm, ccm::HCLK3::FLEXIO2)),
+            ccm,
+            Instance::Flexio2,
+        )
+    }
+
+    fn new(
+        registers: StaticRef<FlexioRegisters>,
+        clock: FlexioClock<'a>,
+        ccm: &'a ccm::Ccm,
+        instance: Instance,
+    ) -> Self {
+        Flexio {
+            registers,
+            clock,
+            ccm,
+            instance,
+            clients: Default::default(),
+        }
+    }
+
+    pub fn is_enabled_clock(&self) -> bool {
+        self.clock.is_enabled()
+    }
+
+    pub fn enable_clock(&self) {
+        self.clock.enable();
+    }
+
+    pub fn disable_clock(&self) {
+        self.clock.disable();
+    }
+
+    /// Set the FlexIO clock root to `pll3_sw_clk` divided by `pred * podf`
+    ///
+    /// `pred` and `podf` are clamped between [1, 8]. The clock gate is
+    /// disabled while the root changes.
+    pub fn set_clock_root(&self, pred: u32, podf: u32) {
+        let enabled = self.is_enabled_clock();
+        self.disable_clock();
+        match self.instance {
+            Instance::Flexio1 => {
+                self.ccm
+                    .set_flexio1_clock(ccm::FlexioClockSelection::PLL3, pred, podf)
+            }
+            Instance::Flexio2 => {
+                self.ccm
+                    .set_flexio2_clock(ccm::FlexioClockSelection::PLL3, pred, podf)
+            }
+        }
+        if enabled {
+            self.enable_clock();
+        }
+    }
+
+    /// Returns the frequency of the FlexIO clock, set by `set_clock_root`
+    pub fn clock_frequency(&self) -> u32 {
+        let divider = match self.instance {
+            Instance::Flexio1 => self.ccm.flexio1_clock_divider(),
+            Instance::Flexio2 => self.ccm.flexio2_clock_divider(),
+        };
+        PLL3_HZ / divider
+    }
+
+    /// Mux the pad of FlexIO pin `pin` to the FlexIO
+    pub fn mux_pin(&self, iomuxc: &Iomuxc, pin: usize) {
+        let (pad, pad_pin) = match self.instance {
+            Instance::Flexio1 => (PadId::AdB1, pin),
+            Instance::Flexio2 if pin < 16 => (PadId::B0, pin),
+            Instance::Flexio2 => (PadId::B1, pin - 16),
+        };
+        // The input path stays enabled so that the pin state can be read
+        iomuxc.enable_sw_mux_ctl_pad_gpio(pad, MuxMode::ALT4, Sion::Enabled, pad_pin);
+    }
+
+    /// Returns the number of shifters
+    pub fn shifters(&self) -> usize {
+        self.registers.param.read(PARAM::SHIFTER) as usize
+    }
+
+    /// Returns the number of timers
+    pub fn timers(&self) -> usize {
+        self.registers.param.read(PARAM::TIMER) as usize
+    }
+
+    /// Returns the number of pins
+    pub fn pins(&self) -> usize {
+        self.registers.param.read(PARAM::PIN) as usize
+    }
+
+    /// Enable the module, resetting it on the first call
+    pub fn enable(&self) {
+        if !self.is_enabled_clock() {
+            self.enable_clock();
+        }
+        if !self.registers.ctrl.is_set(CTRL::FLEXEN) {
+            self.registers.ctrl.modify(CTRL::SWRST::SET);
+            self.registers.ctrl.modify(CTRL::SWRST::CLEAR);
+            self.registers.ctrl.modify(CTRL::FLEXEN::SET);
+        }
+    }
+
+    /// Disable the module and its clock
+    pub fn disable(&self) {
+        self.registers.ctrl.modify(CTRL::FLEXEN::CLEAR);
+        self.disable_clock();
+    }
+
+    /// Add an emulated peripheral to the clients of the interrupt
+    ///
+    /// Returns `NOMEM` once `MAX_CLIENTS` clients are added.
+    pub fn add_client(&self, client: &'a dyn FlexioClient) -> Result<(), ErrorCode> {
+        self.clients
+            .iter()
+            .find(|slot| slot.is_none())
+            .map(|slot| slot.set(client))
+            .ok_or(ErrorCode::NOMEM)
+    }
+
+    /// Configure shifter `shifter`
+    ///
+    /// The shifter is disabled while it is configured, its mode is the
+    /// last field written.
+    pub fn configure_shifter(
+        &self,
+        shifter: usize,
+        control: FieldValue<u32, SHIFTCTL::Register>,
+        config: FieldValue<u32, SHIFTCFG::Register>,
+    ) {
+        self.registers.shiftctl[shifter].write(SHIFTCTL::SMOD::Disabled);
+        self.registers.shiftcfg[shifter].write(config);
+        self.registers.shiftctl[shifter].write(control);
+    }
+
+    /// Configure timer `timer`, comparing with `compare`
+    ///
+    /// The timer is disabled while it is configured, its mode is the last
+    /// field written.
+    pub fn configure_timer(
+        &self,
+        timer: usize,
+        control: FieldValue<u32, TIMCTL::Register>,
+        config: FieldValue<u32, TIMCFG::Register>,
+        compare: u32,
+    ) {
+        self.registers.timctl[timer].write(TIMCTL::TIMOD::Disabled);
+        self.registers.timcfg[timer].write(config);
+        self.registers.timcmp[timer].set(compare);
+        self.registers.timctl[timer].write(control);
+    }
+
+    /// Disable shifter `shifter`
+    pub fn disable_shifter(&self, shifter: usize) {
+        self.registers.shiftctl[shifter].write(SHIFTCTL::SMOD::Disabled);
+    }
+
+    /// Disable timer `timer`
+    pub fn disable_timer(&self, timer: usize) {
+        self.registers.timctl[timer].write(TIMCTL::TIMOD::Disabled);
+    }
+
+    /// Returns `true` if the status flag of shifter `shifter` is set: its
+    /// buffer is empty for a transmitter, full for a receiver
+    pub fn shifter_status(&self, shifter: usize) -> bool {
+        self.registers.shiftstat.get() & (1 << shifter) != 0
+    }
+
+    /// Returns `true` if the error flag of shifter `shifter` is set
+    pub fn shifter_error(&self, shifter: usize) -> bool {
+        self.registers.shifterr.get() & (1 << shifter) != 0
+    }
+
+    /// Clear the error flag of shifter `shifter`
+    pub fn clear_shifter_error(&self, shifter: usize) {
+        self.registers.shifterr.set(1 << shifter);
+    }
+
+    /// Returns `true` if the status flag of timer `timer` is set
+    pub fn timer_status(&self, timer: usize) -> bool {
+        self.registers.timstat.get() & (1 << timer) != 0
+    }
+
+    /// Clear the status flag of timer `timer`
+    pub fn clear_timer_status(&self, timer: usize) {
+        self.registers.timstat.set(1 << timer);
+    }
+
+    /// Interrupt on the status flag of shifter `shifter`
+    pub fn set_shifter_interrupt(&self, shifter: usize, enable: bool) {
+        let mask = 1 << shifter;
+        let enabled = self.registers.shiftsien.get();
+        self.registers.shiftsien.set(if enable {
+            enabled | mask
+        } else {
+            enabled & !mask
+        });
+    }
+
+    /// Returns `true` if the status flag of shifter `shifter` interrupts
+    pub fn is_enabled_shifter_interrupt(&self, shifter: usize) -> bool {
+        self.registers.shiftsien.get() & (1 << shifter) != 0
+    }
+
+    /// Interrupt on the error flag of shifter `shifter`
+    pub fn set_shifter_error_interrupt(&self, shifter: usize, enable: bool) {
+        let mask = 1 << shifter;
+        let enabled = self.registers.shifteien.get();
+        self.registers.shifteien.set(if enable {
+            enabled | mask
+        } else {
+            enabled & !mask
+        });
+    }
+
+    /// Interrupt on the status flag of timer `timer`
+    pub fn set_timer_interrupt(&self, timer: usize, enable: bool) {
+        let mask = 1 << timer;
+        let enabled = self.registers.timien.get();
+        self.registers.timien.set(if enable {
+            enabled | mask
+        } else {
+            enabled & !mask
+        });
+    }
+
+    /// Write the buffer of shifter `shifter`, clearing its status flag
+    pub fn write_shifter_buffer(&self, shifter: usize, value: u32) {
+        self.registers.shiftbuf[shifter].set(value);
+    }
+
+    /// Read the buffer of shifter `shifter`, clearing its status flag
+    pub fn read_shifter_buffer(&self, shifter: usize) -> u32 {
+        self.registers.shiftbuf[shifter].get()
+    }
+
+    /// Read the buffer of shifter `shifter` with its bits reversed
+    pub fn read_shifter_buffer_bit_swapped(&self, shifter: usize) -> u32 {
+        self.registers.shiftbufbis[shifter].get()
+    }
+
+    /// Read the buffer of shifter `shifter` with its bytes reversed
+    pub fn read_shifter_buffer_byte_swapped(&self, shifter: usize) -> u32 {
+        self.registers.shiftbufbys[shifter].get()
+    }
+
+    /// Returns the state of the pins
+    pub fn pin_state(&self) -> u32 {
+        self.registers.pin.get()
+    }
+
+    pub fn handle_interrupt(&self) {
+        for client in self.clients.iter() {
+            client.map(|client| client.handle_interrupt());
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum TxState {
+    Idle,
+    Transmitting,
+    TransmittingWord,
+    AbortRequested,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum RxState {
+    Idle,
+    Receiving,
+    ReceivingWord,
+    AbortRequested,
+}
+
+/// A UART emulated on the FlexIO
+///
+/// UART `index` uses the shifters and the timers `2 * index` to transmit
+/// and `2 * index + 1` to receive. Frames have 6 to 9 data bits, no parity
+/// and one stop bit; there is no flow control. Transfers are interrupt
+/// driven, one word at a time.
+pub struct FlexioUart<'a> {
+    flexio: &'a Flexio<'a>,
+    /// Shifter and timer transmitting
+    tx: usize,
+    /// Shifter and timer receiving
+    rx: usize,
+    tx_pin: usize,
+    rx_pin: usize,
+    /// Data bits per frame, 0 until configured
+    width: Cell<usize>,
+
+    tx_client: OptionalCell<&'a dyn hil::uart::TransmitClient>,
+    rx_client: OptionalCell<&'a dyn hil::uart::ReceiveClient>,
+
+    tx_buffer: TakeCell<'static, [u8]>,
+    tx_position: Cell<usize>,
+    tx_len: Cell<usize>,
+    tx_status: Cell<TxState>,
+
+    rx_buffer: TakeCell<'static, [u8]>,
+    rx_position: Cell<usize>,
+    rx_len: Cell<usize>,
+    rx_status: Cell<RxState>,
+}
+
+impl<'a> FlexioUart<'a> {
+    /// UART `index` of `flexio`, on FlexIO pins `tx_pin` and `rx_pin`
+    pub fn new(flexio: &'a Flexio<'a>, index: usize, tx_pin: usize, rx_pin: usize) -> Self {
+        FlexioUart {
+            flexio,
+            tx: 2 * index,
+            rx: 2 * index + 1,
+            tx_pin,
+            rx_pin,
+            width: Cell::new(0),
+
+            tx_client: OptionalCell::empty(),
+            rx_client: OptionalCell::empty(),
+
+            tx_buffer: TakeCell::empty(),
+            tx_position: Cell::new(0),
+            tx_len: Cell::new(0),
+            tx_status: Cell::new(TxState::Idle),
+
+            rx_buffer: TakeCell::empty(),
+            rx_position: Cell::new(0),
+            rx_len: Cell::new(0),
+            rx_status: Cell::new(RxState::Idle),
+        }
+    }
+
+    fn is_configured(&self) -> bool {
+        self.width.get() != 0
+    }
+
+    /// The data bits of the word received, which are shifted in from the
+    /// most significant bit of the buffer
+    fn read_word(&self) -> u32 {
+        self.flexio.read_shifter_buffer(self.rx) >> (32 - self.width.get())
+    }
+
+    fn handle_transmit(&self) {
+        match self.tx_status.get() {
+            TxState::Transmitting => {
+                let position = self.tx_position.get();
+                if position < self.tx_len.get() {
+                    self.tx_buffer.map(|buf| {
+                        self.flexio
+                            .write_shifter_buffer(self.tx, u32::from(buf[position]));
+                    });
+                    self.tx_position.set(position + 1);
+                } else {
+                    // The last word moved to the shifter
+                    self.flexio.set_shifter_interrupt(self.tx, false);
+                    self.tx_status.set(TxState::Idle);
+                    self.tx_client.map(|client| {
+                        if let Some(buf) = self.tx_buffer.take() {
+                            client.transmitted_buffer(buf, self.tx_len.get(), Ok(()));
+                        }
+                    });
+                }
+            }
+            TxState::TransmittingWord => {
+                self.flexio.set_shifter_interrupt(self.tx, false);
+                self.tx_status.set(TxState::Idle);
+                self.tx_client.map(|client| client.transmitted_word(Ok(())));
+            }
+            TxState::AbortRequested => {
+                self.flexio.set_shifter_interrupt(self.tx, false);
+                self.tx_status.set(TxState::Idle);
+                self.tx_client.map(|client| {
+                    if let Some(buf) = self.tx_buffer.take() {
+                        client.transmitted_buffer(
+                            buf,
+                            self.tx_position.get(),
+                            Err(ErrorCode::CANCEL),
+                        );
+                    } else {
+                        client.transmitted_word(Err(ErrorCode::CANCEL));
+                    }
+                });
+            }
+            TxState::Idle => self.flexio.set_shifter_interrupt(self.tx, false),
+        }
+    }
+
+    fn handle_receive(&self) {
+        let word = self.read_word();
+        match self.rx_status.get() {
+            RxState::Receiving => {
+                let position = self.rx_position.get();
+                self.rx_buffer.map(|buf| buf[position] = word as u8);
+                self.rx_position.set(position + 1);
+                if position + 1 == self.rx_len.get() {
+                    self.stop_receiving();
+                    self.rx_client.map(|client| {
+                        if let Some(buf) = self.rx_buffer.take() {
+                            client.received_buffer(
+                                buf,
+                                self.rx_len.get(),
+                                Ok(()),
+                                hil::uart::Error::None,
+                            );
+                        }
+                    });
+                }
+            }
+            RxState::ReceivingWord => {
+                self.stop_receiving();
+                self.rx_client
+                    .map(|client| client.received_word(word, Ok(()), hil::uart::Error::None));
+            }
+            RxState::AbortRequested => {
+                self.stop_receiving();
+                self.receive_aborted();
+            }
+            RxState::Idle => self.stop_receiving(),
+        }
+    }
+
+    /// The shifter saw a stop bit of 0, or received a word before the last
+    /// one was read.
+    fn handle_receive_error(&self) {
+        self.flexio.clear_shifter_error(self.rx);
+        // Drop the word received, if any
+        let _ = self.flexio.read_shifter_buffer(self.rx);
+        if self.rx_status.get() == RxState::Idle {
+            return;
+        }
+        self.stop_receiving();
+        self.rx_client.map(|client| {
+            if let Some(buf) = self.rx_buffer.take() {
+                client.received_buffer(
+                    buf,
+                    self.rx_position.get(),
+                    Err(ErrorCode::FAIL),
+                    hil::uart::Error::FramingError,
+                );
+            } else {
+                client.received_word(0, Err(ErrorCode::FAIL), hil::uart::Error::FramingError);
+            }
+        });
+    }
+
+    fn stop_receiving(&self) {
+        self.flexio.set_shifter_interrupt(self.rx, false);
+        self.flexio.set_shifter_error_interrupt(self.rx, false);
+        self.rx_status.set(RxState::Idle);
+    }
+
+    fn receive_aborted(&self) {
+        self.rx_client.map(|client| {
+            if let Some(buf) = self.rx_buffer.take() {
+                client.received_buffer(
+                    buf,
+                    self.rx_position.get(),
+                    Err(ErrorCode::CANCEL),
+                    hil::uart::Error::Aborted,
+                );
+            } else {
+                client.received_word(0, Err(ErrorCode::CANCEL), hil::uart::Error::Aborted);
+            }
+        });
+    }
+
+    fn start_receiving(&self, status: RxState) {
+        // Drop a word received while no receive was pending
+        if self.flexio.shifter_status(self.rx) {
+            let _ = self.flexio.read_shifter_buffer(self.rx);
+        }
+        self.flexio.clear_shifter_error(self.rx);
+        self.rx_status.set(status);
+        self.flexio.set_shifter_error_interrupt(self.rx, true);
+        self.flexio.set_shifter_interrupt(self.rx, true);
+    }
+}
+
+impl FlexioClient for FlexioUart<'_> {
+    fn handle_interrupt(&self) {
+        if self.flexio.is_enabled_shifter_interrupt(self.tx) && self.flexio.shifter_status(self.tx)
+        {
+            self.handle_transmit();
+        }
+        if self.flexio.shifter_error(self.rx) {
+            self.handle_receive_error();
+        } else if self.flexio.is_enabled_shifter_interrupt(self.rx)
+            && self.flexio.shifter_status(self.rx)
+        {
+            self.handle_receive();
+        }
+    }
+}
+
+impl hil::uart::Configure for FlexioUart<'_> {
+    fn configure(&self, params: hil::uart::Parameters) -> Result<(), ErrorCode> {
+        if params.parity != hil::uart::Parity::None
+            || params.stop_bits != hil::uart::StopBits::One
+            || params.hw_flow_control
+        {
+            return Err(ErrorCode::NOSUPPORT);
+        }
+        if self.tx_status.get() != TxState::Idle || self.rx_status.get() != RxState::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+
+        // The timer divides the FlexIO clock by 2 * (low byte + 1) for each
+        // bit, and counts 2 * bits - 1 edges in the high byte
+        let baud_rate = params.baud_rate.max(1);
+        let divider = (self.flexio.clock_frequency() + baud_rate / 2) / baud_rate;
+        if !(2..=512).contains(&divider) {
+            return Err(ErrorCode::INVAL);
+        }
+        let width = params.width as u32;
+        let compare = ((2 * width - 1) << 8) | (divider / 2 - 1);
+
+        self.flexio.enable();
+
+        // Transmit: the timer runs while the buffer of the shifter is full,
+        // for the start bit, the data bits and the stop bit.
+        self.flexio.configure_shifter(
+            self.tx,
+            SHIFTCTL::TIMSEL.val(self.tx as u32)
+                + SHIFTCTL::TIMPOL::PositiveEdge
+                + SHIFTCTL::PINCFG::Output
+                + SHIFTCTL::PINSEL.val(self.tx_pin as u32)
+                + SHIFTCTL::PINPOL::ActiveHigh
+                + SHIFTCTL::SMOD::Transmit,
+            SHIFTCFG::INSRC::Pin + SHIFTCFG::SSTOP::High + SHIFTCFG::SSTART::Low,
+        );
+        self.flexio.configure_timer(
+            self.tx,
+            TIMCTL::TRGSEL.val(shifter_status_trigger(self.tx))
+                + TIMCTL::TRGPOL::ActiveLow
+                + TIMCTL::TRGSRC::Internal
+                + TIMCTL::PINCFG::OutputDisabled
+                + TIMCTL::PINSEL.val(self.tx_pin as u32)
+                + TIMCTL::PINPOL::ActiveHigh
+                + TIMCTL::TIMOD::DualBaud,
+            TIMCFG::TIMOUT::One
+                + TIMCFG::TIMDEC::FlexioClock
+                + TIMCFG::TIMRST::Never
+                + TIMCFG::TIMDIS::TimerCompare
+                + TIMCFG::TIMENA::TriggerHigh
+                + TIMCFG::TSTOP::TimerDisable
+                + TIMCFG::TSTART::SET,
+            compare,
+        );
+
+        // Receive: the timer starts on the falling edge of the start bit and
+        // samples in the middle of each bit.
+        self.flexio.configure_shifter(
+            self.rx,
+            SHIFTCTL::TIMSEL.val(self.rx as u32)
+                + SHIFTCTL::TIMPOL::NegativeEdge
+                + SHIFTCTL::PINCFG::OutputDisabled
+                + SHIFTCTL::PINSEL.val(self.rx_pin as u32)
+                + SHIFTCTL::PINPOL::ActiveHigh
+                + SHIFTCTL::SMOD::Receive,
+            SHIFTCFG::INSRC::Pin + SHIFTCFG::SSTOP::High + SHIFTCFG::SSTART::Low,
+        );
+        self.flexio.configure_timer(
+            self.rx,
+            TIMCTL::TRGSEL.val(0)
+                + TIMCTL::TRGPOL::ActiveHigh
+                + TIMCTL::TRGSRC::External
+                + TIMCTL::PINCFG::OutputDisabled
+                + TIMCTL::PINSEL.val(self.rx_pin as u32)
+                + TIMCTL::PINPOL::ActiveLow
+                + TIMCTL::TIMOD::DualBaud,
+            TIMCFG::TIMOUT::OneOnReset
+                + TIMCFG::TIMDEC::FlexioClock
+                + TIMCFG::TIMRST::PinRisingEdge
+                + TIMCFG::TIMDIS::TimerCompare
+                + TIMCFG::TIMENA::PinRisingEdge
+                + TIMCFG::TSTOP::TimerDisable
+                + TIMCFG::TSTART::SET,
+            compare,
+        );
+
+        self.width.set(width as usize);
+        Ok(())
+    }
+}
+
+impl<'a> hil::uart::Transmit<'a> for FlexioUart<'a> {
+    fn set_transmit_client(&self, client: &'a dyn hil::uart::TransmitClient) {
+        self.tx_client.set(client);
+    }
+
+    fn transmit_buffer(
+        &self,
+        tx_data: &'static mut [u8],
+        tx_len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        if !self.is_configured() {
+            Err((ErrorCode::OFF, tx_data))
+        } else if self.tx_status.get() != TxState::Idle {
+            Err((ErrorCode::BUSY, tx_data))
+        } else if tx_len > tx_data.len() {
+            Err((ErrorCode::SIZE, tx_data))
+        } else {
+            self.tx_buffer.replace(tx_data);
+            self.tx_position.set(0);
+            self.tx_len.set(tx_len);
+            self.tx_status.set(TxState::Transmitting);
+            // The status flag is set while the shifter buffer is empty
+            self.flexio.set_shifter_interrupt(self.tx, true);
+            Ok(())
+        }
+    }
+
+    fn transmit_word(&self, word: u32) -> Result<(), ErrorCode> {
+        if !self.is_configured() {
+            return Err(ErrorCode::OFF);
+        } else if self.tx_status.get() != TxState::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        self.flexio
+            .write_shifter_buffer(self.tx, word & ((1 << self.width.get()) - 1));
+        self.tx_status.set(TxState::TransmittingWord);
+        self.flexio.set_shifter_interrupt(self.tx, true);
+        Ok(())
+    }
+
+    fn transmit_abort(&self) -> Result<(), ErrorCode> {
+        if self.tx_status.get() != TxState::Idle {
+            self.tx_status.set(TxState::AbortRequested);
+            Err(ErrorCode::BUSY)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'a> hil::uart::Receive<'a> for FlexioUart<'a> {
+    fn set_receive_client(&self, client: &'a dyn hil::uart::ReceiveClient) {
+        self.rx_client.set(client);
+    }
+
+    fn receive_buffer(
+        &self,
+        rx_buffer: &'static mut [u8],
+        rx_len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        if !self.is_configured() {
+            Err((ErrorCode::OFF, rx_buffer))
+        } else if self.rx_status.get() != RxState::Idle {
+            Err((ErrorCode::BUSY, rx_buffer))
+        } else if rx_len > rx_buffer.len() || rx_len == 0 {
+            Err((ErrorCode::SIZE, rx_buffer))
+        } else {
+            self.rx_buffer.replace(rx_buffer);
+            self.rx_position.set(0);
+            self.rx_len.set(rx_len);
+            self.start_receiving(RxState::Receiving);
+            Ok(())
+        }
+    }
+
+    fn receive_word(&self) -> Result<(), ErrorCode> {
+        if !self.is_configured() {
+            return Err(ErrorCode::OFF);
+        } else if self.rx_status.get() != RxState::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        self.start_receiving(RxState::ReceivingWord);
+        Ok(())
+    }
+
+    fn receive_abort(&self) -> Result<(), ErrorCode> {
+        // The receive is cancelled at the next word received, as for the
+        // LPUART
+        if self.rx_status.get() != RxState::Idle {
+            self.rx_status.set(RxState::AbortRequested);
+            Err(ErrorCode::BUSY)
+        } else {
+            Ok(())
+        }
+    }
+}
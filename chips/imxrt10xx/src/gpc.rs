@@ -0,0 +1,158 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! General Power Controller (GPC).
+//!
+//! The GPC decides which interrupts wake the chip up from STOP mode. Every
+//! interrupt starts masked: the board unmasks the interrupts of the wakeup
+//! sources it wants, typically a GPIO port or the SNVS real time clock, and
+//! a capsule allows deep sleep through the `DeepSleep` trait when it has
+//! nothing to do until one of them fires. The chip then enters STOP mode
+//! instead of WAIT when idle.
+//!
+//! Only interrupts 32 to 159 can wake the chip up. The clocks of the other
+//! peripherals, including the GPT behind the kernel alarm, are stopped in
+//! STOP mode, so alarms are late by the time spent in it.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! peripherals.gpc.init();
+//! peripherals.gpc.enable_wakeup(imxrt1050::nvic::GPIO5_1).unwrap();
+//! chip.set_gpc(&peripherals.gpc);
+//! power_manager.set_deep_sleep(&peripherals.gpc);
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::deep_sleep::DeepSleep;
+use kernel::utilities::registers::interfaces::{Readable, Writeable};
+use kernel::utilities::registers::{register_structs, ReadWrite};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+use crate::ccm::{self, WfiMode};
+use crate::iomuxc_gpr::IomuxcGpr;
+use crate::nvic;
+
+/// The first interrupt covered by the mask registers.
+const FIRST_WAKEUP_IRQ: u32 = 32;
+const NUM_MASK_REGISTERS: usize = 4;
+
+register_structs! {
+    /// General Power Controller
+    GpcRegisters {
+        /// GPC Interface control register
+        (0x000 => cntr: ReadWrite<u32>),
+        (0x004 => _reserved0),
+        /// IRQ masking registers 1 - 4, for interrupts 32 to 159
+        (0x008 => imr: [ReadWrite<u32>; NUM_MASK_REGISTERS]),
+        /// IRQ status registers 1 - 4
+        (0x018 => isr: [ReadWrite<u32>; NUM_MASK_REGISTERS]),
+        (0x028 => @END),
+    }
+}
+
+const GPC_BASE: StaticRef<GpcRegisters> =
+    unsafe { StaticRef::new(0x400F4000 as *const GpcRegisters) };
+
+pub struct Gpc<'a> {
+    registers: StaticRef<GpcRegisters>,
+    ccm: &'a ccm::Ccm,
+    iomuxc_gpr: IomuxcGpr,
+    deep_sleep_allowed: Cell<bool>,
+}
+
+impl<'a> Gpc<'a> {
+    pub const fn new(ccm: &'a ccm::Ccm) -> Gpc<'a> {
+        Gpc {
+            registers: GPC_BASE,
+            ccm,
+            iomuxc_gpr: IomuxcGpr::new(),
+            deep_sleep_allowed: Cell::new(false),
+        }
+    }
+
+    /// Mask every wakeup source. The GPC resets with every interrupt
+    /// unmasked.
+    pub fn init(&self) {
+        self.disable_all_wakeups();
+    }
+
+    fn mask_bit(irq: u32) -> Result<(usize, u32), ErrorCode> {
+        let index = irq.checked_sub(FIRST_WAKEUP_IRQ).ok_or(ErrorCode::INVAL)?;
+        match (index / 32) as usize {
+            register if register < NUM_MASK_REGISTERS => Ok((register, 1 << (index % 32))),
+            _ => Err(ErrorCode::INVAL),
+        }
+    }
+
+    /// Let the interrupt `irq` wake the chip up from STOP mode. The
+    /// peripheral must be configured to raise it, and the interrupt enabled
+    /// in the NVIC.
+    pub fn enable_wakeup(&self, irq: u32) -> Result<(), ErrorCode> {
+        let (register, bit) = Self::mask_bit(irq)?;
+        let imr = &self.registers.imr[register];
+        imr.set(imr.get() & !bit);
+        Ok(())
+    }
+
+    pub fn disable_wakeup(&self, irq: u32) -> Result<(), ErrorCode> {
+        let (register, bit) = Self::mask_bit(irq)?;
+        let imr = &self.registers.imr[register];
+        imr.set(imr.get() | bit);
+        Ok(())
+    }
+
+    pub fn disable_all_wakeups(&self) {
+        for imr in self.registers.imr.iter() {
+            imr.set(0xFFFF_FFFF);
+        }
+    }
+
+    /// Whether at least one interrupt can wake the chip up.
+    pub fn has_wakeups(&self) -> bool {
+        self.registers
+            .imr
+            .iter()
+            .any(|imr| imr.get() != 0xFFFF_FFFF)
+    }
+
+    /// Whether the chip may enter STOP mode when idle: a capsule allowed it
+    /// and a wakeup source is armed, so the chip cannot sleep forever.
+    pub fn deep_sleep_ready(&self) -> bool {
+        self.deep_sleep_allowed.get() && self.has_wakeups()
+    }
+
+    /// Select the mode entered on WFI with SLEEPDEEP set.
+    ///
+    /// Erratum ERR007265: the CCM only takes a low power mode into account
+    /// when it is written while an interrupt is pending and unmasked in the
+    /// GPC, so GPR_IRQ is asserted and unmasked around the write. Must be
+    /// called with interrupts disabled.
+    pub unsafe fn set_wfi_mode(&self, mode: WfiMode) {
+        if mode == WfiMode::Run {
+            self.ccm.set_wfi_mode(mode);
+            return;
+        }
+        let gpr_irq_was_masked = Self::mask_bit(nvic::GPR_IRQ).map_or(false, |(register, bit)| {
+            self.registers.imr[register].get() & bit != 0
+        });
+        self.iomuxc_gpr.set_global_interrupt(true);
+        let _ = self.enable_wakeup(nvic::GPR_IRQ);
+        self.ccm.set_wfi_mode(mode);
+        if gpr_irq_was_masked {
+            let _ = self.disable_wakeup(nvic::GPR_IRQ);
+        }
+        self.iomuxc_gpr.set_global_interrupt(false);
+        cortexm7::nvic::Nvic::new(nvic::GPR_IRQ).clear_pending();
+    }
+}
+
+impl DeepSleep for Gpc<'_> {
+    fn set_deep_sleep_allowed(&self, allowed: bool) {
+        self.deep_sleep_allowed.set(allowed);
+    }
+}
@@ -26,6 +26,8 @@
 //! assert_eq!(pin_from_id as *const _, pin_from_port as *const _);
 //! ```
 
+use core::fmt;
+
 use cortexm7::support::atomic;
 use enum_primitive::cast::FromPrimitive;
 use enum_primitive::enum_from_primitive;
@@ -524,6 +526,60 @@ impl<'a> Ports<'a> {
     }
 }
 
+impl Ports<'_> {
+    /// Returns the port and the pin offset of pin `index`, counting the pins of
+    /// all ports in order.
+    fn locate(&self, index: usize) -> Option<(usize, usize)> {
+        let sizes = [
+            self.gpio1.pins.len(),
+            self.gpio2.pins.len(),
+            self.gpio3.pins.len(),
+            self.gpio4.pins.len(),
+            self.gpio5.pins.len(),
+        ];
+        let mut offset = index;
+        for (port, size) in sizes.iter().enumerate() {
+            if offset < *size {
+                return Some((port, offset));
+            }
+            offset -= size;
+        }
+        None
+    }
+}
+
+impl hil::gpio::PinIntrospection for Ports<'_> {
+    fn num_pins(&self) -> usize {
+        self.gpio1.pins.len()
+            + self.gpio2.pins.len()
+            + self.gpio3.pins.len()
+            + self.gpio4.pins.len()
+            + self.gpio5.pins.len()
+    }
+
+    /// Pins of ports without a clock are not accessible: their registers
+    /// cannot be read.
+    fn pin(&self, index: usize) -> Option<&dyn hil::gpio::Pin> {
+        let (port, offset) = self.locate(index)?;
+        let (enabled, pin) = match port {
+            0 => (self.gpio1.is_enabled_clock(), self.gpio1.pin(offset)),
+            1 => (self.gpio2.is_enabled_clock(), self.gpio2.pin(offset)),
+            2 => (self.gpio3.is_enabled_clock(), self.gpio3.pin(offset)),
+            3 => (self.gpio4.is_enabled_clock(), self.gpio4.pin(offset)),
+            _ => (self.gpio5.is_enabled_clock(), self.gpio5.pin(offset)),
+        };
+        enabled.then_some(pin as &dyn hil::gpio::Pin)
+    }
+
+    /// Pins are named after their port and offset, like `gpio3[17]`.
+    fn write_pin_name(&self, index: usize, writer: &mut dyn fmt::Write) -> fmt::Result {
+        match self.locate(index) {
+            Some((port, offset)) => writer.write_fmt(format_args!("gpio{}[{}]", port + 1, offset)),
+            None => Err(fmt::Error),
+        }
+    }
+}
+
 struct PortClock<'a>(ccm::PeripheralClock<'a>);
 
 impl ClockInterface for PortClock<'_> {
@@ -2,11 +2,21 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 // Copyright Tock Contributors 2022.
 
+//! General Purpose Timer (GPT)
+//!
+//! The GPT counter is 32 bits wide. The driver extends it to a monotonic
+//! 64-bit counter by keeping the upper 32 bits in software, incremented on
+//! every rollover interrupt, so timestamps and alarms do not wrap silently.
+//! Since the output compare only matches the lower 32 bits, alarms further
+//! than one rollover in the future are re-armed until the full 64-bit
+//! deadline has been reached.
+
+use core::cell::Cell;
 use core::sync::atomic::{AtomicU32, Ordering};
 use cortexm7;
 use cortexm7::support::atomic;
 use kernel::hil;
-use kernel::hil::time::{Ticks, Ticks32, Time};
+use kernel::hil::time::{Ticks, Ticks64, Time};
 use kernel::platform::chip::ClockInterface;
 use kernel::utilities::cells::OptionalCell;
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
@@ -167,6 +177,10 @@ pub struct Gpt<'a, S> {
     clock: GptClock<'a>,
     client: OptionalCell<&'a dyn hil::time::AlarmClient>,
     irqn: u32,
+    /// Upper 32 bits of the 64-bit counter
+    high: Cell<u32>,
+    /// Full 64-bit deadline of the alarm
+    expiration: Cell<u64>,
     _selection: core::marker::PhantomData<S>,
 }
 
@@ -204,6 +218,8 @@ impl<'a, S> Gpt<'a, S> {
             clock: GptClock(clock_gate),
             client: OptionalCell::empty(),
             irqn,
+            high: Cell::new(0),
+            expiration: Cell::new(0),
             _selection: core::marker::PhantomData,
         }
     }
@@ -221,10 +237,35 @@ impl<'a, S> Gpt<'a, S> {
     }
 
     pub fn handle_interrupt(&self) {
-        self.registers.sr.modify(SR::OF1::SET);
-        self.registers.ir.modify(IR::OF1IE::CLEAR);
+        if self.registers.sr.is_set(SR::ROV) {
+            self.registers.sr.write(SR::ROV::SET);
+            self.high.set(self.high.get().wrapping_add(1));
+        }
 
-        self.client.map(|client| client.alarm());
+        if self.registers.sr.is_set(SR::OF1) && self.registers.ir.is_set(IR::OF1IE) {
+            self.registers.sr.write(SR::OF1::SET);
+            if self.now_u64() < self.expiration.get() {
+                // Only the lower 32 bits matched, keep the compare armed
+                // until they match again after the next rollover.
+                return;
+            }
+            self.registers.ir.modify(IR::OF1IE::CLEAR);
+
+            self.client.map(|client| client.alarm());
+        }
+    }
+
+    fn now_u64(&self) -> u64 {
+        let low = self.registers.cnt.get();
+        if self.registers.sr.is_set(SR::ROV) {
+            // The counter rolled over but the interrupt has not been
+            // handled yet, so `high` is one behind. Read the counter again
+            // to make sure the value is from after the rollover.
+            let high = self.high.get().wrapping_add(1) as u64;
+            (high << 32) | self.registers.cnt.get() as u64
+        } else {
+            ((self.high.get() as u64) << 32) | low as u64
+        }
     }
 
     /// Start the GPT, specifying the peripheral clock selection and the peripheral clock divider
@@ -277,6 +318,8 @@ impl<'a, S> Gpt<'a, S> {
 
         // Set the value of the Output Compare Register
         self.registers.ocr1.set(0xFFFF_FFFF - 1);
+        self.high.set(0);
+        self.expiration.set(0xFFFF_FFFF - 1);
 
         match selection {
             ccm::PerclkClockSel::IPG => {
@@ -318,8 +361,8 @@ impl<'a, S> Gpt<'a, S> {
         // Enable the GPT
         self.registers.cr.modify(CR::EN::SET);
 
-        // Enable the Output Compare 1 Interrupt Enable
-        self.registers.ir.modify(IR::OF1IE::SET);
+        // Enable the Output Compare 1 and the Rollover Interrupts
+        self.registers.ir.modify(IR::OF1IE::SET + IR::ROVIE::SET);
     }
 
     fn set_frequency(&self, hz: u32) {
@@ -360,10 +403,10 @@ impl hil::time::Frequency for _2 {
 
 impl<F: hil::time::Frequency> hil::time::Time for Gpt<'_, F> {
     type Frequency = F;
-    type Ticks = Ticks32;
+    type Ticks = Ticks64;
 
-    fn now(&self) -> Ticks32 {
-        Ticks32::from(self.registers.cnt.get())
+    fn now(&self) -> Ticks64 {
+        Ticks64::from(self.now_u64())
     }
 }
 
@@ -384,12 +427,13 @@ impl<'a, F: hil::time::Frequency> hil::time::Alarm<'a> for Gpt<'a, F> {
         }
 
         let _ = self.disarm();
+        self.expiration.set(expire.into_u64());
         self.registers.ocr1.set(expire.into_u32());
         self.registers.ir.modify(IR::OF1IE::SET);
     }
 
     fn get_alarm(&self) -> Self::Ticks {
-        Self::Ticks::from(self.registers.ocr1.get())
+        Self::Ticks::from(self.expiration.get())
     }
 
     fn disarm(&self) -> Result<(), ErrorCode> {
@@ -409,7 +453,7 @@ impl<'a, F: hil::time::Frequency> hil::time::Alarm<'a> for Gpt<'a, F> {
     }
 
     fn minimum_dt(&self) -> Self::Ticks {
-        Self::Ticks::from(1)
+        Self::Ticks::from(1u32)
     }
 }
 
@@ -623,4 +623,27 @@ impl Iomuxc {
             .lpuart2_tx_select_input
             .modify(DAISY_SELECT_INPUT::DAISY::CLEAR);
     }
+
+    // ENET RMII inputs: GPIO_B1_04 to GPIO_B1_06, GPIO_B1_10, GPIO_B1_11
+    // and GPIO_EMC_41
+    pub fn enable_enet_rmii_select_inputs(&self) {
+        self.registers
+            .enet0_rxdata_select_input
+            .modify(DAISY_SELECT_INPUT::DAISY::SET);
+        self.registers
+            .enet1_rxdata_select_input
+            .modify(DAISY_SELECT_INPUT::DAISY::SET);
+        self.registers
+            .enet_rxen_select_input
+            .modify(DAISY_SELECT_INPUT::DAISY::SET);
+        self.registers
+            .enet_rxerr_select_input
+            .modify(DAISY_SELECT_INPUT::DAISY::SET);
+        self.registers
+            .enet_ipg_clk_rmii_select_input
+            .modify(DAISY_SELECT_INPUT::DAISY::SET);
+        self.registers
+            .enet_mdio_select_input
+            .modify(DAISY_2BIT_SELECT_INPUT::DAISY.val(0b01));
+    }
 }
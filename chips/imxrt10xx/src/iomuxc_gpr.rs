@@ -0,0 +1,68 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! IOMUXC General Purpose Registers (IOMUXC_GPR).
+//!
+//! Only the ENET reference clock selection and the global interrupt in GPR1
+//! are supported for now.
+
+use kernel::utilities::registers::interfaces::ReadWriteable;
+use kernel::utilities::registers::{register_bitfields, register_structs, ReadWrite};
+use kernel::utilities::StaticRef;
+
+register_structs! {
+    /// IOMUXC General Purpose Registers
+    IomuxcGprRegisters {
+        /// GPR0 General Purpose Register
+        (0x000 => gpr0: ReadWrite<u32>),
+        /// GPR1 General Purpose Register
+        (0x004 => gpr1: ReadWrite<u32, GPR1::Register>),
+        (0x008 => @END),
+    }
+}
+
+register_bitfields![u32,
+    GPR1 [
+        /// Direction of the ENET1_REF_CLK pad: 1 to output the reference
+        /// clock from the ENET PLL to the PHY
+        ENET1_TX_CLK_DIR OFFSET(17) NUMBITS(1) [],
+        /// Assert the GPR_IRQ interrupt
+        GINT OFFSET(12) NUMBITS(1) [],
+        /// Source of the ENET1 reference clock: 0 for the ENET PLL, 1 for
+        /// the ENET1_REF_CLK pad
+        ENET1_CLK_SEL OFFSET(13) NUMBITS(1) []
+    ]
+];
+
+const IOMUXC_GPR_BASE: StaticRef<IomuxcGprRegisters> =
+    unsafe { StaticRef::new(0x400AC000 as *const IomuxcGprRegisters) };
+
+pub struct IomuxcGpr {
+    registers: StaticRef<IomuxcGprRegisters>,
+}
+
+impl IomuxcGpr {
+    pub const fn new() -> Self {
+        Self {
+            registers: IOMUXC_GPR_BASE,
+        }
+    }
+
+    /// Clock the ENET MAC from the ENET PLL and output the reference clock
+    /// to the PHY on the ENET1_REF_CLK pad.
+    pub fn enable_enet1_ref_clock_output(&self) {
+        self.registers
+            .gpr1
+            .modify(GPR1::ENET1_TX_CLK_DIR::SET + GPR1::ENET1_CLK_SEL::CLEAR);
+    }
+
+    /// Assert or release the GPR_IRQ interrupt.
+    pub fn set_global_interrupt(&self, asserted: bool) {
+        self.registers.gpr1.modify(if asserted {
+            GPR1::GINT::SET
+        } else {
+            GPR1::GINT::CLEAR
+        });
+    }
+}
@@ -14,16 +14,30 @@ pub mod chip;
 pub mod nvic;
 
 // Peripherals
+pub mod acmp;
+pub mod adc;
+pub mod adc_etc;
 pub mod ccm;
 pub mod ccm_analog;
+pub mod clock_tree;
 pub mod dcdc;
 pub mod dma;
+pub mod enet;
+pub mod flexio;
+pub mod gpc;
 pub mod gpio;
 pub mod gpt;
 pub mod iomuxc;
+pub mod iomuxc_gpr;
 pub mod iomuxc_snvs;
 pub mod lpi2c;
 pub mod lpuart;
+pub mod ocotp;
+pub mod qtmr;
+pub mod snvs;
+pub mod src;
+pub mod tsc;
+pub mod xbar;
 
 use cortexm7::{initialize_ram_jump_to_main, unhandled_interrupt, CortexM7, CortexMVariant};
 
@@ -9,6 +9,7 @@ use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::{register_bitfields, ReadOnly, ReadWrite, WriteOnly};
 use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
 
 use kernel::hil;
 use kernel::hil::i2c::{self, Error, I2CHwMasterClient, I2CMaster};
@@ -714,6 +715,23 @@ impl<'a> Lpi2c<'a> {
     }
 }
 
+impl i2c::I2CBusSpeed for Lpi2c<'_> {
+    fn max_bus_speed(&self) -> i2c::BusSpeed {
+        i2c::BusSpeed::Fast400k
+    }
+
+    fn set_bus_speed(&self, speed: i2c::BusSpeed) -> Result<(), ErrorCode> {
+        let speed = match speed {
+            i2c::BusSpeed::Standard100k => Lpi2cSpeed::Speed100k,
+            i2c::BusSpeed::Fast400k => Lpi2cSpeed::Speed400k,
+            i2c::BusSpeed::FastPlus1M => return Err(ErrorCode::NOSUPPORT),
+        };
+        // the timings do not depend on the system clock
+        self.set_speed(speed, 0);
+        Ok(())
+    }
+}
+
 impl<'a> i2c::I2CMaster<'a> for Lpi2c<'a> {
     fn set_master_client(&self, master_client: &'a dyn I2CHwMasterClient) {
         self.master_client.replace(master_client);
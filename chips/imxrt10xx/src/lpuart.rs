@@ -310,6 +310,7 @@ const LPUART2_BASE: StaticRef<LpuartRegisters> =
 enum LPUARTStateTX {
     Idle,
     Transmitting,
+    TransmittingWord,
     AbortRequested,
 }
 
@@ -317,6 +318,7 @@ enum LPUARTStateTX {
 enum USARTStateRX {
     Idle,
     Receiving,
+    ReceivingWord,
     AbortRequested,
 }
 
@@ -340,6 +342,11 @@ pub struct Lpuart<'a> {
     rx_status: Cell<USARTStateRX>,
     rx_dma_channel: OptionalCell<&'a dma::DmaChannel>,
     rx_dma_source: dma::DmaHardwareSource,
+
+    /// Whether frames carry 9 data bits.
+    nine_bit: Cell<bool>,
+    /// Bit 8 of the words sent from buffers, set for mark parity.
+    tx_ninth_bit: Cell<u32>,
 }
 
 impl<'a> Lpuart<'a> {
@@ -387,6 +394,9 @@ impl<'a> Lpuart<'a> {
             rx_status: Cell::new(USARTStateRX::Idle),
             rx_dma_channel: OptionalCell::empty(),
             rx_dma_source,
+
+            nine_bit: Cell::new(false),
+            tx_ninth_bit: Cell::new(0),
         }
     }
 
@@ -485,7 +495,9 @@ impl<'a> Lpuart<'a> {
                 let position = self.tx_position.get();
                 if position < self.tx_len.get() {
                     self.tx_buffer.map(|buf| {
-                        self.registers.data.set(buf[position].into());
+                        self.registers
+                            .data
+                            .set(u32::from(buf[position]) | self.tx_ninth_bit.get());
                         self.tx_position.replace(self.tx_position.get() + 1);
                         self.enable_transmit_complete_interrupt();
                     });
@@ -501,6 +513,10 @@ impl<'a> Lpuart<'a> {
                         }
                     });
                 }
+            } else if self.tx_status.get() == LPUARTStateTX::TransmittingWord {
+                // the word moved to the shift register
+                self.tx_status.replace(LPUARTStateTX::Idle);
+                self.tx_client.map(|client| client.transmitted_word(Ok(())));
             } else if self.tx_status.get() == LPUARTStateTX::AbortRequested {
                 self.tx_status.replace(LPUARTStateTX::Idle);
                 self.tx_client.map(|client| {
@@ -510,13 +526,16 @@ impl<'a> Lpuart<'a> {
                             self.tx_position.get(),
                             Err(ErrorCode::CANCEL),
                         );
+                    } else {
+                        client.transmitted_word(Err(ErrorCode::CANCEL));
                     }
                 });
             }
         }
 
         if self.registers.stat.is_set(STAT::RDRF) {
-            let byte = self.registers.data.get() as u8;
+            let word = self.registers.data.get() & 0x1FF;
+            let byte = word as u8;
 
             self.disable_receive_interrupt();
 
@@ -547,6 +566,16 @@ impl<'a> Lpuart<'a> {
                         }
                     });
                 }
+            } else if self.rx_status.get() == USARTStateRX::ReceivingWord {
+                self.rx_status.replace(USARTStateRX::Idle);
+                let err = self.check_status();
+                let rval = if err == hil::uart::Error::None {
+                    Ok(())
+                } else {
+                    Err(ErrorCode::FAIL)
+                };
+                self.rx_client
+                    .map(|client| client.received_word(word, rval, err));
             } else if self.rx_status.get() == USARTStateRX::AbortRequested {
                 self.rx_status.replace(USARTStateRX::Idle);
                 self.rx_client.map(|client| {
@@ -557,6 +586,8 @@ impl<'a> Lpuart<'a> {
                             Err(ErrorCode::CANCEL),
                             hil::uart::Error::Aborted,
                         );
+                    } else {
+                        client.received_word(0, Err(ErrorCode::CANCEL), hil::uart::Error::Aborted);
                     }
                 });
             }
@@ -787,13 +818,23 @@ impl<'a> hil::uart::Transmit<'a> for Lpuart<'a> {
         }
     }
 
-    fn transmit_word(&self, _word: u32) -> Result<(), ErrorCode> {
-        // TODO implement for interrupt-, DMA-based transmits.
-        Err(ErrorCode::FAIL)
+    fn transmit_word(&self, word: u32) -> Result<(), ErrorCode> {
+        if self.tx_status.get() != LPUARTStateTX::Idle || self.tx_buffer.is_some() {
+            return Err(ErrorCode::BUSY);
+        } else if !self.is_transmit_enabled() {
+            return Err(ErrorCode::OFF);
+        }
+
+        // Words are always sent with interrupts, bit 8 is the address mark
+        // in 9-bit mode.
+        self.registers.data.set(word & 0x1FF);
+        self.tx_status.set(LPUARTStateTX::TransmittingWord);
+        self.enable_transmit_complete_interrupt();
+        Ok(())
     }
 
     fn transmit_abort(&self) -> Result<(), ErrorCode> {
-        if self.tx_dma_channel.is_some() {
+        if self.tx_dma_channel.is_some() && self.tx_status.get() == LPUARTStateTX::Idle {
             self.transmit_abort_dma()
         } else {
             self.transmit_abort_interrupt()
@@ -803,14 +844,24 @@ impl<'a> hil::uart::Transmit<'a> for Lpuart<'a> {
 
 impl<'a> hil::uart::Configure for Lpuart<'a> {
     fn configure(&self, params: hil::uart::Parameters) -> Result<(), ErrorCode> {
+        use hil::uart::{Parity, Width};
+
+        // 9-bit frames are either 9 data bits, or 8 data bits with a fixed
+        // parity bit as the ninth bit.
+        let ninth_bit = match (params.width, params.parity) {
+            (Width::Eight, Parity::None) => None,
+            (Width::Nine, Parity::None) | (Width::Eight, Parity::Space) => Some(false),
+            (Width::Eight, Parity::Mark) => Some(true),
+            _ => None,
+        };
         if params.baud_rate != 115200
             || params.stop_bits != hil::uart::StopBits::One
-            || params.parity != hil::uart::Parity::None
             || params.hw_flow_control != false
-            || params.width != hil::uart::Width::Eight
+            || (ninth_bit.is_none()
+                && (params.width != Width::Eight || params.parity != Parity::None))
         {
             panic!(
-                "Currently we only support uart setting of 115200bps 8N1, no hardware flow control"
+                "Currently we only support uart setting of 115200bps 8N1, 9N1, 8M1 or 8S1, no hardware flow control"
             );
         }
 
@@ -833,10 +884,24 @@ impl<'a> hil::uart::Configure for Lpuart<'a> {
 
         self.registers.ctrl.modify(CTRL::PE::CLEAR);
         self.registers.ctrl.modify(CTRL::PT::CLEAR);
-        self.registers.ctrl.modify(CTRL::M::CLEAR);
+        match ninth_bit {
+            None => self.registers.ctrl.modify(CTRL::M::CLEAR),
+            Some(_) => self.registers.ctrl.modify(CTRL::M::SET),
+        }
         self.registers.ctrl.modify(CTRL::ILT::CLEAR);
         self.registers.ctrl.modify(CTRL::IDLECFG::CLEAR);
 
+        // Bit 8 of the words sent from buffers, also used for the bytes
+        // written by DMA
+        self.nine_bit.set(ninth_bit.is_some());
+        if ninth_bit == Some(true) {
+            self.tx_ninth_bit.set(0x100);
+            self.registers.ctrl.modify(CTRL::R9T8::SET);
+        } else {
+            self.tx_ninth_bit.set(0);
+            self.registers.ctrl.modify(CTRL::R9T8::CLEAR);
+        }
+
         // Set 1 stop bit
         self.registers.baud.modify(BAUD::SBNS::CLEAR);
 
@@ -887,12 +952,19 @@ impl<'a> hil::uart::Receive<'a> for Lpuart<'a> {
     }
 
     fn receive_word(&self) -> Result<(), ErrorCode> {
-        // TODO handle interrupt-/DMA-based word receives
-        Err(ErrorCode::FAIL)
+        if self.rx_status.get() != USARTStateRX::Idle || self.rx_buffer.is_some() {
+            return Err(ErrorCode::BUSY);
+        } else if !self.is_receive_enabled() {
+            return Err(ErrorCode::OFF);
+        }
+
+        self.rx_status.set(USARTStateRX::ReceivingWord);
+        self.enable_receive_interrupt();
+        Ok(())
     }
 
     fn receive_abort(&self) -> Result<(), ErrorCode> {
-        if self.rx_dma_channel.is_some() {
+        if self.rx_dma_channel.is_some() && self.rx_status.get() == USARTStateRX::Idle {
             self.receive_abort_dma()
         } else {
             self.receive_abort_interrupt()
@@ -900,6 +972,58 @@ impl<'a> hil::uart::Receive<'a> for Lpuart<'a> {
     }
 }
 
+impl<'a> hil::uart::MultiDrop for Lpuart<'a> {
+    fn set_address_match(&self, address: Option<u8>) -> Result<(), ErrorCode> {
+        if !self.nine_bit.get() {
+            return Err(ErrorCode::INVAL);
+        } else if self.rx_status.get() != USARTStateRX::Idle || self.rx_buffer.is_some() {
+            return Err(ErrorCode::BUSY);
+        }
+
+        // The match configuration can only change while the receiver is
+        // disabled
+        let receive_enabled = self.is_receive_enabled();
+        self.registers.ctrl.modify(CTRL::RE::CLEAR);
+        while self.registers.ctrl.is_set(CTRL::RE) {}
+
+        match address {
+            Some(address) => {
+                // Address words have the address mark in bit 8. Words
+                // following an address which does not match are discarded.
+                self.registers
+                    .r#match
+                    .modify(MATCH::MA1.val(0x100 | address as u32));
+                self.registers
+                    .baud
+                    .modify(BAUD::MATCFG.val(0) + BAUD::MAEN1::SET);
+            }
+            None => self.registers.baud.modify(BAUD::MAEN1::CLEAR),
+        }
+
+        if receive_enabled {
+            self.registers.ctrl.modify(CTRL::RE::SET);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> hil::uart::Break for Lpuart<'a> {
+    fn start_break(&self) -> Result<(), ErrorCode> {
+        if !self.is_transmit_enabled() {
+            return Err(ErrorCode::OFF);
+        }
+        // Break characters are queued after the data being sent, for as
+        // long as SBK is set
+        self.registers.ctrl.modify(CTRL::SBK::SET);
+        Ok(())
+    }
+
+    fn stop_break(&self) -> Result<(), ErrorCode> {
+        self.registers.ctrl.modify(CTRL::SBK::CLEAR);
+        Ok(())
+    }
+}
+
 struct LpuartClock<'a>(ccm::PeripheralClock<'a>);
 
 impl ClockInterface for LpuartClock<'_> {
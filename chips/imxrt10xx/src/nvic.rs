@@ -46,8 +46,8 @@ pub const LPI2C1: u32 = 28;
 // pub const FLEXCAN2: u32 = 37;
 // pub const CM7: u32 = 38;
 // pub const KPP: u32 = 39;
-// pub const TSC_DIG: u32 = 40;
-// pub const GPR_IRQ: u32 = 41;
+pub const TSC_DIG: u32 = 40;
+pub const GPR_IRQ: u32 = 41;
 // pub const LCDIF: u32 = 42;
 // pub const CSI: u32 = 43;
 // pub const PXP: u32 = 44;
@@ -71,8 +71,8 @@ pub const SNVS_LP_WRAPPER: u32 = 48;
 // pub const Temperature_Monitor: u32 = 64;
 // pub const USB_PHY: u32 = 65;
 // pub const USB_PHY: u32 = 66;
-// pub const ADC1: u32 = 67;
-// pub const ADC2: u32 = 68;
+pub const ADC1: u32 = 67;
+pub const ADC2: u32 = 68;
 // pub const DCDC: u32 = 69;
 // pub const GPIO1: u32 = 72;
 // pub const GPIO1: u32 = 73;
@@ -92,8 +92,8 @@ pub const GPIO4_1: u32 = 86;
 pub const GPIO4_2: u32 = 87;
 pub const GPIO5_1: u32 = 88;
 pub const GPIO5_2: u32 = 89;
-// pub const FLEXIO1: u32 = 90;
-// pub const FLEXIO2: u32 = 91;
+pub const FLEXIO1: u32 = 90;
+pub const FLEXIO2: u32 = 91;
 // pub const WDOG1: u32 = 92;
 // pub const RTWDOG: u32 = 93;
 // pub const EWM: u32 = 94;
@@ -114,27 +114,27 @@ pub const GPT2: u32 = 101;
 // pub const USDHC2: u32 = 111;
 // pub const USB: u32 = 112;
 // pub const USB: u32 = 113;
-// pub const ENET: u32 = 114;
-// pub const ENET: u32 = 115;
+pub const ENET: u32 = 114;
+// pub const ENET_1588_TIMER: u32 = 115;
 // pub const XBAR1: u32 = 116;
 // pub const XBAR1: u32 = 117;
-// pub const ADC_ETC: u32 = 118;
-// pub const ADC_ETC: u32 = 119;
-// pub const ADC_ETC: u32 = 120;
-// pub const ADC_ETC: u32 = 121;
+pub const ADC_ETC_IRQ0: u32 = 118;
+pub const ADC_ETC_IRQ1: u32 = 119;
+pub const ADC_ETC_IRQ2: u32 = 120;
+pub const ADC_ETC_ERROR_IRQ: u32 = 121;
 // pub const PIT: u32 = 122;
-// pub const ACMP: u32 = 123;
-// pub const ACMP: u32 = 124;
-// pub const ACMP: u32 = 125;
-// pub const ACMP: u32 = 126;
+pub const ACMP1: u32 = 123;
+pub const ACMP2: u32 = 124;
+pub const ACMP3: u32 = 125;
+pub const ACMP4: u32 = 126;
 // pub const ENC1: u32 = 129;
 // pub const ENC2: u32 = 130;
 // pub const ENC3: u32 = 131;
 // pub const ENC4: u32 = 132;
-// pub const QTIMER1: u32 = 133;
-// pub const QTIMER2: u32 = 134;
-// pub const QTIMER3: u32 = 135;
-// pub const QTIMER4: u32 = 136;
+pub const QTIMER1: u32 = 133;
+pub const QTIMER2: u32 = 134;
+pub const QTIMER3: u32 = 135;
+pub const QTIMER4: u32 = 136;
 // pub const FLEXPWM2: u32 = 137;
 // pub const FLEXPWM2: u32 = 138;
 // pub const FLEXPWM2: u32 = 139;
@@ -0,0 +1,303 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! On-Chip OTP Controller (OCOTP).
+//!
+//! The fuses are organized as 64 words of 32 bits. At reset, the controller
+//! copies them into shadow registers, which can be read like any other
+//! register. `read_shadow()` reads these copies, `read_fuse()` reads a fuse
+//! word directly, and `program_fuse()` burns bits into a fuse word.
+//!
+//! Programming can only set bits and cannot be undone, so it requires the
+//! `OtpProgrammingCapability`, and the IPG clock frequency must be given with
+//! `set_ipg_clock_frequency()` first so that the programming pulse has the
+//! correct length.
+
+use core::cell::Cell;
+use kernel::capabilities::OtpProgrammingCapability;
+use kernel::hil::device_id::DeviceId;
+use kernel::hil::device_key::{DeviceKey, DEVICE_KEY_LEN};
+use kernel::hil::mac_address::{MacAddress, MacAddressSource};
+use kernel::platform::chip::ClockInterface;
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
+use kernel::utilities::registers::{register_bitfields, register_structs, ReadWrite};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+use crate::ccm;
+
+/// Number of 32-bit fuse words.
+pub const NUM_FUSE_WORDS: usize = 64;
+
+/// Lock bits of the fuse banks.
+pub const FUSE_LOCK: usize = 0x00;
+/// Lower 32 bits of the unique ID.
+pub const FUSE_CFG0: usize = 0x01;
+/// Upper 32 bits of the unique ID.
+pub const FUSE_CFG1: usize = 0x02;
+/// Lower 32 bits of the ENET MAC address.
+pub const FUSE_MAC0: usize = 0x22;
+/// Upper 16 bits of the ENET MAC address.
+pub const FUSE_MAC1: usize = 0x23;
+/// First of the four words of the 128-bit general purpose key `SW_GP2`.
+pub const FUSE_SW_GP2: usize = 0x29;
+
+/// Value read from the shadow registers of read-locked fuses.
+const LOCKED_VALUE: u32 = 0xBADA_BADA;
+
+/// Value of `CTRL::WR_UNLOCK` required to program a fuse.
+const WR_UNLOCK_KEY: u32 = 0x3E77;
+
+/// Iterations to wait for the controller to become idle.
+const BUSY_TIMEOUT: usize = 100_000;
+
+#[repr(C)]
+struct ShadowRegister {
+    value: ReadWrite<u32>,
+    _reserved: [u32; 3],
+}
+
+register_structs! {
+    /// On-Chip OTP Controller
+    OcotpRegisters {
+        /// OTP Controller Control Register
+        (0x000 => ctrl: ReadWrite<u32, CTRL::Register>),
+        /// OTP Controller Control Register, set
+        (0x004 => ctrl_set: ReadWrite<u32, CTRL::Register>),
+        /// OTP Controller Control Register, clear
+        (0x008 => ctrl_clr: ReadWrite<u32, CTRL::Register>),
+        /// OTP Controller Control Register, toggle
+        (0x00C => ctrl_tog: ReadWrite<u32, CTRL::Register>),
+        /// OTP Controller Timing Register
+        (0x010 => timing: ReadWrite<u32, TIMING::Register>),
+        (0x014 => _reserved0),
+        /// OTP Controller Write Data Register
+        (0x020 => data: ReadWrite<u32>),
+        (0x024 => _reserved1),
+        /// OTP Controller Read Control Register
+        (0x030 => read_ctrl: ReadWrite<u32, READ_CTRL::Register>),
+        (0x034 => _reserved2),
+        /// OTP Controller Read Data Register
+        (0x040 => read_fuse_data: ReadWrite<u32>),
+        (0x044 => _reserved3),
+        /// Shadow registers of the fuse words
+        (0x400 => shadow: [ShadowRegister; NUM_FUSE_WORDS]),
+        (0x800 => @END),
+    }
+}
+
+register_bitfields![u32,
+    CTRL [
+        /// Write unlock key, must be 0x3E77 to program a fuse
+        WR_UNLOCK OFFSET(16) NUMBITS(16) [],
+        /// Reload the shadow registers from the fuses
+        RELOAD_SHADOWS OFFSET(10) NUMBITS(1) [],
+        /// Set when an access to a locked region or an access while busy
+        /// was requested
+        ERROR OFFSET(9) NUMBITS(1) [],
+        /// Set while the controller is reading or programming fuses
+        BUSY OFFSET(8) NUMBITS(1) [],
+        /// Fuse word to read or program
+        ADDR OFFSET(0) NUMBITS(6) []
+    ],
+    TIMING [
+        /// Time between deassertion of the read strobe and the next access
+        WAIT OFFSET(22) NUMBITS(6) [],
+        /// Strobe period for reading, in IPG clock cycles
+        STROBE_READ OFFSET(16) NUMBITS(6) [],
+        /// Time around the programming strobe, in IPG clock cycles
+        RELAX OFFSET(12) NUMBITS(4) [],
+        /// Strobe period for programming, in IPG clock cycles
+        STROBE_PROG OFFSET(0) NUMBITS(12) []
+    ],
+    READ_CTRL [
+        /// Start a direct read of the fuse word selected by `CTRL::ADDR`
+        READ_FUSE OFFSET(0) NUMBITS(1) []
+    ]
+];
+
+const OCOTP_BASE: StaticRef<OcotpRegisters> =
+    unsafe { StaticRef::new(0x401F4000 as *const OcotpRegisters) };
+
+/// Convert a duration in nanoseconds to IPG clock cycles, rounding up.
+fn ns_to_cycles(ipg_clock_hz: u32, ns: u32) -> u32 {
+    ((ipg_clock_hz as u64 * ns as u64 + 999_999_999) / 1_000_000_000) as u32
+}
+
+pub struct Ocotp<'a> {
+    registers: StaticRef<OcotpRegisters>,
+    clock: ccm::PeripheralClock<'a>,
+    ipg_clock_hz: Cell<Option<u32>>,
+}
+
+impl<'a> Ocotp<'a> {
+    pub const fn new(ccm: &'a ccm::Ccm) -> Self {
+        Self {
+            registers: OCOTP_BASE,
+            clock: ccm::PeripheralClock::ccgr2(ccm, ccm::HCLK2::OCOTP),
+            ipg_clock_hz: Cell::new(None),
+        }
+    }
+
+    /// Set the frequency of the IPG clock, which times fuse accesses.
+    pub fn set_ipg_clock_frequency(&self, hz: u32) {
+        self.ipg_clock_hz.set(Some(hz));
+    }
+
+    fn enable_clock(&self) {
+        if !self.clock.is_enabled() {
+            self.clock.enable();
+        }
+    }
+
+    /// Wait until the controller is idle and clear any previous error.
+    fn wait_idle(&self) -> Result<(), ErrorCode> {
+        for _ in 0..BUSY_TIMEOUT {
+            if !self.registers.ctrl.is_set(CTRL::BUSY) {
+                self.registers.ctrl_clr.write(CTRL::ERROR::SET);
+                return Ok(());
+            }
+        }
+        Err(ErrorCode::BUSY)
+    }
+
+    fn wait_done(&self) -> Result<(), ErrorCode> {
+        for _ in 0..BUSY_TIMEOUT {
+            if !self.registers.ctrl.is_set(CTRL::BUSY) {
+                return if self.registers.ctrl.is_set(CTRL::ERROR) {
+                    Err(ErrorCode::FAIL)
+                } else {
+                    Ok(())
+                };
+            }
+        }
+        Err(ErrorCode::BUSY)
+    }
+
+    /// Read the shadow copy of the fuse word `index`.
+    pub fn read_shadow(&self, index: usize) -> Result<u32, ErrorCode> {
+        self.enable_clock();
+        self.registers
+            .shadow
+            .get(index)
+            .map(|shadow| shadow.value.get())
+            .ok_or(ErrorCode::INVAL)
+    }
+
+    /// Read the fuse word `index` directly from the fuses.
+    pub fn read_fuse(&self, index: usize) -> Result<u32, ErrorCode> {
+        if index >= NUM_FUSE_WORDS {
+            return Err(ErrorCode::INVAL);
+        }
+        self.enable_clock();
+        self.wait_idle()?;
+        self.registers.ctrl.modify(CTRL::ADDR.val(index as u32));
+        self.registers.read_ctrl.write(READ_CTRL::READ_FUSE::SET);
+        self.wait_done()?;
+        Ok(self.registers.read_fuse_data.get())
+    }
+
+    /// Program the bits set in `value` into the fuse word `index`, then
+    /// reload the shadow registers.
+    ///
+    /// Returns `OFF` if the IPG clock frequency was not set, and `FAIL` if
+    /// the controller refused the access, for example because the fuse word
+    /// is locked.
+    pub fn program_fuse(
+        &self,
+        index: usize,
+        value: u32,
+        _capability: &dyn OtpProgrammingCapability,
+    ) -> Result<(), ErrorCode> {
+        if index >= NUM_FUSE_WORDS {
+            return Err(ErrorCode::INVAL);
+        }
+        let ipg_clock_hz = self.ipg_clock_hz.get().ok_or(ErrorCode::OFF)?;
+        self.enable_clock();
+        self.wait_idle()?;
+
+        // 10 us programming pulse, 20 ns relax time and 40 ns read strobe.
+        let relax = ns_to_cycles(ipg_clock_hz, 20).saturating_sub(1);
+        let strobe_prog = ns_to_cycles(ipg_clock_hz, 10_000) + 2 * (relax + 1) - 1;
+        let strobe_read = ns_to_cycles(ipg_clock_hz, 40) + 2 * (relax + 1) - 1;
+        self.registers.timing.modify(
+            TIMING::RELAX.val(relax)
+                + TIMING::STROBE_PROG.val(strobe_prog)
+                + TIMING::STROBE_READ.val(strobe_read),
+        );
+
+        self.registers
+            .ctrl
+            .modify(CTRL::WR_UNLOCK.val(WR_UNLOCK_KEY) + CTRL::ADDR.val(index as u32));
+        // Writing the data register starts programming.
+        self.registers.data.set(value);
+        let result = self.wait_done();
+
+        self.reload_shadows()?;
+        result
+    }
+
+    /// Copy the fuses into the shadow registers again.
+    fn reload_shadows(&self) -> Result<(), ErrorCode> {
+        self.wait_idle()?;
+        self.registers.ctrl_set.write(CTRL::RELOAD_SHADOWS::SET);
+        for _ in 0..BUSY_TIMEOUT {
+            if !self.registers.ctrl.is_set(CTRL::RELOAD_SHADOWS) {
+                return self.wait_done();
+            }
+        }
+        Err(ErrorCode::BUSY)
+    }
+
+    /// The 64-bit unique ID of the chip.
+    pub fn unique_id(&self) -> u64 {
+        let low = self.read_shadow(FUSE_CFG0).unwrap_or(0);
+        let high = self.read_shadow(FUSE_CFG1).unwrap_or(0);
+        (high as u64) << 32 | low as u64
+    }
+}
+
+/// The ENET MAC address fuses. `MAC1` holds the two most significant bytes.
+impl MacAddressSource for Ocotp<'_> {
+    fn mac_address(&self) -> Option<MacAddress> {
+        let mac0 = self.read_shadow(FUSE_MAC0).unwrap_or(0).to_be_bytes();
+        let mac1 = self.read_shadow(FUSE_MAC1).unwrap_or(0).to_be_bytes();
+        let mac = MacAddress::new([mac1[2], mac1[3], mac0[0], mac0[1], mac0[2], mac0[3]]);
+        // Unprogrammed fuses read as zero.
+        Some(mac).filter(|mac| !mac.is_zero())
+    }
+}
+
+impl DeviceId for Ocotp<'_> {
+    fn id_len(&self) -> usize {
+        8
+    }
+
+    fn read_id(&self, buf: &mut [u8]) -> Result<usize, ErrorCode> {
+        let id = buf.get_mut(..8).ok_or(ErrorCode::SIZE)?;
+        id.copy_from_slice(&self.unique_id().to_le_bytes());
+        Ok(8)
+    }
+}
+
+/// The `SW_GP2` fuses, programmed with a key by the manufacturer of the
+/// board. Once `SW_GP2` is read-locked, the key can no longer be read.
+impl DeviceKey for Ocotp<'_> {
+    fn read_key(&self, key: &mut [u8; DEVICE_KEY_LEN]) -> Result<(), ErrorCode> {
+        let mut programmed = false;
+        for (i, chunk) in key.chunks_mut(4).enumerate() {
+            let word = self.read_shadow(FUSE_SW_GP2 + i)?;
+            if word == LOCKED_VALUE {
+                return Err(ErrorCode::RESERVE);
+            }
+            programmed |= word != 0;
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        if programmed {
+            Ok(())
+        } else {
+            Err(ErrorCode::NODEVICE)
+        }
+    }
+}
@@ -0,0 +1,443 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Quad Timer (QTMR) input capture.
+//!
+//! Each of the four QTMR modules has four 16-bit channels, and each channel
+//! can capture the counter value on the edges of its input pin. This driver
+//! uses a channel to measure the period and the high time of an external
+//! signal through the `hil::pwm_capture` interface.
+//!
+//! The counter runs from the IPG clock divided by 8. Counter overflows are
+//! counted in software, so signals slower than one counter wrap can also be
+//! measured. A measurement captures a rising edge, the following falling edge
+//! and the next rising edge. The capture mode is switched in the interrupt
+//! handler after every edge, so the high and low phases of the signal must
+//! each last longer than the interrupt latency.
+//!
+//! The input pins must be routed to the QTMR through the IOMUXC by the board.
+
+use core::cell::Cell;
+
+use kernel::hil;
+use kernel::platform::chip::ClockInterface;
+use kernel::utilities::cells::OptionalCell;
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
+use kernel::utilities::registers::{register_bitfields, ReadWrite};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+use crate::ccm;
+
+/// Registers of one QTMR channel
+#[repr(C)]
+struct QtmrChannelRegisters {
+    /// Timer Channel Compare Register 1
+    comp1: ReadWrite<u16>,
+    /// Timer Channel Compare Register 2
+    comp2: ReadWrite<u16>,
+    /// Timer Channel Capture Register
+    capt: ReadWrite<u16>,
+    /// Timer Channel Load Register
+    load: ReadWrite<u16>,
+    /// Timer Channel Hold Register
+    hold: ReadWrite<u16>,
+    /// Timer Channel Counter Register
+    cntr: ReadWrite<u16>,
+    /// Timer Channel Control Register
+    ctrl: ReadWrite<u16, CTRL::Register>,
+    /// Timer Channel Status and Control Register
+    sctrl: ReadWrite<u16, SCTRL::Register>,
+    /// Timer Channel Comparator Load Register 1
+    cmpld1: ReadWrite<u16>,
+    /// Timer Channel Comparator Load Register 2
+    cmpld2: ReadWrite<u16>,
+    /// Timer Channel Comparator Status and Control Register
+    csctrl: ReadWrite<u16>,
+    /// Timer Channel Input Filter Register
+    filt: ReadWrite<u16, FILT::Register>,
+    /// Timer Channel DMA Enable Register
+    dma: ReadWrite<u16>,
+    _reserved0: [u16; 2],
+    /// Timer Channel Enable Register (only implemented in channel 0)
+    enbl: ReadWrite<u16, ENBL::Register>,
+}
+
+/// Quad timer
+#[repr(C)]
+struct QtmrRegisters {
+    channels: [QtmrChannelRegisters; NUMBER_CHANNELS],
+}
+
+register_bitfields![u16,
+    CTRL [
+        /// Count Mode
+        CM OFFSET(13) NUMBITS(3) [
+            /// No operation
+            NoOperation = 0,
+            /// Count rising edges of primary source
+            RisingEdges = 1
+        ],
+        /// Primary Count Source
+        PCS OFFSET(9) NUMBITS(4) [
+            /// IP bus clock divide by 1 prescaler
+            IpBusDiv1 = 8,
+            /// IP bus clock divide by 8 prescaler
+            IpBusDiv8 = 11,
+            /// IP bus clock divide by 128 prescaler
+            IpBusDiv128 = 15
+        ],
+        /// Secondary Count Source
+        SCS OFFSET(7) NUMBITS(2) [],
+        /// Count Once
+        ONCE OFFSET(6) NUMBITS(1) [],
+        /// Count Length
+        LENGTH OFFSET(5) NUMBITS(1) [],
+        /// Count Direction
+        DIR OFFSET(4) NUMBITS(1) [],
+        /// Co-Channel Initialization
+        COINIT OFFSET(3) NUMBITS(1) [],
+        /// Output Mode
+        OUTMODE OFFSET(0) NUMBITS(3) []
+    ],
+
+    SCTRL [
+        /// Timer Compare Flag
+        TCF OFFSET(15) NUMBITS(1) [],
+        /// Timer Compare Flag Interrupt Enable
+        TCFIE OFFSET(14) NUMBITS(1) [],
+        /// Timer Overflow Flag
+        TOF OFFSET(13) NUMBITS(1) [],
+        /// Timer Overflow Flag Interrupt Enable
+        TOFIE OFFSET(12) NUMBITS(1) [],
+        /// Input Edge Flag
+        IEF OFFSET(11) NUMBITS(1) [],
+        /// Input Edge Flag Interrupt Enable
+        IEFIE OFFSET(10) NUMBITS(1) [],
+        /// Input Polarity Select
+        IPS OFFSET(9) NUMBITS(1) [],
+        /// External Input Signal
+        INPUT OFFSET(8) NUMBITS(1) [],
+        /// Input Capture Mode
+        CAPTURE_MODE OFFSET(6) NUMBITS(2) [
+            /// Capture function is disabled
+            Disabled = 0,
+            /// Load capture register on rising edge of the secondary input
+            RisingEdge = 1,
+            /// Load capture register on falling edge of the secondary input
+            FallingEdge = 2,
+            /// Load capture register on both edges of the secondary input
+            BothEdges = 3
+        ],
+        /// Master Mode
+        MSTR OFFSET(5) NUMBITS(1) [],
+        /// Enable External OFLAG Force
+        EEOF OFFSET(4) NUMBITS(1) [],
+        /// Forced OFLAG Value
+        VAL OFFSET(3) NUMBITS(1) [],
+        /// Force OFLAG Output
+        FORCE OFFSET(2) NUMBITS(1) [],
+        /// Output Polarity Select
+        OPS OFFSET(1) NUMBITS(1) [],
+        /// Output Enable
+        OEN OFFSET(0) NUMBITS(1) []
+    ],
+
+    FILT [
+        /// Input Filter Sample Count
+        FILT_CNT OFFSET(8) NUMBITS(3) [],
+        /// Input Filter Sample Period
+        FILT_PER OFFSET(0) NUMBITS(8) []
+    ],
+
+    ENBL [
+        /// Timer Channel Enable
+        ENBL OFFSET(0) NUMBITS(4) []
+    ]
+];
+
+const QTMR1_BASE: StaticRef<QtmrRegisters> =
+    unsafe { StaticRef::new(0x401DC000 as *const QtmrRegisters) };
+const QTMR2_BASE: StaticRef<QtmrRegisters> =
+    unsafe { StaticRef::new(0x401E0000 as *const QtmrRegisters) };
+const QTMR3_BASE: StaticRef<QtmrRegisters> =
+    unsafe { StaticRef::new(0x401E4000 as *const QtmrRegisters) };
+const QTMR4_BASE: StaticRef<QtmrRegisters> =
+    unsafe { StaticRef::new(0x401E8000 as *const QtmrRegisters) };
+
+const NUMBER_CHANNELS: usize = 4;
+
+/// Assumed IPG clock frequency for the iMXRT1050 processor family.
+///
+/// TODO this is not a constant value; it changes when setting the ARM clock
+/// frequency. Change this after correctly configuring ARM frequency.
+const IMXRT1050_IPG_CLOCK_HZ: u64 = 24_750_000;
+/// The counter is clocked with `CTRL::PCS::IpBusDiv8`
+const COUNTER_HZ: u64 = IMXRT1050_IPG_CLOCK_HZ / 8;
+
+#[derive(Clone, Copy)]
+enum CapturePhase {
+    Idle,
+    /// Waiting for the first rising edge
+    Rising,
+    /// Waiting for the falling edge following the rising edge at `rise`
+    Falling {
+        rise: u64,
+    },
+    /// Waiting for the rising edge that ends the period
+    Period {
+        rise: u64,
+        fall: u64,
+    },
+}
+
+/// One channel of a quad timer
+pub struct QtmrChannel<'a> {
+    registers: StaticRef<QtmrRegisters>,
+    channel: usize,
+    clock: QtmrClock<'a>,
+    phase: Cell<CapturePhase>,
+    /// Number of counter overflows since the capture started
+    overflows: Cell<u64>,
+    client: OptionalCell<&'a dyn hil::pwm_capture::PwmCaptureClient>,
+}
+
+impl<'a> QtmrChannel<'a> {
+    const fn new(
+        registers: StaticRef<QtmrRegisters>,
+        channel: usize,
+        clock_gate: ccm::PeripheralClock<'a>,
+    ) -> Self {
+        Self {
+            registers,
+            channel,
+            clock: QtmrClock(clock_gate),
+            phase: Cell::new(CapturePhase::Idle),
+            overflows: Cell::new(0),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    fn channel_registers(&self) -> &QtmrChannelRegisters {
+        &self.registers.channels[self.channel]
+    }
+
+    fn set_channel_enabled(&self, enabled: bool) {
+        let mask = 1 << self.channel;
+        let enbl = &self.registers.channels[0].enbl;
+        let value = enbl.read(ENBL::ENBL);
+        if enabled {
+            enbl.modify(ENBL::ENBL.val(value | mask));
+        } else {
+            enbl.modify(ENBL::ENBL.val(value & !mask));
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        !matches!(self.phase.get(), CapturePhase::Idle)
+    }
+
+    fn set_capture_edge(&self, rising: bool) {
+        let registers = self.channel_registers();
+        if rising {
+            registers
+                .sctrl
+                .modify(SCTRL::CAPTURE_MODE::RisingEdge + SCTRL::IEF::CLEAR);
+        } else {
+            registers
+                .sctrl
+                .modify(SCTRL::CAPTURE_MODE::FallingEdge + SCTRL::IEF::CLEAR);
+        }
+    }
+
+    fn ticks_to_ns(ticks: u64) -> u64 {
+        ticks * 1_000_000_000 / COUNTER_HZ
+    }
+
+    fn finish_capture(&self, result: Result<(u32, u32), ErrorCode>) {
+        self.stop();
+        self.client.map(|client| match result {
+            Ok((period_ns, high_ns)) => client.capture_done(Ok(()), period_ns, high_ns),
+            Err(error) => client.capture_done(Err(error), 0, 0),
+        });
+    }
+
+    fn stop(&self) {
+        let registers = self.channel_registers();
+        self.set_channel_enabled(false);
+        registers.ctrl.modify(CTRL::CM::NoOperation);
+        registers.sctrl.modify(
+            SCTRL::IEFIE::CLEAR
+                + SCTRL::TOFIE::CLEAR
+                + SCTRL::CAPTURE_MODE::Disabled
+                + SCTRL::IEF::CLEAR
+                + SCTRL::TOF::CLEAR,
+        );
+        self.phase.set(CapturePhase::Idle);
+    }
+
+    fn handle_edge(&self, timestamp: u64) {
+        match self.phase.get() {
+            CapturePhase::Idle => {}
+            CapturePhase::Rising => {
+                self.set_capture_edge(false);
+                self.phase.set(CapturePhase::Falling { rise: timestamp });
+            }
+            CapturePhase::Falling { rise } => {
+                self.set_capture_edge(true);
+                self.phase.set(CapturePhase::Period {
+                    rise,
+                    fall: timestamp,
+                });
+            }
+            CapturePhase::Period { rise, fall } => {
+                let period_ns = Self::ticks_to_ns(timestamp - rise);
+                let high_ns = Self::ticks_to_ns(fall - rise);
+                if period_ns == 0 || period_ns > u32::MAX as u64 {
+                    self.finish_capture(Err(ErrorCode::SIZE));
+                } else {
+                    self.finish_capture(Ok((period_ns as u32, high_ns as u32)));
+                }
+            }
+        }
+    }
+
+    fn handle_interrupt(&self) {
+        if !self.is_active() {
+            return;
+        }
+        let registers = self.channel_registers();
+        let sctrl = registers.sctrl.extract();
+        let overflows = self.overflows.get();
+        let overflowed = sctrl.is_set(SCTRL::TOF);
+        if overflowed {
+            registers.sctrl.modify(SCTRL::TOF::CLEAR);
+            self.overflows.set(overflows + 1);
+        }
+
+        if sctrl.is_set(SCTRL::IEF) {
+            let capture = registers.capt.get();
+            // If the counter overflowed while this interrupt was pending, a
+            // small capture value means the edge came after the overflow.
+            let edge_overflows = if overflowed && capture < 0x8000 {
+                overflows + 1
+            } else {
+                overflows
+            };
+            registers.sctrl.modify(SCTRL::IEF::CLEAR);
+            self.handle_edge((edge_overflows << 16) | capture as u64);
+        }
+    }
+}
+
+impl<'a> hil::pwm_capture::PwmCapture<'a> for QtmrChannel<'a> {
+    fn set_client(&self, client: &'a dyn hil::pwm_capture::PwmCaptureClient) {
+        self.client.set(client);
+    }
+
+    fn start_capture(&self) -> Result<(), ErrorCode> {
+        if self.is_active() {
+            return Err(ErrorCode::BUSY);
+        }
+        if !self.clock.is_enabled() {
+            self.clock.enable();
+        }
+
+        let registers = self.channel_registers();
+        self.set_channel_enabled(false);
+        // Count up the prescaled IPG clock, roll over at 0xFFFF and capture
+        // on the input pin of this channel.
+        registers.ctrl.write(
+            CTRL::CM::NoOperation + CTRL::PCS::IpBusDiv8 + CTRL::SCS.val(self.channel as u16),
+        );
+        registers.load.set(0);
+        registers.cntr.set(0);
+        registers.comp1.set(0xFFFF);
+        registers.filt.set(0);
+        registers.dma.set(0);
+        registers.sctrl.write(SCTRL::IEF::CLEAR + SCTRL::TOF::CLEAR);
+
+        self.overflows.set(0);
+        self.phase.set(CapturePhase::Rising);
+        self.set_capture_edge(true);
+        registers
+            .sctrl
+            .modify(SCTRL::IEFIE::SET + SCTRL::TOFIE::SET);
+        registers.ctrl.modify(CTRL::CM::RisingEdges);
+        self.set_channel_enabled(true);
+        Ok(())
+    }
+
+    fn stop_capture(&self) -> Result<(), ErrorCode> {
+        self.stop();
+        Ok(())
+    }
+}
+
+/// A quad timer module
+pub struct Qtmr<'a> {
+    pub channels: [QtmrChannel<'a>; NUMBER_CHANNELS],
+}
+
+impl<'a> Qtmr<'a> {
+    pub fn new_qtmr1(ccm: &'a ccm::Ccm) -> Self {
+        Qtmr::new(QTMR1_BASE, || {
+            ccm::PeripheralClock::ccgr6(ccm, ccm::HCLK6::QTIMER1)
+        })
+    }
+
+    pub fn new_qtmr2(ccm: &'a ccm::Ccm) -> Self {
+        Qtmr::new(QTMR2_BASE, || {
+            ccm::PeripheralClock::ccgr6(ccm, ccm::HCLK6::QTIMER2)
+        })
+    }
+
+    pub fn new_qtmr3(ccm: &'a ccm::Ccm) -> Self {
+        Qtmr::new(QTMR3_BASE, || {
+            ccm::PeripheralClock::ccgr6(ccm, ccm::HCLK6::QTIMER3)
+        })
+    }
+
+    pub fn new_qtmr4(ccm: &'a ccm::Ccm) -> Self {
+        Qtmr::new(QTMR4_BASE, || {
+            ccm::PeripheralClock::ccgr6(ccm, ccm::HCLK6::QTIMER4)
+        })
+    }
+
+    fn new(
+        registers: StaticRef<QtmrRegisters>,
+        clock_gate: impl Fn() -> ccm::PeripheralClock<'a>,
+    ) -> Self {
+        Self {
+            channels: [
+                QtmrChannel::new(registers, 0, clock_gate()),
+                QtmrChannel::new(registers, 1, clock_gate()),
+                QtmrChannel::new(registers, 2, clock_gate()),
+                QtmrChannel::new(registers, 3, clock_gate()),
+            ],
+        }
+    }
+
+    pub fn handle_interrupt(&self) {
+        for channel in self.channels.iter() {
+            channel.handle_interrupt();
+        }
+    }
+}
+
+struct QtmrClock<'a>(ccm::PeripheralClock<'a>);
+
+impl ClockInterface for QtmrClock<'_> {
+    fn is_enabled(&self) -> bool {
+        self.0.is_enabled()
+    }
+
+    fn enable(&self) {
+        self.0.enable();
+    }
+
+    fn disable(&self) {
+        self.0.disable();
+    }
+}
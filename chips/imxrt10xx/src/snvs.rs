@@ -0,0 +1,64 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Secure Non-Volatile Storage (SNVS).
+//!
+//! Only the low-power general purpose registers are supported for now. They
+//! are in the SNVS low-power domain, so they keep their contents across
+//! resets and, with a coin cell on VDD_SNVS_IN, across loss of main power.
+
+use kernel::hil::backup_registers::BackupRegisters;
+use kernel::utilities::registers::interfaces::{Readable, Writeable};
+use kernel::utilities::registers::{register_structs, ReadWrite};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+const NUM_BACKUP_REGISTERS: usize = 4;
+
+register_structs! {
+    /// Secure Non-Volatile Storage
+    SnvsRegisters {
+        (0x000 => _reserved0),
+        /// SNVS_LP General Purpose Registers 0 - 3
+        (0x100 => lpgpr: [ReadWrite<u32>; NUM_BACKUP_REGISTERS]),
+        (0x110 => @END),
+    }
+}
+
+const SNVS_BASE: StaticRef<SnvsRegisters> =
+    unsafe { StaticRef::new(0x400D4000 as *const SnvsRegisters) };
+
+pub struct Snvs {
+    registers: StaticRef<SnvsRegisters>,
+}
+
+impl Snvs {
+    pub const fn new() -> Self {
+        Self {
+            registers: SNVS_BASE,
+        }
+    }
+}
+
+impl BackupRegisters for Snvs {
+    fn num_registers(&self) -> usize {
+        NUM_BACKUP_REGISTERS
+    }
+
+    fn read(&self, index: usize) -> Result<u32, ErrorCode> {
+        self.registers
+            .lpgpr
+            .get(index)
+            .map(|register| register.get())
+            .ok_or(ErrorCode::INVAL)
+    }
+
+    fn write(&self, index: usize, value: u32) -> Result<(), ErrorCode> {
+        self.registers
+            .lpgpr
+            .get(index)
+            .map(|register| register.set(value))
+            .ok_or(ErrorCode::INVAL)
+    }
+}
@@ -0,0 +1,147 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! System Reset Controller (SRC).
+//!
+//! Reports the cause of the last reset, and resets the chip through
+//! `hil::reset::Reset`. To reset into the ROM serial downloader, the boot
+//! mode is written to the general purpose registers the boot ROM reads after
+//! a warm reset, which keep their value across the reset. The ROM then waits
+//! for an image over UART or USB HID instead of booting from flash, until
+//! the chip is power cycled.
+
+use core::cell::Cell;
+use kernel::capabilities::ResetCapability;
+use kernel::hil::reset::Reset;
+use kernel::hil::reset_reason::{ResetReason, ResetReasonQuery};
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
+use kernel::utilities::registers::{register_bitfields, register_structs, ReadOnly, ReadWrite};
+use kernel::utilities::StaticRef;
+
+register_structs! {
+    /// System Reset Controller
+    SrcRegisters {
+        /// SRC Control Register
+        (0x000 => scr: ReadWrite<u32>),
+        /// SRC Boot Mode Register 1
+        (0x004 => sbmr1: ReadOnly<u32>),
+        /// SRC Reset Status Register
+        (0x008 => srsr: ReadWrite<u32, SRSR::Register>),
+        (0x00C => _reserved0),
+        /// SRC Boot Mode Register 2
+        (0x01C => sbmr2: ReadOnly<u32>),
+        /// SRC General Purpose Registers 1 - 8
+        (0x020 => gpr: [ReadWrite<u32>; 8]),
+        /// SRC General Purpose Register 9, boot mode read by the ROM
+        (0x040 => gpr9: ReadWrite<u32, GPR9::Register>),
+        /// SRC General Purpose Register 10
+        (0x044 => gpr10: ReadWrite<u32, GPR10::Register>),
+        (0x048 => @END),
+    }
+}
+
+register_bitfields![u32,
+    SRSR [
+        /// Temperature sensor software reset
+        TEMPSENSE_RST_B OFFSET(8) NUMBITS(1) [],
+        /// WDOG3 timeout reset
+        WDOG3_RST_B OFFSET(7) NUMBITS(1) [],
+        /// JTAG software reset
+        JTAG_SW_RST OFFSET(6) NUMBITS(1) [],
+        /// JTAG HIGH-Z reset
+        JTAG_RST_B OFFSET(5) NUMBITS(1) [],
+        /// WDOG1 or WDOG2 timeout reset
+        WDOG_RST_B OFFSET(4) NUMBITS(1) [],
+        /// POR_B pin reset
+        IPP_USER_RESET_B OFFSET(3) NUMBITS(1) [],
+        /// CSU reset
+        CSU_RESET_B OFFSET(2) NUMBITS(1) [],
+        /// Core lockup or software reset (SYSRESETREQ)
+        LOCKUP_SYSRESETREQ OFFSET(1) NUMBITS(1) [],
+        /// Power-on reset
+        IPP_RESET_B OFFSET(0) NUMBITS(1) []
+    ],
+    GPR9 [
+        /// Boot mode used by the ROM when `GPR10::PERSIST_BOOT_MODE` is set
+        BOOT_MODE OFFSET(24) NUMBITS(2) [
+            BootFromFuses = 0,
+            SerialDownloader = 1,
+            InternalBoot = 2
+        ]
+    ],
+    GPR10 [
+        /// Boot with `GPR9::BOOT_MODE` instead of the boot mode pins and fuses
+        /// after a warm reset
+        PERSIST_BOOT_MODE OFFSET(28) NUMBITS(1) []
+    ]
+];
+
+const SRC_BASE: StaticRef<SrcRegisters> =
+    unsafe { StaticRef::new(0x400F8000 as *const SrcRegisters) };
+
+pub struct Src {
+    registers: StaticRef<SrcRegisters>,
+    reset_reason: Cell<Option<ResetReason>>,
+}
+
+impl Src {
+    pub const fn new() -> Self {
+        Self {
+            registers: SRC_BASE,
+            reset_reason: Cell::new(None),
+        }
+    }
+}
+
+impl Reset for Src {
+    fn reset(&self, _capability: &dyn ResetCapability) -> ! {
+        // Boot from flash, even if a previous reset asked for the
+        // bootloader.
+        self.registers.gpr10.modify(GPR10::PERSIST_BOOT_MODE::CLEAR);
+        unsafe {
+            cortexm7::scb::reset();
+        }
+        loop {
+            cortexm7::support::nop();
+        }
+    }
+
+    fn reset_to_bootloader(&self, _capability: &dyn ResetCapability) -> ! {
+        self.registers
+            .gpr9
+            .modify(GPR9::BOOT_MODE::SerialDownloader);
+        self.registers.gpr10.modify(GPR10::PERSIST_BOOT_MODE::SET);
+        unsafe {
+            cortexm7::scb::reset();
+        }
+        loop {
+            cortexm7::support::nop();
+        }
+    }
+}
+
+impl ResetReasonQuery for Src {
+    /// The reset status flags are sticky until a power-on reset, so they are
+    /// read and cleared once, and the result is kept for later queries.
+    fn reset_reason(&self) -> ResetReason {
+        self.reset_reason.get().unwrap_or_else(|| {
+            let srsr = self.registers.srsr.extract();
+            let reason = if srsr.is_set(SRSR::WDOG_RST_B) || srsr.is_set(SRSR::WDOG3_RST_B) {
+                ResetReason::Watchdog
+            } else if srsr.is_set(SRSR::LOCKUP_SYSRESETREQ) || srsr.is_set(SRSR::JTAG_SW_RST) {
+                ResetReason::Software
+            } else if srsr.is_set(SRSR::IPP_USER_RESET_B) {
+                ResetReason::ExternalPin
+            } else if srsr.is_set(SRSR::IPP_RESET_B) {
+                ResetReason::PowerOn
+            } else {
+                ResetReason::Unknown
+            };
+            // The flags are write-one-to-clear.
+            self.registers.srsr.set(srsr.get());
+            self.reset_reason.set(Some(reason));
+            reason
+        })
+    }
+}
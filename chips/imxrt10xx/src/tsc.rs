@@ -0,0 +1,315 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Touch Screen Controller (TSC_DIG).
+//!
+//! The TSC drives the layers of a 4-wire or 5-wire resistive panel and has
+//! ADC2 convert the position, so a resistive panel needs no external
+//! controller. It implements the `hil::touch::Touch` interface.
+//!
+//! The controller runs in auto-measure mode: it senses the panel until it
+//! is pressed, waits for the measure delay, and then measures X and Y. Each
+//! measurement ends with another detection, which marks the measurement as
+//! valid if the panel is still pressed. While the panel stays pressed, the
+//! driver starts a new measurement as soon as the previous one completes,
+//! and reports the first position as `Pressed`, the next ones as `Moved`,
+//! and `Released` when a measurement is no longer valid.
+//!
+//! The coordinates are the 12-bit readings of ADC2. Use a `TouchCalibration`
+//! to map them to the screen.
+//!
+//! ADC2 must be given to the TSC before enabling it: the TSC triggers the
+//! conversions of ADC2 directly, and the ADC_ETC must not bypass it.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! peripherals.adc_etc.set_tsc_bypass(false);
+//! peripherals
+//!     .adc2
+//!     .set_touch_screen_trigger(adc::Channel::In3, adc::Channel::In1)?;
+//! peripherals
+//!     .tsc
+//!     .configure(tsc::Wires::Four, tsc::DEFAULT_PRE_CHARGE_TIME, tsc::DEFAULT_MEASURE_DELAY);
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::touch::{self, TouchEvent, TouchStatus};
+use kernel::platform::chip::ClockInterface;
+use kernel::utilities::cells::OptionalCell;
+use kernel::utilities::registers::interfaces::{Readable, Writeable};
+use kernel::utilities::registers::{register_bitfields, register_structs, ReadOnly, ReadWrite};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+use crate::ccm;
+
+register_structs! {
+    /// Touch Screen Controller
+    TscRegisters {
+        /// Basic setting
+        (0x00 => basic_setting: ReadWrite<u32, BASIC_SETTING::Register>),
+        (0x04 => _reserved0),
+        /// Pre-charge time
+        (0x10 => pre_charge_time: ReadWrite<u32>),
+        (0x14 => _reserved1),
+        /// Flow control
+        (0x20 => flow_control: ReadWrite<u32, FLOW_CONTROL::Register>),
+        (0x24 => _reserved2),
+        /// Measure value
+        (0x30 => measure_value: ReadOnly<u32, MEASURE_VALUE::Register>),
+        (0x34 => _reserved3),
+        /// Interrupt enable
+        (0x40 => int_en: ReadWrite<u32, INT_EN::Register>),
+        (0x44 => _reserved4),
+        /// Interrupt signal enable
+        (0x50 => int_sig_en: ReadWrite<u32, INT_SIG_EN::Register>),
+        (0x54 => _reserved5),
+        /// Interrupt status
+        (0x60 => int_status: ReadWrite<u32, INT_STATUS::Register>),
+        (0x64 => @END),
+    }
+}
+
+register_bitfields![u32,
+    BASIC_SETTING [
+        /// Measure delay time, in cycles of the TSC clock
+        MEASURE_DELAY_TIME OFFSET(8) NUMBITS(24) [],
+        /// 4-wire or 5-wire panel
+        WIRES OFFSET(4) NUMBITS(1) [
+            Four = 0,
+            Five = 1
+        ],
+        /// Measure automatically after a touch is detected
+        AUTO_MEASURE OFFSET(0) NUMBITS(1) []
+    ],
+    FLOW_CONTROL [
+        /// Disable the controller
+        DISABLE OFFSET(16) NUMBITS(1) [],
+        /// Start sensing for a touch
+        START_SENSE OFFSET(12) NUMBITS(1) [],
+        /// Drop the measurement in progress
+        DROP_MEASURE OFFSET(8) NUMBITS(1) [],
+        /// Start a measurement
+        START_MEASURE OFFSET(4) NUMBITS(1) [],
+        /// Soft reset
+        SW_RST OFFSET(0) NUMBITS(1) []
+    ],
+    MEASURE_VALUE [
+        /// X coordinate
+        X_VALUE OFFSET(16) NUMBITS(12) [],
+        /// Y coordinate
+        Y_VALUE OFFSET(0) NUMBITS(12) []
+    ],
+    INT_EN [
+        /// Idle software interrupt enable
+        IDLE_SW_INT_EN OFFSET(12) NUMBITS(1) [],
+        /// Detect interrupt enable
+        DETECT_INT_EN OFFSET(4) NUMBITS(1) [],
+        /// Measure interrupt enable
+        MEASURE_INT_EN OFFSET(0) NUMBITS(1) []
+    ],
+    INT_SIG_EN [
+        /// Idle software signal enable
+        IDLE_SW_SIG_EN OFFSET(12) NUMBITS(1) [],
+        /// Valid signal enable
+        VALID_SIG_EN OFFSET(8) NUMBITS(1) [],
+        /// Detect signal enable
+        DETECT_SIG_EN OFFSET(4) NUMBITS(1) [],
+        /// Measure signal enable
+        MEASURE_SIG_EN OFFSET(0) NUMBITS(1) []
+    ],
+    INT_STATUS [
+        /// Idle software flag
+        IDLE_SW OFFSET(12) NUMBITS(1) [],
+        /// The panel was still pressed at the end of the measurement
+        VALID OFFSET(8) NUMBITS(1) [],
+        /// A touch was detected
+        DETECT OFFSET(4) NUMBITS(1) [],
+        /// A measurement completed
+        MEASURE OFFSET(0) NUMBITS(1) []
+    ]
+];
+
+const TSC_BASE: StaticRef<TscRegisters> =
+    unsafe { StaticRef::new(0x400E_0000 as *const TscRegisters) };
+
+/// Pre-charge time used by NXP's examples, in cycles of the TSC clock.
+pub const DEFAULT_PRE_CHARGE_TIME: u32 = 0xFFFF;
+
+/// Time between the detection of a touch and its first measurement, in
+/// cycles of the TSC clock.
+pub const DEFAULT_MEASURE_DELAY: u32 = 0xFFFF;
+
+const MAX_MEASURE_DELAY: u32 = (1 << 24) - 1;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Wires {
+    Four,
+    Five,
+}
+
+pub struct Tsc<'a> {
+    registers: StaticRef<TscRegisters>,
+    clock: TscClock<'a>,
+    wires: Cell<Wires>,
+    pre_charge_time: Cell<u32>,
+    measure_delay: Cell<u32>,
+    enabled: Cell<bool>,
+    pressed: Cell<bool>,
+    x: Cell<u16>,
+    y: Cell<u16>,
+    client: OptionalCell<&'a dyn touch::TouchClient>,
+}
+
+impl<'a> Tsc<'a> {
+    pub const fn new(ccm: &'a ccm::Ccm) -> Self {
+        Self {
+            registers: TSC_BASE,
+            clock: TscClock(ccm::PeripheralClock::ccgr4(ccm, ccm::HCLK4::TSC)),
+            wires: Cell::new(Wires::Four),
+            pre_charge_time: Cell::new(DEFAULT_PRE_CHARGE_TIME),
+            measure_delay: Cell::new(DEFAULT_MEASURE_DELAY),
+            enabled: Cell::new(false),
+            pressed: Cell::new(false),
+            x: Cell::new(0),
+            y: Cell::new(0),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Set the type of panel and the timings, in cycles of the TSC clock.
+    /// They are applied the next time the controller is enabled.
+    pub fn configure(
+        &self,
+        wires: Wires,
+        pre_charge_time: u32,
+        measure_delay: u32,
+    ) -> Result<(), ErrorCode> {
+        if measure_delay > MAX_MEASURE_DELAY {
+            return Err(ErrorCode::INVAL);
+        }
+        self.wires.set(wires);
+        self.pre_charge_time.set(pre_charge_time);
+        self.measure_delay.set(measure_delay);
+        Ok(())
+    }
+
+    pub fn handle_interrupt(&self) {
+        let status = self.registers.int_status.extract();
+        // The flags are cleared by writing ones.
+        self.registers.int_status.set(status.get());
+
+        if !self.enabled.get() || !status.is_set(INT_STATUS::MEASURE) {
+            return;
+        }
+
+        if status.is_set(INT_STATUS::VALID) {
+            let value = self.registers.measure_value.extract();
+            self.x.set(value.read(MEASURE_VALUE::X_VALUE) as u16);
+            self.y.set(value.read(MEASURE_VALUE::Y_VALUE) as u16);
+            let event = if self.pressed.replace(true) {
+                TouchStatus::Moved
+            } else {
+                TouchStatus::Pressed
+            };
+            // Follow the touch until a measurement finds the panel released.
+            self.registers
+                .flow_control
+                .write(FLOW_CONTROL::START_MEASURE::SET);
+            self.report(event);
+        } else {
+            self.registers
+                .flow_control
+                .write(FLOW_CONTROL::START_SENSE::SET);
+            if self.pressed.replace(false) {
+                self.report(TouchStatus::Released);
+            }
+        }
+    }
+
+    fn report(&self, status: TouchStatus) {
+        self.client.map(|client| {
+            client.touch_event(TouchEvent {
+                status,
+                x: self.x.get(),
+                y: self.y.get(),
+                id: 0,
+                size: None,
+                pressure: None,
+            })
+        });
+    }
+}
+
+struct TscClock<'a>(ccm::PeripheralClock<'a>);
+
+impl ClockInterface for TscClock<'_> {
+    fn is_enabled(&self) -> bool {
+        self.0.is_enabled()
+    }
+
+    fn enable(&self) {
+        self.0.enable();
+    }
+
+    fn disable(&self) {
+        self.0.disable();
+    }
+}
+
+impl<'a> touch::Touch<'a> for Tsc<'a> {
+    fn enable(&self) -> Result<(), ErrorCode> {
+        if self.enabled.get() {
+            return Ok(());
+        }
+        self.clock.enable();
+
+        let registers = &self.registers;
+        registers.flow_control.write(FLOW_CONTROL::SW_RST::SET);
+        let wires = match self.wires.get() {
+            Wires::Four => BASIC_SETTING::WIRES::Four,
+            Wires::Five => BASIC_SETTING::WIRES::Five,
+        };
+        registers.basic_setting.write(
+            BASIC_SETTING::MEASURE_DELAY_TIME.val(self.measure_delay.get())
+                + wires
+                + BASIC_SETTING::AUTO_MEASURE::SET,
+        );
+        registers.pre_charge_time.set(self.pre_charge_time.get());
+        registers.int_status.set(u32::MAX);
+        registers.int_sig_en.write(
+            INT_SIG_EN::MEASURE_SIG_EN::SET
+                + INT_SIG_EN::DETECT_SIG_EN::SET
+                + INT_SIG_EN::VALID_SIG_EN::SET,
+        );
+        registers.int_en.write(INT_EN::MEASURE_INT_EN::SET);
+
+        self.pressed.set(false);
+        self.enabled.set(true);
+        registers.flow_control.write(FLOW_CONTROL::START_SENSE::SET);
+        Ok(())
+    }
+
+    fn disable(&self) -> Result<(), ErrorCode> {
+        if !self.enabled.get() {
+            return Ok(());
+        }
+        self.registers.int_en.set(0);
+        self.registers
+            .flow_control
+            .write(FLOW_CONTROL::DROP_MEASURE::SET + FLOW_CONTROL::DISABLE::SET);
+        self.registers.int_status.set(u32::MAX);
+        self.clock.disable();
+        self.enabled.set(false);
+        self.pressed.set(false);
+        Ok(())
+    }
+
+    fn set_client(&self, client: &'a dyn touch::TouchClient) {
+        self.client.set(client);
+    }
+}
@@ -0,0 +1,107 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Inter-peripheral crossbar switch (XBARA1).
+//!
+//! The crossbar connects the event outputs of peripherals, such as the PWM
+//! and PIT triggers, to the event inputs of other peripherals, such as the
+//! ADC_ETC triggers. Each crossbar output selects one of the crossbar inputs.
+//! The input and output numbers are listed in the XBARA1 input and output
+//! assignment tables of the reference manual.
+
+use kernel::platform::chip::ClockInterface;
+use kernel::utilities::registers::interfaces::ReadWriteable;
+use kernel::utilities::registers::{register_bitfields, ReadWrite};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+use crate::ccm;
+
+/// Number of crossbar outputs, two per select register
+const NUMBER_OUTPUTS: usize = 132;
+
+/// Largest input number that a select field holds
+const MAX_INPUT: u8 = 0x7F;
+
+#[repr(C)]
+struct XbarRegisters {
+    /// Crossbar A Select Registers, selecting the inputs of two outputs
+    sel: [ReadWrite<u16, SEL::Register>; NUMBER_OUTPUTS / 2],
+}
+
+register_bitfields![u16,
+    SEL [
+        /// Input selected for the odd output
+        SEL_ODD OFFSET(8) NUMBITS(7) [],
+        /// Input selected for the even output
+        SEL_EVEN OFFSET(0) NUMBITS(7) []
+    ]
+];
+
+const XBAR1_BASE: StaticRef<XbarRegisters> =
+    unsafe { StaticRef::new(0x403B_C000 as *const XbarRegisters) };
+
+/// Crossbar output connected to ADC_ETC trigger `trigger`, from 0 to 7.
+pub const fn adc_etc_trigger_output(trigger: usize) -> usize {
+    103 + trigger
+}
+
+pub struct Xbar<'a> {
+    registers: StaticRef<XbarRegisters>,
+    clock: XbarClock<'a>,
+}
+
+impl<'a> Xbar<'a> {
+    pub const fn new_xbar1(ccm: &'a ccm::Ccm) -> Self {
+        Xbar {
+            registers: XBAR1_BASE,
+            clock: XbarClock(ccm::PeripheralClock::ccgr2(ccm, ccm::HCLK2::XBAR1)),
+        }
+    }
+
+    pub fn is_enabled_clock(&self) -> bool {
+        self.clock.is_enabled()
+    }
+
+    pub fn enable_clock(&self) {
+        self.clock.enable();
+    }
+
+    pub fn disable_clock(&self) {
+        self.clock.disable();
+    }
+
+    /// Route the events of crossbar `input` to crossbar `output`.
+    pub fn connect(&self, input: u8, output: usize) -> Result<(), ErrorCode> {
+        if input > MAX_INPUT || output >= NUMBER_OUTPUTS {
+            return Err(ErrorCode::INVAL);
+        }
+        if !self.is_enabled_clock() {
+            self.enable_clock();
+        }
+        let sel = &self.registers.sel[output / 2];
+        if output % 2 == 0 {
+            sel.modify(SEL::SEL_EVEN.val(input as u16));
+        } else {
+            sel.modify(SEL::SEL_ODD.val(input as u16));
+        }
+        Ok(())
+    }
+}
+
+struct XbarClock<'a>(ccm::PeripheralClock<'a>);
+
+impl ClockInterface for XbarClock<'_> {
+    fn is_enabled(&self) -> bool {
+        self.0.is_enabled()
+    }
+
+    fn enable(&self) {
+        self.0.enable();
+    }
+
+    fn disable(&self) {
+        self.0.disable();
+    }
+}
@@ -148,6 +148,16 @@ impl<'a> dma::DmaClient for Uart<'a> {
 
 impl<'a> hil::uart::Configure for Uart<'a> {
     fn configure(&self, params: hil::uart::Parameters) -> Result<(), ErrorCode> {
+        // The hardware supports neither 6 nor 9 bit words, nor mark and space
+        // parity
+        if params.width == hil::uart::Width::Six
+            || params.width == hil::uart::Width::Nine
+            || params.parity == hil::uart::Parity::Mark
+            || params.parity == hil::uart::Parity::Space
+        {
+            return Err(ErrorCode::NOSUPPORT);
+        }
+
         // Disable module
         let regs = self.registers;
         regs.ctlw0.modify(usci::UCAxCTLW0::UCSWRST::SET);
@@ -162,9 +172,7 @@ impl<'a> hil::uart::Configure for Uart<'a> {
         match params.width {
             hil::uart::Width::Eight => regs.ctlw0.modify(usci::UCAxCTLW0::UC7BIT::CLEAR),
             hil::uart::Width::Seven => regs.ctlw0.modify(usci::UCAxCTLW0::UC7BIT::SET),
-            hil::uart::Width::Six => {
-                panic!("UART: width of 6 bit is not supported by this hardware!")
-            }
+            hil::uart::Width::Six | hil::uart::Width::Nine => unreachable!(),
         }
 
         // Setup stop bits
@@ -14,6 +14,7 @@ use kernel::utilities::cells::VolatileCell;
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::{register_bitfields, register_structs, ReadWrite, WriteOnly};
 use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
 use nrf5x::pinmux::Pinmux;
 
 /// Uninitialized `TWI` instances.
@@ -195,6 +196,21 @@ impl<'a> TWI<'a> {
     }
 }
 
+impl hil::i2c::I2CBusSpeed for TWI<'_> {
+    fn max_bus_speed(&self) -> hil::i2c::BusSpeed {
+        hil::i2c::BusSpeed::Fast400k
+    }
+
+    fn set_bus_speed(&self, speed: hil::i2c::BusSpeed) -> Result<(), ErrorCode> {
+        match speed {
+            hil::i2c::BusSpeed::Standard100k => self.set_speed(Speed::K100),
+            hil::i2c::BusSpeed::Fast400k => self.set_speed(Speed::K400),
+            hil::i2c::BusSpeed::FastPlus1M => return Err(ErrorCode::NOSUPPORT),
+        }
+        Ok(())
+    }
+}
+
 impl<'a> hil::i2c::I2CMaster<'a> for TWI<'a> {
     fn set_master_client(&self, client: &'a dyn hil::i2c::I2CHwMasterClient) {
         self.client.set(client);
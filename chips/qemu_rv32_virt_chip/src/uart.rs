@@ -379,6 +379,13 @@ impl hil::uart::Configure for Uart16550<'_> {
     fn configure(&self, params: hil::uart::Parameters) -> Result<(), ErrorCode> {
         use hil::uart::{Parity, StopBits, Width};
 
+        if params.width == Width::Nine
+            || params.parity == Parity::Mark
+            || params.parity == Parity::Space
+        {
+            return Err(ErrorCode::NOSUPPORT);
+        }
+
         // 16550 operates at a default frequency of 115200. Dividing
         // this by the target frequency gives the divisor register
         // contents.
@@ -393,6 +400,7 @@ impl hil::uart::Configure for Uart16550<'_> {
             Width::Six => lcr.modify(LCR::DataWordLength::Bits6),
             Width::Seven => lcr.modify(LCR::DataWordLength::Bits7),
             Width::Eight => lcr.modify(LCR::DataWordLength::Bits8),
+            Width::Nine => unreachable!(),
         };
 
         match params.stop_bits {
@@ -407,6 +415,7 @@ impl hil::uart::Configure for Uart16550<'_> {
             Parity::None => lcr.modify(LCR::Parity.val(0b000)),
             Parity::Odd => lcr.modify(LCR::Parity.val(0b001)),
             Parity::Even => lcr.modify(LCR::Parity.val(0b011)),
+            Parity::Mark | Parity::Space => unreachable!(),
         };
 
         match params.hw_flow_control {
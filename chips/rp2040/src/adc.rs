@@ -2,13 +2,24 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 // Copyright Tock Contributors 2022.
 
+//! Analog-to-digital converter.
+//!
+//! Single samples are read from the FIFO of the ADC when they are ready.
+//! High-speed sampling converts continuously at the requested frequency,
+//! and two DMA channels chained to each other move the samples from the
+//! FIFO to the two buffers in turn.
+
 use core::cell::Cell;
 use kernel::hil;
-use kernel::utilities::registers::interfaces::{ReadWriteable, Readable};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::{register_bitfields, register_structs, ReadWrite};
-use kernel::utilities::{cells::OptionalCell, StaticRef};
+use kernel::utilities::StaticRef;
 use kernel::ErrorCode;
 
+use crate::dma::{DataSize, DmaChannel, DmaClient, DmaRequest};
+use crate::resets;
+
 register_structs! {
     /// Control and data interface to SAR ADC
     AdcRegisters {
@@ -124,14 +135,25 @@ INTS [
 const ADC_BASE: StaticRef<AdcRegisters> =
     unsafe { StaticRef::new(0x4004C000 as *const AdcRegisters) };
 
+/// Frequency of the ADC clock, which the board sets up
+const ADC_CLOCK_HZ: u32 = 48_000_000;
+
+/// Each conversion takes 96 cycles of the ADC clock
+const MAX_FREQUENCY_HZ: u32 = ADC_CLOCK_HZ / 96;
+
 #[allow(dead_code)]
 #[repr(u32)]
 #[derive(Copy, Clone, PartialEq)]
 pub enum Channel {
+    /// GPIO26
     Channel0 = 0b00000,
+    /// GPIO27
     Channel1 = 0b00001,
+    /// GPIO28
     Channel2 = 0b00010,
+    /// GPIO29
     Channel3 = 0b00011,
+    /// Internal temperature sensor, powered on (TS_EN) when first sampled
     Channel4 = 0b00100,
 }
 
@@ -139,6 +161,7 @@ pub enum Channel {
 enum ADCStatus {
     Idle,
     OneSample,
+    HighSpeed,
 }
 
 pub struct Adc<'a> {
@@ -146,19 +169,120 @@ pub struct Adc<'a> {
     status: Cell<ADCStatus>,
     channel: Cell<Channel>,
     client: OptionalCell<&'a dyn hil::adc::Client>,
+    highspeed_client: OptionalCell<&'a dyn hil::adc::HighSpeedClient>,
+    resets: OptionalCell<&'a resets::Resets>,
+    /// DMA channels filling `buffers`, in turn
+    dma: [OptionalCell<&'a DmaChannel<'a>>; 2],
+    buffers: [TakeCell<'static, [u16]>; 2],
+    lengths: [Cell<usize>; 2],
+    /// Index of the buffer being filled
+    active: Cell<usize>,
 }
 
 impl<'a> Adc<'a> {
-    pub const fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             registers: ADC_BASE,
             status: Cell::new(ADCStatus::Idle),
             channel: Cell::new(Channel::Channel0),
             client: OptionalCell::empty(),
+            highspeed_client: OptionalCell::empty(),
+            resets: OptionalCell::empty(),
+            dma: [OptionalCell::empty(), OptionalCell::empty()],
+            buffers: [TakeCell::empty(), TakeCell::empty()],
+            lengths: [Cell::new(0), Cell::new(0)],
+            active: Cell::new(0),
         }
     }
 
+    pub(crate) fn set_resets(&self, resets: &'a resets::Resets) {
+        self.resets.set(resets);
+    }
+
+    /// Sample at high speed with the `first` and `second` DMA channels, which
+    /// are chained to each other.
+    pub(crate) fn set_dma_channels(
+        &'a self,
+        first: &'a DmaChannel<'a>,
+        second: &'a DmaChannel<'a>,
+    ) {
+        first.set_client(self);
+        second.set_client(self);
+        first.chain_to(Some(second));
+        second.chain_to(Some(first));
+        self.dma[0].set(first);
+        self.dma[1].set(second);
+    }
+
+    /// Address of the FIFO, for the DMA channels.
+    fn fifo_register(&self) -> *const u8 {
+        &self.registers.fifo as *const ReadWrite<u32, FIFO::Register> as *const u8
+    }
+
+    /// Set up DMA channel `index` to fill its buffer, starting when the
+    /// other channel is done unless `start` is set.
+    fn setup_dma(&self, index: usize, start: bool) {
+        let length = self.lengths[index].get();
+        self.dma[index].map(|dma| {
+            self.buffers[index].map(|buf| {
+                let buf = buf.as_mut_ptr() as *mut u8;
+                // The buffer stays with the driver until the channel is done
+                // or aborted.
+                unsafe {
+                    if start {
+                        dma.transfer(
+                            self.fifo_register(),
+                            false,
+                            buf,
+                            true,
+                            length,
+                            DataSize::HalfWord,
+                            DmaRequest::Adc,
+                        );
+                    } else {
+                        dma.prepare(
+                            self.fifo_register(),
+                            false,
+                            buf,
+                            true,
+                            length,
+                            DataSize::HalfWord,
+                            DmaRequest::Adc,
+                        );
+                    }
+                }
+            });
+        });
+    }
+
+    /// Drop the samples waiting in the FIFO.
+    fn drain_fifo(&self) {
+        while !self.registers.fcs.is_set(FCS::EMPTY) {
+            self.registers.fifo.get();
+        }
+        self.registers.fcs.modify(FCS::OVER::SET + FCS::UNDER::SET);
+    }
+
+    /// Stop converting, and stop the DMA channels without calling the
+    /// client. The buffers stay with the driver until `retrieve_buffers`.
+    fn stop_highspeed(&self) {
+        self.registers.cs.modify(CS::START_MANY::CLEAR);
+        self.registers
+            .fcs
+            .modify(FCS::DREQ_EN::CLEAR + FCS::EN::CLEAR);
+        for dma in self.dma.iter() {
+            dma.map(|dma| {
+                dma.abort();
+                dma.disable();
+            });
+        }
+        self.drain_fifo();
+        self.status.set(ADCStatus::Idle);
+    }
+
     pub fn init(&self) {
+        self.resets
+            .map(|resets| resets.unreset(&[resets::Peripheral::Adc], true));
         self.registers.cs.modify(CS::EN::SET);
         while !self.registers.cs.is_set(CS::READY) {}
     }
@@ -180,6 +304,9 @@ impl<'a> Adc<'a> {
     }
 
     pub fn handle_interrupt(&self) {
+        if self.status.get() == ADCStatus::HighSpeed {
+            return;
+        }
         if self.registers.cs.is_set(CS::READY) {
             if self.status.get() == ADCStatus::OneSample {
                 self.status.set(ADCStatus::Idle);
@@ -197,7 +324,7 @@ impl<'a> hil::adc::Adc<'a> for Adc<'a> {
 
     fn sample(&self, channel: &Self::Channel) -> Result<(), ErrorCode> {
         if self.status.get() == ADCStatus::Idle {
-            if *channel as u32 == 4 {
+            if *channel == Channel::Channel4 {
                 self.enable_temperature();
             }
             self.status.set(ADCStatus::OneSample);
@@ -223,7 +350,11 @@ impl<'a> hil::adc::Adc<'a> for Adc<'a> {
     }
 
     fn stop_sampling(&self) -> Result<(), ErrorCode> {
-        Err(ErrorCode::NOSUPPORT)
+        if self.status.get() != ADCStatus::HighSpeed {
+            return Err(ErrorCode::NOSUPPORT);
+        }
+        self.stop_highspeed();
+        Ok(())
     }
 
     fn get_resolution_bits(&self) -> usize {
@@ -238,3 +369,134 @@ impl<'a> hil::adc::Adc<'a> for Adc<'a> {
         self.client.set(client);
     }
 }
+
+impl<'a> hil::adc::AdcHighSpeed<'a> for Adc<'a> {
+    fn sample_highspeed(
+        &self,
+        channel: &Self::Channel,
+        frequency: u32,
+        buffer1: &'static mut [u16],
+        length1: usize,
+        buffer2: &'static mut [u16],
+        length2: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u16], &'static mut [u16])> {
+        if self.status.get() != ADCStatus::Idle {
+            return Err((ErrorCode::BUSY, buffer1, buffer2));
+        }
+        if self.dma[0].is_none() || self.dma[1].is_none() {
+            return Err((ErrorCode::NOSUPPORT, buffer1, buffer2));
+        }
+        let length1 = core::cmp::min(length1, buffer1.len());
+        let length2 = core::cmp::min(length2, buffer2.len());
+        if frequency == 0 || frequency > MAX_FREQUENCY_HZ || length1 == 0 || length2 == 0 {
+            return Err((ErrorCode::INVAL, buffer1, buffer2));
+        }
+
+        if *channel == Channel::Channel4 {
+            self.enable_temperature();
+        }
+        self.status.set(ADCStatus::HighSpeed);
+        self.channel.set(*channel);
+        self.registers.cs.modify(CS::AINSEL.val(*channel as u32));
+        // A conversion starts every 1 + INT + FRAC / 256 cycles.
+        let divider = (ADC_CLOCK_HZ as u64 * 256 / frequency as u64 - 256) as u32;
+        self.registers
+            .div
+            .write(DIV::INT.val(divider >> 8) + DIV::FRAC.val(divider & 0xFF));
+        self.disable_interrupt();
+        self.registers.fcs.modify(
+            FCS::THRESH.val(1)
+                + FCS::DREQ_EN::SET
+                + FCS::SHIFT::CLEAR
+                + FCS::ERR::CLEAR
+                + FCS::EN::SET,
+        );
+        self.drain_fifo();
+
+        self.buffers[0].replace(buffer1);
+        self.lengths[0].set(length1);
+        self.buffers[1].replace(buffer2);
+        self.lengths[1].set(length2);
+        self.active.set(0);
+        self.setup_dma(1, false);
+        self.setup_dma(0, true);
+        self.registers.cs.modify(CS::START_MANY::SET);
+        Ok(())
+    }
+
+    fn provide_buffer(
+        &self,
+        buf: &'static mut [u16],
+        length: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u16])> {
+        if self.status.get() != ADCStatus::HighSpeed {
+            return Err((ErrorCode::OFF, buf));
+        }
+        let length = core::cmp::min(length, buf.len());
+        if length == 0 {
+            return Err((ErrorCode::INVAL, buf));
+        }
+        let active = self.active.get();
+        let (index, start) = if self.buffers[active].is_some() {
+            // Fill the buffer when the active one is full.
+            (1 - active, false)
+        } else {
+            // Both buffers were returned: sampling stalled, and the samples
+            // left in the FIFO are stale.
+            self.drain_fifo();
+            (active, true)
+        };
+        if self.buffers[index].is_some() {
+            return Err((ErrorCode::BUSY, buf));
+        }
+        self.buffers[index].replace(buf);
+        self.lengths[index].set(length);
+        self.setup_dma(index, start);
+        Ok(())
+    }
+
+    fn retrieve_buffers(
+        &self,
+    ) -> Result<(Option<&'static mut [u16]>, Option<&'static mut [u16]>), ErrorCode> {
+        if self.status.get() != ADCStatus::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        Ok((self.buffers[0].take(), self.buffers[1].take()))
+    }
+
+    fn set_highspeed_client(&self, client: &'a dyn hil::adc::HighSpeedClient) {
+        self.highspeed_client.set(client);
+    }
+}
+
+impl DmaClient for Adc<'_> {
+    fn transfer_done(
+        &self,
+        _request: DmaRequest,
+        result: Result<(), ErrorCode>,
+        transferred: usize,
+    ) {
+        if self.status.get() != ADCStatus::HighSpeed {
+            return;
+        }
+        // The channels complete in turn.
+        let index = self.active.get();
+        self.active.set(1 - index);
+        // The channel is restarted once it has a new buffer.
+        self.dma[index].map(|dma| dma.disable());
+        if result.is_err() {
+            self.stop_highspeed();
+        }
+        let length = if result.is_ok() {
+            self.lengths[index].get()
+        } else {
+            transferred
+        };
+        self.buffers[index].take().map(|buf| {
+            // Samples are left-justified.
+            buf[..length].iter_mut().for_each(|sample| *sample <<= 4);
+            self.highspeed_client
+                .map(|client| client.samples_ready(buf, length));
+        });
+    }
+}
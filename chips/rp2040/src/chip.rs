@@ -7,12 +7,18 @@
 use core::fmt::Write;
 use kernel::platform::chip::Chip;
 use kernel::platform::chip::InterruptService;
+use kernel::utilities::cells::OptionalCell;
 
 use crate::adc;
 use crate::clocks::Clocks;
+use crate::crc;
+use crate::dma;
+use crate::dormant::Dormant;
+use crate::flash;
 use crate::gpio::{RPGpio, RPPins, SIO};
 use crate::i2c;
 use crate::interrupts;
+use crate::multicore::Multicore;
 use crate::pwm;
 use crate::resets::Resets;
 use crate::spi;
@@ -37,6 +43,7 @@ pub struct Rp2040<'a, I: InterruptService + 'a> {
     sio: &'a SIO,
     processor0_interrupt_mask: (u128, u128),
     processor1_interrupt_mask: (u128, u128),
+    dormant: OptionalCell<&'a Dormant<'a>>,
 }
 
 impl<'a, I: InterruptService> Rp2040<'a, I> {
@@ -48,8 +55,14 @@ impl<'a, I: InterruptService> Rp2040<'a, I> {
             sio: sio,
             processor0_interrupt_mask: interrupt_mask!(interrupts::SIO_IRQ_PROC1),
             processor1_interrupt_mask: interrupt_mask!(interrupts::SIO_IRQ_PROC0),
+            dormant: OptionalCell::empty(),
         }
     }
+
+    /// Go dormant when idle whenever `dormant` is ready.
+    pub fn set_dormant(&self, dormant: &'a Dormant<'a>) {
+        self.dormant.set(dormant);
+    }
 }
 
 impl<'a, I: InterruptService> Chip for Rp2040<'a, I> {
@@ -100,8 +113,11 @@ impl<'a, I: InterruptService> Chip for Rp2040<'a, I> {
     }
 
     fn sleep(&self) {
-        unsafe {
-            cortexm0p::support::wfi();
+        match self.dormant.extract() {
+            Some(dormant) if dormant.ready() => unsafe { dormant.enter() },
+            _ => unsafe {
+                cortexm0p::support::wfi();
+            },
         }
     }
 
@@ -120,7 +136,11 @@ impl<'a, I: InterruptService> Chip for Rp2040<'a, I> {
 pub struct Rp2040DefaultPeripherals<'a> {
     pub adc: adc::Adc<'a>,
     pub clocks: Clocks,
+    pub crc: crc::Crc<'a>,
+    pub dma: dma::Dma<'a>,
+    pub flash: flash::Flash,
     pub i2c0: i2c::I2c<'a, 'a>,
+    pub multicore: Multicore<'a>,
     pub pins: RPPins<'a>,
     pub pwm: pwm::Pwm<'a>,
     pub resets: Resets,
@@ -140,7 +160,11 @@ impl<'a> Rp2040DefaultPeripherals<'a> {
         Self {
             adc: adc::Adc::new(),
             clocks: Clocks::new(),
+            crc: crc::Crc::new(),
+            dma: dma::Dma::new(),
+            flash: flash::Flash::new(),
             i2c0: i2c::I2c::new_i2c0(),
+            multicore: Multicore::new(),
             pins: RPPins::new(),
             pwm: pwm::Pwm::new(),
             resets: Resets::new(),
@@ -158,13 +182,34 @@ impl<'a> Rp2040DefaultPeripherals<'a> {
 
     pub fn resolve_dependencies(&'static self) {
         self.pwm.set_clocks(&self.clocks);
+        self.pwm.set_timer(&self.timer);
         self.watchdog.resolve_dependencies(&self.resets);
+        self.adc.set_resets(&self.resets);
         self.spi0.set_clocks(&self.clocks);
+        self.spi0.set_resets(&self.resets);
         self.uart0.set_clocks(&self.clocks);
+        self.uart0.set_resets(&self.resets);
+        self.uart1.set_clocks(&self.clocks);
+        self.uart1.set_resets(&self.resets);
+        if let (Some(tx), Some(rx)) = (self.dma.claim_channel(), self.dma.claim_channel()) {
+            self.uart0.set_dma_channels(tx, rx);
+        }
+        if let (Some(tx), Some(rx)) = (self.dma.claim_channel(), self.dma.claim_channel()) {
+            self.uart1.set_dma_channels(tx, rx);
+        }
+        if let (Some(tx), Some(rx)) = (self.dma.claim_channel(), self.dma.claim_channel()) {
+            self.spi0.set_dma_channels(tx, rx);
+        }
+        if let (Some(first), Some(second)) = (self.dma.claim_channel(), self.dma.claim_channel()) {
+            self.adc.set_dma_channels(first, second);
+        }
         kernel::deferred_call::DeferredCallClient::register(&self.uart0);
         kernel::deferred_call::DeferredCallClient::register(&self.uart1);
+        kernel::deferred_call::DeferredCallClient::register(&self.crc);
+        kernel::deferred_call::DeferredCallClient::register(&self.flash);
         self.i2c0.resolve_dependencies(&self.clocks, &self.resets);
         self.usb.set_gpio(self.pins.get_pin(RPGpio::GPIO15));
+        self.usb.set_resets(&self.resets);
     }
 }
 
@@ -175,12 +220,9 @@ impl InterruptService for Rp2040DefaultPeripherals<'_> {
                 self.timer.handle_interrupt();
                 true
             }
-            interrupts::SIO_IRQ_PROC0 => {
-                self.sio.handle_proc_interrupt(Processor::Processor0);
-                true
-            }
-            interrupts::SIO_IRQ_PROC1 => {
-                self.sio.handle_proc_interrupt(Processor::Processor1);
+            interrupts::SIO_IRQ_PROC0 | interrupts::SIO_IRQ_PROC1 => {
+                // The FIFO registers are those of the current processor
+                self.multicore.handle_interrupt();
                 true
             }
             interrupts::SPI0_IRQ => {
@@ -191,6 +233,19 @@ impl InterruptService for Rp2040DefaultPeripherals<'_> {
                 self.uart0.handle_interrupt();
                 true
             }
+            interrupts::UART1_IRQ => {
+                self.uart1.handle_interrupt();
+                true
+            }
+            interrupts::DMA_IRQ_0 => {
+                self.dma.handle_interrupt(dma::DmaIrq::Irq0);
+                self.crc.handle_interrupt();
+                true
+            }
+            interrupts::DMA_IRQ_1 => {
+                self.dma.handle_interrupt(dma::DmaIrq::Irq1);
+                true
+            }
             interrupts::ADC_IRQ_FIFO => {
                 self.adc.handle_interrupt();
                 true
@@ -208,10 +263,10 @@ impl InterruptService for Rp2040DefaultPeripherals<'_> {
                 true
             }
             interrupts::PWM_IRQ_WRAP => {
-                // As the PWM HIL doesn't provide any support for interrupts, they are
-                // simply ignored.
+                // The PWM HIL doesn't use interrupts, they are only used for input capture.
                 //
-                // Note that PWM interrupts are raised only during unit tests.
+                // Note that PWM interrupts are also raised during unit tests.
+                self.pwm.handle_interrupt();
                 true
             }
             _ => false,
@@ -3,6 +3,7 @@
 // Copyright Tock Contributors 2022.
 
 use core::cell::Cell;
+use kernel::hil::clock_tree::{self, Clock as ClockInfo, ClockGate};
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::{register_bitfields, register_structs, ReadOnly, ReadWrite};
 use kernel::utilities::StaticRef;
@@ -745,6 +746,8 @@ pub struct Clocks {
     registers: StaticRef<ClocksRegisters>,
     pll_registers: &'static [StaticRef<PllRegisters>],
     frequencies: [Cell<u32>; NUM_CLOCKS],
+    /// Crystal oscillator frequency given to `pll_init`, 0 until then.
+    xosc_frequency: Cell<u32>,
 }
 
 pub enum PllClock {
@@ -752,6 +755,21 @@ pub enum PllClock {
     Usb = 1,
 }
 
+/// The PLL and clock generator settings which `run_from_xosc` changes, so
+/// that `restore` can bring the clocks back.
+#[derive(Copy, Clone, Default)]
+pub struct ClockState {
+    /// CS, PWR, FBDIV_INT and PRIM of each PLL
+    plls: [(u32, u32, u32, u32); 2],
+    ref_ctrl: u32,
+    ref_div: u32,
+    sys_ctrl: u32,
+    sys_div: u32,
+    usb_ctrl: u32,
+    adc_ctrl: u32,
+    rtc_ctrl: u32,
+}
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 #[repr(usize)]
 pub enum Clock {
@@ -901,6 +919,7 @@ impl Clocks {
                 Cell::new(0),
                 Cell::new(0),
             ],
+            xosc_frequency: Cell::new(0),
         }
     }
 
@@ -950,6 +969,7 @@ impl Clocks {
         post_div2: u32,
     ) {
         let registers = self.pll_registers[clock as usize];
+        self.xosc_frequency.set(xosc_freq * 1_000_000);
 
         // Turn off PLL
         registers
@@ -1014,6 +1034,99 @@ impl Clocks {
             .modify(PWR::PD::SET + PWR::DSMPD::SET + PWR::POSTDIVPD::SET + PWR::VCOPD::SET);
     }
 
+    /// Run clk_ref and clk_sys from the crystal oscillator, stop the
+    /// clocks derived from the PLLs and power both PLLs down. This is
+    /// required before the crystal oscillator goes dormant. Returns the
+    /// previous settings. The frequencies are not updated: nothing should
+    /// use the clocks until they are restored.
+    pub fn run_from_xosc(&self) -> ClockState {
+        let mut state = ClockState {
+            ref_ctrl: self.registers.clk_ref_ctrl.get(),
+            ref_div: self.registers.clk_ref_div.get(),
+            sys_ctrl: self.registers.clk_sys_ctrl.get(),
+            sys_div: self.registers.clk_sys_div.get(),
+            usb_ctrl: self.registers.clk_usb_ctrl.get(),
+            adc_ctrl: self.registers.clk_adc_ctrl.get(),
+            rtc_ctrl: self.registers.clk_rtc_ctrl.get(),
+            ..ClockState::default()
+        };
+        for (saved, registers) in state.plls.iter_mut().zip(self.pll_registers.iter()) {
+            *saved = (
+                registers.cs.get(),
+                registers.pwr.get(),
+                registers.fbdiv_int.get(),
+                registers.prim.get(),
+            );
+        }
+
+        self.registers
+            .clk_ref_ctrl
+            .modify(CLK_REF_CTRL::SRC::XOSC_CLKSRC);
+        while self
+            .registers
+            .clk_ref_selected
+            .read(CLK_REF_SELECTED::VALUE)
+            & (1 << ReferenceClockSource::Xosc as u32)
+            == 0
+        {}
+        self.registers.clk_ref_div.set(1 << 8);
+        self.disable_sys_aux();
+        self.registers.clk_sys_div.set(1 << 8);
+
+        self.registers
+            .clk_usb_ctrl
+            .modify(CLK_USB_CTRL::ENABLE::CLEAR);
+        self.registers
+            .clk_adc_ctrl
+            .modify(CLK_ADC_CTRL::ENABLE::CLEAR);
+        self.registers
+            .clk_rtc_ctrl
+            .modify(CLK_RTC_CTRL::ENABLE::CLEAR);
+
+        self.pll_deinit(PllClock::Sys);
+        self.pll_deinit(PllClock::Usb);
+        state
+    }
+
+    /// Restore the PLLs and clocks changed by `run_from_xosc`, once the
+    /// crystal oscillator is stable again.
+    pub fn restore(&self, state: &ClockState) {
+        for (saved, registers) in state.plls.iter().zip(self.pll_registers.iter()) {
+            let (cs, pwr, fbdiv_int, prim) = *saved;
+            if PWR::PD.read(pwr) != 0 {
+                continue;
+            }
+            registers.cs.set(cs);
+            registers.fbdiv_int.set(fbdiv_int);
+            registers.pwr.modify(PWR::PD::CLEAR + PWR::VCOPD::CLEAR);
+            while !registers.cs.is_set(CS::LOCK) {}
+            registers.prim.set(prim);
+            registers.pwr.set(pwr);
+        }
+
+        self.registers.clk_ref_ctrl.set(state.ref_ctrl);
+        self.registers.clk_ref_div.set(state.ref_div);
+
+        // The auxiliary source may only change while clk_sys runs from
+        // clk_ref.
+        self.registers
+            .clk_sys_ctrl
+            .modify(CLK_SYS_CTRL::AUXSRC.val(CLK_SYS_CTRL::AUXSRC.read(state.sys_ctrl)));
+        self.registers.clk_sys_ctrl.set(state.sys_ctrl);
+        while self
+            .registers
+            .clk_sys_selected
+            .read(CLK_SYS_SELECTED::VALUE)
+            & (1 << CLK_SYS_CTRL::SRC.read(state.sys_ctrl))
+            == 0
+        {}
+        self.registers.clk_sys_div.set(state.sys_div);
+
+        self.registers.clk_usb_ctrl.set(state.usb_ctrl);
+        self.registers.clk_adc_ctrl.set(state.adc_ctrl);
+        self.registers.clk_rtc_ctrl.set(state.rtc_ctrl);
+    }
+
     pub fn set_frequency(&self, clock: Clock, freq: u32) {
         self.frequencies[clock as usize].set(freq);
     }
@@ -1407,3 +1520,214 @@ impl Clocks {
         self.set_frequency(Clock::Rtc, freq);
     }
 }
+
+/// Names of the ENABLED0 and ENABLED1 bits, reported by the `clocks` console
+/// command.
+const CLOCK_GATES: [&str; 47] = [
+    "sys_clocks",
+    "adc_adc",
+    "sys_adc",
+    "sys_busctrl",
+    "sys_busfabric",
+    "sys_dma",
+    "sys_i2c0",
+    "sys_i2c1",
+    "sys_io",
+    "sys_jtag",
+    "sys_vreg",
+    "sys_pads",
+    "sys_pio0",
+    "sys_pio1",
+    "sys_pll_sys",
+    "sys_pll_usb",
+    "sys_psm",
+    "sys_pwm",
+    "sys_resets",
+    "sys_rom",
+    "sys_rosc",
+    "rtc_rtc",
+    "sys_rtc",
+    "sys_sio",
+    "peri_spi0",
+    "sys_spi0",
+    "peri_spi1",
+    "sys_spi1",
+    "sys_sram0",
+    "sys_sram1",
+    "sys_sram2",
+    "sys_sram3",
+    "sys_sram4",
+    "sys_sram5",
+    "sys_syscfg",
+    "sys_sysinfo",
+    "sys_tbman",
+    "sys_timer",
+    "peri_uart0",
+    "sys_uart0",
+    "peri_uart1",
+    "sys_uart1",
+    "sys_usbctrl",
+    "usb_usbctrl",
+    "sys_watchdog",
+    "sys_xip",
+    "sys_xosc",
+];
+
+impl Clocks {
+    fn pll_info(&self, clock: PllClock, name: &'static str) -> ClockInfo {
+        let registers = self.pll_registers[clock as usize];
+        let refdiv = registers.cs.read(CS::REFDIV);
+        let fbdiv = registers.fbdiv_int.read(FBDIV_INT::FBDIV_INT);
+        let divider =
+            refdiv * registers.prim.read(PRIM::POSTDIV1) * registers.prim.read(PRIM::POSTDIV2);
+        let xosc_frequency = self.xosc_frequency.get();
+        ClockInfo {
+            name,
+            enabled: !registers.pwr.is_set(PWR::PD) && registers.cs.is_set(CS::LOCK),
+            source: Some("xosc"),
+            multiplier: fbdiv,
+            divider,
+            frequency_hz: (xosc_frequency != 0 && divider != 0)
+                .then(|| (xosc_frequency as u64 * fbdiv as u64 / divider as u64) as u32),
+        }
+    }
+
+    /// Name of an auxiliary source of the USB, ADC and RTC clocks.
+    fn auxiliary_source_name(auxsrc: u32) -> &'static str {
+        match auxsrc {
+            0 => "pll_usb",
+            1 => "pll_sys",
+            2 => "rosc",
+            3 => "xosc",
+            4 => "gpin0",
+            5 => "gpin1",
+            _ => "?",
+        }
+    }
+
+    fn generator_info(
+        &self,
+        name: &'static str,
+        clock: Clock,
+        enabled: bool,
+        source: &'static str,
+        div: u32,
+    ) -> ClockInfo {
+        ClockInfo {
+            name,
+            enabled,
+            source: Some(source),
+            multiplier: 1,
+            // integer part of the divider, in bits 8 and up
+            divider: div >> 8,
+            frequency_hz: Some(self.get_frequency(clock)),
+        }
+    }
+}
+
+impl clock_tree::ClockTree for Clocks {
+    fn clock(&self, index: usize) -> Option<ClockInfo> {
+        let registers = &self.registers;
+        match index {
+            0 => Some(ClockInfo {
+                name: "xosc",
+                enabled: self.xosc_frequency.get() != 0,
+                source: None,
+                multiplier: 1,
+                divider: 1,
+                frequency_hz: Some(self.xosc_frequency.get()).filter(|&hz| hz != 0),
+            }),
+            1 => Some(self.pll_info(PllClock::Sys, "pll_sys")),
+            2 => Some(self.pll_info(PllClock::Usb, "pll_usb")),
+            3 => {
+                let source = match registers.clk_ref_ctrl.read(CLK_REF_CTRL::SRC) {
+                    0 => "rosc",
+                    1 => match registers.clk_ref_ctrl.read(CLK_REF_CTRL::AUXSRC) {
+                        0 => "pll_usb",
+                        1 => "gpin0",
+                        _ => "gpin1",
+                    },
+                    _ => "xosc",
+                };
+                Some(self.generator_info(
+                    "ref",
+                    Clock::Reference,
+                    true,
+                    source,
+                    registers.clk_ref_div.get(),
+                ))
+            }
+            4 => {
+                let source = if registers.clk_sys_ctrl.is_set(CLK_SYS_CTRL::SRC) {
+                    match registers.clk_sys_ctrl.read(CLK_SYS_CTRL::AUXSRC) {
+                        0 => "pll_sys",
+                        1 => "pll_usb",
+                        2 => "rosc",
+                        3 => "xosc",
+                        4 => "gpin0",
+                        _ => "gpin1",
+                    }
+                } else {
+                    "ref"
+                };
+                Some(self.generator_info(
+                    "sys",
+                    Clock::System,
+                    true,
+                    source,
+                    registers.clk_sys_div.get(),
+                ))
+            }
+            5 => {
+                let source = match registers.clk_peri_ctrl.read(CLK_PERI_CTRL::AUXSRC) {
+                    0 => "sys",
+                    1 => "pll_sys",
+                    2 => "pll_usb",
+                    3 => "rosc",
+                    4 => "xosc",
+                    5 => "gpin0",
+                    _ => "gpin1",
+                };
+                Some(self.generator_info(
+                    "peri",
+                    Clock::Peripheral,
+                    registers.clk_peri_ctrl.is_set(CLK_PERI_CTRL::ENABLE),
+                    source,
+                    1 << 8,
+                ))
+            }
+            6 => Some(self.generator_info(
+                "usb",
+                Clock::Usb,
+                registers.clk_usb_ctrl.is_set(CLK_USB_CTRL::ENABLE),
+                Self::auxiliary_source_name(registers.clk_usb_ctrl.read(CLK_USB_CTRL::AUXSRC)),
+                registers.clk_usb_div.get(),
+            )),
+            7 => Some(self.generator_info(
+                "adc",
+                Clock::Adc,
+                registers.clk_adc_ctrl.is_set(CLK_ADC_CTRL::ENABLE),
+                Self::auxiliary_source_name(registers.clk_adc_ctrl.read(CLK_ADC_CTRL::AUXSRC)),
+                registers.clk_adc_div.get(),
+            )),
+            8 => Some(self.generator_info(
+                "rtc",
+                Clock::Rtc,
+                registers.clk_rtc_ctrl.is_set(CLK_RTC_CTRL::ENABLE),
+                Self::auxiliary_source_name(registers.clk_rtc_ctrl.read(CLK_RTC_CTRL::AUXSRC)),
+                registers.clk_rtc_div.get(),
+            )),
+            _ => None,
+        }
+    }
+
+    fn clock_gate(&self, index: usize) -> Option<ClockGate> {
+        let name = *CLOCK_GATES.get(index)?;
+        let enabled = if index < 32 {
+            self.registers.enabled0.get() & (1 << index) != 0
+        } else {
+            self.registers.enabled1.get() & (1 << (index - 32)) != 0
+        };
+        Some(ClockGate { name, enabled })
+    }
+}
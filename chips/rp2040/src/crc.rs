@@ -0,0 +1,212 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! CRC calculation with the DMA sniffer.
+//!
+//! The DMA controller of the RP2040 can compute a CRC over the data
+//! transferred by one of its channels. This driver reserves DMA channel 11:
+//! each input is copied by the channel, one byte at a time, to a word of the
+//! driver which is never read, while the sniffer updates the CRC.
+//!
+//! The sniffer computes CRCs with the CRC-32 polynomial `0x04C11DB7` and the
+//! CRC-16-CCITT polynomial `0x1021`. Both can consume each byte from the
+//! least significant bit, and the sniffer can bit-reverse and invert its
+//! result, so `CrcAlgorithm::Crc32` and `CrcAlgorithm::Crc16CCITT` need no
+//! processing in software. CRC-16-CCITT starts from `0xFFFF`, as on the
+//! SAM4L.
+//!
+//! The DMA controller must be taken out of reset by the board.
+
+use core::cell::Cell;
+
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
+use kernel::hil::crc::{Client, Crc as CrcHil, CrcAlgorithm, CrcOutput};
+use kernel::utilities::cells::{MapCell, OptionalCell, VolatileCell};
+use kernel::utilities::leasable_buffer::LeasableMutableBuffer;
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+use crate::dma::{DmaRegisters, CRC_CHANNEL, CTRL, DMA_BASE, SNIFF_CTRL};
+
+pub struct Crc<'a> {
+    registers: StaticRef<DmaRegisters>,
+    client: OptionalCell<&'a dyn Client>,
+    algorithm: OptionalCell<CrcAlgorithm>,
+    /// Input being transferred by the channel
+    input_buffer: MapCell<LeasableMutableBuffer<'static, u8>>,
+    /// Destination of the transfers
+    sink: VolatileCell<u32>,
+    compute_requested: Cell<bool>,
+    deferred_call: DeferredCall,
+}
+
+impl<'a> Crc<'a> {
+    pub fn new() -> Self {
+        Self {
+            registers: DMA_BASE,
+            client: OptionalCell::empty(),
+            algorithm: OptionalCell::empty(),
+            input_buffer: MapCell::empty(),
+            sink: VolatileCell::new(0),
+            compute_requested: Cell::new(false),
+            deferred_call: DeferredCall::new(),
+        }
+    }
+
+    fn busy(&self) -> bool {
+        self.input_buffer.is_some() || self.compute_requested.get()
+    }
+
+    /// Configure the sniffer for `algorithm` and seed it.
+    fn reset(&self, algorithm: CrcAlgorithm) {
+        let dmach = SNIFF_CTRL::DMACH.val(CRC_CHANNEL as u32) + SNIFF_CTRL::EN::SET;
+        match algorithm {
+            CrcAlgorithm::Crc32 => {
+                self.registers.sniff_ctrl.write(
+                    dmach
+                        + SNIFF_CTRL::CALC::Crc32BitReversed
+                        + SNIFF_CTRL::OUT_REV::SET
+                        + SNIFF_CTRL::OUT_INV::SET,
+                );
+                self.registers.sniff_data.set(0xFFFF_FFFF);
+            }
+            _ => {
+                self.registers
+                    .sniff_ctrl
+                    .write(dmach + SNIFF_CTRL::CALC::Crc16CcittBitReversed);
+                self.registers.sniff_data.set(0xFFFF);
+            }
+        }
+    }
+
+    pub fn handle_interrupt(&self) {
+        if self.registers.ints0.get() & (1 << CRC_CHANNEL) == 0 {
+            return;
+        }
+        self.registers.intr.set(1 << CRC_CHANNEL);
+
+        let channel = &self.registers.ch[CRC_CHANNEL];
+        let result = if channel.ctrl_trig.is_set(CTRL::AHB_ERROR) {
+            channel
+                .ctrl_trig
+                .write(CTRL::READ_ERROR::SET + CTRL::WRITE_ERROR::SET);
+            Err(ErrorCode::FAIL)
+        } else {
+            Ok(())
+        };
+
+        if let Some(buffer) = self.input_buffer.take() {
+            self.client.map(move |client| {
+                client.input_done(result, buffer);
+            });
+        }
+    }
+}
+
+impl DeferredCallClient for Crc<'_> {
+    fn handle_deferred_call(&self) {
+        if !self.compute_requested.get() {
+            return;
+        }
+        let result = self.registers.sniff_data.get();
+        let output = self.algorithm.extract().map(|algorithm| {
+            self.reset(algorithm);
+            match algorithm {
+                CrcAlgorithm::Crc32 => CrcOutput::Crc32(result),
+                _ => CrcOutput::Crc16CCITT(result as u16),
+            }
+        });
+        self.compute_requested.set(false);
+        self.client.map(|client| {
+            client.crc_done(output.ok_or(ErrorCode::RESERVE));
+        });
+    }
+
+    fn register(&'static self) {
+        self.deferred_call.register(self);
+    }
+}
+
+impl<'a> CrcHil<'a> for Crc<'a> {
+    fn set_client(&self, client: &'a dyn Client) {
+        self.client.set(client);
+    }
+
+    fn algorithm_supported(&self, algorithm: CrcAlgorithm) -> bool {
+        match algorithm {
+            CrcAlgorithm::Crc32 => true,
+            CrcAlgorithm::Crc32C => false,
+            CrcAlgorithm::Crc16CCITT => true,
+        }
+    }
+
+    fn set_algorithm(&self, algorithm: CrcAlgorithm) -> Result<(), ErrorCode> {
+        if self.busy() {
+            return Err(ErrorCode::BUSY);
+        }
+        if !self.algorithm_supported(algorithm) {
+            return Err(ErrorCode::NOSUPPORT);
+        }
+        self.algorithm.set(algorithm);
+        self.reset(algorithm);
+        Ok(())
+    }
+
+    fn input(
+        &self,
+        data: LeasableMutableBuffer<'static, u8>,
+    ) -> Result<(), (ErrorCode, LeasableMutableBuffer<'static, u8>)> {
+        if self.algorithm.is_none() {
+            return Err((ErrorCode::RESERVE, data));
+        }
+        if self.busy() {
+            return Err((ErrorCode::BUSY, data));
+        }
+        if data.len() == 0 {
+            // The channel would not raise its interrupt.
+            return Err((ErrorCode::SIZE, data));
+        }
+
+        let channel = &self.registers.ch[CRC_CHANNEL];
+        channel.read_addr.set(data.as_ptr() as u32);
+        channel
+            .write_addr
+            .set(&self.sink as *const VolatileCell<u32> as u32);
+        channel.trans_count.set(data.len() as u32);
+        self.registers
+            .inte0
+            .set(self.registers.inte0.get() | (1 << CRC_CHANNEL));
+
+        // The channel only reads the buffer, which stays with the driver
+        // until the transfer is over.
+        self.input_buffer.replace(data);
+        self.registers.sniff_ctrl.modify(SNIFF_CTRL::EN::SET);
+        channel.ctrl_trig.write(
+            CTRL::EN::SET
+                + CTRL::DATA_SIZE::Byte
+                + CTRL::INCR_READ::SET
+                + CTRL::CHAIN_TO.val(CRC_CHANNEL as u32)
+                + CTRL::TREQ_SEL::Permanent
+                + CTRL::SNIFF_EN::SET,
+        );
+        Ok(())
+    }
+
+    fn compute(&self) -> Result<(), ErrorCode> {
+        if self.algorithm.is_none() {
+            return Err(ErrorCode::RESERVE);
+        }
+        if self.busy() {
+            return Err(ErrorCode::BUSY);
+        }
+        self.compute_requested.set(true);
+        self.deferred_call.set();
+        Ok(())
+    }
+
+    fn disable(&self) {
+        self.registers.sniff_ctrl.modify(SNIFF_CTRL::EN::CLEAR);
+    }
+}
@@ -0,0 +1,516 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! DMA channels.
+//!
+//! Each channel copies data between memory and peripheral registers, paced
+//! by the transfer request signal (DREQ) of the peripheral, and calls its
+//! client when the transfer is over. Channels raise `DMA_IRQ_0` unless they
+//! are moved to `DMA_IRQ_1`. A channel can be chained to another one, which
+//! starts the other channel when its transfer is over.
+//!
+//! The drivers of the peripherals claim the channels they use from `Dma`, so
+//! that they share the controller.
+//!
+//! Channel 11 is reserved for the CRC driver, which uses the sniffer of the
+//! controller, so only channels 0 to 10 are exposed here.
+//!
+//! The DMA controller must be taken out of reset by the board.
+
+use core::cell::Cell;
+
+use kernel::utilities::cells::OptionalCell;
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
+use kernel::utilities::registers::{register_bitfields, ReadWrite};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+#[repr(C)]
+pub(crate) struct DmaChannelRegisters {
+    /// DMA channel read address
+    pub(crate) read_addr: ReadWrite<u32>,
+    /// DMA channel write address
+    pub(crate) write_addr: ReadWrite<u32>,
+    /// DMA channel transfer count
+    pub(crate) trans_count: ReadWrite<u32>,
+    /// DMA channel control and status, starting the channel when written
+    pub(crate) ctrl_trig: ReadWrite<u32, CTRL::Register>,
+    /// DMA channel control and status, without starting the channel
+    pub(crate) al1_ctrl: ReadWrite<u32, CTRL::Register>,
+    /// Aliases of the registers above in other orders
+    _aliases: [u32; 11],
+}
+
+#[repr(C)]
+pub(crate) struct DmaRegisters {
+    pub(crate) ch: [DmaChannelRegisters; 12],
+    _reserved0: [u32; 64],
+    /// Raw interrupt status, write 1 to clear
+    pub(crate) intr: ReadWrite<u32>,
+    /// Interrupt enables for IRQ 0
+    pub(crate) inte0: ReadWrite<u32>,
+    /// Force interrupts for IRQ 0
+    pub(crate) intf0: ReadWrite<u32>,
+    /// Interrupt status for IRQ 0
+    pub(crate) ints0: ReadWrite<u32>,
+    _reserved1: u32,
+    /// Interrupt enables for IRQ 1
+    pub(crate) inte1: ReadWrite<u32>,
+    /// Force interrupts for IRQ 1
+    pub(crate) intf1: ReadWrite<u32>,
+    /// Interrupt status for IRQ 1
+    pub(crate) ints1: ReadWrite<u32>,
+    /// Pacing timers
+    pub(crate) timer: [ReadWrite<u32>; 4],
+    /// Trigger several channels at once
+    pub(crate) multi_chan_trigger: ReadWrite<u32>,
+    /// Sniffer control
+    pub(crate) sniff_ctrl: ReadWrite<u32, SNIFF_CTRL::Register>,
+    /// Sniffer data, writing it seeds the calculation
+    pub(crate) sniff_data: ReadWrite<u32>,
+    _reserved2: u32,
+    /// Debug FIFO levels
+    pub(crate) fifo_levels: ReadWrite<u32>,
+    /// Abort channels
+    pub(crate) chan_abort: ReadWrite<u32>,
+}
+
+register_bitfields![u32,
+    pub(crate) CTRL [
+        /// A bus error occurred on the bus
+        AHB_ERROR OFFSET(31) NUMBITS(1) [],
+        /// A read bus error occurred, write 1 to clear
+        READ_ERROR OFFSET(30) NUMBITS(1) [],
+        /// A write bus error occurred, write 1 to clear
+        WRITE_ERROR OFFSET(29) NUMBITS(1) [],
+        /// The channel is transferring
+        BUSY OFFSET(24) NUMBITS(1) [],
+        /// Let the sniffer observe the transfers of the channel
+        SNIFF_EN OFFSET(23) NUMBITS(1) [],
+        /// Swap the bytes of half-word and word transfers
+        BSWAP OFFSET(22) NUMBITS(1) [],
+        /// Only raise the interrupt on chained triggers
+        IRQ_QUIET OFFSET(21) NUMBITS(1) [],
+        /// Transfer request signal
+        TREQ_SEL OFFSET(15) NUMBITS(6) [
+            Permanent = 0x3F
+        ],
+        /// Channel to trigger at the end of the transfer, itself to disable
+        CHAIN_TO OFFSET(11) NUMBITS(4) [],
+        /// Apply the address ring to the write address
+        RING_SEL OFFSET(10) NUMBITS(1) [],
+        /// Size of the address ring, 0 for no ring
+        RING_SIZE OFFSET(6) NUMBITS(4) [],
+        /// Increment the write address
+        INCR_WRITE OFFSET(5) NUMBITS(1) [],
+        /// Increment the read address
+        INCR_READ OFFSET(4) NUMBITS(1) [],
+        /// Size of each transfer
+        DATA_SIZE OFFSET(2) NUMBITS(2) [
+            Byte = 0,
+            HalfWord = 1,
+            Word = 2
+        ],
+        /// Give the channel priority
+        HIGH_PRIORITY OFFSET(1) NUMBITS(1) [],
+        /// Enable the channel
+        EN OFFSET(0) NUMBITS(1) []
+    ],
+    pub(crate) SNIFF_CTRL [
+        /// Invert the result when read
+        OUT_INV OFFSET(11) NUMBITS(1) [],
+        /// Bit-reverse the result when read
+        OUT_REV OFFSET(10) NUMBITS(1) [],
+        /// Swap the bytes of the data
+        BSWAP OFFSET(9) NUMBITS(1) [],
+        /// Calculation
+        CALC OFFSET(5) NUMBITS(4) [
+            Crc32 = 0x0,
+            Crc32BitReversed = 0x1,
+            Crc16Ccitt = 0x2,
+            Crc16CcittBitReversed = 0x3,
+            Xor = 0xE,
+            Sum = 0xF
+        ],
+        /// Channel observed by the sniffer
+        DMACH OFFSET(1) NUMBITS(4) [],
+        /// Enable the sniffer
+        EN OFFSET(0) NUMBITS(1) []
+    ]
+];
+
+pub(crate) const DMA_BASE: StaticRef<DmaRegisters> =
+    unsafe { StaticRef::new(0x5000_0000 as *const DmaRegisters) };
+
+/// Number of channels exposed by `Dma`
+pub const CHANNELS: usize = 11;
+
+/// DMA channel reserved for the CRC calculation
+pub(crate) const CRC_CHANNEL: usize = 11;
+
+/// Transfer request signals of the peripherals.
+///
+/// Extend this to add support for more DMA-powered peripherals, see section
+/// 2.5.3.1, System DREQ Table, of the RP2040 datasheet.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum DmaRequest {
+    Spi0Tx = 16,
+    Spi0Rx = 17,
+    Spi1Tx = 18,
+    Spi1Rx = 19,
+    Uart0Tx = 20,
+    Uart0Rx = 21,
+    Uart1Tx = 22,
+    Uart1Rx = 23,
+    Adc = 36,
+    /// No pacing, for copies between memories
+    Unpaced = 0x3F,
+}
+
+/// Size of each transfer of a channel.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum DataSize {
+    Byte = 0,
+    HalfWord = 1,
+    Word = 2,
+}
+
+/// Interrupt raised by a channel.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DmaIrq {
+    Irq0,
+    Irq1,
+}
+
+/// A type that responds to DMA completion events
+pub trait DmaClient {
+    /// The transfer paced by `request` is over, after `transferred`
+    /// transfers, which are bytes for the buffer transfers. `result` is
+    /// `Err(FAIL)` if the channel hit a bus error.
+    fn transfer_done(&self, request: DmaRequest, result: Result<(), ErrorCode>, transferred: usize);
+}
+
+/// A DMA channel.
+pub struct DmaChannel<'a> {
+    registers: StaticRef<DmaRegisters>,
+    channel: usize,
+    client: OptionalCell<&'a dyn DmaClient>,
+    request: OptionalCell<DmaRequest>,
+    len: Cell<usize>,
+    irq: Cell<DmaIrq>,
+    /// Channel started at the end of the transfers
+    chain_to: OptionalCell<usize>,
+    /// A driver uses the channel
+    claimed: Cell<bool>,
+}
+
+impl<'a> DmaChannel<'a> {
+    const fn new(channel: usize) -> Self {
+        Self {
+            registers: DMA_BASE,
+            channel,
+            client: OptionalCell::empty(),
+            request: OptionalCell::empty(),
+            len: Cell::new(0),
+            irq: Cell::new(DmaIrq::Irq0),
+            chain_to: OptionalCell::empty(),
+            claimed: Cell::new(false),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn DmaClient) {
+        self.client.set(client);
+    }
+
+    /// Raise `irq` at the end of the next transfers.
+    pub fn set_irq(&self, irq: DmaIrq) {
+        self.irq.set(irq);
+    }
+
+    /// Start `next` at the end of the next transfers, or nothing if `next`
+    /// is `None`. `next` is prepared with `prepare`.
+    pub fn chain_to(&self, next: Option<&DmaChannel>) {
+        match next {
+            Some(next) => self.chain_to.set(next.channel),
+            None => self.chain_to.clear(),
+        }
+    }
+
+    /// Number of the channel in the controller.
+    pub fn number(&self) -> usize {
+        self.channel
+    }
+
+    /// Whether the channel is transferring.
+    pub fn is_busy(&self) -> bool {
+        self.registers.ch[self.channel].ctrl_trig.is_set(CTRL::BUSY)
+    }
+
+    /// Start `count` transfers of `size` from `read_addr` to `write_addr`,
+    /// one at each request of `request`, incrementing the addresses after
+    /// each transfer as requested.
+    ///
+    /// # Safety
+    ///
+    /// The memory at both addresses must stay valid until the transfers are
+    /// done or aborted.
+    pub unsafe fn transfer(
+        &self,
+        read_addr: *const u8,
+        increment_read: bool,
+        write_addr: *mut u8,
+        increment_write: bool,
+        count: usize,
+        size: DataSize,
+        request: DmaRequest,
+    ) {
+        self.setup(
+            read_addr as u32,
+            increment_read,
+            write_addr as u32,
+            increment_write,
+            count,
+            size,
+            request,
+            true,
+        );
+    }
+
+    /// Set up transfers like `transfer`, but start them only when a channel
+    /// chained to this one is done, or `trigger` is called.
+    ///
+    /// # Safety
+    ///
+    /// The memory at both addresses must stay valid until the transfers are
+    /// done or aborted.
+    pub unsafe fn prepare(
+        &self,
+        read_addr: *const u8,
+        increment_read: bool,
+        write_addr: *mut u8,
+        increment_write: bool,
+        count: usize,
+        size: DataSize,
+        request: DmaRequest,
+    ) {
+        self.setup(
+            read_addr as u32,
+            increment_read,
+            write_addr as u32,
+            increment_write,
+            count,
+            size,
+            request,
+            false,
+        );
+    }
+
+    /// Start the prepared transfers.
+    pub fn trigger(&self) {
+        self.registers.multi_chan_trigger.set(1 << self.channel);
+    }
+
+    /// Ignore the triggers of the channels chained to this one until the
+    /// next transfers are set up, so that a channel whose transfers are over
+    /// does not restart past the end of its buffer.
+    pub fn disable(&self) {
+        self.registers.ch[self.channel]
+            .al1_ctrl
+            .modify(CTRL::EN::CLEAR);
+    }
+
+    /// Start copying `buffer`, one byte at each request of `request`, to the
+    /// peripheral register at `register`. `buffer` must not be empty.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must stay valid until the transfer is done or aborted.
+    pub unsafe fn transfer_from_buffer(
+        &self,
+        buffer: &[u8],
+        register: *const u8,
+        request: DmaRequest,
+    ) {
+        self.setup(
+            buffer.as_ptr() as u32,
+            true,
+            register as u32,
+            false,
+            buffer.len(),
+            DataSize::Byte,
+            request,
+            true,
+        );
+    }
+
+    /// Start copying the peripheral register at `register`, one byte at each
+    /// request of `request`, to `buffer`. `buffer` must not be empty.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must stay valid until the transfer is done or aborted.
+    pub unsafe fn transfer_to_buffer(
+        &self,
+        register: *const u8,
+        buffer: &mut [u8],
+        request: DmaRequest,
+    ) {
+        self.setup(
+            register as u32,
+            false,
+            buffer.as_mut_ptr() as u32,
+            true,
+            buffer.len(),
+            DataSize::Byte,
+            request,
+            true,
+        );
+    }
+
+    fn setup(
+        &self,
+        read_addr: u32,
+        increment_read: bool,
+        write_addr: u32,
+        increment_write: bool,
+        len: usize,
+        size: DataSize,
+        request: DmaRequest,
+        trigger: bool,
+    ) {
+        let channel = &self.registers.ch[self.channel];
+        channel.read_addr.set(read_addr);
+        channel.write_addr.set(write_addr);
+        channel.trans_count.set(len as u32);
+        self.len.set(len);
+        self.request.set(request);
+
+        let mask = 1 << self.channel;
+        let (enable, disable) = match self.irq.get() {
+            DmaIrq::Irq0 => (&self.registers.inte0, &self.registers.inte1),
+            DmaIrq::Irq1 => (&self.registers.inte1, &self.registers.inte0),
+        };
+        disable.set(disable.get() & !mask);
+        enable.set(enable.get() | mask);
+
+        // A channel chained to itself is not chained
+        let ctrl = CTRL::EN::SET
+            + CTRL::DATA_SIZE.val(size as u32)
+            + CTRL::INCR_READ.val(increment_read as u32)
+            + CTRL::INCR_WRITE.val(increment_write as u32)
+            + CTRL::CHAIN_TO.val(self.chain_to.unwrap_or(self.channel) as u32)
+            + CTRL::TREQ_SEL.val(request as u32);
+        if trigger {
+            channel.ctrl_trig.write(ctrl);
+        } else {
+            channel.al1_ctrl.write(ctrl);
+        }
+    }
+
+    /// Transfers done since the transfers started.
+    fn transferred(&self) -> usize {
+        let remaining = self.registers.ch[self.channel].trans_count.get() as usize;
+        self.len.get().saturating_sub(remaining)
+    }
+
+    /// Stop the transfer without calling the client. Returns the number of
+    /// transfers done.
+    pub fn abort(&self) -> usize {
+        let mask = 1 << self.channel;
+        // An abort can raise the interrupt of the channel, so it is disabled
+        // first (RP2040-E13).
+        let inte = match self.irq.get() {
+            DmaIrq::Irq0 => &self.registers.inte0,
+            DmaIrq::Irq1 => &self.registers.inte1,
+        };
+        inte.set(inte.get() & !mask);
+        self.registers.chan_abort.set(mask);
+        while self.registers.chan_abort.get() & mask != 0 {}
+        self.registers.intr.set(mask);
+        self.transferred()
+    }
+
+    fn handle_interrupt(&self) {
+        self.registers.intr.set(1 << self.channel);
+
+        let channel = &self.registers.ch[self.channel];
+        let result = if channel.ctrl_trig.is_set(CTRL::AHB_ERROR) {
+            channel
+                .ctrl_trig
+                .write(CTRL::READ_ERROR::SET + CTRL::WRITE_ERROR::SET);
+            Err(ErrorCode::FAIL)
+        } else {
+            Ok(())
+        };
+
+        let transferred = self.transferred();
+        self.request.map(|request| {
+            self.client
+                .map(|client| client.transfer_done(*request, result, transferred));
+        });
+    }
+}
+
+/// The DMA controller.
+pub struct Dma<'a> {
+    registers: StaticRef<DmaRegisters>,
+    /// Channels 0 to 10
+    pub channels: [DmaChannel<'a>; CHANNELS],
+}
+
+impl<'a> Dma<'a> {
+    pub const fn new() -> Self {
+        Self {
+            registers: DMA_BASE,
+            channels: [
+                DmaChannel::new(0),
+                DmaChannel::new(1),
+                DmaChannel::new(2),
+                DmaChannel::new(3),
+                DmaChannel::new(4),
+                DmaChannel::new(5),
+                DmaChannel::new(6),
+                DmaChannel::new(7),
+                DmaChannel::new(8),
+                DmaChannel::new(9),
+                DmaChannel::new(10),
+            ],
+        }
+    }
+
+    /// Claim a channel that no other driver uses, if any is left.
+    pub fn claim_channel(&self) -> Option<&DmaChannel<'a>> {
+        let channel = self
+            .channels
+            .iter()
+            .find(|channel| !channel.claimed.get())?;
+        channel.claimed.set(true);
+        Some(channel)
+    }
+
+    /// Give back a channel claimed with `claim_channel`, once its transfers
+    /// are over.
+    pub fn release_channel(&self, channel: &DmaChannel<'a>) {
+        channel.client.clear();
+        channel.chain_to.clear();
+        channel.irq.set(DmaIrq::Irq0);
+        channel.claimed.set(false);
+    }
+
+    /// Handle `irq` for the channels exposed here. The CRC driver handles
+    /// its own channel.
+    pub fn handle_interrupt(&self, irq: DmaIrq) {
+        let status = match irq {
+            DmaIrq::Irq0 => self.registers.ints0.get(),
+            DmaIrq::Irq1 => self.registers.ints1.get(),
+        };
+        for channel in self.channels.iter() {
+            if status & (1 << channel.channel) != 0 {
+                channel.handle_interrupt();
+            }
+        }
+    }
+}
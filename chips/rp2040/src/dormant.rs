@@ -0,0 +1,89 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Dormant mode.
+//!
+//! In dormant mode, the crystal oscillator is stopped and the whole chip is
+//! unclocked until an edge on a wake pin, which brings the current down to
+//! a few hundred microamps. The chip goes dormant instead of sleeping when
+//! idle once a capsule allows deep sleep through the `DeepSleep` trait and a
+//! wake pin is set.
+//!
+//! Before going dormant, clk_ref and clk_sys are switched to the crystal
+//! oscillator and the PLLs are powered down. When the oscillator is stable
+//! again after the wake edge, the PLLs and clocks are restored to their
+//! previous settings before the kernel runs. The timer stops with the
+//! oscillator, so alarms are late by the time spent dormant.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let dormant = static_init!(
+//!     rp2040::dormant::Dormant<'static>,
+//!     rp2040::dormant::Dormant::new(&peripherals.clocks, &peripherals.xosc)
+//! );
+//! let wake_pin = peripherals.pins.get_pin(RPGpio::GPIO14);
+//! wake_pin.make_input();
+//! dormant.set_wake_pin(wake_pin, InterruptEdge::FallingEdge);
+//! chip.set_dormant(dormant);
+//! power_manager.set_deep_sleep(dormant);
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::deep_sleep::DeepSleep;
+use kernel::hil::gpio::InterruptEdge;
+use kernel::utilities::cells::OptionalCell;
+
+use crate::clocks::Clocks;
+use crate::gpio::RPGpioPin;
+use crate::xosc::Xosc;
+
+pub struct Dormant<'a> {
+    clocks: &'a Clocks,
+    xosc: &'a Xosc,
+    wake_pin: OptionalCell<(&'a RPGpioPin<'a>, InterruptEdge)>,
+    allowed: Cell<bool>,
+}
+
+impl<'a> Dormant<'a> {
+    pub fn new(clocks: &'a Clocks, xosc: &'a Xosc) -> Dormant<'a> {
+        Dormant {
+            clocks,
+            xosc,
+            wake_pin: OptionalCell::empty(),
+            allowed: Cell::new(false),
+        }
+    }
+
+    /// Wake the chip up on `edge` of `pin`, which must be an input.
+    pub fn set_wake_pin(&self, pin: &'a RPGpioPin<'a>, edge: InterruptEdge) {
+        self.wake_pin.set((pin, edge));
+    }
+
+    /// Whether the chip may go dormant when idle: a capsule allowed it and
+    /// a wake pin is set, so the chip cannot stay dormant forever.
+    pub fn ready(&self) -> bool {
+        self.allowed.get() && self.wake_pin.is_some()
+    }
+
+    /// Go dormant until the wake edge, and restore the clocks. Must be
+    /// called with interrupts disabled.
+    pub unsafe fn enter(&self) {
+        self.wake_pin.map(|(pin, edge)| {
+            let state = self.clocks.run_from_xosc();
+            pin.set_dormant_wake(Some(*edge));
+            self.xosc.dormant();
+            pin.set_dormant_wake(None);
+            self.clocks.restore(&state);
+        });
+    }
+}
+
+impl DeepSleep for Dormant<'_> {
+    fn set_deep_sleep_allowed(&self, allowed: bool) {
+        self.allowed.set(allowed);
+    }
+}
@@ -0,0 +1,274 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Flash driver for the external QSPI flash the RP2040 executes from.
+//!
+//! The flash is read through the XIP window, and erased and programmed with
+//! serial commands sent by the SSI. The commands are sent by the bootrom
+//! functions, which take the flash out of XIP mode first. Until the flash is
+//! back in XIP mode no code can run from it, so the operations run from RAM
+//! with interrupts disabled, and the CPU is stalled for as long as they take:
+//! about 50 ms to erase and program a page.
+//!
+//! The XIP mode is restored with a copy of the second stage bootloader, which
+//! set up the fast quad reads of the flash at boot. The copy is taken before
+//! the first operation.
+//!
+//! Pages are the 4 KiB sectors of the flash, numbered from the start of the
+//! flash.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let pagebuffer = static_init!(
+//!     rp2040::flash::Rp2040Page,
+//!     rp2040::flash::Rp2040Page::default()
+//! );
+//! ```
+
+use core::cell::Cell;
+use core::ops::{Index, IndexMut};
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
+use kernel::hil;
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::ErrorCode;
+
+use crate::rom::FlashFunctions;
+
+/// Address of the XIP window on the flash.
+pub const FLASH_BASE: usize = 0x1000_0000;
+/// Size of the XIP window, and largest flash.
+pub const FLASH_MAX_SIZE: usize = 0x0100_0000;
+/// Size of the sectors of the flash, which are erased one at a time.
+pub const PAGE_SIZE: usize = 4096;
+
+/// Size of the second stage bootloader, at the start of the flash.
+const BOOT2_WORDS: usize = 64;
+/// The 64 KiB block erase command, for erases of whole blocks.
+const BLOCK_SIZE: u32 = 1 << 16;
+const BLOCK_ERASE_CMD: u8 = 0xD8;
+
+pub struct Rp2040Page(pub [u8; PAGE_SIZE]);
+
+impl Default for Rp2040Page {
+    fn default() -> Self {
+        Self([0; PAGE_SIZE])
+    }
+}
+
+impl Index<usize> for Rp2040Page {
+    type Output = u8;
+
+    fn index(&self, idx: usize) -> &u8 {
+        &self.0[idx]
+    }
+}
+
+impl IndexMut<usize> for Rp2040Page {
+    fn index_mut(&mut self, idx: usize) -> &mut u8 {
+        &mut self.0[idx]
+    }
+}
+
+impl AsMut<[u8]> for Rp2040Page {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+/// Leave the XIP mode, erase `erase_len` bytes and program `program_len`
+/// bytes of `data` at flash offset `offset`, and enter the XIP mode again
+/// with `enter_xip`.
+///
+/// This runs from RAM and calls nothing in flash, not even functions of
+/// `core` which may not be inlined.
+#[inline(never)]
+#[link_section = ".ramfunc.rp2040_flash_operation"]
+unsafe fn flash_operation(
+    rom: &FlashFunctions,
+    enter_xip: unsafe extern "C" fn(),
+    offset: u32,
+    erase_len: usize,
+    data: *const u8,
+    program_len: usize,
+) {
+    (rom.connect_internal_flash)();
+    (rom.flash_exit_xip)();
+    if erase_len != 0 {
+        (rom.flash_range_erase)(offset, erase_len, BLOCK_SIZE, BLOCK_ERASE_CMD);
+    }
+    if program_len != 0 {
+        (rom.flash_range_program)(offset, data, program_len);
+    }
+    (rom.flash_flush_cache)();
+    enter_xip();
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FlashState {
+    Ready,
+    Read,
+    Write,
+    Erase,
+}
+
+pub struct Flash {
+    client: OptionalCell<&'static dyn hil::flash::Client<Flash>>,
+    buffer: TakeCell<'static, Rp2040Page>,
+    state: Cell<FlashState>,
+    /// Copy of the second stage bootloader, run from RAM to enter the XIP
+    /// mode.
+    boot2: Cell<[u32; BOOT2_WORDS]>,
+    boot2_copied: Cell<bool>,
+    deferred_call: DeferredCall,
+}
+
+impl Flash {
+    pub fn new() -> Self {
+        Self {
+            client: OptionalCell::empty(),
+            buffer: TakeCell::empty(),
+            state: Cell::new(FlashState::Ready),
+            boot2: Cell::new([0; BOOT2_WORDS]),
+            boot2_copied: Cell::new(false),
+            deferred_call: DeferredCall::new(),
+        }
+    }
+
+    fn check_page(&self, page_number: usize) -> Result<usize, ErrorCode> {
+        if self.state.get() != FlashState::Ready {
+            Err(ErrorCode::BUSY)
+        } else if page_number >= FLASH_MAX_SIZE / PAGE_SIZE {
+            Err(ErrorCode::INVAL)
+        } else {
+            Ok(page_number * PAGE_SIZE)
+        }
+    }
+
+    /// Erase the page at `offset`, then program `data` into it if any.
+    fn erase_and_program(&self, offset: usize, data: Option<&Rp2040Page>) {
+        if !self.boot2_copied.replace(true) {
+            let mut boot2 = [0; BOOT2_WORDS];
+            for (i, word) in boot2.iter_mut().enumerate() {
+                *word = unsafe { core::ptr::read_volatile((FLASH_BASE as *const u32).add(i)) };
+            }
+            self.boot2.set(boot2);
+        }
+
+        let (data, program_len) = match data {
+            Some(page) => (page.0.as_ptr(), PAGE_SIZE),
+            None => (core::ptr::null(), 0),
+        };
+        unsafe {
+            let rom = FlashFunctions::lookup();
+            // The bootloader is Thumb code, and returns when called with a
+            // link register other than 0.
+            let enter_xip: unsafe extern "C" fn() =
+                core::mem::transmute(self.boot2.as_ptr() as usize + 1);
+            cortexm0p::support::atomic(|| {
+                flash_operation(&rom, enter_xip, offset as u32, PAGE_SIZE, data, program_len)
+            });
+        }
+    }
+
+    fn read_page(
+        &self,
+        page_number: usize,
+        buffer: &'static mut Rp2040Page,
+    ) -> Result<(), (ErrorCode, &'static mut Rp2040Page)> {
+        let offset = match self.check_page(page_number) {
+            Ok(offset) => offset,
+            Err(err) => return Err((err, buffer)),
+        };
+        for i in 0..PAGE_SIZE {
+            buffer[i] = unsafe { core::ptr::read_volatile((FLASH_BASE + offset + i) as *const u8) };
+        }
+
+        self.buffer.replace(buffer);
+        self.state.set(FlashState::Read);
+        self.deferred_call.set();
+        Ok(())
+    }
+
+    fn write_page(
+        &self,
+        page_number: usize,
+        buffer: &'static mut Rp2040Page,
+    ) -> Result<(), (ErrorCode, &'static mut Rp2040Page)> {
+        let offset = match self.check_page(page_number) {
+            Ok(offset) => offset,
+            Err(err) => return Err((err, buffer)),
+        };
+        self.erase_and_program(offset, Some(buffer));
+
+        self.buffer.replace(buffer);
+        self.state.set(FlashState::Write);
+        self.deferred_call.set();
+        Ok(())
+    }
+
+    fn erase_page(&self, page_number: usize) -> Result<(), ErrorCode> {
+        let offset = self.check_page(page_number)?;
+        self.erase_and_program(offset, None);
+
+        self.state.set(FlashState::Erase);
+        self.deferred_call.set();
+        Ok(())
+    }
+}
+
+impl<C: hil::flash::Client<Self>> hil::flash::HasClient<'static, C> for Flash {
+    fn set_client(&self, client: &'static C) {
+        self.client.set(client);
+    }
+}
+
+impl hil::flash::Flash for Flash {
+    type Page = Rp2040Page;
+
+    fn read_page(
+        &self,
+        page_number: usize,
+        buf: &'static mut Self::Page,
+    ) -> Result<(), (ErrorCode, &'static mut Self::Page)> {
+        self.read_page(page_number, buf)
+    }
+
+    fn write_page(
+        &self,
+        page_number: usize,
+        buf: &'static mut Self::Page,
+    ) -> Result<(), (ErrorCode, &'static mut Self::Page)> {
+        self.write_page(page_number, buf)
+    }
+
+    fn erase_page(&self, page_number: usize) -> Result<(), ErrorCode> {
+        self.erase_page(page_number)
+    }
+}
+
+impl DeferredCallClient for Flash {
+    fn handle_deferred_call(&self) {
+        let state = self.state.replace(FlashState::Ready);
+        self.client.map(|client| match state {
+            FlashState::Read => {
+                self.buffer.take().map(|buffer| {
+                    client.read_complete(buffer, hil::flash::Error::CommandComplete);
+                });
+            }
+            FlashState::Write => {
+                self.buffer.take().map(|buffer| {
+                    client.write_complete(buffer, hil::flash::Error::CommandComplete);
+                });
+            }
+            FlashState::Erase => client.erase_complete(hil::flash::Error::CommandComplete),
+            FlashState::Ready => {}
+        });
+    }
+
+    fn register(&'static self) {
+        self.deferred_call.register(self);
+    }
+}
@@ -4,9 +4,15 @@
 
 //! GPIO, RP2040
 //!
+//! Pin interrupts are routed to processor 0 through the IO_IRQ_BANK0
+//! interrupt, on edges with `hil::gpio::Interrupt` or on levels with
+//! `RPGpioPin::enable_level_interrupt`.
+//!
 //! ### Author
 //! * Ioana Culic <ioana.culic@wyliodrin.com>
 
+use core::fmt;
+
 use enum_primitive::cast::FromPrimitive;
 use enum_primitive::enum_from_primitive;
 use kernel::hil;
@@ -341,26 +347,36 @@ impl<'a> RPPins<'a> {
 
     pub fn handle_interrupt(&self) {
         for bank_no in 0..4 {
-            let current_val = self.gpio_registers.intr[bank_no].get();
-            let enabled_val = self.gpio_registers.interrupt_proc[0].enable[bank_no].get();
+            let status = self.gpio_registers.interrupt_proc[0].status[bank_no].get();
+            // Acknowledge the edges before calling the clients, so that an
+            // edge during a callback raises the interrupt again. Levels are
+            // acknowledged by the pin changing level.
+            self.gpio_registers.intr[bank_no].set(status & EDGE_BITS);
             for pin in 0..8 {
-                let l_low_reg_no = pin * 4;
-                if (current_val & enabled_val & (1 << l_low_reg_no)) != 0 {
-                    self.pins[pin + bank_no * 8].handle_interrupt();
-                } else if (current_val & enabled_val & (1 << l_low_reg_no + 1)) != 0 {
-                    self.pins[pin + bank_no * 8].handle_interrupt();
-                } else if (current_val & enabled_val & (1 << l_low_reg_no + 2)) != 0 {
-                    self.gpio_registers.intr[bank_no].set(current_val & (1 << l_low_reg_no + 2));
-                    self.pins[pin + bank_no * 8].handle_interrupt();
-                } else if (current_val & enabled_val & (1 << l_low_reg_no + 3)) != 0 {
-                    self.gpio_registers.intr[bank_no].set(current_val & (1 << l_low_reg_no + 3));
-                    self.pins[pin + bank_no * 8].handle_interrupt();
+                if status & (0b1111 << (pin * 4)) != 0 {
+                    if let Some(gpio) = self.pins.get(pin + bank_no * 8) {
+                        gpio.handle_interrupt();
+                    }
                 }
             }
         }
     }
 }
 
+impl hil::gpio::PinIntrospection for RPPins<'_> {
+    fn num_pins(&self) -> usize {
+        self.pins.len()
+    }
+
+    fn pin(&self, index: usize) -> Option<&dyn hil::gpio::Pin> {
+        self.pins.get(index).map(|pin| pin as &dyn hil::gpio::Pin)
+    }
+
+    fn write_pin_name(&self, index: usize, writer: &mut dyn fmt::Write) -> fmt::Result {
+        writer.write_fmt(format_args!("gpio{}", index))
+    }
+}
+
 enum_from_primitive! {
     #[derive(Copy, Clone, PartialEq)]
     #[repr(usize)]
@@ -391,6 +407,19 @@ enum_from_primitive! {
     }
 }
 
+/// The edge bits of the interrupt registers, which hold four bits per pin:
+/// level low, level high, edge low and edge high.
+const EDGE_BITS: u32 = 0xCCCC_CCCC;
+
+/// Level low interrupt bit of a pin
+const LEVEL_LOW: u32 = 0b0001;
+/// Level high interrupt bit of a pin
+const LEVEL_HIGH: u32 = 0b0010;
+/// Falling edge interrupt bit of a pin
+const EDGE_LOW: u32 = 0b0100;
+/// Rising edge interrupt bit of a pin
+const EDGE_HIGH: u32 = 0b1000;
+
 pub struct RPGpioPin<'a> {
     pin: usize,
     client: OptionalCell<&'a dyn hil::gpio::Client>,
@@ -411,7 +440,14 @@ impl<'a> RPGpioPin<'a> {
     }
 
     fn get_mode(&self) -> hil::gpio::Configuration {
-        //TODO - read alternate function
+        match self.gpio_registers.pin[self.pin]
+            .ctrl
+            .read(GPIOx_CTRL::FUNCSEL)
+        {
+            f if f == GpioFunction::SIO as u32 => {}
+            f if f == GpioFunction::NULL as u32 => return hil::gpio::Configuration::LowPower,
+            _ => return hil::gpio::Configuration::Function,
+        }
         let pad_output_disable = !self.gpio_pad_registers.gpio_pad[self.pin].is_set(GPIO_PAD::OD);
         let pin_mask = 1 << self.pin;
         let sio_output_enable = (self.sio_registers.gpio_oe.read(GPIO_OE::OE) & pin_mask) != 0;
@@ -465,6 +501,46 @@ impl<'a> RPGpioPin<'a> {
         self.client.map(|client| client.fired());
     }
 
+    /// The interrupt bank of the pin, and the offset of its bits
+    fn interrupt_bits(&self) -> (usize, usize) {
+        (self.pin / 8, (self.pin % 8) * 4)
+    }
+
+    /// Route the interrupts of the pin in `bits` to processor 0, replacing
+    /// the interrupts routed before.
+    fn set_interrupts(&self, bits: u32) {
+        let (bank, offset) = self.interrupt_bits();
+        // Drop the edges latched while the interrupts were disabled
+        self.gpio_registers.intr[bank].set((EDGE_LOW | EDGE_HIGH) << offset);
+        let enable = &self.gpio_registers.interrupt_proc[0].enable[bank];
+        enable.set(enable.get() & !(0b1111 << offset) | bits << offset);
+    }
+
+    /// Interrupt while the pin is high if `high` is set, low otherwise.
+    ///
+    /// The interrupt is raised again as soon as the client returns while
+    /// the pin is still at this level, so the client must disable the
+    /// interrupts or make the device release the pin in `fired`.
+    pub fn enable_level_interrupt(&self, high: bool) {
+        self.set_interrupts(if high { LEVEL_HIGH } else { LEVEL_LOW });
+    }
+
+    /// Wake the chip up from dormant mode on `edge`, or stop waking it up
+    /// with `None`. The pin must be an input.
+    pub fn set_dormant_wake(&self, edge: Option<hil::gpio::InterruptEdge>) {
+        let interrupt_bank_no = self.pin / 8;
+        let low_reg_no = (self.pin * 4 + 2) % 32;
+        let high_reg_no = low_reg_no + 1;
+        let bits = match edge {
+            Some(hil::gpio::InterruptEdge::RisingEdge) => 1 << high_reg_no,
+            Some(hil::gpio::InterruptEdge::FallingEdge) => 1 << low_reg_no,
+            Some(hil::gpio::InterruptEdge::EitherEdge) => (1 << high_reg_no) | (1 << low_reg_no),
+            None => 0,
+        };
+        let enable = &self.gpio_registers.wake.enable[interrupt_bank_no];
+        enable.set(enable.get() & !(1 << high_reg_no) & !(1 << low_reg_no) | bits);
+    }
+
     // needed for usb errata https://datasheets.raspberrypi.com/rp2040/rp2040-datasheet.pdf#RP2040-E5
 
     pub fn start_usb_errata(&self) -> (u32, u32) {
@@ -497,57 +573,20 @@ impl<'a> hil::gpio::Interrupt<'a> for RPGpioPin<'a> {
     }
 
     fn is_pending(&self) -> bool {
-        let interrupt_bank_no = self.pin / 8;
-        let l_low_reg_no = (self.pin * 4) % 32;
-        let current_val = self.gpio_registers.interrupt_proc[0].status[interrupt_bank_no].get();
-        if (current_val
-            & (1 << l_low_reg_no)
-            & (1 << l_low_reg_no + 1)
-            & (1 << l_low_reg_no + 2)
-            & (1 << l_low_reg_no + 3))
-            == 0
-        {
-            false
-        } else {
-            true
-        }
+        let (bank, offset) = self.interrupt_bits();
+        self.gpio_registers.interrupt_proc[0].status[bank].get() & (0b1111 << offset) != 0
     }
 
     fn enable_interrupts(&self, mode: hil::gpio::InterruptEdge) {
-        let interrupt_bank_no = self.pin / 8;
-        match mode {
-            hil::gpio::InterruptEdge::RisingEdge => {
-                let high_reg_no = (self.pin * 4 + 3) % 32;
-                let current_val =
-                    self.gpio_registers.interrupt_proc[0].enable[interrupt_bank_no].get();
-                self.gpio_registers.interrupt_proc[0].enable[interrupt_bank_no]
-                    .set((1 << high_reg_no) | current_val);
-            }
-            hil::gpio::InterruptEdge::FallingEdge => {
-                let low_reg_no = (self.pin * 4 + 2) % 32;
-                let current_val =
-                    self.gpio_registers.interrupt_proc[0].enable[interrupt_bank_no].get();
-                self.gpio_registers.interrupt_proc[0].enable[interrupt_bank_no]
-                    .set((1 << low_reg_no) | current_val);
-            }
-            hil::gpio::InterruptEdge::EitherEdge => {
-                let low_reg_no = (self.pin * 4 + 2) % 32;
-                let high_reg_no = low_reg_no + 1;
-                let current_val =
-                    self.gpio_registers.interrupt_proc[0].enable[interrupt_bank_no].get();
-                self.gpio_registers.interrupt_proc[0].enable[interrupt_bank_no]
-                    .set((1 << high_reg_no) | (1 << low_reg_no) | current_val);
-            }
-        }
+        self.set_interrupts(match mode {
+            hil::gpio::InterruptEdge::RisingEdge => EDGE_HIGH,
+            hil::gpio::InterruptEdge::FallingEdge => EDGE_LOW,
+            hil::gpio::InterruptEdge::EitherEdge => EDGE_HIGH | EDGE_LOW,
+        });
     }
 
     fn disable_interrupts(&self) {
-        let interrupt_bank_no = self.pin / 8;
-        let low_reg_no = (self.pin * 4 + 2) % 32;
-        let high_reg_no = low_reg_no + 1;
-        let current_val = self.gpio_registers.interrupt_proc[0].enable[interrupt_bank_no].get();
-        self.gpio_registers.interrupt_proc[0].enable[interrupt_bank_no]
-            .set(current_val & !(1 << high_reg_no) & !(1 << low_reg_no));
+        self.set_interrupts(0);
     }
 }
 
@@ -672,21 +711,32 @@ impl SIO {
         }
     }
 
-    pub fn handle_proc_interrupt(&self, for_processor: Processor) {
-        match for_processor {
-            Processor::Processor0 => {
-                // read data from the fifo
-                self.registers.fifo_rd.get();
-                self.registers.fifo_st.set(0xff);
-            }
-            Processor::Processor1 => {
-                if self.registers.cpuid.get() == 1 {
-                    panic!("Kernel should not run on processor 1");
-                } else {
-                    panic!("SIO_PROC1_IRQ should be ignored for processor 1");
-                }
-            }
-        }
+    /// Whether the FIFO from the other processor holds a value.
+    pub fn fifo_valid(&self) -> bool {
+        self.registers.fifo_st.is_set(FIFO_ST::VLD)
+    }
+
+    /// Whether the FIFO to the other processor has room for a value.
+    pub fn fifo_ready(&self) -> bool {
+        self.registers.fifo_st.is_set(FIFO_ST::RDY)
+    }
+
+    /// Write to the FIFO to the other processor, which must be ready.
+    pub fn fifo_write(&self, value: u32) {
+        self.registers.fifo_wr.set(value);
+    }
+
+    /// Read from the FIFO from the other processor, which must be valid.
+    pub fn fifo_read(&self) -> u32 {
+        self.registers.fifo_rd.get()
+    }
+
+    /// Clear the read when empty and written when full flags, which raise
+    /// the SIO interrupt of the processor until cleared.
+    pub fn fifo_clear_errors(&self) {
+        self.registers
+            .fifo_st
+            .write(FIFO_ST::ROE::SET + FIFO_ST::WOF::SET);
     }
 
     pub fn get_processor(&self) -> Processor {
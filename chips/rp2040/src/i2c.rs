@@ -7,6 +7,7 @@ use crate::resets;
 use core::cell::Cell;
 use kernel::debug;
 use kernel::hil;
+use kernel::hil::statistics::{DriverStatistics, Statistics, StatisticsCounters};
 use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::LocalRegisterCopy;
@@ -257,6 +258,8 @@ pub struct I2c<'a, 'c> {
     rw_index: Cell<i32>,
 
     abort_reason: OptionalCell<LocalRegisterCopy<u32, IC_TX_ABRT_SOURCE::Register>>,
+
+    statistics: StatisticsCounters,
 }
 
 impl<'a, 'c> I2c<'a, 'c> {
@@ -277,6 +280,8 @@ impl<'a, 'c> I2c<'a, 'c> {
             rw_index: Cell::new(0),
 
             abort_reason: OptionalCell::empty(),
+
+            statistics: StatisticsCounters::new(),
         }
     }
 
@@ -444,6 +449,7 @@ impl<'a, 'c> I2c<'a, 'c> {
             .ic_intr_mask
             .modify(IC_INTR_MASK::M_TX_EMPTY::SET);
 
+        self.statistics.start();
         Ok(())
     }
 
@@ -542,9 +548,11 @@ impl<'a, 'c> I2c<'a, 'c> {
         assert!(len >= 1);
 
         self.addr.set(addr);
+        self.write_len.set(0);
         self.read_len.set(len);
         self.start_reading();
 
+        self.statistics.start();
         Ok(())
     }
 
@@ -656,6 +664,14 @@ impl<'a, 'c> I2c<'a, 'c> {
             }
         };
 
+        // The abort source does not tell how many bytes were acknowledged, so
+        // failed transfers count none.
+        let bytes = match status {
+            Ok(()) => (self.write_len.get() + self.read_len.get()) as usize,
+            Err(_) => 0,
+        };
+        self.statistics.finish(&status, bytes);
+
         // Reset state before the callback in case the client wants to start a
         // new command inside the callback
         self.state.set(State::Idle);
@@ -689,6 +705,16 @@ impl<'a, 'c> I2c<'a, 'c> {
     }
 }
 
+impl DriverStatistics for I2c<'_, '_> {
+    fn statistics(&self) -> Statistics {
+        self.statistics.get()
+    }
+
+    fn reset_statistics(&self) {
+        self.statistics.reset();
+    }
+}
+
 impl<'a, 'c> hil::i2c::I2CMaster<'c> for I2c<'a, 'c> {
     fn set_master_client(&self, client: &'c dyn hil::i2c::I2CHwMasterClient) {
         self.client.set(client);
@@ -7,11 +7,17 @@
 pub mod adc;
 pub mod chip;
 pub mod clocks;
+pub mod crc;
+pub mod dma;
+pub mod dormant;
+pub mod flash;
 pub mod gpio;
 pub mod i2c;
 pub mod interrupts;
+pub mod multicore;
 pub mod pwm;
 pub mod resets;
+pub mod rom;
 pub mod spi;
 pub mod sysinfo;
 pub mod test;
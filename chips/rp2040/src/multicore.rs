@@ -0,0 +1,219 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Multicore support: starting processor 1 and exchanging messages with it.
+//!
+//! The kernel runs on processor 0. At reset, processor 1 waits in the boot
+//! ROM until `Multicore::launch_core1` hands it a vector table, a stack and
+//! an entry function through the SIO FIFOs. The code started on processor 1
+//! runs outside of the kernel: it must not use the peripherals the kernel
+//! uses, and talks to the kernel through the FIFOs.
+//!
+//! Each processor has a FIFO of 8 words to the other one. A word written by
+//! one processor raises the SIO interrupt of the other one, SIO_IRQ_PROC0 for
+//! processor 0 and SIO_IRQ_PROC1 for processor 1. The RP2040 has no doorbell
+//! registers, so a word of the FIFO doubles as a doorbell. The kernel passes
+//! the words it receives to the `MulticoreClient`.
+//!
+//! The SIO registers are banked per processor, so the code on processor 1 can
+//! use `send`, `receive` and `handle_interrupt` the same way, from its own
+//! SIO_IRQ_PROC1 handler.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! static mut CORE1_STACK: [usize; 512] = [0; 512];
+//!
+//! unsafe extern "C" fn core1_main() -> ! {
+//!     let multicore = rp2040::multicore::Multicore::new();
+//!     loop {
+//!         if let Some(value) = multicore.receive() {
+//!             while multicore.send(value + 1).is_err() {}
+//!         }
+//!     }
+//! }
+//!
+//! peripherals.multicore.set_client(client);
+//! peripherals.multicore.launch_core1(
+//!     core1_main,
+//!     &mut *core::ptr::addr_of_mut!(CORE1_STACK),
+//!     core::ptr::addr_of!(rp2040::BASE_VECTORS) as *const (),
+//! );
+//! let _ = peripherals.multicore.send(41);
+//! ```
+
+use kernel::utilities::cells::OptionalCell;
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable};
+use kernel::utilities::registers::{register_bitfields, register_structs, ReadOnly, ReadWrite};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+use crate::chip::Processor;
+use crate::gpio::SIO;
+use crate::interrupts;
+
+register_structs! {
+    /// Power-on state machine
+    PsmRegisters {
+        /// Force block out of reset (i.e. power it on)
+        (0x000 => frce_on: ReadWrite<u32, PSM::Register>),
+        /// Force into reset (i.e. power it off)
+        (0x004 => frce_off: ReadWrite<u32, PSM::Register>),
+        /// Set to 1 if this peripheral should be reset when the watchdog fires
+        (0x008 => wdsel: ReadWrite<u32, PSM::Register>),
+        /// Indicates the peripheral's registers are ready to access
+        (0x00c => done: ReadOnly<u32, PSM::Register>),
+        (0x010 => @END),
+    }
+}
+
+register_bitfields![u32,
+    PSM [
+        PROC1 OFFSET(16) NUMBITS(1) [],
+        PROC0 OFFSET(15) NUMBITS(1) []
+    ]
+];
+
+const PSM_BASE: StaticRef<PsmRegisters> =
+    unsafe { StaticRef::new(0x40010000 as *const PsmRegisters) };
+
+/// Receives the words written to the FIFO by the other processor.
+pub trait MulticoreClient {
+    fn message_received(&self, message: u32);
+}
+
+pub struct Multicore<'a> {
+    sio: SIO,
+    psm: StaticRef<PsmRegisters>,
+    client: OptionalCell<&'a dyn MulticoreClient>,
+}
+
+impl<'a> Multicore<'a> {
+    pub const fn new() -> Multicore<'a> {
+        Multicore {
+            sio: SIO::new(),
+            psm: PSM_BASE,
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// Set the client of the words received by the current processor, and
+    /// enable its SIO interrupt.
+    pub fn set_client(&self, client: &'a dyn MulticoreClient) {
+        self.client.set(client);
+        let interrupt = match self.sio.get_processor() {
+            Processor::Processor0 => interrupts::SIO_IRQ_PROC0,
+            Processor::Processor1 => interrupts::SIO_IRQ_PROC1,
+        };
+        unsafe {
+            cortexm0p::nvic::Nvic::new(interrupt).enable();
+        }
+    }
+
+    /// Reset processor 1, which then waits in the boot ROM for
+    /// `launch_core1`.
+    pub fn reset_core1(&self) {
+        self.psm.frce_off.modify(PSM::PROC1::SET);
+        // Reading the register back makes sure the write reached the PSM
+        while !self.psm.frce_off.is_set(PSM::PROC1) {}
+        // Processor 1 drains its FIFO from processor 0, then writes a 0 to
+        // the FIFO to processor 0, which `launch_core1` drains.
+        self.psm.frce_off.modify(PSM::PROC1::CLEAR);
+    }
+
+    /// Reset processor 1 and start it at `entry`, running on `stack` with
+    /// the vector table at `vector_table`. Must be called from processor 0,
+    /// and blocks until processor 1 runs.
+    ///
+    /// Words received during the launch are dropped.
+    ///
+    /// # Safety
+    ///
+    /// `entry` runs outside of the kernel, and must only share state with it
+    /// through the FIFOs. `vector_table` must point to a vector table aligned
+    /// to 256 bytes, which processor 1 uses for its interrupts.
+    pub unsafe fn launch_core1(
+        &self,
+        entry: unsafe extern "C" fn() -> !,
+        stack: &'static mut [usize],
+        vector_table: *const (),
+    ) {
+        self.reset_core1();
+
+        // The stack grows down from its end, which must be aligned to 8
+        // bytes.
+        let stack_pointer = (stack.as_mut_ptr_range().end as usize) & !7;
+        let sequence = [
+            0,
+            0,
+            1,
+            vector_table as u32,
+            stack_pointer as u32,
+            entry as usize as u32,
+        ];
+
+        // The boot ROM echoes each word, and starts over when a 0 is sent.
+        let mut step = 0;
+        while step < sequence.len() {
+            let command = sequence[step];
+            if command == 0 {
+                while self.receive().is_some() {}
+                // Processor 1 may wait for room in the FIFO
+                sev();
+            }
+            while self.send(command).is_err() {}
+            let response = loop {
+                if let Some(response) = self.receive() {
+                    break response;
+                }
+            };
+            step = if response == command { step + 1 } else { 0 };
+        }
+    }
+
+    /// Write `message` to the FIFO to the other processor, or return `BUSY`
+    /// if the FIFO is full.
+    pub fn send(&self, message: u32) -> Result<(), ErrorCode> {
+        if !self.sio.fifo_ready() {
+            return Err(ErrorCode::BUSY);
+        }
+        self.sio.fifo_write(message);
+        // Wake the other processor up if it waits for an event
+        sev();
+        Ok(())
+    }
+
+    /// Read a word from the FIFO from the other processor, if any.
+    pub fn receive(&self) -> Option<u32> {
+        if self.sio.fifo_valid() {
+            Some(self.sio.fifo_read())
+        } else {
+            None
+        }
+    }
+
+    /// Handle the SIO interrupt of the current processor: pass the words
+    /// received to the client, and clear the FIFO errors.
+    pub fn handle_interrupt(&self) {
+        self.sio.fifo_clear_errors();
+        while let Some(message) = self.receive() {
+            self.client.map(|client| client.message_received(message));
+        }
+    }
+}
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+#[inline(always)]
+fn sev() {
+    use core::arch::asm;
+    unsafe {
+        asm!("sev", options(nomem, nostack, preserves_flags));
+    }
+}
+
+#[cfg(not(any(target_arch = "arm", target_os = "none")))]
+fn sev() {
+    unimplemented!()
+}
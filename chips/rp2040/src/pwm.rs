@@ -12,14 +12,25 @@
 //! + Configurable top and compare values
 //! + Independent configuration for each channel and for each output/input pin
 //! + Duty cycle from 0% to 100% **inclusive**
+//! + Pins started and stopped independently
+//!
+//! The two pins of a channel share its counter, so starting a pin sets the frequency of the other
+//! pin of the channel as well. Stopping a pin drives it low, and the channel is disabled once
+//! both of its pins are stopped.
+//!
+//! B pins also implement the PwmCapture HIL, measuring the frequency and duty cycle of an external
+//! signal. The measurement is done in hardware by counting edges and high time on the B pin, and
+//! timestamped with the RP2040 timer.
 //!
 //! # Examples
 //!
 //! The integration tests for Raspberry Pi Pico provide some examples using the driver.
 //! See boards/raspberry_pi_pico/src/test/pwm.rs
 
+use core::cell::Cell;
 use kernel::debug;
 use kernel::hil;
+use kernel::hil::time::{Ticks, Time};
 use kernel::utilities::cells::OptionalCell;
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::{
@@ -30,6 +41,8 @@ use kernel::ErrorCode;
 
 use crate::clocks;
 use crate::gpio::RPGpio;
+use crate::interrupts::PWM_IRQ_WRAP;
+use crate::timer::RPTimer;
 
 register_bitfields![u32,
     CSR [
@@ -316,10 +329,38 @@ impl Default for PwmChannelConfiguration {
 const PWM_BASE: StaticRef<PwmRegisters> =
     unsafe { StaticRef::new(0x40050000 as *const PwmRegisters) };
 
+// Number of rising edges counted while measuring the period of a captured signal
+const CAPTURE_EDGES: u16 = 8;
+
+// Progress of an input capture measurement on a channel
+//
+// Each phase ends after two counter wraps. The first wrap aligns the measurement with the
+// signal, the time between the first and the second wrap is measured with the RP2040 timer.
+#[derive(Clone, Copy)]
+enum CapturePhase {
+    Idle,
+    // Counting CAPTURE_EDGES rising edges of pin B
+    Period {
+        first_wrap_us: Option<u32>,
+    },
+    // Counting system clock cycles while pin B is high, until `window_ns` of high time
+    // has been accumulated
+    Duty {
+        period_ns: u32,
+        window_ns: u32,
+        first_wrap_us: Option<u32>,
+    },
+}
+
 /// Main struct for controlling PWM peripheral
 pub struct Pwm<'a> {
     registers: StaticRef<PwmRegisters>,
     clocks: OptionalCell<&'a clocks::Clocks>,
+    timer: OptionalCell<&'a RPTimer<'a>>,
+    capture_phases: [Cell<CapturePhase>; NUMBER_CHANNELS],
+    capture_clients: [OptionalCell<&'a dyn hil::pwm_capture::PwmCaptureClient>; NUMBER_CHANNELS],
+    // Pins A and B of each channel which are running
+    running_pins: [Cell<(bool, bool)>; NUMBER_CHANNELS],
 }
 
 impl<'a> Pwm<'a> {
@@ -331,9 +372,14 @@ impl<'a> Pwm<'a> {
     /// + Also, if interrupts are required, then an interrupt handler must be set. Otherwise, all
     /// the interrupts will be ignored.
     pub fn new() -> Self {
+        const IDLE: Cell<CapturePhase> = Cell::new(CapturePhase::Idle);
         let pwm = Self {
             registers: PWM_BASE,
             clocks: OptionalCell::empty(),
+            timer: OptionalCell::empty(),
+            capture_phases: [IDLE; NUMBER_CHANNELS],
+            capture_clients: core::array::from_fn(|_| OptionalCell::empty()),
+            running_pins: core::array::from_fn(|_| Cell::new((false, false))),
         };
         pwm.init();
         pwm
@@ -608,6 +654,12 @@ impl<'a> Pwm<'a> {
         self.clocks.set(clocks);
     }
 
+    // This method should be called when resolving dependencies for the
+    // default peripherals. The timer is used to timestamp input capture measurements.
+    pub(crate) fn set_timer(&self, timer: &'a RPTimer<'a>) {
+        self.timer.set(timer);
+    }
+
     // Given a channel number and a channel pin, return a struct that allows controlling it
     fn new_pwm_pin(&'a self, channel_number: ChannelNumber, channel_pin: ChannelPin) -> PwmPin<'a> {
         PwmPin {
@@ -714,19 +766,189 @@ impl<'a> Pwm<'a> {
             self.set_compare_value_b(channel_number, compare_value);
         };
         // Finally, enable the channel
+        self.set_running(channel_number, channel_pin, true);
         self.set_enabled(channel_number, true);
         Ok(())
     }
 
-    // Stop a PWM channel.
+    // Record whether a pin is running, return whether the other pin of the channel is running
+    fn set_running(
+        &self,
+        channel_number: ChannelNumber,
+        channel_pin: ChannelPin,
+        running: bool,
+    ) -> bool {
+        let (a, b) = self.running_pins[channel_number as usize].get();
+        let (pins, other) = match channel_pin {
+            ChannelPin::A => ((running, b), b),
+            ChannelPin::B => ((a, running), a),
+        };
+        self.running_pins[channel_number as usize].set(pins);
+        other
+    }
+
+    // Stop a PWM pin, leaving it low.
+    //
+    // The channel keeps running if its other pin does, otherwise it is disabled. This method does
+    // nothing if the pin was already stopped.
+    fn stop_pwm_pin(
+        &self,
+        channel_number: ChannelNumber,
+        channel_pin: ChannelPin,
+    ) -> Result<(), ErrorCode> {
+        let other_running = self.set_running(channel_number, channel_pin, false);
+        if !other_running {
+            self.set_enabled(channel_number, false);
+            self.set_counter(channel_number, 0);
+        }
+        if channel_pin == ChannelPin::A {
+            self.set_compare_value_a(channel_number, 0);
+        } else {
+            self.set_compare_value_b(channel_number, 0);
+        }
+        Ok(())
+    }
+
+    // Start measuring the signal on pin B of the given channel.
     //
-    // This method does nothing if the PWM channel was already disabled.
+    // The measurement is done in two phases:
     //
-    // Note that disabling a PWM channel may result in disabling multiple PWM pins.
-    fn stop_pwm_channel(&self, channel_number: ChannelNumber) -> Result<(), ErrorCode> {
+    // 1. The counter advances on each rising edge of pin B and wraps every
+    //    CAPTURE_EDGES edges. The time between two wraps gives the period.
+    // 2. The counter advances with the system clock only while pin B is high and
+    //    wraps after a fixed amount of high time (about CAPTURE_EDGES periods). The
+    //    ratio between this high time and the time between two wraps gives the duty
+    //    cycle.
+    fn start_capture(&self, channel_number: ChannelNumber) -> Result<(), ErrorCode> {
+        if self.timer.is_none() {
+            return Err(ErrorCode::OFF);
+        }
+        if !matches!(
+            self.capture_phases[channel_number as usize].get(),
+            CapturePhase::Idle
+        ) {
+            return Err(ErrorCode::BUSY);
+        }
+
+        self.set_enabled(channel_number, false);
+        self.set_div_mode(channel_number, DivMode::Rising);
+        self.set_divider_int_frac(channel_number, 1, 0);
+        self.set_top(channel_number, CAPTURE_EDGES - 1);
+        self.set_counter(channel_number, 0);
+        self.capture_phases[channel_number as usize].set(CapturePhase::Period {
+            first_wrap_us: None,
+        });
+        self.clear_interrupt(channel_number);
+        self.enable_interrupt(channel_number);
+        unsafe {
+            cortexm0p::nvic::Nvic::new(PWM_IRQ_WRAP).enable();
+        }
+        self.set_enabled(channel_number, true);
+        Ok(())
+    }
+
+    // Abort a measurement and restore the channel to free running mode
+    fn stop_capture(&self, channel_number: ChannelNumber) -> Result<(), ErrorCode> {
         self.set_enabled(channel_number, false);
+        self.disable_interrupt(channel_number);
+        self.clear_interrupt(channel_number);
+        self.set_div_mode(channel_number, DivMode::FreeRunning);
+        self.capture_phases[channel_number as usize].set(CapturePhase::Idle);
         Ok(())
     }
+
+    // Current value of the RP2040 timer, in microseconds
+    fn now_us(&self) -> u32 {
+        self.timer.map_or(0, |timer| timer.now().into_u32())
+    }
+
+    fn finish_capture(&self, channel_number: ChannelNumber, result: Result<(u32, u32), ErrorCode>) {
+        let _ = self.stop_capture(channel_number);
+        self.capture_clients[channel_number as usize].map(|client| match result {
+            Ok((period_ns, high_ns)) => client.capture_done(Ok(()), period_ns, high_ns),
+            Err(error) => client.capture_done(Err(error), 0, 0),
+        });
+    }
+
+    // Advance the capture state machine of a channel after a counter wrap
+    fn handle_capture_wrap(&self, channel_number: ChannelNumber) {
+        let now_us = self.now_us();
+        match self.capture_phases[channel_number as usize].get() {
+            CapturePhase::Idle => {}
+            CapturePhase::Period {
+                first_wrap_us: None,
+            } => {
+                self.capture_phases[channel_number as usize].set(CapturePhase::Period {
+                    first_wrap_us: Some(now_us),
+                });
+            }
+            CapturePhase::Period {
+                first_wrap_us: Some(first_wrap_us),
+            } => {
+                let elapsed_us = now_us.wrapping_sub(first_wrap_us) as u64;
+                let period_ns = elapsed_us * 1000 / CAPTURE_EDGES as u64;
+                if period_ns == 0 || period_ns > u32::MAX as u64 {
+                    self.finish_capture(channel_number, Err(ErrorCode::SIZE));
+                    return;
+                }
+
+                // Accumulate roughly as much high time as CAPTURE_EDGES periods last.
+                let system_hz = hil::pwm::Pwm::get_maximum_frequency_hz(self) as u64;
+                let ticks = elapsed_us * system_hz / 1_000_000;
+                let int = ((ticks + u16::MAX as u64) / (u16::MAX as u64 + 1)).clamp(1, 255);
+                let top = (ticks / int).clamp(1, u16::MAX as u64 + 1) - 1;
+                let window_ns = (top + 1) * int * 1_000_000_000 / system_hz;
+
+                self.set_enabled(channel_number, false);
+                self.set_div_mode(channel_number, DivMode::High);
+                self.set_divider_int_frac(channel_number, int as u8, 0);
+                self.set_top(channel_number, top as u16);
+                self.set_counter(channel_number, 0);
+                self.capture_phases[channel_number as usize].set(CapturePhase::Duty {
+                    period_ns: period_ns as u32,
+                    window_ns: window_ns as u32,
+                    first_wrap_us: None,
+                });
+                self.set_enabled(channel_number, true);
+            }
+            CapturePhase::Duty {
+                period_ns,
+                window_ns,
+                first_wrap_us: None,
+            } => {
+                self.capture_phases[channel_number as usize].set(CapturePhase::Duty {
+                    period_ns,
+                    window_ns,
+                    first_wrap_us: Some(now_us),
+                });
+            }
+            CapturePhase::Duty {
+                period_ns,
+                window_ns,
+                first_wrap_us: Some(first_wrap_us),
+            } => {
+                let elapsed_ns = (now_us.wrapping_sub(first_wrap_us) as u64 * 1000).max(1);
+                let high_ns =
+                    (period_ns as u64 * window_ns as u64 / elapsed_ns).min(period_ns as u64) as u32;
+                self.finish_capture(channel_number, Ok((period_ns, high_ns)));
+            }
+        }
+    }
+
+    /// Handle the PWM wrap interrupt
+    ///
+    /// Only channels with an input capture in progress are serviced.
+    pub fn handle_interrupt(&self) {
+        for channel_number in CHANNEL_NUMBERS {
+            if let CapturePhase::Idle = self.capture_phases[channel_number as usize].get() {
+                continue;
+            }
+            if self.get_interrupt_status(channel_number) {
+                self.clear_interrupt(channel_number);
+                self.handle_capture_wrap(channel_number);
+            }
+        }
+    }
 }
 
 /// Implementation of the Hardware Interface Layer (HIL)
@@ -769,6 +991,8 @@ impl hil::pwm::Pwm for Pwm<'_> {
 
     /// Stop the given pin
     ///
+    /// The pin is driven low. The other pin of its channel keeps running.
+    ///
     /// ## Errors
     ///
     /// This method may never fail.
@@ -778,8 +1002,8 @@ impl hil::pwm::Pwm for Pwm<'_> {
     /// It is safe to call this method multiple times on the same pin. If the pin is already
     /// stopped, then it does nothing.
     fn stop(&self, pin: &Self::Pin) -> Result<(), ErrorCode> {
-        let (channel_number, _) = self.gpio_to_pwm(*pin);
-        self.stop_pwm_channel(channel_number)
+        let (channel_number, channel_pin) = self.gpio_to_pwm(*pin);
+        self.stop_pwm_pin(channel_number, channel_pin)
     }
 
     /// Return the maximum value of the frequency in Hz
@@ -853,7 +1077,8 @@ impl hil::pwm::PwmPin for PwmPin<'_> {
 
     /// Same as Pwm::stop
     fn stop(&self) -> Result<(), ErrorCode> {
-        self.pwm_struct.stop_pwm_channel(self.channel_number)
+        self.pwm_struct
+            .stop_pwm_pin(self.channel_number, self.channel_pin)
     }
 
     /// Same as Pwm::get_maximum_frequency_hz
@@ -867,6 +1092,32 @@ impl hil::pwm::PwmPin for PwmPin<'_> {
     }
 }
 
+impl<'a> hil::pwm_capture::PwmCapture<'a> for PwmPin<'a> {
+    fn set_client(&self, client: &'a dyn hil::pwm_capture::PwmCaptureClient) {
+        self.pwm_struct.capture_clients[self.channel_number as usize].set(client);
+    }
+
+    /// Start measuring the signal on this pin
+    ///
+    /// ## Errors
+    ///
+    /// + INVAL: only B pins can be used as inputs
+    /// + BUSY: a measurement is already running on this channel
+    fn start_capture(&self) -> Result<(), ErrorCode> {
+        if self.channel_pin != ChannelPin::B {
+            return Err(ErrorCode::INVAL);
+        }
+        self.pwm_struct.start_capture(self.channel_number)
+    }
+
+    fn stop_capture(&self) -> Result<(), ErrorCode> {
+        if self.channel_pin != ChannelPin::B {
+            return Err(ErrorCode::INVAL);
+        }
+        self.pwm_struct.stop_capture(self.channel_number)
+    }
+}
+
 /// Unit tests
 ///
 /// This module provides unit tests for the PWM driver.
@@ -1270,6 +1521,30 @@ pub mod unit_tests {
         assert!(pwm
             .start_pwm_pin(channel_number, channel_pin, max_freq_hz, max_duty_cycle + 1)
             .is_err());
+
+        // Stopping a pin leaves the other pin of the channel running
+        let (_, other_pin) = pwm.gpio_to_pwm(RPGpio::GPIO25);
+        assert!(pwm
+            .start_pwm_pin(
+                channel_number,
+                other_pin,
+                max_freq_hz / 4,
+                max_duty_cycle / 2
+            )
+            .is_ok());
+        assert!(pwm.stop_pwm_pin(channel_number, channel_pin).is_ok());
+        assert_eq!(pwm.registers.ch[channel_number as usize].cc.read(CC::A), 0);
+        assert_eq!(pwm.registers.ch[channel_number as usize].cc.read(CC::B), 2);
+        assert_eq!(
+            pwm.registers.ch[channel_number as usize].csr.read(CSR::EN),
+            1
+        );
+        assert!(pwm.stop_pwm_pin(channel_number, other_pin).is_ok());
+        assert_eq!(pwm.registers.ch[channel_number as usize].cc.read(CC::B), 0);
+        assert_eq!(
+            pwm.registers.ch[channel_number as usize].csr.read(CSR::EN),
+            0
+        );
         debug!("PWM HIL trait OK")
     }
 
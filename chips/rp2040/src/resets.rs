@@ -2,6 +2,14 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 // Copyright Tock Contributors 2022.
 
+//! Peripheral resets, RP2040
+//!
+//! Peripherals are held in reset until they are taken out of it. The boards
+//! reset and release most peripherals while the clocks are set up, and the
+//! drivers of the UART, SPI, I2C, ADC and USB controller take their
+//! peripheral out of reset when they are initialized, as they cannot assume
+//! the boot ROM did.
+
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::{register_bitfields, register_structs, FieldValue, ReadWrite};
 use kernel::utilities::StaticRef;
@@ -178,6 +186,7 @@ register_bitfields![u32,
 const RESETS_BASE: StaticRef<ResetsRegisters> =
     unsafe { StaticRef::new(0x4000C000 as *const ResetsRegisters) };
 
+#[derive(Clone, Copy, PartialEq)]
 pub enum Peripheral {
     Adc,
     BusController,
@@ -309,7 +318,8 @@ impl Resets {
         }
     }
 
-    pub fn reset(&self, peripherals: &'static [Peripheral]) {
+    /// Hold `peripherals` in reset
+    pub fn reset(&self, peripherals: &[Peripheral]) {
         if peripherals.len() > 0 {
             let mut value: FieldValue<u32, RESET::Register> = peripherals[0].get_reset_field_set();
             for peripheral in peripherals {
@@ -319,7 +329,9 @@ impl Resets {
         }
     }
 
-    pub fn unreset(&self, peripherals: &'static [Peripheral], wait_for: bool) {
+    /// Take `peripherals` out of reset, and wait until they are out of it
+    /// if `wait_for` is set
+    pub fn unreset(&self, peripherals: &[Peripheral], wait_for: bool) {
         if peripherals.len() > 0 {
             let mut value: FieldValue<u32, RESET::Register> =
                 peripherals[0].get_reset_field_clear();
@@ -0,0 +1,81 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Functions of the bootrom.
+//!
+//! The bootrom exports its functions through a table of two character codes,
+//! searched with the lookup function the bootrom also exports. The addresses
+//! of the table and of the lookup function are stored as 16-bit pointers at
+//! fixed addresses at the start of the bootrom.
+
+/// Address of the pointer to the table of public functions.
+const ROM_FUNC_TABLE: usize = 0x0000_0014;
+/// Address of the pointer to the table lookup function.
+const ROM_TABLE_LOOKUP: usize = 0x0000_0018;
+
+type RomTableLookup = unsafe extern "C" fn(table: *const u16, code: u32) -> *const ();
+
+/// Address of the bootrom function with the two character `code`, or null if
+/// there is none.
+unsafe fn rom_func_lookup(code: [u8; 2]) -> *const () {
+    let lookup: RomTableLookup =
+        core::mem::transmute(core::ptr::read_volatile(ROM_TABLE_LOOKUP as *const u16) as usize);
+    let table = core::ptr::read_volatile(ROM_FUNC_TABLE as *const u16) as usize as *const u16;
+    lookup(table, u16::from_le_bytes(code) as u32)
+}
+
+/// Reset the chip into the USB bootloader of the bootrom, which appears as a
+/// UF2 mass storage device and as the PICOBOOT interface, as if the BOOTSEL
+/// button was held.
+///
+/// `gpio_activity_pin_mask` selects a GPIO pin the bootrom lights when the
+/// mass storage device is accessed, or 0 for none. `disable_interface_mask`
+/// disables the mass storage interface with bit 0, and the PICOBOOT interface
+/// with bit 1.
+///
+/// The bootrom stores the request in the watchdog scratch registers and
+/// resets the chip with the watchdog.
+///
+/// # Safety
+///
+/// Resets the chip: everything the kernel and the processes were doing is
+/// lost.
+pub unsafe fn reset_to_usb_boot(gpio_activity_pin_mask: u32, disable_interface_mask: u32) -> ! {
+    let reset_to_usb_boot: unsafe extern "C" fn(u32, u32) -> ! =
+        core::mem::transmute(rom_func_lookup(*b"UB"));
+    reset_to_usb_boot(gpio_activity_pin_mask, disable_interface_mask)
+}
+
+/// The bootrom functions driving the external flash through the SSI.
+///
+/// While they run the flash cannot be read through the XIP, so they must be
+/// called from RAM with interrupts disabled.
+#[derive(Clone, Copy)]
+pub(crate) struct FlashFunctions {
+    /// Restore the QSPI pads and the SSI to their reset state.
+    pub connect_internal_flash: unsafe extern "C" fn(),
+    /// Leave the XIP mode, the flash then accepts serial commands.
+    pub flash_exit_xip: unsafe extern "C" fn(),
+    /// Erase the `count` bytes at flash offset `addr`, with the `block_cmd`
+    /// command for blocks of `block_size` bytes and 4 KiB sector erases
+    /// otherwise.
+    pub flash_range_erase:
+        unsafe extern "C" fn(addr: u32, count: usize, block_size: u32, block_cmd: u8),
+    /// Program the `count` bytes at flash offset `addr`, a multiple of 256.
+    pub flash_range_program: unsafe extern "C" fn(addr: u32, data: *const u8, count: usize),
+    /// Flush and enable the XIP cache.
+    pub flash_flush_cache: unsafe extern "C" fn(),
+}
+
+impl FlashFunctions {
+    pub(crate) unsafe fn lookup() -> FlashFunctions {
+        FlashFunctions {
+            connect_internal_flash: core::mem::transmute(rom_func_lookup(*b"IF")),
+            flash_exit_xip: core::mem::transmute(rom_func_lookup(*b"EX")),
+            flash_range_erase: core::mem::transmute(rom_func_lookup(*b"RE")),
+            flash_range_program: core::mem::transmute(rom_func_lookup(*b"RP")),
+            flash_flush_cache: core::mem::transmute(rom_func_lookup(*b"FC")),
+        }
+    }
+}
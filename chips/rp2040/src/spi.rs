@@ -3,6 +3,8 @@
 // Copyright Tock Contributors 2022.
 
 use crate::clocks;
+use crate::dma::{DataSize, DmaChannel, DmaClient, DmaRequest};
+use crate::resets;
 use core::cell::Cell;
 use core::cmp;
 use kernel::hil;
@@ -10,6 +12,7 @@ use kernel::hil::gpio::Output;
 use kernel::hil::spi::SpiMaster;
 use kernel::hil::spi::SpiMasterClient;
 use kernel::hil::spi::{ClockPhase, ClockPolarity};
+use kernel::hil::statistics::{DriverStatistics, Statistics, StatisticsCounters};
 use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::{register_bitfields, register_structs, ReadOnly, ReadWrite};
@@ -21,6 +24,11 @@ const SPI_WRITE_IN_PROGRESS: u8 = 0b010;
 const SPI_IN_PROGRESS: u8 = 0b100;
 const SPI_IDLE: u8 = 0b000;
 
+/// Transfers at least this long are made with DMA, if the SPI has DMA
+/// channels. Shorter ones are made with interrupts, which is cheaper than
+/// setting up the channels.
+const DMA_MIN_LEN: usize = 16;
+
 register_structs! {
     /// controls SPI port
     SpiRegisters {
@@ -237,6 +245,8 @@ const SPI1_BASE: StaticRef<SpiRegisters> =
 pub struct Spi<'a> {
     registers: StaticRef<SpiRegisters>,
     clocks: OptionalCell<&'a clocks::Clocks>,
+    resets: OptionalCell<&'a resets::Resets>,
+    reset: resets::Peripheral,
     master_client: OptionalCell<&'a dyn hil::spi::SpiMasterClient>,
     active_slave: OptionalCell<&'a crate::gpio::RPGpioPin<'a>>,
 
@@ -249,6 +259,17 @@ pub struct Spi<'a> {
 
     transfers: Cell<u8>,
     active_after: Cell<bool>,
+
+    tx_dma: OptionalCell<&'a DmaChannel<'a>>,
+    tx_dma_request: DmaRequest,
+    rx_dma: OptionalCell<&'a DmaChannel<'a>>,
+    rx_dma_request: DmaRequest,
+    /// The transfer in progress is made with DMA
+    dma_in_progress: Cell<bool>,
+    /// Where the receive channel puts the bytes when there is no read buffer
+    rx_sink: Cell<u8>,
+
+    statistics: StatisticsCounters,
 }
 
 impl<'a> Spi<'a> {
@@ -256,6 +277,8 @@ impl<'a> Spi<'a> {
         Self {
             registers: SPI0_BASE,
             clocks: OptionalCell::empty(),
+            resets: OptionalCell::empty(),
+            reset: resets::Peripheral::Spi0,
             master_client: OptionalCell::empty(),
             active_slave: OptionalCell::empty(),
 
@@ -269,6 +292,15 @@ impl<'a> Spi<'a> {
 
             transfers: Cell::new(SPI_IDLE),
             active_after: Cell::new(false),
+
+            tx_dma: OptionalCell::empty(),
+            tx_dma_request: DmaRequest::Spi0Tx,
+            rx_dma: OptionalCell::empty(),
+            rx_dma_request: DmaRequest::Spi0Rx,
+            dma_in_progress: Cell::new(false),
+            rx_sink: Cell::new(0),
+
+            statistics: StatisticsCounters::new(),
         }
     }
 
@@ -276,6 +308,8 @@ impl<'a> Spi<'a> {
         Self {
             registers: SPI1_BASE,
             clocks: OptionalCell::empty(),
+            resets: OptionalCell::empty(),
+            reset: resets::Peripheral::Spi1,
             master_client: OptionalCell::empty(),
             active_slave: OptionalCell::empty(),
 
@@ -289,6 +323,15 @@ impl<'a> Spi<'a> {
 
             transfers: Cell::new(SPI_IDLE),
             active_after: Cell::new(false),
+
+            tx_dma: OptionalCell::empty(),
+            tx_dma_request: DmaRequest::Spi1Tx,
+            rx_dma: OptionalCell::empty(),
+            rx_dma_request: DmaRequest::Spi1Rx,
+            dma_in_progress: Cell::new(false),
+            rx_sink: Cell::new(0),
+
+            statistics: StatisticsCounters::new(),
         }
     }
 
@@ -296,6 +339,23 @@ impl<'a> Spi<'a> {
         self.clocks.set(clocks);
     }
 
+    pub(crate) fn set_resets(&self, resets: &'a resets::Resets) {
+        self.resets.set(resets);
+    }
+
+    /// Make long transfers with the `tx` and `rx` DMA channels.
+    pub(crate) fn set_dma_channels(&'a self, tx: &'a DmaChannel<'a>, rx: &'a DmaChannel<'a>) {
+        tx.set_client(self);
+        rx.set_client(self);
+        self.tx_dma.set(tx);
+        self.rx_dma.set(rx);
+    }
+
+    /// Address of the data register, for the DMA channels.
+    fn data_register(&self) -> *const u8 {
+        &self.registers.sspdr as *const ReadWrite<u32, SSPDR::Register> as *const u8
+    }
+
     fn enable(&self) {
         self.registers.sspcr1.modify(SSPCR1::SSE::SET);
     }
@@ -305,6 +365,11 @@ impl<'a> Spi<'a> {
     }
 
     pub fn handle_interrupt(&self) {
+        // The DMA channels move the data of this transfer.
+        if self.dma_in_progress.get() {
+            return;
+        }
+
         if self.registers.sspsr.is_set(SSPSR::TFE) {
             // if transmit fifo empty is set
             if self.tx_buffer.is_some() {
@@ -344,19 +409,55 @@ impl<'a> Spi<'a> {
         }
 
         if self.transfers.get() == SPI_IN_PROGRESS {
-            if !self.active_after.get() {
-                self.active_slave.map(|p| {
-                    p.set();
-                });
-            }
-            self.master_client.map(|client| {
-                self.registers.sspimsc.modify(SSPIMSC::TXIM::CLEAR);
-                self.registers.sspimsc.modify(SSPIMSC::RXIM::CLEAR);
-                self.disable();
-                self.transfers.set(SPI_IDLE);
-                self.tx_buffer.take().map(|buf| {
-                    client.read_write_done(buf, self.rx_buffer.take(), self.len.get(), Ok(()))
-                });
+            self.finish_transfer(Ok(()));
+        }
+    }
+
+    /// Release the chip select, unless it is held, and give the buffers back
+    /// to the client.
+    fn finish_transfer(&self, result: Result<(), ErrorCode>) {
+        self.statistics.finish(&result, self.len.get());
+        if !self.active_after.get() {
+            self.active_slave.map(|p| {
+                p.set();
+            });
+        }
+        self.master_client.map(|client| {
+            self.registers.sspimsc.modify(SSPIMSC::TXIM::CLEAR);
+            self.registers.sspimsc.modify(SSPIMSC::RXIM::CLEAR);
+            self.disable();
+            self.transfers.set(SPI_IDLE);
+            self.dma_in_progress.set(false);
+            self.tx_buffer.take().map(|buf| {
+                client.read_write_done(buf, self.rx_buffer.take(), self.len.get(), result)
+            });
+        });
+    }
+
+    /// Start the transfer of `count` bytes of the buffers with the DMA
+    /// channels. The receive channel completes the transfer, as the last
+    /// byte is received after it is sent.
+    fn start_dma(&self, tx: &DmaChannel, rx: &DmaChannel, count: usize) {
+        self.dma_in_progress.set(true);
+        self.transfers.set(SPI_IN_PROGRESS);
+        self.len.set(count);
+        let rx_addr = self
+            .rx_buffer
+            .map_or(self.rx_sink.as_ptr(), |buf| buf.as_mut_ptr());
+        // The buffers stay with the driver until the channels are done or
+        // aborted.
+        unsafe {
+            rx.transfer(
+                self.data_register(),
+                false,
+                rx_addr,
+                self.rx_buffer.is_some(),
+                count,
+                DataSize::Byte,
+                self.rx_dma_request,
+            );
+            self.tx_buffer.map(|buf| {
+                tx.transfer_from_buffer(&buf[..count], self.data_register(), self.tx_dma_request)
             });
         }
     }
@@ -406,6 +507,19 @@ impl<'a> Spi<'a> {
                     .set(self.transfers.get() | SPI_READ_IN_PROGRESS);
             }
 
+            let dma = match (self.tx_dma.extract(), self.rx_dma.extract()) {
+                (Some(tx), Some(rx)) if count >= DMA_MIN_LEN && write_buffer.is_some() => {
+                    Some((tx, rx))
+                }
+                _ => None,
+            };
+            if let Some((tx, rx)) = dma {
+                read_buffer.map(|buf| self.rx_buffer.replace(buf));
+                write_buffer.map(|buf| self.tx_buffer.replace(buf));
+                self.start_dma(tx, rx, count);
+                return Ok(());
+            }
+
             read_buffer.map(|buf| {
                 self.rx_buffer.replace(buf);
                 self.len.set(count);
@@ -485,6 +599,36 @@ impl<'a> Spi<'a> {
     }
 }
 
+impl DmaClient for Spi<'_> {
+    fn transfer_done(
+        &self,
+        request: DmaRequest,
+        result: Result<(), ErrorCode>,
+        _transferred: usize,
+    ) {
+        if !self.dma_in_progress.get() {
+            return;
+        }
+        if request == self.rx_dma_request {
+            self.finish_transfer(result);
+        } else if request == self.tx_dma_request && result.is_err() {
+            // Nothing more will be received.
+            self.rx_dma.map(|dma| dma.abort());
+            self.finish_transfer(result);
+        }
+    }
+}
+
+impl DriverStatistics for Spi<'_> {
+    fn statistics(&self) -> Statistics {
+        self.statistics.get()
+    }
+
+    fn reset_statistics(&self) {
+        self.statistics.reset();
+    }
+}
+
 impl<'a> SpiMaster<'a> for Spi<'a> {
     type ChipSelect = &'a crate::gpio::RPGpioPin<'a>;
 
@@ -493,6 +637,9 @@ impl<'a> SpiMaster<'a> for Spi<'a> {
     }
 
     fn init(&self) -> Result<(), ErrorCode> {
+        self.resets
+            .map(|resets| resets.unreset(&[self.reset], true));
+
         match self.set_rate(16 * 1000 * 1000) {
             Err(error) => Err(error),
             Ok(_) => Ok(()),
@@ -530,7 +677,10 @@ impl<'a> SpiMaster<'a> for Spi<'a> {
             Err((error, some_write_buffer, read_buffer)) => {
                 Err((error, some_write_buffer.unwrap(), read_buffer))
             }
-            Ok(()) => Ok(()),
+            Ok(()) => {
+                self.statistics.start();
+                Ok(())
+            }
         }
     }
 
@@ -2,10 +2,19 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 // Copyright Tock Contributors 2022.
 
+//! 64-bit microsecond timer.
+//!
+//! The hardware counter is 64 bits wide, so `RPTimer` uses `Ticks64` and
+//! never wraps in practice. The alarm comparator only matches the lower 32
+//! bits of the counter: alarms further than 2^32 us (about 71 minutes) in
+//! the future are re-armed when the lower bits match until the full 64-bit
+//! deadline has been reached.
+
+use core::cell::Cell;
 use cortexm0p;
 use cortexm0p::support::atomic;
 use kernel::hil;
-use kernel::hil::time::{Alarm, Ticks, Ticks32, Time};
+use kernel::hil::time::{Alarm, Ticks, Ticks64, Time};
 use kernel::utilities::cells::OptionalCell;
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::{
@@ -174,6 +183,9 @@ const TIMER_BASE: StaticRef<TimerRegisters> =
 pub struct RPTimer<'a> {
     registers: StaticRef<TimerRegisters>,
     client: OptionalCell<&'a dyn hil::time::AlarmClient>,
+    /// Full 64-bit deadline of the alarm, the hardware only compares the
+    /// lower 32 bits.
+    expiration: Cell<u64>,
 }
 
 impl<'a> RPTimer<'a> {
@@ -181,6 +193,7 @@ impl<'a> RPTimer<'a> {
         RPTimer {
             registers: TIMER_BASE,
             client: OptionalCell::empty(),
+            expiration: Cell::new(0),
         }
     }
 
@@ -219,16 +232,29 @@ impl<'a> RPTimer<'a> {
 
     pub fn handle_interrupt(&self) {
         self.registers.intr.modify(INTR::ALARM_0::SET);
+        if self.now().into_u64() < self.expiration.get() {
+            // Only the lower 32 bits matched, wait for them to match again.
+            self.registers.alarm0.set(self.expiration.get() as u32);
+            return;
+        }
         self.client.map(|client| client.alarm());
     }
 }
 
 impl Time for RPTimer<'_> {
     type Frequency = hil::time::Freq1MHz;
-    type Ticks = Ticks32;
+    type Ticks = Ticks64;
 
     fn now(&self) -> Self::Ticks {
-        Self::Ticks::from(self.registers.timerawl.get())
+        // The raw registers have no latching, read the high word twice to
+        // detect the lower word wrapping in between.
+        loop {
+            let high = self.registers.timerawh.get();
+            let low = self.registers.timerawl.get();
+            if high == self.registers.timerawh.get() {
+                return Self::Ticks::from(((high as u64) << 32) | low as u64);
+            }
+        }
     }
 }
 
@@ -248,13 +274,14 @@ impl<'a> Alarm<'a> for RPTimer<'a> {
             expire = now.wrapping_add(self.minimum_dt());
         }
 
+        self.expiration.set(expire.into_u64());
         self.registers.alarm0.set(expire.into_u32());
         self.enable_timer_interrupt();
         self.enable_interrupt();
     }
 
     fn get_alarm(&self) -> Self::Ticks {
-        Self::Ticks::from(self.registers.alarm0.get())
+        Self::Ticks::from(self.expiration.get())
     }
 
     fn disarm(&self) -> Result<(), ErrorCode> {
@@ -279,6 +306,6 @@ impl<'a> Alarm<'a> for RPTimer<'a> {
     }
 
     fn minimum_dt(&self) -> Self::Ticks {
-        Self::Ticks::from(50)
+        Self::Ticks::from(50u32)
     }
 }
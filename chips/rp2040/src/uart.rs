@@ -5,17 +5,22 @@
 use core::cell::Cell;
 use kernel::deferred_call::{DeferredCall, DeferredCallClient};
 use kernel::hil;
+use kernel::hil::statistics::{DriverStatistics, Statistics, StatisticsCounters};
 use kernel::hil::uart::ReceiveClient;
 use kernel::hil::uart::{
     Configure, Parameters, Parity, Receive, StopBits, Transmit, TransmitClient, Width,
 };
 use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
-use kernel::utilities::registers::{register_bitfields, register_structs, ReadOnly, ReadWrite};
+use kernel::utilities::registers::{
+    register_bitfields, register_structs, LocalRegisterCopy, ReadOnly, ReadWrite,
+};
 use kernel::utilities::StaticRef;
 use kernel::ErrorCode;
 
 use crate::clocks;
+use crate::dma::{DmaChannel, DmaClient, DmaRequest};
+use crate::resets;
 
 register_structs! {
     /// controls serial port
@@ -362,6 +367,8 @@ register_bitfields! [u32,
 enum UARTStateTX {
     Idle,
     Transmitting,
+    TransmittingWord,
+    TransmittingDma,
     AbortRequested,
 }
 
@@ -369,6 +376,8 @@ enum UARTStateTX {
 enum UARTStateRX {
     Idle,
     Receiving,
+    ReceivingWord,
+    ReceivingDma,
     AbortRequested,
 }
 
@@ -378,9 +387,16 @@ const UART0_BASE: StaticRef<UartRegisters> =
 const UART1_BASE: StaticRef<UartRegisters> =
     unsafe { StaticRef::new(0x40038000 as *const UartRegisters) };
 
+/// Buffers at least this long are transferred with DMA, if the UART has DMA
+/// channels. Shorter ones are sent with interrupts, which is cheaper than
+/// setting up a channel.
+const DMA_MIN_LEN: usize = 16;
+
 pub struct Uart<'a> {
     registers: StaticRef<UartRegisters>,
     clocks: OptionalCell<&'a clocks::Clocks>,
+    resets: OptionalCell<&'a resets::Resets>,
+    reset: resets::Peripheral,
 
     tx_client: OptionalCell<&'a dyn TransmitClient>,
     rx_client: OptionalCell<&'a dyn ReceiveClient>,
@@ -395,7 +411,14 @@ pub struct Uart<'a> {
     rx_len: Cell<usize>,
     rx_status: Cell<UARTStateRX>,
 
+    tx_dma: OptionalCell<&'a DmaChannel<'a>>,
+    tx_dma_request: DmaRequest,
+    rx_dma: OptionalCell<&'a DmaChannel<'a>>,
+    rx_dma_request: DmaRequest,
+
     deferred_call: DeferredCall,
+
+    statistics: StatisticsCounters,
 }
 
 impl<'a> Uart<'a> {
@@ -403,6 +426,8 @@ impl<'a> Uart<'a> {
         Self {
             registers: UART0_BASE,
             clocks: OptionalCell::empty(),
+            resets: OptionalCell::empty(),
+            reset: resets::Peripheral::Uart0,
 
             tx_client: OptionalCell::empty(),
             rx_client: OptionalCell::empty(),
@@ -417,13 +442,22 @@ impl<'a> Uart<'a> {
             rx_len: Cell::new(0),
             rx_status: Cell::new(UARTStateRX::Idle),
 
+            tx_dma: OptionalCell::empty(),
+            tx_dma_request: DmaRequest::Uart0Tx,
+            rx_dma: OptionalCell::empty(),
+            rx_dma_request: DmaRequest::Uart0Rx,
+
             deferred_call: DeferredCall::new(),
+
+            statistics: StatisticsCounters::new(),
         }
     }
     pub fn new_uart1() -> Self {
         Self {
             registers: UART1_BASE,
             clocks: OptionalCell::empty(),
+            resets: OptionalCell::empty(),
+            reset: resets::Peripheral::Uart1,
 
             tx_client: OptionalCell::empty(),
             rx_client: OptionalCell::empty(),
@@ -437,7 +471,14 @@ impl<'a> Uart<'a> {
             rx_len: Cell::new(0),
             rx_status: Cell::new(UARTStateRX::Idle),
 
+            tx_dma: OptionalCell::empty(),
+            tx_dma_request: DmaRequest::Uart1Tx,
+            rx_dma: OptionalCell::empty(),
+            rx_dma_request: DmaRequest::Uart1Rx,
+
             deferred_call: DeferredCall::new(),
+
+            statistics: StatisticsCounters::new(),
         }
     }
 
@@ -445,6 +486,23 @@ impl<'a> Uart<'a> {
         self.clocks.set(clocks);
     }
 
+    pub(crate) fn set_resets(&self, resets: &'a resets::Resets) {
+        self.resets.set(resets);
+    }
+
+    /// Transfer long buffers with the `tx` and `rx` DMA channels.
+    pub(crate) fn set_dma_channels(&'a self, tx: &'a DmaChannel<'a>, rx: &'a DmaChannel<'a>) {
+        tx.set_client(self);
+        rx.set_client(self);
+        self.tx_dma.set(tx);
+        self.rx_dma.set(rx);
+    }
+
+    /// Address of the data register, for the DMA channels.
+    fn data_register(&self) -> *const u8 {
+        &self.registers.uartdr as *const ReadWrite<u32, UARTDR::Register> as *const u8
+    }
+
     pub fn enable(&self) {
         self.registers.uartcr.modify(UARTCR::UARTEN::SET);
     }
@@ -464,11 +522,38 @@ impl<'a> Uart<'a> {
     pub fn enable_receive_interrupt(&self) {
         self.registers.uartifls.modify(UARTIFLS::RXIFLSEL::FIFO_1_8);
 
-        self.registers.uartimsc.modify(UARTIMSC::RXIM::SET);
+        self.registers
+            .uartimsc
+            .modify(UARTIMSC::RXIM::SET + UARTIMSC::RTIM::SET);
     }
 
     pub fn disable_receive_interrupt(&self) {
-        self.registers.uartimsc.modify(UARTIMSC::RXIM::CLEAR);
+        self.registers
+            .uartimsc
+            .modify(UARTIMSC::RXIM::CLEAR + UARTIMSC::RTIM::CLEAR);
+    }
+
+    /// Receive errors are only flagged by interrupts while a DMA channel reads
+    /// the data register. An error also stops the DMA requests.
+    fn enable_receive_error_interrupt(&self) {
+        self.registers.uarticr.write(
+            UARTICR::FEIC::SET + UARTICR::PEIC::SET + UARTICR::BEIC::SET + UARTICR::OEIC::SET,
+        );
+        self.registers.uartimsc.modify(
+            UARTIMSC::FEIM::SET + UARTIMSC::PEIM::SET + UARTIMSC::BEIM::SET + UARTIMSC::OEIM::SET,
+        );
+    }
+
+    fn disable_receive_error_interrupt(&self) {
+        self.registers.uartimsc.modify(
+            UARTIMSC::FEIM::CLEAR
+                + UARTIMSC::PEIM::CLEAR
+                + UARTIMSC::BEIM::CLEAR
+                + UARTIMSC::OEIM::CLEAR,
+        );
+        self.registers.uarticr.write(
+            UARTICR::FEIC::SET + UARTICR::PEIC::SET + UARTICR::BEIC::SET + UARTICR::OEIC::SET,
+        );
     }
 
     fn uart_is_writable(&self) -> bool {
@@ -494,49 +579,135 @@ impl<'a> Uart<'a> {
                     // Transmission is done
                     else {
                         self.tx_status.set(UARTStateTX::Idle);
+                        self.statistics.complete(self.tx_position.get());
                         self.tx_client.map(|client| {
                             self.tx_buffer.take().map(|buf| {
                                 client.transmitted_buffer(buf, self.tx_position.get(), Ok(()));
                             });
                         });
                     }
+                } else if self.tx_status.get() == UARTStateTX::TransmittingWord {
+                    self.disable_transmit_interrupt();
+                    self.tx_status.set(UARTStateTX::Idle);
+                    self.statistics.complete(1);
+                    self.tx_client.map(|client| client.transmitted_word(Ok(())));
                 }
             }
         }
 
         if self.registers.uartimsc.is_set(UARTIMSC::RXIM) {
-            if self.registers.uartfr.is_set(UARTFR::RXFF) {
-                let byte = self.registers.uartdr.get() as u8;
+            // Read everything received, the receive timeout interrupt
+            // fires for data left below the FIFO level
+            while self.registers.uartimsc.is_set(UARTIMSC::RXIM)
+                && !self.registers.uartfr.is_set(UARTFR::RXFE)
+            {
+                let data = self.registers.uartdr.extract();
+                self.receive_data(data.read(UARTDR::DATA) as u8, Self::data_error(data));
+            }
+            self.registers
+                .uarticr
+                .write(UARTICR::RXIC::SET + UARTICR::RTIC::SET);
+        }
 
-                self.disable_receive_interrupt();
-                if self.rx_status.get() == UARTStateRX::Receiving {
-                    if self.rx_position.get() < self.rx_len.get() {
-                        self.rx_buffer.map(|buf| {
-                            buf[self.rx_position.get()] = byte;
-                            self.rx_position.replace(self.rx_position.get() + 1);
-                        });
-                    }
-                    if self.rx_position.get() == self.rx_len.get() {
-                        // reception done
-                        self.rx_status.replace(UARTStateRX::Idle);
-                    } else {
-                        self.enable_receive_interrupt();
+        if self.rx_status.get() == UARTStateRX::ReceivingDma {
+            let status = self.registers.uartmis.extract();
+            let error = if status.is_set(UARTMIS::OEMIS) {
+                hil::uart::Error::OverrunError
+            } else if status.is_set(UARTMIS::PEMIS) {
+                hil::uart::Error::ParityError
+            } else if status.is_set(UARTMIS::FEMIS) || status.is_set(UARTMIS::BEMIS) {
+                hil::uart::Error::FramingError
+            } else {
+                hil::uart::Error::None
+            };
+            if error != hil::uart::Error::None {
+                let received = self.rx_dma.map_or(0, |dma| dma.abort());
+                // Drop the byte with the error, left by the DMA channel
+                let _ = self.registers.uartdr.get();
+                self.disable_receive_error_interrupt();
+                self.rx_status.set(UARTStateRX::Idle);
+                self.statistics.fail(received);
+                self.rx_client.map(|client| {
+                    if let Some(buf) = self.rx_buffer.take() {
+                        client.received_buffer(buf, received, Err(ErrorCode::FAIL), error);
                     }
-                    // notify client if transfer is done
-                    if self.rx_status.get() == UARTStateRX::Idle {
-                        self.rx_client.map(|client| {
-                            if let Some(buf) = self.rx_buffer.take() {
-                                client.received_buffer(
-                                    buf,
-                                    self.rx_len.get(),
-                                    Ok(()),
-                                    hil::uart::Error::None,
-                                );
-                            }
-                        });
+                });
+            }
+        }
+    }
+
+    /// Error flagged with a received byte. A break also sets the framing
+    /// error.
+    fn data_error(data: LocalRegisterCopy<u32, UARTDR::Register>) -> hil::uart::Error {
+        if data.is_set(UARTDR::OE) {
+            hil::uart::Error::OverrunError
+        } else if data.is_set(UARTDR::PE) {
+            hil::uart::Error::ParityError
+        } else if data.is_set(UARTDR::FE) {
+            hil::uart::Error::FramingError
+        } else {
+            hil::uart::Error::None
+        }
+    }
+
+    fn receive_data(&self, byte: u8, error: hil::uart::Error) {
+        match self.rx_status.get() {
+            UARTStateRX::Receiving if error != hil::uart::Error::None => {
+                // reception failed
+                self.disable_receive_interrupt();
+                self.rx_status.set(UARTStateRX::Idle);
+                self.statistics.fail(self.rx_position.get());
+                self.rx_client.map(|client| {
+                    if let Some(buf) = self.rx_buffer.take() {
+                        client.received_buffer(
+                            buf,
+                            self.rx_position.get(),
+                            Err(ErrorCode::FAIL),
+                            error,
+                        );
                     }
+                });
+            }
+            UARTStateRX::Receiving => {
+                if self.rx_position.get() < self.rx_len.get() {
+                    self.rx_buffer.map(|buf| {
+                        buf[self.rx_position.get()] = byte;
+                        self.rx_position.replace(self.rx_position.get() + 1);
+                    });
+                }
+                // notify client if transfer is done
+                if self.rx_position.get() == self.rx_len.get() {
+                    self.disable_receive_interrupt();
+                    self.rx_status.set(UARTStateRX::Idle);
+                    self.statistics.complete(self.rx_len.get());
+                    self.rx_client.map(|client| {
+                        if let Some(buf) = self.rx_buffer.take() {
+                            client.received_buffer(
+                                buf,
+                                self.rx_len.get(),
+                                Ok(()),
+                                hil::uart::Error::None,
+                            );
+                        }
+                    });
                 }
             }
+            UARTStateRX::ReceivingWord => {
+                self.disable_receive_interrupt();
+                self.rx_status.set(UARTStateRX::Idle);
+                let rval = if error == hil::uart::Error::None {
+                    self.statistics.complete(1);
+                    Ok(())
+                } else {
+                    self.statistics.fail(0);
+                    Err(ErrorCode::FAIL)
+                };
+                self.rx_client
+                    .map(|client| client.received_word(byte as u32, rval, error));
+            }
+            UARTStateRX::Idle | UARTStateRX::ReceivingDma | UARTStateRX::AbortRequested => {
+                self.disable_receive_interrupt();
+            }
         }
     }
 
@@ -570,34 +741,89 @@ impl DeferredCallClient for Uart<'_> {
 
     fn handle_deferred_call(&self) {
         if self.tx_status.get() == UARTStateTX::AbortRequested {
+            self.statistics.fail(self.tx_position.get());
+            // Idle before the client is alerted, so it can transmit again
+            // from its callback
+            let buffer = self.tx_buffer.take();
+            self.tx_status.set(UARTStateTX::Idle);
             // alert client
-            self.tx_client.map(|client| {
-                self.tx_buffer.take().map(|buf| {
-                    client.transmitted_buffer(buf, self.tx_position.get(), Err(ErrorCode::CANCEL));
-                });
+            self.tx_client.map(|client| match buffer {
+                Some(buf) => {
+                    client.transmitted_buffer(buf, self.tx_position.get(), Err(ErrorCode::CANCEL))
+                }
+                None => client.transmitted_word(Err(ErrorCode::CANCEL)),
             });
-            self.tx_status.set(UARTStateTX::Idle);
         }
 
         if self.rx_status.get() == UARTStateRX::AbortRequested {
+            self.statistics.fail(self.rx_position.get());
+            // Idle before the client is alerted, so it can receive again
+            // from its callback
+            let buffer = self.rx_buffer.take();
+            self.rx_status.set(UARTStateRX::Idle);
             // alert client
-            self.rx_client.map(|client| {
-                self.rx_buffer.take().map(|buf| {
-                    client.received_buffer(
-                        buf,
-                        self.rx_position.get(),
-                        Err(ErrorCode::CANCEL),
-                        hil::uart::Error::Aborted,
-                    );
+            self.rx_client.map(|client| match buffer {
+                Some(buf) => client.received_buffer(
+                    buf,
+                    self.rx_position.get(),
+                    Err(ErrorCode::CANCEL),
+                    hil::uart::Error::Aborted,
+                ),
+                None => client.received_word(0, Err(ErrorCode::CANCEL), hil::uart::Error::Aborted),
+            });
+        }
+    }
+}
+
+impl DmaClient for Uart<'_> {
+    fn transfer_done(
+        &self,
+        request: DmaRequest,
+        result: Result<(), ErrorCode>,
+        transferred: usize,
+    ) {
+        if request == self.tx_dma_request && self.tx_status.get() == UARTStateTX::TransmittingDma {
+            self.tx_status.set(UARTStateTX::Idle);
+            self.statistics.finish(&result, transferred);
+            self.tx_client.map(|client| {
+                self.tx_buffer.take().map(|buf| {
+                    client.transmitted_buffer(buf, transferred, result);
                 });
             });
+        } else if request == self.rx_dma_request
+            && self.rx_status.get() == UARTStateRX::ReceivingDma
+        {
+            self.disable_receive_error_interrupt();
             self.rx_status.set(UARTStateRX::Idle);
+            self.statistics.finish(&result, transferred);
+            self.rx_client.map(|client| {
+                if let Some(buf) = self.rx_buffer.take() {
+                    client.received_buffer(buf, transferred, result, hil::uart::Error::None);
+                }
+            });
         }
     }
 }
 
+impl DriverStatistics for Uart<'_> {
+    fn statistics(&self) -> Statistics {
+        self.statistics.get()
+    }
+
+    fn reset_statistics(&self) {
+        self.statistics.reset();
+    }
+}
+
 impl Configure for Uart<'_> {
     fn configure(&self, params: Parameters) -> Result<(), ErrorCode> {
+        if params.width == Width::Nine {
+            return Err(ErrorCode::NOSUPPORT);
+        }
+
+        self.resets
+            .map(|resets| resets.unreset(&[self.reset], true));
+
         self.disable();
         self.registers.uartlcr_h.modify(UARTLCR_H::FEN::CLEAR);
 
@@ -631,6 +857,7 @@ impl Configure for Uart<'_> {
             Width::Six => self.registers.uartlcr_h.modify(UARTLCR_H::WLEN::BITS_6),
             Width::Seven => self.registers.uartlcr_h.modify(UARTLCR_H::WLEN::BITS_7),
             Width::Eight => self.registers.uartlcr_h.modify(UARTLCR_H::WLEN::BITS_8),
+            Width::Nine => unreachable!(),
         }
 
         // Configure parity
@@ -638,15 +865,29 @@ impl Configure for Uart<'_> {
             Parity::None => {
                 self.registers.uartlcr_h.modify(UARTLCR_H::PEN::CLEAR);
                 self.registers.uartlcr_h.modify(UARTLCR_H::EPS::CLEAR);
+                self.registers.uartlcr_h.modify(UARTLCR_H::SPS::CLEAR);
             }
 
             Parity::Odd => {
                 self.registers.uartlcr_h.modify(UARTLCR_H::PEN::SET);
                 self.registers.uartlcr_h.modify(UARTLCR_H::EPS::CLEAR);
+                self.registers.uartlcr_h.modify(UARTLCR_H::SPS::CLEAR);
             }
             Parity::Even => {
                 self.registers.uartlcr_h.modify(UARTLCR_H::PEN::SET);
                 self.registers.uartlcr_h.modify(UARTLCR_H::EPS::SET);
+                self.registers.uartlcr_h.modify(UARTLCR_H::SPS::CLEAR);
+            }
+            // With stick parity, the parity bit is the inverse of EPS
+            Parity::Mark => {
+                self.registers.uartlcr_h.modify(UARTLCR_H::PEN::SET);
+                self.registers.uartlcr_h.modify(UARTLCR_H::EPS::CLEAR);
+                self.registers.uartlcr_h.modify(UARTLCR_H::SPS::SET);
+            }
+            Parity::Space => {
+                self.registers.uartlcr_h.modify(UARTLCR_H::PEN::SET);
+                self.registers.uartlcr_h.modify(UARTLCR_H::EPS::SET);
+                self.registers.uartlcr_h.modify(UARTLCR_H::SPS::SET);
             }
         }
 
@@ -676,7 +917,7 @@ impl Configure for Uart<'_> {
 
         self.registers
             .uartdmacr
-            .write(UARTDMACR::TXDMAE::SET + UARTDMACR::RXDMAE::SET);
+            .write(UARTDMACR::TXDMAE::SET + UARTDMACR::RXDMAE::SET + UARTDMACR::DMAONERR::SET);
 
         Ok(())
     }
@@ -694,12 +935,30 @@ impl<'a> Transmit<'a> for Uart<'a> {
     ) -> Result<(), (ErrorCode, &'static mut [u8])> {
         if self.tx_status.get() == UARTStateTX::Idle {
             if tx_len <= tx_buffer.len() {
-                self.tx_buffer.put(Some(tx_buffer));
                 self.tx_position.set(0);
                 self.tx_len.set(tx_len);
-                self.tx_status.set(UARTStateTX::Transmitting);
-                self.enable_transmit_interrupt();
-                self.fill_fifo();
+                self.statistics.start();
+                match self.tx_dma.extract().filter(|_| tx_len >= DMA_MIN_LEN) {
+                    Some(dma) => {
+                        // The buffer stays with the driver until the channel
+                        // is done or aborted.
+                        unsafe {
+                            dma.transfer_from_buffer(
+                                &tx_buffer[..tx_len],
+                                self.data_register(),
+                                self.tx_dma_request,
+                            );
+                        }
+                        self.tx_buffer.put(Some(tx_buffer));
+                        self.tx_status.set(UARTStateTX::TransmittingDma);
+                    }
+                    None => {
+                        self.tx_buffer.put(Some(tx_buffer));
+                        self.tx_status.set(UARTStateTX::Transmitting);
+                        self.enable_transmit_interrupt();
+                        self.fill_fifo();
+                    }
+                }
                 Ok(())
             } else {
                 Err((ErrorCode::SIZE, tx_buffer))
@@ -709,12 +968,23 @@ impl<'a> Transmit<'a> for Uart<'a> {
         }
     }
 
-    fn transmit_word(&self, _word: u32) -> Result<(), ErrorCode> {
-        Err(ErrorCode::FAIL)
+    fn transmit_word(&self, word: u32) -> Result<(), ErrorCode> {
+        if self.tx_status.get() != UARTStateTX::Idle || !self.uart_is_writable() {
+            return Err(ErrorCode::BUSY);
+        }
+
+        self.registers.uartdr.write(UARTDR::DATA.val(word & 0xFF));
+        self.tx_status.set(UARTStateTX::TransmittingWord);
+        self.statistics.start();
+        self.enable_transmit_interrupt();
+        Ok(())
     }
 
     fn transmit_abort(&self) -> Result<(), ErrorCode> {
         if self.tx_status.get() != UARTStateTX::Idle {
+            if self.tx_status.get() == UARTStateTX::TransmittingDma {
+                self.tx_dma.map(|dma| self.tx_position.set(dma.abort()));
+            }
             self.disable_transmit_interrupt();
             self.tx_status.set(UARTStateTX::AbortRequested);
 
@@ -727,6 +997,22 @@ impl<'a> Transmit<'a> for Uart<'a> {
     }
 }
 
+impl hil::uart::Break for Uart<'_> {
+    fn start_break(&self) -> Result<(), ErrorCode> {
+        if !self.registers.uartcr.is_set(UARTCR::TXE) {
+            return Err(ErrorCode::OFF);
+        }
+        // The line is held low once the current character is sent
+        self.registers.uartlcr_h.modify(UARTLCR_H::BRK::SET);
+        Ok(())
+    }
+
+    fn stop_break(&self) -> Result<(), ErrorCode> {
+        self.registers.uartlcr_h.modify(UARTLCR_H::BRK::CLEAR);
+        Ok(())
+    }
+}
+
 impl<'a> Receive<'a> for Uart<'a> {
     fn set_receive_client(&self, client: &'a dyn ReceiveClient) {
         self.rx_client.set(client);
@@ -739,11 +1025,30 @@ impl<'a> Receive<'a> for Uart<'a> {
     ) -> Result<(), (ErrorCode, &'static mut [u8])> {
         if self.rx_status.get() == UARTStateRX::Idle {
             if rx_len <= rx_buffer.len() {
-                self.rx_buffer.put(Some(rx_buffer));
                 self.rx_position.set(0);
                 self.rx_len.set(rx_len);
-                self.rx_status.set(UARTStateRX::Receiving);
-                self.enable_receive_interrupt();
+                self.statistics.start();
+                match self.rx_dma.extract().filter(|_| rx_len >= DMA_MIN_LEN) {
+                    Some(dma) => {
+                        self.enable_receive_error_interrupt();
+                        // The buffer stays with the driver until the channel
+                        // is done or aborted.
+                        unsafe {
+                            dma.transfer_to_buffer(
+                                self.data_register(),
+                                &mut rx_buffer[..rx_len],
+                                self.rx_dma_request,
+                            );
+                        }
+                        self.rx_buffer.put(Some(rx_buffer));
+                        self.rx_status.set(UARTStateRX::ReceivingDma);
+                    }
+                    None => {
+                        self.rx_buffer.put(Some(rx_buffer));
+                        self.rx_status.set(UARTStateRX::Receiving);
+                        self.enable_receive_interrupt();
+                    }
+                }
                 Ok(())
             } else {
                 Err((ErrorCode::SIZE, rx_buffer))
@@ -754,11 +1059,22 @@ impl<'a> Receive<'a> for Uart<'a> {
     }
 
     fn receive_word(&self) -> Result<(), ErrorCode> {
-        Err(ErrorCode::FAIL)
+        if self.rx_status.get() != UARTStateRX::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+
+        self.rx_status.set(UARTStateRX::ReceivingWord);
+        self.statistics.start();
+        self.enable_receive_interrupt();
+        Ok(())
     }
 
     fn receive_abort(&self) -> Result<(), ErrorCode> {
         if self.rx_status.get() != UARTStateRX::Idle {
+            if self.rx_status.get() == UARTStateRX::ReceivingDma {
+                self.rx_dma.map(|dma| self.rx_position.set(dma.abort()));
+                self.disable_receive_error_interrupt();
+            }
             self.disable_receive_interrupt();
             self.rx_status.set(UARTStateRX::AbortRequested);
 
@@ -8,6 +8,7 @@
 //!          Teodora Miu <teodora.miu01@gmail.com>
 
 use crate::gpio::RPGpioPin;
+use crate::resets;
 use core::cell::Cell;
 use kernel::hil;
 use kernel::hil::usb::TransferType;
@@ -1322,6 +1323,7 @@ pub struct UsbCtrl<'a> {
     next_pid_out: [VolatileCell<u8>; 16],
     errata_pin: OptionalCell<&'a RPGpioPin<'a>>,
     counter: VolatileCell<u32>,
+    resets: OptionalCell<&'a resets::Resets>,
 }
 
 impl<'a> UsbCtrl<'a> {
@@ -1389,6 +1391,7 @@ impl<'a> UsbCtrl<'a> {
             ],
             errata_pin: OptionalCell::empty(),
             counter: VolatileCell::new(0),
+            resets: OptionalCell::empty(),
         }
     }
 
@@ -1403,7 +1406,14 @@ impl<'a> UsbCtrl<'a> {
         self.errata_pin.set(gpio_pin);
     }
 
+    pub(crate) fn set_resets(&self, resets: &'a resets::Resets) {
+        self.resets.set(resets);
+    }
+
     pub fn enable(&self) {
+        self.resets
+            .map(|resets| resets.unreset(&[resets::Peripheral::UsbCtrl], true));
+
         self.registers
             .usb_muxing
             .modify(USB_MUXING::TO_PHY::SET + USB_MUXING::SOFTCON::SET);
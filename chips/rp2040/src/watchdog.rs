@@ -2,10 +2,15 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 // Copyright Tock Contributors 2022.
 
+use kernel::capabilities::ResetCapability;
+use kernel::hil::backup_registers::BackupRegisters;
+use kernel::hil::reset::Reset;
+use kernel::hil::reset_reason::{ResetReason, ResetReasonQuery};
 use kernel::utilities::cells::OptionalCell;
-use kernel::utilities::registers::interfaces::{ReadWriteable, Writeable};
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::{register_bitfields, register_structs, ReadWrite};
 use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
 
 use crate::resets;
 
@@ -133,3 +138,69 @@ impl<'a> Watchdog<'a> {
         self.registers.ctrl.write(CTRL::TRIGGER::SET);
     }
 }
+
+/// Scratch registers 4 to 7 are used by the bootrom when rebooting through
+/// the watchdog, so only the first four are exposed.
+const NUM_BACKUP_REGISTERS: usize = 4;
+
+impl BackupRegisters for Watchdog<'_> {
+    fn num_registers(&self) -> usize {
+        NUM_BACKUP_REGISTERS
+    }
+
+    fn read(&self, index: usize) -> Result<u32, ErrorCode> {
+        match index {
+            0 => Ok(self.registers.scratch0.get()),
+            1 => Ok(self.registers.scratch1.get()),
+            2 => Ok(self.registers.scratch2.get()),
+            3 => Ok(self.registers.scratch3.get()),
+            _ => Err(ErrorCode::INVAL),
+        }
+    }
+
+    fn write(&self, index: usize, value: u32) -> Result<(), ErrorCode> {
+        match index {
+            0 => self.registers.scratch0.set(value),
+            1 => self.registers.scratch1.set(value),
+            2 => self.registers.scratch2.set(value),
+            3 => self.registers.scratch3.set(value),
+            _ => return Err(ErrorCode::INVAL),
+        }
+        Ok(())
+    }
+}
+
+impl Reset for Watchdog<'_> {
+    fn reset(&self, _capability: &dyn ResetCapability) -> ! {
+        // The bootrom jumps to the address in scratch registers 4 to 7 after
+        // a watchdog reset if scratch 4 holds its magic value, so clear it to
+        // boot from flash.
+        self.registers.scratch4.set(0);
+        self.reboot();
+        loop {
+            cortexm0p::support::nop();
+        }
+    }
+
+    fn reset_to_bootloader(&self, _capability: &dyn ResetCapability) -> ! {
+        // Leave both USB interfaces of the bootloader enabled, without an
+        // activity LED.
+        unsafe { crate::rom::reset_to_usb_boot(0, 0) }
+    }
+}
+
+impl ResetReasonQuery for Watchdog<'_> {
+    fn reset_reason(&self) -> ResetReason {
+        let reason = self.registers.reason.extract();
+        if reason.is_set(REASON::TIMER) {
+            ResetReason::Watchdog
+        } else if reason.is_set(REASON::FORCE) {
+            // `reboot()` resets the chip by forcing the watchdog.
+            ResetReason::Software
+        } else {
+            // The register is cleared by power-on, brown-out and the RUN pin,
+            // which the watchdog cannot tell apart.
+            ResetReason::PowerOn
+        }
+    }
+}
@@ -181,4 +181,13 @@ impl hil::dac::DacChannel for Dac {
             .write(ConversionData::DATA.val(value as u32));
         Ok(())
     }
+
+    fn get_resolution_bits(&self) -> usize {
+        10
+    }
+
+    fn get_voltage_reference_mv(&self) -> Option<usize> {
+        // The reference is the external VREF pin.
+        None
+    }
 }
@@ -1000,9 +1000,11 @@ impl uart::Configure for USART<'_> {
         };
 
         mode += match parameters.parity {
-            uart::Parity::None => Mode::PAR::NONE, // no parity
-            uart::Parity::Odd => Mode::PAR::ODD,   // odd parity
-            uart::Parity::Even => Mode::PAR::EVEN, // even parity
+            uart::Parity::None => Mode::PAR::NONE,   // no parity
+            uart::Parity::Odd => Mode::PAR::ODD,     // odd parity
+            uart::Parity::Even => Mode::PAR::EVEN,   // even parity
+            uart::Parity::Mark => Mode::PAR::MARK,   // parity bit always 1
+            uart::Parity::Space => Mode::PAR::SPACE, // parity bit always 0
         };
 
         mode += match parameters.hw_flow_control {
@@ -11,9 +11,13 @@ use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::{register_bitfields, ReadWrite};
 use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
 
 use crate::rcc;
 
+/// The peripheral clock runs from the 8 MHz HSI oscillator after reset.
+const DEFAULT_CLOCK_MHZ: usize = 8;
+
 pub enum I2CSpeed {
     Speed100k,
     Speed400k,
@@ -249,6 +253,10 @@ pub struct I2C<'a> {
 
     slave_address: Cell<u8>,
 
+    /// Peripheral clock frequency given to the last `set_speed` call, reused
+    /// when the speed is changed through the `I2CBusSpeed` HIL.
+    clock_mhz: Cell<usize>,
+
     status: Cell<I2CStatus>,
     // transfers: Cell<u8>
 }
@@ -270,6 +278,7 @@ impl<'a> I2C<'a> {
             master_client: OptionalCell::empty(),
 
             slave_address: Cell::new(0),
+            clock_mhz: Cell::new(DEFAULT_CLOCK_MHZ),
 
             buffer: TakeCell::empty(),
             tx_position: Cell::new(0),
@@ -293,6 +302,7 @@ impl<'a> I2C<'a> {
     }
 
     pub fn set_speed(&self, speed: I2CSpeed, system_clock_in_mhz: usize) {
+        self.clock_mhz.set(system_clock_in_mhz);
         self.disable();
         match speed {
             I2CSpeed::Speed100k => {
@@ -475,6 +485,22 @@ impl<'a> I2C<'a> {
     }
 }
 
+impl i2c::I2CBusSpeed for I2C<'_> {
+    fn max_bus_speed(&self) -> i2c::BusSpeed {
+        i2c::BusSpeed::Fast400k
+    }
+
+    fn set_bus_speed(&self, speed: i2c::BusSpeed) -> Result<(), ErrorCode> {
+        let speed = match speed {
+            i2c::BusSpeed::Standard100k => I2CSpeed::Speed100k,
+            i2c::BusSpeed::Fast400k => I2CSpeed::Speed400k,
+            i2c::BusSpeed::FastPlus1M => return Err(ErrorCode::NOSUPPORT),
+        };
+        self.set_speed(speed, self.clock_mhz.get());
+        Ok(())
+    }
+}
+
 impl<'a> i2c::I2CMaster<'a> for I2C<'a> {
     fn set_master_client(&self, master_client: &'a dyn I2CHwMasterClient) {
         self.master_client.replace(master_client);
@@ -31,7 +31,7 @@ struct UsartRegisters {
     /// Receiver timeout register
     rtor: ReadWrite<u32, RTOR::Register>,
     /// Request register
-    rqr: ReadWrite<u32, RTOR::Register>,
+    rqr: ReadWrite<u32, RQR::Register>,
     /// Interrupt and status register
     isr: ReadWrite<u32, ISR::Register>,
     /// Interrupt flag clear register
@@ -89,7 +89,7 @@ register_bitfields![u32,
     ],
     CR2 [
         /// Address of the USART node
-        ADD1 OFFSET(28) NUMBITS(1) [],
+        ADD1 OFFSET(28) NUMBITS(4) [],
         /// Address of the USART node
         ADD0 OFFSET(24) NUMBITS(4) [],
         /// Receiver timeout enable
@@ -283,6 +283,7 @@ const USART3_BASE: StaticRef<UsartRegisters> =
 enum USARTStateTX {
     Idle,
     Transmitting,
+    TransmittingWord,
     AbortRequested,
 }
 
@@ -291,6 +292,7 @@ enum USARTStateTX {
 enum USARTStateRX {
     Idle,
     Receiving,
+    ReceivingWord,
     AbortRequested,
 }
 
@@ -311,6 +313,11 @@ pub struct Usart<'a> {
     rx_len: Cell<usize>,
     rx_status: Cell<USARTStateRX>,
 
+    /// Whether frames carry 9 data bits.
+    nine_bit: Cell<bool>,
+    /// Bit 8 of the words sent from buffers, set for mark parity.
+    tx_ninth_bit: Cell<u32>,
+
     deferred_call: DeferredCall,
 }
 
@@ -333,6 +340,9 @@ impl<'a> Usart<'a> {
             rx_len: Cell::new(0),
             rx_status: Cell::new(USARTStateRX::Idle),
 
+            nine_bit: Cell::new(false),
+            tx_ninth_bit: Cell::new(0),
+
             deferred_call: DeferredCall::new(),
         }
     }
@@ -414,7 +424,9 @@ impl<'a> Usart<'a> {
             if self.tx_status.get() == USARTStateTX::Transmitting {
                 if self.tx_position.get() < self.tx_len.get() {
                     self.tx_buffer.map(|buf| {
-                        self.registers.tdr.set(buf[self.tx_position.get()].into());
+                        self.registers
+                            .tdr
+                            .set(u32::from(buf[self.tx_position.get()]) | self.tx_ninth_bit.get());
                         self.tx_position.replace(self.tx_position.get() + 1);
                     });
                 }
@@ -432,11 +444,16 @@ impl<'a> Usart<'a> {
                         }
                     });
                 }
+            } else if self.tx_status.get() == USARTStateTX::TransmittingWord {
+                // the word moved to the shift register
+                self.tx_status.replace(USARTStateTX::Idle);
+                self.tx_client.map(|client| client.transmitted_word(Ok(())));
             }
         }
 
         if self.registers.isr.is_set(ISR::RXNE) {
-            let byte = self.registers.rdr.get() as u8;
+            let word = self.registers.rdr.get() & 0x1FF;
+            let byte = word as u8;
             self.disable_receive_interrupt();
 
             // ignore IRQ if not receiving
@@ -466,6 +483,19 @@ impl<'a> Usart<'a> {
                         }
                     });
                 }
+            } else if self.rx_status.get() == USARTStateRX::ReceivingWord {
+                self.rx_status.replace(USARTStateRX::Idle);
+                let isr = self.registers.isr.extract();
+                let (rval, error) = if isr.is_set(ISR::PE) {
+                    (Err(ErrorCode::FAIL), hil::uart::Error::ParityError)
+                } else if isr.is_set(ISR::FE) {
+                    (Err(ErrorCode::FAIL), hil::uart::Error::FramingError)
+                } else {
+                    (Ok(()), hil::uart::Error::None)
+                };
+                self.registers.icr.write(ICR::PECF::SET + ICR::FECF::SET);
+                self.rx_client
+                    .map(|client| client.received_word(word, rval, error));
             }
         }
 
@@ -494,25 +524,25 @@ impl DeferredCallClient for Usart<'_> {
     fn handle_deferred_call(&self) {
         if self.tx_status.get() == USARTStateTX::AbortRequested {
             // alert client
-            self.tx_client.map(|client| {
-                self.tx_buffer.take().map(|buf| {
-                    client.transmitted_buffer(buf, self.tx_position.get(), Err(ErrorCode::CANCEL));
-                });
+            self.tx_client.map(|client| match self.tx_buffer.take() {
+                Some(buf) => {
+                    client.transmitted_buffer(buf, self.tx_position.get(), Err(ErrorCode::CANCEL))
+                }
+                None => client.transmitted_word(Err(ErrorCode::CANCEL)),
             });
             self.tx_status.set(USARTStateTX::Idle);
         }
 
         if self.rx_status.get() == USARTStateRX::AbortRequested {
             // alert client
-            self.rx_client.map(|client| {
-                self.rx_buffer.take().map(|buf| {
-                    client.received_buffer(
-                        buf,
-                        self.rx_position.get(),
-                        Err(ErrorCode::CANCEL),
-                        hil::uart::Error::Aborted,
-                    );
-                });
+            self.rx_client.map(|client| match self.rx_buffer.take() {
+                Some(buf) => client.received_buffer(
+                    buf,
+                    self.rx_position.get(),
+                    Err(ErrorCode::CANCEL),
+                    hil::uart::Error::Aborted,
+                ),
+                None => client.received_word(0, Err(ErrorCode::CANCEL), hil::uart::Error::Aborted),
             });
             self.rx_status.set(USARTStateRX::Idle);
         }
@@ -545,8 +575,16 @@ impl<'a> hil::uart::Transmit<'a> for Usart<'a> {
         }
     }
 
-    fn transmit_word(&self, _word: u32) -> Result<(), ErrorCode> {
-        Err(ErrorCode::FAIL)
+    fn transmit_word(&self, word: u32) -> Result<(), ErrorCode> {
+        if self.tx_status.get() != USARTStateTX::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+
+        // bit 8 is the address mark in 9-bit mode
+        self.registers.tdr.set(word & 0x1FF);
+        self.tx_status.set(USARTStateTX::TransmittingWord);
+        self.enable_transmit_interrupt();
+        Ok(())
     }
 
     fn transmit_abort(&self) -> Result<(), ErrorCode> {
@@ -565,20 +603,46 @@ impl<'a> hil::uart::Transmit<'a> for Usart<'a> {
 
 impl hil::uart::Configure for Usart<'_> {
     fn configure(&self, params: hil::uart::Parameters) -> Result<(), ErrorCode> {
+        use hil::uart::{Parity, Width};
+
+        // 9-bit frames are either 9 data bits, or 8 data bits with a fixed
+        // parity bit as the ninth bit.
+        let ninth_bit = match (params.width, params.parity) {
+            (Width::Eight, Parity::None) => None,
+            (Width::Nine, Parity::None) | (Width::Eight, Parity::Space) => Some(false),
+            (Width::Eight, Parity::Mark) => Some(true),
+            _ => None,
+        };
         if params.baud_rate != 115200
             || params.stop_bits != hil::uart::StopBits::One
-            || params.parity != hil::uart::Parity::None
             || params.hw_flow_control != false
-            || params.width != hil::uart::Width::Eight
+            || (ninth_bit.is_none()
+                && (params.width != Width::Eight || params.parity != Parity::None))
         {
             panic!(
-                "Currently we only support uart setting of 115200bps 8N1, no hardware flow control"
+                "Currently we only support uart setting of 115200bps 8N1, 9N1, 8M1 or 8S1, no hardware flow control"
             );
         }
 
-        // Configure the word length - 0: 1 Start bit, 8 Data bits, n Stop bits
-        self.registers.cr1.modify(CR1::M0::CLEAR);
+        // The word length and the address detection can only be written
+        // while the USART is disabled
+        self.registers.cr1.modify(CR1::UE::CLEAR);
+
+        // Configure the word length - 00: 1 Start bit, 8 Data bits, n Stop
+        // bits, 01: 1 Start bit, 9 Data bits, n Stop bits
+        match ninth_bit {
+            None => self.registers.cr1.modify(CR1::M0::CLEAR),
+            Some(_) => self.registers.cr1.modify(CR1::M0::SET),
+        }
         self.registers.cr1.modify(CR1::M1::CLEAR);
+        self.nine_bit.set(ninth_bit.is_some());
+        self.tx_ninth_bit
+            .set(if ninth_bit == Some(true) { 0x100 } else { 0 });
+
+        // Disable address detection
+        self.registers
+            .cr1
+            .modify(CR1::MME::CLEAR + CR1::WAKE::CLEAR);
 
         // Set the stop bit length - 00: 1 Stop bits
         self.registers.cr2.modify(CR2::STOP.val(0b00 as u32));
@@ -634,7 +698,13 @@ impl<'a> hil::uart::Receive<'a> for Usart<'a> {
     }
 
     fn receive_word(&self) -> Result<(), ErrorCode> {
-        Err(ErrorCode::FAIL)
+        if self.rx_status.get() != USARTStateRX::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+
+        self.rx_status.set(USARTStateRX::ReceivingWord);
+        self.enable_receive_interrupt();
+        Ok(())
     }
 
     fn receive_abort(&self) -> Result<(), ErrorCode> {
@@ -651,6 +721,43 @@ impl<'a> hil::uart::Receive<'a> for Usart<'a> {
     }
 }
 
+impl hil::uart::MultiDrop for Usart<'_> {
+    fn set_address_match(&self, address: Option<u8>) -> Result<(), ErrorCode> {
+        if !self.nine_bit.get() {
+            return Err(ErrorCode::INVAL);
+        } else if self.rx_status.get() != USARTStateRX::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+
+        // The address detection can only be written while the USART is
+        // disabled
+        self.registers.cr1.modify(CR1::UE::CLEAR);
+        match address {
+            Some(address) => {
+                // In 9-bit mode, address words have the address mark in bit
+                // 8, and all 8 bits of the address are compared
+                self.registers.cr2.modify(
+                    CR2::ADD1.val((address >> 4) as u32)
+                        + CR2::ADD0.val((address & 0xF) as u32)
+                        + CR2::ADDM7::SET,
+                );
+                self.registers.cr1.modify(CR1::MME::SET + CR1::WAKE::SET);
+            }
+            None => self
+                .registers
+                .cr1
+                .modify(CR1::MME::CLEAR + CR1::WAKE::CLEAR),
+        }
+        self.registers.cr1.modify(CR1::UE::SET);
+
+        if address.is_some() {
+            // Discard words until an address word matches
+            self.registers.rqr.write(RQR::MMRQ::SET);
+        }
+        Ok(())
+    }
+}
+
 struct UsartClock<'a>(rcc::PeripheralClock<'a>);
 
 impl ClockInterface for UsartClock<'_> {
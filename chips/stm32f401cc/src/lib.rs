@@ -6,7 +6,9 @@
 
 use cortexm4::{unhandled_interrupt, CortexM4, CortexMVariant};
 
-pub use stm32f4xx::{adc, chip, dbg, dma, exti, gpio, nvic, rcc, spi, syscfg, tim2, usart};
+pub use stm32f4xx::{
+    adc, chip, crc, dbg, dma, exti, gpio, nvic, pwr, rcc, rtc, spi, syscfg, tim, tim2, uid, usart,
+};
 
 pub mod interrupt_service;
 
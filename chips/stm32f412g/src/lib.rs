@@ -7,7 +7,8 @@
 use cortexm4::{CortexM4, CortexMVariant};
 
 pub use stm32f4xx::{
-    adc, chip, dbg, dma, exti, fsmc, gpio, i2c, nvic, rcc, spi, syscfg, tim2, trng, usart,
+    adc, chip, crc, dbg, dma, exti, fsmc, gpio, i2c, nvic, pwr, rcc, rtc, spi, syscfg, tim, tim2,
+    trng, uid, usart,
 };
 
 pub mod interrupt_service;
@@ -0,0 +1,11 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Digital to analog converter
+
+use kernel::utilities::StaticRef;
+use stm32f4xx::dac::DacRegisters;
+
+pub(crate) const DAC_BASE: StaticRef<DacRegisters> =
+    unsafe { StaticRef::new(0x4000_7400 as *const DacRegisters) };
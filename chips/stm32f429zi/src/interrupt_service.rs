@@ -4,13 +4,15 @@
 
 use stm32f4xx::chip::Stm32f4xxDefaultPeripherals;
 
-use crate::{can_registers, stm32f429zi_nvic, trng_registers};
+use crate::{can_registers, dac_registers, stm32f429zi_nvic, trng_registers};
 
 pub struct Stm32f429ziDefaultPeripherals<'a> {
     pub stm32f4: Stm32f4xxDefaultPeripherals<'a>,
     // Once implemented, place Stm32f429zi specific peripherals here
     pub trng: stm32f4xx::trng::Trng<'a>,
     pub can1: stm32f4xx::can::Can<'a>,
+    pub dac_channel1: stm32f4xx::dac::DacChannel<'a>,
+    pub dac_channel2: stm32f4xx::dac::DacChannel<'a>,
 }
 
 impl<'a> Stm32f429ziDefaultPeripherals<'a> {
@@ -24,6 +26,16 @@ impl<'a> Stm32f429ziDefaultPeripherals<'a> {
             stm32f4: Stm32f4xxDefaultPeripherals::new(rcc, exti, dma1, dma2),
             trng: stm32f4xx::trng::Trng::new(trng_registers::RNG_BASE, rcc),
             can1: stm32f4xx::can::Can::new(rcc, can_registers::CAN1_BASE),
+            dac_channel1: stm32f4xx::dac::DacChannel::new(
+                dac_registers::DAC_BASE,
+                stm32f4xx::dac::Channel::Channel1,
+                rcc,
+            ),
+            dac_channel2: stm32f4xx::dac::DacChannel::new(
+                dac_registers::DAC_BASE,
+                stm32f4xx::dac::Channel::Channel2,
+                rcc,
+            ),
         }
     }
     // Necessary for setting up circular dependencies and registering deferred calls
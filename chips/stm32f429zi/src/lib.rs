@@ -7,10 +7,12 @@
 use cortexm4::{CortexM4, CortexMVariant};
 
 pub use stm32f4xx::{
-    adc, can, chip, dbg, dma, exti, gpio, nvic, rcc, spi, syscfg, tim2, trng, usart,
+    adc, can, chip, crc, dac, dbg, dma, exti, gpio, nvic, pwr, rcc, rtc, spi, syscfg, tim, tim2,
+    trng, uid, usart,
 };
 
 pub mod can_registers;
+pub mod dac_registers;
 pub mod interrupt_service;
 pub mod stm32f429zi_nvic;
 pub mod trng_registers;
@@ -4,9 +4,13 @@
 
 use stm32f4xx::chip::Stm32f4xxDefaultPeripherals;
 
+use crate::dac_registers;
+
 pub struct Stm32f446reDefaultPeripherals<'a> {
     pub stm32f4: Stm32f4xxDefaultPeripherals<'a>,
     // Once implemented, place Stm32f446re specific peripherals here
+    pub dac_channel1: stm32f4xx::dac::DacChannel<'a>,
+    pub dac_channel2: stm32f4xx::dac::DacChannel<'a>,
 }
 
 impl<'a> Stm32f446reDefaultPeripherals<'a> {
@@ -18,6 +22,16 @@ impl<'a> Stm32f446reDefaultPeripherals<'a> {
     ) -> Self {
         Self {
             stm32f4: Stm32f4xxDefaultPeripherals::new(rcc, exti, dma1, dma2),
+            dac_channel1: stm32f4xx::dac::DacChannel::new(
+                dac_registers::DAC_BASE,
+                stm32f4xx::dac::Channel::Channel1,
+                rcc,
+            ),
+            dac_channel2: stm32f4xx::dac::DacChannel::new(
+                dac_registers::DAC_BASE,
+                stm32f4xx::dac::Channel::Channel2,
+                rcc,
+            ),
         }
     }
     // Necessary for setting up circular dependencies & registering deferred
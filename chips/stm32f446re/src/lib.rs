@@ -4,8 +4,12 @@
 
 #![no_std]
 
-pub use stm32f4xx::{adc, chip, dbg, dma, exti, gpio, nvic, rcc, spi, syscfg, tim2, usart};
+pub use stm32f4xx::{
+    adc, chip, crc, dac, dbg, dma, exti, gpio, nvic, pwr, rcc, rtc, spi, syscfg, tim, tim2, uid,
+    usart,
+};
 
+pub mod dac_registers;
 pub mod interrupt_service;
 pub mod stm32f446re_nvic;
 
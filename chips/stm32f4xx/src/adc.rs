@@ -2,12 +2,40 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 // Copyright Tock Contributors 2022.
 
+//! Analog to digital converter (ADC1).
+//!
+//! Single samples are read with the EOC interrupt. High-speed sampling
+//! converts continuously into buffers through DMA2 stream 0, which must be
+//! set with `set_dma`. With `set_scan_sequence`, high-speed sampling
+//! converts a list of channels in scan mode instead of a single channel, and
+//! the samples of each sequence are stored one after the other. Buffers are
+//! handed back at the end of the last whole sequence that fits in them.
+//!
+//! The ADC is not triggered by a timer, so the sampling frequency only
+//! selects the sampling time of the channels: the rate is the closest one
+//! the ADC clock allows, roughly from 16 kHz to 500 kHz divided by the
+//! number of channels in the sequence.
+//!
+//! The board sets up the DMA stream:
+//!
+//! ```rust,ignore
+//! use stm32f429zi::dma::Dma2Peripheral;
+//!
+//! let adc_stream = &base_peripherals.dma2_streams[Dma2Peripheral::ADC1.get_stream_idx()];
+//! dma2.enable_clock();
+//! adc_stream.set_client(&base_peripherals.adc1);
+//! adc_stream.setup(Dma2Peripheral::ADC1);
+//! base_peripherals.adc1.set_dma(adc_stream);
+//! cortexm4::nvic::Nvic::new(Dma2Peripheral::ADC1.get_stream_irqn()).enable();
+//! ```
+
+use crate::dma;
 use crate::rcc;
 use core::cell::Cell;
 use kernel::hil;
 use kernel::platform::chip::ClockInterface;
-use kernel::utilities::cells::OptionalCell;
-use kernel::utilities::registers::interfaces::{ReadWriteable, Readable};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::{register_bitfields, ReadOnly, ReadWrite};
 use kernel::utilities::StaticRef;
 use kernel::ErrorCode;
@@ -264,6 +292,24 @@ const ADC1_BASE: StaticRef<AdcRegisters> =
 const ADC_COMMON_BASE: StaticRef<AdcCommonRegisters> =
     unsafe { StaticRef::new(0x4001_2300 as *const AdcCommonRegisters) };
 
+// for use by dma2
+pub(crate) fn get_address_dr() -> u32 {
+    &ADC1_BASE.dr as *const ReadOnly<u32, DR::Register> as u32
+}
+
+/// Frequency of the ADC clock: the 16 MHz HSI, divided by the default
+/// prescaler of 2.
+const ADC_CLOCK_HZ: u32 = 8_000_000;
+
+/// Cycles taken by a conversion, in addition to the sampling time.
+const CONVERSION_CYCLES: u32 = 12;
+
+/// Sampling times, in ADC clock cycles, indexed by their SMPx value.
+const SAMPLING_CYCLES: [u32; 8] = [3, 15, 28, 56, 84, 112, 144, 480];
+
+/// Maximum number of channels in a scan sequence.
+pub const MAX_SCAN_CHANNELS: usize = 16;
+
 #[allow(dead_code)]
 #[repr(u32)]
 #[derive(Copy, Clone, PartialEq)]
@@ -303,6 +349,7 @@ enum ADCStatus {
     Idle,
     Off,
     OneSample,
+    HighSpeed,
 }
 
 pub struct Adc<'a> {
@@ -311,10 +358,32 @@ pub struct Adc<'a> {
     clock: AdcClock<'a>,
     status: Cell<ADCStatus>,
     client: OptionalCell<&'a dyn hil::adc::Client>,
+    highspeed_client: OptionalCell<&'a dyn hil::adc::HighSpeedClient>,
+    dma: OptionalCell<&'a dma::Stream<'a, dma::Dma2<'a>>>,
+    scan_channels: Cell<[Channel; MAX_SCAN_CHANNELS]>,
+    scan_len: Cell<usize>,
+    /// Number of samples of the buffer being filled.
+    length: Cell<usize>,
+    next_buffer: TakeCell<'static, [u16]>,
+    next_length: Cell<usize>,
+    /// Buffer being filled when sampling was stopped.
+    stopped_buffer: TakeCell<'static, [u16]>,
+}
+
+/// Lend `buffer` to the DMA, which transfers bytes.
+fn as_bytes(buffer: &'static mut [u16]) -> &'static mut [u8] {
+    let len = buffer.len() * 2;
+    unsafe { core::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, len) }
+}
+
+/// Recover a buffer lent with `as_bytes`.
+fn from_bytes(buffer: &'static mut [u8]) -> &'static mut [u16] {
+    let len = buffer.len() / 2;
+    unsafe { core::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u16, len) }
 }
 
 impl<'a> Adc<'a> {
-    pub const fn new(rcc: &'a rcc::Rcc) -> Adc {
+    pub fn new(rcc: &'a rcc::Rcc) -> Adc {
         Adc {
             registers: ADC1_BASE,
             common_registers: ADC_COMMON_BASE,
@@ -324,7 +393,97 @@ impl<'a> Adc<'a> {
             )),
             status: Cell::new(ADCStatus::Off),
             client: OptionalCell::empty(),
+            highspeed_client: OptionalCell::empty(),
+            dma: OptionalCell::empty(),
+            scan_channels: Cell::new([Channel::Channel0; MAX_SCAN_CHANNELS]),
+            scan_len: Cell::new(0),
+            length: Cell::new(0),
+            next_buffer: TakeCell::empty(),
+            next_length: Cell::new(0),
+            stopped_buffer: TakeCell::empty(),
+        }
+    }
+
+    pub fn set_dma(&self, dma: &'a dma::Stream<'a, dma::Dma2<'a>>) {
+        self.dma.set(dma);
+    }
+
+    /// Set the channels converted by high-speed sampling, in order. An empty
+    /// list converts the channel given to `sample_highspeed` instead.
+    pub fn set_scan_sequence(&self, channels: &[Channel]) -> Result<(), ErrorCode> {
+        if channels.len() > MAX_SCAN_CHANNELS {
+            return Err(ErrorCode::SIZE);
         }
+        if self.status.get() == ADCStatus::HighSpeed {
+            return Err(ErrorCode::BUSY);
+        }
+        let mut scan_channels = self.scan_channels.get();
+        scan_channels[..channels.len()].copy_from_slice(channels);
+        self.scan_channels.set(scan_channels);
+        self.scan_len.set(channels.len());
+        Ok(())
+    }
+
+    /// Program the regular sequence with `channels`, all sampled for
+    /// `sampling_time` (an SMPx value).
+    fn set_sequence(&self, channels: &[Channel], sampling_time: u32) {
+        let (mut sqr1, mut sqr2, mut sqr3) = (0, 0, 0);
+        for (index, channel) in channels.iter().enumerate() {
+            let value = *channel as u32;
+            match index {
+                0..=5 => sqr3 |= value << (5 * index),
+                6..=11 => sqr2 |= value << (5 * (index - 6)),
+                _ => sqr1 |= value << (5 * (index - 12)),
+            }
+        }
+        self.registers
+            .sqr1
+            .set(sqr1 | (((channels.len() - 1) as u32) << 20));
+        self.registers.sqr2.set(sqr2);
+        self.registers.sqr3.set(sqr3);
+
+        let (mut smpr1, mut smpr2) = (0, 0);
+        for channel in 0..19 {
+            if channel < 10 {
+                smpr2 |= sampling_time << (3 * channel);
+            } else {
+                smpr1 |= sampling_time << (3 * (channel - 10));
+            }
+        }
+        self.registers.smpr1.set(smpr1);
+        self.registers.smpr2.set(smpr2);
+    }
+
+    /// The longest sampling time at which `channels` channels are converted
+    /// at least `frequency` times per second.
+    fn sampling_time(frequency: u32, channels: usize) -> u32 {
+        SAMPLING_CYCLES
+            .iter()
+            .rposition(|cycles| {
+                ADC_CLOCK_HZ / ((cycles + CONVERSION_CYCLES) * channels as u32) >= frequency
+            })
+            .unwrap_or(0) as u32
+    }
+
+    /// Start filling `buffer` with `length` samples.
+    fn start_transfer(&self, buffer: &'static mut [u16], length: usize) {
+        self.length.set(length);
+        self.dma
+            .map(|dma| dma.do_transfer(as_bytes(buffer), length));
+        // Re-arm the DMA requests and restart the conversions, which stop
+        // on overrun while no transfer is set.
+        self.registers.cr2.modify(CR2::DMA::CLEAR);
+        self.registers.sr.modify(SR::OVR::CLEAR);
+        self.registers.cr2.modify(CR2::DMA::SET);
+        self.registers.cr2.modify(CR2::SWSTART::SET);
+    }
+
+    fn stop_conversions(&self) {
+        self.registers
+            .cr2
+            .modify(CR2::CONT::CLEAR + CR2::DMA::CLEAR + CR2::ALIGN::CLEAR);
+        self.registers.cr1.modify(CR1::SCAN::CLEAR);
+        self.status.set(ADCStatus::Idle);
     }
 
     pub fn enable(&self) {
@@ -398,6 +557,8 @@ impl<'a> hil::adc::Adc<'a> for Adc<'a> {
         if self.status.get() == ADCStatus::Idle {
             self.status.set(ADCStatus::OneSample);
             self.registers.sqr1.modify(SQR1::L.val(0b0000));
+            self.registers.smpr1.set(0);
+            self.registers.smpr2.set(0);
             self.registers.sqr3.modify(SQR3::SQ1.val(*channel as u32));
             self.registers.cr1.modify(CR1::EOCIE::SET);
             self.registers.cr2.modify(CR2::SWSTART::SET);
@@ -416,7 +577,18 @@ impl<'a> hil::adc::Adc<'a> for Adc<'a> {
     }
 
     fn stop_sampling(&self) -> Result<(), ErrorCode> {
-        Err(ErrorCode::NOSUPPORT)
+        match self.status.get() {
+            ADCStatus::HighSpeed => {
+                self.stop_conversions();
+                let (buffer, _) = self.dma.map_or((None, 0), |dma| dma.abort_transfer());
+                if let Some(buffer) = buffer {
+                    self.stopped_buffer.replace(from_bytes(buffer));
+                }
+                Ok(())
+            }
+            ADCStatus::OneSample => Err(ErrorCode::BUSY),
+            ADCStatus::Idle | ADCStatus::Off => Ok(()),
+        }
     }
 
     fn get_resolution_bits(&self) -> usize {
@@ -432,14 +604,13 @@ impl<'a> hil::adc::Adc<'a> for Adc<'a> {
     }
 }
 
-/// Not yet supported
 impl<'a> hil::adc::AdcHighSpeed<'a> for Adc<'a> {
     /// Capture buffered samples from the ADC continuously at a given
     /// frequency, calling the client whenever a buffer fills up. The client is
     /// then expected to either stop sampling or provide an additional buffer
-    /// to sample into. Note that due to hardware constraints the maximum
-    /// frequency range of the ADC is from 187 kHz to 23 Hz (although its
-    /// precision is limited at higher frequencies due to aliasing).
+    /// to sample into. The channels of the scan sequence are sampled instead of
+    /// `channel` if one is set, and the lengths are rounded down to a whole
+    /// number of sequences.
     ///
     /// - `channel`: the ADC channel to sample
     /// - `frequency`: frequency to sample at
@@ -449,14 +620,60 @@ impl<'a> hil::adc::AdcHighSpeed<'a> for Adc<'a> {
     /// - `length2`: number of samples to collect (up to buffer length)
     fn sample_highspeed(
         &self,
-        _channel: &Self::Channel,
-        _frequency: u32,
+        channel: &Self::Channel,
+        frequency: u32,
         buffer1: &'static mut [u16],
-        _length1: usize,
+        length1: usize,
         buffer2: &'static mut [u16],
-        _length2: usize,
+        length2: usize,
     ) -> Result<(), (ErrorCode, &'static mut [u16], &'static mut [u16])> {
-        Err((ErrorCode::NOSUPPORT, buffer1, buffer2))
+        if self.dma.is_none() {
+            return Err((ErrorCode::NOSUPPORT, buffer1, buffer2));
+        }
+        if self.status.get() == ADCStatus::Off {
+            self.enable();
+        }
+        if self.status.get() != ADCStatus::Idle {
+            return Err((ErrorCode::BUSY, buffer1, buffer2));
+        }
+
+        let scan_channels = self.scan_channels.get();
+        let channels = match self.scan_len.get() {
+            0 => core::slice::from_ref(channel),
+            len => &scan_channels[..len],
+        };
+        let whole_sequences = |length: usize, buffer: &[u16]| {
+            let length = core::cmp::min(length, buffer.len());
+            length - length % channels.len()
+        };
+        let length1 = whole_sequences(length1, buffer1);
+        let length2 = whole_sequences(length2, buffer2);
+        if length1 == 0 || frequency == 0 {
+            return Err((ErrorCode::INVAL, buffer1, buffer2));
+        }
+        if channels.contains(&Channel::Channel18) {
+            self.enable_temperature();
+        }
+
+        self.status.set(ADCStatus::HighSpeed);
+        self.set_sequence(channels, Self::sampling_time(frequency, channels.len()));
+        self.registers
+            .cr1
+            .modify(CR1::SCAN::SET + CR1::EOCIE::CLEAR);
+        // Samples are left-justified, and the end of conversion flag is set
+        // at the end of each sequence.
+        self.registers
+            .cr2
+            .modify(CR2::CONT::SET + CR2::ALIGN::SET + CR2::EOCS::CLEAR + CR2::DDS::CLEAR);
+
+        if length2 > 0 {
+            self.next_buffer.replace(buffer2);
+            self.next_length.set(length2);
+        } else {
+            self.stopped_buffer.replace(buffer2);
+        }
+        self.start_transfer(buffer1, length1);
+        Ok(())
     }
 
     /// Provide a new buffer to send on-going buffered continuous samples to.
@@ -467,9 +684,23 @@ impl<'a> hil::adc::AdcHighSpeed<'a> for Adc<'a> {
     fn provide_buffer(
         &self,
         buf: &'static mut [u16],
-        _length: usize,
+        length: usize,
     ) -> Result<(), (ErrorCode, &'static mut [u16])> {
-        Err((ErrorCode::NOSUPPORT, buf))
+        if self.status.get() != ADCStatus::HighSpeed {
+            return Err((ErrorCode::OFF, buf));
+        }
+        if self.next_buffer.is_some() {
+            return Err((ErrorCode::BUSY, buf));
+        }
+        let channels = core::cmp::max(self.scan_len.get(), 1);
+        let length = core::cmp::min(length, buf.len());
+        let length = length - length % channels;
+        if length == 0 {
+            return Err((ErrorCode::INVAL, buf));
+        }
+        self.next_buffer.replace(buf);
+        self.next_length.set(length);
+        Ok(())
     }
 
     /// Reclaim buffers after the ADC is stopped.
@@ -477,8 +708,35 @@ impl<'a> hil::adc::AdcHighSpeed<'a> for Adc<'a> {
     fn retrieve_buffers(
         &self,
     ) -> Result<(Option<&'static mut [u16]>, Option<&'static mut [u16]>), ErrorCode> {
-        Err(ErrorCode::NOSUPPORT)
+        if self.status.get() == ADCStatus::HighSpeed {
+            return Err(ErrorCode::BUSY);
+        }
+        Ok((self.stopped_buffer.take(), self.next_buffer.take()))
+    }
+
+    fn set_highspeed_client(&self, client: &'a dyn hil::adc::HighSpeedClient) {
+        self.highspeed_client.set(client);
     }
+}
 
-    fn set_highspeed_client(&self, _client: &'a dyn hil::adc::HighSpeedClient) {}
+impl<'a> dma::StreamClient<'a, dma::Dma2<'a>> for Adc<'a> {
+    fn transfer_done(&self, pid: dma::Dma2Peripheral) {
+        if pid != dma::Dma2Peripheral::ADC1 || self.status.get() != ADCStatus::HighSpeed {
+            return;
+        }
+        let buffer = self.dma.map_or(None, |dma| dma.return_buffer());
+        let length = self.length.get();
+
+        // Continue with the next buffer before handing this one back, so
+        // that few samples are lost.
+        match self.next_buffer.take() {
+            Some(next) => self.start_transfer(next, self.next_length.get()),
+            None => self.stop_conversions(),
+        }
+
+        if let Some(buffer) = buffer {
+            self.highspeed_client
+                .map(|client| client.samples_ready(from_bytes(buffer), length));
+        }
+    }
 }
@@ -8,6 +8,7 @@ use core::fmt::Write;
 use cortexm4::{self, CortexM4, CortexMVariant};
 use kernel::platform::chip::Chip;
 use kernel::platform::chip::InterruptService;
+use kernel::utilities::cells::OptionalCell;
 
 use crate::dma;
 use crate::nvic;
@@ -16,16 +17,24 @@ pub struct Stm32f4xx<'a, I: InterruptService + 'a> {
     mpu: cortexm4::mpu::MPU,
     userspace_kernel_boundary: cortexm4::syscall::SysCall,
     interrupt_service: &'a I,
+    pwr: OptionalCell<&'a crate::pwr::Pwr<'a>>,
 }
 
 pub struct Stm32f4xxDefaultPeripherals<'a> {
     pub adc1: crate::adc::Adc<'a>,
+    pub crc: crate::crc::Crc<'a>,
     pub dma1_streams: [crate::dma::Stream<'a, dma::Dma1<'a>>; 8],
     pub dma2_streams: [crate::dma::Stream<'a, dma::Dma2<'a>>; 8],
     pub exti: &'a crate::exti::Exti<'a>,
     pub i2c1: crate::i2c::I2C<'a>,
+    pub pwr: crate::pwr::Pwr<'a>,
+    pub rcc: &'a crate::rcc::Rcc,
+    pub rtc: crate::rtc::Rtc<'a>,
     pub spi3: crate::spi::Spi<'a>,
     pub tim2: crate::tim2::Tim2<'a>,
+    pub tim3: crate::tim::Tim<'a>,
+    pub tim4: crate::tim::Tim<'a>,
+    pub uid: crate::uid::Uid,
     pub usart1: crate::usart::Usart<'a, dma::Dma2<'a>>,
     pub usart2: crate::usart::Usart<'a, dma::Dma1<'a>>,
     pub usart3: crate::usart::Usart<'a, dma::Dma1<'a>>,
@@ -42,10 +51,14 @@ impl<'a> Stm32f4xxDefaultPeripherals<'a> {
     ) -> Self {
         Self {
             adc1: crate::adc::Adc::new(rcc),
+            crc: crate::crc::Crc::new(rcc),
             dma1_streams: dma::new_dma1_stream(dma1),
             dma2_streams: dma::new_dma2_stream(dma2),
             exti,
             i2c1: crate::i2c::I2C::new(rcc),
+            pwr: crate::pwr::Pwr::new(rcc),
+            rcc,
+            rtc: crate::rtc::Rtc::new(rcc),
             spi3: crate::spi::Spi::new(
                 crate::spi::SPI3_BASE,
                 crate::spi::SpiClock(crate::rcc::PeripheralClock::new(
@@ -56,6 +69,9 @@ impl<'a> Stm32f4xxDefaultPeripherals<'a> {
                 dma::Dma1Peripheral::SPI3_RX,
             ),
             tim2: crate::tim2::Tim2::new(rcc),
+            tim3: crate::tim::Tim::new_tim3(rcc),
+            tim4: crate::tim::Tim::new_tim4(rcc),
+            uid: crate::uid::Uid::new(),
             usart1: crate::usart::Usart::new_usart1(rcc),
             usart2: crate::usart::Usart::new_usart2(rcc),
             usart3: crate::usart::Usart::new_usart3(rcc),
@@ -75,6 +91,9 @@ impl<'a> Stm32f4xxDefaultPeripherals<'a> {
     // Setup any circular dependencies and register deferred calls
     pub fn setup_circular_deps(&'static self) {
         self.gpio_ports.setup_circular_deps();
+        self.pwr.set_exti(self.exti);
+        self.rtc.set_pwr(&self.pwr);
+        self.rtc.set_exti(self.exti);
 
         // Note: Boards with a CAN bus present also need to register its
         // deferred call.
@@ -82,6 +101,7 @@ impl<'a> Stm32f4xxDefaultPeripherals<'a> {
         kernel::deferred_call::DeferredCallClient::register(&self.usart2);
         kernel::deferred_call::DeferredCallClient::register(&self.usart3);
         kernel::deferred_call::DeferredCallClient::register(&self.fsmc);
+        kernel::deferred_call::DeferredCallClient::register(&self.crc);
     }
 }
 
@@ -107,6 +127,9 @@ impl<'a> InterruptService for Stm32f4xxDefaultPeripherals<'a> {
                 self.dma1_streams[dma::Dma1Peripheral::SPI3_TX.get_stream_idx()].handle_interrupt()
             }
 
+            nvic::DMA2_Stream0 => {
+                self.dma2_streams[dma::Dma2Peripheral::ADC1.get_stream_idx()].handle_interrupt()
+            }
             nvic::DMA2_Stream5 => self.dma2_streams
                 [dma::Dma2Peripheral::USART1_RX.get_stream_idx()]
             .handle_interrupt(),
@@ -134,6 +157,10 @@ impl<'a> InterruptService for Stm32f4xxDefaultPeripherals<'a> {
             nvic::EXTI15_10 => self.exti.handle_interrupt(),
 
             nvic::TIM2 => self.tim2.handle_interrupt(),
+            nvic::TIM3 => self.tim3.handle_interrupt(),
+            nvic::TIM4 => self.tim4.handle_interrupt(),
+
+            nvic::RTC_WKUP => self.rtc.handle_wakeup_interrupt(),
 
             _ => return false,
         }
@@ -147,8 +174,15 @@ impl<'a, I: InterruptService + 'a> Stm32f4xx<'a, I> {
             mpu: cortexm4::mpu::MPU::new(),
             userspace_kernel_boundary: cortexm4::syscall::SysCall::new(),
             interrupt_service,
+            pwr: OptionalCell::empty(),
         }
     }
+
+    /// Enter the deep sleep mode selected in the PWR when idle, whenever
+    /// the PWR is ready for it.
+    pub fn set_pwr(&self, pwr: &'a crate::pwr::Pwr<'a>) {
+        self.pwr.set(pwr);
+    }
 }
 
 impl<'a, I: InterruptService + 'a> Chip for Stm32f4xx<'a, I> {
@@ -186,8 +220,15 @@ impl<'a, I: InterruptService + 'a> Chip for Stm32f4xx<'a, I> {
     }
 
     fn sleep(&self) {
+        let mode = self.pwr.and_then(|pwr| pwr.deep_sleep_ready());
         unsafe {
-            cortexm4::scb::unset_sleepdeep();
+            match mode {
+                Some(mode) => {
+                    self.pwr.map(|pwr| pwr.prepare_deep_sleep(mode));
+                    cortexm4::scb::set_sleepdeep();
+                }
+                None => cortexm4::scb::unset_sleepdeep(),
+            }
             cortexm4::support::wfi();
         }
     }
@@ -0,0 +1,253 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! CRC calculation unit.
+//!
+//! The unit computes CRCs with the CRC-32 polynomial `0x04C11DB7` over
+//! 32-bit words, consuming each word from the most significant bit, with an
+//! initial value of `0xFFFFFFFF`. `CrcAlgorithm::Crc32` consumes each byte
+//! from the least significant bit, so the driver bit-reverses each word
+//! before writing it, and bit-reverses and inverts the result.
+//!
+//! Input which does not fill a whole word is kept until the next input, and
+//! the last 1 to 3 bytes of the data are added to the CRC in software when
+//! the result is computed.
+//!
+//! The unit computes a word in 4 AHB clock cycles, so the input is written
+//! synchronously, and the clients are called back from a deferred call.
+
+use core::cell::Cell;
+
+use crate::rcc;
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
+use kernel::hil::crc::{Client, Crc as CrcHil, CrcAlgorithm, CrcOutput};
+use kernel::platform::chip::ClockInterface;
+use kernel::utilities::cells::{MapCell, OptionalCell};
+use kernel::utilities::leasable_buffer::LeasableMutableBuffer;
+use kernel::utilities::registers::interfaces::{Readable, Writeable};
+use kernel::utilities::registers::{register_bitfields, ReadWrite};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+#[repr(C)]
+struct CrcRegisters {
+    /// Data register
+    dr: ReadWrite<u32>,
+    /// Independent data register
+    idr: ReadWrite<u32>,
+    /// Control register
+    cr: ReadWrite<u32, CR::Register>,
+}
+
+register_bitfields![u32,
+    CR [
+        /// Reset the CRC calculation unit to its initial value
+        RESET OFFSET(0) NUMBITS(1) []
+    ]
+];
+
+const CRC_BASE: StaticRef<CrcRegisters> =
+    unsafe { StaticRef::new(0x4002_3000 as *const CrcRegisters) };
+
+const POLYNOMIAL: u32 = 0x04C1_1DB7;
+
+/// Largest input processed at once, which bounds the time spent in `input`.
+const MAX_INPUT_LEN: usize = 1024;
+
+pub struct Crc<'a> {
+    registers: StaticRef<CrcRegisters>,
+    clock: CrcClock<'a>,
+    client: OptionalCell<&'a dyn Client>,
+    algorithm: OptionalCell<CrcAlgorithm>,
+    /// Input bytes which do not fill a word yet
+    pending: Cell<[u8; 4]>,
+    pending_len: Cell<usize>,
+    /// Input already processed, waiting for the deferred call
+    input_buffer: MapCell<LeasableMutableBuffer<'static, u8>>,
+    compute_requested: Cell<bool>,
+    deferred_call: DeferredCall,
+}
+
+impl<'a> Crc<'a> {
+    pub fn new(rcc: &'a rcc::Rcc) -> Self {
+        Self {
+            registers: CRC_BASE,
+            clock: CrcClock(rcc::PeripheralClock::new(
+                rcc::PeripheralClockType::AHB1(rcc::HCLK1::CRC),
+                rcc,
+            )),
+            client: OptionalCell::empty(),
+            algorithm: OptionalCell::empty(),
+            pending: Cell::new([0; 4]),
+            pending_len: Cell::new(0),
+            input_buffer: MapCell::empty(),
+            compute_requested: Cell::new(false),
+            deferred_call: DeferredCall::new(),
+        }
+    }
+
+    pub fn is_enabled_clock(&self) -> bool {
+        self.clock.is_enabled()
+    }
+
+    pub fn enable_clock(&self) {
+        self.clock.enable();
+    }
+
+    pub fn disable_clock(&self) {
+        self.clock.disable();
+    }
+
+    fn busy(&self) -> bool {
+        self.input_buffer.is_some() || self.compute_requested.get()
+    }
+
+    fn reset(&self) {
+        if !self.is_enabled_clock() {
+            self.enable_clock();
+        }
+        self.registers.cr.write(CR::RESET::SET);
+        self.pending_len.set(0);
+    }
+
+    /// Add the word of the little-endian `bytes` to the CRC.
+    fn write_word(&self, bytes: [u8; 4]) {
+        self.registers
+            .dr
+            .set(u32::from_le_bytes(bytes).reverse_bits());
+    }
+
+    /// Compute the CRC, adding the pending bytes in software.
+    fn result(&self) -> u32 {
+        let mut crc = self.registers.dr.get();
+        let pending = self.pending.get();
+        for byte in pending.iter().take(self.pending_len.get()) {
+            crc ^= (byte.reverse_bits() as u32) << 24;
+            for _ in 0..8 {
+                crc = if crc & 0x8000_0000 != 0 {
+                    (crc << 1) ^ POLYNOMIAL
+                } else {
+                    crc << 1
+                };
+            }
+        }
+        !crc.reverse_bits()
+    }
+}
+
+impl DeferredCallClient for Crc<'_> {
+    fn handle_deferred_call(&self) {
+        if let Some(buffer) = self.input_buffer.take() {
+            self.client.map(move |client| {
+                client.input_done(Ok(()), buffer);
+            });
+        } else if self.compute_requested.get() {
+            let result = self.result();
+            self.reset();
+            self.compute_requested.set(false);
+            self.client.map(|client| {
+                client.crc_done(Ok(CrcOutput::Crc32(result)));
+            });
+        }
+    }
+
+    fn register(&'static self) {
+        self.deferred_call.register(self);
+    }
+}
+
+impl<'a> CrcHil<'a> for Crc<'a> {
+    fn set_client(&self, client: &'a dyn Client) {
+        self.client.set(client);
+    }
+
+    fn algorithm_supported(&self, algorithm: CrcAlgorithm) -> bool {
+        // The polynomial of the unit is fixed.
+        match algorithm {
+            CrcAlgorithm::Crc32 => true,
+            CrcAlgorithm::Crc32C => false,
+            CrcAlgorithm::Crc16CCITT => false,
+        }
+    }
+
+    fn set_algorithm(&self, algorithm: CrcAlgorithm) -> Result<(), ErrorCode> {
+        if self.busy() {
+            return Err(ErrorCode::BUSY);
+        }
+        if !self.algorithm_supported(algorithm) {
+            return Err(ErrorCode::NOSUPPORT);
+        }
+        self.algorithm.set(algorithm);
+        self.reset();
+        Ok(())
+    }
+
+    fn input(
+        &self,
+        mut data: LeasableMutableBuffer<'static, u8>,
+    ) -> Result<(), (ErrorCode, LeasableMutableBuffer<'static, u8>)> {
+        if self.algorithm.is_none() {
+            return Err((ErrorCode::RESERVE, data));
+        }
+        if self.busy() {
+            return Err((ErrorCode::BUSY, data));
+        }
+        if !self.is_enabled_clock() {
+            self.enable_clock();
+        }
+
+        if data.len() > MAX_INPUT_LEN {
+            data.slice(0..MAX_INPUT_LEN);
+        }
+
+        let mut pending = self.pending.get();
+        let mut pending_len = self.pending_len.get();
+        for i in 0..data.len() {
+            pending[pending_len] = data[i];
+            pending_len += 1;
+            if pending_len == 4 {
+                self.write_word(pending);
+                pending_len = 0;
+            }
+        }
+        self.pending.set(pending);
+        self.pending_len.set(pending_len);
+
+        self.input_buffer.replace(data);
+        self.deferred_call.set();
+        Ok(())
+    }
+
+    fn compute(&self) -> Result<(), ErrorCode> {
+        if self.algorithm.is_none() {
+            return Err(ErrorCode::RESERVE);
+        }
+        if self.busy() {
+            return Err(ErrorCode::BUSY);
+        }
+        self.compute_requested.set(true);
+        self.deferred_call.set();
+        Ok(())
+    }
+
+    fn disable(&self) {
+        self.disable_clock();
+    }
+}
+
+struct CrcClock<'a>(rcc::PeripheralClock<'a>);
+
+impl ClockInterface for CrcClock<'_> {
+    fn is_enabled(&self) -> bool {
+        self.0.is_enabled()
+    }
+
+    fn enable(&self) {
+        self.0.enable();
+    }
+
+    fn disable(&self) {
+        self.0.disable();
+    }
+}
@@ -0,0 +1,257 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Digital to analog converter
+//!
+//! The DAC has two 12-bit channels, whose outputs are PA4 (channel 1) and PA5
+//! (channel 2). Each channel is a separate `DacChannel`, and values are
+//! written through software triggers. The board sets the output pin of a
+//! channel to analog mode before using it:
+//!
+//! ```rust,ignore
+//! gpio_ports
+//!     .get_pin(stm32f429zi::gpio::PinId::PA04)
+//!     .map(|pin| pin.set_mode(stm32f429zi::gpio::Mode::AnalogMode));
+//! ```
+
+use crate::rcc;
+use core::cell::Cell;
+use kernel::hil;
+use kernel::platform::chip::ClockInterface;
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
+use kernel::utilities::registers::{register_bitfields, ReadOnly, ReadWrite, WriteOnly};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+#[repr(C)]
+pub struct DacRegisters {
+    /// control register
+    cr: ReadWrite<u32, CR::Register>,
+    /// software trigger register
+    swtrigr: WriteOnly<u32, SWTRIGR::Register>,
+    /// channel 1 12-bit right-aligned data holding register
+    dhr12r1: ReadWrite<u32, DHR12R::Register>,
+    /// channel 1 12-bit left-aligned data holding register
+    dhr12l1: ReadWrite<u32>,
+    /// channel 1 8-bit right-aligned data holding register
+    dhr8r1: ReadWrite<u32>,
+    /// channel 2 12-bit right-aligned data holding register
+    dhr12r2: ReadWrite<u32, DHR12R::Register>,
+    /// channel 2 12-bit left-aligned data holding register
+    dhr12l2: ReadWrite<u32>,
+    /// channel 2 8-bit right-aligned data holding register
+    dhr8r2: ReadWrite<u32>,
+    /// dual 12-bit right-aligned data holding register
+    dhr12rd: ReadWrite<u32>,
+    /// dual 12-bit left-aligned data holding register
+    dhr12ld: ReadWrite<u32>,
+    /// dual 8-bit right-aligned data holding register
+    dhr8rd: ReadWrite<u32>,
+    /// channel 1 data output register
+    dor1: ReadOnly<u32, DOR::Register>,
+    /// channel 2 data output register
+    dor2: ReadOnly<u32, DOR::Register>,
+    /// status register
+    sr: ReadWrite<u32, SR::Register>,
+}
+
+register_bitfields![u32,
+    CR [
+        /// DAC channel2 DMA underrun interrupt enable
+        DMAUDRIE2 OFFSET(29) NUMBITS(1) [],
+        /// DAC channel2 DMA enable
+        DMAEN2 OFFSET(28) NUMBITS(1) [],
+        /// DAC channel2 mask/amplitude selector
+        MAMP2 OFFSET(24) NUMBITS(4) [],
+        /// DAC channel2 noise/triangle wave generation enable
+        WAVE2 OFFSET(22) NUMBITS(2) [],
+        /// DAC channel2 trigger selection
+        TSEL2 OFFSET(19) NUMBITS(3) [
+            Software = 0b111
+        ],
+        /// DAC channel2 trigger enable
+        TEN2 OFFSET(18) NUMBITS(1) [],
+        /// DAC channel2 output buffer disable
+        BOFF2 OFFSET(17) NUMBITS(1) [],
+        /// DAC channel2 enable
+        EN2 OFFSET(16) NUMBITS(1) [],
+        /// DAC channel1 DMA underrun interrupt enable
+        DMAUDRIE1 OFFSET(13) NUMBITS(1) [],
+        /// DAC channel1 DMA enable
+        DMAEN1 OFFSET(12) NUMBITS(1) [],
+        /// DAC channel1 mask/amplitude selector
+        MAMP1 OFFSET(8) NUMBITS(4) [],
+        /// DAC channel1 noise/triangle wave generation enable
+        WAVE1 OFFSET(6) NUMBITS(2) [],
+        /// DAC channel1 trigger selection
+        TSEL1 OFFSET(3) NUMBITS(3) [
+            Software = 0b111
+        ],
+        /// DAC channel1 trigger enable
+        TEN1 OFFSET(2) NUMBITS(1) [],
+        /// DAC channel1 output buffer disable
+        BOFF1 OFFSET(1) NUMBITS(1) [],
+        /// DAC channel1 enable
+        EN1 OFFSET(0) NUMBITS(1) []
+    ],
+    SWTRIGR [
+        /// DAC channel2 software trigger
+        SWTRIG2 OFFSET(1) NUMBITS(1) [],
+        /// DAC channel1 software trigger
+        SWTRIG1 OFFSET(0) NUMBITS(1) []
+    ],
+    DHR12R [
+        /// 12-bit right-aligned data
+        DACDHR OFFSET(0) NUMBITS(12) []
+    ],
+    DOR [
+        /// DAC data output
+        DACDOR OFFSET(0) NUMBITS(12) []
+    ],
+    SR [
+        /// DAC channel2 DMA underrun flag
+        DMAUDR2 OFFSET(29) NUMBITS(1) [],
+        /// DAC channel1 DMA underrun flag
+        DMAUDR1 OFFSET(13) NUMBITS(1) []
+    ]
+];
+
+/// Number of bits of the output values.
+const RESOLUTION_BITS: usize = 12;
+
+/// The output range is set by VREF+, which is connected to VDDA on most
+/// boards.
+const VOLTAGE_REFERENCE_MV: usize = 3300;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Channel {
+    /// Output on PA4
+    Channel1,
+    /// Output on PA5
+    Channel2,
+}
+
+pub struct DacChannel<'a> {
+    registers: StaticRef<DacRegisters>,
+    channel: Channel,
+    clock: DacClock<'a>,
+    enabled: Cell<bool>,
+}
+
+impl<'a> DacChannel<'a> {
+    pub const fn new(
+        registers: StaticRef<DacRegisters>,
+        channel: Channel,
+        rcc: &'a rcc::Rcc,
+    ) -> DacChannel<'a> {
+        DacChannel {
+            registers,
+            channel,
+            clock: DacClock(rcc::PeripheralClock::new(
+                rcc::PeripheralClockType::APB1(rcc::PCLK1::DAC),
+                rcc,
+            )),
+            enabled: Cell::new(false),
+        }
+    }
+
+    pub fn is_enabled_clock(&self) -> bool {
+        self.clock.is_enabled()
+    }
+
+    pub fn enable_clock(&self) {
+        self.clock.enable();
+    }
+
+    pub fn disable_clock(&self) {
+        self.clock.disable();
+    }
+
+    /// Enable the channel with the output buffer and software triggers. The
+    /// other channel is left untouched, as both share the clock and the
+    /// control register.
+    fn initialize(&self) {
+        self.enable_clock();
+        match self.channel {
+            Channel::Channel1 => self.registers.cr.modify(
+                CR::TSEL1::Software
+                    + CR::TEN1::SET
+                    + CR::BOFF1::CLEAR
+                    + CR::WAVE1::CLEAR
+                    + CR::DMAEN1::CLEAR
+                    + CR::EN1::SET,
+            ),
+            Channel::Channel2 => self.registers.cr.modify(
+                CR::TSEL2::Software
+                    + CR::TEN2::SET
+                    + CR::BOFF2::CLEAR
+                    + CR::WAVE2::CLEAR
+                    + CR::DMAEN2::CLEAR
+                    + CR::EN2::SET,
+            ),
+        }
+        self.enabled.set(true);
+    }
+
+    /// Returns the value currently output by the channel.
+    pub fn get_output(&self) -> usize {
+        match self.channel {
+            Channel::Channel1 => self.registers.dor1.read(DOR::DACDOR) as usize,
+            Channel::Channel2 => self.registers.dor2.read(DOR::DACDOR) as usize,
+        }
+    }
+}
+
+struct DacClock<'a>(rcc::PeripheralClock<'a>);
+
+impl ClockInterface for DacClock<'_> {
+    fn is_enabled(&self) -> bool {
+        self.0.is_enabled()
+    }
+
+    fn enable(&self) {
+        self.0.enable();
+    }
+
+    fn disable(&self) {
+        self.0.disable();
+    }
+}
+
+impl hil::dac::DacChannel for DacChannel<'_> {
+    fn set_value(&self, value: usize) -> Result<(), ErrorCode> {
+        if value >= 1 << RESOLUTION_BITS {
+            return Err(ErrorCode::INVAL);
+        }
+        if !self.enabled.get() {
+            self.initialize();
+        }
+
+        // The value moves to the output register one APB1 clock cycle after
+        // the trigger.
+        match self.channel {
+            Channel::Channel1 => {
+                self.registers
+                    .dhr12r1
+                    .write(DHR12R::DACDHR.val(value as u32));
+                self.registers.swtrigr.write(SWTRIGR::SWTRIG1::SET);
+            }
+            Channel::Channel2 => {
+                self.registers
+                    .dhr12r2
+                    .write(DHR12R::DACDHR.val(value as u32));
+                self.registers.swtrigr.write(SWTRIGR::SWTRIG2::SET);
+            }
+        }
+        Ok(())
+    }
+
+    fn get_resolution_bits(&self) -> usize {
+        RESOLUTION_BITS
+    }
+
+    fn get_voltage_reference_mv(&self) -> Option<usize> {
+        Some(VOLTAGE_REFERENCE_MV)
+    }
+}
@@ -109,4 +109,12 @@ impl Dbg {
             .dbgmcu_apb1_fz
             .modify(DBGMCU_APB1_FZ::DBG_TIM2_STOP::SET);
     }
+
+    /// Enable the trace pins in asynchronous mode, making the SWO pin
+    /// (PB3) output the ITM trace.
+    pub fn enable_async_trace(&self) {
+        self.registers
+            .dbgmcu_cr
+            .modify(DBGMCU_CR::TRACE_IOEN::SET + DBGMCU_CR::TRACE_MODE.val(0));
+    }
 }
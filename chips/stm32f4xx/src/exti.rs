@@ -12,6 +12,7 @@ use kernel::utilities::registers::{register_bitfields, ReadWrite};
 use kernel::utilities::StaticRef;
 
 use crate::gpio;
+use crate::nvic;
 use crate::syscfg;
 
 /// External interrupt/event controller
@@ -386,6 +387,22 @@ enum_from_primitive! {
     }
 }
 
+impl LineId {
+    /// NVIC interrupt on which the line is delivered. Lines 5 to 9, and 10
+    /// to 15, share an interrupt.
+    pub fn irqn(&self) -> u32 {
+        match *self as u8 {
+            0 => nvic::EXTI0,
+            1 => nvic::EXTI1,
+            2 => nvic::EXTI2,
+            3 => nvic::EXTI3,
+            4 => nvic::EXTI4,
+            5..=9 => nvic::EXTI9_5,
+            _ => nvic::EXTI15_10,
+        }
+    }
+}
+
 // `line_gpiopin_map` is used to call `handle_interrupt()` on the pin.
 pub struct Exti<'a> {
     registers: StaticRef<ExtiRegisters>,
@@ -433,6 +450,11 @@ impl<'a> Exti<'a> {
         self.clock.disable();
     }
 
+    /// The pin associated with `lineid`, if any.
+    pub fn line_gpiopin(&self, lineid: LineId) -> Option<&'static gpio::Pin<'static>> {
+        self.line_gpiopin_map[usize::from(lineid as u8)].extract()
+    }
+
     pub fn associate_line_gpiopin(&self, lineid: LineId, pin: &'static gpio::Pin<'static>) {
         self.line_gpiopin_map[usize::from(lineid as u8)].set(pin);
         self.syscfg.configure_interrupt(pin.get_pinid());
@@ -507,6 +529,31 @@ impl<'a> Exti<'a> {
         }
     }
 
+    /// Deliver the RTC wakeup timer, on the rising edge of line 22, to the
+    /// `RTC_WKUP` interrupt.
+    pub fn enable_rtc_wakeup_line(&self) {
+        self.registers.rtsr.modify(RTSR::TR22::SET);
+        self.registers.imr.modify(IMR::MR22::SET);
+    }
+
+    pub fn disable_rtc_wakeup_line(&self) {
+        self.registers.imr.modify(IMR::MR22::CLEAR);
+    }
+
+    pub fn is_rtc_wakeup_line_enabled(&self) -> bool {
+        self.registers.imr.is_set(IMR::MR22)
+    }
+
+    pub fn clear_rtc_wakeup_pending(&self) {
+        self.registers.pr.write(PR::PR22::SET);
+    }
+
+    /// Whether at least one line is unmasked. Only those lines wake the chip
+    /// up from Stop mode.
+    pub fn has_wakeups(&self) -> bool {
+        self.registers.imr.get() != 0
+    }
+
     pub fn is_pending(&self, lineid: LineId) -> bool {
         let val = match lineid {
             LineId::Exti0 => self.registers.pr.read(PR::PR0),
@@ -167,6 +167,9 @@ pub struct Fsmc<'a> {
     buffer: TakeCell<'static, [u8]>,
     bus_width: Cell<usize>,
     len: Cell<usize>,
+    /// An operation waits for its deferred call.
+    busy: Cell<bool>,
+    aborted: Cell<bool>,
 
     deferred_call: DeferredCall,
 }
@@ -185,6 +188,8 @@ impl<'a> Fsmc<'a> {
             buffer: TakeCell::empty(),
             bus_width: Cell::new(1),
             len: Cell::new(0),
+            busy: Cell::new(false),
+            aborted: Cell::new(false),
 
             deferred_call: DeferredCall::new(),
         }
@@ -244,22 +249,38 @@ impl<'a> Fsmc<'a> {
         self.bank[bank as usize].map_or(None, |bank| Some(bank.ram.get()))
     }
 
-    #[cfg(all(target_arch = "arm", target_os = "none"))]
     #[inline]
     fn write_reg(&self, bank: FsmcBanks, addr: u16) {
+        self.bank[bank as usize].map(|bank| bank.write_reg(addr));
+    }
+
+    #[inline]
+    fn write_data(&self, bank: FsmcBanks, data: u16) {
+        self.bank[bank as usize].map(|bank| bank.write_data(data));
+    }
+}
+
+impl FsmcBank {
+    /// Write the address (register) of the device. Writes are synchronous,
+    /// so this can also be used by the panic handler, once the FSMC is
+    /// enabled.
+    #[cfg(all(target_arch = "arm", target_os = "none"))]
+    #[inline]
+    pub fn write_reg(&self, addr: u16) {
         use kernel::utilities::registers::interfaces::Writeable;
-        self.bank[bank as usize].map(|bank| bank.reg.set(addr));
+        self.reg.set(addr);
         unsafe {
             use core::arch::asm;
             asm!("dsb 0xf");
         }
     }
 
+    /// Write data to the device.
     #[cfg(all(target_arch = "arm", target_os = "none"))]
     #[inline]
-    fn write_data(&self, bank: FsmcBanks, data: u16) {
+    pub fn write_data(&self, data: u16) {
         use kernel::utilities::registers::interfaces::Writeable;
-        self.bank[bank as usize].map(|bank| bank.ram.set(data));
+        self.ram.set(data);
         unsafe {
             use core::arch::asm;
             asm!("dsb 0xf");
@@ -267,12 +288,12 @@ impl<'a> Fsmc<'a> {
     }
 
     #[cfg(not(any(target_arch = "arm", target_os = "none")))]
-    fn write_reg(&self, _bank: FsmcBanks, _addr: u16) {
+    pub fn write_reg(&self, _addr: u16) {
         unimplemented!()
     }
 
     #[cfg(not(any(target_arch = "arm", target_os = "none")))]
-    fn write_data(&self, _bank: FsmcBanks, _data: u16) {
+    pub fn write_data(&self, _data: u16) {
         unimplemented!()
     }
 }
@@ -283,15 +304,21 @@ impl DeferredCallClient for Fsmc<'_> {
     }
 
     fn handle_deferred_call(&self) {
+        self.busy.set(false);
+        let (len, status) = if self.aborted.take() {
+            (0, Err(ErrorCode::CANCEL))
+        } else {
+            (self.len.get(), Ok(()))
+        };
         self.buffer.take().map_or_else(
             || {
                 self.client.map(move |client| {
-                    client.command_complete(None, 0, Ok(()));
+                    client.command_complete(None, 0, status);
                 });
             },
             |buffer| {
                 self.client.map(move |client| {
-                    client.command_complete(Some(buffer), self.len.get(), Ok(()));
+                    client.command_complete(Some(buffer), len, status);
                 });
             },
         );
@@ -319,6 +346,7 @@ impl Bus8080<'static> for Fsmc<'_> {
         match addr_width {
             BusWidth::Bits8 => {
                 self.write_reg(FsmcBanks::Bank1, addr as u16);
+                self.busy.set(true);
                 self.deferred_call.set();
                 Ok(())
             }
@@ -350,6 +378,7 @@ impl Bus8080<'static> for Fsmc<'_> {
             self.buffer.replace(buffer);
             self.bus_width.set(bytes);
             self.len.set(len);
+            self.busy.set(true);
             self.deferred_call.set();
             Ok(())
         } else {
@@ -381,6 +410,7 @@ impl Bus8080<'static> for Fsmc<'_> {
             self.buffer.replace(buffer);
             self.bus_width.set(bytes);
             self.len.set(len);
+            self.busy.set(true);
             self.deferred_call.set();
             Ok(())
         } else {
@@ -388,6 +418,17 @@ impl Bus8080<'static> for Fsmc<'_> {
         }
     }
 
+    fn abort(&self) -> Result<(), ErrorCode> {
+        // The transfer itself is synchronous, so only its completion can be
+        // aborted.
+        if self.busy.get() {
+            self.aborted.set(true);
+            Ok(())
+        } else {
+            Err(ErrorCode::INVAL)
+        }
+    }
+
     fn set_client(&self, client: &'static dyn Client) {
         self.client.replace(client);
     }
@@ -6,17 +6,81 @@ use core::cell::Cell;
 
 use kernel::hil;
 use kernel::hil::i2c::{self, Error, I2CHwMasterClient, I2CMaster};
+use kernel::hil::statistics::{DriverStatistics, Statistics, StatisticsCounters};
 use kernel::platform::chip::ClockInterface;
 use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::{register_bitfields, ReadWrite};
 use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
 
 use crate::rcc;
 
+/// Bus speeds. The I2C peripherals support standard and fast mode only:
+/// fast mode plus is limited to the separate FMPI2C peripheral of some
+/// models, so `Speed1M` is rejected with `NOSUPPORT`.
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum I2CSpeed {
     Speed100k,
     Speed400k,
+    Speed1M,
+}
+
+/// Peripheral clock range accepted by the I2C peripheral, in MHz. Fast mode
+/// needs at least 4 MHz.
+const MIN_CLOCK_MHZ: u32 = 2;
+const MIN_FAST_MODE_CLOCK_MHZ: u32 = 4;
+const MAX_CLOCK_MHZ: u32 = 50;
+
+/// Maximum SCL rise time in standard and fast mode, in nanoseconds.
+const MAX_RISE_TIME_SM_NS: u32 = 1000;
+const MAX_RISE_TIME_FM_NS: u32 = 300;
+
+/// Register values for a bus speed at a given peripheral clock.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Timing {
+    freq_mhz: u32,
+    fast_mode: bool,
+    ccr: u32,
+    trise: u32,
+}
+
+impl Timing {
+    /// Compute the timings for `speed` from the APB1 clock frequency. The
+    /// clock control value is rounded up, so the bus never runs faster than
+    /// requested.
+    fn compute(speed: I2CSpeed, pclk_hz: u32) -> Result<Timing, ErrorCode> {
+        let freq_mhz = pclk_hz / 1_000_000;
+        if !(MIN_CLOCK_MHZ..=MAX_CLOCK_MHZ).contains(&freq_mhz) {
+            return Err(ErrorCode::INVAL);
+        }
+        match speed {
+            I2CSpeed::Speed100k => {
+                // Thigh = Tlow = CCR * Tpclk
+                let ccr = (pclk_hz + 2 * 100_000 - 1) / (2 * 100_000);
+                Ok(Timing {
+                    freq_mhz,
+                    fast_mode: false,
+                    ccr: ccr.max(4),
+                    trise: freq_mhz * MAX_RISE_TIME_SM_NS / 1000 + 1,
+                })
+            }
+            I2CSpeed::Speed400k => {
+                if freq_mhz < MIN_FAST_MODE_CLOCK_MHZ {
+                    return Err(ErrorCode::INVAL);
+                }
+                // duty cycle 2, Thigh = CCR * Tpclk, Tlow = 2 * CCR * Tpclk
+                let ccr = (pclk_hz + 3 * 400_000 - 1) / (3 * 400_000);
+                Ok(Timing {
+                    freq_mhz,
+                    fast_mode: true,
+                    ccr: ccr.max(1),
+                    trise: freq_mhz * MAX_RISE_TIME_FM_NS / 1000 + 1,
+                })
+            }
+            I2CSpeed::Speed1M => Err(ErrorCode::NOSUPPORT),
+        }
+    }
 }
 
 /// Inter-Integrated Circuit
@@ -198,7 +262,11 @@ pub struct I2C<'a> {
 
     slave_address: Cell<u8>,
 
+    rcc: &'a rcc::Rcc,
+
     status: Cell<I2CStatus>,
+
+    statistics: StatisticsCounters,
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -221,6 +289,7 @@ impl<'a> I2C<'a> {
             master_client: OptionalCell::empty(),
 
             slave_address: Cell::new(0),
+            rcc,
 
             buffer: TakeCell::empty(),
             tx_position: Cell::new(0),
@@ -230,33 +299,35 @@ impl<'a> I2C<'a> {
             rx_len: Cell::new(0),
 
             status: Cell::new(I2CStatus::Idle),
+
+            statistics: StatisticsCounters::new(),
         }
     }
 
-    pub fn set_speed(&self, speed: I2CSpeed, system_clock_in_mhz: usize) {
+    /// Set the bus speed. The timings are computed from the APB1 clock
+    /// frequency read back from the RCC, so this must be called again if the
+    /// clock configuration changes.
+    ///
+    /// Returns `FAIL` if the APB1 frequency is unknown, which happens when
+    /// the system clock runs from the HSE and `Rcc::set_hse_frequency` was
+    /// not called, and `INVAL` if it is out of the range the peripheral
+    /// accepts for the speed.
+    pub fn set_speed(&self, speed: I2CSpeed) -> Result<(), ErrorCode> {
+        let pclk_hz = self.rcc.get_apb1_frequency().ok_or(ErrorCode::FAIL)?;
+        let timing = Timing::compute(speed, pclk_hz)?;
         self.disable();
-        self.registers
-            .cr2
-            .modify(CR2::FREQ.val(system_clock_in_mhz as u32));
-        match speed {
-            I2CSpeed::Speed100k => {
-                self.registers
-                    .ccr
-                    .modify(CCR::CCR.val(system_clock_in_mhz as u32 * 5) + CCR::FS::SM_MODE);
-                self.registers
-                    .trise
-                    .modify(TRISE::TRISE.val(system_clock_in_mhz as u32 + 1));
-            }
-            I2CSpeed::Speed400k => {
-                self.registers
-                    .ccr
-                    .modify(CCR::CCR.val(system_clock_in_mhz as u32 * 5 / 6) + CCR::FS::FM_MODE);
-                self.registers
-                    .trise
-                    .modify(TRISE::TRISE.val(system_clock_in_mhz as u32 + 1));
-            }
-        }
+        self.registers.cr2.modify(CR2::FREQ.val(timing.freq_mhz));
+        self.registers.ccr.write(
+            CCR::CCR.val(timing.ccr)
+                + if timing.fast_mode {
+                    CCR::FS::FM_MODE
+                } else {
+                    CCR::FS::SM_MODE
+                },
+        );
+        self.registers.trise.write(TRISE::TRISE.val(timing.trise));
         self.enable();
+        Ok(())
     }
 
     pub fn is_enabled_clock(&self) -> bool {
@@ -310,11 +381,7 @@ impl<'a> I2C<'a> {
             if self.buffer.is_some() && self.rx_position.get() == self.rx_len.get() {
                 self.registers.cr1.modify(CR1::STOP::SET);
                 self.stop();
-                self.master_client.map(|client| {
-                    self.buffer
-                        .take()
-                        .map(|buf| client.command_complete(buf, Ok(())))
-                });
+                self.complete(Ok(()));
             }
         }
 
@@ -324,20 +391,12 @@ impl<'a> I2C<'a> {
                     if self.tx_position.get() < self.tx_len.get() {
                         self.registers.cr1.modify(CR1::STOP::SET);
                         self.stop();
-                        self.master_client.map(|client| {
-                            self.buffer
-                                .take()
-                                .map(|buf| client.command_complete(buf, Err(Error::DataNak)))
-                        });
+                        self.complete(Err(Error::DataNak));
                     } else {
                         if self.status.get() == I2CStatus::Writing {
                             self.registers.cr1.modify(CR1::STOP::SET);
                             self.stop();
-                            self.master_client.map(|client| {
-                                self.buffer
-                                    .take()
-                                    .map(|buf| client.command_complete(buf, Ok(())))
-                            });
+                            self.complete(Ok(()));
                         } else {
                             self.status.set(I2CStatus::Reading);
                             self.start_read();
@@ -352,11 +411,7 @@ impl<'a> I2C<'a> {
                     };
                     self.registers.cr1.modify(CR1::STOP::SET);
                     self.stop();
-                    self.master_client.map(|client| {
-                        self.buffer
-                            .take()
-                            .map(|buf| client.command_complete(buf, status))
-                    });
+                    self.complete(status);
                 }
                 _ => panic!("i2c status error"),
             }
@@ -364,19 +419,32 @@ impl<'a> I2C<'a> {
     }
 
     pub fn handle_error(&self) {
-        self.master_client.map(|client| {
-            self.buffer
-                .take()
-                .map(|buf| client.command_complete(buf, Err(Error::DataNak)))
-        });
+        self.complete(Err(Error::DataNak));
         self.stop();
     }
 
+    /// Return the buffer to the client, with the result of the transfer.
+    fn complete(&self, status: Result<(), Error>) {
+        if let Some(buf) = self.buffer.take() {
+            self.statistics
+                .finish(&status, self.tx_position.get() + self.rx_position.get());
+            self.master_client
+                .map(|client| client.command_complete(buf, status));
+        }
+    }
+
     fn reset(&self) {
         self.disable();
         self.enable();
     }
 
+    /// Count a new transfer, and clear the positions of the previous one.
+    fn start_transfer(&self) {
+        self.tx_position.set(0);
+        self.rx_position.set(0);
+        self.statistics.start();
+    }
+
     fn start_write(&self) {
         self.tx_position.set(0);
         self.registers
@@ -404,6 +472,20 @@ impl<'a> I2C<'a> {
     }
 }
 
+impl i2c::I2CBusSpeed for I2C<'_> {
+    fn max_bus_speed(&self) -> i2c::BusSpeed {
+        i2c::BusSpeed::Fast400k
+    }
+
+    fn set_bus_speed(&self, speed: i2c::BusSpeed) -> Result<(), ErrorCode> {
+        self.set_speed(match speed {
+            i2c::BusSpeed::Standard100k => I2CSpeed::Speed100k,
+            i2c::BusSpeed::Fast400k => I2CSpeed::Speed400k,
+            i2c::BusSpeed::FastPlus1M => I2CSpeed::Speed1M,
+        })
+    }
+}
+
 impl<'a> i2c::I2CMaster<'a> for I2C<'a> {
     fn set_master_client(&self, master_client: &'a dyn I2CHwMasterClient) {
         self.master_client.replace(master_client);
@@ -423,6 +505,7 @@ impl<'a> i2c::I2CMaster<'a> for I2C<'a> {
     ) -> Result<(), (Error, &'static mut [u8])> {
         if self.status.get() == I2CStatus::Idle {
             self.reset();
+            self.start_transfer();
             self.status.set(I2CStatus::WritingReading);
             self.slave_address.set(addr);
             self.buffer.replace(data);
@@ -442,6 +525,7 @@ impl<'a> i2c::I2CMaster<'a> for I2C<'a> {
     ) -> Result<(), (Error, &'static mut [u8])> {
         if self.status.get() == I2CStatus::Idle {
             self.reset();
+            self.start_transfer();
             self.status.set(I2CStatus::Writing);
             self.slave_address.set(addr);
             self.buffer.replace(data);
@@ -460,6 +544,7 @@ impl<'a> i2c::I2CMaster<'a> for I2C<'a> {
     ) -> Result<(), (Error, &'static mut [u8])> {
         if self.status.get() == I2CStatus::Idle {
             self.reset();
+            self.start_transfer();
             self.status.set(I2CStatus::Reading);
             self.slave_address.set(addr);
             self.buffer.replace(buffer);
@@ -472,6 +557,16 @@ impl<'a> i2c::I2CMaster<'a> for I2C<'a> {
     }
 }
 
+impl DriverStatistics for I2C<'_> {
+    fn statistics(&self) -> Statistics {
+        self.statistics.get()
+    }
+
+    fn reset_statistics(&self) {
+        self.statistics.reset();
+    }
+}
+
 struct I2CClock<'a>(rcc::PeripheralClock<'a>);
 
 impl ClockInterface for I2CClock<'_> {
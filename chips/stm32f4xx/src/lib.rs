@@ -16,17 +16,23 @@ pub mod nvic;
 // Peripherals
 pub mod adc;
 pub mod can;
+pub mod crc;
+pub mod dac;
 pub mod dbg;
 pub mod dma;
 pub mod exti;
 pub mod fsmc;
 pub mod gpio;
 pub mod i2c;
+pub mod pwr;
 pub mod rcc;
+pub mod rtc;
 pub mod spi;
 pub mod syscfg;
+pub mod tim;
 pub mod tim2;
 pub mod trng;
+pub mod uid;
 pub mod usart;
 
 use cortexm4::{initialize_ram_jump_to_main, unhandled_interrupt, CortexM4, CortexMVariant};
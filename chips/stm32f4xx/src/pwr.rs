@@ -0,0 +1,267 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Power controller (PWR).
+//!
+//! Besides the backup domain write protection, the PWR selects the low-power
+//! mode the chip enters when idle. By default the chip only stops the CPU
+//! clock. Once a capsule allows deep sleep through the `DeepSleep` trait,
+//! and a wakeup source is armed, the chip enters the `DeepSleepMode` chosen
+//! by the board instead:
+//!
+//! - In Stop mode, every clock of the 1.2 V domain is stopped, but SRAM and
+//!   registers are kept. Any unmasked EXTI line wakes the chip up, such as a
+//!   GPIO interrupt or the RTC wakeup timer. The regulator can be put in
+//!   low-power mode too, with the flash powered down, for a lower
+//!   consumption and a slower wakeup.
+//! - In Standby mode, the 1.2 V domain is powered off, and only the backup
+//!   domain is kept. Only the WKUP pin and the RTC wake the chip up, through
+//!   a reset.
+//!
+//! The timers behind the kernel alarm are stopped in both modes, so alarms
+//! are late by the time spent in them. The chip wakes up from Stop mode
+//! running on the HSI, as all the boards in this tree do.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! base_peripherals.rtc.enable_clock(stm32f4xx::rcc::RtcClockSource::Lsi).unwrap();
+//! base_peripherals.rtc.start_wakeup_timer(60).unwrap();
+//! base_peripherals.pwr.set_deep_sleep_mode(stm32f4xx::pwr::DeepSleepMode::StopLowPower);
+//! chip.set_pwr(&base_peripherals.pwr);
+//! power_manager.set_deep_sleep(&base_peripherals.pwr);
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::deep_sleep::DeepSleep;
+use kernel::platform::chip::ClockInterface;
+use kernel::utilities::cells::OptionalCell;
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable};
+use kernel::utilities::registers::{register_bitfields, register_structs, ReadWrite};
+use kernel::utilities::StaticRef;
+
+use crate::exti;
+use crate::rcc;
+
+register_structs! {
+    /// Power control
+    PwrRegisters {
+        /// power control register
+        (0x000 => cr: ReadWrite<u32, CR::Register>),
+        /// power control/status register
+        (0x004 => csr: ReadWrite<u32, CSR::Register>),
+        (0x008 => @END),
+    }
+}
+
+register_bitfields![u32,
+    CR [
+        /// Under-drive enable in stop mode
+        UDEN OFFSET(18) NUMBITS(2) [],
+        /// Over-drive switching enabled
+        ODSWEN OFFSET(17) NUMBITS(1) [],
+        /// Over-drive enable
+        ODEN OFFSET(16) NUMBITS(1) [],
+        /// Regulator voltage scaling output selection
+        VOS OFFSET(14) NUMBITS(2) [],
+        /// ADC DC1
+        ADCDC1 OFFSET(13) NUMBITS(1) [],
+        /// Main regulator low voltage in deep sleep mode
+        MRUDS OFFSET(11) NUMBITS(1) [],
+        /// Low-power regulator low voltage in deep sleep mode
+        LPUDS OFFSET(10) NUMBITS(1) [],
+        /// Flash power-down in Stop mode
+        FPDS OFFSET(9) NUMBITS(1) [],
+        /// Disable backup domain write protection
+        DBP OFFSET(8) NUMBITS(1) [],
+        /// PVD level selection
+        PLS OFFSET(5) NUMBITS(3) [],
+        /// Power voltage detector enable
+        PVDE OFFSET(4) NUMBITS(1) [],
+        /// Clear standby flag
+        CSBF OFFSET(3) NUMBITS(1) [],
+        /// Clear wakeup flag
+        CWUF OFFSET(2) NUMBITS(1) [],
+        /// Power-down deepsleep
+        PDDS OFFSET(1) NUMBITS(1) [],
+        /// Low-power deep sleep
+        LPDS OFFSET(0) NUMBITS(1) []
+    ],
+    CSR [
+        /// Under-drive ready flag
+        UDRDY OFFSET(18) NUMBITS(2) [],
+        /// Over-drive mode switching ready
+        ODSWRDY OFFSET(17) NUMBITS(1) [],
+        /// Over-drive mode ready
+        ODRDY OFFSET(16) NUMBITS(1) [],
+        /// Regulator voltage scaling output selection ready bit
+        VOSRDY OFFSET(14) NUMBITS(1) [],
+        /// Backup regulator enable
+        BRE OFFSET(9) NUMBITS(1) [],
+        /// Enable WKUP pin
+        EWUP OFFSET(8) NUMBITS(1) [],
+        /// Backup regulator ready
+        BRR OFFSET(3) NUMBITS(1) [],
+        /// PVD output
+        PVDO OFFSET(2) NUMBITS(1) [],
+        /// Standby flag
+        SBF OFFSET(1) NUMBITS(1) [],
+        /// Wakeup flag
+        WUF OFFSET(0) NUMBITS(1) []
+    ]
+];
+
+const PWR_BASE: StaticRef<PwrRegisters> =
+    unsafe { StaticRef::new(0x40007000 as *const PwrRegisters) };
+
+/// Low-power mode entered when idle, once deep sleep is allowed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeepSleepMode {
+    /// Stop mode, with the main regulator on.
+    Stop,
+    /// Stop mode, with the regulator in low-power mode and the flash
+    /// powered down.
+    StopLowPower,
+    /// Standby mode. Waking up resets the chip.
+    Standby,
+}
+
+pub struct Pwr<'a> {
+    registers: StaticRef<PwrRegisters>,
+    clock: PwrClock<'a>,
+    exti: OptionalCell<&'a exti::Exti<'a>>,
+    deep_sleep_mode: Cell<DeepSleepMode>,
+    deep_sleep_allowed: Cell<bool>,
+}
+
+impl<'a> Pwr<'a> {
+    pub const fn new(rcc: &'a rcc::Rcc) -> Self {
+        Self {
+            registers: PWR_BASE,
+            clock: PwrClock(rcc::PeripheralClock::new(
+                rcc::PeripheralClockType::APB1(rcc::PCLK1::PWR),
+                rcc,
+            )),
+            exti: OptionalCell::empty(),
+            deep_sleep_mode: Cell::new(DeepSleepMode::Stop),
+            deep_sleep_allowed: Cell::new(false),
+        }
+    }
+
+    pub fn set_exti(&self, exti: &'a exti::Exti<'a>) {
+        self.exti.set(exti);
+    }
+
+    fn enable_clock(&self) {
+        if !self.clock.is_enabled() {
+            self.clock.enable();
+        }
+    }
+
+    /// Allow writes to the backup domain (RTC and backup registers).
+    pub fn enable_backup_domain_access(&self) {
+        self.enable_clock();
+        self.registers.cr.modify(CR::DBP::SET);
+    }
+
+    pub fn is_backup_domain_access_enabled(&self) -> bool {
+        self.clock.is_enabled() && self.registers.cr.is_set(CR::DBP)
+    }
+
+    /// Select the mode entered when idle, once deep sleep is allowed.
+    pub fn set_deep_sleep_mode(&self, mode: DeepSleepMode) {
+        self.deep_sleep_mode.set(mode);
+    }
+
+    pub fn deep_sleep_mode(&self) -> DeepSleepMode {
+        self.deep_sleep_mode.get()
+    }
+
+    /// Let a rising edge on the WKUP pin (PA0) wake the chip up from
+    /// Standby mode.
+    pub fn set_wakeup_pin_enabled(&self, enabled: bool) {
+        self.enable_clock();
+        self.registers.csr.modify(if enabled {
+            CSR::EWUP::SET
+        } else {
+            CSR::EWUP::CLEAR
+        });
+    }
+
+    /// Whether a wakeup source of the selected mode is armed, so the chip
+    /// cannot sleep forever.
+    pub fn has_wakeups(&self) -> bool {
+        match self.deep_sleep_mode.get() {
+            DeepSleepMode::Stop | DeepSleepMode::StopLowPower => {
+                self.exti.map_or(false, |exti| exti.has_wakeups())
+            }
+            DeepSleepMode::Standby => {
+                (self.clock.is_enabled() && self.registers.csr.is_set(CSR::EWUP))
+                    || self
+                        .exti
+                        .map_or(false, |exti| exti.is_rtc_wakeup_line_enabled())
+            }
+        }
+    }
+
+    /// The mode the chip may enter when idle: a capsule allowed deep sleep
+    /// and a wakeup source is armed. `None` if it must only stop the CPU
+    /// clock.
+    pub fn deep_sleep_ready(&self) -> Option<DeepSleepMode> {
+        if self.deep_sleep_allowed.get() && self.has_wakeups() {
+            Some(self.deep_sleep_mode.get())
+        } else {
+            None
+        }
+    }
+
+    /// Configure the regulator for `mode`, entered on the next WFI with
+    /// SLEEPDEEP set.
+    pub fn prepare_deep_sleep(&self, mode: DeepSleepMode) {
+        self.enable_clock();
+        let cr = match mode {
+            DeepSleepMode::Stop => CR::PDDS::CLEAR + CR::LPDS::CLEAR + CR::FPDS::CLEAR,
+            DeepSleepMode::StopLowPower => CR::PDDS::CLEAR + CR::LPDS::SET + CR::FPDS::SET,
+            DeepSleepMode::Standby => CR::PDDS::SET,
+        };
+        // A wakeup flag left set makes the chip leave Standby mode at once.
+        self.registers.cr.modify(cr + CR::CWUF::SET);
+    }
+
+    /// Whether the chip was reset by a wakeup from Standby mode. The flag is
+    /// kept until `clear_standby_flag()`.
+    pub fn woke_from_standby(&self) -> bool {
+        self.enable_clock();
+        self.registers.csr.is_set(CSR::SBF)
+    }
+
+    pub fn clear_standby_flag(&self) {
+        self.enable_clock();
+        self.registers.cr.modify(CR::CSBF::SET);
+    }
+}
+
+impl DeepSleep for Pwr<'_> {
+    fn set_deep_sleep_allowed(&self, allowed: bool) {
+        self.deep_sleep_allowed.set(allowed);
+    }
+}
+
+struct PwrClock<'a>(rcc::PeripheralClock<'a>);
+
+impl ClockInterface for PwrClock<'_> {
+    fn is_enabled(&self) -> bool {
+        self.0.is_enabled()
+    }
+
+    fn enable(&self) {
+        self.0.enable();
+    }
+
+    fn disable(&self) {
+        self.0.disable();
+    }
+}
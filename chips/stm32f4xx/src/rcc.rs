@@ -2,10 +2,14 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 // Copyright Tock Contributors 2022.
 
+use core::cell::Cell;
+use kernel::hil::clock_tree::{Clock, ClockGate, ClockTree};
+use kernel::hil::reset_reason::{ResetReason, ResetReasonQuery};
 use kernel::platform::chip::ClockInterface;
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable};
 use kernel::utilities::registers::{register_bitfields, ReadWrite};
 use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
 
 /// Reset and clock control
 #[repr(C)]
@@ -727,22 +731,165 @@ register_bitfields![u32,
 const RCC_BASE: StaticRef<RccRegisters> =
     unsafe { StaticRef::new(0x40023800 as *const RccRegisters) };
 
+/// Frequency of the internal high-speed oscillator
+const HSI_FREQUENCY_HZ: u32 = 16_000_000;
+
 pub struct Rcc {
     registers: StaticRef<RccRegisters>,
+    reset_reason: Cell<Option<ResetReason>>,
+    hse_frequency_hz: Cell<Option<u32>>,
 }
 
 impl Rcc {
     pub const fn new() -> Rcc {
         Rcc {
             registers: RCC_BASE,
+            reset_reason: Cell::new(None),
+            hse_frequency_hz: Cell::new(None),
+        }
+    }
+
+    /// Set the frequency of the crystal or clock on the HSE input. It is
+    /// needed to compute the clock frequencies when the system clock runs
+    /// from the HSE, directly or through the PLL.
+    pub fn set_hse_frequency(&self, hz: u32) {
+        self.hse_frequency_hz.set(Some(hz));
+    }
+
+    /// Source, multiplication factor N and division factors M and P of the
+    /// main PLL.
+    fn pll_factors(&self) -> (PllSource, u32, u32, u32) {
+        let pllcfgr = self.registers.pllcfgr.get();
+        let source = if pllcfgr & (1 << 22) == 0 {
+            PllSource::Hsi
+        } else {
+            PllSource::Hse
+        };
+        let m = pllcfgr & 0x3F;
+        let n = (pllcfgr >> 6) & 0x1FF;
+        let p = (((pllcfgr >> 16) & 0b11) + 1) * 2;
+        (source, n, m, p)
+    }
+
+    fn get_pll_frequency(&self) -> Option<u32> {
+        let (source, n, m, p) = self.pll_factors();
+        let input = match source {
+            PllSource::Hsi => HSI_FREQUENCY_HZ,
+            PllSource::Hse => self.hse_frequency_hz.get()?,
+        };
+        if m == 0 {
+            return None;
+        }
+        Some((input as u64 * n as u64 / m as u64 / p as u64) as u32)
+    }
+
+    fn sys_clock_source(&self) -> Option<SysClockSource> {
+        match (self.registers.cfgr.get() >> 2) & 0b11 {
+            0b00 => Some(SysClockSource::Hsi),
+            0b01 => Some(SysClockSource::Hse),
+            0b10 => Some(SysClockSource::Pll),
+            _ => None,
+        }
+    }
+
+    /// Frequency of the system clock, read back from the clock
+    /// configuration. `None` if it runs from an HSE of unknown frequency.
+    pub fn get_sys_clock_frequency(&self) -> Option<u32> {
+        match self.sys_clock_source()? {
+            SysClockSource::Hsi => Some(HSI_FREQUENCY_HZ),
+            SysClockSource::Hse => self.hse_frequency_hz.get(),
+            SysClockSource::Pll => self.get_pll_frequency(),
         }
     }
 
+    fn ahb_prescaler_shift(&self) -> u32 {
+        let hpre = self.registers.cfgr.read(CFGR::HPRE);
+        // 0xxx: not divided, 1000 to 1111: divided by 2, 4, 8, 16, 64, 128,
+        // 256, 512 (there is no division by 32)
+        match hpre {
+            0..=7 => 0,
+            8..=11 => hpre - 7,
+            _ => hpre - 6,
+        }
+    }
+
+    /// Frequency of the AHB bus.
+    pub fn get_ahb_frequency(&self) -> Option<u32> {
+        let shift = self.ahb_prescaler_shift();
+        self.get_sys_clock_frequency().map(|hz| hz >> shift)
+    }
+
+    fn apb_prescaler_shift(ppre: u32) -> u32 {
+        // 0xx: not divided, 100 to 111: divided by 2, 4, 8, 16
+        if ppre < 4 {
+            0
+        } else {
+            ppre - 3
+        }
+    }
+
+    /// Frequency of the APB1 bus, which clocks I2C, the USART2 and 3, and
+    /// TIM2 among others.
+    pub fn get_apb1_frequency(&self) -> Option<u32> {
+        let shift = Self::apb_prescaler_shift(self.registers.cfgr.read(CFGR::PPRE1));
+        self.get_ahb_frequency().map(|hz| hz >> shift)
+    }
+
+    /// Frequency of the timers on APB1, twice that of the bus when it is
+    /// divided.
+    pub fn get_apb1_timer_frequency(&self) -> Option<u32> {
+        let shift = Self::apb_prescaler_shift(self.registers.cfgr.read(CFGR::PPRE1));
+        self.get_apb1_frequency()
+            .map(|hz| if shift == 0 { hz } else { hz * 2 })
+    }
+
+    /// Frequency of the APB2 bus.
+    pub fn get_apb2_frequency(&self) -> Option<u32> {
+        let shift = Self::apb_prescaler_shift(self.registers.cfgr.read(CFGR::PPRE2));
+        self.get_ahb_frequency().map(|hz| hz >> shift)
+    }
+
     fn configure_rng_clock(&self) {
         self.registers.pllcfgr.modify(PLLCFGR::PLLQ.val(2));
         self.registers.cr.modify(CR::PLLON::SET);
     }
 
+    /// Start the low-speed oscillator `source` and clock the RTC from it.
+    ///
+    /// The RTC clock is part of the backup domain, which must be writable.
+    /// Once selected, the source can only be changed by a backup domain
+    /// reset, so `BUSY` is returned if another source is already in use.
+    pub fn enable_rtc_clock(&self, source: RtcClockSource) -> Result<(), ErrorCode> {
+        let rtcsel = match source {
+            RtcClockSource::Lse => 0b01,
+            RtcClockSource::Lsi => 0b10,
+        };
+        match self.registers.bdcr.read(BDCR::RTCSEL) {
+            0 => {}
+            current if current == rtcsel => {}
+            _ => return Err(ErrorCode::BUSY),
+        }
+
+        match source {
+            RtcClockSource::Lse => {
+                self.registers.bdcr.modify(BDCR::LSEON::SET);
+                while !self.registers.bdcr.is_set(BDCR::LSERDY) {}
+            }
+            RtcClockSource::Lsi => {
+                self.registers.csr.modify(CSR::LSION::SET);
+                while !self.registers.csr.is_set(CSR::LSIRDY) {}
+            }
+        }
+        self.registers
+            .bdcr
+            .modify(BDCR::RTCSEL.val(rtcsel) + BDCR::RTCEN::SET);
+        Ok(())
+    }
+
+    pub fn is_enabled_rtc_clock(&self) -> bool {
+        self.registers.bdcr.is_set(BDCR::RTCEN)
+    }
+
     // I2C1 clock
 
     fn is_enabled_i2c1_clock(&self) -> bool {
@@ -787,6 +934,34 @@ impl Rcc {
         self.registers.apb1enr.modify(APB1ENR::TIM2EN::CLEAR)
     }
 
+    // TIM3 clock
+
+    fn is_enabled_tim3_clock(&self) -> bool {
+        self.registers.apb1enr.is_set(APB1ENR::TIM3EN)
+    }
+
+    fn enable_tim3_clock(&self) {
+        self.registers.apb1enr.modify(APB1ENR::TIM3EN::SET)
+    }
+
+    fn disable_tim3_clock(&self) {
+        self.registers.apb1enr.modify(APB1ENR::TIM3EN::CLEAR)
+    }
+
+    // TIM4 clock
+
+    fn is_enabled_tim4_clock(&self) -> bool {
+        self.registers.apb1enr.is_set(APB1ENR::TIM4EN)
+    }
+
+    fn enable_tim4_clock(&self) {
+        self.registers.apb1enr.modify(APB1ENR::TIM4EN::SET)
+    }
+
+    fn disable_tim4_clock(&self) {
+        self.registers.apb1enr.modify(APB1ENR::TIM4EN::CLEAR)
+    }
+
     // SYSCFG clock
 
     fn is_enabled_syscfg_clock(&self) -> bool {
@@ -828,6 +1003,19 @@ impl Rcc {
         self.registers.ahb1enr.modify(AHB1ENR::DMA2EN::CLEAR)
     }
 
+    // CRC clock
+    fn is_enabled_crc_clock(&self) -> bool {
+        self.registers.ahb1enr.is_set(AHB1ENR::CRCEN)
+    }
+
+    fn enable_crc_clock(&self) {
+        self.registers.ahb1enr.modify(AHB1ENR::CRCEN::SET)
+    }
+
+    fn disable_crc_clock(&self) {
+        self.registers.ahb1enr.modify(AHB1ENR::CRCEN::CLEAR)
+    }
+
     // GPIOH clock
 
     fn is_enabled_gpioh_clock(&self) -> bool {
@@ -1052,6 +1240,195 @@ impl Rcc {
     fn disable_can1_clock(&self) {
         self.registers.apb1enr.modify(APB1ENR::CAN1EN::CLEAR);
     }
+
+    // DAC clock
+
+    fn is_enabled_dac_clock(&self) -> bool {
+        self.registers.apb1enr.is_set(APB1ENR::DACEN)
+    }
+
+    fn enable_dac_clock(&self) {
+        self.registers.apb1enr.modify(APB1ENR::DACEN::SET);
+    }
+
+    fn disable_dac_clock(&self) {
+        self.registers.apb1enr.modify(APB1ENR::DACEN::CLEAR);
+    }
+
+    // PWR clock
+
+    fn is_enabled_pwr_clock(&self) -> bool {
+        self.registers.apb1enr.is_set(APB1ENR::PWREN)
+    }
+
+    fn enable_pwr_clock(&self) {
+        self.registers.apb1enr.modify(APB1ENR::PWREN::SET);
+    }
+
+    fn disable_pwr_clock(&self) {
+        self.registers.apb1enr.modify(APB1ENR::PWREN::CLEAR);
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum PllSource {
+    Hsi,
+    Hse,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum SysClockSource {
+    Hsi,
+    Hse,
+    Pll,
+}
+
+/// Peripheral clock gates reported by the `clocks` console command.
+const CLOCK_GATES: [(&str, fn(&Rcc) -> bool); 27] = [
+    ("gpioa", Rcc::is_enabled_gpioa_clock),
+    ("gpiob", Rcc::is_enabled_gpiob_clock),
+    ("gpioc", Rcc::is_enabled_gpioc_clock),
+    ("gpiod", Rcc::is_enabled_gpiod_clock),
+    ("gpioe", Rcc::is_enabled_gpioe_clock),
+    ("gpiof", Rcc::is_enabled_gpiof_clock),
+    ("gpiog", Rcc::is_enabled_gpiog_clock),
+    ("gpioh", Rcc::is_enabled_gpioh_clock),
+    ("crc", Rcc::is_enabled_crc_clock),
+    ("dma1", Rcc::is_enabled_dma1_clock),
+    ("dma2", Rcc::is_enabled_dma2_clock),
+    ("otgfs", Rcc::is_enabled_otgfs_clock),
+    ("rng", Rcc::is_enabled_rng_clock),
+    ("fmc", Rcc::is_enabled_fmc_clock),
+    ("tim2", Rcc::is_enabled_tim2_clock),
+    ("tim3", Rcc::is_enabled_tim3_clock),
+    ("tim4", Rcc::is_enabled_tim4_clock),
+    ("spi3", Rcc::is_enabled_spi3_clock),
+    ("usart2", Rcc::is_enabled_usart2_clock),
+    ("usart3", Rcc::is_enabled_usart3_clock),
+    ("i2c1", Rcc::is_enabled_i2c1_clock),
+    ("can1", Rcc::is_enabled_can1_clock),
+    ("pwr", Rcc::is_enabled_pwr_clock),
+    ("dac", Rcc::is_enabled_dac_clock),
+    ("usart1", Rcc::is_enabled_usart1_clock),
+    ("adc1", Rcc::is_enabled_adc1_clock),
+    ("syscfg", Rcc::is_enabled_syscfg_clock),
+];
+
+impl ClockTree for Rcc {
+    fn clock(&self, index: usize) -> Option<Clock> {
+        let derived = |name, source, divider_shift: u32, frequency_hz: Option<u32>| Clock {
+            name,
+            enabled: true,
+            source: Some(source),
+            multiplier: 1,
+            divider: 1 << divider_shift,
+            frequency_hz,
+        };
+        match index {
+            0 => Some(Clock {
+                name: "hsi",
+                enabled: self.registers.cr.is_set(CR::HSION),
+                source: None,
+                multiplier: 1,
+                divider: 1,
+                frequency_hz: Some(HSI_FREQUENCY_HZ),
+            }),
+            1 => Some(Clock {
+                name: "hse",
+                enabled: self.registers.cr.is_set(CR::HSEON),
+                source: None,
+                multiplier: 1,
+                divider: 1,
+                frequency_hz: self.hse_frequency_hz.get(),
+            }),
+            2 => {
+                let (source, n, m, p) = self.pll_factors();
+                Some(Clock {
+                    name: "pll",
+                    enabled: self.registers.cr.is_set(CR::PLLON),
+                    source: Some(match source {
+                        PllSource::Hsi => "hsi",
+                        PllSource::Hse => "hse",
+                    }),
+                    multiplier: n,
+                    divider: m * p,
+                    frequency_hz: self.get_pll_frequency(),
+                })
+            }
+            3 => Some(derived(
+                "sysclk",
+                match self.sys_clock_source() {
+                    Some(SysClockSource::Hsi) => "hsi",
+                    Some(SysClockSource::Hse) => "hse",
+                    Some(SysClockSource::Pll) => "pll",
+                    None => "?",
+                },
+                0,
+                self.get_sys_clock_frequency(),
+            )),
+            4 => Some(derived(
+                "ahb",
+                "sysclk",
+                self.ahb_prescaler_shift(),
+                self.get_ahb_frequency(),
+            )),
+            5 => Some(derived(
+                "apb1",
+                "ahb",
+                Self::apb_prescaler_shift(self.registers.cfgr.read(CFGR::PPRE1)),
+                self.get_apb1_frequency(),
+            )),
+            6 => Some(derived(
+                "apb2",
+                "ahb",
+                Self::apb_prescaler_shift(self.registers.cfgr.read(CFGR::PPRE2)),
+                self.get_apb2_frequency(),
+            )),
+            _ => None,
+        }
+    }
+
+    fn clock_gate(&self, index: usize) -> Option<ClockGate> {
+        CLOCK_GATES.get(index).map(|&(name, is_enabled)| ClockGate {
+            name,
+            enabled: is_enabled(self),
+        })
+    }
+}
+
+impl ResetReasonQuery for Rcc {
+    /// The reset flags accumulate until they are cleared, so they are read
+    /// and cleared once, and the result is kept for later queries.
+    fn reset_reason(&self) -> ResetReason {
+        self.reset_reason.get().unwrap_or_else(|| {
+            let csr = self.registers.csr.extract();
+            // The pin flag is also set by internal resets, as they drive
+            // NRST low, so it is checked last.
+            let reason = if csr.is_set(CSR::WDGRSTF) || csr.is_set(CSR::WWDGRSTF) {
+                ResetReason::Watchdog
+            } else if csr.is_set(CSR::SFTRSTF) {
+                ResetReason::Software
+            } else if csr.is_set(CSR::PORRSTF) || csr.is_set(CSR::BORRSTF) {
+                ResetReason::PowerOn
+            } else if csr.is_set(CSR::PADRSTF) {
+                ResetReason::ExternalPin
+            } else {
+                ResetReason::Unknown
+            };
+            self.registers.csr.modify(CSR::RMVF::SET);
+            self.reset_reason.set(Some(reason));
+            reason
+        })
+    }
+}
+
+/// Clock sources for the RTC
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RtcClockSource {
+    /// 32.768 kHz external crystal
+    Lse,
+    /// Internal RC oscillator, around 32 kHz
+    Lsi,
 }
 
 /// Clock sources for CPU
@@ -1080,6 +1457,7 @@ pub enum PeripheralClockType {
 pub enum HCLK1 {
     DMA1,
     DMA2,
+    CRC,
     GPIOH,
     GPIOG,
     GPIOF,
@@ -1104,11 +1482,15 @@ pub enum HCLK2 {
 /// Peripherals clocked by PCLK1
 pub enum PCLK1 {
     TIM2,
+    TIM3,
+    TIM4,
     USART2,
     USART3,
     SPI3,
     I2C1,
     CAN1,
+    DAC,
+    PWR,
 }
 
 /// Peripherals clocked by PCLK2
@@ -1134,6 +1516,7 @@ impl<'a> ClockInterface for PeripheralClock<'a> {
             PeripheralClockType::AHB1(ref v) => match v {
                 HCLK1::DMA1 => self.rcc.is_enabled_dma1_clock(),
                 HCLK1::DMA2 => self.rcc.is_enabled_dma2_clock(),
+                HCLK1::CRC => self.rcc.is_enabled_crc_clock(),
                 HCLK1::GPIOH => self.rcc.is_enabled_gpioh_clock(),
                 HCLK1::GPIOG => self.rcc.is_enabled_gpiog_clock(),
                 HCLK1::GPIOF => self.rcc.is_enabled_gpiof_clock(),
@@ -1152,11 +1535,15 @@ impl<'a> ClockInterface for PeripheralClock<'a> {
             },
             PeripheralClockType::APB1(ref v) => match v {
                 PCLK1::TIM2 => self.rcc.is_enabled_tim2_clock(),
+                PCLK1::TIM3 => self.rcc.is_enabled_tim3_clock(),
+                PCLK1::TIM4 => self.rcc.is_enabled_tim4_clock(),
                 PCLK1::USART2 => self.rcc.is_enabled_usart2_clock(),
                 PCLK1::USART3 => self.rcc.is_enabled_usart3_clock(),
                 PCLK1::I2C1 => self.rcc.is_enabled_i2c1_clock(),
                 PCLK1::SPI3 => self.rcc.is_enabled_spi3_clock(),
                 PCLK1::CAN1 => self.rcc.is_enabled_can1_clock(),
+                PCLK1::DAC => self.rcc.is_enabled_dac_clock(),
+                PCLK1::PWR => self.rcc.is_enabled_pwr_clock(),
             },
             PeripheralClockType::APB2(ref v) => match v {
                 PCLK2::USART1 => self.rcc.is_enabled_usart1_clock(),
@@ -1175,6 +1562,9 @@ impl<'a> ClockInterface for PeripheralClock<'a> {
                 HCLK1::DMA2 => {
                     self.rcc.enable_dma2_clock();
                 }
+                HCLK1::CRC => {
+                    self.rcc.enable_crc_clock();
+                }
                 HCLK1::GPIOH => {
                     self.rcc.enable_gpioh_clock();
                 }
@@ -1215,6 +1605,12 @@ impl<'a> ClockInterface for PeripheralClock<'a> {
                 PCLK1::TIM2 => {
                     self.rcc.enable_tim2_clock();
                 }
+                PCLK1::TIM3 => {
+                    self.rcc.enable_tim3_clock();
+                }
+                PCLK1::TIM4 => {
+                    self.rcc.enable_tim4_clock();
+                }
                 PCLK1::USART2 => {
                     self.rcc.enable_usart2_clock();
                 }
@@ -1230,6 +1626,12 @@ impl<'a> ClockInterface for PeripheralClock<'a> {
                 PCLK1::CAN1 => {
                     self.rcc.enable_can1_clock();
                 }
+                PCLK1::DAC => {
+                    self.rcc.enable_dac_clock();
+                }
+                PCLK1::PWR => {
+                    self.rcc.enable_pwr_clock();
+                }
             },
             PeripheralClockType::APB2(ref v) => match v {
                 PCLK2::USART1 => {
@@ -1254,6 +1656,9 @@ impl<'a> ClockInterface for PeripheralClock<'a> {
                 HCLK1::DMA2 => {
                     self.rcc.disable_dma2_clock();
                 }
+                HCLK1::CRC => {
+                    self.rcc.disable_crc_clock();
+                }
                 HCLK1::GPIOH => {
                     self.rcc.disable_gpioh_clock();
                 }
@@ -1294,6 +1699,12 @@ impl<'a> ClockInterface for PeripheralClock<'a> {
                 PCLK1::TIM2 => {
                     self.rcc.disable_tim2_clock();
                 }
+                PCLK1::TIM3 => {
+                    self.rcc.disable_tim3_clock();
+                }
+                PCLK1::TIM4 => {
+                    self.rcc.disable_tim4_clock();
+                }
                 PCLK1::USART2 => {
                     self.rcc.disable_usart2_clock();
                 }
@@ -1309,6 +1720,12 @@ impl<'a> ClockInterface for PeripheralClock<'a> {
                 PCLK1::CAN1 => {
                     self.rcc.disable_can1_clock();
                 }
+                PCLK1::DAC => {
+                    self.rcc.disable_dac_clock();
+                }
+                PCLK1::PWR => {
+                    self.rcc.disable_pwr_clock();
+                }
             },
             PeripheralClockType::APB2(ref v) => match v {
                 PCLK2::USART1 => {
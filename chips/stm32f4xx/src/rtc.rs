@@ -0,0 +1,246 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Real-time clock (RTC).
+//!
+//! The backup registers and the wakeup timer are supported. The backup
+//! registers are part of the backup domain, so they keep their contents
+//! across resets and, with a battery on VBAT, across loss of main power.
+//!
+//! The wakeup timer periodically raises the `RTC_WKUP` interrupt, through
+//! EXTI line 22, which wakes the chip up from Stop and Standby modes. It
+//! counts seconds of the RTC clock, which must first be enabled with
+//! `enable_clock()`.
+
+use kernel::hil::backup_registers::BackupRegisters;
+use kernel::utilities::cells::OptionalCell;
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
+use kernel::utilities::registers::{register_bitfields, register_structs, ReadWrite};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+use crate::exti;
+use crate::pwr;
+use crate::rcc;
+
+const NUM_BACKUP_REGISTERS: usize = 20;
+
+/// Longest wakeup period, in seconds.
+pub const MAX_WAKEUP_PERIOD_S: u32 = 1 << 17;
+
+register_structs! {
+    /// Real-time clock
+    RtcRegisters {
+        (0x000 => _reserved0),
+        /// control register
+        (0x008 => cr: ReadWrite<u32, CR::Register>),
+        /// initialization and status register
+        (0x00C => isr: ReadWrite<u32, ISR::Register>),
+        /// prescaler register
+        (0x010 => prer: ReadWrite<u32, PRER::Register>),
+        /// wakeup timer register
+        (0x014 => wutr: ReadWrite<u32, WUTR::Register>),
+        (0x018 => _reserved1),
+        /// write protection register
+        (0x024 => wpr: ReadWrite<u32, WPR::Register>),
+        (0x028 => _reserved2),
+        /// backup registers
+        (0x050 => bkpr: [ReadWrite<u32>; NUM_BACKUP_REGISTERS]),
+        (0x0A0 => @END),
+    }
+}
+
+register_bitfields![u32,
+    CR [
+        /// Wakeup timer interrupt enable
+        WUTIE OFFSET(14) NUMBITS(1) [],
+        /// Wakeup timer enable
+        WUTE OFFSET(10) NUMBITS(1) [],
+        /// Wakeup clock selection
+        WUCKSEL OFFSET(0) NUMBITS(3) [
+            /// 1 Hz clock
+            Spre = 0b100,
+            /// 1 Hz clock, with 2^16 added to the wakeup timer
+            SpreExtended = 0b110
+        ]
+    ],
+    ISR [
+        /// Wakeup timer flag
+        WUTF OFFSET(10) NUMBITS(1) [],
+        /// Initialization mode
+        INIT OFFSET(7) NUMBITS(1) [],
+        /// Initialization flag
+        INITF OFFSET(6) NUMBITS(1) [],
+        /// Wakeup timer write flag
+        WUTWF OFFSET(2) NUMBITS(1) []
+    ],
+    PRER [
+        /// Asynchronous prescaler factor
+        PREDIV_A OFFSET(16) NUMBITS(7) [],
+        /// Synchronous prescaler factor
+        PREDIV_S OFFSET(0) NUMBITS(15) []
+    ],
+    WUTR [
+        /// Wakeup auto-reload value
+        WUT OFFSET(0) NUMBITS(16) []
+    ],
+    WPR [
+        /// Write protection key
+        KEY OFFSET(0) NUMBITS(8) []
+    ]
+];
+
+const RTC_BASE: StaticRef<RtcRegisters> =
+    unsafe { StaticRef::new(0x40002800 as *const RtcRegisters) };
+
+/// Receiver of the wakeup timer interrupts.
+pub trait WakeupClient {
+    fn wakeup(&self);
+}
+
+pub struct Rtc<'a> {
+    registers: StaticRef<RtcRegisters>,
+    rcc: &'a rcc::Rcc,
+    pwr: OptionalCell<&'a pwr::Pwr<'a>>,
+    exti: OptionalCell<&'a exti::Exti<'a>>,
+    wakeup_client: OptionalCell<&'a dyn WakeupClient>,
+}
+
+impl<'a> Rtc<'a> {
+    pub const fn new(rcc: &'a rcc::Rcc) -> Self {
+        Self {
+            registers: RTC_BASE,
+            rcc,
+            pwr: OptionalCell::empty(),
+            exti: OptionalCell::empty(),
+            wakeup_client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_pwr(&self, pwr: &'a pwr::Pwr<'a>) {
+        self.pwr.set(pwr);
+    }
+
+    pub fn set_exti(&self, exti: &'a exti::Exti<'a>) {
+        self.exti.set(exti);
+    }
+
+    pub fn set_wakeup_client(&self, client: &'a dyn WakeupClient) {
+        self.wakeup_client.set(client);
+    }
+
+    /// Make the backup domain writable.
+    fn enable_write(&self) -> Result<(), ErrorCode> {
+        self.pwr.map_or(Err(ErrorCode::OFF), |pwr| {
+            if !pwr.is_backup_domain_access_enabled() {
+                pwr.enable_backup_domain_access();
+            }
+            Ok(())
+        })
+    }
+
+    /// Run `f` with the write protection of the RTC registers lifted.
+    fn unlocked<F: FnOnce()>(&self, f: F) {
+        self.registers.wpr.write(WPR::KEY.val(0xCA));
+        self.registers.wpr.write(WPR::KEY.val(0x53));
+        f();
+        // Any other key locks the registers again.
+        self.registers.wpr.write(WPR::KEY.val(0xFF));
+    }
+
+    /// Clock the RTC from `source`, with a 1 Hz time base.
+    ///
+    /// Returns `BUSY` if the RTC already runs from another source.
+    pub fn enable_clock(&self, source: rcc::RtcClockSource) -> Result<(), ErrorCode> {
+        self.enable_write()?;
+        self.rcc.enable_rtc_clock(source)?;
+
+        // The prescalers divide the clock by (PREDIV_A + 1) * (PREDIV_S + 1).
+        let prediv_s = match source {
+            rcc::RtcClockSource::Lse => 255,
+            rcc::RtcClockSource::Lsi => 249,
+        };
+        self.unlocked(|| {
+            self.registers.isr.modify(ISR::INIT::SET);
+            while !self.registers.isr.is_set(ISR::INITF) {}
+            self.registers
+                .prer
+                .write(PRER::PREDIV_A.val(127) + PRER::PREDIV_S.val(prediv_s));
+            self.registers.isr.modify(ISR::INIT::CLEAR);
+        });
+        Ok(())
+    }
+
+    /// Raise the wakeup interrupt every `period_s` seconds, from 1 to
+    /// `MAX_WAKEUP_PERIOD_S`.
+    pub fn start_wakeup_timer(&self, period_s: u32) -> Result<(), ErrorCode> {
+        if period_s == 0 || period_s > MAX_WAKEUP_PERIOD_S {
+            return Err(ErrorCode::INVAL);
+        }
+        if !self.rcc.is_enabled_rtc_clock() {
+            return Err(ErrorCode::OFF);
+        }
+        self.enable_write()?;
+
+        let (wucksel, wut) = if period_s <= 1 << 16 {
+            (CR::WUCKSEL::Spre, period_s - 1)
+        } else {
+            (CR::WUCKSEL::SpreExtended, period_s - 1 - (1 << 16))
+        };
+        self.unlocked(|| {
+            self.registers.cr.modify(CR::WUTE::CLEAR);
+            while !self.registers.isr.is_set(ISR::WUTWF) {}
+            self.registers.wutr.write(WUTR::WUT.val(wut));
+            self.registers.isr.modify(ISR::WUTF::CLEAR);
+            self.registers
+                .cr
+                .modify(wucksel + CR::WUTIE::SET + CR::WUTE::SET);
+        });
+        self.exti.map(|exti| {
+            exti.clear_rtc_wakeup_pending();
+            exti.enable_rtc_wakeup_line();
+        });
+        Ok(())
+    }
+
+    pub fn stop_wakeup_timer(&self) -> Result<(), ErrorCode> {
+        self.enable_write()?;
+        self.unlocked(|| {
+            self.registers.cr.modify(CR::WUTIE::CLEAR + CR::WUTE::CLEAR);
+        });
+        self.exti.map(|exti| exti.disable_rtc_wakeup_line());
+        Ok(())
+    }
+
+    pub fn handle_wakeup_interrupt(&self) {
+        // Clearing the flag only needs the backup domain to be writable.
+        if self.enable_write().is_ok() {
+            self.registers.isr.modify(ISR::WUTF::CLEAR);
+        }
+        self.exti.map(|exti| exti.clear_rtc_wakeup_pending());
+        self.wakeup_client.map(|client| client.wakeup());
+    }
+}
+
+impl BackupRegisters for Rtc<'_> {
+    fn num_registers(&self) -> usize {
+        NUM_BACKUP_REGISTERS
+    }
+
+    fn read(&self, index: usize) -> Result<u32, ErrorCode> {
+        self.registers
+            .bkpr
+            .get(index)
+            .map(|register| register.get())
+            .ok_or(ErrorCode::INVAL)
+    }
+
+    fn write(&self, index: usize, value: u32) -> Result<(), ErrorCode> {
+        let register = self.registers.bkpr.get(index).ok_or(ErrorCode::INVAL)?;
+        // The backup domain is write protected after reset.
+        self.enable_write()?;
+        register.set(value);
+        Ok(())
+    }
+}
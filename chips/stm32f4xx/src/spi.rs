@@ -9,6 +9,7 @@ use kernel::ErrorCode;
 use kernel::hil;
 use kernel::hil::gpio::Output;
 use kernel::hil::spi::{self, ClockPhase, ClockPolarity, SpiMasterClient};
+use kernel::hil::statistics::{DriverStatistics, Statistics, StatisticsCounters};
 use kernel::platform::chip::ClockInterface;
 use kernel::utilities::cells::OptionalCell;
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable};
@@ -167,6 +168,8 @@ pub struct Spi<'a> {
     active_slave: OptionalCell<&'a crate::gpio::Pin<'a>>,
 
     active_after: Cell<bool>,
+
+    statistics: StatisticsCounters,
 }
 
 // for use by `set_dma`
@@ -197,6 +200,8 @@ impl<'a> Spi<'a> {
             active_slave: OptionalCell::empty(),
 
             active_after: Cell::new(false),
+
+            statistics: StatisticsCounters::new(),
         }
     }
 
@@ -411,6 +416,7 @@ impl<'a> spi::SpiMaster<'a> for Spi<'a> {
         {
             Err((e, write_buffer.unwrap(), read_buffer))
         } else {
+            self.statistics.start();
             Ok(())
         }
     }
@@ -500,6 +506,7 @@ impl<'a> dma::StreamClient<'a, Dma1<'a>> for Spi<'a> {
 
             let length = self.dma_len.get();
             self.dma_len.set(0);
+            self.statistics.complete(length);
 
             self.master_client.map(|client| {
                 tx_buffer.map(|t| {
@@ -510,6 +517,16 @@ impl<'a> dma::StreamClient<'a, Dma1<'a>> for Spi<'a> {
     }
 }
 
+impl DriverStatistics for Spi<'_> {
+    fn statistics(&self) -> Statistics {
+        self.statistics.get()
+    }
+
+    fn reset_statistics(&self) {
+        self.statistics.reset();
+    }
+}
+
 pub struct SpiClock<'a>(pub rcc::PeripheralClock<'a>);
 
 impl ClockInterface for SpiClock<'_> {
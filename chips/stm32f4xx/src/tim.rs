@@ -0,0 +1,513 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! General purpose timers TIM3 and TIM4: PWM output and input capture.
+//!
+//! Each timer has four channels sharing one 16-bit counter. The channels
+//! generate PWM signals through `hil::pwm::Pwm`, with the channel as the pin.
+//! As the counter is shared, all the channels of a timer run at the same
+//! frequency: starting a channel at another frequency than the channels
+//! already running returns `BUSY`.
+//!
+//! A timer can instead measure an external signal on its channel 1 input
+//! through `hil::pwm_capture`, in PWM input mode. Rising edges reset the
+//! counter, channel 1 captures the period and channel 2 the high time, so
+//! the measurement does not depend on the interrupt latency. The counter
+//! runs at 1 MHz during a measurement: periods from 1 us to 65 ms are
+//! measured, with a 1 us resolution. A timer cannot generate PWM signals
+//! and measure a signal at the same time.
+//!
+//! The timer pins must be set to their alternate function by the board, AF2
+//! for both TIM3 and TIM4.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let mux_pwm = components::pwm::PwmMuxComponent::new(&base_peripherals.tim3)
+//!     .finalize(components::pwm_mux_component_static!(stm32f4xx::tim::Tim));
+//! let pwm_capture = components::pwm_capture::PwmCaptureComponent::new(
+//!     board_kernel,
+//!     capsules_extra::pwm_capture::DRIVER_NUM,
+//! )
+//! .finalize(components::pwm_capture_component_static!(&base_peripherals.tim4));
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil;
+use kernel::platform::chip::ClockInterface;
+use kernel::utilities::cells::OptionalCell;
+use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
+use kernel::utilities::registers::{register_bitfields, register_structs, ReadWrite, WriteOnly};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+use crate::rcc;
+
+register_structs! {
+    /// General purpose timer
+    TimRegisters {
+        /// control register 1
+        (0x000 => cr1: ReadWrite<u32, CR1::Register>),
+        /// control register 2
+        (0x004 => cr2: ReadWrite<u32>),
+        /// slave mode control register
+        (0x008 => smcr: ReadWrite<u32, SMCR::Register>),
+        /// DMA/Interrupt enable register
+        (0x00C => dier: ReadWrite<u32, DIER::Register>),
+        /// status register
+        (0x010 => sr: ReadWrite<u32, SR::Register>),
+        /// event generation register
+        (0x014 => egr: WriteOnly<u32, EGR::Register>),
+        /// capture/compare mode register 1
+        (0x018 => ccmr1: ReadWrite<u32, CCMR1::Register>),
+        /// capture/compare mode register 2
+        (0x01C => ccmr2: ReadWrite<u32, CCMR2::Register>),
+        /// capture/compare enable register
+        (0x020 => ccer: ReadWrite<u32, CCER::Register>),
+        /// counter
+        (0x024 => cnt: ReadWrite<u32>),
+        /// prescaler
+        (0x028 => psc: ReadWrite<u32>),
+        /// auto-reload register
+        (0x02C => arr: ReadWrite<u32>),
+        (0x030 => _reserved0),
+        /// capture/compare registers 1 to 4
+        (0x034 => ccr: [ReadWrite<u32>; 4]),
+        (0x044 => @END),
+    }
+}
+
+register_bitfields![u32,
+    CR1 [
+        /// Auto-reload preload enable
+        ARPE OFFSET(7) NUMBITS(1) [],
+        /// Update request source
+        URS OFFSET(2) NUMBITS(1) [],
+        /// Counter enable
+        CEN OFFSET(0) NUMBITS(1) []
+    ],
+    SMCR [
+        /// Trigger selection
+        TS OFFSET(4) NUMBITS(3) [
+            /// Filtered timer input 1
+            TI1FP1 = 0b101
+        ],
+        /// Slave mode selection
+        SMS OFFSET(0) NUMBITS(3) [
+            Disabled = 0b000,
+            /// The trigger resets the counter
+            Reset = 0b100
+        ]
+    ],
+    DIER [
+        /// Capture/Compare 1 interrupt enable
+        CC1IE OFFSET(1) NUMBITS(1) [],
+        /// Update interrupt enable
+        UIE OFFSET(0) NUMBITS(1) []
+    ],
+    SR [
+        /// Capture/Compare 1 interrupt flag
+        CC1IF OFFSET(1) NUMBITS(1) [],
+        /// Update interrupt flag
+        UIF OFFSET(0) NUMBITS(1) []
+    ],
+    EGR [
+        /// Update generation
+        UG OFFSET(0) NUMBITS(1) []
+    ],
+    CCMR1 [
+        /// Output compare 2 mode
+        OC2M OFFSET(12) NUMBITS(3) [
+            Pwm1 = 0b110
+        ],
+        /// Output compare 2 preload enable
+        OC2PE OFFSET(11) NUMBITS(1) [],
+        /// Capture/Compare 2 selection
+        CC2S OFFSET(8) NUMBITS(2) [
+            Output = 0b00,
+            /// IC2 is mapped on TI1
+            TI1 = 0b10
+        ],
+        /// Output compare 1 mode
+        OC1M OFFSET(4) NUMBITS(3) [
+            Pwm1 = 0b110
+        ],
+        /// Output compare 1 preload enable
+        OC1PE OFFSET(3) NUMBITS(1) [],
+        /// Capture/Compare 1 selection
+        CC1S OFFSET(0) NUMBITS(2) [
+            Output = 0b00,
+            /// IC1 is mapped on TI1
+            TI1 = 0b01
+        ]
+    ],
+    CCMR2 [
+        /// Output compare 4 mode
+        OC4M OFFSET(12) NUMBITS(3) [
+            Pwm1 = 0b110
+        ],
+        /// Output compare 4 preload enable
+        OC4PE OFFSET(11) NUMBITS(1) [],
+        /// Capture/Compare 4 selection
+        CC4S OFFSET(8) NUMBITS(2) [
+            Output = 0b00
+        ],
+        /// Output compare 3 mode
+        OC3M OFFSET(4) NUMBITS(3) [
+            Pwm1 = 0b110
+        ],
+        /// Output compare 3 preload enable
+        OC3PE OFFSET(3) NUMBITS(1) [],
+        /// Capture/Compare 3 selection
+        CC3S OFFSET(0) NUMBITS(2) [
+            Output = 0b00
+        ]
+    ],
+    CCER [
+        /// Capture/Compare 4 output enable
+        CC4E OFFSET(12) NUMBITS(1) [],
+        /// Capture/Compare 3 output enable
+        CC3E OFFSET(8) NUMBITS(1) [],
+        /// Capture/Compare 2 polarity
+        CC2P OFFSET(5) NUMBITS(1) [],
+        /// Capture/Compare 2 output enable
+        CC2E OFFSET(4) NUMBITS(1) [],
+        /// Capture/Compare 1 polarity
+        CC1P OFFSET(1) NUMBITS(1) [],
+        /// Capture/Compare 1 output enable
+        CC1E OFFSET(0) NUMBITS(1) []
+    ]
+];
+
+const TIM3_BASE: StaticRef<TimRegisters> =
+    unsafe { StaticRef::new(0x40000400 as *const TimRegisters) };
+const TIM4_BASE: StaticRef<TimRegisters> =
+    unsafe { StaticRef::new(0x40000800 as *const TimRegisters) };
+
+/// Value of `get_maximum_duty_cycle()`, for a 100% duty cycle.
+const MAX_DUTY_CYCLE: usize = 1 << 16;
+
+/// Frequency of the counter during a measurement.
+const CAPTURE_COUNTER_HZ: u32 = 1_000_000;
+
+/// Channel of a timer, used as the pin of `hil::pwm::Pwm`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimChannel {
+    Channel1 = 0,
+    Channel2 = 1,
+    Channel3 = 2,
+    Channel4 = 3,
+}
+
+const CHANNELS: [TimChannel; 4] = [
+    TimChannel::Channel1,
+    TimChannel::Channel2,
+    TimChannel::Channel3,
+    TimChannel::Channel4,
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Idle,
+    /// Channels generate PWM signals at `frequency_hz`.
+    Pwm {
+        frequency_hz: usize,
+    },
+    /// Waiting for the first rising edge, which starts the counter from 0.
+    CaptureFirstEdge,
+    /// Waiting for the rising edge ending the period.
+    CapturePeriod,
+}
+
+pub struct Tim<'a> {
+    registers: StaticRef<TimRegisters>,
+    clock: TimClock<'a>,
+    rcc: &'a rcc::Rcc,
+    mode: Cell<Mode>,
+    capture_client: OptionalCell<&'a dyn hil::pwm_capture::PwmCaptureClient>,
+}
+
+impl<'a> Tim<'a> {
+    pub const fn new_tim3(rcc: &'a rcc::Rcc) -> Self {
+        Self::new(TIM3_BASE, rcc::PCLK1::TIM3, rcc)
+    }
+
+    pub const fn new_tim4(rcc: &'a rcc::Rcc) -> Self {
+        Self::new(TIM4_BASE, rcc::PCLK1::TIM4, rcc)
+    }
+
+    const fn new(registers: StaticRef<TimRegisters>, clock: rcc::PCLK1, rcc: &'a rcc::Rcc) -> Self {
+        Self {
+            registers,
+            clock: TimClock(rcc::PeripheralClock::new(
+                rcc::PeripheralClockType::APB1(clock),
+                rcc,
+            )),
+            rcc,
+            mode: Cell::new(Mode::Idle),
+            capture_client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn is_enabled_clock(&self) -> bool {
+        self.clock.is_enabled()
+    }
+
+    pub fn enable_clock(&self) {
+        self.clock.enable();
+    }
+
+    pub fn disable_clock(&self) {
+        self.clock.disable();
+    }
+
+    fn timer_frequency(&self) -> Option<usize> {
+        self.rcc.get_apb1_timer_frequency().map(|hz| hz as usize)
+    }
+
+    fn is_output_enabled(&self, channel: TimChannel) -> bool {
+        let ccer = self.registers.ccer.extract();
+        match channel {
+            TimChannel::Channel1 => ccer.is_set(CCER::CC1E),
+            TimChannel::Channel2 => ccer.is_set(CCER::CC2E),
+            TimChannel::Channel3 => ccer.is_set(CCER::CC3E),
+            TimChannel::Channel4 => ccer.is_set(CCER::CC4E),
+        }
+    }
+
+    fn set_output_enabled(&self, channel: TimChannel, enabled: bool) {
+        let field = match channel {
+            TimChannel::Channel1 => CCER::CC1E,
+            TimChannel::Channel2 => CCER::CC2E,
+            TimChannel::Channel3 => CCER::CC3E,
+            TimChannel::Channel4 => CCER::CC4E,
+        };
+        self.registers
+            .ccer
+            .modify(if enabled { field.val(1) } else { field.val(0) });
+    }
+
+    /// Drive `channel` high while the counter is below its compare value.
+    fn configure_pwm_output(&self, channel: TimChannel) {
+        match channel {
+            TimChannel::Channel1 => self
+                .registers
+                .ccmr1
+                .modify(CCMR1::CC1S::Output + CCMR1::OC1M::Pwm1 + CCMR1::OC1PE::SET),
+            TimChannel::Channel2 => self
+                .registers
+                .ccmr1
+                .modify(CCMR1::CC2S::Output + CCMR1::OC2M::Pwm1 + CCMR1::OC2PE::SET),
+            TimChannel::Channel3 => self
+                .registers
+                .ccmr2
+                .modify(CCMR2::CC3S::Output + CCMR2::OC3M::Pwm1 + CCMR2::OC3PE::SET),
+            TimChannel::Channel4 => self
+                .registers
+                .ccmr2
+                .modify(CCMR2::CC4S::Output + CCMR2::OC4M::Pwm1 + CCMR2::OC4PE::SET),
+        }
+    }
+
+    /// Whether a channel other than `channel` generates a PWM signal.
+    fn other_outputs_enabled(&self, channel: TimChannel) -> bool {
+        CHANNELS
+            .iter()
+            .any(|&other| other != channel && self.is_output_enabled(other))
+    }
+
+    /// Run the counter at `frequency_hz`, wrapping around at the largest
+    /// auto-reload value the 16-bit counter allows.
+    fn set_pwm_frequency(&self, clock_hz: usize, frequency_hz: usize) {
+        let ticks = clock_hz / frequency_hz;
+        let prescaler = (ticks - 1) / 0x1_0000;
+        let period = ticks / (prescaler + 1);
+        self.registers.cr1.modify(CR1::CEN::CLEAR);
+        self.registers.psc.set(prescaler as u32);
+        self.registers.arr.set(period as u32 - 1);
+        self.registers.cnt.set(0);
+        // Load the prescaler and auto-reload values now.
+        self.registers.egr.write(EGR::UG::SET);
+        self.registers.cr1.modify(CR1::ARPE::SET + CR1::CEN::SET);
+    }
+
+    fn end_capture(&self) {
+        self.registers.dier.set(0);
+        self.registers.cr1.modify(CR1::CEN::CLEAR);
+        self.registers.smcr.write(SMCR::SMS::Disabled);
+        self.registers.ccer.set(0);
+        self.registers.ccmr1.set(0);
+        self.registers.sr.set(0);
+        self.mode.set(Mode::Idle);
+        self.disable_clock();
+    }
+
+    fn finish_capture(&self, result: Result<(u32, u32), ErrorCode>) {
+        self.end_capture();
+        self.capture_client.map(|client| match result {
+            Ok((period_ns, high_ns)) => client.capture_done(Ok(()), period_ns, high_ns),
+            Err(error) => client.capture_done(Err(error), 0, 0),
+        });
+    }
+
+    pub fn handle_interrupt(&self) {
+        let sr = self.registers.sr.extract();
+        // The flags are cleared by writing 0, so only clear those read.
+        self.registers.sr.set(!sr.get());
+
+        match self.mode.get() {
+            Mode::CaptureFirstEdge if sr.is_set(SR::CC1IF) => {
+                self.mode.set(Mode::CapturePeriod);
+            }
+            Mode::CapturePeriod if sr.is_set(SR::CC1IF) => {
+                // The edge ending the period reset the counter after it was
+                // captured by channel 1. Channel 2 captured the falling edge.
+                let ns_per_tick = 1_000_000_000 / CAPTURE_COUNTER_HZ;
+                let period_ns = self.registers.ccr[0].get() * ns_per_tick;
+                let high_ns = self.registers.ccr[1].get() * ns_per_tick;
+                if period_ns == 0 {
+                    self.finish_capture(Err(ErrorCode::SIZE));
+                } else {
+                    self.finish_capture(Ok((period_ns, high_ns)));
+                }
+            }
+            Mode::CapturePeriod if sr.is_set(SR::UIF) => {
+                // The counter wrapped around before the end of the period.
+                self.finish_capture(Err(ErrorCode::SIZE));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl hil::pwm::Pwm for Tim<'_> {
+    type Pin = TimChannel;
+
+    fn start(
+        &self,
+        pin: &Self::Pin,
+        frequency_hz: usize,
+        duty_cycle: usize,
+    ) -> Result<(), ErrorCode> {
+        let channel = *pin;
+        if duty_cycle > MAX_DUTY_CYCLE {
+            return Err(ErrorCode::INVAL);
+        }
+        let clock_hz = self.timer_frequency().ok_or(ErrorCode::FAIL)?;
+        if frequency_hz == 0 || frequency_hz > clock_hz / 2 {
+            return Err(ErrorCode::INVAL);
+        }
+
+        match self.mode.get() {
+            Mode::CaptureFirstEdge | Mode::CapturePeriod => return Err(ErrorCode::BUSY),
+            Mode::Pwm {
+                frequency_hz: current,
+            } if current == frequency_hz => {}
+            Mode::Pwm { .. } if self.other_outputs_enabled(channel) => return Err(ErrorCode::BUSY),
+            Mode::Pwm { .. } | Mode::Idle => {
+                if !self.is_enabled_clock() {
+                    self.enable_clock();
+                }
+                self.set_pwm_frequency(clock_hz, frequency_hz);
+                self.mode.set(Mode::Pwm { frequency_hz });
+            }
+        }
+
+        let period = self.registers.arr.get() as usize + 1;
+        let compare = (duty_cycle as u64 * period as u64 / MAX_DUTY_CYCLE as u64) as u32;
+        self.registers.ccr[channel as usize].set(compare);
+        self.configure_pwm_output(channel);
+        self.set_output_enabled(channel, true);
+        Ok(())
+    }
+
+    fn stop(&self, pin: &Self::Pin) -> Result<(), ErrorCode> {
+        if let Mode::Pwm { .. } = self.mode.get() {
+            let channel = *pin;
+            self.set_output_enabled(channel, false);
+            self.registers.ccr[channel as usize].set(0);
+            if !self.other_outputs_enabled(channel) {
+                self.registers.cr1.modify(CR1::CEN::CLEAR);
+                self.mode.set(Mode::Idle);
+                self.disable_clock();
+            }
+        }
+        Ok(())
+    }
+
+    fn get_maximum_frequency_hz(&self) -> usize {
+        self.timer_frequency().map_or(0, |hz| hz / 2)
+    }
+
+    fn get_maximum_duty_cycle(&self) -> usize {
+        MAX_DUTY_CYCLE
+    }
+}
+
+impl<'a> hil::pwm_capture::PwmCapture<'a> for Tim<'a> {
+    fn set_client(&self, client: &'a dyn hil::pwm_capture::PwmCaptureClient) {
+        self.capture_client.set(client);
+    }
+
+    fn start_capture(&self) -> Result<(), ErrorCode> {
+        if self.mode.get() != Mode::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        let clock_hz = self.timer_frequency().ok_or(ErrorCode::FAIL)?;
+        let prescaler = clock_hz as u32 / CAPTURE_COUNTER_HZ;
+        if prescaler == 0 {
+            return Err(ErrorCode::FAIL);
+        }
+
+        self.enable_clock();
+        // Only counter overflows raise update interrupts, not the resets by
+        // the rising edges.
+        self.registers.cr1.write(CR1::URS::SET);
+        self.registers.psc.set(prescaler - 1);
+        self.registers.arr.set(0xFFFF);
+        // Both channels capture the input of channel 1, channel 1 on rising
+        // edges and channel 2 on falling edges.
+        self.registers
+            .ccmr1
+            .write(CCMR1::CC1S::TI1 + CCMR1::CC2S::TI1);
+        self.registers
+            .ccer
+            .write(CCER::CC1P::CLEAR + CCER::CC1E::SET + CCER::CC2P::SET + CCER::CC2E::SET);
+        self.registers
+            .smcr
+            .write(SMCR::TS::TI1FP1 + SMCR::SMS::Reset);
+        self.registers.egr.write(EGR::UG::SET);
+        self.registers.sr.set(0);
+
+        self.mode.set(Mode::CaptureFirstEdge);
+        self.registers.dier.write(DIER::CC1IE::SET + DIER::UIE::SET);
+        self.registers.cr1.modify(CR1::CEN::SET);
+        Ok(())
+    }
+
+    fn stop_capture(&self) -> Result<(), ErrorCode> {
+        if let Mode::CaptureFirstEdge | Mode::CapturePeriod = self.mode.get() {
+            self.end_capture();
+        }
+        Ok(())
+    }
+}
+
+struct TimClock<'a>(rcc::PeripheralClock<'a>);
+
+impl ClockInterface for TimClock<'_> {
+    fn is_enabled(&self) -> bool {
+        self.0.is_enabled()
+    }
+
+    fn enable(&self) {
+        self.0.enable();
+    }
+
+    fn disable(&self) {
+        self.0.disable();
+    }
+}
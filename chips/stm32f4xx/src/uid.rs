@@ -0,0 +1,53 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! 96-bit unique device identifier.
+
+use kernel::hil::device_id::DeviceId;
+use kernel::utilities::registers::interfaces::Readable;
+use kernel::utilities::registers::{register_structs, ReadOnly};
+use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
+
+const UID_LEN: usize = 12;
+
+register_structs! {
+    /// Unique device ID register
+    UidRegisters {
+        /// Bits 0 to 95 of the unique ID, least significant word first
+        (0x000 => uid: [ReadOnly<u32>; UID_LEN / 4]),
+        (0x00C => @END),
+    }
+}
+
+const UID_BASE: StaticRef<UidRegisters> =
+    unsafe { StaticRef::new(0x1FFF7A10 as *const UidRegisters) };
+
+pub struct Uid {
+    registers: StaticRef<UidRegisters>,
+}
+
+impl Uid {
+    pub const fn new() -> Self {
+        Self {
+            registers: UID_BASE,
+        }
+    }
+}
+
+impl DeviceId for Uid {
+    fn id_len(&self) -> usize {
+        UID_LEN
+    }
+
+    fn read_id(&self, buf: &mut [u8]) -> Result<usize, ErrorCode> {
+        if buf.len() < UID_LEN {
+            return Err(ErrorCode::SIZE);
+        }
+        for (chunk, word) in buf.chunks_exact_mut(4).zip(self.registers.uid.iter()) {
+            chunk.copy_from_slice(&word.get().to_le_bytes());
+        }
+        Ok(UID_LEN)
+    }
+}
@@ -5,6 +5,7 @@
 use core::cell::Cell;
 use kernel::deferred_call::{DeferredCall, DeferredCallClient};
 use kernel::hil;
+use kernel::hil::statistics::{DriverStatistics, Statistics, StatisticsCounters};
 use kernel::platform::chip::ClockInterface;
 use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
@@ -15,6 +16,14 @@ use kernel::ErrorCode;
 use crate::dma;
 use crate::rcc;
 
+/// Trace point identifiers, see `kernel::trace`.
+pub mod trace {
+    /// Transmission completed, with its length.
+    pub const TX_DONE: u8 = 0x20;
+    /// Reception completed, with its length.
+    pub const RX_DONE: u8 = 0x21;
+}
+
 /// Universal synchronous asynchronous receiver transmitter
 #[repr(C)]
 pub struct UsartRegisters {
@@ -168,6 +177,7 @@ pub(crate) fn get_address_dr(regs: StaticRef<UsartRegisters>) -> u32 {
 enum USARTStateRX {
     Idle,
     DMA_Receiving,
+    Word_Receiving,
     Aborted(Result<(), ErrorCode>, hil::uart::Error),
 }
 
@@ -176,6 +186,7 @@ enum USARTStateRX {
 enum USARTStateTX {
     Idle,
     DMA_Transmitting,
+    Word_Transmitting,
     Aborted(Result<(), ErrorCode>),
     Transfer_Completing, // DMA finished, but not all bytes sent
 }
@@ -204,7 +215,12 @@ pub struct Usart<'a, DMA: dma::StreamServer<'a>> {
     partial_rx_buffer: TakeCell<'static, [u8]>,
     partial_rx_len: Cell<usize>,
 
+    /// Whether frames carry 9 data bits.
+    nine_bit: Cell<bool>,
+
     deferred_call: DeferredCall,
+
+    statistics: StatisticsCounters,
 }
 
 // for use by `set_dma`
@@ -282,7 +298,11 @@ impl<'a, DMA: dma::StreamServer<'a>> Usart<'a, DMA> {
             partial_rx_buffer: TakeCell::empty(),
             partial_rx_len: Cell::new(0),
 
+            nine_bit: Cell::new(false),
+
             deferred_call: DeferredCall::new(),
+
+            statistics: StatisticsCounters::new(),
         }
     }
 
@@ -306,6 +326,14 @@ impl<'a, DMA: dma::StreamServer<'a>> Usart<'a, DMA> {
     // According to section 25.4.13, we need to make sure that USART TC flag is
     // set before disabling the DMA TX on the peripheral side.
     pub fn handle_interrupt(&self) {
+        // Words are received with the RXNE interrupt rather than DMA
+        if self.registers.cr1.is_set(CR1::RXNEIE) && self.registers.sr.is_set(SR::RXNE) {
+            self.receive_word_done();
+        }
+
+        if !self.registers.cr1.is_set(CR1::TCIE) {
+            return;
+        }
         self.clear_transmit_complete();
         self.disable_transmit_complete_interrupt();
 
@@ -319,6 +347,8 @@ impl<'a, DMA: dma::StreamServer<'a>> Usart<'a, DMA> {
             let buffer = self.tx_dma.map_or(None, |tx_dma| tx_dma.return_buffer());
             let len = self.tx_len.get();
             self.tx_len.set(0);
+            kernel::trace_point!(trace::TX_DONE, len as u32);
+            self.statistics.complete(len);
 
             // alert client
             self.tx_client.map(|client| {
@@ -326,6 +356,33 @@ impl<'a, DMA: dma::StreamServer<'a>> Usart<'a, DMA> {
                     client.transmitted_buffer(buf, len, Ok(()));
                 });
             });
+        } else if self.usart_tx_state.get() == USARTStateTX::Word_Transmitting {
+            self.usart_tx_state.set(USARTStateTX::Idle);
+            self.statistics.complete(1);
+            self.tx_client.map(|client| client.transmitted_word(Ok(())));
+        }
+    }
+
+    fn receive_word_done(&self) {
+        // Reading SR then DR clears the error flags
+        let sr = self.registers.sr.extract();
+        let word = self.registers.dr.get() & if self.nine_bit.get() { 0x1FF } else { 0xFF };
+        self.registers.cr1.modify(CR1::RXNEIE::CLEAR);
+
+        if self.usart_rx_state.get() == USARTStateRX::Word_Receiving {
+            self.usart_rx_state.set(USARTStateRX::Idle);
+            let (rcode, error) = if sr.is_set(SR::PE) {
+                (Err(ErrorCode::FAIL), hil::uart::Error::ParityError)
+            } else if sr.is_set(SR::FE) {
+                (Err(ErrorCode::FAIL), hil::uart::Error::FramingError)
+            } else if sr.is_set(SR::ORE) {
+                (Err(ErrorCode::FAIL), hil::uart::Error::OverrunError)
+            } else {
+                (Ok(()), hil::uart::Error::None)
+            };
+            self.statistics.finish(&rcode, 1);
+            self.rx_client
+                .map(|client| client.received_word(word, rcode, error));
         }
     }
 
@@ -437,6 +494,8 @@ impl<'a, DMA: dma::StreamServer<'a>> Usart<'a, DMA> {
 
                 let length = self.rx_len.get();
                 self.rx_len.set(0);
+                kernel::trace_point!(trace::RX_DONE, length as u32);
+                self.statistics.complete(length);
 
                 // alert client
                 self.rx_client.map(|client| {
@@ -457,21 +516,25 @@ impl<'a, DMA: dma::StreamServer<'a>> DeferredCallClient for Usart<'a, DMA> {
     fn handle_deferred_call(&self) {
         if let USARTStateTX::Aborted(rcode) = self.usart_tx_state.get() {
             // alert client
-            self.tx_client.map(|client| {
-                self.partial_tx_buffer.take().map(|buf| {
-                    client.transmitted_buffer(buf, self.partial_tx_len.get(), rcode);
+            self.statistics.finish(&rcode, self.partial_tx_len.get());
+            self.tx_client
+                .map(|client| match self.partial_tx_buffer.take() {
+                    Some(buf) => client.transmitted_buffer(buf, self.partial_tx_len.get(), rcode),
+                    None => client.transmitted_word(rcode),
                 });
-            });
             self.usart_tx_state.set(USARTStateTX::Idle);
         }
 
         if let USARTStateRX::Aborted(rcode, error) = self.usart_rx_state.get() {
             // alert client
-            self.rx_client.map(|client| {
-                self.partial_rx_buffer.take().map(|buf| {
-                    client.received_buffer(buf, self.partial_rx_len.get(), rcode, error);
+            self.statistics.finish(&rcode, self.partial_rx_len.get());
+            self.rx_client
+                .map(|client| match self.partial_rx_buffer.take() {
+                    Some(buf) => {
+                        client.received_buffer(buf, self.partial_rx_len.get(), rcode, error)
+                    }
+                    None => client.received_word(0, rcode, error),
                 });
-            });
             self.usart_rx_state.set(USARTStateRX::Idle);
         }
     }
@@ -503,18 +566,37 @@ impl<'a, DMA: dma::StreamServer<'a>> hil::uart::Transmit<'a> for Usart<'a, DMA>
         });
 
         self.usart_tx_state.set(USARTStateTX::DMA_Transmitting);
+        self.statistics.start();
 
         // enable dma tx on peripheral side
         self.enable_tx();
         Ok(())
     }
 
-    fn transmit_word(&self, _word: u32) -> Result<(), ErrorCode> {
-        Err(ErrorCode::FAIL)
+    fn transmit_word(&self, word: u32) -> Result<(), ErrorCode> {
+        if self.usart_tx_state.get() != USARTStateTX::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+
+        // Words are sent without DMA, bit 8 is the address mark in 9-bit
+        // mode
+        self.usart_tx_state.set(USARTStateTX::Word_Transmitting);
+        self.statistics.start();
+        self.clear_transmit_complete();
+        self.registers.dr.set(word & 0x1FF);
+        self.enable_transmit_complete_interrupt();
+        Ok(())
     }
 
     fn transmit_abort(&self) -> Result<(), ErrorCode> {
-        if self.usart_tx_state.get() != USARTStateTX::Idle {
+        if self.usart_tx_state.get() == USARTStateTX::Word_Transmitting {
+            self.disable_transmit_complete_interrupt();
+            self.partial_tx_len.set(0);
+            self.usart_tx_state
+                .set(USARTStateTX::Aborted(Err(ErrorCode::CANCEL)));
+            self.deferred_call.set();
+            Err(ErrorCode::BUSY)
+        } else if self.usart_tx_state.get() != USARTStateTX::Idle {
             self.abort_tx(Err(ErrorCode::CANCEL));
             Err(ErrorCode::BUSY)
         } else {
@@ -525,19 +607,39 @@ impl<'a, DMA: dma::StreamServer<'a>> hil::uart::Transmit<'a> for Usart<'a, DMA>
 
 impl<'a, DMA: dma::StreamServer<'a>> hil::uart::Configure for Usart<'a, DMA> {
     fn configure(&self, params: hil::uart::Parameters) -> Result<(), ErrorCode> {
+        use hil::uart::{Parity, Width};
+
+        // The ninth bit of the bytes written by DMA is always 0, so 8-bit
+        // words with space parity are sent as 9-bit words. Mark parity would
+        // need the ninth bit set, which DMA cannot do.
+        let nine_bit = match (params.width, params.parity) {
+            (Width::Eight, Parity::None) => Some(false),
+            (Width::Nine, Parity::None) | (Width::Eight, Parity::Space) => Some(true),
+            _ => None,
+        };
         if params.baud_rate != 115200
             || params.stop_bits != hil::uart::StopBits::One
-            || params.parity != hil::uart::Parity::None
             || params.hw_flow_control != false
-            || params.width != hil::uart::Width::Eight
+            || nine_bit.is_none()
         {
             panic!(
-                "Currently we only support uart setting of 115200bps 8N1, no hardware flow control"
+                "Currently we only support uart setting of 115200bps 8N1, 9N1 or 8S1, no hardware flow control"
             );
         }
 
-        // Configure the word length - 0: 1 Start bit, 8 Data bits, n Stop bits
-        self.registers.cr1.modify(CR1::M::CLEAR);
+        // Configure the word length - 0: 1 Start bit, 8 Data bits, n Stop
+        // bits, 1: 1 Start bit, 9 Data bits, n Stop bits
+        if nine_bit == Some(true) {
+            self.registers.cr1.modify(CR1::M::SET);
+        } else {
+            self.registers.cr1.modify(CR1::M::CLEAR);
+        }
+        self.nine_bit.set(nine_bit == Some(true));
+
+        // Disable address detection
+        self.registers
+            .cr1
+            .modify(CR1::WAKE::CLEAR + CR1::RWU::CLEAR);
 
         // Set the stop bit length - 00: 1 Stop bits
         self.registers.cr2.modify(CR2::STOP.val(0b00 as u32));
@@ -591,6 +693,7 @@ impl<'a, DMA: dma::StreamServer<'a>> hil::uart::Receive<'a> for Usart<'a, DMA> {
         });
 
         self.usart_rx_state.set(USARTStateRX::DMA_Receiving);
+        self.statistics.start();
 
         // enable dma rx on the peripheral side
         self.enable_rx();
@@ -598,15 +701,71 @@ impl<'a, DMA: dma::StreamServer<'a>> hil::uart::Receive<'a> for Usart<'a, DMA> {
     }
 
     fn receive_word(&self) -> Result<(), ErrorCode> {
-        Err(ErrorCode::FAIL)
+        if self.usart_rx_state.get() != USARTStateRX::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+
+        self.usart_rx_state.set(USARTStateRX::Word_Receiving);
+        self.statistics.start();
+        self.registers.cr1.modify(CR1::RXNEIE::SET);
+        Ok(())
     }
 
     fn receive_abort(&self) -> Result<(), ErrorCode> {
+        if self.usart_rx_state.get() == USARTStateRX::Word_Receiving {
+            self.registers.cr1.modify(CR1::RXNEIE::CLEAR);
+            self.partial_rx_len.set(0);
+            self.usart_rx_state.set(USARTStateRX::Aborted(
+                Err(ErrorCode::CANCEL),
+                hil::uart::Error::Aborted,
+            ));
+            self.deferred_call.set();
+            return Err(ErrorCode::BUSY);
+        }
         self.abort_rx(Err(ErrorCode::CANCEL), hil::uart::Error::Aborted);
         Err(ErrorCode::BUSY)
     }
 }
 
+impl<'a, DMA: dma::StreamServer<'a>> hil::uart::MultiDrop for Usart<'a, DMA> {
+    fn set_address_match(&self, address: Option<u8>) -> Result<(), ErrorCode> {
+        if !self.nine_bit.get() {
+            return Err(ErrorCode::INVAL);
+        } else if self.usart_rx_state.get() != USARTStateRX::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+
+        match address {
+            // Only the 4 low bits of address words are compared
+            Some(address) if address > 0xF => Err(ErrorCode::INVAL),
+            Some(address) => {
+                // Address words have the address mark in bit 8. Entering
+                // mute mode discards words until an address word matches.
+                self.registers.cr2.modify(CR2::ADD.val(address as u32));
+                self.registers.cr1.modify(CR1::WAKE::SET);
+                self.registers.cr1.modify(CR1::RWU::SET);
+                Ok(())
+            }
+            None => {
+                self.registers
+                    .cr1
+                    .modify(CR1::WAKE::CLEAR + CR1::RWU::CLEAR);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<'a, DMA: dma::StreamServer<'a>> DriverStatistics for Usart<'a, DMA> {
+    fn statistics(&self) -> Statistics {
+        self.statistics.get()
+    }
+
+    fn reset_statistics(&self) {
+        self.statistics.reset();
+    }
+}
+
 impl<'a> dma::StreamClient<'a, dma::Dma1<'a>> for Usart<'a, dma::Dma1<'a>> {
     fn transfer_done(&self, pid: dma::Dma1Peripheral) {
         self.transfer_done(pid);
@@ -104,8 +104,20 @@ pub unsafe trait UdpDriverCapability {}
 /// kernel
 pub unsafe trait CreatePortTableCapability {}
 
+/// The `OtpProgrammingCapability` allows the holder to permanently program
+/// one-time-programmable memory, such as the fuses of a chip. Programming a
+/// fuse cannot be undone, and the wrong fuse can make a chip unbootable, so
+/// this should only be given to code that is trusted to do so.
+pub unsafe trait OtpProgrammingCapability {}
+
 /// The `NetworkCapabilityCreationCapability` allows the holder to instantiate
 /// `NetworkCapability`S and visibility capabilities for the IP and UDP layers
 /// of the networking stack. A capsule would never hold this capability although
 /// it may hold capabilities created via this capability.
 pub unsafe trait NetworkCapabilityCreationCapability {}
+
+/// The `ResetCapability` allows the holder to reset the chip, including into
+/// its ROM bootloader. A reset stops every process, and the bootloader can
+/// replace the kernel, so this should only be given to code that is trusted
+/// to do so.
+pub unsafe trait ResetCapability {}
@@ -20,6 +20,11 @@
 //! components::debug_writer::DebugWriterComponent::new(uart_mux).finalize(components::debug_writer_component_static!());
 //! ```
 //!
+//! The rate of debug output can be limited, so that heavy logging does not
+//! fill the buffer faster than the UART can empty it: see
+//! [`debug_set_rate_limit`]. Output that is dropped, because of the limit or
+//! because the buffer is full, is counted, see [`debug_take_dropped`].
+//!
 //! The debug queue is optional, if not set in the board it is just ignored.
 //! You can add one in the board file as follows:
 //!
@@ -54,6 +59,7 @@
 //! ```
 
 use core::cell::Cell;
+use core::cmp;
 use core::fmt::{write, Arguments, Result, Write};
 use core::panic::PanicInfo;
 use core::str;
@@ -120,6 +126,7 @@ pub unsafe fn panic_print<W: Write + IoWrite, C: Chip, PP: ProcessPrinter>(
     panic_banner(writer, panic_info);
     // Flush debug buffer if needed
     flush(writer);
+    crate::trace::flush(writer);
     panic_cpu_state(chip, writer);
     panic_process_info(processes, process_printer, writer);
 }
@@ -385,6 +392,11 @@ pub struct DebugWriter {
     internal_buffer: TakeCell<'static, RingBuffer<'static, u8>>,
     // Number of debug!() calls.
     count: Cell<usize>,
+    // Number of bytes that can still be written before the rate limit is
+    // reached, or `None` if the output is not limited.
+    budget: Cell<Option<usize>>,
+    // Number of bytes dropped since the count was last taken.
+    dropped: Cell<usize>,
 }
 
 /// Static variable that holds the kernel's reference to the debug tool. This is
@@ -423,6 +435,8 @@ impl DebugWriter {
             output_buffer: TakeCell::new(out_buffer),
             internal_buffer: TakeCell::new(internal_buffer),
             count: Cell::new(0), // how many debug! calls
+            budget: Cell::new(None),
+            dropped: Cell::new(0),
         }
     }
 
@@ -528,15 +542,21 @@ impl IoWrite for DebugWriterWrapper {
     fn write(&mut self, bytes: &[u8]) -> usize {
         const FULL_MSG: &[u8] = b"\n*** DEBUG BUFFER FULL ***\n";
         self.dw.map_or(0, |dw| {
-            dw.internal_buffer.map_or(0, |ring_buffer| {
+            // Bytes over the rate limit are dropped silently: they are
+            // reported with the count of dropped bytes.
+            let allowed = dw
+                .budget
+                .get()
+                .map_or(bytes.len(), |budget| cmp::min(budget, bytes.len()));
+            let written = dw.internal_buffer.map_or(0, |ring_buffer| {
                 let available_len_for_msg =
                     ring_buffer.available_len().saturating_sub(FULL_MSG.len());
 
-                if available_len_for_msg >= bytes.len() {
-                    for &b in bytes {
+                if available_len_for_msg >= allowed {
+                    for &b in &bytes[..allowed] {
                         ring_buffer.enqueue(b);
                     }
-                    bytes.len()
+                    allowed
                 } else {
                     for &b in &bytes[..available_len_for_msg] {
                         ring_buffer.enqueue(b);
@@ -548,7 +568,12 @@ impl IoWrite for DebugWriterWrapper {
                     }
                     available_len_for_msg
                 }
-            })
+            });
+            if let Some(budget) = dw.budget.get() {
+                dw.budget.set(Some(budget - written));
+            }
+            dw.dropped.add(bytes.len() - written);
+            written
         })
     }
 }
@@ -596,6 +621,30 @@ pub fn debug_available_len() -> usize {
     writer.available_len()
 }
 
+/// Limit the debug output to `budget` more bytes, or remove the limit if
+/// `budget` is `None`. Bytes written over the limit are dropped.
+///
+/// This is meant to be called periodically, for example by a capsule with an
+/// alarm, to limit the output to a rate the UART can keep up with.
+pub fn debug_set_rate_limit(budget: Option<usize>) {
+    if let Some(writer) = unsafe { try_get_debug_writer() } {
+        writer.dw.map(|dw| dw.budget.set(budget));
+    }
+}
+
+/// Return the number of bytes that can still be written before the rate limit
+/// is reached, or `None` if the output is not limited.
+pub fn debug_rate_limit() -> Option<usize> {
+    unsafe { try_get_debug_writer() }
+        .and_then(|writer| writer.dw.map_or(None, |dw| dw.budget.get()))
+}
+
+/// Return the number of bytes of debug output dropped since the last call.
+pub fn debug_take_dropped() -> usize {
+    unsafe { try_get_debug_writer() }
+        .map_or(0, |writer| writer.dw.map_or(0, |dw| dw.dropped.replace(0)))
+}
+
 fn write_header(writer: &mut DebugWriterWrapper, (file, line): &(&'static str, u32)) -> Result {
     writer.increment_count();
     let count = writer.get_count();
@@ -120,7 +120,7 @@ impl From<ErrorCode> for Result<(), ErrorCode> {
 /// The key to this conversion and portability between the kernel and userspace
 /// is that `ErrorCode`, which only expresses errors, is assigned fixed values,
 /// but does not use value 0 by convention. This allows us to use 0 as success
-/// in ReturnCode.
+/// in StatusCode.
 pub fn into_statuscode(r: Result<(), ErrorCode>) -> usize {
     match r {
         Ok(()) => 0,
@@ -0,0 +1,33 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Interface for small banks of registers that retain their contents across
+//! resets.
+//!
+//! Many chips have a few words of storage that survive a reset of the core,
+//! and sometimes also a loss of main power when a backup battery is present.
+//! Examples are the RTC backup registers of STM32 chips, the SNVS general
+//! purpose registers of i.MX RT chips and the watchdog scratch registers of
+//! the RP2040. They are too small for general storage, but are useful for
+//! keeping counters and flags across reboots.
+//!
+//! Accesses are synchronous, as they are plain register reads and writes.
+
+use crate::ErrorCode;
+
+/// A bank of 32-bit registers retained across resets.
+pub trait BackupRegisters {
+    /// Number of registers available in the bank.
+    fn num_registers(&self) -> usize;
+
+    /// Read the register at `index`.
+    ///
+    /// Returns `INVAL` if `index` is out of range.
+    fn read(&self, index: usize) -> Result<u32, ErrorCode>;
+
+    /// Write `value` to the register at `index`.
+    ///
+    /// Returns `INVAL` if `index` is out of range.
+    fn write(&self, index: usize, value: u32) -> Result<(), ErrorCode>;
+}
@@ -42,6 +42,15 @@ pub trait Bus8080<'a> {
         len: usize,
     ) -> Result<(), (ErrorCode, &'static mut [u8])>;
 
+    /// Abort the operation in progress, for example after a timeout.
+    ///
+    /// Return values:
+    /// - Ok(()): the operation is aborted, and the client's
+    ///   `command_complete` is called with `Err(CANCEL)` and the buffer
+    /// - Err(INVAL): no operation is in progress
+    /// - Err(NOSUPPORT): the bus cannot abort operations
+    fn abort(&self) -> Result<(), ErrorCode>;
+
     fn set_client(&self, client: &'a dyn Client);
 }
 
@@ -51,6 +60,7 @@ pub trait Client {
     /// set_address does not return a buffer
     /// write and read return a buffer
     /// len should be set to the number of data elements written
+    /// status is `Err(CANCEL)` if the operation was aborted
     fn command_complete(
         &self,
         buffer: Option<&'static mut [u8]>,
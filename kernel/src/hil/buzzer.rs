@@ -27,6 +27,28 @@ pub trait Buzzer<'a> {
     /// - `FAIL`: Cannot start the buzzer.
     fn buzz(&self, frequency_hz: usize, duration_ms: usize) -> Result<(), ErrorCode>;
 
+    /// Play a sound sweeping from `start_hz` to `end_hz` over a chosen
+    /// duration, in `steps` tones of equal length. The first tone is at
+    /// `start_hz` and the last one at `end_hz`, so sirens and alarms do not
+    /// need to request every tone. Once the last tone finishes, the
+    /// `buzzer_done()` callback is called.
+    /// If it is called while the buzzer is playing, the current sound is
+    /// replaced by the sweep.
+    ///
+    /// Return values:
+    ///
+    /// - `Ok(())`: The attempt at starting the sweep was successful.
+    /// - `INVAL`: `steps` is zero, or each tone would be shorter than a
+    ///   millisecond.
+    /// - `FAIL`: Cannot start the buzzer.
+    fn sweep(
+        &self,
+        start_hz: usize,
+        end_hz: usize,
+        duration_ms: usize,
+        steps: usize,
+    ) -> Result<(), ErrorCode>;
+
     /// Stop the sound currenty playing.
     /// After the buzzer is successfully stopped, the `buzzer_done()`
     /// callback is called.
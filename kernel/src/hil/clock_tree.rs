@@ -0,0 +1,43 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Interface for inspecting the clock configuration of a chip.
+//!
+//! A chip describes its clock tree as a list of clocks, each running from a
+//! source clock through a multiplier and a divider, and a list of peripheral
+//! clock gates. Both are read back from the hardware each time, so that they
+//! show the configuration the chip actually runs with, which makes
+//! peripherals left without a clock easy to spot.
+
+/// An oscillator, a PLL, or a clock derived from another clock.
+#[derive(Copy, Clone, Debug)]
+pub struct Clock {
+    pub name: &'static str,
+    pub enabled: bool,
+    /// The clock this one runs from, `None` for oscillators.
+    pub source: Option<&'static str>,
+    /// Factor applied to the frequency of the source, 1 if there is none.
+    pub multiplier: u32,
+    /// Divider applied to the frequency of the source, 1 if there is none.
+    pub divider: u32,
+    /// Frequency of the clock, `None` if it cannot be determined.
+    pub frequency_hz: Option<u32>,
+}
+
+/// The clock gate of a peripheral.
+#[derive(Copy, Clone, Debug)]
+pub struct ClockGate {
+    pub name: &'static str,
+    pub enabled: bool,
+}
+
+pub trait ClockTree {
+    /// Clock `index`, from the oscillators to the bus clocks. Returns `None`
+    /// once `index` is past the last clock.
+    fn clock(&self, index: usize) -> Option<Clock>;
+
+    /// Peripheral clock gate `index`. Returns `None` once `index` is past
+    /// the last gate.
+    fn clock_gate(&self, index: usize) -> Option<ClockGate>;
+}
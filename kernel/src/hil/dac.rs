@@ -10,4 +10,15 @@ use crate::ErrorCode;
 pub trait DacChannel {
     /// Set the DAC output value.
     fn set_value(&self, value: usize) -> Result<(), ErrorCode>;
+
+    /// Function to ask the DAC how many bits of resolution the output values
+    /// have. Valid values are in `0..(1 << bits)`.
+    fn get_resolution_bits(&self) -> usize;
+
+    /// Function to ask the DAC for the reference voltage of its output,
+    /// which is the voltage of the largest output value. This allows the
+    /// user of this interface to compute the value of a given voltage.
+    ///
+    /// The returned reference voltage is in millivolts, or `None` if unknown.
+    fn get_voltage_reference_mv(&self) -> Option<usize>;
 }
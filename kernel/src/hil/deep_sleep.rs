@@ -0,0 +1,19 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Interface for letting the chip use its deep sleep state when idle.
+//!
+//! In deep sleep, the clocks of most peripherals are stopped and only the
+//! wakeup sources configured by the board wake the chip up. Timers driven by
+//! the stopped clocks, including the kernel alarm on most chips, do not
+//! advance. Capsules which know that nothing needs to happen until a wakeup
+//! source fires, such as a duty cycling scheduler between its windows, use
+//! this interface to allow deep sleep, and forbid it again as soon as they
+//! need the rest of the chip.
+
+pub trait DeepSleep {
+    /// Allow or forbid deep sleep. The chip still only enters deep sleep
+    /// when idle, and when at least one wakeup source is armed.
+    fn set_deep_sleep_allowed(&self, allowed: bool);
+}
@@ -0,0 +1,24 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Interface for reading a unique identifier of the device.
+//!
+//! Most microcontrollers are programmed with a factory identifier that is
+//! unique to each chip, for example the 96-bit unique ID of STM32 chips or
+//! the unique ID fuses of i.MX RT chips. The identifier is read synchronously,
+//! as it is stored in memory-mapped registers.
+
+use crate::ErrorCode;
+
+/// A unique, read-only identifier of the chip.
+pub trait DeviceId {
+    /// Length of the identifier, in bytes.
+    fn id_len(&self) -> usize;
+
+    /// Copy the identifier into `buf`.
+    ///
+    /// On success, returns the number of bytes written, which is `id_len()`.
+    /// Returns `SIZE` if `buf` is shorter than `id_len()`.
+    fn read_id(&self, buf: &mut [u8]) -> Result<usize, ErrorCode>;
+}
@@ -0,0 +1,25 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Interface for reading a secret key unique to the device.
+//!
+//! Some chips can hold a key that never leaves the device, for example in
+//! one-time programmable fuses or derived by a physically unclonable function
+//! (PUF). Capsules use it to encrypt the data they store, so that a copy of
+//! the storage is useless on another device. The key is read synchronously,
+//! as it is stored in memory-mapped registers.
+
+use crate::ErrorCode;
+
+/// Length of the device key, in bytes.
+pub const DEVICE_KEY_LEN: usize = 16;
+
+/// A secret 128-bit key unique to the device.
+pub trait DeviceKey {
+    /// Copy the key into `key`.
+    ///
+    /// Returns `NODEVICE` if no key is provisioned, or `RESERVE` if the key
+    /// cannot be read, for example because it is locked.
+    fn read_key(&self, key: &mut [u8; DEVICE_KEY_LEN]) -> Result<(), ErrorCode>;
+}
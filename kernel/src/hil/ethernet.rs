@@ -0,0 +1,63 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Interface for sending and receiving Ethernet frames.
+//!
+//! A frame starts with the destination MAC address and ends with the payload:
+//! the preamble and the frame check sequence are added and removed by the
+//! hardware. The MAC address of the adapter is set and read through
+//! [`crate::hil::mac_address::Configure`].
+
+use crate::ErrorCode;
+
+/// Length of the Ethernet header: destination address, source address and
+/// EtherType.
+pub const HEADER_LEN: usize = 14;
+
+/// Largest frame, without the frame check sequence, that can be sent or
+/// received.
+pub const MAX_FRAME_LEN: usize = 1514;
+
+/// An Ethernet MAC.
+pub trait EthernetAdapter<'a> {
+    /// Set the client that is given received frames.
+    fn set_receive_client(&self, client: &'a dyn RxClient);
+
+    /// Set the client that is told when a frame has been sent.
+    fn set_transmit_client(&self, client: &'a dyn TxClient);
+
+    /// Start sending and receiving frames.
+    fn enable(&self) -> Result<(), ErrorCode>;
+
+    /// Stop sending and receiving frames. A frame being sent is aborted.
+    fn disable(&self) -> Result<(), ErrorCode>;
+
+    /// Whether the PHY reports that the link is up.
+    fn link_up(&self) -> bool;
+
+    /// Send the first `len` bytes of `frame`.
+    ///
+    /// Returns `OFF` if the adapter is not enabled, `SIZE` if `len` is
+    /// larger than `MAX_FRAME_LEN` or the buffer, and `BUSY` if a frame is
+    /// already being sent. On success, `frame` is returned through
+    /// `TxClient::transmit_done()`.
+    fn transmit(
+        &self,
+        frame: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])>;
+}
+
+/// Client for frames sent with `EthernetAdapter::transmit()`.
+pub trait TxClient {
+    /// The frame has been sent, or sending failed.
+    fn transmit_done(&self, frame: &'static mut [u8], result: Result<(), ErrorCode>);
+}
+
+/// Client for received frames.
+pub trait RxClient {
+    /// A frame was received. `frame` does not include the frame check
+    /// sequence and is only valid for the duration of the call.
+    fn received_frame(&self, frame: &[u8]);
+}
@@ -5,6 +5,7 @@
 //! HIL for General Purpose Input-Output (GPIO) pins.
 
 use core::cell::Cell;
+use core::fmt;
 
 use crate::utilities::cells::OptionalCell;
 use crate::ErrorCode;
@@ -81,6 +82,20 @@ impl<T: Input + Output + Configure> Pin for T {}
 impl<'a, T: Pin + Interrupt<'a>> InterruptPin<'a> for T {}
 impl<'a, T: Pin + InterruptWithValue<'a>> InterruptValuePin<'a> for T {}
 
+/// The GPIO pins of a chip, by index, so that debugging tools can look pins
+/// up by name and inspect or drive them.
+pub trait PinIntrospection {
+    /// Number of pins, including those which cannot be accessed.
+    fn num_pins(&self) -> usize;
+
+    /// Pin `index`. Returns `None` past the last pin, or if the pin cannot be
+    /// accessed, for example because its port has no clock.
+    fn pin(&self, index: usize) -> Option<&dyn Pin>;
+
+    /// Write the name of pin `index`, as used in the chip documentation.
+    fn write_pin_name(&self, index: usize, writer: &mut dyn fmt::Write) -> fmt::Result;
+}
+
 /// Control and configure a GPIO pin.
 pub trait Configure {
     /// Return the current pin configuration.
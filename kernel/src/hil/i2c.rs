@@ -95,6 +95,38 @@ pub trait I2CMaster<'a> {
     ) -> Result<(), (Error, &'static mut [u8])>;
 }
 
+/// Standard I2C bus frequencies, from the slowest to the fastest.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum BusSpeed {
+    /// Standard mode, 100 kHz.
+    Standard100k,
+    /// Fast mode, 400 kHz.
+    Fast400k,
+    /// Fast mode plus, 1 MHz.
+    FastPlus1M,
+}
+
+impl BusSpeed {
+    pub fn frequency_hz(&self) -> u32 {
+        match self {
+            BusSpeed::Standard100k => 100_000,
+            BusSpeed::Fast400k => 400_000,
+            BusSpeed::FastPlus1M => 1_000_000,
+        }
+    }
+}
+
+/// Interface for configuring the frequency of an I2C Master hardware driver.
+pub trait I2CBusSpeed {
+    /// The fastest bus speed the hardware driver supports.
+    fn max_bus_speed(&self) -> BusSpeed;
+
+    /// Set the bus frequency. Must only be called while the bus is idle.
+    ///
+    /// Returns `NOSUPPORT` if the speed is faster than `max_bus_speed`.
+    fn set_bus_speed(&self, speed: BusSpeed) -> Result<(), ErrorCode>;
+}
+
 /// Interface for an SMBus Master hardware driver.
 /// The device implementing this will also seperately implement
 /// I2CMaster.
@@ -0,0 +1,96 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Interface for satellite positioning receivers.
+//!
+//! A receiver reports a fix about once per second while it is started: the
+//! time, and the position if the receiver has one. All values are integers
+//! in fixed units, so clients need no floating point.
+
+use crate::ErrorCode;
+
+/// Kind of position solution.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FixQuality {
+    /// No position, only the time may be valid.
+    None,
+    /// Standalone satellite positioning.
+    Gps,
+    /// Positioning corrected with a differential station or SBAS.
+    Differential,
+    /// Real time kinematic positioning, fixed or float.
+    Rtk,
+    /// Position estimated by dead reckoning.
+    Estimated,
+}
+
+impl From<FixQuality> for u32 {
+    fn from(quality: FixQuality) -> u32 {
+        match quality {
+            FixQuality::None => 0,
+            FixQuality::Gps => 1,
+            FixQuality::Differential => 2,
+            FixQuality::Rtk => 3,
+            FixQuality::Estimated => 4,
+        }
+    }
+}
+
+/// UTC time of a fix.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct UtcTime {
+    /// Full year, 0 if the date is unknown
+    pub year: u16,
+    /// 1 to 12, 0 if the date is unknown
+    pub month: u8,
+    /// 1 to 31, 0 if the date is unknown
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub millisecond: u16,
+}
+
+/// A fix reported by the receiver.
+///
+/// The position is only meaningful if `quality` is not `FixQuality::None`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Fix {
+    pub quality: FixQuality,
+    pub time: UtcTime,
+    /// Latitude in 1e-7 degrees, positive to the north
+    pub latitude: i32,
+    /// Longitude in 1e-7 degrees, positive to the east
+    pub longitude: i32,
+    /// Altitude above the mean sea level in millimeters
+    pub altitude: Option<i32>,
+    /// Number of satellites used for the position
+    pub satellites: u8,
+    /// Speed over the ground in millimeters per second
+    pub speed: Option<u32>,
+    /// Course over the ground in hundredths of a degree, clockwise from the
+    /// true north
+    pub course: Option<u32>,
+}
+
+/// A satellite positioning receiver.
+pub trait Location<'a> {
+    /// Set the client that receives the fixes.
+    fn set_client(&self, client: &'a dyn LocationClient);
+
+    /// Start reporting fixes to the client.
+    ///
+    /// Returns `Ok(())` even if the receiver is already started.
+    fn start(&self) -> Result<(), ErrorCode>;
+
+    /// Stop reporting fixes. The receiver may keep tracking satellites, so
+    /// that the next fix comes quickly.
+    fn stop(&self) -> Result<(), ErrorCode>;
+}
+
+/// Client interface for satellite positioning receivers.
+pub trait LocationClient {
+    /// Called with each fix, about once per second.
+    fn fix(&self, fix: &Fix);
+}
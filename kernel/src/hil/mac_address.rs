@@ -0,0 +1,104 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Interfaces for providing and configuring 48-bit IEEE 802 MAC addresses.
+//!
+//! Ethernet controllers and WiFi radios both need a MAC address, which is
+//! usually burned into the fuses of the chip, but can also come from
+//! nonvolatile storage on boards without programmed fuses. `MacAddressSource`
+//! is implemented by places a MAC address can be read from, and `Configure`
+//! by network interfaces that use one.
+
+use core::fmt;
+
+use crate::ErrorCode;
+
+/// A 48-bit MAC address, most significant byte first.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct MacAddress([u8; 6]);
+
+impl MacAddress {
+    pub const fn new(bytes: [u8; 6]) -> Self {
+        MacAddress(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 6] {
+        &self.0
+    }
+
+    /// Whether the address is all zeros, as read from unprogrammed fuses.
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0; 6]
+    }
+
+    /// Whether the group bit is set.
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+
+    /// Whether the address is locally administered rather than assigned by
+    /// the IEEE.
+    pub fn is_locally_administered(&self) -> bool {
+        self.0[0] & 0x02 != 0
+    }
+
+    /// Whether the address can be used by an interface: it is neither zero,
+    /// nor multicast, nor broadcast.
+    pub fn is_valid_unicast(&self) -> bool {
+        !self.is_zero() && !self.is_multicast()
+    }
+
+    /// Create a locally administered unicast address from the low bytes of
+    /// `id`, for example a unique chip ID.
+    pub fn new_local(id: &[u8]) -> Self {
+        let mut bytes = [0; 6];
+        for (i, byte) in id.iter().enumerate() {
+            bytes[i % 6] ^= *byte;
+        }
+        bytes[0] = (bytes[0] & !0x01) | 0x02;
+        MacAddress(bytes)
+    }
+}
+
+impl From<[u8; 6]> for MacAddress {
+    fn from(bytes: [u8; 6]) -> Self {
+        MacAddress(bytes)
+    }
+}
+
+impl fmt::Debug for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let b = &self.0;
+        write!(
+            f,
+            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            b[0], b[1], b[2], b[3], b[4], b[5]
+        )
+    }
+}
+
+/// A place a MAC address can be read from synchronously, such as fuses.
+pub trait MacAddressSource {
+    /// Return the MAC address, or `None` if none is provisioned.
+    fn mac_address(&self) -> Option<MacAddress>;
+}
+
+/// A network interface that uses a MAC address.
+pub trait Configure {
+    /// Set the MAC address used by the interface.
+    ///
+    /// Returns `INVAL` if the address is not a valid unicast address, and
+    /// `BUSY` if the interface cannot change its address while it is
+    /// running.
+    fn set_mac_address(&self, address: MacAddress) -> Result<(), ErrorCode>;
+
+    /// Return the MAC address used by the interface.
+    fn get_mac_address(&self) -> MacAddress;
+}
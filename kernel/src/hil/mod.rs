@@ -6,15 +6,21 @@
 
 pub mod adc;
 pub mod analog_comparator;
+pub mod backup_registers;
 pub mod ble_advertising;
 pub mod bus8080;
 pub mod buzzer;
 pub mod can;
+pub mod clock_tree;
 pub mod crc;
 pub mod dac;
+pub mod deep_sleep;
+pub mod device_id;
+pub mod device_key;
 pub mod digest;
 pub mod eic;
 pub mod entropy;
+pub mod ethernet;
 pub mod flash;
 pub mod gpio;
 pub mod gpio_async;
@@ -22,15 +28,22 @@ pub mod hasher;
 pub mod i2c;
 pub mod kv_system;
 pub mod led;
+pub mod location;
 pub mod log;
+pub mod mac_address;
 pub mod nonvolatile_storage;
 pub mod public_key_crypto;
+pub mod pulse_capture;
 pub mod pwm;
+pub mod pwm_capture;
 pub mod radio;
+pub mod reset;
+pub mod reset_reason;
 pub mod rng;
 pub mod screen;
 pub mod sensors;
 pub mod spi;
+pub mod statistics;
 pub mod symmetric_encryption;
 pub mod text_screen;
 pub mod time;
@@ -0,0 +1,44 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Interface for timestamping the edges of a digital signal.
+//!
+//! Protocols which encode data in the length of pulses, such as infrared
+//! remote controls, ultrasonic range finders or single-wire sensors like the
+//! DHT22, are decoded from the time between the edges of a signal. This
+//! interface reports each edge with its timestamp, so that their capsules do
+//! not time GPIO interrupts themselves. It can be implemented with the input
+//! capture of a timer, a PIO state machine, or in software with a GPIO
+//! interrupt and a clock.
+
+use crate::ErrorCode;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Edge {
+    Rising,
+    Falling,
+}
+
+/// Capture of the edges of the signal on a single pin.
+pub trait PulseCapture<'a> {
+    /// Set the client that receives the edges.
+    fn set_client(&self, client: &'a dyn PulseCaptureClient);
+
+    /// Start reporting every edge of the signal to the client, until
+    /// `stop()`. Returns `ALREADY` if the capture is already running.
+    fn start(&self) -> Result<(), ErrorCode>;
+
+    /// Stop reporting edges. Returns `OFF` if the capture is not running.
+    fn stop(&self) -> Result<(), ErrorCode>;
+}
+
+/// Client interface for edge capture.
+pub trait PulseCaptureClient {
+    /// Called for each edge of the signal.
+    ///
+    /// `timestamp_us` is the time of the edge in microseconds. It wraps
+    /// around, so only the difference between two timestamps, computed with
+    /// `wrapping_sub`, is meaningful.
+    fn edge(&self, edge: Edge, timestamp_us: u32);
+}
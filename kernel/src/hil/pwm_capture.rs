@@ -0,0 +1,46 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Interface for measuring the frequency and duty cycle of an external
+//! PWM-like signal (input capture).
+//!
+//! This is the input counterpart of [`crate::hil::pwm::PwmPin`]: instead of
+//! generating a periodic signal on a pin, the hardware observes a periodic
+//! signal on a pin and reports its period and the time it spent high. This
+//! enables, for example, RPM sensing on fan tachometer outputs or decoding
+//! the pulses of a hobby RC receiver.
+
+use crate::ErrorCode;
+
+/// Input capture on a single pin.
+pub trait PwmCapture<'a> {
+    /// Set the client that receives the measurement results.
+    fn set_client(&self, client: &'a dyn PwmCaptureClient);
+
+    /// Start a single measurement of the signal on the pin. The result is
+    /// delivered through `PwmCaptureClient::capture_done()`.
+    ///
+    /// Returns `BUSY` if a measurement is already in progress. If no signal
+    /// is present on the pin, the measurement does not complete until it is
+    /// aborted with `stop_capture()`.
+    fn start_capture(&self) -> Result<(), ErrorCode>;
+
+    /// Abort an in-progress measurement. No callback is issued for an
+    /// aborted measurement.
+    fn stop_capture(&self) -> Result<(), ErrorCode>;
+}
+
+/// Client interface for input capture.
+pub trait PwmCaptureClient {
+    /// Called when a measurement started with `start_capture()` completes.
+    ///
+    /// - `period_ns` is the period of the signal in nanoseconds. The frequency
+    ///   in Hertz is `1_000_000_000 / period_ns`.
+    /// - `high_ns` is the time the signal spent high during one period, in
+    ///   nanoseconds. The duty cycle is `high_ns / period_ns`.
+    ///
+    /// On error (e.g. `SIZE` when the signal is too slow to be measured by
+    /// the hardware), `period_ns` and `high_ns` are 0.
+    fn capture_done(&self, result: Result<(), ErrorCode>, period_ns: u32, high_ns: u32);
+}
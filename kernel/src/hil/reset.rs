@@ -0,0 +1,21 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Interface for resetting the chip from software.
+//!
+//! Resetting stops every process and the kernel, so both methods require the
+//! `ResetCapability`.
+
+use crate::capabilities::ResetCapability;
+
+/// Reset the chip, optionally into its ROM bootloader.
+pub trait Reset {
+    /// Reset the chip and boot the kernel again.
+    fn reset(&self, capability: &dyn ResetCapability) -> !;
+
+    /// Reset the chip into its ROM bootloader, so that it can be flashed
+    /// again through the bootloader's download interface. How the chip
+    /// leaves the bootloader depends on the chip.
+    fn reset_to_bootloader(&self, capability: &dyn ResetCapability) -> !;
+}
@@ -0,0 +1,41 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Interface for querying why the chip was last reset.
+
+/// Cause of the last reset of the chip.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ResetReason {
+    /// Power was applied or restored (including brown-outs).
+    PowerOn,
+    /// The external reset pin was asserted.
+    ExternalPin,
+    /// A watchdog timer expired.
+    Watchdog,
+    /// Software requested the reset (e.g. `SYSRESETREQ`).
+    Software,
+    /// The chip cannot tell, or the cause does not fit any other variant.
+    Unknown,
+}
+
+impl From<ResetReason> for u32 {
+    fn from(reason: ResetReason) -> u32 {
+        match reason {
+            ResetReason::PowerOn => 0,
+            ResetReason::ExternalPin => 1,
+            ResetReason::Watchdog => 2,
+            ResetReason::Software => 3,
+            ResetReason::Unknown => 4,
+        }
+    }
+}
+
+/// Report the cause of the last reset.
+pub trait ResetReasonQuery {
+    /// Return the cause of the last reset.
+    ///
+    /// Chips that latch reset flags until they are cleared report the same
+    /// reason for the whole time the kernel runs.
+    fn reset_reason(&self) -> ResetReason;
+}
@@ -35,6 +35,21 @@ pub trait HumidityClient {
     fn callback(&self, value: usize);
 }
 
+/// A basic interface for a barometric pressure sensor
+pub trait PressureDriver<'a> {
+    fn set_client(&self, client: &'a dyn PressureClient);
+    fn read_pressure(&self) -> Result<(), ErrorCode>;
+}
+
+/// Client for receiving pressure readings.
+pub trait PressureClient {
+    /// Called when a pressure reading has completed.
+    ///
+    /// - `value`: the most recently read pressure in pascals, or Err on
+    /// failure.
+    fn callback(&self, value: Result<u32, ErrorCode>);
+}
+
 /// A basic interface for a Air Quality sensor
 pub trait AirQualityDriver<'a> {
     /// Set the client to be notified when the capsule has data ready.
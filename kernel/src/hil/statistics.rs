@@ -0,0 +1,102 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Interface for reading the operation counters of a driver.
+//!
+//! Bus drivers, such as UARTs, SPI and I2C controllers, count the operations
+//! they start, the ones which complete successfully and the ones which fail,
+//! and the bytes moved by both. A driver with a failing bus shows more
+//! errors, or operations started which never end, without a debugger.
+//!
+//! Drivers usually keep their counters in a [`StatisticsCounters`], which
+//! only takes a few cell updates per operation. Counters wrap around.
+
+use core::cell::Cell;
+
+/// The counters of a driver at one time.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Statistics {
+    /// Operations started.
+    pub started: u32,
+    /// Operations which completed successfully.
+    pub completed: u32,
+    /// Operations which completed with an error, including aborted ones.
+    pub errors: u32,
+    /// Bytes transmitted and received, by operations with and without
+    /// errors.
+    pub bytes: u32,
+}
+
+/// A driver counting its operations.
+pub trait DriverStatistics {
+    /// The current counters.
+    fn statistics(&self) -> Statistics;
+
+    /// Set every counter back to zero.
+    fn reset_statistics(&self);
+}
+
+/// Counters updated by a driver as operations start and end.
+pub struct StatisticsCounters {
+    started: Cell<u32>,
+    completed: Cell<u32>,
+    errors: Cell<u32>,
+    bytes: Cell<u32>,
+}
+
+impl StatisticsCounters {
+    pub const fn new() -> StatisticsCounters {
+        StatisticsCounters {
+            started: Cell::new(0),
+            completed: Cell::new(0),
+            errors: Cell::new(0),
+            bytes: Cell::new(0),
+        }
+    }
+
+    /// An operation started.
+    pub fn start(&self) {
+        self.started.set(self.started.get().wrapping_add(1));
+    }
+
+    /// An operation completed successfully, moving `bytes` bytes.
+    pub fn complete(&self, bytes: usize) {
+        self.completed.set(self.completed.get().wrapping_add(1));
+        self.add_bytes(bytes);
+    }
+
+    /// An operation failed, after moving `bytes` bytes.
+    pub fn fail(&self, bytes: usize) {
+        self.errors.set(self.errors.get().wrapping_add(1));
+        self.add_bytes(bytes);
+    }
+
+    /// An operation ended with `result`, after moving `bytes` bytes.
+    pub fn finish<E>(&self, result: &Result<(), E>, bytes: usize) {
+        match result {
+            Ok(()) => self.complete(bytes),
+            Err(_) => self.fail(bytes),
+        }
+    }
+
+    fn add_bytes(&self, bytes: usize) {
+        self.bytes.set(self.bytes.get().wrapping_add(bytes as u32));
+    }
+
+    pub fn get(&self) -> Statistics {
+        Statistics {
+            started: self.started.get(),
+            completed: self.completed.get(),
+            errors: self.errors.get(),
+            bytes: self.bytes.get(),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.started.set(0);
+        self.completed.set(0);
+        self.errors.set(0);
+        self.bytes.set(0);
+    }
+}
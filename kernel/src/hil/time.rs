@@ -35,6 +35,11 @@ pub trait Ticks: Clone + Copy + From<u32> + fmt::Debug + Ord + PartialOrd + Eq {
     /// are 32 bits.
     fn into_u32(self) -> u32;
 
+    /// Converts the type into a `u64`, filling the higher bits with 0.
+    /// Unlike `into_u32`, this never loses information for the tick
+    /// widths Tock supports.
+    fn into_u64(self) -> u64;
+
     /// Add two values, wrapping around on overflow using standard
     /// unsigned arithmetic.
     fn wrapping_add(self, other: Self) -> Self;
@@ -118,6 +123,30 @@ pub trait ConvertTicks<T: Ticks> {
     /// rounding down any fractions. If the value overflows u32, `u32::MAX`
     /// is returned,
     fn ticks_to_us(&self, tick: T) -> u32;
+
+    /// Returns the number of milliseconds in the provided number of ticks,
+    /// rounding down any fractions. Unlike `ticks_to_ms`, the result is
+    /// not limited to `u32`, so this is suitable for long-running (64-bit)
+    /// timestamps. If the value overflows u64, `u64::MAX` is returned.
+    fn ticks_to_ms_u64(&self, tick: T) -> u64;
+
+    /// Returns the number of microseconds in the provided number of ticks,
+    /// rounding down any fractions. Unlike `ticks_to_us`, the result is
+    /// not limited to `u32`, so this is suitable for long-running (64-bit)
+    /// timestamps. If the value overflows u64, `u64::MAX` is returned.
+    fn ticks_to_us_u64(&self, tick: T) -> u64;
+}
+
+/// Computes `ticks * numerator / denominator` without overflowing in the
+/// intermediate product, saturating at `u64::MAX`.
+fn scale_u64(ticks: u64, numerator: u32, denominator: u32) -> u64 {
+    let denominator = denominator as u64;
+    let whole = ticks / denominator;
+    // `remainder < denominator`, so this product always fits in a u64.
+    let remainder = ticks % denominator;
+    whole
+        .saturating_mul(numerator as u64)
+        .saturating_add(remainder * numerator as u64 / denominator)
 }
 
 impl<T: Time + ?Sized> ConvertTicks<<T as Time>::Ticks> for T {
@@ -149,6 +178,19 @@ impl<T: Time + ?Sized> ConvertTicks<<T as Time>::Ticks> for T {
     fn ticks_to_us(&self, tick: <T as Time>::Ticks) -> u32 {
         tick.saturating_scale(1_000_000, <T as Time>::Frequency::frequency())
     }
+
+    #[inline]
+    fn ticks_to_ms_u64(&self, tick: <T as Time>::Ticks) -> u64 {
+        scale_u64(tick.into_u64(), 1_000, <T as Time>::Frequency::frequency())
+    }
+    #[inline]
+    fn ticks_to_us_u64(&self, tick: <T as Time>::Ticks) -> u64 {
+        scale_u64(
+            tick.into_u64(),
+            1_000_000,
+            <T as Time>::Frequency::frequency(),
+        )
+    }
 }
 
 /// Represents a static moment in time, that does not change over
@@ -405,6 +447,10 @@ impl Ticks for Ticks32 {
         self.0
     }
 
+    fn into_u64(self) -> u64 {
+        self.0 as u64
+    }
+
     fn wrapping_add(self, other: Self) -> Self {
         Ticks32(self.0.wrapping_add(other.0))
     }
@@ -486,6 +532,10 @@ impl Ticks for Ticks24 {
         self.0
     }
 
+    fn into_u64(self) -> u64 {
+        self.0 as u64
+    }
+
     fn wrapping_add(self, other: Self) -> Self {
         Ticks24(self.0.wrapping_add(other.0) & 0x00FFFFFF)
     }
@@ -579,6 +629,10 @@ impl Ticks for Ticks16 {
         self.0 as u32
     }
 
+    fn into_u64(self) -> u64 {
+        self.0 as u64
+    }
+
     fn wrapping_add(self, other: Self) -> Self {
         Ticks16(self.0.wrapping_add(other.0))
     }
@@ -645,12 +699,6 @@ impl Eq for Ticks16 {}
 #[derive(Clone, Copy, Debug)]
 pub struct Ticks64(u64);
 
-impl Ticks64 {
-    pub fn into_u64(self) -> u64 {
-        self.0
-    }
-}
-
 impl From<u32> for Ticks64 {
     fn from(val: u32) -> Self {
         Ticks64(val as u64)
@@ -672,6 +720,10 @@ impl Ticks for Ticks64 {
         self.0 as u32
     }
 
+    fn into_u64(self) -> u64 {
+        self.0
+    }
+
     fn wrapping_add(self, other: Self) -> Self {
         Ticks64(self.0.wrapping_add(other.0))
     }
@@ -765,6 +817,20 @@ mod tests {
         assert_eq!(us, !0u32);
     }
 
+    #[test]
+    fn test_from_ticks64_u64() {
+        // Ten hours of 1 MHz ticks do not fit in a u32 of microseconds
+        let ten_hours = 10 * 3600 * 1_000_000u64;
+        let ms = Test1MHz64().ticks_to_ms_u64(ten_hours.into());
+        assert_eq!(ms, 10 * 3600 * 1_000);
+
+        let us = Test1MHz64().ticks_to_us_u64(ten_hours.into());
+        assert_eq!(us, ten_hours);
+
+        let us = Test1MHz64().ticks_to_us_u64(u64::MAX.into());
+        assert_eq!(us, u64::MAX);
+    }
+
     #[test]
     fn test_to_ticks64() {
         let t = Test1MHz64().ticks_from_seconds(1);
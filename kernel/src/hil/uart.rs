@@ -19,6 +19,12 @@ pub enum Parity {
     None = 0,
     Odd = 1,
     Even = 2,
+    /// The parity bit is always 1. In 9-bit multi-drop protocols this marks
+    /// address words.
+    Mark = 3,
+    /// The parity bit is always 0. In 9-bit multi-drop protocols this marks
+    /// data words.
+    Space = 4,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -26,6 +32,7 @@ pub enum Width {
     Six = 6,
     Seven = 7,
     Eight = 8,
+    Nine = 9,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -329,3 +336,39 @@ pub trait ReceiveAdvanced<'a>: Receive<'a> {
         interbyte_timeout: u8,
     ) -> Result<(), (ErrorCode, &'static mut [u8])>;
 }
+
+/// Trait for UARTs which can filter received words by address, for 9-bit
+/// multi-drop buses such as RS-485.
+///
+/// On these buses each word carries a ninth bit which is set for address
+/// words and clear for data words. The UART is configured with
+/// `Width::Nine`, or with `Parity::Mark` or `Parity::Space` to send address
+/// or data words with 8-bit buffers. Words are sent and received with
+/// `transmit_word` and `receive_word`, which carry all nine bits.
+pub trait MultiDrop {
+    /// Only receive the words following an address word which matches
+    /// `address`, until an address word which does not match. `None`
+    /// receives all words.
+    ///
+    /// Returns Ok(()), or
+    /// - BUSY: A transmission or reception is in progress.
+    /// - INVAL: The UART is not configured for 9-bit words, or `address` is
+    ///          larger than the hardware can match.
+    /// - NOSUPPORT: The UART cannot match addresses.
+    fn set_address_match(&self, address: Option<u8>) -> Result<(), ErrorCode>;
+}
+
+/// Trait for UARTs which can send a break, holding the transmit line low for
+/// longer than a word. Breaks start frames on buses such as LIN.
+pub trait Break {
+    /// Start holding the transmit line low, once the word being sent is
+    /// done. The line stays low until `stop_break` is called, so the caller
+    /// times the break.
+    ///
+    /// Returns Ok(()), or
+    /// - OFF: The transmitter is disabled.
+    fn start_break(&self) -> Result<(), ErrorCode>;
+
+    /// Release the transmit line, ending the break.
+    fn stop_break(&self) -> Result<(), ErrorCode>;
+}
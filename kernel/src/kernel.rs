@@ -24,7 +24,7 @@ use crate::memop;
 use crate::platform::chip::Chip;
 use crate::platform::mpu::MPU;
 use crate::platform::platform::ContextSwitchCallback;
-use crate::platform::platform::KernelResources;
+use crate::platform::platform::{KernelHooks, KernelResources};
 use crate::platform::platform::{ProcessFault, SyscallDriverLookup, SyscallFilter};
 use crate::platform::scheduler_timer::SchedulerTimer;
 use crate::platform::watchdog::WatchDog;
@@ -36,6 +36,7 @@ use crate::syscall::SyscallDriver;
 use crate::syscall::{ContextSwitchReason, SyscallReturn};
 use crate::syscall::{Syscall, YieldCall};
 use crate::syscall_driver::CommandReturn;
+use crate::trace;
 use crate::upcall::{Upcall, UpcallId};
 use crate::utilities::cells::{NumericCellExt, OptionalCell};
 
@@ -480,6 +481,15 @@ impl Kernel {
         resources.watchdog().setup();
         // Before we begin, verify that deferred calls were soundly setup.
         DeferredCall::verify_setup();
+        // Drivers send trace points to the sink of the board from now on.
+        //
+        // # Safety
+        //
+        // Trace points run in bottom halves and processes, neither of which
+        // can run while the sink is set.
+        unsafe {
+            trace::set_trace_sink(resources.kernel_hooks().trace_sink());
+        }
         loop {
             self.kernel_loop_operation(resources, chip, ipc, false, capability);
         }
@@ -777,6 +787,21 @@ impl Kernel {
     ) {
         // Hook for process debugging.
         process.debug_syscall_called(syscall);
+        // Without a tracer, as with the `()` hooks, this is known at compile
+        // time and the reports below are compiled out.
+        let syscall_tracer = resources.kernel_hooks().syscall_tracer();
+        if let Some(tracer) = syscall_tracer {
+            tracer.syscall_entered(process.processid(), &syscall);
+        }
+
+        // Every value returned to the process goes through this closure, so
+        // that the syscall tracer sees it.
+        let set_return_value = |rval: SyscallReturn| {
+            if let Some(tracer) = syscall_tracer {
+                tracer.syscall_returned(process.processid(), &rval);
+            }
+            process.set_syscall_return_value(rval);
+        };
 
         // Enforce platform-specific syscall filtering here.
         //
@@ -806,7 +831,7 @@ impl Kernel {
                 // Check all other syscalls for filtering.
                 if let Err(response) = resources.syscall_filter().filter_syscall(process, &syscall)
                 {
-                    process.set_syscall_return_value(SyscallReturn::Failure(response));
+                    set_return_value(SyscallReturn::Failure(response));
 
                     if config::CONFIG.trace_syscalls {
                         debug!(
@@ -835,7 +860,7 @@ impl Kernel {
                         rval
                     );
                 }
-                process.set_syscall_return_value(rval);
+                set_return_value(rval);
             }
             Syscall::Yield { which, address } => {
                 if config::CONFIG.trace_syscalls {
@@ -1025,7 +1050,7 @@ impl Kernel {
                             );
                         }
 
-                        process.set_syscall_return_value(rval);
+                        set_return_value(rval);
                     }
                     Syscall::Command {
                         driver_number,
@@ -1051,7 +1076,7 @@ impl Kernel {
                                 res,
                             );
                         }
-                        process.set_syscall_return_value(res);
+                        set_return_value(res);
                     }
                     Syscall::ReadWriteAllow {
                         driver_number,
@@ -1168,7 +1193,7 @@ impl Kernel {
                                 res
                             );
                         }
-                        process.set_syscall_return_value(res);
+                        set_return_value(res);
                     }
                     Syscall::UserspaceReadableAllow {
                         driver_number,
@@ -1247,7 +1272,7 @@ impl Kernel {
                                 res
                             );
                         }
-                        process.set_syscall_return_value(res);
+                        set_return_value(res);
                     }
                     Syscall::ReadOnlyAllow {
                         driver_number,
@@ -1365,7 +1390,7 @@ impl Kernel {
                             );
                         }
 
-                        process.set_syscall_return_value(res);
+                        set_return_value(res);
                     }
                     Syscall::Yield { .. }
                     | Syscall::Exit { .. }
@@ -1386,7 +1411,7 @@ impl Kernel {
                 1 => process.try_restart(Some(completion_code as u32)),
                 // The process called an invalid variant of the Exit
                 // system call class.
-                _ => process.set_syscall_return_value(SyscallReturn::Failure(ErrorCode::NOSUPPORT)),
+                _ => set_return_value(SyscallReturn::Failure(ErrorCode::NOSUPPORT)),
             },
         }
     }
@@ -110,10 +110,13 @@ pub mod ipc;
 pub mod platform;
 pub mod process;
 pub mod process_checker;
+pub mod process_load_report;
 pub mod processbuffer;
 pub mod scheduler;
 pub mod storage_permissions;
 pub mod syscall;
+pub mod syscall_trace;
+pub mod trace;
 pub mod upcall;
 pub mod utilities;
 
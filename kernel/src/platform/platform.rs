@@ -10,9 +10,12 @@ use crate::platform::scheduler_timer;
 use crate::platform::watchdog;
 use crate::process;
 use crate::process_checker::CredentialsCheckingPolicy;
+use crate::process_load_report::ProcessLoadMonitor;
 use crate::scheduler::Scheduler;
 use crate::syscall;
 use crate::syscall_driver::SyscallDriver;
+use crate::syscall_trace::SyscallTracer;
+use crate::trace::TraceSink;
 use tock_tbf::types::CommandPermissions;
 
 /// Combination trait that boards provide to the kernel that includes all of
@@ -51,6 +54,10 @@ pub trait KernelResources<C: Chip> {
     /// of the kernel.
     type WatchDog: watchdog::WatchDog;
 
+    /// The implementation of the debugging and monitoring hooks the kernel
+    /// will use.
+    type KernelHooks: KernelHooks;
+
     /// Returns a reference to the implementation of the SyscallDriverLookup this
     /// platform will use to route syscalls.
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup;
@@ -82,6 +89,10 @@ pub trait KernelResources<C: Chip> {
     /// Returns a reference to the implementation of the WatchDog on this
     /// platform.
     fn watchdog(&self) -> &Self::WatchDog;
+
+    /// Returns a reference to the implementation of the KernelHooks this
+    /// platform wants the kernel to use.
+    fn kernel_hooks(&self) -> &Self::KernelHooks;
 }
 
 /// Configure the system call dispatch mapping.
@@ -293,3 +304,27 @@ pub trait ContextSwitchCallback {
 impl ContextSwitchCallback for () {
     fn context_switch_hook(&self, _process: &dyn process::Process) {}
 }
+
+/// Trait for providing the observers the kernel reports to, for debugging and
+/// monitoring. The default implementations provide none.
+pub trait KernelHooks {
+    /// The sink trace points are sent to. The kernel takes it when its loop
+    /// starts: trace points run in drivers, which cannot reach the board, so
+    /// the kernel keeps the sink for them.
+    fn trace_sink(&self) -> Option<&'static dyn TraceSink> {
+        None
+    }
+
+    /// The tracer the system calls of processes are reported to.
+    fn syscall_tracer(&self) -> Option<&dyn SyscallTracer> {
+        None
+    }
+
+    /// The monitor the processes the kernel loads are reported to.
+    fn process_load_monitor(&self) -> Option<&dyn ProcessLoadMonitor> {
+        None
+    }
+}
+
+/// Implement default KernelHooks trait for unit.
+impl KernelHooks for () {}
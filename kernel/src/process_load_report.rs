@@ -0,0 +1,118 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Reports of the flash and RAM each process needed when it was loaded.
+//!
+//! For every TBF object with a header, the loader compares the flash and RAM
+//! the process requires to what is left of the application regions of the
+//! board, and reports the result. A process which does not fit is printed
+//! with `debug!()`, with the sizes required and available:
+//!
+//! ```text
+//! Process not loaded: [1] "sensors" flash 16384/12288 B at 0x60050000,
+//!     RAM ?/20480 B at 0x20205000: Not enough flash available for TBF
+//! ```
+//!
+//! Boards can also keep the reports, for example to print them on the process
+//! console with `capsules_extra::process_load_log`, by providing a monitor
+//! with `KernelHooks::process_load_monitor()`. The functions loading
+//! processes take it from the `KernelResources` of the board:
+//!
+//! ```ignore
+//! impl KernelHooks for Board {
+//!     fn process_load_monitor(&self) -> Option<&dyn ProcessLoadMonitor> {
+//!         Some(self.process_load_log)
+//!     }
+//! }
+//! ```
+
+use core::fmt;
+
+use crate::debug;
+use crate::process::ProcessLoadError;
+
+/// The resources a process needed when the kernel tried to load it.
+#[derive(Clone, Copy)]
+pub struct ProcessLoadReport {
+    /// Index of the process slot the process was loaded into.
+    pub index: usize,
+    /// Package name from the TBF header, if it could be parsed.
+    pub name: Option<&'static str>,
+    /// Start of the TBF object in flash.
+    pub flash_address: usize,
+    /// Bytes of flash of the TBF object.
+    pub flash_required: usize,
+    /// Bytes of flash from the TBF object to the end of the application
+    /// region.
+    pub flash_available: usize,
+    /// Start of the RAM left for processes.
+    pub ram_address: usize,
+    /// Bytes of RAM the process needs, including the memory the kernel keeps
+    /// for it. `None` if loading failed before it was known.
+    pub ram_required: Option<usize>,
+    /// Bytes of RAM left for processes.
+    pub ram_available: usize,
+    pub result: Result<(), ProcessLoadError>,
+}
+
+impl fmt::Display for ProcessLoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {:?} flash {}/{} B at {:#010x}, RAM ",
+            self.index,
+            self.name.unwrap_or("(no name)"),
+            self.flash_required,
+            self.flash_available,
+            self.flash_address
+        )?;
+        match self.ram_required {
+            Some(ram_required) => write!(f, "{}", ram_required)?,
+            None => write!(f, "?")?,
+        }
+        write!(
+            f,
+            "/{} B at {:#010x}: ",
+            self.ram_available, self.ram_address
+        )?;
+
+        match self.result {
+            Ok(()) => write!(f, "loaded"),
+            Err(ProcessLoadError::NotEnoughMemory)
+                if self
+                    .ram_required
+                    .map_or(false, |ram_required| ram_required <= self.ram_available) =>
+            {
+                // The RAM is there, but the MPU could not fit a region with
+                // the size and alignment it needs.
+                write!(
+                    f,
+                    "{:?}, the MPU could not align a region in the RAM left",
+                    ProcessLoadError::NotEnoughMemory
+                )
+            }
+            Err(ProcessLoadError::MpuInvalidFlashLength) => write!(
+                f,
+                "{:?}, the MPU needs a supported size at an aligned address",
+                ProcessLoadError::MpuInvalidFlashLength
+            ),
+            Err(error) => write!(f, "{:?}", error),
+        }
+    }
+}
+
+/// Receiver of the reports of the processes the kernel tries to load.
+pub trait ProcessLoadMonitor {
+    fn process_load_attempted(&self, report: &ProcessLoadReport);
+}
+
+/// Print `report` if the process was not loaded, and send it to `monitor`.
+pub(crate) fn report(report: &ProcessLoadReport, monitor: Option<&dyn ProcessLoadMonitor>) {
+    if report.result.is_err() {
+        debug!("Process not loaded: {}", report);
+    }
+    if let Some(monitor) = monitor {
+        monitor.process_load_attempted(report);
+    }
+}
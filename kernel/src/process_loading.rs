@@ -17,13 +17,16 @@ use crate::create_capability;
 use crate::debug;
 use crate::kernel::{Kernel, ProcessCheckerMachine};
 use crate::platform::chip::Chip;
-use crate::platform::platform::KernelResources;
+use crate::platform::platform::{KernelHooks, KernelResources};
 use crate::process::{Process, ShortID};
 use crate::process_checker::AppCredentialsChecker;
+use crate::process_load_report::{self, ProcessLoadMonitor, ProcessLoadReport};
 use crate::process_policies::ProcessFaultPolicy;
 use crate::process_standard::ProcessStandard;
+use crate::utilities::cells::{MapCell, TakeCell};
 
 /// Errors that can occur when trying to load and create processes.
+#[derive(Clone, Copy)]
 pub enum ProcessLoadError {
     /// No TBF header was found.
     TbfHeaderNotFound,
@@ -86,6 +89,15 @@ pub enum ProcessLoadError {
     /// this counter.
     CredentialsReject(u32),
 
+    /// The application flash or RAM region given by the board ends before it
+    /// starts, or its start is not aligned to 4 bytes. The linker script
+    /// likely defines `_sapps`, `_eapps`, `_sappmem` or `_eappmem` wrong.
+    InvalidAppRegion { start: usize, end: usize },
+
+    /// A process was loaded after boot, but every process slot of the board
+    /// is used.
+    NoProcessSlot,
+
     /// Process loading error due (likely) to a bug in the kernel. If you get
     /// this error please open a bug report.
     InternalError,
@@ -161,11 +173,53 @@ impl fmt::Debug for ProcessLoadError {
                 write!(f, "Credentials index {} rejected.", index)
             }
 
+            ProcessLoadError::InvalidAppRegion { start, end } => write!(
+                f,
+                "App region {:#010x}-{:#010x} is reversed or unaligned",
+                start, end
+            ),
+
+            ProcessLoadError::NoProcessSlot => write!(f, "No process slot left"),
+
             ProcessLoadError::InternalError => write!(f, "Error in kernel. Likely a bug."),
         }
     }
 }
 
+/// Get the application flash and RAM regions between the addresses of the
+/// linker symbols `_sapps` and `_eapps`, and `_sappmem` and `_eappmem`, to
+/// pass to `load_processes`. The end of each region is the address after its
+/// last byte.
+///
+/// Returns `InvalidAppRegion` if a region ends before it starts, or starts on
+/// an address not aligned to 4 bytes, instead of loading processes from
+/// memory outside of the regions the linker reserved for them.
+///
+/// # Safety
+///
+/// The regions must only be used by processes, and this must only be called
+/// once.
+pub unsafe fn app_regions(
+    flash_start: *const u8,
+    flash_end: *const u8,
+    memory_start: *mut u8,
+    memory_end: *const u8,
+) -> Result<(&'static [u8], &'static mut [u8]), ProcessLoadError> {
+    let check = |start: usize, end: usize| {
+        if end < start || start % 4 != 0 {
+            Err(ProcessLoadError::InvalidAppRegion { start, end })
+        } else {
+            Ok(end - start)
+        }
+    };
+    let flash_len = check(flash_start as usize, flash_end as usize)?;
+    let memory_len = check(memory_start as usize, memory_end as usize)?;
+    Ok((
+        core::slice::from_raw_parts(flash_start, flash_len),
+        core::slice::from_raw_parts_mut(memory_start, memory_len),
+    ))
+}
+
 /// Load processes (stored as TBF objects in flash) into runnable
 /// process structures stored in the `procs` array. If the kernel is
 /// configured with an `AppCredentialsChecker`, this method scans the
@@ -193,6 +247,7 @@ where
         app_memory,
         &mut procs,
         fault_policy,
+        kernel_resources.kernel_hooks().process_load_monitor(),
         capability_management,
     )?;
     let _res = check_processes(kernel_resources, kernel.get_checker());
@@ -206,10 +261,13 @@ where
 /// for which code size is tight and do not need to check TBF
 /// credentials can call this method instead of `load_and_check_processes`
 /// because it results in a smaller kernel, as it does not invoke
-/// the credential checking state machine.
+/// the credential checking state machine. Like `load_and_check_processes`,
+/// it reports each process to the process load monitor of
+/// `kernel_resources`, if any.
 #[inline(always)]
-pub fn load_processes<C: Chip>(
+pub fn load_processes<KR: KernelResources<C>, C: Chip>(
     kernel: &'static Kernel,
+    kernel_resources: &KR,
     chip: &'static C,
     app_flash: &'static [u8],
     app_memory: &'static mut [u8],
@@ -224,6 +282,7 @@ pub fn load_processes<C: Chip>(
         app_memory,
         &mut procs,
         fault_policy,
+        kernel_resources.kernel_hooks().process_load_monitor(),
         capability_management,
     )?;
 
@@ -247,6 +306,122 @@ pub fn load_processes<C: Chip>(
     Ok(())
 }
 
+/// Loads a process from a TBF object written to flash after boot, for
+/// example by `capsules_extra::app_loader`.
+pub trait ProcessLoader {
+    /// Load the process stored as the TBF object `app_flash`, and start it.
+    fn load_process(&self, app_flash: &'static [u8]) -> Result<(), ProcessLoadError>;
+}
+
+/// Loads processes at boot like `load_processes`, but keeps the memory left
+/// for processes so that more can be loaded later, into the free process
+/// slots. Like `load_processes`, it does not check the credentials of
+/// processes. Processes loaded later are not reported to the process load
+/// monitor of the board, and only printed if they do not load.
+pub struct DynamicProcessLoader<C: 'static + Chip> {
+    kernel: &'static Kernel,
+    chip: &'static C,
+    procs: MapCell<&'static mut [Option<&'static dyn Process>]>,
+    fault_policy: &'static dyn ProcessFaultPolicy,
+    /// Memory left for processes.
+    app_memory: TakeCell<'static, [u8]>,
+}
+
+impl<C: 'static + Chip> DynamicProcessLoader<C> {
+    pub fn new(
+        kernel: &'static Kernel,
+        chip: &'static C,
+        procs: &'static mut [Option<&'static dyn Process>],
+        fault_policy: &'static dyn ProcessFaultPolicy,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> Self {
+        Self {
+            kernel,
+            chip,
+            procs: MapCell::new(procs),
+            fault_policy,
+            app_memory: TakeCell::empty(),
+        }
+    }
+
+    /// Load the processes in `app_flash` into `app_memory` and make them
+    /// runnable, as `load_processes` does.
+    pub fn load_processes<KR: KernelResources<C>>(
+        &self,
+        kernel_resources: &KR,
+        app_flash: &'static [u8],
+        app_memory: &'static mut [u8],
+    ) -> Result<(), ProcessLoadError> {
+        let capability = create_capability!(ProcessManagementCapability);
+        let approval = create_capability!(ProcessApprovalCapability);
+        self.procs
+            .map(|procs| {
+                let remaining_memory = load_processes_from_flash(
+                    self.kernel,
+                    self.chip,
+                    app_flash,
+                    app_memory,
+                    procs,
+                    self.fault_policy,
+                    kernel_resources.kernel_hooks().process_load_monitor(),
+                    &capability,
+                )?;
+                self.app_memory.replace(remaining_memory);
+                for proc in procs.iter().flatten() {
+                    proc.mark_credentials_pass(None, ShortID::LocallyUnique, &approval)
+                        .or(Err(ProcessLoadError::InternalError))?;
+                }
+                Ok(())
+            })
+            .unwrap_or(Err(ProcessLoadError::InternalError))
+    }
+}
+
+impl<C: 'static + Chip> ProcessLoader for DynamicProcessLoader<C> {
+    fn load_process(&self, app_flash: &'static [u8]) -> Result<(), ProcessLoadError> {
+        let capability = create_capability!(ProcessManagementCapability);
+        let approval = create_capability!(ProcessApprovalCapability);
+        self.procs
+            .map(|procs| {
+                let index = procs
+                    .iter()
+                    .position(|proc| proc.is_none())
+                    .ok_or(ProcessLoadError::NoProcessSlot)?;
+                let app_memory = self
+                    .app_memory
+                    .take()
+                    .ok_or(ProcessLoadError::NotEnoughMemory)?;
+                match load_process(
+                    self.kernel,
+                    self.chip,
+                    app_flash,
+                    app_memory,
+                    index,
+                    self.fault_policy,
+                    None,
+                    &capability,
+                ) {
+                    Ok((_, remaining_memory, Some(proc))) => {
+                        self.app_memory.replace(remaining_memory);
+                        procs[index] = Some(proc);
+                        proc.mark_credentials_pass(None, ShortID::LocallyUnique, &approval)
+                            .or(Err(ProcessLoadError::InternalError))
+                    }
+                    // Padding, or a process which is not enabled.
+                    Ok((_, remaining_memory, None)) => {
+                        self.app_memory.replace(remaining_memory);
+                        Err(ProcessLoadError::TbfHeaderNotFound)
+                    }
+                    Err((_, remaining_memory, err)) => {
+                        self.app_memory.replace(remaining_memory);
+                        Err(err)
+                    }
+                }
+            })
+            .unwrap_or(Err(ProcessLoadError::InternalError))
+    }
+}
+
 /// Helper function to load processes from flash into an array of active
 /// processes. This is the default template for loading processes, but a board
 /// is able to create its own `load_processes()` function and use that instead.
@@ -266,9 +441,9 @@ pub fn load_processes<C: Chip>(
 /// processes from slices of flash an memory is fundamentally unsafe. Therefore,
 /// we require the `ProcessManagementCapability` to call this function.
 ///
-/// Returns `Ok(())` if process discovery went as expected. Returns a
-/// `ProcessLoadError` if something goes wrong during TBF parsing or process
-/// creation.
+/// Returns the memory left for processes if process discovery went as
+/// expected. Returns a `ProcessLoadError` if something goes wrong during TBF
+/// parsing or process creation.
 #[inline(always)]
 fn load_processes_from_flash<C: Chip>(
     kernel: &'static Kernel,
@@ -277,8 +452,9 @@ fn load_processes_from_flash<C: Chip>(
     app_memory: &'static mut [u8],
     procs: &mut &'static mut [Option<&'static dyn Process>],
     fault_policy: &'static dyn ProcessFaultPolicy,
+    monitor: Option<&dyn ProcessLoadMonitor>,
     capability: &dyn ProcessManagementCapability,
-) -> Result<(), ProcessLoadError> {
+) -> Result<&'static mut [u8], ProcessLoadError> {
     if config::CONFIG.debug_load_processes {
         debug!(
             "Loading processes from flash={:#010X}-{:#010X} into sram={:#010X}-{:#010X}",
@@ -302,6 +478,7 @@ fn load_processes_from_flash<C: Chip>(
             remaining_memory,
             index,
             fault_policy,
+            monitor,
             capability,
         );
         match load_result {
@@ -320,7 +497,8 @@ fn load_processes_from_flash<C: Chip>(
                     }
                 }
             }
-            Err((_new_flash, _new_mem, err)) => {
+            Err((_new_flash, new_mem, err)) => {
+                remaining_memory = new_mem;
                 if config::CONFIG.debug_load_processes {
                     debug!("No more processes to load: {:?}.", err);
                 }
@@ -329,7 +507,7 @@ fn load_processes_from_flash<C: Chip>(
             }
         }
     }
-    Ok(())
+    Ok(remaining_memory)
 }
 
 /// Use `checker` to transition `procs` from the
@@ -364,6 +542,7 @@ fn load_process<C: Chip>(
     app_memory: &'static mut [u8],
     index: usize,
     fault_policy: &'static dyn ProcessFaultPolicy,
+    monitor: Option<&dyn ProcessLoadMonitor>,
     _capability: &dyn ProcessManagementCapability,
 ) -> Result<
     (
@@ -418,11 +597,31 @@ fn load_process<C: Chip>(
             }
         };
 
+    // Resources this process needs, reported once we know whether it
+    // loaded. Regions which are skipped over are not reported.
+    let mut report = ProcessLoadReport {
+        index,
+        name: None,
+        flash_address: app_flash.as_ptr() as usize,
+        flash_required: entry_length as usize,
+        flash_available: app_flash.len(),
+        ram_address: app_memory.as_ptr() as usize,
+        ram_required: None,
+        ram_available: app_memory.len(),
+        result: Ok(()),
+    };
+
     // Now we can get a slice which only encompasses the length of flash
     // described by this tbf header.  We will either parse this as an actual
     // app, or skip over this region.
     let entry_flash = match app_flash.get(0..entry_length as usize) {
-        None => return Err((app_flash, app_memory, ProcessLoadError::NotEnoughFlash)),
+        None => {
+            if header_length > 0 {
+                report.result = Err(ProcessLoadError::NotEnoughFlash);
+                process_load_report::report(&report, monitor);
+            }
+            return Err((app_flash, app_memory, ProcessLoadError::NotEnoughFlash));
+        }
         Some(val) => val,
     };
 
@@ -456,15 +655,19 @@ fn load_process<C: Chip>(
                 fault_policy,
                 true,
                 index,
+                &mut report,
             );
             match result {
                 Ok(tuple) => tuple,
                 Err((err, memory)) => {
+                    report.result = Err(err);
+                    process_load_report::report(&report, monitor);
                     return Err((remaining_flash, memory, err));
                 }
             }
         };
         process_option.map(|process| {
+            process_load_report::report(&report, monitor);
             if config::CONFIG.debug_load_processes {
                 debug!(
                     "Loaded process[{}] from flash={:#010X}-{:#010X} into sram={:#010X}-{:#010X} = {:?}",
@@ -25,6 +25,7 @@ use crate::platform::mpu::{self, MPU};
 use crate::process::{Error, FunctionCall, FunctionCallSource, Process, State, Task};
 use crate::process::{FaultAction, ProcessCustomGrantIdentifier, ProcessId};
 use crate::process::{ProcessAddresses, ProcessSizes, ShortID};
+use crate::process_load_report::ProcessLoadReport;
 use crate::process_loading::ProcessLoadError;
 use crate::process_policies::ProcessFaultPolicy;
 use crate::processbuffer::{ReadOnlyProcessBuffer, ReadWriteProcessBuffer};
@@ -1350,6 +1351,7 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
         fault_policy: &'static dyn ProcessFaultPolicy,
         require_kernel_version: bool,
         index: usize,
+        report: &mut ProcessLoadReport,
     ) -> Result<(Option<&'static dyn Process>, &'a mut [u8]), (ProcessLoadError, &'a mut [u8])>
     {
         // Get a slice for just the app header.
@@ -1366,6 +1368,7 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
         };
 
         let process_name = tbf_header.get_package_name();
+        report.name = process_name;
 
         // If this isn't an app (i.e. it is padding) or it is an app but it
         // isn't enabled, then we can skip it and do not create a `Process`
@@ -1534,6 +1537,7 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
 
         // Minimum memory size for the process.
         let min_total_memory_size = min_process_ram_size + initial_kernel_memory_size;
+        report.ram_required = Some(min_total_memory_size);
 
         // Check if this process requires a fixed memory start address. If so,
         // try to adjust the memory region to work for this process.
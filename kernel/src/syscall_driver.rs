@@ -155,6 +155,30 @@ impl CommandReturn {
     pub fn success_u32_u64(data0: u32, data1: u64) -> Self {
         CommandReturn(SyscallReturn::SuccessU32U64(data0, data1))
     }
+
+    /// Whether the command succeeded, with or without data
+    pub fn is_success(&self) -> bool {
+        matches!(
+            self.0,
+            SyscallReturn::Success
+                | SyscallReturn::SuccessU32(_)
+                | SyscallReturn::SuccessU32U32(_, _)
+                | SyscallReturn::SuccessU32U32U32(_, _, _)
+                | SyscallReturn::SuccessU64(_)
+                | SyscallReturn::SuccessU32U64(_, _)
+        )
+    }
+
+    /// The error code of a failed command, `None` if it succeeded
+    pub fn get_failure(&self) -> Option<ErrorCode> {
+        match self.0 {
+            SyscallReturn::Failure(rc)
+            | SyscallReturn::FailureU32(rc, _)
+            | SyscallReturn::FailureU32U32(rc, _, _)
+            | SyscallReturn::FailureU64(rc, _) => Some(rc),
+            _ => None,
+        }
+    }
 }
 
 impl From<Result<(), ErrorCode>> for CommandReturn {
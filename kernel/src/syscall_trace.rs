@@ -0,0 +1,39 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Hook observing the system calls of processes.
+//!
+//! The kernel reports every system call to the tracer of the board, which it
+//! provides with `KernelHooks::syscall_tracer()`: once when a process makes
+//! it, before the kernel handles it, and once with the value returned to the
+//! process. Yield and exit return nothing, so only the first is reported for
+//! them. Filtered system calls are reported with the error returned by the
+//! filter.
+//!
+//! Unlike `trace_syscalls` in the kernel configuration, which prints every
+//! system call with `debug!()`, the tracer is part of the board and can keep
+//! the system calls in RAM, as `capsules_extra::syscall_trace` does.
+//!
+//! ```ignore
+//! impl KernelHooks for Board {
+//!     fn syscall_tracer(&self) -> Option<&dyn SyscallTracer> {
+//!         Some(self.syscall_trace)
+//!     }
+//! }
+//! ```
+//!
+//! System calls are not reported if the board has no tracer.
+
+use crate::process::ProcessId;
+use crate::syscall::{Syscall, SyscallReturn};
+
+/// Receiver of the system calls of processes.
+pub trait SyscallTracer {
+    /// `processid` made `syscall`, which the kernel is about to handle.
+    fn syscall_entered(&self, processid: ProcessId, syscall: &Syscall);
+
+    /// The kernel returns `result` to `processid`, for the last system call
+    /// it entered.
+    fn syscall_returned(&self, processid: ProcessId, result: &SyscallReturn);
+}
@@ -0,0 +1,159 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2023.
+
+//! Trace points for timing-sensitive debugging.
+//!
+//! `debug!()` formats its message and queues it for a UART, which takes long
+//! enough to change the behavior of the code being debugged. A trace point
+//! instead records an event, an identifier and a 32-bit value, with a few
+//! stores. Events go to the trace sink of the board, which it provides with
+//! `KernelHooks::trace_sink()`:
+//!
+//! - [`TraceBuffer`] keeps the most recent events in RAM. They are printed
+//!   with the panic report.
+//! - On Cortex-M, `cortexm::itm::ItmTrace` writes them to the ITM stimulus
+//!   ports, from which a debug probe reads them over SWO.
+//!
+//! Trace points do nothing if the board has no sink, and until the kernel
+//! loop starts.
+//!
+//! Identifiers are chosen by the drivers that emit them, which should make
+//! them public so that traces can be decoded.
+//!
+//! ```ignore
+//! pub const TRACE_RX: u8 = 0x20;
+//!
+//! kernel::trace_point!(TRACE_RX, len as u32);
+//! ```
+//!
+//! The board creates the sink during initialization, and keeps it in the
+//! struct implementing `KernelHooks`:
+//!
+//! ```ignore
+//! let trace_buffer = static_init!(
+//!     kernel::trace::TraceBuffer,
+//!     kernel::trace::TraceBuffer::new(static_init!(
+//!         kernel::collections::ring_buffer::RingBuffer<'static, kernel::trace::TraceEvent>,
+//!         kernel::collections::ring_buffer::RingBuffer::new(static_init!(
+//!             [kernel::trace::TraceEvent; 64],
+//!             [kernel::trace::TraceEvent::default(); 64]
+//!         ))
+//!     ))
+//! );
+//!
+//! impl KernelHooks for Board {
+//!     fn trace_sink(&self) -> Option<&'static dyn TraceSink> {
+//!         Some(self.trace_buffer)
+//!     }
+//! }
+//! ```
+
+use core::fmt::Write;
+
+use crate::collections::queue::Queue;
+use crate::collections::ring_buffer::RingBuffer;
+use crate::utilities::cells::TakeCell;
+
+/// Destination of trace events.
+pub trait TraceSink {
+    /// Record the event `id` with `value`. This must be fast, and must not
+    /// block.
+    fn event(&self, id: u8, value: u32);
+
+    /// Print the events recorded, if the sink keeps them. Called when the
+    /// kernel panics.
+    fn dump(&self, _writer: &mut dyn Write) {}
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TraceEvent {
+    pub id: u8,
+    pub value: u32,
+}
+
+/// Trace sink that keeps the most recent events in a ring buffer.
+pub struct TraceBuffer {
+    events: TakeCell<'static, RingBuffer<'static, TraceEvent>>,
+}
+
+impl TraceBuffer {
+    pub fn new(events: &'static mut RingBuffer<'static, TraceEvent>) -> TraceBuffer {
+        TraceBuffer {
+            events: TakeCell::new(events),
+        }
+    }
+}
+
+impl TraceSink for TraceBuffer {
+    fn event(&self, id: u8, value: u32) {
+        self.events.map(|events| {
+            // Older events are overwritten.
+            events.push(TraceEvent { id, value });
+        });
+    }
+
+    fn dump(&self, writer: &mut dyn Write) {
+        self.events.map(|events| {
+            let _ = writer.write_fmt(format_args!(
+                "\r\n---| Trace events, oldest first ({}):\r\n",
+                events.len()
+            ));
+            let (first, second) = events.as_slices();
+            for event in first.into_iter().chain(second).flatten() {
+                let _ = writer.write_fmt(format_args!(
+                    "  {:#04x}: {:#010x}\r\n",
+                    event.id, event.value
+                ));
+            }
+        });
+    }
+}
+
+/// Sink events are sent to, taken from the `KernelHooks` of the board.
+static mut TRACE_SINK: Option<&'static dyn TraceSink> = None;
+
+/// Set the sink trace events are sent to, when the kernel loop starts.
+///
+/// # Safety
+///
+/// No trace point may run concurrently.
+pub(crate) unsafe fn set_trace_sink(sink: Option<&'static dyn TraceSink>) {
+    TRACE_SINK = sink;
+}
+
+/// Send the event `id` with `value` to the trace sink, if any. Use the
+/// [`trace_point!`](crate::trace_point) macro instead.
+#[inline]
+pub fn trace_event(id: u8, value: u32) {
+    if let Some(sink) = unsafe { TRACE_SINK } {
+        sink.event(id, value);
+    }
+}
+
+/// Print the events kept by the trace sink, if any.
+///
+/// # Safety
+///
+/// Only to be called from the panic handler.
+pub unsafe fn flush(writer: &mut dyn Write) {
+    if let Some(sink) = TRACE_SINK {
+        sink.dump(writer);
+    }
+}
+
+/// Record a trace event, with an optional 32-bit value.
+///
+/// ```ignore
+/// kernel::trace_point!(TRACE_IRQ);
+/// kernel::trace_point!(TRACE_RX, len as u32);
+/// ```
+#[macro_export]
+macro_rules! trace_point {
+    ($id:expr $(,)?) => {{
+        $crate::trace::trace_event($id, 0)
+    }};
+    ($id:expr, $value:expr $(,)?) => {{
+        $crate::trace::trace_event($id, $value)
+    }};
+}
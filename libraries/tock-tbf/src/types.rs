@@ -36,6 +36,7 @@ impl From<core::array::TryFromSliceError> for InitialTbfParseError {
 }
 
 /// Error when parsing an app's TBF header.
+#[derive(Clone, Copy)]
 pub enum TbfParseError {
     /// Not enough bytes in the buffer to parse the expected field.
     NotEnoughFlash,
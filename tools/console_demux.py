@@ -0,0 +1,95 @@
+#!/usr/bin/env python3
+
+# Licensed under the Apache License, Version 2.0 or the MIT License.
+# SPDX-License-Identifier: Apache-2.0 OR MIT
+# Copyright Tock Contributors 2023.
+
+'''
+Demultiplex the output of a board whose UART mux is framed.
+
+Each frame is tagged with a channel: kernel debug output, the process console,
+low level debug, or the console output of one process. The script prints
+every line prefixed with its channel, or only the channels given with
+--channel.
+
+    ./console_demux.py /dev/ttyACM0
+    ./console_demux.py /dev/ttyACM0 --channel app:2 --channel debug
+
+Keys typed are sent to the board as is, so that the process console can still
+be used.
+'''
+
+import argparse
+import sys
+import threading
+
+from serial import Serial
+
+FRAME_SYNC = 0xA5
+FRAME_HEADER_LEN = 5
+
+KERNEL_DEBUG = 0
+PROCESS_CONSOLE = 1
+LOW_LEVEL_DEBUG = 2
+APP_BASE = 0x100
+
+NAMES = {
+    KERNEL_DEBUG: "debug",
+    PROCESS_CONSOLE: "console",
+    LOW_LEVEL_DEBUG: "lldb",
+}
+
+
+def channel_name(channel):
+    if channel >= APP_BASE:
+        return "app:{}".format(channel - APP_BASE)
+    return NAMES.get(channel, "ch:{}".format(channel))
+
+
+def frames(serial):
+    '''Yield the (channel, data) of the frames read from `serial`.'''
+    while True:
+        sync = serial.read(1)
+        if not sync or sync[0] != FRAME_SYNC:
+            # Output before framing was enabled, or a lost byte.
+            continue
+        header = serial.read(FRAME_HEADER_LEN - 1)
+        channel = int.from_bytes(header[0:2], "little")
+        length = int.from_bytes(header[2:4], "little")
+        yield channel, serial.read(length)
+
+
+def forward_input(serial):
+    for line in sys.stdin:
+        serial.write(line.replace("\n", "\r").encode())
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__,
+                                     formatter_class=argparse.RawDescriptionHelpFormatter)
+    parser.add_argument("port", help="serial port of the board")
+    parser.add_argument("--baud", type=int, default=115200)
+    parser.add_argument("--channel", action="append",
+                        help="only print this channel, for example debug, console or app:1")
+    args = parser.parse_args()
+
+    serial = Serial(args.port, args.baud)
+    threading.Thread(target=forward_input, args=(serial,), daemon=True).start()
+
+    # Output is printed by line, so that the lines of different channels do
+    # not mix.
+    partial = {}
+    for channel, data in frames(serial):
+        name = channel_name(channel)
+        if args.channel and name not in args.channel:
+            continue
+        text = partial.pop(channel, "") + data.decode(errors="replace")
+        lines = text.replace("\r", "").split("\n")
+        for line in lines[:-1]:
+            print("[{}] {}".format(name, line), flush=True)
+        if lines[-1]:
+            partial[channel] = lines[-1]
+
+
+if __name__ == "__main__":
+    main()